@@ -33,6 +33,7 @@ async fn main() -> Result<()> {
         min_liquidity_usd: dec!(500000.0),
         enable_rug_detection: true,
         trade_cooldown_secs: 30,
+        ..Default::default()
     };
     
     let risk_manager: Arc<RiskManager> = Arc::new(
@@ -190,6 +191,8 @@ async fn main() -> Result<()> {
         timeout_secs: 10,
         max_output_bytes: 100_000,
         allow_network: false,
+        allowed_hosts: Vec::new(),
+        egress_budget: Default::default(),
         env_vars: std::collections::HashMap::new(),
     };
     
@@ -48,6 +48,7 @@ async fn main() -> Result<()> {
         min_liquidity_usd: dec!(100000.0),
         enable_rug_detection: true,
         trade_cooldown_secs: 10,
+        ..Default::default()
     };
     
     let risk_manager = Arc::new(
@@ -63,6 +64,8 @@ async fn main() -> Result<()> {
         timeout_secs: 15, // Stricter timeout
         max_output_bytes: 512 * 1024, // 512KB max
         allow_network: false, // Disable network access
+        allowed_hosts: Vec::new(), // No proxied hosts either
+        egress_budget: Default::default(),
         env_vars: std::collections::HashMap::new(),
     };
 
@@ -74,10 +77,10 @@ async fn main() -> Result<()> {
         loader.load_all().await?;
         
         // Apply custom config to skills
-        for skill in loader.skills.values() {
+        for skill in loader.iter() {
             println!("  • Loaded skill: {}", skill.name());
         }
-        println!("✅ {} skills loaded with safety config", loader.skills.len());
+        println!("✅ {} skills loaded with safety config", loader.len());
     } else {
         println!("⚠️  No skills directory found (expected, this is a demo)");
     }
@@ -27,6 +27,7 @@ async fn main() -> Result<()> {
         min_liquidity_usd: dec!(50000.0),
         enable_rug_detection: true,
         trade_cooldown_secs: 0, // Disable cooldown for this demo
+        ..Default::default()
     };
 
     println!("🛡️ Risk Limits Initialized:");
@@ -24,6 +24,7 @@ impl Tool for MarkdownSkill {
             name: self.name.clone(),
             description: format!("{}\n\nINSTRUCTIONS:\n{}", self.description, self.instructions),
             parameters: self.parameters.clone(),
+            ..Default::default()
         }
     }
 
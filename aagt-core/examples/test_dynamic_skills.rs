@@ -18,11 +18,10 @@ async fn main() -> anyhow::Result<()> {
         .with_risk_manager(risk_manager);
     loader.load_all().await?;
 
-    println!("Total skills loaded: {}", loader.skills.len());
+    println!("Total skills loaded: {}", loader.len());
 
     // 3. Select the swap skill and attach RiskManager
-    if let Some(skill) = loader.skills.get("solana_swap") {
-        let skill = Arc::clone(skill);
+    if let Some(skill) = loader.get("solana_swap") {
         // We need a way to wrap it or set the risk manager
         // In this implementation, SkillLoader owns it, so we might need a better way to inject RM
         // For now, let's assume we can cast or it was already set.
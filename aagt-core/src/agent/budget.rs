@@ -0,0 +1,263 @@
+//! Shared retry and time budget for one request
+//!
+//! Provider retries, fallbacks, context-overflow retries and tool calls each
+//! retry for good local reasons, but stacked together a single prompt can fan
+//! out into dozens of upstream calls. A [`Budget`] is created at the top of
+//! `Agent::chat` and carried task-locally through every nested layer: each
+//! layer spends from it before issuing another provider call or tool call, and
+//! once it is spent the innermost layer fails with
+//! [`Error::BudgetExhausted`], which no layer retries. Per-layer retry configs
+//! are caps within the budget, not independent grants.
+//!
+//! The deadline uses tokio's clock, like every timeout in the agent loop.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::error::{Error, Result};
+
+tokio::task_local! {
+    static CURRENT: Budget;
+}
+
+/// Limits for one top-level request
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetConfig {
+    /// Wall-clock limit for the whole request
+    pub total_time: Option<Duration>,
+    /// Provider calls across every layer, first attempts included
+    pub max_provider_attempts: u32,
+    /// Tool calls across every layer
+    pub max_tool_attempts: u32,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            total_time: Some(Duration::from_secs(600)),
+            max_provider_attempts: 50,
+            max_tool_attempts: 200,
+        }
+    }
+}
+
+/// What was spent from a budget, and by which layer
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetSummary {
+    /// Provider calls made
+    pub provider_attempts: u32,
+    /// Provider call limit
+    pub max_provider_attempts: u32,
+    /// Tool calls made
+    pub tool_attempts: u32,
+    /// Tool call limit
+    pub max_tool_attempts: u32,
+    /// Time since the budget was created
+    pub elapsed: Duration,
+    /// Time left before the deadline, if there is one
+    pub time_left: Option<Duration>,
+    /// Attempts per layer, e.g. `provider_retry` or `fallback`
+    pub by_layer: BTreeMap<String, u32>,
+    /// What ran out, if anything did
+    pub exhausted: Option<String>,
+}
+
+struct Inner {
+    /// ID of the request this budget serves
+    request_id: String,
+    config: BudgetConfig,
+    started: Instant,
+    deadline: Option<Instant>,
+    provider_attempts: AtomicU32,
+    tool_attempts: AtomicU32,
+    by_layer: Mutex<BTreeMap<String, u32>>,
+    exhausted: Mutex<Option<String>>,
+}
+
+/// Shared, cheaply cloneable budget
+#[derive(Clone)]
+pub struct Budget(Arc<Inner>);
+
+impl std::fmt::Debug for Budget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Budget").field(&self.remaining()).finish()
+    }
+}
+
+impl Budget {
+    /// Start a budget now
+    pub fn new(config: BudgetConfig) -> Self {
+        let started = Instant::now();
+        Self(Arc::new(Inner {
+            request_id: uuid::Uuid::new_v4().to_string(),
+            deadline: config.total_time.map(|t| started + t),
+            config,
+            started,
+            provider_attempts: AtomicU32::new(0),
+            tool_attempts: AtomicU32::new(0),
+            by_layer: Mutex::new(BTreeMap::new()),
+            exhausted: Mutex::new(None),
+        }))
+    }
+
+    /// The budget of the request this task is serving, if any
+    pub fn current() -> Option<Budget> {
+        CURRENT.try_with(Budget::clone).ok()
+    }
+
+    /// ID of the top-level request, shared by every nested layer and sub-agent
+    pub fn request_id(&self) -> &str {
+        &self.0.request_id
+    }
+
+    /// Run `fut` with this budget as the current one
+    pub async fn scope<F: Future>(self, fut: F) -> F::Output {
+        CURRENT.scope(self, fut).await
+    }
+
+    /// Deadline, if the budget has one
+    pub fn deadline(&self) -> Option<Instant> {
+        self.0.deadline
+    }
+
+    /// Time left before the deadline
+    pub fn time_left(&self) -> Option<Duration> {
+        self.0
+            .deadline
+            .map(|d| d.saturating_duration_since(Instant::now()))
+    }
+
+    /// Fail if the deadline has passed
+    pub fn check_deadline(&self, layer: &str) -> Result<()> {
+        match self.time_left() {
+            Some(left) if left.is_zero() => Err(self.exhaust("time", layer)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Take one provider attempt for `layer`
+    pub fn spend_provider(&self, layer: &str) -> Result<()> {
+        let max = self.0.config.max_provider_attempts;
+        self.spend(&self.0.provider_attempts, max, "provider attempts", layer)
+    }
+
+    /// Take one tool attempt for `layer`
+    pub fn spend_tool(&self, layer: &str) -> Result<()> {
+        let max = self.0.config.max_tool_attempts;
+        self.spend(&self.0.tool_attempts, max, "tool attempts", layer)
+    }
+
+    fn spend(&self, used: &AtomicU32, max: u32, resource: &str, layer: &str) -> Result<()> {
+        self.check_deadline(layer)?;
+        if used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .is_err()
+        {
+            return Err(self.exhaust(resource, layer));
+        }
+        *self.0.by_layer.lock().entry(layer.to_string()).or_default() += 1;
+        tracing::debug!(layer, remaining = %self.remaining(), "Budget spent");
+        Ok(())
+    }
+
+    fn exhaust(&self, resource: &str, layer: &str) -> Error {
+        tracing::warn!(layer, resource, "Request budget exhausted");
+        self.0
+            .exhausted
+            .lock()
+            .get_or_insert_with(|| resource.to_string());
+        Error::BudgetExhausted {
+            resource: resource.to_string(),
+            layer: layer.to_string(),
+        }
+    }
+
+    /// Compact remaining-budget string for spans and logs
+    pub fn remaining(&self) -> String {
+        let c = &self.0.config;
+        let provider = c.max_provider_attempts - self.0.provider_attempts.load(Ordering::Acquire);
+        let tool = c.max_tool_attempts - self.0.tool_attempts.load(Ordering::Acquire);
+        match self.time_left() {
+            Some(left) => format!("provider={} tool={} time={:?}", provider, tool, left),
+            None => format!("provider={} tool={}", provider, tool),
+        }
+    }
+
+    /// Consumed and remaining budget
+    pub fn summary(&self) -> BudgetSummary {
+        BudgetSummary {
+            provider_attempts: self.0.provider_attempts.load(Ordering::Acquire),
+            max_provider_attempts: self.0.config.max_provider_attempts,
+            tool_attempts: self.0.tool_attempts.load(Ordering::Acquire),
+            max_tool_attempts: self.0.config.max_tool_attempts,
+            elapsed: self.0.started.elapsed(),
+            time_left: self.time_left(),
+            by_layer: self.0.by_layer.lock().clone(),
+            exhausted: self.0.exhausted.lock().clone(),
+        }
+    }
+}
+
+/// Take a provider attempt from the current budget (no-op outside one)
+pub fn spend_provider_attempt(layer: &str) -> Result<()> {
+    Budget::current().map_or(Ok(()), |b| b.spend_provider(layer))
+}
+
+/// Take a tool attempt from the current budget (no-op outside one)
+pub fn spend_tool_attempt(layer: &str) -> Result<()> {
+    Budget::current().map_or(Ok(()), |b| b.spend_tool(layer))
+}
+
+/// Cap `timeout` at the current budget's time left
+pub fn clamp_timeout(timeout: Duration) -> Duration {
+    Budget::current()
+        .and_then(|b| b.time_left())
+        .map_or(timeout, |left| timeout.min(left))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_spending_is_shared_across_clones_and_scoped() {
+        let budget = Budget::new(BudgetConfig {
+            total_time: None,
+            max_provider_attempts: 2,
+            max_tool_attempts: 1,
+        });
+        assert!(spend_provider_attempt("outside").is_ok());
+        assert_eq!(budget.summary().provider_attempts, 0);
+
+        let inner = budget.clone();
+        let result = budget
+            .clone()
+            .scope(async move {
+                spend_provider_attempt("agent")?;
+                inner.spend_provider("provider_retry")?;
+                spend_tool_attempt("agent")?;
+                spend_provider_attempt("fallback")
+            })
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::BudgetExhausted { ref resource, ref layer })
+                if resource == "provider attempts" && layer == "fallback"
+        ));
+        assert!(!result.unwrap_err().is_retryable());
+
+        let summary = budget.summary();
+        assert_eq!((summary.provider_attempts, summary.tool_attempts), (2, 1));
+        assert_eq!(summary.by_layer["provider_retry"], 1);
+        assert_eq!(summary.exhausted.as_deref(), Some("provider attempts"));
+    }
+}
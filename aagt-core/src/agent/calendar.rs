@@ -0,0 +1,358 @@
+//! Trading calendars for schedule gating
+//!
+//! A [`TradingCalendar`] says when a market is open: which weekdays, which
+//! local hours, minus holidays and maintenance windows. Scheduled jobs that
+//! declare a calendar skip ticks while it is closed, and strategies can gate on
+//! one with `Condition::WithinCalendar`. Calendars are looked up by name in a
+//! [`CalendarRegistry`], which ships `crypto` (24/7), `weekdays` (Mon-Fri UTC)
+//! and `us_equities` (Mon-Fri 09:30-16:00 New York, holidays not included)
+//! and loads custom ones from JSON or YAML files.
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+
+/// Daily session, in the calendar's time zone
+///
+/// `close` before `open` means the session runs overnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionHours {
+    /// Session start (inclusive)
+    pub open: NaiveTime,
+    /// Session end (exclusive)
+    pub close: NaiveTime,
+}
+
+impl SessionHours {
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.open <= self.close {
+            self.open <= time && time < self.close
+        } else {
+            time >= self.open || time < self.close
+        }
+    }
+}
+
+/// A period the market is closed regardless of hours, e.g. exchange maintenance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    /// Window start (inclusive)
+    pub start: DateTime<Utc>,
+    /// Window end (exclusive)
+    pub end: DateTime<Utc>,
+    /// Shown in skip events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// When a market is open
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradingCalendar {
+    /// Name jobs and conditions refer to
+    pub name: String,
+    /// Zone `days`, `hours` and `holidays` are interpreted in
+    #[serde(default = "default_tz")]
+    pub tz: Tz,
+    /// Open weekdays
+    #[serde(default = "every_day")]
+    pub days: Vec<Weekday>,
+    /// Daily session; open all day when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hours: Option<SessionHours>,
+    /// Local dates the market is closed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub holidays: Vec<NaiveDate>,
+    /// Closures on top of the regular schedule
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance: Vec<MaintenanceWindow>,
+}
+
+fn default_tz() -> Tz {
+    Tz::UTC
+}
+
+fn every_day() -> Vec<Weekday> {
+    vec![
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ]
+}
+
+impl TradingCalendar {
+    /// Always open (crypto markets)
+    pub fn always_open(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            tz: Tz::UTC,
+            days: every_day(),
+            hours: None,
+            holidays: Vec::new(),
+            maintenance: Vec::new(),
+        }
+    }
+
+    /// Open all day Monday to Friday in `tz`
+    pub fn weekdays(name: impl Into<String>, tz: Tz) -> Self {
+        Self {
+            tz,
+            days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            ..Self::always_open(name)
+        }
+    }
+
+    /// US equities regular session; add exchange holidays with [`holiday`](Self::holiday)
+    pub fn us_equities() -> Self {
+        Self {
+            hours: Some(SessionHours {
+                open: NaiveTime::from_hms_opt(9, 30, 0).expect("valid time"),
+                close: NaiveTime::from_hms_opt(16, 0, 0).expect("valid time"),
+            }),
+            ..Self::weekdays("us_equities", chrono_tz::America::New_York)
+        }
+    }
+
+    /// Close on a local date
+    pub fn holiday(mut self, date: NaiveDate) -> Self {
+        self.holidays.push(date);
+        self
+    }
+
+    /// Close for a maintenance window
+    pub fn maintenance(mut self, window: MaintenanceWindow) -> Self {
+        self.maintenance.push(window);
+        self
+    }
+
+    /// Whether the market is open at `at`
+    pub fn is_open(&self, at: DateTime<Utc>) -> bool {
+        self.closed_reason(at).is_none()
+    }
+
+    /// Why the market is closed at `at`, or `None` if it is open
+    pub fn closed_reason(&self, at: DateTime<Utc>) -> Option<String> {
+        if let Some(window) = self
+            .maintenance
+            .iter()
+            .find(|w| w.start <= at && at < w.end)
+        {
+            return Some(format!(
+                "maintenance{}",
+                window
+                    .reason
+                    .as_ref()
+                    .map(|r| format!(": {}", r))
+                    .unwrap_or_default()
+            ));
+        }
+        let local = at.with_timezone(&self.tz);
+        // The hours after midnight of an overnight session belong to the previous day's session
+        let session_date = match self.hours {
+            Some(hours) if hours.open > hours.close && local.time() < hours.close => local
+                .date_naive()
+                .pred_opt()
+                .unwrap_or_else(|| local.date_naive()),
+            _ => local.date_naive(),
+        };
+        if self.holidays.contains(&session_date) {
+            return Some(format!("holiday {}", session_date));
+        }
+        if !self.days.contains(&session_date.weekday()) {
+            return Some(format!("closed on {}", session_date.weekday()));
+        }
+        match self.hours {
+            Some(hours) if !hours.contains(local.time()) => Some(format!(
+                "outside {}-{} {}",
+                hours.open.format("%H:%M"),
+                hours.close.format("%H:%M"),
+                self.tz
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Calendars by name, shared by the scheduler and strategy engine
+pub struct CalendarRegistry {
+    calendars: RwLock<HashMap<String, Arc<TradingCalendar>>>,
+}
+
+impl Default for CalendarRegistry {
+    /// Registry with the built-in calendars
+    fn default() -> Self {
+        let registry = Self::empty();
+        registry.register(TradingCalendar::always_open("crypto"));
+        registry.register(TradingCalendar::weekdays("weekdays", Tz::UTC));
+        registry.register(TradingCalendar::us_equities());
+        registry
+    }
+}
+
+impl CalendarRegistry {
+    /// Registry without the built-in calendars
+    pub fn empty() -> Self {
+        Self {
+            calendars: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Add or replace a calendar
+    pub fn register(&self, calendar: TradingCalendar) {
+        self.calendars
+            .write()
+            .insert(calendar.name.clone(), Arc::new(calendar));
+    }
+
+    /// Register every calendar in a JSON or YAML file (a single calendar or a list)
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<usize> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum OneOrMany {
+            Many(Vec<TradingCalendar>),
+            One(TradingCalendar),
+        }
+
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let is_yaml = matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("yaml" | "yml")
+        );
+        let parsed: OneOrMany = if is_yaml {
+            serde_yaml_ng::from_str(&text).map_err(|e| {
+                Error::agent_config(format!("Invalid calendar file {}: {}", path.display(), e))
+            })?
+        } else {
+            serde_json::from_str(&text).map_err(|e| {
+                Error::agent_config(format!("Invalid calendar file {}: {}", path.display(), e))
+            })?
+        };
+        let calendars = match parsed {
+            OneOrMany::Many(calendars) => calendars,
+            OneOrMany::One(calendar) => vec![calendar],
+        };
+        let count = calendars.len();
+        for calendar in calendars {
+            self.register(calendar);
+        }
+        Ok(count)
+    }
+
+    /// Calendar called `name`
+    pub fn get(&self, name: &str) -> Result<Arc<TradingCalendar>> {
+        self.calendars
+            .read()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::agent_config(format!("Unknown trading calendar: {}", name)))
+    }
+
+    /// Why calendar `name` is closed at `at`, or `None` if it is open
+    pub fn closed_reason(&self, name: &str, at: DateTime<Utc>) -> Result<Option<String>> {
+        Ok(self.get(name)?.closed_reason(at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn ny(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        chrono_tz::America::New_York
+            .with_ymd_and_hms(y, m, d, h, min, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_us_equities_hours_weekends_and_holidays() {
+        let calendar =
+            TradingCalendar::us_equities().holiday(NaiveDate::from_ymd_opt(2026, 12, 25).unwrap());
+
+        // Friday 2026-10-16
+        assert!(calendar.is_open(ny(2026, 10, 16, 9, 30)));
+        assert!(!calendar.is_open(ny(2026, 10, 16, 16, 0)));
+        assert_eq!(
+            calendar.closed_reason(ny(2026, 10, 16, 8, 0)).unwrap(),
+            "outside 09:30-16:00 America/New_York"
+        );
+        // Weekend, including late Friday UTC which is still Friday in New York
+        assert_eq!(
+            calendar.closed_reason(ny(2026, 10, 17, 12, 0)).unwrap(),
+            "closed on Sat"
+        );
+        assert!(calendar.is_open(Utc.with_ymd_and_hms(2026, 10, 16, 19, 59, 0).unwrap()));
+        assert_eq!(
+            calendar.closed_reason(ny(2026, 12, 25, 12, 0)).unwrap(),
+            "holiday 2026-12-25"
+        );
+        // Same wall-clock session across the DST change
+        assert!(calendar.is_open(ny(2026, 11, 2, 9, 30)));
+        assert!(TradingCalendar::always_open("crypto").is_open(ny(2026, 10, 17, 3, 0)));
+    }
+
+    #[test]
+    fn test_custom_calendars_load_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("calendars.yaml");
+        std::fs::write(
+            &path,
+            r#"
+- name: dex_maintenance
+  maintenance:
+    - start: 2026-10-16T02:00:00Z
+      end: 2026-10-16T03:00:00Z
+      reason: validator upgrade
+- name: asia_overnight
+  tz: Asia/Tokyo
+  days: [Mon, Tue, Wed, Thu, Fri]
+  hours: { open: "21:00:00", close: "03:00:00" }
+"#,
+        )
+        .unwrap();
+
+        let registry = CalendarRegistry::default();
+        assert_eq!(registry.load(&path).unwrap(), 2);
+        let at = |h, m| Utc.with_ymd_and_hms(2026, 10, 16, h, m, 0).unwrap();
+        assert_eq!(
+            registry
+                .closed_reason("dex_maintenance", at(2, 30))
+                .unwrap()
+                .unwrap(),
+            "maintenance: validator upgrade"
+        );
+        assert!(registry.get("dex_maintenance").unwrap().is_open(at(3, 0)));
+        // 13:00 UTC Friday is 22:00 in Tokyo, inside the overnight session
+        assert!(registry.get("asia_overnight").unwrap().is_open(at(13, 0)));
+        assert!(!registry.get("asia_overnight").unwrap().is_open(at(8, 0)));
+        // Sat 01:00 in Tokyo is still Friday's session
+        assert!(registry.get("asia_overnight").unwrap().is_open(at(16, 0)));
+        // Mon 01:00 in Tokyo would be Sunday's session, which does not exist
+        let monday_early = Utc.with_ymd_and_hms(2026, 10, 18, 16, 0, 0).unwrap();
+        assert_eq!(
+            registry
+                .closed_reason("asia_overnight", monday_early)
+                .unwrap()
+                .unwrap(),
+            "closed on Sun"
+        );
+        assert!(registry.get("nasdaq").is_err());
+    }
+}
@@ -3,21 +3,47 @@
 //! This module provides the `ContextManager` which is responsible for:
 //! - Managing conversation history (short-term memory)
 //! - Constructing the final prompt/messages for the LLM
-//! - Handling token budgeting and windowing
+//! - Handling token budgeting and windowing, with a pluggable [`TokenCounter`]
 //! - Injecting system prompts and dynamic context (RAG)
-
-use crate::agent::message::Message;
+//! - Stubbing old tool results when tool-output aging is set
+//! - Folding old history into a rolling summary when [`ContextConfig::summarize`] is set
+//!
+//! [`ContextManager::render_preview`] returns the exact assembled context with
+//! each section attributed to its source, and the system prompt's text to its
+//! named sections, without calling a provider. Its
+//! [`RenderedContext::to_snapshot`] text is stable across runs, so tests can
+//! pin the rendering with [`assert_snapshot`] or [`assert_context_snapshot!`]
+//! and see prompt changes as snapshot diffs. Set `AAGT_BLESS=1` to rewrite
+//! snapshot files instead of comparing.
+
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::agent::history_summary::{HistorySummarizer, SummarizeConfig};
+use crate::agent::message::{ContentPart, Message, Role};
+use crate::agent::provider::Provider;
+use crate::agent::system_prompt::SystemPrompt;
+use crate::agent::tool_aging::ToolOutputAging;
+use crate::skills::tool::ToolDefinition;
 use crate::error::Result;
 
+/// Env var that makes snapshot assertions rewrite their files
+pub const BLESS_ENV: &str = "AAGT_BLESS";
+
 /// Configuration for the Context Manager
 #[derive(Debug, Clone)]
 pub struct ContextConfig {
-    /// Maximum tokens allowed in the context window
+    /// Maximum tokens allowed in the context window, including the response reserve
     pub max_tokens: usize,
     /// Maximum number of messages to keep in history
     pub max_history_messages: usize,
     /// Reserve tokens for the response
     pub response_reserve: usize,
+    /// Summarize old history instead of only dropping it (needs a summary provider)
+    pub summarize: Option<SummarizeConfig>,
 }
 
 impl Default for ContextConfig {
@@ -26,37 +52,365 @@ impl Default for ContextConfig {
             max_tokens: 128000, // Modern default (e.g. GPT-4o)
             max_history_messages: 50,
             response_reserve: 4096,
+            summarize: None,
         }
     }
 }
 
+/// Tokens added to every message for its role and separators
+const MESSAGE_OVERHEAD: usize = 4;
+
+/// Estimates how many tokens text takes up in the model's context
+pub trait TokenCounter: Send + Sync {
+    /// Tokens in `text`
+    fn count(&self, text: &str) -> usize;
+
+    /// Tokens a message costs, counting tool calls and results as the model sees them
+    fn count_message(&self, message: &Message) -> usize {
+        self.count(&section_text(message)) + MESSAGE_OVERHEAD
+    }
+}
+
+/// Cheap estimate of one token per four characters
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CharEstimate;
+
+impl TokenCounter for CharEstimate {
+    fn count(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+}
+
+/// Exact counts for OpenAI-style models (`cl100k_base`)
+#[cfg(feature = "tiktoken")]
+pub struct TiktokenCounter {
+    bpe: tiktoken_rs::CoreBPE,
+}
+
+#[cfg(feature = "tiktoken")]
+impl TiktokenCounter {
+    /// Load the `cl100k_base` encoding
+    pub fn new() -> Result<Self> {
+        Ok(Self { bpe: tokenizer()? })
+    }
+}
+
+#[cfg(feature = "tiktoken")]
+impl TokenCounter for TiktokenCounter {
+    fn count(&self, text: &str) -> usize {
+        self.bpe.encode_with_special_tokens(text).len()
+    }
+}
+
 /// Trait for injecting dynamic context
 #[async_trait::async_trait]
 pub trait ContextInjector: Send + Sync {
     /// Generate messages to inject into the context
     async fn inject(&self) -> Result<Vec<Message>>;
+
+    /// Generate messages for a context built over `history`
+    ///
+    /// Override to look at the conversation, e.g. to retrieve documents for the
+    /// latest user message; defaults to [`inject`](Self::inject).
+    async fn inject_with_history(&self, history: &[Message]) -> Result<Vec<Message>> {
+        let _ = history;
+        self.inject().await
+    }
+
+    /// Key its sections are attributed to in a [`RenderedContext`]
+    fn source_key(&self) -> String {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name).to_string()
+    }
+}
+
+/// One message of an assembled context and where it came from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ContextSection {
+    /// `system_prompt`, `leading`, an injector's [`ContextInjector::source_key`],
+    /// or `history[i]` for the i-th history message
+    pub source: String,
+    /// Message role
+    pub role: Role,
+    /// Message text, with tool calls and results spelled out
+    pub text: String,
+    /// Tokens counted against the window for this message
+    pub tokens: usize,
+}
+
+/// Text one [`SystemPrompt`] section contributed to the system prompt
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PromptPart {
+    /// Section key, e.g. `identity` or `legacy`
+    pub key: String,
+    /// Rendered text, header included
+    pub text: String,
+    /// Tokens of `text` alone
+    pub tokens: usize,
+}
+
+/// The context a provider would receive, section by section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenderedContext {
+    /// Sections in the order they are sent
+    pub sections: Vec<ContextSection>,
+    /// Sum of section tokens
+    pub total_tokens: usize,
+    /// Sections of the `system_prompt` message, in render order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub prompt_parts: Vec<PromptPart>,
+}
+
+impl RenderedContext {
+    /// One line naming the system prompt's sections and their tokens
+    ///
+    /// `None` for a prompt that is a single `legacy` string, which needs no
+    /// attribution.
+    pub fn prompt_attribution(&self) -> Option<String> {
+        match self.prompt_parts.as_slice() {
+            [] => None,
+            [only] if only.key == "legacy" => None,
+            parts => Some(format!(
+                "> sections: {}",
+                parts
+                    .iter()
+                    .map(|p| format!("{} ({} tokens)", p.key, p.tokens))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+
+    /// Deterministic text rendering for snapshot files
+    ///
+    /// `normalize_whitespace` trims trailing spaces, unifies line endings and
+    /// collapses runs of blank lines so editor noise doesn't fail snapshots.
+    pub fn to_snapshot(&self, normalize_whitespace: bool) -> String {
+        let mut out = format!(
+            "# {} sections, {} tokens\n",
+            self.sections.len(),
+            self.total_tokens
+        );
+        for section in &self.sections {
+            out.push_str(&format!(
+                "\n## [{}] {} ({} tokens)\n",
+                section.source,
+                section.role.as_str(),
+                section.tokens
+            ));
+            if section.source == "system_prompt" {
+                if let Some(line) = self.prompt_attribution() {
+                    out.push_str(&line);
+                    out.push('\n');
+                }
+            }
+            out.push_str(&section.text);
+            out.push('\n');
+        }
+        if normalize_whitespace {
+            normalize(&out)
+        } else {
+            out
+        }
+    }
+}
+
+fn normalize(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut blank = false;
+    for line in text.replace("\r\n", "\n").lines() {
+        let line = line.trim_end();
+        if line.is_empty() && blank {
+            continue;
+        }
+        blank = line.is_empty();
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(feature = "tiktoken")]
+fn tokenizer() -> Result<tiktoken_rs::CoreBPE> {
+    tiktoken_rs::cl100k_base().map_err(|e| {
+        crate::error::Error::Internal(format!("Failed to load tokenizer: {}", e))
+    })
+}
+
+/// Message text as the model sees it, including tool calls and results
+pub(crate) fn section_text(message: &Message) -> String {
+    match &message.content {
+        crate::agent::message::Content::Text(text) => text.clone(),
+        crate::agent::message::Content::Parts(parts) => parts
+            .iter()
+            .map(|part| match part {
+                ContentPart::Text { text } => text.clone(),
+                ContentPart::Image { .. } => "[image]".to_string(),
+                ContentPart::FileRef { .. } => part.placeholder().unwrap_or_default(),
+                ContentPart::ToolCall {
+                    name, arguments, ..
+                } => format!("[tool call] {}({})", name, arguments),
+                ContentPart::ToolResult { name, content, .. } => format!(
+                    "[tool result] {}: {}",
+                    name.as_deref().unwrap_or("?"),
+                    content
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// History from `first` on, split into the units trimming keeps or drops whole:
+/// an assistant message that calls tools together with the results after it, or
+/// any other single message. Results whose call lies before `first` are skipped.
+pub(crate) fn exchange_units(history: &[Message], first: usize) -> Vec<Range<usize>> {
+    let calls_tools = |message: &Message| match &message.content {
+        crate::agent::message::Content::Parts(parts) => parts
+            .iter()
+            .any(|part| matches!(part, ContentPart::ToolCall { .. })),
+        crate::agent::message::Content::Text(_) => false,
+    };
+    let mut units: Vec<Range<usize>> = Vec::new();
+    for (i, message) in history.iter().enumerate().skip(first) {
+        if message.role == Role::Tool {
+            match units.last_mut() {
+                Some(unit) if calls_tools(&history[unit.start]) => {
+                    unit.end = i + 1;
+                    continue;
+                }
+                None => continue,
+                Some(_) => {}
+            }
+        }
+        units.push(i..i + 1);
+    }
+    units
+}
+
+/// Options for an agent's context preview
+#[derive(Debug, Clone, Default)]
+pub struct PreviewOptions {
+    /// Re-render tool definitions instead of using the cached ones
+    pub force_fresh: bool,
+}
+
+/// Compare `actual` with the snapshot at `path`, or rewrite it when `AAGT_BLESS` is set
+///
+/// Panics with a line diff on mismatch, or if the snapshot doesn't exist yet.
+pub fn assert_snapshot(actual: &str, path: impl AsRef<Path>) {
+    let bless = std::env::var(BLESS_ENV).is_ok_and(|v| !v.is_empty() && v != "0");
+    if let Err(message) = check_snapshot(actual, path.as_ref(), bless) {
+        panic!("{}", message);
+    }
+}
+
+fn check_snapshot(actual: &str, path: &Path, bless: bool) -> std::result::Result<(), String> {
+    if bless {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        return std::fs::write(path, actual).map_err(|e| e.to_string());
+    }
+    let expected = std::fs::read_to_string(path).map_err(|e| {
+        format!(
+            "Snapshot {} not readable ({}); run with {}=1 to create it",
+            path.display(),
+            e,
+            BLESS_ENV
+        )
+    })?;
+    if expected == actual {
+        return Ok(());
+    }
+    let mut diff = String::new();
+    let (old, new): (Vec<_>, Vec<_>) = (expected.lines().collect(), actual.lines().collect());
+    for i in 0..old.len().max(new.len()) {
+        let (o, n) = (old.get(i), new.get(i));
+        if o != n {
+            if let Some(o) = o {
+                diff.push_str(&format!("{:>4} - {}\n", i + 1, o));
+            }
+            if let Some(n) = n {
+                diff.push_str(&format!("{:>4} + {}\n", i + 1, n));
+            }
+        }
+    }
+    Err(format!(
+        "Context snapshot {} changed; run with {}=1 to accept:\n{}",
+        path.display(),
+        BLESS_ENV,
+        diff
+    ))
+}
+
+/// Assert an agent's rendered context for `messages` matches a snapshot file
+///
+/// `path` is relative to the calling crate's manifest directory. Must be used in
+/// an async context. Set `AAGT_BLESS=1` to write the current rendering instead.
+///
+/// ```ignore
+/// assert_context_snapshot!(agent, fixture_messages, "snapshots/trading_agent.txt");
+/// ```
+#[macro_export]
+macro_rules! assert_context_snapshot {
+    ($agent:expr, $messages:expr, $path:expr) => {
+        $crate::agent::context::assert_snapshot(
+            &$agent
+                .render_context_preview(&$messages, ::std::default::Default::default())
+                .await
+                .expect("context preview failed")
+                .to_snapshot(true),
+            ::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join($path),
+        )
+    };
 }
 
 /// Manages the context window for an agent
 pub struct ContextManager {
     config: ContextConfig,
-    system_prompt: Option<String>,
+    system_prompt: parking_lot::RwLock<Option<SystemPrompt>>,
     injectors: Vec<Box<dyn ContextInjector>>,
+    tool_aging: Option<ToolOutputAging>,
+    summarizer: Option<HistorySummarizer>,
+    counter: Arc<dyn TokenCounter>,
 }
 
 impl ContextManager {
-    /// Create a new ContextManager
+    /// Create a new ContextManager counting tokens with [`CharEstimate`]
+    ///
+    /// Use [`set_token_counter`](Self::set_token_counter) for exact counts, e.g.
+    /// `TiktokenCounter` with the `tiktoken` feature.
     pub fn new(config: ContextConfig) -> Self {
+        let counter: Arc<dyn TokenCounter> = Arc::new(CharEstimate);
         Self {
             config,
-            system_prompt: None,
+            system_prompt: parking_lot::RwLock::new(None),
             injectors: Vec::new(),
+            tool_aging: None,
+            summarizer: None,
+            counter,
         }
     }
 
-    /// Set the system prompt
+    /// Count tokens with `counter` instead, e.g. to match another model family
+    pub fn set_token_counter(&mut self, counter: Arc<dyn TokenCounter>) {
+        self.counter = counter;
+    }
+
+    /// Set the system prompt as a single `legacy` section
     pub fn set_system_prompt(&mut self, prompt: impl Into<String>) {
-        self.system_prompt = Some(prompt.into());
+        *self.system_prompt.get_mut() = Some(SystemPrompt::legacy(prompt));
+    }
+
+    /// Set the system prompt from named sections
+    pub fn set_system_prompt_sections(&mut self, prompt: SystemPrompt) {
+        *self.system_prompt.get_mut() = Some(prompt);
+    }
+
+    /// Swap the system prompt of a shared manager; the next assembled context uses it
+    pub fn replace_system_prompt(&self, prompt: SystemPrompt) {
+        *self.system_prompt.write() = Some(prompt);
     }
 
     /// Add a context injector
@@ -64,35 +418,138 @@ impl ContextManager {
         self.injectors.push(injector);
     }
 
+    /// Replace old tool results with stubs in assembled contexts
+    pub fn set_tool_aging(&mut self, aging: ToolOutputAging) {
+        self.tool_aging = Some(aging);
+    }
+
+    /// Summarize old history through `provider` (only when [`ContextConfig::summarize`] is set)
+    pub fn set_summary_provider(&mut self, provider: Arc<dyn Provider>) {
+        self.summarizer = self
+            .config
+            .summarize
+            .clone()
+            .map(|config| HistorySummarizer::new(config, provider));
+    }
+
     /// Construct the final list of messages to send to the provider
     ///
     /// This method applies:
     /// 1. System prompt injection (Protected)
     /// 2. Dynamic Context Injection (RAG, etc.) (Protected)
-    /// 3. Token budgeting using tiktoken (Soft Pruning)
+    /// 3. Token budgeting with the [`TokenCounter`] (Soft Pruning)
     /// 4. Message windowing (based on max_history_messages)
+    ///
+    /// History is trimmed oldest first. The last user message is always kept,
+    /// and a tool call is dropped or kept together with its results.
     pub async fn build_context(&self, history: &[Message]) -> Result<Vec<Message>> {
-        // 1. Initialize Tokenizer
-        let bpe = tiktoken_rs::cl100k_base().map_err(|e| {
-            crate::error::Error::Internal(format!("Failed to load tokenizer: {}", e))
-        })?;
+        self.build_context_with(history, Vec::new(), &[]).await
+    }
+
+    /// Like [`ContextManager::build_context`], with per-call protected messages
+    /// (e.g. a tool catalog filtered for this step) placed before the injectors
+    ///
+    /// `tools` are the definitions sent with the request; their serialized
+    /// size counts against the history budget.
+    pub async fn build_context_with(
+        &self,
+        history: &[Message],
+        leading: Vec<Message>,
+        tools: &[ToolDefinition],
+    ) -> Result<Vec<Message>> {
+        Ok(self
+            .assemble(history, leading, tools)
+            .await?
+            .into_iter()
+            .map(|(_, message, _)| message)
+            .collect())
+    }
+
+    /// The context [`ContextManager::build_context`] would produce, with attribution
+    pub async fn render_preview(&self, history: &[Message]) -> Result<RenderedContext> {
+        self.render_preview_with(history, Vec::new(), &[]).await
+    }
 
+    /// The context [`ContextManager::build_context_with`] would produce, with attribution
+    pub async fn render_preview_with(
+        &self,
+        history: &[Message],
+        leading: Vec<Message>,
+        tools: &[ToolDefinition],
+    ) -> Result<RenderedContext> {
+        let prompt_parts = match &*self.system_prompt.read() {
+            Some(prompt) => prompt
+                .render_parts()
+                .into_iter()
+                .map(|(key, text)| PromptPart {
+                    key: key.to_string(),
+                    tokens: self.counter.count(&text),
+                    text,
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let sections: Vec<_> = self
+            .assemble(history, leading, tools)
+            .await?
+            .into_iter()
+            .map(|(source, message, tokens)| ContextSection {
+                source,
+                role: message.role.clone(),
+                text: section_text(&message),
+                tokens,
+            })
+            .collect();
+        Ok(RenderedContext {
+            total_tokens: sections.iter().map(|s| s.tokens).sum(),
+            sections,
+            prompt_parts,
+        })
+    }
+
+    /// Final messages with their source keys and token costs
+    async fn assemble(
+        &self,
+        history: &[Message],
+        leading: Vec<Message>,
+        tools: &[ToolDefinition],
+    ) -> Result<Vec<(String, Message, usize)>> {
         let mut final_context_start = Vec::new();
 
         // --- 1. System Prompt (Protected) ---
-        if let Some(prompt) = &self.system_prompt {
-            final_context_start.push(Message::system(prompt.clone()));
+        if let Some(prompt) = self.system_prompt.read().clone() {
+            for section in prompt.sections() {
+                tracing::debug!(section = %section.key, chars = section.text.len(), "System prompt section");
+            }
+            final_context_start.push(("system_prompt".to_string(), Message::system(prompt.render())));
         }
+        final_context_start.extend(leading.into_iter().map(|m| ("leading".to_string(), m)));
 
         // --- 2. Run Injectors (Protected - e.g. RAG) ---
         // In a more advanced version, we might want to budget RAG too, but for now we treat it as critical context.
         for injector in &self.injectors {
-            match injector.inject().await {
-                Ok(msgs) => final_context_start.extend(msgs),
+            match injector.inject_with_history(history).await {
+                Ok(msgs) => {
+                    let source = injector.source_key();
+                    final_context_start.extend(msgs.into_iter().map(|m| (source.clone(), m)));
+                }
                 Err(e) => tracing::warn!("Context injector failed: {}", e),
             }
         }
 
+        // Old history folded into the rolling summary is replaced by it
+        let offset = match &self.summarizer {
+            Some(summarizer) => match summarizer.summarize(history).await {
+                Some((summary, covered)) => {
+                    final_context_start.push(("history_summary".to_string(), summary));
+                    covered
+                }
+                None => 0,
+            },
+            None => 0,
+        };
+        let history = &history[offset..];
+
         // --- 3. Calculate Budget ---
         // Safety Margin: 1000 tokens for formatting, JSON overhead, and fragmentation
         const SAFETY_MARGIN: usize = 1000;
@@ -100,18 +557,25 @@ impl ContextManager {
         let reserved_response = self.config.response_reserve;
         let max_window = self.config.max_tokens;
 
-        // Calculate current usage from System + RAG
-        let mut current_usage = 0;
-        for msg in &final_context_start {
-            current_usage += bpe.encode_with_special_tokens(&msg.content.as_text()).len();
-            current_usage += 4; // Approx per-message overhead
-        }
+        // Calculate current usage from System + RAG + tool definitions
+        let mut current_usage: usize = tools
+            .iter()
+            .map(|def| self.counter.count(&serde_json::to_string(def).unwrap_or_default()))
+            .sum();
+        let final_context_start: Vec<_> = final_context_start
+            .into_iter()
+            .map(|(source, msg)| {
+                let cost = self.counter.count_message(&msg);
+                current_usage += cost;
+                (source, msg, cost)
+            })
+            .collect();
 
         // Check if we already blew the budget
         let total_reserved = reserved_response + SAFETY_MARGIN + current_usage;
         if total_reserved > max_window {
             tracing::warn!(
-                "System prompt + RAG context exceeds context window! (Usage: {}, Limit: {})",
+                "System prompt + RAG context + tools exceed context window! (Usage: {}, Limit: {})",
                 current_usage,
                 max_window - reserved_response - SAFETY_MARGIN
             );
@@ -124,113 +588,317 @@ impl ContextManager {
             0
         };
 
-        // --- 4. Select History (Sliding Window) ---
-        // Prioritize: Latest messages -> Oldest messages
-        // Also respect max_history_messages count
-
-        let mut selected_history = Vec::new();
-        let mut history_usage = 0;
-
-        // Pre-filter by count limit to avoid iterating 10k messages if we only want 50
-        // Taking the LAST N messages
-        let history_slice = if history.len() > self.config.max_history_messages {
-            &history[history.len() - self.config.max_history_messages..]
-        } else {
-            history
+        // Stub old tool results; persisted history keeps the full text
+        let history = match &self.tool_aging {
+            Some(aging) => aging.apply(history, history_budget, |m| self.counter.count_message(m)),
+            None => std::borrow::Cow::Borrowed(history),
         };
 
-        // Iterate REVERSE (Latest first)
-        for msg in history_slice.iter().rev() {
-            let content_text = msg.content.as_text();
-            let tokens = bpe.encode_with_special_tokens(&content_text).len();
-            let cost = tokens + 4; // Overhead
-
-            if history_usage + cost <= history_budget {
-                history_usage += cost;
-                selected_history.push(msg.clone());
+        // --- 4. Select History (Sliding Window) ---
+        // Prioritize: Latest messages -> Oldest messages, a whole tool exchange at a time.
+        // The count limit applies first; the last user message is kept regardless.
+        let first = history.len().saturating_sub(self.config.max_history_messages);
+        let pinned = history.iter().rposition(|m| m.role == Role::User);
+        let cost = |i: usize| self.counter.count_message(&history[i]);
+
+        let mut history_usage = pinned.map_or(0, cost);
+        let mut selected: Vec<Range<usize>> = pinned.filter(|&i| i < first).map(|i| i..i + 1).into_iter().collect();
+        let mut full = false;
+        for unit in exchange_units(&history, first).into_iter().rev() {
+            if pinned == Some(unit.start) {
+                selected.push(unit);
+                continue;
+            }
+            if full {
+                // Past the budget: only an older pinned message is still wanted
+                if pinned.is_none_or(|p| p > unit.start || p < first) {
+                    break;
+                }
+                continue;
+            }
+            let unit_cost: usize = unit.clone().map(cost).sum();
+            if history_usage + unit_cost <= history_budget {
+                history_usage += unit_cost;
+                selected.push(unit);
             } else {
                 tracing::debug!(
                     "Context window limit reached, pruning older messages. (Budget: {}, Used: {})",
                     history_budget,
                     history_usage
                 );
-                break;
+                full = true;
             }
         }
+        if history_usage > history_budget {
+            tracing::warn!(
+                "Last user message alone exceeds the history budget (Usage: {}, Budget: {})",
+                history_usage,
+                history_budget
+            );
+        }
 
         // --- 5. Assemble Final Context ---
 
         // Start with System + RAG
         let mut final_messages = final_context_start;
 
-        // Append History (Reverse back to chronological order)
-        selected_history.reverse();
-        final_messages.extend(selected_history);
+        // Append History in chronological order
+        selected.sort_by_key(|unit| unit.start);
+        final_messages.extend(
+            selected
+                .into_iter()
+                .flatten()
+                .map(|i| (format!("history[{}]", i + offset), history[i].clone(), cost(i))),
+        );
 
         Ok(final_messages)
     }
 
-    /// Estimate token count for a list of messages using tiktoken
+    /// Estimate token count for a list of messages
+    ///
+    /// Uses `cl100k_base` with the `tiktoken` feature, [`CharEstimate`] otherwise.
     pub fn estimate_tokens(messages: &[Message]) -> usize {
+        #[cfg(feature = "tiktoken")]
         if let Ok(bpe) = tiktoken_rs::cl100k_base() {
-            messages
+            return messages
                 .iter()
-                .map(|m| bpe.encode_with_special_tokens(&m.content.as_text()).len() + 4)
-                .sum()
-        } else {
-            // Fallback to heuristic if tokenizer fails
-            messages
-                .iter()
-                .map(|m| m.content.as_text().len() / 4)
-                .sum::<usize>()
+                .map(|m| bpe.encode_with_special_tokens(&m.content.as_text()).len() + MESSAGE_OVERHEAD)
+                .sum();
         }
+        messages
+            .iter()
+            .map(|m| CharEstimate.count(&m.content.as_text()) + MESSAGE_OVERHEAD)
+            .sum()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    // use crate::agent::message::Content;
+    use crate::agent::personality::{PersonalityManager, Persona};
 
-    #[test]
-    fn test_context_windowing() {
+    fn snapshot_path(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/snapshots")
+            .join(name)
+    }
+
+    struct Rag;
+
+    #[async_trait::async_trait]
+    impl ContextInjector for Rag {
+        async fn inject(&self) -> Result<Vec<Message>> {
+            Ok(vec![Message::system("Retrieved: SOL funding rate is 0.01%")])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_context_windowing() {
         let config = ContextConfig {
             max_history_messages: 5,
-            max_tokens: 100, // Very small window
+            // 1000-token safety margin + 10 reserved leaves ~15 tokens for history
+            max_tokens: 1030,
             response_reserve: 10,
-            ..Default::default()
+            summarize: None,
         };
         let mut mgr = ContextManager::new(config);
-        mgr.set_system_prompt("System"); // Approx 1 token + overhead
+        mgr.set_system_prompt("System");
 
-        // Create messages
-        // "Hello" is 1 token.
-        let _history = vec![
-            Message::user("1. Long message that should be pruned because it exceeds budget..."), // ~10+ tokens
+        let history = vec![
+            Message::user("1. Long message that should be pruned because it exceeds budget..."),
             Message::user("2. Medium"),
             Message::user("3. Short"),
         ];
+        let rendered = mgr.render_preview(&history).await.unwrap();
+        assert_snapshot(
+            &rendered.to_snapshot(true),
+            snapshot_path("context_windowing.txt"),
+        );
+        assert_eq!(mgr.build_context(&history).await.unwrap().len(), 3);
+    }
 
-        // System (1) + Overhead (4) = 5
-        // Safety (1000) ?? Wait, safety margin is 1000 in code.
-        // My test config max_tokens=100 is smaller than SAFETY_MARGIN (1000).
-        // This will cause budget to be 0.
-        // I need to adjust test or const.
-        // The const is private inside build_context.
-        // I can't change it.
-        // I should update the test to use realistic numbers or the implementation to handle small limits gracefully?
-        // Or make SAFETY_MARGIN configurable?
-        // Ideally configurable or proportional.
-        // Let's rely on standard test first.
+    /// A tool-calling assistant turn followed by its result
+    fn exchange(id: &str, result_chars: usize) -> [Message; 2] {
+        [
+            Message::assistant(crate::agent::message::Content::Parts(vec![
+                ContentPart::ToolCall {
+                    id: id.to_string(),
+                    name: "market_report".to_string(),
+                    arguments: serde_json::json!({"symbol": "SOL"}),
+                },
+            ])),
+            Message::tool_result(id, "x".repeat(result_chars)).with_tool_name("market_report"),
+        ]
     }
 
-    #[test]
-    fn test_basic_inclusion() {
-        // Normal case
-        let config = ContextConfig::default();
-        let mgr = ContextManager::new(config);
+    fn token_manager(max_context_tokens: usize) -> ContextManager {
+        let mut mgr = ContextManager::new(ContextConfig {
+            max_tokens: max_context_tokens,
+            max_history_messages: 100,
+            response_reserve: 100,
+            summarize: None,
+        });
+        mgr.set_system_prompt("You are a trading agent.");
+        mgr.set_token_counter(Arc::new(CharEstimate));
+        mgr
+    }
+
+    #[tokio::test]
+    async fn test_token_budget_keeps_tool_pairs_and_last_user_message() {
+        let mut history = vec![Message::user("Morning report?")];
+        history.extend(exchange("call_1", 2000));
+        history.push(Message::assistant("SOL looks weak."));
+        history.push(Message::user("And now?"));
+        history.extend(exchange("call_2", 400));
+        history.extend(exchange("call_3", 40));
+
+        for max_context_tokens in (1100..1900).step_by(25) {
+            let mgr = token_manager(max_context_tokens);
+            let context = mgr.build_context(&history).await.unwrap();
+            let counter = CharEstimate;
+
+            let used: usize = context.iter().map(|m| counter.count_message(m)).sum();
+            assert!(used <= max_context_tokens - 100, "{} > {}", used, max_context_tokens);
+            assert!(context.iter().any(|m| m.content.as_text() == "And now?"));
+            for (i, message) in context.iter().enumerate() {
+                let calls = section_text(message).starts_with("[tool call]");
+                let results = message.role == Role::Tool;
+                let prev_calls = i > 0
+                    && (section_text(&context[i - 1]).starts_with("[tool call]")
+                        || context[i - 1].role == Role::Tool);
+                assert!(!results || prev_calls, "orphaned result at {}", i);
+                let next_result = context.get(i + 1).is_some_and(|m| m.role == Role::Tool);
+                assert!(!calls || next_result, "call without result at {}", i);
+            }
+        }
+
+        // Room for everything newer than the 2000-char result; its call goes with it
+        let context = token_manager(1700).build_context(&history).await.unwrap();
+        assert_eq!(context.len(), 7);
+        assert_eq!(context[1].content.as_text(), "SOL looks weak.");
+    }
+
+    #[tokio::test]
+    async fn test_token_budget_counts_tool_definitions() {
+        let tools: Vec<ToolDefinition> = (0..40)
+            .map(|i| ToolDefinition {
+                name: format!("market_tool_{}", i),
+                description: "Reports market data for a trading pair. ".repeat(10),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": { "symbol": { "type": "string" } }
+                }),
+                is_verified: true,
+                side_effect_free: true,
+                ..Default::default()
+            })
+            .collect();
+        let counter = CharEstimate;
+        let tool_tokens: usize = tools
+            .iter()
+            .map(|def| counter.count(&serde_json::to_string(def).unwrap()))
+            .sum();
+        assert!(tool_tokens > 4000, "{}", tool_tokens);
+
+        let mut history = vec![Message::user("Morning report?")];
+        for i in 0..8 {
+            history.extend(exchange(&format!("call_{}", i), 2000));
+        }
+        history.push(Message::user("And now?"));
+
+        for max_context_tokens in (tool_tokens + 1200..tool_tokens + 6000).step_by(200) {
+            let mgr = token_manager(max_context_tokens);
+            let context = mgr.build_context_with(&history, Vec::new(), &tools).await.unwrap();
+
+            let used: usize = context.iter().map(|m| counter.count_message(m)).sum();
+            assert!(
+                used + tool_tokens <= max_context_tokens - 100,
+                "{} + {} > {}",
+                used,
+                tool_tokens,
+                max_context_tokens
+            );
+            assert_eq!(context.last().unwrap().content.as_text(), "And now?");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversized_last_user_message_is_never_dropped() {
+        let mut history = vec![Message::user("Earlier question")];
+        history.extend(exchange("call_1", 40));
+        history.push(Message::user("y".repeat(20_000)));
+
+        let rendered = token_manager(1200).render_preview(&history).await.unwrap();
+        let sources: Vec<_> = rendered.sections.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(sources, ["system_prompt", "history[3]"]);
+        assert_eq!(rendered.sections[1].tokens, 5000 + MESSAGE_OVERHEAD);
+    }
+
+    #[tokio::test]
+    async fn test_basic_inclusion() {
+        let mgr = ContextManager::new(ContextConfig::default());
         let history = vec![Message::user("test")];
         let ctx = mgr.build_context(&history).await.unwrap();
         assert_eq!(ctx.len(), 1);
+        assert_snapshot(
+            &mgr.render_preview(&history).await.unwrap().to_snapshot(true),
+            snapshot_path("context_basic.txt"),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sections_attributed_to_injectors_and_deterministic() {
+        let mut mgr = ContextManager::new(ContextConfig::default());
+        mgr.set_system_prompt("You are a trading agent.");
+        mgr.add_injector(Box::new(PersonalityManager::new(
+            Persona::analytical_trader(),
+        )));
+        mgr.add_injector(Box::new(Rag));
+        let history = vec![Message::user("Long SOL?"), Message::assistant("Checking.")];
+
+        let rendered = mgr
+            .render_preview_with(&history, vec![Message::system("catalog")], &[])
+            .await
+            .unwrap();
+        let sources: Vec<_> = rendered.sections.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(
+            sources,
+            ["system_prompt", "leading", "persona", "Rag", "history[0]", "history[1]"]
+        );
+        assert!(rendered.sections[2].text.contains("Senior Quant Strategist"));
+        assert_eq!(
+            rendered.total_tokens,
+            rendered.sections.iter().map(|s| s.tokens).sum::<usize>()
+        );
+        // Same sections in the same order as the messages actually sent
+        let built = mgr
+            .build_context_with(&history, vec![Message::system("catalog")], &[])
+            .await
+            .unwrap();
+        assert_eq!(built.len(), rendered.sections.len());
+
+        let again = mgr
+            .render_preview_with(&history, vec![Message::system("catalog")], &[])
+            .await
+            .unwrap();
+        assert_eq!(rendered.to_snapshot(true), again.to_snapshot(true));
+    }
+
+    #[test]
+    fn test_bless_then_compare_workflow() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/agent.txt");
+
+        let err = check_snapshot("a\n", &path, false).unwrap_err();
+        assert!(err.contains("AAGT_BLESS=1"));
+        check_snapshot("a\nb\n", &path, true).unwrap();
+        assert!(check_snapshot("a\nb\n", &path, false).is_ok());
+
+        let diff = check_snapshot("a\nc\n", &path, false).unwrap_err();
+        assert!(diff.ends_with("   2 - b\n   2 + c\n"), "{}", diff);
+        check_snapshot("a\nc\n", &path, true).unwrap();
+        assert!(check_snapshot("a\nc\n", &path, false).is_ok());
+
+        assert_eq!(normalize("x  \r\n\n\n\ny\t\n"), "x\n\ny\n");
     }
 }
@@ -1,6 +1,8 @@
 //! Agent system - the core AI agent abstraction
 
+use std::collections::HashSet;
 use std::sync::Arc;
+use arc_swap::ArcSwap;
 use tokio::sync::broadcast;
 use tracing::{info, instrument, error, debug};
 use anyhow;
@@ -8,19 +10,44 @@ use anyhow;
 use crate::error::{Error, Result};
 use crate::agent::context::ContextInjector;
 use crate::agent::message::{Message, Role, Content};
-use crate::agent::provider::Provider;
+use crate::agent::provider::{PriceTable, Provider};
 use crate::agent::memory::Memory;
-use crate::agent::session::SessionStatus;
+use crate::agent::session::{AgentSession, SessionStatus, SessionUsage, META_AGENT, META_TITLE, META_USER_ID};
+use crate::agent::events::{ApprovalEvent, EventFilter, EventHub, EventStream, ResponseEvent, Severity, ToolEvent};
+use crate::agent::budget::{self, Budget, BudgetConfig, BudgetSummary};
+use crate::agent::dev_trace::{DevTracer, StepTrace};
+use crate::agent::event_log::EventRecorder;
+use crate::agent::replay::{ArtifactStore, EventId, ReplayBuffer, ReplayConfig, ReplaySubscription};
+use crate::agent::feedback::{FeedbackEntry, FeedbackStore, Rating, ResponseRecord, ResponseRef};
+use crate::agent::history_summary::SummarizeConfig;
+use crate::agent::tool_aging::{ToolAgingConfig, ToolOutputAging};
+use crate::agent::tool_routing::{RoutingContext, ToolRouter, ToolVisibility};
+use crate::agent::typed_output;
 use crate::skills::tool::{Tool, ToolSet};
-use crate::agent::streaming::StreamingResponse;
+use crate::skills::tool::compress::{self, CompressionConfig};
+use crate::agent::streaming::{StreamingResponse, Usage};
+/// Token for stopping a run early, see [`Agent::chat_with_cancel`]
+pub use tokio_util::sync::CancellationToken;
 use crate::skills::tool::memory::{SearchHistoryTool, RememberThisTool, TieredSearchTool, FetchDocumentTool}; // Corrected import for memory tools
-use crate::agent::context::{ContextManager, ContextConfig}; // ContextInjector is already imported above
+use crate::agent::context::{ContextManager, ContextConfig, PreviewOptions, RenderedContext, TokenCounter}; // ContextInjector is already imported above
 use crate::agent::multi_agent::{Coordinator, AgentRole, MultiAgent, AgentMessage};
-use crate::agent::personality::{Persona, PersonalityManager};
+use crate::agent::personality::Persona;
+use crate::agent::system_prompt::{SectionKey, SystemPrompt};
 use crate::agent::cache::Cache;
 use crate::agent::scheduler::Scheduler;
-use crate::skills::tool::{DelegateTool, CronTool};
+use crate::skills::tool::{DelegateTool, CronTool, RecallToolOutputTool};
+use crate::skills::tool::subagent::{SpawnSubagentTool, SubagentConfig, TokenBudget};
+use crate::skills::tool::introspection::IntrospectionTool;
 use crate::infra::notification::{Notifier, NotifyChannel};
+use crate::infra::secrets::{PreflightReport, Secrets};
+use crate::infra::validation::{closest_match, ConfigIssue, ConfigIssues, Validate};
+use crate::infra::response_format::{FormatTarget, FormatterChain, ResponseFormatters};
+
+/// Memory collection holding the full text of reduced tool outputs
+pub const TOOL_OUTPUT_COLLECTION: &str = "tool_outputs";
+
+/// Namespace dynamic skills are registered under, e.g. `skills__get_token_price`
+pub const SKILL_NAMESPACE: &str = "skills";
 
 /// Configuration for an Agent
 #[derive(Debug, Clone)]
@@ -29,18 +56,24 @@ pub struct AgentConfig {
     pub name: String,
     /// Model to use (provider specific string)
     pub model: String,
-    /// System prompt / Preamble
+    /// System prompt / Preamble, rendered as the `legacy` section
     pub preamble: String,
+    /// Named system prompt sections besides `legacy`
+    pub prompt_sections: SystemPrompt,
     /// Temperature for generation
     pub temperature: Option<f64>,
     /// Max tokens to generate
     pub max_tokens: Option<u64>,
+    /// Reasoning budget for thinking models, sent as the provider's budget parameter
+    pub max_reasoning_tokens: Option<u64>,
     /// Additional provider-specific parameters
     pub extra_params: Option<serde_json::Value>,
     /// Policy for risky tools
     pub tool_policy: RiskyToolPolicy,
     /// Max history messages to send to LLM (Sliding window)
     pub max_history_messages: usize,
+    /// Model context window in tokens; history is trimmed to fit (default: 128k)
+    pub max_context_tokens: Option<usize>,
     /// Max characters allowed in tool output before truncation
     pub max_tool_output_chars: usize,
     /// Enable strict JSON mode (response_format: json_object)
@@ -51,6 +84,27 @@ pub struct AgentConfig {
     pub role: AgentRole,
     /// Max parallel tool calls (default: 5)
     pub max_parallel_tools: usize,
+    /// Fold the first tool example into native tool descriptions
+    /// (for providers that only see descriptions, not the injected prompt)
+    pub fold_tool_examples: bool,
+    /// Prompt template version, recorded with each response for feedback analysis
+    pub prompt_version: Option<String>,
+    /// Experiment variant, recorded with each response for feedback analysis
+    pub experiment_variant: Option<String>,
+    /// Only expose and run side-effect-free tools
+    pub read_only: bool,
+    /// Emit [`AgentEvent::StreamDelta`], [`AgentEvent::Reasoning`] and [`AgentEvent::ToolCallDelta`] while a step streams
+    pub emit_stream_deltas: bool,
+    /// Request whole replies through [`Provider::complete`] instead of streaming
+    pub prefer_complete: bool,
+    /// Re-prompts after an unparsable [`Agent::prompt_typed`] response (default: 2)
+    pub typed_output_retries: usize,
+    /// Seconds to wait for an approval before giving up; `None` waits forever (default: 300)
+    pub approval_timeout_secs: Option<u64>,
+    /// What a timed-out approval does to the run
+    pub approval_timeout_action: ApprovalTimeoutAction,
+    /// What a step does when one of its tool calls fails
+    pub tool_failure_policy: ToolFailurePolicy,
 }
 
 impl Default for AgentConfig {
@@ -59,20 +113,153 @@ impl Default for AgentConfig {
             name: "agent".to_string(),
             model: "gpt-4o".to_string(),
             preamble: "You are a helpful AI assistant.".to_string(),
+            prompt_sections: SystemPrompt::default(),
             temperature: Some(0.7),
             max_tokens: Some(4096),
+            max_reasoning_tokens: None,
             extra_params: None,
             tool_policy: RiskyToolPolicy::default(),
             max_history_messages: 20,
+            max_context_tokens: None,
             max_tool_output_chars: 4096,
             json_mode: false,
             persona: None,
             role: AgentRole::Assistant,
             max_parallel_tools: 5,
+            fold_tool_examples: false,
+            prompt_version: None,
+            experiment_variant: None,
+            read_only: false,
+            emit_stream_deltas: true,
+            prefer_complete: false,
+            typed_output_retries: 2,
+            approval_timeout_secs: Some(300),
+            approval_timeout_action: ApprovalTimeoutAction::default(),
+            tool_failure_policy: ToolFailurePolicy::default(),
+        }
+    }
+}
+
+impl Validate for AgentConfig {
+    fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        if self.model.trim().is_empty() {
+            issues.push(ConfigIssue::error("agent.model", "model name cannot be empty"));
+        }
+        if let Some(t) = self.temperature {
+            if !(0.0..=2.0).contains(&t) {
+                issues.push(
+                    ConfigIssue::error("agent.temperature", format!("{} is outside 0.0-2.0", t))
+                        .suggest("most providers accept 0.0 to 2.0; 0.7 is a common default"),
+                );
+            }
+        }
+        if self.max_tokens == Some(0) {
+            issues.push(
+                ConfigIssue::error("agent.max_tokens", "must be at least 1")
+                    .suggest("leave unset to use the provider's default"),
+            );
+        }
+        if self.max_reasoning_tokens == Some(0) {
+            issues.push(
+                ConfigIssue::error("agent.max_reasoning_tokens", "must be at least 1")
+                    .suggest("leave unset to use the provider's default"),
+            );
+        }
+        if self.max_history_messages == 0 {
+            issues.push(ConfigIssue::error("agent.max_history_messages", "must be at least 1"));
+        }
+        if let (Some(window), Some(reserve)) = (self.max_context_tokens, self.max_tokens) {
+            if window as u64 <= reserve {
+                issues.push(
+                    ConfigIssue::error("agent.max_context_tokens", "must be larger than max_tokens")
+                        .suggest("the response reserve would leave no room for the prompt"),
+                );
+            }
+        }
+        if self.approval_timeout_secs == Some(0) {
+            issues.push(
+                ConfigIssue::error("agent.approval_timeout_secs", "must be at least 1")
+                    .suggest("leave unset to wait for approvals indefinitely"),
+            );
+        }
+        if self.max_parallel_tools == 0 {
+            issues.push(ConfigIssue::error(
+                "agent.max_parallel_tools",
+                "must be at least 1, or no tool call can ever run",
+            ));
+        }
+        if self.tool_failure_policy == (ToolFailurePolicy::RetryFailed { attempts: 0 }) {
+            issues.push(
+                ConfigIssue::warning("agent.tool_failure_policy", "retry_failed with 0 attempts never retries")
+                    .suggest("use continue_with_errors, or at least 1 attempt"),
+            );
+        }
+        if self.max_tool_output_chars < 256 {
+            issues.push(ConfigIssue::warning(
+                "agent.max_tool_output_chars",
+                format!("{} chars leaves little room for any tool result", self.max_tool_output_chars),
+            ));
+        }
+        if self.system_prompt().render().trim().is_empty() {
+            issues.push(ConfigIssue::warning("agent.preamble", "system prompt is empty"));
+        }
+        issues
+    }
+}
+
+impl AgentConfig {
+    /// The full system prompt: `prompt_sections`, `preamble` as `legacy` and
+    /// the persona, unless a `persona` section overrides it
+    pub fn system_prompt(&self) -> SystemPrompt {
+        self.system_prompt_with(self.persona.as_ref())
+    }
+
+    /// [`AgentConfig::system_prompt`] with `persona` in place of the configured one
+    pub fn system_prompt_with(&self, persona: Option<&Persona>) -> SystemPrompt {
+        let mut prompt = self.prompt_sections.clone();
+        if !self.preamble.is_empty() {
+            prompt.set(SectionKey::Legacy, self.preamble.clone());
+        }
+        if let Some(persona) = persona {
+            if prompt.get(SectionKey::Persona).is_none() {
+                prompt.set(SectionKey::Persona, persona.to_prompt());
+            }
+        }
+        prompt
+    }
+
+    /// [`Validate::validate`] plus checks that tool policy overrides name tools in `tools`
+    ///
+    /// Unknown names are warnings, not errors: skills installed at runtime can
+    /// legitimately be configured before they exist.
+    pub fn validate_with_tools(&self, tools: &ToolSet) -> Vec<ConfigIssue> {
+        let mut issues = self.validate();
+        let mut overrides: Vec<&String> = self.tool_policy.overrides.keys().collect();
+        overrides.sort();
+        for name in overrides {
+            if tools.contains(name) {
+                continue;
+            }
+            let mut issue = ConfigIssue::warning(
+                format!("agent.tool_policy.overrides.{}", name),
+                "policy override for a tool that is not registered",
+            );
+            if let Some(known) = closest_match(name, tools.iter().map(|(n, _)| n.as_str())) {
+                issue = issue.suggest(format!("did you mean '{}'?", known));
+            }
+            issues.push(issue);
         }
+        issues
     }
 }
 
+/// Max provider calls per chat turn before the agent gives up
+pub const MAX_AGENT_STEPS: usize = 15;
+
+/// Times a step is retried with older history dropped after a context overflow
+pub const MAX_CONTEXT_OVERFLOW_RETRIES: usize = 2;
+
 /// Policy for tool execution
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -94,6 +281,13 @@ pub struct RiskyToolPolicy {
     pub overrides: std::collections::HashMap<String, ToolPolicy>,
 }
 
+impl RiskyToolPolicy {
+    /// Configured policy for a tool (override, else the default)
+    pub fn policy_for(&self, tool: &str) -> &ToolPolicy {
+        self.overrides.get(tool).unwrap_or(&self.default_policy)
+    }
+}
+
 impl Default for RiskyToolPolicy {
     fn default() -> Self {
         Self {
@@ -103,22 +297,128 @@ impl Default for RiskyToolPolicy {
     }
 }
 
+/// What happens when nobody answers an approval request in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalTimeoutAction {
+    /// Deny the call and tell the model the approval timed out
+    #[default]
+    DenyAndContinue,
+    /// Abort the run with [`Error::ToolApprovalRequired`]
+    FailRun,
+}
+
+/// What a step does when one of its tool calls fails
+///
+/// A call fails when the tool runs and returns an error or times out. Unknown,
+/// hidden or disabled tools and denied approvals are always reported to the
+/// model as that call's result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ToolFailurePolicy {
+    /// Report the error to the model as the call's result
+    #[default]
+    ContinueWithErrors,
+    /// Cancel the step's calls still in flight and fail the run with the error
+    AbortStep,
+    /// Run a failed call up to `attempts` more times, then report the error
+    RetryFailed {
+        /// Extra runs per failed call
+        attempts: u32,
+    },
+}
+
+/// Result of one tool call in a step
+struct ToolCallOutcome {
+    id: String,
+    name: String,
+    output: String,
+    /// Redacted error of a call that ran and failed
+    failure: Option<String>,
+}
+
+impl ToolCallOutcome {
+    /// A call that was rejected before running; the model sees `error`
+    fn rejected(id: String, name: String, error: impl std::fmt::Display) -> Self {
+        Self { id, name, output: format!("Error: {}", error), failure: None }
+    }
+}
+
 /// Events emitted by the Agent during execution
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type", content = "data", rename_all = "snake_case")]
 pub enum AgentEvent {
     /// Agent started thinking (prompt received)
     Thinking { prompt: String },
+    /// Chunk of model text as it streams in, before the step finishes
+    StreamDelta { content: String },
+    /// Chunk of the model's reasoning trace, never part of the response or history
+    Reasoning { content: String },
+    /// Tool call read from the model's stream, before it runs
+    ///
+    /// Providers deliver each call whole, so `arguments` is the complete JSON.
+    ToolCallDelta {
+        id: String,
+        tool: String,
+        arguments: String,
+    },
     /// Agent decided to use a tool
     ToolCall { tool: String, input: String },
     /// Tool execution requires approval
-    ApprovalPending { tool: String, input: String },
+    ApprovalPending {
+        tool: String,
+        input: String,
+        /// When the request times out, if approvals have a timeout
+        deadline: Option<chrono::DateTime<chrono::Utc>>,
+    },
     /// Tool execution finished
     ToolResult { tool: String, output: String },
+    /// Tool call short-circuited by the tool's circuit breaker
+    ToolUnavailable { tool: String, reason: String },
     /// Agent generated a final response
-    Response { content: String },
+    Response {
+        /// Stable ID for attaching feedback to this response
+        response_id: String,
+        /// Raw model output
+        content: String,
+        /// Output of each registered formatter chain, keyed by consumer
+        #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+        formatted: std::collections::BTreeMap<String, String>,
+    },
+    /// Token usage of one model call, estimated when the provider reported none
+    Usage {
+        model: String,
+        prompt_tokens: u32,
+        completion_tokens: u32,
+        total_tokens: u32,
+        #[serde(default)]
+        estimated: bool,
+        /// Estimated USD cost, when the agent has a price for the model
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cost_usd: Option<f64>,
+    },
     /// Error occurred
     Error { message: String },
+    /// The run was cancelled through its token; the session was checkpointed as cancelled
+    Cancelled {
+        /// Step the run stopped at
+        step: usize,
+        /// Tool calls of the step that were cut off before finishing
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        interrupted_tools: Vec<String>,
+    },
+    /// The persona was swapped through [`Agent::set_persona`]; carries the role names
+    PersonaChanged {
+        previous: Option<String>,
+        current: Option<String>,
+    },
+    /// Event emitted by a spawned sub-agent
+    Subagent {
+        /// ID of the parent request (see [`Budget::request_id`](crate::agent::budget::Budget::request_id))
+        request_id: String,
+        child: usize,
+        event: Box<AgentEvent>,
+    },
 }
 
 /// Handler for user approvals
@@ -189,13 +489,13 @@ impl crate::skills::tool::Tool for AskUserTool {
             description: "Ask the user for clarification, additional information, or a final decision. Use this when you are stuck or need human input.".to_string(),
             parameters: schema_json,
             parameters_ts: Some("interface AskUserArgs {\n  /** The question to ask the user */\n  question: string;\n}".to_string()),
-            is_binary: false,
             is_verified: true,
+            ..Default::default()
         }
     }
 
     async fn call(&self, arguments: &str) -> anyhow::Result<String> {
-        let args: AskUserArgs = serde_json::from_str(arguments)?;
+        let args: AskUserArgs = crate::skills::tool::parse_args(&self.name(), arguments)?;
         self.handler.ask(&args.question).await
     }
 }
@@ -235,15 +535,34 @@ impl ApprovalHandler for ChannelApprovalHandler {
 /// The main Agent struct
 pub struct Agent<P: Provider> {
     provider: Arc<P>,
-    tools: ToolSet,
+    /// Tools, resynced by a watched skill loader whenever its skills change
+    tools: Arc<ArcSwap<ToolSet>>,
     config: AgentConfig,
     context_manager: ContextManager,
-    events: broadcast::Sender<AgentEvent>,
+    events: EventHub,
     approval_handler: Arc<dyn ApprovalHandler>,
     cache: Option<Arc<dyn Cache>>,
     notifier: Option<Arc<dyn Notifier>>,
     memory: Option<Arc<dyn Memory>>,
     session_id: Option<String>,
+    /// User recorded in checkpointed session metadata
+    user_id: Option<String>,
+    secrets: Option<Arc<Secrets>>,
+    formatters: ResponseFormatters,
+    feedback: Option<Arc<FeedbackStore>>,
+    tool_router: Option<Arc<dyn ToolRouter>>,
+    workflow_state: parking_lot::RwLock<Option<String>>,
+    persona: parking_lot::RwLock<Option<Persona>>,
+    session_tags: parking_lot::RwLock<Vec<String>>,
+    budget: BudgetConfig,
+    last_budget: parking_lot::RwLock<Option<BudgetSummary>>,
+    pricing: Option<PriceTable>,
+    last_usage: parking_lot::RwLock<Option<Usage>>,
+    session_usage: parking_lot::RwLock<SessionUsage>,
+    /// Token budget shared with sub-agents; the agent's own calls spend from it too
+    token_budget: Option<Arc<TokenBudget>>,
+    dev_trace: Option<Arc<DevTracer>>,
+    event_recorder: Option<Arc<EventRecorder>>,
 }
 
 impl<P: Provider> Agent<P> {
@@ -257,17 +576,61 @@ impl<P: Provider> Agent<P> {
         self.events.subscribe()
     }
 
+    /// Subscribe to the events matching `filter`; non-matching events are never cloned for it
+    pub fn subscribe_filtered(&self, filter: EventFilter) -> EventStream {
+        self.events.subscribe_filtered(filter)
+    }
+
+    /// Typed stream of tool calls and results
+    pub fn tool_events(&self) -> EventStream<ToolEvent> {
+        self.events.tool_events()
+    }
+
+    /// Typed stream of final responses
+    pub fn responses(&self) -> EventStream<ResponseEvent> {
+        self.events.responses()
+    }
+
+    /// Typed stream of tool calls awaiting approval
+    pub fn approvals(&self) -> EventStream<ApprovalEvent> {
+        self.events.approvals()
+    }
+
+    /// Events buffered after `since` (all buffered ones if `None`), then live events
+    ///
+    /// Lets a client that connects mid-conversation, or reconnects with the last
+    /// id it saw, catch up without gaps or duplicates.
+    pub fn subscribe_with_replay(&self, since: Option<EventId>) -> ReplaySubscription {
+        self.events.subscribe_with_replay(since)
+    }
+
+    /// Recorder appending this agent's events to disk, if [`AgentBuilder::record_events`] was set
+    pub fn event_recorder(&self) -> Option<&Arc<EventRecorder>> {
+        self.event_recorder.as_ref()
+    }
+
+    /// Drop the session's buffered events; late subscribers only see new ones
+    pub fn end_session(&self) {
+        self.events.replay().clear();
+    }
+
     /// Helper to emit events safely
     fn emit(&self, event: AgentEvent) {
-        if let Err(e) = self.events.send(event) {
-            tracing::debug!("Failed to emit event (no receivers): {}", e);
+        if self.events.send(event) == 0 {
+            tracing::debug!("Emitted event had no receivers");
         }
     }
     
     /// Send a notification via the configured notifier
     pub async fn notify(&self, channel: NotifyChannel, message: &str) -> Result<()> {
+        self.notify_with(Severity::Info, channel, message).await
+    }
+
+    /// Send a notification with a severity, letting routing notifiers digest or throttle it
+    pub async fn notify_with(&self, severity: Severity, channel: NotifyChannel, message: &str) -> Result<()> {
         if let Some(notifier) = &self.notifier {
-             notifier.notify(channel, message).await
+             let formatted = self.formatters.format(channel.name(), message);
+             notifier.notify_with(severity, channel, formatted.as_deref().unwrap_or(message)).await
         } else {
              // If no notifier configured, log warning but don't fail hard
              tracing::warn!("Agent tried to notify but no notifier is configured: {}", message);
@@ -278,12 +641,17 @@ impl<P: Provider> Agent<P> {
     /// Save current state to persistent storage
     pub async fn checkpoint(&self, messages: &[Message], step: usize, status: SessionStatus) -> Result<()> {
         if let (Some(memory), Some(session_id)) = (&self.memory, &self.session_id) {
-            let session = crate::agent::session::AgentSession {
+            let persona = self.persona();
+            let session = AgentSession {
                 id: session_id.clone(),
                 messages: messages.to_vec(),
                 step,
                 status,
                 updated_at: chrono::Utc::now(),
+                usage: self.session_usage.read().clone(),
+                metadata: self.session_metadata(messages),
+                persona: persona.clone(),
+                persona_cleared: persona.is_none(),
             };
             memory.store_session(session).await?;
             debug!("Agent checkpoint saved for session: {}", session_id);
@@ -291,11 +659,31 @@ impl<P: Provider> Agent<P> {
         Ok(())
     }
 
+    /// Descriptive fields stored with a checkpoint
+    fn session_metadata(&self, messages: &[Message]) -> std::collections::BTreeMap<String, String> {
+        let mut metadata = std::collections::BTreeMap::new();
+        metadata.insert(META_AGENT.to_string(), self.config.name.clone());
+        if let Some(user_id) = &self.user_id {
+            metadata.insert(META_USER_ID.to_string(), user_id.clone());
+        }
+        if let Some(title) = AgentSession::title_from(messages) {
+            metadata.insert(META_TITLE.to_string(), title);
+        }
+        metadata
+    }
+
     /// Resume a previously saved session
+    ///
+    /// The session's persona is restored, or cleared if it had none when saved;
+    /// sessions from before personas were recorded keep the current persona.
     pub async fn resume(&self, session_id: &str) -> Result<String> {
         if let Some(memory) = &self.memory {
             if let Some(session) = memory.retrieve_session(session_id).await? {
                 info!("Resuming agent session: {}", session_id);
+                if let Some(persona) = session.restored_persona() {
+                    self.set_persona(persona);
+                }
+                *self.session_usage.write() = session.usage;
                 // We restart the chat with the loaded messages
                 return self.chat(session.messages).await;
             }
@@ -316,16 +704,161 @@ impl<P: Provider> Agent<P> {
         self.chat(messages).await
     }
 
+    /// Send a prompt and parse the response as `T`
+    ///
+    /// Asks for JSON matching `T`'s schema in JSON mode. When the reply doesn't
+    /// parse, the model is re-prompted with the error up to
+    /// [`AgentConfig::typed_output_retries`] times; after that this fails with
+    /// [`Error::SchemaValidation`]. Fences and prose around the JSON are ignored.
+    pub async fn prompt_typed<T>(&self, prompt: impl Into<String>) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned + schemars::JsonSchema,
+    {
+        let instruction = typed_output::schema_instruction::<T>();
+        let mut messages = vec![Message::user(prompt.into())];
+        let mut retries = 0;
+        loop {
+            let text = self.chat_with_schema(messages.clone(), Some(&instruction), &CancellationToken::new()).await?;
+            match typed_output::parse_response::<T>(&text) {
+                Ok(value) => return Ok(value),
+                Err(error) if retries < self.config.typed_output_retries => {
+                    retries += 1;
+                    tracing::debug!("Typed response did not parse ({}), re-prompting ({}/{})", error, retries, self.config.typed_output_retries);
+                    messages.push(Message::assistant(text));
+                    messages.push(Message::user(format!(
+                        "That response could not be parsed: {}. Reply again with only JSON matching the schema.",
+                        error
+                    )));
+                }
+                Err(error) => return Err(Error::SchemaValidation { raw: text, error }),
+            }
+        }
+    }
+
     /// Send messages and get a response (non-streaming)
+    ///
+    /// Runs within a [`Budget`]: a new one from the builder's [`BudgetConfig`], or
+    /// the caller's when this is a nested request (e.g. a sub-agent).
     #[instrument(skip(self, messages), fields(model = %self.config.model, message_count = messages.len()))]
-    pub async fn chat(&self, mut messages: Vec<Message>) -> Result<String> {
+    pub async fn chat(&self, messages: Vec<Message>) -> Result<String> {
+        self.chat_with_schema(messages, None, &CancellationToken::new()).await
+    }
+
+    /// [`chat`](Self::chat) that stops early once `token` is cancelled
+    ///
+    /// The token is checked between steps and aborts the provider stream and
+    /// tool calls in flight. Finished tool results are kept, cut-off calls are
+    /// recorded as cancelled, and the session is checkpointed as
+    /// [`SessionStatus::Cancelled`] so [`resume`](Self::resume) can continue it.
+    /// Emits [`AgentEvent::Cancelled`] and fails with [`Error::Cancelled`].
+    #[instrument(skip(self, messages, token), fields(model = %self.config.model, message_count = messages.len()))]
+    pub async fn chat_with_cancel(&self, messages: Vec<Message>, token: CancellationToken) -> Result<String> {
+        self.chat_with_schema(messages, None, &token).await
+    }
+
+    /// [`chat`](Self::chat), adding `response_schema` to the system prompt in JSON mode
+    async fn chat_with_schema(&self, messages: Vec<Message>, response_schema: Option<&str>, cancel: &CancellationToken) -> Result<String> {
+        if Budget::current().is_some() {
+            return self.run_chat(messages, response_schema, cancel).await;
+        }
+        let budget = Budget::new(self.budget.clone());
+        let run = budget.clone().scope(self.run_chat(messages, response_schema, cancel));
+        let result = match budget.deadline() {
+            Some(deadline) => tokio::time::timeout_at(deadline, run)
+                .await
+                .unwrap_or_else(|_| Err(budget.check_deadline("agent").unwrap_err())),
+            None => run.await,
+        };
+        *self.last_budget.write() = Some(budget.summary());
+        result
+    }
+
+    /// The exact context the next step of [`chat`](Self::chat) would send for `messages`,
+    /// attributed per source; no provider is called
+    ///
+    /// Applies tool routing, the tool catalog, injectors and history budgeting the
+    /// same way a real step does.
+    pub async fn render_context_preview(&self, messages: &[Message], options: PreviewOptions) -> Result<RenderedContext> {
+        let (_, visible) = self.route_tools(messages).await;
+        let catalog = if options.force_fresh {
+            let mut tools = ToolSet::clone(&self.tools());
+            tools.invalidate_definitions();
+            tools.render_catalog(Some(&visible)).await
+        } else {
+            self.tools().render_catalog(Some(&visible)).await
+        };
+        let tools = self.request_tools(&visible).await;
+        let mut rendered = self.context_manager.render_preview_with(messages, catalog, &tools).await?;
+        for section in &mut rendered.sections {
+            if section.source == "leading" {
+                section.source = "tool_catalog".to_string();
+            }
+        }
+        Ok(rendered)
+    }
+
+    /// Budget consumed by the last top-level [`chat`](Self::chat) or [`prompt`](Self::prompt)
+    pub fn last_budget(&self) -> Option<BudgetSummary> {
+        self.last_budget.read().clone()
+    }
+
+    /// Tokens used by every model call of the last [`chat`](Self::chat) or [`prompt`](Self::prompt)
+    pub fn last_usage(&self) -> Option<Usage> {
+        self.last_usage.read().clone()
+    }
+
+    /// Tokens and estimated spend since the session started, saved with each checkpoint
+    pub fn session_usage(&self) -> SessionUsage {
+        self.session_usage.read().clone()
+    }
+
+    /// Token budget shared with sub-agents, if [`AgentBuilder::with_subagents`] was used
+    pub fn token_budget(&self) -> Option<Arc<TokenBudget>> {
+        self.token_budget.clone()
+    }
+
+    /// Count one model call's usage, estimating it when the provider reported none
+    fn record_usage(&self, reported: Option<Usage>, prompt_chars: usize, completion_chars: usize) {
+        let usage = reported.unwrap_or_else(|| Usage::estimate(prompt_chars, completion_chars));
+        let cost_usd = self
+            .pricing
+            .as_ref()
+            .and_then(|prices| prices.estimate(&self.config.model, &usage));
+        self.emit(AgentEvent::Usage {
+            model: self.config.model.clone(),
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+            estimated: usage.estimated,
+            cost_usd,
+        });
+        self.session_usage.write().add(&usage, cost_usd);
+        if let Some(budget) = &self.token_budget {
+            budget.consume(usage.total_tokens as u64);
+        }
+        *self.last_usage.write().get_or_insert_with(Usage::default) += &usage;
+    }
+
+    /// Checkpoint a cancelled run and report it
+    async fn cancel_run(&self, messages: &[Message], step: usize, interrupted_tools: Vec<String>) -> Result<String> {
+        info!("Agent run cancelled at step {}", step);
+        self.emit(AgentEvent::Cancelled { step, interrupted_tools });
+        self.checkpoint(messages, step, SessionStatus::Cancelled).await?;
+        Err(Error::Cancelled)
+    }
+
+    async fn run_chat(&self, mut messages: Vec<Message>, response_schema: Option<&str>, cancel: &CancellationToken) -> Result<String> {
         let mut steps = 0;
-        const MAX_STEPS: usize = 15;
+        let mut turn_tools: Vec<String> = Vec::new();
+        *self.last_usage.write() = None;
 
         loop {
-            if steps >= MAX_STEPS {
+            if steps >= MAX_AGENT_STEPS {
                 return Err(Error::agent_config("Max agent steps exceeded"));
             }
+            if cancel.is_cancelled() {
+                return self.cancel_run(&messages, steps, Vec::new()).await;
+            }
             steps += 1;
 
             if let Some(last) = messages.last() {
@@ -347,24 +880,102 @@ impl<P: Provider> Agent<P> {
                 }
             }
 
-            // Context Window Management via ContextManager
-            let context_messages = self.context_manager.build_context(&messages).await
-                .map_err(|e| Error::agent_config(format!("Failed to build context: {}", e)))?;
-
-            let stream = self.stream_chat(context_messages).await?;
+            // Route tools for this step; the catalog and request only carry visible ones
+            let (visibility, visible) = self.route_tools(&messages).await;
+            let catalog = self.tools().render_catalog(Some(&visible)).await;
+            let tools = self.request_tools(&visible).await;
+
+            // Context Window Management via ContextManager; on overflow, retry with older history dropped
+            let mut skip = 0;
+            let mut overflow_retries = 0;
+            let mut prompt_chars;
+            let (stream, mut trace) = loop {
+                let context_messages = self.context_manager.build_context_with(&messages[skip..], catalog.clone(), &tools).await
+                    .map_err(|e| Error::agent_config(format!("Failed to build context: {}", e)))?;
+                let layer = if overflow_retries == 0 { "agent" } else { "context_overflow" };
+                let request = self.chat_request(context_messages, tools.clone(), response_schema).await;
+                let mut trace = match &self.dev_trace {
+                    Some(tracer) => {
+                        let context = self.context_manager.render_preview_with(&messages[skip..], catalog.clone(), &tools).await.ok();
+                        let session = self.session_id.as_deref().unwrap_or("default");
+                        Some(tracer.begin(session, steps, overflow_retries + 1, &request, context))
+                    }
+                    None => None,
+                };
+                prompt_chars = request_chars(&request);
+                let sent = tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => {
+                        self.finish_trace(trace, None);
+                        return self.cancel_run(&messages, steps, Vec::new()).await;
+                    }
+                    sent = self.send_request(request, layer) => sent,
+                };
+                match sent {
+                    Err(e) if e.is_context_overflow() && overflow_retries < MAX_CONTEXT_OVERFLOW_RETRIES => {
+                        self.finish_trace(trace.take(), Some(&e));
+                        let Some(next) = overflow_trim(&messages, skip) else {
+                            break (Err(e), trace);
+                        };
+                        tracing::warn!("Context overflow ({}), retrying without {} oldest messages", e, next);
+                        skip = next;
+                        overflow_retries += 1;
+                    }
+                    result => break (result, trace),
+                }
+            };
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    self.finish_trace(trace, Some(&e));
+                    return Err(e);
+                }
+            };
             
             let mut full_text = String::new();
+            // Reasoning is streamed to subscribers and counted, but never sent back to the model
+            let mut reasoning = String::new();
             let mut tool_calls = Vec::new(); // (id, name, args)
+            let mut usage: Option<Usage> = None;
 
             let mut stream_inner = stream.into_inner();
 
-            // Consume the stream
+            // Consume the stream; dropping it on cancellation aborts the provider request
             use futures::StreamExt;
-            while let Some(chunk) = stream_inner.next().await {
-                match chunk? {
+            loop {
+                let chunk = tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => {
+                        self.finish_trace(trace, None);
+                        return self.cancel_run(&messages, steps, Vec::new()).await;
+                    }
+                    chunk = stream_inner.next() => chunk,
+                };
+                let Some(chunk) = chunk else { break };
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        self.finish_trace(trace, Some(&e));
+                        return Err(e);
+                    }
+                };
+                if let Some(trace) = &mut trace {
+                    trace.record(&chunk);
+                }
+                let streamed = tool_calls.len();
+                match chunk {
                     crate::agent::streaming::StreamingChoice::Message(text) => {
+                        if self.config.emit_stream_deltas && !text.is_empty() {
+                            self.emit(AgentEvent::StreamDelta { content: text.clone() });
+                        }
                         full_text.push_str(&text);
                     }
+                    crate::agent::streaming::StreamingChoice::Thought(text) => {
+                        if self.config.emit_stream_deltas && !text.is_empty() {
+                            self.emit(AgentEvent::Reasoning { content: text.clone() });
+                        }
+                        reasoning.push_str(&text);
+                    }
                     crate::agent::streaming::StreamingChoice::ToolCall { id, name, arguments } => {
                         tool_calls.push((id, name, arguments));
                     }
@@ -375,13 +986,47 @@ impl<P: Provider> Agent<P> {
                              tool_calls.push((tc.id, tc.name, tc.arguments));
                          }
                     }
+                    crate::agent::streaming::StreamingChoice::Usage(reported) => {
+                        *usage.get_or_insert_with(Usage::default) += &reported;
+                    }
+                    crate::agent::streaming::StreamingChoice::Metadata(metadata) => {
+                        if let Some(model) = &metadata.model {
+                            info!(model = %model, provider = ?metadata.provider, "Response served by {}", model);
+                        }
+                    }
                     _ => {}
                 }
+                if self.config.emit_stream_deltas {
+                    for (id, name, arguments) in &tool_calls[streamed..] {
+                        self.emit(AgentEvent::ToolCallDelta {
+                            id: id.clone(),
+                            tool: name.clone(),
+                            arguments: arguments.to_string(),
+                        });
+                    }
+                }
+            }
+            self.finish_trace(trace, None);
+            if !reasoning.is_empty() {
+                debug!(chars = reasoning.len(), "Model reasoned before answering");
             }
+            let completion_chars = full_text.len()
+                + reasoning.len()
+                + tool_calls.iter().map(|(_, name, args)| name.len() + args.to_string().len()).sum::<usize>();
+            self.record_usage(usage, prompt_chars, completion_chars);
 
             // If no tool calls, we are done
             if tool_calls.is_empty() {
-                self.emit(AgentEvent::Response { content: full_text.clone() });
+                let response_id = uuid::Uuid::new_v4().to_string();
+                self.emit(AgentEvent::Response {
+                    response_id: response_id.clone(),
+                    content: full_text.clone(),
+                    formatted: self.formatters.format_all(&full_text),
+                });
+
+                messages.push(Message::assistant(full_text.clone()).with_response_id(response_id.clone()));
+                self.checkpoint(&messages, steps, SessionStatus::Completed).await?;
+                self.register_response(response_id, messages.len() - 1, turn_tools).await;
                 
                 // Store in cache
                 if let Some(cache) = &self.cache {
@@ -392,6 +1037,12 @@ impl<P: Provider> Agent<P> {
             }
 
             // We have tool calls.
+            for (_, name, _) in &tool_calls {
+                if !turn_tools.contains(name) {
+                    turn_tools.push(name.clone());
+                }
+            }
+
             // 1. Append Assistant Message (Thought + Calls) to history
             let mut parts = Vec::new();
             if !full_text.is_empty() {
@@ -408,21 +1059,24 @@ impl<P: Provider> Agent<P> {
                 role: Role::Assistant,
                 name: None,
                 content: Content::Parts(parts),
+                response_id: None,
             });
 
             // 2. Execute Tools (Parallel with Limit)
-            let tools = &self.tools;
+            let tools = &self.tools();
             let policy = &self.config.tool_policy;
             let events = &self.events;
             let approval_handler = &self.approval_handler;
             let max_parallel = self.config.max_parallel_tools;
+            let visibility = &visibility;
             
             use futures::stream;
             
             let current_messages = Arc::new(messages.clone());
+            let called: Vec<(String, String)> = tool_calls.iter().map(|(id, name, _)| (id.clone(), name.clone())).collect();
             
-            let results: Vec<crate::error::Result<(String, String, String)>> = stream::iter(tool_calls)
-                .map(|(id, name, args)| {
+            let mut calls = stream::iter(tool_calls.into_iter().enumerate())
+                .map(|(index, (id, name, args))| {
                     let name_clone = name.clone();
                     let id_clone = id.clone();
                     let args_str = args.to_string();
@@ -430,13 +1084,37 @@ impl<P: Provider> Agent<P> {
                     
                     async move {
                         // 1. Get tool definition (cached in ToolSet)
+                        // Ambiguous bare names are reported back to the model so it can pick one
+                        let name_clone = match tools.resolve(&name_clone) {
+                            Ok(resolved) => resolved.to_string(),
+                            Err(e @ Error::ToolNotFound(_)) => return Err(e),
+                            Err(e) => {
+                                let _ = events.send(AgentEvent::Error { message: e.to_string() });
+                                return Ok((index, ToolCallOutcome::rejected(id_clone, name_clone, e)));
+                            }
+                        };
                         let tool_ref = tools.get(&name_clone).ok_or_else(|| Error::ToolNotFound(name_clone.clone()))?;
-                        
+
+                        // Hidden by the router: the call stays in history, paired with this error
+                        if !visibility.allows(&name_clone) {
+                            let e = Error::ToolHidden(name_clone.clone());
+                            let _ = events.send(AgentEvent::Error { message: e.to_string() });
+                            return Ok((index, ToolCallOutcome::rejected(id_clone, name_clone, e)));
+                        }
+
+                        if let Err(e) = self.check_breaker(&name_clone) {
+                            return Ok((index, ToolCallOutcome::rejected(id_clone, name_clone, e)));
+                        }
+
                         let def = tool_ref.definition().await;
 
+                        if let Err(e) = self.check_read_only(&name_clone, &def) {
+                            let _ = events.send(AgentEvent::Error { message: e.to_string() });
+                            return Ok((index, ToolCallOutcome::rejected(id_clone, name_clone, e)));
+                        }
+
                         // 2. Check policy and security overrides
-                        let mut effective_policy = policy.overrides.get(&name_clone)
-                            .unwrap_or(&policy.default_policy).clone();
+                        let mut effective_policy = policy.policy_for(&name_clone).clone();
                         
                         // Binary Safety Override: Unverified binary skills ALWAYS require approval
                         if def.is_binary && !def.is_verified {
@@ -446,6 +1124,11 @@ impl<P: Provider> Agent<P> {
                             }
                         }
 
+                        if effective_policy != ToolPolicy::Disabled {
+                            budget::spend_tool_attempt("agent")?;
+                        }
+
+                        let mut ran = false;
                         let result = match effective_policy {
                             ToolPolicy::Disabled => {
                                 Err(Error::tool_execution(name_clone.clone(), "Tool execution is disabled by policy".to_string()))
@@ -453,7 +1136,8 @@ impl<P: Provider> Agent<P> {
                             ToolPolicy::RequiresApproval => {
                                 let _ = events.send(AgentEvent::ApprovalPending { 
                                     tool: name_clone.clone(), 
-                                    input: args_str.clone() 
+                                    input: args_str.clone(),
+                                    deadline: self.approval_deadline(),
                                 });
                                 
                                 // Checkpoint before awaiting approval
@@ -463,21 +1147,26 @@ impl<P: Provider> Agent<P> {
                                 }).await?;
 
                                 // Ask approval handler
-                                match approval_handler.approve(&name_clone, &args_str).await {
+                                match self.await_approval(approval_handler.as_ref(), &name_clone, &args_str).await {
                                     Ok(true) => {
                                         let _ = events.send(AgentEvent::ToolCall { 
                                             tool: name_clone.clone(), 
                                             input: args_str.clone() 
                                         });
-                                        tools.call(&name_clone, &args_str).await
-                                            .map_err(|e| Error::tool_execution(name_clone.clone(), e.to_string()))
+                                        ran = true;
+                                        self.run_tool(&name_clone, &args_str).await
                                     }
                                     Ok(false) => {
                                         Err(Error::ToolApprovalRequired { tool_name: name_clone.clone() })
                                     }
-                                    Err(e) => {
-                                        Err(Error::tool_execution(name_clone.clone(), format!("Approval check failed: {}", e)))
-                                    }
+                                    Err(e @ Error::ApprovalTimeout { .. }) => match self.config.approval_timeout_action {
+                                        ApprovalTimeoutAction::DenyAndContinue => Err(e),
+                                        ApprovalTimeoutAction::FailRun => {
+                                            let _ = events.send(AgentEvent::Error { message: e.to_string() });
+                                            return Err(Error::ToolApprovalRequired { tool_name: name_clone.clone() });
+                                        }
+                                    },
+                                    Err(e) => Err(e),
                                 }
                             }
                             ToolPolicy::Auto => {
@@ -485,58 +1174,289 @@ impl<P: Provider> Agent<P> {
                                     tool: name_clone.clone(), 
                                     input: args_str.clone() 
                                 });
-                                tools.call(&name_clone, &args_str).await
-                                    .map_err(|e| Error::tool_execution(name_clone.clone(), e.to_string()))
+                                ran = true;
+                                self.run_tool(&name_clone, &args_str).await
                             }
                         };
                         
                         match result {
                             Ok(output) => {
+                                let output = self.reduce_tool_output(&name_clone, self.redact_secrets(output)).await;
                                 let _ = events.send(AgentEvent::ToolResult { 
                                     tool: name_clone.clone(), 
                                     output: output.clone() 
                                 });
-                                Ok((id_clone, name_clone, output))
+                                Ok((index, ToolCallOutcome { id: id_clone, name: name_clone, output, failure: None }))
                             },
                             Err(e) => {
-                                let _ = events.send(AgentEvent::Error { message: e.to_string() });
-                                Ok((id_clone, name_clone, format!("Error: {}", e)))
+                                let message = self.redact_secrets(e.to_string());
+                                let _ = events.send(AgentEvent::Error { message: message.clone() });
+                                let mut outcome = ToolCallOutcome::rejected(id_clone, name_clone, &message);
+                                outcome.failure = ran.then_some(message);
+                                Ok((index, outcome))
                             }
                         }
                     }
                 })
-                .buffer_unordered(max_parallel)
-                .collect()
-                .await;
+                .buffer_unordered(max_parallel);
+
+            let mut outcomes = Vec::new();
+            let mut cancelled = false;
+            loop {
+                let res = tokio::select! {
+                    biased;
+                    _ = cancel.cancelled() => {
+                        // Dropping the stream cancels the calls still in flight
+                        cancelled = true;
+                        break;
+                    }
+                    res = calls.next() => res,
+                };
+                let Some(res) = res else { break };
+                // Tool failures are reported to the model; only unknown tools, checkpoint and budget errors get here
+                let (index, outcome) = res?;
+                if let (Some(message), ToolFailurePolicy::AbortStep) = (&outcome.failure, self.config.tool_failure_policy) {
+                    // Dropping the stream cancels the calls still in flight
+                    drop(calls);
+                    tracing::warn!(tool = %outcome.name, "Tool call failed, aborting the step");
+                    return Err(Error::tool_execution(outcome.name, message.clone()));
+                }
+                outcomes.push((index, outcome));
+            }
+
+            drop(calls);
+
+            // Calls cut off by cancellation still need a result to keep the history valid
+            let mut interrupted = Vec::new();
+            if cancelled {
+                for (index, (id, name)) in called.into_iter().enumerate() {
+                    if !outcomes.iter().any(|(done, _)| *done == index) {
+                        interrupted.push(name.clone());
+                        outcomes.push((index, ToolCallOutcome::rejected(id, name, "Cancelled before the call finished")));
+                    }
+                }
+            }
 
-            // 3. Append Tool Results to history
-            for res in results {
-                let (id, name, output) = res.unwrap(); // Safe because we handle Err inside async move
+            // 3. Append Tool Results to history, in the order the model called them
+            outcomes.sort_by_key(|(index, _)| *index);
+            for (_, outcome) in outcomes {
                  messages.push(Message {
                     role: Role::Tool,
                     name: None,
                     content: Content::Parts(vec![crate::agent::message::ContentPart::ToolResult {
-                        tool_call_id: id,
-                        content: output,
-                        name: Some(name),
+                        tool_call_id: outcome.id,
+                        content: outcome.output,
+                        name: Some(outcome.name),
                     }]),
+                    response_id: None,
                 });
             }
+            if cancelled {
+                return self.cancel_run(&messages, steps, interrupted).await;
+            }
         }
     }
 
+    /// Remember the model, prompt version, variant and tools behind a response
+    async fn register_response(&self, response_id: String, message_index: usize, tools: Vec<String>) {
+        let Some(store) = &self.feedback else {
+            return;
+        };
+        let record = ResponseRecord {
+            response_id,
+            session_id: self.session_id.clone().unwrap_or_else(|| "default".to_string()),
+            message_index,
+            model: self.config.model.clone(),
+            prompt_version: self.config.prompt_version.clone(),
+            experiment_variant: self.config.experiment_variant.clone(),
+            tools,
+            created_at: chrono::Utc::now(),
+        };
+        if let Err(e) = store.register_response(record).await {
+            tracing::warn!("Failed to register response for feedback: {}", e);
+        }
+    }
+
+    /// Record user feedback on a response in `session_id`
+    ///
+    /// `response` is either the `response_id` from [`AgentEvent::Response`] or the
+    /// index of the assistant message in the stored session history.
+    pub async fn record_feedback(
+        &self,
+        session_id: &str,
+        response: impl Into<ResponseRef>,
+        rating: Rating,
+        comment: Option<String>,
+        tags: Vec<String>,
+    ) -> Result<FeedbackEntry> {
+        let store = self.feedback.as_ref()
+            .ok_or_else(|| Error::agent_config("No feedback store configured"))?;
+        let response = response.into();
+        let not_found = || Error::ResponseNotFound {
+            session_id: session_id.to_string(),
+            response: response.to_string(),
+        };
+
+        let response_id = match &response {
+            ResponseRef::Id(id) => id.clone(),
+            ResponseRef::Index(index) => {
+                let session = match &self.memory {
+                    Some(memory) => memory.retrieve_session(session_id).await?,
+                    None => None,
+                };
+                session
+                    .and_then(|s| s.messages.get(*index).and_then(|m| m.response_id.clone()))
+                    .ok_or_else(not_found)?
+            }
+        };
+        let record = store.response(&response_id)
+            .filter(|r| r.session_id == session_id)
+            .ok_or_else(not_found)?;
+
+        let entry = FeedbackEntry::new(&record, rating, comment, tags);
+        store.submit(entry.clone()).await?;
+        Ok(entry)
+    }
+
+    /// The attached feedback store, if any
+    pub fn feedback(&self) -> Option<&Arc<FeedbackStore>> {
+        self.feedback.as_ref()
+    }
+
     /// Stream a prompt response
     pub async fn stream(&self, prompt: impl Into<String>) -> Result<StreamingResponse> {
         let messages = vec![Message::user(prompt.into())];
         self.stream_chat(messages).await
     }
 
+    /// Set the workflow state tool routers see
+    pub fn set_workflow_state(&self, state: Option<String>) {
+        *self.workflow_state.write() = state;
+    }
+
+    /// The persona the agent currently speaks as
+    pub fn persona(&self) -> Option<Persona> {
+        self.persona.read().clone()
+    }
+
+    /// Swap the persona without rebuilding the agent
+    ///
+    /// Takes effect from the next model call, including within a running chat.
+    /// Emits [`AgentEvent::PersonaChanged`].
+    pub fn set_persona(&self, persona: Option<Persona>) {
+        let (previous, current) = {
+            let mut slot = self.persona.write();
+            self.context_manager
+                .replace_system_prompt(self.config.system_prompt_with(persona.as_ref()));
+            let previous = std::mem::replace(&mut *slot, persona);
+            (previous.map(|p| p.role), slot.as_ref().map(|p| p.role.clone()))
+        };
+        info!(?previous, ?current, "Persona changed");
+        self.emit(AgentEvent::PersonaChanged { previous, current });
+    }
+
+    /// System prompt with the current persona
+    fn system_prompt(&self) -> SystemPrompt {
+        self.config.system_prompt_with(self.persona.read().as_ref())
+    }
+
+    /// Set the session tags tool routers see
+    pub fn set_session_tags(&self, tags: Vec<String>) {
+        *self.session_tags.write() = tags;
+    }
+
+    /// Router visibility for `messages`, and the names of the tools it leaves
+    /// visible after read-only mode is applied
+    async fn route_tools(&self, messages: &[Message]) -> (ToolVisibility, HashSet<String>) {
+        let visibility = match &self.tool_router {
+            Some(router) => {
+                let ctx = RoutingContext::from_history(
+                    messages,
+                    self.workflow_state.read().clone(),
+                    self.session_tags.read().clone(),
+                );
+                router.visible_tools(&ctx)
+            }
+            None => ToolVisibility::All,
+        };
+        let tools = self.tools();
+        let mut visible = HashSet::new();
+        for (name, _) in tools.iter() {
+            if !visibility.allows(name) {
+                continue;
+            }
+            if self.config.read_only {
+                match tools.definition(name).await {
+                    Some(def) if def.side_effect_free => {}
+                    _ => continue,
+                }
+            }
+            visible.insert(name.clone());
+        }
+        (visibility, visible)
+    }
+
+    /// Reject tools with side effects in read-only mode
+    fn check_read_only(&self, name: &str, def: &crate::skills::tool::ToolDefinition) -> Result<()> {
+        if self.config.read_only && !def.side_effect_free {
+            return Err(Error::tool_execution(
+                name.to_string(),
+                "Tool has side effects and the agent is in read-only mode".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fail fast when the tool's circuit breaker is open, so the model can adapt
+    fn check_breaker(&self, name: &str) -> Result<()> {
+        match self.tools().short_circuit(name) {
+            Some(e) => {
+                if let Error::ToolUnavailable { reason, .. } = &e {
+                    self.emit(AgentEvent::ToolUnavailable { tool: name.to_string(), reason: reason.clone() });
+                }
+                Err(e)
+            }
+            None => Ok(()),
+        }
+    }
+
     /// Stream a chat response
     pub async fn stream_chat(&self, messages: Vec<Message>) -> Result<StreamingResponse> {
+        let (_, visible) = self.route_tools(&messages).await;
+        self.stream_with_tools(messages, &visible, "agent").await
+    }
+
+    /// Stream a chat response offering only the `visible` tools, spending a provider attempt for `layer`
+    async fn stream_with_tools(&self, messages: Vec<Message>, visible: &HashSet<String>, layer: &str) -> Result<StreamingResponse> {
+        let request = self.chat_request(messages, self.request_tools(visible).await, None).await;
+        self.send_request(request, layer).await
+    }
+
+    /// Definitions of the `visible` tools, as sent to the provider
+    async fn request_tools(&self, visible: &HashSet<String>) -> Vec<crate::skills::tool::ToolDefinition> {
+        let mut tools = self.tools().definitions().await;
+        tools.retain(|def| visible.contains(&def.name));
+        if self.config.fold_tool_examples {
+            for def in &mut tools {
+                def.description = def.description_with_example();
+            }
+        }
+        tools
+    }
+
+    /// Build the provider request for `messages`, offering `tools`
+    ///
+    /// A `response_schema` instruction is appended to the system prompt.
+    async fn chat_request(
+        &self,
+        messages: Vec<Message>,
+        tools: Vec<crate::skills::tool::ToolDefinition>,
+        response_schema: Option<&str>,
+    ) -> crate::agent::provider::ChatRequest {
         let mut extra = self.config.extra_params.clone().unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
         
-        // Inject JSON mode if enabled
-        if self.config.json_mode {
+        // Inject JSON mode if enabled (always on for typed prompts)
+        if self.config.json_mode || response_schema.is_some() {
             if let serde_json::Value::Object(ref mut map) = extra {
                 if !map.contains_key("response_format") {
                      map.insert("response_format".to_string(), serde_json::json!({ "type": "json_object" }));
@@ -544,75 +1464,245 @@ impl<P: Provider> Agent<P> {
             }
         }
 
-        let request = crate::agent::provider::ChatRequest {
+        let mut system_prompt = self.system_prompt().render();
+        if let Some(schema) = response_schema {
+            system_prompt = format!("{}\n\n{}", system_prompt, schema);
+        }
+
+        // Models without vision get a note in place of each image
+        let messages = if self.provider.supports_vision() {
+            messages
+        } else {
+            messages.into_iter().map(Message::with_image_placeholders).collect()
+        };
+
+        crate::agent::provider::ChatRequest {
             model: self.config.model.clone(),
-            system_prompt: Some(self.config.preamble.clone()),
+            system_prompt: Some(system_prompt),
             messages,
-            tools: self.tools.definitions().await,
+            tools,
             temperature: self.config.temperature,
             max_tokens: self.config.max_tokens,
+            max_reasoning_tokens: self.config.max_reasoning_tokens,
             extra_params: Some(extra),
-        };
+            headers: Default::default(),
+        }
+    }
 
+    /// Send `request`, spending a provider attempt for `layer`
+    async fn send_request(&self, request: crate::agent::provider::ChatRequest, layer: &str) -> Result<StreamingResponse> {
+        budget::spend_provider_attempt(layer)?;
+        if self.config.prefer_complete {
+            return Ok(self.provider.complete(request).await?.into_stream());
+        }
         self.provider.stream_completion(request).await
     }
 
+    /// Write a dev trace; tracing failures never fail the chat
+    fn finish_trace(&self, trace: Option<StepTrace>, error: Option<&Error>) {
+        if let (Some(tracer), Some(trace)) = (&self.dev_trace, trace) {
+            if let Err(e) = tracer.finish(trace, error) {
+                tracing::warn!("Failed to write dev trace: {}", e);
+            }
+        }
+    }
+
+    /// When an approval requested now times out, if approvals have a timeout
+    fn approval_deadline(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        let secs = self.config.approval_timeout_secs?;
+        Some(chrono::Utc::now() + chrono::Duration::seconds(secs as i64))
+    }
+
+    /// Ask `handler` to approve a call, failing with [`Error::ApprovalTimeout`] past the deadline
+    ///
+    /// Without a timeout, a handler whose consumer went away would block the run forever.
+    async fn await_approval(&self, handler: &dyn ApprovalHandler, name: &str, arguments: &str) -> Result<bool> {
+        let check_failed = |e: anyhow::Error| Error::tool_execution(name.to_string(), format!("Approval check failed: {}", e));
+        let Some(secs) = self.config.approval_timeout_secs else {
+            return handler.approve(name, arguments).await.map_err(check_failed);
+        };
+        match tokio::time::timeout(std::time::Duration::from_secs(secs), handler.approve(name, arguments)).await {
+            Ok(answer) => answer.map_err(check_failed),
+            Err(_) => {
+                tracing::warn!(tool = %name, timeout_secs = secs, "Approval timed out");
+                Err(Error::ApprovalTimeout { tool_name: name.to_string(), timeout_secs: secs })
+            }
+        }
+    }
+
+    /// Run a tool, retrying failures as [`AgentConfig::tool_failure_policy`] allows
+    async fn run_tool(&self, name: &str, arguments: &str) -> Result<String> {
+        let retries = match self.config.tool_failure_policy {
+            ToolFailurePolicy::RetryFailed { attempts } => attempts,
+            _ => 0,
+        };
+        let mut attempt = 0;
+        loop {
+            match self.tools().call(name, arguments).await.map_err(|e| tool_call_error(name, e)) {
+                Err(e) if attempt < retries => {
+                    attempt += 1;
+                    let message = self.redact_secrets(e.to_string());
+                    tracing::warn!(tool = %name, "Tool call failed, retrying ({}/{}): {}", attempt, retries, message);
+                    budget::spend_tool_attempt("agent")?;
+                }
+                result => return result,
+            }
+        }
+    }
+
     /// Call a tool by name (Direct call helper)
-    #[instrument(skip(self, arguments), fields(tool_name = %name))]
     pub async fn call_tool(&self, name: &str, arguments: &str) -> Result<String> {
+        self.call_tool_with_cancel(name, arguments, CancellationToken::new()).await
+    }
+
+    /// [`call_tool`](Self::call_tool) that gives up with [`Error::Cancelled`] once `token` is cancelled
+    ///
+    /// Cancelling drops the call, so a tool stops at its next await point.
+    #[instrument(skip(self, arguments, token), fields(tool_name = %name))]
+    pub async fn call_tool_with_cancel(&self, name: &str, arguments: &str, token: CancellationToken) -> Result<String> {
+        tokio::select! {
+            biased;
+            _ = token.cancelled() => {
+                info!(tool = %name, "Tool call cancelled");
+                Err(Error::Cancelled)
+            }
+            result = self.call_tool_inner(name, arguments) => result,
+        }
+    }
+
+    async fn call_tool_inner(&self, name: &str, arguments: &str) -> Result<String> {
+        self.check_breaker(name)?;
+
         // 1. Check Policy
-        let policy = self.config.tool_policy.overrides.get(name)
-            .unwrap_or(&self.config.tool_policy.default_policy);
+        let policy = self.config.tool_policy.policy_for(name);
 
         match policy {
             ToolPolicy::Disabled => {
                  return Err(Error::tool_execution(name.to_string(), "Tool execution is disabled by policy".to_string()));
             }
             ToolPolicy::RequiresApproval => {
-                self.emit(AgentEvent::ApprovalPending { tool: name.to_string(), input: arguments.to_string() });
+                self.emit(AgentEvent::ApprovalPending {
+                    tool: name.to_string(),
+                    input: arguments.to_string(),
+                    deadline: self.approval_deadline(),
+                });
                 
-                match self.approval_handler.approve(name, arguments).await {
+                match self.await_approval(self.approval_handler.as_ref(), name, arguments).await {
                     Ok(true) => {}, // Proceed
                     Ok(false) => return Err(Error::ToolApprovalRequired { tool_name: name.to_string() }),
-                    Err(e) => return Err(Error::tool_execution(name.to_string(), format!("Approval check failed: {}", e)))
+                    Err(e @ Error::ApprovalTimeout { .. }) => {
+                        return Err(match self.config.approval_timeout_action {
+                            ApprovalTimeoutAction::DenyAndContinue => e,
+                            ApprovalTimeoutAction::FailRun => Error::ToolApprovalRequired { tool_name: name.to_string() },
+                        })
+                    }
+                    Err(e) => return Err(e),
                 }
             }
             ToolPolicy::Auto => {} // Proceed
         }
 
+        if let Some(def) = self.tools().definition(name).await {
+            self.check_read_only(name, &def)?;
+        }
+
+        budget::spend_tool_attempt("agent")?;
         self.emit(AgentEvent::ToolCall { tool: name.to_string(), input: arguments.to_string() });
 
-        let result = self.tools.call(name, arguments).await;
+        let result = self.tools().call(name, arguments).await;
         
         match result {
-            Ok(mut output) => {
-                // Quota Protection: Truncate tool output if too long
-                if output.len() > self.config.max_tool_output_chars {
-                    let original_len = output.len();
-                    output.truncate(self.config.max_tool_output_chars);
-                    output.push_str(&format!("\n\n(Note: Output truncated from {} to {} chars to save tokens)", 
-                        original_len, self.config.max_tool_output_chars));
-                }
+            Ok(output) => {
+                // Quota Protection: Reduce tool output if too long
+                let output = self.reduce_tool_output(name, self.redact_secrets(output)).await;
 
                 self.emit(AgentEvent::ToolResult { tool: name.to_string(), output: output.clone() });
                 Ok(output)
             },
             Err(e) => {
-                self.emit(AgentEvent::Error { message: e.to_string() });
-                // Map anyhow error to ToolExecution error
-                Err(Error::tool_execution(name.to_string(), e.to_string()))
+                let message = self.redact_secrets(e.to_string());
+                self.emit(AgentEvent::Error { message: message.clone() });
+                match tool_call_error(name, e) {
+                    timeout @ Error::ToolTimeout { .. } => Err(timeout),
+                    // Map anyhow error to ToolExecution error
+                    _ => Err(Error::tool_execution(name.to_string(), message)),
+                }
             }
         }
     }
 
-    /// Check if agent has a tool
-    pub fn has_tool(&self, name: &str) -> bool {
-        self.tools.contains(name)
+    /// Scrub resolved secret values from text headed to the model or events
+    fn redact_secrets(&self, text: String) -> String {
+        match &self.secrets {
+            Some(secrets) => secrets.redact(&text),
+            None => text,
+        }
+    }
+
+    /// Response formatter chains, e.g. for formatting streamed deltas per consumer
+    pub fn formatters(&self) -> &ResponseFormatters {
+        &self.formatters
+    }
+
+    /// Check that every tool's required secrets resolve (`None` without a secret resolver)
+    pub async fn preflight_secrets(&self) -> Option<PreflightReport> {
+        let secrets = self.secrets.as_ref()?;
+        Some(secrets.preflight(&self.tools()).await)
+    }
+
+    /// Shrink an oversized tool output, keeping JSON valid and storing the original in memory
+    ///
+    /// Outputs within `max_tool_output_chars` are returned as they are.
+    async fn reduce_tool_output(&self, name: &str, output: String) -> String {
+        if output.len() <= self.config.max_tool_output_chars {
+            return output;
+        }
+        let projection = self
+            .tools()
+            .definition(name)
+            .await
+            .and_then(|def| def.result_projection);
+        let config = CompressionConfig::with_max_chars(self.config.max_tool_output_chars);
+        let compressed = compress::compress(&output, projection.as_deref(), &config);
+
+        let mut note = format!(
+            "Output reduced from {} to {} chars to save tokens: {}.",
+            compressed.original_len,
+            compressed.text.len(),
+            compressed.notes.join("; ")
+        );
+
+        if let Some(memory) = &self.memory {
+            let path = format!("{}/{}.json", name, uuid::Uuid::new_v4());
+            let user_id = self.user_id.as_deref().unwrap_or("default");
+            match memory
+                .store_knowledge(user_id, Some(&self.config.name), &path, &output, TOOL_OUTPUT_COLLECTION)
+                .await
+            {
+                Ok(()) => note.push_str(&format!(
+                    " Full output stored as collection '{}', path '{}' (use fetch_document for details).",
+                    TOOL_OUTPUT_COLLECTION, path
+                )),
+                Err(e) => tracing::warn!("Failed to store full output of tool {}: {}", name, e),
+            }
+        }
+
+        format!("{}\n\n(Note: {})", compressed.text, note)
+    }
+
+    /// Snapshot of the current tools
+    fn tools(&self) -> Arc<ToolSet> {
+        self.tools.load_full()
+    }
+
+    /// Check if agent has a tool
+    pub fn has_tool(&self, name: &str) -> bool {
+        self.tools().contains(name)
     }
 
     /// Add tool definitions
     pub async fn tool_definitions(&self) -> Vec<crate::skills::tool::ToolDefinition> {
-        self.tools.definitions().await
+        self.tools().definitions().await
     }
 
     /// Get the agent's configuration
@@ -687,6 +1777,34 @@ pub struct AgentBuilder<P: Provider> {
     has_dynamic_skill: bool,
     memory: Option<Arc<dyn Memory>>,
     session_id: Option<String>,
+    user_id: Option<String>,
+    /// Whether build() auto-loads DynamicSkills from ./skills
+    auto_load_skills: bool,
+    subagents: Option<SubagentConfig>,
+    /// Whether build() registers the `introspect` tool
+    introspection: bool,
+    /// Risk limits reported by the `introspect` tool
+    #[cfg(feature = "trading")]
+    risk_config: Option<crate::trading::risk::RiskConfig>,
+    secrets: Option<Arc<Secrets>>,
+    formatters: ResponseFormatters,
+    feedback: Option<Arc<FeedbackStore>>,
+    tool_router: Option<Arc<dyn ToolRouter>>,
+    strict_validation: bool,
+    replay: ReplayConfig,
+    replay_artifacts: Option<Arc<dyn ArtifactStore>>,
+    budget: BudgetConfig,
+    pricing: Option<PriceTable>,
+    debug_trace_dir: Option<std::path::PathBuf>,
+    debug_trace_limit: usize,
+    event_log: Option<std::path::PathBuf>,
+    tool_aging: Option<ToolAgingConfig>,
+    summarize: Option<SummarizeConfig>,
+    /// Provider for history summaries; the agent's own when `None`
+    summary_provider: Option<Arc<dyn Provider>>,
+    token_counter: Option<Arc<dyn TokenCounter>>,
+    /// Whether the preamble was set explicitly rather than left at its default
+    preamble_set: bool,
 }
 
 impl<P: Provider> AgentBuilder<P> {
@@ -705,8 +1823,39 @@ impl<P: Provider> AgentBuilder<P> {
             has_dynamic_skill: false,
             memory: None,
             session_id: None,
+            user_id: None,
+            auto_load_skills: true,
+            subagents: None,
+            introspection: true,
+            #[cfg(feature = "trading")]
+            risk_config: None,
+            secrets: None,
+            formatters: ResponseFormatters::new(),
+            feedback: None,
+            tool_router: None,
+            strict_validation: false,
+            replay: ReplayConfig::default(),
+            replay_artifacts: None,
+            budget: BudgetConfig::default(),
+            pricing: None,
+            debug_trace_dir: None,
+            debug_trace_limit: crate::agent::dev_trace::DEFAULT_MAX_TRACES,
+            event_log: None,
+            tool_aging: None,
+            summarize: None,
+            summary_provider: None,
+            token_counter: None,
+            preamble_set: false,
         }
     }
+}
+
+impl<P: Provider> AgentBuilder<P> {
+    /// Set the agent's name, used in logs and session metadata
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.config.name = name.into();
+        self
+    }
 
     /// Set the model to use
     pub fn model(mut self, model: impl Into<String>) -> Self {
@@ -714,9 +1863,47 @@ impl<P: Provider> AgentBuilder<P> {
         self
     }
 
-    /// Set the system prompt
+    /// Set the system prompt as one string (the `legacy` section)
     pub fn system_prompt(mut self, prompt: impl Into<String>) -> Self {
         self.config.preamble = prompt.into();
+        self.preamble_set = true;
+        self
+    }
+
+    /// Set or override one system prompt section
+    ///
+    /// The default preamble is dropped once any other section is set, unless
+    /// [`AgentBuilder::system_prompt`] was called.
+    pub fn prompt_section(mut self, key: impl Into<SectionKey>, text: impl Into<String>) -> Self {
+        match key.into() {
+            SectionKey::Legacy => return self.system_prompt(text),
+            key => self.config.prompt_sections.set(key, text),
+        }
+        if !self.preamble_set {
+            self.config.preamble.clear();
+        }
+        self
+    }
+
+    /// Remove one system prompt section
+    pub fn remove_section(mut self, key: impl Into<SectionKey>) -> Self {
+        match key.into() {
+            SectionKey::Legacy => return self.system_prompt(""),
+            key => self.config.prompt_sections.remove(key),
+        };
+        self
+    }
+
+    /// Set every system prompt section, separator and header style at once
+    pub fn prompt(mut self, prompt: SystemPrompt) -> Self {
+        self.config.prompt_sections = SystemPrompt::new()
+            .separator(prompt.separator.clone())
+            .headers(prompt.headers);
+        self.config.preamble.clear();
+        self.preamble_set = true;
+        for section in prompt.sections() {
+            self = self.prompt_section(section.key.clone(), section.text.clone());
+        }
         self
     }
 
@@ -737,6 +1924,12 @@ impl<P: Provider> AgentBuilder<P> {
         self
     }
 
+    /// Set the reasoning budget of thinking models
+    pub fn max_reasoning_tokens(mut self, tokens: u64) -> Self {
+        self.config.max_reasoning_tokens = Some(tokens);
+        self
+    }
+
     /// Add extra provider-specific parameters
     pub fn extra_params(mut self, params: serde_json::Value) -> Self {
         self.config.extra_params = Some(params);
@@ -767,6 +1960,18 @@ impl<P: Provider> AgentBuilder<P> {
         self
     }
 
+    /// Set the model's context window in tokens
+    pub fn max_context_tokens(mut self, tokens: usize) -> Self {
+        self.config.max_context_tokens = Some(tokens);
+        self
+    }
+
+    /// Count context tokens with `counter` instead of the `cl100k_base` tokenizer
+    pub fn token_counter(mut self, counter: impl TokenCounter + 'static) -> Self {
+        self.token_counter = Some(Arc::new(counter));
+        self
+    }
+
     /// Set max tool output characters
     pub fn max_tool_output_chars(mut self, count: usize) -> Self {
         self.config.max_tool_output_chars = count;
@@ -779,6 +1984,32 @@ impl<P: Provider> AgentBuilder<P> {
         self
     }
     
+    /// Fold the first tool example into native tool descriptions
+    pub fn fold_tool_examples(mut self, enable: bool) -> Self {
+        self.config.fold_tool_examples = enable;
+        self
+    }
+
+    /// Emit stream deltas to subscribers while the model responds (default: on)
+    pub fn emit_stream_deltas(mut self, enable: bool) -> Self {
+        self.config.emit_stream_deltas = enable;
+        self
+    }
+
+    /// Re-prompt up to `retries` times when a typed response doesn't parse (default: 2)
+    pub fn typed_output_retries(mut self, retries: usize) -> Self {
+        self.config.typed_output_retries = retries;
+        self
+    }
+
+    /// Ask the provider for whole replies instead of streams (default: off)
+    ///
+    /// Stream deltas still fire, once per step with the full text.
+    pub fn prefer_complete(mut self, enable: bool) -> Self {
+        self.config.prefer_complete = enable;
+        self
+    }
+
     /// Set the agent's personality
     pub fn persona(mut self, persona: Persona) -> Self {
         self.config.persona = Some(persona);
@@ -791,12 +2022,91 @@ impl<P: Provider> AgentBuilder<P> {
         self
     }
 
+    /// Bounds of the buffer late subscribers replay from (`ReplayConfig::disabled()` to turn it off)
+    pub fn event_replay(mut self, config: ReplayConfig) -> Self {
+        self.replay = config;
+        self
+    }
+
+    /// Keep the full payloads of events truncated in the replay buffer
+    pub fn replay_artifacts(mut self, store: impl ArtifactStore + 'static) -> Self {
+        self.replay_artifacts = Some(Arc::new(store));
+        self
+    }
+
+    /// Time and attempt limits shared by every retrying layer of one request
+    pub fn budget(mut self, config: BudgetConfig) -> Self {
+        self.budget = config;
+        self
+    }
+
+    /// Prices used to estimate the cost of each model call
+    ///
+    /// Without a table, usage is still counted but carries no cost.
+    pub fn pricing(mut self, prices: PriceTable) -> Self {
+        self.pricing = Some(prices);
+        self
+    }
+
+    /// Write a readable trace of every provider call under `dir`; local development only
+    ///
+    /// See [`crate::agent::dev_trace`]. Traces hold full prompts and responses.
+    pub fn debug_trace_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.debug_trace_dir = Some(dir.into());
+        self
+    }
+
+    /// Keep at most `max` dev traces (default [`crate::agent::dev_trace::DEFAULT_MAX_TRACES`])
+    pub fn debug_trace_limit(mut self, max: usize) -> Self {
+        self.debug_trace_limit = max;
+        self
+    }
+
+    /// Append every event, tagged with the session id, to the JSONL file at `path`
+    ///
+    /// See [`crate::agent::event_log`]. Building then needs a Tokio runtime.
+    pub fn record_events(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.event_log = Some(path.into());
+        self
+    }
+
+    /// Stub old tool results in the context and register `recall_tool_output`
+    ///
+    /// See [`crate::agent::tool_aging`]. Off by default; history keeps the full text.
+    pub fn tool_output_aging(mut self, config: ToolAgingConfig) -> Self {
+        self.tool_aging = Some(config);
+        self
+    }
+
+    /// Fold old history into a rolling summary instead of only dropping it
+    ///
+    /// See [`crate::agent::history_summary`]. Summaries use the agent's provider
+    /// unless [`AgentBuilder::summary_provider`] sets a cheaper one. Keep
+    /// `trigger_messages` within `max_history_messages`, or windowing drops
+    /// messages before they are summarized.
+    pub fn summarize_history(mut self, config: SummarizeConfig) -> Self {
+        self.summarize = Some(config);
+        self
+    }
+
+    /// Provider for history summaries (default: the agent's own)
+    pub fn summary_provider(mut self, provider: impl Provider + 'static) -> Self {
+        self.summary_provider = Some(Arc::new(provider));
+        self
+    }
+
     /// Set session ID for persistence
     pub fn session_id(mut self, id: impl Into<String>) -> Self {
         self.session_id = Some(id.into());
         self
     }
 
+    /// Record `id` as the owner of checkpointed sessions, for listing them by user
+    pub fn user_id(mut self, id: impl Into<String>) -> Self {
+        self.user_id = Some(id.into());
+        self
+    }
+
     /// Set the agent's role
     pub fn role(mut self, role: AgentRole) -> Self {
         self.config.role = role;
@@ -821,6 +2131,18 @@ impl<P: Provider> AgentBuilder<P> {
         self
     }
 
+    /// Cancel tool calls that run longer than `timeout`; the model sees the timeout as the result
+    pub fn default_tool_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.tools = std::mem::take(&mut self.tools).with_default_timeout(timeout);
+        self
+    }
+
+    /// Cancel calls to the tool `name` that run longer than `timeout`
+    pub fn tool_timeout(mut self, name: impl Into<String>, timeout: std::time::Duration) -> Self {
+        self.tools = std::mem::take(&mut self.tools).with_tool_timeout(name, timeout);
+        self
+    }
+
     /// Add multiple tools from a toolset
     pub fn tools(mut self, tools: ToolSet) -> Self {
         for (_, tool) in tools.iter() {
@@ -862,10 +2184,9 @@ impl<P: Provider> AgentBuilder<P> {
             ));
         }
         
-        // Add all loaded skills as tools
-        for skill_ref in skill_loader.skills.iter() {
-            self.tools.add_shared(Arc::clone(skill_ref.value()) as Arc<dyn crate::skills::tool::Tool>);
-        }
+        // Add all loaded skills as tools, namespaced so they never shadow internal tools;
+        // skills installed later (e.g. through ClawHub) are synced by the built agent
+        self.tools.watch_skills(&skill_loader, SKILL_NAMESPACE)?;
         
         // Add ClawHub and ReadSkillDoc tools
         self.tools.add(crate::skills::ClawHubTool::new(Arc::clone(&skill_loader)));
@@ -917,21 +2238,20 @@ impl<P: Provider> AgentBuilder<P> {
     /// - Network access: disabled (secure sandbox)
     /// 
     /// To use Python Sidecar instead, call `.with_code_interpreter()` before `.build()`.
-    pub fn build(mut self) -> Result<Agent<P>> {
-        // Validate configuration
-        if self.config.model.is_empty() {
-            return Err(Error::agent_config("model name cannot be empty"));
-        }
-        if self.config.max_history_messages == 0 {
-            return Err(Error::agent_config("max_history_messages must be at least 1"));
-        }
-
+    pub fn build(mut self) -> Result<Agent<P>>
+    where
+        P: 'static,
+    {
         // SECURITY DEFAULT: Auto-enable DynamicSkill if no execution model configured
-        if !self.has_sidecar && !self.has_dynamic_skill {
+        if self.auto_load_skills && !self.has_sidecar && !self.has_dynamic_skill {
             info!("No execution model configured. Auto-enabling DynamicSkill (default)...");
             
             // Try to load skills from default directory
-            let skill_loader = Arc::new(crate::skills::SkillLoader::new("./skills"));
+            let mut skill_loader = crate::skills::SkillLoader::new("./skills");
+            if let Some(secrets) = &self.secrets {
+                skill_loader = skill_loader.with_secrets(Arc::clone(secrets));
+            }
+            let skill_loader = Arc::new(skill_loader);
             
             // Attempt to load skills (non-fatal if directory doesn't exist)
             match tokio::task::block_in_place(|| {
@@ -941,9 +2261,7 @@ impl<P: Provider> AgentBuilder<P> {
                     info!("Loaded DynamicSkills from ./skills");
                     
                     // Add all loaded skills as tools
-                    for skill_ref in skill_loader.skills.iter() {
-                        self.tools.add_shared(Arc::clone(skill_ref.value()) as Arc<dyn crate::skills::tool::Tool>);
-                    }
+                    self.tools.watch_skills(&skill_loader, SKILL_NAMESPACE)?;
                     
                     // Add ClawHub and ReadSkillDoc tools
                     self.tools.add(crate::skills::ClawHubTool::new(Arc::clone(&skill_loader)));
@@ -959,40 +2277,126 @@ impl<P: Provider> AgentBuilder<P> {
             }
         }
 
-        let (tx, _) = broadcast::channel(1000);
+        let mut replay = ReplayBuffer::new(self.replay);
+        if let Some(store) = self.replay_artifacts {
+            replay = replay.with_artifacts(store);
+        }
+        let tx = EventHub::new(1000).with_replay(replay);
+        let provider = Arc::new(self.provider);
+
+        // Sub-agents draw from the tools registered so far (not ask_user or the spawn tool itself)
+        let mut token_budget = None;
+        if let Some(subagent_config) = self.subagents.take() {
+            let spawn_tool = SpawnSubagentTool::new(
+                Arc::clone(&provider),
+                self.tools.clone(),
+                self.config.clone(),
+                subagent_config,
+            )
+            .with_events(tx.clone());
+            token_budget = Some(spawn_tool.budget());
+            self.tools.add(spawn_tool);
+        }
 
         let mut context_config = ContextConfig::default();
         context_config.max_history_messages = self.config.max_history_messages;
+        if let Some(tokens) = self.config.max_context_tokens {
+            context_config.max_tokens = tokens;
+        }
         if let Some(tokens) = self.config.max_tokens {
             // Rough heuristic: Context window is usually larger than max_tokens (generation limit)
             // But we don't have model context window size in config yet.
             // For now, let's just ensure we respect max_history_messages primarily.
             context_config.response_reserve = tokens as usize;
         }
+        context_config.summarize = self.summarize.map(|mut summarize| {
+            summarize.model.get_or_insert_with(|| self.config.model.clone());
+            summarize
+        });
 
         let mut context_manager = ContextManager::new(context_config);
-        context_manager.set_system_prompt(self.config.preamble.clone());
-        
-        // Inject all tools as TS interfaces in the system prompt
-        // This fulfills the 'Replace JSON with TS in Prompt' requirement.
-        context_manager.add_injector(Box::new(self.tools.clone()));
+        context_manager.set_system_prompt_sections(self.config.system_prompt());
+        if let Some(counter) = self.token_counter {
+            context_manager.set_token_counter(counter);
+        }
+        context_manager.set_summary_provider(
+            self.summary_provider.unwrap_or_else(|| Arc::clone(&provider) as Arc<dyn Provider>),
+        );
+        // The TS tool catalog is rendered per step in chat(), filtered by the tool router
 
         for injector in self.injectors {
             context_manager.add_injector(injector);
         }
 
-        if let Some(persona) = &self.config.persona {
-            context_manager.add_injector(Box::new(PersonalityManager::new(persona.clone())));
-        }
-
         // Auto-register AskUser tool if handler available
         let mut tools = self.tools;
         if let Some(handler) = &self.interaction_handler {
             tools.add(AskUserTool { handler: Arc::clone(handler) });
         }
 
+        if let Some(config) = self.tool_aging {
+            let aging = ToolOutputAging::new(config);
+            tools.add(RecallToolOutputTool::new(aging.archive()));
+            context_manager.set_tool_aging(aging);
+        }
+
+        // Read-only self-description, built from the final toolset
+        if self.introspection {
+            let mut introspect = IntrospectionTool::new(&self.config, tools.clone()).with_provider(
+                provider.name(),
+                provider.supports_tools(),
+                provider.supports_streaming(),
+            );
+            if let Some(memory) = &self.memory {
+                introspect = introspect.with_memory(Arc::clone(memory), self.session_id.clone());
+            }
+            #[cfg(feature = "trading")]
+            if let Some(risk) = &self.risk_config {
+                introspect = introspect.with_risk_config(risk);
+            }
+            tools.add(introspect);
+        }
+
+        // Validate against the final toolset, reporting every issue at once
+        #[allow(unused_mut)]
+        let mut issues = self.config.validate_with_tools(&tools);
+        #[cfg(feature = "trading")]
+        if let Some(risk) = &self.risk_config {
+            issues.extend(risk.validate());
+        }
+        ConfigIssues::check(issues, self.strict_validation)?;
+
+        let event_recorder = self
+            .event_log
+            .map(|path| {
+                let session = self.session_id.clone().unwrap_or_else(|| "default".to_string());
+                EventRecorder::spawn(tx.subscribe(), path, session).map(Arc::new)
+            })
+            .transpose()?;
+
+        let dev_trace = self.debug_trace_dir.map(|dir| {
+            tracing::warn!("Dev tracing to {}; traces hold full prompts, not for production", dir.display());
+            Arc::new(DevTracer::new(dir).max_traces(self.debug_trace_limit).with_secrets(self.secrets.clone()))
+        });
+
+        // Skills installed or removed later (e.g. through ClawHub) reach the tools right away
+        let loader = tools.skill_loader();
+        let tools = Arc::new(ArcSwap::from_pointee(tools));
+        if let Some(loader) = loader {
+            let watched = Arc::downgrade(&tools);
+            loader.on_change(move || match watched.upgrade() {
+                Some(tools) => {
+                    sync_skills(&tools);
+                    true
+                }
+                None => false,
+            });
+            sync_skills(&tools);
+        }
+
+        let persona = self.config.persona.clone();
         Ok(Agent {
-            provider: Arc::new(self.provider),
+            provider,
             tools,
             config: self.config,
             context_manager,
@@ -1002,6 +2406,22 @@ impl<P: Provider> AgentBuilder<P> {
             notifier: self.notifier,
             memory: self.memory,
             session_id: self.session_id,
+            user_id: self.user_id,
+            secrets: self.secrets,
+            formatters: self.formatters,
+            feedback: self.feedback,
+            tool_router: self.tool_router,
+            workflow_state: parking_lot::RwLock::new(None),
+            persona: parking_lot::RwLock::new(persona),
+            session_tags: parking_lot::RwLock::new(Vec::new()),
+            budget: self.budget,
+            last_budget: parking_lot::RwLock::new(None),
+            pricing: self.pricing,
+            last_usage: parking_lot::RwLock::new(None),
+            session_usage: parking_lot::RwLock::new(SessionUsage::default()),
+            token_budget,
+            dev_trace,
+            event_recorder,
         })
     }
 
@@ -1016,6 +2436,114 @@ impl<P: Provider> AgentBuilder<P> {
         self.tools.add(CronTool::new(Arc::downgrade(&scheduler)));
         self
     }
+
+    /// Allow the agent to spawn ephemeral sub-agents via the `spawn_subagent` tool
+    pub fn with_subagents(mut self, config: SubagentConfig) -> Self {
+        self.subagents = Some(config);
+        self
+    }
+
+    /// Resolve tool secrets through `secrets` and redact their values from tool output and events
+    ///
+    /// Set this before `build()` so auto-loaded skills receive it; pass the same
+    /// resolver to `SkillLoader::with_secrets` when loading skills yourself.
+    pub fn secrets(mut self, secrets: Arc<Secrets>) -> Self {
+        self.secrets = Some(secrets);
+        self
+    }
+
+    /// Format final responses for a consumer (e.g. a [`NotifyChannel::name`]) with an ordered chain
+    pub fn response_formatter(mut self, consumer: impl Into<String>, target: FormatTarget, chain: FormatterChain) -> Self {
+        self.formatters = self.formatters.register(consumer, target, chain);
+        self
+    }
+
+    /// Register each response in `store` so user feedback can be attached to it
+    pub fn with_feedback(mut self, store: Arc<FeedbackStore>) -> Self {
+        self.feedback = Some(store);
+        self
+    }
+
+    /// Prompt template version recorded with each response
+    pub fn prompt_version(mut self, version: impl Into<String>) -> Self {
+        self.config.prompt_version = Some(version.into());
+        self
+    }
+
+    /// Experiment variant recorded with each response
+    pub fn experiment_variant(mut self, variant: impl Into<String>) -> Self {
+        self.config.experiment_variant = Some(variant.into());
+        self
+    }
+
+    /// Enable or disable the built-in `introspect` tool (default: enabled)
+    pub fn introspection(mut self, enable: bool) -> Self {
+        self.introspection = enable;
+        self
+    }
+
+    /// Risk limits the `introspect` tool reports to the model
+    #[cfg(feature = "trading")]
+    pub fn risk_config(mut self, config: crate::trading::risk::RiskConfig) -> Self {
+        self.risk_config = Some(config);
+        self
+    }
+
+    /// Decide per step which tools the model sees (see [`crate::agent::tool_routing`])
+    pub fn tool_router(mut self, router: impl ToolRouter + 'static) -> Self {
+        self.tool_router = Some(Arc::new(router));
+        self
+    }
+
+    /// Give up on approvals after `secs` seconds; `None` waits forever (default: 300)
+    pub fn approval_timeout_secs(mut self, secs: Option<u64>) -> Self {
+        self.config.approval_timeout_secs = secs;
+        self
+    }
+
+    /// What a timed-out approval does to the run (default: deny and continue)
+    pub fn on_approval_timeout(mut self, action: ApprovalTimeoutAction) -> Self {
+        self.config.approval_timeout_action = action;
+        self
+    }
+
+    /// What a step does when a tool call fails (default: report it to the model)
+    pub fn tool_failure_policy(mut self, policy: ToolFailurePolicy) -> Self {
+        self.config.tool_failure_policy = policy;
+        self
+    }
+
+    /// Only expose and run side-effect-free tools
+    pub fn read_only(mut self, enable: bool) -> Self {
+        self.config.read_only = enable;
+        self
+    }
+
+    /// Fail `build()` on configuration warnings too, not just errors (default: disabled)
+    pub fn strict_validation(mut self, strict: bool) -> Self {
+        self.strict_validation = strict;
+        self
+    }
+
+    /// Enable or disable auto-loading DynamicSkills from ./skills in build() (default: enabled)
+    pub fn auto_load_skills(mut self, enable: bool) -> Self {
+        self.auto_load_skills = enable;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl<P: Provider> crate::agent::session::SessionResumer for Agent<P> {
+    async fn resume_session(&self, session: &crate::agent::session::AgentSession) -> Result<String> {
+        info!("Resuming interrupted session: {}", session.id);
+        *self.session_usage.write() = session.usage.clone();
+        if let Some(persona) = session.restored_persona() {
+            self.set_persona(persona);
+        }
+        let mut messages = session.messages.clone();
+        messages.push(Message::system(crate::agent::session::RESUME_NOTE));
+        self.chat(messages).await
+    }
 }
 
 #[async_trait::async_trait]
@@ -1039,11 +2567,56 @@ impl<P: Provider> MultiAgent for Agent<P> {
     async fn process(&self, input: &str) -> Result<String> {
         self.prompt(input).await
     }
+
+    fn tool_names(&self) -> Vec<String> {
+        self.tools().names()
+    }
+}
+
+/// Swap in a copy of `tools` resynced with its skill loader
+fn sync_skills(tools: &ArcSwap<ToolSet>) {
+    tools.rcu(|current| {
+        let mut next = ToolSet::clone(current);
+        next.sync_skills();
+        next
+    });
+}
+
+/// Wrap a failed tool call, keeping timeouts distinguishable from other failures
+fn tool_call_error(name: &str, e: anyhow::Error) -> Error {
+    match e.downcast::<Error>() {
+        Ok(timeout @ Error::ToolTimeout { .. }) => timeout,
+        Ok(e) => Error::tool_execution(name, e.to_string()),
+        Err(e) => Error::tool_execution(name, e.to_string()),
+    }
+}
+
+/// Characters sent in `request`, for estimating usage when the provider reports none
+fn request_chars(request: &crate::agent::provider::ChatRequest) -> usize {
+    request.system_prompt.as_ref().map_or(0, String::len)
+        + request.messages.iter().map(|m| m.content.as_text().len()).sum::<usize>()
+        + request
+            .tools
+            .iter()
+            .map(|t| t.name.len() + t.description.len() + t.parameters.to_string().len())
+            .sum::<usize>()
+}
+
+/// Index to restart history from after a context overflow: drop the older half,
+/// starting at a user message so tool calls stay paired with their results
+fn overflow_trim(messages: &[Message], skip: usize) -> Option<usize> {
+    let last = messages.len().checked_sub(1)?;
+    let mut next = skip + (messages.len() - skip) / 2;
+    while next < last && messages[next].role != Role::User {
+        next += 1;
+    }
+    (next > skip && next <= last).then_some(next)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::StubProvider;
 
     #[test]
     fn test_agent_config_default() {
@@ -1051,4 +2624,675 @@ mod tests {
         assert_eq!(config.model, "gpt-4o");
         assert_eq!(config.max_tokens, Some(4096));
     }
+
+    #[test]
+    fn test_build_reports_all_config_errors() {
+        let result = AgentBuilder::new(StubProvider)
+            .model("")
+            .temperature(9.0)
+            .max_history_messages(0)
+            .auto_load_skills(false)
+            .introspection(false)
+            .build();
+        let Err(Error::InvalidConfig(issues)) = result else {
+            panic!("expected InvalidConfig");
+        };
+        let paths: Vec<&str> = issues.errors().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, ["agent.model", "agent.temperature", "agent.max_history_messages"]);
+
+        // Warnings pass unless strict
+        let lenient = AgentBuilder::new(StubProvider)
+            .preamble("")
+            .auto_load_skills(false)
+            .introspection(false);
+        assert!(lenient.build().is_ok());
+        let strict = AgentBuilder::new(StubProvider)
+            .preamble("")
+            .strict_validation(true)
+            .auto_load_skills(false)
+            .introspection(false)
+            .build();
+        assert!(matches!(strict, Err(Error::InvalidConfig(i)) if i.at("agent.preamble").is_some()));
+    }
+
+    /// Fails every call, alternating between a context overflow and a dropped stream
+    struct Failing(Arc<std::sync::atomic::AtomicU32>);
+
+    #[async_trait::async_trait]
+    impl Provider for Failing {
+        async fn stream_completion(
+            &self,
+            _request: crate::agent::provider::ChatRequest,
+        ) -> Result<StreamingResponse> {
+            if self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) % 2 == 0 {
+                Err(Error::ProviderApi("context_length_exceeded".to_string()))
+            } else {
+                Err(Error::StreamInterrupted("connection reset".to_string()))
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_layers_never_exceed_the_request_budget() {
+        use crate::agent::provider::{CircuitBreakerConfig, ResilientProvider, RetryConfig, RetryProvider};
+        use std::sync::atomic::Ordering;
+        use std::time::Duration;
+
+        let mut overflow_retried = false;
+        for max_retries in [0, 2, 50] {
+            for max_attempts in [1, 3, 7, 20] {
+                let calls = Arc::new(std::sync::atomic::AtomicU32::new(0));
+                let retry = RetryConfig {
+                    max_retries,
+                    base_delay: Duration::from_millis(1),
+                    max_delay: Duration::from_millis(1),
+                    jitter: 0.0,
+                };
+                let provider = RetryProvider::new(
+                    ResilientProvider::new(
+                        Failing(calls.clone()),
+                        RetryProvider::new(Failing(calls.clone()), retry.clone()),
+                        CircuitBreakerConfig::default(),
+                    ),
+                    retry,
+                );
+                let agent = AgentBuilder::new(provider)
+                    .auto_load_skills(false)
+                    .introspection(false)
+                    .budget(BudgetConfig {
+                        total_time: Some(Duration::from_secs(5)),
+                        max_provider_attempts: max_attempts,
+                        max_tool_attempts: 0,
+                    })
+                    .build()
+                    .unwrap();
+                let history = (0..8)
+                    .map(|i| if i % 2 == 0 { Message::user(format!("q{}", i)) } else { Message::assistant(format!("a{}", i)) })
+                    .collect();
+
+                assert!(agent.chat(history).await.is_err());
+                let summary = agent.last_budget().unwrap();
+                let calls = calls.load(Ordering::SeqCst);
+                assert!(calls <= max_attempts, "{} calls with budget {}", calls, max_attempts);
+                assert_eq!(summary.provider_attempts, calls);
+                overflow_retried |= summary.by_layer.contains_key("context_overflow");
+            }
+        }
+        assert!(overflow_retried);
+    }
+
+    /// Description changes every time its definition is rendered
+    struct Versioned(std::sync::atomic::AtomicU32);
+
+    #[async_trait::async_trait]
+    impl Tool for Versioned {
+        fn name(&self) -> String {
+            "quote".to_string()
+        }
+
+        async fn definition(&self) -> crate::skills::tool::ToolDefinition {
+            let version = self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            crate::skills::tool::ToolDefinition {
+                name: self.name(),
+                description: format!("Quote a pair (v{})", version),
+                parameters: serde_json::json!({ "type": "object", "properties": {} }),
+                is_verified: true,
+                side_effect_free: true,
+                ..Default::default()
+            }
+        }
+
+        async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+            Ok("1.0".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trading_agent_context_snapshot() {
+        let agent = AgentBuilder::new(StubProvider)
+            .preamble("You are a trading agent. Never trade without a stop loss.")
+            .persona(crate::agent::personality::Persona::analytical_trader())
+            .tool(crate::skills::tool::CalculatorTool::new())
+            .tool(Versioned(std::sync::atomic::AtomicU32::new(0)))
+            .auto_load_skills(false)
+            .introspection(false)
+            .build()
+            .unwrap();
+        let messages = vec![
+            Message::user("What's my P&L on 2 SOL bought at 140?"),
+            Message::assistant("Let me check the price."),
+            Message::user("Use the latest quote."),
+        ];
+
+        crate::assert_context_snapshot!(agent, messages, "tests/snapshots/trading_agent.txt");
+
+        // Cached definitions are reused unless a fresh render is forced
+        let cached = agent.render_context_preview(&messages, PreviewOptions::default()).await.unwrap();
+        assert!(cached.sections[1].text.contains("Quote a pair (v1)"));
+        let fresh = agent
+            .render_context_preview(&messages, PreviewOptions { force_fresh: true })
+            .await
+            .unwrap();
+        assert_eq!(fresh.sections[1].source, "tool_catalog");
+        assert!(fresh.sections[1].text.contains("Quote a pair (v2)"));
+    }
+
+    #[tokio::test]
+    async fn test_installed_skills_reach_the_next_step() {
+        let loader = Arc::new(crate::skills::SkillLoader::new("./no-skills-here"));
+        let agent = AgentBuilder::new(StubProvider)
+            .auto_load_skills(false)
+            .introspection(false)
+            .with_dynamic_skills(Arc::clone(&loader))
+            .unwrap()
+            .build()
+            .unwrap();
+        let tool = crate::skills::tool::namespaced(SKILL_NAMESPACE, "price_alert");
+        assert!(!agent.has_tool(&tool));
+
+        // What ClawHub's install leaves behind, without the download
+        let (metadata, instructions) = crate::skills::frontmatter::parse(
+            "---\nname: price_alert\ndescription: Alert when a token crosses a price\n---\nRun the script.",
+        )
+        .unwrap();
+        let skill = crate::skills::DynamicSkill::new(metadata, instructions, std::path::PathBuf::from("."));
+        loader.insert(skill);
+
+        let names: Vec<String> = agent.tool_definitions().await.into_iter().map(|d| d.name).collect();
+        assert!(names.contains(&tool), "{:?}", names);
+        let messages = vec![Message::user("Ping me when SOL hits 200")];
+        let rendered = agent.render_context_preview(&messages, PreviewOptions::default()).await.unwrap();
+        let catalog = rendered.sections.iter().find(|s| s.source == "tool_catalog").unwrap();
+        assert!(catalog.text.contains("Alert when a token crosses a price"));
+
+        loader.remove("price_alert");
+        assert!(!agent.has_tool(&tool));
+    }
+
+    #[tokio::test]
+    async fn test_prompt_sections_override_and_attribution() {
+        let messages = vec![Message::user("Long SOL?")];
+        let legacy = AgentBuilder::new(StubProvider)
+            .system_prompt("You are a trading agent.\nNever trade without a stop loss.")
+            .auto_load_skills(false)
+            .introspection(false)
+            .build()
+            .unwrap();
+        let rendered = legacy.render_context_preview(&messages, PreviewOptions::default()).await.unwrap();
+        assert_eq!(rendered.sections[0].text, "You are a trading agent.\nNever trade without a stop loss.");
+        assert_eq!(rendered.prompt_attribution(), None);
+
+        let agent = AgentBuilder::new(StubProvider)
+            .prompt_section("safety", "Never reveal keys.")
+            .prompt_section("identity", "You are a trading agent.")
+            .prompt_section("output_format", "Answer in markdown.")
+            .prompt_section("tenant", "Acme desk only.")
+            .prompt_section("safety", "Never reveal keys or seed phrases.")
+            .remove_section("output_format")
+            .auto_load_skills(false)
+            .introspection(false)
+            .build()
+            .unwrap();
+        // The default preamble gives way to explicit sections
+        let text = "You are a trading agent.\n\nNever reveal keys or seed phrases.\n\nAcme desk only.";
+        assert_eq!(agent.config().system_prompt().render(), text);
+
+        let rendered = agent.render_context_preview(&messages, PreviewOptions::default()).await.unwrap();
+        assert_eq!(rendered.sections[0].text, text);
+        let keys: Vec<_> = rendered.prompt_parts.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(keys, ["identity", "safety", "tenant"]);
+        assert!(rendered.prompt_parts[1].text.contains("seed phrases"));
+        let snapshot = rendered.to_snapshot(true);
+        assert!(snapshot.contains("## [system_prompt] system ("));
+        assert!(snapshot.contains("> sections: identity (6 tokens), safety ("), "{}", snapshot);
+    }
+
+    #[tokio::test]
+    async fn test_set_persona_changes_next_context() {
+        use crate::agent::personality::Persona;
+
+        let messages = vec![Message::user("Long SOL?")];
+        let agent = AgentBuilder::new(StubProvider)
+            .persona(Persona::analytical_trader())
+            .auto_load_skills(false)
+            .introspection(false)
+            .build()
+            .unwrap();
+        let before = agent.render_context_preview(&messages, PreviewOptions::default()).await.unwrap();
+        assert!(before.sections[0].text.contains("Senior Quant Strategist"));
+
+        let mut events = agent.subscribe();
+        agent.set_persona(Some(Persona::technical_assistant()));
+        let after = agent.render_context_preview(&messages, PreviewOptions::default()).await.unwrap();
+        assert_ne!(before.sections[0].text, after.sections[0].text);
+        assert!(after.sections[0].text.contains("Senior Technical Assistant"));
+        assert!(!after.sections[0].text.contains("Senior Quant Strategist"));
+        assert_eq!(agent.persona().map(|p| p.role).as_deref(), Some("Senior Technical Assistant"));
+        match events.recv().await.unwrap() {
+            AgentEvent::PersonaChanged { previous, current } => {
+                assert_eq!(previous.as_deref(), Some("Senior Quant Strategist"));
+                assert_eq!(current.as_deref(), Some("Senior Technical Assistant"));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        agent.set_persona(None);
+        let cleared = agent.render_context_preview(&messages, PreviewOptions::default()).await.unwrap();
+        assert!(!cleared.prompt_parts.iter().any(|p| p.key == "persona"));
+    }
+
+    /// Memory that only keeps sessions
+    #[derive(Default)]
+    struct Sessions(parking_lot::Mutex<std::collections::HashMap<String, AgentSession>>);
+
+    #[async_trait::async_trait]
+    impl Memory for Sessions {
+        async fn store(&self, _: &str, _: Option<&str>, _: Message) -> Result<()> {
+            Ok(())
+        }
+
+        async fn retrieve(&self, _: &str, _: Option<&str>, _: usize) -> Vec<Message> {
+            Vec::new()
+        }
+
+        async fn clear(&self, _: &str, _: Option<&str>) -> Result<()> {
+            Ok(())
+        }
+
+        async fn undo(&self, _: &str, _: Option<&str>) -> Result<Option<Message>> {
+            Ok(None)
+        }
+
+        async fn store_session(&self, session: AgentSession) -> Result<()> {
+            self.0.lock().insert(session.id.clone(), session);
+            Ok(())
+        }
+
+        async fn retrieve_session(&self, id: &str) -> Result<Option<AgentSession>> {
+            Ok(self.0.lock().get(id).cloned())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_restores_checkpointed_persona() {
+        use crate::agent::personality::Persona;
+
+        let memory = Arc::new(Sessions::default());
+        let agent = AgentBuilder::new(StubProvider)
+            .persona(Persona::analytical_trader())
+            .with_memory(memory.clone())
+            .session_id("s1")
+            .auto_load_skills(false)
+            .introspection(false)
+            .build()
+            .unwrap();
+        let messages = vec![Message::user("Long SOL?")];
+        let role = |agent: &Agent<StubProvider>| agent.persona().map(|p| p.role);
+
+        agent.checkpoint(&messages, 1, SessionStatus::Thinking).await.unwrap();
+        agent.set_persona(Some(Persona::technical_assistant()));
+        agent.resume("s1").await.unwrap();
+        assert_eq!(role(&agent).as_deref(), Some("Senior Quant Strategist"));
+
+        agent.set_persona(None);
+        agent.checkpoint(&messages, 1, SessionStatus::Thinking).await.unwrap();
+        agent.set_persona(Some(Persona::technical_assistant()));
+        agent.resume("s1").await.unwrap();
+        assert_eq!(role(&agent), None);
+
+        // Sessions saved before personas were recorded leave the current one alone
+        let mut legacy = memory.retrieve_session("s1").await.unwrap().unwrap();
+        legacy.persona_cleared = false;
+        memory.store_session(legacy).await.unwrap();
+        agent.set_persona(Some(Persona::technical_assistant()));
+        agent.resume("s1").await.unwrap();
+        assert_eq!(role(&agent).as_deref(), Some("Senior Technical Assistant"));
+    }
+
+    /// Replies with queued responses in order
+    struct Scripted(parking_lot::Mutex<Vec<Result<StreamingResponse>>>);
+
+    #[async_trait::async_trait]
+    impl Provider for Scripted {
+        async fn stream_completion(
+            &self,
+            _request: crate::agent::provider::ChatRequest,
+        ) -> Result<StreamingResponse> {
+            self.0.lock().remove(0)
+        }
+
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dev_trace_writes_one_redacted_file_per_call() {
+        use crate::agent::streaming::{MockStreamBuilder, Usage};
+
+        let dir = tempfile::tempdir().unwrap();
+        let env = dir.path().join("secrets.env");
+        std::fs::write(&env, "API_KEY=sk-test-0123456789\n").unwrap();
+        let secrets = Arc::new(Secrets::new(Arc::new(
+            crate::infra::secrets::FileSecretProvider::open(env).unwrap(),
+        )));
+        secrets.resolve("API_KEY").unwrap();
+
+        let script = || {
+            Scripted(parking_lot::Mutex::new(vec![
+                Err(Error::ProviderApi("context_length_exceeded".to_string())),
+                Ok(MockStreamBuilder::new()
+                    .tool_call("c1", "calculator", serde_json::json!({ "expr": "2 * 140" }))
+                    .usage(Usage::new(120, 8))
+                    .done()
+                    .build()),
+                Ok(MockStreamBuilder::new().message("Your P&L is 280.").done().build()),
+            ]))
+        };
+        let history = || {
+            vec![
+                Message::user("Hi"),
+                Message::assistant("Noted."),
+                Message::user("What's 2 SOL at 140?"),
+            ]
+        };
+        let traces = dir.path().join("traces");
+        let agent = AgentBuilder::new(script())
+            .preamble("You are a trading agent. Exchange key: sk-test-0123456789")
+            .tool(crate::skills::tool::CalculatorTool::new())
+            .secrets(secrets.clone())
+            .session_id("s1")
+            .debug_trace_dir(&traces)
+            .auto_load_skills(false)
+            .introspection(false)
+            .build()
+            .unwrap();
+        assert_eq!(agent.chat(history()).await.unwrap(), "Your P&L is 280.");
+
+        let mut files: Vec<String> = std::fs::read_dir(traces.join("s1"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files.len(), 6, "{:?}", files);
+        let markdown: Vec<_> = files.iter().filter(|f| f.ends_with(".md")).collect();
+        assert!(markdown[0].starts_with("001_") && !markdown[0].contains("attempt"));
+        assert!(markdown[1].starts_with("001_") && markdown[1].ends_with("_attempt2.md"));
+        assert!(markdown[2].starts_with("002_"));
+
+        let read = |name: &str| std::fs::read_to_string(traces.join("s1").join(name)).unwrap();
+        let overflow = read(markdown[0]);
+        assert!(overflow.contains("| Outcome | error: "));
+        let first = read(markdown[1]);
+        assert!(first.contains("### `system_prompt` · system"));
+        assert!(first.contains("## Tool calls") && first.contains("`calculator` (c1)"));
+        assert!(first.contains("120 prompt / 8 completion / 128 total"));
+        let second = read(markdown[2]);
+        assert!(second.contains("[tool result]") && second.contains("Your P&L is 280."));
+        for file in &files {
+            let text = read(file);
+            assert!(!text.contains("sk-test-0123456789"), "secret leaked into {}", file);
+            assert!(text.contains("[REDACTED:API_KEY]"), "{}", file);
+        }
+        let meta: serde_json::Value = serde_json::from_str(&read(&markdown[2].replace(".md", "_meta.json"))).unwrap();
+        assert_eq!(meta["step"], 2);
+        assert_eq!(meta["response"], "Your P&L is 280.");
+
+        // Without the option nothing is captured
+        let untraced = AgentBuilder::new(script())
+            .tool(crate::skills::tool::CalculatorTool::new())
+            .auto_load_skills(false)
+            .introspection(false)
+            .build()
+            .unwrap();
+        assert!(untraced.dev_trace.is_none());
+        untraced.chat(history()).await.unwrap();
+        assert_eq!(std::fs::read_dir(&traces).unwrap().count(), 1);
+    }
+
+    /// Scripted replies, keeping the messages of every request
+    struct Recording(Scripted, Arc<parking_lot::Mutex<Vec<Vec<Message>>>>);
+
+    #[async_trait::async_trait]
+    impl Provider for Recording {
+        async fn stream_completion(
+            &self,
+            request: crate::agent::provider::ChatRequest,
+        ) -> Result<StreamingResponse> {
+            self.1.lock().push(request.messages.clone());
+            self.0.stream_completion(request).await
+        }
+
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+    }
+
+    /// Returns a long, numbered market report
+    struct Report(std::sync::atomic::AtomicU32);
+
+    #[async_trait::async_trait]
+    impl Tool for Report {
+        fn name(&self) -> String {
+            "market_report".to_string()
+        }
+
+        async fn definition(&self) -> crate::skills::tool::ToolDefinition {
+            crate::skills::tool::ToolDefinition {
+                name: self.name(),
+                description: "Session report for a pair".to_string(),
+                parameters: serde_json::json!({ "type": "object", "properties": {} }),
+                is_verified: true,
+                side_effect_free: true,
+                ..Default::default()
+            }
+        }
+
+        async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+            let n = self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+            Ok(format!("Report {} for SOL/USDC. ", n)
+                + &"Volume was steady with no large prints across the session. ".repeat(60))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_output_aging_shrinks_context_and_recalls_exact_text() {
+        use crate::agent::message::ContentPart;
+        use crate::agent::streaming::MockStreamBuilder;
+
+        let run = |aging: Option<ToolAgingConfig>| async move {
+            let mut script: Vec<Result<StreamingResponse>> = ["c1", "c2", "c3"]
+                .iter()
+                .map(|id| Ok(MockStreamBuilder::new().tool_call(*id, "market_report", serde_json::json!({})).done().build()))
+                .collect();
+            // Only aging registers the recall tool
+            if aging.is_some() {
+                script.push(Ok(MockStreamBuilder::new()
+                    .tool_call("c4", "recall_tool_output", serde_json::json!({ "call_id": "c1" }))
+                    .done()
+                    .build()));
+            }
+            script.push(Ok(MockStreamBuilder::new().message("Done.").done().build()));
+            let seen = Arc::new(parking_lot::Mutex::new(Vec::new()));
+            let mut builder = AgentBuilder::new(Recording(Scripted(parking_lot::Mutex::new(script)), seen.clone()))
+                .tool(Report(std::sync::atomic::AtomicU32::new(0)))
+                .auto_load_skills(false)
+                .introspection(false);
+            if let Some(config) = aging {
+                builder = builder.tool_output_aging(config);
+            }
+            let agent = builder.build().unwrap();
+            assert_eq!(agent.chat(vec![Message::user("How did SOL trade?")]).await.unwrap(), "Done.");
+            let requests = seen.lock().clone();
+            requests
+        };
+        let results = |messages: &[Message]| -> Vec<(String, String)> {
+            messages
+                .iter()
+                .filter_map(|m| match &m.content {
+                    Content::Parts(parts) => parts.iter().find_map(|p| match p {
+                        ContentPart::ToolResult { tool_call_id, content, .. } => Some((tool_call_id.clone(), content.clone())),
+                        _ => None,
+                    }),
+                    Content::Text(_) => None,
+                })
+                .collect()
+        };
+        let bpe = tiktoken_rs::cl100k_base().unwrap();
+        let tokens = |messages: &[Message]| -> usize {
+            messages.iter().map(|m| bpe.encode_with_special_tokens(&serde_json::to_string(m).unwrap()).len()).sum()
+        };
+
+        let plain = run(None).await;
+        let aged = run(Some(ToolAgingConfig::default().keep_recent(1).after_steps(Some(1)))).await;
+
+        // Fourth request carries three reports; the two older ones are stubbed
+        let (before, after) = (tokens(&plain[3]), tokens(&aged[3]));
+        assert!(after * 2 < before, "aged {} vs plain {} tokens", after, before);
+        let stubbed = results(&aged[3]);
+        assert_eq!(stubbed.iter().map(|(id, _)| id.as_str()).collect::<Vec<_>>(), ["c1", "c2", "c3"]);
+        assert!(stubbed[0].1.starts_with("[market_report output elided: Report 1 for SOL/USDC."));
+        assert!(stubbed[0].1.contains("recall_tool_output"));
+        assert_eq!(stubbed[2], results(&plain[3])[2]);
+
+        // Recall puts the exact original back into the context
+        let original = &results(&plain[3])[0].1;
+        let recalled = results(&aged[4]);
+        assert_eq!(recalled.last().unwrap(), &("c4".to_string(), original.clone()));
+    }
+
+    /// Memory that keeps `(user_id, collection, content)` of stored knowledge
+    #[derive(Default)]
+    struct Knowledge(parking_lot::Mutex<Vec<(String, String, String)>>);
+
+    #[async_trait::async_trait]
+    impl Memory for Knowledge {
+        async fn store(&self, _: &str, _: Option<&str>, _: Message) -> Result<()> {
+            Ok(())
+        }
+
+        async fn retrieve(&self, _: &str, _: Option<&str>, _: usize) -> Vec<Message> {
+            Vec::new()
+        }
+
+        async fn clear(&self, _: &str, _: Option<&str>) -> Result<()> {
+            Ok(())
+        }
+
+        async fn undo(&self, _: &str, _: Option<&str>) -> Result<Option<Message>> {
+            Ok(None)
+        }
+
+        async fn store_knowledge(&self, user_id: &str, _: Option<&str>, _: &str, content: &str, collection: &str) -> Result<()> {
+            self.0.lock().push((user_id.to_string(), collection.to_string(), content.to_string()));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chat_reduces_oversized_tool_output() {
+        use crate::agent::streaming::MockStreamBuilder;
+
+        let script = vec![
+            Ok(MockStreamBuilder::new().tool_call("c1", "market_report", serde_json::json!({})).done().build()),
+            Ok(MockStreamBuilder::new().message("Done.").done().build()),
+        ];
+        let memory = Arc::new(Knowledge::default());
+        let agent = AgentBuilder::new(Scripted(parking_lot::Mutex::new(script)))
+            .tool(Report(std::sync::atomic::AtomicU32::new(0)))
+            .max_tool_output_chars(1000)
+            .with_memory(memory.clone())
+            .user_id("trader-7")
+            .session_id("s1")
+            .auto_load_skills(false)
+            .introspection(false)
+            .build()
+            .unwrap();
+        let mut events = agent.subscribe();
+        assert_eq!(agent.chat(vec![Message::user("How did SOL trade?")]).await.unwrap(), "Done.");
+
+        let mut shown = None;
+        while let Ok(event) = events.try_recv() {
+            if let AgentEvent::ToolResult { output, .. } = event {
+                shown = Some(output);
+            }
+        }
+        let shown = shown.unwrap();
+        assert!(shown.contains("Output reduced from"), "{}", shown);
+        assert!(shown.contains(TOOL_OUTPUT_COLLECTION));
+
+        let stored = memory.0.lock().clone();
+        assert_eq!(stored.len(), 1);
+        let (user_id, collection, content) = &stored[0];
+        assert_eq!((user_id.as_str(), collection.as_str()), ("trader-7", TOOL_OUTPUT_COLLECTION));
+        assert!(content.starts_with("Report 1 for SOL/USDC.") && content.len() > 1000);
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_is_streamed_but_never_kept() {
+        use crate::agent::streaming::MockStreamBuilder;
+
+        let script = vec![
+            Ok(MockStreamBuilder::new()
+                .thought("SOL broke resistance,")
+                .message("Hold")
+                .thought(" volume confirms.")
+                .message(" SOL.")
+                .done()
+                .build()),
+            Ok(MockStreamBuilder::new().message("Sell half at $180.").done().build()),
+        ];
+        let seen = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let agent = AgentBuilder::new(Recording(Scripted(parking_lot::Mutex::new(script)), seen.clone()))
+            .max_reasoning_tokens(1024)
+            .auto_load_skills(false)
+            .introspection(false)
+            .build()
+            .unwrap();
+        assert_eq!(agent.config.max_reasoning_tokens, Some(1024));
+        let mut events = agent.subscribe();
+
+        let reply = agent.chat(vec![Message::user("SOL?")]).await.unwrap();
+        assert_eq!(reply, "Hold SOL.");
+        let mut reasoning = String::new();
+        while let Ok(event) = events.try_recv() {
+            match event {
+                AgentEvent::Reasoning { content } => reasoning.push_str(&content),
+                AgentEvent::Response { content, .. } => assert_eq!(content, "Hold SOL."),
+                _ => {}
+            }
+        }
+        assert_eq!(reasoning, "SOL broke resistance, volume confirms.");
+
+        // The next turn sends the reply back without its reasoning
+        let history = vec![Message::user("SOL?"), Message::assistant(reply), Message::user("Exit plan?")];
+        agent.chat(history).await.unwrap();
+        let sent = serde_json::to_string(&seen.lock()[1]).unwrap();
+        assert!(sent.contains("Hold SOL.") && !sent.contains("resistance"), "{}", sent);
+    }
+
+    #[tokio::test]
+    async fn test_parent_usage_spends_the_subagent_budget() {
+        use crate::agent::streaming::{MockStreamBuilder, Usage};
+
+        let agent = AgentBuilder::new(Scripted(parking_lot::Mutex::new(vec![Ok(MockStreamBuilder::new()
+            .message("Done.")
+            .usage(Usage::new(30, 10))
+            .done()
+            .build())])))
+            .with_subagents(SubagentConfig { token_budget: Some(100), ..Default::default() })
+            .auto_load_skills(false)
+            .introspection(false)
+            .build()
+            .unwrap();
+        agent.prompt("Hi").await.unwrap();
+
+        let budget = agent.token_budget().unwrap();
+        assert_eq!(budget.used(), 40);
+        assert_eq!(budget.remaining(), Some(60));
+    }
 }
@@ -0,0 +1,431 @@
+//! Developer-mode conversation traces
+//!
+//! **Not for production.** Traces hold full prompts, tool output and
+//! responses. Only values known to the agent's [`Secrets`] are redacted, and
+//! files are written synchronously on the agent loop. Use them for local
+//! prompt iteration.
+//!
+//! With `AgentBuilder::debug_trace_dir` set, every provider call is written
+//! as a markdown file meant for reading in an editor:
+//! `{dir}/{session}/{step:03}_{timestamp}.md`. A `_meta.json` file with the
+//! same name holds the machine-readable version. Each file contains:
+//! - the assembled context, attributed per source;
+//! - the tools offered;
+//! - the reassembled streamed response and its tool calls;
+//! - timing and usage.
+//!
+//! Retried calls within a step (e.g. after a context overflow) get an
+//! `_attempt{n}` suffix. Only the newest [`DevTracer::max_traces`] traces
+//! are kept. When the option is unset, no trace data is captured or
+//! formatted.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::debug;
+
+use crate::agent::context::RenderedContext;
+use crate::agent::message::{Message, Role};
+use crate::agent::provider::ChatRequest;
+use crate::agent::streaming::{ResponseMetadata, StreamingChoice, Usage};
+use crate::error::{Error, Result};
+use crate::infra::secrets::Secrets;
+use crate::skills::tool::ToolDefinition;
+
+/// Traces kept by default
+pub const DEFAULT_MAX_TRACES: usize = 200;
+
+/// Tool results longer than this are truncated in the markdown (not the JSON)
+pub const TOOL_RESULT_CHARS: usize = 2000;
+
+/// Writes one trace per provider call; see the module docs
+#[derive(Debug)]
+pub struct DevTracer {
+    dir: PathBuf,
+    max_traces: usize,
+    secrets: Option<Arc<Secrets>>,
+}
+
+/// One tool call the model made
+#[derive(Debug, Clone, Serialize)]
+pub struct TracedToolCall {
+    /// Call ID
+    pub id: String,
+    /// Tool name
+    pub name: String,
+    /// Arguments as sent by the model
+    pub arguments: serde_json::Value,
+}
+
+/// A provider call in progress; feed it chunks, then hand it to [`DevTracer::finish`]
+#[derive(Debug, Serialize)]
+pub struct StepTrace {
+    session: String,
+    step: usize,
+    attempt: usize,
+    model: String,
+    started_at: DateTime<Utc>,
+    #[serde(skip)]
+    started: Instant,
+    context: Option<RenderedContext>,
+    messages: Vec<Message>,
+    tools: Vec<ToolDefinition>,
+    response: String,
+    thoughts: String,
+    tool_calls: Vec<TracedToolCall>,
+    usage: Option<Usage>,
+    /// Model that actually answered, when the provider reported it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    served_by: Option<ResponseMetadata>,
+}
+
+impl StepTrace {
+    /// Record a streamed chunk
+    pub fn record(&mut self, chunk: &StreamingChoice) {
+        match chunk {
+            StreamingChoice::Message(text) => self.response.push_str(text),
+            StreamingChoice::Thought(text) => self.thoughts.push_str(text),
+            StreamingChoice::ToolCall {
+                id,
+                name,
+                arguments,
+            } => self.tool_calls.push(TracedToolCall {
+                id: id.clone(),
+                name: name.clone(),
+                arguments: arguments.clone(),
+            }),
+            StreamingChoice::ParallelToolCalls(map) => {
+                let mut sorted: Vec<_> = map.iter().collect();
+                sorted.sort_by_key(|(k, _)| **k);
+                self.tool_calls
+                    .extend(sorted.into_iter().map(|(_, tc)| TracedToolCall {
+                        id: tc.id.clone(),
+                        name: tc.name.clone(),
+                        arguments: tc.arguments.clone(),
+                    }));
+            }
+            StreamingChoice::Usage(usage) => self.usage = Some(usage.clone()),
+            StreamingChoice::Metadata(metadata) => self.served_by = Some(metadata.clone()),
+            StreamingChoice::Done => {}
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TraceMeta<'a> {
+    #[serde(flatten)]
+    trace: &'a StepTrace,
+    duration_ms: u128,
+    error: Option<String>,
+}
+
+impl DevTracer {
+    /// Trace into `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_traces: DEFAULT_MAX_TRACES,
+            secrets: None,
+        }
+    }
+
+    /// Keep only the newest `max` traces across all sessions
+    pub fn max_traces(mut self, max: usize) -> Self {
+        self.max_traces = max;
+        self
+    }
+
+    /// Redact the values `secrets` has resolved
+    pub fn with_secrets(mut self, secrets: Option<Arc<Secrets>>) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// Trace directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Start tracing a provider call
+    pub fn begin(
+        &self,
+        session: &str,
+        step: usize,
+        attempt: usize,
+        request: &ChatRequest,
+        context: Option<RenderedContext>,
+    ) -> StepTrace {
+        StepTrace {
+            session: session.to_string(),
+            step,
+            attempt,
+            model: request.model.clone(),
+            started_at: Utc::now(),
+            started: Instant::now(),
+            context,
+            messages: request.messages.clone(),
+            tools: request.tools.clone(),
+            response: String::new(),
+            thoughts: String::new(),
+            tool_calls: Vec::new(),
+            usage: None,
+            served_by: None,
+        }
+    }
+
+    /// Write `trace` (with the error that ended it, if any) and rotate old traces
+    ///
+    /// Returns the markdown file's path.
+    pub fn finish(&self, trace: StepTrace, error: Option<&Error>) -> Result<PathBuf> {
+        let session_dir = self.dir.join(sanitize(&trace.session));
+        std::fs::create_dir_all(&session_dir)?;
+        let mut stem = format!(
+            "{:03}_{}",
+            trace.step,
+            trace.started_at.format("%Y%m%dT%H%M%S%3fZ")
+        );
+        if trace.attempt > 1 {
+            stem.push_str(&format!("_attempt{}", trace.attempt));
+        }
+
+        let meta = TraceMeta {
+            trace: &trace,
+            duration_ms: trace.started.elapsed().as_millis(),
+            error: error.map(|e| e.to_string()),
+        };
+        let markdown = self.redact(render_markdown(&meta));
+        let json = self.redact(serde_json::to_string_pretty(&meta)?);
+
+        let path = session_dir.join(format!("{}.md", stem));
+        std::fs::write(&path, markdown)?;
+        std::fs::write(session_dir.join(format!("{}_meta.json", stem)), json)?;
+        self.rotate()?;
+        debug!("Wrote dev trace {}", path.display());
+        Ok(path)
+    }
+
+    fn redact(&self, text: String) -> String {
+        match &self.secrets {
+            Some(secrets) => secrets.redact(&text),
+            None => text,
+        }
+    }
+
+    /// Delete the oldest traces beyond the limit
+    fn rotate(&self) -> Result<()> {
+        let mut traces = Vec::new();
+        for session in std::fs::read_dir(&self.dir)? {
+            let session = session?.path();
+            if !session.is_dir() {
+                continue;
+            }
+            for entry in std::fs::read_dir(&session)? {
+                let path = entry?.path();
+                let Some(stem) = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.strip_suffix(".md"))
+                else {
+                    continue;
+                };
+                // Order by timestamp, then step: step numbers restart with every chat
+                let timestamp = stem.split('_').nth(1).unwrap_or_default().to_string();
+                traces.push((timestamp, stem.to_string(), session.clone()));
+            }
+        }
+        if traces.len() <= self.max_traces {
+            return Ok(());
+        }
+        traces.sort();
+        let excess = traces.len() - self.max_traces;
+        for (_, stem, session) in traces.into_iter().take(excess) {
+            std::fs::remove_file(session.join(format!("{}.md", stem)))?;
+            match std::fs::remove_file(session.join(format!("{}_meta.json", stem))) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn sanitize(session: &str) -> String {
+    session
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Fenced block that survives backticks in `text`
+fn fenced(text: &str, lang: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}\n", fence, lang, text, fence)
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((cut, _)) => format!(
+            "{}\n… ({} more chars)",
+            &text[..cut],
+            text[cut..].chars().count()
+        ),
+        None => text.to_string(),
+    }
+}
+
+fn render_markdown(meta: &TraceMeta<'_>) -> String {
+    let trace = meta.trace;
+    let mut out = format!("# Step {} · session `{}`", trace.step, trace.session);
+    if trace.attempt > 1 {
+        out.push_str(&format!(" · attempt {}", trace.attempt));
+    }
+    out.push_str("\n\n> Developer trace, not for production use.\n\n");
+
+    let usage = trace.usage.as_ref().map_or("n/a".to_string(), |u| {
+        format!(
+            "{} prompt / {} completion / {} total",
+            u.prompt_tokens, u.completion_tokens, u.total_tokens
+        )
+    });
+    let outcome = match (&meta.error, trace.tool_calls.len()) {
+        (Some(e), _) => format!("error: {}", e),
+        (None, 0) => "final response".to_string(),
+        (None, n) => format!("{} tool call{}", n, if n == 1 { "" } else { "s" }),
+    };
+    out.push_str("| | |\n|---|---|\n");
+    for (key, value) in [
+        ("Model", trace.model.clone()),
+        ("Started", trace.started_at.to_rfc3339()),
+        ("Duration", format!("{} ms", meta.duration_ms)),
+        ("Tokens", usage),
+        ("Outcome", outcome),
+    ] {
+        out.push_str(&format!("| {} | {} |\n", key, value.replace('|', "\\|")));
+    }
+
+    match &trace.context {
+        Some(context) => {
+            out.push_str(&format!(
+                "\n## Context ({} sections, {} tokens)\n",
+                context.sections.len(),
+                context.total_tokens
+            ));
+            for section in &context.sections {
+                let text = if section.role == Role::Tool {
+                    truncate(&section.text, TOOL_RESULT_CHARS)
+                } else {
+                    section.text.clone()
+                };
+                let attribution = match context.prompt_attribution() {
+                    Some(line) if section.source == "system_prompt" => format!("{}\n\n", line),
+                    _ => String::new(),
+                };
+                out.push_str(&format!(
+                    "\n### `{}` · {} · {} tokens\n\n{}{}",
+                    section.source,
+                    section.role.as_str(),
+                    section.tokens,
+                    attribution,
+                    fenced(&text, "text")
+                ));
+            }
+        }
+        None => {
+            out.push_str(&format!("\n## Messages ({})\n", trace.messages.len()));
+            for (i, message) in trace.messages.iter().enumerate() {
+                let mut text = message.content.as_text();
+                if message.role == Role::Tool {
+                    text = truncate(&text, TOOL_RESULT_CHARS);
+                }
+                out.push_str(&format!(
+                    "\n### [{}] {}\n\n{}",
+                    i,
+                    message.role.as_str(),
+                    fenced(&text, "text")
+                ));
+            }
+        }
+    }
+
+    out.push_str(&format!(
+        "\n## Tools ({})\n\n<details>\n<summary>Definitions</summary>\n\n{}\n</details>\n",
+        trace.tools.len(),
+        fenced(
+            &serde_json::to_string_pretty(&trace.tools).unwrap_or_default(),
+            "json"
+        )
+    ));
+
+    out.push_str(&format!(
+        "\n## Response\n\n{}",
+        fenced(&trace.response, "text")
+    ));
+    if !trace.thoughts.is_empty() {
+        out.push_str(&format!(
+            "\n### Thoughts\n\n{}",
+            fenced(&trace.thoughts, "text")
+        ));
+    }
+    if !trace.tool_calls.is_empty() {
+        out.push_str("\n## Tool calls\n");
+        for call in &trace.tool_calls {
+            out.push_str(&format!(
+                "\n### `{}` ({})\n\n{}",
+                call.name,
+                call.id,
+                fenced(&call.arguments.to_string(), "json")
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_keeps_newest_traces_across_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let tracer = DevTracer::new(dir.path()).max_traces(3);
+        let request = ChatRequest::default();
+        for (session, step) in [("a", 1), ("a", 2), ("b", 1), ("a", 3), ("b", 2)] {
+            let mut trace = tracer.begin(session, step, 1, &request, None);
+            trace.record(&StreamingChoice::Message(format!("{} {}", session, step)));
+            tracer.finish(trace, None).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        let mut kept = Vec::new();
+        for session in ["a", "b"] {
+            for entry in std::fs::read_dir(dir.path().join(session)).unwrap() {
+                let name = entry.unwrap().file_name().into_string().unwrap();
+                if let Some(stem) = name.strip_suffix(".md") {
+                    assert!(dir
+                        .path()
+                        .join(session)
+                        .join(format!("{}_meta.json", stem))
+                        .exists());
+                    kept.push(format!("{}/{}", session, &stem[..3]));
+                }
+            }
+        }
+        kept.sort();
+        assert_eq!(kept, ["a/003", "b/001", "b/002"]);
+        assert_eq!(std::fs::read_dir(dir.path().join("a")).unwrap().count(), 2);
+    }
+}
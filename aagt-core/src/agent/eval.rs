@@ -0,0 +1,284 @@
+//! Offline evaluation of prompts and sampling parameters
+//!
+//! An [`EvalSuite`] is a fixed list of cases, each a prompt plus the phrases a
+//! good answer must contain. [`EvalRunner`] sends every case straight to a
+//! [`Provider`] with one [`EvalConfig`] and scores the replies into an
+//! [`EvalReport`]. See [`sweep`] for running a suite across parameter grids.
+
+pub mod sweep;
+
+pub use sweep::{
+    BestCell, CellResult, SweepCell, SweepDimension, SweepOverride, SweepReport, SweepSample,
+    SweepSpec,
+};
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::message::Message;
+use crate::agent::provider::{with_latency_tag, ChatRequest, Provider};
+use crate::error::Result;
+
+/// One evaluation case
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalCase {
+    /// Unique name, used as the row label in reports
+    pub name: String,
+    /// User prompt
+    pub prompt: String,
+    /// Phrases the reply must contain (case-insensitive); score is the fraction found
+    pub must_contain: Vec<String>,
+}
+
+impl EvalCase {
+    /// Create a case
+    pub fn new(name: impl Into<String>, prompt: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            prompt: prompt.into(),
+            must_contain: Vec::new(),
+        }
+    }
+
+    /// Require `phrase` in the reply
+    pub fn expect(mut self, phrase: impl Into<String>) -> Self {
+        self.must_contain.push(phrase.into());
+        self
+    }
+
+    /// Score a reply in [0, 1]
+    pub fn score(&self, output: &str) -> f64 {
+        if self.must_contain.is_empty() {
+            return 1.0;
+        }
+        let output = output.to_lowercase();
+        let found = self
+            .must_contain
+            .iter()
+            .filter(|p| output.contains(&p.to_lowercase()))
+            .count();
+        found as f64 / self.must_contain.len() as f64
+    }
+}
+
+/// A named list of cases shared by every run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EvalSuite {
+    /// Suite name
+    pub name: String,
+    /// Cases in report order
+    pub cases: Vec<EvalCase>,
+}
+
+impl EvalSuite {
+    /// Create an empty suite
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            cases: Vec::new(),
+        }
+    }
+
+    /// Add a case
+    pub fn case(mut self, case: EvalCase) -> Self {
+        self.cases.push(case);
+        self
+    }
+}
+
+/// Model and sampling parameters for a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvalConfig {
+    /// Model name
+    pub model: String,
+    /// System prompt
+    pub system_prompt: Option<String>,
+    /// Prompt template version, for labelling
+    pub prompt_version: Option<String>,
+    /// Sampling temperature
+    pub temperature: Option<f64>,
+    /// Nucleus sampling, sent as the `top_p` extra parameter
+    pub top_p: Option<f64>,
+    /// Max tokens per reply
+    pub max_tokens: Option<u64>,
+}
+
+impl Default for EvalConfig {
+    fn default() -> Self {
+        Self {
+            model: "gpt-4o".to_string(),
+            system_prompt: None,
+            prompt_version: None,
+            temperature: Some(0.7),
+            top_p: None,
+            max_tokens: Some(1024),
+        }
+    }
+}
+
+impl EvalConfig {
+    fn request(&self, case: &EvalCase) -> ChatRequest {
+        ChatRequest {
+            model: self.model.clone(),
+            system_prompt: self.system_prompt.clone(),
+            messages: vec![Message::user(case.prompt.clone())],
+            tools: Vec::new(),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            max_reasoning_tokens: None,
+            extra_params: self.top_p.map(|p| serde_json::json!({ "top_p": p })),
+            headers: Default::default(),
+        }
+    }
+}
+
+/// Result of one case
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseResult {
+    /// Case name
+    pub name: String,
+    /// Score in [0, 1] (0 on error)
+    pub score: f64,
+    /// Model reply
+    pub output: String,
+    /// Wall-clock time for the reply
+    pub latency_ms: u64,
+    /// Provider error, if the call failed
+    pub error: Option<String>,
+}
+
+/// Aggregate metric over a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvalMetric {
+    /// Mean case score
+    MeanScore,
+    /// Fraction of cases scoring 1.0
+    PassRate,
+    /// Mean latency in milliseconds (lower is better)
+    MeanLatencyMs,
+}
+
+impl EvalMetric {
+    /// Whether larger values are better
+    pub fn higher_is_better(self) -> bool {
+        !matches!(self, Self::MeanLatencyMs)
+    }
+}
+
+/// Results of running a suite once with one config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalReport {
+    /// Suite name
+    pub suite: String,
+    /// Config the suite ran with
+    pub config: EvalConfig,
+    /// Per-case results in suite order
+    pub cases: Vec<CaseResult>,
+}
+
+impl EvalReport {
+    /// Aggregate `metric` over all cases
+    pub fn metric(&self, metric: EvalMetric) -> f64 {
+        if self.cases.is_empty() {
+            return 0.0;
+        }
+        let n = self.cases.len() as f64;
+        match metric {
+            EvalMetric::MeanScore => self.cases.iter().map(|c| c.score).sum::<f64>() / n,
+            EvalMetric::PassRate => self.cases.iter().filter(|c| c.score >= 1.0).count() as f64 / n,
+            EvalMetric::MeanLatencyMs => {
+                self.cases.iter().map(|c| c.latency_ms as f64).sum::<f64>() / n
+            }
+        }
+    }
+
+    /// Result for the case named `name`
+    pub fn case(&self, name: &str) -> Option<&CaseResult> {
+        self.cases.iter().find(|c| c.name == name)
+    }
+}
+
+/// Runs suites against a provider
+pub struct EvalRunner {
+    provider: Arc<dyn Provider>,
+    config: EvalConfig,
+    tag: Option<String>,
+}
+
+impl EvalRunner {
+    /// Run against `provider` with the default config
+    pub fn new(provider: Arc<dyn Provider>) -> Self {
+        Self {
+            provider,
+            config: EvalConfig::default(),
+            tag: None,
+        }
+    }
+
+    /// Base config; sweeps override individual fields of it
+    pub fn with_config(mut self, config: EvalConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Tag provider calls for [`LatencyProvider`](crate::agent::provider::LatencyProvider) reports
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    /// The base config
+    pub fn config(&self) -> &EvalConfig {
+        &self.config
+    }
+
+    /// Run `suite` with the base config
+    pub async fn run(&self, suite: &EvalSuite) -> Result<EvalReport> {
+        self.run_with(suite, &self.config).await
+    }
+
+    /// Run `suite` with `config`
+    ///
+    /// Provider errors are recorded per case (score 0) rather than aborting the run.
+    pub async fn run_with(&self, suite: &EvalSuite, config: &EvalConfig) -> Result<EvalReport> {
+        let mut cases = Vec::with_capacity(suite.cases.len());
+        for case in &suite.cases {
+            let started = Instant::now();
+            let call = async {
+                match self.provider.stream_completion(config.request(case)).await {
+                    Ok(stream) => stream.collect_text().await,
+                    Err(e) => Err(e),
+                }
+            };
+            let reply = match &self.tag {
+                Some(tag) => with_latency_tag(tag.clone(), call).await,
+                None => call.await,
+            };
+            let latency_ms = started.elapsed().as_millis() as u64;
+            cases.push(match reply {
+                Ok(output) => CaseResult {
+                    name: case.name.clone(),
+                    score: case.score(&output),
+                    output,
+                    latency_ms,
+                    error: None,
+                },
+                Err(e) => CaseResult {
+                    name: case.name.clone(),
+                    score: 0.0,
+                    output: String::new(),
+                    latency_ms,
+                    error: Some(e.to_string()),
+                },
+            });
+        }
+        Ok(EvalReport {
+            suite: suite.name.clone(),
+            config: config.clone(),
+            cases,
+        })
+    }
+}
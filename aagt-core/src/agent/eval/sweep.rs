@@ -0,0 +1,748 @@
+//! Parameter sweeps over an [`EvalSuite`]
+//!
+//! A [`SweepSpec`] lists dimensions (models, temperatures, top_p values,
+//! prompt template versions); [`EvalRunner::sweep`] runs the suite once per
+//! cell of their cross product, optionally several times per cell, and
+//! collects a [`SweepReport`] that renders as a comparison matrix.
+//!
+//! Cells are always produced in the same order, and when a progress file is
+//! given each finished cell is appended to it, so an interrupted sweep picks
+//! up where it stopped instead of paying for the completed cells again.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use super::{EvalConfig, EvalMetric, EvalReport, EvalRunner, EvalSuite};
+use crate::error::{Error, Result};
+
+/// One value along a sweep dimension
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SweepOverride {
+    /// Use another model
+    Model { model: String },
+    /// Use another temperature
+    Temperature { value: f64 },
+    /// Use another top_p
+    TopP { value: f64 },
+    /// Use another system prompt, labelled by its template version
+    PromptTemplate {
+        version: String,
+        system_prompt: String,
+    },
+}
+
+impl SweepOverride {
+    /// Apply the override to `config`
+    pub fn apply(&self, config: &mut EvalConfig) {
+        match self {
+            Self::Model { model } => config.model = model.clone(),
+            Self::Temperature { value } => config.temperature = Some(*value),
+            Self::TopP { value } => config.top_p = Some(*value),
+            Self::PromptTemplate {
+                version,
+                system_prompt,
+            } => {
+                config.prompt_version = Some(version.clone());
+                config.system_prompt = Some(system_prompt.clone());
+            }
+        }
+    }
+
+    /// Short value label used in cell IDs
+    pub fn label(&self) -> String {
+        match self {
+            Self::Model { model } => model.clone(),
+            Self::Temperature { value } | Self::TopP { value } => value.to_string(),
+            Self::PromptTemplate { version, .. } => version.clone(),
+        }
+    }
+}
+
+/// A named list of overrides to sweep over
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SweepDimension {
+    /// Dimension name, used in cell IDs
+    pub name: String,
+    /// Values in sweep order
+    pub values: Vec<SweepOverride>,
+}
+
+impl SweepDimension {
+    /// Create a dimension from arbitrary overrides
+    pub fn new(name: impl Into<String>, values: Vec<SweepOverride>) -> Self {
+        Self {
+            name: name.into(),
+            values,
+        }
+    }
+
+    /// Sweep over models
+    pub fn models<S: Into<String>>(models: impl IntoIterator<Item = S>) -> Self {
+        Self::new(
+            "model",
+            models
+                .into_iter()
+                .map(|m| SweepOverride::Model { model: m.into() })
+                .collect(),
+        )
+    }
+
+    /// Sweep over temperatures
+    pub fn temperatures(values: impl IntoIterator<Item = f64>) -> Self {
+        Self::new(
+            "temperature",
+            values
+                .into_iter()
+                .map(|value| SweepOverride::Temperature { value })
+                .collect(),
+        )
+    }
+
+    /// Sweep over top_p values
+    pub fn top_p(values: impl IntoIterator<Item = f64>) -> Self {
+        Self::new(
+            "top_p",
+            values
+                .into_iter()
+                .map(|value| SweepOverride::TopP { value })
+                .collect(),
+        )
+    }
+
+    /// Sweep over `(version, system_prompt)` templates
+    pub fn templates<V: Into<String>, P: Into<String>>(
+        templates: impl IntoIterator<Item = (V, P)>,
+    ) -> Self {
+        Self::new(
+            "prompt",
+            templates
+                .into_iter()
+                .map(|(version, prompt)| SweepOverride::PromptTemplate {
+                    version: version.into(),
+                    system_prompt: prompt.into(),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// One point of a sweep grid
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepCell {
+    /// Stable ID, e.g. `model=gpt-4o, temperature=0.2`
+    pub id: String,
+    /// Overrides applied on top of the runner's base config
+    pub overrides: Vec<SweepOverride>,
+}
+
+impl SweepCell {
+    /// Config for this cell
+    pub fn config(&self, base: &EvalConfig) -> EvalConfig {
+        let mut config = base.clone();
+        for o in &self.overrides {
+            o.apply(&mut config);
+        }
+        config
+    }
+}
+
+/// Random subset of a large grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SweepSample {
+    /// Cells to keep
+    pub count: usize,
+    /// RNG seed, so the same subset is chosen on resume
+    pub seed: u64,
+}
+
+/// What to sweep and how
+#[derive(Debug, Clone)]
+pub struct SweepSpec {
+    /// Dimensions; the first one varies slowest
+    pub dimensions: Vec<SweepDimension>,
+    /// Largest grid that may run (default 64)
+    pub max_cells: usize,
+    /// Run a seeded random subset of the grid instead of all of it
+    pub sample: Option<SweepSample>,
+    /// Runs per cell (default 1); more than one enables noise estimates
+    pub repeats: usize,
+}
+
+impl Default for SweepSpec {
+    fn default() -> Self {
+        Self {
+            dimensions: Vec::new(),
+            max_cells: 64,
+            sample: None,
+            repeats: 1,
+        }
+    }
+}
+
+impl SweepSpec {
+    /// Create an empty spec
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a dimension
+    pub fn dimension(mut self, dimension: SweepDimension) -> Self {
+        self.dimensions.push(dimension);
+        self
+    }
+
+    /// Set the cell cap
+    pub fn max_cells(mut self, max_cells: usize) -> Self {
+        self.max_cells = max_cells;
+        self
+    }
+
+    /// Run `count` cells chosen at random with `seed`
+    pub fn sample(mut self, count: usize, seed: u64) -> Self {
+        self.sample = Some(SweepSample { count, seed });
+        self
+    }
+
+    /// Run each cell `repeats` times
+    pub fn repeats(mut self, repeats: usize) -> Self {
+        self.repeats = repeats;
+        self
+    }
+
+    /// Size of the full cross product
+    pub fn grid_size(&self) -> usize {
+        self.dimensions.iter().map(|d| d.values.len()).product()
+    }
+
+    /// Expand into cells, in grid order
+    ///
+    /// Fails if the grid exceeds `max_cells` and no sample is configured, or
+    /// if the sample itself exceeds the cap. Sampled cells keep grid order.
+    pub fn cells(&self) -> Result<Vec<SweepCell>> {
+        if self.dimensions.iter().any(|d| d.values.is_empty()) {
+            return Err(Error::agent_config("Sweep dimension has no values"));
+        }
+        let total = self.grid_size();
+        let indices: Vec<usize> = match self.sample {
+            Some(sample) => {
+                if sample.count > self.max_cells {
+                    return Err(Error::agent_config(format!(
+                        "Sweep sample of {} exceeds the cap of {} cells",
+                        sample.count, self.max_cells
+                    )));
+                }
+                let mut rng = StdRng::seed_from_u64(sample.seed);
+                let mut picked =
+                    rand::seq::index::sample(&mut rng, total, sample.count.min(total)).into_vec();
+                picked.sort_unstable();
+                picked
+            }
+            None => {
+                if total > self.max_cells {
+                    return Err(Error::agent_config(format!(
+                        "Sweep grid has {} cells, over the cap of {}; raise max_cells or use sample mode",
+                        total, self.max_cells
+                    )));
+                }
+                (0..total).collect()
+            }
+        };
+        Ok(indices.into_iter().map(|i| self.cell_at(i)).collect())
+    }
+
+    fn cell_at(&self, mut index: usize) -> SweepCell {
+        let mut picks = vec![0; self.dimensions.len()];
+        for (pick, dim) in picks.iter_mut().zip(&self.dimensions).rev() {
+            *pick = index % dim.values.len();
+            index /= dim.values.len();
+        }
+        let overrides: Vec<SweepOverride> = picks
+            .iter()
+            .zip(&self.dimensions)
+            .map(|(&i, dim)| dim.values[i].clone())
+            .collect();
+        let id = self
+            .dimensions
+            .iter()
+            .zip(&overrides)
+            .map(|(dim, o)| format!("{}={}", dim.name, o.label()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        SweepCell { id, overrides }
+    }
+}
+
+/// All runs of one cell
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CellResult {
+    /// Cell ID
+    pub cell_id: String,
+    /// One report per repeat
+    pub runs: Vec<EvalReport>,
+}
+
+impl CellResult {
+    /// Mean of `metric` across repeats
+    pub fn mean(&self, metric: EvalMetric) -> f64 {
+        mean(&self.values(metric))
+    }
+
+    /// Sample standard deviation of `metric` across repeats (0 for one run)
+    pub fn stdev(&self, metric: EvalMetric) -> f64 {
+        stdev(&self.values(metric))
+    }
+
+    fn values(&self, metric: EvalMetric) -> Vec<f64> {
+        self.runs.iter().map(|r| r.metric(metric)).collect()
+    }
+
+    fn case_values(&self, case: &str, metric: EvalMetric) -> Vec<f64> {
+        self.runs
+            .iter()
+            .filter_map(|r| r.case(case))
+            .map(|c| match metric {
+                EvalMetric::MeanScore => c.score,
+                EvalMetric::PassRate => f64::from(u8::from(c.score >= 1.0)),
+                EvalMetric::MeanLatencyMs => c.latency_ms as f64,
+            })
+            .collect()
+    }
+}
+
+/// The winning cell for a metric
+#[derive(Debug, Clone, PartialEq)]
+pub struct BestCell {
+    /// Cell ID
+    pub cell_id: String,
+    /// Mean metric value
+    pub value: f64,
+    /// Second-best cell and its value
+    pub runner_up: Option<(String, f64)>,
+    /// Whether the lead over the runner-up is smaller than the spread
+    /// observed across repeats, i.e. probably noise
+    pub within_noise: bool,
+}
+
+/// Results of a sweep
+#[derive(Debug, Clone)]
+pub struct SweepReport {
+    /// Suite name
+    pub suite: String,
+    /// Case names in suite order
+    pub cases: Vec<String>,
+    /// Cell results in cell order
+    pub cells: Vec<CellResult>,
+    /// Runs per cell
+    pub repeats: usize,
+    /// Cells loaded from the progress file instead of run
+    pub resumed: usize,
+}
+
+impl SweepReport {
+    /// Result for the cell with `cell_id`
+    pub fn cell(&self, cell_id: &str) -> Option<&CellResult> {
+        self.cells.iter().find(|c| c.cell_id == cell_id)
+    }
+
+    /// Best cell by `metric`; ties go to the earlier cell
+    pub fn best_cell(&self, metric: EvalMetric) -> Option<BestCell> {
+        let mut ranked: Vec<&CellResult> = self.cells.iter().collect();
+        ranked.sort_by(|a, b| {
+            let (a, b) = (a.mean(metric), b.mean(metric));
+            if metric.higher_is_better() {
+                b.total_cmp(&a)
+            } else {
+                a.total_cmp(&b)
+            }
+        });
+        let best = ranked.first()?;
+        let runner_up = ranked.get(1);
+        let within_noise = match runner_up {
+            Some(second) if self.repeats > 1 => {
+                let gap = (best.mean(metric) - second.mean(metric)).abs();
+                gap < best.stdev(metric).max(second.stdev(metric))
+            }
+            _ => false,
+        };
+        Some(BestCell {
+            cell_id: best.cell_id.clone(),
+            value: best.mean(metric),
+            runner_up: runner_up.map(|c| (c.cell_id.clone(), c.mean(metric))),
+            within_noise,
+        })
+    }
+
+    /// Markdown matrix: one row per case plus an aggregate row, one column per cell
+    ///
+    /// With repeats, values show `mean ± stdev`. A note under the table names
+    /// the best cell and flags it when its lead is within noise.
+    pub fn to_markdown(&self, metric: EvalMetric) -> String {
+        let mut out = format!(
+            "## Sweep: {} ({})\n\n| case |",
+            self.suite,
+            metric_name(metric)
+        );
+        for cell in &self.cells {
+            let _ = write!(out, " {} |", cell.cell_id);
+        }
+        out.push_str("\n|---|");
+        out.push_str(&"---|".repeat(self.cells.len()));
+        out.push('\n');
+
+        for case in &self.cases {
+            let _ = write!(out, "| {} |", case);
+            for cell in &self.cells {
+                let values = cell.case_values(case, metric);
+                let _ = write!(out, " {} |", self.format_value(&values));
+            }
+            out.push('\n');
+        }
+        out.push_str("| **aggregate** |");
+        for cell in &self.cells {
+            let _ = write!(out, " **{}** |", self.format_value(&cell.values(metric)));
+        }
+        out.push('\n');
+
+        if let Some(best) = self.best_cell(metric) {
+            let _ = write!(out, "\nBest: `{}` ({:.3})", best.cell_id, best.value);
+            if let Some((runner_up, _)) = &best.runner_up {
+                if best.within_noise {
+                    let _ = write!(
+                        out,
+                        ". Lead over `{}` is within run-to-run variance; add repeats before trusting it.",
+                        runner_up
+                    );
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// CSV with one row per cell, repeat and case
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("cell,run,case,score,latency_ms,error\n");
+        for cell in &self.cells {
+            for (run, report) in cell.runs.iter().enumerate() {
+                for case in &report.cases {
+                    let _ = writeln!(
+                        out,
+                        "{},{},{},{},{},{}",
+                        csv_field(&cell.cell_id),
+                        run,
+                        csv_field(&case.name),
+                        case.score,
+                        case.latency_ms,
+                        csv_field(case.error.as_deref().unwrap_or(""))
+                    );
+                }
+            }
+        }
+        out
+    }
+
+    fn format_value(&self, values: &[f64]) -> String {
+        if values.is_empty() {
+            return "-".to_string();
+        }
+        if self.repeats > 1 {
+            format!("{:.3} ± {:.3}", mean(values), stdev(values))
+        } else {
+            format!("{:.3}", mean(values))
+        }
+    }
+}
+
+impl EvalRunner {
+    /// Run `suite` once per cell of `spec` (times `repeats`)
+    ///
+    /// With `progress`, finished cells are appended to that JSONL file and
+    /// cells already in it are loaded instead of run again.
+    pub async fn sweep(
+        &self,
+        suite: &EvalSuite,
+        spec: &SweepSpec,
+        progress: Option<&Path>,
+    ) -> Result<SweepReport> {
+        let cells = spec.cells()?;
+        let repeats = spec.repeats.max(1);
+        let mut done = match progress {
+            Some(path) => load_progress(path)?,
+            None => HashMap::new(),
+        };
+
+        let mut results = Vec::with_capacity(cells.len());
+        let mut resumed = 0;
+        for cell in &cells {
+            if let Some(existing) = done.remove(&cell.id) {
+                if existing.runs.len() >= repeats {
+                    resumed += 1;
+                    results.push(existing);
+                    continue;
+                }
+            }
+            let config = cell.config(&self.config);
+            let mut runs = Vec::with_capacity(repeats);
+            for _ in 0..repeats {
+                runs.push(self.run_with(suite, &config).await?);
+            }
+            let result = CellResult {
+                cell_id: cell.id.clone(),
+                runs,
+            };
+            if let Some(path) = progress {
+                append_progress(path, &result)?;
+            }
+            tracing::info!("Sweep cell {} done", cell.id);
+            results.push(result);
+        }
+
+        Ok(SweepReport {
+            suite: suite.name.clone(),
+            cases: suite.cases.iter().map(|c| c.name.clone()).collect(),
+            cells: results,
+            repeats,
+            resumed,
+        })
+    }
+}
+
+fn load_progress(path: &Path) -> Result<HashMap<String, CellResult>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut done = HashMap::new();
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        // A line cut short by an interrupted write is simply re-run
+        match serde_json::from_str::<CellResult>(line) {
+            Ok(result) => {
+                done.insert(result.cell_id.clone(), result);
+            }
+            Err(e) => tracing::warn!("Skipping unreadable sweep progress line: {}", e),
+        }
+    }
+    Ok(done)
+}
+
+fn append_progress(path: &Path, result: &CellResult) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(result)?)?;
+    file.sync_data()?;
+    Ok(())
+}
+
+fn metric_name(metric: EvalMetric) -> &'static str {
+    match metric {
+        EvalMetric::MeanScore => "mean score",
+        EvalMetric::PassRate => "pass rate",
+        EvalMetric::MeanLatencyMs => "mean latency ms",
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn stdev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let m = mean(values);
+    let var = values.iter().map(|v| (v - m).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    var.sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::eval::EvalCase;
+    use crate::agent::provider::{ChatRequest, Provider};
+    use crate::agent::streaming::{StreamingChoice, StreamingResponse};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Answers "SOL" only at low temperature; `noisy` alternates its answer per call
+    struct MockProvider {
+        calls: AtomicUsize,
+        noisy: bool,
+    }
+
+    impl MockProvider {
+        fn new(noisy: bool) -> Arc<Self> {
+            Arc::new(Self {
+                calls: AtomicUsize::new(0),
+                noisy,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Provider for MockProvider {
+        async fn stream_completion(
+            &self,
+            request: ChatRequest,
+        ) -> crate::error::Result<StreamingResponse> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let cold = request.temperature.unwrap_or(1.0) < 0.5;
+            let text = if (self.noisy && call % 2 == 0) || (!self.noisy && cold) {
+                format!("{} says buy SOL", request.model)
+            } else {
+                "Not sure".to_string()
+            };
+            let stream = futures::stream::once(async move { Ok(StreamingChoice::Message(text)) });
+            Ok(StreamingResponse::new(Box::pin(stream)))
+        }
+
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+    }
+
+    fn suite() -> EvalSuite {
+        EvalSuite::new("picks")
+            .case(EvalCase::new("sol", "What should I buy?").expect("sol"))
+            .case(EvalCase::new("free", "Anything?"))
+    }
+
+    fn spec() -> SweepSpec {
+        SweepSpec::new()
+            .dimension(SweepDimension::models(["a", "b"]))
+            .dimension(SweepDimension::temperatures([0.2, 0.9]))
+    }
+
+    #[test]
+    fn test_expansion_order_cap_and_sample() {
+        let ids: Vec<String> = spec().cells().unwrap().into_iter().map(|c| c.id).collect();
+        assert_eq!(
+            ids,
+            [
+                "model=a, temperature=0.2",
+                "model=a, temperature=0.9",
+                "model=b, temperature=0.2",
+                "model=b, temperature=0.9"
+            ]
+        );
+        let cell = &spec().cells().unwrap()[2];
+        let config = cell.config(&EvalConfig::default());
+        assert_eq!(
+            (config.model.as_str(), config.temperature),
+            ("b", Some(0.2))
+        );
+
+        assert!(spec().max_cells(3).cells().is_err());
+        let sampled = spec().max_cells(3).sample(3, 7).cells().unwrap();
+        assert_eq!(sampled.len(), 3);
+        assert_eq!(sampled, spec().max_cells(3).sample(3, 7).cells().unwrap());
+        let positions: Vec<usize> = sampled
+            .iter()
+            .map(|c| ids.iter().position(|id| *id == c.id).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[tokio::test]
+    async fn test_sweep_matrix_and_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let progress = dir.path().join("sweep.jsonl");
+        let provider = MockProvider::new(false);
+        let runner = EvalRunner::new(provider.clone());
+
+        // Interrupted after the first half of the grid
+        let half = SweepSpec::new()
+            .dimension(SweepDimension::models(["a"]))
+            .dimension(SweepDimension::temperatures([0.2, 0.9]));
+        runner
+            .sweep(&suite(), &half, Some(&progress))
+            .await
+            .unwrap();
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 4);
+
+        let report = runner
+            .sweep(&suite(), &spec(), Some(&progress))
+            .await
+            .unwrap();
+        assert_eq!(report.resumed, 2);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 8);
+        assert_eq!(report.cells[1].cell_id, "model=a, temperature=0.9");
+
+        let again = runner
+            .sweep(&suite(), &spec(), Some(&progress))
+            .await
+            .unwrap();
+        assert_eq!(again.resumed, 4);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 8);
+
+        let best = report.best_cell(EvalMetric::MeanScore).unwrap();
+        assert_eq!(best.cell_id, "model=a, temperature=0.2");
+        assert_eq!(best.value, 1.0);
+        assert!(!best.within_noise);
+
+        let md = report.to_markdown(EvalMetric::MeanScore);
+        let lines: Vec<&str> = md.lines().collect();
+        assert_eq!(
+            lines[2],
+            "| case | model=a, temperature=0.2 | model=a, temperature=0.9 | model=b, temperature=0.2 | model=b, temperature=0.9 |"
+        );
+        assert_eq!(lines[4], "| sol | 1.000 | 0.000 | 1.000 | 0.000 |");
+        assert_eq!(lines[5], "| free | 1.000 | 1.000 | 1.000 | 1.000 |");
+        assert_eq!(
+            lines[6],
+            "| **aggregate** | **1.000** | **0.500** | **1.000** | **0.500** |"
+        );
+
+        let csv = report.to_csv();
+        assert_eq!(csv.lines().count(), 1 + 4 * 2);
+        assert!(csv.contains("\"model=b, temperature=0.9\",0,sol,0,"));
+    }
+
+    #[tokio::test]
+    async fn test_repeat_variance_flags_noise() {
+        let runner = EvalRunner::new(MockProvider::new(true));
+        let spec = SweepSpec::new()
+            .dimension(SweepDimension::templates([
+                ("v1", "Be brief"),
+                ("v2", "Be bold"),
+            ]))
+            .repeats(3);
+        let suite = EvalSuite::new("noisy").case(EvalCase::new("sol", "Buy?").expect("sol"));
+        let report = runner.sweep(&suite, &spec, None).await.unwrap();
+
+        // Calls alternate hit/miss: v1 gets [1, 0, 1], v2 gets [0, 1, 0]
+        let v1 = report.cell("prompt=v1").unwrap();
+        assert_eq!(v1.runs.len(), 3);
+        assert!((v1.mean(EvalMetric::MeanScore) - 2.0 / 3.0).abs() < 1e-9);
+        assert!(v1.stdev(EvalMetric::MeanScore) > 0.5);
+        assert_eq!(v1.runs[0].config.prompt_version.as_deref(), Some("v1"));
+
+        let best = report.best_cell(EvalMetric::MeanScore).unwrap();
+        assert_eq!(best.cell_id, "prompt=v1");
+        assert!(best.within_noise);
+        let md = report.to_markdown(EvalMetric::MeanScore);
+        assert!(md.contains("0.667 ± 0.577"));
+        assert!(md.contains("within run-to-run variance"));
+    }
+}
@@ -0,0 +1,337 @@
+//! Durable audit log of agent events
+//!
+//! The broadcast channel behind [`Agent::subscribe`](crate::agent::Agent::subscribe)
+//! forgets events nobody is listening for. An [`EventRecorder`] stays subscribed
+//! for the life of the agent and appends every event, stamped with its session
+//! and time, to a JSONL file; [`EventLog`] reads a session's events back in order.
+//!
+//! Recording never blocks the agent. Events wait in the broadcast buffer while
+//! the recorder writes; if it falls far enough behind for that buffer to
+//! overflow, the lost events are counted in [`EventRecorder::dropped`] and the
+//! next recorded event carries the gap in [`RecordedEvent::lost_before`].
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::agent::core::AgentEvent;
+use crate::error::{Error, Result};
+
+/// One line of the event log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Session the event belongs to
+    pub session_id: String,
+    /// When the recorder received the event
+    pub recorded_at: DateTime<Utc>,
+    /// Events lost to backpressure just before this one
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub lost_before: u64,
+    /// The event itself
+    pub event: AgentEvent,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+/// Appends an agent's events to a JSONL file from a background task
+pub struct EventRecorder {
+    path: PathBuf,
+    session_id: String,
+    recorded: Arc<AtomicU64>,
+    dropped: Arc<AtomicU64>,
+    flushes: mpsc::Sender<oneshot::Sender<()>>,
+}
+
+impl EventRecorder {
+    /// Record `events` for `session_id` into `path`, creating it if needed
+    ///
+    /// Must be called within a Tokio runtime. The task ends once every sender
+    /// of `events` is gone, i.e. when the agent is dropped.
+    pub fn spawn(
+        events: broadcast::Receiver<AgentEvent>,
+        path: impl Into<PathBuf>,
+        session_id: impl Into<String>,
+    ) -> Result<Self> {
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|_| Error::agent_config("Event recording needs a Tokio runtime"))?;
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+
+        let (flushes, flush_requests) = mpsc::channel(8);
+        let recorder = Self {
+            path,
+            session_id: session_id.into(),
+            recorded: Arc::new(AtomicU64::new(0)),
+            dropped: Arc::new(AtomicU64::new(0)),
+            flushes,
+        };
+        let writer = Writer {
+            file: tokio::fs::File::from_std(file),
+            session_id: recorder.session_id.clone(),
+            recorded: Arc::clone(&recorder.recorded),
+            dropped: Arc::clone(&recorder.dropped),
+            lost: 0,
+        };
+        runtime.spawn(writer.run(events, flush_requests));
+        Ok(recorder)
+    }
+
+    /// Log file path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Session the events are recorded under
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Events written so far
+    pub fn recorded(&self) -> u64 {
+        self.recorded.load(Ordering::Relaxed)
+    }
+
+    /// Events lost because the recorder fell behind or a write failed
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Wait until every event emitted before this call is on disk
+    pub async fn flush(&self) {
+        let (done, wait) = oneshot::channel();
+        // A finished task has already written everything it received
+        if self.flushes.send(done).await.is_ok() {
+            let _ = wait.await;
+        }
+    }
+}
+
+/// Background half of an [`EventRecorder`]
+struct Writer {
+    file: tokio::fs::File,
+    session_id: String,
+    recorded: Arc<AtomicU64>,
+    dropped: Arc<AtomicU64>,
+    /// Lost events not yet reported on a recorded line
+    lost: u64,
+}
+
+impl Writer {
+    async fn run(
+        mut self,
+        mut events: broadcast::Receiver<AgentEvent>,
+        mut flush_requests: mpsc::Receiver<oneshot::Sender<()>>,
+    ) {
+        let mut batch = Vec::new();
+        loop {
+            tokio::select! {
+                received = events.recv() => match received {
+                    Ok(event) => self.push(&mut batch, event),
+                    Err(RecvError::Lagged(n)) => self.lose(n),
+                    Err(RecvError::Closed) => break,
+                },
+                Some(done) = flush_requests.recv() => {
+                    self.drain(&mut events, &mut batch);
+                    self.write(&mut batch).await;
+                    let _ = done.send(());
+                    continue;
+                }
+            }
+            // Write whatever else is already queued in one append
+            self.drain(&mut events, &mut batch);
+            self.write(&mut batch).await;
+        }
+        self.write(&mut batch).await;
+    }
+
+    fn drain(
+        &mut self,
+        events: &mut broadcast::Receiver<AgentEvent>,
+        batch: &mut Vec<RecordedEvent>,
+    ) {
+        loop {
+            match events.try_recv() {
+                Ok(event) => self.push(batch, event),
+                Err(TryRecvError::Lagged(n)) => self.lose(n),
+                Err(TryRecvError::Empty | TryRecvError::Closed) => return,
+            }
+        }
+    }
+
+    fn push(&mut self, batch: &mut Vec<RecordedEvent>, event: AgentEvent) {
+        batch.push(RecordedEvent {
+            session_id: self.session_id.clone(),
+            recorded_at: Utc::now(),
+            lost_before: std::mem::take(&mut self.lost),
+            event,
+        });
+    }
+
+    fn lose(&mut self, n: u64) {
+        tracing::warn!(lost = n, "Event recorder fell behind; events dropped");
+        self.lost += n;
+        self.dropped.fetch_add(n, Ordering::Relaxed);
+    }
+
+    async fn write(&mut self, batch: &mut Vec<RecordedEvent>) {
+        if batch.is_empty() {
+            return;
+        }
+        let mut lines = String::new();
+        let mut count = 0;
+        for record in batch.drain(..) {
+            match serde_json::to_string(&record) {
+                Ok(json) => {
+                    lines.push_str(&json);
+                    lines.push('\n');
+                    count += 1;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to serialize event for the log: {}", e);
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        let written = match self.file.write_all(lines.as_bytes()).await {
+            Ok(()) => self.file.flush().await,
+            Err(e) => Err(e),
+        };
+        match written {
+            Ok(()) => self.recorded.fetch_add(count, Ordering::Relaxed),
+            Err(e) => {
+                tracing::warn!("Failed to append to the event log: {}", e);
+                self.dropped.fetch_add(count, Ordering::Relaxed)
+            }
+        };
+    }
+}
+
+/// Reads back a log written by [`EventRecorder`]
+pub struct EventLog {
+    path: PathBuf,
+}
+
+impl EventLog {
+    /// Log at `path`
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Log file path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Events of `session_id` in the order they were emitted
+    pub fn load(&self, session_id: &str) -> Result<Vec<RecordedEvent>> {
+        Ok(self
+            .load_all()?
+            .into_iter()
+            .filter(|r| r.session_id == session_id)
+            .collect())
+    }
+
+    /// Every recorded event, all sessions interleaved as written
+    ///
+    /// A missing file is an empty log; unreadable lines (a write torn by a
+    /// crash) are skipped.
+    pub fn load_all(&self) -> Result<Vec<RecordedEvent>> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(text
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .filter_map(|l| serde_json::from_str(l).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{PriceProvider, PriceTool};
+    use crate::agent::core::Agent;
+    use crate::agent::message::Role;
+    use crate::agent::provider::{ChatRequest, Provider};
+    use crate::agent::streaming::{MockStreamBuilder, StreamingResponse};
+    use crate::skills::tool::{Tool, ToolDefinition};
+    use async_trait::async_trait;
+
+    #[tokio::test]
+    async fn test_recorded_log_replays_the_emitted_sequence() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit/events.jsonl");
+        let agent = Agent::builder(PriceProvider)
+            .tool(PriceTool)
+            .session_id("desk-1")
+            .record_events(&path)
+            .auto_load_skills(false)
+            .build()
+            .unwrap();
+        let mut live = agent.subscribe();
+
+        assert_eq!(agent.prompt("Price of SOL?").await.unwrap(), "SOL is $150");
+        let recorder = agent.event_recorder().unwrap();
+        recorder.flush().await;
+
+        let mut emitted = Vec::new();
+        while let Ok(event) = live.try_recv() {
+            emitted.push(serde_json::to_value(event).unwrap());
+        }
+        let log = EventLog::open(&path).load("desk-1").unwrap();
+        let replayed: Vec<_> = log
+            .iter()
+            .map(|r| serde_json::to_value(&r.event).unwrap())
+            .collect();
+        assert_eq!(replayed, emitted);
+        assert!(replayed
+            .iter()
+            .any(|e| e["type"] == "tool_call" && e["data"]["tool"] == "price"));
+        assert!(replayed
+            .iter()
+            .any(|e| e["type"] == "tool_result" && e["data"]["output"] == "150"));
+        assert_eq!(replayed.last().unwrap()["type"], "response");
+        assert_eq!(recorder.recorded(), log.len() as u64);
+        assert_eq!(recorder.dropped(), 0);
+        assert!(EventLog::open(&path).load("other").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_recorder_counts_events_it_fell_behind_on() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let (tx, rx) = broadcast::channel(4);
+        // Fill past capacity before the recorder task gets to run
+        for i in 0..10 {
+            tx.send(AgentEvent::Thinking {
+                prompt: i.to_string(),
+            })
+            .unwrap();
+        }
+        let recorder = EventRecorder::spawn(rx, &path, "s").unwrap();
+        recorder.flush().await;
+
+        assert_eq!(recorder.dropped(), 6);
+        let log = EventLog::open(&path).load("s").unwrap();
+        assert_eq!(log.len(), 4);
+        assert_eq!(log[0].lost_before, 6);
+        assert!(matches!(&log[0].event, AgentEvent::Thinking { prompt } if prompt == "6"));
+    }
+}
@@ -0,0 +1,711 @@
+//! Filtered agent event subscriptions
+//!
+//! [`Agent::subscribe`](crate::agent::Agent::subscribe) hands every consumer the
+//! full broadcast stream. [`EventHub`] additionally keeps a list of filtered
+//! subscribers: each event is matched against their [`EventFilter`] by reference
+//! and only cloned into the queues of subscribers that want it, so a narrow
+//! subscriber costs nothing for events it doesn't match.
+//!
+//! Each filtered subscriber has its own bounded queue. When it falls behind,
+//! matching events are dropped for that subscriber only and an
+//! [`EventItem::Lagged`] marker is delivered ahead of the next event that fits.
+//!
+//! Every event is also numbered and recorded in the hub's
+//! [`ReplayBuffer`], so late subscribers can catch up with
+//! [`EventHub::subscribe_with_replay`].
+
+use std::collections::BTreeMap;
+use std::ops::BitOr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use parking_lot::RwLock;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::agent::core::AgentEvent;
+use crate::agent::replay::{EventId, ReplayBuffer, ReplaySubscription};
+
+/// Default queue size for filtered subscribers
+pub const DEFAULT_SUBSCRIBER_CAPACITY: usize = 256;
+
+/// Discriminant of an [`AgentEvent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Thinking,
+    StreamDelta,
+    Reasoning,
+    ToolCallDelta,
+    ToolCall,
+    ApprovalPending,
+    ToolResult,
+    ToolUnavailable,
+    Response,
+    Usage,
+    Error,
+    Cancelled,
+    PersonaChanged,
+    Subagent,
+}
+
+impl EventKind {
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+/// Set of event kinds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventKinds(u16);
+
+impl EventKinds {
+    /// Every kind
+    pub const ALL: Self = Self(u16::MAX);
+    /// No kinds
+    pub const NONE: Self = Self(0);
+
+    /// Whether `kind` is in the set
+    pub fn contains(self, kind: EventKind) -> bool {
+        self.0 & kind.bit() != 0
+    }
+
+    /// Add `kind` to the set
+    pub fn with(self, kind: EventKind) -> Self {
+        Self(self.0 | kind.bit())
+    }
+}
+
+impl From<EventKind> for EventKinds {
+    fn from(kind: EventKind) -> Self {
+        Self(kind.bit())
+    }
+}
+
+impl BitOr for EventKind {
+    type Output = EventKinds;
+
+    fn bitor(self, rhs: Self) -> EventKinds {
+        EventKinds::from(self).with(rhs)
+    }
+}
+
+impl BitOr<EventKind> for EventKinds {
+    type Output = EventKinds;
+
+    fn bitor(self, rhs: EventKind) -> EventKinds {
+        self.with(rhs)
+    }
+}
+
+impl FromIterator<EventKind> for EventKinds {
+    fn from_iter<I: IntoIterator<Item = EventKind>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::NONE, Self::with)
+    }
+}
+
+/// How important an event is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl AgentEvent {
+    /// This event's kind
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Self::Thinking { .. } => EventKind::Thinking,
+            Self::StreamDelta { .. } => EventKind::StreamDelta,
+            Self::Reasoning { .. } => EventKind::Reasoning,
+            Self::ToolCallDelta { .. } => EventKind::ToolCallDelta,
+            Self::ToolCall { .. } => EventKind::ToolCall,
+            Self::ApprovalPending { .. } => EventKind::ApprovalPending,
+            Self::ToolResult { .. } => EventKind::ToolResult,
+            Self::ToolUnavailable { .. } => EventKind::ToolUnavailable,
+            Self::Response { .. } => EventKind::Response,
+            Self::Usage { .. } => EventKind::Usage,
+            Self::Error { .. } => EventKind::Error,
+            Self::Cancelled { .. } => EventKind::Cancelled,
+            Self::PersonaChanged { .. } => EventKind::PersonaChanged,
+            Self::Subagent { .. } => EventKind::Subagent,
+        }
+    }
+
+    /// This event's severity (sub-agent events take their inner event's)
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::Thinking { .. }
+            | Self::StreamDelta { .. }
+            | Self::Reasoning { .. }
+            | Self::ToolCallDelta { .. }
+            | Self::Usage { .. } => Severity::Debug,
+            Self::ToolCall { .. }
+            | Self::ToolResult { .. }
+            | Self::Response { .. }
+            | Self::PersonaChanged { .. } => Severity::Info,
+            Self::ApprovalPending { .. } | Self::ToolUnavailable { .. } | Self::Cancelled { .. } => {
+                Severity::Warning
+            }
+            Self::Error { .. } => Severity::Error,
+            Self::Subagent { event, .. } => event.severity(),
+        }
+    }
+
+    /// Tool this event is about, if any (looks through sub-agent events)
+    pub fn tool(&self) -> Option<&str> {
+        match self {
+            Self::ToolCall { tool, .. }
+            | Self::ApprovalPending { tool, .. }
+            | Self::ToolResult { tool, .. }
+            | Self::ToolUnavailable { tool, .. }
+            | Self::ToolCallDelta { tool, .. } => Some(tool),
+            Self::Subagent { event, .. } => event.tool(),
+            _ => None,
+        }
+    }
+}
+
+/// Which events a filtered subscriber receives
+///
+/// All set criteria must hold. Tool patterns only constrain events that name a
+/// tool; combine them with [`EventFilter::kinds`] to receive tool events only.
+#[derive(Debug, Clone)]
+pub struct EventFilter {
+    kinds: EventKinds,
+    tools: Vec<String>,
+    min_severity: Option<Severity>,
+}
+
+impl Default for EventFilter {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl EventFilter {
+    /// Match every event
+    pub fn all() -> Self {
+        Self {
+            kinds: EventKinds::ALL,
+            tools: Vec::new(),
+            min_severity: None,
+        }
+    }
+
+    /// Only these kinds
+    pub fn kinds(mut self, kinds: impl Into<EventKinds>) -> Self {
+        self.kinds = kinds.into();
+        self
+    }
+
+    /// Only tool events whose tool matches `pattern` (`*` matches any run of characters)
+    ///
+    /// Can be called repeatedly; a tool matching any pattern passes.
+    pub fn tool(mut self, pattern: impl Into<String>) -> Self {
+        self.tools.push(pattern.into());
+        self
+    }
+
+    /// Only events at or above `severity`
+    pub fn min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = Some(severity);
+        self
+    }
+
+    /// Whether `event` passes the filter
+    pub fn matches(&self, event: &AgentEvent) -> bool {
+        if !self.kinds.contains(event.kind()) {
+            return false;
+        }
+        if self.min_severity.is_some_and(|min| event.severity() < min) {
+            return false;
+        }
+        match event.tool() {
+            Some(tool) if !self.tools.is_empty() => {
+                self.tools.iter().any(|p| wildcard_match(p, tool))
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Match `text` against `pattern`, where `*` matches any run of characters
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*`: exact match
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// An item from a filtered subscription
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventItem<T = AgentEvent> {
+    /// A matching event
+    Event(T),
+    /// This many matching events were dropped because the subscriber fell behind
+    Lagged(u64),
+}
+
+struct FilteredSubscriber {
+    filter: EventFilter,
+    tx: mpsc::Sender<EventItem>,
+    lagged: AtomicU64,
+}
+
+/// What happened when an event was offered to a filtered subscriber
+enum Delivery {
+    /// Filter didn't match
+    Skipped,
+    /// Queued, or counted as lagged
+    Matched,
+    /// Subscriber dropped its stream
+    Closed,
+}
+
+impl FilteredSubscriber {
+    /// Queue `event` if it matches, cloning it only then
+    fn offer(&self, event: &AgentEvent) -> Delivery {
+        if self.tx.is_closed() {
+            return Delivery::Closed;
+        }
+        if !self.filter.matches(event) {
+            return Delivery::Skipped;
+        }
+
+        let lagged = self.lagged.load(Ordering::Relaxed);
+        if lagged > 0 {
+            if self.tx.try_send(EventItem::Lagged(lagged)).is_err() {
+                self.lagged.fetch_add(1, Ordering::Relaxed);
+                return Delivery::Matched;
+            }
+            self.lagged.fetch_sub(lagged, Ordering::Relaxed);
+        }
+        match self.tx.try_send(EventItem::Event(event.clone())) {
+            Ok(()) => Delivery::Matched,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.lagged.fetch_add(1, Ordering::Relaxed);
+                Delivery::Matched
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => Delivery::Closed,
+        }
+    }
+}
+
+/// Agent event sender with broadcast and filtered subscribers
+#[derive(Clone)]
+pub struct EventHub {
+    broadcast: broadcast::Sender<AgentEvent>,
+    filtered: Arc<RwLock<Vec<FilteredSubscriber>>>,
+    replay: Arc<ReplayBuffer>,
+}
+
+impl EventHub {
+    /// Create a hub whose broadcast channel holds `capacity` events
+    pub fn new(capacity: usize) -> Self {
+        broadcast::channel(capacity).0.into()
+    }
+
+    /// Record events in `replay` instead of a default-sized buffer
+    pub fn with_replay(mut self, replay: ReplayBuffer) -> Self {
+        self.replay = Arc::new(replay);
+        self
+    }
+
+    /// Deliver `event` to matching filtered subscribers and all broadcast receivers
+    ///
+    /// Returns how many subscribers it reached.
+    pub fn send(&self, event: AgentEvent) -> usize {
+        self.replay.append(&event);
+        let mut reached = 0;
+        let mut closed = false;
+        {
+            let filtered = self.filtered.read();
+            for subscriber in filtered.iter() {
+                match subscriber.offer(&event) {
+                    Delivery::Matched => reached += 1,
+                    Delivery::Closed => closed = true,
+                    Delivery::Skipped => {}
+                }
+            }
+        }
+        if closed {
+            self.filtered.write().retain(|s| !s.tx.is_closed());
+        }
+
+        if self.broadcast.receiver_count() > 0 {
+            reached += self.broadcast.send(event).unwrap_or(0);
+        }
+        reached
+    }
+
+    /// Receive every event over the broadcast channel
+    pub fn subscribe(&self) -> broadcast::Receiver<AgentEvent> {
+        self.broadcast.subscribe()
+    }
+
+    /// Buffered events after `since` (all buffered ones if `None`), then live events
+    pub fn subscribe_with_replay(&self, since: Option<EventId>) -> ReplaySubscription {
+        self.replay.subscribe(since)
+    }
+
+    /// The replay buffer behind [`EventHub::subscribe_with_replay`]
+    pub fn replay(&self) -> &ReplayBuffer {
+        &self.replay
+    }
+
+    /// Receive only events matching `filter`
+    pub fn subscribe_filtered(&self, filter: EventFilter) -> EventStream {
+        self.subscribe_filtered_with_capacity(filter, DEFAULT_SUBSCRIBER_CAPACITY)
+    }
+
+    /// Like [`EventHub::subscribe_filtered`] with a custom queue size
+    pub fn subscribe_filtered_with_capacity(
+        &self,
+        filter: EventFilter,
+        capacity: usize,
+    ) -> EventStream {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        self.filtered.write().push(FilteredSubscriber {
+            filter,
+            tx,
+            lagged: AtomicU64::new(0),
+        });
+        EventStream { rx, map: Some }
+    }
+
+    /// Number of live filtered subscribers
+    pub fn filtered_subscriber_count(&self) -> usize {
+        self.filtered
+            .read()
+            .iter()
+            .filter(|s| !s.tx.is_closed())
+            .count()
+    }
+}
+
+impl From<broadcast::Sender<AgentEvent>> for EventHub {
+    fn from(broadcast: broadcast::Sender<AgentEvent>) -> Self {
+        Self {
+            broadcast,
+            filtered: Arc::default(),
+            replay: Arc::new(ReplayBuffer::new(Default::default())),
+        }
+    }
+}
+
+/// A filtered subscription, optionally mapped to a typed event
+///
+/// Yields `None` once the agent is dropped.
+pub struct EventStream<T = AgentEvent> {
+    rx: mpsc::Receiver<EventItem>,
+    map: fn(AgentEvent) -> Option<T>,
+}
+
+impl<T> EventStream<T> {
+    /// Wait for the next item
+    pub async fn recv(&mut self) -> Option<EventItem<T>> {
+        std::future::poll_fn(|cx| self.poll_item(cx)).await
+    }
+
+    fn poll_item(&mut self, cx: &mut Context<'_>) -> Poll<Option<EventItem<T>>> {
+        loop {
+            let item = match self.rx.poll_recv(cx) {
+                Poll::Ready(Some(item)) => item,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            match item {
+                EventItem::Event(event) => {
+                    if let Some(typed) = (self.map)(event) {
+                        return Poll::Ready(Some(EventItem::Event(typed)));
+                    }
+                }
+                EventItem::Lagged(n) => return Poll::Ready(Some(EventItem::Lagged(n))),
+            }
+        }
+    }
+}
+
+impl<T> Stream for EventStream<T> {
+    type Item = EventItem<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_item(cx)
+    }
+}
+
+/// A tool being called or returning
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolEvent {
+    /// The agent called a tool
+    Call { tool: String, input: String },
+    /// A tool returned
+    Result { tool: String, output: String },
+}
+
+/// A final response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseEvent {
+    /// Stable ID for attaching feedback
+    pub response_id: String,
+    /// Raw model output
+    pub content: String,
+    /// Output of each formatter chain, keyed by consumer
+    pub formatted: BTreeMap<String, String>,
+}
+
+/// A tool call waiting for approval
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApprovalEvent {
+    /// Tool awaiting approval
+    pub tool: String,
+    /// Arguments it would be called with
+    pub input: String,
+    /// When the request times out, if approvals have a timeout
+    pub deadline: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl EventHub {
+    /// Tool calls and results
+    pub fn tool_events(&self) -> EventStream<ToolEvent> {
+        let rx = self
+            .subscribe_filtered(
+                EventFilter::all().kinds(EventKind::ToolCall | EventKind::ToolResult),
+            )
+            .rx;
+        EventStream {
+            rx,
+            map: |event| match event {
+                AgentEvent::ToolCall { tool, input } => Some(ToolEvent::Call { tool, input }),
+                AgentEvent::ToolResult { tool, output } => Some(ToolEvent::Result { tool, output }),
+                _ => None,
+            },
+        }
+    }
+
+    /// Final responses
+    pub fn responses(&self) -> EventStream<ResponseEvent> {
+        let rx = self
+            .subscribe_filtered(EventFilter::all().kinds(EventKind::Response))
+            .rx;
+        EventStream {
+            rx,
+            map: |event| match event {
+                AgentEvent::Response {
+                    response_id,
+                    content,
+                    formatted,
+                } => Some(ResponseEvent {
+                    response_id,
+                    content,
+                    formatted,
+                }),
+                _ => None,
+            },
+        }
+    }
+
+    /// Tool calls waiting for approval
+    pub fn approvals(&self) -> EventStream<ApprovalEvent> {
+        let rx = self
+            .subscribe_filtered(EventFilter::all().kinds(EventKind::ApprovalPending))
+            .rx;
+        EventStream {
+            rx,
+            map: |event| match event {
+                AgentEvent::ApprovalPending { tool, input, deadline } => {
+                    Some(ApprovalEvent { tool, input, deadline })
+                }
+                _ => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn tool_result(tool: &str) -> AgentEvent {
+        AgentEvent::ToolResult {
+            tool: tool.to_string(),
+            output: "x".repeat(1024),
+        }
+    }
+
+    #[test]
+    fn test_filter_combinations() {
+        let call = AgentEvent::ToolCall {
+            tool: "swap_tokens".to_string(),
+            input: "{}".to_string(),
+        };
+        let error = AgentEvent::Error {
+            message: "boom".to_string(),
+        };
+        let thinking = AgentEvent::Thinking {
+            prompt: "hi".to_string(),
+        };
+        let nested = AgentEvent::Subagent {
+            request_id: "r".to_string(),
+            child: 0,
+            event: Box::new(call.clone()),
+        };
+
+        assert!(EventFilter::all().matches(&thinking));
+
+        let tools = EventFilter::all().kinds(EventKind::ToolCall | EventKind::ToolResult);
+        assert!(tools.matches(&call) && tools.matches(&tool_result("x")));
+        assert!(!tools.matches(&error) && !tools.matches(&nested));
+
+        // Tool patterns constrain tool events only
+        let swaps = EventFilter::all().tool("swap_*").tool("bridge");
+        assert!(swaps.matches(&call) && swaps.matches(&nested));
+        assert!(swaps.matches(&tool_result("bridge")) && swaps.matches(&error));
+        assert!(!swaps.matches(&tool_result("price")));
+
+        let urgent = EventFilter::all().min_severity(Severity::Warning);
+        assert!(urgent.matches(&error) && !urgent.matches(&call) && !urgent.matches(&thinking));
+        let approval = AgentEvent::ApprovalPending {
+            tool: "swap_tokens".to_string(),
+            input: "{}".to_string(),
+            deadline: None,
+        };
+        assert!(urgent.clone().tool("swap*").matches(&approval));
+        assert!(!urgent.tool("price").matches(&approval));
+
+        let none: EventKinds = [].into_iter().collect();
+        assert!(!EventFilter::all().kinds(none).matches(&error));
+
+        for (pattern, text, expected) in [
+            ("*", "anything", true),
+            ("swap", "swap", true),
+            ("swap", "swap_tokens", false),
+            ("*_tokens", "swap_tokens", true),
+            ("s*p*s", "swap_tokens", true),
+            ("a*a", "a", false),
+        ] {
+            assert_eq!(
+                wildcard_match(pattern, text),
+                expected,
+                "{} ~ {}",
+                pattern,
+                text
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_event_storm_narrow_subscriber_and_lag_marker() {
+        let hub = EventHub::new(16);
+        // Capacity well below the storm size: only matched events ever enter this queue
+        let mut narrow = hub.subscribe_filtered_with_capacity(
+            EventFilter::all().kinds(EventKind::ToolResult).tool("swap"),
+            16,
+        );
+        let mut broad = hub.subscribe_filtered_with_capacity(EventFilter::all(), 4);
+        let mut typed = hub.tool_events();
+
+        for i in 0..10_000 {
+            hub.send(tool_result(if i % 1000 == 0 { "swap" } else { "price" }));
+        }
+
+        let mut matched = 0;
+        while let Ok(Some(item)) =
+            tokio::time::timeout(std::time::Duration::from_millis(10), narrow.recv()).await
+        {
+            assert!(
+                matches!(item, EventItem::Event(AgentEvent::ToolResult { ref tool, .. }) if tool == "swap")
+            );
+            matched += 1;
+        }
+        assert_eq!(matched, 10);
+
+        // The broad subscriber fell behind: it keeps its queue, then learns what it missed
+        for _ in 0..4 {
+            assert!(matches!(broad.recv().await, Some(EventItem::Event(_))));
+        }
+        hub.send(AgentEvent::Error {
+            message: "after storm".to_string(),
+        });
+        assert_eq!(
+            broad
+                .recv()
+                .await
+                .map(|i| matches!(i, EventItem::Lagged(9_996))),
+            Some(true)
+        );
+        assert!(matches!(
+            broad.recv().await,
+            Some(EventItem::Event(AgentEvent::Error { .. }))
+        ));
+
+        // Typed stream gets the same events, already converted
+        assert_eq!(
+            typed.next().await,
+            Some(EventItem::Event(ToolEvent::Result {
+                tool: "swap".to_string(),
+                output: "x".repeat(1024),
+            }))
+        );
+
+        drop(narrow);
+        drop(broad);
+        drop(typed);
+        hub.send(tool_result("swap"));
+        assert_eq!(hub.filtered_subscriber_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_typed_streams() {
+        let hub = EventHub::new(16);
+        let mut approvals = hub.approvals();
+        let mut responses = hub.responses();
+
+        hub.send(AgentEvent::ApprovalPending {
+            tool: "swap".to_string(),
+            input: "{}".to_string(),
+            deadline: None,
+        });
+        hub.send(AgentEvent::Response {
+            response_id: "resp-1".to_string(),
+            content: "done".to_string(),
+            formatted: BTreeMap::new(),
+        });
+        drop(hub);
+
+        assert_eq!(
+            approvals.next().await,
+            Some(EventItem::Event(ApprovalEvent {
+                tool: "swap".to_string(),
+                deadline: None,
+                input: "{}".to_string(),
+            }))
+        );
+        assert_eq!(approvals.next().await, None);
+        match responses.recv().await {
+            Some(EventItem::Event(r)) => assert_eq!(
+                (r.response_id.as_str(), r.content.as_str()),
+                ("resp-1", "done")
+            ),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,619 @@
+//! Turn-level user feedback
+//!
+//! Every final agent response gets a stable `response_id`, carried on
+//! [`AgentEvent::Response`](crate::agent::core::AgentEvent::Response) and on the
+//! assistant message stored in the session. With a [`FeedbackStore`] attached the
+//! agent also registers a [`ResponseRecord`] per response, so feedback recorded
+//! later is enriched with the model, prompt version, experiment variant and tools
+//! of that turn and can be aggregated along those dimensions.
+//!
+//! ```ignore
+//! let feedback = Arc::new(FeedbackStore::open("data/feedback.jsonl").await?);
+//! let agent = Agent::builder(provider).with_feedback(feedback.clone()).build()?;
+//! agent.record_feedback("s1", response_id.as_str(), Rating::Down, Some("wrong pair".into()), vec![]).await?;
+//! let by_model = feedback.aggregate(FeedbackDimension::Model, start..end);
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::error::Result;
+
+/// Key used for dimension values that were not set
+pub const UNSET_KEY: &str = "(none)";
+
+/// User rating of a response
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    /// Thumbs up
+    Up,
+    /// Thumbs down
+    Down,
+    /// Score in `[0, 1]`
+    Score(f32),
+}
+
+impl Rating {
+    /// Normalized value in `[0, 1]` (up = 1, down = 0)
+    pub fn value(&self) -> f32 {
+        match self {
+            Rating::Up => 1.0,
+            Rating::Down => 0.0,
+            Rating::Score(score) => score.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Whether the rating counts as positive (value >= 0.5)
+    pub fn is_positive(&self) -> bool {
+        self.value() >= 0.5
+    }
+}
+
+/// Which response feedback is about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResponseRef {
+    /// Index of the assistant message in the session history
+    Index(usize),
+    /// Response ID from the `Response` event
+    Id(String),
+}
+
+impl From<usize> for ResponseRef {
+    fn from(index: usize) -> Self {
+        Self::Index(index)
+    }
+}
+
+impl From<&str> for ResponseRef {
+    fn from(id: &str) -> Self {
+        Self::Id(id.to_string())
+    }
+}
+
+impl From<String> for ResponseRef {
+    fn from(id: String) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl fmt::Display for ResponseRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "#{}", index),
+            Self::Id(id) => f.write_str(id),
+        }
+    }
+}
+
+/// What the agent knew about a response when it produced it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseRecord {
+    /// Stable response ID
+    pub response_id: String,
+    /// Session the response belongs to
+    pub session_id: String,
+    /// Index of the assistant message in the session history
+    pub message_index: usize,
+    /// Model that produced the response
+    pub model: String,
+    /// Prompt template version, if configured
+    pub prompt_version: Option<String>,
+    /// Experiment variant, if configured
+    pub experiment_variant: Option<String>,
+    /// Tools called during the turn, in first-call order
+    pub tools: Vec<String>,
+    /// When the response was produced
+    pub created_at: DateTime<Utc>,
+}
+
+/// A piece of feedback, enriched from its [`ResponseRecord`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedbackEntry {
+    /// Unique feedback ID
+    pub id: String,
+    /// Session the response belongs to
+    pub session_id: String,
+    /// Response the feedback is about
+    pub response_id: String,
+    /// Index of the assistant message in the session history
+    pub message_index: usize,
+    /// The rating
+    pub rating: Rating,
+    /// Free-text comment
+    pub comment: Option<String>,
+    /// Caller-defined tags
+    pub tags: Vec<String>,
+    /// Model that produced the response
+    pub model: String,
+    /// Prompt template version
+    pub prompt_version: Option<String>,
+    /// Experiment variant
+    pub experiment_variant: Option<String>,
+    /// Tools called during the turn
+    pub tools: Vec<String>,
+    /// When the feedback was recorded
+    pub created_at: DateTime<Utc>,
+}
+
+impl FeedbackEntry {
+    /// Feedback on `response`, copying its enrichment fields
+    pub fn new(
+        response: &ResponseRecord,
+        rating: Rating,
+        comment: Option<String>,
+        tags: Vec<String>,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: response.session_id.clone(),
+            response_id: response.response_id.clone(),
+            message_index: response.message_index,
+            rating,
+            comment,
+            tags,
+            model: response.model.clone(),
+            prompt_version: response.prompt_version.clone(),
+            experiment_variant: response.experiment_variant.clone(),
+            tools: response.tools.clone(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Override the timestamp (e.g. when importing)
+    pub fn at(mut self, at: DateTime<Utc>) -> Self {
+        self.created_at = at;
+        self
+    }
+
+    fn keys(&self, dimension: FeedbackDimension) -> Vec<String> {
+        let or_unset =
+            |value: &Option<String>| value.clone().unwrap_or_else(|| UNSET_KEY.to_string());
+        match dimension {
+            FeedbackDimension::Model => vec![self.model.clone()],
+            FeedbackDimension::PromptVersion => vec![or_unset(&self.prompt_version)],
+            FeedbackDimension::ExperimentVariant => vec![or_unset(&self.experiment_variant)],
+            FeedbackDimension::Tool if self.tools.is_empty() => vec![UNSET_KEY.to_string()],
+            FeedbackDimension::Tool => self.tools.clone(),
+        }
+    }
+}
+
+/// Dimension to slice feedback by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedbackDimension {
+    /// Model name
+    Model,
+    /// Prompt template version
+    PromptVersion,
+    /// Experiment variant
+    ExperimentVariant,
+    /// Each tool called in the turn (an entry counts once per tool)
+    Tool,
+}
+
+/// Feedback totals for one dimension value
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedbackAggregate {
+    /// Dimension value ([`UNSET_KEY`] when not set)
+    pub key: String,
+    /// Feedback entries
+    pub count: usize,
+    /// Positive ratings
+    pub positive: usize,
+    /// Negative ratings
+    pub negative: usize,
+    /// Mean normalized rating
+    pub mean_score: f64,
+}
+
+impl FeedbackAggregate {
+    /// Share of positive ratings
+    pub fn positive_rate(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.positive as f64 / self.count as f64
+        }
+    }
+}
+
+/// One line of the feedback log
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum FeedbackLine {
+    Response(ResponseRecord),
+    Feedback(FeedbackEntry),
+}
+
+#[derive(Default)]
+struct FeedbackState {
+    responses: HashMap<String, ResponseRecord>,
+    feedback: Vec<FeedbackEntry>,
+}
+
+/// Response records and feedback, optionally persisted as an append-only JSONL file
+pub struct FeedbackStore {
+    path: Option<PathBuf>,
+    state: RwLock<FeedbackState>,
+    writer: tokio::sync::Mutex<()>,
+}
+
+impl FeedbackStore {
+    /// Store that keeps everything in memory
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            state: RwLock::new(FeedbackState::default()),
+            writer: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Open a JSONL-backed store, loading any existing log
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut state = FeedbackState::default();
+        if path.exists() {
+            let content = tokio::fs::read_to_string(&path).await?;
+            for (n, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str(line) {
+                    Ok(FeedbackLine::Response(record)) => {
+                        state.responses.insert(record.response_id.clone(), record);
+                    }
+                    Ok(FeedbackLine::Feedback(entry)) => state.feedback.push(entry),
+                    Err(e) => tracing::warn!(
+                        "Skipping malformed feedback line {} in {:?}: {}",
+                        n + 1,
+                        path,
+                        e
+                    ),
+                }
+            }
+        }
+        Ok(Self {
+            path: Some(path),
+            state: RwLock::new(state),
+            writer: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Remember what the agent knew when producing a response
+    pub async fn register_response(&self, record: ResponseRecord) -> Result<()> {
+        self.append(&FeedbackLine::Response(record.clone())).await?;
+        self.state
+            .write()
+            .responses
+            .insert(record.response_id.clone(), record);
+        Ok(())
+    }
+
+    /// Look up a response record
+    pub fn response(&self, response_id: &str) -> Option<ResponseRecord> {
+        self.state.read().responses.get(response_id).cloned()
+    }
+
+    /// Most recent response registered for a session
+    pub fn latest_response(&self, session_id: &str) -> Option<ResponseRecord> {
+        self.state
+            .read()
+            .responses
+            .values()
+            .filter(|r| r.session_id == session_id)
+            .max_by_key(|r| (r.created_at, r.message_index))
+            .cloned()
+    }
+
+    /// Persist a feedback entry
+    pub async fn submit(&self, entry: FeedbackEntry) -> Result<()> {
+        self.append(&FeedbackLine::Feedback(entry.clone())).await?;
+        self.state.write().feedback.push(entry);
+        Ok(())
+    }
+
+    /// All feedback for a session, oldest first
+    pub fn for_session(&self, session_id: &str) -> Vec<FeedbackEntry> {
+        self.state
+            .read()
+            .feedback
+            .iter()
+            .filter(|e| e.session_id == session_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Feedback recorded within `range`, oldest first
+    pub fn export(&self, range: Range<DateTime<Utc>>) -> Vec<FeedbackEntry> {
+        self.state
+            .read()
+            .feedback
+            .iter()
+            .filter(|e| range.contains(&e.created_at))
+            .cloned()
+            .collect()
+    }
+
+    /// Feedback within `range` as JSONL, one entry per line
+    pub fn export_jsonl(&self, range: Range<DateTime<Utc>>) -> Result<String> {
+        let mut out = String::new();
+        for entry in self.export(range) {
+            out.push_str(&serde_json::to_string(&entry)?);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Rating totals per dimension value within `range`, sorted by key
+    pub fn aggregate(
+        &self,
+        dimension: FeedbackDimension,
+        range: Range<DateTime<Utc>>,
+    ) -> Vec<FeedbackAggregate> {
+        let mut totals: BTreeMap<String, (usize, usize, f64)> = BTreeMap::new();
+        for entry in self.export(range) {
+            for key in entry.keys(dimension) {
+                let total = totals.entry(key).or_default();
+                total.0 += 1;
+                if entry.rating.is_positive() {
+                    total.1 += 1;
+                }
+                total.2 += entry.rating.value() as f64;
+            }
+        }
+        totals
+            .into_iter()
+            .map(|(key, (count, positive, sum))| FeedbackAggregate {
+                key,
+                count,
+                positive,
+                negative: count - positive,
+                mean_score: sum / count as f64,
+            })
+            .collect()
+    }
+
+    async fn append(&self, line: &FeedbackLine) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut json = serde_json::to_string(line)?;
+        json.push('\n');
+
+        let _guard = self.writer.lock().await;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.ok();
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        file.write_all(json.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{PriceProvider, PriceTool};
+    use crate::agent::core::{Agent, AgentEvent};
+    use crate::agent::memory::Memory;
+    use crate::agent::message::{Message, Role};
+    use crate::agent::provider::{ChatRequest, Provider};
+    use crate::agent::session::AgentSession;
+    use crate::agent::streaming::{MockStreamBuilder, StreamingResponse};
+    use crate::error::Error;
+    use crate::skills::tool::{Tool, ToolDefinition};
+    use async_trait::async_trait;
+    use chrono::Duration;
+    use std::sync::Arc;
+
+    /// Memory that only keeps sessions
+    #[derive(Default)]
+    struct Sessions(parking_lot::Mutex<HashMap<String, AgentSession>>);
+
+    #[async_trait]
+    impl Memory for Sessions {
+        async fn store(&self, _: &str, _: Option<&str>, _: Message) -> Result<()> {
+            Ok(())
+        }
+
+        async fn retrieve(&self, _: &str, _: Option<&str>, _: usize) -> Vec<Message> {
+            Vec::new()
+        }
+
+        async fn clear(&self, _: &str, _: Option<&str>) -> Result<()> {
+            Ok(())
+        }
+
+        async fn undo(&self, _: &str, _: Option<&str>) -> Result<Option<Message>> {
+            Ok(None)
+        }
+
+        async fn store_session(&self, session: AgentSession) -> Result<()> {
+            self.0.lock().insert(session.id.clone(), session);
+            Ok(())
+        }
+
+        async fn retrieve_session(&self, id: &str) -> Result<Option<AgentSession>> {
+            Ok(self.0.lock().get(id).cloned())
+        }
+    }
+
+    fn last_response_id(events: &mut tokio::sync::broadcast::Receiver<AgentEvent>) -> String {
+        let mut id = None;
+        while let Ok(event) = events.try_recv() {
+            if let AgentEvent::Response { response_id, .. } = event {
+                id = Some(response_id);
+            }
+        }
+        id.expect("no response event")
+    }
+
+    #[tokio::test]
+    async fn test_agent_feedback_survives_resume() {
+        let memory = Arc::new(Sessions::default());
+        let store = Arc::new(FeedbackStore::in_memory());
+        let agent = Agent::builder(PriceProvider)
+            .model("gpt-4o")
+            .prompt_version("v3")
+            .experiment_variant("terse")
+            .tool(PriceTool)
+            .with_feedback(Arc::clone(&store))
+            .with_memory(memory.clone())
+            .session_id("s1")
+            .auto_load_skills(false)
+            .introspection(false)
+            .build()
+            .unwrap();
+
+        let mut events = agent.subscribe();
+        agent.prompt("price of SOL?").await.unwrap();
+        let first_id = last_response_id(&mut events);
+
+        let stored = memory.retrieve_session("s1").await.unwrap().unwrap();
+        assert_eq!(stored.messages.len(), 4);
+        assert_eq!(
+            stored.messages[3].response_id.as_deref(),
+            Some(first_id.as_str())
+        );
+
+        agent.resume("s1").await.unwrap();
+        let second_id = last_response_id(&mut events);
+        assert_ne!(first_id, second_id);
+        let stored = memory.retrieve_session("s1").await.unwrap().unwrap();
+        assert_eq!(
+            stored.messages[3].response_id.as_deref(),
+            Some(first_id.as_str())
+        );
+
+        let entry = agent
+            .record_feedback(
+                "s1",
+                3usize,
+                Rating::Down,
+                Some("stale price".into()),
+                vec!["price".into()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(entry.response_id, first_id);
+        assert_eq!(entry.model, "gpt-4o");
+        assert_eq!(entry.prompt_version.as_deref(), Some("v3"));
+        assert_eq!(entry.experiment_variant.as_deref(), Some("terse"));
+        assert_eq!(entry.tools, vec!["price".to_string()]);
+
+        let resumed = agent
+            .record_feedback("s1", second_id.as_str(), Rating::Up, None, vec![])
+            .await
+            .unwrap();
+        assert_eq!(resumed.message_index, 4);
+        assert!(resumed.tools.is_empty());
+        assert_eq!(store.for_session("s1").len(), 2);
+
+        for target in [
+            ResponseRef::from("missing"),
+            ResponseRef::from(0usize),
+            ResponseRef::from(9usize),
+        ] {
+            let err = agent
+                .record_feedback("s1", target, Rating::Up, None, vec![])
+                .await
+                .unwrap_err();
+            assert!(matches!(err, Error::ResponseNotFound { .. }), "{}", err);
+        }
+        let err = agent
+            .record_feedback("other", first_id.as_str(), Rating::Up, None, vec![])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ResponseNotFound { .. }));
+    }
+
+    fn record(id: &str, model: &str, version: Option<&str>, tools: &[&str]) -> ResponseRecord {
+        ResponseRecord {
+            response_id: id.to_string(),
+            session_id: "s1".to_string(),
+            message_index: 1,
+            model: model.to_string(),
+            prompt_version: version.map(str::to_string),
+            experiment_variant: None,
+            tools: tools.iter().map(|t| t.to_string()).collect(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_aggregation_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("feedback.jsonl");
+        let store = FeedbackStore::open(&path).await.unwrap();
+
+        let a = record("r1", "gpt-4o", Some("v1"), &["swap", "price"]);
+        let b = record("r2", "gpt-4o", Some("v2"), &["price"]);
+        let c = record("r3", "claude", None, &[]);
+        for r in [&a, &b, &c] {
+            store.register_response(r.clone()).await.unwrap();
+        }
+        store
+            .submit(FeedbackEntry::new(&a, Rating::Up, None, vec![]))
+            .await
+            .unwrap();
+        store
+            .submit(FeedbackEntry::new(
+                &b,
+                Rating::Down,
+                Some("wrong".into()),
+                vec![],
+            ))
+            .await
+            .unwrap();
+        store
+            .submit(FeedbackEntry::new(&b, Rating::Score(0.75), None, vec![]))
+            .await
+            .unwrap();
+        store
+            .submit(FeedbackEntry::new(&c, Rating::Score(0.25), None, vec![]))
+            .await
+            .unwrap();
+
+        let now = Utc::now();
+        let range = now - Duration::hours(1)..now + Duration::hours(1);
+
+        let by_model = store.aggregate(FeedbackDimension::Model, range.clone());
+        assert_eq!(by_model.len(), 2);
+        let gpt = &by_model[1];
+        assert_eq!(
+            (gpt.key.as_str(), gpt.count, gpt.positive, gpt.negative),
+            ("gpt-4o", 3, 2, 1)
+        );
+        assert!((gpt.mean_score - 1.75 / 3.0).abs() < 1e-9);
+        assert!((gpt.positive_rate() - 2.0 / 3.0).abs() < 1e-9);
+
+        let by_tool = store.aggregate(FeedbackDimension::Tool, range.clone());
+        let keys: Vec<_> = by_tool.iter().map(|a| (a.key.as_str(), a.count)).collect();
+        assert_eq!(keys, vec![(UNSET_KEY, 1), ("price", 3), ("swap", 1)]);
+
+        let by_version = store.aggregate(FeedbackDimension::PromptVersion, range.clone());
+        assert_eq!(by_version[0].key, UNSET_KEY);
+
+        // Nothing outside the range
+        let later = now + Duration::hours(2)..now + Duration::hours(3);
+        assert!(store.aggregate(FeedbackDimension::Model, later).is_empty());
+
+        let reopened = FeedbackStore::open(&path).await.unwrap();
+        assert_eq!(reopened.response("r2"), Some(b));
+        assert_eq!(reopened.for_session("s1").len(), 4);
+        assert_eq!(reopened.export_jsonl(range).unwrap().lines().count(), 4);
+    }
+}
@@ -0,0 +1,287 @@
+//! Rolling summary of old conversation history
+//!
+//! Windowing drops the oldest messages, and with them facts the user stated
+//! long ago. With [`ContextConfig::summarize`] set, the [`ContextManager`]
+//! instead folds old messages into a single system message once too many are
+//! not yet covered by the summary. The summary is cached and extended
+//! incrementally: each provider call only sees the previous summary and the
+//! newly folded messages. History is only ever cut between exchanges, so a tool
+//! call is never separated from its results, and the last user message always
+//! stays verbatim.
+//!
+//! Summaries are a convenience: if a summary call fails, the previous summary
+//! (if any) is kept and the rest of the history is windowed as usual.
+//!
+//! [`ContextConfig::summarize`]: crate::agent::context::ContextConfig::summarize
+//! [`ContextManager`]: crate::agent::context::ContextManager
+
+use std::fmt::Write;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+use crate::agent::budget;
+use crate::agent::context::{exchange_units, section_text};
+use crate::agent::message::{Message, Role};
+use crate::agent::provider::{ChatRequest, Provider};
+use crate::error::{Error, Result};
+
+/// Text the summary message starts with
+pub const SUMMARY_PREFIX: &str = "Conversation summary so far: ";
+
+/// Instructions for summary calls
+const SUMMARY_INSTRUCTIONS: &str = "You compress conversations for an assistant's own future reference. \
+Keep every fact, preference, number and decision the user stated, and anything still pending. \
+Reply with the summary only.";
+
+/// When and how old history is summarized
+#[derive(Debug, Clone)]
+pub struct SummarizeConfig {
+    /// Summarize once more than this many messages are not covered by the summary;
+    /// the newest half of them stays verbatim
+    pub trigger_messages: usize,
+    /// Length the summary should stay within, in tokens
+    pub target_tokens: usize,
+    /// Model for summary calls; `None` uses the agent's model
+    pub model: Option<String>,
+}
+
+impl Default for SummarizeConfig {
+    fn default() -> Self {
+        Self {
+            trigger_messages: 16,
+            target_tokens: 512,
+            model: None,
+        }
+    }
+}
+
+/// Cached summary of a history prefix
+#[derive(Debug, Clone)]
+struct Summary {
+    /// Leading history messages the summary replaces
+    covered: usize,
+    /// Fingerprint of those messages, to notice a different history
+    fingerprint: u64,
+    text: String,
+}
+
+/// Folds old history into a cached summary through a provider
+pub struct HistorySummarizer {
+    config: SummarizeConfig,
+    provider: Arc<dyn Provider>,
+    cached: tokio::sync::Mutex<Option<Summary>>,
+}
+
+impl HistorySummarizer {
+    /// Summarize with `provider` as set out in `config`
+    pub fn new(config: SummarizeConfig, provider: Arc<dyn Provider>) -> Self {
+        Self {
+            config,
+            provider,
+            cached: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// The summary message for `history` and how many leading messages it replaces
+    ///
+    /// Calls the provider only when the uncovered tail grew past the trigger.
+    pub async fn summarize(&self, history: &[Message]) -> Option<(Message, usize)> {
+        let mut cached = self.cached.lock().await;
+        // The cache only applies while history still starts with the messages it covers
+        let mut summary = cached
+            .take()
+            .filter(|s| s.covered <= history.len() && fingerprint(&history[..s.covered]) == s.fingerprint);
+        let covered = summary.as_ref().map_or(0, |s| s.covered);
+
+        if history.len() - covered > self.config.trigger_messages {
+            let cut = self.fold_point(history, covered);
+            if cut > covered {
+                let previous = summary.as_ref().map(|s| s.text.as_str());
+                match self.request(previous, &history[covered..cut]).await {
+                    Ok(text) => {
+                        tracing::debug!("Summarized history up to message {} ({} new)", cut, cut - covered);
+                        summary = Some(Summary {
+                            covered: cut,
+                            fingerprint: fingerprint(&history[..cut]),
+                            text,
+                        });
+                    }
+                    Err(e) => tracing::warn!("History summary failed, keeping the previous one: {}", e),
+                }
+            }
+        }
+
+        *cached = summary.clone();
+        summary.map(|s| (Message::system(format!("{}{}", SUMMARY_PREFIX, s.text)), s.covered))
+    }
+
+    /// Where to cut history so the newest half of the trigger stays verbatim
+    ///
+    /// The cut falls between exchanges and never past the last user message.
+    fn fold_point(&self, history: &[Message], covered: usize) -> usize {
+        let keep = (self.config.trigger_messages / 2).max(1);
+        let mut limit = history.len().saturating_sub(keep);
+        if let Some(pinned) = history.iter().rposition(|m| m.role == Role::User) {
+            limit = limit.min(pinned);
+        }
+        exchange_units(history, covered)
+            .into_iter()
+            .map(|unit| unit.start)
+            .filter(|&start| start <= limit)
+            .max()
+            .unwrap_or(covered)
+    }
+
+    /// Ask the provider to extend `previous` with `messages`
+    async fn request(&self, previous: Option<&str>, messages: &[Message]) -> Result<String> {
+        budget::spend_provider_attempt("history_summary")?;
+
+        let mut prompt = String::new();
+        if let Some(previous) = previous {
+            let _ = writeln!(prompt, "Summary so far:\n{}\n", previous);
+        }
+        prompt.push_str("Messages to fold into the summary:\n");
+        for message in messages {
+            let _ = writeln!(prompt, "{}: {}", message.role.as_str(), section_text(message));
+        }
+        let _ = write!(
+            prompt,
+            "\nWrite the updated summary in at most {} tokens.",
+            self.config.target_tokens
+        );
+
+        let request = ChatRequest {
+            model: self.config.model.clone().unwrap_or_default(),
+            system_prompt: Some(SUMMARY_INSTRUCTIONS.to_string()),
+            messages: vec![Message::user(prompt)],
+            temperature: Some(0.0),
+            max_tokens: Some(self.config.target_tokens as u64),
+            ..Default::default()
+        };
+        let text = self.provider.complete(request).await?.text.trim().to_string();
+        if text.is_empty() {
+            return Err(Error::Internal("Provider returned an empty summary".to_string()));
+        }
+        Ok(text)
+    }
+}
+
+/// Hash of `messages`, including tool calls and results
+fn fingerprint(messages: &[Message]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for message in messages {
+        serde_json::to_string(message).unwrap_or_default().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::context::{CharEstimate, ContextConfig, ContextManager};
+    use crate::agent::message::{Content, ContentPart};
+    use crate::agent::streaming::{MockStreamBuilder, StreamingResponse};
+    use parking_lot::Mutex;
+
+    /// Answers every summary request with "Summary N", keeping the prompts
+    #[derive(Default)]
+    struct CannedSummaries {
+        prompts: Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Provider for CannedSummaries {
+        async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+            let mut prompts = self.prompts.lock();
+            prompts.push(request.messages[0].content.as_text());
+            Ok(MockStreamBuilder::new()
+                .message(format!("Summary {}", prompts.len()))
+                .done()
+                .build())
+        }
+
+        fn name(&self) -> &'static str {
+            "canned"
+        }
+    }
+
+    fn manager(provider: Arc<CannedSummaries>) -> ContextManager {
+        let mut mgr = ContextManager::new(ContextConfig {
+            summarize: Some(SummarizeConfig {
+                trigger_messages: 4,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        mgr.set_token_counter(Arc::new(CharEstimate));
+        mgr.set_summary_provider(provider);
+        mgr
+    }
+
+    fn texts(context: &[Message]) -> Vec<String> {
+        context.iter().map(|m| m.content.as_text()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_old_history_is_replaced_by_an_extended_summary() {
+        let provider = Arc::new(CannedSummaries::default());
+        let mgr = manager(provider.clone());
+        let mut history = vec![
+            Message::user("My stop loss is always 5%."),
+            Message::assistant("Noted."),
+            Message::user("I only trade SOL."),
+            Message::assistant("Got it."),
+            Message::user("What is SOL at?"),
+        ];
+
+        let context = texts(&mgr.build_context(&history).await.unwrap());
+        assert_eq!(
+            context,
+            [
+                format!("{}Summary 1", SUMMARY_PREFIX),
+                "Got it.".to_string(),
+                "What is SOL at?".to_string(),
+            ]
+        );
+        assert!(provider.prompts.lock()[0].contains("user: My stop loss is always 5%."));
+
+        // Cached until the uncovered tail passes the trigger again
+        history.push(Message::assistant("$150."));
+        mgr.build_context(&history).await.unwrap();
+        assert_eq!(provider.prompts.lock().len(), 1);
+
+        history.extend([Message::user("Buy 1 SOL."), Message::assistant("Done.")]);
+        history.push(Message::user("And now?"));
+        let context = texts(&mgr.build_context(&history).await.unwrap());
+        assert_eq!(context[0], format!("{}Summary 2", SUMMARY_PREFIX));
+        assert_eq!(context[1..], ["Done.", "And now?"]);
+        let prompts = provider.prompts.lock();
+        assert!(prompts[1].starts_with("Summary so far:\nSummary 1"));
+        assert!(!prompts[1].contains("My stop loss"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_exchanges_are_not_split() {
+        let provider = Arc::new(CannedSummaries::default());
+        let mgr = manager(provider);
+        let call = Message::assistant(Content::Parts(vec![ContentPart::ToolCall {
+            id: "call_1".to_string(),
+            name: "get_price".to_string(),
+            arguments: serde_json::json!({"symbol": "SOL"}),
+        }]));
+        let history = vec![
+            Message::user("Hi"),
+            Message::assistant("Hello"),
+            Message::user("Price of SOL?"),
+            call,
+            Message::tool_result("call_1", "150").with_tool_name("get_price"),
+            Message::user("Thanks"),
+        ];
+
+        // Keeping 2 would cut between the call and its result, so the cut moves back
+        let context = mgr.build_context(&history).await.unwrap();
+        assert_eq!(context.len(), 4);
+        assert_eq!(context[1].role, Role::Assistant);
+        assert_eq!(context[2].role, Role::Tool);
+    }
+}
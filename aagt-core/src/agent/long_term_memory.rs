@@ -0,0 +1,456 @@
+//! Long-term memory searched by meaning
+//!
+//! [`LongTermMemory`] writes every message to a [`VectorStore`], tagged with the
+//! user and agent it belongs to. Search is hybrid: the store's vector search,
+//! oversampled and filtered down to the caller's own entries, fused by
+//! reciprocal rank with keyword matches over recent messages. Vector stores
+//! know nothing about owners, so the filter always runs here and one user's
+//! memories never surface in another's results.
+//!
+//! [`remember`](Memory::remember) checks new knowledge against the caller's
+//! existing entries first and handles a near-duplicate by [`DedupPolicy`]:
+//! with an embedder attached, entries whose embedding cosine reaches the
+//! threshold count as the same; without one, only the same text (ignoring case,
+//! punctuation and spacing) does.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+
+use crate::agent::memory::{matches_terms, message_document, Memory, MemoryStatus, RememberOutcome};
+use crate::agent::message::Message;
+use crate::error::Result;
+use crate::knowledge::rag::{Document, Embeddings, VectorStore};
+use crate::knowledge::store::memory::cosine;
+
+/// Metadata key holding the owning user
+pub const USER_ID_KEY: &str = "user_id";
+/// Metadata key holding the owning agent, absent for user-wide entries
+pub const AGENT_ID_KEY: &str = "agent_id";
+/// Metadata key holding when a knowledge entry was stored or last merged into (RFC 3339)
+pub const UPDATED_AT_KEY: &str = "updated_at";
+
+/// Vector candidates fetched per requested result on the first pass
+const OVERSAMPLE: usize = 4;
+/// Widest pass, as a multiple of the requested results, before giving up on filling `limit`
+const MAX_OVERSAMPLE: usize = 64;
+/// Reciprocal-rank-fusion constant; damps the weight of the very top ranks
+const RRF_K: f32 = 60.0;
+/// Closest existing entries compared against new knowledge
+const DEDUP_CANDIDATES: usize = 8;
+
+/// What [`remember`](Memory::remember) does with knowledge that repeats an existing entry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DedupPolicy {
+    /// Keep the existing entry and drop the new knowledge
+    #[default]
+    Skip,
+    /// Append the new knowledge to the existing entry and bump its timestamp
+    Merge,
+    /// Store it as a new entry anyway
+    Store,
+}
+
+/// [`Memory`] over a vector store, scoped per user and agent
+///
+/// Recent messages are also kept in process (up to
+/// [`with_max_recent`](Self::with_max_recent) per conversation) for
+/// [`retrieve`](Memory::retrieve), [`undo`](Memory::undo),
+/// [`clear`](Memory::clear) and the keyword half of search; entries written by
+/// an earlier process are only reachable through vector search.
+pub struct LongTermMemory {
+    store: Arc<dyn VectorStore>,
+    /// Conversation key -> (store ID, message), oldest first
+    recent: DashMap<String, VecDeque<(String, Message)>>,
+    max_recent: usize,
+    /// Compares knowledge by meaning for deduplication
+    embedder: Option<Arc<dyn Embeddings>>,
+    dedup: DedupPolicy,
+    dedup_threshold: f32,
+}
+
+impl LongTermMemory {
+    /// Memory writing to `store`
+    pub fn new(store: Arc<dyn VectorStore>) -> Self {
+        Self {
+            store,
+            recent: DashMap::new(),
+            max_recent: 1000,
+            embedder: None,
+            dedup: DedupPolicy::default(),
+            dedup_threshold: 0.9,
+        }
+    }
+
+    /// Find duplicate knowledge by embedding similarity instead of exact text
+    pub fn with_embedder(mut self, embedder: Arc<dyn Embeddings>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    /// How [`remember`](Memory::remember) handles duplicates (default: skip)
+    pub fn with_dedup(mut self, policy: DedupPolicy) -> Self {
+        self.dedup = policy;
+        self
+    }
+
+    /// Cosine similarity at which knowledge counts as a duplicate (default: 0.9)
+    pub fn with_dedup_threshold(mut self, threshold: f32) -> Self {
+        self.dedup_threshold = threshold;
+        self
+    }
+
+    /// Keep at most `count` recent messages per conversation in process
+    pub fn with_max_recent(mut self, count: usize) -> Self {
+        self.max_recent = count.max(1);
+        self
+    }
+
+    fn key(user_id: &str, agent_id: Option<&str>) -> String {
+        match agent_id {
+            Some(agent) => format!("{}:{}", user_id, agent),
+            None => user_id.to_string(),
+        }
+    }
+
+    fn owner_metadata(user_id: &str, agent_id: Option<&str>) -> HashMap<String, String> {
+        let mut metadata = HashMap::from([(USER_ID_KEY.to_string(), user_id.to_string())]);
+        if let Some(agent) = agent_id {
+            metadata.insert(AGENT_ID_KEY.to_string(), agent.to_string());
+        }
+        metadata
+    }
+
+    /// Vector matches owned by the caller, widening the search until `limit` survive the filter
+    async fn semantic(&self, user_id: &str, agent_id: Option<&str>, query: &str, limit: usize) -> Result<Vec<Document>> {
+        let mut fetch = limit.saturating_mul(OVERSAMPLE);
+        loop {
+            let found = self.store.search(query, fetch).await?;
+            let exhausted = found.len() < fetch;
+            let mut own: Vec<Document> = found
+                .into_iter()
+                .filter(|doc| owned_by(doc, user_id, agent_id))
+                .collect();
+            if own.len() >= limit || exhausted || fetch >= limit.saturating_mul(MAX_OVERSAMPLE) {
+                own.truncate(limit);
+                return Ok(own);
+            }
+            fetch = fetch.saturating_mul(2);
+        }
+    }
+
+    /// The caller's knowledge entry that already says `content`, if any
+    async fn find_duplicate(&self, user_id: &str, agent_id: Option<&str>, content: &str) -> Result<Option<Document>> {
+        let candidates: Vec<Document> = self
+            .semantic(user_id, agent_id, content, DEDUP_CANDIDATES)
+            .await?
+            .into_iter()
+            .filter(|doc| doc.metadata.contains_key("collection"))
+            .collect();
+        let Some(embedder) = &self.embedder else {
+            let content = normalize(content);
+            return Ok(candidates.into_iter().find(|doc| normalize(&doc.content) == content));
+        };
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+        let new = embedder.embed(content).await?;
+        let texts: Vec<&str> = candidates.iter().map(|doc| doc.content.as_str()).collect();
+        let existing = embedder.embed_batch(&texts).await?;
+        Ok(candidates
+            .into_iter()
+            .zip(existing)
+            .map(|(doc, embedding)| (cosine(&new, &embedding), doc))
+            .filter(|(similarity, _)| *similarity >= self.dedup_threshold)
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, doc)| doc))
+    }
+
+    /// Recent messages containing every query term, newest first
+    fn keyword(&self, user_id: &str, agent_id: Option<&str>, query: &str, limit: usize) -> Vec<Document> {
+        let Some(recent) = self.recent.get(&Self::key(user_id, agent_id)) else {
+            return Vec::new();
+        };
+        recent
+            .iter()
+            .rev()
+            .filter(|(_, message)| matches_terms(&message.text(), query))
+            .take(limit)
+            .map(|(id, message)| {
+                let mut doc = message_document(id.clone(), message, 0.0);
+                doc.metadata = Self::owner_metadata(user_id, agent_id);
+                doc
+            })
+            .collect()
+    }
+}
+
+/// Lowercased words of `text`, without punctuation
+fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `doc` was stored for exactly this user and agent
+fn owned_by(doc: &Document, user_id: &str, agent_id: Option<&str>) -> bool {
+    doc.metadata.get(USER_ID_KEY).map(String::as_str) == Some(user_id)
+        && doc.metadata.get(AGENT_ID_KEY).map(String::as_str) == agent_id
+}
+
+/// Merge ranked lists by reciprocal rank; scores are scaled so a top hit in both is 1.0
+fn fuse(lists: [Vec<Document>; 2], limit: usize) -> Vec<Document> {
+    let mut fused: HashMap<String, (f32, Document)> = HashMap::new();
+    for list in lists {
+        for (rank, doc) in list.into_iter().enumerate() {
+            let weight = 1.0 / (RRF_K + rank as f32 + 1.0);
+            fused.entry(doc.id.clone()).or_insert((0.0, doc)).0 += weight;
+        }
+    }
+    let best = 2.0 / (RRF_K + 1.0);
+    let mut results: Vec<Document> = fused
+        .into_values()
+        .map(|(weight, doc)| Document {
+            score: weight / best,
+            ..doc
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results.truncate(limit);
+    results
+}
+
+#[async_trait]
+impl Memory for LongTermMemory {
+    async fn store(&self, user_id: &str, agent_id: Option<&str>, message: Message) -> Result<()> {
+        let text = message.text();
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+        let mut metadata = Self::owner_metadata(user_id, agent_id);
+        metadata.insert("role".to_string(), message.role.as_str().to_string());
+        let id = self.store.store(&text, metadata).await?;
+
+        let mut recent = self.recent.entry(Self::key(user_id, agent_id)).or_default();
+        if recent.len() >= self.max_recent {
+            recent.pop_front();
+        }
+        recent.push_back((id, message));
+        Ok(())
+    }
+
+    async fn retrieve(&self, user_id: &str, agent_id: Option<&str>, limit: usize) -> Vec<Message> {
+        self.recent
+            .get(&Self::key(user_id, agent_id))
+            .map(|recent| {
+                let skip = recent.len().saturating_sub(limit);
+                recent.iter().skip(skip).map(|(_, message)| message.clone()).collect()
+            })
+            .unwrap_or_default()
+    }
+
+    async fn search(&self, user_id: &str, agent_id: Option<&str>, query: &str, limit: usize) -> Result<Vec<Document>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let semantic = self.semantic(user_id, agent_id, query, limit).await?;
+        let keyword = self.keyword(user_id, agent_id, query, limit);
+        Ok(fuse([semantic, keyword], limit))
+    }
+
+    async fn store_knowledge(&self, user_id: &str, agent_id: Option<&str>, title: &str, content: &str, collection: &str) -> Result<()> {
+        let mut metadata = Self::owner_metadata(user_id, agent_id);
+        metadata.insert("title".to_string(), title.to_string());
+        metadata.insert("collection".to_string(), collection.to_string());
+        metadata.insert(UPDATED_AT_KEY.to_string(), chrono::Utc::now().to_rfc3339());
+        self.store.store(content, metadata).await?;
+        Ok(())
+    }
+
+    async fn remember(&self, user_id: &str, agent_id: Option<&str>, title: &str, content: &str, collection: &str) -> Result<RememberOutcome> {
+        let duplicate = match self.dedup {
+            DedupPolicy::Store => None,
+            _ => self.find_duplicate(user_id, agent_id, content).await?,
+        };
+        let Some(existing) = duplicate else {
+            self.store_knowledge(user_id, agent_id, title, content, collection).await?;
+            return Ok(RememberOutcome::Stored);
+        };
+        let existing_title = existing.metadata.get("title").cloned().unwrap_or(existing.title);
+        if self.dedup == DedupPolicy::Skip {
+            return Ok(RememberOutcome::Skipped { title: existing_title });
+        }
+
+        // Vector stores can't update in place: store the merged entry, then drop the old one
+        let mut metadata = existing.metadata;
+        metadata.insert(UPDATED_AT_KEY.to_string(), chrono::Utc::now().to_rfc3339());
+        let merged = format!("{}\n{}", existing.content, content);
+        self.store.store(&merged, metadata).await?;
+        self.store.delete(&existing.id).await?;
+        Ok(RememberOutcome::Merged { title: existing_title })
+    }
+
+    async fn clear(&self, user_id: &str, agent_id: Option<&str>) -> Result<()> {
+        if let Some((_, recent)) = self.recent.remove(&Self::key(user_id, agent_id)) {
+            for (id, _) in recent {
+                self.store.delete(&id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn undo(&self, user_id: &str, agent_id: Option<&str>) -> Result<Option<Message>> {
+        let last = self
+            .recent
+            .get_mut(&Self::key(user_id, agent_id))
+            .and_then(|mut recent| recent.pop_back());
+        match last {
+            Some((id, message)) => {
+                self.store.delete(&id).await?;
+                Ok(Some(message))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn status(&self, user_id: &str, agent_id: Option<&str>) -> MemoryStatus {
+        MemoryStatus {
+            short_term: None,
+            long_term: Some(self.recent.get(&Self::key(user_id, agent_id)).map_or(0, |r| r.len())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::rag::Embeddings;
+    use crate::knowledge::store::InMemoryVectorStore;
+    use crate::skills::tool::{RememberThisTool, Tool};
+
+    /// Counts mentions of a few tickers, so texts about the same coin embed close together
+    struct TickerEmbeddings;
+
+    #[async_trait]
+    impl Embeddings for TickerEmbeddings {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+            let text = text.to_lowercase();
+            Ok(["sol", "btc", "eth", "ledger"]
+                .iter()
+                .map(|t| text.matches(t).count() as f32)
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_semantic_search_never_crosses_users() {
+        let store = Arc::new(InMemoryVectorStore::with_embedder(Arc::new(TickerEmbeddings)));
+        let memory = LongTermMemory::new(store);
+
+        // Bob's notes match a SOL query as well as Alice's and crowd the first pass
+        for i in 0..12 {
+            memory.store("bob", None, Message::user(format!("SOL SOL entry {}", i))).await.unwrap();
+        }
+        memory.store("alice", None, Message::user("I'm holding SOL until summer")).await.unwrap();
+        memory.store("alice", None, Message::user("BTC cold storage is done")).await.unwrap();
+        memory.store("alice", Some("trader"), Message::user("SOL stop at 120")).await.unwrap();
+
+        let results = memory.search("alice", None, "sol position", 2).await.unwrap();
+        assert!(!results.is_empty());
+        assert_eq!(results[0].content, "I'm holding SOL until summer");
+        assert!(results.iter().all(|doc| owned_by(doc, "alice", None)));
+
+        let results = memory.search("bob", None, "sol", 20).await.unwrap();
+        assert_eq!(results.len(), 12);
+        assert!(results.iter().all(|doc| !doc.content.contains("summer")));
+
+        // Agent-scoped entries only answer for that agent
+        let results = memory.search("alice", Some("trader"), "sol", 5).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].content, "SOL stop at 120");
+
+        // Keyword matches fuse with vector hits; a message found by both ranks first
+        let results = memory.search("alice", None, "cold storage", 5).await.unwrap();
+        assert_eq!(results[0].content, "BTC cold storage is done");
+
+        assert_eq!(memory.undo("alice", None).await.unwrap().unwrap().text(), "BTC cold storage is done");
+        assert!(memory.search("alice", None, "btc", 5).await.unwrap().iter().all(|d| !d.content.contains("BTC")));
+    }
+
+    /// A user's knowledge entries, sorted by content
+    async fn knowledge(memory: &LongTermMemory, user_id: &str) -> Vec<Document> {
+        let mut entries: Vec<Document> = memory
+            .search(user_id, None, "sol btc eth", 20)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|doc| doc.metadata.contains_key("collection"))
+            .collect();
+        entries.sort_by(|a, b| a.content.cmp(&b.content));
+        entries
+    }
+
+    async fn remember(tool: &RememberThisTool, title: &str, content: &str) -> String {
+        let args = serde_json::json!({"title": title, "content": content});
+        tool.call(&args.to_string()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_remember_dedups_by_policy() {
+        for policy in [DedupPolicy::Skip, DedupPolicy::Merge, DedupPolicy::Store] {
+            let store = Arc::new(InMemoryVectorStore::with_embedder(Arc::new(TickerEmbeddings)));
+            let memory = LongTermMemory::new(store.clone())
+                .with_embedder(store.embedder().clone())
+                .with_dedup(policy);
+            let tool = RememberThisTool::new(Arc::new(memory));
+
+            assert!(remember(&tool, "SOL preference", "user prefers SOL").await.contains("successfully saved"));
+            // Unrelated knowledge is never a duplicate
+            assert!(remember(&tool, "Cold storage", "BTC is in cold storage").await.contains("successfully saved"));
+            let reply = remember(&tool, "Likes SOL", "the user likes SOL").await;
+
+            let memory = LongTermMemory::new(store);
+            // The tool stores for the placeholder user
+            let entries = knowledge(&memory, "default").await;
+            match policy {
+                DedupPolicy::Skip => {
+                    assert_eq!(
+                        reply,
+                        "Not saved: existing memory 'SOL preference' already says this. No need to remember it again."
+                    );
+                    assert_eq!(entries.len(), 2);
+                }
+                DedupPolicy::Merge => {
+                    assert!(reply.starts_with("Merged into existing memory 'SOL preference'"), "{}", reply);
+                    assert_eq!(entries.len(), 2);
+                    let merged = entries.iter().find(|doc| doc.title == "SOL preference").unwrap();
+                    assert_eq!(merged.content, "user prefers SOL\nthe user likes SOL");
+                }
+                DedupPolicy::Store => {
+                    assert!(reply.contains("successfully saved"));
+                    assert_eq!(entries.len(), 3);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remember_without_embedder_matches_normalized_text() {
+        let memory = LongTermMemory::new(Arc::new(InMemoryVectorStore::with_embedder(Arc::new(TickerEmbeddings))));
+
+        assert_eq!(memory.remember("alice", None, "Pref", "User prefers SOL.", "preferences").await.unwrap(), RememberOutcome::Stored);
+        assert_eq!(
+            memory.remember("alice", None, "Pref again", "  user PREFERS sol ", "general").await.unwrap(),
+            RememberOutcome::Skipped { title: "Pref".to_string() }
+        );
+        // Similar meaning but different words: only an embedder would catch it
+        assert_eq!(memory.remember("alice", None, "Likes", "the user likes SOL", "preferences").await.unwrap(), RememberOutcome::Stored);
+        // Another user's identical entry is not a duplicate
+        assert_eq!(memory.remember("bob", None, "Pref", "User prefers SOL.", "preferences").await.unwrap(), RememberOutcome::Stored);
+        assert_eq!(knowledge(&memory, "alice").await.len(), 2);
+    }
+}
@@ -1,8 +1,13 @@
 //! Memory system for agents
 //!
 //! Provides short-term (conversation) and long-term (persistent) memory.
+//!
+//! Versioned stores also answer as-of queries ([`Memory::search_as_of`],
+//! [`Memory::fetch_document_as_of`]). Short-term memory is not versioned:
+//! it only ever holds the live conversation.
 
 use std::collections::VecDeque;
+use std::future::Future;
 use std::sync::Arc;
 
 use dashmap::DashMap;
@@ -14,6 +19,76 @@ use std::sync::Weak;
 use async_trait::async_trait;
 
 use crate::agent::scheduler::Scheduler;
+use crate::infra::encryption::{self, EncryptedStore, EncryptionConfig, EncryptionProvider};
+use crate::infra::instance::InstanceLock;
+
+tokio::task_local! {
+    static AS_OF: i64;
+}
+
+/// Run `fut` with memory reads pinned to `as_of` (unix seconds)
+///
+/// Memory tools query versioned stores as of this time unless a call names
+/// its own, so a replay sees what the agent could see back then.
+pub async fn with_as_of<F: Future>(as_of: i64, fut: F) -> F::Output {
+    AS_OF.scope(as_of, fut).await
+}
+
+/// The time set by [`with_as_of`] for the current task
+pub fn current_as_of() -> Option<i64> {
+    AS_OF.try_with(|t| *t).ok()
+}
+
+/// Parse an as-of time: unix seconds, RFC 3339, or `YYYY-MM-DD` (end of that day, UTC)
+pub fn parse_as_of(value: &str) -> crate::error::Result<i64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<i64>() {
+        return Ok(secs);
+    }
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.timestamp());
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|day| day.and_hms_opt(23, 59, 59))
+        .map(|end| end.and_utc().timestamp())
+        .ok_or_else(|| {
+            crate::error::Error::MemoryRetrieval(format!(
+                "Invalid as_of '{}': expected unix seconds, RFC 3339 or YYYY-MM-DD",
+                value
+            ))
+        })
+}
+
+/// Messages scanned by the default, recency-based [`Memory::search`]
+pub const RECENT_SEARCH_WINDOW: usize = 200;
+
+/// Whether `text` contains every whitespace-separated term of `query`, ignoring case
+pub(crate) fn matches_terms(text: &str, query: &str) -> bool {
+    let text = text.to_lowercase();
+    let mut terms = query.split_whitespace().peekable();
+    terms.peek().is_some() && terms.all(|term| text.contains(&term.to_lowercase()))
+}
+
+/// A remembered message as a search result
+pub(crate) fn message_document(id: String, message: &Message, score: f32) -> crate::knowledge::rag::Document {
+    crate::knowledge::rag::Document {
+        id,
+        title: format!("Recent conversation ({})", message.role.as_str()),
+        content: message.text(),
+        summary: None,
+        collection: None,
+        path: None,
+        metadata: HashMap::new(),
+        score,
+    }
+}
+
+fn unversioned() -> crate::error::Error {
+    crate::error::Error::MemoryRetrieval(
+        "This memory keeps no history; as-of queries need a versioned store such as QMD".to_string(),
+    )
+}
 
 /// Trait for memory implementations
 #[async_trait]
@@ -33,9 +108,32 @@ pub trait Memory: Send + Sync {
     async fn retrieve(&self, user_id: &str, agent_id: Option<&str>, limit: usize) -> Vec<Message>;
 
     /// Search the memory for relevant content
+    ///
+    /// The default scans the last [`RECENT_SEARCH_WINDOW`] messages from
+    /// [`retrieve`](Memory::retrieve) for every query term, newest first.
+    /// Stores that can search by meaning override it.
     async fn search(&self, user_id: &str, agent_id: Option<&str>, query: &str, limit: usize) -> crate::error::Result<Vec<crate::knowledge::rag::Document>> {
-        let _ = (user_id, agent_id, query, limit);
-        Ok(Vec::new())
+        let messages = self.retrieve(user_id, agent_id, RECENT_SEARCH_WINDOW).await;
+        Ok(messages
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, message)| matches_terms(&message.text(), query))
+            .take(limit)
+            .enumerate()
+            .map(|(rank, (i, message))| message_document(format!("recent_{}", i), message, 1.0 / (rank + 1) as f32))
+            .collect())
+    }
+
+    /// Search the memory as it was at `as_of` (unix seconds); `None` is [`Memory::search`]
+    ///
+    /// Only versioned stores can answer for a past time. The default errors
+    /// for `Some` rather than silently returning current content.
+    async fn search_as_of(&self, user_id: &str, agent_id: Option<&str>, query: &str, limit: usize, as_of: Option<i64>) -> crate::error::Result<Vec<crate::knowledge::rag::Document>> {
+        match as_of {
+            None => self.search(user_id, agent_id, query, limit).await,
+            Some(_) => Err(unversioned()),
+        }
     }
 
     /// Store a specific piece of knowledge (not just a message)
@@ -44,6 +142,15 @@ pub trait Memory: Send + Sync {
         Ok(())
     }
 
+    /// Store knowledge unless it repeats an existing entry, reporting what was done
+    ///
+    /// The default always stores through [`store_knowledge`](Memory::store_knowledge);
+    /// stores that can find duplicates override it.
+    async fn remember(&self, user_id: &str, agent_id: Option<&str>, title: &str, content: &str, collection: &str) -> crate::error::Result<RememberOutcome> {
+        self.store_knowledge(user_id, agent_id, title, content, collection).await?;
+        Ok(RememberOutcome::Stored)
+    }
+
     /// Clear memory for a user
     async fn clear(&self, user_id: &str, agent_id: Option<&str>) -> crate::error::Result<()>;
 
@@ -65,6 +172,20 @@ pub trait Memory: Send + Sync {
         Ok(None)
     }
 
+    /// Fetch the revision of a document current at `as_of`; `None` is [`Memory::fetch_document`]
+    async fn fetch_document_as_of(&self, collection: &str, path: &str, as_of: Option<i64>) -> crate::error::Result<Option<crate::knowledge::rag::Document>> {
+        match as_of {
+            None => self.fetch_document(collection, path).await,
+            Some(_) => Err(unversioned()),
+        }
+    }
+
+    /// Fetch a full document by its ID (e.g. a QMD docid)
+    async fn fetch_document_by_id(&self, id: &str) -> crate::error::Result<Option<crate::knowledge::rag::Document>> {
+        let _ = id;
+        Ok(None)
+    }
+
     /// Store an agent session state
     async fn store_session(&self, _session: crate::agent::session::AgentSession) -> crate::error::Result<()> {
         Ok(())
@@ -74,6 +195,107 @@ pub trait Memory: Send + Sync {
     async fn retrieve_session(&self, _session_id: &str) -> crate::error::Result<Option<crate::agent::session::AgentSession>> {
         Ok(None)
     }
+
+    /// List every stored agent session
+    async fn list_sessions(&self) -> crate::error::Result<Vec<crate::agent::session::AgentSession>> {
+        Ok(Vec::new())
+    }
+
+    /// Delete a stored agent session; returns false if it didn't exist or sessions aren't stored
+    async fn delete_session(&self, session_id: &str) -> crate::error::Result<bool> {
+        let _ = session_id;
+        Ok(false)
+    }
+
+    /// Sessions matching `query` without their history, most recently updated first
+    ///
+    /// The default loads every session through [`list_sessions`](Self::list_sessions);
+    /// stores that keep an index should override it.
+    async fn list_session_summaries(&self, query: &crate::agent::session::SessionQuery) -> crate::error::Result<Vec<crate::agent::session::SessionSummary>> {
+        let mut summaries: Vec<_> = self
+            .list_sessions()
+            .await?
+            .iter()
+            .map(|session| session.summary())
+            .filter(|summary| query.matches(summary))
+            .collect();
+        summaries.sort_by_key(|summary| std::cmp::Reverse(summary.updated_at));
+        Ok(summaries
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    /// Delete sessions not updated for `age`; returns how many were deleted
+    async fn delete_sessions_older_than(&self, age: std::time::Duration) -> crate::error::Result<usize> {
+        let Some(cutoff) = chrono::Duration::from_std(age)
+            .ok()
+            .and_then(|age| chrono::Utc::now().checked_sub_signed(age))
+        else {
+            return Ok(0);
+        };
+        let mut deleted = 0;
+        for session in self.list_sessions().await? {
+            if session.updated_at < cutoff && self.delete_session(&session.id).await? {
+                deleted += 1;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Try to take a named lease for `ttl`; returns false while another holder owns it
+    ///
+    /// The default always grants the lease, which is only correct for stores that
+    /// are not shared between processes.
+    async fn try_acquire_lease(&self, name: &str, holder: &str, ttl: std::time::Duration) -> crate::error::Result<bool> {
+        let _ = (name, holder, ttl);
+        Ok(true)
+    }
+
+    /// Entry counts for a user (fields are `None` when the backend doesn't track them)
+    async fn status(&self, _user_id: &str, _agent_id: Option<&str>) -> MemoryStatus {
+        MemoryStatus::default()
+    }
+}
+
+/// What [`Memory::remember`] did with a piece of knowledge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RememberOutcome {
+    /// Stored as a new entry
+    Stored,
+    /// Appended to the existing entry with this title
+    Merged {
+        /// Title of the entry it was merged into
+        title: String,
+    },
+    /// Dropped because the existing entry with this title already says it
+    Skipped {
+        /// Title of the matching entry
+        title: String,
+    },
+}
+
+/// Entry counts reported by [`Memory::status`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct MemoryStatus {
+    /// Entries in short-term (conversation) memory
+    pub short_term: Option<usize>,
+    /// Entries in long-term (persistent) memory
+    pub long_term: Option<usize>,
+}
+
+/// One conversation buffer reported by [`ShortTermMemory::conversations`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversationUsage {
+    /// Storage key (`user` or `user:agent`)
+    pub key: String,
+    /// Owning user
+    pub user_id: String,
+    /// Time since the conversation was last used
+    pub idle: std::time::Duration,
+    /// Approximate size of the stored text
+    pub bytes: u64,
 }
 
 /// Short-term memory - stores recent conversation history
@@ -90,31 +312,91 @@ pub struct ShortTermMemory {
     last_access: DashMap<String, std::time::Instant>,
     /// Persistence path
     path: PathBuf,
+    /// Encrypts the snapshot file when set
+    encryption: Option<Arc<dyn EncryptionProvider>>,
+    /// Gates snapshot writes on holding the data directory
+    instance_lock: Option<Arc<InstanceLock>>,
+    /// Why the snapshot failed to load; saves are refused so it is not overwritten
+    unreadable: Option<crate::error::Error>,
 }
 
+/// Associated data binding encrypted snapshots to short-term memory
+const SHORT_TERM_AAD: &[u8] = b"short_term_memory";
+
 impl ShortTermMemory {
     /// Create with custom capacity and persistence path
     pub async fn new(max_messages: usize, max_users: usize, path: impl Into<PathBuf>) -> Self {
-        let path = path.into();
+        Self::open(max_messages, max_users, path.into(), None).await
+    }
+
+    /// Create with a persistence file encrypted at rest
+    ///
+    /// An existing plaintext file is still loaded and is encrypted on the next save.
+    /// Fails if the existing file cannot be decrypted with `provider`.
+    pub async fn with_encryption(
+        max_messages: usize,
+        max_users: usize,
+        path: impl Into<PathBuf>,
+        provider: Arc<dyn EncryptionProvider>,
+    ) -> crate::error::Result<Self> {
+        let mut mem = Self::open(max_messages, max_users, path.into(), Some(provider)).await;
+        match mem.unreadable.take() {
+            Some(e) => Err(e),
+            None => Ok(mem),
+        }
+    }
+
+    /// Create with encryption decided by `config`
+    pub async fn with_encryption_config(
+        max_messages: usize,
+        max_users: usize,
+        path: impl Into<PathBuf>,
+        config: &EncryptionConfig,
+        provider: &Arc<dyn EncryptionProvider>,
+    ) -> crate::error::Result<Self> {
+        match config.provider_for(EncryptedStore::ShortTermMemory, provider) {
+            Some(provider) => Self::with_encryption(max_messages, max_users, path, provider).await,
+            None => Ok(Self::new(max_messages, max_users, path).await),
+        }
+    }
+
+    async fn open(
+        max_messages: usize,
+        max_users: usize,
+        path: PathBuf,
+        encryption: Option<Arc<dyn EncryptionProvider>>,
+    ) -> Self {
         let store = DashMap::new();
         let last_access = DashMap::new();
         
-        let mem = Self {
+        let mut mem = Self {
             max_messages,
             max_users,
             store,
             last_access,
             path,
+            encryption,
+            instance_lock: None,
+            unreadable: None,
         };
         
         // Try to load existing state
         if let Err(e) = mem.load().await {
-            tracing::warn!("Failed to load short-term memory from {:?}: {}", mem.path, e);
+            tracing::warn!("Failed to load short-term memory from {:?}, leaving it untouched: {}", mem.path, e);
+            mem.unreadable = Some(e);
         }
         
         mem
     }
 
+    /// Only write snapshots while `lock` is the current writer
+    ///
+    /// Read-only instances keep changes in memory; a fenced instance gets an error.
+    pub fn with_instance_lock(mut self, lock: Arc<InstanceLock>) -> Self {
+        self.instance_lock = Some(lock);
+        self
+    }
+
     /// Create with default capacity (100 messages per user, 1000 active users)
     pub async fn default_capacity() -> Self {
         Self::new(100, 1000, "data/short_term_memory.json").await
@@ -126,9 +408,12 @@ impl ShortTermMemory {
             return Ok(());
         }
         
-        let content = tokio::fs::read_to_string(&self.path).await
+        let raw = tokio::fs::read(&self.path).await
             .map_err(|e| crate::error::Error::Internal(format!("Failed to read memory file: {}", e)))?;
-            
+        let raw = encryption::open(self.encryption.as_deref(), raw, SHORT_TERM_AAD)?;
+        let content = String::from_utf8(raw)
+            .map_err(|e| crate::error::Error::Internal(format!("Memory file is not UTF-8: {}", e)))?;
+
         if content.trim().is_empty() {
             return Ok(());
         }
@@ -148,6 +433,20 @@ impl ShortTermMemory {
 
     /// Save state to disk
     async fn save(&self) -> crate::error::Result<()> {
+        if let Some(e) = &self.unreadable {
+            return Err(crate::error::Error::Internal(format!(
+                "Refusing to overwrite memory file {:?} that failed to load: {}",
+                self.path, e
+            )));
+        }
+
+        if let Some(lock) = &self.instance_lock {
+            match lock.check_writer("short-term memory snapshot") {
+                Err(crate::error::Error::InstanceReadOnly(_)) => return Ok(()),
+                other => other?,
+            }
+        }
+
         if let Some(parent) = self.path.parent() {
             tokio::fs::create_dir_all(parent).await.ok();
         }
@@ -157,10 +456,11 @@ impl ShortTermMemory {
         
         let json = serde_json::to_string_pretty(&data)
              .map_err(|e| crate::error::Error::Internal(format!("Failed to serialize memory: {}", e)))?;
-             
+        let bytes = encryption::seal(self.encryption.as_deref(), json.into_bytes(), SHORT_TERM_AAD)?;
+
         // Atomic save: write to tmp then rename
         let tmp_path = self.path.with_extension("tmp");
-        tokio::fs::write(&tmp_path, json).await
+        tokio::fs::write(&tmp_path, bytes).await
              .map_err(|e| crate::error::Error::Internal(format!("Failed to write temporary memory file: {}", e)))?;
              
         tokio::fs::rename(tmp_path, &self.path).await
@@ -169,6 +469,15 @@ impl ShortTermMemory {
         Ok(())
     }
 
+    /// Re-encrypt a snapshot file under a new key (plaintext files are encrypted)
+    pub async fn rewrap(
+        path: impl AsRef<std::path::Path>,
+        old: &dyn EncryptionProvider,
+        new: &dyn EncryptionProvider,
+    ) -> crate::error::Result<()> {
+        encryption::rewrap_file(path, old, new, SHORT_TERM_AAD).await
+    }
+
     /// Get current message count for a user/agent pair
     pub fn message_count(&self, user_id: &str, agent_id: Option<&str>) -> usize {
         let key = self.key(user_id, agent_id);
@@ -184,6 +493,37 @@ impl ShortTermMemory {
         }
     }
     
+    /// Every conversation buffer with its owner, idle time and approximate size
+    pub fn conversations(&self) -> Vec<ConversationUsage> {
+        let now = std::time::Instant::now();
+        self.store
+            .iter()
+            .map(|entry| {
+                let key = entry.key().clone();
+                ConversationUsage {
+                    user_id: key.split(':').next().unwrap_or_default().to_string(),
+                    idle: self
+                        .last_access
+                        .get(&key)
+                        .map(|t| now.duration_since(*t))
+                        .unwrap_or_default(),
+                    bytes: entry.value().iter().map(|m| m.content.as_text().len() as u64).sum(),
+                    key,
+                }
+            })
+            .collect()
+    }
+
+    /// Drop the conversation stored under `key` (see [`ShortTermMemory::conversations`])
+    pub async fn remove_conversation(&self, key: &str) -> crate::error::Result<bool> {
+        let removed = self.store.remove(key).is_some();
+        self.last_access.remove(key);
+        if removed {
+            self.save().await?;
+        }
+        Ok(removed)
+    }
+
     /// Prune inactive users (older than duration) - Useful for manual cleanup
     pub fn prune_inactive(&self, duration: std::time::Duration) {
         let now = std::time::Instant::now();
@@ -346,6 +686,14 @@ impl Memory for ShortTermMemory {
         
         Ok(results)
     }
+
+    async fn status(&self, user_id: &str, agent_id: Option<&str>) -> MemoryStatus {
+        let key = self.key(user_id, agent_id);
+        MemoryStatus {
+            short_term: Some(self.store.get(&key).map(|v| v.len()).unwrap_or(0)),
+            long_term: None,
+        }
+    }
 }
 
 /// Combined memory manager for tiered storage
@@ -439,6 +787,10 @@ impl Memory for MemoryManager {
         self.cold_tier.store_knowledge(user_id, agent_id, title, content, collection).await
     }
 
+    async fn remember(&self, user_id: &str, agent_id: Option<&str>, title: &str, content: &str, collection: &str) -> crate::error::Result<RememberOutcome> {
+        self.cold_tier.remember(user_id, agent_id, title, content, collection).await
+    }
+
     async fn clear(&self, user_id: &str, agent_id: Option<&str>) -> crate::error::Result<()> {
         self.hot_tier.clear(user_id, agent_id).await?;
         self.cold_tier.clear(user_id, agent_id).await?;
@@ -456,12 +808,80 @@ impl Memory for MemoryManager {
     async fn retrieve_session(&self, session_id: &str) -> crate::error::Result<Option<crate::agent::session::AgentSession>> {
         self.cold_tier.retrieve_session(session_id).await
     }
+
+    async fn fetch_document(&self, collection: &str, path: &str) -> crate::error::Result<Option<crate::knowledge::rag::Document>> {
+        self.cold_tier.fetch_document(collection, path).await
+    }
+
+    async fn search_as_of(&self, user_id: &str, agent_id: Option<&str>, query: &str, limit: usize, as_of: Option<i64>) -> crate::error::Result<Vec<crate::knowledge::rag::Document>> {
+        match as_of {
+            None => self.search_unified(user_id, agent_id, query, limit).await,
+            // The hot tier is the live conversation and has no history
+            Some(_) => self.cold_tier.search_as_of(user_id, agent_id, query, limit, as_of).await,
+        }
+    }
+
+    async fn fetch_document_as_of(&self, collection: &str, path: &str, as_of: Option<i64>) -> crate::error::Result<Option<crate::knowledge::rag::Document>> {
+        self.cold_tier.fetch_document_as_of(collection, path, as_of).await
+    }
+
+    async fn fetch_document_by_id(&self, id: &str) -> crate::error::Result<Option<crate::knowledge::rag::Document>> {
+        self.cold_tier.fetch_document_by_id(id).await
+    }
+
+    async fn list_sessions(&self) -> crate::error::Result<Vec<crate::agent::session::AgentSession>> {
+        self.cold_tier.list_sessions().await
+    }
+
+    async fn delete_session(&self, session_id: &str) -> crate::error::Result<bool> {
+        self.cold_tier.delete_session(session_id).await
+    }
+
+    async fn list_session_summaries(&self, query: &crate::agent::session::SessionQuery) -> crate::error::Result<Vec<crate::agent::session::SessionSummary>> {
+        self.cold_tier.list_session_summaries(query).await
+    }
+
+    async fn delete_sessions_older_than(&self, age: std::time::Duration) -> crate::error::Result<usize> {
+        self.cold_tier.delete_sessions_older_than(age).await
+    }
+
+    async fn try_acquire_lease(&self, name: &str, holder: &str, ttl: std::time::Duration) -> crate::error::Result<bool> {
+        self.cold_tier.try_acquire_lease(name, holder, ttl).await
+    }
+
+    async fn status(&self, user_id: &str, agent_id: Option<&str>) -> MemoryStatus {
+        let hot = self.hot_tier.status(user_id, agent_id).await;
+        let cold = self.cold_tier.status(user_id, agent_id).await;
+        MemoryStatus {
+            short_term: hot.short_term,
+            // Whatever the cold tier holds is long-term from the agent's point of view
+            long_term: cold.long_term.or(cold.short_term),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_as_of_scope_and_unversioned_memory() {
+        assert_eq!(parse_as_of("1704067200").unwrap(), 1_704_067_200);
+        assert_eq!(parse_as_of("2024-01-01T00:00:00Z").unwrap(), 1_704_067_200);
+        assert_eq!(parse_as_of("2024-01-01").unwrap(), 1_704_067_200 + 86_399);
+        assert!(parse_as_of("last tuesday").is_err());
+
+        assert_eq!(current_as_of(), None);
+        assert_eq!(with_as_of(42, async { current_as_of() }).await, Some(42));
+
+        let memory = ShortTermMemory::new(10, 10, std::env::temp_dir().join("aagt-as-of-test.json")).await;
+        assert!(memory.search_as_of("u", None, "q", 5, None).await.is_ok());
+        assert!(matches!(
+            memory.search_as_of("u", None, "q", 5, Some(42)).await,
+            Err(crate::error::Error::MemoryRetrieval(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_short_term_memory() {
         let memory = ShortTermMemory::new(3, 10, "test_stm.json").await;
@@ -478,4 +898,66 @@ mod tests {
         
         let _ = std::fs::remove_file("test_stm.json");
     }
+
+    #[tokio::test]
+    async fn test_short_term_memory_encryption() {
+        use crate::infra::encryption::AesGcmProvider;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stm.json");
+        let key: Arc<dyn EncryptionProvider> = Arc::new(AesGcmProvider::new("k1", &[7; 32]).unwrap());
+
+        // Legacy plaintext file loads and is encrypted on the next save
+        let plain = ShortTermMemory::new(10, 10, &path).await;
+        plain.store("user1", None, Message::user("top secret")).await.unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().contains("top secret"));
+
+        let memory = ShortTermMemory::with_encryption(10, 10, &path, key.clone()).await.unwrap();
+        assert_eq!(memory.retrieve("user1", None, 10).await.len(), 1);
+        memory.store("user1", None, Message::user("more")).await.unwrap();
+        let raw = std::fs::read(&path).unwrap();
+        assert!(encryption::is_encrypted(&raw));
+        assert!(!String::from_utf8_lossy(&raw).contains("top secret"));
+
+        let reopened = ShortTermMemory::with_encryption(10, 10, &path, key.clone()).await.unwrap();
+        assert_eq!(reopened.retrieve("user1", None, 10).await.len(), 2);
+
+        // Wrong key is an error
+        let wrong: Arc<dyn EncryptionProvider> = Arc::new(AesGcmProvider::new("k1", &[8; 32]).unwrap());
+        assert!(ShortTermMemory::with_encryption(10, 10, &path, wrong).await.is_err());
+
+        // Without a key the encrypted snapshot is left untouched
+        let keyless = ShortTermMemory::new(10, 10, &path).await;
+        keyless.store("user1", None, Message::user("clobber")).await.unwrap();
+        assert!(keyless.clear("user1", None).await.is_err());
+        let intact = ShortTermMemory::with_encryption(10, 10, &path, key.clone()).await.unwrap();
+        assert_eq!(intact.retrieve("user1", None, 10).await.len(), 2);
+
+        // Rotation
+        let k2 = AesGcmProvider::new("k2", &[9; 32]).unwrap();
+        ShortTermMemory::rewrap(&path, key.as_ref(), &k2).await.unwrap();
+        assert_eq!(encryption::key_id_of(&std::fs::read(&path).unwrap()), Some("k2"));
+        let rotated = ShortTermMemory::with_encryption(10, 10, &path, Arc::new(k2)).await.unwrap();
+        assert_eq!(rotated.retrieve("user1", None, 10).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_short_term_memory_encryption_config() {
+        use crate::infra::encryption::AesGcmProvider;
+
+        let dir = tempfile::tempdir().unwrap();
+        let key: Arc<dyn EncryptionProvider> = Arc::new(AesGcmProvider::new("k1", &[7; 32]).unwrap());
+        let mut config = EncryptionConfig { enabled: true, ..Default::default() };
+
+        let path = dir.path().join("on.json");
+        let memory = ShortTermMemory::with_encryption_config(10, 10, &path, &config, &key).await.unwrap();
+        memory.store("user1", None, Message::user("hi")).await.unwrap();
+        assert!(encryption::is_encrypted(&std::fs::read(&path).unwrap()));
+
+        config.overrides.insert(EncryptedStore::ShortTermMemory, false);
+        let path = dir.path().join("off.json");
+        let memory = ShortTermMemory::with_encryption_config(10, 10, &path, &config, &key).await.unwrap();
+        memory.store("user1", None, Message::user("hi")).await.unwrap();
+        assert!(!encryption::is_encrypted(&std::fs::read(&path).unwrap()));
+    }
 }
@@ -1,7 +1,15 @@
 //! Message types for LLM communication
 
+use std::path::Path;
+
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 
+use crate::error::Error;
+
+/// Largest local image [`Message::user_with_image`] attaches (the Anthropic limit)
+pub const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+
 /// Role of the message sender
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -109,6 +117,32 @@ pub enum ContentPart {
         /// Result content
         content: String,
     },
+    /// Document in the QMD store, such as a file a tool produced
+    ///
+    /// Providers send the reference as text; the model reads the document
+    /// through a tool.
+    FileRef {
+        /// Path of the document in the store
+        virtual_path: String,
+        /// Short content hash of the document
+        docid: String,
+    },
+}
+
+impl ContentPart {
+    /// Text standing in for an image or file reference, `None` for other parts
+    pub fn placeholder(&self) -> Option<String> {
+        match self {
+            Self::Image { source: ImageSource::Base64 { media_type, .. } } => {
+                Some(format!("[image: {}]", media_type))
+            }
+            Self::Image { source: ImageSource::Url { url } } => Some(format!("[image: {}]", url)),
+            Self::FileRef { virtual_path, docid } => {
+                Some(format!("[file: {} #{}]", virtual_path, docid))
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Source for image content
@@ -139,6 +173,9 @@ pub struct Message {
     /// Optional name (for multi-agent scenarios)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    /// Stable ID of the agent response this message carries (final assistant turns)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_id: Option<String>,
 }
 
 impl Message {
@@ -148,6 +185,7 @@ impl Message {
             role,
             content: content.into(),
             name: None,
+            response_id: None,
         }
     }
 
@@ -166,6 +204,49 @@ impl Message {
         Self::new(Role::Assistant, content)
     }
 
+    /// Create a user message with `text` and the image at `path`, base64-encoded
+    ///
+    /// The media type comes from the file extension (png, jpeg, gif or webp);
+    /// files over [`MAX_IMAGE_BYTES`] are rejected.
+    pub fn user_with_image(text: impl Into<String>, path: impl AsRef<Path>) -> crate::error::Result<Self> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase);
+        let media_type = match extension.as_deref() {
+            Some("png") => "image/png",
+            Some("jpg" | "jpeg") => "image/jpeg",
+            Some("gif") => "image/gif",
+            Some("webp") => "image/webp",
+            _ => {
+                return Err(Error::Attachment(format!(
+                    "{} is not a png, jpeg, gif or webp image",
+                    path.display()
+                )))
+            }
+        };
+        let size = std::fs::metadata(path)?.len();
+        if size > MAX_IMAGE_BYTES {
+            return Err(Error::Attachment(format!(
+                "{} is {} bytes, over the {} byte limit",
+                path.display(),
+                size,
+                MAX_IMAGE_BYTES
+            )));
+        }
+        let data = base64::engine::general_purpose::STANDARD.encode(std::fs::read(path)?);
+        Ok(Self::user(Content::Parts(vec![
+            ContentPart::Text { text: text.into() },
+            ContentPart::Image {
+                source: ImageSource::Base64 {
+                    media_type: media_type.to_string(),
+                    data,
+                },
+            },
+        ])))
+    }
+
     /// Create a tool result message
     pub fn tool_result(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
         Self {
@@ -176,6 +257,7 @@ impl Message {
                 content: content.into(),
             }]),
             name: None,
+            response_id: None,
         }
     }
 
@@ -202,6 +284,24 @@ impl Message {
         self
     }
 
+    /// Replace images with text placeholders, for models without vision
+    pub fn with_image_placeholders(mut self) -> Self {
+        if let Content::Parts(parts) = &mut self.content {
+            for part in parts {
+                if let (ContentPart::Image { .. }, Some(text)) = (&*part, part.placeholder()) {
+                    *part = ContentPart::Text { text };
+                }
+            }
+        }
+        self
+    }
+
+    /// Tag this message with an agent response ID
+    pub fn with_response_id(mut self, id: impl Into<String>) -> Self {
+        self.response_id = Some(id.into());
+        self
+    }
+
     /// Get the text content of this message
     pub fn text(&self) -> String {
         self.content.as_text()
@@ -275,6 +375,33 @@ mod tests {
         assert!((args.amount - 100.0).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn test_user_with_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let chart = dir.path().join("chart.PNG");
+        std::fs::write(&chart, b"\x89PNG\r\n").unwrap();
+
+        let msg = Message::user_with_image("What does this chart show?", &chart).unwrap();
+        let Content::Parts(parts) = &msg.content else { panic!("expected parts") };
+        assert!(matches!(
+            &parts[1],
+            ContentPart::Image { source: ImageSource::Base64 { media_type, data } }
+                if media_type == "image/png" && data == "iVBORw0K"
+        ));
+        assert_eq!(msg.text(), "What does this chart show?");
+
+        let degraded = msg.with_image_placeholders();
+        let Content::Parts(parts) = &degraded.content else { panic!("expected parts") };
+        assert!(matches!(&parts[1], ContentPart::Text { text } if text == "[image: image/png]"));
+
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, "SOL").unwrap();
+        assert!(matches!(Message::user_with_image("", &notes), Err(Error::Attachment(_))));
+        let huge = dir.path().join("huge.jpg");
+        std::fs::File::create(&huge).unwrap().set_len(MAX_IMAGE_BYTES + 1).unwrap();
+        assert!(matches!(Message::user_with_image("", &huge), Err(Error::Attachment(_))));
+    }
+
     #[test]
     fn test_tool_result_name() {
         let msg = Message::tool_result("call_1", "result").with_tool_name("get_price");
@@ -1,17 +1,49 @@
+pub mod budget;
 pub mod cache;
+pub mod calendar;
 pub mod context;
 pub mod core;
+pub mod dev_trace;
+pub mod eval;
+pub mod event_log;
+pub mod events;
+pub mod feedback;
+pub mod history_summary;
+pub mod long_term_memory;
 pub mod memory;
 pub mod message;
 pub mod multi_agent;
 pub mod namespaced_memory; // NEW: Namespaced shared memory
 pub mod personality;
 pub mod provider;
+pub mod replay;
 pub mod scheduler;
 pub mod session;
 pub mod streaming;
+pub mod system_prompt;
+pub mod tool_aging;
+pub mod tool_routing;
+pub mod typed_output;
 
+pub use budget::{Budget, BudgetConfig, BudgetSummary};
+pub use calendar::{CalendarRegistry, TradingCalendar};
 pub use core::{Agent, AgentBuilder, AgentConfig};
+pub use dev_trace::DevTracer;
+pub use eval::{EvalCase, EvalConfig, EvalMetric, EvalReport, EvalRunner, EvalSuite};
+pub use event_log::{EventLog, EventRecorder, RecordedEvent};
+pub use events::{EventFilter, EventHub, EventItem, EventKind, EventKinds, EventStream, Severity};
+pub use feedback::{FeedbackDimension, FeedbackEntry, FeedbackStore, Rating, ResponseRef};
+pub use history_summary::{HistorySummarizer, SummarizeConfig};
+pub use long_term_memory::{DedupPolicy, LongTermMemory};
+pub use replay::{ArtifactStore, EventId, FileArtifactStore, ReplayConfig, ReplayEvent, ReplaySubscription};
 pub use namespaced_memory::{MemoryEntry, NamespacedMemory};
-pub use session::{AgentSession, SessionStatus};
+pub use session::{
+    AgentSession, InterruptedAction, RecoveryOutcome, RecoveryPolicy, RecoveryReport, SessionManager,
+    SessionQuery, SessionResumer, SessionStatus, SessionSummary,
+};
+pub use system_prompt::{PromptSection, SectionKey, SystemPrompt};
+pub use tool_aging::{ToolAgingConfig, ToolOutputAging, ToolOutputArchive};
+pub use tool_routing::{
+    RoutingContext, RoutingRule, RuleRouter, RuleRouterConfig, ToolRouter, ToolVisibility,
+};
 // NEW
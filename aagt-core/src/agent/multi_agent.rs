@@ -1,16 +1,28 @@
 //! Multi-agent coordination system
 //!
-//! Enables multiple specialized agents to work together.
+//! Enables multiple specialized agents to work together. Handoffs are routed
+//! in memory unless the coordinator has a [`JobQueue`], which makes them
+//! survive a crash (see [`job_queue`]).
 
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use dashmap::DashMap;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::error::{Error, Result};
-use crate::agent::scheduler::Scheduler;
+use crate::agent::scheduler::{CatchUpPolicy, Scheduler};
 use crate::agent::memory::Memory;
+use crate::infra::instance::InstanceLock;
+use crate::skills::tool::ToolSet;
+
+pub mod job_queue;
+pub mod task_board;
+
+pub use job_queue::{Job, JobPayload, JobQueue, JobQueueConfig};
+pub use task_board::{NewTask, Task, TaskBoard, TaskFilter, TaskState, TaskStatus};
 
 /// Role of an agent in a multi-agent system
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
@@ -44,7 +56,7 @@ impl AgentRole {
 }
 
 /// Message between agents
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct AgentMessage {
     /// Sender role
     pub from: AgentRole,
@@ -57,7 +69,7 @@ pub struct AgentMessage {
 }
 
 /// Type of inter-agent message
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum MessageType {
     /// Request for action
     Request,
@@ -84,6 +96,11 @@ pub trait MultiAgent: Send + Sync {
 
     /// Process a user request
     async fn process(&self, input: &str) -> Result<String>;
+
+    /// Names of the tools this agent can call, sorted
+    fn tool_names(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 /// Coordinator for multi-agent systems
@@ -96,6 +113,14 @@ pub struct Coordinator {
     pub scheduler: tokio::sync::OnceCell<Arc<Scheduler>>,
     /// Shared memory for the system
     pub memory: tokio::sync::OnceCell<Arc<dyn Memory>>,
+    /// Data directory claim handed to the scheduler
+    instance_lock: Option<Arc<InstanceLock>>,
+    /// Job file and catch-up policy handed to the scheduler
+    scheduler_store: Option<(PathBuf, CatchUpPolicy)>,
+    /// Tool allowlists per role, applied by [`tools_for`](Self::tools_for)
+    role_tools: HashMap<AgentRole, Vec<String>>,
+    /// Durable backing for directed handoffs
+    jobs: Option<Arc<JobQueue>>,
 }
 
 impl Coordinator {
@@ -106,6 +131,10 @@ impl Coordinator {
             max_rounds: 10,
             scheduler: tokio::sync::OnceCell::new(),
             memory: tokio::sync::OnceCell::new(),
+            instance_lock: None,
+            scheduler_store: None,
+            role_tools: HashMap::new(),
+            jobs: None,
         }
     }
 
@@ -115,6 +144,76 @@ impl Coordinator {
         self
     }
 
+    /// Only fire scheduled jobs while `lock` is the current writer
+    pub fn with_instance_lock(mut self, lock: Arc<InstanceLock>) -> Self {
+        self.instance_lock = Some(lock);
+        self
+    }
+
+    /// Persist scheduled jobs to `path`, restoring them when the scheduler starts
+    pub fn with_scheduler_store(mut self, path: impl Into<PathBuf>, catch_up: CatchUpPolicy) -> Self {
+        self.scheduler_store = Some((path.into(), catch_up));
+        self
+    }
+
+    /// Queue directed handoffs in `queue` so they survive a crash
+    ///
+    /// Each handoff is still handled right away by the caller. If it fails, the
+    /// caller gets the error and the job is dead-lettered rather than retried, so
+    /// it never runs twice. If the process dies first, the job's lease runs out
+    /// and a worker started with [`spawn_worker`](Self::spawn_worker) delivers it
+    /// again; its response then has no pipeline to return to and is only logged.
+    /// Jobs queued with [`JobQueue::enqueue`] are retried by the workers up to
+    /// `max_attempts`.
+    pub fn with_job_queue(mut self, queue: Arc<JobQueue>) -> Self {
+        self.jobs = Some(queue);
+        self
+    }
+
+    /// The job queue, if handoffs are durable
+    pub fn job_queue(&self) -> Option<&Arc<JobQueue>> {
+        self.jobs.as_ref()
+    }
+
+    /// Jobs that used up their attempts (empty without a job queue)
+    pub fn dead_letters(&self) -> Result<Vec<Job>> {
+        match &self.jobs {
+            Some(jobs) => jobs.dead_letters(),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Only give agents in `role` the named tools when built through [`tools_for`](Self::tools_for)
+    pub fn with_role_tools<S: Into<String>>(
+        mut self,
+        role: AgentRole,
+        tools: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.role_tools
+            .insert(role, tools.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// The part of `tools` an agent in `role` may use
+    ///
+    /// Roles without an allowlist get the whole set. The subset shares tool
+    /// instances with `tools`; pass it to
+    /// [`AgentBuilder::tools`](crate::agent::core::AgentBuilder::tools).
+    pub fn tools_for(&self, role: &AgentRole, tools: &ToolSet) -> Result<ToolSet> {
+        match self.role_tools.get(role) {
+            Some(allowed) => tools.subset(allowed),
+            None => Ok(tools.clone()),
+        }
+    }
+
+    /// Tool names of every registered agent, by role
+    pub fn agent_capabilities(&self) -> HashMap<AgentRole, Vec<String>> {
+        self.agents
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().tool_names()))
+            .collect()
+    }
+
     /// Register an agent
     pub fn register(&self, agent: Arc<dyn MultiAgent>) {
         self.agents.insert(agent.role(), agent);
@@ -128,7 +227,19 @@ impl Coordinator {
     /// Start the background scheduler
     pub async fn start_scheduler(self: &Arc<Self>) -> Arc<Scheduler> {
         let scheduler = self.scheduler.get_or_init(|| async {
-            let scheduler = Arc::new(Scheduler::new(Arc::downgrade(self)).await);
+            let mut scheduler = Scheduler::new(Arc::downgrade(self)).await;
+            if let Some(lock) = &self.instance_lock {
+                scheduler = scheduler.with_instance_lock(Arc::clone(lock));
+            }
+            if let Some((path, catch_up)) = &self.scheduler_store {
+                scheduler = scheduler.with_store(path.clone()).with_catch_up(*catch_up);
+            }
+            let scheduler = Arc::new(scheduler);
+            match scheduler.restore().await {
+                Ok(0) => {}
+                Ok(n) => info!("Restored {} scheduled jobs", n),
+                Err(e) => tracing::warn!("Failed to restore scheduled jobs: {}", e),
+            }
             
             // Link scheduler to memory if available
             if let Some(memory) = self.memory.get() {
@@ -150,7 +261,7 @@ impl Coordinator {
         if let Some(target_role) = &message.to {
             // Directed message
             if let Some(agent) = self.get(target_role) {
-                return agent.handle_message(message).await;
+                return self.dispatch(&agent, JobPayload::Message { message }).await;
             } else {
                 return Err(Error::AgentCommunication(format!(
                     "No agent with role: {:?}",
@@ -187,7 +298,7 @@ impl Coordinator {
             .ok_or_else(|| Error::AgentCoordination(format!("No lead agent found for role: {:?}", lead_role)))?;
 
         // 1. Initial processing by lead agent
-        let mut current_result = lead.process(task).await?;
+        let mut current_result = self.run_task(&lead, task).await?;
         let mut current_role = lead_role.clone();
 
         // 2. Pass result through the rest of the workflow chain OR follow handovers
@@ -208,7 +319,7 @@ impl Coordinator {
                     msg_type,
                 };
 
-                if let Some(response) = agent.handle_message(message).await? {
+                if let Some(response) = self.dispatch(&agent, JobPayload::Message { message }).await? {
                     // Check for Handover
                     if matches!(response.msg_type, MessageType::Handover) {
                         // Dynamic handover: the agent specifies the next role in the content or target
@@ -247,6 +358,94 @@ impl Coordinator {
         Ok(current_result)
     }
 
+    /// Run `task` on the agent in `role`
+    pub async fn delegate(&self, role: &AgentRole, task: &str) -> Result<String> {
+        let agent = self.get(role).ok_or_else(|| {
+            Error::AgentCoordination(format!("No agent registered for role: {:?}", role))
+        })?;
+        self.run_task(&agent, task).await
+    }
+
+    async fn run_task(&self, agent: &Arc<dyn MultiAgent>, task: &str) -> Result<String> {
+        let response = self
+            .dispatch(agent, JobPayload::Task { input: task.to_string() })
+            .await?;
+        Ok(response.map(|r| r.content).unwrap_or_default())
+    }
+
+    /// Hand `payload` to `agent`, through the job queue when there is one
+    ///
+    /// A failed handoff is dead-lettered: the error goes to the caller instead of a retry.
+    async fn dispatch(&self, agent: &Arc<dyn MultiAgent>, payload: JobPayload) -> Result<Option<AgentMessage>> {
+        let Some(jobs) = &self.jobs else {
+            return Self::execute(agent.as_ref(), payload).await;
+        };
+        let job = jobs.enqueue_leased(&agent.role(), payload)?;
+        let result = Self::execute(agent.as_ref(), job.payload).await;
+        match &result {
+            Ok(_) => jobs.ack(&job.id)?,
+            Err(e) => jobs.dead_letter(&job.id, &e.to_string())?,
+        }
+        result
+    }
+
+    async fn execute(agent: &dyn MultiAgent, payload: JobPayload) -> Result<Option<AgentMessage>> {
+        match payload {
+            JobPayload::Message { message } => agent.handle_message(message).await,
+            JobPayload::Task { input } => Ok(Some(AgentMessage {
+                from: agent.role(),
+                to: None,
+                content: agent.process(&input).await?,
+                msg_type: MessageType::Response,
+            })),
+        }
+    }
+
+    /// Handle the next queued job for `role`; `false` when none was ready
+    pub async fn run_next(&self, role: &AgentRole) -> Result<bool> {
+        let (Some(jobs), Some(agent)) = (&self.jobs, self.get(role)) else {
+            return Ok(false);
+        };
+        let Some(job) = jobs.lease(role)? else {
+            return Ok(false);
+        };
+        let id = job.id.clone();
+        match Self::execute(agent.as_ref(), job.payload).await {
+            Ok(response) => {
+                jobs.ack(&id)?;
+                info!(job = %id, role = role.name(), response = ?response.map(|r| r.content), "Queued job handled");
+            }
+            Err(e) => {
+                warn!(job = %id, role = role.name(), "Queued job failed: {}", e);
+                jobs.fail(&id, &e.to_string())?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Keep handling `role`'s queued jobs in the background
+    ///
+    /// The worker stops once the coordinator is dropped, and right away when
+    /// it has no job queue.
+    pub fn spawn_worker(self: &Arc<Self>, role: AgentRole) -> tokio::task::JoinHandle<()> {
+        let coordinator = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                let Some(this) = coordinator.upgrade() else { break };
+                let Some(interval) = this.jobs.as_ref().map(|jobs| jobs.config().poll_interval) else {
+                    break;
+                };
+                match this.run_next(&role).await {
+                    Ok(true) => continue,
+                    Ok(false) => {}
+                    Err(e) => warn!(role = role.name(), "Job queue worker error: {}", e),
+                }
+                drop(this);
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
     /// Get list of registered agent roles
     pub fn roles(&self) -> Vec<AgentRole> {
         self.agents.iter().map(|r| r.key().clone()).collect()
@@ -312,4 +511,75 @@ mod tests {
 
         assert_eq!(coordinator.roles().len(), 2);
     }
+
+    /// Fails its first `failures` tasks
+    struct FlakyAgent {
+        failures: std::sync::atomic::AtomicUsize,
+        done: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl MultiAgent for FlakyAgent {
+        fn role(&self) -> AgentRole {
+            AgentRole::Trader
+        }
+
+        async fn handle_message(&self, _message: AgentMessage) -> Result<Option<AgentMessage>> {
+            Ok(None)
+        }
+
+        async fn process(&self, input: &str) -> Result<String> {
+            use std::sync::atomic::Ordering;
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                return Err(Error::AgentCoordination("exchange offline".to_string()));
+            }
+            self.done.fetch_add(1, Ordering::SeqCst);
+            Ok(format!("done: {}", input))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_handoff_is_dead_lettered_and_queued_jobs_retry() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let queue = Arc::new(
+            JobQueue::in_memory(JobQueueConfig {
+                max_attempts: 3,
+                retry_delay: std::time::Duration::ZERO,
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        let coordinator = Coordinator::new().with_job_queue(queue.clone());
+        let trader = Arc::new(FlakyAgent {
+            failures: AtomicUsize::new(1),
+            done: AtomicUsize::new(0),
+        });
+        coordinator.register(trader.clone());
+
+        // The caller sees the failure; no worker runs the handoff a second time
+        assert!(coordinator.delegate(&AgentRole::Trader, "Buy 1 SOL").await.is_err());
+        assert_eq!(queue.pending().unwrap(), 0);
+        assert!(!coordinator.run_next(&AgentRole::Trader).await.unwrap());
+        assert_eq!(trader.done.load(Ordering::SeqCst), 0);
+        let dead = coordinator.dead_letters().unwrap();
+        assert_eq!(dead.len(), 1);
+        assert!(dead[0].last_error.as_deref().unwrap().contains("exchange offline"));
+
+        // Jobs queued for the workers are retried
+        trader.failures.store(1, Ordering::SeqCst);
+        queue.enqueue(&AgentRole::Trader, JobPayload::Task { input: "Hedge".to_string() }).unwrap();
+        assert!(coordinator.run_next(&AgentRole::Trader).await.unwrap());
+        assert_eq!(queue.pending().unwrap(), 1);
+        assert!(coordinator.run_next(&AgentRole::Trader).await.unwrap());
+        assert_eq!(trader.done.load(Ordering::SeqCst), 1);
+        assert_eq!(queue.pending().unwrap(), 0);
+
+        // A successful handoff leaves nothing behind
+        let result = coordinator.delegate(&AgentRole::Trader, "Sell 1 SOL").await.unwrap();
+        assert_eq!(result, "done: Sell 1 SOL");
+        assert_eq!(queue.pending().unwrap(), 0);
+        assert_eq!(coordinator.dead_letters().unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,435 @@
+//! Durable job queue for coordinator handoffs
+//!
+//! With a [`JobQueue`] set, the [`Coordinator`](super::Coordinator) writes every
+//! directed handoff to a SQLite table before the target agent runs. Whoever
+//! handles a job holds a lease on it: the job stays hidden from other workers
+//! for the visibility timeout and is deleted when acked. A job that is never
+//! acked (the process died) or that failed becomes visible again and is
+//! redelivered, until `max_attempts` deliveries have been made and it moves
+//! to the dead letters.
+//!
+//! Delivery is at least once: a handler still running when its lease runs out
+//! can see its job delivered again, so `visibility_timeout` should exceed the
+//! longest handoff.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use super::{AgentMessage, AgentRole};
+use crate::error::{Error, Result};
+use crate::infra::outbox::{Clock, SystemClock};
+
+/// Configuration for the job queue
+#[derive(Debug, Clone)]
+pub struct JobQueueConfig {
+    /// Deliveries before a job is dead-lettered
+    pub max_attempts: u32,
+    /// How long a leased job stays hidden before it is redelivered
+    pub visibility_timeout: Duration,
+    /// Delay before a failed job is offered again
+    pub retry_delay: Duration,
+    /// How often an idle worker looks for jobs
+    pub poll_interval: Duration,
+}
+
+impl Default for JobQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            visibility_timeout: Duration::from_secs(300),
+            retry_delay: Duration::from_secs(5),
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Work handed to an agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobPayload {
+    /// Run [`MultiAgent::process`](super::MultiAgent::process) on `input`
+    Task { input: String },
+    /// Deliver through [`MultiAgent::handle_message`](super::MultiAgent::handle_message)
+    Message { message: AgentMessage },
+}
+
+/// A queued job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    /// Unique job ID
+    pub id: String,
+    /// Role of the agent that handles the job
+    pub target: AgentRole,
+    /// What the agent is asked to do
+    pub payload: JobPayload,
+    /// Deliveries made so far
+    pub attempts: u32,
+    /// When the job was queued
+    pub enqueued_at: DateTime<Utc>,
+    /// Until when the job is hidden from workers
+    pub visible_at: DateTime<Utc>,
+    /// Error of the last failed delivery
+    pub last_error: Option<String>,
+    /// When the job was given up on
+    pub dead_at: Option<DateTime<Utc>>,
+}
+
+/// SQLite-backed queue of agent jobs
+///
+/// Every call runs in its own transaction, so two workers never lease the
+/// same job.
+pub struct JobQueue {
+    conn: Mutex<Connection>,
+    config: JobQueueConfig,
+    clock: Arc<dyn Clock>,
+}
+
+impl JobQueue {
+    /// Open (or create) the queue at `path`
+    pub fn open(path: impl AsRef<Path>, config: JobQueueConfig) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(path).map_err(db)?;
+        conn.execute_batch("PRAGMA journal_mode = WAL").map_err(db)?;
+        Self::with_connection(conn, config)
+    }
+
+    /// Queue that lives only as long as the process
+    pub fn in_memory(config: JobQueueConfig) -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory().map_err(db)?, config)
+    }
+
+    fn with_connection(conn: Connection, config: JobQueueConfig) -> Result<Self> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS jobs (
+                id TEXT PRIMARY KEY,
+                target TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                attempts INTEGER NOT NULL,
+                enqueued_at INTEGER NOT NULL,
+                visible_at INTEGER NOT NULL,
+                last_error TEXT,
+                dead_at INTEGER
+            );
+            CREATE INDEX IF NOT EXISTS jobs_ready ON jobs (target, dead_at, visible_at);",
+        )
+        .map_err(db)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            config,
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// Use `clock` instead of the wall clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The queue's configuration
+    pub fn config(&self) -> &JobQueueConfig {
+        &self.config
+    }
+
+    /// Queue a job for the agent in `target`
+    pub fn enqueue(&self, target: &AgentRole, payload: JobPayload) -> Result<Job> {
+        self.insert(target, payload, 0, self.clock.now())
+    }
+
+    /// Queue a job already leased by the caller, who handles it right away
+    pub fn enqueue_leased(&self, target: &AgentRole, payload: JobPayload) -> Result<Job> {
+        let now = self.clock.now();
+        self.insert(target, payload, 1, self.later(now, self.config.visibility_timeout))
+    }
+
+    fn insert(&self, target: &AgentRole, payload: JobPayload, attempts: u32, visible_at: DateTime<Utc>) -> Result<Job> {
+        let job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            target: target.clone(),
+            payload,
+            attempts,
+            enqueued_at: self.clock.now(),
+            visible_at,
+            last_error: None,
+            dead_at: None,
+        };
+        self.conn
+            .lock()
+            .execute(
+                "INSERT INTO jobs (id, target, payload, attempts, enqueued_at, visible_at) VALUES (?, ?, ?, ?, ?, ?)",
+                params![
+                    job.id,
+                    role_key(target)?,
+                    serde_json::to_string(&job.payload)?,
+                    job.attempts,
+                    job.enqueued_at.timestamp_millis(),
+                    job.visible_at.timestamp_millis(),
+                ],
+            )
+            .map_err(db)?;
+        tracing::debug!(id = %job.id, target = target.name(), "Job queued");
+        Ok(job)
+    }
+
+    /// Lease the oldest visible job for `target`
+    ///
+    /// Jobs whose last delivery used up their attempts are dead-lettered on the way.
+    pub fn lease(&self, target: &AgentRole) -> Result<Option<Job>> {
+        let now = self.clock.now();
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction().map_err(db)?;
+        self.bury_expired(&tx, now)?;
+        let job = tx
+            .query_row(
+                "SELECT * FROM jobs WHERE target = ? AND dead_at IS NULL AND visible_at <= ?
+                 ORDER BY visible_at, enqueued_at LIMIT 1",
+                params![role_key(target)?, now.timestamp_millis()],
+                job_from_row,
+            )
+            .optional()
+            .map_err(db)?;
+        let Some(mut job) = job else {
+            tx.commit().map_err(db)?;
+            return Ok(None);
+        };
+        job.attempts += 1;
+        job.visible_at = self.later(now, self.config.visibility_timeout);
+        tx.execute(
+            "UPDATE jobs SET attempts = ?, visible_at = ? WHERE id = ?",
+            params![job.attempts, job.visible_at.timestamp_millis(), job.id],
+        )
+        .map_err(db)?;
+        tx.commit().map_err(db)?;
+        tracing::debug!(id = %job.id, attempt = job.attempts, "Job leased");
+        Ok(Some(job))
+    }
+
+    /// Remove a handled job
+    pub fn ack(&self, id: &str) -> Result<()> {
+        self.conn
+            .lock()
+            .execute("DELETE FROM jobs WHERE id = ?", params![id])
+            .map_err(db)?;
+        Ok(())
+    }
+
+    /// Record a failed delivery, retrying after `retry_delay` or dead-lettering the job
+    pub fn fail(&self, id: &str, error: &str) -> Result<()> {
+        let now = self.clock.now();
+        let retry_at = self.later(now, self.config.retry_delay);
+        let conn = self.conn.lock();
+        let dead = conn
+            .query_row(
+                "UPDATE jobs SET last_error = ?1,
+                    dead_at = CASE WHEN attempts >= ?2 THEN ?3 END,
+                    visible_at = CASE WHEN attempts >= ?2 THEN visible_at ELSE ?4 END
+                 WHERE id = ?5 AND dead_at IS NULL
+                 RETURNING dead_at IS NOT NULL",
+                params![error, self.config.max_attempts, now.timestamp_millis(), retry_at.timestamp_millis(), id],
+                |row| row.get::<_, bool>(0),
+            )
+            .optional()
+            .map_err(db)?;
+        if dead == Some(true) {
+            tracing::warn!(id, error, "Job dead-lettered after {} attempts", self.config.max_attempts);
+        }
+        Ok(())
+    }
+
+    /// Give up on a job right away, without retrying it
+    pub fn dead_letter(&self, id: &str, error: &str) -> Result<()> {
+        self.conn
+            .lock()
+            .execute(
+                "UPDATE jobs SET last_error = ?, dead_at = ? WHERE id = ? AND dead_at IS NULL",
+                params![error, self.clock.now().timestamp_millis(), id],
+            )
+            .map_err(db)?;
+        tracing::warn!(id, error, "Job dead-lettered");
+        Ok(())
+    }
+
+    /// Jobs not yet handled or given up on
+    pub fn pending(&self) -> Result<usize> {
+        let count: i64 = self
+            .conn
+            .lock()
+            .query_row("SELECT count(*) FROM jobs WHERE dead_at IS NULL", [], |row| row.get(0))
+            .map_err(db)?;
+        Ok(count as usize)
+    }
+
+    /// Jobs given up on, oldest first
+    pub fn dead_letters(&self) -> Result<Vec<Job>> {
+        let mut conn = self.conn.lock();
+        let tx = conn.transaction().map_err(db)?;
+        self.bury_expired(&tx, self.clock.now())?;
+        let jobs = {
+            let mut stmt = tx
+                .prepare("SELECT * FROM jobs WHERE dead_at IS NOT NULL ORDER BY dead_at, enqueued_at")
+                .map_err(db)?;
+            let rows = stmt.query_map([], job_from_row).map_err(db)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db)?
+        };
+        tx.commit().map_err(db)?;
+        Ok(jobs)
+    }
+
+    /// Dead-letter jobs whose lease ran out on their last attempt
+    fn bury_expired(&self, tx: &rusqlite::Transaction<'_>, now: DateTime<Utc>) -> Result<()> {
+        let buried = tx
+            .execute(
+                "UPDATE jobs SET dead_at = ?1, last_error = coalesce(last_error, 'visibility timeout expired')
+                 WHERE dead_at IS NULL AND attempts >= ?2 AND visible_at <= ?1",
+                params![now.timestamp_millis(), self.config.max_attempts],
+            )
+            .map_err(db)?;
+        if buried > 0 {
+            tracing::warn!("{} jobs dead-lettered after their last lease expired", buried);
+        }
+        Ok(())
+    }
+
+    fn later(&self, now: DateTime<Utc>, delay: Duration) -> DateTime<Utc> {
+        chrono::Duration::from_std(delay)
+            .ok()
+            .and_then(|delay| now.checked_add_signed(delay))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+}
+
+/// Column value identifying `role` (custom roles can share a built-in role's name)
+fn role_key(role: &AgentRole) -> Result<String> {
+    Ok(serde_json::to_string(role)?)
+}
+
+fn job_from_row(row: &Row<'_>) -> rusqlite::Result<Job> {
+    let millis = |ms: i64| DateTime::from_timestamp_millis(ms).unwrap_or_default();
+    Ok(Job {
+        id: row.get("id")?,
+        target: json_column(row, "target")?,
+        payload: json_column(row, "payload")?,
+        attempts: row.get("attempts")?,
+        enqueued_at: millis(row.get("enqueued_at")?),
+        visible_at: millis(row.get("visible_at")?),
+        last_error: row.get("last_error")?,
+        dead_at: row.get::<_, Option<i64>>("dead_at")?.map(millis),
+    })
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &Row<'_>, column: &str) -> rusqlite::Result<T> {
+    let text: String = row.get(column)?;
+    serde_json::from_str(&text).map_err(|e| {
+        let index = row.as_ref().column_index(column).unwrap_or_default();
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+fn db(e: rusqlite::Error) -> Error {
+    Error::JobQueue(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ManualClock;
+
+    fn task(input: &str) -> JobPayload {
+        JobPayload::Task { input: input.to_string() }
+    }
+
+    #[test]
+    fn test_unacked_job_is_redelivered_after_the_visibility_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jobs.db");
+        let config = JobQueueConfig {
+            max_attempts: 2,
+            visibility_timeout: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let clock = ManualClock::new();
+        let open = || JobQueue::open(&path, config.clone()).unwrap().with_clock(clock.clone());
+
+        let queue = open();
+        let queued = queue.enqueue(&AgentRole::Trader, task("Buy 1 SOL")).unwrap();
+        assert!(queue.lease(&AgentRole::Researcher).unwrap().is_none());
+        let leased = queue.lease(&AgentRole::Trader).unwrap().unwrap();
+        assert_eq!((leased.id.as_str(), leased.attempts), (queued.id.as_str(), 1));
+        // The worker crashes without acking
+        drop(queue);
+
+        let queue = open();
+        assert!(queue.lease(&AgentRole::Trader).unwrap().is_none());
+        clock.advance(Duration::from_secs(61));
+        let redelivered = queue.lease(&AgentRole::Trader).unwrap().unwrap();
+        assert_eq!((redelivered.id.as_str(), redelivered.attempts), (queued.id.as_str(), 2));
+        assert!(matches!(&redelivered.payload, JobPayload::Task { input } if input == "Buy 1 SOL"));
+
+        // The second lease was the last one
+        clock.advance(Duration::from_secs(61));
+        assert!(queue.lease(&AgentRole::Trader).unwrap().is_none());
+        let dead = queue.dead_letters().unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].last_error.as_deref(), Some("visibility timeout expired"));
+        assert_eq!(queue.pending().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_failed_jobs_retry_then_dead_letter() {
+        let clock = ManualClock::new();
+        let queue = JobQueue::in_memory(JobQueueConfig {
+            max_attempts: 2,
+            retry_delay: Duration::from_secs(5),
+            ..Default::default()
+        })
+        .unwrap()
+        .with_clock(clock.clone());
+        let custom = AgentRole::Custom("trader".to_string());
+        queue.enqueue(&custom, task("Hedge")).unwrap();
+        assert!(queue.lease(&AgentRole::Trader).unwrap().is_none());
+
+        let job = queue.lease(&custom).unwrap().unwrap();
+        queue.fail(&job.id, "exchange offline").unwrap();
+        assert!(queue.lease(&custom).unwrap().is_none());
+        clock.advance(Duration::from_secs(5));
+        let job = queue.lease(&custom).unwrap().unwrap();
+        queue.fail(&job.id, "exchange still offline").unwrap();
+
+        let dead = queue.dead_letters().unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!((dead[0].attempts, dead[0].last_error.as_deref()), (2, Some("exchange still offline")));
+
+        let done = queue.enqueue(&custom, task("Report")).unwrap();
+        queue.lease(&custom).unwrap().unwrap();
+        queue.ack(&done.id).unwrap();
+        assert_eq!(queue.pending().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_dead_letter_skips_retries_and_huge_delays_do_not_overflow() {
+        let queue = JobQueue::in_memory(JobQueueConfig {
+            visibility_timeout: Duration::MAX,
+            retry_delay: Duration::MAX,
+            ..Default::default()
+        })
+        .unwrap();
+        let job = queue.enqueue_leased(&AgentRole::Trader, task("Buy 1 SOL")).unwrap();
+        assert_eq!(job.visible_at, DateTime::<Utc>::MAX_UTC);
+        queue.fail(&job.id, "exchange offline").unwrap();
+        assert_eq!(queue.pending().unwrap(), 1);
+
+        queue.dead_letter(&job.id, "exchange offline").unwrap();
+        assert_eq!(queue.pending().unwrap(), 0);
+        let dead = queue.dead_letters().unwrap();
+        assert_eq!((dead[0].attempts, dead[0].last_error.as_deref()), (1, Some("exchange offline")));
+    }
+}
@@ -0,0 +1,844 @@
+//! Shared task board for coordinator-less workflows
+//!
+//! Agents post tasks to a durable board and workers claim them one at a time.
+//! Claims are leases: if a worker crashes, its task returns to `Open` once the
+//! lease expires so another worker can pick it up.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, Mutex};
+
+use super::{AgentMessage, AgentRole, MessageType};
+use crate::error::{Error, Result};
+
+/// Default claim lease
+pub const DEFAULT_LEASE: Duration = Duration::from_secs(300);
+
+/// Status of a task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TaskStatus {
+    /// Waiting to be claimed
+    Open,
+    /// Being worked on
+    Claimed {
+        /// Claiming agent
+        by: String,
+        /// When the claim lapses unless renewed
+        lease_expires_at: DateTime<Utc>,
+    },
+    /// Finished successfully
+    Done {
+        /// Result reported by the worker
+        result: String,
+    },
+    /// Finished with an error
+    Failed {
+        /// Error reported by the worker
+        error: String,
+    },
+}
+
+impl TaskStatus {
+    /// Status without its details
+    pub fn state(&self) -> TaskState {
+        match self {
+            Self::Open => TaskState::Open,
+            Self::Claimed { .. } => TaskState::Claimed,
+            Self::Done { .. } => TaskState::Done,
+            Self::Failed { .. } => TaskState::Failed,
+        }
+    }
+}
+
+/// Status discriminant used for filtering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Open,
+    Claimed,
+    Done,
+    Failed,
+}
+
+/// A task on the board
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    /// Unique task ID
+    pub id: String,
+    /// Short description
+    pub title: String,
+    /// Task input
+    #[serde(default)]
+    pub payload: serde_json::Value,
+    /// Agent that posted the task
+    pub creator: String,
+    /// Current status
+    pub status: TaskStatus,
+    /// Higher priorities are claimed first
+    #[serde(default)]
+    pub priority: i32,
+    /// When the task was posted
+    pub created_at: DateTime<Utc>,
+    /// When the status last changed
+    pub updated_at: DateTime<Utc>,
+    /// Optional due time (informational; overdue tasks are claimed first)
+    pub deadline: Option<DateTime<Utc>>,
+}
+
+/// A task to post
+#[derive(Debug, Clone)]
+pub struct NewTask {
+    pub title: String,
+    pub creator: String,
+    pub payload: serde_json::Value,
+    pub priority: i32,
+    pub deadline: Option<DateTime<Utc>>,
+}
+
+impl NewTask {
+    /// Create a task with an empty payload and default priority
+    pub fn new(title: impl Into<String>, creator: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            creator: creator.into(),
+            payload: serde_json::Value::Null,
+            priority: 0,
+            deadline: None,
+        }
+    }
+
+    /// Set the task input
+    pub fn with_payload(mut self, payload: serde_json::Value) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Set the priority
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Set a deadline
+    pub fn with_deadline(mut self, deadline: DateTime<Utc>) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+}
+
+/// Criteria for listing or claiming tasks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskFilter {
+    /// Only tasks in this state
+    pub state: Option<TaskState>,
+    /// Only tasks posted by this agent
+    pub creator: Option<String>,
+    /// Only tasks whose title contains this text (case-insensitive)
+    pub title_contains: Option<String>,
+    /// Only tasks at or above this priority
+    pub min_priority: Option<i32>,
+}
+
+impl TaskFilter {
+    /// Match everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only tasks in `state`
+    pub fn with_state(mut self, state: TaskState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Only tasks posted by `creator`
+    pub fn with_creator(mut self, creator: impl Into<String>) -> Self {
+        self.creator = Some(creator.into());
+        self
+    }
+
+    /// Only tasks whose title contains `text`
+    pub fn with_title(mut self, text: impl Into<String>) -> Self {
+        self.title_contains = Some(text.into());
+        self
+    }
+
+    /// Only tasks at or above `priority`
+    pub fn with_min_priority(mut self, priority: i32) -> Self {
+        self.min_priority = Some(priority);
+        self
+    }
+
+    /// Whether `task` matches
+    pub fn matches(&self, task: &Task) -> bool {
+        self.state.is_none_or(|s| task.status.state() == s)
+            && self.creator.as_ref().is_none_or(|c| &task.creator == c)
+            && self
+                .title_contains
+                .as_ref()
+                .is_none_or(|t| task.title.to_lowercase().contains(&t.to_lowercase()))
+            && self.min_priority.is_none_or(|p| task.priority >= p)
+    }
+}
+
+/// Task board activity
+#[derive(Debug, Clone)]
+pub enum TaskEvent {
+    Posted {
+        id: String,
+        title: String,
+    },
+    Claimed {
+        id: String,
+        by: String,
+    },
+    Completed {
+        id: String,
+        by: String,
+        latency_ms: i64,
+    },
+    Failed {
+        id: String,
+        by: String,
+        error: String,
+    },
+    LeaseExpired {
+        id: String,
+        by: String,
+    },
+}
+
+/// Queue depth and completion metrics
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskBoardStats {
+    pub open: usize,
+    pub claimed: usize,
+    pub done: usize,
+    pub failed: usize,
+    /// Mean time from posting to completion, over completed tasks
+    pub avg_completion_ms: Option<i64>,
+}
+
+/// Persistence for the task board
+#[async_trait]
+pub trait TaskStore: Send + Sync {
+    async fn load(&self) -> Result<Vec<Task>>;
+    async fn save(&self, tasks: &[Task]) -> Result<()>;
+}
+
+/// JSON file store for the task board
+pub struct FileTaskStore {
+    path: PathBuf,
+}
+
+impl FileTaskStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl TaskStore for FileTaskStore {
+    async fn load(&self) -> Result<Vec<Task>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = tokio::fs::read_to_string(&self.path).await?;
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        serde_json::from_str(&content).map_err(|e| {
+            Error::Internal(format!(
+                "Task board file at {:?} is malformed: {}",
+                self.path, e
+            ))
+        })
+    }
+
+    async fn save(&self, tasks: &[Task]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await.ok();
+        }
+
+        // Write tmp -> rename so a crash never leaves a truncated board
+        let tmp_path = self
+            .path
+            .with_extension(format!("tmp.{}", uuid::Uuid::new_v4()));
+        tokio::fs::write(&tmp_path, serde_json::to_vec_pretty(tasks)?).await?;
+        if let Err(e) = tokio::fs::rename(&tmp_path, &self.path).await {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+/// Store that keeps nothing (the board is lost on restart)
+pub struct InMemoryTaskStore;
+
+#[async_trait]
+impl TaskStore for InMemoryTaskStore {
+    async fn load(&self) -> Result<Vec<Task>> {
+        Ok(Vec::new())
+    }
+    async fn save(&self, _: &[Task]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Shared queue of claimable tasks
+///
+/// All mutations run under a single lock and are persisted before they
+/// return, so two agents can never claim the same task.
+pub struct TaskBoard {
+    tasks: Mutex<Vec<Task>>,
+    store: Arc<dyn TaskStore>,
+    lease: Duration,
+    events: broadcast::Sender<TaskEvent>,
+}
+
+impl TaskBoard {
+    /// Open a board, reloading tasks from the store
+    pub async fn new(store: Arc<dyn TaskStore>) -> Result<Self> {
+        let tasks = store.load().await?;
+        let (events, _) = broadcast::channel(100);
+        Ok(Self {
+            tasks: Mutex::new(tasks),
+            store,
+            lease: DEFAULT_LEASE,
+            events,
+        })
+    }
+
+    /// Set how long a claim lasts before the task is reopened
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Subscribe to board activity
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.events.subscribe()
+    }
+
+    fn emit(&self, event: TaskEvent) {
+        let _ = self.events.send(event);
+    }
+
+    fn lease_expiry(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + chrono::Duration::from_std(self.lease).unwrap_or(chrono::Duration::MAX)
+    }
+
+    /// Post a new open task
+    pub async fn post(&self, new: NewTask) -> Result<Task> {
+        let now = Utc::now();
+        let task = Task {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: new.title,
+            payload: new.payload,
+            creator: new.creator,
+            status: TaskStatus::Open,
+            priority: new.priority,
+            created_at: now,
+            updated_at: now,
+            deadline: new.deadline,
+        };
+
+        let mut tasks = self.tasks.lock().await;
+        tasks.push(task.clone());
+        if let Err(e) = self.store.save(&tasks).await {
+            tasks.pop();
+            return Err(e);
+        }
+        drop(tasks);
+
+        tracing::debug!(id = %task.id, title = %task.title, "Task posted");
+        self.emit(TaskEvent::Posted {
+            id: task.id.clone(),
+            title: task.title.clone(),
+        });
+        Ok(task)
+    }
+
+    /// Atomically claim the most urgent open task matching `filter`
+    ///
+    /// Tasks are ordered by priority, then deadline, then age. Expired leases
+    /// are reclaimed first.
+    pub async fn claim_next(&self, agent: &str, filter: &TaskFilter) -> Result<Option<Task>> {
+        let now = Utc::now();
+        let mut tasks = self.tasks.lock().await;
+        let expired = self.reclaim_locked(&mut tasks, now);
+        let mut previous = expired.clone();
+
+        let candidate = tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.status == TaskStatus::Open && filter.matches(t))
+            .min_by_key(|(_, t)| {
+                (
+                    std::cmp::Reverse(t.priority),
+                    t.deadline.unwrap_or(DateTime::<Utc>::MAX_UTC),
+                    t.created_at,
+                )
+            })
+            .map(|(i, _)| i);
+
+        let claimed = candidate.map(|i| {
+            previous.push((i, tasks[i].clone()));
+            let task = &mut tasks[i];
+            task.status = TaskStatus::Claimed {
+                by: agent.to_string(),
+                lease_expires_at: self.lease_expiry(now),
+            };
+            task.updated_at = now;
+            task.clone()
+        });
+
+        if !previous.is_empty() {
+            self.save_or_restore(&mut tasks, previous).await?;
+        }
+        drop(tasks);
+        self.emit_expired(&expired);
+
+        if let Some(task) = &claimed {
+            tracing::debug!(id = %task.id, agent, "Task claimed");
+            self.emit(TaskEvent::Claimed {
+                id: task.id.clone(),
+                by: agent.to_string(),
+            });
+        }
+        Ok(claimed)
+    }
+
+    /// Extend the lease on a task `agent` holds
+    pub async fn renew_lease(&self, id: &str, agent: &str) -> Result<Task> {
+        let now = Utc::now();
+        let expiry = self.lease_expiry(now);
+        self.update_claimed(id, agent, |task| {
+            task.status = TaskStatus::Claimed {
+                by: agent.to_string(),
+                lease_expires_at: expiry,
+            };
+        })
+        .await
+    }
+
+    /// Mark a claimed task as done
+    pub async fn complete(&self, id: &str, agent: &str, result: impl Into<String>) -> Result<Task> {
+        let result = result.into();
+        let task = self
+            .update_claimed(id, agent, |task| task.status = TaskStatus::Done { result })
+            .await?;
+
+        let latency_ms = (task.updated_at - task.created_at).num_milliseconds();
+        tracing::info!(id = %task.id, agent, latency_ms, "Task completed");
+        self.emit(TaskEvent::Completed {
+            id: task.id.clone(),
+            by: agent.to_string(),
+            latency_ms,
+        });
+        Ok(task)
+    }
+
+    /// Mark a claimed task as failed
+    pub async fn fail(&self, id: &str, agent: &str, error: impl Into<String>) -> Result<Task> {
+        let error = error.into();
+        let task = self
+            .update_claimed(id, agent, |task| {
+                task.status = TaskStatus::Failed {
+                    error: error.clone(),
+                }
+            })
+            .await?;
+
+        tracing::warn!(id = %task.id, agent, error = %error, "Task failed");
+        self.emit(TaskEvent::Failed {
+            id: task.id.clone(),
+            by: agent.to_string(),
+            error,
+        });
+        Ok(task)
+    }
+
+    async fn update_claimed(
+        &self,
+        id: &str,
+        agent: &str,
+        f: impl FnOnce(&mut Task),
+    ) -> Result<Task> {
+        let now = Utc::now();
+        let mut tasks = self.tasks.lock().await;
+        let expired = self.reclaim_locked(&mut tasks, now);
+
+        let Some(index) = tasks.iter().position(|t| t.id == id) else {
+            Self::restore(&mut tasks, expired);
+            return Err(Error::AgentCoordination(format!("Task not found: {}", id)));
+        };
+        match &tasks[index].status {
+            TaskStatus::Claimed { by, .. } if by == agent => {}
+            other => {
+                let err = Error::AgentCoordination(format!(
+                    "Task {} is not claimed by {} (status: {:?})",
+                    id,
+                    agent,
+                    other.state()
+                ));
+                Self::restore(&mut tasks, expired);
+                return Err(err);
+            }
+        }
+
+        let mut previous = expired.clone();
+        previous.push((index, tasks[index].clone()));
+        let task = &mut tasks[index];
+        f(task);
+        task.updated_at = now;
+        let updated = task.clone();
+        self.save_or_restore(&mut tasks, previous).await?;
+        drop(tasks);
+        self.emit_expired(&expired);
+        Ok(updated)
+    }
+
+    /// Reopen tasks whose claim lease has lapsed, returning how many were reopened
+    pub async fn reclaim_expired(&self) -> Result<usize> {
+        let mut tasks = self.tasks.lock().await;
+        let expired = self.reclaim_locked(&mut tasks, Utc::now());
+        if !expired.is_empty() {
+            self.save_or_restore(&mut tasks, expired.clone()).await?;
+        }
+        drop(tasks);
+        self.emit_expired(&expired);
+        Ok(expired.len())
+    }
+
+    /// Reopen lapsed claims, returning each reopened task's index and previous version
+    fn reclaim_locked(&self, tasks: &mut [Task], now: DateTime<Utc>) -> Vec<(usize, Task)> {
+        let mut expired = Vec::new();
+        for (i, task) in tasks.iter_mut().enumerate() {
+            if let TaskStatus::Claimed { lease_expires_at, .. } = &task.status {
+                if *lease_expires_at <= now {
+                    expired.push((i, task.clone()));
+                    task.status = TaskStatus::Open;
+                    task.updated_at = now;
+                }
+            }
+        }
+        expired
+    }
+
+    /// Report leases reopened by [`reclaim_locked`](Self::reclaim_locked) once they are saved
+    fn emit_expired(&self, expired: &[(usize, Task)]) {
+        for (_, task) in expired {
+            if let TaskStatus::Claimed { by, .. } = &task.status {
+                tracing::warn!(id = %task.id, agent = %by, "Task lease expired; reopening");
+                self.emit(TaskEvent::LeaseExpired {
+                    id: task.id.clone(),
+                    by: by.clone(),
+                });
+            }
+        }
+    }
+
+    /// Persist `tasks`, putting back the `previous` versions of changed entries if that fails
+    async fn save_or_restore(&self, tasks: &mut [Task], previous: Vec<(usize, Task)>) -> Result<()> {
+        if let Err(e) = self.store.save(tasks).await {
+            Self::restore(tasks, previous);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn restore(tasks: &mut [Task], previous: Vec<(usize, Task)>) {
+        // Newest first, so an entry changed twice ends up at its original version
+        for (i, task) in previous.into_iter().rev() {
+            tasks[i] = task;
+        }
+    }
+
+    /// Get a task by ID
+    pub async fn get(&self, id: &str) -> Option<Task> {
+        self.tasks.lock().await.iter().find(|t| t.id == id).cloned()
+    }
+
+    /// Tasks matching `filter`, oldest first
+    pub async fn list(&self, filter: &TaskFilter) -> Vec<Task> {
+        self.tasks
+            .lock()
+            .await
+            .iter()
+            .filter(|t| filter.matches(t))
+            .cloned()
+            .collect()
+    }
+
+    /// Queue depth and completion latency
+    pub async fn stats(&self) -> TaskBoardStats {
+        let tasks = self.tasks.lock().await;
+        let mut stats = TaskBoardStats::default();
+        let mut total_latency = 0i64;
+        for task in tasks.iter() {
+            match task.status.state() {
+                TaskState::Open => stats.open += 1,
+                TaskState::Claimed => stats.claimed += 1,
+                TaskState::Done => {
+                    stats.done += 1;
+                    total_latency += (task.updated_at - task.created_at).num_milliseconds();
+                }
+                TaskState::Failed => stats.failed += 1,
+            }
+        }
+        if stats.done > 0 {
+            stats.avg_completion_ms = Some(total_latency / stats.done as i64);
+        }
+        stats
+    }
+
+    /// Claim matching tasks in the background and feed them to an agent's
+    /// [`listen`](crate::agent::Agent::listen) loop as external events
+    ///
+    /// The agent is expected to report back with `complete` or `fail` (for
+    /// example via the task tools); unfinished tasks reopen when the lease lapses.
+    pub fn spawn_poller(
+        self: &Arc<Self>,
+        agent: impl Into<String>,
+        filter: TaskFilter,
+        interval: Duration,
+        sender: mpsc::Sender<AgentMessage>,
+    ) -> tokio::task::JoinHandle<()> {
+        let board = self.clone();
+        let agent = agent.into();
+        tokio::spawn(async move {
+            loop {
+                match board.claim_next(&agent, &filter).await {
+                    Ok(Some(task)) => {
+                        let message = AgentMessage {
+                            from: AgentRole::Custom(task.creator.clone()),
+                            to: Some(AgentRole::Custom(agent.clone())),
+                            content: format!(
+                                "Task {} claimed for you: {}\nPayload: {}\nReport back with the task tools when finished.",
+                                task.id, task.title, task.payload
+                            ),
+                            msg_type: MessageType::Request,
+                        };
+                        if sender.send(message).await.is_err() {
+                            // Listener is gone; let the lease lapse
+                            break;
+                        }
+                        continue;
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!("Task poller for {} failed to claim: {}", agent, e),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn board() -> Arc<TaskBoard> {
+        Arc::new(TaskBoard::new(Arc::new(InMemoryTaskStore)).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_claims_are_exclusive() {
+        let board = board().await;
+        for i in 0..5 {
+            board
+                .post(NewTask::new(format!("verify contract {}", i), "analyst"))
+                .await
+                .unwrap();
+        }
+
+        let mut handles = Vec::new();
+        for w in 0..10 {
+            let board = board.clone();
+            handles.push(tokio::spawn(async move {
+                board
+                    .claim_next(&format!("worker{}", w), &TaskFilter::new())
+                    .await
+                    .unwrap()
+            }));
+        }
+
+        let mut claimed = Vec::new();
+        for handle in handles {
+            if let Some(task) = handle.await.unwrap() {
+                claimed.push(task.id);
+            }
+        }
+        claimed.sort();
+        claimed.dedup();
+        assert_eq!(claimed.len(), 5);
+        assert_eq!(board.stats().await.claimed, 5);
+    }
+
+    #[tokio::test]
+    async fn test_priority_and_completion() {
+        let board = board().await;
+        board.post(NewTask::new("low", "a")).await.unwrap();
+        let high = board
+            .post(NewTask::new("high", "a").with_priority(5))
+            .await
+            .unwrap();
+
+        let task = board
+            .claim_next("w1", &TaskFilter::new())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(task.id, high.id);
+
+        // Only the claimant may finish the task
+        assert!(board.complete(&task.id, "w2", "nope").await.is_err());
+        let done = board.complete(&task.id, "w1", "ok").await.unwrap();
+        assert_eq!(
+            done.status,
+            TaskStatus::Done {
+                result: "ok".to_string()
+            }
+        );
+        assert!(board.fail(&task.id, "w1", "late").await.is_err());
+
+        let stats = board.stats().await;
+        assert_eq!((stats.open, stats.done), (1, 1));
+        assert!(stats.avg_completion_ms.is_some());
+        assert_eq!(
+            board
+                .list(&TaskFilter::new().with_state(TaskState::Open))
+                .await
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lease_expiry_reopens_task() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(FileTaskStore::new(dir.path().join("tasks.json")));
+        let board = TaskBoard::new(store.clone())
+            .await
+            .unwrap()
+            .with_lease(Duration::from_millis(20));
+        let mut events = board.subscribe();
+
+        let task = board.post(NewTask::new("check", "analyst")).await.unwrap();
+        board
+            .claim_next("crashed", &TaskFilter::new())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(board
+            .claim_next("w2", &TaskFilter::new())
+            .await
+            .unwrap()
+            .is_none());
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        let reclaimed = board
+            .claim_next("w2", &TaskFilter::new())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reclaimed.id, task.id);
+        assert!(board.complete(&task.id, "crashed", "stale").await.is_err());
+
+        let mut expired = false;
+        while let Ok(event) = events.try_recv() {
+            expired |= matches!(event, TaskEvent::LeaseExpired { ref by, .. } if by == "crashed");
+        }
+        assert!(expired);
+
+        // Claims are durable
+        let reopened = TaskBoard::new(store).await.unwrap();
+        assert_eq!(reopened.stats().await.claimed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_poller_feeds_listen_loop() {
+        let board = board().await;
+        board
+            .post(
+                NewTask::new("summarize", "analyst")
+                    .with_payload(serde_json::json!({"token": "SOL"})),
+            )
+            .await
+            .unwrap();
+
+        let (tx, mut rx) = mpsc::channel(4);
+        let handle = board.spawn_poller("worker", TaskFilter::new(), Duration::from_millis(10), tx);
+        let message = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        handle.abort();
+
+        assert!(message.content.contains("summarize"));
+        assert!(message.content.contains("SOL"));
+        assert_eq!(board.stats().await.claimed, 1);
+    }
+
+    /// Store whose saves fail while `failing` is set
+    #[derive(Default)]
+    struct FlakyStore {
+        failing: std::sync::atomic::AtomicBool,
+    }
+
+    #[async_trait]
+    impl TaskStore for FlakyStore {
+        async fn load(&self) -> Result<Vec<Task>> {
+            Ok(Vec::new())
+        }
+        async fn save(&self, _: &[Task]) -> Result<()> {
+            if self.failing.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(Error::Internal("disk full".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_save_rolls_back_changes() {
+        let store = Arc::new(FlakyStore::default());
+        let board = TaskBoard::new(store.clone())
+            .await
+            .unwrap()
+            .with_lease(Duration::from_millis(20));
+        let mut events = board.subscribe();
+        let fail = |on: bool| store.failing.store(on, std::sync::atomic::Ordering::SeqCst);
+
+        let task = board.post(NewTask::new("check", "analyst")).await.unwrap();
+        let _ = events.try_recv();
+
+        fail(true);
+        assert!(board.claim_next("w1", &TaskFilter::new()).await.is_err());
+        assert_eq!(board.get(&task.id).await.unwrap().status, TaskStatus::Open);
+
+        fail(false);
+        board.claim_next("w1", &TaskFilter::new()).await.unwrap().unwrap();
+        let claimed = board.get(&task.id).await.unwrap().status;
+
+        fail(true);
+        assert!(board.complete(&task.id, "w1", "ok").await.is_err());
+        assert_eq!(board.get(&task.id).await.unwrap().status, claimed);
+
+        // Lease lapses, but neither the reclaim nor a new claim survives a failed save
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(board.reclaim_expired().await.is_err());
+        assert!(board.claim_next("w2", &TaskFilter::new()).await.is_err());
+        assert_eq!(board.get(&task.id).await.unwrap().status, claimed);
+
+        let _ = events.try_recv(); // the successful claim
+        assert!(events.try_recv().is_err());
+    }
+}
@@ -137,4 +137,8 @@ impl ContextInjector for PersonalityManager {
         // Personas are injected as a hidden system-style guidance piece
         Ok(vec![Message::system(self.persona.to_prompt())])
     }
+
+    fn source_key(&self) -> String {
+        "persona".to_string()
+    }
 }
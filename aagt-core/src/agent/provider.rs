@@ -1,15 +1,37 @@
 //! Provider trait for LLM integrations
 
+use std::collections::BTreeMap;
+
 use async_trait::async_trait;
 
 use crate::error::Result;
 use crate::agent::message::Message;
-use crate::agent::streaming::StreamingResponse;
+use crate::agent::message::ToolCall;
+use crate::agent::streaming::{MockStreamBuilder, StreamingChoice, StreamingResponse, Usage};
 use crate::skills::tool::ToolDefinition;
 
+mod key_pool;
+mod latency;
+mod middleware;
+mod pricing;
+mod priority;
+mod recording;
 mod resilient;
+mod retry;
 
+pub use key_pool::{with_pool_session, ApiKeyEntry, KeyPool, KeySelection, KeyUsage, PooledProvider};
+pub use latency::{
+    current_latency_tag, with_latency_tag, LatencyPhase, LatencyProvider, LatencyRecorder, LatencyReport,
+    LatencySample, LatencyStats, Percentiles,
+};
+pub use middleware::{ProviderMiddleware, RedactingLogger, WithMiddleware};
+pub use pricing::{ModelPrice, PriceTable};
+pub use priority::{
+    current_priority, with_priority, PriorityGate, PriorityGateConfig, PriorityGateStats, RequestPriority,
+};
+pub use recording::{RecordedExchange, RecordingProvider, ReplayProvider, RequestKey};
 pub use resilient::{ResilientProvider, CircuitBreakerConfig};
+pub use retry::{RetryConfig, RetryProvider};
 
 /// Request for a chat completion
 #[derive(Debug, Clone, Default)]
@@ -26,8 +48,71 @@ pub struct ChatRequest {
     pub temperature: Option<f64>,
     /// Optional max tokens
     pub max_tokens: Option<u64>,
+    /// Token budget for the model's reasoning, for providers that take one
+    pub max_reasoning_tokens: Option<u64>,
     /// Optional provider-specific parameters
     pub extra_params: Option<serde_json::Value>,
+    /// Extra HTTP headers, sent by the HTTP providers that support them
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Full reply to a [`ChatRequest`], as returned by [`Provider::complete`]
+#[derive(Debug, Clone, Default)]
+pub struct CompletionResponse {
+    /// Generated text
+    pub text: String,
+    /// Tools the model asked to call, in order
+    pub tool_calls: Vec<ToolCall>,
+    /// Token usage, if the provider reported it
+    pub usage: Option<Usage>,
+    /// Why generation stopped (e.g. `stop`, `length`, `tool_calls`)
+    pub finish_reason: Option<String>,
+}
+
+impl CompletionResponse {
+    /// Drain `stream` into a single response
+    ///
+    /// Streams carry no finish reason, so it is inferred from the tool calls.
+    pub async fn from_stream(stream: StreamingResponse) -> Result<Self> {
+        use futures::StreamExt;
+
+        let mut response = Self::default();
+        let mut stream = stream.into_inner();
+        while let Some(chunk) = stream.next().await {
+            match chunk? {
+                StreamingChoice::Message(text) => response.text.push_str(&text),
+                StreamingChoice::ToolCall { id, name, arguments } => {
+                    response.tool_calls.push(ToolCall { id, name, arguments });
+                }
+                StreamingChoice::ParallelToolCalls(map) => {
+                    let mut sorted: Vec<_> = map.into_iter().collect();
+                    sorted.sort_by_key(|(k, _)| *k);
+                    response.tool_calls.extend(sorted.into_iter().map(|(_, tc)| tc));
+                }
+                StreamingChoice::Usage(usage) => response.usage = Some(usage),
+                StreamingChoice::Thought(_) | StreamingChoice::Metadata(_) => {}
+                StreamingChoice::Done => break,
+            }
+        }
+        let reason = if response.tool_calls.is_empty() { "stop" } else { "tool_calls" };
+        response.finish_reason = Some(reason.to_string());
+        Ok(response)
+    }
+
+    /// Replay the response as a stream: text, tool calls, usage, then done
+    pub fn into_stream(self) -> StreamingResponse {
+        let mut builder = MockStreamBuilder::new();
+        if !self.text.is_empty() {
+            builder = builder.message(self.text);
+        }
+        for call in self.tool_calls {
+            builder = builder.tool_call(call.id, call.name, call.arguments);
+        }
+        if let Some(usage) = self.usage {
+            builder = builder.usage(usage);
+        }
+        builder.done().build()
+    }
 }
 
 /// Trait for LLM providers
@@ -41,6 +126,14 @@ pub trait Provider: Send + Sync {
         request: ChatRequest,
     ) -> Result<StreamingResponse>;
 
+    /// Run a completion request without streaming
+    ///
+    /// Defaults to draining [`Provider::stream_completion`]; providers with a
+    /// non-streaming endpoint should override it.
+    async fn complete(&self, request: ChatRequest) -> Result<CompletionResponse> {
+        CompletionResponse::from_stream(self.stream_completion(request).await?).await
+    }
+
     /// Get provider name (for logging/debugging)
     fn name(&self) -> &'static str;
 
@@ -53,4 +146,53 @@ pub trait Provider: Send + Sync {
     fn supports_tools(&self) -> bool {
         true
     }
+
+    /// Check if provider accepts image parts; agents send placeholders to those that don't
+    fn supports_vision(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Streaming;
+
+    #[async_trait]
+    impl Provider for Streaming {
+        async fn stream_completion(&self, _request: ChatRequest) -> Result<StreamingResponse> {
+            Ok(MockStreamBuilder::new()
+                .message("Checking ")
+                .message("SOL")
+                .tool_call("call_1", "get_price", serde_json::json!({"symbol": "SOL"}))
+                .usage(Usage {
+                    prompt_tokens: 12,
+                    completion_tokens: 5,
+                    total_tokens: 17,
+                    ..Default::default()
+                })
+                .done()
+                .build())
+        }
+
+        fn name(&self) -> &'static str {
+            "streaming"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_complete_drains_the_stream() {
+        let response = Streaming.complete(ChatRequest::default()).await.unwrap();
+        assert_eq!(response.text, "Checking SOL");
+        assert_eq!(response.tool_calls.len(), 1);
+        assert_eq!(response.tool_calls[0].name, "get_price");
+        assert_eq!(response.usage.as_ref().map(|u| u.total_tokens), Some(17));
+        assert_eq!(response.finish_reason.as_deref(), Some("tool_calls"));
+
+        let replayed = CompletionResponse::from_stream(response.into_stream()).await.unwrap();
+        assert_eq!(replayed.text, "Checking SOL");
+        assert_eq!(replayed.tool_calls[0].id, "call_1");
+        assert_eq!(replayed.usage.map(|u| u.prompt_tokens), Some(12));
+    }
 }
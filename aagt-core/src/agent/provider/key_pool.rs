@@ -0,0 +1,870 @@
+//! Spreading requests across several API keys
+//!
+//! A [`KeyPool`] lists API keys for one provider by secret reference; the
+//! values come from a [`SecretProvider`]. [`PooledProvider`] builds one client
+//! per key and picks a key for every request by [`KeySelection`].
+//!
+//! Per-key accounting:
+//! - It records requests, token usage and estimated spend (from a
+//!   [`PriceTable`]).
+//! - Counters reset each calendar month (UTC).
+//! - With [`KeyPool::persist_to`], accounting survives restarts.
+//!
+//! When a key is sidelined:
+//! - After [`KeyPool::sideline_after`] consecutive auth or quota failures
+//!   (401/403/429), the key rests for [`KeyPool::cooldown`]. After that it
+//!   gets a probe request: success restores it, failure sidelines it again.
+//! - A key that reaches its monthly budget is sidelined until the month ends.
+//! - Both cases send a notifier alert. Requests that fail on a key are retried
+//!   on the next key, spending from the request budget.
+//!
+//! Keys with weight 0 are a reserve, used only when no weighted key is usable.
+//! Logs, spans and [`KeyUsage`] identify a key by its secret reference and an
+//! 8-hex-digit fingerprint of its value, never by the value itself.
+//!
+//! Prompt caching is per key (billing project) on most providers, so
+//! rotating keys on every request defeats it. Use
+//! [`KeySelection::StickyPerSession`] for cache-heavy workloads: requests
+//! made inside [`with_pool_session`] stay on one key per session. Outside one,
+//! they stay on one key per system prompt.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Utc};
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn, Instrument};
+
+use crate::agent::budget;
+use crate::agent::provider::PriceTable;
+use crate::agent::provider::{ChatRequest, Provider};
+use crate::agent::streaming::{StreamingChoice, StreamingResponse, Usage};
+use crate::error::{Error, Result};
+use crate::infra::notification::{Notifier, NotifyChannel};
+use crate::infra::secrets::SecretProvider;
+
+tokio::task_local! {
+    static POOL_SESSION: String;
+}
+
+/// Run `fut` with its provider calls sticking to one key per `session`
+pub async fn with_pool_session<F: Future>(session: impl Into<String>, fut: F) -> F::Output {
+    POOL_SESSION.scope(session.into(), fut).await
+}
+
+/// One key in a pool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyEntry {
+    /// Secret name to resolve the key value through
+    pub secret_ref: String,
+    /// Share of traffic; 0 keeps the key in reserve
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// Estimated USD spend per calendar month before the key is sidelined
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monthly_budget: Option<f64>,
+    /// Never use this key
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl ApiKeyEntry {
+    /// Key resolved from `secret_ref`, weight 1, no budget
+    pub fn new(secret_ref: impl Into<String>) -> Self {
+        Self {
+            secret_ref: secret_ref.into(),
+            weight: default_weight(),
+            monthly_budget: None,
+            disabled: false,
+        }
+    }
+
+    /// Share of traffic; 0 keeps the key in reserve
+    pub fn weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Estimated USD spend per month before the key is sidelined
+    pub fn monthly_budget(mut self, usd: f64) -> Self {
+        self.monthly_budget = Some(usd);
+        self
+    }
+
+    /// Never use this key
+    pub fn disabled(mut self) -> Self {
+        self.disabled = true;
+        self
+    }
+}
+
+/// How a pool picks a key per request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySelection {
+    /// Smooth weighted round-robin
+    #[default]
+    WeightedRoundRobin,
+    /// The key unused for longest
+    LeastRecentlyUsed,
+    /// One key per session (weighted rendezvous hashing), for prompt-cache affinity
+    StickyPerSession,
+}
+
+/// Keys for one provider and how to use them
+#[derive(Debug, Clone)]
+pub struct KeyPool {
+    /// Keys in the pool
+    pub keys: Vec<ApiKeyEntry>,
+    /// Key selection strategy
+    pub selection: KeySelection,
+    /// Consecutive auth/quota failures before a key is sidelined
+    pub sideline_after: u32,
+    /// Rest before a sidelined key is probed again
+    pub cooldown: Duration,
+    /// JSON file holding per-key accounting across restarts
+    pub state_path: Option<PathBuf>,
+}
+
+impl KeyPool {
+    /// Pool of `keys` with weighted round-robin, sidelining after 2 failures for 10 minutes
+    pub fn new(keys: Vec<ApiKeyEntry>) -> Self {
+        Self {
+            keys,
+            selection: KeySelection::default(),
+            sideline_after: 2,
+            cooldown: Duration::from_secs(600),
+            state_path: None,
+        }
+    }
+
+    /// Key selection strategy
+    pub fn selection(mut self, selection: KeySelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Consecutive auth/quota failures before a key is sidelined
+    pub fn sideline_after(mut self, failures: u32) -> Self {
+        self.sideline_after = failures.max(1);
+        self
+    }
+
+    /// Rest before a sidelined key is probed again
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Persist per-key accounting to `path`
+    pub fn persist_to(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_path = Some(path.into());
+        self
+    }
+}
+
+/// Accounting for one key in the current month
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeyUsage {
+    /// Secret reference of the key
+    pub secret_ref: String,
+    /// Short fingerprint of the key value
+    pub fingerprint: String,
+    /// Month the counters cover (`YYYY-MM`)
+    pub month: String,
+    /// Requests sent with this key
+    pub requests: u64,
+    /// Requests that failed with an auth or quota error
+    pub failures: u64,
+    /// Prompt tokens reported by the provider
+    pub prompt_tokens: u64,
+    /// Completion tokens reported by the provider
+    pub completion_tokens: u64,
+    /// Estimated USD spend
+    pub spend_usd: f64,
+    /// Auth/quota failures since the last success
+    pub consecutive_failures: u32,
+    /// Sidelined until this time (probed after it)
+    pub sidelined_until: Option<DateTime<Utc>>,
+    /// Why the key is sidelined
+    pub sideline_reason: Option<String>,
+    /// Last time the key was picked
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl KeyUsage {
+    /// Short label for logs and metrics
+    pub fn label(&self) -> String {
+        format!("{}#{}", self.secret_ref, self.fingerprint)
+    }
+
+    fn roll_month(&mut self, month: &str) {
+        if self.month != month {
+            *self = Self {
+                secret_ref: std::mem::take(&mut self.secret_ref),
+                fingerprint: std::mem::take(&mut self.fingerprint),
+                month: month.to_string(),
+                last_used: self.last_used,
+                ..Self::default()
+            };
+        }
+    }
+}
+
+/// Whether `error` says the key itself is unusable (auth, quota or its rate limit)
+fn is_key_failure(error: &Error) -> bool {
+    error.is_auth_failure()
+        || error.provider_status() == Some(429)
+        || matches!(error, Error::ProviderRateLimit { .. })
+}
+
+fn fingerprint(value: &str) -> String {
+    Sha256::digest(value.as_bytes())[..4]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn month_of(at: DateTime<Utc>) -> String {
+    at.format("%Y-%m").to_string()
+}
+
+fn hash_of(parts: &[&str]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    parts.hash(&mut hasher);
+    hasher.finish()
+}
+
+struct LedgerState {
+    usage: Vec<KeyUsage>,
+    /// Smooth weighted round-robin counters
+    current: Vec<i64>,
+}
+
+/// Shared per-key state; cloned into every stream so usage chunks are accounted
+#[derive(Clone)]
+struct Ledger {
+    provider: &'static str,
+    config: Arc<KeyPool>,
+    state: Arc<Mutex<LedgerState>>,
+    prices: Arc<PriceTable>,
+    notifier: Option<(Arc<dyn Notifier>, NotifyChannel)>,
+}
+
+impl Ledger {
+    fn usable(&self, usage: &KeyUsage, index: usize, now: DateTime<Utc>) -> bool {
+        let entry = &self.config.keys[index];
+        !entry.disabled
+            && entry.monthly_budget.is_none_or(|b| usage.spend_usd < b)
+            && usage.sidelined_until.is_none_or(|until| until <= now)
+    }
+
+    /// Pick a key not in `tried`, marking it used
+    fn select(&self, now: DateTime<Utc>, sticky: Option<&str>, tried: &[usize]) -> Option<usize> {
+        let month = month_of(now);
+        let mut state = self.state.lock();
+        let state = &mut *state;
+        for usage in &mut state.usage {
+            usage.roll_month(&month);
+        }
+        let usable: Vec<usize> = (0..state.usage.len())
+            .filter(|i| !tried.contains(i) && self.usable(&state.usage[*i], *i, now))
+            .collect();
+        let weighted: Vec<usize> = usable
+            .iter()
+            .copied()
+            .filter(|i| self.config.keys[*i].weight > 0)
+            .collect();
+        let (candidates, reserve) = if weighted.is_empty() {
+            (usable, true)
+        } else {
+            (weighted, false)
+        };
+        let weight = |i: usize| {
+            if reserve {
+                1
+            } else {
+                self.config.keys[i].weight
+            }
+        };
+
+        let chosen = match (self.config.selection, sticky) {
+            (KeySelection::StickyPerSession, Some(session)) => {
+                candidates.iter().copied().max_by(|a, b| {
+                    let score = |i: usize| {
+                        let h = hash_of(&[session, &state.usage[i].fingerprint]);
+                        let u = (h as f64 + 1.0) / (u64::MAX as f64 + 2.0);
+                        weight(i) as f64 / -u.ln()
+                    };
+                    score(*a).total_cmp(&score(*b))
+                })
+            }
+            (KeySelection::LeastRecentlyUsed, _) => candidates
+                .iter()
+                .copied()
+                .min_by_key(|i| state.usage[*i].last_used),
+            _ => {
+                let total: i64 = candidates.iter().map(|i| weight(*i) as i64).sum();
+                for i in &candidates {
+                    state.current[*i] += weight(*i) as i64;
+                }
+                let best = candidates
+                    .iter()
+                    .copied()
+                    .max_by_key(|i| (state.current[*i], std::cmp::Reverse(*i)));
+                if let Some(best) = best {
+                    state.current[best] -= total;
+                }
+                best
+            }
+        }?;
+
+        let usage = &mut state.usage[chosen];
+        usage.requests += 1;
+        usage.last_used = Some(now);
+        if reserve {
+            info!(provider = self.provider, key = %usage.label(), "Using reserve API key");
+        }
+        Some(chosen)
+    }
+
+    /// Clear failures after a successful response; returns a recovery alert
+    fn succeeded(&self, index: usize) -> Option<String> {
+        let alert = {
+            let mut state = self.state.lock();
+            let usage = &mut state.usage[index];
+            usage.consecutive_failures = 0;
+            let recovered = usage.sidelined_until.take().is_some();
+            usage.sideline_reason = None;
+            recovered.then(|| format!("API key {} for {} recovered", usage.label(), self.provider))
+        };
+        self.save();
+        alert
+    }
+
+    /// Count an auth/quota failure; returns an alert when the key gets sidelined
+    fn failed(&self, index: usize, error: &Error, now: DateTime<Utc>) -> Option<String> {
+        let alert = {
+            let mut state = self.state.lock();
+            let usage = &mut state.usage[index];
+            usage.failures += 1;
+            usage.consecutive_failures += 1;
+            warn!(provider = self.provider, key = %usage.label(), "API key failed: {}", error);
+            (usage.consecutive_failures >= self.config.sideline_after).then(|| {
+                let cooldown = chrono::Duration::from_std(self.config.cooldown).unwrap_or_default();
+                usage.sidelined_until = Some(now + cooldown);
+                usage.sideline_reason = Some(error.to_string());
+                format!(
+                    "API key {} for {} sidelined for {:?} after {} failures: {}",
+                    usage.label(),
+                    self.provider,
+                    self.config.cooldown,
+                    usage.consecutive_failures,
+                    error
+                )
+            })
+        };
+        self.save();
+        alert
+    }
+
+    /// Account reported usage; returns an alert when the key reaches its budget
+    fn used(
+        &self,
+        index: usize,
+        model: &str,
+        reported: &Usage,
+        now: DateTime<Utc>,
+    ) -> Option<String> {
+        let alert = {
+            let mut state = self.state.lock();
+            let usage = &mut state.usage[index];
+            usage.prompt_tokens += reported.prompt_tokens as u64;
+            usage.completion_tokens += reported.completion_tokens as u64;
+            usage.spend_usd += self.prices.estimate(model, reported).unwrap_or_default();
+            tracing::debug!(
+                provider = self.provider,
+                key = %usage.label(),
+                spend_usd = usage.spend_usd,
+                "API key usage"
+            );
+            match self.config.keys[index].monthly_budget {
+                Some(budget) if usage.spend_usd >= budget && usage.sideline_reason.is_none() => {
+                    let next_month = (now.date_naive().with_day(1).unwrap_or_default()
+                        + chrono::Months::new(1))
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap_or_default()
+                    .and_utc();
+                    usage.sidelined_until = Some(next_month);
+                    usage.sideline_reason = Some("monthly budget exhausted".to_string());
+                    Some(format!(
+                        "API key {} for {} reached its monthly budget (${:.2} of ${:.2}); sidelined until {}",
+                        usage.label(),
+                        self.provider,
+                        usage.spend_usd,
+                        budget,
+                        next_month.format("%Y-%m-%d")
+                    ))
+                }
+                _ => None,
+            }
+        };
+        self.save();
+        alert
+    }
+
+    async fn alert(&self, message: Option<String>) {
+        let Some(message) = message else { return };
+        warn!("{}", message);
+        if let Some((notifier, channel)) = &self.notifier {
+            if let Err(e) = notifier.notify(channel.clone(), &message).await {
+                warn!("Failed to send key pool alert: {}", e);
+            }
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.config.state_path else {
+            return;
+        };
+        let snapshot: BTreeMap<String, KeyUsage> = self
+            .state
+            .lock()
+            .usage
+            .iter()
+            .map(|u| (u.fingerprint.clone(), u.clone()))
+            .collect();
+        let result = serde_json::to_vec_pretty(&snapshot)
+            .map_err(Error::from)
+            .and_then(|json| {
+                let tmp = path.with_extension("tmp");
+                std::fs::write(&tmp, json)?;
+                std::fs::rename(&tmp, path)?;
+                Ok(())
+            });
+        if let Err(e) = result {
+            warn!(
+                "Failed to persist key pool state to {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// A provider that spreads requests across a [`KeyPool`]
+pub struct PooledProvider<P> {
+    providers: Vec<P>,
+    ledger: Ledger,
+}
+
+impl<P: Provider> PooledProvider<P> {
+    /// Resolve every key through `secrets` and build a client per key with `build`
+    pub fn new(
+        pool: KeyPool,
+        secrets: &dyn SecretProvider,
+        build: impl Fn(&str) -> Result<P>,
+    ) -> Result<Self> {
+        if pool.keys.is_empty() {
+            return Err(Error::agent_config("Key pool has no keys"));
+        }
+        let mut saved: BTreeMap<String, KeyUsage> = match &pool.state_path {
+            Some(path) if path.exists() => serde_json::from_slice(&std::fs::read(path)?)?,
+            _ => BTreeMap::new(),
+        };
+        let month = month_of(Utc::now());
+        let mut providers = Vec::with_capacity(pool.keys.len());
+        let mut usage = Vec::with_capacity(pool.keys.len());
+        for entry in &pool.keys {
+            let secret = secrets.get(&entry.secret_ref)?;
+            providers.push(build(secret.expose())?);
+            let fingerprint = fingerprint(secret.expose());
+            let mut key_usage = saved.remove(&fingerprint).unwrap_or_else(|| KeyUsage {
+                month: month.clone(),
+                ..KeyUsage::default()
+            });
+            key_usage.secret_ref = entry.secret_ref.clone();
+            key_usage.fingerprint = fingerprint;
+            usage.push(key_usage);
+        }
+        let provider = providers[0].name();
+        Ok(Self {
+            providers,
+            ledger: Ledger {
+                provider,
+                state: Arc::new(Mutex::new(LedgerState {
+                    current: vec![0; usage.len()],
+                    usage,
+                })),
+                config: Arc::new(pool),
+                prices: Arc::new(PriceTable::default()),
+                notifier: None,
+            },
+        })
+    }
+
+    /// Estimate spend with `prices`
+    pub fn with_prices(mut self, prices: PriceTable) -> Self {
+        self.ledger.prices = Arc::new(prices);
+        self
+    }
+
+    /// Send sideline and recovery alerts through `notifier`
+    pub fn with_notifier(mut self, notifier: Arc<dyn Notifier>, channel: NotifyChannel) -> Self {
+        self.ledger.notifier = Some((notifier, channel));
+        self
+    }
+
+    /// Accounting for every key, in pool order
+    pub fn usage(&self) -> Vec<KeyUsage> {
+        self.ledger.state.lock().usage.clone()
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Provider for PooledProvider<P> {
+    fn name(&self) -> &'static str {
+        self.ledger.provider
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.providers[0].supports_streaming()
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.providers[0].supports_tools()
+    }
+
+    fn supports_vision(&self) -> bool {
+        self.providers[0].supports_vision()
+    }
+
+    async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+        let sticky = POOL_SESSION
+            .try_with(|s| s.clone())
+            .ok()
+            .or_else(|| request.system_prompt.clone());
+        let mut tried = Vec::new();
+        loop {
+            let Some(index) = self.ledger.select(Utc::now(), sticky.as_deref(), &tried) else {
+                return Err(Error::ProviderAuth(format!(
+                    "No usable API key in the {} key pool (disabled, sidelined or over budget)",
+                    self.ledger.provider
+                )));
+            };
+            if !tried.is_empty() {
+                budget::spend_provider_attempt("key_pool")?;
+            }
+            tried.push(index);
+
+            let label = self.ledger.state.lock().usage[index].label();
+            let span = tracing::debug_span!("pooled_request", provider = self.ledger.provider, key = %label);
+            match self.providers[index]
+                .stream_completion(request.clone())
+                .instrument(span)
+                .await
+            {
+                Ok(stream) => {
+                    self.ledger.alert(self.ledger.succeeded(index)).await;
+                    let ledger = self.ledger.clone();
+                    let model = request.model.clone();
+                    let stream = stream.into_inner().map(move |chunk| {
+                        if let Ok(StreamingChoice::Usage(usage)) = &chunk {
+                            if let Some(alert) = ledger.used(index, &model, usage, Utc::now()) {
+                                let ledger = ledger.clone();
+                                tokio::spawn(async move { ledger.alert(Some(alert)).await });
+                            }
+                        }
+                        chunk
+                    });
+                    return Ok(StreamingResponse::from_stream(stream));
+                }
+                Err(e) if is_key_failure(&e) => {
+                    self.ledger
+                        .alert(self.ledger.failed(index, &e, Utc::now()))
+                        .await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::provider::ModelPrice;
+    use crate::agent::streaming::MockStreamBuilder;
+    use crate::infra::secrets::SecretString;
+    use std::collections::HashMap;
+
+    struct Keys(HashMap<String, String>);
+
+    impl SecretProvider for Keys {
+        fn get(&self, key: &str) -> Result<SecretString> {
+            self.0
+                .get(key)
+                .map(|v| SecretString::new(v.clone()))
+                .ok_or_else(|| Error::SecretNotFound(key.to_string()))
+        }
+    }
+
+    fn keys(n: usize) -> Keys {
+        Keys(
+            (0..n)
+                .map(|i| (format!("KEY_{}", i), format!("sk-value-{}", i)))
+                .collect(),
+        )
+    }
+
+    /// Answers with the key it was built with; keys listed in `rejected` get a 401
+    #[derive(Clone, Default)]
+    struct World {
+        calls: Arc<Mutex<Vec<String>>>,
+        rejected: Arc<Mutex<Vec<String>>>,
+    }
+
+    struct KeyStub {
+        key: String,
+        world: World,
+    }
+
+    #[async_trait]
+    impl Provider for KeyStub {
+        async fn stream_completion(&self, _request: ChatRequest) -> Result<StreamingResponse> {
+            self.world.calls.lock().push(self.key.clone());
+            if self.world.rejected.lock().contains(&self.key) {
+                return Err(Error::Provider {
+                    provider: "openai".to_string(),
+                    status: Some(401),
+                    code: Some("invalid_api_key".to_string()),
+                    message: "Incorrect API key provided".to_string(),
+                    retry_after: None,
+                });
+            }
+            Ok(MockStreamBuilder::new()
+                .message(self.key.clone())
+                .usage(Usage {
+                    prompt_tokens: 1_000_000,
+                    completion_tokens: 0,
+                    total_tokens: 1_000_000,
+                    ..Default::default()
+                })
+                .done()
+                .build())
+        }
+
+        fn name(&self) -> &'static str {
+            "stub"
+        }
+    }
+
+    fn pooled(pool: KeyPool, secrets: &Keys, world: &World) -> PooledProvider<KeyStub> {
+        PooledProvider::new(pool, secrets, |key| {
+            Ok(KeyStub {
+                key: key.to_string(),
+                world: world.clone(),
+            })
+        })
+        .unwrap()
+        .with_prices(PriceTable::empty().set("test", ModelPrice::new(1.0, 0.0)))
+    }
+
+    async fn send(provider: &PooledProvider<KeyStub>, system: &str) -> Result<String> {
+        let request = ChatRequest {
+            model: "test-model".to_string(),
+            system_prompt: Some(system.to_string()),
+            ..ChatRequest::default()
+        };
+        provider
+            .stream_completion(request)
+            .await?
+            .collect_text()
+            .await
+    }
+
+    #[derive(Default)]
+    struct Alerts(Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl Notifier for Alerts {
+        async fn notify(&self, _channel: NotifyChannel, message: &str) -> Result<()> {
+            self.0.lock().push(message.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_selection_strategies_distribute_and_stick() {
+        let secrets = keys(3);
+        let world = World::default();
+        let entries = vec![
+            ApiKeyEntry::new("KEY_0").weight(2),
+            ApiKeyEntry::new("KEY_1"),
+            ApiKeyEntry::new("KEY_2").weight(0),
+        ];
+        let provider = pooled(KeyPool::new(entries.clone()), &secrets, &world);
+        for _ in 0..30 {
+            send(&provider, "same").await.unwrap();
+        }
+        let requests: Vec<u64> = provider.usage().iter().map(|u| u.requests).collect();
+        assert_eq!(requests, [20, 10, 0]);
+        // Smooth: the heavy key never runs three times in a row
+        assert!(!world
+            .calls
+            .lock()
+            .windows(3)
+            .any(|w| w.iter().all(|k| k == "sk-value-0")));
+
+        let lru = pooled(
+            KeyPool::new(entries.clone()).selection(KeySelection::LeastRecentlyUsed),
+            &secrets,
+            &world,
+        );
+        let answers: Vec<String> = futures::future::join_all((0..4).map(|_| send(&lru, "x")))
+            .await
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(
+            answers[..2]
+                .iter()
+                .collect::<std::collections::HashSet<_>>()
+                .len(),
+            2
+        );
+
+        let sticky = pooled(
+            KeyPool::new(entries).selection(KeySelection::StickyPerSession),
+            &secrets,
+            &world,
+        );
+        let mut seen = std::collections::HashSet::new();
+        for session in 0..20 {
+            let first = with_pool_session(format!("s{}", session), send(&sticky, "x"))
+                .await
+                .unwrap();
+            for _ in 0..3 {
+                let again = with_pool_session(format!("s{}", session), send(&sticky, "x"))
+                    .await
+                    .unwrap();
+                assert_eq!(again, first);
+            }
+            seen.insert(first);
+        }
+        assert_eq!(
+            seen.len(),
+            2,
+            "sessions spread over both weighted keys, never the reserve"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_budget_sidelining_and_accounting_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = dir.path().join("keys.json");
+        let secrets = keys(2);
+        let world = World::default();
+        let alerts = Arc::new(Alerts::default());
+        // Every response costs $1
+        let pool = || {
+            KeyPool::new(vec![
+                ApiKeyEntry::new("KEY_0").monthly_budget(2.0),
+                ApiKeyEntry::new("KEY_1"),
+            ])
+            .persist_to(&state)
+        };
+        let provider =
+            pooled(pool(), &secrets, &world).with_notifier(alerts.clone(), NotifyChannel::Log);
+        for _ in 0..6 {
+            send(&provider, "x").await.unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let usage = provider.usage();
+        assert_eq!((usage[0].requests, usage[1].requests), (2, 4));
+        assert_eq!(usage[0].spend_usd, 2.0);
+        assert_eq!(
+            usage[0].sideline_reason.as_deref(),
+            Some("monthly budget exhausted")
+        );
+        assert_eq!(alerts.0.lock().len(), 1);
+        assert!(alerts.0.lock()[0].starts_with(&format!("API key KEY_0#{} ", usage[0].fingerprint)));
+
+        // Restart: accounting and the sideline are restored from disk
+        let restarted = pooled(pool(), &secrets, &world);
+        assert_eq!(restarted.usage(), usage);
+        send(&restarted, "x").await.unwrap();
+        assert_eq!(restarted.usage()[1].requests, 5);
+        assert_eq!(restarted.usage()[1].spend_usd, 5.0);
+
+        // Key values never reach the state file
+        let saved = std::fs::read_to_string(&state).unwrap();
+        assert!(!saved.contains("sk-value"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_key_is_sidelined_then_probed_back() {
+        let secrets = keys(2);
+        let world = World::default();
+        world.rejected.lock().push("sk-value-0".to_string());
+        let alerts = Arc::new(Alerts::default());
+        let provider = pooled(
+            KeyPool::new(vec![ApiKeyEntry::new("KEY_0"), ApiKeyEntry::new("KEY_1")])
+                .selection(KeySelection::LeastRecentlyUsed)
+                .cooldown(Duration::from_millis(50)),
+            &secrets,
+            &world,
+        )
+        .with_notifier(alerts.clone(), NotifyChannel::Log);
+
+        // Each failure fails over to the other key within the same request
+        for _ in 0..4 {
+            assert_eq!(send(&provider, "x").await.unwrap(), "sk-value-1");
+        }
+        let usage = provider.usage();
+        assert_eq!(usage[0].failures, 2);
+        assert!(usage[0].sidelined_until.is_some());
+        assert_eq!(
+            world
+                .calls
+                .lock()
+                .iter()
+                .filter(|k| *k == "sk-value-0")
+                .count(),
+            2
+        );
+
+        // After the cooldown a probe succeeds and the key is back in rotation
+        world.rejected.lock().clear();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(send(&provider, "x").await.unwrap(), "sk-value-0");
+        assert!(provider.usage()[0].sidelined_until.is_none());
+        let alerts = alerts.0.lock();
+        assert_eq!(alerts.len(), 2);
+        assert!(alerts[0].contains("sidelined") && alerts[1].ends_with("recovered"));
+        assert!(alerts.iter().all(|a| !a.contains("sk-value")));
+
+        // With every key rejected the request fails instead of looping
+        world
+            .rejected
+            .lock()
+            .extend(["sk-value-0".to_string(), "sk-value-1".to_string()]);
+        assert!(matches!(
+            send(&provider, "x").await,
+            Err(Error::ProviderAuth(_))
+        ));
+    }
+}
@@ -0,0 +1,644 @@
+//! Streaming latency measurement per provider and model
+//!
+//! [`LatencyProvider`] wraps a provider and times every call on the monotonic
+//! clock: time to first chunk of any kind, time to first content chunk (text,
+//! thought or tool call, not usage/done metadata), total stream time, chunk
+//! counts and output throughput. Each call becomes a [`LatencySample`] in a
+//! shared [`LatencyRecorder`]. Calls made inside [`with_latency_tag`] carry that
+//! tag, so a [`LatencyReport`] can be scoped to one eval run; streams that fail
+//! or are dropped early are still recorded, with the phases they reached and an
+//! error.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::provider::{ChatRequest, Provider};
+use crate::agent::streaming::{StreamingChoice, StreamingResponse, StreamingResult};
+use crate::error::{Error, Result};
+
+tokio::task_local! {
+    static LATENCY_TAG: String;
+}
+
+/// Run `fut` with every provider call it makes tagged as `tag`
+pub async fn with_latency_tag<F: Future>(tag: impl Into<String>, fut: F) -> F::Output {
+    LATENCY_TAG.scope(tag.into(), fut).await
+}
+
+/// Latency tag of the current task, if any
+pub fn current_latency_tag() -> Option<String> {
+    LATENCY_TAG.try_with(|t| t.clone()).ok()
+}
+
+/// Timings of one provider call; durations are milliseconds since the request was sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencySample {
+    /// Provider name
+    pub provider: String,
+    /// Requested model
+    pub model: String,
+    /// Run tag from [`with_latency_tag`]
+    pub tag: Option<String>,
+    /// Wall-clock time the request was sent (for windowing only)
+    pub started_at: DateTime<Utc>,
+    /// Until the first chunk of any kind
+    pub first_byte_ms: Option<f64>,
+    /// Until the first content chunk
+    pub first_token_ms: Option<f64>,
+    /// Until the stream completed (or failed)
+    pub total_ms: Option<f64>,
+    /// Chunks received
+    pub chunks: usize,
+    /// Content chunks received
+    pub content_chunks: usize,
+    /// Completion tokens reported by the provider's usage chunk
+    pub completion_tokens: Option<u32>,
+    /// Why the call did not complete
+    pub error: Option<String>,
+}
+
+impl LatencySample {
+    /// Output tokens per second after the first token
+    ///
+    /// Uses reported completion tokens, else content chunks as a proxy.
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        let generation_ms = self.total_ms? - self.first_token_ms?;
+        if generation_ms <= 0.0 {
+            return None;
+        }
+        let tokens = self
+            .completion_tokens
+            .map(|t| t as f64)
+            .unwrap_or(self.content_chunks as f64);
+        Some(tokens / (generation_ms / 1000.0))
+    }
+}
+
+/// A measured phase of a call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyPhase {
+    /// Time to first chunk
+    FirstByte,
+    /// Time to first content chunk
+    FirstToken,
+    /// Total stream time
+    Total,
+    /// Output tokens per second
+    TokensPerSecond,
+}
+
+impl LatencyPhase {
+    const ALL: [LatencyPhase; 4] = [
+        Self::FirstByte,
+        Self::FirstToken,
+        Self::Total,
+        Self::TokensPerSecond,
+    ];
+
+    fn value(self, sample: &LatencySample) -> Option<f64> {
+        match self {
+            Self::FirstByte => sample.first_byte_ms,
+            Self::FirstToken => sample.first_token_ms,
+            Self::Total => sample.total_ms,
+            Self::TokensPerSecond => sample.tokens_per_second(),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::FirstByte => "first byte (ms)",
+            Self::FirstToken => "first token (ms)",
+            Self::Total => "total (ms)",
+            Self::TokensPerSecond => "tokens/s",
+        }
+    }
+}
+
+/// Bounded, shared store of latency samples
+#[derive(Clone)]
+pub struct LatencyRecorder {
+    samples: Arc<Mutex<VecDeque<LatencySample>>>,
+    capacity: usize,
+}
+
+impl Default for LatencyRecorder {
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+impl LatencyRecorder {
+    /// Keep at most `capacity` samples, dropping the oldest
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: Arc::new(Mutex::new(VecDeque::new())),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Add a sample
+    pub fn record(&self, sample: LatencySample) {
+        tracing::debug!(
+            provider = %sample.provider,
+            model = %sample.model,
+            first_token_ms = ?sample.first_token_ms,
+            total_ms = ?sample.total_ms,
+            error = ?sample.error,
+            "Provider call latency"
+        );
+        let mut samples = self.samples.lock();
+        if samples.len() >= self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    /// All samples, oldest first
+    pub fn samples(&self) -> Vec<LatencySample> {
+        self.samples.lock().iter().cloned().collect()
+    }
+
+    /// Report over all samples
+    pub fn report(&self) -> LatencyReport {
+        LatencyReport::from_samples(&self.samples())
+    }
+
+    /// Report over samples tagged `tag`
+    pub fn report_for_tag(&self, tag: &str) -> LatencyReport {
+        let samples: Vec<_> = self
+            .samples()
+            .into_iter()
+            .filter(|s| s.tag.as_deref() == Some(tag))
+            .collect();
+        LatencyReport::from_samples(&samples)
+    }
+
+    /// Report over samples started at or after `since`
+    pub fn report_since(&self, since: DateTime<Utc>) -> LatencyReport {
+        let samples: Vec<_> = self
+            .samples()
+            .into_iter()
+            .filter(|s| s.started_at >= since)
+            .collect();
+        LatencyReport::from_samples(&samples)
+    }
+}
+
+/// p50/p95/p99 of one phase (nearest rank)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Percentiles {
+    /// Median
+    pub p50: f64,
+    /// 95th percentile
+    pub p95: f64,
+    /// 99th percentile
+    pub p99: f64,
+    /// Values the percentiles were computed from
+    pub count: usize,
+}
+
+impl Percentiles {
+    /// Percentiles of `values` (`None` when empty)
+    pub fn of(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let rank = |p: f64| {
+            let index = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[index.clamp(1, sorted.len()) - 1]
+        };
+        Some(Self {
+            p50: rank(50.0),
+            p95: rank(95.0),
+            p99: rank(99.0),
+            count: sorted.len(),
+        })
+    }
+}
+
+/// Aggregated latency of one provider/model pair
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyStats {
+    /// Calls measured
+    pub calls: usize,
+    /// Calls that failed or were cut short
+    pub errors: usize,
+    /// Time to first chunk
+    pub first_byte: Option<Percentiles>,
+    /// Time to first content chunk
+    pub first_token: Option<Percentiles>,
+    /// Total stream time
+    pub total: Option<Percentiles>,
+    /// Output throughput
+    pub tokens_per_second: Option<Percentiles>,
+}
+
+impl LatencyStats {
+    /// Percentiles for `phase`
+    pub fn phase(&self, phase: LatencyPhase) -> Option<Percentiles> {
+        match phase {
+            LatencyPhase::FirstByte => self.first_byte,
+            LatencyPhase::FirstToken => self.first_token,
+            LatencyPhase::Total => self.total,
+            LatencyPhase::TokensPerSecond => self.tokens_per_second,
+        }
+    }
+}
+
+/// Latency statistics per `provider/model`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencyReport {
+    /// Stats keyed by `provider/model`
+    pub combos: BTreeMap<String, LatencyStats>,
+}
+
+impl LatencyReport {
+    /// Aggregate `samples` per provider/model
+    pub fn from_samples(samples: &[LatencySample]) -> Self {
+        let mut groups: BTreeMap<String, Vec<&LatencySample>> = BTreeMap::new();
+        for sample in samples {
+            groups
+                .entry(format!("{}/{}", sample.provider, sample.model))
+                .or_default()
+                .push(sample);
+        }
+        let combos = groups
+            .into_iter()
+            .map(|(key, group)| {
+                let phase = |p: LatencyPhase| {
+                    let values: Vec<f64> = group.iter().filter_map(|s| p.value(s)).collect();
+                    Percentiles::of(&values)
+                };
+                let stats = LatencyStats {
+                    calls: group.len(),
+                    errors: group.iter().filter(|s| s.error.is_some()).count(),
+                    first_byte: phase(LatencyPhase::FirstByte),
+                    first_token: phase(LatencyPhase::FirstToken),
+                    total: phase(LatencyPhase::Total),
+                    tokens_per_second: phase(LatencyPhase::TokensPerSecond),
+                };
+                (key, stats)
+            })
+            .collect();
+        Self { combos }
+    }
+
+    /// Side-by-side table: one column per provider/model, p50/p95/p99 per phase
+    pub fn to_markdown(&self) -> String {
+        if self.combos.is_empty() {
+            return "No latency samples.\n".to_string();
+        }
+        let mut out = String::from("| metric |");
+        for key in self.combos.keys() {
+            let _ = write!(out, " {} |", key);
+        }
+        out.push_str("\n|---|");
+        out.push_str(&"---|".repeat(self.combos.len()));
+        out.push('\n');
+
+        out.push_str("| calls (errors) |");
+        for stats in self.combos.values() {
+            let _ = write!(out, " {} ({}) |", stats.calls, stats.errors);
+        }
+        out.push('\n');
+
+        for phase in LatencyPhase::ALL {
+            let _ = write!(out, "| {} p50 / p95 / p99 |", phase.label());
+            for stats in self.combos.values() {
+                match stats.phase(phase) {
+                    Some(p) => {
+                        let _ = write!(out, " {:.0} / {:.0} / {:.0} |", p.p50, p.p95, p.p99);
+                    }
+                    None => out.push_str(" - |"),
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Provider wrapper that records a [`LatencySample`] for every call
+pub struct LatencyProvider<P: Provider> {
+    inner: P,
+    recorder: LatencyRecorder,
+}
+
+impl<P: Provider> LatencyProvider<P> {
+    /// Measure calls to `provider` into `recorder`
+    pub fn new(provider: P, recorder: LatencyRecorder) -> Self {
+        Self {
+            inner: provider,
+            recorder,
+        }
+    }
+
+    /// The shared recorder
+    pub fn recorder(&self) -> &LatencyRecorder {
+        &self.recorder
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Provider for LatencyProvider<P> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.inner.supports_tools()
+    }
+
+    fn supports_vision(&self) -> bool {
+        self.inner.supports_vision()
+    }
+
+    async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+        let measurement = Measurement {
+            sent: Instant::now(),
+            sample: LatencySample {
+                provider: self.inner.name().to_string(),
+                model: request.model.clone(),
+                tag: current_latency_tag(),
+                started_at: Utc::now(),
+                first_byte_ms: None,
+                first_token_ms: None,
+                total_ms: None,
+                chunks: 0,
+                content_chunks: 0,
+                completion_tokens: None,
+                error: None,
+            },
+            recorder: self.recorder.clone(),
+            finished: false,
+        };
+        match self.inner.stream_completion(request).await {
+            Ok(stream) => Ok(StreamingResponse::from_stream(MeasuredStream {
+                inner: stream.into_inner(),
+                measurement,
+            })),
+            Err(e) => {
+                let mut measurement = measurement;
+                measurement.finish(Some(e.to_string()));
+                Err(e)
+            }
+        }
+    }
+}
+
+/// In-progress sample; recorded exactly once, on completion, error or drop
+struct Measurement {
+    sent: Instant,
+    sample: LatencySample,
+    recorder: LatencyRecorder,
+    finished: bool,
+}
+
+impl Measurement {
+    fn elapsed_ms(&self) -> f64 {
+        self.sent.elapsed().as_secs_f64() * 1000.0
+    }
+
+    fn observe(&mut self, chunk: &StreamingChoice) {
+        let now = self.elapsed_ms();
+        self.sample.chunks += 1;
+        self.sample.first_byte_ms.get_or_insert(now);
+        match chunk {
+            StreamingChoice::Message(_)
+            | StreamingChoice::Thought(_)
+            | StreamingChoice::ToolCall { .. }
+            | StreamingChoice::ParallelToolCalls(_) => {
+                self.sample.content_chunks += 1;
+                self.sample.first_token_ms.get_or_insert(now);
+            }
+            StreamingChoice::Usage(usage) => {
+                self.sample.completion_tokens = Some(usage.completion_tokens);
+            }
+            StreamingChoice::Metadata(_) | StreamingChoice::Done => {}
+        }
+    }
+
+    fn finish(&mut self, error: Option<String>) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        self.sample.total_ms = Some(self.elapsed_ms());
+        self.sample.error = error;
+        self.recorder.record(self.sample.clone());
+    }
+}
+
+impl Drop for Measurement {
+    fn drop(&mut self) {
+        self.finish(Some("Stream dropped before completion".to_string()));
+    }
+}
+
+struct MeasuredStream {
+    inner: StreamingResult,
+    measurement: Measurement,
+}
+
+impl Stream for MeasuredStream {
+    type Item = std::result::Result<StreamingChoice, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let item = match this.inner.as_mut().poll_next(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(item) => item,
+        };
+        match &item {
+            Some(Ok(chunk)) => {
+                this.measurement.observe(chunk);
+                if chunk.is_done() {
+                    this.measurement.finish(None);
+                }
+            }
+            Some(Err(e)) => this.measurement.finish(Some(e.to_string())),
+            None => this.measurement.finish(None),
+        }
+        Poll::Ready(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::streaming::Usage;
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    /// Waits `delays[i]` ms before yielding `chunks[i]`
+    struct DelayedProvider {
+        delays: Vec<u64>,
+        chunks: Vec<Result<StreamingChoice>>,
+    }
+
+    #[async_trait]
+    impl Provider for DelayedProvider {
+        async fn stream_completion(&self, _: ChatRequest) -> Result<StreamingResponse> {
+            let items: Vec<_> = self
+                .delays
+                .iter()
+                .copied()
+                .zip(self.chunks.iter().map(|c| match c {
+                    Ok(c) => Ok(c.clone()),
+                    Err(e) => Err(Error::StreamInterrupted(e.to_string())),
+                }))
+                .collect();
+            let stream = futures::stream::iter(items).then(|(delay, chunk)| async move {
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                chunk
+            });
+            Ok(StreamingResponse::from_stream(stream))
+        }
+
+        fn name(&self) -> &'static str {
+            "delayed"
+        }
+    }
+
+    fn within(value: Option<f64>, expected: f64) -> bool {
+        // Sleeps never end early; allow generous scheduling slack on the high side
+        value.is_some_and(|v| v >= expected - 1.0 && v < expected + 60.0)
+    }
+
+    #[tokio::test]
+    async fn test_phases_measured_and_partial_on_error() {
+        let recorder = LatencyRecorder::default();
+        let provider = LatencyProvider::new(
+            DelayedProvider {
+                delays: vec![40, 40, 40, 0, 0],
+                chunks: vec![
+                    Ok(StreamingChoice::Usage(Usage::default())),
+                    Ok(StreamingChoice::Message("Hello".into())),
+                    Ok(StreamingChoice::Message(" world".into())),
+                    Ok(StreamingChoice::Usage(Usage {
+                        prompt_tokens: 5,
+                        completion_tokens: 4,
+                        total_tokens: 9,
+                        ..Default::default()
+                    })),
+                    Ok(StreamingChoice::Done),
+                ],
+            },
+            recorder.clone(),
+        );
+        let request = ChatRequest {
+            model: "m1".into(),
+            ..Default::default()
+        };
+        let text = with_latency_tag("run-1", async {
+            provider
+                .stream_completion(request.clone())
+                .await
+                .unwrap()
+                .collect_text()
+                .await
+                .unwrap()
+        })
+        .await;
+        assert_eq!(text, "Hello world");
+
+        let sample = &recorder.samples()[0];
+        assert_eq!(sample.tag.as_deref(), Some("run-1"));
+        assert!(within(sample.first_byte_ms, 40.0), "{:?}", sample);
+        assert!(within(sample.first_token_ms, 80.0), "{:?}", sample);
+        assert!(within(sample.total_ms, 120.0), "{:?}", sample);
+        assert_eq!((sample.chunks, sample.content_chunks), (5, 2));
+        assert_eq!(sample.completion_tokens, Some(4));
+        assert!(sample.error.is_none());
+
+        let failing = LatencyProvider::new(
+            DelayedProvider {
+                delays: vec![30, 30],
+                chunks: vec![
+                    Ok(StreamingChoice::Message("Hi".into())),
+                    Err(Error::StreamInterrupted("connection reset".into())),
+                ],
+            },
+            recorder.clone(),
+        );
+        assert!(failing
+            .stream_completion(request)
+            .await
+            .unwrap()
+            .collect_text()
+            .await
+            .is_err());
+        let partial = &recorder.samples()[1];
+        assert!(within(partial.first_token_ms, 30.0));
+        assert!(within(partial.total_ms, 60.0));
+        assert!(partial
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("connection reset"));
+        assert_eq!(
+            recorder.report_for_tag("run-1").combos["delayed/m1"].calls,
+            1
+        );
+        assert_eq!(recorder.report().combos["delayed/m1"].errors, 1);
+    }
+
+    #[test]
+    fn test_percentiles_and_report() {
+        let values: Vec<f64> = (1..=100).map(f64::from).collect();
+        let p = Percentiles::of(&values).unwrap();
+        assert_eq!((p.p50, p.p95, p.p99, p.count), (50.0, 95.0, 99.0, 100));
+        let p = Percentiles::of(&[30.0, 10.0, 20.0]).unwrap();
+        assert_eq!((p.p50, p.p95, p.p99), (20.0, 30.0, 30.0));
+        assert!(Percentiles::of(&[]).is_none());
+
+        let sample = |provider: &str, first_token: f64| LatencySample {
+            provider: provider.into(),
+            model: "m".into(),
+            tag: None,
+            started_at: Utc::now(),
+            first_byte_ms: Some(first_token / 2.0),
+            first_token_ms: Some(first_token),
+            total_ms: Some(first_token + 1000.0),
+            chunks: 12,
+            content_chunks: 10,
+            completion_tokens: Some(50),
+            error: None,
+        };
+        let report = LatencyReport::from_samples(&[
+            sample("fast", 100.0),
+            sample("fast", 300.0),
+            sample("slow", 900.0),
+        ]);
+        let fast = &report.combos["fast/m"];
+        assert_eq!(fast.first_token.unwrap().p50, 100.0);
+        assert_eq!(fast.first_token.unwrap().p99, 300.0);
+        assert_eq!(fast.tokens_per_second.unwrap().p50, 50.0);
+
+        let md = report.to_markdown();
+        let lines: Vec<&str> = md.lines().collect();
+        assert_eq!(lines[0], "| metric | fast/m | slow/m |");
+        assert_eq!(lines[2], "| calls (errors) | 2 (0) | 1 (0) |");
+        assert_eq!(
+            lines[4],
+            "| first token (ms) p50 / p95 / p99 | 100 / 300 / 300 | 900 / 900 / 900 |"
+        );
+    }
+}
@@ -0,0 +1,342 @@
+//! Request and response hooks around a provider
+//!
+//! [`WithMiddleware`] runs an ordered list of [`ProviderMiddleware`] around a
+//! provider. Request hooks run in order before the provider serializes the
+//! [`ChatRequest`]; chunk and response hooks run in reverse order, so the first
+//! middleware sees the request first and the reply last. Middleware can rewrite
+//! messages, add [`ChatRequest::headers`] for a proxy, or log what is sent.
+//!
+//! [`RedactingLogger`] logs every request with header values and secrets
+//! scrubbed.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, LazyLock};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use regex::Regex;
+
+use crate::agent::provider::{ChatRequest, CompletionResponse, Provider};
+use crate::agent::streaming::{StreamingChoice, StreamingResponse};
+use crate::error::Result;
+use crate::infra::secrets::Secrets;
+
+/// Hooks run around every call to a wrapped provider
+#[async_trait]
+pub trait ProviderMiddleware: Send + Sync {
+    /// Inspect or rewrite a request before it is sent; an error aborts the call
+    async fn on_request(&self, _request: &mut ChatRequest) -> Result<()> {
+        Ok(())
+    }
+
+    /// Inspect or rewrite one streamed chunk
+    fn on_chunk(&self, _chunk: &mut StreamingChoice) {}
+
+    /// Inspect or rewrite a non-streaming reply
+    async fn on_response(&self, _response: &mut CompletionResponse) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Provider that runs middleware around every call
+pub struct WithMiddleware<P: Provider> {
+    inner: P,
+    middleware: Vec<Arc<dyn ProviderMiddleware>>,
+}
+
+impl<P: Provider> WithMiddleware<P> {
+    /// Wrap `provider` with `middleware`, outermost first
+    pub fn new(provider: P, middleware: Vec<Arc<dyn ProviderMiddleware>>) -> Self {
+        Self {
+            inner: provider,
+            middleware,
+        }
+    }
+
+    /// Add `middleware` inside the existing ones
+    pub fn layer(mut self, middleware: Arc<dyn ProviderMiddleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    async fn prepare(&self, request: &mut ChatRequest) -> Result<()> {
+        for middleware in &self.middleware {
+            middleware.on_request(request).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Provider for WithMiddleware<P> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.inner.supports_tools()
+    }
+
+    fn supports_vision(&self) -> bool {
+        self.inner.supports_vision()
+    }
+
+    async fn stream_completion(&self, mut request: ChatRequest) -> Result<StreamingResponse> {
+        self.prepare(&mut request).await?;
+        let stream = self.inner.stream_completion(request).await?;
+        let middleware = self.middleware.clone();
+        Ok(StreamingResponse::from_stream(stream.into_inner().map(
+            move |chunk| {
+                chunk.map(|mut chunk| {
+                    for m in middleware.iter().rev() {
+                        m.on_chunk(&mut chunk);
+                    }
+                    chunk
+                })
+            },
+        )))
+    }
+
+    async fn complete(&self, mut request: ChatRequest) -> Result<CompletionResponse> {
+        self.prepare(&mut request).await?;
+        let mut response = self.inner.complete(request).await?;
+        for middleware in self.middleware.iter().rev() {
+            middleware.on_response(&mut response).await?;
+        }
+        Ok(response)
+    }
+}
+
+/// Header names whose values are never logged (exact or as a `-` separated part)
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "cookie",
+    "key",
+    "token",
+    "secret",
+    "signature",
+];
+
+/// Credential-shaped strings scrubbed from logged bodies
+static KEY_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(sk-[a-z0-9_-]{16,}|bearer\s+[a-z0-9._~+/=-]{8,})").expect("valid regex")
+});
+
+/// Logs every request body at debug level, scrubbed of keys
+///
+/// Values of sensitive headers, credential-shaped strings (`sk-…`,
+/// `Bearer …`) and, with [`with_secrets`](Self::with_secrets), every secret
+/// value handed out to tools are replaced before anything is logged.
+#[derive(Default)]
+pub struct RedactingLogger {
+    secrets: Option<Arc<Secrets>>,
+    keep: usize,
+    recent: Mutex<VecDeque<String>>,
+}
+
+impl RedactingLogger {
+    /// Logger that scrubs headers and credential-shaped strings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also scrub every value resolved by `secrets`
+    pub fn with_secrets(mut self, secrets: Arc<Secrets>) -> Self {
+        self.secrets = Some(secrets);
+        self
+    }
+
+    /// Keep the last `count` logged bodies for [`recent`](Self::recent)
+    pub fn keep_recent(mut self, count: usize) -> Self {
+        self.keep = count;
+        self
+    }
+
+    /// The last logged bodies, oldest first
+    pub fn recent(&self) -> Vec<String> {
+        self.recent.lock().iter().cloned().collect()
+    }
+
+    /// The request as scrubbed JSON
+    pub fn render(&self, request: &ChatRequest) -> String {
+        let headers: serde_json::Map<String, serde_json::Value> = request
+            .headers
+            .iter()
+            .map(|(name, value)| {
+                let value = if is_sensitive_header(name) {
+                    "[REDACTED]"
+                } else {
+                    value.as_str()
+                };
+                (name.clone(), value.into())
+            })
+            .collect();
+        let tools: Vec<&str> = request.tools.iter().map(|t| t.name.as_str()).collect();
+        let body = serde_json::json!({
+            "model": request.model,
+            "system_prompt": request.system_prompt,
+            "messages": request.messages,
+            "tools": tools,
+            "temperature": request.temperature,
+            "max_tokens": request.max_tokens,
+            "max_reasoning_tokens": request.max_reasoning_tokens,
+            "extra_params": request.extra_params,
+            "headers": headers,
+        })
+        .to_string();
+        let body = match &self.secrets {
+            Some(secrets) => secrets.redact(&body),
+            None => body,
+        };
+        KEY_PATTERN.replace_all(&body, "[REDACTED]").into_owned()
+    }
+}
+
+#[async_trait]
+impl ProviderMiddleware for RedactingLogger {
+    async fn on_request(&self, request: &mut ChatRequest) -> Result<()> {
+        let body = self.render(request);
+        tracing::debug!(target: "aagt::provider::request", model = %request.model, "{}", body);
+        if self.keep > 0 {
+            let mut recent = self.recent.lock();
+            if recent.len() == self.keep {
+                recent.pop_front();
+            }
+            recent.push_back(body);
+        }
+        Ok(())
+    }
+}
+
+fn is_sensitive_header(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SENSITIVE_HEADERS
+        .iter()
+        .any(|s| name == *s || name.split('-').any(|part| part == *s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::message::Message;
+    use crate::agent::streaming::MockStreamBuilder;
+
+    /// Echoes the last message and the proxy header back
+    struct Echo;
+
+    #[async_trait]
+    impl Provider for Echo {
+        async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+            let via = request
+                .headers
+                .get("x-proxy-route")
+                .cloned()
+                .unwrap_or_default();
+            let text = request
+                .messages
+                .last()
+                .map(|m| m.content.as_text())
+                .unwrap_or_default();
+            Ok(MockStreamBuilder::new()
+                .message(format!("{} via {}", text, via))
+                .done()
+                .build())
+        }
+
+        fn name(&self) -> &'static str {
+            "echo"
+        }
+    }
+
+    /// Tags requests with a route header and records hook order
+    struct Route {
+        tag: &'static str,
+        order: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl ProviderMiddleware for Route {
+        async fn on_request(&self, request: &mut ChatRequest) -> Result<()> {
+            self.order.lock().push(format!("request {}", self.tag));
+            request
+                .headers
+                .insert("x-proxy-route".to_string(), self.tag.to_string());
+            Ok(())
+        }
+
+        fn on_chunk(&self, chunk: &mut StreamingChoice) {
+            if let StreamingChoice::Message(text) = chunk {
+                self.order.lock().push(format!("chunk {}", self.tag));
+                text.push_str(&format!(" [{}]", self.tag));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_runs_in_order_and_logs_redacted_requests() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let logger = Arc::new(RedactingLogger::new().keep_recent(2));
+        let provider = WithMiddleware::new(
+            Echo,
+            vec![
+                Arc::new(Route {
+                    tag: "outer",
+                    order: order.clone(),
+                }),
+                Arc::new(Route {
+                    tag: "inner",
+                    order: order.clone(),
+                }),
+            ],
+        )
+        .layer(logger.clone());
+
+        let mut request = ChatRequest {
+            model: "gpt-4o".to_string(),
+            messages: vec![Message::user("my key is sk-abcdefghijklmnopqrstuvwx")],
+            ..Default::default()
+        };
+        request
+            .headers
+            .insert("Authorization".to_string(), "Bearer abc".to_string());
+        request
+            .headers
+            .insert("X-Api-Key".to_string(), "hunter2hunter2".to_string());
+
+        let text = provider
+            .stream_completion(request.clone())
+            .await
+            .unwrap()
+            .collect_text()
+            .await
+            .unwrap();
+        assert!(text.ends_with("via inner [inner] [outer]"));
+        assert_eq!(
+            *order.lock(),
+            [
+                "request outer",
+                "request inner",
+                "chunk inner",
+                "chunk outer"
+            ]
+        );
+
+        // The logger runs last, so it sees the headers the others added
+        let logged = logger.recent();
+        assert_eq!(logged.len(), 1);
+        let body: serde_json::Value = serde_json::from_str(&logged[0]).unwrap();
+        assert_eq!(body["headers"]["Authorization"], "[REDACTED]");
+        assert_eq!(body["headers"]["X-Api-Key"], "[REDACTED]");
+        assert_eq!(body["headers"]["x-proxy-route"], "inner");
+        assert!(!logged[0].contains("sk-abcdefghijklmnopqrstuvwx"));
+
+        let response = provider.complete(request).await.unwrap();
+        assert!(response.text.ends_with("via inner"));
+        assert_eq!(logger.recent().len(), 2);
+    }
+}
@@ -0,0 +1,96 @@
+//! Model prices for spend estimates
+//!
+//! Prices are USD per million tokens, matched by the longest model-name
+//! prefix after any `vendor/` routing prefix (so `openai/gpt-4o-mini` prices
+//! as `gpt-4o-mini`). The built-in table lists list prices for common models
+//! and goes stale; set your own with [`PriceTable::set`].
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::agent::streaming::Usage;
+
+/// Price of one model
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    /// USD per million prompt tokens
+    pub input_per_mtok: f64,
+    /// USD per million completion tokens
+    pub output_per_mtok: f64,
+}
+
+impl ModelPrice {
+    /// Price from per-million-token rates
+    pub fn new(input_per_mtok: f64, output_per_mtok: f64) -> Self {
+        Self {
+            input_per_mtok,
+            output_per_mtok,
+        }
+    }
+
+    /// USD cost of `usage`
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.prompt_tokens as f64 * self.input_per_mtok
+            + usage.completion_tokens as f64 * self.output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// Prices by model-name prefix
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceTable {
+    prices: BTreeMap<String, ModelPrice>,
+}
+
+impl Default for PriceTable {
+    /// Table with built-in list prices
+    fn default() -> Self {
+        [
+            ("gpt-4o", 2.50, 10.00),
+            ("gpt-4o-mini", 0.15, 0.60),
+            ("gpt-4.1", 2.00, 8.00),
+            ("gpt-4.1-mini", 0.40, 1.60),
+            ("o3-mini", 1.10, 4.40),
+            ("claude-3-5-sonnet", 3.00, 15.00),
+            ("claude-3-7-sonnet", 3.00, 15.00),
+            ("claude-sonnet-4", 3.00, 15.00),
+            ("claude-3-5-haiku", 0.80, 4.00),
+            ("claude-opus-4", 15.00, 75.00),
+        ]
+        .into_iter()
+        .fold(Self::empty(), |table, (model, input, output)| {
+            table.set(model, ModelPrice::new(input, output))
+        })
+    }
+}
+
+impl PriceTable {
+    /// Table without built-in prices
+    pub fn empty() -> Self {
+        Self {
+            prices: BTreeMap::new(),
+        }
+    }
+
+    /// Price models starting with `prefix`
+    pub fn set(mut self, prefix: impl Into<String>, price: ModelPrice) -> Self {
+        self.prices.insert(prefix.into(), price);
+        self
+    }
+
+    /// Price of `model`, if any prefix matches
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        let model = model.rsplit('/').next().unwrap_or(model);
+        self.prices
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| *price)
+    }
+
+    /// Estimated USD cost of `usage` on `model`
+    pub fn estimate(&self, model: &str, usage: &Usage) -> Option<f64> {
+        self.price(model).map(|p| p.cost(usage))
+    }
+}
@@ -0,0 +1,537 @@
+//! Prioritized admission control in front of a provider
+//!
+//! [`PriorityGate`] limits how many requests reach the wrapped provider at once,
+//! queues the rest per [`RequestPriority`], and sheds the lowest-priority work with
+//! [`Error::ProviderOverloaded`] when the queues are full. Requests are tagged with
+//! [`with_priority`]; untagged requests count as interactive.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::agent::provider::{ChatRequest, Provider};
+use crate::agent::streaming::StreamingResponse;
+use crate::error::{Error, Result};
+
+tokio::task_local! {
+    static PRIORITY: RequestPriority;
+}
+
+/// Run `fut` with every provider request it makes tagged as `priority`
+pub async fn with_priority<F: Future>(priority: RequestPriority, fut: F) -> F::Output {
+    PRIORITY.scope(priority, fut).await
+}
+
+/// Priority of the current task (interactive when untagged)
+pub fn current_priority() -> RequestPriority {
+    PRIORITY.try_with(|p| *p).unwrap_or_default()
+}
+
+/// Admission priority of a provider request
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestPriority {
+    /// Bulk work that can wait (backfills, summarization)
+    Batch,
+    /// Cron jobs and scheduled strategies
+    Scheduled,
+    /// A user is waiting on the answer
+    #[default]
+    Interactive,
+}
+
+impl RequestPriority {
+    const ALL: [RequestPriority; 3] = [Self::Batch, Self::Scheduled, Self::Interactive];
+
+    fn level(self) -> usize {
+        self as usize
+    }
+}
+
+impl std::fmt::Display for RequestPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Batch => "batch",
+            Self::Scheduled => "scheduled",
+            Self::Interactive => "interactive",
+        })
+    }
+}
+
+/// Configuration for [`PriorityGate`]
+#[derive(Debug, Clone)]
+pub struct PriorityGateConfig {
+    /// Requests forwarded to the inner provider at once
+    pub max_concurrency: usize,
+    /// Fraction of `max_concurrency` each priority may occupy (missing = 1.0)
+    pub shares: HashMap<RequestPriority, f64>,
+    /// Queue length cap per priority (missing = unbounded)
+    pub queue_caps: HashMap<RequestPriority, usize>,
+    /// Total queued requests before lower-priority waiters are shed
+    pub max_queued: usize,
+    /// Waiting this long promotes a request one priority level (zero disables aging)
+    pub aging_interval: Duration,
+    /// Lower bound for the retry-after suggested to shed callers
+    pub min_retry_after: Duration,
+}
+
+impl Default for PriorityGateConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 4,
+            shares: HashMap::from([
+                (RequestPriority::Interactive, 1.0),
+                (RequestPriority::Scheduled, 0.75),
+                (RequestPriority::Batch, 0.5),
+            ]),
+            queue_caps: HashMap::from([
+                (RequestPriority::Interactive, 100),
+                (RequestPriority::Scheduled, 200),
+                (RequestPriority::Batch, 50),
+            ]),
+            max_queued: 256,
+            aging_interval: Duration::from_secs(30),
+            min_retry_after: Duration::from_secs(1),
+        }
+    }
+}
+
+impl PriorityGateConfig {
+    fn share_limit(&self, priority: RequestPriority) -> usize {
+        let share = self
+            .shares
+            .get(&priority)
+            .copied()
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0);
+        ((self.max_concurrency as f64 * share).ceil() as usize).max(1)
+    }
+
+    fn queue_cap(&self, priority: RequestPriority) -> usize {
+        self.queue_caps
+            .get(&priority)
+            .copied()
+            .unwrap_or(usize::MAX)
+    }
+}
+
+/// Queue depth and shedding gauges for a [`PriorityGate`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PriorityGateStats {
+    /// Requests waiting for a slot
+    pub queued: BTreeMap<RequestPriority, usize>,
+    /// Requests currently running against the provider
+    pub in_flight: BTreeMap<RequestPriority, usize>,
+    /// Requests rejected with `ProviderOverloaded` since creation
+    pub shed: BTreeMap<RequestPriority, u64>,
+    /// Requests admitted ahead of their priority because they aged
+    pub promoted: u64,
+}
+
+/// Provider wrapper that admits requests by priority and sheds load when saturated
+pub struct PriorityGate<P: Provider> {
+    inner: Arc<P>,
+    gate: Arc<Gate>,
+}
+
+impl<P: Provider> PriorityGate<P> {
+    pub fn new(provider: P, config: PriorityGateConfig) -> Self {
+        Self {
+            inner: Arc::new(provider),
+            gate: Arc::new(Gate {
+                config,
+                state: Mutex::new(GateState::default()),
+            }),
+        }
+    }
+
+    /// Current queue depths, in-flight counts and shed counters
+    pub fn stats(&self) -> PriorityGateStats {
+        let state = self.gate.state.lock();
+        let mut stats = PriorityGateStats {
+            promoted: state.promoted,
+            ..Default::default()
+        };
+        for priority in RequestPriority::ALL {
+            let level = priority.level();
+            let queued = state
+                .waiters
+                .iter()
+                .filter(|w| w.priority == priority)
+                .count();
+            stats.queued.insert(priority, queued);
+            stats.in_flight.insert(priority, state.in_flight[level]);
+            stats.shed.insert(priority, state.shed[level]);
+        }
+        stats
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Provider for PriorityGate<P> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.inner.supports_tools()
+    }
+
+    fn supports_vision(&self) -> bool {
+        self.inner.supports_vision()
+    }
+
+    async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+        let permit = self.gate.acquire(current_priority()).await?;
+        let stream = self.inner.stream_completion(request).await?;
+        // The slot stays taken until the caller finishes (or drops) the stream
+        Ok(StreamingResponse::from_stream(stream.into_inner().map(
+            move |item| {
+                let _held = &permit;
+                item
+            },
+        )))
+    }
+}
+
+struct Waiter {
+    priority: RequestPriority,
+    enqueued: Instant,
+    grant: oneshot::Sender<Result<Permit>>,
+}
+
+#[derive(Default)]
+struct GateState {
+    waiters: Vec<Waiter>,
+    in_flight: [usize; 3],
+    shed: [u64; 3],
+    promoted: u64,
+    /// Moving average of how long a request holds its slot
+    avg_service: Option<Duration>,
+}
+
+struct Gate {
+    config: PriorityGateConfig,
+    state: Mutex<GateState>,
+}
+
+impl Gate {
+    async fn acquire(self: &Arc<Self>, priority: RequestPriority) -> Result<Permit> {
+        let granted = {
+            let mut state = self.state.lock();
+            let queued = state
+                .waiters
+                .iter()
+                .filter(|w| w.priority == priority)
+                .count();
+            if queued >= self.config.queue_cap(priority) {
+                return Err(self.shed(&mut state, priority));
+            }
+            if state.waiters.len() >= self.config.max_queued {
+                // Make room by shedding the newest waiter of the lowest priority below ours
+                let victim = state
+                    .waiters
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, w)| w.priority < priority)
+                    .min_by_key(|(_, w)| (w.priority, Reverse(w.enqueued)))
+                    .map(|(i, _)| i);
+                match victim {
+                    Some(i) => {
+                        let waiter = state.waiters.remove(i);
+                        let err = self.shed(&mut state, waiter.priority);
+                        let _ = waiter.grant.send(Err(err));
+                    }
+                    None => return Err(self.shed(&mut state, priority)),
+                }
+            }
+
+            let (grant, granted) = oneshot::channel();
+            state.waiters.push(Waiter {
+                priority,
+                enqueued: Instant::now(),
+                grant,
+            });
+            self.dispatch(&mut state);
+            granted
+        };
+        granted
+            .await
+            .map_err(|_| Error::Internal("Priority gate dropped a queued request".to_string()))?
+    }
+
+    /// Hand free slots to the highest (aged) priority waiters, oldest first
+    fn dispatch(self: &Arc<Self>, state: &mut GateState) {
+        let now = Instant::now();
+        while state.in_flight.iter().sum::<usize>() < self.config.max_concurrency {
+            let next = state
+                .waiters
+                .iter()
+                .enumerate()
+                .filter(|(_, w)| {
+                    state.in_flight[w.priority.level()] < self.config.share_limit(w.priority)
+                })
+                .max_by_key(|(_, w)| (self.effective_level(w, now), Reverse(w.enqueued)))
+                .map(|(i, _)| i);
+            let Some(i) = next else {
+                break;
+            };
+
+            let waiter = state.waiters.remove(i);
+            let level = waiter.priority.level();
+            if self.effective_level(&waiter, now) > level {
+                state.promoted += 1;
+            }
+            state.in_flight[level] += 1;
+            let permit = Permit {
+                gate: Some(self.clone()),
+                priority: waiter.priority,
+                started: now,
+            };
+            if let Err(returned) = waiter.grant.send(Ok(permit)) {
+                // Caller gave up; release inline since we already hold the lock
+                if let Ok(mut permit) = returned {
+                    permit.gate = None;
+                }
+                state.in_flight[level] -= 1;
+            }
+        }
+    }
+
+    fn effective_level(&self, waiter: &Waiter, now: Instant) -> usize {
+        let waited = now.duration_since(waiter.enqueued).as_nanos();
+        let promotions = waited
+            .checked_div(self.config.aging_interval.as_nanos())
+            .unwrap_or(0) as usize;
+        (waiter.priority.level() + promotions).min(RequestPriority::Interactive.level())
+    }
+
+    fn shed(&self, state: &mut GateState, priority: RequestPriority) -> Error {
+        state.shed[priority.level()] += 1;
+        let per_request = state.avg_service.unwrap_or(self.config.min_retry_after);
+        let backlog = (state.waiters.len() / self.config.max_concurrency.max(1)) as u32 + 1;
+        let retry_after = (per_request * backlog).max(self.config.min_retry_after);
+        tracing::warn!(
+            "Priority gate shed a {} request (retry after {:?})",
+            priority,
+            retry_after
+        );
+        Error::ProviderOverloaded {
+            priority: priority.to_string(),
+            retry_after_secs: retry_after.as_secs_f64().ceil() as u64,
+        }
+    }
+}
+
+/// A taken concurrency slot, released on drop
+struct Permit {
+    gate: Option<Arc<Gate>>,
+    priority: RequestPriority,
+    started: Instant,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let Some(gate) = self.gate.take() else {
+            return;
+        };
+        let mut state = gate.state.lock();
+        state.in_flight[self.priority.level()] -= 1;
+        let elapsed = self.started.elapsed();
+        state.avg_service = Some(match state.avg_service {
+            Some(avg) => (avg * 4 + elapsed) / 5,
+            None => elapsed,
+        });
+        gate.dispatch(&mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::streaming::MockStreamBuilder;
+
+    /// Provider that takes `delay` to answer
+    struct SlowProvider {
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl Provider for SlowProvider {
+        async fn stream_completion(&self, _request: ChatRequest) -> Result<StreamingResponse> {
+            tokio::time::sleep(self.delay).await;
+            Ok(MockStreamBuilder::new().message("ok").done().build())
+        }
+
+        fn name(&self) -> &'static str {
+            "slow"
+        }
+    }
+
+    fn gate(delay_ms: u64, config: PriorityGateConfig) -> Arc<PriorityGate<SlowProvider>> {
+        let provider = SlowProvider {
+            delay: Duration::from_millis(delay_ms),
+        };
+        Arc::new(PriorityGate::new(provider, config))
+    }
+
+    /// Run one request at `priority`, returning its latency
+    fn spawn_call(
+        gate: &Arc<PriorityGate<SlowProvider>>,
+        priority: RequestPriority,
+    ) -> tokio::task::JoinHandle<Result<Duration>> {
+        let gate = gate.clone();
+        tokio::spawn(with_priority(priority, async move {
+            let start = Instant::now();
+            gate.stream_completion(ChatRequest::default())
+                .await?
+                .collect_text()
+                .await?;
+            Ok(start.elapsed())
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_interactive_latency_stays_bounded() {
+        let config = PriorityGateConfig {
+            max_concurrency: 2,
+            ..Default::default()
+        };
+        let gate = gate(50, config);
+        let batch: Vec<_> = (0..10)
+            .map(|_| spawn_call(&gate, RequestPriority::Batch))
+            .collect();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        // Batch may only use half the slots, so an interactive request gets the other one
+        assert_eq!(gate.stats().in_flight[&RequestPriority::Batch], 1);
+
+        let interactive = spawn_call(&gate, RequestPriority::Interactive)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(
+            interactive < Duration::from_millis(150),
+            "interactive took {:?}",
+            interactive
+        );
+
+        for handle in batch {
+            handle.await.unwrap().unwrap();
+        }
+        let stats = gate.stats();
+        assert_eq!(stats.queued.values().sum::<usize>(), 0);
+        assert_eq!(stats.in_flight.values().sum::<usize>(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_batch_is_shed_first() {
+        let config = PriorityGateConfig {
+            max_concurrency: 1,
+            max_queued: 4,
+            aging_interval: Duration::ZERO,
+            ..Default::default()
+        };
+        let gate = gate(30, config);
+        let running = spawn_call(&gate, RequestPriority::Interactive);
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let batch: Vec<_> = (0..4)
+            .map(|_| spawn_call(&gate, RequestPriority::Batch))
+            .collect();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let scheduled: Vec<_> = (0..2)
+            .map(|_| spawn_call(&gate, RequestPriority::Scheduled))
+            .collect();
+
+        running.await.unwrap().unwrap();
+        for handle in scheduled {
+            handle.await.unwrap().unwrap();
+        }
+        let mut shed = 0;
+        for handle in batch {
+            match handle.await.unwrap() {
+                Err(Error::ProviderOverloaded {
+                    priority,
+                    retry_after_secs,
+                }) => {
+                    assert_eq!(priority, "batch");
+                    assert!(retry_after_secs >= 1);
+                    shed += 1;
+                }
+                other => assert!(other.is_ok()),
+            }
+        }
+        assert_eq!(shed, 2);
+        let stats = gate.stats();
+        assert_eq!(stats.shed[&RequestPriority::Batch], 2);
+        assert_eq!(stats.shed[&RequestPriority::Scheduled], 0);
+
+        // With only higher-priority work queued, the newcomer itself is shed
+        let gate = gate_with_full_interactive_queue().await;
+        let err = spawn_call(&gate, RequestPriority::Batch)
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(err.is_retryable());
+    }
+
+    async fn gate_with_full_interactive_queue() -> Arc<PriorityGate<SlowProvider>> {
+        let config = PriorityGateConfig {
+            max_concurrency: 1,
+            max_queued: 1,
+            ..Default::default()
+        };
+        let gate = gate(50, config);
+        spawn_call(&gate, RequestPriority::Interactive);
+        spawn_call(&gate, RequestPriority::Interactive);
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        gate
+    }
+
+    #[tokio::test]
+    async fn test_aging_prevents_starvation() {
+        let config = PriorityGateConfig {
+            max_concurrency: 1,
+            aging_interval: Duration::from_millis(20),
+            ..Default::default()
+        };
+        let gate = gate(60, config);
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let run = |priority| {
+            let handle = spawn_call(&gate, priority);
+            let order = order.clone();
+            tokio::spawn(async move {
+                handle.await.unwrap().unwrap();
+                order.lock().push(priority);
+            })
+        };
+
+        let first = run(RequestPriority::Interactive);
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let batch = run(RequestPriority::Batch);
+        // By the time the slot frees, the batch request has aged to interactive
+        tokio::time::sleep(Duration::from_millis(45)).await;
+        let later: Vec<_> = (0..3).map(|_| run(RequestPriority::Interactive)).collect();
+
+        first.await.unwrap();
+        batch.await.unwrap();
+        for handle in later {
+            handle.await.unwrap();
+        }
+        assert_eq!(order.lock()[1], RequestPriority::Batch);
+        assert_eq!(gate.stats().promoted, 1);
+    }
+}
@@ -0,0 +1,377 @@
+//! Record provider traffic and replay it deterministically
+//!
+//! [`RecordingProvider`] tees every request and the chunks its provider
+//! streamed back to a JSONL file, one [`RecordedExchange`] per line.
+//! [`ReplayProvider`] serves those chunks again for matching requests, so a
+//! production trace becomes a test that never calls the model.
+//!
+//! Requests match on a hash of their model, messages and tools. Response ids
+//! the agent stamps on messages are left out and tools are sorted by name, as
+//! neither is stable between runs.
+//! A request with no recorded match fails with the first field where it
+//! differs from the next unplayed recording.
+//!
+//! An exchange is written once its stream ends; a stream dropped before then
+//! is not recorded.
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::agent::provider::{ChatRequest, CompletionResponse, Provider};
+use crate::agent::streaming::{StreamingChoice, StreamingResponse};
+use crate::error::{Error, Result};
+
+/// The parts of a request that decide which recording answers it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestKey {
+    /// Model name
+    pub model: String,
+    /// Messages, without response ids
+    pub messages: Vec<Value>,
+    /// Tool definitions, sorted by name
+    pub tools: Vec<Value>,
+}
+
+impl RequestKey {
+    /// Key of `request`
+    pub fn new(request: &ChatRequest) -> Result<Self> {
+        let messages = request
+            .messages
+            .iter()
+            .map(|message| {
+                let mut value = serde_json::to_value(message)?;
+                if let Value::Object(fields) = &mut value {
+                    fields.remove("response_id");
+                }
+                Ok(value)
+            })
+            .collect::<Result<_>>()?;
+        // Tool sets list definitions in no fixed order
+        let mut tools: Vec<_> = request.tools.iter().collect();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        let tools = tools
+            .into_iter()
+            .map(serde_json::to_value)
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(Self {
+            model: request.model.clone(),
+            messages,
+            tools,
+        })
+    }
+
+    /// Stable hex hash of the key
+    pub fn hash(&self) -> Result<String> {
+        let digest = Sha256::digest(serde_json::to_string(self)?.as_bytes());
+        Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// Path of the first field where `self` differs from `recorded`, with both values
+    pub fn divergence(&self, recorded: &RequestKey) -> Option<String> {
+        let (actual, expected) = (serde_json::to_value(self).ok()?, serde_json::to_value(recorded).ok()?);
+        first_divergence("", &expected, &actual).map(|(path, expected, actual)| {
+            format!("{}: recorded {}, got {}", path, expected, actual)
+        })
+    }
+}
+
+/// First differing path between two JSON values, with both sides
+fn first_divergence(path: &str, expected: &Value, actual: &Value) -> Option<(String, String, String)> {
+    let join = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => {
+            let mut keys: Vec<_> = e.keys().chain(a.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            keys.into_iter().find_map(|key| {
+                let missing = Value::Null;
+                first_divergence(&join(key), e.get(key).unwrap_or(&missing), a.get(key).unwrap_or(&missing))
+            })
+        }
+        (Value::Array(e), Value::Array(a)) => e
+            .iter()
+            .zip(a)
+            .enumerate()
+            .find_map(|(i, (e, a))| first_divergence(&format!("{}[{}]", path, i), e, a))
+            .or_else(|| {
+                (e.len() != a.len()).then(|| {
+                    (format!("{}.len()", path), e.len().to_string(), a.len().to_string())
+                })
+            }),
+        _ if expected == actual => None,
+        _ => Some((path.to_string(), expected.to_string(), actual.to_string())),
+    }
+}
+
+/// One request and the reply it got: a line of a recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    /// [`RequestKey::hash`] of `request`
+    pub hash: String,
+    /// What the request was matched on
+    pub request: RequestKey,
+    /// Chunks in the order they were streamed
+    pub chunks: Vec<StreamingChoice>,
+    /// Error that failed the request or ended the stream after `chunks`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RecordedExchange {
+    fn new(request: RequestKey) -> Result<Self> {
+        Ok(Self {
+            hash: request.hash()?,
+            request,
+            chunks: Vec::new(),
+            error: None,
+        })
+    }
+
+    /// The recorded reply as a stream, ending with the recorded error if any
+    fn into_stream(self) -> StreamingResponse {
+        let error = self
+            .error
+            .map(|e| Err(Error::ProviderApi(format!("recorded error: {}", e))));
+        StreamingResponse::from_stream(futures::stream::iter(
+            self.chunks.into_iter().map(Ok).chain(error),
+        ))
+    }
+}
+
+/// JSONL file exchanges are appended to
+#[derive(Clone)]
+struct Recorder {
+    file: Arc<Mutex<File>>,
+}
+
+impl Recorder {
+    fn write(&self, exchange: &RecordedExchange) {
+        let result = serde_json::to_string(exchange)
+            .map_err(Error::from)
+            .and_then(|line| Ok(writeln!(self.file.lock(), "{}", line)?));
+        if let Err(e) = result {
+            tracing::warn!("Failed to record provider exchange: {}", e);
+        }
+    }
+}
+
+/// Provider that records every exchange with `P` to a JSONL file
+pub struct RecordingProvider<P: Provider> {
+    inner: P,
+    path: PathBuf,
+    recorder: Recorder,
+}
+
+impl<P: Provider> RecordingProvider<P> {
+    /// Record `provider`'s exchanges to `path`, appending to an existing recording
+    pub fn new(provider: P, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            inner: provider,
+            path,
+            recorder: Recorder {
+                file: Arc::new(Mutex::new(file)),
+            },
+        })
+    }
+
+    /// File the exchanges are written to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Provider for RecordingProvider<P> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.inner.supports_tools()
+    }
+
+    fn supports_vision(&self) -> bool {
+        self.inner.supports_vision()
+    }
+
+    async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+        let mut exchange = RecordedExchange::new(RequestKey::new(&request)?)?;
+        let stream = match self.inner.stream_completion(request).await {
+            Ok(response) => response.into_inner(),
+            Err(e) => {
+                exchange.error = Some(e.to_string());
+                self.recorder.write(&exchange);
+                return Err(e);
+            }
+        };
+
+        let recorder = self.recorder.clone();
+        Ok(StreamingResponse::from_stream(futures::stream::unfold(
+            (stream, Some(exchange)),
+            move |(mut stream, mut exchange)| {
+                let recorder = recorder.clone();
+                async move {
+                    let chunk = stream.next().await;
+                    let recording = exchange.as_mut()?;
+                    match &chunk {
+                        Some(Ok(choice)) => recording.chunks.push(choice.clone()),
+                        Some(Err(e)) => recording.error = Some(e.to_string()),
+                        None => {}
+                    }
+                    if !matches!(chunk, Some(Ok(_))) {
+                        if let Some(recording) = exchange.take() {
+                            recorder.write(&recording);
+                        }
+                    }
+                    chunk.map(|chunk| (chunk, (stream, exchange)))
+                }
+            },
+        )))
+    }
+
+    async fn complete(&self, request: ChatRequest) -> Result<CompletionResponse> {
+        let mut exchange = RecordedExchange::new(RequestKey::new(&request)?)?;
+        let result = self.inner.complete(request).await;
+        match &result {
+            Ok(response) => {
+                let mut stream = response.clone().into_stream().into_inner();
+                while let Some(Ok(chunk)) = stream.next().await {
+                    exchange.chunks.push(chunk);
+                }
+            }
+            Err(e) => exchange.error = Some(e.to_string()),
+        }
+        self.recorder.write(&exchange);
+        result
+    }
+}
+
+/// Provider that answers from a recording made by [`RecordingProvider`]
+///
+/// Each recorded exchange is played once. Identical requests are answered
+/// by their recordings in order.
+pub struct ReplayProvider {
+    pending: Mutex<VecDeque<RecordedExchange>>,
+}
+
+impl ReplayProvider {
+    /// Replay `exchanges`
+    pub fn new(exchanges: impl IntoIterator<Item = RecordedExchange>) -> Self {
+        Self {
+            pending: Mutex::new(exchanges.into_iter().collect()),
+        }
+    }
+
+    /// Replay the recording at `path`
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut exchanges = Vec::new();
+        for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let exchange = serde_json::from_str(&line).map_err(|e| {
+                Error::MessageParse(format!("{} line {}: {}", path.display(), i + 1, e))
+            })?;
+            exchanges.push(exchange);
+        }
+        Ok(Self::new(exchanges))
+    }
+
+    /// Exchanges not played yet
+    pub fn remaining(&self) -> usize {
+        self.pending.lock().len()
+    }
+
+    /// Take the recording that answers `request`
+    fn take(&self, request: &ChatRequest) -> Result<RecordedExchange> {
+        let key = RequestKey::new(request)?;
+        let hash = key.hash()?;
+        let mut pending = self.pending.lock();
+        if let Some(i) = pending.iter().position(|e| e.hash == hash) {
+            return Ok(pending.remove(i).expect("position is in range"));
+        }
+        let reason = match pending.front() {
+            Some(next) => key
+                .divergence(&next.request)
+                .unwrap_or_else(|| "hash differs from the recording".to_string()),
+            None => "no recorded exchanges left".to_string(),
+        };
+        Err(Error::ProviderApi(format!("replay mismatch at {}", reason)))
+    }
+}
+
+#[async_trait]
+impl Provider for ReplayProvider {
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+        let exchange = self.take(&request)?;
+        if exchange.chunks.is_empty() {
+            if let Some(error) = exchange.error {
+                return Err(Error::ProviderApi(format!("recorded error: {}", error)));
+            }
+        }
+        Ok(exchange.into_stream())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::message::Message;
+
+    fn request(prompt: &str) -> ChatRequest {
+        ChatRequest {
+            model: "mock-model".to_string(),
+            messages: vec![Message::system("You are a trading agent."), Message::user(prompt)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_key_ignores_response_ids() {
+        let mut tagged = request("Price of SOL?");
+        tagged.messages[1] = Message::user("Price of SOL?").with_response_id("resp-1");
+        let key = RequestKey::new(&tagged).unwrap();
+        assert_eq!(key.hash().unwrap(), RequestKey::new(&request("Price of SOL?")).unwrap().hash().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_mismatch_names_the_first_divergent_field() {
+        let recorded = RecordedExchange::new(RequestKey::new(&request("Price of SOL?")).unwrap()).unwrap();
+        let replay = ReplayProvider::new([recorded]);
+
+        let err = replay.stream_completion(request("Price of ETH?")).await.err().unwrap();
+        let message = err.to_string();
+        assert!(message.contains("messages[1].content"), "{}", message);
+        assert!(message.contains("\"Price of SOL?\"") && message.contains("\"Price of ETH?\""));
+        assert_eq!(replay.remaining(), 1);
+
+        replay.stream_completion(request("Price of SOL?")).await.unwrap();
+        let err = replay.stream_completion(request("Price of SOL?")).await.err().unwrap();
+        assert!(err.to_string().contains("no recorded exchanges left"));
+    }
+}
@@ -4,7 +4,9 @@ use std::time::{Duration, Instant};
 use async_trait::async_trait;
 use tracing::{info, warn};
 
-use crate::error::Result;
+use crate::agent::budget;
+use crate::error::{Error, Result};
+use crate::agent::provider::retry::{first_chunk, retry_stream, RetryConfig};
 use crate::agent::provider::Provider;
 use crate::agent::streaming::StreamingResponse;
 
@@ -42,6 +44,8 @@ pub struct ResilientProvider<P: Provider, F: Provider> {
     primary: Arc<P>,
     fallback: Arc<F>,
     config: CircuitBreakerConfig,
+    /// Retries of transient primary failures before one counts against the breaker
+    retry: Option<RetryConfig>,
     state: Arc<Mutex<CircuitStateInternal>>,
 }
 
@@ -57,6 +61,7 @@ impl<P: Provider, F: Provider> ResilientProvider<P, F> {
             primary: Arc::new(primary),
             fallback: Arc::new(fallback),
             config,
+            retry: None,
             state: Arc::new(Mutex::new(CircuitStateInternal {
                 state: CircuitState::Closed,
                 failures: 0,
@@ -65,6 +70,14 @@ impl<P: Provider, F: Provider> ResilientProvider<P, F> {
         }
     }
 
+    /// Retry retryable primary errors with backoff before reporting a failure
+    ///
+    /// Each attempt gets its own `request_timeout`; a timed out attempt is retried too.
+    pub fn with_retry(mut self, config: RetryConfig) -> Self {
+        self.retry = Some(config);
+        self
+    }
+
     async fn check_state(&self) -> CircuitState {
         let mut router = self.state.lock().await;
         
@@ -130,26 +143,45 @@ impl<P: Provider, F: Provider> Provider for ResilientProvider<P, F> {
         };
 
         if use_primary {
-            // Attempt Primary with Timeout
-            match tokio::time::timeout(
-                self.config.request_timeout,
-                self.primary.stream_completion(request.clone())
-            ).await {
-                Ok(Ok(response)) => {
+            // Attempt Primary with Timeout (never past the request budget's deadline),
+            // covering the wait for the first chunk so a stalled stream can't hang the run
+            let attempt = || async {
+                match tokio::time::timeout(
+                    budget::clamp_timeout(self.config.request_timeout),
+                    first_chunk(self.primary.stream_completion(request.clone())),
+                )
+                .await
+                {
+                    Ok(result) => result,
+                    Err(_) => Err(Error::StreamTimeout {
+                        timeout_secs: self.config.request_timeout.as_secs(),
+                    }),
+                }
+            };
+            let result = match &self.retry {
+                Some(retry) => retry_stream(retry, self.primary.name(), attempt).await,
+                None => attempt().await,
+            };
+            match result {
+                Ok(response) => {
                     self.report_success().await;
                     return Ok(response);
                 }
-                Ok(Err(e)) => {
-                    warn!("Primary provider failed: {}", e);
-                    self.report_failure().await;
-                    // Fallthrough to fallback
+                // Out of budget: falling back would only spend more
+                Err(e @ Error::BudgetExhausted { .. }) => return Err(e),
+                // The request itself was rejected: the primary is healthy
+                Err(e) if e.is_invalid_request() => {
+                    self.report_success().await;
+                    return Err(e);
                 }
-                Err(_) => {
-                    warn!("Primary provider timed out (> {:?})", self.config.request_timeout);
+                Err(e) => {
+                    warn!("Primary provider failed: {}", e);
                     self.report_failure().await;
                     // Fallthrough to fallback
                 }
             }
+            // The fallback call is a retry of the request
+            budget::spend_provider_attempt("fallback")?;
         }
 
         // Fallback Logic
@@ -0,0 +1,464 @@
+//! Provider retries within the request budget
+//!
+//! [`RetryProvider`] retries retryable provider errors with exponential
+//! backoff. Its [`RetryConfig`] is a cap: every retry is also spent from the
+//! current [`Budget`](crate::agent::budget::Budget), and a backoff that would
+//! run past the budget's deadline fails fast instead of sleeping.
+//!
+//! A stream that fails before yielding anything is retried like a failed
+//! request. Once a chunk has been yielded the error is surfaced instead, so
+//! callers never see the start of a response twice.
+
+use std::future::Future;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use rand::Rng;
+use tracing::{warn, Instrument};
+
+use crate::agent::budget::{self, Budget};
+use crate::agent::provider::{ChatRequest, Provider};
+use crate::agent::streaming::StreamingResponse;
+use crate::error::{Error, Result};
+
+/// Retry limits for [`RetryProvider`]
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for each one after
+    pub base_delay: Duration,
+    /// Longest delay between attempts
+    pub max_delay: Duration,
+    /// Fraction of each delay randomized either way, so clients don't retry in lockstep (0.0 to 1.0)
+    pub jitter: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Backoff before retry number `retry` (1-based)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+        delay.mul_f64(1.0 + rand::thread_rng().gen_range(-jitter..=jitter))
+    }
+}
+
+/// Run `attempt` until it succeeds, fails for good, or `config` runs out
+///
+/// `provider` names the retried provider in logs.
+pub(crate) async fn retry_stream<F, Fut>(
+    config: &RetryConfig,
+    provider: &str,
+    attempt: F,
+) -> Result<StreamingResponse>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<StreamingResponse>>,
+{
+    let mut retry = 0;
+    loop {
+        let span = tracing::debug_span!(
+            "provider_attempt",
+            provider,
+            retry,
+            budget = Budget::current().map(|b| b.remaining()).unwrap_or_default(),
+        );
+        let error = match first_chunk(attempt()).instrument(span).await {
+            Ok(response) => return Ok(response),
+            Err(e) if !e.is_retryable() || retry >= config.max_retries => return Err(e),
+            Err(e) => e,
+        };
+
+        retry += 1;
+        let delay = match error.retry_after() {
+            Some(retry_after) => retry_after.max(config.delay(retry)),
+            None => config.delay(retry),
+        };
+        if let Some(left) = Budget::current().and_then(|b| b.time_left()) {
+            if delay >= left {
+                return Err(Error::BudgetExhausted {
+                    resource: "time".to_string(),
+                    layer: "provider_retry".to_string(),
+                });
+            }
+        }
+        budget::spend_provider_attempt("provider_retry")?;
+        warn!(
+            "Provider {} failed ({}), retry {}/{} in {:?}",
+            provider, error, retry, config.max_retries, delay
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Wait for the first chunk, so a stream that fails before yielding counts as a failed request
+pub(crate) async fn first_chunk(
+    response: impl Future<Output = Result<StreamingResponse>>,
+) -> Result<StreamingResponse> {
+    let mut stream = response.await?.into_inner();
+    match stream.next().await {
+        Some(Err(e)) => Err(e),
+        first => Ok(StreamingResponse::from_stream(
+            futures::stream::iter(first).chain(stream),
+        )),
+    }
+}
+
+/// A provider that retries transient failures of the provider it wraps
+pub struct RetryProvider<P> {
+    inner: P,
+    config: RetryConfig,
+}
+
+impl<P: Provider> RetryProvider<P> {
+    /// Wrap `inner`
+    pub fn new(inner: P, config: RetryConfig) -> Self {
+        Self { inner, config }
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Provider for RetryProvider<P> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+        retry_stream(&self.config, self.inner.name(), || {
+            self.inner.stream_completion(request.clone())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::budget::BudgetConfig;
+    use crate::agent::provider::{CircuitBreakerConfig, ResilientProvider};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// Always fails with a retryable error, counting calls
+    struct Failing(Arc<AtomicU32>);
+
+    #[async_trait]
+    impl Provider for Failing {
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        async fn stream_completion(&self, _request: ChatRequest) -> Result<StreamingResponse> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Err(Error::StreamInterrupted("connection reset".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nested_retry_and_fallback_stay_within_budget() {
+        for (max_retries, max_attempts) in [(10, 4), (2, 6), (100, 1)] {
+            let calls = Arc::new(AtomicU32::new(0));
+            let retry = RetryConfig {
+                max_retries,
+                base_delay: Duration::from_millis(10),
+                max_delay: Duration::from_millis(10),
+                jitter: 0.0,
+            };
+            // Retry around a primary/fallback pair that each retry on their own
+            let provider = RetryProvider::new(
+                ResilientProvider::new(
+                    RetryProvider::new(Failing(calls.clone()), retry.clone()),
+                    RetryProvider::new(Failing(calls.clone()), retry.clone()),
+                    CircuitBreakerConfig::default(),
+                ),
+                retry,
+            );
+            let budget = Budget::new(BudgetConfig {
+                total_time: None,
+                max_provider_attempts: max_attempts,
+                max_tool_attempts: 0,
+            });
+
+            let result = budget
+                .clone()
+                .scope(async {
+                    budget::spend_provider_attempt("agent")?;
+                    provider.stream_completion(ChatRequest::default()).await
+                })
+                .await;
+            assert!(matches!(result, Err(Error::BudgetExhausted { .. })));
+            assert_eq!(calls.load(Ordering::SeqCst), max_attempts);
+            assert_eq!(budget.summary().provider_attempts, max_attempts);
+        }
+    }
+
+    fn api_error(status: u16, code: &str) -> Error {
+        Error::Provider {
+            provider: "openai".to_string(),
+            status: Some(status),
+            code: Some(code.to_string()),
+            message: "body".to_string(),
+            retry_after: None,
+        }
+    }
+
+    /// Fails its first `failures` calls, alternating between a failed request
+    /// and a stream that errors before its first chunk, then answers "ok"
+    struct Flaky {
+        failures: u32,
+        calls: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl Provider for Flaky {
+        fn name(&self) -> &'static str {
+            "flaky"
+        }
+
+        async fn stream_completion(&self, _request: ChatRequest) -> Result<StreamingResponse> {
+            use crate::agent::streaming::MockStreamBuilder;
+
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call >= self.failures {
+                return Ok(MockStreamBuilder::new().message("ok").done().build());
+            }
+            if call % 2 == 0 {
+                return Err(api_error(503, "overloaded"));
+            }
+            Ok(MockStreamBuilder::new()
+                .error(Error::StreamInterrupted("connection reset".to_string()))
+                .build())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resilient_provider_retries_before_tripping() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let fallback_calls = Arc::new(AtomicU32::new(0));
+        let provider = ResilientProvider::new(
+            Flaky {
+                failures: 3,
+                calls: calls.clone(),
+            },
+            Failing(fallback_calls.clone()),
+            CircuitBreakerConfig {
+                failure_threshold: 1,
+                ..CircuitBreakerConfig::default()
+            },
+        )
+        .with_retry(RetryConfig {
+            max_retries: 3,
+            base_delay: Duration::from_millis(20),
+            max_delay: Duration::from_millis(40),
+            jitter: 0.0,
+        });
+
+        let started = std::time::Instant::now();
+        let response = provider.stream_completion(ChatRequest::default()).await.unwrap();
+        let elapsed = started.elapsed();
+        assert_eq!(response.collect_text().await.unwrap(), "ok");
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(fallback_calls.load(Ordering::SeqCst), 0);
+        // Backoff of 20 + 40 + 40 ms between the four attempts
+        assert!(elapsed >= Duration::from_millis(100), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+
+        // The primary's failures never reached the breaker
+        let response = provider.stream_completion(ChatRequest::default()).await.unwrap();
+        assert_eq!(response.collect_text().await.unwrap(), "ok");
+        assert_eq!(fallback_calls.load(Ordering::SeqCst), 0);
+    }
+
+    /// Returns a stream that never yields, like a primary that sent headers and stalled
+    struct Stalled(Arc<AtomicU32>);
+
+    #[async_trait]
+    impl Provider for Stalled {
+        fn name(&self) -> &'static str {
+            "stalled"
+        }
+
+        async fn stream_completion(&self, _request: ChatRequest) -> Result<StreamingResponse> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(StreamingResponse::from_stream(futures::stream::pending()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stall_before_first_chunk_times_out_and_falls_back() {
+        use crate::agent::streaming::MockStreamBuilder;
+
+        struct Answering;
+
+        #[async_trait]
+        impl Provider for Answering {
+            fn name(&self) -> &'static str {
+                "answering"
+            }
+
+            async fn stream_completion(&self, _request: ChatRequest) -> Result<StreamingResponse> {
+                Ok(MockStreamBuilder::new().message("fallback").done().build())
+            }
+        }
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let config = CircuitBreakerConfig {
+            request_timeout: Duration::from_millis(50),
+            ..CircuitBreakerConfig::default()
+        };
+        let retry = RetryConfig {
+            max_retries: 1,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+            jitter: 0.0,
+        };
+
+        for retry in [None, Some(retry)] {
+            calls.store(0, Ordering::SeqCst);
+            let mut provider = ResilientProvider::new(Stalled(calls.clone()), Answering, config.clone());
+            if let Some(retry) = retry {
+                provider = provider.with_retry(retry);
+            }
+            let text = tokio::time::timeout(Duration::from_secs(5), async {
+                provider
+                    .stream_completion(ChatRequest::default())
+                    .await?
+                    .collect_text()
+                    .await
+            })
+            .await
+            .expect("a stalled primary must not hang the request")
+            .unwrap();
+            assert_eq!(text, "fallback");
+        }
+        // Timed-out stalls are retried like any other transient failure
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    /// Rejects every request as malformed
+    struct Rejecting(Arc<AtomicU32>);
+
+    #[async_trait]
+    impl Provider for Rejecting {
+        fn name(&self) -> &'static str {
+            "rejecting"
+        }
+
+        async fn stream_completion(&self, _request: ChatRequest) -> Result<StreamingResponse> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Err(api_error(400, "invalid_request_error"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejected_requests_neither_retry_nor_trip_the_breaker() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let fallback_calls = Arc::new(AtomicU32::new(0));
+        let provider = ResilientProvider::new(
+            Rejecting(calls.clone()),
+            Failing(fallback_calls.clone()),
+            CircuitBreakerConfig {
+                failure_threshold: 1,
+                ..CircuitBreakerConfig::default()
+            },
+        )
+        .with_retry(RetryConfig {
+            base_delay: Duration::from_millis(1),
+            ..RetryConfig::default()
+        });
+
+        for expected_calls in 1..=2 {
+            let result = provider.stream_completion(ChatRequest::default()).await;
+            assert!(matches!(result, Err(Error::Provider { status: Some(400), .. })));
+            assert_eq!(calls.load(Ordering::SeqCst), expected_calls);
+        }
+        assert_eq!(fallback_calls.load(Ordering::SeqCst), 0);
+    }
+
+    /// Streams a chunk, then fails
+    struct Truncated(Arc<AtomicU32>);
+
+    #[async_trait]
+    impl Provider for Truncated {
+        fn name(&self) -> &'static str {
+            "truncated"
+        }
+
+        async fn stream_completion(&self, _request: ChatRequest) -> Result<StreamingResponse> {
+            use crate::agent::streaming::MockStreamBuilder;
+
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(MockStreamBuilder::new()
+                .message("partial")
+                .error(Error::StreamInterrupted("connection reset".to_string()))
+                .build())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_errors_after_the_first_chunk_are_not_retried() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let provider = RetryProvider::new(
+            Truncated(calls.clone()),
+            RetryConfig {
+                base_delay: Duration::from_millis(1),
+                ..RetryConfig::default()
+            },
+        );
+
+        let mut stream = provider
+            .stream_completion(ChatRequest::default())
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(matches!(
+            stream.next().await,
+            Some(Ok(crate::agent::streaming::StreamingChoice::Message(text))) if text == "partial"
+        ));
+        assert!(matches!(stream.next().await, Some(Err(Error::StreamInterrupted(_)))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_status_classification() {
+        assert!(api_error(429, "rate_limit_exceeded").is_retryable());
+        assert!(api_error(502, "bad_gateway").is_retryable());
+        assert!(!api_error(400, "invalid_request_error").is_retryable());
+        assert!(api_error(400, "invalid_request_error").is_invalid_request());
+        // An exhausted quota won't recover by waiting
+        assert!(!api_error(429, "insufficient_quota").is_retryable());
+        assert!(api_error(429, "insufficient_quota").is_auth_failure());
+        assert!(!Error::ProviderApi("No embedding returned".to_string()).is_retryable());
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let config = RetryConfig {
+            base_delay: Duration::from_millis(100),
+            jitter: 0.5,
+            ..RetryConfig::default()
+        };
+        for _ in 0..100 {
+            let delay = config.delay(1);
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(150));
+        }
+    }
+}
@@ -0,0 +1,575 @@
+//! Replay buffer for late event subscribers
+//!
+//! Broadcast channels don't replay, so a UI that connects mid-conversation
+//! (e.g. after a page refresh) would miss everything emitted so far. The
+//! [`ReplayBuffer`] numbers every event with a per-session, monotonically
+//! increasing [`EventId`] and keeps the most recent ones in a bounded ring.
+//! [`ReplayBuffer::subscribe`] returns the buffered events after a given id
+//! followed by the live stream, with no gap or duplicate between the two, so a
+//! client can resume exactly where it left off (the SSE `Last-Event-ID` flow).
+//!
+//! Oversized payloads (full tool outputs) are truncated in the buffer; an
+//! optional [`ArtifactStore`] keeps the full event and the truncated copy
+//! carries its reference. Live subscribers always get the full event.
+
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::agent::core::AgentEvent;
+use crate::agent::events::EventItem;
+use crate::error::Result;
+use crate::skills::tool::compress::truncate_text;
+
+/// Per-session event sequence number, starting at 1
+pub type EventId = u64;
+
+/// Bounds for the replay buffer
+#[derive(Debug, Clone)]
+pub struct ReplayConfig {
+    /// Events kept (0 disables buffering; ids and live delivery still work)
+    pub max_events: usize,
+    /// Approximate payload bytes kept
+    pub max_bytes: usize,
+    /// Longest payload string stored before truncation
+    pub max_payload_chars: usize,
+    /// Buffer is dropped after this long without events
+    pub ttl: Duration,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            max_events: 1000,
+            max_bytes: 1024 * 1024,
+            max_payload_chars: 4096,
+            ttl: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+impl ReplayConfig {
+    /// No buffering; late subscribers only see live events
+    pub fn disabled() -> Self {
+        Self {
+            max_events: 0,
+            ..Self::default()
+        }
+    }
+}
+
+/// Keeps full payloads of events truncated in the buffer
+pub trait ArtifactStore: Send + Sync {
+    /// Store the wire JSON of event `id`, returning a reference clients can fetch
+    fn put(&self, id: EventId, json: &str) -> Option<String>;
+}
+
+/// Index row of a [`FileArtifactStore`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactRecord {
+    /// Reference handed to clients (`artifact://<uuid>`)
+    pub reference: String,
+    /// File name inside the store directory
+    pub file: String,
+    /// Event the artifact was cut from
+    pub event_id: EventId,
+    /// Owning user, if the store was scoped to one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// When the artifact was written
+    pub created_at: DateTime<Utc>,
+    /// File size
+    pub bytes: u64,
+}
+
+/// Artifacts as files in a directory, listed in its `index.jsonl`
+pub struct FileArtifactStore {
+    dir: PathBuf,
+    user_id: Option<String>,
+    index: Mutex<()>,
+}
+
+impl FileArtifactStore {
+    /// Index file name inside the directory
+    pub const INDEX: &'static str = "index.jsonl";
+
+    /// Store artifacts under `dir`, creating it if needed
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            user_id: None,
+            index: Mutex::new(()),
+        })
+    }
+
+    /// Record `user_id` as the owner of new artifacts
+    pub fn for_user(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    /// Store directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Every indexed artifact, oldest first
+    pub fn records(&self) -> Result<Vec<ArtifactRecord>> {
+        let _guard = self.index.lock();
+        self.read_index()
+    }
+
+    /// Full event JSON behind `reference`
+    pub fn fetch(&self, reference: &str) -> Result<Option<String>> {
+        let records = self.records()?;
+        match records.iter().find(|r| r.reference == reference) {
+            Some(record) => Ok(Some(std::fs::read_to_string(self.dir.join(&record.file))?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Delete the index row and file of `reference`; returns the bytes freed
+    pub fn remove(&self, reference: &str) -> Result<u64> {
+        let _guard = self.index.lock();
+        let (removed, kept): (Vec<_>, Vec<_>) = self
+            .read_index()?
+            .into_iter()
+            .partition(|r| r.reference == reference);
+        let mut rows = String::new();
+        for record in &kept {
+            rows.push_str(&serde_json::to_string(record)?);
+            rows.push('\n');
+        }
+        std::fs::write(self.dir.join(Self::INDEX), rows)?;
+        let mut freed = 0;
+        for record in removed {
+            match std::fs::remove_file(self.dir.join(&record.file)) {
+                Ok(()) => freed += record.bytes,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(freed)
+    }
+
+    fn read_index(&self) -> Result<Vec<ArtifactRecord>> {
+        let text = match std::fs::read_to_string(self.dir.join(Self::INDEX)) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(text
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .filter_map(|l| serde_json::from_str(l).ok())
+            .collect())
+    }
+
+    fn write(&self, id: EventId, json: &str) -> Result<String> {
+        let uuid = uuid::Uuid::new_v4();
+        let record = ArtifactRecord {
+            reference: format!("artifact://{}", uuid),
+            file: format!("{}.json", uuid),
+            event_id: id,
+            user_id: self.user_id.clone(),
+            created_at: Utc::now(),
+            bytes: json.len() as u64,
+        };
+        std::fs::write(self.dir.join(&record.file), json)?;
+        let _guard = self.index.lock();
+        let mut index = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join(Self::INDEX))?;
+        writeln!(index, "{}", serde_json::to_string(&record)?)?;
+        Ok(record.reference)
+    }
+}
+
+impl ArtifactStore for FileArtifactStore {
+    fn put(&self, id: EventId, json: &str) -> Option<String> {
+        self.write(id, json)
+            .map_err(|e| tracing::warn!("Failed to store artifact for event {}: {}", id, e))
+            .ok()
+    }
+}
+
+/// How a buffered event was shortened
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Truncation {
+    /// Payload bytes before truncation
+    pub original_len: usize,
+    /// Where the full event is kept, if an artifact store is configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub artifact: Option<String>,
+}
+
+/// An event with its sequence number, in wire format
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayEvent {
+    /// Sequence number within the session
+    pub id: EventId,
+    /// The event (payloads may be truncated when replayed)
+    #[serde(flatten)]
+    pub event: AgentEvent,
+    /// Set when this is a truncated buffered copy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<Truncation>,
+}
+
+impl ReplayEvent {
+    /// Server-sent events frame; the `id` line backs `Last-Event-ID` resumption
+    pub fn to_sse(&self) -> String {
+        format!(
+            "id: {}\nevent: {}\ndata: {}\n\n",
+            self.id,
+            format!("{:?}", self.event.kind()).to_lowercase(),
+            serde_json::to_string(self).unwrap_or_default()
+        )
+    }
+}
+
+struct State {
+    next_id: EventId,
+    events: VecDeque<(ReplayEvent, usize)>,
+    bytes: usize,
+    last_append: Instant,
+}
+
+/// Bounded, numbered history of a session's events
+pub struct ReplayBuffer {
+    config: ReplayConfig,
+    artifacts: Option<Arc<dyn ArtifactStore>>,
+    state: Mutex<State>,
+    live: broadcast::Sender<ReplayEvent>,
+}
+
+impl ReplayBuffer {
+    /// Empty buffer with `config` bounds
+    pub fn new(config: ReplayConfig) -> Self {
+        Self {
+            config,
+            artifacts: None,
+            state: Mutex::new(State {
+                next_id: 1,
+                events: VecDeque::new(),
+                bytes: 0,
+                last_append: Instant::now(),
+            }),
+            live: broadcast::channel(1024).0,
+        }
+    }
+
+    /// Keep full payloads of truncated events in `store`
+    pub fn with_artifacts(mut self, store: Arc<dyn ArtifactStore>) -> Self {
+        self.artifacts = Some(store);
+        self
+    }
+
+    /// Number and deliver `event`; returns its id
+    ///
+    /// Held under one short lock so ids, the ring and live delivery stay in order.
+    pub fn append(&self, event: &AgentEvent) -> EventId {
+        let mut state = self.state.lock();
+        self.expire(&mut state);
+        let id = state.next_id;
+        state.next_id += 1;
+        state.last_append = Instant::now();
+
+        if self.live.receiver_count() > 0 {
+            let _ = self.live.send(ReplayEvent {
+                id,
+                event: event.clone(),
+                truncated: None,
+            });
+        }
+        if self.config.max_events == 0 {
+            return id;
+        }
+
+        let stored = self.shrink(id, event.clone());
+        let size = payload_len(&stored.event) + 64;
+        state.bytes += size;
+        state.events.push_back((stored, size));
+        while state.events.len() > self.config.max_events
+            || (state.bytes > self.config.max_bytes && state.events.len() > 1)
+        {
+            if let Some((_, size)) = state.events.pop_front() {
+                state.bytes -= size;
+            }
+        }
+        id
+    }
+
+    /// Buffered events after `since` (or from the buffer start), then live ones
+    pub fn subscribe(&self, since: Option<EventId>) -> ReplaySubscription {
+        let mut state = self.state.lock();
+        self.expire(&mut state);
+        // Subscribing under the lock means the backlog and live stream meet exactly
+        let live = self.live.subscribe();
+        let since = since.unwrap_or(0);
+        let backlog: VecDeque<_> = state
+            .events
+            .iter()
+            .filter(|(e, _)| e.id > since)
+            .map(|(e, _)| e.clone())
+            .collect();
+        // Events the client hasn't seen but the buffer no longer holds
+        let first_kept = backlog.front().map_or(state.next_id, |e| e.id);
+        let missed = first_kept.saturating_sub(since + 1);
+        ReplaySubscription {
+            missed: (since > 0 && missed > 0).then_some(missed),
+            backlog,
+            live,
+        }
+    }
+
+    /// Drop buffered events (ids keep counting)
+    pub fn clear(&self) {
+        let mut state = self.state.lock();
+        state.events.clear();
+        state.bytes = 0;
+    }
+
+    /// Number of buffered events
+    pub fn len(&self) -> usize {
+        let mut state = self.state.lock();
+        self.expire(&mut state);
+        state.events.len()
+    }
+
+    /// Whether nothing is buffered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn expire(&self, state: &mut State) {
+        if !state.events.is_empty() && state.last_append.elapsed() > self.config.ttl {
+            state.events.clear();
+            state.bytes = 0;
+        }
+    }
+
+    /// Truncate oversized payloads, handing the full event to the artifact store
+    fn shrink(&self, id: EventId, mut event: AgentEvent) -> ReplayEvent {
+        let max = self.config.max_payload_chars;
+        let original_len = payload_len(&event);
+        if payloads_mut(&mut event).iter().all(|p| p.len() <= max) {
+            return ReplayEvent {
+                id,
+                event,
+                truncated: None,
+            };
+        }
+        let artifact = self.artifacts.as_ref().and_then(|store| {
+            let full = ReplayEvent {
+                id,
+                event: event.clone(),
+                truncated: None,
+            };
+            store.put(id, &serde_json::to_string(&full).ok()?)
+        });
+        for payload in payloads_mut(&mut event) {
+            if payload.len() > max {
+                *payload = truncate_text(payload, max);
+            }
+        }
+        ReplayEvent {
+            id,
+            event,
+            truncated: Some(Truncation {
+                original_len,
+                artifact,
+            }),
+        }
+    }
+}
+
+/// Large string fields of an event
+fn payloads_mut(event: &mut AgentEvent) -> Vec<&mut String> {
+    match event {
+        AgentEvent::Thinking { prompt } => vec![prompt],
+        AgentEvent::StreamDelta { content } | AgentEvent::Reasoning { content } => vec![content],
+        AgentEvent::ToolCallDelta { arguments, .. } => vec![arguments],
+        AgentEvent::ToolCall { input, .. } | AgentEvent::ApprovalPending { input, .. } => {
+            vec![input]
+        }
+        AgentEvent::ToolResult { output, .. } => vec![output],
+        AgentEvent::Response {
+            content, formatted, ..
+        } => std::iter::once(content)
+            .chain(formatted.values_mut())
+            .collect(),
+        AgentEvent::Subagent { event, .. } => payloads_mut(event),
+        AgentEvent::ToolUnavailable { .. }
+        | AgentEvent::Usage { .. }
+        | AgentEvent::Error { .. }
+        | AgentEvent::Cancelled { .. }
+        | AgentEvent::PersonaChanged { .. } => Vec::new(),
+    }
+}
+
+fn payload_len(event: &AgentEvent) -> usize {
+    payloads_mut(&mut event.clone())
+        .iter()
+        .map(|p| p.len())
+        .sum()
+}
+
+/// Buffered events followed by live ones
+pub struct ReplaySubscription {
+    missed: Option<u64>,
+    backlog: VecDeque<ReplayEvent>,
+    live: broadcast::Receiver<ReplayEvent>,
+}
+
+impl ReplaySubscription {
+    /// Next event; [`EventItem::Lagged`] reports events lost before the
+    /// resume point or while this subscriber fell behind
+    ///
+    /// Yields `None` once the agent is dropped.
+    pub async fn recv(&mut self) -> Option<EventItem<ReplayEvent>> {
+        if let Some(missed) = self.missed.take() {
+            return Some(EventItem::Lagged(missed));
+        }
+        if let Some(event) = self.backlog.pop_front() {
+            return Some(EventItem::Event(event));
+        }
+        match self.live.recv().await {
+            Ok(event) => Some(EventItem::Event(event)),
+            Err(broadcast::error::RecvError::Lagged(n)) => Some(EventItem::Lagged(n)),
+            Err(broadcast::error::RecvError::Closed) => None,
+        }
+    }
+
+    /// Number of buffered events still to be delivered
+    pub fn backlog_len(&self) -> usize {
+        self.backlog.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(output: &str) -> AgentEvent {
+        AgentEvent::ToolResult {
+            tool: "fetch".to_string(),
+            output: output.to_string(),
+        }
+    }
+
+    async fn next(sub: &mut ReplaySubscription) -> ReplayEvent {
+        match sub.recv().await {
+            Some(EventItem::Event(event)) => event,
+            other => panic!("expected an event, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_late_subscriber_gets_transcript_then_live_and_can_resume() {
+        let buffer = ReplayBuffer::new(ReplayConfig::default());
+        for i in 0..3 {
+            buffer.append(&result(&format!("out {}", i)));
+        }
+
+        let mut late = buffer.subscribe(None);
+        assert_eq!(late.backlog_len(), 3);
+        buffer.append(&result("live"));
+        let ids: Vec<_> = [
+            next(&mut late).await,
+            next(&mut late).await,
+            next(&mut late).await,
+            next(&mut late).await,
+        ]
+        .iter()
+        .map(|e| e.id)
+        .collect();
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+
+        // Resuming after id 2 skips what the client already saw
+        let mut resumed = buffer.subscribe(Some(2));
+        assert_eq!(next(&mut resumed).await.id, 3);
+        assert_eq!(next(&mut resumed).await.id, 4);
+        assert_eq!(resumed.backlog_len(), 0);
+
+        let sse = buffer
+            .subscribe(Some(3))
+            .backlog
+            .pop_front()
+            .unwrap()
+            .to_sse();
+        assert!(
+            sse.starts_with("id: 4\nevent: toolresult\ndata: {\"id\":4,\"type\":\"tool_result\"")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_oversized_payloads_truncated_with_artifact_reference() {
+        struct Artifacts(Mutex<Vec<(EventId, String)>>);
+        impl ArtifactStore for Artifacts {
+            fn put(&self, id: EventId, json: &str) -> Option<String> {
+                self.0.lock().push((id, json.to_string()));
+                Some(format!("artifact://events/{}", id))
+            }
+        }
+
+        let artifacts = Arc::new(Artifacts(Mutex::new(Vec::new())));
+        let config = ReplayConfig {
+            max_payload_chars: 100,
+            max_bytes: 2_000,
+            ..Default::default()
+        };
+        let buffer = ReplayBuffer::new(config).with_artifacts(artifacts.clone());
+        let mut live = buffer.subscribe(None);
+        let big = "x".repeat(10_000);
+        buffer.append(&result(&big));
+
+        // Live subscribers see the full output; the buffered copy is truncated
+        match next(&mut live).await.event {
+            AgentEvent::ToolResult { output, .. } => assert_eq!(output.len(), 10_000),
+            other => panic!("{:?}", other),
+        }
+        let stored = buffer.subscribe(None).backlog.pop_front().unwrap();
+        let truncation = stored.truncated.clone().unwrap();
+        assert_eq!(truncation.original_len, 10_000);
+        assert_eq!(truncation.artifact.as_deref(), Some("artifact://events/1"));
+        assert!(serde_json::to_string(&stored).unwrap().len() < 400);
+        assert!(artifacts.0.lock()[0].1.contains(&big));
+
+        // Byte bound evicts oldest events
+        for _ in 0..40 {
+            buffer.append(&result(&"y".repeat(90)));
+        }
+        assert!(buffer.len() < 20);
+        let mut resumed = buffer.subscribe(Some(1));
+        assert!(matches!(resumed.recv().await, Some(EventItem::Lagged(n)) if n > 0));
+    }
+
+    #[tokio::test]
+    async fn test_buffer_expires_after_ttl_and_on_clear() {
+        let config = ReplayConfig {
+            ttl: Duration::from_millis(20),
+            ..Default::default()
+        };
+        let buffer = ReplayBuffer::new(config);
+        buffer.append(&result("a"));
+        assert_eq!(buffer.len(), 1);
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(buffer.is_empty());
+
+        // Ids keep increasing across expiry so resumption stays unambiguous
+        assert_eq!(buffer.append(&result("b")), 2);
+        buffer.clear();
+        assert_eq!(buffer.subscribe(None).backlog_len(), 0);
+    }
+}
@@ -1,17 +1,40 @@
 //! Background scheduler for proactive agent tasks
 //!
 //! Enables agents to handle periodic tasks and timed events using tokio-cron-scheduler.
+//!
+//! Cron jobs with a `tz` are evaluated on that zone's wall clock by [`TzCron`]
+//! (see its DST policy); without one they run in UTC. Jobs with a `calendar`
+//! skip ticks while the [`TradingCalendar`](crate::agent::calendar::TradingCalendar)
+//! is closed and report each skip as [`SchedulerEvent::Skipped`].
+//!
+//! With [`Scheduler::with_store`] the job list is rewritten to a JSONL file on
+//! every change and after every tick. [`Scheduler::restore`] reloads it on
+//! startup and handles fires missed while the process was down according to
+//! the [`CatchUpPolicy`].
 
-use std::sync::Weak;
-use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use dashmap::DashMap;
-use tracing::{info, error, debug};
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+use tracing::{info, error, debug, warn};
 use tokio_cron_scheduler::{Job, JobScheduler};
 
 use crate::error::{Error, Result};
+use crate::agent::calendar::CalendarRegistry;
 use crate::agent::multi_agent::{Coordinator, AgentRole};
+use crate::agent::provider::{with_priority, RequestPriority};
+use crate::infra::instance::InstanceLock;
+
+/// Times a scheduled run is retried after the provider sheds it
+const MAX_OVERLOAD_RETRIES: u32 = 3;
+
+/// Most missed fires replayed per job under [`CatchUpPolicy::RunAll`]
+const MAX_CATCH_UP_RUNS: usize = 100;
 
 /// Schedule for a job
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,7 +80,7 @@ pub enum JobPayload {
 /// A scheduled job (Metadata for listing/canceling)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CronJob {
-    /// Unique ID (Matches the one in tokio-cron-scheduler)
+    /// Unique ID, stable across restarts
     pub id: Uuid,
     /// Human-readable name
     pub name: String,
@@ -67,16 +90,300 @@ pub struct CronJob {
     pub payload: JobPayload,
     /// Whether the job is enabled
     pub enabled: bool,
+    /// IANA time zone the cron expression is evaluated in (UTC when absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tz: Option<String>,
+    /// Trading calendar gating runs; ticks while it is closed are skipped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar: Option<String>,
+    /// When the job is next due; `None` once a one-shot job has fired
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+impl CronJob {
+    /// Fires that fell due between `next_run` and `now`, at most `cap`
+    pub fn missed_fires(&self, now: DateTime<Utc>, cap: usize) -> usize {
+        let mut due = self.next_run;
+        let mut missed = 0;
+        while let Some(at) = due.filter(|at| *at <= now && missed < cap) {
+            missed += 1;
+            due = next_after(&self.schedule, self.tz.as_deref(), at);
+        }
+        missed
+    }
+}
+
+/// What to do with fires missed while the scheduler was down
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUpPolicy {
+    /// Drop missed fires and wait for the next one
+    Skip,
+    /// Run once on startup, however many fires were missed
+    #[default]
+    RunOnce,
+    /// Run every missed fire on startup, one after another
+    RunAll,
+}
+
+impl CatchUpPolicy {
+    /// Runs to replay for `missed` fires
+    pub fn runs(self, missed: usize) -> usize {
+        match self {
+            Self::Skip => 0,
+            Self::RunOnce => missed.min(1),
+            Self::RunAll => missed.min(MAX_CATCH_UP_RUNS),
+        }
+    }
+}
+
+/// Per-job scheduling options
+#[derive(Debug, Clone, Default)]
+pub struct JobOptions {
+    /// IANA time zone for cron expressions, e.g. `America/New_York`
+    pub tz: Option<String>,
+    /// Calendar name in the scheduler's [`CalendarRegistry`]
+    pub calendar: Option<String>,
+}
+
+/// What happened to a scheduled tick
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SchedulerEvent {
+    /// The job ran
+    Fired {
+        job_id: Uuid,
+        name: String,
+        at: DateTime<Utc>,
+    },
+    /// The job's calendar was closed, so the tick was dropped
+    Skipped {
+        job_id: Uuid,
+        name: String,
+        calendar: String,
+        reason: String,
+        at: DateTime<Utc>,
+    },
+}
+
+/// A cron expression evaluated on a time zone's wall clock
+///
+/// DST policy: wall-clock times skipped by a spring-forward transition fire
+/// once, at the first instant after the gap (a 02:30 job runs at 03:00 that
+/// day); times repeated by a fall-back transition fire once, at their first
+/// occurrence.
+#[derive(Debug, Clone)]
+pub struct TzCron {
+    cron: croner::Cron,
+    tz: Tz,
+}
+
+impl TzCron {
+    /// Parse a six-field (seconds first) cron expression for zone `tz`
+    pub fn parse(expr: &str, tz: &str) -> Result<Self> {
+        let cron = croner::Cron::new(expr)
+            .with_seconds_required()
+            .with_dom_and_dow()
+            .parse()
+            .map_err(|e| Error::agent_config(format!("Invalid cron expression '{}': {}", expr, e)))?;
+        Ok(Self { cron, tz: parse_tz(tz)? })
+    }
+
+    /// Next fire after `now`, as (local wall-clock time, instant)
+    ///
+    /// `last` is the wall-clock time of the previous fire; passing it keeps a
+    /// repeated fall-back hour from firing twice.
+    pub fn next_fire(&self, now: DateTime<Utc>, last: Option<NaiveDateTime>) -> Option<(NaiveDateTime, DateTime<Utc>)> {
+        let mut from = now.with_timezone(&self.tz).naive_local();
+        if let Some(last) = last {
+            from = from.max(last);
+        }
+        // Search on the naive wall clock, then map to an instant by the DST policy
+        let local = self
+            .cron
+            .find_next_occurrence(&Utc.from_utc_datetime(&from), false)
+            .ok()?
+            .naive_utc();
+        Some((local, resolve_local(&self.tz, local)))
+    }
+}
+
+/// The first fire of `schedule` strictly after `after`
+fn next_after(schedule: &JobSchedule, tz: Option<&str>, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    match schedule {
+        JobSchedule::At { at } => (*at > after).then_some(*at),
+        JobSchedule::Every { interval_secs } => {
+            Some(after + chrono::Duration::seconds((*interval_secs).max(1) as i64))
+        }
+        JobSchedule::Cron { expr } => TzCron::parse(expr, tz.unwrap_or("UTC"))
+            .ok()?
+            .next_fire(after, None)
+            .map(|(_, at)| at),
+    }
+}
+
+fn parse_tz(tz: &str) -> Result<Tz> {
+    tz.parse()
+        .map_err(|_| Error::agent_config(format!("Unknown time zone: {}", tz)))
+}
+
+/// Map a wall-clock time to an instant: earliest when repeated, end of the gap when skipped
+fn resolve_local(tz: &Tz, local: NaiveDateTime) -> DateTime<Utc> {
+    if let Some(at) = tz.from_local_datetime(&local).earliest() {
+        return at.with_timezone(&Utc);
+    }
+    let mut probe = local.with_second(0).and_then(|t| t.with_nanosecond(0)).unwrap_or(local);
+    // Gaps are at most a few hours; bound the walk anyway
+    for _ in 0..24 * 60 {
+        probe += chrono::Duration::minutes(1);
+        if let Some(at) = tz.from_local_datetime(&probe).earliest() {
+            return at.with_timezone(&Utc);
+        }
+    }
+    Utc.from_utc_datetime(&local)
+}
+
+/// Everything one job needs to run a tick
+#[derive(Clone)]
+struct JobRunner {
+    id: Uuid,
+    name: String,
+    payload: JobPayload,
+    calendar: Option<String>,
+    calendars: Arc<CalendarRegistry>,
+    coordinator: Weak<Coordinator>,
+    instance_lock: Option<Arc<InstanceLock>>,
+    events: broadcast::Sender<SchedulerEvent>,
+    jobs: Arc<DashMap<Uuid, CronJob>>,
+    store: Option<Arc<JobStore>>,
+}
+
+impl JobRunner {
+    /// Whether a tick at `now` may run; closed calendars emit a skip event
+    fn admit(&self, now: DateTime<Utc>) -> bool {
+        if !Scheduler::may_run(self.instance_lock.as_deref(), &self.name) {
+            return false;
+        }
+        let Some(calendar) = &self.calendar else {
+            return true;
+        };
+        let reason = match self.calendars.closed_reason(calendar, now) {
+            Ok(None) => return true,
+            Ok(Some(reason)) => reason,
+            Err(e) => e.to_string(),
+        };
+        info!("Skipping scheduled job {}: calendar {} closed ({})", self.name, calendar, reason);
+        let _ = self.events.send(SchedulerEvent::Skipped {
+            job_id: self.id,
+            name: self.name.clone(),
+            calendar: calendar.clone(),
+            reason,
+            at: now,
+        });
+        false
+    }
+
+    async fn tick(self, kind: &'static str) {
+        let now = Utc::now();
+        self.advance(now).await;
+        if !self.admit(now) {
+            return;
+        }
+        let _ = self.events.send(SchedulerEvent::Fired { job_id: self.id, name: self.name.clone(), at: now });
+        if let Err(e) = Scheduler::run_payload(&self.coordinator, &self.name, self.payload).await {
+            error!("Failed to execute {} job {}: {}", kind, self.name, e);
+        }
+    }
+
+    /// Record the next due time, so a restart knows which fires it missed
+    async fn advance(&self, now: DateTime<Utc>) {
+        match self.jobs.get_mut(&self.id) {
+            Some(mut job) => job.next_run = next_after(&job.schedule, job.tz.as_deref(), now),
+            None => return,
+        }
+        if let Some(store) = &self.store {
+            if let Err(e) = store.save(&self.jobs).await {
+                warn!("Failed to persist scheduled jobs: {}", e);
+            }
+        }
+    }
+}
+
+/// JSONL file holding one [`CronJob`] per line
+struct JobStore {
+    path: PathBuf,
+    writer: tokio::sync::Mutex<()>,
+}
+
+impl JobStore {
+    /// Jobs in the file; a missing file holds none
+    async fn load(&self) -> Result<Vec<CronJob>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = tokio::fs::read_to_string(&self.path).await?;
+        let mut jobs = Vec::new();
+        for (n, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(job) => jobs.push(job),
+                Err(e) => warn!("Skipping malformed job line {} in {:?}: {}", n + 1, self.path, e),
+            }
+        }
+        Ok(jobs)
+    }
+
+    /// Rewrite the file with `jobs`
+    async fn save(&self, jobs: &DashMap<Uuid, CronJob>) -> Result<()> {
+        let _guard = self.writer.lock().await;
+        let mut sorted: Vec<CronJob> = jobs.iter().map(|r| r.value().clone()).collect();
+        sorted.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+        let mut content = String::new();
+        for job in &sorted {
+            content.push_str(&serde_json::to_string(job)?);
+            content.push('\n');
+        }
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await.ok();
+        }
+        // Write tmp -> rename so a crash never leaves a truncated job list
+        let tmp_path = self.path.with_extension(format!("tmp.{}", Uuid::new_v4()));
+        tokio::fs::write(&tmp_path, content).await?;
+        if let Err(e) = tokio::fs::rename(&tmp_path, &self.path).await {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
 }
 
 /// Scheduler service wrapping tokio-cron-scheduler
 pub struct Scheduler {
     /// Registered jobs metadata
-    jobs: DashMap<Uuid, CronJob>,
+    jobs: Arc<DashMap<Uuid, CronJob>>,
+    /// Job IDs in the underlying scheduler, which change on every restart
+    scheduled: DashMap<Uuid, Uuid>,
+    /// Where the job list is persisted
+    store: Option<Arc<JobStore>>,
+    /// How [`restore`](Self::restore) handles missed fires
+    catch_up: CatchUpPolicy,
     /// The underlying scheduler
     scheduler: tokio::sync::Mutex<JobScheduler>,
     /// Weak reference to coordinator for execution
     coordinator: Weak<Coordinator>,
+    /// Jobs only fire while this instance is the writer
+    instance_lock: Option<Arc<InstanceLock>>,
+    /// Calendars jobs may be gated on
+    calendars: Arc<CalendarRegistry>,
+    /// Fire and skip notifications
+    events: broadcast::Sender<SchedulerEvent>,
+    /// Time-zone aware cron jobs, driven by their own task
+    tz_tasks: DashMap<Uuid, JoinHandle<()>>,
+    /// Set once [`run`](Self::run) starts the scheduler
+    started: watch::Sender<bool>,
 }
 
 impl Scheduler {
@@ -84,99 +391,314 @@ impl Scheduler {
     pub async fn new(coordinator: Weak<Coordinator>) -> Self {
         let scheduler = JobScheduler::new().await.expect("Failed to initialize JobScheduler");
         Self {
-            jobs: DashMap::new(),
+            jobs: Arc::new(DashMap::new()),
+            scheduled: DashMap::new(),
+            store: None,
+            catch_up: CatchUpPolicy::default(),
             scheduler: tokio::sync::Mutex::new(scheduler),
             coordinator,
+            instance_lock: None,
+            calendars: Arc::new(CalendarRegistry::default()),
+            events: broadcast::channel(64).0,
+            tz_tasks: DashMap::new(),
+            started: watch::channel(false).0,
         }
     }
 
-    /// Add a job
+    /// Gate jobs on calendars from `calendars` instead of the built-ins
+    pub fn with_calendars(mut self, calendars: Arc<CalendarRegistry>) -> Self {
+        self.calendars = calendars;
+        self
+    }
+
+    /// Calendars jobs may be gated on
+    pub fn calendars(&self) -> &Arc<CalendarRegistry> {
+        &self.calendars
+    }
+
+    /// Receive fire and skip notifications
+    pub fn subscribe(&self) -> broadcast::Receiver<SchedulerEvent> {
+        self.events.subscribe()
+    }
+
+    /// Skip job runs unless `lock` is the current writer
+    pub fn with_instance_lock(mut self, lock: Arc<InstanceLock>) -> Self {
+        self.instance_lock = Some(lock);
+        self
+    }
+
+    /// Persist jobs to the JSONL file at `path`; call [`restore`](Self::restore) to reload them
+    pub fn with_store(mut self, path: impl Into<PathBuf>) -> Self {
+        self.store = Some(Arc::new(JobStore {
+            path: path.into(),
+            writer: tokio::sync::Mutex::new(()),
+        }));
+        self
+    }
+
+    /// Handle fires missed while the scheduler was down with `policy`
+    pub fn with_catch_up(mut self, policy: CatchUpPolicy) -> Self {
+        self.catch_up = policy;
+        self
+    }
+
+    /// Reschedule the jobs in the store, returning how many were restored
+    ///
+    /// Missed fires are replayed once [`run`](Self::run) starts, as the
+    /// catch-up policy allows. One-shot jobs whose time has passed are dropped
+    /// after their catch-up run.
+    pub async fn restore(&self) -> Result<usize> {
+        let Some(store) = &self.store else {
+            return Ok(0);
+        };
+        let now = Utc::now();
+        let mut restored = 0;
+        for job in store.load().await? {
+            if self.jobs.contains_key(&job.id) {
+                continue;
+            }
+            let runs = self.catch_up.runs(job.missed_fires(now, MAX_CATCH_UP_RUNS));
+            if runs > 0 {
+                info!("Catching up {} missed run(s) of scheduled job {}", runs, job.name);
+                let runner = self.runner(job.id, &job.name, &job.payload, job.calendar.clone());
+                self.spawn_catch_up(runner, runs);
+            }
+            if matches!(job.schedule, JobSchedule::At { at } if at <= now) {
+                continue;
+            }
+            let options = JobOptions { tz: job.tz, calendar: job.calendar };
+            match self.schedule_job(job.id, job.name.clone(), job.schedule, job.payload, options).await {
+                Ok(()) => restored += 1,
+                Err(e) => warn!("Dropping stored job {}: {}", job.name, e),
+            }
+        }
+        store.save(&self.jobs).await?;
+        Ok(restored)
+    }
+
+    /// Add a job in UTC, without a calendar
     pub async fn add_job(&self, name: String, schedule: JobSchedule, payload: JobPayload) -> Result<Uuid> {
-        let coordinator_weak = self.coordinator.clone();
-        let payload_clone = payload.clone();
-        let name_clone = name.clone();
-        
+        self.add_job_with(name, schedule, payload, JobOptions::default()).await
+    }
+
+    /// Add a job with a time zone and/or calendar
+    pub async fn add_job_with(
+        &self,
+        name: String,
+        schedule: JobSchedule,
+        payload: JobPayload,
+        options: JobOptions,
+    ) -> Result<Uuid> {
+        let id = Uuid::new_v4();
+        self.schedule_job(id, name, schedule, payload, options).await?;
+        self.persist().await?;
+        Ok(id)
+    }
+
+    /// Register a job under `id` with the underlying scheduler or a zoned task
+    async fn schedule_job(
+        &self,
+        id: Uuid,
+        name: String,
+        schedule: JobSchedule,
+        payload: JobPayload,
+        options: JobOptions,
+    ) -> Result<()> {
+        if let Some(tz) = &options.tz {
+            parse_tz(tz)?;
+        }
+        if let Some(calendar) = &options.calendar {
+            self.calendars.get(calendar)?;
+        }
+        let runner = self.runner(id, &name, &payload, options.calendar.clone());
+
         // 1. Create the job based on schedule type
-        let job = match &schedule {
-            JobSchedule::At { at } => {
+        let job = match (&schedule, &options.tz) {
+            (JobSchedule::Cron { expr }, Some(tz)) => {
+                // tokio-cron-scheduler fixes the UTC offset at creation, so
+                // zoned jobs are driven here to follow DST
+                let cron = TzCron::parse(expr, tz)?;
+                self.tz_tasks.insert(id, self.spawn_tz_cron(cron, runner));
+                self.insert_job(id, name, schedule, payload, options);
+                return Ok(());
+            }
+            (JobSchedule::At { at }, _) => {
                 let now = Utc::now();
                 let duration = at.signed_duration_since(now).to_std()
                     .map_err(|_| Error::agent_config("Scheduled time is in the past"))?;
                 
                 // One-shot job using a duration
                 Job::new_one_shot_async(duration, move |_uuid, _l| {
-                    let coordinator_weak = coordinator_weak.clone();
-                    let payload = payload_clone.clone();
-                    let name = name_clone.clone();
-                    Box::pin(async move {
-                        if let Err(e) = Self::execute_payload(&coordinator_weak, &name, payload).await {
-                            error!("Failed to execute one-shot job {}: {}", name, e);
-                        }
-                    })
+                    Box::pin(runner.clone().tick("one-shot"))
                 }).map_err(|e| Error::Internal(format!("Failed to create one-shot job: {}", e)))?
             }
-            JobSchedule::Every { interval_secs } => {
+            (JobSchedule::Every { interval_secs }, _) => {
                 let duration = std::time::Duration::from_secs(*interval_secs);
                 Job::new_repeated_async(duration, move |_uuid, _l| {
-                    let coordinator_weak = coordinator_weak.clone();
-                    let payload = payload_clone.clone();
-                    let name = name_clone.clone();
-                    Box::pin(async move {
-                        if let Err(e) = Self::execute_payload(&coordinator_weak, &name, payload).await {
-                            error!("Failed to execute repeated job {}: {}", name, e);
-                        }
-                    })
+                    Box::pin(runner.clone().tick("repeated"))
                 }).map_err(|e| Error::Internal(format!("Failed to create repeated job: {}", e)))?
             }
-            JobSchedule::Cron { expr } => {
+            (JobSchedule::Cron { expr }, None) => {
                 Job::new_async(expr.as_str(), move |_uuid, _l| {
-                    let coordinator_weak = coordinator_weak.clone();
-                    let payload = payload_clone.clone();
-                    let name = name_clone.clone();
-                    Box::pin(async move {
-                        if let Err(e) = Self::execute_payload(&coordinator_weak, &name, payload).await {
-                            error!("Failed to execute cron job {}: {}", name, e);
-                        }
-                    })
+                    Box::pin(runner.clone().tick("cron"))
                 }).map_err(|e| Error::Internal(format!("Failed to create cron job: {}", e)))?
             }
         };
 
         // 2. Add to underlying scheduler
         let sched = self.scheduler.lock().await;
-        let id = sched.add(job).await
+        let scheduled_id = sched.add(job).await
             .map_err(|e| Error::Internal(format!("Failed to add job to scheduler: {}", e)))?;
+        self.scheduled.insert(id, scheduled_id);
         
         // 3. Store metadata
+        self.insert_job(id, name, schedule, payload, options);
+        
+        Ok(())
+    }
+
+    fn runner(&self, id: Uuid, name: &str, payload: &JobPayload, calendar: Option<String>) -> JobRunner {
+        JobRunner {
+            id,
+            name: name.to_string(),
+            payload: payload.clone(),
+            calendar,
+            calendars: Arc::clone(&self.calendars),
+            coordinator: self.coordinator.clone(),
+            instance_lock: self.instance_lock.clone(),
+            events: self.events.clone(),
+            jobs: Arc::clone(&self.jobs),
+            store: self.store.clone(),
+        }
+    }
+
+    fn insert_job(&self, id: Uuid, name: String, schedule: JobSchedule, payload: JobPayload, options: JobOptions) {
+        let next_run = next_after(&schedule, options.tz.as_deref(), Utc::now());
         self.jobs.insert(id, CronJob {
             id,
             name,
             schedule,
             payload,
             enabled: true,
+            tz: options.tz,
+            calendar: options.calendar,
+            next_run,
+        });
+    }
+
+    /// Rewrite the store, if any, with the current jobs
+    async fn persist(&self) -> Result<()> {
+        match &self.store {
+            Some(store) => store.save(&self.jobs).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Replay `runs` missed fires back to back once the scheduler starts
+    fn spawn_catch_up(&self, runner: JobRunner, runs: usize) {
+        let mut started = self.started.subscribe();
+        tokio::spawn(async move {
+            if started.wait_for(|s| *s).await.is_err() {
+                return;
+            }
+            for _ in 0..runs {
+                runner.clone().tick("catch-up").await;
+            }
         });
-        
-        Ok(id)
     }
 
-    /// List all jobs
+    /// Drive a zoned cron job: sleep to each fire, run it in the background
+    fn spawn_tz_cron(&self, cron: TzCron, runner: JobRunner) -> JoinHandle<()> {
+        let mut started = self.started.subscribe();
+        tokio::spawn(async move {
+            if started.wait_for(|s| *s).await.is_err() {
+                return;
+            }
+            let mut last = None;
+            loop {
+                let now = Utc::now();
+                let Some((local, at)) = cron.next_fire(now, last) else {
+                    warn!("Cron job {} has no further fire times", runner.name);
+                    return;
+                };
+                tokio::time::sleep((at - now).to_std().unwrap_or_default()).await;
+                last = Some(local);
+                tokio::spawn(runner.clone().tick("cron"));
+            }
+        })
+    }
+
+    /// List all jobs, soonest due first
     pub fn list_jobs(&self) -> Vec<CronJob> {
-        self.jobs.iter().map(|r| r.value().clone()).collect()
+        let mut jobs: Vec<CronJob> = self.jobs.iter().map(|r| r.value().clone()).collect();
+        jobs.sort_by_key(|job| (job.next_run.is_none(), job.next_run));
+        jobs
     }
 
-    /// Remove a job
+    /// Cancel a job so it neither fires nor comes back on restore
+    pub async fn cancel_job(&self, id: Uuid) -> Result<bool> {
+        if let Some((_, task)) = self.tz_tasks.remove(&id) {
+            task.abort();
+        } else if let Some((_, scheduled_id)) = self.scheduled.remove(&id) {
+            let sched = self.scheduler.lock().await;
+            sched.remove(&scheduled_id).await
+                .map_err(|e| Error::Internal(format!("Failed to remove job: {}", e)))?;
+        }
+        let removed = self.jobs.remove(&id).is_some();
+        if removed {
+            self.persist().await?;
+        }
+        Ok(removed)
+    }
+
+    /// Remove a job; same as [`cancel_job`](Self::cancel_job)
     pub async fn remove_job(&self, id: Uuid) -> Result<bool> {
-        let sched = self.scheduler.lock().await;
-        sched.remove(&id).await
-            .map_err(|e| Error::Internal(format!("Failed to remove job: {}", e)))?;
-        Ok(self.jobs.remove(&id).is_some())
+        self.cancel_job(id).await
     }
 
     /// Start the scheduler loop
     pub async fn run(&self) {
+        if let Some(lock) = &self.instance_lock {
+            if let Err(e) = lock.check_writer("scheduler") {
+                warn!("Scheduler not started: {}", e);
+                return;
+            }
+        }
         let sched = self.scheduler.lock().await;
         if let Err(e) = sched.start().await {
             error!("Failed to start scheduler: {}", e);
+            return;
+        }
+        self.started.send_replace(true);
+    }
+
+    /// Whether a job may fire; a fenced instance must not double-fire the new holder's jobs
+    fn may_run(lock: Option<&InstanceLock>, name: &str) -> bool {
+        match lock.map(|l| l.check_writer("scheduled job")) {
+            Some(Err(e)) => {
+                warn!("Skipping scheduled job {}: {}", name, e);
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Run a payload as scheduled work, retrying later instead of dropping it when shed
+    async fn run_payload(coordinator_weak: &Weak<Coordinator>, name: &str, payload: JobPayload) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            let run = Self::execute_payload(coordinator_weak, name, payload.clone());
+            match with_priority(RequestPriority::Scheduled, run).await {
+                Err(Error::ProviderOverloaded { retry_after_secs, .. }) if attempt < MAX_OVERLOAD_RETRIES => {
+                    attempt += 1;
+                    warn!(
+                        "Scheduled job {} was shed by the provider, retrying in {}s ({}/{})",
+                        name, retry_after_secs, attempt, MAX_OVERLOAD_RETRIES
+                    );
+                    tokio::time::sleep(std::time::Duration::from_secs(retry_after_secs)).await;
+                }
+                result => return result,
+            }
         }
     }
 
@@ -234,3 +756,168 @@ impl Scheduler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ny(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        chrono_tz::America::New_York
+            .with_ymd_and_hms(y, m, d, h, min, 0)
+            .earliest()
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    /// Walk `cron` from `start` with a mocked clock, collecting fire instants
+    fn fires(cron: &TzCron, start: DateTime<Utc>, count: usize) -> Vec<DateTime<Utc>> {
+        let (mut now, mut last, mut out) = (start, None, Vec::new());
+        for _ in 0..count {
+            let (local, at) = cron.next_fire(now, last).unwrap();
+            out.push(at);
+            now = at;
+            last = Some(local);
+        }
+        out
+    }
+
+    #[test]
+    fn test_tz_cron_follows_dst_in_both_directions() {
+        let daily = TzCron::parse("0 30 2 * * *", "America/New_York").unwrap();
+        // Spring forward 2026-03-08: 02:30 does not exist and runs at 03:00 EDT
+        let spring = fires(&daily, ny(2026, 3, 6, 12, 0), 3);
+        assert_eq!(spring[0], ny(2026, 3, 7, 2, 30));
+        assert_eq!(spring[1], Utc.with_ymd_and_hms(2026, 3, 8, 7, 0, 0).unwrap());
+        assert_eq!(spring[2], ny(2026, 3, 9, 2, 30));
+        assert_eq!(spring[2].hour(), 6);
+
+        // Fall back 2026-11-01: 01:30 happens twice and runs once, on the EDT pass
+        let fall_job = TzCron::parse("0 30 1 * * *", "America/New_York").unwrap();
+        let fall = fires(&fall_job, ny(2026, 10, 31, 12, 0), 2);
+        assert_eq!(fall[0], Utc.with_ymd_and_hms(2026, 11, 1, 5, 30, 0).unwrap());
+        assert_eq!(fall[1], Utc.with_ymd_and_hms(2026, 11, 2, 6, 30, 0).unwrap());
+
+        // An every-30-minutes job does not replay the repeated hour
+        let half_hourly = TzCron::parse("0 */30 * * * *", "America/New_York").unwrap();
+        let start = Utc.with_ymd_and_hms(2026, 11, 1, 5, 10, 0).unwrap(); // 01:10 EDT
+        let ticks = fires(&half_hourly, start, 3);
+        assert_eq!(
+            ticks,
+            vec![
+                Utc.with_ymd_and_hms(2026, 11, 1, 5, 30, 0).unwrap(),
+                Utc.with_ymd_and_hms(2026, 11, 1, 7, 0, 0).unwrap(), // 02:00 EST
+                Utc.with_ymd_and_hms(2026, 11, 1, 7, 30, 0).unwrap(),
+            ]
+        );
+        assert!(TzCron::parse("0 30 2 * * *", "Mars/Olympus").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_calendar_closed_ticks_emit_skip_events() {
+        let scheduler = Scheduler::new(Weak::new()).await;
+        let mut events = scheduler.subscribe();
+        let id = Uuid::new_v4();
+        let payload = JobPayload::AgentTurn { role: AgentRole::Assistant, prompt: "check".to_string() };
+        let runner = scheduler.runner(id, "open bell", &payload, Some("us_equities".to_string()));
+
+        // Saturday is skipped with a distinct event; Monday's session is admitted
+        assert!(!runner.admit(ny(2026, 10, 17, 10, 0)));
+        match events.try_recv().unwrap() {
+            SchedulerEvent::Skipped { job_id, calendar, reason, .. } => {
+                assert_eq!((job_id, calendar.as_str(), reason.as_str()), (id, "us_equities", "closed on Sat"));
+            }
+            other => panic!("expected a skip, got {:?}", other),
+        }
+        assert!(runner.admit(ny(2026, 10, 19, 10, 0)));
+        assert!(events.try_recv().is_err());
+
+        let unknown = JobOptions { calendar: Some("lse".to_string()), ..Default::default() };
+        let schedule = JobSchedule::Every { interval_secs: 60 };
+        assert!(scheduler.add_job_with("x".to_string(), schedule, runner.payload.clone(), unknown).await.is_err());
+    }
+
+    #[test]
+    fn test_jobs_persisted_before_tz_support_still_load() {
+        let old = r#"{
+            "id": "6f1c2a56-7a53-4c1e-9a57-1d2b8e7f0c11",
+            "name": "daily report",
+            "schedule": {"kind": "cron", "expr": "0 0 9 * * *"},
+            "payload": {"kind": "agentTurn", "role": "Assistant", "prompt": "report"},
+            "enabled": true
+        }"#;
+        let job: CronJob = serde_json::from_str(old).unwrap();
+        assert_eq!((job.tz, job.calendar), (None, None));
+
+        let zoned = CronJob { tz: Some("America/New_York".to_string()), calendar: Some("us_equities".to_string()), ..job };
+        let json = serde_json::to_value(&zoned).unwrap();
+        assert_eq!(json["tz"], "America/New_York");
+        let back: CronJob = serde_json::from_value(json).unwrap();
+        assert_eq!(back.calendar.as_deref(), Some("us_equities"));
+    }
+
+    #[tokio::test]
+    async fn test_jobs_survive_a_restart_and_catch_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jobs.jsonl");
+        let payload = JobPayload::AgentTurn { role: AgentRole::Assistant, prompt: "rebalance".to_string() };
+
+        // Scheduled but never started, so the one-shot is missed while "down"
+        let (rebalance, hourly) = {
+            let scheduler = Scheduler::new(Weak::new()).await.with_store(&path);
+            let at = Utc::now() + chrono::Duration::seconds(1);
+            let rebalance = scheduler.add_job("rebalance".to_string(), JobSchedule::At { at }, payload.clone()).await.unwrap();
+            let hourly = scheduler.add_job("hourly".to_string(), JobSchedule::Every { interval_secs: 3600 }, payload.clone()).await.unwrap();
+            let cancelled = scheduler.add_job("cancelled".to_string(), JobSchedule::Every { interval_secs: 60 }, payload.clone()).await.unwrap();
+            assert!(scheduler.cancel_job(cancelled).await.unwrap());
+            (rebalance, hourly)
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+        let scheduler = Scheduler::new(Weak::new()).await.with_store(&path);
+        assert_eq!(scheduler.restore().await.unwrap(), 1);
+        let ids: Vec<Uuid> = scheduler.list_jobs().iter().map(|j| j.id).collect();
+        assert_eq!(ids, vec![hourly]);
+
+        let mut events = scheduler.subscribe();
+        scheduler.run().await;
+        let fired = tokio::time::timeout(std::time::Duration::from_secs(5), events.recv()).await.unwrap().unwrap();
+        assert!(matches!(fired, SchedulerEvent::Fired { job_id, .. } if job_id == rebalance));
+
+        // The fired one-shot does not come back a second time
+        let again = Scheduler::new(Weak::new()).await.with_store(&path);
+        again.restore().await.unwrap();
+        let ids: Vec<Uuid> = again.list_jobs().iter().map(|j| j.id).collect();
+        assert_eq!(ids, vec![hourly]);
+    }
+
+    #[test]
+    fn test_catch_up_policies_count_missed_fires() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+        let job = CronJob {
+            id: Uuid::new_v4(),
+            name: "minutely".to_string(),
+            schedule: JobSchedule::Every { interval_secs: 60 },
+            payload: JobPayload::AgentTurn { role: AgentRole::Assistant, prompt: "tick".to_string() },
+            enabled: true,
+            tz: None,
+            calendar: None,
+            next_run: Some(now - chrono::Duration::seconds(290)),
+        };
+        let missed = job.missed_fires(now, MAX_CATCH_UP_RUNS);
+        assert_eq!(missed, 5);
+        assert_eq!(
+            [CatchUpPolicy::Skip, CatchUpPolicy::RunOnce, CatchUpPolicy::RunAll].map(|p| p.runs(missed)),
+            [0, 1, 5]
+        );
+
+        // Daily 09:00 New York, last due three days ago
+        let daily = CronJob {
+            schedule: JobSchedule::Cron { expr: "0 0 9 * * *".to_string() },
+            tz: Some("America/New_York".to_string()),
+            next_run: Some(ny(2026, 10, 13, 9, 0)),
+            ..job
+        };
+        assert_eq!(daily.missed_fires(ny(2026, 10, 16, 10, 0), MAX_CATCH_UP_RUNS), 4);
+        assert_eq!(daily.missed_fires(ny(2026, 10, 16, 8, 0), MAX_CATCH_UP_RUNS), 3);
+    }
+}
@@ -1,5 +1,22 @@
-use crate::agent::message::Message;
+//! Persistent agent sessions and recovery of sessions left unfinished by a restart
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::agent::memory::Memory;
+use crate::agent::message::{Message, Role};
+use crate::agent::personality::Persona;
+use crate::agent::streaming::Usage;
+use crate::error::Result;
+use crate::infra::notification::{Notifier, NotifyChannel};
+use crate::infra::instance::InstanceLock;
 
 /// Status of an agent session
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -20,8 +37,45 @@ pub enum SessionStatus {
     Completed,
     /// Agent has failed
     Failed(String),
+    /// Session was interrupted and not recovered before going stale
+    Expired,
+    /// The run was cancelled by its caller; [`Agent::resume`](crate::agent::Agent::resume) continues it
+    Cancelled,
 }
 
+impl SessionStatus {
+    /// Whether the session has finished (completed, failed, expired or cancelled)
+    ///
+    /// Recovery leaves terminal sessions alone, so a cancelled run only continues when resumed explicitly.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Completed | Self::Failed(_) | Self::Expired | Self::Cancelled)
+    }
+
+    /// Name of the status without its details, as serialized (e.g. `awaiting_approval`)
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Thinking => "thinking",
+            Self::PendingTools => "pending_tools",
+            Self::AwaitingApproval { .. } => "awaiting_approval",
+            Self::Executing => "executing",
+            Self::Completed => "completed",
+            Self::Failed(_) => "failed",
+            Self::Expired => "expired",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Metadata key for the user a session belongs to
+pub const META_USER_ID: &str = "user_id";
+/// Metadata key for the name of the agent that wrote a session
+pub const META_AGENT: &str = "agent";
+/// Metadata key for a session's title, taken from its first user message
+pub const META_TITLE: &str = "title";
+
+/// Max characters of the first user message kept as a session title
+const TITLE_CHARS: usize = 80;
+
 /// A persistent session representing an agent's current state and history
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentSession {
@@ -35,6 +89,18 @@ pub struct AgentSession {
     pub status: SessionStatus,
     /// Timestamp of the last update
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Tokens and spend accumulated over the session
+    #[serde(default)]
+    pub usage: SessionUsage,
+    /// Small descriptive fields for listings, see [`META_USER_ID`], [`META_AGENT`] and [`META_TITLE`]
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+    /// Persona the agent was speaking as, restored on resume
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona: Option<Persona>,
+    /// The agent had no persona when saved, so resuming clears the current one
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub persona_cleared: bool,
 }
 
 impl AgentSession {
@@ -46,6 +112,762 @@ impl AgentSession {
             step: 0,
             status: SessionStatus::Thinking,
             updated_at: chrono::Utc::now(),
+            usage: SessionUsage::default(),
+            metadata: BTreeMap::new(),
+            persona: None,
+            persona_cleared: false,
+        }
+    }
+
+    /// Persona to apply on resume, `None` when the session does not record one
+    ///
+    /// `Some(None)` means the persona was cleared before the session was saved.
+    pub fn restored_persona(&self) -> Option<Option<Persona>> {
+        if self.persona_cleared {
+            Some(None)
+        } else {
+            self.persona.clone().map(Some)
+        }
+    }
+
+    /// Title for a conversation: its first user message, shortened to one line
+    pub fn title_from(messages: &[Message]) -> Option<String> {
+        let first = messages.iter().find(|m| m.role == Role::User)?.text();
+        let line = first.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            return None;
+        }
+        Some(match line.char_indices().nth(TITLE_CHARS) {
+            Some((end, _)) => format!("{}…", line[..end].trim_end()),
+            None => line,
+        })
+    }
+
+    /// Listing entry for this session
+    pub fn summary(&self) -> SessionSummary {
+        SessionSummary {
+            id: self.id.clone(),
+            updated_at: self.updated_at,
+            status: Some(self.status.label().to_string()),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+/// A stored session without its history, as listed by [`Memory::list_session_summaries`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub id: String,
+    /// When the session was last checkpointed
+    pub updated_at: DateTime<Utc>,
+    /// [`SessionStatus::label`], if the store could read it
+    pub status: Option<String>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Filter and page for [`Memory::list_session_summaries`]; most recently updated first
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionQuery {
+    /// Only sessions whose id starts with this
+    pub id_prefix: Option<String>,
+    /// Only sessions whose [`META_USER_ID`] is this
+    pub user_id: Option<String>,
+    /// Max sessions returned (`None` = all)
+    pub limit: Option<usize>,
+    /// Sessions skipped before the first returned
+    pub offset: usize,
+}
+
+impl SessionQuery {
+    /// Every session
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only sessions whose id starts with `prefix`
+    pub fn id_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.id_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Only sessions belonging to `user_id`
+    pub fn user(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    /// Return at most `limit` sessions
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Skip the first `offset` matching sessions
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Whether `summary` passes the filters (paging aside)
+    pub fn matches(&self, summary: &SessionSummary) -> bool {
+        self.id_prefix.as_deref().is_none_or(|p| summary.id.starts_with(p))
+            && self
+                .user_id
+                .as_deref()
+                .is_none_or(|u| summary.metadata.get(META_USER_ID).map(String::as_str) == Some(u))
+    }
+}
+
+/// Token usage and estimated spend accumulated over a session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionUsage {
+    /// Model calls counted
+    pub calls: u64,
+    /// Prompt tokens across all calls
+    pub prompt_tokens: u64,
+    /// Completion tokens across all calls
+    pub completion_tokens: u64,
+    /// Total tokens across all calls
+    pub total_tokens: u64,
+    /// Estimated USD spend on calls to priced models
+    pub cost_usd: f64,
+    /// Whether any call's counts were estimated rather than reported
+    pub estimated: bool,
+    /// Prompt tokens written to provider prompt caches
+    #[serde(default)]
+    pub cache_creation_tokens: u64,
+    /// Prompt tokens served from provider prompt caches
+    #[serde(default)]
+    pub cache_read_tokens: u64,
+}
+
+impl SessionUsage {
+    /// Count one model call and its cost, if priced
+    pub fn add(&mut self, usage: &Usage, cost_usd: Option<f64>) {
+        self.calls += 1;
+        self.prompt_tokens += usage.prompt_tokens as u64;
+        self.completion_tokens += usage.completion_tokens as u64;
+        self.total_tokens += usage.total_tokens as u64;
+        self.cost_usd += cost_usd.unwrap_or_default();
+        self.estimated |= usage.estimated;
+        self.cache_creation_tokens += usage.cache_creation_tokens as u64;
+        self.cache_read_tokens += usage.cache_read_tokens as u64;
+    }
+}
+
+/// Lease that keeps two instances from recovering the same sessions
+pub const RECOVERY_LEASE: &str = "session_recovery";
+
+/// System note appended to a session's history when it is resumed
+pub const RESUME_NOTE: &str =
+    "Resuming after interruption. Continue the task from where the conversation left off.";
+
+/// What recovery does with a session interrupted mid-turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterruptedAction {
+    /// Run the session again through the configured [`SessionResumer`]
+    Resume,
+    /// Tell the owner the session can be resumed by id
+    Notify,
+    /// Mark the session Expired
+    Expire,
+}
+
+/// Policy applied by [`SessionManager::recover`]
+#[derive(Debug, Clone)]
+pub struct RecoveryPolicy {
+    /// Action for Thinking, PendingTools and Executing sessions
+    pub interrupted: InterruptedAction,
+    /// Re-send pending approval requests through the notifier
+    pub reissue_approvals: bool,
+    /// Sessions not updated for this long are expired whatever their status
+    pub stale_after: Duration,
+    /// Max sessions resumed at the same time
+    pub max_concurrent_resumes: usize,
+    /// Min delay between starting two resumes (protects the provider)
+    pub resume_interval: Duration,
+    /// How long a recovery run holds [`RECOVERY_LEASE`]
+    pub lease_ttl: Duration,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self {
+            interrupted: InterruptedAction::Notify,
+            reissue_approvals: true,
+            stale_after: Duration::from_secs(24 * 3600),
+            max_concurrent_resumes: 2,
+            resume_interval: Duration::from_secs(1),
+            lease_ttl: Duration::from_secs(600),
+        }
+    }
+}
+
+/// What happened to one session during recovery
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum RecoveryOutcome {
+    /// Session was run again to completion
+    Resumed,
+    /// Owner was told the session can be resumed
+    Notified,
+    /// Pending approval request was sent again
+    ApprovalReissued,
+    /// Session was marked Expired
+    Expired,
+    /// Resuming or notifying failed
+    Failed { error: String },
+}
+
+/// Journal entry for one recovered session
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryEntry {
+    pub session_id: String,
+    /// Status the session was found in
+    pub previous_status: SessionStatus,
+    pub outcome: RecoveryOutcome,
+    pub at: DateTime<Utc>,
+}
+
+/// Per-outcome counts for a recovery run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RecoveryStats {
+    pub resumed: usize,
+    pub notified: usize,
+    pub approvals_reissued: usize,
+    pub expired: usize,
+    pub failed: usize,
+}
+
+/// Result of [`SessionManager::recover`]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RecoveryReport {
+    /// False when another instance holds the recovery lease (nothing was done)
+    pub lease_acquired: bool,
+    pub entries: Vec<RecoveryEntry>,
+}
+
+impl RecoveryReport {
+    /// Count entries by outcome
+    pub fn stats(&self) -> RecoveryStats {
+        let mut stats = RecoveryStats::default();
+        for entry in &self.entries {
+            match entry.outcome {
+                RecoveryOutcome::Resumed => stats.resumed += 1,
+                RecoveryOutcome::Notified => stats.notified += 1,
+                RecoveryOutcome::ApprovalReissued => stats.approvals_reissued += 1,
+                RecoveryOutcome::Expired => stats.expired += 1,
+                RecoveryOutcome::Failed { .. } => stats.failed += 1,
+            }
+        }
+        stats
+    }
+
+    /// Outcome recorded for a session, if it was recovered
+    pub fn outcome(&self, session_id: &str) -> Option<&RecoveryOutcome> {
+        self.entries
+            .iter()
+            .find(|e| e.session_id == session_id)
+            .map(|e| &e.outcome)
+    }
+}
+
+/// Runs an interrupted session again
+#[async_trait]
+pub trait SessionResumer: Send + Sync {
+    /// Continue `session` and return the final response
+    async fn resume_session(&self, session: &AgentSession) -> Result<String>;
+}
+
+/// Finds sessions left unfinished by a crash or deploy and applies a [`RecoveryPolicy`]
+pub struct SessionManager {
+    memory: Arc<dyn Memory>,
+    instance_id: String,
+    policy: RecoveryPolicy,
+    notifier: Option<(Arc<dyn Notifier>, NotifyChannel)>,
+    resumer: Option<Arc<dyn SessionResumer>>,
+    instance_lock: Option<Arc<InstanceLock>>,
+    journal: Mutex<Vec<RecoveryEntry>>,
+}
+
+impl SessionManager {
+    /// Manage sessions in `memory`; `instance_id` identifies this process for the lease
+    pub fn new(memory: Arc<dyn Memory>, instance_id: impl Into<String>) -> Self {
+        Self {
+            memory,
+            instance_id: instance_id.into(),
+            policy: RecoveryPolicy::default(),
+            notifier: None,
+            resumer: None,
+            instance_lock: None,
+            journal: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Set the recovery policy
+    pub fn with_policy(mut self, policy: RecoveryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Send approval reminders and resume notices through `notifier`
+    pub fn with_notifier(mut self, notifier: Arc<dyn Notifier>, channel: NotifyChannel) -> Self {
+        self.notifier = Some((notifier, channel));
+        self
+    }
+
+    /// Resume interrupted sessions with `resumer`
+    pub fn with_resumer(mut self, resumer: Arc<dyn SessionResumer>) -> Self {
+        self.resumer = Some(resumer);
+        self
+    }
+
+    /// Only recover sessions while `lock` is the current writer
+    pub fn with_instance_lock(mut self, lock: Arc<InstanceLock>) -> Self {
+        self.instance_lock = Some(lock);
+        self
+    }
+
+    /// Every recovery outcome recorded by this manager
+    pub fn journal(&self) -> Vec<RecoveryEntry> {
+        self.journal.lock().clone()
+    }
+
+    /// Scan for unfinished sessions and recover them
+    ///
+    /// Does nothing (and reports `lease_acquired: false`) while another instance
+    /// holds the recovery lease or this instance is not the data directory writer.
+    pub async fn recover(&self) -> Result<RecoveryReport> {
+        if let Some(Err(e)) = self.instance_lock.as_ref().map(|l| l.check_writer("session recovery")) {
+            info!("Session recovery skipped: {}", e);
+            return Ok(RecoveryReport::default());
+        }
+        let acquired = self
+            .memory
+            .try_acquire_lease(RECOVERY_LEASE, &self.instance_id, self.policy.lease_ttl)
+            .await?;
+        if !acquired {
+            info!("Session recovery skipped: lease held by another instance");
+            return Ok(RecoveryReport::default());
+        }
+
+        let mut sessions: Vec<AgentSession> = self
+            .memory
+            .list_sessions()
+            .await?
+            .into_iter()
+            .filter(|s| !s.status.is_terminal())
+            .collect();
+        sessions.sort_by_key(|s| s.updated_at);
+
+        let stale_after =
+            chrono::Duration::from_std(self.policy.stale_after).unwrap_or(chrono::Duration::MAX);
+        let now = Utc::now();
+        let mut entries = Vec::new();
+        let mut to_resume = Vec::new();
+
+        for session in sessions {
+            let id = session.id.clone();
+            let previous = session.status.clone();
+            let outcome = if now - session.updated_at > stale_after {
+                self.expire(session).await
+            } else if let SessionStatus::AwaitingApproval {
+                tool_name,
+                arguments,
+            } = &session.status
+            {
+                if !self.policy.reissue_approvals {
+                    continue;
+                }
+                let message = format!(
+                    "Session {} is still waiting for approval to run `{}` with {}",
+                    session.id, tool_name, arguments
+                );
+                self.send(&message, RecoveryOutcome::ApprovalReissued).await
+            } else {
+                match self.policy.interrupted {
+                    InterruptedAction::Resume if self.resumer.is_some() => {
+                        to_resume.push(session);
+                        continue;
+                    }
+                    InterruptedAction::Expire => self.expire(session).await,
+                    // Without a resumer the owner is told instead
+                    _ => {
+                        let message = format!(
+                            "Session {} was interrupted while {:?}. Resume it with session id `{}`.",
+                            session.id, session.status, session.id
+                        );
+                        self.send(&message, RecoveryOutcome::Notified).await
+                    }
+                }
+            };
+            entries.push(self.record(id, previous, outcome));
+        }
+
+        entries.extend(self.resume_all(to_resume).await);
+        let report = RecoveryReport {
+            lease_acquired: true,
+            entries,
+        };
+        let stats = report.stats();
+        info!(
+            resumed = stats.resumed,
+            notified = stats.notified,
+            approvals_reissued = stats.approvals_reissued,
+            expired = stats.expired,
+            failed = stats.failed,
+            "Session recovery finished"
+        );
+        Ok(report)
+    }
+
+    async fn expire(&self, mut session: AgentSession) -> RecoveryOutcome {
+        session.status = SessionStatus::Expired;
+        session.updated_at = Utc::now();
+        match self.memory.store_session(session).await {
+            Ok(()) => RecoveryOutcome::Expired,
+            Err(e) => RecoveryOutcome::Failed {
+                error: e.to_string(),
+            },
+        }
+    }
+
+    async fn send(&self, message: &str, outcome: RecoveryOutcome) -> RecoveryOutcome {
+        let Some((notifier, channel)) = &self.notifier else {
+            warn!("{} (no notifier configured)", message);
+            return outcome;
+        };
+        match notifier.notify(channel.clone(), message).await {
+            Ok(()) => outcome,
+            Err(e) => RecoveryOutcome::Failed {
+                error: e.to_string(),
+            },
+        }
+    }
+
+    /// Resume sessions oldest first, spacing out starts and capping concurrency
+    async fn resume_all(&self, sessions: Vec<AgentSession>) -> Vec<RecoveryEntry> {
+        let Some(resumer) = &self.resumer else {
+            return Vec::new();
+        };
+        if sessions.is_empty() {
+            return Vec::new();
+        }
+
+        let mut ticker =
+            tokio::time::interval(self.policy.resume_interval.max(Duration::from_millis(1)));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let ticker = tokio::sync::Mutex::new(ticker);
+
+        stream::iter(sessions)
+            .then(|session| {
+                let ticker = &ticker;
+                async move {
+                    ticker.lock().await.tick().await;
+                    session
+                }
+            })
+            .map(|session| async move {
+                let id = session.id.clone();
+                let previous = session.status.clone();
+                let outcome = match resumer.resume_session(&session).await {
+                    Ok(_) => {
+                        self.finish(session, SessionStatus::Completed, RecoveryOutcome::Resumed)
+                            .await
+                    }
+                    Err(e) => {
+                        let error = e.to_string();
+                        self.finish(
+                            session,
+                            SessionStatus::Failed(error.clone()),
+                            RecoveryOutcome::Failed { error },
+                        )
+                        .await
+                    }
+                };
+                self.record(id, previous, outcome)
+            })
+            .buffer_unordered(self.policy.max_concurrent_resumes.max(1))
+            .collect()
+            .await
+    }
+
+    /// Store the final status of a resumed session, keeping any history it checkpointed
+    async fn finish(
+        &self,
+        session: AgentSession,
+        status: SessionStatus,
+        outcome: RecoveryOutcome,
+    ) -> RecoveryOutcome {
+        let mut latest = match self.memory.retrieve_session(&session.id).await {
+            Ok(Some(latest)) => latest,
+            _ => session,
+        };
+        latest.status = status;
+        latest.updated_at = Utc::now();
+        match self.memory.store_session(latest).await {
+            Ok(()) => outcome,
+            Err(e) => RecoveryOutcome::Failed {
+                error: e.to_string(),
+            },
+        }
+    }
+
+    fn record(
+        &self,
+        session_id: String,
+        previous_status: SessionStatus,
+        outcome: RecoveryOutcome,
+    ) -> RecoveryEntry {
+        info!(session = %session_id, ?previous_status, ?outcome, "Recovered session");
+        let entry = RecoveryEntry {
+            session_id,
+            previous_status,
+            outcome,
+            at: Utc::now(),
+        };
+        self.journal.lock().push(entry.clone());
+        entry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Session store with lease semantics, shared by several managers
+    #[derive(Default)]
+    struct SharedStore {
+        sessions: Mutex<HashMap<String, AgentSession>>,
+        leases: Mutex<HashMap<String, (String, std::time::Instant)>>,
+    }
+
+    #[async_trait]
+    impl Memory for SharedStore {
+        async fn store(&self, _: &str, _: Option<&str>, _: Message) -> Result<()> {
+            Ok(())
+        }
+
+        async fn retrieve(&self, _: &str, _: Option<&str>, _: usize) -> Vec<Message> {
+            Vec::new()
+        }
+
+        async fn clear(&self, _: &str, _: Option<&str>) -> Result<()> {
+            Ok(())
+        }
+
+        async fn undo(&self, _: &str, _: Option<&str>) -> Result<Option<Message>> {
+            Ok(None)
         }
+
+        async fn store_session(&self, session: AgentSession) -> Result<()> {
+            self.sessions.lock().insert(session.id.clone(), session);
+            Ok(())
+        }
+
+        async fn retrieve_session(&self, id: &str) -> Result<Option<AgentSession>> {
+            Ok(self.sessions.lock().get(id).cloned())
+        }
+
+        async fn list_sessions(&self) -> Result<Vec<AgentSession>> {
+            Ok(self.sessions.lock().values().cloned().collect())
+        }
+
+        async fn try_acquire_lease(&self, name: &str, holder: &str, ttl: Duration) -> Result<bool> {
+            let mut leases = self.leases.lock();
+            let now = std::time::Instant::now();
+            match leases.get(name) {
+                Some((owner, expires)) if owner != holder && *expires > now => Ok(false),
+                _ => {
+                    leases.insert(name.to_string(), (holder.to_string(), now + ttl));
+                    Ok(true)
+                }
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingNotifier {
+        messages: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Notifier for RecordingNotifier {
+        async fn notify(&self, _channel: NotifyChannel, message: &str) -> Result<()> {
+            self.messages.lock().push(message.to_string());
+            Ok(())
+        }
+    }
+
+    /// Resumer that records start order and peak concurrency, failing sessions named "bad"
+    #[derive(Default)]
+    struct RecordingResumer {
+        started: Mutex<Vec<String>>,
+        running: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl SessionResumer for RecordingResumer {
+        async fn resume_session(&self, session: &AgentSession) -> Result<String> {
+            self.started.lock().push(session.id.clone());
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            if session.id == "bad" {
+                return Err(crate::error::Error::Internal("provider down".to_string()));
+            }
+            Ok("done".to_string())
+        }
+    }
+
+    fn session(id: &str, status: SessionStatus, minutes_ago: i64) -> AgentSession {
+        AgentSession {
+            id: id.to_string(),
+            messages: vec![Message::user("check SOL liquidity")],
+            step: 1,
+            status,
+            updated_at: Utc::now() - chrono::Duration::minutes(minutes_ago),
+            usage: SessionUsage::default(),
+            metadata: BTreeMap::new(),
+            persona: None,
+            persona_cleared: false,
+        }
+    }
+
+    async fn seeded_store() -> Arc<SharedStore> {
+        let store = Arc::new(SharedStore::default());
+        let approval = SessionStatus::AwaitingApproval {
+            tool_name: "swap".to_string(),
+            arguments: r#"{"amount":5}"#.to_string(),
+        };
+        for s in [
+            session("approve", approval, 30),
+            session("old", SessionStatus::Thinking, 3 * 24 * 60),
+            session("think-1", SessionStatus::Thinking, 20),
+            session("think-2", SessionStatus::PendingTools, 10),
+            session("bad", SessionStatus::Executing, 15),
+            session("done", SessionStatus::Completed, 5),
+        ] {
+            store.store_session(s).await.unwrap();
+        }
+        store
+    }
+
+    fn policy(interrupted: InterruptedAction) -> RecoveryPolicy {
+        RecoveryPolicy {
+            interrupted,
+            resume_interval: Duration::from_millis(20),
+            ..Default::default()
+        }
+    }
+
+    async fn status_of(store: &SharedStore, id: &str) -> SessionStatus {
+        store.retrieve_session(id).await.unwrap().unwrap().status
+    }
+
+    #[tokio::test]
+    async fn test_resume_policy() {
+        let store = seeded_store().await;
+        let notifier = Arc::new(RecordingNotifier::default());
+        let resumer = Arc::new(RecordingResumer::default());
+        let manager = SessionManager::new(store.clone(), "node-1")
+            .with_policy(RecoveryPolicy {
+                max_concurrent_resumes: 1,
+                ..policy(InterruptedAction::Resume)
+            })
+            .with_notifier(notifier.clone(), NotifyChannel::Log)
+            .with_resumer(resumer.clone());
+
+        let started = std::time::Instant::now();
+        let report = manager.recover().await.unwrap();
+        assert!(report.lease_acquired);
+        assert_eq!(
+            report.stats(),
+            RecoveryStats {
+                resumed: 2,
+                notified: 0,
+                approvals_reissued: 1,
+                expired: 1,
+                failed: 1,
+            }
+        );
+        assert!(report.outcome("done").is_none());
+
+        // Oldest first, one at a time, spaced by the resume interval
+        assert_eq!(*resumer.started.lock(), vec!["think-1", "bad", "think-2"]);
+        assert_eq!(resumer.peak.load(Ordering::SeqCst), 1);
+        assert!(started.elapsed() >= Duration::from_millis(40));
+
+        assert_eq!(status_of(&store, "think-1").await, SessionStatus::Completed);
+        assert_eq!(status_of(&store, "old").await, SessionStatus::Expired);
+        assert!(
+            matches!(status_of(&store, "bad").await, SessionStatus::Failed(e) if e.contains("provider down"))
+        );
+        assert!(matches!(
+            status_of(&store, "approve").await,
+            SessionStatus::AwaitingApproval { .. }
+        ));
+
+        let messages = notifier.messages.lock();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("approve") && messages[0].contains("swap"));
+        assert_eq!(manager.journal().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_notify_and_expire_policies() {
+        let store = seeded_store().await;
+        let notifier = Arc::new(RecordingNotifier::default());
+        let report = SessionManager::new(store.clone(), "node-1")
+            .with_policy(policy(InterruptedAction::Notify))
+            .with_notifier(notifier.clone(), NotifyChannel::Log)
+            .recover()
+            .await
+            .unwrap();
+        assert_eq!(report.outcome("think-1"), Some(&RecoveryOutcome::Notified));
+        assert_eq!(report.outcome("old"), Some(&RecoveryOutcome::Expired));
+        assert_eq!(status_of(&store, "think-1").await, SessionStatus::Thinking);
+        assert!(notifier
+            .messages
+            .lock()
+            .iter()
+            .any(|m| m.contains("`think-2`")));
+
+        let store = seeded_store().await;
+        let report = SessionManager::new(store.clone(), "node-1")
+            .with_policy(RecoveryPolicy {
+                reissue_approvals: false,
+                ..policy(InterruptedAction::Expire)
+            })
+            .recover()
+            .await
+            .unwrap();
+        assert_eq!(report.stats().expired, 4);
+        assert!(report.outcome("approve").is_none());
+        assert_eq!(status_of(&store, "think-2").await, SessionStatus::Expired);
+    }
+
+    #[tokio::test]
+    async fn test_lease_prevents_double_recovery() {
+        let store = seeded_store().await;
+        let first = Arc::new(RecordingResumer::default());
+        let second = Arc::new(RecordingResumer::default());
+        let a = SessionManager::new(store.clone(), "node-a")
+            .with_policy(policy(InterruptedAction::Resume))
+            .with_resumer(first.clone());
+        let b = SessionManager::new(store.clone(), "node-b")
+            .with_policy(policy(InterruptedAction::Resume))
+            .with_resumer(second.clone());
+
+        let (ra, rb) = tokio::join!(a.recover(), b.recover());
+        let (ra, rb) = (ra.unwrap(), rb.unwrap());
+        assert!(ra.lease_acquired != rb.lease_acquired);
+        assert_eq!(first.started.lock().len() + second.started.lock().len(), 3);
+        assert!(!ra.lease_acquired || rb.entries.is_empty());
     }
 }
@@ -19,10 +19,80 @@ pub struct Usage {
     pub completion_tokens: u32,
     /// Total number of tokens
     pub total_tokens: u32,
+    /// Counts were estimated from text length because the provider reported none
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub estimated: bool,
+    /// Prompt tokens written to the provider's prompt cache (included in `prompt_tokens`)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cache_creation_tokens: u32,
+    /// Prompt tokens served from the provider's prompt cache (included in `prompt_tokens`)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub cache_read_tokens: u32,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// Characters per token assumed when estimating usage
+pub const CHARS_PER_TOKEN: usize = 4;
+
+impl Usage {
+    /// Reported usage; the total is the sum of both counts
+    pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        Self {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            estimated: false,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+        }
+    }
+
+    /// Record prompt-cache writes and reads, which `prompt_tokens` already counts
+    pub fn with_cache(mut self, creation_tokens: u32, read_tokens: u32) -> Self {
+        self.cache_creation_tokens = creation_tokens;
+        self.cache_read_tokens = read_tokens;
+        self
+    }
+
+    /// Usage estimated at [`CHARS_PER_TOKEN`] characters per token
+    pub fn estimate(prompt_chars: usize, completion_chars: usize) -> Self {
+        let tokens = |chars: usize| chars.div_ceil(CHARS_PER_TOKEN) as u32;
+        Self {
+            estimated: true,
+            ..Self::new(tokens(prompt_chars), tokens(completion_chars))
+        }
+    }
+}
+
+impl std::ops::AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        self.estimated |= other.estimated;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+    }
+}
+
+/// Which model and upstream provider actually served a response
+///
+/// Routers such as OpenRouter may answer with a fallback model rather than
+/// the one requested.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseMetadata {
+    /// Model that generated the response
+    pub model: Option<String>,
+    /// Upstream provider that ran it, when the API routes between several
+    pub provider: Option<String>,
 }
 
 /// A chunk from a streaming response
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum StreamingChoice {
     /// Text content chunk
     Message(String),
@@ -40,12 +110,17 @@ pub enum StreamingChoice {
     /// Multiple tool calls (parallel)
     ParallelToolCalls(HashMap<usize, ToolCall>),
 
-    /// Thinking/reasoning chunk (e.g., Gemini's thoughts)
+    /// Thinking/reasoning chunk (e.g., Gemini's thoughts, DeepSeek's `reasoning_content`)
+    ///
+    /// Not part of the reply: agents never add it to the response or history.
     Thought(String),
 
     /// Usage information (emitted at the end)
     Usage(Usage),
 
+    /// Which model served the response (emitted before its content)
+    Metadata(ResponseMetadata),
+
     /// Stream finished
     Done,
 }
@@ -151,6 +226,12 @@ impl MockStreamBuilder {
         self
     }
 
+    /// Add a reasoning chunk
+    pub fn thought(mut self, text: impl Into<String>) -> Self {
+        self.chunks.push(Ok(StreamingChoice::Thought(text.into())));
+        self
+    }
+
     /// Add a tool call
     pub fn tool_call(
         mut self,
@@ -0,0 +1,282 @@
+//! Composable system prompts
+//!
+//! A [`SystemPrompt`] is a set of named sections rendered in a fixed order:
+//! `identity`, `legacy`, `persona`, `safety`, `output_format`, then custom
+//! sections in the order they were first set. Setting a key again replaces
+//! its text in place, so one part can be overridden without touching the rest.
+//!
+//! A plain preamble string is a prompt with a single `legacy` section and
+//! renders to exactly that string.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Key of a system prompt section
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionKey {
+    /// Who the agent is
+    Identity,
+    /// A preamble set as one string, e.g. with `AgentBuilder::system_prompt`
+    Legacy,
+    /// The agent's [`Persona`](crate::agent::personality::Persona)
+    Persona,
+    /// Rules the agent must follow
+    Safety,
+    /// How responses are formatted
+    OutputFormat,
+    /// Anything else, e.g. tenant-specific additions
+    Custom(String),
+}
+
+impl SectionKey {
+    /// Key as written in config and attribution
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Identity => "identity",
+            Self::Legacy => "legacy",
+            Self::Persona => "persona",
+            Self::Safety => "safety",
+            Self::OutputFormat => "output_format",
+            Self::Custom(key) => key,
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Identity => 0,
+            Self::Legacy => 1,
+            Self::Persona => 2,
+            Self::Safety => 3,
+            Self::OutputFormat => 4,
+            Self::Custom(_) => 5,
+        }
+    }
+
+    /// Header text, e.g. `Output format` for `output_format`
+    fn title(&self) -> String {
+        let words = self.as_str().replace(['_', '-'], " ");
+        let mut chars = words.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => words,
+        }
+    }
+}
+
+impl From<&str> for SectionKey {
+    fn from(key: &str) -> Self {
+        match key {
+            "identity" => Self::Identity,
+            "legacy" => Self::Legacy,
+            "persona" => Self::Persona,
+            "safety" => Self::Safety,
+            "output_format" => Self::OutputFormat,
+            other => Self::Custom(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for SectionKey {
+    fn from(key: String) -> Self {
+        Self::from(key.as_str())
+    }
+}
+
+impl fmt::Display for SectionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One section of a [`SystemPrompt`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptSection {
+    /// Section key
+    pub key: SectionKey,
+    /// Section text; empty sections are not rendered
+    pub text: String,
+}
+
+/// System prompt built from named sections
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemPrompt {
+    sections: Vec<PromptSection>,
+    /// Text placed between rendered sections
+    pub separator: String,
+    /// Start each section with a `## Title` line
+    pub headers: bool,
+}
+
+impl Default for SystemPrompt {
+    fn default() -> Self {
+        Self {
+            sections: Vec::new(),
+            separator: "\n\n".to_string(),
+            headers: false,
+        }
+    }
+}
+
+impl SystemPrompt {
+    /// Prompt without sections
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prompt whose only section is `text` under `legacy`
+    pub fn legacy(text: impl Into<String>) -> Self {
+        Self::new().section(SectionKey::Legacy, text)
+    }
+
+    /// Set `key` to `text`, replacing any earlier text for it
+    pub fn section(mut self, key: impl Into<SectionKey>, text: impl Into<String>) -> Self {
+        self.set(key, text);
+        self
+    }
+
+    /// Place `separator` between sections
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Render a header line above each section
+    pub fn headers(mut self, headers: bool) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Set `key` to `text` in place
+    pub fn set(&mut self, key: impl Into<SectionKey>, text: impl Into<String>) {
+        let (key, text) = (key.into(), text.into());
+        match self.sections.iter_mut().find(|s| s.key == key) {
+            Some(section) => section.text = text,
+            None => self.sections.push(PromptSection { key, text }),
+        }
+    }
+
+    /// Remove `key`, returning its text
+    pub fn remove(&mut self, key: impl Into<SectionKey>) -> Option<String> {
+        let key = key.into();
+        let index = self.sections.iter().position(|s| s.key == key)?;
+        Some(self.sections.remove(index).text)
+    }
+
+    /// Text of `key`, if set
+    pub fn get(&self, key: impl Into<SectionKey>) -> Option<&str> {
+        let key = key.into();
+        self.sections
+            .iter()
+            .find(|s| s.key == key)
+            .map(|s| s.text.as_str())
+    }
+
+    /// Non-empty sections in render order
+    pub fn sections(&self) -> Vec<&PromptSection> {
+        let mut sections: Vec<_> = self
+            .sections
+            .iter()
+            .filter(|s| !s.text.is_empty())
+            .collect();
+        sections.sort_by_key(|s| s.key.rank());
+        sections
+    }
+
+    /// Whether nothing would be rendered
+    pub fn is_empty(&self) -> bool {
+        self.sections.iter().all(|s| s.text.is_empty())
+    }
+
+    /// Each section's rendered text, headers included, in render order
+    pub fn render_parts(&self) -> Vec<(SectionKey, String)> {
+        self.sections()
+            .into_iter()
+            .map(|s| {
+                let text = if self.headers {
+                    format!("## {}\n{}", s.key.title(), s.text)
+                } else {
+                    s.text.clone()
+                };
+                (s.key.clone(), text)
+            })
+            .collect()
+    }
+
+    /// The full prompt text
+    pub fn render(&self) -> String {
+        self.render_parts()
+            .into_iter()
+            .map(|(_, text)| text)
+            .collect::<Vec<_>>()
+            .join(&self.separator)
+    }
+}
+
+impl From<String> for SystemPrompt {
+    fn from(text: String) -> Self {
+        Self::legacy(text)
+    }
+}
+
+impl From<&str> for SystemPrompt {
+    fn from(text: &str) -> Self {
+        Self::legacy(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_override_by_key_keeps_order() {
+        let prompt = SystemPrompt::new()
+            .section("tenant_rules", "Only trade majors.")
+            .section("output_format", "Answer in markdown.")
+            .section("safety", "Never reveal keys.")
+            .section("identity", "You are a trading agent.")
+            .section("safety", "Never reveal keys or seed phrases.");
+
+        assert_eq!(
+            prompt.render(),
+            "You are a trading agent.\n\nNever reveal keys or seed phrases.\n\nAnswer in markdown.\n\nOnly trade majors."
+        );
+        let keys: Vec<_> = prompt
+            .sections()
+            .iter()
+            .map(|s| s.key.to_string())
+            .collect();
+        assert_eq!(
+            keys,
+            ["identity", "safety", "output_format", "tenant_rules"]
+        );
+
+        let mut trimmed = prompt.clone().separator("\n").headers(true);
+        assert_eq!(
+            trimmed.remove("output_format").as_deref(),
+            Some("Answer in markdown.")
+        );
+        assert_eq!(
+            trimmed.render(),
+            "## Identity\nYou are a trading agent.\n## Safety\nNever reveal keys or seed phrases.\n## Tenant rules\nOnly trade majors."
+        );
+
+        let json = serde_json::to_string(&prompt).unwrap();
+        assert!(
+            json.contains(r#"{"key":{"custom":"tenant_rules"}"#),
+            "{}",
+            json
+        );
+        assert_eq!(serde_json::from_str::<SystemPrompt>(&json).unwrap(), prompt);
+    }
+
+    #[test]
+    fn test_legacy_renders_verbatim() {
+        let text = "  You are a helpful AI assistant.\n";
+        assert_eq!(SystemPrompt::legacy(text).render(), text);
+        assert_eq!(SystemPrompt::legacy("").render(), "");
+    }
+}
@@ -0,0 +1,346 @@
+//! Compact references for old tool results
+//!
+//! Tool outputs stay in history verbatim, but every later step pays for them
+//! again. With aging enabled, the [`ContextManager`] swaps older tool results
+//! for a one-line stub in the assembled context only: tool name, a short
+//! extractive summary and the byte count. The stub keeps the `tool_call_id`,
+//! so call/result pairing is untouched, and the model can get the full text
+//! back through the `recall_tool_output` tool.
+//!
+//! The most recent results and results a later assistant message quotes are
+//! always kept verbatim.
+//!
+//! [`ContextManager`]: crate::agent::context::ContextManager
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
+use crate::agent::message::{Content, ContentPart, Message, Role};
+use crate::skills::tool::RECALL_TOOL_OUTPUT_TOOL;
+
+/// Longest summary kept in a stub, in characters
+const SUMMARY_CHARS: usize = 160;
+
+/// When tool results are replaced by stubs
+#[derive(Debug, Clone)]
+pub struct ToolAgingConfig {
+    /// Most recent tool results that are never stubbed
+    pub keep_recent: usize,
+    /// Stub results once this many assistant turns follow them; `None` ages only under pressure
+    pub after_steps: Option<usize>,
+    /// Stub every eligible result while history exceeds this share of its token budget
+    pub pressure_ratio: Option<f64>,
+    /// Words in a row a later assistant message must share with a result to count as quoting it
+    pub quote_min_words: usize,
+}
+
+impl Default for ToolAgingConfig {
+    fn default() -> Self {
+        Self {
+            keep_recent: 2,
+            after_steps: Some(2),
+            pressure_ratio: Some(0.7),
+            quote_min_words: 8,
+        }
+    }
+}
+
+impl ToolAgingConfig {
+    /// Never stub the last `n` results
+    pub fn keep_recent(mut self, n: usize) -> Self {
+        self.keep_recent = n;
+        self
+    }
+
+    /// Stub results after `steps` assistant turns, or only under pressure with `None`
+    pub fn after_steps(mut self, steps: Option<usize>) -> Self {
+        self.after_steps = steps;
+        self
+    }
+
+    /// Stub eligible results when history uses more than `ratio` of its budget
+    pub fn pressure_ratio(mut self, ratio: Option<f64>) -> Self {
+        self.pressure_ratio = ratio;
+        self
+    }
+
+    /// Word run that marks a result as quoted
+    pub fn quote_min_words(mut self, words: usize) -> Self {
+        self.quote_min_words = words.max(1);
+        self
+    }
+}
+
+/// Full text of stubbed tool results, by call id
+#[derive(Debug, Clone, Default)]
+pub struct ToolOutputArchive {
+    outputs: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl ToolOutputArchive {
+    /// Original output of `call_id`, if it was stubbed
+    pub fn get(&self, call_id: &str) -> Option<String> {
+        self.outputs.read().get(call_id).cloned()
+    }
+
+    fn insert(&self, call_id: &str, output: &str) {
+        if !self.outputs.read().contains_key(call_id) {
+            self.outputs
+                .write()
+                .insert(call_id.to_string(), output.to_string());
+        }
+    }
+}
+
+/// Applies a [`ToolAgingConfig`] to history and archives what it stubs
+#[derive(Debug, Clone, Default)]
+pub struct ToolOutputAging {
+    config: ToolAgingConfig,
+    archive: ToolOutputArchive,
+}
+
+impl ToolOutputAging {
+    /// Aging with `config` and an empty archive
+    pub fn new(config: ToolAgingConfig) -> Self {
+        Self {
+            config,
+            archive: ToolOutputArchive::default(),
+        }
+    }
+
+    /// Archive shared with the recall tool
+    pub fn archive(&self) -> ToolOutputArchive {
+        self.archive.clone()
+    }
+
+    /// `history` with old tool results stubbed
+    ///
+    /// `cost` is a message's token cost and `budget` the tokens available to
+    /// history, for the pressure trigger. Borrows when nothing is stubbed.
+    pub fn apply<'a>(
+        &self,
+        history: &'a [Message],
+        budget: usize,
+        cost: impl Fn(&Message) -> usize,
+    ) -> Cow<'a, [Message]> {
+        let results: Vec<usize> = history
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.role == Role::Tool && tool_results(m).next().is_some())
+            .map(|(i, _)| i)
+            .collect();
+        let eligible = &results[..results.len().saturating_sub(self.config.keep_recent)];
+        if eligible.is_empty() {
+            return Cow::Borrowed(history);
+        }
+
+        let under_pressure = self.config.pressure_ratio.is_some_and(|ratio| {
+            history.iter().map(&cost).sum::<usize>() as f64 > ratio * budget as f64
+        });
+
+        let mut aged: Option<Vec<Message>> = None;
+        for &index in eligible {
+            let later = &history[index + 1..];
+            let old = self.config.after_steps.is_some_and(|steps| {
+                later.iter().filter(|m| m.role == Role::Assistant).count() >= steps
+            });
+            if !(old || under_pressure) {
+                continue;
+            }
+            if let Some(stub) = self.stub(history, index, later) {
+                aged.get_or_insert_with(|| history.to_vec())[index] = stub;
+            }
+        }
+        match aged {
+            Some(messages) => Cow::Owned(messages),
+            None => Cow::Borrowed(history),
+        }
+    }
+
+    /// Stubbed copy of `history[index]`, unless every result in it must stay verbatim
+    fn stub(&self, history: &[Message], index: usize, later: &[Message]) -> Option<Message> {
+        let message = &history[index];
+        let Content::Parts(parts) = &message.content else {
+            return None;
+        };
+        let quotes: Vec<String> = later
+            .iter()
+            .filter(|m| m.role == Role::Assistant)
+            .map(|m| m.content.as_text())
+            .collect();
+
+        let mut changed = false;
+        let parts = parts
+            .iter()
+            .map(|part| match part {
+                ContentPart::ToolResult {
+                    tool_call_id,
+                    name,
+                    content,
+                } => {
+                    if quotes
+                        .iter()
+                        .any(|text| quotes_from(text, content, self.config.quote_min_words))
+                    {
+                        return part.clone();
+                    }
+                    let tool = name
+                        .clone()
+                        .or_else(|| call_name(&history[..index], tool_call_id))
+                        .unwrap_or_else(|| "tool".to_string());
+                    let text = stub_text(&tool, tool_call_id, content);
+                    if text.len() >= content.len() {
+                        return part.clone();
+                    }
+                    self.archive.insert(tool_call_id, content);
+                    changed = true;
+                    ContentPart::ToolResult {
+                        tool_call_id: tool_call_id.clone(),
+                        name: name.clone(),
+                        content: text,
+                    }
+                }
+                other => other.clone(),
+            })
+            .collect();
+        changed.then(|| Message {
+            content: Content::Parts(parts),
+            ..message.clone()
+        })
+    }
+}
+
+fn tool_results(message: &Message) -> impl Iterator<Item = &ContentPart> {
+    let parts = match &message.content {
+        Content::Parts(parts) => parts.as_slice(),
+        Content::Text(_) => &[],
+    };
+    parts
+        .iter()
+        .filter(|p| matches!(p, ContentPart::ToolResult { .. }))
+}
+
+/// Name of the tool call `id` in an earlier assistant message
+fn call_name(history: &[Message], id: &str) -> Option<String> {
+    history.iter().rev().find_map(|m| match &m.content {
+        Content::Parts(parts) => parts.iter().find_map(|p| match p {
+            ContentPart::ToolCall {
+                id: call_id, name, ..
+            } if call_id == id => Some(name.clone()),
+            _ => None,
+        }),
+        Content::Text(_) => None,
+    })
+}
+
+/// Whether `text` repeats at least `min_words` consecutive words of `output`
+fn quotes_from(text: &str, output: &str, min_words: usize) -> bool {
+    let words = |s: &str| -> Vec<String> {
+        s.split_whitespace()
+            .map(|w| {
+                w.trim_matches(|c: char| !c.is_alphanumeric())
+                    .to_lowercase()
+            })
+            .filter(|w| !w.is_empty())
+            .collect()
+    };
+    let (said, source) = (words(text), words(output));
+    if said.len() < min_words || source.len() < min_words {
+        return false;
+    }
+    let shingles: HashSet<&[String]> = source.windows(min_words).collect();
+    said.windows(min_words).any(|w| shingles.contains(w))
+}
+
+/// One-line extractive summary of a tool output
+fn summarize(output: &str) -> String {
+    let output = output.trim();
+    match serde_json::from_str::<serde_json::Value>(output) {
+        Ok(serde_json::Value::Object(map)) => {
+            let keys: Vec<_> = map.keys().take(8).map(String::as_str).collect();
+            return format!("JSON object with keys {}", keys.join(", "));
+        }
+        Ok(serde_json::Value::Array(items)) => {
+            return format!("JSON array of {} items", items.len());
+        }
+        _ => {}
+    }
+    let line = output.lines().next().unwrap_or_default();
+    let sentence = line
+        .find(". ")
+        .map(|end| &line[..=end])
+        .unwrap_or(line)
+        .trim();
+    match sentence.char_indices().nth(SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}…", &sentence[..end]),
+        None => sentence.to_string(),
+    }
+}
+
+fn stub_text(tool: &str, call_id: &str, output: &str) -> String {
+    format!(
+        "[{} output elided: {} ({} bytes). Call {} with call_id \"{}\" for the full text.]",
+        tool,
+        summarize(output),
+        output.len(),
+        RECALL_TOOL_OUTPUT_TOOL,
+        call_id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(id: &str, name: &str) -> Message {
+        Message {
+            role: Role::Assistant,
+            content: Content::Parts(vec![ContentPart::ToolCall {
+                id: id.to_string(),
+                name: name.to_string(),
+                arguments: serde_json::json!({}),
+            }]),
+            name: None,
+            response_id: None,
+        }
+    }
+
+    fn result_text(message: &Message) -> &str {
+        match &message.content {
+            Content::Parts(parts) => match &parts[0] {
+                ContentPart::ToolResult { content, .. } => content,
+                _ => panic!("not a tool result"),
+            },
+            Content::Text(_) => panic!("not a tool result"),
+        }
+    }
+
+    #[test]
+    fn test_quoted_results_stay_verbatim() {
+        let quoted = "The SOL pool holds 1200 tokens of liquidity at a fee tier of thirty basis points today. "
+            .repeat(10);
+        let plain = "Block 4412 finalized. ".repeat(40);
+        let history = vec![
+            Message::user("check"),
+            call("c1", "pool_info"),
+            Message::tool_result("c1", quoted.clone()),
+            call("c2", "chain_head"),
+            Message::tool_result("c2", plain.clone()),
+            Message::assistant("It says the SOL pool holds 1200 tokens of liquidity at a fee tier of thirty basis points."),
+            Message::user("and now?"),
+            Message::assistant("Nothing changed."),
+        ];
+        let aging = ToolOutputAging::new(ToolAgingConfig::default().keep_recent(0));
+        let aged = aging.apply(&history, 100_000, |_| 0);
+
+        assert_eq!(result_text(&aged[2]), quoted);
+        let stub = result_text(&aged[4]);
+        assert!(stub.starts_with("[chain_head output elided: Block 4412 finalized."));
+        assert!(stub.contains("call_id \"c2\""));
+        assert_eq!(aging.archive().get("c2").as_deref(), Some(plain.as_str()));
+        assert_eq!(aging.archive().get("c1"), None);
+    }
+}
@@ -0,0 +1,481 @@
+//! Conversation-aware tool visibility
+//!
+//! A [`ToolRouter`] is consulted before every provider call and decides which
+//! tools the model is shown, both in `ChatRequest::tools` and in the injected
+//! tool catalog. Calls to a tool the router hides are rejected with
+//! [`Error::ToolHidden`] even if the model names it anyway. Hiding a tool never
+//! rewrites history, so earlier calls and their results stay paired.
+//!
+//! [`RuleRouter`] is a serde-configurable router built from ordered rules:
+//!
+//! ```json
+//! {
+//!   "rules": [
+//!     { "name": "onboarding", "tags": ["onboarding"], "visibility": { "only": ["profile_*"] } },
+//!     { "message": "(?i)\\b(buy|sell)\\b", "visibility": { "only": ["market_*", "risk_*"] } }
+//!   ],
+//!   "default": "all"
+//! }
+//! ```
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::events::wildcard_match;
+use crate::agent::message::{Content, ContentPart, Message, Role};
+use crate::error::{Error, Result};
+
+/// Number of trailing messages scanned for recently used tools
+const RECENT_TOOL_WINDOW: usize = 10;
+
+/// Which tools the model may see; names may use `*` wildcards
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolVisibility {
+    /// Every tool
+    #[default]
+    All,
+    /// Only the listed tools
+    Only(Vec<String>),
+    /// Every tool except the listed ones
+    Except(Vec<String>),
+}
+
+impl ToolVisibility {
+    /// Whether `tool` is visible
+    pub fn allows(&self, tool: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Only(names) => names.iter().any(|p| wildcard_match(p, tool)),
+            Self::Except(names) => !names.iter().any(|p| wildcard_match(p, tool)),
+        }
+    }
+}
+
+/// What a router can see about the conversation
+#[derive(Debug, Clone, Default)]
+pub struct RoutingContext {
+    /// Text of the latest user message
+    pub last_user_message: Option<String>,
+    /// Current workflow state, if the caller tracks one
+    pub state: Option<String>,
+    /// Tags on the session
+    pub tags: Vec<String>,
+    /// Tools called in the last few messages, most recent first
+    pub recent_tools: Vec<String>,
+}
+
+impl RoutingContext {
+    /// Build a context from conversation history
+    pub fn from_history(messages: &[Message], state: Option<String>, tags: Vec<String>) -> Self {
+        let last_user_message = messages
+            .iter()
+            .rev()
+            .find(|m| m.role == Role::User)
+            .map(|m| m.content.as_text());
+
+        let mut recent_tools: Vec<String> = Vec::new();
+        for message in messages.iter().rev().take(RECENT_TOOL_WINDOW) {
+            if let Content::Parts(parts) = &message.content {
+                for part in parts.iter().rev() {
+                    if let ContentPart::ToolCall { name, .. } = part {
+                        if !recent_tools.contains(name) {
+                            recent_tools.push(name.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        Self {
+            last_user_message,
+            state,
+            tags,
+            recent_tools,
+        }
+    }
+}
+
+/// Decides which tools the model sees at each step
+pub trait ToolRouter: Send + Sync {
+    /// Tools visible in `ctx`
+    fn visible_tools(&self, ctx: &RoutingContext) -> ToolVisibility;
+}
+
+/// One rule of a [`RuleRouter`]; every condition set must hold for it to match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutingRule {
+    /// Label for logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Regex the latest user message must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Tags the session must all carry
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Required workflow state
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    /// Tool (wildcards allowed) that must have been used recently
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recent_tool: Option<String>,
+    /// Visibility when the rule matches
+    pub visibility: ToolVisibility,
+}
+
+impl RoutingRule {
+    /// A rule that always matches, yielding `visibility`
+    pub fn new(visibility: ToolVisibility) -> Self {
+        Self {
+            visibility,
+            ..Default::default()
+        }
+    }
+
+    /// Set the label
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Require the latest user message to match `pattern`
+    pub fn message(mut self, pattern: impl Into<String>) -> Self {
+        self.message = Some(pattern.into());
+        self
+    }
+
+    /// Require a session tag
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Require a workflow state
+    pub fn state(mut self, state: impl Into<String>) -> Self {
+        self.state = Some(state.into());
+        self
+    }
+
+    /// Require a recently used tool
+    pub fn recent_tool(mut self, pattern: impl Into<String>) -> Self {
+        self.recent_tool = Some(pattern.into());
+        self
+    }
+}
+
+/// Serialized form of a [`RuleRouter`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuleRouterConfig {
+    /// Rules, first match wins
+    #[serde(default)]
+    pub rules: Vec<RoutingRule>,
+    /// Visibility when no rule matches
+    #[serde(default)]
+    pub default: ToolVisibility,
+}
+
+/// Router that applies the first matching rule
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "RuleRouterConfig")]
+pub struct RuleRouter {
+    config: RuleRouterConfig,
+    patterns: Vec<Option<Regex>>,
+}
+
+impl RuleRouter {
+    /// Build a router, compiling message patterns
+    pub fn new(config: RuleRouterConfig) -> Result<Self> {
+        let patterns = config
+            .rules
+            .iter()
+            .map(|rule| {
+                rule.message
+                    .as_deref()
+                    .map(|p| {
+                        Regex::new(p).map_err(|e| {
+                            Error::agent_config(format!("Invalid routing pattern '{}': {}", p, e))
+                        })
+                    })
+                    .transpose()
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { config, patterns })
+    }
+
+    /// The rules and default
+    pub fn config(&self) -> &RuleRouterConfig {
+        &self.config
+    }
+
+    fn matches(&self, index: usize, ctx: &RoutingContext) -> bool {
+        let rule = &self.config.rules[index];
+        if let Some(pattern) = &self.patterns[index] {
+            match &ctx.last_user_message {
+                Some(text) if pattern.is_match(text) => {}
+                _ => return false,
+            }
+        }
+        if !rule.tags.iter().all(|t| ctx.tags.contains(t)) {
+            return false;
+        }
+        if rule.state.is_some() && rule.state != ctx.state {
+            return false;
+        }
+        match &rule.recent_tool {
+            Some(p) => ctx.recent_tools.iter().any(|t| wildcard_match(p, t)),
+            None => true,
+        }
+    }
+}
+
+impl TryFrom<RuleRouterConfig> for RuleRouter {
+    type Error = Error;
+
+    fn try_from(config: RuleRouterConfig) -> Result<Self> {
+        Self::new(config)
+    }
+}
+
+impl Serialize for RuleRouter {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        self.config.serialize(serializer)
+    }
+}
+
+impl ToolRouter for RuleRouter {
+    fn visible_tools(&self, ctx: &RoutingContext) -> ToolVisibility {
+        for index in 0..self.config.rules.len() {
+            if self.matches(index, ctx) {
+                let rule = &self.config.rules[index];
+                tracing::debug!(
+                    rule = rule.name.as_deref().unwrap_or("unnamed"),
+                    "Tool routing rule matched"
+                );
+                return rule.visibility.clone();
+            }
+        }
+        self.config.default.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::core::Agent;
+    use crate::agent::provider::{ChatRequest, Provider};
+    use crate::agent::streaming::{MockStreamBuilder, StreamingResponse};
+    use crate::skills::tool::{Tool, ToolDefinition};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn router() -> RuleRouter {
+        serde_json::from_value(serde_json::json!({
+            "rules": [
+                { "name": "onboarding", "tags": ["onboarding"], "visibility": { "only": ["profile_*"] } },
+                { "message": "(?i)\\b(buy|sell)\\b", "visibility": { "only": ["market_*", "place_order"] } },
+                { "recent_tool": "market_*", "visibility": { "except": ["profile_*"] } }
+            ],
+            "default": { "only": ["market_*"] }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rule_ordering() {
+        let router = router();
+        let ctx = |msg: &str, tags: &[&str], history: Vec<Message>| {
+            let mut messages = history;
+            messages.push(Message::user(msg.to_string()));
+            RoutingContext::from_history(
+                &messages,
+                None,
+                tags.iter().map(|t| t.to_string()).collect(),
+            )
+        };
+
+        // The tag rule comes first, so it wins over the message rule
+        let v = router.visible_tools(&ctx("buy SOL", &["onboarding"], Vec::new()));
+        assert_eq!(v, ToolVisibility::Only(vec!["profile_*".into()]));
+        let v = router.visible_tools(&ctx("Buy SOL", &[], Vec::new()));
+        assert!(v.allows("place_order") && !v.allows("profile_update"));
+
+        let call = Message {
+            role: Role::Assistant,
+            name: None,
+            content: Content::Parts(vec![ContentPart::ToolCall {
+                id: "c1".into(),
+                name: "market_price".into(),
+                arguments: serde_json::json!({}),
+            }]),
+            response_id: None,
+        };
+        let v = router.visible_tools(&ctx("and ETH?", &[], vec![call]));
+        assert!(v.allows("place_order") && !v.allows("profile_update"));
+        assert_eq!(
+            router.visible_tools(&ctx("hello", &[], Vec::new())),
+            ToolVisibility::Only(vec!["market_*".into()])
+        );
+
+        assert!(serde_json::from_str::<RuleRouter>(
+            r#"{"rules":[{"message":"(","visibility":"all"}]}"#
+        )
+        .is_err());
+    }
+
+    /// What one provider request exposed: tool names, catalog tokens, latest tool result
+    type Seen = Arc<parking_lot::Mutex<Vec<(Vec<String>, usize, Option<String>)>>>;
+
+    /// Calls `tool` on a fresh user prompt and records each request
+    struct ScriptedProvider {
+        tool: &'static str,
+        seen: Seen,
+    }
+
+    #[async_trait]
+    impl Provider for ScriptedProvider {
+        async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+            let mut tools: Vec<String> = request.tools.iter().map(|t| t.name.clone()).collect();
+            tools.sort();
+            let bpe = tiktoken_rs::cl100k_base().unwrap();
+            let catalog_tokens = request
+                .messages
+                .iter()
+                .filter(|m| m.role == Role::System)
+                .map(|m| bpe.encode_with_special_tokens(&m.content.as_text()).len())
+                .sum();
+            let last = request.messages.last();
+            let tool_result = last.and_then(|m| match &m.content {
+                Content::Parts(parts) => parts.iter().find_map(|p| match p {
+                    ContentPart::ToolResult { content, .. } => Some(content.clone()),
+                    _ => None,
+                }),
+                _ => None,
+            });
+            self.seen.lock().push((tools, catalog_tokens, tool_result));
+
+            let builder = match last.map(|m| &m.role) {
+                Some(Role::User) => {
+                    MockStreamBuilder::new().tool_call("c1", self.tool, serde_json::json!({}))
+                }
+                _ => MockStreamBuilder::new().message("done"),
+            };
+            Ok(builder.done().build())
+        }
+
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+    }
+
+    struct NamedTool {
+        name: &'static str,
+        side_effect_free: bool,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for NamedTool {
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+
+        async fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: self.name(),
+                description: format!(
+                    "The {} tool, with a reasonably long description.",
+                    self.name
+                ),
+                parameters: serde_json::json!({ "type": "object" }),
+                parameters_ts: Some(format!("interface {}Args {{}}", self.name)),
+                is_verified: true,
+                side_effect_free: self.side_effect_free,
+                ..Default::default()
+            }
+        }
+
+        async fn call(&self, _: &str) -> anyhow::Result<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok("ok".to_string())
+        }
+    }
+
+    /// Run one "buy SOL" turn in which the model calls `tool`
+    async fn run(
+        tool: &'static str,
+        router: Option<RuleRouter>,
+        read_only: bool,
+        tags: Vec<String>,
+    ) -> (Vec<(Vec<String>, usize, Option<String>)>, usize) {
+        let seen = Seen::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut builder = Agent::builder(ScriptedProvider {
+            tool,
+            seen: Arc::clone(&seen),
+        })
+        .auto_load_skills(false)
+        .introspection(false)
+        .read_only(read_only);
+        for (name, side_effect_free) in [
+            ("profile_update", false),
+            ("market_price", true),
+            ("place_order", false),
+        ] {
+            builder = builder.tool(NamedTool {
+                name,
+                side_effect_free,
+                calls: Arc::clone(&calls),
+            });
+        }
+        if let Some(router) = router {
+            builder = builder.tool_router(router);
+        }
+        let agent = builder.build().unwrap();
+        agent.set_session_tags(tags);
+
+        agent.prompt("buy SOL").await.unwrap();
+        let seen = seen.lock().clone();
+        (seen, calls.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn test_hidden_tool_call_rejected_and_catalog_shrinks() {
+        let (unrouted, _) = run("market_price", None, false, Vec::new()).await;
+        assert_eq!(
+            unrouted[0].0,
+            ["market_price", "place_order", "profile_update"]
+        );
+
+        let (seen, calls) = run(
+            "place_order",
+            Some(router()),
+            false,
+            vec!["onboarding".into()],
+        )
+        .await;
+        assert_eq!(seen[0].0, ["profile_update"]);
+        assert!(seen[0].1 < unrouted[0].1);
+        assert_eq!(calls, 0);
+        let result = seen[1].2.as_deref().unwrap();
+        assert!(result.contains("not available"), "{}", result);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_composes_with_router() {
+        // Without a router, read-only keeps only side-effect-free tools
+        let (seen, calls) = run("place_order", None, true, Vec::new()).await;
+        assert_eq!(seen[0].0, ["market_price"]);
+        assert_eq!(calls, 0);
+        assert!(seen[1].2.as_deref().unwrap().contains("read-only"));
+
+        // The router shows place_order for "buy", read-only still removes it
+        let (seen, calls) = run("market_price", Some(router()), true, Vec::new()).await;
+        assert_eq!(seen[0].0, ["market_price"]);
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,98 @@
+//! Responses parsed into Rust types
+//!
+//! [`Agent::prompt_typed`] asks for JSON matching a type's schema and parses
+//! the reply. Models often wrap JSON in markdown fences or a sentence of
+//! prose, so [`parse_response`] looks past both before giving up.
+//!
+//! [`Agent::prompt_typed`]: crate::agent::Agent::prompt_typed
+
+use schemars::JsonSchema;
+use serde::de::{DeserializeOwned, IgnoredAny};
+
+use crate::skills::tool::args::args_schema;
+
+/// System prompt instruction asking for JSON matching `T`
+pub fn schema_instruction<T: JsonSchema>() -> String {
+    let schema = serde_json::to_string_pretty(&args_schema::<T>()).unwrap_or_default();
+    format!(
+        "Respond only with JSON matching this schema, without any other text:\n{}",
+        schema
+    )
+}
+
+/// Parse `text` as `T`, ignoring markdown code fences and prose around the JSON
+///
+/// On failure returns the error for the embedded JSON if there is some,
+/// otherwise for the text as given (fences stripped).
+pub fn parse_response<T: DeserializeOwned>(text: &str) -> Result<T, serde_json::Error> {
+    let text = strip_fences(text.trim());
+    let mut error = match serde_json::from_str(text) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+    // Fall back to the outermost object or array inside surrounding prose
+    for (open, close) in [('{', '}'), ('[', ']')] {
+        if let (Some(start), Some(end)) = (text.find(open), text.rfind(close)) {
+            if start < end {
+                let candidate = &text[start..=end];
+                match serde_json::from_str(candidate) {
+                    Ok(value) => return Ok(value),
+                    // Well-formed JSON of the wrong shape explains the failure best
+                    Err(e) if serde_json::from_str::<IgnoredAny>(candidate).is_ok() => error = e,
+                    Err(_) => {}
+                }
+            }
+        }
+    }
+    Err(error)
+}
+
+/// Contents of the first ``` fenced block, or `text` unchanged
+fn strip_fences(text: &str) -> &str {
+    let Some(start) = text.find("```") else {
+        return text;
+    };
+    let body = &text[start + 3..];
+    // Skip the info string (e.g. `json`) up to the end of the line
+    let body = body.split_once('\n').map_or(body, |(_, rest)| rest);
+    match body.find("```") {
+        Some(end) => body[..end].trim(),
+        None => body.trim(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, JsonSchema, PartialEq)]
+    struct Quote {
+        symbol: String,
+        price: f64,
+    }
+
+    #[test]
+    fn test_parse_response_looks_past_fences_and_prose() {
+        let expected = Quote {
+            symbol: "SOL".to_string(),
+            price: 150.0,
+        };
+        for text in [
+            r#"{"symbol": "SOL", "price": 150}"#,
+            "```json\n{\"symbol\": \"SOL\", \"price\": 150}\n```",
+            "Here you go:\n```\n{\"symbol\": \"SOL\", \"price\": 150}\n```\nAnything else?",
+            r#"Sure! {"symbol": "SOL", "price": 150} Hope that helps."#,
+        ] {
+            assert_eq!(parse_response::<Quote>(text).unwrap(), expected, "{}", text);
+        }
+
+        let error = parse_response::<Quote>(r#"{"symbol": "SOL"}"#).unwrap_err();
+        assert!(
+            error.to_string().contains("missing field `price`"),
+            "{}",
+            error
+        );
+        assert!(schema_instruction::<Quote>().contains("\"price\""));
+    }
+}
@@ -13,15 +13,43 @@ pub enum Error {
     #[error("Agent configuration error: {0}")]
     AgentConfig(String),
 
+    /// One or more configuration issues, reported together
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(crate::infra::validation::ConfigIssues),
+
     /// Agent execution failed
     #[error("Agent execution error: {0}")]
     AgentExecution(String),
 
+    /// The request's shared retry/time budget ran out; never retried
+    #[error("Request budget exhausted: out of {resource} in {layer}")]
+    BudgetExhausted {
+        /// What ran out: provider attempts, tool attempts or time
+        resource: String,
+        /// Layer that tried to spend it
+        layer: String,
+    },
+
     // ============ Provider Errors ============
     /// Provider API error
     #[error("Provider API error: {0}")]
     ProviderApi(String),
 
+    /// Error response from a provider's API, parsed from its error body
+    #[error("{provider} API error{}: {message}", describe_status(.status, .code))]
+    Provider {
+        /// Provider that answered
+        provider: String,
+        /// HTTP status of the response
+        status: Option<u16>,
+        /// Provider-specific error code or type (e.g. `rate_limit_error`)
+        code: Option<String>,
+        /// Error message from the body
+        message: String,
+        /// How long the provider asked us to wait before retrying
+        retry_after: Option<std::time::Duration>,
+    },
+
     /// Provider authentication failed
     #[error("Provider authentication error: {0}")]
     ProviderAuth(String),
@@ -33,11 +61,46 @@ pub enum Error {
         retry_after_secs: u64,
     },
 
+    /// Request shed by admission control because the provider is saturated
+    #[error("Provider overloaded: {priority} request shed, retry after {retry_after_secs}s")]
+    ProviderOverloaded {
+        /// Priority of the shed request
+        priority: String,
+        /// Suggested seconds to wait before retrying
+        retry_after_secs: u64,
+    },
+
     // ============ Tool Errors ============
     /// Tool not found in agent's toolset
     #[error("Tool not found: {0}")]
     ToolNotFound(String),
 
+    /// A tool with this name is already registered
+    #[error("Tool name already registered: {0}")]
+    ToolNameConflict(String),
+
+    /// A bare tool name matches tools in several namespaces
+    #[error("Tool name {name} is ambiguous, use one of: {}", candidates.join(", "))]
+    AmbiguousToolName {
+        /// Name as called
+        name: String,
+        /// Namespaced names it could refer to
+        candidates: Vec<String>,
+    },
+
+    /// Tool exists but is hidden by the agent's tool router at this point in the conversation
+    #[error("Tool not available in the current conversation state: {0}")]
+    ToolHidden(String),
+
+    /// Tool short-circuited by its circuit breaker
+    #[error("Tool temporarily unavailable: {tool_name} ({reason})")]
+    ToolUnavailable {
+        /// Name of the tool
+        tool_name: String,
+        /// Recent failures and when to retry, or why it was disabled
+        reason: String,
+    },
+
     /// Tool execution failed
     #[error("Tool execution error: {tool_name} - {message}")]
     ToolExecution {
@@ -47,6 +110,15 @@ pub enum Error {
         message: String,
     },
 
+    /// Tool ran past its time limit and was cancelled
+    #[error("Tool {tool_name} timed out after {}ms", elapsed.as_millis())]
+    ToolTimeout {
+        /// Name of the tool
+        tool_name: String,
+        /// How long the call ran before it was cancelled
+        elapsed: std::time::Duration,
+    },
+
     /// Tool approval required
     #[error("Tool execution blocked: {tool_name} requires approval but no handler was available")]
     ToolApprovalRequired {
@@ -54,6 +126,19 @@ pub enum Error {
         tool_name: String,
     },
 
+    /// Nobody answered an approval request before its deadline
+    #[error("Approval for {tool_name} timed out after {timeout_secs}s")]
+    ApprovalTimeout {
+        /// Name of the tool
+        tool_name: String,
+        /// How long the agent waited
+        timeout_secs: u64,
+    },
+
+    /// A run or tool call was cancelled through its cancellation token
+    #[error("Cancelled")]
+    Cancelled,
+
     /// Invalid tool arguments
     #[error("Invalid tool arguments for {tool_name}: {message}")]
     ToolArguments {
@@ -72,6 +157,20 @@ pub enum Error {
     #[error("Message serialization error: {0}")]
     MessageSerialize(#[from] serde_json::Error),
 
+    /// File could not be attached to a message
+    #[error("Attachment rejected: {0}")]
+    Attachment(String),
+
+    /// Model output did not match the requested schema, even after retries
+    #[error("Response does not match the requested schema: {error}")]
+    SchemaValidation {
+        /// Last response text from the model
+        raw: String,
+        /// Why parsing it failed
+        #[source]
+        error: serde_json::Error,
+    },
+
     // ============ Streaming Errors ============
     /// Stream interrupted
     #[error("Stream interrupted: {0}")]
@@ -147,16 +246,83 @@ pub enum Error {
     #[error("Agent communication error: {0}")]
     AgentCommunication(String),
 
+    /// Durable job queue could not be read or written
+    #[error("Job queue error: {0}")]
+    JobQueue(String),
+
     // ============ Network Errors ============
     /// HTTP request failed
     #[error("HTTP error: {0}")]
-    Http(#[from] reqwest::Error),
+    Http(reqwest::Error),
+
+    /// Request timed out or could not connect, so no response was received
+    #[error("Transport error: {0}")]
+    Transport(#[source] reqwest::Error),
+
+    /// Outbound request to a host outside the egress allowlist
+    #[error("Outbound request to '{host}' blocked: not in the allowed hosts [{}]; add it to allowed_hosts to permit it", .allowlist.join(", "))]
+    EgressDenied {
+        /// Host that was refused
+        host: String,
+        /// Allowed host patterns at the time
+        allowlist: Vec<String>,
+    },
+
+    /// A per-invocation egress byte or request budget ran out
+    #[error("Egress budget exhausted: {0}")]
+    EgressBudgetExceeded(String),
 
     // ============ System Errors ============
     /// IO error
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    /// Encryption or decryption failed
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    /// A required secret could not be resolved
+    #[error("Secret not available: {0}")]
+    SecretNotFound(String),
+
+    /// Another instance holds the data directory
+    #[error("Data directory is locked by another instance: {holder}")]
+    InstanceLocked {
+        /// Description of the current holder
+        holder: String,
+    },
+
+    /// A writer-only duty was attempted on a read-only instance
+    #[error("Read-only instance cannot perform writer duty: {0}")]
+    InstanceReadOnly(String),
+
+    /// This instance's lock was taken over by a newer holder
+    #[error("Instance fenced: token {token} superseded by {current}")]
+    InstanceFenced {
+        /// Token this instance acquired
+        token: u64,
+        /// Token currently in the lock file
+        current: u64,
+    },
+
+    /// Too few users behind the data for a k-anonymous export
+    #[error("Refusing anonymized export: {distinct_users} distinct users is below k = {k}")]
+    PrivacyThreshold {
+        /// Distinct users in the requested range
+        distinct_users: usize,
+        /// Configured k
+        k: usize,
+    },
+
+    /// Feedback targeted a response that doesn't exist in the session
+    #[error("Response {response} not found in session {session_id}")]
+    ResponseNotFound {
+        /// Session the feedback was for
+        session_id: String,
+        /// Response ID or message index that was given
+        response: String,
+    },
+
     // ============ Generic Errors ============
     /// Internal error
     #[error("Internal error: {0}")]
@@ -167,6 +333,26 @@ pub enum Error {
     Other(AnyhowError),
 }
 
+/// Timeouts and connection failures become [`Error::Transport`]
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() || error.is_connect() {
+            Self::Transport(error)
+        } else {
+            Self::Http(error)
+        }
+    }
+}
+
+fn describe_status(status: &Option<u16>, code: &Option<String>) -> String {
+    match (status, code) {
+        (Some(status), Some(code)) => format!(" {} ({})", status, code),
+        (Some(status), None) => format!(" {}", status),
+        (None, Some(code)) => format!(" ({})", code),
+        (None, None) => String::new(),
+    }
+}
+
 impl Error {
     /// Create a new agent configuration error
     pub fn agent_config(msg: impl Into<String>) -> Self {
@@ -190,14 +376,83 @@ impl Error {
         }
     }
 
-    /// Check if this error is retryable
+    /// Whether the provider rejected the request for exceeding the model's context window
+    pub fn is_context_overflow(&self) -> bool {
+        let message = match self {
+            Self::Provider { code: Some(code), .. } if code == "context_length_exceeded" => {
+                return true
+            }
+            Self::Provider { message, .. } | Self::ProviderApi(message) => message.to_lowercase(),
+            _ => return false,
+        };
+        ["context_length_exceeded", "context length", "context window", "prompt is too long"]
+            .iter()
+            .any(|needle| message.contains(needle))
+    }
+
+    /// HTTP status of a provider error response
+    pub fn provider_status(&self) -> Option<u16> {
+        match self {
+            Self::Provider { status, .. } => *status,
+            _ => None,
+        }
+    }
+
+    /// How long the provider asked us to wait before retrying, if it said
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            Self::Provider { retry_after, .. } => *retry_after,
+            Self::ProviderRateLimit { retry_after_secs }
+            | Self::ProviderOverloaded { retry_after_secs, .. } => {
+                Some(std::time::Duration::from_secs(*retry_after_secs))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the provider rejected the key: 401/402/403, or a 429 for an exhausted quota
+    pub fn is_auth_failure(&self) -> bool {
+        match self {
+            Self::ProviderAuth(_) => true,
+            Self::Provider { status, code, .. } => {
+                matches!(status, Some(401..=403))
+                    || code.as_deref().is_some_and(|code| {
+                        matches!(
+                            code,
+                            "invalid_api_key"
+                                | "insufficient_quota"
+                                | "authentication_error"
+                                | "permission_error"
+                                | "API_KEY_INVALID"
+                                | "PERMISSION_DENIED"
+                                | "UNAUTHENTICATED"
+                        )
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the provider rejected the request itself (400/404/413/422), so resending it can't help
+    pub fn is_invalid_request(&self) -> bool {
+        matches!(self.provider_status(), Some(400 | 404 | 413 | 422)) && !self.is_auth_failure()
+    }
+
+    /// Check if this error is retryable: rate limits, 408/5xx responses, timeouts and dropped connections
     pub fn is_retryable(&self) -> bool {
-        matches!(
-            self,
-            Self::ProviderRateLimit { .. }
-                | Self::StreamInterrupted(_)
-                | Self::StreamTimeout { .. }
-                | Self::Http(_)
-        )
+        match self {
+            Self::Provider { status, .. } => {
+                matches!(status, Some(408 | 429 | 500..=599)) && !self.is_auth_failure()
+            }
+            _ => matches!(
+                self,
+                Self::ProviderRateLimit { .. }
+                    | Self::ProviderOverloaded { .. }
+                    | Self::StreamInterrupted(_)
+                    | Self::StreamTimeout { .. }
+                    | Self::Http(_)
+                    | Self::Transport(_)
+            ),
+        }
     }
 }
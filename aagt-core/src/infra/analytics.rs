@@ -0,0 +1,595 @@
+//! Tool usage analytics with a privacy-preserving export
+//!
+//! [`ToolAnalytics`] keeps raw per-call records for local use. Reports shared
+//! outside the process go through [`ToolAnalytics::export_anonymized`], and only
+//! the following survives into an [`AnonymizedReport`]:
+//!
+//! - report period, `k` and `epsilon`
+//! - per tool: call count, failure count and rate, token total, argument key names
+//! - per topic (record tags such as collections): call count
+//! - per activity cohort: call count and salted user pseudonyms
+//!
+//! User ids, argument values and prompt text never leave. Every aggregate backed by
+//! fewer than `k` distinct users is dropped, counts get optional Laplace noise, and
+//! pseudonyms use a salt that changes every `salt_period`, so they join within a
+//! period but not across periods.
+
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::{Mutex, RwLock};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Range;
+
+use crate::error::{Error, Result};
+
+/// Max characters kept per argument value in a record sketch
+const SKETCH_VALUE_CHARS: usize = 32;
+
+/// Activity cohorts as (label, minimum calls), highest first
+const COHORTS: &[(&str, u64)] = &[
+    ("heavy (20+ calls)", 20),
+    ("regular (5-19 calls)", 5),
+    ("light (1-4 calls)", 1),
+];
+
+/// One tool call as recorded locally
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    /// Caller
+    pub user_id: String,
+    /// Tool name
+    pub tool: String,
+    /// Argument sketch: key -> truncated value
+    pub arguments: BTreeMap<String, String>,
+    /// Whether the call succeeded
+    pub success: bool,
+    /// Topic tags (collections, categories); never prompt text
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Tokens spent on the turn that made the call
+    #[serde(default)]
+    pub tokens: u64,
+    /// When the call happened
+    pub at: DateTime<Utc>,
+}
+
+impl ToolCallRecord {
+    /// Record a call now, sketching the top-level JSON arguments
+    pub fn new(
+        user_id: impl Into<String>,
+        tool: impl Into<String>,
+        arguments: &serde_json::Value,
+        success: bool,
+    ) -> Self {
+        let arguments = arguments
+            .as_object()
+            .map(|map| {
+                map.iter()
+                    .map(|(k, v)| {
+                        let text = match v {
+                            serde_json::Value::String(s) => s.clone(),
+                            other => other.to_string(),
+                        };
+                        (k.clone(), text.chars().take(SKETCH_VALUE_CHARS).collect())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            user_id: user_id.into(),
+            tool: tool.into(),
+            arguments,
+            success,
+            tags: Vec::new(),
+            tokens: 0,
+            at: Utc::now(),
+        }
+    }
+
+    /// Attach topic tags
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Attach token usage
+    pub fn with_tokens(mut self, tokens: u64) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
+    /// Override the timestamp
+    pub fn at(mut self, at: DateTime<Utc>) -> Self {
+        self.at = at;
+        self
+    }
+}
+
+/// Privacy settings for [`ToolAnalytics::export_anonymized`]
+#[derive(Debug, Clone)]
+pub struct PrivacyConfig {
+    /// Minimum distinct users behind any exported aggregate
+    pub k: usize,
+    /// Laplace noise budget for counts; `None` exports exact counts
+    pub epsilon: Option<f64>,
+    /// How long one pseudonym salt stays valid
+    pub salt_period: Duration,
+    /// Fixed noise seed for reproducible exports (tests, audits)
+    pub noise_seed: Option<u64>,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            k: 5,
+            epsilon: Some(1.0),
+            salt_period: Duration::days(1),
+            noise_seed: None,
+        }
+    }
+}
+
+/// Usage of one tool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolAggregate {
+    /// Tool name
+    pub tool: String,
+    /// Calls (noised)
+    pub calls: u64,
+    /// Failed calls (noised)
+    pub failures: u64,
+    /// `failures / calls`
+    pub failure_rate: f64,
+    /// Tokens spent on turns using the tool
+    pub tokens: u64,
+    /// Argument names seen, without values
+    pub argument_keys: Vec<String>,
+}
+
+/// Calls per topic tag
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopicAggregate {
+    /// Tag or collection
+    pub topic: String,
+    /// Calls (noised)
+    pub calls: u64,
+}
+
+/// Users grouped by activity level
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CohortAggregate {
+    /// Activity bucket
+    pub cohort: String,
+    /// Salted pseudonyms of the members
+    pub members: Vec<String>,
+    /// Calls by the cohort (noised)
+    pub calls: u64,
+}
+
+/// Usage report that is safe to share across teams
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnonymizedReport {
+    /// Start of the covered range (inclusive)
+    pub period_start: DateTime<Utc>,
+    /// End of the covered range (exclusive)
+    pub period_end: DateTime<Utc>,
+    /// k-anonymity threshold applied
+    pub k: usize,
+    /// Noise budget applied, if any
+    pub epsilon: Option<f64>,
+    /// All calls in the range (noised)
+    pub total_calls: u64,
+    /// Per-tool usage
+    pub tools: Vec<ToolAggregate>,
+    /// Per-topic usage
+    pub topics: Vec<TopicAggregate>,
+    /// Per-cohort usage
+    pub cohorts: Vec<CohortAggregate>,
+    /// Aggregates dropped for having fewer than `k` users
+    pub suppressed: usize,
+}
+
+impl AnonymizedReport {
+    /// Render as pretty JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(Error::from)
+    }
+
+    /// Render as a markdown summary
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# Tool usage {} to {}\n\nk = {}, epsilon = {}, {} calls, {} aggregates suppressed\n\n",
+            self.period_start.format("%Y-%m-%d %H:%M"),
+            self.period_end.format("%Y-%m-%d %H:%M"),
+            self.k,
+            self.epsilon.map_or("none".to_string(), |e| e.to_string()),
+            self.total_calls,
+            self.suppressed
+        );
+        out.push_str("## Tools\n\n| Tool | Calls | Failures | Failure rate | Tokens | Arguments |\n|---|---|---|---|---|---|\n");
+        for t in &self.tools {
+            out.push_str(&format!(
+                "| {} | {} | {} | {:.1}% | {} | {} |\n",
+                t.tool,
+                t.calls,
+                t.failures,
+                t.failure_rate * 100.0,
+                t.tokens,
+                t.argument_keys.join(", ")
+            ));
+        }
+        out.push_str("\n## Topics\n\n| Topic | Calls |\n|---|---|\n");
+        for t in &self.topics {
+            out.push_str(&format!("| {} | {} |\n", t.topic, t.calls));
+        }
+        out.push_str("\n## Cohorts\n\n| Cohort | Users | Calls |\n|---|---|---|\n");
+        for c in &self.cohorts {
+            out.push_str(&format!(
+                "| {} | {} | {} |\n",
+                c.cohort,
+                c.members.len(),
+                c.calls
+            ));
+        }
+        out
+    }
+}
+
+/// In-process store of tool call records
+#[derive(Default)]
+pub struct ToolAnalytics {
+    records: RwLock<Vec<ToolCallRecord>>,
+    /// Pseudonym salt per period index; never persisted or exported
+    salts: Mutex<HashMap<i64, [u8; 32]>>,
+}
+
+impl ToolAnalytics {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a record
+    pub fn record(&self, record: ToolCallRecord) {
+        self.records.write().push(record);
+    }
+
+    /// Raw records in `range` (contains user ids; keep local)
+    pub fn records(&self, range: Range<DateTime<Utc>>) -> Vec<ToolCallRecord> {
+        self.records
+            .read()
+            .iter()
+            .filter(|r| range.contains(&r.at))
+            .cloned()
+            .collect()
+    }
+
+    /// Build a shareable report for `range`
+    ///
+    /// Fails when fewer than `k` distinct users are in range.
+    pub fn export_anonymized(
+        &self,
+        range: Range<DateTime<Utc>>,
+        config: &PrivacyConfig,
+    ) -> Result<AnonymizedReport> {
+        if config.k == 0 {
+            return Err(Error::agent_config("k must be at least 1"));
+        }
+        if matches!(config.epsilon, Some(e) if !(e.is_finite() && e > 0.0)) {
+            return Err(Error::agent_config("epsilon must be positive"));
+        }
+
+        let records = self.records(range.clone());
+        let distinct_users = records
+            .iter()
+            .map(|r| r.user_id.as_str())
+            .collect::<BTreeSet<_>>()
+            .len();
+        if distinct_users < config.k {
+            return Err(Error::PrivacyThreshold {
+                distinct_users,
+                k: config.k,
+            });
+        }
+
+        let mut noise = Noise::new(config);
+        let mut suppressed = 0;
+
+        let mut by_tool: BTreeMap<&str, Vec<&ToolCallRecord>> = BTreeMap::new();
+        let mut by_topic: BTreeMap<&str, Vec<&ToolCallRecord>> = BTreeMap::new();
+        let mut by_user: BTreeMap<&str, u64> = BTreeMap::new();
+        for record in &records {
+            by_tool.entry(&record.tool).or_default().push(record);
+            for tag in &record.tags {
+                by_topic.entry(tag).or_default().push(record);
+            }
+            *by_user.entry(&record.user_id).or_default() += 1;
+        }
+
+        let mut tools = Vec::new();
+        for (tool, calls) in by_tool {
+            if distinct(&calls) < config.k {
+                suppressed += 1;
+                continue;
+            }
+            let total = noise.apply(calls.len() as u64);
+            let failures = noise
+                .apply(calls.iter().filter(|r| !r.success).count() as u64)
+                .min(total);
+            let argument_keys: BTreeSet<&String> =
+                calls.iter().flat_map(|r| r.arguments.keys()).collect();
+            tools.push(ToolAggregate {
+                tool: tool.to_string(),
+                calls: total,
+                failures,
+                failure_rate: if total == 0 {
+                    0.0
+                } else {
+                    failures as f64 / total as f64
+                },
+                tokens: calls.iter().map(|r| r.tokens).sum(),
+                argument_keys: argument_keys.into_iter().cloned().collect(),
+            });
+        }
+
+        let mut topics = Vec::new();
+        for (topic, calls) in by_topic {
+            if distinct(&calls) < config.k {
+                suppressed += 1;
+                continue;
+            }
+            topics.push(TopicAggregate {
+                topic: topic.to_string(),
+                calls: noise.apply(calls.len() as u64),
+            });
+        }
+
+        let salt = self.salt(range.start, config.salt_period);
+        let mut cohorts = Vec::new();
+        for (i, (label, min_calls)) in COHORTS.iter().enumerate() {
+            let max_calls = i.checked_sub(1).map(|prev| COHORTS[prev].1);
+            let members: Vec<(&str, u64)> = by_user
+                .iter()
+                .filter(|(_, calls)| {
+                    **calls >= *min_calls && max_calls.is_none_or(|max| **calls < max)
+                })
+                .map(|(user, calls)| (*user, *calls))
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            if members.len() < config.k {
+                suppressed += 1;
+                continue;
+            }
+            let mut pseudonyms: Vec<String> = members
+                .iter()
+                .map(|(user, _)| pseudonym(&salt, user))
+                .collect();
+            pseudonyms.sort();
+            cohorts.push(CohortAggregate {
+                cohort: label.to_string(),
+                members: pseudonyms,
+                calls: noise.apply(members.iter().map(|(_, calls)| calls).sum()),
+            });
+        }
+
+        Ok(AnonymizedReport {
+            period_start: range.start,
+            period_end: range.end,
+            k: config.k,
+            epsilon: config.epsilon,
+            total_calls: noise.apply(records.len() as u64),
+            tools,
+            topics,
+            cohorts,
+            suppressed,
+        })
+    }
+
+    /// Salt for the period containing `at`, created on first use
+    fn salt(&self, at: DateTime<Utc>, period: Duration) -> [u8; 32] {
+        let period_secs = period.num_seconds().max(1);
+        let index = at.timestamp().div_euclid(period_secs);
+        *self.salts.lock().entry(index).or_insert_with(rand::random)
+    }
+}
+
+fn distinct(records: &[&ToolCallRecord]) -> usize {
+    records
+        .iter()
+        .map(|r| r.user_id.as_str())
+        .collect::<BTreeSet<_>>()
+        .len()
+}
+
+fn pseudonym(salt: &[u8; 32], user_id: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(user_id.as_bytes());
+    hasher.finalize()[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Laplace mechanism with sensitivity 1
+struct Noise {
+    scale: Option<f64>,
+    rng: StdRng,
+}
+
+impl Noise {
+    fn new(config: &PrivacyConfig) -> Self {
+        Self {
+            scale: config.epsilon.map(|e| 1.0 / e),
+            rng: config
+                .noise_seed
+                .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64),
+        }
+    }
+
+    fn apply(&mut self, count: u64) -> u64 {
+        let Some(scale) = self.scale else {
+            return count;
+        };
+        // Inverse CDF sampling of Laplace(0, scale)
+        let u: f64 = self.rng.gen_range(-0.5..0.5);
+        let sample = -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln();
+        (count as f64 + sample).round().max(0.0) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-05-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn exact(k: usize) -> PrivacyConfig {
+        PrivacyConfig {
+            k,
+            epsilon: None,
+            ..Default::default()
+        }
+    }
+
+    fn call(analytics: &ToolAnalytics, user: &str, tool: &str, at: DateTime<Utc>) {
+        analytics.record(
+            ToolCallRecord::new(user, tool, &serde_json::json!({ "symbol": "SOL" }), true)
+                .with_tags(vec!["markets".to_string()])
+                .at(at),
+        );
+    }
+
+    #[test]
+    fn test_suppression_below_k() {
+        let analytics = ToolAnalytics::new();
+        let at = start() + Duration::hours(1);
+        for i in 0..5 {
+            call(&analytics, &format!("user-{}", i), "get_price", at);
+        }
+        for i in 0..3 {
+            call(&analytics, &format!("user-{}", i), "swap", at);
+        }
+        let range = start()..start() + Duration::days(1);
+
+        let report = analytics
+            .export_anonymized(range.clone(), &exact(5))
+            .unwrap();
+        let tools: Vec<_> = report.tools.iter().map(|t| t.tool.as_str()).collect();
+        assert_eq!(tools, vec!["get_price"]);
+        assert_eq!(report.tools[0].calls, 5);
+        assert_eq!(report.topics[0].calls, 8);
+        assert_eq!(report.suppressed, 1);
+
+        // Too few users overall: refuse to export
+        let err = analytics.export_anonymized(range, &exact(6)).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::PrivacyThreshold {
+                distinct_users: 5,
+                k: 6
+            }
+        ));
+    }
+
+    #[test]
+    fn test_noise_bounds() {
+        let analytics = ToolAnalytics::new();
+        let at = start() + Duration::hours(1);
+        for i in 0..50 {
+            call(&analytics, &format!("user-{}", i % 10), "get_price", at);
+        }
+        let range = start()..start() + Duration::days(1);
+
+        let mut changed = false;
+        for seed in 0..20 {
+            let config = PrivacyConfig {
+                k: 5,
+                epsilon: Some(1.0),
+                noise_seed: Some(seed),
+                ..Default::default()
+            };
+            let report = analytics.export_anonymized(range.clone(), &config).unwrap();
+            let tool = &report.tools[0];
+            // P(|Laplace(0, 1)| > 15) is about 3e-7
+            assert!(tool.calls.abs_diff(50) <= 15, "calls {}", tool.calls);
+            assert!(tool.failures <= tool.calls);
+            changed |= tool.calls != 50 || report.total_calls != 50;
+
+            // The same seed reproduces the same report
+            assert_eq!(
+                report,
+                analytics.export_anonymized(range.clone(), &config).unwrap()
+            );
+        }
+        assert!(changed);
+    }
+
+    #[test]
+    fn test_salt_rotation_breaks_cross_period_joins() {
+        let analytics = ToolAnalytics::new();
+        for day in 0..2 {
+            let at = start() + Duration::days(day) + Duration::hours(1);
+            for i in 0..5 {
+                call(&analytics, &format!("user-{}", i), "get_price", at);
+            }
+        }
+        let members = |from: DateTime<Utc>, hours: i64| {
+            let report = analytics
+                .export_anonymized(from..from + Duration::hours(hours), &exact(5))
+                .unwrap();
+            report.cohorts[0].members.clone()
+        };
+
+        let day1 = members(start(), 24);
+        assert_eq!(day1.len(), 5);
+        // Another report within the same period joins on the same pseudonyms
+        assert_eq!(members(start() + Duration::minutes(30), 12), day1);
+
+        let day2 = members(start() + Duration::days(1), 24);
+        assert_eq!(day2.len(), 5);
+        assert!(day1.iter().all(|p| !day2.contains(p)));
+    }
+
+    #[test]
+    fn test_no_raw_identifiers_in_output() {
+        let analytics = ToolAnalytics::new();
+        let at = start() + Duration::hours(1);
+        for i in 0..6 {
+            analytics.record(
+                ToolCallRecord::new(
+                    format!("alice{}@example.com", i),
+                    "transfer",
+                    &serde_json::json!({ "to": "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin", "memo": "rent for flat 4B" }),
+                    i % 2 == 0,
+                )
+                .with_tags(vec!["payments".to_string()])
+                .at(at),
+            );
+        }
+        let report = analytics
+            .export_anonymized(
+                start()..start() + Duration::days(1),
+                &PrivacyConfig::default(),
+            )
+            .unwrap();
+
+        for rendered in [report.to_json().unwrap(), report.to_markdown()] {
+            assert!(!rendered.contains("alice"), "{}", rendered);
+            assert!(!rendered.contains("example.com"));
+            assert!(!rendered.contains("9xQeWvG8"));
+            assert!(!rendered.contains("rent"));
+            assert!(rendered.contains("memo"));
+            assert!(rendered.contains("payments"));
+        }
+    }
+}
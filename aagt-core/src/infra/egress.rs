@@ -0,0 +1,809 @@
+//! Outbound network policy for skills and tools
+//!
+//! A [`HostAllowlist`] holds exact hosts (`api.example.com`) and wildcard
+//! subdomains (`*.example.com`, which does not match `example.com` itself).
+//! Native tools send through an [`AllowlistedClient`]; sandboxed skills keep
+//! `--unshare-net` and reach the network only through an [`EgressProxy`] on a
+//! unix socket bound into the sandbox. Both check every host, count requests
+//! and bytes against an [`EgressBudget`], and log denials.
+//!
+//! A denied request fails with [`Error::EgressDenied`], naming the host and
+//! the allowlist, so the operator can extend it deliberately.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+use tracing::warn;
+
+use crate::error::{Error, Result};
+
+/// Hosts outbound requests may reach
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostAllowlist {
+    hosts: Vec<String>,
+}
+
+impl HostAllowlist {
+    /// Allow exact hosts and `*.domain` wildcards
+    pub fn new<S: AsRef<str>>(hosts: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            hosts: hosts
+                .into_iter()
+                .map(|h| h.as_ref().trim().trim_end_matches('.').to_ascii_lowercase())
+                .filter(|h| !h.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Allowed host patterns
+    pub fn hosts(&self) -> &[String] {
+        &self.hosts
+    }
+
+    /// Whether no host is allowed
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+
+    /// Whether `host` matches an entry
+    pub fn allows(&self, host: &str) -> bool {
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .trim_end_matches('.')
+            .to_ascii_lowercase();
+        self.hosts
+            .iter()
+            .any(|pattern| match pattern.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+                None => *pattern == host,
+            })
+    }
+
+    /// `Ok` if `host` is allowed, else [`Error::EgressDenied`]
+    pub fn check(&self, host: &str) -> Result<()> {
+        if self.allows(host) {
+            return Ok(());
+        }
+        warn!(host, allowlist = ?self.hosts, "Outbound request denied");
+        Err(Error::EgressDenied {
+            host: host.to_string(),
+            allowlist: self.hosts.clone(),
+        })
+    }
+}
+
+/// Invalid allowlist entries, e.g. URLs or inner wildcards
+pub(crate) fn invalid_hosts(hosts: &[String]) -> Vec<&str> {
+    hosts
+        .iter()
+        .map(String::as_str)
+        .filter(|h| {
+            let name = h.strip_prefix("*.").unwrap_or(h);
+            name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':'))
+        })
+        .collect()
+}
+
+/// Per-invocation limits on outbound traffic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EgressBudget {
+    /// Bytes sent plus received; `None` is unlimited
+    pub max_bytes: Option<u64>,
+    /// Requests or tunnels opened; `None` is unlimited
+    pub max_requests: Option<u64>,
+}
+
+impl Default for EgressBudget {
+    fn default() -> Self {
+        Self {
+            max_bytes: Some(10 * 1024 * 1024),
+            max_requests: Some(100),
+        }
+    }
+}
+
+impl EgressBudget {
+    /// No limits
+    pub fn unlimited() -> Self {
+        Self {
+            max_bytes: None,
+            max_requests: None,
+        }
+    }
+}
+
+/// Outbound traffic counted so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct EgressStats {
+    /// Requests admitted
+    pub requests: u64,
+    /// Bytes sent plus received
+    pub bytes: u64,
+    /// Requests refused by the allowlist
+    pub denied: u64,
+}
+
+/// Allowlist plus budget accounting, shared by a client or proxy
+#[derive(Debug, Default)]
+struct Meter {
+    allowlist: HostAllowlist,
+    budget: EgressBudget,
+    requests: AtomicU64,
+    bytes: AtomicU64,
+    denied: AtomicU64,
+}
+
+impl Meter {
+    fn new(allowlist: HostAllowlist, budget: EgressBudget) -> Self {
+        Self {
+            allowlist,
+            budget,
+            ..Default::default()
+        }
+    }
+
+    /// Check the host and count a request
+    fn admit(&self, host: &str) -> Result<()> {
+        if let Err(e) = self.allowlist.check(host) {
+            self.denied.fetch_add(1, Ordering::Relaxed);
+            return Err(e);
+        }
+        let requests = self.requests.fetch_add(1, Ordering::Relaxed) + 1;
+        match self.budget.max_requests {
+            Some(max) if requests > max => Err(Error::EgressBudgetExceeded(format!(
+                "request to '{}' would exceed {} requests",
+                host, max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Count transferred bytes
+    fn consume(&self, bytes: usize) -> Result<()> {
+        let total = self.bytes.fetch_add(bytes as u64, Ordering::Relaxed) + bytes as u64;
+        match self.budget.max_bytes {
+            Some(max) if total > max => Err(Error::EgressBudgetExceeded(format!(
+                "more than {} bytes transferred",
+                max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn stats(&self) -> EgressStats {
+        EgressStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            denied: self.denied.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A fully read response from an [`AllowlistedClient`]
+#[derive(Debug, Clone)]
+pub struct EgressResponse {
+    /// HTTP status
+    pub status: reqwest::StatusCode,
+    /// Response headers
+    pub headers: reqwest::header::HeaderMap,
+    /// Body, within the byte budget
+    pub body: Vec<u8>,
+}
+
+impl EgressResponse {
+    /// Body as (lossy) UTF-8
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// Body parsed as JSON
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+/// reqwest client that only reaches allowlisted hosts, within a budget
+///
+/// Native tools that call out should send through this rather than a bare
+/// `reqwest::Client`. Redirects are followed only to allowlisted hosts.
+#[derive(Debug, Clone)]
+pub struct AllowlistedClient {
+    client: reqwest::Client,
+    meter: Arc<Meter>,
+}
+
+impl AllowlistedClient {
+    /// Client for `allowlist` with the default [`EgressBudget`]
+    pub fn new(allowlist: HostAllowlist) -> Result<Self> {
+        Self::with_budget(allowlist, EgressBudget::default())
+    }
+
+    /// Client for `allowlist` with `budget` over its lifetime
+    pub fn with_budget(allowlist: HostAllowlist, budget: EgressBudget) -> Result<Self> {
+        let redirects = allowlist.clone();
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+                let host = attempt.url().host_str().unwrap_or_default().to_string();
+                if attempt.previous().len() >= 10 {
+                    attempt.error("too many redirects")
+                } else if let Err(e) = redirects.check(&host) {
+                    attempt.error(e)
+                } else {
+                    attempt.follow()
+                }
+            }))
+            .build()?;
+        Ok(Self {
+            client,
+            meter: Arc::new(Meter::new(allowlist, budget)),
+        })
+    }
+
+    /// Start a request; send it with [`AllowlistedClient::execute`]
+    pub fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    /// GET `url`
+    pub async fn get(&self, url: &str) -> Result<EgressResponse> {
+        self.execute(self.client.get(url).build()?).await
+    }
+
+    /// Send `request` if its host is allowed, reading the body within the byte budget
+    pub async fn execute(&self, request: reqwest::Request) -> Result<EgressResponse> {
+        self.meter
+            .admit(request.url().host_str().unwrap_or_default())?;
+        if let Some(body) = request.body().and_then(|b| b.as_bytes()) {
+            self.meter.consume(body.len())?;
+        }
+        let mut response = self.client.execute(request).await?;
+        let (status, headers) = (response.status(), response.headers().clone());
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            self.meter.consume(chunk.len())?;
+            body.extend_from_slice(&chunk);
+        }
+        Ok(EgressResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    /// Traffic so far
+    pub fn stats(&self) -> EgressStats {
+        self.meter.stats()
+    }
+}
+
+#[cfg(unix)]
+pub use proxy::EgressProxy;
+
+#[cfg(unix)]
+mod proxy {
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    use tokio::io::{
+        AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+    };
+    use tokio::net::{TcpStream, UnixListener, UnixStream};
+    use tokio::task::{JoinHandle, JoinSet};
+    use tracing::{debug, warn};
+
+    use super::{EgressBudget, EgressStats, HostAllowlist, Meter};
+    use crate::error::{Error, Result};
+
+    /// Longest request head the proxy reads
+    const MAX_HEAD_BYTES: usize = 16 * 1024;
+
+    /// HTTP(S) forward proxy on a unix socket, limited to allowlisted hosts
+    ///
+    /// Handles `CONNECT host:port` tunnels and absolute-form plain HTTP
+    /// requests, one plain request per connection. Stops and removes its
+    /// socket on drop.
+    pub struct EgressProxy {
+        path: PathBuf,
+        meter: Arc<Meter>,
+        task: JoinHandle<()>,
+    }
+
+    impl EgressProxy {
+        /// Listen on `path` (which must not exist yet)
+        pub fn start(
+            path: impl Into<PathBuf>,
+            allowlist: HostAllowlist,
+            budget: EgressBudget,
+        ) -> Result<Self> {
+            let path = path.into();
+            let listener = UnixListener::bind(&path)?;
+            let meter = Arc::new(Meter::new(allowlist, budget));
+            let shared = Arc::clone(&meter);
+            let task = tokio::spawn(async move {
+                let mut connections = JoinSet::new();
+                while let Ok((stream, _)) = listener.accept().await {
+                    connections.spawn(serve(stream, Arc::clone(&shared)));
+                    while connections.try_join_next().is_some() {}
+                }
+            });
+            Ok(Self { path, meter, task })
+        }
+
+        /// Socket path
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// Traffic so far
+        pub fn stats(&self) -> EgressStats {
+            self.meter.stats()
+        }
+    }
+
+    impl Drop for EgressProxy {
+        fn drop(&mut self) {
+            self.task.abort();
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    async fn serve(stream: UnixStream, meter: Arc<Meter>) {
+        let (read, mut write) = stream.into_split();
+        let mut read = BufReader::new(read);
+        let head = match read_head(&mut read).await {
+            Ok(head) => head,
+            Err(e) => {
+                debug!("Egress proxy dropped a malformed request: {}", e);
+                return;
+            }
+        };
+        let mut lines = head.split("\r\n");
+        let request_line = lines.next().unwrap_or_default();
+        let mut parts = request_line.split_whitespace();
+        let (method, target, version) = (
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or("HTTP/1.1"),
+        );
+
+        let connect = method.eq_ignore_ascii_case("CONNECT");
+        let destination = if connect {
+            split_authority(target, 443)
+        } else {
+            reqwest::Url::parse(target).ok().and_then(|url| {
+                let host = url.host_str()?.to_string();
+                let port = url.port_or_known_default()?;
+                let path = match url.query() {
+                    Some(query) => format!("{}?{}", url.path(), query),
+                    None => url.path().to_string(),
+                };
+                Some((host, port, path))
+            })
+        };
+        let Some((host, port, path)) = destination else {
+            let _ = respond(
+                &mut write,
+                400,
+                "Bad Request",
+                "expected CONNECT host:port or an absolute http:// URL",
+            )
+            .await;
+            return;
+        };
+
+        // Plain HTTP gets one request per connection, so every request is admitted
+        let mut body_len = 0;
+        let mut forwarded = String::new();
+        if !connect {
+            forwarded = format!("{} {} {}\r\n", method, path, version);
+            for line in lines.filter(|l| !l.is_empty()) {
+                let lower = line.to_ascii_lowercase();
+                if lower.starts_with("proxy-")
+                    || lower.starts_with("connection:")
+                    || lower.starts_with("keep-alive:")
+                {
+                    continue;
+                }
+                if lower.starts_with("transfer-encoding:") {
+                    let _ = respond(
+                        &mut write,
+                        411,
+                        "Length Required",
+                        "chunked request bodies are not supported, send Content-Length",
+                    )
+                    .await;
+                    return;
+                }
+                if let Some(value) = lower.strip_prefix("content-length:") {
+                    let Ok(len) = value.trim().parse() else {
+                        let _ = respond(&mut write, 400, "Bad Request", "invalid Content-Length").await;
+                        return;
+                    };
+                    body_len = len;
+                }
+                forwarded.push_str(line);
+                forwarded.push_str("\r\n");
+            }
+            forwarded.push_str("Connection: close\r\n\r\n");
+        }
+
+        if let Err(e) = meter.admit(&host) {
+            let (code, reason) = match e {
+                Error::EgressDenied { .. } => (403, "Forbidden"),
+                _ => (429, "Too Many Requests"),
+            };
+            let _ = respond(&mut write, code, reason, &e.to_string()).await;
+            return;
+        }
+
+        let mut upstream = match TcpStream::connect((host.as_str(), port)).await {
+            Ok(upstream) => upstream,
+            Err(e) => {
+                let _ = respond(
+                    &mut write,
+                    502,
+                    "Bad Gateway",
+                    &format!("connecting to {}:{}: {}", host, port, e),
+                )
+                .await;
+                return;
+            }
+        };
+
+        if connect {
+            if write
+                .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+                .await
+                .is_err()
+            {
+                return;
+            }
+        } else if meter.consume(forwarded.len()).is_err()
+            || upstream.write_all(forwarded.as_bytes()).await.is_err()
+        {
+            return;
+        }
+
+        let (mut up_read, mut up_write) = upstream.split();
+        let result = if connect {
+            tokio::try_join!(
+                pump(&mut read, &mut up_write, &meter),
+                pump(&mut up_read, &mut write, &meter),
+            )
+        } else {
+            // Only the declared body goes upstream; anything pipelined after it is dropped
+            let mut body = (&mut read).take(body_len);
+            tokio::try_join!(
+                copy_metered(&mut body, &mut up_write, &meter),
+                pump(&mut up_read, &mut write, &meter),
+            )
+        };
+        if let Err(e) = result {
+            warn!(host, "Egress connection cut: {}", e);
+        }
+    }
+
+    /// Read up to the blank line ending the request head
+    async fn read_head<R: AsyncBufReadExt + Unpin>(read: &mut R) -> Result<String> {
+        let mut head = String::new();
+        loop {
+            let before = head.len();
+            if read.read_line(&mut head).await? == 0 {
+                return Err(Error::Internal(
+                    "connection closed before request head".to_string(),
+                ));
+            }
+            if head.len() > MAX_HEAD_BYTES {
+                return Err(Error::Internal("request head too large".to_string()));
+            }
+            if head[before..].trim_end().is_empty() {
+                return Ok(head.trim_end().to_string());
+            }
+        }
+    }
+
+    /// `host:port` (with optional IPv6 brackets) into its parts
+    fn split_authority(authority: &str, default_port: u16) -> Option<(String, u16, String)> {
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => (host, port.parse().ok()?),
+            _ => (authority, default_port),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        (!host.is_empty()).then(|| (host.to_string(), port, String::new()))
+    }
+
+    /// Copy until EOF, counting every byte against the budget, then close `write`
+    async fn pump<R, W>(read: &mut R, write: &mut W, meter: &Meter) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        copy_metered(read, write, meter).await?;
+        write.shutdown().await?;
+        Ok(())
+    }
+
+    /// Copy until EOF, counting every byte against the budget
+    async fn copy_metered<R, W>(read: &mut R, write: &mut W, meter: &Meter) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut buf = vec![0u8; 8192];
+        loop {
+            let n = read.read(&mut buf).await?;
+            if n == 0 {
+                return Ok(());
+            }
+            meter.consume(n)?;
+            write.write_all(&buf[..n]).await?;
+        }
+    }
+
+    async fn respond<W: AsyncWrite + Unpin>(
+        write: &mut W,
+        code: u16,
+        reason: &str,
+        message: &str,
+    ) -> std::io::Result<()> {
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            code,
+            reason,
+            message.len(),
+            message
+        );
+        write.write_all(response.as_bytes()).await?;
+        write.shutdown().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+
+    /// Serves `/small` as "ok" and `/big` as 64 KiB, one request per connection
+    async fn test_server() -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut head = Vec::new();
+                    let mut byte = [0u8; 1];
+                    while !head.ends_with(b"\r\n\r\n")
+                        && stream.read(&mut byte).await.unwrap_or(0) == 1
+                    {
+                        head.push(byte[0]);
+                    }
+                    let head = String::from_utf8_lossy(&head);
+                    let body = if head.starts_with("GET /big") {
+                        "x".repeat(64 * 1024)
+                    } else {
+                        "ok".to_string()
+                    };
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_allowlist_matching() {
+        let allowlist = HostAllowlist::new(["api.example.com", "*.coingecko.com"]);
+        assert!(allowlist.allows("API.example.com."));
+        assert!(allowlist.allows("pro-api.coingecko.com"));
+        assert!(!allowlist.allows("coingecko.com"));
+        assert!(!allowlist.allows("evilcoingecko.com"));
+        assert!(!allowlist.allows("example.com"));
+        assert_eq!(
+            invalid_hosts(&[
+                "https://x.com/".to_string(),
+                "a.*.com".to_string(),
+                "*.ok.io".to_string()
+            ]),
+            ["https://x.com/", "a.*.com"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_allowlisted_client_checks_hosts_and_budget() {
+        let port = test_server().await;
+        let budget = EgressBudget {
+            max_bytes: Some(4096),
+            max_requests: None,
+        };
+        let client =
+            AllowlistedClient::with_budget(HostAllowlist::new(["127.0.0.1"]), budget).unwrap();
+
+        let ok = client
+            .get(&format!("http://127.0.0.1:{}/small", port))
+            .await
+            .unwrap();
+        assert_eq!(ok.text(), "ok");
+
+        let denied = client
+            .get(&format!("http://localhost:{}/small", port))
+            .await
+            .unwrap_err();
+        assert!(matches!(&denied, Error::EgressDenied { host, .. } if host == "localhost"));
+        assert!(
+            denied.to_string().contains("'localhost'")
+                && denied.to_string().contains("[127.0.0.1]")
+        );
+
+        let cut = client
+            .get(&format!("http://127.0.0.1:{}/big", port))
+            .await
+            .unwrap_err();
+        assert!(matches!(cut, Error::EgressBudgetExceeded(_)), "{}", cut);
+        let stats = client.stats();
+        assert_eq!((stats.requests, stats.denied), (2, 1));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_proxy_allows_denies_and_cuts_off() {
+        let port = test_server().await;
+        let dir = tempfile::tempdir().unwrap();
+        let budget = EgressBudget {
+            max_bytes: Some(8192),
+            max_requests: None,
+        };
+        let proxy = EgressProxy::start(
+            dir.path().join("egress.sock"),
+            HostAllowlist::new(["127.0.0.1"]),
+            budget,
+        )
+        .unwrap();
+
+        let fetch = |url: String| {
+            let path = proxy.path().to_path_buf();
+            async move {
+                let mut stream = tokio::net::UnixStream::connect(path).await.unwrap();
+                let request = format!("GET {} HTTP/1.1\r\nHost: x\r\nConnection: close\r\nProxy-Connection: close\r\n\r\n", url);
+                stream.write_all(request.as_bytes()).await.unwrap();
+                let mut response = Vec::new();
+                let _ = stream.read_to_end(&mut response).await;
+                String::from_utf8_lossy(&response).into_owned()
+            }
+        };
+
+        let ok = fetch(format!("http://127.0.0.1:{}/small", port)).await;
+        assert!(
+            ok.starts_with("HTTP/1.1 200") && ok.ends_with("\r\n\r\nok"),
+            "{}",
+            ok
+        );
+
+        let denied = fetch(format!("http://localhost:{}/small", port)).await;
+        assert!(denied.starts_with("HTTP/1.1 403"), "{}", denied);
+        assert!(denied.contains("'localhost'") && denied.contains("[127.0.0.1]"));
+
+        // The connection is dropped once the budget runs out
+        let cut = fetch(format!("http://127.0.0.1:{}/big", port)).await;
+        assert!(cut.len() < 16 * 1024, "{} bytes", cut.len());
+        assert_eq!(proxy.stats().denied, 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_proxy_admits_one_plain_request_per_connection() {
+        // Keep-alive server that counts the requests it answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let served = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = Arc::clone(&served);
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = tokio::io::BufReader::new(stream);
+            loop {
+                let mut head = String::new();
+                let mut line = String::new();
+                while stream.read_line(&mut line).await.unwrap_or(0) > 0 && line != "\r\n" {
+                    head.push_str(&line);
+                    line.clear();
+                }
+                if head.is_empty() {
+                    return;
+                }
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").await;
+                if head.to_ascii_lowercase().contains("connection: close") {
+                    return;
+                }
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let proxy = EgressProxy::start(
+            dir.path().join("egress.sock"),
+            HostAllowlist::new(["127.0.0.1"]),
+            EgressBudget::default(),
+        )
+        .unwrap();
+
+        // The second request names a host outside the allowlist
+        let mut stream = tokio::net::UnixStream::connect(proxy.path()).await.unwrap();
+        let requests = format!(
+            "GET http://127.0.0.1:{}/a HTTP/1.1\r\nHost: 127.0.0.1\r\nConnection: keep-alive\r\n\r\n\
+             GET /b HTTP/1.1\r\nHost: evil.example\r\n\r\n",
+            port
+        );
+        stream.write_all(requests.as_bytes()).await.unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+        let response = String::from_utf8_lossy(&response);
+
+        assert_eq!(response.matches("HTTP/1.1 200").count(), 1, "{}", response);
+        assert_eq!(served.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(proxy.stats().requests, 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore] // Requires bwrap, socat and curl
+    async fn test_sandboxed_skill_egress() {
+        use crate::skills::tool::Tool;
+        use crate::skills::{SkillExecutionConfig, SkillLoader};
+
+        let port = test_server().await;
+        // Inside the crate dir: the sandbox hides the host's /tmp
+        let root = tempfile::tempdir_in(env!("CARGO_MANIFEST_DIR")).unwrap();
+        let skill = root.path().join("fetch");
+        std::fs::create_dir_all(skill.join("scripts")).unwrap();
+        std::fs::write(
+            skill.join("SKILL.md"),
+            "---\nname: fetch\ndescription: Fetch quotes\nruntime: bash\nscript: fetch.sh\nrequires:\n  network: [127.0.0.1, api.unapproved.example]\n---\nFetches.\n",
+        )
+        .unwrap();
+        std::fs::write(
+            skill.join("scripts/fetch.sh"),
+            format!(
+                "curl -s -w ' %{{http_code}}\\n' http://127.0.0.1:{0}/small\n\
+                 curl -s -o /dev/null -w '%{{http_code}}\\n' http://localhost:{0}/small\n\
+                 curl -s http://127.0.0.1:{0}/big | wc -c\n",
+                port
+            ),
+        )
+        .unwrap();
+
+        let config = SkillExecutionConfig {
+            allowed_hosts: vec!["127.0.0.1".to_string()],
+            egress_budget: EgressBudget {
+                max_bytes: Some(8192),
+                max_requests: None,
+            },
+            ..SkillExecutionConfig::default()
+        };
+        let loader = SkillLoader::new(root.path()).with_execution_config(config);
+        loader.load_all().await.unwrap();
+        let preflight = loader.network_preflight();
+        assert_eq!(preflight["fetch"], ["api.unapproved.example"]);
+
+        let tool = loader.get("fetch").unwrap();
+        let output = tool.call("{}").await.unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "ok 200");
+        assert_eq!(lines[1], "403");
+        assert!(
+            lines[2].trim().parse::<usize>().unwrap() < 8192,
+            "{}",
+            output
+        );
+    }
+}
@@ -0,0 +1,506 @@
+//! Encryption at rest for persisted conversation data
+//!
+//! Payloads are sealed with AES-256-GCM behind a small header carrying the
+//! key ID, so data written under an old key can still be opened after rotation.
+//! Data without the header is treated as legacy plaintext and migrated the next
+//! time its store rewrites it.
+//!
+//! Wire format: `MAGIC | key_id_len (u8) | key_id | nonce (12 bytes) | ciphertext`.
+//!
+//! Covered stores are listed in [`EncryptedStore`]. Everything else stays
+//! plaintext even with encryption enabled: QMD document content and embeddings
+//! (full-text and vector search index them as written), the JSONL event log,
+//! dev traces, and trading/scheduler state. Keep those on an encrypted volume
+//! if they may hold sensitive data.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::error::{Error, Result};
+
+/// Marks an encrypted payload
+pub const MAGIC: &[u8; 6] = b"AAGTE1";
+
+/// Prefix for encrypted payloads stored in text columns (hex encoded)
+pub const TEXT_PREFIX: &str = "aagt-enc:";
+
+/// Prefix for keyed hashes stored in text columns (hex encoded)
+pub const HASH_PREFIX: &str = "aagt-hmac:";
+
+/// Environment variable holding the default hex-encoded 256-bit key
+pub const KEY_ENV_VAR: &str = "AAGT_ENCRYPTION_KEY";
+
+const NONCE_LEN: usize = 12;
+
+/// Encrypts and decrypts byte payloads bound to associated data
+pub trait EncryptionProvider: Send + Sync {
+    /// ID of the key used for new ciphertexts
+    fn key_id(&self) -> &str;
+
+    /// Seal `plaintext`, authenticating `aad` alongside it
+    fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>>;
+
+    /// Open a payload produced by [`EncryptionProvider::encrypt`]
+    fn decrypt(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>>;
+
+    /// Deterministic hash of `data` under the active key, for equality lookups
+    /// on values that are otherwise sealed; `context` separates uses
+    fn keyed_hash(&self, data: &[u8], context: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// AES-256-GCM provider with one active key and any number of decrypt-only keys
+pub struct AesGcmProvider {
+    active: String,
+    keys: HashMap<String, Aes256Gcm>,
+    /// HMAC-SHA256 keyed with a subkey of the active key
+    hasher: Hmac<Sha256>,
+}
+
+impl AesGcmProvider {
+    /// Create a provider from a raw 32-byte key
+    pub fn new(key_id: impl Into<String>, key: &[u8]) -> Result<Self> {
+        let key_id = key_id.into();
+        if key_id.is_empty() || key_id.len() > u8::MAX as usize {
+            return Err(Error::Encryption("key id must be 1-255 bytes".to_string()));
+        }
+        let mut keys = HashMap::new();
+        keys.insert(key_id.clone(), cipher(key)?);
+        // Hash under a derived subkey rather than the AES key itself
+        let subkey = hmac_sha256(key, b"aagt keyed hash").finalize().into_bytes();
+        Ok(Self {
+            active: key_id,
+            keys,
+            hasher: hmac_sha256(&subkey, b""),
+        })
+    }
+
+    /// Create a provider from a hex-encoded key in an environment variable
+    pub fn from_env(var: &str, key_id: impl Into<String>) -> Result<Self> {
+        let hex = std::env::var(var)
+            .map_err(|_| Error::Encryption(format!("environment variable {} is not set", var)))?;
+        Self::new(key_id, &decode_hex(hex.trim())?)
+    }
+
+    /// Create a provider from a key file (32 raw bytes or 64 hex characters)
+    pub fn from_key_file(path: impl AsRef<Path>, key_id: impl Into<String>) -> Result<Self> {
+        let bytes = std::fs::read(path.as_ref())?;
+        let key = match std::str::from_utf8(&bytes) {
+            Ok(text) if text.trim().len() == 64 => decode_hex(text.trim())?,
+            _ => bytes,
+        };
+        Self::new(key_id, &key)
+    }
+
+    /// Also accept an older key when decrypting
+    pub fn with_decrypt_key(mut self, key_id: impl Into<String>, key: &[u8]) -> Result<Self> {
+        self.keys.insert(key_id.into(), cipher(key)?);
+        Ok(self)
+    }
+}
+
+impl EncryptionProvider for AesGcmProvider {
+    fn key_id(&self) -> &str {
+        &self.active
+    }
+
+    fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let cipher = &self.keys[&self.active];
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|_| Error::Encryption("encryption failed".to_string()))?;
+
+        let mut out =
+            Vec::with_capacity(MAGIC.len() + 1 + self.active.len() + NONCE_LEN + ciphertext.len());
+        out.extend_from_slice(MAGIC);
+        out.push(self.active.len() as u8);
+        out.extend_from_slice(self.active.as_bytes());
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    fn decrypt(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let header = parse_header(data)?;
+        let cipher = self
+            .keys
+            .get(header.key_id)
+            .ok_or_else(|| Error::Encryption(format!("unknown key id '{}'", header.key_id)))?;
+        cipher
+            .decrypt(
+                Nonce::from_slice(header.nonce),
+                Payload {
+                    msg: header.ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| {
+                Error::Encryption(format!(
+                    "decryption with key '{}' failed (wrong key or corrupted data)",
+                    header.key_id
+                ))
+            })
+    }
+
+    fn keyed_hash(&self, data: &[u8], context: &[u8]) -> Result<Vec<u8>> {
+        let mut mac = self.hasher.clone();
+        // Length-prefix the context so `(ab, c)` and `(a, bc)` hash differently
+        mac.update(&(context.len() as u64).to_be_bytes());
+        mac.update(context);
+        mac.update(data);
+        Ok(mac.finalize().into_bytes().to_vec())
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac
+}
+
+fn cipher(key: &[u8]) -> Result<Aes256Gcm> {
+    Aes256Gcm::new_from_slice(key)
+        .map_err(|_| Error::Encryption(format!("key must be 32 bytes, got {}", key.len())))
+}
+
+struct Header<'a> {
+    key_id: &'a str,
+    nonce: &'a [u8],
+    ciphertext: &'a [u8],
+}
+
+fn parse_header(data: &[u8]) -> Result<Header<'_>> {
+    let malformed = || Error::Encryption("malformed encryption header".to_string());
+    let rest = data.strip_prefix(MAGIC.as_slice()).ok_or_else(malformed)?;
+    let (&id_len, rest) = rest.split_first().ok_or_else(malformed)?;
+    let id_len = id_len as usize;
+    if rest.len() < id_len + NONCE_LEN {
+        return Err(malformed());
+    }
+    let key_id = std::str::from_utf8(&rest[..id_len]).map_err(|_| malformed())?;
+    Ok(Header {
+        key_id,
+        nonce: &rest[id_len..id_len + NONCE_LEN],
+        ciphertext: &rest[id_len + NONCE_LEN..],
+    })
+}
+
+/// Whether a payload carries the encryption header
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Key ID recorded in an encrypted payload
+pub fn key_id_of(data: &[u8]) -> Option<&str> {
+    parse_header(data).ok().map(|h| h.key_id)
+}
+
+/// Decrypt a payload, passing legacy plaintext through unchanged
+pub fn open(
+    provider: Option<&dyn EncryptionProvider>,
+    data: Vec<u8>,
+    aad: &[u8],
+) -> Result<Vec<u8>> {
+    if !is_encrypted(&data) {
+        return Ok(data);
+    }
+    match provider {
+        Some(provider) => provider.decrypt(&data, aad),
+        None => Err(Error::Encryption(
+            "data is encrypted but no key is configured".to_string(),
+        )),
+    }
+}
+
+/// Encrypt a payload when a provider is configured
+pub fn seal(
+    provider: Option<&dyn EncryptionProvider>,
+    data: Vec<u8>,
+    aad: &[u8],
+) -> Result<Vec<u8>> {
+    match provider {
+        Some(provider) => provider.encrypt(&data, aad),
+        None => Ok(data),
+    }
+}
+
+/// Encrypt a string for a text column
+pub fn seal_text(
+    provider: Option<&dyn EncryptionProvider>,
+    text: &str,
+    aad: &[u8],
+) -> Result<String> {
+    match provider {
+        Some(provider) => Ok(format!(
+            "{}{}",
+            TEXT_PREFIX,
+            encode_hex(&provider.encrypt(text.as_bytes(), aad)?)
+        )),
+        None => Ok(text.to_string()),
+    }
+}
+
+/// Decrypt a text column value, passing legacy plaintext through unchanged
+pub fn open_text(
+    provider: Option<&dyn EncryptionProvider>,
+    text: String,
+    aad: &[u8],
+) -> Result<String> {
+    let Some(hex) = text.strip_prefix(TEXT_PREFIX) else {
+        return Ok(text);
+    };
+    let plaintext = open(provider, decode_hex(hex)?, aad)?;
+    String::from_utf8(plaintext)
+        .map_err(|e| Error::Encryption(format!("decrypted text is not UTF-8: {}", e)))
+}
+
+/// Keyed hash of a string for a text column; plaintext when no provider is configured
+pub fn hash_text(
+    provider: Option<&dyn EncryptionProvider>,
+    text: &str,
+    context: &[u8],
+) -> Result<String> {
+    match provider {
+        Some(provider) => Ok(format!(
+            "{}{}",
+            HASH_PREFIX,
+            encode_hex(&provider.keyed_hash(text.as_bytes(), context)?)
+        )),
+        None => Ok(text.to_string()),
+    }
+}
+
+/// Re-encrypt a payload under `new`, encrypting legacy plaintext as well
+pub fn rewrap(
+    data: &[u8],
+    old: &dyn EncryptionProvider,
+    new: &dyn EncryptionProvider,
+    aad: &[u8],
+) -> Result<Vec<u8>> {
+    let plaintext = if is_encrypted(data) {
+        old.decrypt(data, aad)?
+    } else {
+        data.to_vec()
+    };
+    new.encrypt(&plaintext, aad)
+}
+
+/// Re-encrypt a text column value under `new`
+pub fn rewrap_text(
+    text: &str,
+    old: &dyn EncryptionProvider,
+    new: &dyn EncryptionProvider,
+    aad: &[u8],
+) -> Result<String> {
+    let plaintext = open_text(Some(old), text.to_string(), aad)?;
+    seal_text(Some(new), &plaintext, aad)
+}
+
+/// Re-encrypt a file in place (written via tmp + rename)
+pub async fn rewrap_file(
+    path: impl AsRef<Path>,
+    old: &dyn EncryptionProvider,
+    new: &dyn EncryptionProvider,
+    aad: &[u8],
+) -> Result<()> {
+    let path = path.as_ref();
+    let data = tokio::fs::read(path).await?;
+    let rewrapped = rewrap(&data, old, new, aad)?;
+
+    let tmp_path = path.with_extension("rewrap.tmp");
+    tokio::fs::write(&tmp_path, rewrapped).await?;
+    tokio::fs::rename(&tmp_path, path).await?;
+    Ok(())
+}
+
+/// Progress of a bulk rewrap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RewrapProgress {
+    /// Items processed so far
+    pub done: usize,
+    /// Total items to process
+    pub total: usize,
+}
+
+/// Persistence boundary that can be encrypted
+///
+/// These are the only stores [`EncryptionConfig`] covers; see the module docs
+/// for what stays plaintext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptedStore {
+    /// `ShortTermMemory` snapshot file
+    ShortTermMemory,
+    /// Session blobs in the QMD sessions table
+    Sessions,
+}
+
+/// Which stores are encrypted
+///
+/// `enabled` applies to every [`EncryptedStore`]; `overrides` switch
+/// individual stores on or off.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    /// Encrypt conversation data at rest
+    #[serde(default)]
+    pub enabled: bool,
+    /// Per-store exceptions to `enabled`
+    #[serde(default)]
+    pub overrides: HashMap<EncryptedStore, bool>,
+}
+
+impl EncryptionConfig {
+    /// Whether `store` should be encrypted
+    pub fn is_enabled_for(&self, store: EncryptedStore) -> bool {
+        self.overrides
+            .get(&store)
+            .copied()
+            .unwrap_or(self.enabled)
+    }
+
+    /// Provider to hand to `store`, if it is encrypted
+    pub fn provider_for(
+        &self,
+        store: EncryptedStore,
+        provider: &Arc<dyn EncryptionProvider>,
+    ) -> Option<Arc<dyn EncryptionProvider>> {
+        self.is_enabled_for(store).then(|| provider.clone())
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(Error::Encryption("hex string has odd length".to_string()));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| Error::Encryption("invalid hex string".to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(id: &str, fill: u8) -> AesGcmProvider {
+        AesGcmProvider::new(id, &[fill; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_and_wrong_key() {
+        let k1 = provider("k1", 1);
+        let sealed = k1.encrypt(b"secret plan", b"sessions").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert_eq!(key_id_of(&sealed), Some("k1"));
+        assert_eq!(k1.decrypt(&sealed, b"sessions").unwrap(), b"secret plan");
+
+        // Associated data is bound to the ciphertext
+        assert!(k1.decrypt(&sealed, b"other").is_err());
+
+        // Same key id, different key material
+        let imposter = provider("k1", 9);
+        assert!(imposter
+            .decrypt(&sealed, b"sessions")
+            .unwrap_err()
+            .to_string()
+            .contains("wrong key"));
+
+        let other = provider("k2", 1);
+        assert!(other
+            .decrypt(&sealed, b"sessions")
+            .unwrap_err()
+            .to_string()
+            .contains("unknown key id"));
+
+        assert!(AesGcmProvider::new("short", &[0; 16]).is_err());
+    }
+
+    #[test]
+    fn test_keyed_hash_is_stable_per_key_and_context() {
+        let k1 = provider("k1", 1);
+        let hash = k1.keyed_hash(b"alice", b"users").unwrap();
+        assert_eq!(hash, provider("k1", 1).keyed_hash(b"alice", b"users").unwrap());
+        assert_ne!(hash, k1.keyed_hash(b"alice", b"other").unwrap());
+        assert_ne!(hash, provider("k1", 2).keyed_hash(b"alice", b"users").unwrap());
+
+        let text = hash_text(Some(&k1), "alice", b"users").unwrap();
+        assert!(text.starts_with(HASH_PREFIX) && !text.contains("alice"));
+        assert_eq!(hash_text(None, "alice", b"users").unwrap(), "alice");
+    }
+
+    #[test]
+    fn test_legacy_plaintext_and_rotation() {
+        let k1 = provider("k1", 1);
+        let legacy = b"{\"messages\":[]}".to_vec();
+        assert_eq!(open(Some(&k1), legacy.clone(), b"stm").unwrap(), legacy);
+        assert_eq!(
+            open_text(Some(&k1), "plain".to_string(), b"s").unwrap(),
+            "plain"
+        );
+
+        let text = seal_text(Some(&k1), "hello", b"s").unwrap();
+        assert!(text.starts_with(TEXT_PREFIX));
+        assert!(open_text(None, text.clone(), b"s").is_err());
+
+        // Rotate: the new provider keeps k1 for reading old data
+        let k2 = AesGcmProvider::new("k2", &[2; 32])
+            .unwrap()
+            .with_decrypt_key("k1", &[1; 32])
+            .unwrap();
+        assert_eq!(open_text(Some(&k2), text.clone(), b"s").unwrap(), "hello");
+
+        let rotated = rewrap_text(&text, &k1, &k2, b"s").unwrap();
+        let bytes = decode_hex(rotated.strip_prefix(TEXT_PREFIX).unwrap()).unwrap();
+        assert_eq!(key_id_of(&bytes), Some("k2"));
+        assert_eq!(
+            open_text(Some(&provider("k2", 2)), rotated, b"s").unwrap(),
+            "hello"
+        );
+
+        // Rewrapping plaintext encrypts it
+        let wrapped = rewrap(&legacy, &k1, &k2, b"stm").unwrap();
+        assert_eq!(k2.decrypt(&wrapped, b"stm").unwrap(), legacy);
+    }
+
+    #[test]
+    fn test_config_overrides() {
+        let mut config = EncryptionConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(config.is_enabled_for(EncryptedStore::Sessions));
+        assert!(config.is_enabled_for(EncryptedStore::ShortTermMemory));
+
+        config.overrides.insert(EncryptedStore::Sessions, false);
+        assert!(!config.is_enabled_for(EncryptedStore::Sessions));
+        assert!(config.is_enabled_for(EncryptedStore::ShortTermMemory));
+
+        config.enabled = false;
+        config.overrides.insert(EncryptedStore::ShortTermMemory, true);
+        assert!(config.is_enabled_for(EncryptedStore::ShortTermMemory));
+
+        // Stores that are never encrypted are not accepted
+        assert!(serde_json::from_str::<EncryptedStore>("\"documents\"").is_err());
+    }
+}
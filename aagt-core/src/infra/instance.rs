@@ -0,0 +1,519 @@
+//! Coordination between agent instances sharing one data directory
+//!
+//! The first instance to start takes an exclusive `flock` on `instance.lock` and
+//! becomes the writer. Every acquisition bumps a fencing token stored in the lock
+//! file; writers re-read it before each write batch so an instance that was paused
+//! and then superseded cannot overwrite the new holder's data.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::error::{Error, Result};
+
+/// Lock file name inside the data directory
+pub const LOCK_FILE: &str = "instance.lock";
+/// Heartbeat file name inside the data directory
+pub const HEARTBEAT_FILE: &str = "instance.heartbeat";
+/// Guard file serialising takeovers; never unlinked, unlike [`LOCK_FILE`]
+pub const TAKEOVER_LOCK_FILE: &str = "instance.lock.takeover";
+
+/// What to do when another instance already holds the data directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LockPolicy {
+    /// Refuse to start, naming the current holder
+    #[default]
+    FailFast,
+    /// Start without writer duties (no scheduler, risk or index writes)
+    ReadOnlySecondary,
+    /// Steal the lock when the holder's heartbeat is older than `stale_after`
+    Takeover {
+        /// Heartbeat age after which the holder is presumed dead
+        stale_after: Duration,
+    },
+}
+
+/// Role this instance ended up with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceMode {
+    /// Holds the lock and performs writer-only duties
+    Writer,
+    /// Shares the directory read-only with another writer
+    ReadOnly,
+}
+
+/// Identity written into the lock file by the holder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockHolder {
+    /// Process id
+    pub pid: u32,
+    /// Host the process runs on
+    pub hostname: String,
+    /// When the lock was acquired
+    pub started_at: DateTime<Utc>,
+    /// Fencing token of this acquisition
+    pub token: u64,
+}
+
+impl std::fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pid {} on {} (since {}, token {})",
+            self.pid,
+            self.hostname,
+            self.started_at.to_rfc3339(),
+            self.token
+        )
+    }
+}
+
+impl LockHolder {
+    fn current(token: u64) -> Self {
+        Self {
+            pid: std::process::id(),
+            hostname: hostname(),
+            started_at: Utc::now(),
+            token,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Heartbeat {
+    token: u64,
+    beat_at: DateTime<Utc>,
+}
+
+/// Exclusive claim on a data directory
+///
+/// Keep it alive for the lifetime of the process; dropping it releases the `flock`.
+pub struct InstanceLock {
+    dir: PathBuf,
+    mode: InstanceMode,
+    holder: LockHolder,
+    file: Mutex<Option<File>>,
+}
+
+impl std::fmt::Debug for InstanceLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InstanceLock")
+            .field("dir", &self.dir)
+            .field("mode", &self.mode)
+            .field("holder", &self.holder)
+            .finish()
+    }
+}
+
+impl InstanceLock {
+    /// Claim `dir` according to `policy`
+    pub fn acquire(dir: impl Into<PathBuf>, policy: LockPolicy) -> Result<Arc<Self>> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(LOCK_FILE);
+
+        let mut file = open_lock_file(&path)?;
+        if file.try_lock_exclusive().is_ok() {
+            let previous = read_holder(&mut file)?;
+            return Self::claim(dir, file, previous.map_or(0, |h| h.token)).map(Arc::new);
+        }
+
+        let current = read_holder(&mut file)?
+            .ok_or_else(|| Error::Internal(format!("Lock file {:?} is held but empty", path)))?;
+        drop(file);
+
+        match policy {
+            LockPolicy::FailFast => Err(Error::InstanceLocked {
+                holder: current.to_string(),
+            }),
+            LockPolicy::ReadOnlySecondary => {
+                info!(
+                    "Data directory {:?} held by {}; starting read-only",
+                    dir, current
+                );
+                Ok(Arc::new(Self {
+                    dir,
+                    mode: InstanceMode::ReadOnly,
+                    holder: current,
+                    file: Mutex::new(None),
+                }))
+            }
+            LockPolicy::Takeover { stale_after } => {
+                if heartbeat_age(&dir, &current) < stale_after {
+                    return Err(Error::InstanceLocked {
+                        holder: current.to_string(),
+                    });
+                }
+                Self::take_over(dir, stale_after).map(Arc::new)
+            }
+        }
+    }
+
+    /// Steal `dir` from a holder whose heartbeat is older than `stale_after`
+    ///
+    /// Runs under the takeover guard, so concurrent takeovers queue up; each
+    /// re-reads the holder once inside and sees the previous winner's fresh
+    /// heartbeat instead of claiming the same token again.
+    fn take_over(dir: PathBuf, stale_after: Duration) -> Result<Self> {
+        let guard = open_lock_file(&dir.join(TAKEOVER_LOCK_FILE))?;
+        guard.lock_exclusive()?;
+
+        let path = dir.join(LOCK_FILE);
+        let mut file = open_lock_file(&path)?;
+        if file.try_lock_exclusive().is_ok() {
+            // Released while we waited for the guard
+            let previous = read_holder(&mut file)?;
+            return Self::claim(dir, file, previous.map_or(0, |h| h.token));
+        }
+        let current = read_holder(&mut file)?
+            .ok_or_else(|| Error::Internal(format!("Lock file {:?} is held but empty", path)))?;
+        drop(file);
+
+        let age = heartbeat_age(&dir, &current);
+        if age < stale_after {
+            return Err(Error::InstanceLocked {
+                holder: current.to_string(),
+            });
+        }
+
+        warn!(
+            "Taking over data directory {:?} from {} (heartbeat {}s old)",
+            dir,
+            current,
+            age.as_secs()
+        );
+        // The new lock file is written and locked before it replaces the old
+        // one, so nobody can find it empty and restart the token count. The
+        // stale holder keeps its flock on the replaced inode; the bumped
+        // fencing token is what keeps it from writing.
+        let staged = dir.join(format!("{}.new", LOCK_FILE));
+        let mut file = open_lock_file(&staged)?;
+        file.try_lock_exclusive()?;
+        let holder = LockHolder::current(current.token + 1);
+        write_holder(&mut file, &holder)?;
+        std::fs::rename(&staged, &path)?;
+        Self::writer(dir, file, holder)
+    }
+
+    fn claim(dir: PathBuf, mut file: File, previous_token: u64) -> Result<Self> {
+        let holder = LockHolder::current(previous_token + 1);
+        write_holder(&mut file, &holder)?;
+        Self::writer(dir, file, holder)
+    }
+
+    fn writer(dir: PathBuf, file: File, holder: LockHolder) -> Result<Self> {
+        let lock = Self {
+            dir,
+            mode: InstanceMode::Writer,
+            holder,
+            file: Mutex::new(Some(file)),
+        };
+        lock.beat()?;
+        info!("Acquired data directory {:?} as {}", lock.dir, lock.holder);
+        Ok(lock)
+    }
+
+    /// Role of this instance
+    pub fn mode(&self) -> InstanceMode {
+        self.mode
+    }
+
+    /// Data directory this lock guards
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Lock holder: this instance when writing, the other instance when read-only
+    pub fn holder(&self) -> &LockHolder {
+        &self.holder
+    }
+
+    /// Fencing token of this instance (`None` when read-only)
+    pub fn token(&self) -> Option<u64> {
+        (self.mode == InstanceMode::Writer).then_some(self.holder.token)
+    }
+
+    /// Whether writer-only duties may run right now
+    pub fn is_writer(&self) -> bool {
+        self.check_writer("writer check").is_ok()
+    }
+
+    /// Fail unless this instance is the current writer
+    ///
+    /// Re-reads the fencing token from disk, so call it before every write batch.
+    pub fn check_writer(&self, duty: &str) -> Result<()> {
+        if self.mode == InstanceMode::ReadOnly {
+            return Err(Error::InstanceReadOnly(duty.to_string()));
+        }
+        let current =
+            read_holder(&mut File::open(self.dir.join(LOCK_FILE))?)?.map_or(0, |h| h.token);
+        if current != self.holder.token {
+            return Err(Error::InstanceFenced {
+                token: self.holder.token,
+                current,
+            });
+        }
+        Ok(())
+    }
+
+    /// Refresh the heartbeat file
+    pub fn beat(&self) -> Result<()> {
+        self.check_writer("heartbeat")?;
+        let heartbeat = Heartbeat {
+            token: self.holder.token,
+            beat_at: Utc::now(),
+        };
+        let json = serde_json::to_vec(&heartbeat)
+            .map_err(|e| Error::Internal(format!("Failed to serialize heartbeat: {}", e)))?;
+        let path = self.dir.join(HEARTBEAT_FILE);
+        let tmp = path.with_extension(format!("tmp.{}", self.holder.token));
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Refresh the heartbeat every `interval` until fenced, made read-only, or aborted
+    pub fn spawn_heartbeat(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let lock = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                match lock.beat() {
+                    Ok(()) => debug!("Instance heartbeat refreshed"),
+                    Err(e) => {
+                        warn!("Stopping instance heartbeat: {}", e);
+                        break;
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        if let Some(file) = self.file.lock().take() {
+            let _ = FileExt::unlock(&file);
+        }
+    }
+}
+
+fn open_lock_file(path: &Path) -> Result<File> {
+    Ok(OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?)
+}
+
+fn read_holder(file: &mut File) -> Result<Option<LockHolder>> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_string(&mut content)?;
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|e| Error::Internal(format!("Malformed instance lock file: {}", e)))
+}
+
+fn write_holder(file: &mut File, holder: &LockHolder) -> Result<()> {
+    let json = serde_json::to_vec_pretty(holder)
+        .map_err(|e| Error::Internal(format!("Failed to serialize lock holder: {}", e)))?;
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&json)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn read_heartbeat(dir: &Path) -> Option<Heartbeat> {
+    let content = std::fs::read(dir.join(HEARTBEAT_FILE)).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+/// Time since `holder` last beat, or since it started if it never did
+fn heartbeat_age(dir: &Path, holder: &LockHolder) -> Duration {
+    let last_beat = read_heartbeat(dir)
+        .filter(|hb| hb.token == holder.token)
+        .map_or(holder.started_at, |hb| hb.beat_at);
+    (Utc::now() - last_beat).to_std().unwrap_or_default()
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::memory::{Memory, ShortTermMemory};
+    use crate::agent::message::Message;
+
+    #[test]
+    fn test_second_instance_fails_fast() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = InstanceLock::acquire(dir.path(), LockPolicy::default()).unwrap();
+        assert_eq!(first.mode(), InstanceMode::Writer);
+        assert_eq!(first.token(), Some(1));
+
+        let err = InstanceLock::acquire(dir.path(), LockPolicy::FailFast).unwrap_err();
+        match err {
+            Error::InstanceLocked { holder } => {
+                assert!(
+                    holder.contains(&format!("pid {}", std::process::id())),
+                    "{}",
+                    holder
+                )
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+
+        // A fresh heartbeat blocks takeover as well
+        let err = InstanceLock::acquire(
+            dir.path(),
+            LockPolicy::Takeover {
+                stale_after: Duration::from_secs(60),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::InstanceLocked { .. }));
+
+        // Releasing hands out the next token
+        drop(first);
+        let next = InstanceLock::acquire(dir.path(), LockPolicy::FailFast).unwrap();
+        assert_eq!(next.token(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_read_only_secondary_skips_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let writer = InstanceLock::acquire(dir.path(), LockPolicy::FailFast).unwrap();
+        let secondary = InstanceLock::acquire(dir.path(), LockPolicy::ReadOnlySecondary).unwrap();
+
+        assert_eq!(secondary.mode(), InstanceMode::ReadOnly);
+        assert_eq!(secondary.holder(), writer.holder());
+        assert!(!secondary.is_writer());
+        assert!(matches!(
+            secondary.check_writer("scheduler"),
+            Err(Error::InstanceReadOnly(_))
+        ));
+
+        let path = dir.path().join("memory.json");
+        let memory = ShortTermMemory::new(10, 10, &path)
+            .await
+            .with_instance_lock(secondary);
+        memory.store("u", None, Message::user("hi")).await.unwrap();
+        assert_eq!(memory.message_count("u", None), 1);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_stale_takeover_fences_zombie() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("memory.json");
+
+        let old = InstanceLock::acquire(dir.path(), LockPolicy::FailFast).unwrap();
+        let zombie = ShortTermMemory::new(10, 10, &path)
+            .await
+            .with_instance_lock(old.clone());
+        zombie
+            .store("u", None, Message::user("before"))
+            .await
+            .unwrap();
+        assert!(path.exists());
+
+        // The old holder stops beating (paused process)
+        std::thread::sleep(Duration::from_millis(50));
+        let new = InstanceLock::acquire(
+            dir.path(),
+            LockPolicy::Takeover {
+                stale_after: Duration::from_millis(20),
+            },
+        )
+        .unwrap();
+        assert_eq!(new.token(), Some(2));
+        assert!(new.is_writer());
+
+        // The zombie resumes and tries to write
+        assert!(matches!(
+            old.check_writer("write"),
+            Err(Error::InstanceFenced {
+                token: 1,
+                current: 2
+            })
+        ));
+        assert!(old.beat().is_err());
+        let err = zombie.clear("u", None).await.unwrap_err();
+        assert!(matches!(err, Error::InstanceFenced { .. }));
+        let on_disk = std::fs::read_to_string(&path).unwrap();
+        assert!(on_disk.contains("before"));
+
+        let fresh = ShortTermMemory::new(10, 10, &path)
+            .await
+            .with_instance_lock(new);
+        fresh
+            .store("u", None, Message::user("after"))
+            .await
+            .unwrap();
+        assert!(std::fs::read_to_string(&path).unwrap().contains("after"));
+    }
+
+    #[test]
+    fn test_racing_takeovers_elect_one_writer() {
+        let takeover = LockPolicy::Takeover {
+            stale_after: Duration::from_secs(60),
+        };
+        for _ in 0..20 {
+            let dir = tempfile::tempdir().unwrap();
+            let old = InstanceLock::acquire(dir.path(), LockPolicy::FailFast).unwrap();
+            let stale = Heartbeat {
+                token: 1,
+                beat_at: Utc::now() - chrono::Duration::hours(1),
+            };
+            std::fs::write(dir.path().join(HEARTBEAT_FILE), serde_json::to_vec(&stale).unwrap()).unwrap();
+
+            let barrier = Arc::new(std::sync::Barrier::new(4));
+            let racers: Vec<_> = (0..4)
+                .map(|_| {
+                    let dir = dir.path().to_path_buf();
+                    let barrier = Arc::clone(&barrier);
+                    std::thread::spawn(move || {
+                        barrier.wait();
+                        InstanceLock::acquire(dir, takeover)
+                    })
+                })
+                .collect();
+            let results: Vec<_> = racers.into_iter().map(|r| r.join().unwrap()).collect();
+
+            let winners: Vec<_> = results.iter().filter_map(|r| r.as_ref().ok()).collect();
+            assert_eq!(winners.len(), 1, "{:?}", results);
+            assert_eq!(winners[0].token(), Some(2));
+            assert!(winners[0].is_writer());
+            assert!(!old.is_writer());
+
+            // A racer that found the holder stale just before the winner claimed it
+            let late = InstanceLock::take_over(dir.path().to_path_buf(), Duration::from_secs(60));
+            assert!(matches!(late, Err(Error::InstanceLocked { .. })), "{:?}", late);
+            assert!(winners[0].is_writer());
+        }
+    }
+}
@@ -1,11 +1,16 @@
 //! Background maintenance tasks for resource cleanup
 
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::task::JoinHandle;
-use tracing::info;
+use tracing::{debug, info, warn};
 
 use crate::agent::memory::ShortTermMemory;
+use crate::error::Result;
+use crate::infra::instance::InstanceLock;
+use crate::infra::retention::RetentionEnforcer;
+use crate::knowledge::consolidation::MemoryConsolidator;
 
 /// Configuration for background tasks
 #[derive(Debug, Clone)]
@@ -25,9 +30,17 @@ impl Default for MaintenanceConfig {
     }
 }
 
+/// A snapshot of some store that [`MaintenanceManager::start_backups`] takes on a schedule
+#[async_trait::async_trait]
+pub trait BackupJob: Send + Sync {
+    /// Take one backup, returning where it was written
+    async fn run(&self) -> Result<PathBuf>;
+}
+
 /// Manager for background maintenance tasks
 pub struct MaintenanceManager {
     tasks: Vec<JoinHandle<()>>,
+    instance_lock: Option<Arc<InstanceLock>>,
 }
 
 impl MaintenanceManager {
@@ -35,21 +48,33 @@ impl MaintenanceManager {
     pub fn new() -> Self {
         Self {
             tasks: Vec::new(),
+            instance_lock: None,
         }
     }
 
+    /// Only run maintenance passes while `lock` is the current writer
+    pub fn with_instance_lock(mut self, lock: Arc<InstanceLock>) -> Self {
+        self.instance_lock = Some(lock);
+        self
+    }
+
     /// Start memory cleanup task
     pub fn start_memory_cleanup(
         &mut self,
         memory: Arc<ShortTermMemory>,
         config: MaintenanceConfig,
     ) {
+        let instance_lock = self.instance_lock.clone();
         let handle = tokio::spawn(async move {
             let interval = Duration::from_secs(config.memory_cleanup_interval_secs);
             let inactive_timeout = Duration::from_secs(config.memory_inactive_timeout_secs);
             
             loop {
                 tokio::time::sleep(interval).await;
+                if let Some(Err(e)) = instance_lock.as_ref().map(|l| l.check_writer("memory cleanup")) {
+                    debug!("Skipping short-term memory cleanup: {}", e);
+                    continue;
+                }
                 info!("Running scheduled short-term memory cleanup");
                 memory.prune_inactive(inactive_timeout);
             }
@@ -57,6 +82,69 @@ impl MaintenanceManager {
         self.tasks.push(handle);
     }
 
+    /// Start periodic long-term memory consolidation
+    ///
+    /// Each pass is capped by the consolidator's merge token budget.
+    pub fn start_memory_consolidation(&mut self, consolidator: Arc<MemoryConsolidator>, interval: Duration) {
+        let instance_lock = self.instance_lock.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Some(Err(e)) = instance_lock.as_ref().map(|l| l.check_writer("memory consolidation")) {
+                    debug!("Skipping memory consolidation: {}", e);
+                    continue;
+                }
+                info!("Running scheduled long-term memory consolidation");
+                if let Err(e) = consolidator.run().await {
+                    warn!("Memory consolidation failed: {}", e);
+                }
+            }
+        });
+        self.tasks.push(handle);
+    }
+
+    /// Start periodic data retention enforcement
+    ///
+    /// Each run's report lands in the enforcer's journal and notifier.
+    pub fn start_retention(&mut self, enforcer: Arc<RetentionEnforcer>, interval: Duration) {
+        let instance_lock = self.instance_lock.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Some(Err(e)) = instance_lock.as_ref().map(|l| l.check_writer("data retention")) {
+                    debug!("Skipping data retention: {}", e);
+                    continue;
+                }
+                info!("Running scheduled data retention");
+                if let Err(e) = enforcer.run().await {
+                    warn!("Data retention failed: {}", e);
+                }
+            }
+        });
+        self.tasks.push(handle);
+    }
+
+    /// Start periodic backups
+    ///
+    /// A failed backup is logged and retried at the next interval.
+    pub fn start_backups(&mut self, job: Arc<dyn BackupJob>, interval: Duration) {
+        let instance_lock = self.instance_lock.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Some(Err(e)) = instance_lock.as_ref().map(|l| l.check_writer("backup")) {
+                    debug!("Skipping backup: {}", e);
+                    continue;
+                }
+                info!("Running scheduled backup");
+                match job.run().await {
+                    Ok(path) => info!("Backup written to {}", path.display()),
+                    Err(e) => warn!("Backup failed: {}", e),
+                }
+            }
+        });
+        self.tasks.push(handle);
+    }
 
     /// Shutdown all background tasks
     pub async fn shutdown(self) {
@@ -1,11 +1,21 @@
+pub mod analytics;
+pub mod egress;
+pub mod encryption;
 pub mod format;
+pub mod instance;
 pub mod logging;
 pub mod maintenance;
 pub mod notification;
+pub mod notification_router;
 pub mod notifications;
 pub mod observable;
+pub mod outbox;
+pub mod response_format;
+pub mod retention;
+pub mod secrets;
+pub mod validation;
 #[cfg(feature = "telegram")]
 pub mod telegram;
 
 #[cfg(feature = "telegram")]
-pub use telegram::TelegramNotifier;
+pub use telegram::{TelegramApprovalHandler, TelegramNotifier};
@@ -1,5 +1,6 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use crate::agent::Severity;
 use crate::error::Result;
 
 /// Notification channel types
@@ -18,6 +19,19 @@ pub enum NotifyChannel {
     Log,
 }
 
+impl NotifyChannel {
+    /// Stable name used for logging and formatter lookup
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotifyChannel::Email => "email",
+            NotifyChannel::Telegram => "telegram",
+            NotifyChannel::Discord => "discord",
+            NotifyChannel::Webhook { .. } => "webhook",
+            NotifyChannel::Log => "log",
+        }
+    }
+}
+
 /// Trait for sending notifications
 /// 
 /// Implement this trait to connect the Agent to external communication systems
@@ -26,6 +40,14 @@ pub enum NotifyChannel {
 pub trait Notifier: Send + Sync {
     /// Send a notification
     async fn notify(&self, channel: NotifyChannel, message: &str) -> Result<()>;
+
+    /// Send a notification with a severity
+    ///
+    /// Notifiers that don't distinguish severities deliver it like `notify`.
+    async fn notify_with(&self, severity: Severity, channel: NotifyChannel, message: &str) -> Result<()> {
+        let _ = severity;
+        self.notify(channel, message).await
+    }
 }
 
 /// A no-op notifier that logs to tracing
@@ -0,0 +1,600 @@
+//! Severity-aware notification routing
+//!
+//! `NotificationRouter` sits in front of one or more downstream [`Notifier`]s and
+//! decides per message whether to deliver it now, batch it into a digest, or drop
+//! it. Each channel can be throttled with a token bucket; messages over the limit
+//! are deferred until the bucket refills instead of being lost.
+//!
+//! ```ignore
+//! let router = NotificationRouter::builder()
+//!     .route(NotifyChannel::Telegram, telegram)
+//!     .rule(Severity::Debug, Delivery::Drop)
+//!     .rule(Severity::Info, Delivery::Digest)
+//!     .throttle(NotifyChannel::Telegram, ThrottleConfig::new(10, Duration::from_secs(3600)))
+//!     .build();
+//! router.spawn_flusher();
+//! let agent = Agent::builder(provider).notifier(router.clone()).build()?;
+//! agent.notify_with(Severity::Error, NotifyChannel::Telegram, "Stop loss hit").await?;
+//! ```
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::agent::Severity;
+use crate::error::Result;
+use crate::infra::notification::{Notifier, NotifyChannel};
+use crate::infra::outbox::{Clock, SystemClock};
+
+/// What the router does with a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Send right away (subject to the channel's throttle)
+    Immediate,
+    /// Batch into the channel's next digest
+    Digest,
+    /// Discard
+    Drop,
+}
+
+/// Token-bucket limit for a channel
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottleConfig {
+    /// Messages that can be sent back to back
+    pub max_messages: u32,
+    /// Time for an empty bucket to refill completely
+    pub per: Duration,
+}
+
+impl ThrottleConfig {
+    /// At most `max_messages` every `per`
+    pub fn new(max_messages: u32, per: Duration) -> Self {
+        Self { max_messages, per }
+    }
+}
+
+/// When digests are flushed
+#[derive(Debug, Clone)]
+pub struct DigestConfig {
+    /// Flush once the oldest batched message is this old
+    pub interval: Duration,
+    /// Flush as soon as this many messages are batched
+    pub max_items: usize,
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(900),
+            max_items: 20,
+        }
+    }
+}
+
+/// Routing counters
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouterStats {
+    /// Messages and digests handed to a downstream notifier
+    pub delivered: u64,
+    /// Messages batched into a digest
+    pub digested: u64,
+    /// Messages held back by a throttle
+    pub deferred: u64,
+    /// Messages discarded by a rule, a full queue or a missing route
+    pub dropped: u64,
+    /// Downstream delivery failures
+    pub failed: u64,
+}
+
+struct TokenBucket {
+    config: ThrottleConfig,
+    tokens: f64,
+    refilled_at: DateTime<Utc>,
+}
+
+impl TokenBucket {
+    fn new(config: ThrottleConfig, now: DateTime<Utc>) -> Self {
+        Self {
+            config,
+            tokens: config.max_messages as f64,
+            refilled_at: now,
+        }
+    }
+
+    fn try_take(&mut self, now: DateTime<Utc>) -> bool {
+        let capacity = self.config.max_messages as f64;
+        let per = self.config.per.as_secs_f64();
+        let elapsed = (now - self.refilled_at).to_std().unwrap_or_default().as_secs_f64();
+        self.tokens = if per > 0.0 {
+            (self.tokens + elapsed / per * capacity).min(capacity)
+        } else {
+            capacity
+        };
+        self.refilled_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+struct ChannelQueue {
+    channel: NotifyChannel,
+    bucket: Option<TokenBucket>,
+    /// Immediate messages waiting for the throttle
+    deferred: VecDeque<String>,
+    /// Messages batched for the next digest
+    digest: Vec<String>,
+    digest_since: Option<DateTime<Utc>>,
+}
+
+impl ChannelQueue {
+    fn take_token(&mut self, now: DateTime<Utc>) -> bool {
+        self.bucket.as_mut().is_none_or(|bucket| bucket.try_take(now))
+    }
+
+    fn digest_due(&self, now: DateTime<Utc>, config: &DigestConfig) -> bool {
+        let Some(since) = self.digest_since else {
+            return false;
+        };
+        self.digest.len() >= config.max_items
+            || (now - since).to_std().unwrap_or_default() >= config.interval
+    }
+
+    fn take_digest(&mut self) -> String {
+        self.digest_since = None;
+        let items = std::mem::take(&mut self.digest);
+        let mut text = format!("Digest ({} notifications):", items.len());
+        for item in items {
+            text.push_str("\n- ");
+            text.push_str(&item);
+        }
+        text
+    }
+}
+
+struct RouterInner {
+    routes: Vec<(NotifyChannel, Arc<dyn Notifier>)>,
+    fallback: Option<Arc<dyn Notifier>>,
+    rules: Vec<(Severity, Delivery)>,
+    throttles: Vec<(NotifyChannel, ThrottleConfig)>,
+    digest: DigestConfig,
+    max_queued: usize,
+    poll_interval: Duration,
+    clock: Arc<dyn Clock>,
+    queues: Mutex<Vec<ChannelQueue>>,
+    delivered: AtomicU64,
+    digested: AtomicU64,
+    deferred: AtomicU64,
+    dropped: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Builder for [`NotificationRouter`]
+pub struct NotificationRouterBuilder {
+    routes: Vec<(NotifyChannel, Arc<dyn Notifier>)>,
+    fallback: Option<Arc<dyn Notifier>>,
+    rules: Vec<(Severity, Delivery)>,
+    throttles: Vec<(NotifyChannel, ThrottleConfig)>,
+    digest: DigestConfig,
+    max_queued: usize,
+    poll_interval: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl NotificationRouterBuilder {
+    /// Deliver a channel's messages through `notifier`
+    pub fn route(mut self, channel: NotifyChannel, notifier: impl Notifier + 'static) -> Self {
+        self.routes.retain(|(c, _)| c != &channel);
+        self.routes.push((channel, Arc::new(notifier)));
+        self
+    }
+
+    /// Notifier for channels without a route (unrouted messages are dropped otherwise)
+    pub fn fallback(mut self, notifier: impl Notifier + 'static) -> Self {
+        self.fallback = Some(Arc::new(notifier));
+        self
+    }
+
+    /// How to deliver messages of a severity (default: `Delivery::Immediate`)
+    pub fn rule(mut self, severity: Severity, delivery: Delivery) -> Self {
+        self.rules.retain(|(s, _)| *s != severity);
+        self.rules.push((severity, delivery));
+        self
+    }
+
+    /// Rate-limit a channel; digests count as one message
+    pub fn throttle(mut self, channel: NotifyChannel, config: ThrottleConfig) -> Self {
+        self.throttles.retain(|(c, _)| c != &channel);
+        self.throttles.push((channel, config));
+        self
+    }
+
+    /// When digests are flushed
+    pub fn digest(mut self, config: DigestConfig) -> Self {
+        self.digest = config;
+        self
+    }
+
+    /// Messages a channel may hold deferred or batched before new ones are dropped
+    pub fn max_queued(mut self, max: usize) -> Self {
+        self.max_queued = max;
+        self
+    }
+
+    /// How often the background flusher runs
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Use a custom clock
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn build(self) -> NotificationRouter {
+        NotificationRouter {
+            inner: Arc::new(RouterInner {
+                routes: self.routes,
+                fallback: self.fallback,
+                rules: self.rules,
+                throttles: self.throttles,
+                digest: self.digest,
+                max_queued: self.max_queued,
+                poll_interval: self.poll_interval,
+                clock: self.clock,
+                queues: Mutex::new(Vec::new()),
+                delivered: AtomicU64::new(0),
+                digested: AtomicU64::new(0),
+                deferred: AtomicU64::new(0),
+                dropped: AtomicU64::new(0),
+                failed: AtomicU64::new(0),
+            }),
+        }
+    }
+}
+
+/// Notifier that routes by severity, throttles per channel and batches digests
+#[derive(Clone)]
+pub struct NotificationRouter {
+    inner: Arc<RouterInner>,
+}
+
+impl NotificationRouter {
+    pub fn builder() -> NotificationRouterBuilder {
+        NotificationRouterBuilder {
+            routes: Vec::new(),
+            fallback: None,
+            rules: Vec::new(),
+            throttles: Vec::new(),
+            digest: DigestConfig::default(),
+            max_queued: 100,
+            poll_interval: Duration::from_secs(1),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Routing counters
+    pub fn stats(&self) -> RouterStats {
+        RouterStats {
+            delivered: self.inner.delivered.load(Ordering::Relaxed),
+            digested: self.inner.digested.load(Ordering::Relaxed),
+            deferred: self.inner.deferred.load(Ordering::Relaxed),
+            dropped: self.inner.dropped.load(Ordering::Relaxed),
+            failed: self.inner.failed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Messages currently deferred or batched across all channels
+    pub async fn pending(&self) -> usize {
+        let queues = self.inner.queues.lock().await;
+        queues.iter().map(|q| q.deferred.len() + q.digest.len()).sum()
+    }
+
+    /// Deliver deferred messages the throttles now allow and any digests that are due,
+    /// returning how many sends succeeded
+    pub async fn flush_due(&self) -> usize {
+        let now = self.inner.clock.now();
+        let mut sends = Vec::new();
+        {
+            let mut queues = self.inner.queues.lock().await;
+            for queue in queues.iter_mut() {
+                while !queue.deferred.is_empty() && queue.take_token(now) {
+                    if let Some(message) = queue.deferred.pop_front() {
+                        sends.push((queue.channel.clone(), message));
+                    }
+                }
+                if queue.deferred.is_empty()
+                    && queue.digest_due(now, &self.inner.digest)
+                    && queue.take_token(now)
+                {
+                    sends.push((queue.channel.clone(), queue.take_digest()));
+                }
+            }
+        }
+
+        let mut delivered = 0;
+        for (channel, message) in sends {
+            match self.deliver(channel, &message).await {
+                Ok(()) => delivered += 1,
+                Err(e) => tracing::warn!("Routed notification failed: {}", e),
+            }
+        }
+        delivered
+    }
+
+    /// Start the background flusher; it exits once every router handle is dropped
+    pub fn spawn_flusher(&self) -> tokio::task::JoinHandle<()> {
+        let weak = Arc::downgrade(&self.inner);
+        let interval = self.inner.poll_interval;
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(inner) = weak.upgrade() else {
+                    break;
+                };
+                NotificationRouter { inner }.flush_due().await;
+            }
+        })
+    }
+
+    fn delivery(&self, severity: Severity) -> Delivery {
+        self.inner
+            .rules
+            .iter()
+            .find(|(s, _)| *s == severity)
+            .map(|(_, d)| *d)
+            .unwrap_or(Delivery::Immediate)
+    }
+
+    fn notifier_for(&self, channel: &NotifyChannel) -> Option<&Arc<dyn Notifier>> {
+        self.inner
+            .routes
+            .iter()
+            .find(|(c, _)| c == channel)
+            .map(|(_, n)| n)
+            .or(self.inner.fallback.as_ref())
+    }
+
+    async fn deliver(&self, channel: NotifyChannel, message: &str) -> Result<()> {
+        let Some(notifier) = self.notifier_for(&channel) else {
+            self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!("No notifier routed for channel {}", channel.name());
+            return Ok(());
+        };
+        match notifier.notify(channel, message).await {
+            Ok(()) => {
+                self.inner.delivered.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(e) => {
+                self.inner.failed.fetch_add(1, Ordering::Relaxed);
+                Err(e)
+            }
+        }
+    }
+
+    async fn route(&self, severity: Severity, channel: NotifyChannel, message: &str) -> Result<()> {
+        let delivery = self.delivery(severity);
+        if delivery == Delivery::Drop || self.notifier_for(&channel).is_none() {
+            self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        let now = self.inner.clock.now();
+        let send = {
+            let mut queues = self.inner.queues.lock().await;
+            let queue = match queues.iter().position(|q| q.channel == channel) {
+                Some(pos) => &mut queues[pos],
+                None => {
+                    let bucket = self
+                        .inner
+                        .throttles
+                        .iter()
+                        .find(|(c, _)| c == &channel)
+                        .map(|(_, config)| TokenBucket::new(*config, now));
+                    queues.push(ChannelQueue {
+                        channel: channel.clone(),
+                        bucket,
+                        deferred: VecDeque::new(),
+                        digest: Vec::new(),
+                        digest_since: None,
+                    });
+                    queues.last_mut().expect("queue was just pushed")
+                }
+            };
+
+            let queued = queue.deferred.len() + queue.digest.len();
+            if queued >= self.inner.max_queued
+                && (delivery == Delivery::Digest || !queue.deferred.is_empty())
+            {
+                self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+                None
+            } else if delivery == Delivery::Digest {
+                self.inner.digested.fetch_add(1, Ordering::Relaxed);
+                queue.digest_since.get_or_insert(now);
+                queue.digest.push(message.to_string());
+                let full = queue.digest.len() >= self.inner.digest.max_items;
+                if full && queue.deferred.is_empty() && queue.take_token(now) {
+                    Some(queue.take_digest())
+                } else {
+                    None
+                }
+            } else if queue.deferred.is_empty() && queue.take_token(now) {
+                Some(message.to_string())
+            } else if queued >= self.inner.max_queued {
+                self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+                None
+            } else {
+                self.inner.deferred.fetch_add(1, Ordering::Relaxed);
+                queue.deferred.push_back(message.to_string());
+                None
+            }
+        };
+
+        match send {
+            Some(message) => self.deliver(channel, &message).await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for NotificationRouter {
+    async fn notify(&self, channel: NotifyChannel, message: &str) -> Result<()> {
+        self.route(Severity::Info, channel, message).await
+    }
+
+    async fn notify_with(&self, severity: Severity, channel: NotifyChannel, message: &str) -> Result<()> {
+        self.route(severity, channel, message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ManualClock;
+
+    /// Records each delivery with the clock time it arrived at
+    #[derive(Clone)]
+    struct RecordingNotifier {
+        clock: Arc<ManualClock>,
+        sent: Arc<std::sync::Mutex<Vec<(DateTime<Utc>, String)>>>,
+    }
+
+    impl RecordingNotifier {
+        fn new(clock: Arc<ManualClock>) -> Self {
+            Self {
+                clock,
+                sent: Arc::default(),
+            }
+        }
+
+        fn sent(&self) -> Vec<(DateTime<Utc>, String)> {
+            self.sent.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl Notifier for RecordingNotifier {
+        async fn notify(&self, _channel: NotifyChannel, message: &str) -> Result<()> {
+            self.sent.lock().unwrap().push((self.clock.now(), message.to_string()));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_throttle_spaces_out_deliveries() {
+        let clock = ManualClock::new();
+        let recorder = RecordingNotifier::new(clock.clone());
+        let router = NotificationRouter::builder()
+            .route(NotifyChannel::Telegram, recorder.clone())
+            .throttle(NotifyChannel::Telegram, ThrottleConfig::new(2, Duration::from_secs(3600)))
+            .max_queued(2)
+            .clock(clock.clone())
+            .build();
+
+        let start = clock.now();
+        for i in 1..=5 {
+            router
+                .notify_with(Severity::Error, NotifyChannel::Telegram, &format!("alert {}", i))
+                .await
+                .unwrap();
+        }
+        assert_eq!(router.pending().await, 2);
+        assert_eq!(router.flush_due().await, 0);
+
+        clock.advance(Duration::from_secs(1800));
+        assert_eq!(router.flush_due().await, 1);
+        clock.advance(Duration::from_secs(1800));
+        assert_eq!(router.flush_due().await, 1);
+
+        let half_hour = chrono::Duration::seconds(1800);
+        assert_eq!(
+            recorder.sent(),
+            vec![
+                (start, "alert 1".to_string()),
+                (start, "alert 2".to_string()),
+                (start + half_hour, "alert 3".to_string()),
+                (start + half_hour * 2, "alert 4".to_string()),
+            ]
+        );
+        assert_eq!(
+            router.stats(),
+            RouterStats {
+                delivered: 4,
+                deferred: 2,
+                dropped: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(router.pending().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_digest_flushes_on_size_and_timer() {
+        let clock = ManualClock::new();
+        let recorder = RecordingNotifier::new(clock.clone());
+        let router = NotificationRouter::builder()
+            .route(NotifyChannel::Telegram, recorder.clone())
+            .rule(Severity::Debug, Delivery::Drop)
+            .rule(Severity::Info, Delivery::Digest)
+            .digest(DigestConfig {
+                interval: Duration::from_secs(600),
+                max_items: 3,
+            })
+            .clock(clock.clone())
+            .build();
+
+        for i in 1..=3 {
+            router.notify(NotifyChannel::Telegram, &format!("fill {}", i)).await.unwrap();
+        }
+        router.notify(NotifyChannel::Telegram, "fill 4").await.unwrap();
+        router
+            .notify_with(Severity::Debug, NotifyChannel::Telegram, "tick")
+            .await
+            .unwrap();
+        router
+            .notify_with(Severity::Warning, NotifyChannel::Telegram, "slippage high")
+            .await
+            .unwrap();
+        router
+            .notify_with(Severity::Error, NotifyChannel::Discord, "unrouted")
+            .await
+            .unwrap();
+
+        assert_eq!(router.flush_due().await, 0);
+        clock.advance(Duration::from_secs(600));
+        assert_eq!(router.flush_due().await, 1);
+
+        let sent: Vec<String> = recorder.sent().into_iter().map(|(_, m)| m).collect();
+        assert_eq!(
+            sent,
+            vec![
+                "Digest (3 notifications):\n- fill 1\n- fill 2\n- fill 3".to_string(),
+                "slippage high".to_string(),
+                "Digest (1 notifications):\n- fill 4".to_string(),
+            ]
+        );
+        assert_eq!(
+            router.stats(),
+            RouterStats {
+                delivered: 3,
+                digested: 4,
+                dropped: 2,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -5,6 +5,7 @@
 
 use async_trait::async_trait;
 use crate::agent::core::AgentEvent;
+use crate::agent::events::{EventFilter, EventItem, EventStream};
 
 /// Trait for observing agent events
 #[async_trait]
@@ -15,7 +16,7 @@ pub trait AgentObserver: Send + Sync {
 
 /// A dispatcher that forwards events from a broadcast channel to multiple observers
 pub struct EventDispatcher {
-    observers: Vec<Box<dyn AgentObserver>>,
+    observers: Vec<(Box<dyn AgentObserver>, EventFilter)>,
 }
 
 impl EventDispatcher {
@@ -24,14 +25,32 @@ impl EventDispatcher {
     }
 
     pub fn add_observer(&mut self, observer: Box<dyn AgentObserver>) {
-        self.observers.push(observer);
+        self.add_filtered_observer(observer, EventFilter::all());
+    }
+
+    /// Add an observer that only sees events matching `filter`
+    pub fn add_filtered_observer(&mut self, observer: Box<dyn AgentObserver>, filter: EventFilter) {
+        self.observers.push((observer, filter));
     }
 
     pub async fn dispatch(&self, event: &AgentEvent) {
-        for observer in &self.observers {
+        for (observer, filter) in &self.observers {
+            if !filter.matches(event) {
+                continue;
+            }
             if let Err(e) = observer.on_event(event).await {
                 tracing::error!("Observer failed to handle event: {}", e);
             }
         }
     }
+
+    /// Dispatch events from a filtered subscription until the agent is dropped
+    pub async fn run(&self, mut events: EventStream) {
+        while let Some(item) = events.recv().await {
+            match item {
+                EventItem::Event(event) => self.dispatch(&event).await,
+                EventItem::Lagged(n) => tracing::warn!("Event dispatcher lagged, skipped {} events", n),
+            }
+        }
+    }
 }
@@ -0,0 +1,680 @@
+//! Durable notification outbox
+//!
+//! `OutboxNotifier` wraps any [`Notifier`] and persists each notification
+//! before acknowledging it. A dispatcher delivers queued notifications in order
+//! per channel, retrying with exponential backoff until an entry is dead-lettered.
+//!
+//! ```ignore
+//! let outbox = OutboxNotifier::new(telegram, Arc::new(FileOutboxStore::new("data/outbox.json")), OutboxConfig::default()).await?;
+//! outbox.spawn_dispatcher();
+//! let agent = Agent::builder(provider).notifier(outbox.clone()).build()?;
+//! ```
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Notify};
+
+use crate::error::{Error, Result};
+use crate::infra::notification::{Notifier, NotifyChannel};
+
+/// Source of the current time (replaceable in tests)
+pub trait Clock: Send + Sync {
+    /// Current time
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Wall-clock time
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Configuration for the outbox
+#[derive(Debug, Clone)]
+pub struct OutboxConfig {
+    /// Attempts before an entry is dead-lettered
+    pub max_attempts: u32,
+    /// Delay after the first failed attempt
+    pub base_backoff: Duration,
+    /// Upper bound for the retry delay
+    pub max_backoff: Duration,
+    /// Identical messages on the same channel within this window are dropped
+    pub dedup_window: Duration,
+    /// How often the dispatcher checks for due entries
+    pub poll_interval: Duration,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            base_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(300),
+            dedup_window: Duration::from_secs(60),
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Delivery state of an outbox entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxStatus {
+    /// Waiting for (re)delivery
+    Pending,
+    /// Gave up after `max_attempts`
+    DeadLetter,
+}
+
+/// A queued notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    /// Unique entry ID
+    pub id: String,
+    /// Target channel
+    pub channel: NotifyChannel,
+    /// Message body
+    pub message: String,
+    /// When the notification was queued
+    pub created_at: DateTime<Utc>,
+    /// Delivery attempts made so far
+    pub attempts: u32,
+    /// Earliest time of the next attempt
+    pub next_attempt_at: DateTime<Utc>,
+    /// Error from the last failed attempt
+    pub last_error: Option<String>,
+    /// Delivery state
+    pub status: OutboxStatus,
+}
+
+/// Delivery counters
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboxStats {
+    /// Notifications delivered
+    pub delivered: u64,
+    /// Failed delivery attempts
+    pub failed_attempts: u64,
+    /// Entries moved to the dead-letter state
+    pub dead_lettered: u64,
+    /// Notifications dropped as duplicates
+    pub deduplicated: u64,
+}
+
+/// Persistence for queued notifications
+#[async_trait]
+pub trait OutboxStore: Send + Sync {
+    async fn load(&self) -> Result<Vec<OutboxEntry>>;
+    async fn save(&self, entries: &[OutboxEntry]) -> Result<()>;
+}
+
+/// JSON file store for the outbox
+pub struct FileOutboxStore {
+    path: PathBuf,
+}
+
+impl FileOutboxStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl OutboxStore for FileOutboxStore {
+    async fn load(&self) -> Result<Vec<OutboxEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = tokio::fs::read_to_string(&self.path).await?;
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        serde_json::from_str(&content).map_err(|e| {
+            Error::Internal(format!(
+                "Outbox file at {:?} is malformed: {}",
+                self.path, e
+            ))
+        })
+    }
+
+    async fn save(&self, entries: &[OutboxEntry]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await.ok();
+        }
+
+        // Write tmp -> rename so a crash never leaves a truncated outbox
+        let tmp_path = self
+            .path
+            .with_extension(format!("tmp.{}", uuid::Uuid::new_v4()));
+        let json = serde_json::to_vec_pretty(entries)?;
+        tokio::fs::write(&tmp_path, json).await?;
+        if let Err(e) = tokio::fs::rename(&tmp_path, &self.path).await {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+/// Store that keeps nothing (queued notifications are lost on restart)
+pub struct InMemoryOutboxStore;
+
+#[async_trait]
+impl OutboxStore for InMemoryOutboxStore {
+    async fn load(&self) -> Result<Vec<OutboxEntry>> {
+        Ok(Vec::new())
+    }
+    async fn save(&self, _: &[OutboxEntry]) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct OutboxState {
+    entries: Vec<OutboxEntry>,
+    /// Recently delivered (channel, message, queued_at) for de-duplication
+    recent: Vec<(NotifyChannel, String, DateTime<Utc>)>,
+}
+
+struct OutboxInner {
+    notifier: Arc<dyn Notifier>,
+    store: Arc<dyn OutboxStore>,
+    config: OutboxConfig,
+    clock: Arc<dyn Clock>,
+    state: Mutex<OutboxState>,
+    wake: Notify,
+    stopped: AtomicBool,
+    delivered: AtomicU64,
+    failed_attempts: AtomicU64,
+    dead_lettered: AtomicU64,
+    deduplicated: AtomicU64,
+}
+
+/// Notifier that queues notifications durably and delivers them with retries
+#[derive(Clone)]
+pub struct OutboxNotifier {
+    inner: Arc<OutboxInner>,
+}
+
+impl OutboxNotifier {
+    /// Wrap a notifier, reloading any entries left in the store
+    pub async fn new(
+        notifier: impl Notifier + 'static,
+        store: Arc<dyn OutboxStore>,
+        config: OutboxConfig,
+    ) -> Result<Self> {
+        Self::with_clock(Arc::new(notifier), store, config, Arc::new(SystemClock)).await
+    }
+
+    /// Wrap a shared notifier using a custom clock
+    pub async fn with_clock(
+        notifier: Arc<dyn Notifier>,
+        store: Arc<dyn OutboxStore>,
+        config: OutboxConfig,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self> {
+        let entries = store.load().await?;
+        if !entries.is_empty() {
+            tracing::info!("Outbox reloaded {} queued notifications", entries.len());
+        }
+
+        Ok(Self {
+            inner: Arc::new(OutboxInner {
+                notifier,
+                store,
+                config,
+                clock,
+                state: Mutex::new(OutboxState {
+                    entries,
+                    recent: Vec::new(),
+                }),
+                wake: Notify::new(),
+                stopped: AtomicBool::new(false),
+                delivered: AtomicU64::new(0),
+                failed_attempts: AtomicU64::new(0),
+                dead_lettered: AtomicU64::new(0),
+                deduplicated: AtomicU64::new(0),
+            }),
+        })
+    }
+
+    /// Start the background dispatcher
+    pub fn spawn_dispatcher(&self) -> tokio::task::JoinHandle<()> {
+        let outbox = self.clone();
+        tokio::spawn(async move {
+            while !outbox.inner.stopped.load(Ordering::SeqCst) {
+                outbox.dispatch_due().await;
+                tokio::select! {
+                    _ = outbox.inner.wake.notified() => {}
+                    _ = tokio::time::sleep(outbox.inner.config.poll_interval) => {}
+                }
+            }
+        })
+    }
+
+    /// Attempt delivery of every entry that is due, returning how many were delivered
+    ///
+    /// Only the oldest pending entry of each channel is attempted, so a channel
+    /// that is backing off holds back its later messages and order is preserved.
+    pub async fn dispatch_due(&self) -> usize {
+        let mut delivered = 0;
+        loop {
+            let batch = self.due_heads().await;
+            if batch.is_empty() {
+                break;
+            }
+
+            let mut progressed = false;
+            for entry in batch {
+                let result = self
+                    .inner
+                    .notifier
+                    .notify(entry.channel.clone(), &entry.message)
+                    .await;
+                progressed |= result.is_ok();
+                if result.is_ok() {
+                    delivered += 1;
+                }
+                self.record_attempt(&entry.id, result).await;
+            }
+
+            if !progressed {
+                break;
+            }
+        }
+        delivered
+    }
+
+    async fn due_heads(&self) -> Vec<OutboxEntry> {
+        let now = self.inner.clock.now();
+        let state = self.inner.state.lock().await;
+
+        let mut seen: Vec<&NotifyChannel> = Vec::new();
+        let mut due = Vec::new();
+        for entry in state
+            .entries
+            .iter()
+            .filter(|e| e.status == OutboxStatus::Pending)
+        {
+            if seen.contains(&&entry.channel) {
+                continue;
+            }
+            seen.push(&entry.channel);
+            if entry.next_attempt_at <= now {
+                due.push(entry.clone());
+            }
+        }
+        due
+    }
+
+    async fn record_attempt(&self, id: &str, result: Result<()>) {
+        let now = self.inner.clock.now();
+        let mut state = self.inner.state.lock().await;
+        let Some(pos) = state.entries.iter().position(|e| e.id == id) else {
+            return;
+        };
+
+        match result {
+            Ok(()) => {
+                let entry = state.entries.remove(pos);
+                self.inner.delivered.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!(id = %entry.id, attempts = entry.attempts + 1, "Outbox notification delivered");
+                state
+                    .recent
+                    .push((entry.channel, entry.message, entry.created_at));
+            }
+            Err(e) => {
+                self.inner.failed_attempts.fetch_add(1, Ordering::Relaxed);
+                let max_attempts = self.inner.config.max_attempts;
+                let backoff = self.backoff(state.entries[pos].attempts + 1);
+                let entry = &mut state.entries[pos];
+                entry.attempts += 1;
+                entry.last_error = Some(e.to_string());
+
+                if entry.attempts >= max_attempts {
+                    entry.status = OutboxStatus::DeadLetter;
+                    self.inner.dead_lettered.fetch_add(1, Ordering::Relaxed);
+                    // Logged rather than notified to avoid feedback loops
+                    tracing::error!(id = %entry.id, attempts = entry.attempts, error = %e, "Outbox notification dead-lettered");
+                } else {
+                    entry.next_attempt_at = now + backoff;
+                    tracing::warn!(id = %entry.id, attempts = entry.attempts, retry_in_ms = backoff.num_milliseconds(), error = %e, "Outbox delivery failed");
+                }
+            }
+        }
+
+        if let Err(e) = self.inner.store.save(&state.entries).await {
+            tracing::error!("Failed to persist notification outbox: {}", e);
+        }
+    }
+
+    fn backoff(&self, attempts: u32) -> chrono::Duration {
+        let config = &self.inner.config;
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        let delay = config
+            .base_backoff
+            .saturating_mul(factor)
+            .min(config.max_backoff);
+        chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::MAX)
+    }
+
+    /// Entries still waiting for delivery (excluding dead letters)
+    pub async fn pending_count(&self) -> usize {
+        let state = self.inner.state.lock().await;
+        state
+            .entries
+            .iter()
+            .filter(|e| e.status == OutboxStatus::Pending)
+            .count()
+    }
+
+    /// Entries that exhausted their attempts
+    pub async fn dead_letters(&self) -> Vec<OutboxEntry> {
+        let state = self.inner.state.lock().await;
+        state
+            .entries
+            .iter()
+            .filter(|e| e.status == OutboxStatus::DeadLetter)
+            .cloned()
+            .collect()
+    }
+
+    /// Re-queue a dead-lettered entry with a fresh attempt budget
+    pub async fn retry_dead_letter(&self, id: &str) -> Result<()> {
+        let now = self.inner.clock.now();
+        let mut state = self.inner.state.lock().await;
+        let entry = state
+            .entries
+            .iter_mut()
+            .find(|e| e.id == id && e.status == OutboxStatus::DeadLetter)
+            .ok_or_else(|| Error::Internal(format!("Dead letter not found: {}", id)))?;
+
+        entry.status = OutboxStatus::Pending;
+        entry.attempts = 0;
+        entry.next_attempt_at = now;
+        self.inner.store.save(&state.entries).await?;
+        drop(state);
+
+        self.inner.wake.notify_one();
+        Ok(())
+    }
+
+    /// Delivery counters
+    pub fn stats(&self) -> OutboxStats {
+        OutboxStats {
+            delivered: self.inner.delivered.load(Ordering::Relaxed),
+            failed_attempts: self.inner.failed_attempts.load(Ordering::Relaxed),
+            dead_lettered: self.inner.dead_lettered.load(Ordering::Relaxed),
+            deduplicated: self.inner.deduplicated.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Stop the dispatcher and try to deliver what is due until `deadline` passes
+    ///
+    /// Returns the number of entries still pending.
+    pub async fn shutdown(&self, deadline: Duration) -> usize {
+        self.inner.stopped.store(true, Ordering::SeqCst);
+        self.inner.wake.notify_one();
+
+        let flush = async {
+            while self.pending_count().await > 0 {
+                if self.dispatch_due().await == 0 {
+                    tokio::time::sleep(self.inner.config.poll_interval).await;
+                }
+            }
+        };
+        let _ = tokio::time::timeout(deadline, flush).await;
+
+        let remaining = self.pending_count().await;
+        if remaining > 0 {
+            tracing::warn!(
+                "Outbox shut down with {} undelivered notifications",
+                remaining
+            );
+        }
+        remaining
+    }
+}
+
+#[async_trait]
+impl Notifier for OutboxNotifier {
+    async fn notify(&self, channel: NotifyChannel, message: &str) -> Result<()> {
+        let now = self.inner.clock.now();
+        let window = chrono::Duration::from_std(self.inner.config.dedup_window)
+            .unwrap_or(chrono::Duration::MAX);
+        let mut state = self.inner.state.lock().await;
+
+        state.recent.retain(|(_, _, at)| now - *at < window);
+        let duplicate = state
+            .entries
+            .iter()
+            .map(|e| (&e.channel, &e.message, e.created_at))
+            .chain(state.recent.iter().map(|(c, m, at)| (c, m, *at)))
+            .any(|(c, m, at)| *c == channel && m == message && now - at < window);
+        if duplicate {
+            self.inner.deduplicated.fetch_add(1, Ordering::Relaxed);
+            tracing::debug!("Outbox dropped duplicate notification");
+            return Ok(());
+        }
+
+        state.entries.push(OutboxEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            channel,
+            message: message.to_string(),
+            created_at: now,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            status: OutboxStatus::Pending,
+        });
+
+        // Durable before acknowledging
+        if let Err(e) = self.inner.store.save(&state.entries).await {
+            state.entries.pop();
+            return Err(e);
+        }
+        drop(state);
+
+        self.inner.wake.notify_one();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ManualClock;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Fails the first `failures` calls, then records deliveries
+    #[derive(Default)]
+    struct FlakyNotifier {
+        failures: AtomicUsize,
+        calls: AtomicUsize,
+        delivered: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl FlakyNotifier {
+        fn failing(failures: usize) -> Arc<Self> {
+            let notifier = Self::default();
+            notifier.failures.store(failures, Ordering::SeqCst);
+            Arc::new(notifier)
+        }
+
+        fn delivered(&self) -> Vec<String> {
+            self.delivered.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl Notifier for FlakyNotifier {
+        async fn notify(&self, _channel: NotifyChannel, message: &str) -> Result<()> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                return Err(Error::Internal("telegram unavailable".to_string()));
+            }
+            self.delivered.lock().unwrap().push(message.to_string());
+            Ok(())
+        }
+    }
+
+    async fn outbox(
+        notifier: Arc<FlakyNotifier>,
+        clock: Arc<ManualClock>,
+        config: OutboxConfig,
+    ) -> OutboxNotifier {
+        OutboxNotifier::with_clock(notifier, Arc::new(InMemoryOutboxStore), config, clock)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ordered_delivery_with_backoff() {
+        let notifier = FlakyNotifier::failing(2);
+        let clock = ManualClock::new();
+        let outbox = outbox(notifier.clone(), clock.clone(), OutboxConfig::default()).await;
+
+        for msg in ["a", "b", "c"] {
+            outbox.notify(NotifyChannel::Telegram, msg).await.unwrap();
+        }
+
+        // First attempt fails and holds back the rest of the channel
+        assert_eq!(outbox.dispatch_due().await, 0);
+        assert_eq!(notifier.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(outbox.pending_count().await, 3);
+
+        // Not due until the 1s backoff has passed
+        clock.advance(Duration::from_millis(900));
+        let calls = notifier.calls.load(Ordering::SeqCst);
+        assert_eq!(outbox.dispatch_due().await, 0);
+        assert_eq!(notifier.calls.load(Ordering::SeqCst), calls);
+
+        // Second failure doubles the backoff to 2s
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(outbox.dispatch_due().await, 0);
+        clock.advance(Duration::from_millis(1900));
+        assert_eq!(outbox.dispatch_due().await, 0);
+        clock.advance(Duration::from_millis(100));
+
+        assert_eq!(outbox.dispatch_due().await, 3);
+        assert_eq!(notifier.delivered(), vec!["a", "b", "c"]);
+        assert_eq!(outbox.pending_count().await, 0);
+        assert_eq!(outbox.stats().failed_attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_dedup_window() {
+        let notifier = FlakyNotifier::failing(0);
+        let clock = ManualClock::new();
+        let outbox = outbox(notifier.clone(), clock.clone(), OutboxConfig::default()).await;
+
+        outbox
+            .notify(NotifyChannel::Telegram, "alert")
+            .await
+            .unwrap();
+        outbox
+            .notify(NotifyChannel::Telegram, "alert")
+            .await
+            .unwrap();
+        outbox
+            .notify(NotifyChannel::Discord, "alert")
+            .await
+            .unwrap();
+        assert_eq!(outbox.pending_count().await, 2);
+
+        // Still a duplicate after delivery, until the window passes
+        outbox.dispatch_due().await;
+        outbox
+            .notify(NotifyChannel::Telegram, "alert")
+            .await
+            .unwrap();
+        assert_eq!(outbox.pending_count().await, 0);
+
+        clock.advance(Duration::from_secs(61));
+        outbox
+            .notify(NotifyChannel::Telegram, "alert")
+            .await
+            .unwrap();
+        assert_eq!(outbox.pending_count().await, 1);
+        assert_eq!(outbox.stats().deduplicated, 2);
+    }
+
+    #[tokio::test]
+    async fn test_dead_letter_and_retry() {
+        let notifier = FlakyNotifier::failing(2);
+        let clock = ManualClock::new();
+        let config = OutboxConfig {
+            max_attempts: 2,
+            ..Default::default()
+        };
+        let outbox = outbox(notifier.clone(), clock.clone(), config).await;
+
+        outbox
+            .notify(NotifyChannel::Telegram, "first")
+            .await
+            .unwrap();
+        outbox.dispatch_due().await;
+        clock.advance(Duration::from_secs(1));
+        outbox.dispatch_due().await;
+
+        let dead = outbox.dead_letters().await;
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].attempts, 2);
+        assert_eq!(outbox.pending_count().await, 0);
+
+        // Dead letters do not block the channel
+        outbox
+            .notify(NotifyChannel::Telegram, "second")
+            .await
+            .unwrap();
+        outbox.dispatch_due().await;
+        assert_eq!(notifier.delivered(), vec!["second"]);
+
+        outbox.retry_dead_letter(&dead[0].id).await.unwrap();
+        assert_eq!(outbox.dispatch_due().await, 1);
+        assert_eq!(notifier.delivered(), vec!["second", "first"]);
+        assert!(outbox.dead_letters().await.is_empty());
+        assert!(outbox.retry_dead_letter(&dead[0].id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_queued_notifications_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Arc::new(FileOutboxStore::new(dir.path().join("outbox.json")));
+        let clock = ManualClock::new();
+
+        let outbox =
+            outbox_with_store(FlakyNotifier::failing(1), clock.clone(), store.clone()).await;
+        outbox
+            .notify(NotifyChannel::Telegram, "risk alert")
+            .await
+            .unwrap();
+        outbox.dispatch_due().await;
+        drop(outbox);
+
+        let notifier = FlakyNotifier::failing(0);
+        let outbox = outbox_with_store(notifier.clone(), clock.clone(), store).await;
+        assert_eq!(outbox.pending_count().await, 1);
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(outbox.shutdown(Duration::from_secs(1)).await, 0);
+        assert_eq!(notifier.delivered(), vec!["risk alert"]);
+    }
+
+    async fn outbox_with_store(
+        notifier: Arc<FlakyNotifier>,
+        clock: Arc<ManualClock>,
+        store: Arc<FileOutboxStore>,
+    ) -> OutboxNotifier {
+        OutboxNotifier::with_clock(notifier, store, OutboxConfig::default(), clock)
+            .await
+            .unwrap()
+    }
+}
@@ -0,0 +1,700 @@
+//! Post-processing for final responses before they reach a consumer
+//!
+//! Each consumer (a notification channel, a web UI, a voice bridge, ...) registers an
+//! ordered [`FormatterChain`] and a [`FormatTarget`] in [`ResponseFormatters`]. The
+//! agent applies every chain to the final response and publishes the results in
+//! `AgentEvent::Response::formatted`; the raw text stays in `content`.
+//!
+//! # Streaming
+//!
+//! [`ResponseFormatters::format_delta`] only runs formatters that implement
+//! [`ResponseFormatter::format_delta`]. Of the built-ins that is just
+//! [`TelegramMarkdownV2Escaper`]. Limitations of the streaming subset:
+//!
+//! - Deltas are escaped as plain text, so markdown (bold, links, code) is shown
+//!   literally instead of being converted to MarkdownV2 entities.
+//! - [`MarkdownNormalizer`], [`DocidLinkRewriter`] and [`PlainTextifier`] need the
+//!   whole response (fences, references and link syntax can span deltas) and are
+//!   skipped.
+//! - Streaming consumers should replace the streamed text with the `formatted`
+//!   entry of the final `Response` event when it arrives.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Characters that must be escaped in Telegram MarkdownV2 text
+const TELEGRAM_RESERVED: &[char] = &[
+    '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!', '\\',
+];
+
+/// Length of a QMD docid
+const DOCID_LEN: usize = 6;
+
+/// Output flavour a consumer expects
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormatTarget {
+    /// CommonMark-style markdown (web UIs, logs)
+    #[default]
+    Markdown,
+    /// Telegram MarkdownV2
+    Telegram,
+    /// No markup at all (voice, SMS)
+    PlainText,
+}
+
+/// A single transformation of a final response
+pub trait ResponseFormatter: Send + Sync {
+    /// Transform a complete response
+    fn format(&self, text: &str, target: FormatTarget) -> String;
+
+    /// Transform one streamed delta without seeing the rest of the response
+    ///
+    /// Returns `None` (the default) when the formatter is not streaming-safe.
+    fn format_delta(&self, delta: &str, target: FormatTarget) -> Option<String> {
+        let _ = (delta, target);
+        None
+    }
+}
+
+/// Ordered list of formatters applied one after another
+#[derive(Clone, Default)]
+pub struct FormatterChain {
+    formatters: Vec<Arc<dyn ResponseFormatter>>,
+}
+
+impl FormatterChain {
+    /// Create an empty chain
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a formatter to the end of the chain
+    pub fn with(mut self, formatter: impl ResponseFormatter + 'static) -> Self {
+        self.formatters.push(Arc::new(formatter));
+        self
+    }
+
+    /// Whether the chain has no formatters
+    pub fn is_empty(&self) -> bool {
+        self.formatters.is_empty()
+    }
+
+    /// Run every formatter over a complete response
+    pub fn format(&self, text: &str, target: FormatTarget) -> String {
+        self.formatters
+            .iter()
+            .fold(text.to_string(), |text, f| f.format(&text, target))
+    }
+
+    /// Run the streaming-safe formatters over a delta, passing it through the rest
+    pub fn format_delta(&self, delta: &str, target: FormatTarget) -> String {
+        self.formatters.iter().fold(delta.to_string(), |delta, f| {
+            f.format_delta(&delta, target).unwrap_or(delta)
+        })
+    }
+}
+
+/// Formatter chains keyed by consumer name
+///
+/// Notification channels are looked up by [`NotifyChannel::name`](crate::infra::notification::NotifyChannel::name).
+#[derive(Clone, Default)]
+pub struct ResponseFormatters {
+    consumers: BTreeMap<String, (FormatTarget, FormatterChain)>,
+}
+
+impl ResponseFormatters {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register (or replace) the chain for a consumer
+    pub fn register(
+        mut self,
+        consumer: impl Into<String>,
+        target: FormatTarget,
+        chain: FormatterChain,
+    ) -> Self {
+        self.consumers.insert(consumer.into(), (target, chain));
+        self
+    }
+
+    /// Whether no consumer is registered
+    pub fn is_empty(&self) -> bool {
+        self.consumers.is_empty()
+    }
+
+    /// Format a complete response for one consumer (`None` when it has no chain)
+    pub fn format(&self, consumer: &str, text: &str) -> Option<String> {
+        let (target, chain) = self.consumers.get(consumer)?;
+        Some(chain.format(text, *target))
+    }
+
+    /// Format a streamed delta for one consumer (`None` when it has no chain)
+    pub fn format_delta(&self, consumer: &str, delta: &str) -> Option<String> {
+        let (target, chain) = self.consumers.get(consumer)?;
+        Some(chain.format_delta(delta, *target))
+    }
+
+    /// Format a complete response for every registered consumer
+    pub fn format_all(&self, text: &str) -> BTreeMap<String, String> {
+        self.consumers
+            .iter()
+            .map(|(name, (target, chain))| (name.clone(), chain.format(text, *target)))
+            .collect()
+    }
+}
+
+/// Cleans up common model output quirks
+///
+/// Clamps heading jumps (e.g. `#` followed by `###`), collapses runs of blank
+/// lines, strips stray XML-like tags (`<thinking>`, `</answer>`), trims trailing
+/// whitespace and closes an unclosed code fence. Code fences are left untouched.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkdownNormalizer;
+
+impl ResponseFormatter for MarkdownNormalizer {
+    fn format(&self, text: &str, _target: FormatTarget) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let mut fence: Option<&str> = None;
+        let mut last_level = 0;
+
+        for line in text.lines() {
+            if let Some(marker) = fence_marker(line) {
+                match fence {
+                    None => fence = Some(marker),
+                    Some(open) if line.trim() == open => fence = None,
+                    Some(_) => {}
+                }
+                lines.push(line.trim_end().to_string());
+                continue;
+            }
+            if fence.is_some() {
+                lines.push(line.to_string());
+                continue;
+            }
+
+            let line = strip_xml_tags(line);
+            let line = line.trim_end();
+            if line.trim().is_empty() {
+                if lines.last().is_some_and(|l| !l.is_empty()) {
+                    lines.push(String::new());
+                }
+                continue;
+            }
+
+            match heading(line) {
+                Some((level, rest)) => {
+                    let level = if last_level == 0 {
+                        level
+                    } else {
+                        level.min(last_level + 1)
+                    };
+                    last_level = level;
+                    lines.push(format!("{} {}", "#".repeat(level), rest));
+                }
+                None => lines.push(line.to_string()),
+            }
+        }
+
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+        if let Some(open) = fence {
+            lines.push(open.to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+/// Converts markdown to Telegram MarkdownV2
+///
+/// Code spans, fenced blocks, links and `**bold**` become MarkdownV2 entities,
+/// headings become bold lines, and every other reserved character is escaped.
+/// Single-character emphasis is shown literally.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TelegramMarkdownV2Escaper;
+
+impl ResponseFormatter for TelegramMarkdownV2Escaper {
+    fn format(&self, text: &str, _target: FormatTarget) -> String {
+        let mut lines = Vec::new();
+        let mut fence: Option<&str> = None;
+
+        for line in text.lines() {
+            if let Some(marker) = fence_marker(line) {
+                match fence {
+                    None => {
+                        fence = Some(marker);
+                        let lang = line.trim()[marker.len()..].trim();
+                        let lang: String = lang
+                            .chars()
+                            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-'))
+                            .collect();
+                        lines.push(format!("```{}", lang));
+                        continue;
+                    }
+                    Some(open) if line.trim() == open => {
+                        fence = None;
+                        lines.push("```".to_string());
+                        continue;
+                    }
+                    Some(_) => {}
+                }
+            }
+            if fence.is_some() {
+                lines.push(escape_telegram_code(line));
+            } else if let Some((_, rest)) = heading(line) {
+                lines.push(format!("*{}*", escape_telegram(rest)));
+            } else {
+                lines.push(telegram_inline(line));
+            }
+        }
+        if fence.is_some() {
+            lines.push("```".to_string());
+        }
+        lines.join("\n")
+    }
+
+    fn format_delta(&self, delta: &str, _target: FormatTarget) -> Option<String> {
+        Some(escape_telegram(delta))
+    }
+}
+
+/// Looks up document titles for [`DocidLinkRewriter`]
+pub trait DocidResolver: Send + Sync {
+    /// Title of the document with this docid, or `None` if it does not exist
+    fn title(&self, docid: &str) -> Option<String>;
+}
+
+impl<F> DocidResolver for F
+where
+    F: Fn(&str) -> Option<String> + Send + Sync,
+{
+    fn title(&self, docid: &str) -> Option<String> {
+        self(docid)
+    }
+}
+
+/// Rewrites docid references (`#a1b2c3` or `aagt://#a1b2c3`) into links
+///
+/// `url_template` may contain `{docid}`. References that don't resolve are left
+/// as plain text, as is anything inside code.
+pub struct DocidLinkRewriter {
+    resolver: Arc<dyn DocidResolver>,
+    url_template: String,
+}
+
+impl DocidLinkRewriter {
+    /// Create a rewriter that links to `url_template` (e.g. `/docs/{docid}`)
+    pub fn new(resolver: Arc<dyn DocidResolver>, url_template: impl Into<String>) -> Self {
+        Self {
+            resolver,
+            url_template: url_template.into(),
+        }
+    }
+
+    fn rewrite(&self, text: &str, target: FormatTarget) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(pos) = rest.find('#') {
+            let (before, from_hash) = rest.split_at(pos);
+            let hex = &from_hash[1..];
+            let docid_end = hex
+                .char_indices()
+                .find(|(_, c)| !c.is_ascii_alphanumeric())
+                .map_or(hex.len(), |(i, _)| i);
+            let prefixed = before.ends_with("aagt://");
+            let boundary = prefixed
+                || !before
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| c.is_alphanumeric() || matches!(c, '#' | '/' | '(' | '&'));
+
+            let resolved = (boundary
+                && docid_end == DOCID_LEN
+                && hex[..DOCID_LEN].chars().all(|c| c.is_ascii_hexdigit()))
+            .then(|| hex[..DOCID_LEN].to_lowercase())
+            .and_then(|docid| Some((self.resolver.title(&docid)?, docid)));
+
+            match resolved {
+                Some((title, docid)) => {
+                    out.push_str(before.strip_suffix("aagt://").unwrap_or(before));
+                    let title = title.replace(['[', ']'], "");
+                    let url = self.url_template.replace("{docid}", &docid);
+                    match target {
+                        FormatTarget::PlainText => out.push_str(&format!("{} ({})", title, url)),
+                        _ => out.push_str(&format!("[{}]({})", title, url)),
+                    }
+                    rest = &hex[DOCID_LEN..];
+                }
+                None => {
+                    out.push_str(before);
+                    out.push('#');
+                    rest = hex;
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+impl ResponseFormatter for DocidLinkRewriter {
+    fn format(&self, text: &str, target: FormatTarget) -> String {
+        map_outside_code(text, |segment| self.rewrite(segment, target))
+    }
+}
+
+/// Strips markdown for voice and SMS consumers
+///
+/// Drops fence lines (keeping their content), heading and list markers, block
+/// quotes, table separators and emphasis; links and images become their text.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlainTextifier;
+
+impl ResponseFormatter for PlainTextifier {
+    fn format(&self, text: &str, _target: FormatTarget) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let mut in_fence = false;
+
+        for line in text.lines() {
+            if fence_marker(line).is_some() {
+                in_fence = !in_fence;
+                continue;
+            }
+            if in_fence {
+                lines.push(line.to_string());
+                continue;
+            }
+
+            let mut line = line.trim();
+            if let Some((_, rest)) = heading(line) {
+                line = rest;
+            }
+            while let Some(rest) = line.strip_prefix('>') {
+                line = rest.trim_start();
+            }
+            for marker in ["- ", "* ", "+ "] {
+                if let Some(rest) = line.strip_prefix(marker) {
+                    line = rest;
+                }
+            }
+            if line.starts_with('|') && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' ')) {
+                continue;
+            }
+
+            let line = plain_inline(line);
+            if line.is_empty() {
+                if lines.last().is_some_and(|l| !l.is_empty()) {
+                    lines.push(String::new());
+                }
+                continue;
+            }
+            lines.push(line);
+        }
+
+        while lines.last().is_some_and(|l| l.is_empty()) {
+            lines.pop();
+        }
+        lines.join("\n")
+    }
+}
+
+/// Opening/closing code fence marker (```` ``` ```` or `~~~`) on this line
+fn fence_marker(line: &str) -> Option<&'static str> {
+    let line = line.trim_start();
+    if line.starts_with("```") {
+        Some("```")
+    } else if line.starts_with("~~~") {
+        Some("~~~")
+    } else {
+        None
+    }
+}
+
+/// ATX heading level and text
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &trimmed[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+/// Remove `<tag>`, `</tag>` and `<tag/>` outside inline code
+///
+/// Only tags at a word boundary are removed so generics like `Vec<T>` survive.
+fn strip_xml_tags(line: &str) -> String {
+    map_outside_code(line, |segment| {
+        let mut out = String::with_capacity(segment.len());
+        let mut rest = segment;
+        while let Some(pos) = rest.find('<') {
+            let (before, from_lt) = rest.split_at(pos);
+            out.push_str(before);
+            let at_boundary = !out.chars().next_back().is_some_and(|c| c.is_alphanumeric());
+            match from_lt.find('>') {
+                Some(end) if at_boundary && is_tag(&from_lt[1..end]) => rest = &from_lt[end + 1..],
+                _ => {
+                    out.push('<');
+                    rest = &from_lt[1..];
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    })
+}
+
+/// Whether the text between `<` and `>` looks like a bare tag name
+fn is_tag(inner: &str) -> bool {
+    let name = inner.strip_prefix('/').unwrap_or(inner);
+    let name = name.strip_suffix('/').unwrap_or(name);
+    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | ':'))
+}
+
+/// Apply `f` to the parts of `text` that are outside code fences and inline code
+fn map_outside_code(text: &str, f: impl Fn(&str) -> String) -> String {
+    let mut out = Vec::new();
+    let mut in_fence = false;
+    for line in text.split('\n') {
+        if fence_marker(line).is_some() {
+            in_fence = !in_fence;
+            out.push(line.to_string());
+            continue;
+        }
+        if in_fence {
+            out.push(line.to_string());
+            continue;
+        }
+        let mut mapped = String::with_capacity(line.len());
+        for (i, part) in line.split('`').enumerate() {
+            if i > 0 {
+                mapped.push('`');
+            }
+            // Odd parts are inside a code span, unless the closing backtick is missing
+            if i % 2 == 1 && line.matches('`').count() > i {
+                mapped.push_str(part);
+            } else {
+                mapped.push_str(&f(part));
+            }
+        }
+        out.push(mapped);
+    }
+    out.join("\n")
+}
+
+/// `[text](url)` at the start of `s`: (text, url, bytes consumed)
+fn parse_link(s: &str) -> Option<(&str, &str, usize)> {
+    let close = s.find(']')?;
+    let text = &s[1..close];
+    if text.contains('[') {
+        return None;
+    }
+    let after = s[close + 1..].strip_prefix('(')?;
+    let end = after.find(')')?;
+    Some((text, &after[..end], close + 2 + end + 1))
+}
+
+fn escape_telegram(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if TELEGRAM_RESERVED.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn escape_telegram_code(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('`', "\\`")
+}
+
+/// Convert one line of inline markdown to MarkdownV2
+fn telegram_inline(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                out.push('`');
+                out.push_str(&escape_telegram_code(&rest[1..=end]));
+                out.push('`');
+                rest = &rest[end + 2..];
+                continue;
+            }
+        } else if c == '[' {
+            if let Some((text, url, len)) = parse_link(rest) {
+                let url = url.replace('\\', "\\\\").replace(')', "\\)");
+                out.push_str(&format!("[{}]({})", escape_telegram(text), url));
+                rest = &rest[len..];
+                continue;
+            }
+        } else if let Some(after) = rest.strip_prefix("**") {
+            if let Some(end) = after.find("**").filter(|&end| end > 0) {
+                out.push_str(&format!("*{}*", escape_telegram(&after[..end])));
+                rest = &after[end + 2..];
+                continue;
+            }
+        }
+        out.push_str(&escape_telegram(&rest[..c.len_utf8()]));
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Strip inline markdown from one line
+fn plain_inline(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        let link_start = if rest.starts_with("![") { 1 } else { 0 };
+        if c == '[' || link_start == 1 {
+            if let Some((text, _, len)) = parse_link(&rest[link_start..]) {
+                out.push_str(text);
+                rest = &rest[link_start + len..];
+                continue;
+            }
+        }
+        if let Some(after) = ["**", "__", "~~"].iter().find_map(|m| rest.strip_prefix(m)) {
+            rest = after;
+            continue;
+        }
+        if c != '`' {
+            out.push(c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_markdown_normalizer_pathological() {
+        let input = "<thinking>\n### Title\n\n\n\n##### Jumped\n# Back   \ntext with </answer> tag and Vec<T>\n```rust\nlet x = 1;\n\n\n<tag>\n";
+        let expected = "### Title\n\n#### Jumped\n# Back\ntext with  tag and Vec<T>\n```rust\nlet x = 1;\n\n\n<tag>\n```";
+        assert_eq!(
+            MarkdownNormalizer.format(input, FormatTarget::Markdown),
+            expected
+        );
+
+        // Degenerate input doesn't panic or grow
+        assert_eq!(MarkdownNormalizer.format("", FormatTarget::Markdown), "");
+        assert_eq!(
+            MarkdownNormalizer.format("\n\n<a><b></b>\n\n", FormatTarget::Markdown),
+            ""
+        );
+        assert_eq!(
+            MarkdownNormalizer.format("#######", FormatTarget::Markdown),
+            "#######"
+        );
+        assert_eq!(
+            MarkdownNormalizer.format("`<code>` <", FormatTarget::Markdown),
+            "`<code>` <"
+        );
+    }
+
+    #[test]
+    fn test_telegram_escaper() {
+        let input = "## Price update\nSOL is up 5.2% (see [chart](https://x.io/a_b)) - **big** move!\nUse `a_b*c` now\n```py\nprint(`x`)";
+        let expected = "*Price update*\nSOL is up 5\\.2% \\(see [chart](https://x.io/a_b)\\) \\- *big* move\\!\nUse `a_b*c` now\n```py\nprint(\\`x\\`)\n```";
+        assert_eq!(
+            TelegramMarkdownV2Escaper.format(input, FormatTarget::Telegram),
+            expected
+        );
+
+        // Unterminated entities fall back to escaped text
+        assert_eq!(
+            TelegramMarkdownV2Escaper.format("**open [link `tick", FormatTarget::Telegram),
+            "\\*\\*open \\[link \\`tick"
+        );
+        assert_eq!(
+            TelegramMarkdownV2Escaper.format_delta("1.5 **x**", FormatTarget::Telegram),
+            Some("1\\.5 \\*\\*x\\*\\*".to_string())
+        );
+    }
+
+    #[test]
+    fn test_docid_link_rewriter() {
+        let titles: HashMap<String, String> =
+            HashMap::from([("a1b2c3".to_string(), "SOL [strategy] notes".to_string())]);
+        let resolver = move |docid: &str| titles.get(docid).cloned();
+        let rewriter = DocidLinkRewriter::new(Arc::new(resolver), "/docs/{docid}");
+
+        let input =
+            "See #a1b2c3, aagt://#A1B2C3 and #ffffff.\nNot `#a1b2c3`, x#a1b2c3 or #a1b2c3d.";
+        assert_eq!(
+            rewriter.format(input, FormatTarget::Markdown),
+            "See [SOL strategy notes](/docs/a1b2c3), [SOL strategy notes](/docs/a1b2c3) and #ffffff.\nNot `#a1b2c3`, x#a1b2c3 or #a1b2c3d."
+        );
+        assert_eq!(
+            rewriter.format("Source: #a1b2c3", FormatTarget::PlainText),
+            "Source: SOL strategy notes (/docs/a1b2c3)"
+        );
+        // Missing docids stay plain text
+        assert_eq!(
+            rewriter.format("aagt://#000000", FormatTarget::Markdown),
+            "aagt://#000000"
+        );
+    }
+
+    #[test]
+    fn test_plain_textifier() {
+        let input = "# Summary\n> **Note:** read [the docs](https://x.io) ![chart](c.png)\n\n\n- item `one`\n| a | b |\n|---|---|\n```\nraw **code**\n```";
+        assert_eq!(
+            PlainTextifier.format(input, FormatTarget::PlainText),
+            "Summary\nNote: read the docs chart\n\nitem one\n| a | b |\nraw **code**"
+        );
+    }
+
+    #[test]
+    fn test_per_consumer_chain_selection() {
+        let formatters = ResponseFormatters::new()
+            .register(
+                "telegram",
+                FormatTarget::Telegram,
+                FormatterChain::new()
+                    .with(MarkdownNormalizer)
+                    .with(TelegramMarkdownV2Escaper),
+            )
+            .register(
+                "voice",
+                FormatTarget::PlainText,
+                FormatterChain::new().with(PlainTextifier),
+            )
+            .register(
+                "web",
+                FormatTarget::Markdown,
+                FormatterChain::new().with(MarkdownNormalizer),
+            );
+
+        let raw = "## Done!\n\n\n**SOL** bought.";
+        let all = formatters.format_all(raw);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all["telegram"], "*Done\\!*\n\n*SOL* bought\\.");
+        assert_eq!(all["voice"], "Done!\n\nSOL bought.");
+        assert_eq!(all["web"], "## Done!\n\n**SOL** bought.");
+        assert_eq!(formatters.format("discord", raw), None);
+
+        // Only the escaper runs on deltas
+        assert_eq!(
+            formatters.format_delta("telegram", "## a.").unwrap(),
+            "\\#\\# a\\."
+        );
+        assert_eq!(formatters.format_delta("voice", "## a.").unwrap(), "## a.");
+    }
+}
@@ -0,0 +1,1159 @@
+//! Unified data retention
+//!
+//! Data piles up in several stores: sessions, short-term conversation memory,
+//! long-term memory vectors, imported documents, replay artifacts and log
+//! files. A [`RetentionPolicy`] sets limits per [`DataClass`] (maximum age
+//! and/or total bytes, minus exempt tags). [`RetentionEnforcer`] applies them
+//! through one [`RetentionTarget`] per store, oldest item first.
+//!
+//! Integrity rules:
+//! - Documents take their vectors with them.
+//! - Artifacts lose their index row together with their file.
+//! - Items still referenced are skipped: sessions awaiting approval, or
+//!   anything a [`ReferenceGuard`] vouches for, such as open task-board items.
+//!
+//! Per-user overrides replace the class policy for that user. A user on legal
+//! hold is never touched, and their data doesn't count towards byte caps.
+//! Each run produces a [`RetentionReport`], which is kept in the enforcer's
+//! journal and sent through the notifier. A dry run only lists what would go.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::agent::memory::{Memory, ShortTermMemory};
+use crate::agent::multi_agent::{TaskBoard, TaskFilter, TaskState};
+use crate::agent::replay::FileArtifactStore;
+use crate::agent::session::SessionStatus;
+use crate::error::Result;
+use crate::infra::notification::{Notifier, NotifyChannel};
+use crate::infra::validation::{ConfigIssue, Validate};
+use crate::knowledge::consolidation::{CREATED_AT, PINNED, USER_ID};
+use crate::knowledge::rag::VectorStore;
+use crate::knowledge::store::InMemoryVectorStore;
+
+/// Metadata key tying a vector to the imported document (file name) it came from
+pub const DOCUMENT: &str = "document";
+
+/// Metadata key holding comma-separated tags on a vector
+pub const TAGS: &str = "tags";
+
+/// Kinds of retained data, in the order they are enforced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataClass {
+    /// Persisted agent sessions
+    Sessions,
+    /// Conversation buffers
+    ShortTermMemory,
+    /// Long-term memory vectors
+    LongTermMemory,
+    /// Imported documents and their vectors
+    Documents,
+    /// Replay artifacts
+    Artifacts,
+    /// Log files
+    Logs,
+}
+
+impl fmt::Display for DataClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Sessions => "sessions",
+            Self::ShortTermMemory => "short_term_memory",
+            Self::LongTermMemory => "long_term_memory",
+            Self::Documents => "documents",
+            Self::Artifacts => "artifacts",
+            Self::Logs => "logs",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Limits for one data class
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClassPolicy {
+    /// Delete items at least this old
+    pub max_age: Option<Duration>,
+    /// Delete oldest items while the class holds more than this
+    pub max_total_bytes: Option<u64>,
+    /// Items carrying any of these tags are kept
+    pub exempt_tags: Vec<String>,
+}
+
+impl ClassPolicy {
+    /// Delete items at least `age` old
+    pub fn max_age(mut self, age: Duration) -> Self {
+        self.max_age = Some(age);
+        self
+    }
+
+    /// Keep the class under `bytes`
+    pub fn max_total_bytes(mut self, bytes: u64) -> Self {
+        self.max_total_bytes = Some(bytes);
+        self
+    }
+
+    /// Never delete items tagged `tag`
+    pub fn exempt_tag(mut self, tag: impl Into<String>) -> Self {
+        self.exempt_tags.push(tag.into());
+        self
+    }
+}
+
+/// Retention limits for every data class
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+    /// Class-wide limits; classes without one are kept forever
+    pub classes: BTreeMap<DataClass, ClassPolicy>,
+    /// Per-user limits that replace the class-wide ones
+    pub user_overrides: HashMap<String, BTreeMap<DataClass, ClassPolicy>>,
+    /// Users whose data is never deleted
+    pub legal_holds: HashSet<String>,
+}
+
+impl RetentionPolicy {
+    /// Set the limits for `class`
+    pub fn class(mut self, class: DataClass, policy: ClassPolicy) -> Self {
+        self.classes.insert(class, policy);
+        self
+    }
+
+    /// Set `user`'s limits for `class`
+    pub fn user_override(
+        mut self,
+        user: impl Into<String>,
+        class: DataClass,
+        policy: ClassPolicy,
+    ) -> Self {
+        self.user_overrides
+            .entry(user.into())
+            .or_default()
+            .insert(class, policy);
+        self
+    }
+
+    /// Exempt all of `user`'s data
+    pub fn legal_hold(mut self, user: impl Into<String>) -> Self {
+        self.legal_holds.insert(user.into());
+        self
+    }
+
+    /// Limits governing an item of `class` owned by `user`, and whose they are
+    fn policy_for<'a>(
+        &self,
+        class: DataClass,
+        user: Option<&'a str>,
+    ) -> Option<(&ClassPolicy, Option<&'a str>)> {
+        let user_policy = user.and_then(|u| {
+            self.user_overrides
+                .get(u)
+                .and_then(|o| o.get(&class))
+                .map(|p| (p, Some(u)))
+        });
+        user_policy.or_else(|| self.classes.get(&class).map(|p| (p, None)))
+    }
+}
+
+impl Validate for RetentionPolicy {
+    fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        let overrides = self.user_overrides.iter().flat_map(|(user, classes)| {
+            classes
+                .iter()
+                .map(move |(class, p)| (format!("retention.users.{}.{}", user, class), p))
+        });
+        let classes = self
+            .classes
+            .iter()
+            .map(|(class, p)| (format!("retention.{}", class), p));
+        for (path, policy) in classes.chain(overrides) {
+            if policy.max_age.is_none() && policy.max_total_bytes.is_none() {
+                issues.push(
+                    ConfigIssue::warning(
+                        &path,
+                        "no max_age or max_total_bytes; nothing is deleted",
+                    )
+                    .suggest("remove the entry or set a limit"),
+                );
+            }
+            if policy.max_total_bytes == Some(0) {
+                issues.push(ConfigIssue::warning(
+                    format!("{}.max_total_bytes", path),
+                    "0 deletes every item that isn't exempt",
+                ));
+            }
+        }
+        let mut held: Vec<_> = self
+            .legal_holds
+            .iter()
+            .filter(|u| self.user_overrides.contains_key(*u))
+            .collect();
+        held.sort();
+        for user in held {
+            issues.push(ConfigIssue::warning(
+                format!("retention.users.{}", user),
+                "user is on legal hold; their overrides are ignored",
+            ));
+        }
+        issues
+    }
+}
+
+/// One deletable item in a store
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetainedItem {
+    /// Store-specific ID
+    pub id: String,
+    /// Owning user, when the store tracks one
+    pub user_id: Option<String>,
+    /// Age reference: creation or last activity
+    pub created_at: DateTime<Utc>,
+    /// Approximate size
+    pub bytes: u64,
+    /// Tags matched against exempt tags
+    pub tags: Vec<String>,
+    /// Why the store itself won't let this go, e.g. a pending approval
+    pub referenced_by: Option<String>,
+}
+
+impl RetainedItem {
+    /// An item with no owner, tags or references
+    pub fn new(id: impl Into<String>, created_at: DateTime<Utc>, bytes: u64) -> Self {
+        Self {
+            id: id.into(),
+            user_id: None,
+            created_at,
+            bytes,
+            tags: Vec::new(),
+            referenced_by: None,
+        }
+    }
+}
+
+/// A store the enforcer can walk and delete from
+#[async_trait]
+pub trait RetentionTarget: Send + Sync {
+    /// Class of everything in this store
+    fn class(&self) -> DataClass;
+
+    /// Every item currently stored
+    async fn items(&self) -> Result<Vec<RetainedItem>>;
+
+    /// Delete `item` and anything that depends on it; returns the bytes freed
+    async fn delete(&self, item: &RetainedItem) -> Result<u64>;
+}
+
+/// Cross-store reference check run before anything is deleted
+#[async_trait]
+pub trait ReferenceGuard: Send + Sync {
+    /// Why `item` must be kept, or `None` if it may go
+    async fn reference(&self, class: DataClass, item: &RetainedItem) -> Option<String>;
+}
+
+/// Keeps sessions named by an open or claimed task's `session_id` payload field
+pub struct OpenTaskGuard {
+    board: Arc<TaskBoard>,
+}
+
+impl OpenTaskGuard {
+    /// Guard sessions referenced from `board`
+    pub fn new(board: Arc<TaskBoard>) -> Self {
+        Self { board }
+    }
+}
+
+#[async_trait]
+impl ReferenceGuard for OpenTaskGuard {
+    async fn reference(&self, class: DataClass, item: &RetainedItem) -> Option<String> {
+        if class != DataClass::Sessions {
+            return None;
+        }
+        for state in [TaskState::Open, TaskState::Claimed] {
+            let tasks = self.board.list(&TaskFilter::new().with_state(state)).await;
+            if let Some(task) = tasks
+                .iter()
+                .find(|t| t.payload.get("session_id").and_then(|v| v.as_str()) == Some(&item.id))
+            {
+                return Some(format!("task {}", task.id));
+            }
+        }
+        None
+    }
+}
+
+/// Sessions persisted in a [`Memory`]; sessions awaiting approval are kept
+pub struct SessionTarget {
+    memory: Arc<dyn Memory>,
+}
+
+impl SessionTarget {
+    /// Sessions stored in `memory`
+    pub fn new(memory: Arc<dyn Memory>) -> Self {
+        Self { memory }
+    }
+}
+
+#[async_trait]
+impl RetentionTarget for SessionTarget {
+    fn class(&self) -> DataClass {
+        DataClass::Sessions
+    }
+
+    async fn items(&self) -> Result<Vec<RetainedItem>> {
+        Ok(self
+            .memory
+            .list_sessions()
+            .await?
+            .into_iter()
+            .map(|session| {
+                let bytes = serde_json::to_vec(&session).map_or(0, |b| b.len() as u64);
+                let mut item = RetainedItem::new(&session.id, session.updated_at, bytes);
+                if matches!(session.status, SessionStatus::AwaitingApproval { .. }) {
+                    item.referenced_by = Some("unresolved approval".to_string());
+                }
+                item
+            })
+            .collect())
+    }
+
+    async fn delete(&self, item: &RetainedItem) -> Result<u64> {
+        let deleted = self.memory.delete_session(&item.id).await?;
+        Ok(if deleted { item.bytes } else { 0 })
+    }
+}
+
+/// Conversation buffers in [`ShortTermMemory`], aged by idle time
+pub struct ShortTermTarget {
+    memory: Arc<ShortTermMemory>,
+}
+
+impl ShortTermTarget {
+    /// Conversations in `memory`
+    pub fn new(memory: Arc<ShortTermMemory>) -> Self {
+        Self { memory }
+    }
+}
+
+#[async_trait]
+impl RetentionTarget for ShortTermTarget {
+    fn class(&self) -> DataClass {
+        DataClass::ShortTermMemory
+    }
+
+    async fn items(&self) -> Result<Vec<RetainedItem>> {
+        let now = Utc::now();
+        Ok(self
+            .memory
+            .conversations()
+            .into_iter()
+            .map(|c| {
+                let idle = chrono::Duration::from_std(c.idle).unwrap_or_default();
+                let mut item = RetainedItem::new(c.key, now - idle, c.bytes);
+                item.user_id = Some(c.user_id);
+                item
+            })
+            .collect())
+    }
+
+    async fn delete(&self, item: &RetainedItem) -> Result<u64> {
+        let removed = self.memory.remove_conversation(&item.id).await?;
+        Ok(if removed { item.bytes } else { 0 })
+    }
+}
+
+/// Long-term memory vectors, aged by their `created_at` metadata
+///
+/// Vectors carrying [`DOCUMENT`] belong to imported documents and are left to
+/// the documents target.
+pub struct VectorTarget {
+    store: Arc<InMemoryVectorStore>,
+}
+
+impl VectorTarget {
+    /// Memories in `store`
+    pub fn new(store: Arc<InMemoryVectorStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl RetentionTarget for VectorTarget {
+    fn class(&self) -> DataClass {
+        DataClass::LongTermMemory
+    }
+
+    async fn items(&self) -> Result<Vec<RetainedItem>> {
+        let now = Utc::now();
+        Ok(self
+            .store
+            .entries()
+            .into_iter()
+            .filter(|e| !e.metadata.contains_key(DOCUMENT))
+            .map(|e| {
+                let created_at = e
+                    .metadata
+                    .get(CREATED_AT)
+                    .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                    .map_or(now, |t| t.with_timezone(&Utc));
+                let mut item = RetainedItem::new(&e.id, created_at, e.content.len() as u64);
+                item.user_id = e.metadata.get(USER_ID).cloned();
+                if e.metadata.get(PINNED).is_some_and(|p| p == "true") {
+                    item.tags.push(PINNED.to_string());
+                }
+                if let Some(tags) = e.metadata.get(TAGS) {
+                    item.tags
+                        .extend(tags.split(',').map(|t| t.trim().to_string()));
+                }
+                item
+            })
+            .collect())
+    }
+
+    async fn delete(&self, item: &RetainedItem) -> Result<u64> {
+        self.store.delete(&item.id).await?;
+        Ok(item.bytes)
+    }
+}
+
+/// Files in a directory (imported documents or logs), aged by modification time
+pub struct FileTarget {
+    class: DataClass,
+    dir: PathBuf,
+    prefix: Option<String>,
+    keep_newest: bool,
+    vectors: Option<Arc<InMemoryVectorStore>>,
+}
+
+impl FileTarget {
+    /// Files directly inside `dir`
+    pub fn new(class: DataClass, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            class,
+            dir: dir.into(),
+            prefix: None,
+            keep_newest: false,
+            vectors: None,
+        }
+    }
+
+    /// Only files whose name starts with `prefix`
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Never delete the newest file (the one a log appender is writing)
+    pub fn keep_newest(mut self) -> Self {
+        self.keep_newest = true;
+        self
+    }
+
+    /// Delete vectors whose [`DOCUMENT`] metadata names a deleted file
+    pub fn cascade_vectors(mut self, store: Arc<InMemoryVectorStore>) -> Self {
+        self.vectors = Some(store);
+        self
+    }
+}
+
+#[async_trait]
+impl RetentionTarget for FileTarget {
+    fn class(&self) -> DataClass {
+        self.class
+    }
+
+    async fn items(&self) -> Result<Vec<RetainedItem>> {
+        let mut items = Vec::new();
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(items),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !meta.is_file() || self.prefix.as_ref().is_some_and(|p| !name.starts_with(p)) {
+                continue;
+            }
+            let modified: DateTime<Utc> = meta.modified().unwrap_or(SystemTime::now()).into();
+            items.push(RetainedItem::new(name, modified, meta.len()));
+        }
+        if self.keep_newest {
+            if let Some(newest) = items.iter_mut().max_by_key(|i| i.created_at) {
+                newest.referenced_by = Some("active file".to_string());
+            }
+        }
+        Ok(items)
+    }
+
+    async fn delete(&self, item: &RetainedItem) -> Result<u64> {
+        // Vectors first: a crash in between leaves a file without vectors, never the reverse
+        if let Some(store) = &self.vectors {
+            for entry in store.entries() {
+                if entry.metadata.get(DOCUMENT) == Some(&item.id) {
+                    store.delete(&entry.id).await?;
+                }
+            }
+        }
+        match std::fs::remove_file(self.dir.join(&item.id)) {
+            Ok(()) => Ok(item.bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Artifacts in a [`FileArtifactStore`], plus files its index has lost track of
+pub struct ArtifactTarget {
+    store: Arc<FileArtifactStore>,
+}
+
+impl ArtifactTarget {
+    /// Artifacts in `store`
+    pub fn new(store: Arc<FileArtifactStore>) -> Self {
+        Self { store }
+    }
+}
+
+/// ID prefix for files with no index row
+const ORPHAN: &str = "orphan:";
+
+#[async_trait]
+impl RetentionTarget for ArtifactTarget {
+    fn class(&self) -> DataClass {
+        DataClass::Artifacts
+    }
+
+    async fn items(&self) -> Result<Vec<RetainedItem>> {
+        let records = self.store.records()?;
+        let indexed: HashSet<_> = records.iter().map(|r| r.file.clone()).collect();
+        let mut items: Vec<_> = records
+            .into_iter()
+            .map(|r| {
+                let mut item = RetainedItem::new(r.reference, r.created_at, r.bytes);
+                item.user_id = r.user_id;
+                item
+            })
+            .collect();
+        let orphans = FileTarget::new(DataClass::Artifacts, self.store.dir())
+            .items()
+            .await?;
+        items.extend(
+            orphans
+                .into_iter()
+                .filter(|f| f.id != FileArtifactStore::INDEX && !indexed.contains(&f.id))
+                .map(|f| RetainedItem {
+                    id: format!("{}{}", ORPHAN, f.id),
+                    ..f
+                }),
+        );
+        Ok(items)
+    }
+
+    async fn delete(&self, item: &RetainedItem) -> Result<u64> {
+        match item.id.strip_prefix(ORPHAN) {
+            Some(file) => {
+                std::fs::remove_file(self.store.dir().join(file))?;
+                Ok(item.bytes)
+            }
+            None => self.store.remove(&item.id),
+        }
+    }
+}
+
+/// Per-class outcome of a run
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ClassReport {
+    /// Items looked at
+    pub examined: usize,
+    /// Items deleted (or that would be, in a dry run)
+    pub deleted: usize,
+    /// Bytes freed (or that would be)
+    pub bytes_reclaimed: u64,
+    /// Items past policy kept because something references them
+    pub skipped_referenced: usize,
+    /// Items kept for a legal hold
+    pub held: usize,
+    /// Items kept for an exempt tag
+    pub exempt: usize,
+}
+
+/// Why an item was deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionReason {
+    /// Older than `max_age`
+    MaxAge,
+    /// Oldest while over `max_total_bytes`
+    MaxTotalBytes,
+}
+
+/// One deleted (or, in a dry run, deletable) item
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeletedItem {
+    /// Data class
+    pub class: DataClass,
+    /// Item ID
+    pub id: String,
+    /// Owning user
+    pub user_id: Option<String>,
+    /// Item size
+    pub bytes: u64,
+    /// Which limit it broke
+    pub reason: DeletionReason,
+}
+
+/// Outcome of one enforcement run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RetentionReport {
+    /// Time the policy was evaluated against
+    pub at: DateTime<Utc>,
+    /// Whether nothing was actually deleted
+    pub dry_run: bool,
+    /// Counts per class
+    pub classes: BTreeMap<DataClass, ClassReport>,
+    /// Every deleted (or deletable) item, oldest first per class
+    pub deleted: Vec<DeletedItem>,
+    /// Items kept for references, with the reason
+    pub skipped: Vec<(DataClass, String, String)>,
+    /// Failures; the run continues past them
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for RetentionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let deleted: usize = self.classes.values().map(|c| c.deleted).sum();
+        let bytes: u64 = self.classes.values().map(|c| c.bytes_reclaimed).sum();
+        write!(
+            f,
+            "Retention{}: {} item{} {}deleted, {} bytes reclaimed, {} kept for references, {} error{}",
+            if self.dry_run { " (dry run)" } else { "" },
+            deleted,
+            if deleted == 1 { "" } else { "s" },
+            if self.dry_run { "would be " } else { "" },
+            bytes,
+            self.skipped.len(),
+            self.errors.len(),
+            if self.errors.len() == 1 { "" } else { "s" },
+        )?;
+        for (class, report) in &self.classes {
+            write!(
+                f,
+                "\n  {}: {}/{} deleted, {} bytes, {} referenced, {} held, {} exempt",
+                class,
+                report.deleted,
+                report.examined,
+                report.bytes_reclaimed,
+                report.skipped_referenced,
+                report.held,
+                report.exempt
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Applies a [`RetentionPolicy`] to every registered store
+pub struct RetentionEnforcer {
+    policy: RetentionPolicy,
+    targets: Vec<Arc<dyn RetentionTarget>>,
+    guards: Vec<Arc<dyn ReferenceGuard>>,
+    dry_run: bool,
+    notifier: Option<(Arc<dyn Notifier>, NotifyChannel)>,
+    journal: Mutex<Vec<RetentionReport>>,
+}
+
+impl RetentionEnforcer {
+    /// Enforce `policy`; add stores with [`RetentionEnforcer::with_target`]
+    pub fn new(policy: RetentionPolicy) -> Self {
+        Self {
+            policy,
+            targets: Vec::new(),
+            guards: Vec::new(),
+            dry_run: false,
+            notifier: None,
+            journal: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Add a store
+    pub fn with_target(mut self, target: impl RetentionTarget + 'static) -> Self {
+        self.targets.push(Arc::new(target));
+        self
+    }
+
+    /// Add a reference check
+    pub fn with_guard(mut self, guard: impl ReferenceGuard + 'static) -> Self {
+        self.guards.push(Arc::new(guard));
+        self
+    }
+
+    /// Only report what would be deleted
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Send each report through `notifier`
+    pub fn with_notifier(mut self, notifier: Arc<dyn Notifier>, channel: NotifyChannel) -> Self {
+        self.notifier = Some((notifier, channel));
+        self
+    }
+
+    /// Every report produced by this enforcer
+    pub fn journal(&self) -> Vec<RetentionReport> {
+        self.journal.lock().clone()
+    }
+
+    /// Enforce the policy now
+    pub async fn run(&self) -> Result<RetentionReport> {
+        self.run_at(Utc::now()).await
+    }
+
+    /// Enforce the policy as of `now`
+    pub async fn run_at(&self, now: DateTime<Utc>) -> Result<RetentionReport> {
+        let mut report = RetentionReport {
+            at: now,
+            dry_run: self.dry_run,
+            classes: BTreeMap::new(),
+            deleted: Vec::new(),
+            skipped: Vec::new(),
+            errors: Vec::new(),
+        };
+        let mut targets = self.targets.clone();
+        targets.sort_by_key(|t| t.class());
+        for target in targets {
+            if let Err(e) = self.enforce(target.as_ref(), now, &mut report).await {
+                warn!("Retention for {} failed: {}", target.class(), e);
+                report.errors.push(format!("{}: {}", target.class(), e));
+            }
+        }
+
+        info!("{}", report);
+        self.journal.lock().push(report.clone());
+        if let Some((notifier, channel)) = &self.notifier {
+            if let Err(e) = notifier.notify(channel.clone(), &report.to_string()).await {
+                warn!("Failed to send retention report: {}", e);
+            }
+        }
+        Ok(report)
+    }
+
+    async fn enforce(
+        &self,
+        target: &dyn RetentionTarget,
+        now: DateTime<Utc>,
+        report: &mut RetentionReport,
+    ) -> Result<()> {
+        let class = target.class();
+        let mut items = target.items().await?;
+        items.sort_by_key(|i| i.created_at);
+        let counts = report.classes.entry(class).or_default();
+        counts.examined += items.len();
+
+        // Byte totals per policy scope (class-wide, or one overridden user); held data is left out
+        let mut totals: HashMap<Option<String>, u64> = HashMap::new();
+        let mut governed = Vec::new();
+        for item in items {
+            let user = item.user_id.as_deref();
+            if user.is_some_and(|u| self.policy.legal_holds.contains(u)) {
+                counts.held += 1;
+                continue;
+            }
+            let Some((policy, scope)) = self.policy.policy_for(class, user) else {
+                continue;
+            };
+            let scope = scope.map(str::to_string);
+            *totals.entry(scope.clone()).or_default() += item.bytes;
+            governed.push((item, policy, scope));
+        }
+
+        for (item, policy, scope) in governed {
+            if item.tags.iter().any(|t| policy.exempt_tags.contains(t)) {
+                counts.exempt += 1;
+                continue;
+            }
+            let age = (now - item.created_at).to_std().unwrap_or_default();
+            let total = totals.get(&scope).copied().unwrap_or_default();
+            let reason = if policy.max_age.is_some_and(|max| age >= max) {
+                DeletionReason::MaxAge
+            } else if policy.max_total_bytes.is_some_and(|max| total > max) {
+                DeletionReason::MaxTotalBytes
+            } else {
+                continue;
+            };
+
+            let mut reference = item.referenced_by.clone();
+            for guard in &self.guards {
+                if reference.is_some() {
+                    break;
+                }
+                reference = guard.reference(class, &item).await;
+            }
+            if let Some(reason) = reference {
+                counts.skipped_referenced += 1;
+                report.skipped.push((class, item.id.clone(), reason));
+                continue;
+            }
+
+            let freed = if self.dry_run {
+                item.bytes
+            } else {
+                match target.delete(&item).await {
+                    Ok(freed) => freed,
+                    Err(e) => {
+                        report.errors.push(format!("{} {}: {}", class, item.id, e));
+                        continue;
+                    }
+                }
+            };
+            if let Some(total) = totals.get_mut(&scope) {
+                *total = total.saturating_sub(item.bytes);
+            }
+            counts.deleted += 1;
+            counts.bytes_reclaimed += freed;
+            report.deleted.push(DeletedItem {
+                class,
+                id: item.id,
+                user_id: item.user_id,
+                bytes: item.bytes,
+                reason,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::message::Message;
+    use crate::agent::multi_agent::task_board::InMemoryTaskStore;
+    use crate::agent::multi_agent::NewTask;
+    use crate::agent::replay::{ArtifactStore, EventId};
+    use crate::agent::session::AgentSession;
+    use crate::knowledge::rag::Embeddings;
+
+    struct Fixed;
+
+    #[async_trait]
+    impl Embeddings for Fixed {
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>> {
+            Ok(vec![1.0, 0.0])
+        }
+    }
+
+    #[derive(Default)]
+    struct Sessions(Mutex<HashMap<String, AgentSession>>);
+
+    #[async_trait]
+    impl Memory for Sessions {
+        async fn store(&self, _: &str, _: Option<&str>, _: Message) -> Result<()> {
+            Ok(())
+        }
+        async fn retrieve(&self, _: &str, _: Option<&str>, _: usize) -> Vec<Message> {
+            Vec::new()
+        }
+        async fn clear(&self, _: &str, _: Option<&str>) -> Result<()> {
+            Ok(())
+        }
+        async fn undo(&self, _: &str, _: Option<&str>) -> Result<Option<Message>> {
+            Ok(None)
+        }
+        async fn list_sessions(&self) -> Result<Vec<AgentSession>> {
+            Ok(self.0.lock().values().cloned().collect())
+        }
+        async fn delete_session(&self, id: &str) -> Result<bool> {
+            Ok(self.0.lock().remove(id).is_some())
+        }
+    }
+
+    #[derive(Default)]
+    struct Captured(Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl Notifier for Captured {
+        async fn notify(&self, _channel: NotifyChannel, message: &str) -> Result<()> {
+            self.0.lock().push(message.to_string());
+            Ok(())
+        }
+    }
+
+    fn days_ago(days: i64) -> DateTime<Utc> {
+        Utc::now() - chrono::Duration::days(days)
+    }
+
+    fn write_file(path: &std::path::Path, bytes: usize, days_old: u64) {
+        std::fs::write(path, "x".repeat(bytes)).unwrap();
+        let mtime = SystemTime::now() - Duration::from_secs(days_old * 86_400);
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+    }
+
+    async fn remember(store: &InMemoryVectorStore, text: &str, meta: &[(&str, String)]) -> String {
+        let meta = meta
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect();
+        store.store(text, meta).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_enforcement_keeps_cross_store_integrity_and_legal_holds() {
+        let dir = tempfile::tempdir().unwrap();
+        let month = Duration::from_secs(30 * 86_400);
+
+        // Sessions: one expired, one awaiting approval, one referenced by an open task, one fresh
+        let sessions = Arc::new(Sessions::default());
+        for (id, age, status) in [
+            ("done", 60, SessionStatus::Completed),
+            (
+                "approval",
+                60,
+                SessionStatus::AwaitingApproval {
+                    tool_name: "swap".to_string(),
+                    arguments: "{}".to_string(),
+                },
+            ),
+            ("tasked", 60, SessionStatus::Completed),
+            ("fresh", 1, SessionStatus::Completed),
+        ] {
+            let mut session = AgentSession::new(id.to_string());
+            session.status = status;
+            session.updated_at = days_ago(age);
+            sessions.0.lock().insert(id.to_string(), session);
+        }
+        let board = Arc::new(TaskBoard::new(Arc::new(InMemoryTaskStore)).await.unwrap());
+        board
+            .post(
+                NewTask::new("review", "planner")
+                    .with_payload(serde_json::json!({ "session_id": "tasked" })),
+            )
+            .await
+            .unwrap();
+
+        // Short-term conversations for a regular and a held user
+        let short_term = Arc::new(ShortTermMemory::new(10, 10, dir.path().join("stm.json")).await);
+        short_term
+            .store("alice", None, Message::user("hi"))
+            .await
+            .unwrap();
+        short_term
+            .store("carol", None, Message::user("hi"))
+            .await
+            .unwrap();
+
+        // Memories and document vectors in one store
+        let vectors = Arc::new(InMemoryVectorStore::with_embedder(Arc::new(Fixed)));
+        let old = days_ago(60).to_rfc3339();
+        remember(
+            &vectors,
+            "alice old",
+            &[(USER_ID, "alice".into()), (CREATED_AT, old.clone())],
+        )
+        .await;
+        remember(
+            &vectors,
+            "carol old",
+            &[(USER_ID, "carol".into()), (CREATED_AT, old.clone())],
+        )
+        .await;
+        remember(
+            &vectors,
+            "pinned",
+            &[
+                (USER_ID, "alice".into()),
+                (CREATED_AT, old.clone()),
+                (PINNED, "true".into()),
+            ],
+        )
+        .await;
+        remember(
+            &vectors,
+            "alice new",
+            &[
+                (USER_ID, "alice".into()),
+                (CREATED_AT, days_ago(1).to_rfc3339()),
+            ],
+        )
+        .await;
+        let docs = dir.path().join("docs");
+        std::fs::create_dir(&docs).unwrap();
+        write_file(&docs.join("old.md"), 100, 60);
+        write_file(&docs.join("new.md"), 100, 1);
+        for (doc, chunk) in [("old.md", 0), ("old.md", 1), ("new.md", 0)] {
+            remember(
+                &vectors,
+                &format!("{} {}", doc, chunk),
+                &[(DOCUMENT, doc.into())],
+            )
+            .await;
+        }
+
+        // Artifacts: one indexed, one orphaned file
+        let artifacts = Arc::new(FileArtifactStore::new(dir.path().join("artifacts")).unwrap());
+        let reference = artifacts.put(1 as EventId, "{\"full\":true}").unwrap();
+        write_file(&artifacts.dir().join("lost.json"), 50, 60);
+
+        let policy = RetentionPolicy::default()
+            .class(DataClass::Sessions, ClassPolicy::default().max_age(month))
+            .class(
+                DataClass::ShortTermMemory,
+                ClassPolicy::default().max_age(Duration::ZERO),
+            )
+            .class(
+                DataClass::LongTermMemory,
+                ClassPolicy::default().max_age(month).exempt_tag(PINNED),
+            )
+            .class(DataClass::Documents, ClassPolicy::default().max_age(month))
+            .class(DataClass::Artifacts, ClassPolicy::default().max_age(month))
+            .legal_hold("carol");
+        let notifier = Arc::new(Captured::default());
+        let enforcer = |dry_run| {
+            RetentionEnforcer::new(policy.clone())
+                .with_target(SessionTarget::new(sessions.clone()))
+                .with_target(ShortTermTarget::new(short_term.clone()))
+                .with_target(VectorTarget::new(vectors.clone()))
+                .with_target(
+                    FileTarget::new(DataClass::Documents, &docs).cascade_vectors(vectors.clone()),
+                )
+                .with_target(ArtifactTarget::new(artifacts.clone()))
+                .with_guard(OpenTaskGuard::new(board.clone()))
+                .with_notifier(notifier.clone(), NotifyChannel::Log)
+                .dry_run(dry_run)
+        };
+
+        // Dry run lists the same deletions without touching anything
+        let planned = enforcer(true).run().await.unwrap();
+        assert_eq!(vectors.len(), 7);
+        assert_eq!(sessions.0.lock().len(), 4);
+        assert!(notifier.0.lock()[0].starts_with("Retention (dry run): 5 items would be deleted"));
+
+        let enforcer = enforcer(false);
+        let report = enforcer.run().await.unwrap();
+        let ids = |r: &RetentionReport| {
+            r.deleted
+                .iter()
+                .map(|d| format!("{}/{}", d.class, d.id))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&planned), ids(&report));
+        assert_eq!(report.errors, Vec::<String>::new());
+
+        assert_eq!(report.classes[&DataClass::Sessions].deleted, 1);
+        assert_eq!(report.classes[&DataClass::Sessions].skipped_referenced, 2);
+        let mut kept: Vec<_> = sessions.0.lock().keys().cloned().collect();
+        kept.sort();
+        assert_eq!(kept, ["approval", "fresh", "tasked"]);
+        assert!(report
+            .skipped
+            .iter()
+            .any(|(_, id, why)| id == "tasked" && why.starts_with("task ")));
+
+        // Legal hold beats the zero max_age and the expired memory
+        let users: Vec<_> = short_term
+            .conversations()
+            .into_iter()
+            .map(|c| c.user_id)
+            .collect();
+        assert_eq!(users, ["carol"]);
+        let remaining: Vec<_> = vectors.entries().into_iter().map(|e| e.content).collect();
+        assert_eq!(remaining, ["carol old", "pinned", "alice new", "new.md 0"]);
+        assert_eq!(report.classes[&DataClass::LongTermMemory].held, 1);
+        assert_eq!(report.classes[&DataClass::LongTermMemory].exempt, 1);
+
+        // No vectors for missing documents, no artifact files without index rows
+        assert!(!docs.join("old.md").exists());
+        for entry in vectors.entries() {
+            if let Some(doc) = entry.metadata.get(DOCUMENT) {
+                assert!(docs.join(doc).exists(), "orphaned vector for {}", doc);
+            }
+        }
+        assert!(!artifacts.dir().join("lost.json").exists());
+        assert!(artifacts.fetch(&reference).unwrap().is_some());
+        assert_eq!(enforcer.journal().len(), 1);
+        assert_eq!(notifier.0.lock().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_byte_caps_delete_oldest_when_ages_do_not_trigger() {
+        let dir = tempfile::tempdir().unwrap();
+        for (i, age) in [5u64, 4, 3, 2, 1].iter().enumerate() {
+            write_file(&dir.path().join(format!("agent.log.{}", i)), 100, *age);
+        }
+        write_file(&dir.path().join("other.txt"), 1000, 9);
+
+        let artifacts = Arc::new(FileArtifactStore::new(dir.path().join("artifacts")).unwrap());
+        let alice = FileArtifactStore::new(artifacts.dir())
+            .unwrap()
+            .for_user("alice");
+        for id in 0..3 {
+            alice.put(id, &"y".repeat(100)).unwrap();
+        }
+
+        let policy = RetentionPolicy::default()
+            .class(
+                DataClass::Logs,
+                ClassPolicy::default()
+                    .max_age(Duration::from_secs(30 * 86_400))
+                    .max_total_bytes(250),
+            )
+            // Alice's override replaces the (unlimited) class policy for her artifacts
+            .class(DataClass::Artifacts, ClassPolicy::default())
+            .user_override(
+                "alice",
+                DataClass::Artifacts,
+                ClassPolicy::default().max_total_bytes(100),
+            );
+        assert_eq!(policy.validate().len(), 1);
+
+        let report = RetentionEnforcer::new(policy)
+            .with_target(
+                FileTarget::new(DataClass::Logs, dir.path())
+                    .prefix("agent.log")
+                    .keep_newest(),
+            )
+            .with_target(ArtifactTarget::new(artifacts.clone()))
+            .run()
+            .await
+            .unwrap();
+
+        let logs = &report.classes[&DataClass::Logs];
+        assert_eq!(
+            (logs.examined, logs.deleted, logs.bytes_reclaimed),
+            (5, 3, 300)
+        );
+        assert!(report
+            .deleted
+            .iter()
+            .all(|d| d.reason == DeletionReason::MaxTotalBytes));
+        for (i, exists) in [false, false, false, true, true].iter().enumerate() {
+            assert_eq!(
+                dir.path().join(format!("agent.log.{}", i)).exists(),
+                *exists
+            );
+        }
+        assert!(dir.path().join("other.txt").exists());
+
+        // Oldest two artifacts go with their index rows
+        assert_eq!(report.classes[&DataClass::Artifacts].deleted, 2);
+        let records = artifacts.records().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].event_id, 2);
+        let files = std::fs::read_dir(artifacts.dir()).unwrap().count();
+        assert_eq!(files, 2, "index plus one artifact file");
+    }
+}
@@ -0,0 +1,478 @@
+//! Central secret resolution for tools and skills
+//!
+//! Tools declare the keys they need (`ToolDefinition::required_secrets`, or
+//! `requires.env` in SKILL.md) and resolve them through a [`SecretProvider`] at
+//! call time instead of reading the process environment. [`Secrets`] remembers
+//! every value it hands out so the agent can scrub it from tool output and
+//! events.
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use zeroize::Zeroize;
+
+use crate::error::{Error, Result};
+use crate::skills::tool::ToolSet;
+
+/// Resolved values shorter than this are not redacted (too likely to match ordinary text)
+const MIN_REDACTED_LEN: usize = 4;
+
+/// A secret value that is wiped from memory on drop and never printed by `Debug`
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Wrap a secret value
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// Borrow the plaintext value
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString([REDACTED])")
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Source of secret values
+pub trait SecretProvider: Send + Sync {
+    /// Look up a secret, failing with [`Error::SecretNotFound`] when it is not set
+    fn get(&self, key: &str) -> Result<SecretString>;
+
+    /// Re-read the underlying source so later lookups see rotated values
+    fn reload(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads secrets from process environment variables
+#[derive(Debug, Clone, Default)]
+pub struct EnvSecretProvider {
+    prefix: String,
+}
+
+impl EnvSecretProvider {
+    /// Look keys up as-is
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look `KEY` up as `{prefix}KEY`
+    pub fn with_prefix(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+        }
+    }
+}
+
+impl SecretProvider for EnvSecretProvider {
+    fn get(&self, key: &str) -> Result<SecretString> {
+        std::env::var(format!("{}{}", self.prefix, key))
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(SecretString)
+            .ok_or_else(|| Error::SecretNotFound(key.to_string()))
+    }
+}
+
+/// Reads secrets from a dotenv-style file (`KEY=value` lines)
+pub struct FileSecretProvider {
+    path: PathBuf,
+    values: RwLock<HashMap<String, SecretString>>,
+}
+
+impl FileSecretProvider {
+    /// Load secrets from `path`
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let provider = Self {
+            path: path.into(),
+            values: RwLock::new(HashMap::new()),
+        };
+        provider.reload()?;
+        Ok(provider)
+    }
+}
+
+impl SecretProvider for FileSecretProvider {
+    fn get(&self, key: &str) -> Result<SecretString> {
+        self.values
+            .read()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| Error::SecretNotFound(key.to_string()))
+    }
+
+    fn reload(&self) -> Result<()> {
+        let mut content = std::fs::read_to_string(&self.path)?;
+        let values = parse_dotenv(&content);
+        content.zeroize();
+        *self.values.write() = values;
+        Ok(())
+    }
+}
+
+/// Parse `KEY=value` lines, skipping blanks and `#` comments and accepting `export KEY=...`
+fn parse_dotenv(content: &str) -> HashMap<String, SecretString> {
+    let mut values = HashMap::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+        values.insert(key.trim().to_string(), SecretString::new(value));
+    }
+    values
+}
+
+/// Tries several providers in order; the first one that has the key wins
+#[derive(Default)]
+pub struct CompositeSecretProvider {
+    providers: Vec<Arc<dyn SecretProvider>>,
+}
+
+impl CompositeSecretProvider {
+    /// Create an empty composite
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a provider with lower precedence than those already added
+    pub fn with(mut self, provider: Arc<dyn SecretProvider>) -> Self {
+        self.providers.push(provider);
+        self
+    }
+}
+
+impl SecretProvider for CompositeSecretProvider {
+    fn get(&self, key: &str) -> Result<SecretString> {
+        for provider in &self.providers {
+            match provider.get(key) {
+                Err(Error::SecretNotFound(_)) => continue,
+                other => return other,
+            }
+        }
+        Err(Error::SecretNotFound(key.to_string()))
+    }
+
+    fn reload(&self) -> Result<()> {
+        self.providers.iter().try_for_each(|p| p.reload())
+    }
+}
+
+/// Missing secrets found by [`Secrets::preflight`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PreflightReport {
+    /// Tool name -> keys that did not resolve
+    pub missing: BTreeMap<String, Vec<String>>,
+}
+
+impl PreflightReport {
+    /// Whether every required secret resolved
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Resolves secrets for tools and redacts every value it has handed out
+pub struct Secrets {
+    provider: Arc<dyn SecretProvider>,
+    resolved: RwLock<Vec<(String, SecretString)>>,
+}
+
+impl Secrets {
+    /// Resolve secrets through `provider`
+    pub fn new(provider: Arc<dyn SecretProvider>) -> Self {
+        Self {
+            provider,
+            resolved: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Resolve a secret and register its value for redaction
+    pub fn resolve(&self, key: &str) -> Result<SecretString> {
+        let value = self.provider.get(key)?;
+        self.register(key, &value);
+        Ok(value)
+    }
+
+    /// Resolve several secrets, reporting every missing key at once
+    pub fn resolve_all(&self, keys: &[String]) -> Result<Vec<(String, SecretString)>> {
+        let mut values = Vec::with_capacity(keys.len());
+        let mut missing = Vec::new();
+        for key in keys {
+            match self.resolve(key) {
+                Ok(value) => values.push((key.clone(), value)),
+                Err(Error::SecretNotFound(_)) => missing.push(key.as_str()),
+                Err(e) => return Err(e),
+            }
+        }
+        if !missing.is_empty() {
+            return Err(Error::SecretNotFound(missing.join(", ")));
+        }
+        Ok(values)
+    }
+
+    /// Re-read the provider; previously resolved values stay redacted
+    pub fn reload(&self) -> Result<()> {
+        self.provider.reload()
+    }
+
+    /// Check that every tool's `required_secrets` resolve
+    pub async fn preflight(&self, tools: &ToolSet) -> PreflightReport {
+        let mut report = PreflightReport::default();
+        for (name, tool) in tools.iter() {
+            let missing: Vec<String> = tool
+                .definition()
+                .await
+                .required_secrets
+                .into_iter()
+                .filter(|key| self.resolve(key).is_err())
+                .collect();
+            if !missing.is_empty() {
+                report.missing.insert(name.clone(), missing);
+            }
+        }
+        report
+    }
+
+    /// Replace every resolved secret value in `text` with `[REDACTED:KEY]`
+    pub fn redact(&self, text: &str) -> String {
+        let resolved = self.resolved.read();
+        let mut out = text.to_string();
+        for (key, value) in resolved.iter() {
+            if out.contains(value.expose()) {
+                out = out.replace(value.expose(), &format!("[REDACTED:{}]", key));
+            }
+        }
+        out
+    }
+
+    fn register(&self, key: &str, value: &SecretString) {
+        if value.expose().len() < MIN_REDACTED_LEN {
+            return;
+        }
+        let mut resolved = self.resolved.write();
+        if resolved.iter().any(|(_, v)| v == value) {
+            return;
+        }
+        resolved.push((key.to_string(), value.clone()));
+        // Longest first so a secret containing another is replaced whole
+        resolved.sort_by_key(|(_, v)| std::cmp::Reverse(v.expose().len()));
+    }
+}
+
+impl fmt::Debug for Secrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Secrets")
+            .field("resolved", &self.resolved.read().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::StubProvider;
+    use crate::agent::core::{Agent, AgentEvent};
+    use crate::agent::provider::{ChatRequest, Provider};
+    use crate::agent::streaming::{MockStreamBuilder, StreamingResponse};
+    use crate::skills::tool::{Tool, ToolDefinition};
+    use crate::skills::{sandbox_env, SkillExecutionConfig, SkillMetadata, SANDBOX_BASE_ENV};
+    use async_trait::async_trait;
+
+    fn write_env(dir: &tempfile::TempDir, name: &str, content: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn secrets_from_file(dir: &tempfile::TempDir, content: &str) -> Arc<Secrets> {
+        let path = write_env(dir, "secrets.env", content);
+        Arc::new(Secrets::new(Arc::new(
+            FileSecretProvider::open(path).unwrap(),
+        )))
+    }
+
+    #[test]
+    fn test_precedence_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_env(
+            &dir,
+            ".env",
+            "# comment\nexport SHARED=\"from-file\"\nFILE_ONLY='file-value'\n",
+        );
+        std::env::set_var("AAGT_SECRETS_TEST_SHARED", "from-env");
+
+        let file = Arc::new(FileSecretProvider::open(&path).unwrap());
+        let env = Arc::new(EnvSecretProvider::with_prefix("AAGT_SECRETS_TEST_"));
+        let composite = CompositeSecretProvider::new()
+            .with(env.clone())
+            .with(file.clone());
+        assert_eq!(composite.get("SHARED").unwrap().expose(), "from-env");
+        assert_eq!(composite.get("FILE_ONLY").unwrap().expose(), "file-value");
+        assert!(matches!(
+            composite.get("NOPE"),
+            Err(Error::SecretNotFound(k)) if k == "NOPE"
+        ));
+
+        let file_first = CompositeSecretProvider::new().with(file).with(env);
+        assert_eq!(file_first.get("SHARED").unwrap().expose(), "from-file");
+
+        // Rotation is picked up without rebuilding anything
+        std::fs::write(&path, "SHARED=rotated\nFILE_ONLY=rotated-too\n").unwrap();
+        assert_eq!(file_first.get("FILE_ONLY").unwrap().expose(), "file-value");
+        file_first.reload().unwrap();
+        assert_eq!(file_first.get("FILE_ONLY").unwrap().expose(), "rotated-too");
+        assert_eq!(
+            format!("{:?}", file_first.get("SHARED").unwrap()),
+            "SecretString([REDACTED])"
+        );
+    }
+
+    fn metadata(name: &str, env: &[&str]) -> SkillMetadata {
+        serde_yaml_ng::from_str(&format!(
+            "name: {}\ndescription: test\nrequires:\n  env: [{}]\n",
+            name,
+            env.join(", ")
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_sandbox_env_is_scoped_to_declared_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let secrets = secrets_from_file(&dir, "BIRDEYE_KEY=birdeye-123\nHELIUS_KEY=helius-456\n");
+        let config = SkillExecutionConfig::default();
+
+        let env = sandbox_env(
+            &metadata("birdeye", &["BIRDEYE_KEY"]),
+            &config,
+            Some(&secrets),
+        )
+        .unwrap();
+        assert!(env
+            .iter()
+            .any(|(k, v)| k == "BIRDEYE_KEY" && v.expose() == "birdeye-123"));
+        assert!(env
+            .iter()
+            .all(|(k, _)| k == "BIRDEYE_KEY" || SANDBOX_BASE_ENV.contains(&k.as_str())));
+
+        let missing = sandbox_env(
+            &metadata("jupiter", &["JUPITER_KEY", "HELIUS_KEY"]),
+            &config,
+            Some(&secrets),
+        );
+        assert!(matches!(missing, Err(Error::SecretNotFound(k)) if k == "JUPITER_KEY"));
+        assert!(sandbox_env(&metadata("unconfigured", &["BIRDEYE_KEY"]), &config, None).is_err());
+    }
+
+    /// Tool that leaks the secret it resolves
+    struct LeakyTool {
+        secrets: Arc<Secrets>,
+    }
+
+    #[async_trait]
+    impl Tool for LeakyTool {
+        fn name(&self) -> String {
+            "leaky".to_string()
+        }
+
+        async fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: self.name(),
+                description: "Echoes its API key".to_string(),
+                parameters: serde_json::json!({}),
+                is_verified: true,
+                required_secrets: vec!["API_KEY".to_string()],
+                ..Default::default()
+            }
+        }
+
+        async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+            let key = self.secrets.resolve("API_KEY")?;
+            Ok(format!("called with key={}", key.expose()))
+        }
+    }
+
+    struct NeedsMissing;
+
+    #[async_trait]
+    impl Tool for NeedsMissing {
+        fn name(&self) -> String {
+            "needs_missing".to_string()
+        }
+
+        async fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: self.name(),
+                description: "Needs keys that are not configured".to_string(),
+                parameters: serde_json::json!({}),
+                is_verified: true,
+                required_secrets: vec!["API_KEY".to_string(), "MISSING_KEY".to_string()],
+                ..Default::default()
+            }
+        }
+
+        async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+            Ok(String::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preflight_and_output_redaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let secrets = secrets_from_file(&dir, "API_KEY=sk-test-0123456789\n");
+        let agent = Agent::builder(StubProvider)
+            .tool(LeakyTool {
+                secrets: secrets.clone(),
+            })
+            .tool(NeedsMissing)
+            .secrets(secrets.clone())
+            .auto_load_skills(false)
+            .build()
+            .unwrap();
+
+        let report = agent.preflight_secrets().await.unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.missing.len(), 1);
+        assert_eq!(report.missing["needs_missing"], vec!["MISSING_KEY"]);
+
+        // Definitions name the key but never carry its value
+        let defs = serde_json::to_string(&agent.tool_definitions().await).unwrap();
+        assert!(defs.contains("API_KEY"));
+        assert!(!defs.contains("sk-test-0123456789"));
+
+        let mut events = agent.subscribe();
+        let output = agent.call_tool("leaky", "{}").await.unwrap();
+        assert_eq!(output, "called with key=[REDACTED:API_KEY]");
+        while let Ok(event) = events.try_recv() {
+            if let AgentEvent::ToolResult { output, .. } = event {
+                assert!(!output.contains("sk-test-0123456789"));
+            }
+        }
+    }
+}
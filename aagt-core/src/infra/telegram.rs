@@ -1,6 +1,16 @@
+use dashmap::DashMap;
 use reqwest::Client;
-use serde_json::json;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::oneshot;
+
+use crate::agent::core::ApprovalHandler;
+
+const TELEGRAM_API: &str = "https://api.telegram.org";
 
 /// Telegram Notifier - send one-way notifications to Telegram
 /// 
@@ -76,6 +86,12 @@ impl crate::infra::observable::AgentObserver for TelegramNotifier {
             AgentEvent::Thinking { prompt } => {
                 format!("─── *thinking* ───\n`{}`", prompt)
             }
+            // One message per token would flood the chat; the response carries the full text
+            AgentEvent::StreamDelta { .. }
+            | AgentEvent::Reasoning { .. }
+            | AgentEvent::ToolCallDelta { .. } => return Ok(()),
+            // Token accounting is for spend tracking, not for the chat
+            AgentEvent::Usage { .. } => return Ok(()),
             AgentEvent::ToolCall { tool, input } => {
                 format!("─── *tool call* ───\n*target:* `{}`\n*input:* `{}`", tool, input)
             }
@@ -83,21 +99,311 @@ impl crate::infra::observable::AgentObserver for TelegramNotifier {
                 let preview = if output.len() > 100 { format!("{}...", &output[..100]) } else { output.clone() };
                 format!("─── *tool result* ───\n*target:* `{}`\n*output:* `{}`", tool, preview)
             }
-            AgentEvent::ApprovalPending { tool, input } => {
+            AgentEvent::ToolUnavailable { tool, reason } => {
+                format!("─── *tool unavailable* ───\n*target:* `{}`\n*reason:* {}", tool, reason)
+            }
+            AgentEvent::ApprovalPending { tool, input, .. } => {
                 format!("─── *approval required* ───\n*target:* `{}`\n*input:* `{}`", tool, input)
             }
-            AgentEvent::Response { content } => {
+            AgentEvent::Response { content, .. } => {
                 format!("─── *response* ───\n{}", content)
             }
             AgentEvent::Error { message } => {
                 format!("─── *error* ───\n{}", message)
             }
+            AgentEvent::Cancelled { step, .. } => {
+                format!("─── *cancelled* ───\nstopped at step {}", step)
+            }
+            AgentEvent::PersonaChanged { previous, current } => {
+                let role = |r: &Option<String>| r.clone().unwrap_or_else(|| "none".to_string());
+                format!("─── *persona* ───\n{} → {}", role(previous), role(current))
+            }
+            AgentEvent::Subagent { request_id, child, event } => {
+                format!("─── *subagent {}* ───\n*request:* `{}`\n*event:* `{:?}`", child, request_id, event)
+            }
         };
 
         self.notify(&message).await
     }
 }
 
+/// Approval handler that asks on Telegram with inline Approve/Deny buttons
+///
+/// Each request is sent to `chat_id` with the tool name and pretty-printed
+/// arguments. While any approval is pending, one background task long-polls
+/// `getUpdates` for button presses; the callback data carries the request ID,
+/// so concurrent approvals resolve independently. An unanswered request is
+/// denied after [`with_timeout`](Self::with_timeout).
+///
+/// The bot must not be polled by anything else at the same time: Telegram
+/// hands each update to a single consumer.
+///
+/// # Example
+///
+/// ```ignore
+/// let approvals = TelegramApprovalHandler::new("1234567890:ABCdefGHI...", "123456789")
+///     .allowed_chat_ids([123456789])
+///     .with_timeout(Duration::from_secs(120));
+///
+/// let agent = Agent::builder(provider).approval_handler(approvals).build()?;
+/// ```
+pub struct TelegramApprovalHandler {
+    chat_id: String,
+    timeout: Duration,
+    poller: Poller,
+}
+
+impl TelegramApprovalHandler {
+    /// Create a handler posting approval requests to `chat_id`
+    pub fn new(bot_token: impl Into<String>, chat_id: impl Into<String>) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            chat_id: chat_id.into(),
+            timeout: Duration::from_secs(300),
+            poller: Poller {
+                client,
+                api_base: TELEGRAM_API.to_string(),
+                bot_token: bot_token.into(),
+                allowed: None,
+                poll_timeout_secs: 25,
+                pending: Arc::new(DashMap::new()),
+                polling: Arc::new(AtomicBool::new(false)),
+                offset: Arc::new(AtomicI64::new(0)),
+            },
+        }
+    }
+
+    /// Only accept button presses from these user/chat IDs (default: anyone who can see the message)
+    pub fn allowed_chat_ids(mut self, ids: impl IntoIterator<Item = i64>) -> Self {
+        self.poller.allowed = Some(Arc::new(ids.into_iter().collect()));
+        self
+    }
+
+    /// Deny requests nobody answers within `timeout` (default: 5 minutes)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Use another Bot API server, e.g. a self-hosted one
+    pub fn with_api_base(mut self, base: impl Into<String>) -> Self {
+        self.poller.api_base = base.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Seconds each `getUpdates` long poll waits for a button press (default: 25)
+    pub fn with_poll_timeout(mut self, secs: u64) -> Self {
+        self.poller.poll_timeout_secs = secs;
+        self
+    }
+
+    /// Approvals currently waiting for an answer
+    pub fn pending(&self) -> usize {
+        self.poller.pending.len()
+    }
+
+    fn request_text(tool_name: &str, arguments: &str) -> String {
+        let pretty = serde_json::from_str::<Value>(arguments)
+            .and_then(|v| serde_json::to_string_pretty(&v))
+            .unwrap_or_else(|_| arguments.to_string());
+        format!("Approval required\nTool: {}\n\n{}", tool_name, pretty)
+    }
+}
+
+#[async_trait::async_trait]
+impl ApprovalHandler for TelegramApprovalHandler {
+    async fn approve(&self, tool_name: &str, arguments: &str) -> anyhow::Result<bool> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let (tx, rx) = oneshot::channel();
+        self.poller.pending.insert(id.clone(), tx);
+
+        let payload = json!({
+            "chat_id": self.chat_id,
+            "text": Self::request_text(tool_name, arguments),
+            "reply_markup": {
+                "inline_keyboard": [[
+                    { "text": "Approve", "callback_data": format!("approve:{}", id) },
+                    { "text": "Deny", "callback_data": format!("deny:{}", id) }
+                ]]
+            }
+        });
+        if let Err(e) = self.poller.call("sendMessage", &payload).await {
+            self.poller.pending.remove(&id);
+            return Err(e.into());
+        }
+        self.poller.ensure_running();
+
+        match tokio::time::timeout(self.timeout, rx).await {
+            Ok(Ok(approved)) => Ok(approved),
+            Ok(Err(_)) => Err(crate::error::Error::Internal("Approval responder dropped".to_string()).into()),
+            Err(_) => {
+                self.poller.pending.remove(&id);
+                tracing::warn!(tool = %tool_name, request_id = %id, "Telegram approval timed out, denying");
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Long-polls `getUpdates` and resolves pending approvals from button presses
+#[derive(Clone)]
+struct Poller {
+    client: Client,
+    api_base: String,
+    bot_token: String,
+    allowed: Option<Arc<HashSet<i64>>>,
+    poll_timeout_secs: u64,
+    pending: Arc<DashMap<String, oneshot::Sender<bool>>>,
+    polling: Arc<AtomicBool>,
+    /// Next update ID to fetch; kept across poller restarts so presses aren't replayed
+    offset: Arc<AtomicI64>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    callback_query: Option<CallbackQuery>,
+}
+
+#[derive(Deserialize)]
+struct CallbackQuery {
+    id: String,
+    from: TelegramUser,
+    message: Option<CallbackMessage>,
+    data: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TelegramUser {
+    id: i64,
+}
+
+#[derive(Deserialize)]
+struct CallbackMessage {
+    message_id: i64,
+    chat: TelegramChat,
+}
+
+#[derive(Deserialize)]
+struct TelegramChat {
+    id: i64,
+}
+
+impl Poller {
+    async fn call(&self, method: &str, payload: &Value) -> crate::error::Result<Value> {
+        let url = format!("{}/bot{}/{}", self.api_base, self.bot_token, method);
+        let response = self.client
+            .post(&url)
+            .json(payload)
+            .send()
+            .await
+            .map_err(|e| crate::error::Error::Internal(format!("Telegram API error: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(crate::error::Error::Internal(
+                format!("Telegram API returned {}: {}", status, body)
+            ));
+        }
+
+        let body: Value = response.json().await
+            .map_err(|e| crate::error::Error::Internal(format!("Telegram API error: {}", e)))?;
+        Ok(body.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    /// Start the polling task unless one is already running
+    fn ensure_running(&self) {
+        if self.polling.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            tokio::spawn(self.clone().run());
+        }
+    }
+
+    async fn run(self) {
+        loop {
+            if self.pending.is_empty() {
+                self.polling.store(false, Ordering::Release);
+                // An approval registered after the check above saw the flag still set; pick it up
+                if self.pending.is_empty()
+                    || self.polling.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire).is_err()
+                {
+                    return;
+                }
+            }
+
+            let payload = json!({
+                "offset": self.offset.load(Ordering::Acquire),
+                "timeout": self.poll_timeout_secs,
+                "allowed_updates": ["callback_query"]
+            });
+            let updates = match self.call("getUpdates", &payload).await {
+                Ok(result) => serde_json::from_value::<Vec<Update>>(result).unwrap_or_default(),
+                Err(e) => {
+                    tracing::warn!("Telegram getUpdates failed: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+
+            for update in updates {
+                self.offset.fetch_max(update.update_id + 1, Ordering::AcqRel);
+                if let Some(query) = update.callback_query {
+                    self.handle(query).await;
+                }
+            }
+        }
+    }
+
+    async fn handle(&self, query: CallbackQuery) {
+        let allowed = self.allowed.as_ref().is_none_or(|ids| {
+            ids.contains(&query.from.id) || query.message.as_ref().is_some_and(|m| ids.contains(&m.chat.id))
+        });
+        let decision = query.data.as_deref().and_then(|data| {
+            let (action, id) = data.split_once(':')?;
+            match action {
+                "approve" => Some((true, id)),
+                "deny" => Some((false, id)),
+                _ => None,
+            }
+        });
+
+        let reply = match decision {
+            _ if !allowed => {
+                tracing::warn!(user = query.from.id, "Ignoring approval from a chat that may not approve");
+                "You are not allowed to approve this"
+            }
+            None => return,
+            Some((approved, id)) => match self.pending.remove(id) {
+                Some((_, responder)) => {
+                    let _ = responder.send(approved);
+                    if let Some(message) = &query.message {
+                        // Drop the buttons so the request can't be answered twice
+                        let clear = json!({
+                            "chat_id": message.chat.id,
+                            "message_id": message.message_id,
+                            "reply_markup": { "inline_keyboard": [] }
+                        });
+                        if let Err(e) = self.call("editMessageReplyMarkup", &clear).await {
+                            tracing::debug!("Failed to clear approval buttons: {}", e);
+                        }
+                    }
+                    if approved { "Approved" } else { "Denied" }
+                }
+                None => "This request has expired",
+            },
+        };
+
+        let answer = json!({ "callback_query_id": query.id, "text": reply });
+        if let Err(e) = self.call("answerCallbackQuery", &answer).await {
+            tracing::debug!("Failed to answer callback query: {}", e);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
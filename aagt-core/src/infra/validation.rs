@@ -0,0 +1,240 @@
+//! Collect-all configuration validation
+//!
+//! Config structs implement [`Validate`] and report every problem at once as
+//! [`ConfigIssue`]s with a dotted path (`risk.max_single_trade_usd`), rather than
+//! failing on the first one. [`ConfigIssues::check`] turns a list into a
+//! result: errors fail, warnings are logged (or fail too in strict mode).
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::error::{Error, Result};
+
+/// How serious a configuration issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueSeverity {
+    /// Suspicious but usable
+    Warning,
+    /// The configuration cannot be used
+    Error,
+}
+
+/// One problem found in a configuration
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigIssue {
+    /// Dotted path of the offending field, e.g. `agent.max_tokens`
+    pub path: String,
+    /// Error or warning
+    pub severity: IssueSeverity,
+    /// What is wrong
+    pub message: String,
+    /// How to fix it, if there is an obvious fix
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl ConfigIssue {
+    /// An error at `path`
+    pub fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            severity: IssueSeverity::Error,
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    /// A warning at `path`
+    pub fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: IssueSeverity::Warning,
+            ..Self::error(path, message)
+        }
+    }
+
+    /// Attach a fix suggestion
+    pub fn suggest(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.severity {
+            IssueSeverity::Error => "error",
+            IssueSeverity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", label, self.path, self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (hint: {})", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// A configuration that can check itself
+pub trait Validate {
+    /// Every issue found, in field order; empty when the config is fine
+    fn validate(&self) -> Vec<ConfigIssue>;
+}
+
+/// Issues that made a configuration unusable, carried by [`Error::InvalidConfig`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConfigIssues(pub Vec<ConfigIssue>);
+
+impl ConfigIssues {
+    /// Fail with every error (and, when `strict`, every warning); log the rest
+    pub fn check(issues: Vec<ConfigIssue>, strict: bool) -> Result<()> {
+        let fatal = |issue: &ConfigIssue| strict || issue.severity == IssueSeverity::Error;
+        if issues.iter().any(fatal) {
+            return Err(Error::InvalidConfig(Self(issues)));
+        }
+        for issue in &issues {
+            tracing::warn!("Configuration {}", issue);
+        }
+        Ok(())
+    }
+
+    /// Issues with error severity
+    pub fn errors(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.0.iter().filter(|i| i.severity == IssueSeverity::Error)
+    }
+
+    /// Issues with warning severity
+    pub fn warnings(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.0
+            .iter()
+            .filter(|i| i.severity == IssueSeverity::Warning)
+    }
+
+    /// Issue at `path`, if any
+    pub fn at(&self, path: &str) -> Option<&ConfigIssue> {
+        self.0.iter().find(|i| i.path == path)
+    }
+}
+
+/// Multi-line rendering for CLI output: a count line, then errors before warnings
+impl fmt::Display for ConfigIssues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors = self.errors().count();
+        let warnings = self.0.len() - errors;
+        write!(
+            f,
+            "{} error{}, {} warning{}",
+            errors,
+            if errors == 1 { "" } else { "s" },
+            warnings,
+            if warnings == 1 { "" } else { "s" }
+        )?;
+        for issue in self.errors().chain(self.warnings()) {
+            write!(f, "\n  {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// The candidate closest to `name`, if it is a plausible typo
+pub fn closest_match<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    candidates
+        .into_iter()
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(d, c)| *d <= (c.len().max(name.len()) / 3).max(1))
+        .min()
+        .map(|(_, c)| c)
+}
+
+/// Levenshtein distance in characters
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (diagonal + usize::from(ca != *cb))
+                .min(above + 1)
+                .min(row[j] + 1);
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::core::{AgentConfig, ToolPolicy};
+    use crate::skills::tool::{CalculatorTool, ToolSet};
+    use crate::skills::SkillExecutionConfig;
+
+    #[test]
+    fn test_agent_config_reports_every_issue() {
+        let mut config = AgentConfig {
+            model: " ".to_string(),
+            temperature: Some(3.5),
+            max_tokens: Some(0),
+            max_history_messages: 0,
+            max_parallel_tools: 0,
+            ..AgentConfig::default()
+        };
+        config
+            .tool_policy
+            .overrides
+            .insert("calculater".to_string(), ToolPolicy::Disabled);
+        let mut tools = ToolSet::new();
+        tools.add(CalculatorTool::new());
+
+        let issues = ConfigIssues(config.validate_with_tools(&tools));
+        for path in [
+            "agent.model",
+            "agent.temperature",
+            "agent.max_tokens",
+            "agent.max_history_messages",
+            "agent.max_parallel_tools",
+        ] {
+            assert_eq!(
+                issues.at(path).map(|i| i.severity),
+                Some(IssueSeverity::Error),
+                "{}",
+                path
+            );
+        }
+        let typo = issues.at("agent.tool_policy.overrides.calculater").unwrap();
+        assert_eq!(typo.severity, IssueSeverity::Warning);
+        assert_eq!(
+            typo.suggestion.as_deref(),
+            Some("did you mean 'calculator'?")
+        );
+
+        let rendered = issues.to_string();
+        assert!(rendered.starts_with("5 errors, 1 warning\n  error: agent.model:"));
+        assert!(rendered.ends_with("(hint: did you mean 'calculator'?)"));
+    }
+
+    #[test]
+    fn test_strict_mode_fails_on_warnings() {
+        let config = SkillExecutionConfig {
+            timeout_secs: 0,
+            allow_network: true,
+            ..SkillExecutionConfig::default()
+        };
+        let issues = config.validate();
+        assert_eq!(issues[0].path, "skills.execution.timeout_secs");
+        assert_eq!(issues[1].severity, IssueSeverity::Warning);
+
+        let warnings_only = SkillExecutionConfig {
+            allow_network: true,
+            ..SkillExecutionConfig::default()
+        }
+        .validate();
+        assert!(ConfigIssues::check(warnings_only.clone(), false).is_ok());
+        let err = ConfigIssues::check(warnings_only, true).unwrap_err();
+        assert!(matches!(err, Error::InvalidConfig(issues) if issues.0.len() == 1));
+    }
+}
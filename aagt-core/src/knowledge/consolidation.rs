@@ -0,0 +1,598 @@
+//! Long-term memory consolidation
+//!
+//! Automatic write-through leaves many near-identical long-term entries that
+//! crowd retrieval. [`MemoryConsolidator`] clusters entries of the same user,
+//! agent and `kind` whose embeddings have cosine similarity of at least
+//! `similarity_threshold` and were written within `window` of each other, then either keeps the most
+//! recent one or asks a provider for a merged entry. Consolidated entries are
+//! not deleted: they get a [`SUPERSEDED_BY`] marker, which retrieval skips.
+//!
+//! Pinned entries (`pinned = "true"` or `kind = "preference"`) are only ever
+//! folded into an identical entry, and never rewritten by the provider.
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::agent::message::Message;
+use crate::agent::provider::{ChatRequest, Provider};
+use crate::agent::streaming::StreamingChoice;
+use crate::error::Result;
+use crate::infra::validation::{ConfigIssue, ConfigIssues, Validate};
+use crate::knowledge::rag::VectorStore;
+use crate::knowledge::store::memory::cosine;
+use crate::knowledge::store::{InMemoryVectorStore, StoredEntry};
+
+/// Metadata key: owning user
+pub const USER_ID: &str = "user_id";
+/// Metadata key: owning agent, absent for user-wide entries
+pub const AGENT_ID: &str = "agent_id";
+/// Metadata key: entry kind (`fact`, `preference`, ...); kinds are never mixed
+pub const KIND: &str = "kind";
+/// Metadata key: `"true"` exempts the entry from consolidation unless identical
+pub const PINNED: &str = "pinned";
+/// Metadata key: RFC 3339 write time; missing means the Unix epoch
+pub const CREATED_AT: &str = "created_at";
+/// Metadata key: ID of the entry that replaced this one
+pub const SUPERSEDED_BY: &str = "superseded_by";
+/// Metadata key: comma-separated member IDs of a merged entry
+pub const CONSOLIDATED_FROM: &str = "consolidated_from";
+
+/// Whether an entry has been consolidated away and should not be retrieved
+pub fn is_superseded(metadata: &HashMap<String, String>) -> bool {
+    metadata.contains_key(SUPERSEDED_BY)
+}
+
+/// What to do with a cluster of near-duplicates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsolidationMode {
+    /// Keep the most recent entry, supersede the rest
+    KeepLatest,
+    /// Store a provider-written entry preserving every distinct fact
+    Merge,
+}
+
+/// Settings for a consolidation pass
+#[derive(Debug, Clone)]
+pub struct ConsolidationConfig {
+    /// Minimum cosine similarity to the cluster's newest entry
+    pub similarity_threshold: f32,
+    /// Maximum age gap to the cluster's newest entry
+    pub window: Duration,
+    /// Keep-latest or merge
+    pub mode: ConsolidationMode,
+    /// Model used for merge summaries
+    pub model: String,
+    /// Tokens merge summaries may spend per pass (`None` = unlimited)
+    pub merge_token_budget: Option<u64>,
+    /// Report proposed merges without changing anything
+    pub dry_run: bool,
+}
+
+impl Default for ConsolidationConfig {
+    fn default() -> Self {
+        Self {
+            similarity_threshold: 0.92,
+            window: Duration::from_secs(30 * 24 * 3600),
+            mode: ConsolidationMode::KeepLatest,
+            model: "gpt-4o-mini".to_string(),
+            merge_token_budget: Some(20_000),
+            dry_run: false,
+        }
+    }
+}
+
+impl Validate for ConsolidationConfig {
+    fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        if !(self.similarity_threshold > 0.0 && self.similarity_threshold <= 1.0) {
+            issues.push(ConfigIssue::error(
+                "memory.consolidation.similarity_threshold",
+                format!("must be in (0, 1], got {}", self.similarity_threshold),
+            ));
+        } else if self.similarity_threshold < 0.8 {
+            issues.push(
+                ConfigIssue::warning(
+                    "memory.consolidation.similarity_threshold",
+                    "below 0.8 merges entries that are merely related",
+                )
+                .suggest("use 0.9 or higher"),
+            );
+        }
+        if self.mode == ConsolidationMode::Merge && self.model.trim().is_empty() {
+            issues.push(ConfigIssue::error(
+                "memory.consolidation.model",
+                "merge mode needs a model",
+            ));
+        }
+        issues
+    }
+}
+
+/// One cluster of near-duplicates
+#[derive(Debug, Clone, Serialize)]
+pub struct ProposedMerge {
+    /// Owning user
+    pub user_id: String,
+    /// Owning agent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+    /// Shared entry kind
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// Entry kept as the representative (keep-latest mode)
+    pub keep: String,
+    /// Every member ID, newest first, `keep` included
+    pub members: Vec<String>,
+    /// Whether the cluster contains a pinned entry (always keep-latest)
+    pub pinned: bool,
+    /// Merged entry ID, once written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merged_into: Option<String>,
+    /// Whether the cluster was actually consolidated
+    pub applied: bool,
+}
+
+/// Outcome of a consolidation pass
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConsolidationReport {
+    /// Clusters of two or more entries found
+    pub clusters_found: usize,
+    /// Entries marked superseded
+    pub entries_superseded: usize,
+    /// Merged entries written
+    pub entries_merged: usize,
+    /// Tokens spent on merge summaries
+    pub tokens_spent: u64,
+    /// Clusters left alone because the merge budget ran out
+    pub skipped_for_budget: usize,
+    /// Every cluster, for review (all unapplied in a dry run)
+    pub proposals: Vec<ProposedMerge>,
+}
+
+/// Scope entries are consolidated within
+type Scope = (String, Option<String>, Option<String>);
+
+struct Candidate<'a> {
+    entry: &'a StoredEntry,
+    created_at: DateTime<Utc>,
+    pinned: bool,
+}
+
+/// Consolidates near-duplicate long-term memories in an [`InMemoryVectorStore`]
+///
+/// Run it on a schedule with
+/// [`MaintenanceManager::start_memory_consolidation`](crate::infra::maintenance::MaintenanceManager::start_memory_consolidation).
+pub struct MemoryConsolidator {
+    store: Arc<InMemoryVectorStore>,
+    provider: Option<Arc<dyn Provider>>,
+    config: ConsolidationConfig,
+}
+
+impl MemoryConsolidator {
+    /// Consolidate `store` with `config`
+    pub fn new(store: Arc<InMemoryVectorStore>, config: ConsolidationConfig) -> Self {
+        Self {
+            store,
+            provider: None,
+            config,
+        }
+    }
+
+    /// Provider that writes merged entries (required for merge mode)
+    pub fn with_provider(mut self, provider: Arc<dyn Provider>) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Settings in use
+    pub fn config(&self) -> &ConsolidationConfig {
+        &self.config
+    }
+
+    /// Find clusters and, unless `dry_run`, consolidate them
+    ///
+    /// Idempotent: superseded entries are ignored and a consolidated cluster
+    /// leaves a single live entry, so a second pass finds nothing.
+    pub async fn run(&self) -> Result<ConsolidationReport> {
+        let mut issues = self.config.validate();
+        if self.config.mode == ConsolidationMode::Merge && self.provider.is_none() {
+            issues.push(ConfigIssue::error(
+                "memory.consolidation.mode",
+                "merge mode needs a provider",
+            ));
+        }
+        ConfigIssues::check(issues, false)?;
+
+        let entries = self.store.entries();
+        let mut report = ConsolidationReport::default();
+        for (scope, candidates) in group(&entries) {
+            for cluster in self.cluster(candidates) {
+                report.clusters_found += 1;
+                let mut proposal = proposal(&scope, &cluster);
+                if !self.config.dry_run {
+                    self.apply(&cluster, &mut proposal, &mut report).await?;
+                }
+                report.proposals.push(proposal);
+            }
+        }
+        info!(
+            clusters = report.clusters_found,
+            superseded = report.entries_superseded,
+            tokens = report.tokens_spent,
+            dry_run = self.config.dry_run,
+            "Memory consolidation pass finished"
+        );
+        Ok(report)
+    }
+
+    /// Greedy clustering: the newest unassigned entry seeds a cluster and
+    /// takes every older unassigned entry close enough to it
+    fn cluster<'a>(&self, candidates: Vec<Candidate<'a>>) -> Vec<Vec<Candidate<'a>>> {
+        let mut remaining = candidates;
+        let mut clusters = Vec::new();
+        while !remaining.is_empty() {
+            let seed = remaining.remove(0);
+            let (members, rest): (Vec<_>, Vec<_>) =
+                remaining.into_iter().partition(|c| self.matches(&seed, c));
+            remaining = rest;
+            if !members.is_empty() {
+                let mut cluster = vec![seed];
+                cluster.extend(members);
+                clusters.push(cluster);
+            }
+        }
+        clusters
+    }
+
+    fn matches(&self, seed: &Candidate<'_>, other: &Candidate<'_>) -> bool {
+        let gap = (seed.created_at - other.created_at)
+            .to_std()
+            .unwrap_or_default();
+        if gap > self.config.window {
+            return false;
+        }
+        if seed.pinned || other.pinned {
+            return seed.entry.content.trim() == other.entry.content.trim();
+        }
+        cosine(&seed.entry.embedding, &other.entry.embedding) >= self.config.similarity_threshold
+    }
+
+    async fn apply(
+        &self,
+        cluster: &[Candidate<'_>],
+        proposal: &mut ProposedMerge,
+        report: &mut ConsolidationReport,
+    ) -> Result<()> {
+        let keep = if self.config.mode == ConsolidationMode::Merge && !proposal.pinned {
+            let spent = report.tokens_spent;
+            if self.config.merge_token_budget.is_some_and(|b| spent >= b) {
+                report.skipped_for_budget += 1;
+                return Ok(());
+            }
+            let (merged, tokens) = self.merge(cluster).await?;
+            report.tokens_spent += tokens;
+
+            let mut metadata = cluster[0].entry.metadata.clone();
+            metadata.remove(SUPERSEDED_BY);
+            metadata.insert(CONSOLIDATED_FROM.to_string(), proposal.members.join(","));
+            let id = self.store.store(&merged, metadata).await?;
+            report.entries_merged += 1;
+            proposal.merged_into = Some(id.clone());
+            id
+        } else {
+            proposal.keep.clone()
+        };
+
+        for member in &proposal.members {
+            if *member != keep && self.store.set_metadata(member, SUPERSEDED_BY, &keep) {
+                report.entries_superseded += 1;
+            }
+        }
+        proposal.applied = true;
+        Ok(())
+    }
+
+    /// Ask the provider for one entry preserving every distinct fact
+    async fn merge(&self, cluster: &[Candidate<'_>]) -> Result<(String, u64)> {
+        let provider = self.provider.as_ref().expect("checked in run");
+        let listing: String = cluster
+            .iter()
+            .map(|c| format!("- {}\n", c.entry.content.trim()))
+            .collect();
+        let request = ChatRequest {
+            model: self.config.model.clone(),
+            system_prompt: Some(
+                "Merge these near-duplicate memory entries into a single entry. Keep every \
+                 distinct fact, number and date; drop repetition. Reply with the merged entry only."
+                    .to_string(),
+            ),
+            messages: vec![Message::user(listing.clone())],
+            temperature: Some(0.0),
+            max_tokens: Some(512),
+            ..ChatRequest::default()
+        };
+
+        let mut stream = provider.stream_completion(request).await?;
+        let mut merged = String::new();
+        let mut reported = None;
+        while let Some(chunk) = stream.next().await {
+            match chunk? {
+                StreamingChoice::Message(text) => merged.push_str(&text),
+                StreamingChoice::Usage(usage) => reported = Some(usage.total_tokens as u64),
+                StreamingChoice::Done => break,
+                _ => {}
+            }
+        }
+        // Rough 4-chars-per-token estimate when the provider reports no usage
+        let tokens = reported.unwrap_or(((listing.len() + merged.len()) / 4 + 1) as u64);
+        let merged = merged.trim().to_string();
+        if merged.is_empty() {
+            warn!("Provider returned an empty merge, keeping the newest entry instead");
+            return Ok((cluster[0].entry.content.clone(), tokens));
+        }
+        Ok((merged, tokens))
+    }
+}
+
+/// Live entries grouped by scope, each group newest first
+fn group(entries: &[StoredEntry]) -> BTreeMap<Scope, Vec<Candidate<'_>>> {
+    let mut groups: BTreeMap<Scope, Vec<(usize, Candidate<'_>)>> = BTreeMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let meta = &entry.metadata;
+        if is_superseded(meta) {
+            continue;
+        }
+        let Some(user) = meta.get(USER_ID) else {
+            continue;
+        };
+        let kind = meta.get(KIND).cloned();
+        let candidate = Candidate {
+            entry,
+            created_at: meta
+                .get(CREATED_AT)
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.with_timezone(&Utc))
+                .unwrap_or_default(),
+            pinned: meta.get(PINNED).is_some_and(|p| p == "true")
+                || kind.as_deref() == Some("preference"),
+        };
+        groups
+            .entry((user.clone(), meta.get(AGENT_ID).cloned(), kind))
+            .or_default()
+            .push((index, candidate));
+    }
+    groups
+        .into_iter()
+        .map(|(scope, mut candidates)| {
+            // Newest first; later insertion wins ties
+            candidates.sort_by(|(ia, a), (ib, b)| b.created_at.cmp(&a.created_at).then(ib.cmp(ia)));
+            (scope, candidates.into_iter().map(|(_, c)| c).collect())
+        })
+        .collect()
+}
+
+fn proposal(scope: &Scope, cluster: &[Candidate<'_>]) -> ProposedMerge {
+    // Keep a pinned entry over a newer unpinned duplicate so the pin survives
+    let keep = cluster.iter().find(|c| c.pinned).unwrap_or(&cluster[0]);
+    ProposedMerge {
+        user_id: scope.0.clone(),
+        agent_id: scope.1.clone(),
+        kind: scope.2.clone(),
+        keep: keep.entry.id.clone(),
+        members: cluster.iter().map(|c| c.entry.id.clone()).collect(),
+        pinned: cluster.iter().any(|c| c.pinned),
+        merged_into: None,
+        applied: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::streaming::{MockStreamBuilder, StreamingResponse, Usage};
+    use crate::knowledge::rag::Embeddings;
+    use async_trait::async_trait;
+
+    /// Embeds by topic keyword so similarity is predictable
+    struct TopicEmbeddings;
+
+    #[async_trait]
+    impl Embeddings for TopicEmbeddings {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+            let text = text.to_lowercase();
+            let mut v = vec![
+                text.contains("staking") as u8 as f32,
+                text.contains("dca") as u8 as f32,
+                text.contains("dark mode") as u8 as f32,
+            ];
+            // Small per-text jitter keeps near-duplicates just under identical
+            v.push((text.len() % 7) as f32 * 0.01);
+            Ok(v)
+        }
+    }
+
+    struct MergeProvider;
+
+    #[async_trait]
+    impl Provider for MergeProvider {
+        async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+            assert!(request.messages[0].text().contains("SOL staking"));
+            Ok(MockStreamBuilder::new()
+                .message("User tracks SOL staking yields on Marinade and Jito")
+                .usage(Usage {
+                    prompt_tokens: 40,
+                    completion_tokens: 12,
+                    total_tokens: 52,
+                    ..Default::default()
+                })
+                .done()
+                .build())
+        }
+
+        fn name(&self) -> &'static str {
+            "merge"
+        }
+    }
+
+    async fn entry(
+        store: &InMemoryVectorStore,
+        content: &str,
+        kind: &str,
+        days_ago: i64,
+        pinned: bool,
+    ) -> String {
+        let created = Utc::now() - chrono::Duration::days(days_ago);
+        let mut meta = HashMap::from([
+            (USER_ID.to_string(), "alice".to_string()),
+            (KIND.to_string(), kind.to_string()),
+            (CREATED_AT.to_string(), created.to_rfc3339()),
+        ]);
+        if pinned {
+            meta.insert(PINNED.to_string(), "true".to_string());
+        }
+        store.store(content, meta).await.unwrap()
+    }
+
+    async fn seeded() -> (Arc<InMemoryVectorStore>, Vec<String>) {
+        let store = Arc::new(InMemoryVectorStore::with_embedder(Arc::new(
+            TopicEmbeddings,
+        )));
+        let ids = vec![
+            entry(
+                &store,
+                "User asked about SOL staking yields",
+                "fact",
+                3,
+                false,
+            )
+            .await,
+            entry(
+                &store,
+                "User asked about SOL staking yields on Jito",
+                "fact",
+                2,
+                false,
+            )
+            .await,
+            entry(
+                &store,
+                "User asked about SOL staking on Marinade",
+                "fact",
+                1,
+                false,
+            )
+            .await,
+            // Same topic, different kind: never merged with the facts
+            entry(&store, "Check SOL staking weekly", "task", 1, false).await,
+            // Unrelated
+            entry(&store, "User runs a weekly DCA into BTC", "fact", 1, false).await,
+            // Pinned near-duplicates that differ are left alone; identical ones fold
+            entry(&store, "Prefers dark mode charts", "preference", 5, false).await,
+            entry(
+                &store,
+                "Prefers dark mode charts and candles",
+                "preference",
+                4,
+                false,
+            )
+            .await,
+            entry(&store, "Prefers dark mode charts", "note", 3, true).await,
+            entry(&store, "Prefers dark mode charts", "note", 2, true).await,
+        ];
+        (store, ids)
+    }
+
+    fn live(store: &InMemoryVectorStore) -> Vec<String> {
+        store
+            .entries()
+            .into_iter()
+            .filter(|e| !is_superseded(&e.metadata))
+            .map(|e| e.content)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_keep_latest_is_idempotent_and_spares_pinned() {
+        let (store, ids) = seeded().await;
+
+        let dry = ConsolidationConfig {
+            dry_run: true,
+            ..Default::default()
+        };
+        let plan = MemoryConsolidator::new(store.clone(), dry)
+            .run()
+            .await
+            .unwrap();
+        assert_eq!(plan.clusters_found, 2);
+        assert!(plan.proposals.iter().all(|p| !p.applied));
+        assert_eq!(live(&store).len(), 9);
+
+        let consolidator = MemoryConsolidator::new(store.clone(), ConsolidationConfig::default());
+        let report = consolidator.run().await.unwrap();
+        assert_eq!((report.clusters_found, report.entries_superseded), (2, 3));
+        let staking = &report.proposals.iter().find(|p| !p.pinned).unwrap();
+        assert_eq!(staking.keep, ids[2]);
+        assert_eq!(
+            staking.members,
+            vec![ids[2].clone(), ids[1].clone(), ids[0].clone()]
+        );
+        let pinned = &report.proposals.iter().find(|p| p.pinned).unwrap();
+        assert_eq!(pinned.members, vec![ids[8].clone(), ids[7].clone()]);
+
+        let remaining = live(&store);
+        assert_eq!(remaining.len(), 6);
+        assert!(remaining.contains(&"Prefers dark mode charts and candles".to_string()));
+        let hits = store.search("SOL staking", 10).await.unwrap();
+        assert!(hits.iter().all(|d| d.id != ids[0] && d.id != ids[1]));
+
+        let again = consolidator.run().await.unwrap();
+        assert_eq!((again.clusters_found, again.entries_superseded), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_merge_mode_writes_linked_entry_within_budget() {
+        let (store, ids) = seeded().await;
+        let config = ConsolidationConfig {
+            mode: ConsolidationMode::Merge,
+            ..Default::default()
+        };
+        assert!(MemoryConsolidator::new(store.clone(), config.clone())
+            .run()
+            .await
+            .is_err());
+
+        let consolidator =
+            MemoryConsolidator::new(store.clone(), config).with_provider(Arc::new(MergeProvider));
+        let report = consolidator.run().await.unwrap();
+        assert_eq!((report.entries_merged, report.tokens_spent), (1, 52));
+        // The pinned cluster is folded, not rewritten
+        assert_eq!(report.entries_superseded, 3 + 1);
+
+        let merged_id = report
+            .proposals
+            .iter()
+            .find_map(|p| p.merged_into.clone())
+            .unwrap();
+        let merged = store
+            .entries()
+            .into_iter()
+            .find(|e| e.id == merged_id)
+            .unwrap();
+        assert_eq!(
+            merged.metadata[CONSOLIDATED_FROM],
+            format!("{},{},{}", ids[2], ids[1], ids[0])
+        );
+        assert_eq!(merged.metadata[KIND], "fact");
+        assert!(live(&store)
+            .contains(&"User tracks SOL staking yields on Marinade and Jito".to_string()));
+
+        let again = consolidator.run().await.unwrap();
+        assert_eq!((again.clusters_found, again.tokens_spent), (0, 0));
+    }
+}
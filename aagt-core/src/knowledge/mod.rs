@@ -1,2 +1,3 @@
+pub mod consolidation;
 pub mod rag;
 pub mod store;
@@ -44,9 +44,26 @@ pub trait VectorStore: Send + Sync {
 }
 
 /// Interface for embeddings providers
-/// (Optional: Application might handle embeddings manually)
+///
+/// Shared by every store that embeds text (memory vector search, QMD hybrid
+/// search), so one `Arc<dyn Embeddings>` can back all of them and the model is
+/// loaded once.
 #[async_trait]
 pub trait Embeddings: Send + Sync {
     /// Generate embedding vector for text
     async fn embed(&self, text: &str) -> Result<Vec<f32>>;
+
+    /// Embed several texts; override when the backend batches natively
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let mut out = Vec::with_capacity(texts.len());
+        for text in texts {
+            out.push(self.embed(text).await?);
+        }
+        Ok(out)
+    }
+
+    /// Output dimension, when known without embedding anything
+    fn dimension(&self) -> Option<usize> {
+        None
+    }
 }
@@ -0,0 +1,293 @@
+//! Approximate nearest-neighbour index for large in-memory stores
+//!
+//! [`HnswIndex`] is a Hierarchical Navigable Small World graph over
+//! normalized embeddings, so distance is `1 - cosine`. Entries are added one
+//! at a time; removed entries are tombstoned (still traversed, never
+//! returned) until the owner rebuilds the index.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// When and how to search with an approximate index instead of a full scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnnConfig {
+    /// Links per node on upper layers (twice as many on the bottom layer)
+    pub m: usize,
+    /// Candidates considered while linking a new entry
+    pub ef_construction: usize,
+    /// Candidates considered per search; raise for recall, lower for speed
+    pub ef_search: usize,
+    /// Build the index once the store holds more than this many entries
+    pub threshold: usize,
+}
+
+impl Default for AnnConfig {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 200,
+            ef_search: 64,
+            threshold: 10_000,
+        }
+    }
+}
+
+/// HNSW graph keyed by entry ID
+pub(crate) struct HnswIndex {
+    config: AnnConfig,
+    vectors: Vec<Vec<f32>>,
+    keys: Vec<String>,
+    /// `links[node][level]`: neighbours of `node` on `level`
+    links: Vec<Vec<Vec<u32>>>,
+    deleted: Vec<bool>,
+    dead: usize,
+    by_key: HashMap<String, u32>,
+    entry: Option<u32>,
+    rng: StdRng,
+}
+
+impl HnswIndex {
+    /// Empty index; level assignment is seeded so builds are reproducible
+    pub(crate) fn new(config: AnnConfig) -> Self {
+        Self {
+            config,
+            vectors: Vec::new(),
+            keys: Vec::new(),
+            links: Vec::new(),
+            deleted: Vec::new(),
+            dead: 0,
+            by_key: HashMap::new(),
+            entry: None,
+            rng: StdRng::seed_from_u64(0x5eed),
+        }
+    }
+
+    /// Live (not removed) entries
+    pub(crate) fn len(&self) -> usize {
+        self.keys.len() - self.dead
+    }
+
+    /// Whether tombstones make up over a quarter of the graph
+    pub(crate) fn needs_rebuild(&self) -> bool {
+        self.dead * 4 > self.keys.len()
+    }
+
+    /// Link `vector` into the graph under `key`, replacing an entry with the same key
+    pub(crate) fn insert(&mut self, key: &str, vector: &[f32]) {
+        self.remove(key);
+        let vector = normalized(vector);
+        let node = self.keys.len() as u32;
+        let level = self.random_level();
+        self.vectors.push(vector);
+        self.keys.push(key.to_string());
+        self.links.push(vec![Vec::new(); level + 1]);
+        self.deleted.push(false);
+        self.by_key.insert(key.to_string(), node);
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(node);
+            return;
+        };
+        let query = self.vectors[node as usize].clone();
+        let top = self.links[entry as usize].len() - 1;
+        let mut nearest = vec![entry];
+        for l in (level + 1..=top).rev() {
+            nearest = self.closest(&query, &nearest, 1, l);
+        }
+        for l in (0..=level.min(top)).rev() {
+            let found = self.search_layer(&query, &nearest, self.config.ef_construction, l);
+            let neighbours: Vec<u32> = found
+                .iter()
+                .take(self.config.m.max(2))
+                .map(|&(_, n)| n)
+                .collect();
+            for &other in &neighbours {
+                self.links[other as usize][l].push(node);
+                self.prune(other, l);
+            }
+            self.links[node as usize][l] = neighbours;
+            nearest = found.into_iter().map(|(_, n)| n).collect();
+        }
+        if level > top {
+            self.entry = Some(node);
+        }
+    }
+
+    /// Tombstone `key`; returns false if it is not indexed
+    pub(crate) fn remove(&mut self, key: &str) -> bool {
+        match self.by_key.remove(key) {
+            Some(node) => {
+                self.deleted[node as usize] = true;
+                self.dead += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Up to `k` live entries closest to `query`, as (key, cosine similarity), best first
+    pub(crate) fn search(&self, query: &[f32], k: usize) -> Vec<(&str, f32)> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+        let query = normalized(query);
+        let mut nearest = vec![entry];
+        for l in (1..self.links[entry as usize].len()).rev() {
+            nearest = self.closest(&query, &nearest, 1, l);
+        }
+        self.search_layer(&query, &nearest, self.config.ef_search.max(k), 0)
+            .into_iter()
+            .filter(|&(_, n)| !self.deleted[n as usize])
+            .take(k)
+            .map(|(d, n)| (self.keys[n as usize].as_str(), 1.0 - f32::from_bits(d)))
+            .collect()
+    }
+
+    /// Level for a new node: geometric with ratio `1/m`
+    fn random_level(&mut self) -> usize {
+        let scale = 1.0 / (self.config.m.max(2) as f64).ln();
+        let u: f64 = self.rng.gen_range(f64::EPSILON..1.0);
+        (-u.ln() * scale) as usize
+    }
+
+    fn closest(&self, query: &[f32], from: &[u32], ef: usize, level: usize) -> Vec<u32> {
+        self.search_layer(query, from, ef, level)
+            .into_iter()
+            .map(|(_, n)| n)
+            .collect()
+    }
+
+    /// Best-first search of one layer, returning up to `ef` (distance key, node) pairs, nearest first
+    fn search_layer(
+        &self,
+        query: &[f32],
+        from: &[u32],
+        ef: usize,
+        level: usize,
+    ) -> Vec<(u32, u32)> {
+        let mut visited: HashSet<u32> = from.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut nearest = BinaryHeap::new();
+        for &node in from {
+            let d = self.distance_key(query, node);
+            candidates.push(Reverse((d, node)));
+            nearest.push((d, node));
+        }
+        while nearest.len() > ef {
+            nearest.pop();
+        }
+        while let Some(Reverse((d, node))) = candidates.pop() {
+            if nearest.len() >= ef && nearest.peek().is_some_and(|&(worst, _)| d > worst) {
+                break;
+            }
+            for &other in &self.links[node as usize][level] {
+                if !visited.insert(other) {
+                    continue;
+                }
+                let d = self.distance_key(query, other);
+                if nearest.len() < ef || nearest.peek().is_some_and(|&(worst, _)| d < worst) {
+                    candidates.push(Reverse((d, other)));
+                    nearest.push((d, other));
+                    if nearest.len() > ef {
+                        nearest.pop();
+                    }
+                }
+            }
+        }
+        nearest.into_sorted_vec()
+    }
+
+    /// Keep only the closest links of `node` on `level` once it has too many
+    fn prune(&mut self, node: u32, level: usize) {
+        let max = if level == 0 {
+            self.config.m.max(2) * 2
+        } else {
+            self.config.m.max(2)
+        };
+        if self.links[node as usize][level].len() <= max {
+            return;
+        }
+        let base = &self.vectors[node as usize];
+        let mut scored: Vec<(u32, u32)> = self.links[node as usize][level]
+            .iter()
+            .map(|&other| (distance_key(base, &self.vectors[other as usize]), other))
+            .collect();
+        scored.sort_unstable();
+        self.links[node as usize][level] = scored.into_iter().take(max).map(|(_, n)| n).collect();
+    }
+
+    fn distance_key(&self, query: &[f32], node: u32) -> u32 {
+        distance_key(query, &self.vectors[node as usize])
+    }
+}
+
+/// `1 - cosine` of unit vectors as ordered bits (non-negative floats sort like their bits)
+fn distance_key(a: &[f32], b: &[f32]) -> u32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    (1.0 - dot).max(0.0).to_bits()
+}
+
+fn normalized(vector: &[f32]) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return vector.to_vec();
+    }
+    vector.iter().map(|x| x / norm).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::store::memory::cosine;
+
+    #[test]
+    fn test_ann_top10_recall_against_brute_force() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut random = |n: usize| -> Vec<Vec<f32>> {
+            (0..n)
+                .map(|_| (0..24).map(|_| rng.gen_range(-1.0f32..1.0)).collect())
+                .collect()
+        };
+        let vectors = random(2000);
+        let queries = random(50);
+
+        let mut index = HnswIndex::new(AnnConfig {
+            ef_construction: 100,
+            ef_search: 100,
+            threshold: 0,
+            ..Default::default()
+        });
+        for (i, v) in vectors.iter().enumerate() {
+            index.insert(&i.to_string(), v);
+        }
+        // Tombstoned entries are never returned
+        for i in 0..100 {
+            assert!(index.remove(&i.to_string()));
+        }
+        assert_eq!(index.len(), 1900);
+        assert!(!index.needs_rebuild());
+
+        let mut hits = 0;
+        for query in &queries {
+            let mut exact: Vec<(f32, usize)> = (100..vectors.len())
+                .map(|i| (cosine(query, &vectors[i]), i))
+                .collect();
+            exact.sort_by(|a, b| b.0.total_cmp(&a.0));
+            let exact: HashSet<String> =
+                exact.iter().take(10).map(|(_, i)| i.to_string()).collect();
+
+            let found = index.search(query, 10);
+            assert_eq!(found.len(), 10);
+            assert!(found
+                .iter()
+                .all(|(key, _)| key.parse::<usize>().unwrap() >= 100));
+            hits += found.iter().filter(|(key, _)| exact.contains(*key)).count();
+        }
+        let recall = hits as f64 / (queries.len() * 10) as f64;
+        assert!(recall >= 0.9, "recall@10 was {:.3}", recall);
+    }
+}
@@ -0,0 +1,288 @@
+//! In-process vector store backed by a shared [`Embeddings`] provider
+//!
+//! Searches scan every entry by default. With [`InMemoryVectorStore::with_ann`],
+//! stores past the configured size are searched through an HNSW index
+//! instead; it is kept up to date on store and rebuilt once deletes leave
+//! too many tombstones.
+
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::knowledge::consolidation::is_superseded;
+use crate::knowledge::store::ann::{AnnConfig, HnswIndex};
+use crate::knowledge::rag::{Document, Embeddings, VectorStore};
+
+/// Candidates fetched per requested result, so filtering still leaves enough
+const ANN_OVERSAMPLE: usize = 4;
+
+/// A stored document with its embedding
+#[derive(Debug, Clone)]
+pub struct StoredEntry {
+    /// Document ID
+    pub id: String,
+    /// Stored text
+    pub content: String,
+    /// Metadata given at store time (plus consolidation markers)
+    pub metadata: HashMap<String, String>,
+    /// Embedding of `content`
+    pub embedding: Vec<f32>,
+}
+
+/// Brute-force cosine-similarity store kept in memory
+///
+/// Pass the same `Arc<dyn Embeddings>` used by other stores (e.g. QMD hybrid
+/// search) so the model is shared.
+pub struct InMemoryVectorStore {
+    embedder: Arc<dyn Embeddings>,
+    entries: RwLock<Vec<StoredEntry>>,
+    dimension: RwLock<Option<usize>>,
+    ann_config: Option<AnnConfig>,
+    /// Locked after `entries` when both are needed
+    ann: RwLock<Option<AnnState>>,
+}
+
+/// The ANN index and each indexed entry's position in `entries`
+struct AnnState {
+    index: HnswIndex,
+    positions: HashMap<String, usize>,
+}
+
+impl AnnState {
+    fn build(config: &AnnConfig, entries: &[StoredEntry]) -> Self {
+        let mut index = HnswIndex::new(config.clone());
+        let mut positions = HashMap::with_capacity(entries.len());
+        for (i, entry) in entries.iter().enumerate() {
+            index.insert(&entry.id, &entry.embedding);
+            positions.insert(entry.id.clone(), i);
+        }
+        Self { index, positions }
+    }
+}
+
+impl InMemoryVectorStore {
+    /// Create a store that embeds with `embedder`
+    pub fn with_embedder(embedder: Arc<dyn Embeddings>) -> Self {
+        let dimension = embedder.dimension();
+        Self {
+            embedder,
+            entries: RwLock::new(Vec::new()),
+            dimension: RwLock::new(dimension),
+            ann_config: None,
+            ann: RwLock::new(None),
+        }
+    }
+
+    /// Search through an approximate index once the store outgrows `config.threshold`
+    pub fn with_ann(mut self, config: AnnConfig) -> Self {
+        self.ann_config = Some(config);
+        self
+    }
+
+    /// Whether searches currently go through the approximate index
+    pub fn is_ann_active(&self) -> bool {
+        self.ann.read().is_some()
+    }
+
+    /// The embeddings provider
+    pub fn embedder(&self) -> &Arc<dyn Embeddings> {
+        &self.embedder
+    }
+
+    /// Number of stored documents
+    pub fn len(&self) -> usize {
+        self.entries.read().len()
+    }
+
+    /// Whether the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.read().is_empty()
+    }
+
+    /// Snapshot of every stored entry, superseded ones included, in insertion order
+    pub fn entries(&self) -> Vec<StoredEntry> {
+        self.entries.read().clone()
+    }
+
+    /// Set a metadata key on `id`; returns false if there is no such entry
+    pub fn set_metadata(&self, id: &str, key: &str, value: impl Into<String>) -> bool {
+        match self.entries.write().iter_mut().find(|e| e.id == id) {
+            Some(entry) => {
+                entry.metadata.insert(key.to_string(), value.into());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Fix the dimension on first use and reject vectors that disagree
+    fn check_dimension(&self, embedding: &[f32]) -> Result<()> {
+        let mut dimension = self.dimension.write();
+        match *dimension {
+            Some(d) if d != embedding.len() => Err(Error::Internal(format!(
+                "Embedding dimension mismatch: store uses {}, embedder returned {}",
+                d,
+                embedding.len()
+            ))),
+            Some(_) => Ok(()),
+            None => {
+                *dimension = Some(embedding.len());
+                Ok(())
+            }
+        }
+    }
+}
+
+pub(crate) fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm =
+        a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        0.0
+    } else {
+        dot / norm
+    }
+}
+
+#[async_trait]
+impl VectorStore for InMemoryVectorStore {
+    async fn store(&self, content: &str, metadata: HashMap<String, String>) -> Result<String> {
+        let embedding = self.embedder.embed(content).await?;
+        self.check_dimension(&embedding)?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut entries = self.entries.write();
+        entries.push(StoredEntry {
+            id: id.clone(),
+            content: content.to_string(),
+            metadata,
+            embedding,
+        });
+        if let Some(config) = &self.ann_config {
+            let mut ann = self.ann.write();
+            match ann.as_mut() {
+                Some(state) => {
+                    let entry = &entries[entries.len() - 1];
+                    state.index.insert(&entry.id, &entry.embedding);
+                    state.positions.insert(id.clone(), entries.len() - 1);
+                }
+                None if entries.len() > config.threshold => {
+                    tracing::debug!("Building ANN index over {} entries", entries.len());
+                    *ann = Some(AnnState::build(config, &entries));
+                }
+                None => {}
+            }
+        }
+        Ok(id)
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<Document>> {
+        if self.is_empty() {
+            return Ok(Vec::new());
+        }
+        let query = self.embedder.embed(query).await?;
+        self.check_dimension(&query)?;
+
+        let entries = self.entries.read();
+        if let Some(state) = self.ann.read().as_ref() {
+            // Post-filter oversampled candidates; too few survivors fall back to a full scan
+            let candidates: Vec<(f32, &StoredEntry)> = state
+                .index
+                .search(&query, limit.saturating_mul(ANN_OVERSAMPLE))
+                .into_iter()
+                .filter_map(|(id, score)| Some((score, &entries[*state.positions.get(id)?])))
+                .filter(|(_, e)| !is_superseded(&e.metadata))
+                .take(limit)
+                .collect();
+            if candidates.len() == limit || candidates.len() == state.index.len() {
+                return Ok(candidates.into_iter().map(|(score, e)| to_document(score, e)).collect());
+            }
+        }
+        let mut scored: Vec<(f32, &StoredEntry)> = entries
+            .iter()
+            // Consolidated duplicates stay stored for audit but never surface
+            .filter(|e| !is_superseded(&e.metadata))
+            .map(|e| (cosine(&query, &e.embedding), e))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        Ok(scored
+            .into_iter()
+            .take(limit)
+            .map(|(score, e)| to_document(score, e))
+            .collect())
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        let mut entries = self.entries.write();
+        entries.retain(|e| e.id != id);
+        let mut ann = self.ann.write();
+        if let (Some(state), Some(config)) = (ann.as_mut(), &self.ann_config) {
+            if state.index.remove(id) {
+                if state.index.needs_rebuild() || entries.len() <= config.threshold {
+                    *ann = (entries.len() > config.threshold).then(|| AnnState::build(config, &entries));
+                } else {
+                    state.positions = entries.iter().enumerate().map(|(i, e)| (e.id.clone(), i)).collect();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn to_document(score: f32, e: &StoredEntry) -> Document {
+    Document {
+        id: e.id.clone(),
+        title: e.metadata.get("title").cloned().unwrap_or_default(),
+        content: e.content.clone(),
+        summary: None,
+        collection: e.metadata.get("collection").cloned(),
+        path: e.metadata.get("path").cloned(),
+        metadata: e.metadata.clone(),
+        score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::consolidation::SUPERSEDED_BY;
+
+    /// Embeds "x,y" as the vector [x, y]
+    struct PointEmbeddings;
+
+    #[async_trait]
+    impl Embeddings for PointEmbeddings {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+            Ok(text.split(',').map(|v| v.trim().parse().unwrap_or(0.0)).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ann_index_follows_threshold_filters_and_deletes() {
+        let store = InMemoryVectorStore::with_embedder(Arc::new(PointEmbeddings)).with_ann(AnnConfig {
+            threshold: 4,
+            ..Default::default()
+        });
+        let mut ids = HashMap::new();
+        for point in ["1,0", "1,0.1", "1,0.3", "0,1"] {
+            ids.insert(point, store.store(point, HashMap::new()).await.unwrap());
+        }
+        assert!(!store.is_ann_active());
+        ids.insert("-1,0", store.store("-1,0", HashMap::new()).await.unwrap());
+        assert!(store.is_ann_active());
+
+        let contents = |docs: Vec<Document>| docs.into_iter().map(|d| d.content).collect::<Vec<_>>();
+        assert_eq!(contents(store.search("1,0", 2).await.unwrap()), ["1,0", "1,0.1"]);
+
+        // Superseded entries are filtered out of the ANN candidates
+        assert!(store.set_metadata(&ids["1,0"], SUPERSEDED_BY, "x"));
+        assert_eq!(contents(store.search("1,0", 2).await.unwrap()), ["1,0.1", "1,0.3"]);
+
+        // Deleted entries never come back, and the index goes away below the threshold
+        store.delete(&ids["1,0.1"]).await.unwrap();
+        assert!(!store.is_ann_active());
+        assert_eq!(contents(store.search("1,0", 2).await.unwrap()), ["1,0.3", "0,1"]);
+    }
+}
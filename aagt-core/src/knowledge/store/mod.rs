@@ -1,2 +1,9 @@
 // Core knowledge storage traits and common types.
-// Individual implementations are provide by external crates.
+// Durable implementations are provided by external crates; `memory` is an
+// in-process store for small deployments and tests.
+
+pub mod ann;
+pub mod memory;
+
+pub use ann::AnnConfig;
+pub use memory::{InMemoryVectorStore, StoredEntry};
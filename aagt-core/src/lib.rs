@@ -19,8 +19,18 @@ pub mod prelude;
 pub mod skills;
 #[cfg(feature = "trading")]
 pub mod trading;
+#[cfg(test)]
+mod test_support;
 
 // Re-export common types for convenience
 pub use agent::core::{Agent, AgentBuilder, AgentConfig};
 pub use agent::message::{Content, Message, Role};
 pub use error::{Error, Result};
+
+// Lets `#[tool]` expansions in this crate's tests resolve `aagt_core::...` paths
+#[cfg(test)]
+extern crate self as aagt_core;
+
+// Used by code generated from aagt-macros
+#[doc(hidden)]
+pub use anyhow;
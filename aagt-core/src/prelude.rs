@@ -8,7 +8,7 @@
 pub use crate::error::{Error, Result};
 
 // Agent
-pub use crate::agent::context::{ContextConfig, ContextInjector, ContextManager};
+pub use crate::agent::context::{ContextConfig, ContextInjector, ContextManager, TokenCounter};
 pub use crate::agent::core::{Agent, AgentBuilder, AgentConfig};
 pub use crate::agent::memory::{Memory, MemoryManager, ShortTermMemory};
 pub use crate::agent::message::{Content, ContentPart, ImageSource, Message, Role, ToolCall};
@@ -25,11 +25,11 @@ pub use crate::skills::{DynamicSkill, SkillExecutionConfig, SkillLoader};
 pub use crate::trading::pipeline::{Context as PipelineContext, Pipeline, Step};
 #[cfg(feature = "trading")]
 pub use crate::trading::risk::{
-    RiskCheck, RiskCheckBuilder, RiskConfig, RiskManager, TradeContext,
+    FailureCooldown, RiskCheck, RiskCheckBuilder, RiskConfig, RiskManager, TradeContext,
 };
 #[cfg(feature = "trading")]
 pub use crate::trading::strategy::{Action, Condition, FileStrategyStore, Strategy, StrategyStore};
 
 // Infra
-pub use crate::infra::maintenance::{MaintenanceConfig, MaintenanceManager};
+pub use crate::infra::maintenance::{BackupJob, MaintenanceConfig, MaintenanceManager};
 pub use crate::infra::notification::NotifyChannel;
@@ -0,0 +1,627 @@
+//! ClawHub registry search and install
+//!
+//! Search output from the `clawhub` CLI is parsed into [`RegistryEntry`]s
+//! (JSON when the CLI emits it, otherwise one `slug version description` line
+//! per result), ranked by a transparent score and rendered as a table.
+//! Unverified results a small edit away from a higher-ranked name are flagged
+//! as possible typosquats. Installs are checked against the
+//! [`ClawHubPolicy`] denylist and publisher trust list first.
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::infra::validation::edit_distance;
+use crate::skills::tool::{parse_args, ArgsExt, Tool, ToolDefinition};
+use crate::skills::SkillLoader;
+
+/// Max edit distance at which a lower-ranked name is flagged as a typosquat
+const TYPOSQUAT_DISTANCE: usize = 2;
+
+/// One search result from the registry
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RegistryEntry {
+    /// Package slug
+    pub slug: String,
+    /// Latest version
+    pub version: Option<String>,
+    /// One-line description
+    pub description: Option<String>,
+    /// Publisher handle
+    pub publisher: Option<String>,
+    /// Registry marks the publisher as verified
+    pub verified: bool,
+    /// Last publish time
+    pub published_at: Option<DateTime<Utc>>,
+    /// Download count
+    pub downloads: Option<u64>,
+}
+
+/// `major.minor.patch` plus whether there is a pre-release suffix
+fn parse_version(version: &str) -> Option<(u64, u64, u64, bool)> {
+    let version = version.trim().trim_start_matches('v');
+    let (core, pre) = match version.split_once(['-', '+']) {
+        Some((core, rest)) => (
+            core,
+            version.as_bytes()[core.len()] == b'-' && !rest.is_empty(),
+        ),
+        None => (version, false),
+    };
+    let mut parts = core.split('.').map(|p| p.parse::<u64>());
+    let (major, minor, patch) = (
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+    );
+    parts.next().is_none().then_some((major, minor, patch, pre))
+}
+
+/// Semver ordering (pre-releases sort before their release); unparseable versions sort first
+fn version_key(version: Option<&str>) -> Option<(u64, u64, u64, bool)> {
+    version
+        .and_then(parse_version)
+        .map(|(ma, mi, pa, pre)| (ma, mi, pa, !pre))
+}
+
+fn str_field<'a>(obj: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|k| obj.get(*k).and_then(Value::as_str))
+}
+
+fn entry_from_json(obj: &Value) -> Option<RegistryEntry> {
+    let slug = str_field(obj, &["slug", "name"])?.to_string();
+    let owner = obj
+        .get("publisher")
+        .or_else(|| obj.get("owner"))
+        .or_else(|| obj.get("author"));
+    let publisher = match owner {
+        Some(Value::String(s)) => Some(s.clone()),
+        Some(o) => str_field(o, &["handle", "name", "username"]).map(String::from),
+        None => None,
+    };
+    let verified = ["verified", "publisher_verified", "verifiedPublisher"]
+        .iter()
+        .find_map(|k| obj.get(*k).and_then(Value::as_bool))
+        .or_else(|| {
+            owner
+                .and_then(|o| o.get("verified"))
+                .and_then(Value::as_bool)
+        })
+        .unwrap_or(false);
+    let published_at = ["published_at", "publishedAt", "updated_at", "updatedAt"]
+        .iter()
+        .find_map(|k| match obj.get(*k)? {
+            Value::String(s) => DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|d| d.with_timezone(&Utc)),
+            // Unix milliseconds
+            Value::Number(n) => Utc.timestamp_millis_opt(n.as_i64()?).single(),
+            _ => None,
+        });
+    Some(RegistryEntry {
+        slug,
+        version: str_field(obj, &["version", "latest_version", "latestVersion"]).map(String::from),
+        description: str_field(obj, &["description", "summary"]).map(String::from),
+        publisher,
+        verified,
+        published_at,
+        downloads: ["downloads", "installs", "downloadCount"]
+            .iter()
+            .find_map(|k| obj.get(*k).and_then(Value::as_u64)),
+    })
+}
+
+fn entry_from_line(line: &str) -> Option<RegistryEntry> {
+    let mut tokens = line.split_whitespace();
+    let slug = tokens.next()?;
+    let is_slug = slug
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "@/_.-".contains(c))
+        && slug.chars().any(|c| c.is_ascii_alphabetic());
+    if !is_slug {
+        return None;
+    }
+    let rest: Vec<&str> = tokens.collect();
+    let version = rest.iter().position(|t| parse_version(t).is_some());
+    let description: Vec<&str> = rest
+        .iter()
+        .enumerate()
+        .filter(|(i, t)| Some(*i) != version && !matches!(**t, "-" | "—" | "|"))
+        .map(|(_, t)| *t)
+        .collect();
+    Some(RegistryEntry {
+        slug: slug.to_string(),
+        version: version.map(|i| rest[i].trim_start_matches('v').to_string()),
+        description: (!description.is_empty()).then(|| description.join(" ")),
+        ..RegistryEntry::default()
+    })
+}
+
+/// Parse `clawhub search` output, keeping the newest version of each slug
+pub fn parse_search_output(stdout: &str) -> Vec<RegistryEntry> {
+    let entries: Vec<RegistryEntry> = match serde_json::from_str::<Value>(stdout.trim()) {
+        Ok(value) => {
+            let items = value.as_array().or_else(|| {
+                ["results", "skills", "items"]
+                    .iter()
+                    .find_map(|k| value.get(*k).and_then(Value::as_array))
+            });
+            items
+                .map(|items| items.iter().filter_map(entry_from_json).collect())
+                .unwrap_or_default()
+        }
+        Err(_) => stdout.lines().filter_map(entry_from_line).collect(),
+    };
+
+    let mut newest: BTreeMap<String, RegistryEntry> = BTreeMap::new();
+    for entry in entries {
+        match newest.get(&entry.slug) {
+            Some(existing)
+                if version_key(existing.version.as_deref())
+                    >= version_key(entry.version.as_deref()) => {}
+            _ => {
+                newest.insert(entry.slug.clone(), entry);
+            }
+        }
+    }
+    newest.into_values().collect()
+}
+
+/// Install and trust rules for [`ClawHubTool`]
+#[derive(Debug, Clone)]
+pub struct ClawHubPolicy {
+    /// Publishers treated as verified even if the registry doesn't say so
+    pub trusted_publishers: HashSet<String>,
+    /// Slugs that are never installed
+    pub denylist: HashSet<String>,
+    /// Install skills from unverified publishers (with a warning) instead of refusing
+    pub allow_unverified: bool,
+}
+
+impl Default for ClawHubPolicy {
+    fn default() -> Self {
+        Self {
+            trusted_publishers: HashSet::new(),
+            denylist: HashSet::new(),
+            allow_unverified: true,
+        }
+    }
+}
+
+impl ClawHubPolicy {
+    /// Whether `entry`'s publisher is verified by the registry or trusted locally
+    pub fn is_verified(&self, entry: &RegistryEntry) -> bool {
+        entry.verified
+            || entry
+                .publisher
+                .as_ref()
+                .is_some_and(|p| self.trusted_publishers.contains(p))
+    }
+
+    /// Whether `slug` is on the denylist (case-insensitive)
+    pub fn is_denied(&self, slug: &str) -> bool {
+        let slug = slug.trim().to_lowercase();
+        self.denylist.iter().any(|d| d.to_lowercase() == slug)
+    }
+}
+
+/// A search result with its score
+#[derive(Debug, Clone)]
+pub struct RankedEntry {
+    /// The result
+    pub entry: RegistryEntry,
+    /// Publisher verified or trusted
+    pub verified: bool,
+    /// Ranking score (see [`rank`])
+    pub score: f64,
+    /// Higher-ranked slug this one is suspiciously close to
+    pub typosquat_of: Option<String>,
+}
+
+/// Rank results, best first
+///
+/// Score: verified publisher +3, published within 90 days +2 (within a year
+/// +1), `log10(downloads + 1)` capped at 3, stable (>= 1.0.0) version +0.5,
+/// exact slug match +1. Ties break on slug, so the order is deterministic.
+pub fn rank(
+    entries: Vec<RegistryEntry>,
+    query: &str,
+    policy: &ClawHubPolicy,
+    now: DateTime<Utc>,
+) -> Vec<RankedEntry> {
+    let mut ranked: Vec<RankedEntry> = entries
+        .into_iter()
+        .map(|entry| {
+            let verified = policy.is_verified(&entry);
+            let mut score = if verified { 3.0 } else { 0.0 };
+            if let Some(published) = entry.published_at {
+                let days = (now - published).num_days();
+                score += if days <= 90 {
+                    2.0
+                } else if days <= 365 {
+                    1.0
+                } else {
+                    0.0
+                };
+            }
+            if let Some(downloads) = entry.downloads {
+                score += ((downloads + 1) as f64).log10().min(3.0);
+            }
+            if matches!(entry.version.as_deref().and_then(parse_version), Some((major, _, _, false)) if major >= 1)
+            {
+                score += 0.5;
+            }
+            if entry.slug.eq_ignore_ascii_case(query.trim()) {
+                score += 1.0;
+            }
+            RankedEntry {
+                entry,
+                verified,
+                score,
+                typosquat_of: None,
+            }
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.entry.slug.cmp(&b.entry.slug))
+    });
+
+    for i in 0..ranked.len() {
+        if ranked[i].verified || ranked[i].entry.slug.len() < 4 {
+            continue;
+        }
+        let slug = &ranked[i].entry.slug;
+        ranked[i].typosquat_of = ranked[..i]
+            .iter()
+            .map(|r| &r.entry.slug)
+            .find(|other| edit_distance(slug, other) <= TYPOSQUAT_DISTANCE)
+            .cloned();
+    }
+    ranked
+}
+
+fn format_downloads(n: u64) -> String {
+    match n {
+        0..=999 => n.to_string(),
+        1_000..=999_999 => format!("{:.1}k", n as f64 / 1e3),
+        _ => format!("{:.1}M", n as f64 / 1e6),
+    }
+}
+
+/// Compact table with a warning line per suspected typosquat
+pub fn render_results(query: &str, ranked: &[RankedEntry]) -> String {
+    if ranked.is_empty() {
+        return format!("No skills found for \"{}\".", query);
+    }
+    let mut out = format!(
+        "Found {} skills for \"{}\", best first (score: verified publisher +3, updated within 90d +2 / 1y +1, log10 downloads up to +3, stable version +0.5, exact name +1).\n\n",
+        ranked.len(),
+        query
+    );
+    out.push_str(
+        "| # | slug | version | updated | downloads | publisher | score | description |\n",
+    );
+    out.push_str(
+        "|---|------|---------|---------|-----------|-----------|-------|-------------|\n",
+    );
+    for (i, r) in ranked.iter().enumerate() {
+        let e = &r.entry;
+        let publisher = match (&e.publisher, r.verified) {
+            (Some(p), true) => format!("{} (verified)", p),
+            (Some(p), false) => p.clone(),
+            (None, true) => "(verified)".to_string(),
+            (None, false) => "-".to_string(),
+        };
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {:.1} | {} |\n",
+            i + 1,
+            e.slug,
+            e.version.as_deref().unwrap_or("-"),
+            e.published_at
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            e.downloads
+                .map(format_downloads)
+                .unwrap_or_else(|| "-".to_string()),
+            publisher,
+            r.score,
+            e.description.as_deref().unwrap_or("").replace('|', "/"),
+        ));
+    }
+    for r in ranked {
+        if let Some(original) = &r.typosquat_of {
+            out.push_str(&format!(
+                "\nWARNING: '{}' looks like a typosquat of '{}' (edit distance {}); do not install it unless the user asked for it by exact name.",
+                r.entry.slug,
+                original,
+                edit_distance(&r.entry.slug, original)
+            ));
+        }
+    }
+    out
+}
+
+/// Tool to search and install skills from ClawHub using CLI (npm/pnpm/bun)
+pub struct ClawHubTool {
+    loader: Arc<SkillLoader>,
+    policy: ClawHubPolicy,
+}
+
+impl ClawHubTool {
+    pub fn new(loader: Arc<SkillLoader>) -> Self {
+        Self {
+            loader,
+            policy: ClawHubPolicy::default(),
+        }
+    }
+
+    /// Set the denylist and publisher trust rules
+    pub fn with_policy(mut self, policy: ClawHubPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    async fn run_cli(
+        &self,
+        manager: &str,
+        subcommand: &str,
+        arg: &str,
+    ) -> anyhow::Result<std::process::Output> {
+        let (cmd, base_args) = match manager {
+            "pnpm" => ("pnpm", vec!["dlx", "clawhub@latest"]),
+            "bun" => ("bunx", vec!["clawhub@latest"]),
+            _ => ("npx", vec!["clawhub@latest"]),
+        };
+        Ok(tokio::process::Command::new(cmd)
+            .args(&base_args)
+            .arg(subcommand)
+            .arg(arg)
+            .output()
+            .await?)
+    }
+
+    async fn install(&self, slug: &str, manager: &str) -> anyhow::Result<String> {
+        if self.policy.is_denied(slug) {
+            return Err(anyhow::anyhow!(
+                "Refusing to install '{}': it is on the skill denylist",
+                slug
+            ));
+        }
+
+        // Look the exact slug up first so the publisher can be checked
+        let listing = self.run_cli(manager, "search", slug).await?;
+        let entry = parse_search_output(&String::from_utf8_lossy(&listing.stdout))
+            .into_iter()
+            .find(|e| e.slug == slug);
+        let verified = entry.as_ref().is_some_and(|e| self.policy.is_verified(e));
+        if !verified && !self.policy.allow_unverified {
+            return Err(anyhow::anyhow!(
+                "Refusing to install '{}': its publisher is not verified. Ask the user to install it manually if they trust it.",
+                slug
+            ));
+        }
+
+        info!("Installing skill from ClawHub: {} (via {})", slug, manager);
+        let output = self.run_cli(manager, "install", slug).await?;
+        if !output.status.success() {
+            let err = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("Failed to install skill: {}", err));
+        }
+
+        // Refresh the loader to pick up the new skill
+        info!(
+            "Skill {} installed successfully, refreshing registry...",
+            slug
+        );
+        self.loader.load_all().await?;
+        let mut result = format!(
+            "Successfully installed '{}'. It is now available for use.",
+            slug
+        );
+        let name = slug.rsplit('/').next().unwrap_or(slug);
+        if let Some(skill) = self.loader.get(name) {
+            let declared = &skill.metadata().requires.network;
+            if !declared.is_empty() {
+                result.push_str(&format!("\nNetwork: it calls {}.", declared.join(", ")));
+                let unapproved = skill.unapproved_hosts();
+                if !unapproved.is_empty() {
+                    warn!(skill = slug, hosts = ?unapproved, "Installed skill needs hosts outside allowed_hosts");
+                    result.push_str(&format!(
+                        " Not yet in allowed_hosts: {}. Ask the user to approve them before relying on it.",
+                        unapproved.join(", ")
+                    ));
+                }
+            }
+        }
+        if !verified {
+            warn!(skill = slug, "Installed skill from an unverified publisher");
+            let publisher = entry
+                .and_then(|e| e.publisher)
+                .unwrap_or_else(|| "unknown".to_string());
+            result.push_str(&format!(
+                "\nWARNING: '{}' is from an unverified publisher ({}). Read its manual with read_skill_manual and tell the user before running it.",
+                slug, publisher
+            ));
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl Tool for ClawHubTool {
+    fn name(&self) -> String {
+        "clawhub_manager".to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Search and install new skills from the ClawHub.ai registry. Supports 'search' to find skills (ranked, with typosquat warnings) and 'install' to add them to your environment.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["search", "install"],
+                        "description": "The action to perform"
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "Search query or skill slug to install"
+                    },
+                    "manager": {
+                        "type": "string",
+                        "enum": ["npm", "pnpm", "bun"],
+                        "description": "The package manager to use (default: npm)"
+                    },
+                    "raw": {
+                        "type": "boolean",
+                        "description": "Return the CLI's search output unparsed (for debugging)"
+                    }
+                },
+                "required": ["action", "query"]
+            }),
+            parameters_ts: Some("interface ClawHubArgs {\n  action: 'search' | 'install';\n  query: string; // Search query or skill slug\n  manager?: 'npm' | 'pnpm' | 'bun'; // Package manager (default: npm)\n  raw?: boolean; // Unparsed search output, for debugging\n}".to_string()),
+            is_verified: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        #[derive(Deserialize, schemars::JsonSchema)]
+        struct Args {
+            action: String,
+            query: String,
+            manager: Option<String>,
+            #[serde(default)]
+            raw: bool,
+        }
+        let args: Args = parse_args(&self.name(), arguments)?;
+        self.require_one_of("action", &args.action, &["search", "install"])?;
+        self.require_non_empty("query", &args.query)?;
+        if let Some(manager) = &args.manager {
+            self.require_one_of("manager", manager, &["npm", "pnpm", "bun"])?;
+        }
+        let manager = args.manager.as_deref().unwrap_or("npm");
+
+        match args.action.as_str() {
+            "search" => {
+                info!(
+                    "Searching ClawHub registry for: {} (via {})",
+                    args.query, manager
+                );
+                let output = self.run_cli(manager, "search", &args.query).await?;
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                if args.raw {
+                    return Ok(stdout.to_string());
+                }
+                let ranked = rank(
+                    parse_search_output(&stdout),
+                    &args.query,
+                    &self.policy,
+                    Utc::now(),
+                );
+                Ok(render_results(&args.query, &ranked))
+            }
+            "install" => self.install(&args.query, manager).await,
+            _ => Err(anyhow::anyhow!("Unknown action: {}", args.action)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON_FIXTURE: &str = r#"{"results": [
+        {"slug": "pirce-feed", "version": "0.1.0", "owner": {"handle": "anon42"}, "downloads": 12, "publishedAt": "2026-10-01T00:00:00Z"},
+        {"slug": "price-feed", "version": "1.3.0", "owner": {"handle": "acme", "verified": true}, "downloads": 48000, "publishedAt": "2026-09-20T00:00:00Z", "description": "Spot prices | CEX and DEX"},
+        {"slug": "price-feed", "version": "1.10.0", "owner": {"handle": "acme", "verified": true}, "downloads": 52000, "publishedAt": "2026-10-02T00:00:00Z"},
+        {"slug": "price-alerts", "version": "2.0.0-beta.1", "publisher": "bob", "downloads": 900, "updated_at": 1735689600000}
+    ]}"#;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_rank() {
+        let entries = parse_search_output(JSON_FIXTURE);
+        assert_eq!(entries.len(), 3);
+        let feed = entries.iter().find(|e| e.slug == "price-feed").unwrap();
+        // 1.10.0 beats 1.3.0 numerically, not lexically
+        assert_eq!(feed.version.as_deref(), Some("1.10.0"));
+        assert!(feed.verified);
+
+        let policy = ClawHubPolicy {
+            trusted_publishers: ["bob".to_string()].into(),
+            ..ClawHubPolicy::default()
+        };
+        let ranked = rank(entries.clone(), "price feed", &policy, now());
+        let order: Vec<&str> = ranked.iter().map(|r| r.entry.slug.as_str()).collect();
+        assert_eq!(order, ["price-feed", "price-alerts", "pirce-feed"]);
+        // Same input, same order
+        let again = rank(
+            entries.into_iter().rev().collect(),
+            "price feed",
+            &policy,
+            now(),
+        );
+        assert!(again.iter().map(|r| r.entry.slug.as_str()).eq(order));
+        assert!(ranked[1].verified);
+
+        // Text fallback
+        let text = parse_search_output(
+            "Results:\nprice-feed  v1.2.0  Spot prices\nsol-tools 0.3.1 - Solana helpers\n",
+        );
+        assert_eq!(text[0].slug, "price-feed");
+        assert_eq!(text[0].version.as_deref(), Some("1.2.0"));
+        assert_eq!(text[1].description.as_deref(), Some("Solana helpers"));
+    }
+
+    #[test]
+    fn test_typosquat_flagged() {
+        let ranked = rank(
+            parse_search_output(JSON_FIXTURE),
+            "price-feed",
+            &ClawHubPolicy::default(),
+            now(),
+        );
+        let squat = ranked
+            .iter()
+            .find(|r| r.entry.slug == "pirce-feed")
+            .unwrap();
+        assert_eq!(squat.typosquat_of.as_deref(), Some("price-feed"));
+        assert!(ranked.iter().filter(|r| r.typosquat_of.is_some()).count() == 1);
+
+        let table = render_results("price-feed", &ranked);
+        assert!(
+            table.contains("| 1 | price-feed | 1.10.0 | 2026-10-02 | 52.0k | acme (verified) |")
+        );
+        assert!(table.contains(
+            "WARNING: 'pirce-feed' looks like a typosquat of 'price-feed' (edit distance 2)"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_denylist_refuses_install() {
+        let tool =
+            ClawHubTool::new(Arc::new(SkillLoader::new("./skills"))).with_policy(ClawHubPolicy {
+                denylist: ["wallet-drainer".to_string()].into(),
+                ..ClawHubPolicy::default()
+            });
+        let err = tool
+            .call(r#"{"action": "install", "query": "Wallet-Drainer"}"#)
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Refusing to install 'Wallet-Drainer': it is on the skill denylist"));
+    }
+}
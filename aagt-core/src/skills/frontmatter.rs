@@ -0,0 +1,162 @@
+//! SKILL.md frontmatter parsing
+//!
+//! A manifest is an optional UTF-8 BOM, a line containing only `---`, the YAML
+//! metadata, another line containing only `---`, then the instructions. Lines may
+//! end in `\n` or `\r\n`, and the closing delimiter may be the last line of the
+//! file. Indented `---` (e.g. inside a YAML block scalar) is not a delimiter.
+
+use crate::error::{Error, Result};
+use crate::skills::SkillMetadata;
+
+/// The two halves of a SKILL.md file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frontmatter<'a> {
+    /// YAML between the delimiters, line endings untouched
+    pub yaml: &'a str,
+    /// Everything after the closing delimiter line, byte for byte
+    pub body: &'a str,
+}
+
+/// Split a SKILL.md file into frontmatter YAML and body
+pub fn split(content: &str) -> Result<Frontmatter<'_>> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let mut lines = content.split_inclusive('\n');
+
+    let opening = lines.next().unwrap_or_default();
+    if strip_eol(opening) != "---" {
+        return Err(Error::Internal(
+            "SKILL.md line 1: expected frontmatter to open with a line containing only ---"
+                .to_string(),
+        ));
+    }
+
+    let yaml_start = opening.len();
+    let mut offset = yaml_start;
+    let mut line_no = 1;
+    for line in lines {
+        line_no += 1;
+        if strip_eol(line) == "---" {
+            return Ok(Frontmatter {
+                yaml: &content[yaml_start..offset],
+                body: &content[offset + line.len()..],
+            });
+        }
+        offset += line.len();
+    }
+
+    Err(Error::Internal(format!(
+        "SKILL.md frontmatter opened on line 1 is never closed: no line containing only --- in lines 2-{}",
+        line_no
+    )))
+}
+
+/// Parse a SKILL.md file into its metadata and instructions
+///
+/// Instructions keep their internal formatting; only blank lines around them are dropped.
+pub fn parse(content: &str) -> Result<(SkillMetadata, String)> {
+    let frontmatter = split(content)?;
+    if frontmatter.yaml.trim().is_empty() {
+        return Err(Error::Internal(
+            "SKILL.md line 2: frontmatter is empty (name and description are required)".to_string(),
+        ));
+    }
+
+    let metadata: SkillMetadata = serde_yaml_ng::from_str(frontmatter.yaml).map_err(|e| {
+        // YAML starts on line 2 of the file
+        let line = e.location().map(|l| l.line() + 1).unwrap_or(2);
+        Error::Internal(format!(
+            "Failed to parse Skill YAML (SKILL.md line {}): {}",
+            line, e
+        ))
+    })?;
+
+    let instructions = frontmatter
+        .body
+        .trim_start_matches(['\r', '\n'])
+        .trim_end_matches(['\r', '\n'])
+        .to_string();
+    Ok((metadata, instructions))
+}
+
+fn strip_eol(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &[(&str, &str)] = &[
+        ("lf", include_str!("../../tests/fixtures/skill_md/lf.md")),
+        (
+            "crlf",
+            include_str!("../../tests/fixtures/skill_md/crlf.md"),
+        ),
+        ("bom", include_str!("../../tests/fixtures/skill_md/bom.md")),
+        (
+            "embedded_dashes",
+            include_str!("../../tests/fixtures/skill_md/embedded_dashes.md"),
+        ),
+        (
+            "no_trailing_newline",
+            include_str!("../../tests/fixtures/skill_md/no_trailing_newline.md"),
+        ),
+    ];
+
+    #[test]
+    fn test_fixtures_parse() {
+        for (case, content) in FIXTURES {
+            let (metadata, instructions) =
+                parse(content).unwrap_or_else(|e| panic!("{}: {}", case, e));
+            assert_eq!(metadata.name, "price_check", "{}", case);
+            assert_eq!(metadata.script.as_deref(), Some("run.py"), "{}", case);
+
+            let eol = if *case == "crlf" { "\r\n" } else { "\n" };
+            let expected = ["# Price check", "", "    indented code", "Done."].join(eol);
+            if *case == "no_trailing_newline" {
+                assert_eq!(instructions, "", "{}", case);
+            } else {
+                assert_eq!(instructions, expected, "{}", case);
+            }
+
+            if *case == "embedded_dashes" {
+                assert_eq!(
+                    metadata.description,
+                    "Checks a price.\n---\nNot a delimiter.\n"
+                );
+            } else {
+                assert_eq!(metadata.description, "Checks a price.", "{}", case);
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_and_unclosed() {
+        let empty_body = include_str!("../../tests/fixtures/skill_md/empty_instructions.md");
+        let (metadata, instructions) = parse(empty_body).unwrap();
+        assert_eq!(metadata.name, "price_check");
+        assert_eq!(instructions, "");
+
+        let empty_yaml = include_str!("../../tests/fixtures/skill_md/empty_frontmatter.md");
+        let frontmatter = split(empty_yaml).unwrap();
+        assert_eq!((frontmatter.yaml, frontmatter.body), ("", "Body only.\n"));
+        let err = parse(empty_yaml).unwrap_err().to_string();
+        assert!(err.contains("line 2: frontmatter is empty"), "{}", err);
+
+        let err = parse("---\nname: x\n--- \nbody\n").unwrap_err().to_string();
+        assert!(
+            err.contains("never closed") && err.contains("lines 2-4"),
+            "{}",
+            err
+        );
+
+        let err = parse("# no frontmatter\n").unwrap_err().to_string();
+        assert!(err.contains("line 1"), "{}", err);
+
+        let err = parse("---\nname: x\ndescription: [\n---\n")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("SKILL.md line"), "{}", err);
+    }
+}
@@ -1,9 +1,15 @@
 pub mod tool;
 pub mod capabilities;
+pub mod frontmatter;
+#[cfg(feature = "wasm")]
 pub mod runtime;
+pub mod clawhub;
+
+pub use clawhub::{ClawHubPolicy, ClawHubTool, RankedEntry, RegistryEntry};
 
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use dashmap::DashMap;
 
@@ -13,11 +19,18 @@ use serde_json::{json, Value};
 use tracing::{info, warn};
 
 use crate::error::{Error, Result};
-use crate::skills::tool::{Tool, ToolDefinition};
+use crate::infra::egress::{self, EgressBudget, HostAllowlist};
+#[cfg(unix)]
+use crate::infra::egress::EgressProxy;
+use crate::infra::secrets::{SecretString, Secrets};
+use crate::infra::validation::{ConfigIssue, Validate};
+use crate::skills::tool::{parse_args, Tool, ToolDefinition, ToolExample};
 use crate::agent::context::ContextInjector;
 use crate::agent::message::Message;
+#[cfg(feature = "wasm")]
+use crate::skills::runtime::WasmSkill;
 #[cfg(feature = "trading")]
-use crate::trading::risk::RiskManager;
+use crate::trading::risk::{ReservationOrigin, RiskManager};
 #[cfg(feature = "trading")]
 use crate::trading::strategy::{Action, ActionExecutor};
 
@@ -44,8 +57,86 @@ pub struct SkillMetadata {
     /// Kind of skill (e.g., 'tool', 'knowledge', 'agent')
     #[serde(default = "default_skill_kind")]
     pub kind: String,
+    /// Few-shot usage examples
+    #[serde(default)]
+    pub examples: Vec<ToolExample>,
+    /// Fields to keep when a large JSON result is reduced
+    #[serde(default)]
+    pub result_projection: Option<Vec<String>>,
+    /// What the skill needs from its environment
+    #[serde(default)]
+    pub requires: SkillRequirements,
+    /// Binaries and packages checked before the skill is offered to the model
+    #[serde(default)]
+    pub dependencies: SkillDependencies,
+}
+
+/// `requires` block of a `SKILL.md` frontmatter
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SkillRequirements {
+    /// Secret keys passed into the sandbox as environment variables
+    #[serde(default)]
+    pub env: Vec<String>,
+    /// Hosts the skill calls, surfaced for approval against `allowed_hosts`
+    #[serde(default)]
+    pub network: Vec<String>,
+}
+
+/// `dependencies` block of a `SKILL.md` frontmatter
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SkillDependencies {
+    /// Executables that must be on `PATH` (the runtime's interpreter is implied)
+    #[serde(default)]
+    pub binaries: Vec<String>,
+    /// Python requirements (e.g. `requests>=2.0`), probed by distribution name in the sandbox
+    #[serde(default)]
+    pub python_packages: Vec<String>,
+}
+
+/// Result of a skill's dependency pre-flight
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SkillHealth {
+    /// Not verified; treated as available
+    #[default]
+    Unchecked,
+    /// Every declared dependency was found
+    Healthy,
+    /// Something is missing; the skill is not offered as callable
+    Unhealthy {
+        /// What is missing, e.g. `missing python3`
+        reasons: Vec<String>,
+    },
 }
 
+impl SkillHealth {
+    /// Whether the skill can be called
+    pub fn is_available(&self) -> bool {
+        !matches!(self, Self::Unhealthy { .. })
+    }
+}
+
+/// Prints the distributions in `argv` that are not installed, space separated
+const PYTHON_DISTRIBUTION_PROBE: &str = "import importlib.metadata as md, sys\n\
+def missing(name):\n    try:\n        md.version(name)\n        return False\n    except md.PackageNotFoundError:\n        return True\n\
+print(' '.join(d for d in sys.argv[1:] if missing(d)))";
+
+/// Ambient variables passed through to the sandbox (everything else is cleared)
+pub(crate) const SANDBOX_BASE_ENV: &[&str] = &["PATH", "HOME", "LANG", "TZ"];
+
+/// Where the egress proxy socket is bound inside the sandbox (on its private /tmp)
+const SANDBOX_EGRESS_SOCKET: &str = "/tmp/aagt-egress.sock";
+
+/// Loopback port the in-sandbox relay listens on for `HTTP(S)_PROXY`
+const SANDBOX_PROXY_PORT: u16 = 3128;
+
+/// Relays the loopback proxy port to the egress socket, waits for it, then runs the skill
+///
+/// Port 3128 is `0C38` in `/proc/net/tcp`.
+const SANDBOX_RELAY_SCRIPT: &str = "socat TCP-LISTEN:3128,bind=127.0.0.1,fork,reuseaddr UNIX-CONNECT:/tmp/aagt-egress.sock & \
+i=0; while [ $i -lt 100 ] && ! grep -q ':0C38 ' /proc/net/tcp 2>/dev/null; do sleep 0.01; i=$((i+1)); done; \
+exec \"$0\" \"$@\"";
+
 fn default_skill_kind() -> String {
     "tool".to_string()
 }
@@ -57,9 +148,13 @@ pub struct SkillExecutionConfig {
     pub timeout_secs: u64,
     /// Maximum output size in bytes (to prevent memory exhaustion)
     pub max_output_bytes: usize,
-    /// Whether to allow network access (future: implement via sandbox)
+    /// Whether to allow unrestricted network access (overrides `allowed_hosts`)
     pub allow_network: bool,
-    /// Custom environment variables
+    /// Hosts sandboxed skills may reach through the egress proxy (exact or `*.domain`)
+    pub allowed_hosts: Vec<String>,
+    /// Per-invocation limits on proxied traffic
+    pub egress_budget: EgressBudget,
+    /// Non-secret environment variables (secrets come from `requires.env` and a [`Secrets`] resolver)
     pub env_vars: HashMap<String, String>,
 }
 
@@ -69,11 +164,64 @@ impl Default for SkillExecutionConfig {
             timeout_secs: 30,
             max_output_bytes: 1024 * 1024, // 1MB
             allow_network: false,
+            allowed_hosts: Vec::new(),
+            egress_budget: EgressBudget::default(),
             env_vars: HashMap::new(),
         }
     }
 }
 
+impl Validate for SkillExecutionConfig {
+    fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        if self.timeout_secs == 0 {
+            issues.push(ConfigIssue::error(
+                "skills.execution.timeout_secs",
+                "must be at least 1, or every skill times out immediately",
+            ));
+        }
+        if self.max_output_bytes == 0 {
+            issues.push(ConfigIssue::error("skills.execution.max_output_bytes", "must be at least 1"));
+        }
+        if self.allow_network {
+            issues.push(ConfigIssue::warning(
+                "skills.execution.allow_network",
+                "skills can reach the network",
+            ));
+            if !self.allowed_hosts.is_empty() {
+                issues.push(ConfigIssue::warning(
+                    "skills.execution.allowed_hosts",
+                    "ignored while allow_network is on",
+                ));
+            }
+        }
+        for host in egress::invalid_hosts(&self.allowed_hosts) {
+            issues.push(
+                ConfigIssue::error(
+                    "skills.execution.allowed_hosts",
+                    format!("'{}' is not a host name", host),
+                )
+                .suggest("use a bare host like api.example.com or a wildcard like *.example.com"),
+            );
+        }
+        let mut keys: Vec<&String> = self.env_vars.keys().collect();
+        keys.sort();
+        for key in keys {
+            let upper = key.to_uppercase();
+            if ["KEY", "SECRET", "TOKEN", "PASSWORD"].iter().any(|s| upper.contains(s)) {
+                issues.push(
+                    ConfigIssue::warning(
+                        format!("skills.execution.env_vars.{}", key),
+                        "looks like a secret in plain env_vars",
+                    )
+                    .suggest("declare it in the skill's requires.env and resolve it via Secrets"),
+                );
+            }
+        }
+        issues
+    }
+}
+
 /// A skill that executes an external script
 pub struct DynamicSkill {
     metadata: SkillMetadata,
@@ -83,8 +231,14 @@ pub struct DynamicSkill {
     risk_manager: Option<Arc<RiskManager>>,
     #[cfg(feature = "trading")]
     executor: Option<Arc<dyn ActionExecutor>>,
+    #[cfg(feature = "trading")]
+    session_id: Option<String>,
     execution_config: SkillExecutionConfig,
-    wasm_runtime: Arc<crate::skills::runtime::WasmRuntime>,
+    secrets: Option<Arc<Secrets>>,
+    health: SkillHealth,
+    /// Compiled module of a `runtime: wasm` skill
+    #[cfg(feature = "wasm")]
+    wasm: Option<WasmSkill>,
 }
 
 impl DynamicSkill {
@@ -98,8 +252,13 @@ impl DynamicSkill {
             risk_manager: None,
             #[cfg(feature = "trading")]
             executor: None,
+            #[cfg(feature = "trading")]
+            session_id: None,
             execution_config: SkillExecutionConfig::default(),
-            wasm_runtime: Arc::new(crate::skills::runtime::WasmRuntime::new().expect("Failed to init WasmRuntime")),
+            secrets: None,
+            health: SkillHealth::Unchecked,
+            #[cfg(feature = "wasm")]
+            wasm: None,
         }
     }
 
@@ -117,16 +276,233 @@ impl DynamicSkill {
         self
     }
 
+    /// Set the session that risk reservations are attributed to
+    #[cfg(feature = "trading")]
+    pub fn with_session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
     /// Set custom execution configuration
     pub fn with_execution_config(mut self, config: SkillExecutionConfig) -> Self {
         self.execution_config = config;
         self
     }
 
+    /// Resolve the skill's `requires.env` secrets through `secrets`
+    pub fn with_secrets(mut self, secrets: Arc<Secrets>) -> Self {
+        self.secrets = Some(secrets);
+        self
+    }
+
+    /// Run `runtime: wasm` calls on an already compiled module
+    #[cfg(feature = "wasm")]
+    pub fn with_wasm(mut self, module: WasmSkill) -> Self {
+        self.wasm = Some(module);
+        self
+    }
+
+    /// Record the outcome of [`SkillLoader::verify_skill`]
+    pub fn with_health(mut self, health: SkillHealth) -> Self {
+        self.health = health;
+        self
+    }
+
+    /// Outcome of the dependency pre-flight
+    pub fn health(&self) -> &SkillHealth {
+        &self.health
+    }
+
+    /// Interpreter the script runs under, or `None` for wasm and script-less skills
+    fn interpreter(&self) -> Option<&str> {
+        self.metadata.script.as_ref()?;
+        match self.metadata.runtime.as_deref().unwrap_or("python3") {
+            "python" | "python3" => Some("python3"),
+            "bash" | "sh" => Some("bash"),
+            "node" | "js" => Some("node"),
+            "wasm" => None,
+            lang => Some(lang),
+        }
+    }
+
+    /// Bubblewrap with a read-only root, private /tmp and, unless allowed, no network
+    fn sandbox_command(&self) -> tokio::process::Command {
+        let mut cmd = tokio::process::Command::new("bwrap");
+
+        // 1. Root is read-only
+        cmd.arg("--ro-bind").arg("/").arg("/");
+
+        // 2. Devices
+        cmd.arg("--dev").arg("/dev");
+        cmd.arg("--proc").arg("/proc");
+
+        // 3. Private /tmp
+        cmd.arg("--tmpfs").arg("/tmp");
+
+        // 4. Bind current directory (so script can be read/write in project)
+        if let Ok(cwd) = std::env::current_dir() {
+            cmd.arg("--bind").arg(&cwd).arg(&cwd);
+        }
+
+        // 5. Network Isolation (Enforced by default unless configured otherwise)
+        if !self.execution_config.allow_network {
+            cmd.arg("--unshare-net");
+        }
+        cmd
+    }
+
+    /// Declared Python packages that are not installed inside the sandbox
+    async fn missing_python_packages(&self) -> Result<Vec<String>> {
+        let packages = &self.metadata.dependencies.python_packages;
+        let distributions: Vec<&str> = packages.iter().map(|p| python_distribution(p)).collect();
+        let mut cmd = self.sandbox_command();
+        cmd.arg("python3").arg("-c").arg(PYTHON_DISTRIBUTION_PROBE).args(&distributions);
+        cmd.env_clear();
+        for (key, value) in self.sandbox_env()? {
+            cmd.env(key, value.expose());
+        }
+        let timeout = std::time::Duration::from_secs(self.execution_config.timeout_secs);
+        let output = tokio::time::timeout(timeout, cmd.output())
+            .await
+            .map_err(|_| Error::tool_execution(self.name(), "Package probe timed out"))?
+            .map_err(|e| Error::tool_execution(self.name(), format!("Package probe failed: {}", e)))?;
+        if !output.status.success() {
+            return Err(Error::tool_execution(
+                self.name(),
+                format!("Package probe failed: {}", String::from_utf8_lossy(&output.stderr).trim()),
+            ));
+        }
+        let missing = String::from_utf8_lossy(&output.stdout);
+        let missing: Vec<&str> = missing.split_whitespace().collect();
+        Ok(packages
+            .iter()
+            .zip(&distributions)
+            .filter(|(_, distribution)| missing.contains(distribution))
+            .map(|(package, _)| package.clone())
+            .collect())
+    }
+
     /// Access metadata
     pub fn metadata(&self) -> &SkillMetadata {
         &self.metadata
     }
+
+    /// Hosts from `requires.network` that the execution config doesn't allow
+    pub fn unapproved_hosts(&self) -> Vec<String> {
+        if self.execution_config.allow_network {
+            return Vec::new();
+        }
+        let allowlist = HostAllowlist::new(&self.execution_config.allowed_hosts);
+        self.metadata
+            .requires
+            .network
+            .iter()
+            .filter(|host| !allowlist.allows(host))
+            .cloned()
+            .collect()
+    }
+
+    /// Start an egress proxy for one invocation, if `allowed_hosts` calls for it
+    #[cfg(unix)]
+    fn start_egress(&self) -> Result<Option<EgressProxy>> {
+        if self.execution_config.allow_network || self.execution_config.allowed_hosts.is_empty() {
+            return Ok(None);
+        }
+        if which::which("socat").is_err() {
+            return Err(Error::tool_execution(
+                self.name(),
+                "allowed_hosts needs 'socat' to relay the egress proxy into the sandbox",
+            ));
+        }
+        let socket = std::env::temp_dir().join(format!("aagt-egress-{}.sock", uuid::Uuid::new_v4()));
+        let proxy = EgressProxy::start(
+            socket,
+            HostAllowlist::new(&self.execution_config.allowed_hosts),
+            self.execution_config.egress_budget,
+        )?;
+        Ok(Some(proxy))
+    }
+
+    /// Environment for the sandboxed process
+    pub(crate) fn sandbox_env(&self) -> Result<Vec<(String, SecretString)>> {
+        sandbox_env(&self.metadata, &self.execution_config, self.secrets.as_deref())
+    }
+
+    /// Module file of a `runtime: wasm` skill
+    #[cfg(feature = "wasm")]
+    fn wasm_path(&self) -> Result<PathBuf> {
+        let wasm_file = self.metadata.script.as_ref().ok_or_else(|| {
+            Error::tool_execution(self.name(), "No wasm file defined for this skill".to_string())
+        })?;
+        Ok(self.base_dir.join("scripts").join(wasm_file))
+    }
+
+    /// Run a `runtime: wasm` skill in the Wasm runtime instead of `bwrap`
+    #[cfg(feature = "wasm")]
+    async fn call_wasm(&self, arguments: &str) -> Result<String> {
+        if let Some(module) = &self.wasm {
+            return module.call(arguments, &self.execution_config).await;
+        }
+        let wasm_path = self.wasm_path()?;
+        let bytes = tokio::fs::read(&wasm_path).await?;
+        if runtime::wasm_skill::is_component(&bytes) {
+            // Components export `run(string) -> string` instead of allocate/call
+            return runtime::WasmRuntime::new()?.call(&wasm_path, arguments);
+        }
+        WasmSkill::new(self.name(), &bytes)?.call(arguments, &self.execution_config).await
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    async fn call_wasm(&self, _arguments: &str) -> Result<String> {
+        Err(Error::tool_execution(self.name(), "Wasm skills need the 'wasm' feature"))
+    }
+
+    fn redact(&self, text: String) -> String {
+        match &self.secrets {
+            Some(secrets) => secrets.redact(&text),
+            None => text,
+        }
+    }
+}
+
+/// Distribution name of a requirement: `requests>=2.0` -> `requests`, `PyYAML[libyaml]` -> `PyYAML`
+fn python_distribution(requirement: &str) -> &str {
+    requirement
+        .split(|c: char| "<>=!~;[ ".contains(c))
+        .next()
+        .unwrap_or(requirement)
+}
+
+/// Environment for a skill's sandboxed process: a few ambient basics, `env_vars`,
+/// and only the secrets the skill declares
+pub(crate) fn sandbox_env(
+    metadata: &SkillMetadata,
+    config: &SkillExecutionConfig,
+    secrets: Option<&Secrets>,
+) -> Result<Vec<(String, SecretString)>> {
+    let mut env: Vec<(String, SecretString)> = SANDBOX_BASE_ENV
+        .iter()
+        .filter_map(|key| std::env::var(key).ok().map(|v| (key.to_string(), SecretString::new(v))))
+        .collect();
+    env.extend(
+        config
+            .env_vars
+            .iter()
+            .map(|(k, v)| (k.clone(), SecretString::new(v.clone()))),
+    );
+
+    let required = &metadata.requires.env;
+    if !required.is_empty() {
+        let secrets = secrets.ok_or_else(|| {
+            Error::SecretNotFound(format!(
+                "{} (skill '{}' has no secret provider configured)",
+                required.join(", "),
+                metadata.name
+            ))
+        })?;
+        env.extend(secrets.resolve_all(required)?);
+    }
+    Ok(env)
 }
 
 #[cfg(feature = "trading")]
@@ -147,19 +523,36 @@ impl Tool for DynamicSkill {
     }
 
     async fn definition(&self) -> ToolDefinition {
+        let description = match &self.health {
+            SkillHealth::Unhealthy { reasons } => {
+                format!("{} (unavailable: {})", self.metadata.description, reasons.join(", "))
+            }
+            _ => self.metadata.description.clone(),
+        };
         ToolDefinition {
             name: self.metadata.name.clone(),
-            description: self.metadata.description.clone(),
+            description,
             parameters: self.metadata.parameters.clone().unwrap_or(json!({})),
             parameters_ts: self.metadata.interface.clone(),
             is_binary: self.metadata.runtime.as_deref() == Some("wasm"),
             is_verified: false, // Default to unverified
+            examples: self.metadata.examples.clone(),
+            result_projection: self.metadata.result_projection.clone(),
+            required_secrets: self.metadata.requires.env.clone(),
+            ..Default::default()
         }
     }
 
 
 
     async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        if let SkillHealth::Unhealthy { reasons } = &self.health {
+            return Err(Error::tool_execution(
+                self.name(),
+                format!("Skill unavailable: {}", reasons.join(", ")),
+            )
+            .into());
+        }
         let runtime_type = self.metadata.runtime.as_deref().unwrap_or("python3");
 
         let interpreter = match runtime_type {
@@ -171,12 +564,9 @@ impl Tool for DynamicSkill {
         };
 
         if interpreter == "wasm" {
-            let wasm_file = self.metadata.script.as_ref().ok_or_else(|| {
-                Error::tool_execution(self.name(), "No wasm file defined for this skill".to_string())
-            })?;
-            let wasm_path = self.base_dir.join("scripts").join(wasm_file);
             info!(tool = %self.name(), "Executing Wasm skill");
-            return self.wasm_runtime.call(&wasm_path, arguments).map_err(|e| e.into());
+            let output = self.call_wasm(arguments).await?;
+            return Ok(self.redact(output));
         }
 
         let script_file = self.metadata.script.as_ref().ok_or_else(|| {
@@ -206,28 +596,17 @@ impl Tool for DynamicSkill {
              ).into());
         }
 
-        let mut cmd = tokio::process::Command::new("bwrap");
-        
-        // 1. Root is read-only
-        cmd.arg("--ro-bind").arg("/").arg("/");
-        
-        // 2. Devices
-        cmd.arg("--dev").arg("/dev");
-        cmd.arg("--proc").arg("/proc");
-        
-        // 3. Private /tmp
-        cmd.arg("--tmpfs").arg("/tmp");
-        
-        // 4. Bind current directory (so script can be read/write in project)
-        if let Ok(cwd) = std::env::current_dir() {
-            cmd.arg("--bind").arg(&cwd).arg(&cwd);
-        }
-        
-        // 5. Network Isolation (Enforced by default unless configured otherwise)
-        if !self.execution_config.allow_network {
-            cmd.arg("--unshare-net");
+        let mut cmd = self.sandbox_command();
+
+        // 5b. Allowlisted egress: only through the proxy socket, via an in-sandbox relay
+        #[cfg(unix)]
+        let egress = self.start_egress()?;
+        #[cfg(unix)]
+        if let Some(proxy) = &egress {
+            cmd.arg("--bind").arg(proxy.path()).arg(SANDBOX_EGRESS_SOCKET);
+            cmd.arg("sh").arg("-c").arg(SANDBOX_RELAY_SCRIPT);
         }
-        
+
         // 6. The actual command
         cmd.arg(interpreter);
 
@@ -241,9 +620,18 @@ impl Tool for DynamicSkill {
         cmd.stdout(std::process::Stdio::piped())
            .stderr(std::process::Stdio::piped());
            
-        // Environment variables
-        for (key, value) in &self.execution_config.env_vars {
-            cmd.env(key, value);
+        // Environment: nothing ambient beyond the basics, plus this skill's declared secrets
+        cmd.env_clear();
+        for (key, value) in self.sandbox_env()? {
+            cmd.env(key, value.expose());
+        }
+        #[cfg(unix)]
+        if egress.is_some() {
+            let proxy = format!("http://127.0.0.1:{}", SANDBOX_PROXY_PORT);
+            for key in ["HTTP_PROXY", "HTTPS_PROXY", "http_proxy", "https_proxy"] {
+                cmd.env(key, &proxy);
+            }
+            cmd.env("AAGT_EGRESS_SOCKET", SANDBOX_EGRESS_SOCKET);
         }
 
         // Set timeout
@@ -267,8 +655,14 @@ impl Tool for DynamicSkill {
                 message: format!("Process failed: {}", e) 
             })?;
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        #[cfg(unix)]
+        if let Some(proxy) = &egress {
+            let stats = proxy.stats();
+            info!(tool = %self.name(), requests = stats.requests, bytes = stats.bytes, denied = stats.denied, "Skill egress");
+        }
+
+        let stdout = self.redact(String::from_utf8_lossy(&output.stdout).to_string());
+        let stderr = self.redact(String::from_utf8_lossy(&output.stderr).to_string());
 
         if !output.status.success() {
             return Err(Error::ToolExecution {
@@ -300,7 +694,11 @@ impl Tool for DynamicSkill {
                         };
 
                         // 1. Check Risk
-                        rm.check_and_reserve(&context).await
+                        let mut origin = ReservationOrigin::new().with_tool(self.name());
+                        if let Some(ref session_id) = self.session_id {
+                            origin = origin.with_session(session_id.clone());
+                        }
+                        let reservation_id = rm.check_and_reserve_with_origin(&context, origin).await
                             .map_err(|e| Error::tool_execution(self.name(), format!("Risk Check Denied: {}", e)))?;
 
                         info!("Risk check approved for skill {}", self.name());
@@ -314,27 +712,31 @@ impl Tool for DynamicSkill {
                                  amount: proposal.amount,
                              };
                              
-                             let pipeline_ctx = crate::trading::pipeline::Context::new(format!("Skill execution: {}", self.name()));
-                             // We could pass more context if needed
+                             let mut pipeline_ctx = crate::trading::pipeline::Context::new(format!("Skill execution: {}", self.name()));
+                             pipeline_ctx.set("user_id", context.user_id.as_str());
+                             pipeline_ctx.set("skill", self.name());
+                             pipeline_ctx.set("reservation_id", reservation_id.as_str());
                              
                              let result = match executor.execute(&action, &pipeline_ctx).await {
                                 Ok(res) => res,
                                 Err(e) => {
                                     // Fix #2.2: Rollback on Execution Failure
                                     warn!("Skill execution failed, rolling back risk reservation: {}", e);
-                                    rm.rollback_trade(&context.user_id, context.amount_usd).await;
+                                    if let Err(rollback_err) = rm.rollback_reservation(&reservation_id).await {
+                                        warn!("Failed to roll back reservation {}: {}", reservation_id, rollback_err);
+                                    }
                                     return Err(Error::tool_execution(self.name(), format!("Execution Failed (Rolled Back): {}", e)).into());
                                 }
                              };
                                 
                              // Once executed success, we confirm the trade to RiskManager (commit)
-                             rm.commit_trade(&context.user_id, context.amount_usd).await?;
+                             rm.commit_reservation(&reservation_id, rust_decimal::Decimal::ZERO).await?;
                              
                              return Ok(format!("SUCCESS: Trade executed: {}", result));
                         } else {
                             // Simulation Mode (Legacy behavior)
                             // Still commit the risk usage as "Paper Trading"
-                            rm.commit_trade(&context.user_id, context.amount_usd).await?;
+                            rm.commit_reservation(&reservation_id, rust_decimal::Decimal::ZERO).await?;
                             return Ok(format!("SIMULATION SUCCESS: Trade approved by risk manager but NO EXECUTOR configured. Proposal: {:?}", proposal));
                         }
                     } else {
@@ -350,12 +752,24 @@ impl Tool for DynamicSkill {
 
 /// Registry and loader for dynamic skills
 pub struct SkillLoader {
-    pub skills: DashMap<String, Arc<DynamicSkill>>,
+    /// Loaded skills; only changed through `insert`, `remove` and `load_all`
+    /// so watchers hear about it
+    skills: DashMap<String, Arc<DynamicSkill>>,
     base_path: PathBuf,
     #[cfg(feature = "trading")]
     risk_manager: Option<Arc<RiskManager>>,
     #[cfg(feature = "trading")]
     executor: Option<Arc<dyn ActionExecutor>>,
+    #[cfg(feature = "trading")]
+    session_id: Option<String>,
+    secrets: Option<Arc<Secrets>>,
+    execution_config: Option<SkillExecutionConfig>,
+    skip_unhealthy: bool,
+    health: DashMap<String, SkillHealth>,
+    /// Bumped whenever `skills` changes, so watching toolsets know to resync
+    generation: AtomicU64,
+    /// Run after every change; dropped once they return `false`
+    watchers: parking_lot::Mutex<Vec<Box<dyn Fn() -> bool + Send + Sync>>>,
 }
 
 impl SkillLoader {
@@ -368,9 +782,147 @@ impl SkillLoader {
             risk_manager: None,
             #[cfg(feature = "trading")]
             executor: None,
+            #[cfg(feature = "trading")]
+            session_id: None,
+            secrets: None,
+            execution_config: None,
+            skip_unhealthy: false,
+            health: DashMap::new(),
+            generation: AtomicU64::new(0),
+            watchers: parking_lot::Mutex::new(Vec::new()),
         }
     }
 
+    /// Counter bumped by every change; see [`ToolSet::watch_skills`](crate::skills::tool::ToolSet::watch_skills)
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Run `watcher` after every change to the skills, until it returns `false`
+    pub fn on_change(&self, watcher: impl Fn() -> bool + Send + Sync + 'static) {
+        self.watchers.lock().push(Box::new(watcher));
+    }
+
+    /// The skill called `name`
+    pub fn get(&self, name: &str) -> Option<Arc<DynamicSkill>> {
+        self.skills.get(name).map(|skill| Arc::clone(skill.value()))
+    }
+
+    /// All loaded skills, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = Arc<DynamicSkill>> + '_ {
+        self.skills.iter().map(|skill| Arc::clone(skill.value()))
+    }
+
+    /// Number of loaded skills
+    pub fn len(&self) -> usize {
+        self.skills.len()
+    }
+
+    /// Whether no skills are loaded
+    pub fn is_empty(&self) -> bool {
+        self.skills.is_empty()
+    }
+
+    /// Add or replace a skill, returning the one it replaced
+    pub fn insert(&self, skill: DynamicSkill) -> Option<Arc<DynamicSkill>> {
+        let previous = self.skills.insert(skill.name(), Arc::new(skill));
+        self.changed();
+        previous
+    }
+
+    /// Remove the skill called `name`
+    pub fn remove(&self, name: &str) -> Option<Arc<DynamicSkill>> {
+        let (_, skill) = self.skills.remove(name)?;
+        self.changed();
+        Some(skill)
+    }
+
+    fn changed(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        // Run them unlocked so a watcher can register another or change the skills itself
+        let mut watchers = std::mem::take(&mut *self.watchers.lock());
+        watchers.retain(|watcher| watcher());
+        let mut current = self.watchers.lock();
+        watchers.append(&mut current);
+        *current = watchers;
+    }
+
+    /// Resolve declared secrets for all loaded skills through `secrets`
+    pub fn with_secrets(mut self, secrets: Arc<Secrets>) -> Self {
+        self.secrets = Some(secrets);
+        self
+    }
+
+    /// Run all loaded skills with `config` (timeouts, network policy)
+    pub fn with_execution_config(mut self, config: SkillExecutionConfig) -> Self {
+        self.execution_config = Some(config);
+        self
+    }
+
+    /// Leave skills with missing dependencies out of [`skills`](Self::skills) instead of annotating them
+    pub fn skip_unhealthy(mut self, skip: bool) -> Self {
+        self.skip_unhealthy = skip;
+        self
+    }
+
+    /// Pre-flight result of every skill seen by [`load_all`](Self::load_all), skipped ones included
+    pub fn skills_health(&self) -> BTreeMap<String, SkillHealth> {
+        self.health
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+
+    /// Check that `skill`'s interpreter, declared binaries and Python packages exist
+    ///
+    /// Binaries are looked up on `PATH`; packages are probed by importing them
+    /// inside the same sandbox the skill runs in.
+    pub async fn verify_skill(&self, skill: &DynamicSkill) -> SkillHealth {
+        let dependencies = &skill.metadata.dependencies;
+        let mut binaries: Vec<&str> = skill.interpreter().into_iter().collect();
+        if !dependencies.python_packages.is_empty() {
+            binaries.push("python3");
+        }
+        binaries.extend(dependencies.binaries.iter().map(String::as_str));
+
+        let mut reasons = Vec::new();
+        for binary in binaries {
+            let reason = format!("missing {}", binary);
+            if which::which(binary).is_err() && !reasons.contains(&reason) {
+                reasons.push(reason);
+            }
+        }
+        if !dependencies.python_packages.is_empty() && reasons.is_empty() {
+            if which::which("bwrap").is_err() {
+                reasons.push("missing bwrap to probe python packages".to_string());
+            } else {
+                match skill.missing_python_packages().await {
+                    Ok(missing) => {
+                        reasons.extend(missing.into_iter().map(|p| format!("missing python package {}", p)))
+                    }
+                    Err(e) => reasons.push(e.to_string()),
+                }
+            }
+        }
+
+        if reasons.is_empty() {
+            SkillHealth::Healthy
+        } else {
+            SkillHealth::Unhealthy { reasons }
+        }
+    }
+
+    /// Skill name -> hosts it declares in `requires.network` that aren't allowed yet
+    pub fn network_preflight(&self) -> BTreeMap<String, Vec<String>> {
+        self.skills
+            .iter()
+            .filter_map(|skill| {
+                let hosts = skill.unapproved_hosts();
+                (!hosts.is_empty()).then(|| (skill.key().clone(), hosts))
+            })
+            .collect()
+    }
+
     /// Set a risk manager for all loaded skills
     #[cfg(feature = "trading")]
     pub fn with_risk_manager(mut self, risk_manager: Arc<RiskManager>) -> Self {
@@ -385,6 +937,13 @@ impl SkillLoader {
         self
     }
 
+    /// Attribute risk reservations from all loaded skills to a session
+    #[cfg(feature = "trading")]
+    pub fn with_session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
     /// Load all skills from the base directory
     pub async fn load_all(&self) -> Result<()> {
         if !self.base_path.exists() {
@@ -395,23 +954,43 @@ impl SkillLoader {
         while let Some(entry) = entries.next_entry().await? {
             let path = entry.path();
             if path.is_dir() {
-                if let Ok(skill) = self.load_skill(&path).await {
-                    #[cfg(feature = "trading")]
-                    let mut skill = skill;
-                    #[cfg(feature = "trading")]
-                    {
-                        if let Some(ref rm) = self.risk_manager {
-                            skill = skill.with_risk_manager(Arc::clone(rm));
+                match self.load_skill(&path).await {
+                    Ok(mut skill) => {
+                        if let Some(ref secrets) = self.secrets {
+                            skill = skill.with_secrets(Arc::clone(secrets));
+                        }
+                        if let Some(ref config) = self.execution_config {
+                            skill = skill.with_execution_config(config.clone());
                         }
-                        if let Some(ref exec) = self.executor {
-                            skill = skill.with_executor(Arc::clone(exec));
+                        #[cfg(feature = "trading")]
+                        {
+                            if let Some(ref rm) = self.risk_manager {
+                                skill = skill.with_risk_manager(Arc::clone(rm));
+                            }
+                            if let Some(ref exec) = self.executor {
+                                skill = skill.with_executor(Arc::clone(exec));
+                            }
+                            if let Some(ref session_id) = self.session_id {
+                                skill = skill.with_session_id(session_id.clone());
+                            }
                         }
+                        let health = self.verify_skill(&skill).await;
+                        self.health.insert(skill.name(), health.clone());
+                        if let SkillHealth::Unhealthy { reasons } = &health {
+                            if self.skip_unhealthy {
+                                warn!("Skipping skill {}: {}", skill.name(), reasons.join(", "));
+                                continue;
+                            }
+                            warn!("Skill {} is unavailable: {}", skill.name(), reasons.join(", "));
+                        }
+                        info!("Loaded dynamic skill: {}", skill.name());
+                        self.skills.insert(skill.name(), Arc::new(skill.with_health(health)));
                     }
-                    info!("Loaded dynamic skill: {}", skill.name());
-                    self.skills.insert(skill.name(), Arc::new(skill));
+                    Err(e) => warn!("Skipping skill at {:?}: {}", path, e),
                 }
             }
         }
+        self.changed();
         Ok(())
     }
 
@@ -422,46 +1001,44 @@ impl SkillLoader {
         }
 
         let content = tokio::fs::read_to_string(&manifest_path).await?;
-        
-        // Find frontmatter delimiters
-        let start_delimiter = "---\n";
-        let end_delimiter = "\n---";
-        
-        // Normalize line endings for delimiter search? 
-        // Or just search based on robust logic.
-        // Let's assume \n or \r\n. 
-        // We will look for "\n---" which indicates the end delimiter on its own line.
-        
-        let yaml_str;
-        let instructions;
-
-        // Ensure file starts with YAML frontmatter
-        if content.starts_with(start_delimiter) || content.starts_with("---\r\n") {
-             // Find end of frontmatter
-             if let Some(end_idx) = content[4..].find(end_delimiter) {
-                 let actual_end_idx = end_idx + 4; // Add back the initial offset
-                 yaml_str = &content[4..actual_end_idx]; // Exclude delimiters
-                 
-                 // Instructions start after the end delimiter + delimiter length (4 chars for \n---)
-                 // content[actual_end_idx] starts with \n. 
-                 // \n--- is 4 chars.
-                 let rest_start = actual_end_idx + 4;
-                 if rest_start < content.len() {
-                     instructions = content[rest_start..].trim().to_string();
-                 } else {
-                     instructions = String::new();
-                 }
-             } else {
-                 return Err(Error::Internal("SKILL.md frontmatter unclosed (missing closing ---)".to_string()));
-             }
-        } else {
-             return Err(Error::Internal("SKILL.md must start with ---".to_string()));
+        let (mut metadata, instructions) = frontmatter::parse(&content)?;
+
+        // Examples must match the declared schema: fail in debug builds, drop in release
+        if let Some(parameters) = metadata.parameters.clone() {
+            let mut invalid = Vec::new();
+            for example in &metadata.examples {
+                if let Err(e) = crate::skills::tool::schema::validate(&example.arguments, &parameters) {
+                    invalid.push(format!("'{}': {}", example.description, e));
+                }
+            }
+            if !invalid.is_empty() {
+                let message = format!("Skill {} has invalid examples: {}", metadata.name, invalid.join("; "));
+                if cfg!(debug_assertions) {
+                    return Err(Error::Internal(message));
+                }
+                warn!("{}", message);
+                metadata.examples.retain(|example| {
+                    crate::skills::tool::schema::validate(&example.arguments, &parameters).is_ok()
+                });
+            }
         }
 
-        let metadata: SkillMetadata = serde_yaml_ng::from_str(yaml_str)
-            .map_err(|e| Error::Internal(format!("Failed to parse Skill YAML: {}", e)))?;
-        
-        Ok(DynamicSkill::new(metadata, instructions, path.to_path_buf()))
+        #[allow(unused_mut)]
+        let mut skill = DynamicSkill::new(metadata, instructions, path.to_path_buf());
+        if skill.metadata.runtime.as_deref() == Some("wasm") {
+            #[cfg(not(feature = "wasm"))]
+            return Err(Error::Internal(format!("Skill {} needs the 'wasm' feature", skill.name())));
+            // Core modules are compiled once here; components still load per call
+            #[cfg(feature = "wasm")]
+            {
+                let bytes = tokio::fs::read(skill.wasm_path()?).await?;
+                if !runtime::wasm_skill::is_component(&bytes) {
+                    let module = WasmSkill::new(skill.name(), &bytes)?;
+                    skill = skill.with_wasm(module);
+                }
+            }
+        }
+        Ok(skill)
     }
 }
 
@@ -471,6 +1048,10 @@ impl ContextInjector for SkillLoader {
         // Redundant - ToolSet now handles tool definitions in TS style
         Ok(Vec::new())
     }
+
+    fn source_key(&self) -> String {
+        "skills".to_string()
+    }
 }
 
 /// Tool to read the full SKILL.md guide for a specific skill
@@ -505,120 +1086,85 @@ impl Tool for ReadSkillDoc {
                 "required": ["skill_name"]
             }),
             parameters_ts: Some("interface ReadSkillArgs {\n  skill_name: string; // The name of the skill to read manual for\n}".to_string()),
-            is_binary: false,
             is_verified: true,
+            ..Default::default()
         }
     }
 
     async fn call(&self, arguments: &str) -> anyhow::Result<String> {
-        #[derive(Deserialize)]
+        #[derive(Deserialize, schemars::JsonSchema)]
         struct Args {
             skill_name: String,
         }
-        let args: Args = serde_json::from_str(arguments)?;
+        let args: Args = parse_args(&self.name(), arguments)?;
         
-        if let Some(skill) = self.loader.skills.get(&args.skill_name) {
+        if let Some(skill) = self.loader.get(&args.skill_name) {
             Ok(format!("# Skill: {}\n\n{}", skill.name(), skill.instructions))
         } else {
             Err(anyhow::anyhow!("Skill '{}' not found in registry", args.skill_name))
         }
     }
 }
-/// Tool to search and install skills from ClawHub using CLI (npm/pnpm/bun)
-pub struct ClawHubTool {
-    loader: Arc<SkillLoader>,
-}
 
-impl ClawHubTool {
-    pub fn new(loader: Arc<SkillLoader>) -> Self {
-        Self { loader }
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
 
-#[async_trait]
-impl Tool for ClawHubTool {
-    fn name(&self) -> String {
-        "clawhub_manager".to_string()
+    fn skill(name: &str) -> DynamicSkill {
+        let (metadata, instructions) =
+            frontmatter::parse(&format!("---\nname: {name}\ndescription: Test skill\n---\nDo it.")).unwrap();
+        DynamicSkill::new(metadata, instructions, PathBuf::from("."))
     }
 
-    async fn definition(&self) -> ToolDefinition {
-        ToolDefinition {
-            name: self.name(),
-            description: "Search and install new skills from the ClawHub.ai registry. Supports 'search' to find skills and 'install' to add them to your environment.".to_string(),
-            parameters: json!({
-                "type": "object",
-                "properties": {
-                    "action": {
-                        "type": "string",
-                        "enum": ["search", "install"],
-                        "description": "The action to perform"
-                    },
-                    "query": {
-                        "type": "string",
-                        "description": "Search query or skill slug to install"
-                    },
-                    "manager": {
-                        "type": "string",
-                        "enum": ["npm", "pnpm", "bun"],
-                        "description": "The package manager to use (default: npm)"
-                    }
-                },
-                "required": ["action", "query"]
-            }),
-            parameters_ts: Some("interface ClawHubArgs {\n  action: 'search' | 'install';\n  query: string; // Search query or skill slug\n  manager?: 'npm' | 'pnpm' | 'bun'; // Package manager (default: npm)\n}".to_string()),
-            is_binary: false,
-            is_verified: true,
-        }
-    }
+    #[test]
+    fn test_watchers_can_register_watchers() {
+        let loader = Arc::new(SkillLoader::new("./no-skills-here"));
+        let added = Arc::new(AtomicUsize::new(0));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (weak, late) = (Arc::downgrade(&loader), Arc::clone(&calls));
+        loader.on_change(move || {
+            let late = Arc::clone(&late);
+            weak.upgrade().unwrap().on_change(move || {
+                late.fetch_add(1, Ordering::SeqCst);
+                true
+            });
+            added.fetch_add(1, Ordering::SeqCst) == 0
+        });
 
-    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
-        #[derive(Deserialize)]
-        struct Args {
-            action: String,
-            query: String,
-            manager: Option<String>,
-        }
-        let args: Args = serde_json::from_str(arguments)?;
+        loader.insert(skill("quote"));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert!(loader.get("quote").is_some());
 
-        let manager = args.manager.as_deref().unwrap_or("npm");
-        let (cmd, base_args) = match manager {
-            "pnpm" => ("pnpm", vec!["dlx", "clawhub@latest"]),
-            "bun" => ("bunx", vec!["clawhub@latest"]),
-            _ => ("npx", vec!["clawhub@latest"]),
-        };
+        // The first watcher retired itself, the one it added survived
+        loader.remove("quote");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(loader.watchers.lock().len(), 2);
+        assert!(loader.is_empty());
+    }
 
-        match args.action.as_str() {
-            "search" => {
-                info!("Searching ClawHub registry for: {} (via {})", args.query, manager);
-                let output = tokio::process::Command::new(cmd)
-                    .args(&base_args)
-                    .arg("search")
-                    .arg(&args.query)
-                    .output()
-                    .await?;
-                
-                Ok(String::from_utf8_lossy(&output.stdout).to_string())
-            }
-            "install" => {
-                info!("Installing skill from ClawHub: {} (via {})", args.query, manager);
-                let output = tokio::process::Command::new(cmd)
-                    .args(&base_args)
-                    .arg("install")
-                    .arg(&args.query)
-                    .output()
-                    .await?;
-
-                if output.status.success() {
-                    // Refresh the loader to pick up the new skill
-                    info!("Skill {} installed successfully, refreshing registry...", args.query);
-                    self.loader.load_all().await?;
-                    Ok(format!("Successfully installed '{}'. It is now available for use.", args.query))
-                } else {
-                    let err = String::from_utf8_lossy(&output.stderr);
-                    Err(anyhow::anyhow!("Failed to install skill: {}", err))
-                }
-            }
-            _ => Err(anyhow::anyhow!("Unknown action: {}", args.action)),
+    #[test]
+    fn test_python_probe_checks_distributions_not_import_names() {
+        if which::which("python3").is_err() {
+            return;
         }
+        // python-dateutil installs the `dateutil` module, so guessing the import name fails
+        let site = tempfile::tempdir().unwrap();
+        let info = site.path().join("python_dateutil-2.9.0.dist-info");
+        std::fs::create_dir_all(&info).unwrap();
+        std::fs::write(info.join("METADATA"), "Metadata-Version: 2.1\nName: python-dateutil\nVersion: 2.9.0\n").unwrap();
+        std::fs::create_dir_all(site.path().join("dateutil")).unwrap();
+        std::fs::write(site.path().join("dateutil/__init__.py"), "").unwrap();
+
+        let requirements = ["python-dateutil>=2.8", "aagt-no-such-dist[extra]"];
+        let output = std::process::Command::new("python3")
+            .arg("-c")
+            .arg(PYTHON_DISTRIBUTION_PROBE)
+            .args(requirements.iter().map(|r| python_distribution(r)))
+            .env("PYTHONPATH", site.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "aagt-no-such-dist");
     }
 }
@@ -1,3 +1,5 @@
 pub mod wasm;
+pub mod wasm_skill;
 
 pub use wasm::WasmRuntime;
+pub use wasm_skill::WasmSkill;
@@ -0,0 +1,157 @@
+//! Core WebAssembly modules as skills, without an OS sandbox
+//!
+//! Script skills need `bwrap`, which macOS and most containers lack. A skill
+//! declared with `runtime: wasm` instead runs in wasmtime, isolated by the
+//! module boundary itself: it gets no filesystem, network or environment.
+//!
+//! A module must export:
+//! - `memory`
+//! - `allocate(len: i32) -> i32`, returning a buffer of `len` bytes
+//! - `call(ptr: i32, len: i32) -> i64`, taking the JSON arguments written to an
+//!   allocated buffer and returning its UTF-8 output packed as `ptr << 32 | len`
+//!
+//! Modules built for `wasm32-wasip1` may import WASI; they see an empty
+//! environment. Each call gets a fresh instance, so no state leaks between calls.
+//! Runs are cut off at [`SkillExecutionConfig::timeout_secs`] through epoch
+//! interruption, and longer outputs than `max_output_bytes` are rejected.
+
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use wasmtime::{Config, Engine, Linker, Module, Store};
+use wasmtime_wasi::preview1::WasiP1Ctx;
+use wasmtime_wasi::WasiCtxBuilder;
+
+use crate::error::{Error, Result};
+use crate::skills::SkillExecutionConfig;
+
+/// How often the shared engine's epoch advances
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Engine shared by all wasm skills, with a background thread ticking its epoch
+fn engine() -> Result<&'static Engine> {
+    static ENGINE: OnceLock<std::result::Result<Engine, String>> = OnceLock::new();
+    ENGINE
+        .get_or_init(|| {
+            let mut config = Config::new();
+            config.epoch_interruption(true);
+            let engine = Engine::new(&config).map_err(|e| e.to_string())?;
+            let ticker = engine.clone();
+            std::thread::Builder::new()
+                .name("aagt-wasm-epoch".to_string())
+                .spawn(move || loop {
+                    std::thread::sleep(EPOCH_TICK);
+                    ticker.increment_epoch();
+                })
+                .map_err(|e| e.to_string())?;
+            Ok(engine)
+        })
+        .as_ref()
+        .map_err(|e| Error::Internal(format!("Failed to create Wasm engine: {}", e)))
+}
+
+/// Whether `bytes` is a component rather than a core module (binary layer field)
+pub fn is_component(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"\0asm") && bytes.get(6..8) == Some(&[1, 0])
+}
+
+/// A compiled skill module speaking the allocate/call ABI
+#[derive(Clone)]
+pub struct WasmSkill {
+    name: String,
+    module: Module,
+}
+
+impl WasmSkill {
+    /// Compile the module at `path` for the skill `name`
+    pub fn load(name: impl Into<String>, path: &Path) -> Result<Self> {
+        Self::new(name, &std::fs::read(path)?)
+    }
+
+    /// Compile a module (binary or text format) for the skill `name`
+    pub fn new(name: impl Into<String>, bytes: &[u8]) -> Result<Self> {
+        let name = name.into();
+        let module = Module::new(engine()?, bytes).map_err(|e| {
+            Error::tool_execution(name.clone(), format!("Failed to load Wasm module: {}", e))
+        })?;
+        for export in ["memory", "allocate", "call"] {
+            if module.get_export(export).is_none() {
+                return Err(Error::tool_execution(
+                    name,
+                    format!("Wasm module must export '{}'", export),
+                ));
+            }
+        }
+        Ok(Self { name, module })
+    }
+
+    /// Run the skill on `arguments` within the limits of `config`
+    pub async fn call(&self, arguments: &str, config: &SkillExecutionConfig) -> Result<String> {
+        let skill = self.clone();
+        let arguments = arguments.to_string();
+        let timeout = Duration::from_secs(config.timeout_secs);
+        let max_output_bytes = config.max_output_bytes;
+        tokio::task::spawn_blocking(move || skill.run(&arguments, timeout, max_output_bytes))
+            .await
+            .map_err(|e| {
+                Error::tool_execution(self.name.clone(), format!("Wasm task failed: {}", e))
+            })?
+    }
+
+    fn run(&self, arguments: &str, timeout: Duration, max_output_bytes: usize) -> Result<String> {
+        let fail = |message: String| Error::tool_execution(self.name.clone(), message);
+        let engine = self.module.engine();
+
+        let mut linker: Linker<WasiP1Ctx> = Linker::new(engine);
+        wasmtime_wasi::preview1::add_to_linker_sync(&mut linker, |ctx| ctx)
+            .map_err(|e| fail(format!("Failed to link WASI: {}", e)))?;
+        let mut store = Store::new(engine, WasiCtxBuilder::new().build_p1());
+        let ticks = timeout.as_millis().div_ceil(EPOCH_TICK.as_millis()).max(1);
+        store.set_epoch_deadline(ticks as u64);
+
+        let timed_out = |e: wasmtime::Error| match e.downcast_ref::<wasmtime::Trap>() {
+            Some(wasmtime::Trap::Interrupt) => {
+                fail(format!("Execution timed out after {}s", timeout.as_secs()))
+            }
+            _ => fail(format!("Wasm execution failed: {}", e)),
+        };
+        let instance = linker
+            .instantiate(&mut store, &self.module)
+            .map_err(|e| fail(format!("Failed to instantiate Wasm module: {}", e)))?;
+        if let Ok(init) = instance.get_typed_func::<(), ()>(&mut store, "_initialize") {
+            init.call(&mut store, ()).map_err(timed_out)?;
+        }
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| fail("Wasm module must export 'memory'".to_string()))?;
+        let allocate = instance
+            .get_typed_func::<i32, i32>(&mut store, "allocate")
+            .map_err(|e| fail(format!("Bad 'allocate' export: {}", e)))?;
+        let call = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, "call")
+            .map_err(|e| fail(format!("Bad 'call' export: {}", e)))?;
+
+        let input = arguments.as_bytes();
+        let len =
+            i32::try_from(input.len()).map_err(|_| fail("Arguments too large".to_string()))?;
+        let ptr = allocate.call(&mut store, len).map_err(timed_out)?;
+        memory
+            .write(&mut store, ptr as u32 as usize, input)
+            .map_err(|e| fail(format!("'allocate' returned a bad buffer: {}", e)))?;
+
+        let packed = call.call(&mut store, (ptr, len)).map_err(timed_out)? as u64;
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        if out_len > max_output_bytes {
+            return Err(fail(format!(
+                "Output of {} bytes exceeds max_output_bytes ({})",
+                out_len, max_output_bytes
+            )));
+        }
+        let mut output = vec![0; out_len];
+        memory
+            .read(&store, out_ptr, &mut output)
+            .map_err(|e| fail(format!("'call' returned a bad buffer: {}", e)))?;
+        String::from_utf8(output).map_err(|_| fail("Wasm output is not UTF-8".to_string()))
+    }
+}
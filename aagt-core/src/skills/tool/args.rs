@@ -0,0 +1,516 @@
+//! Typed argument parsing for hand-written tools
+//!
+//! [`parse_args`] replaces the usual `serde_json::from_str` + `map_err` at the top of
+//! `Tool::call`. Every failure becomes an `Error::ToolArguments` with the same
+//! message shape, `<path>: <problem>; expected <schema>; received <value>`, so the
+//! argument-repair flow sees one format whether a tool is hand-written or generated
+//! by `#[tool]`.
+//!
+//! Unless told otherwise with `args = ...`, `#[tool]` on a struct expects a
+//! `StructNameArgs` type; a missing one is reported at the struct:
+//!
+//! ```compile_fail
+//! #[aagt_macros::tool(name = "get_price", description = "Get a price")]
+//! struct GetPrice;
+//!
+//! impl GetPrice {
+//!     async fn execute(&self, args: PriceQuery) -> aagt_core::error::Result<String> {
+//!         Ok(args.symbol)
+//!     }
+//! }
+//!
+//! #[derive(serde::Deserialize, schemars::JsonSchema)]
+//! struct PriceQuery {
+//!     symbol: String,
+//! }
+//! ```
+
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::skills::tool::{coerce_args, schema, Tool};
+
+/// Longest excerpt of a received value quoted in an error
+const MAX_EXCERPT_CHARS: usize = 80;
+
+/// A structured argument problem, rendered into `Error::ToolArguments`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgumentError {
+    /// Path of the offending field, e.g. `$.amount`
+    pub path: String,
+    /// What is wrong with it
+    pub problem: String,
+    /// Schema the field should satisfy
+    pub expected: Option<Value>,
+    /// Excerpt of what was received
+    pub received: Option<String>,
+}
+
+impl ArgumentError {
+    pub fn new(path: impl Into<String>, problem: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            problem: problem.into(),
+            expected: None,
+            received: None,
+        }
+    }
+
+    /// Attach the schema the field should satisfy
+    pub fn with_expected(mut self, expected: Value) -> Self {
+        self.expected = Some(expected);
+        self
+    }
+
+    /// Attach an excerpt of the received value
+    pub fn with_received(mut self, received: &Value) -> Self {
+        self.received = Some(excerpt(&received.to_string()));
+        self
+    }
+
+    /// Convert into the `Error::ToolArguments` reported by `tool_name`
+    pub fn into_error(self, tool_name: impl Into<String>) -> Error {
+        Error::ToolArguments {
+            tool_name: tool_name.into(),
+            message: self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.problem)?;
+        if let Some(expected) = &self.expected {
+            write!(f, "; expected {}", expected)?;
+        }
+        if let Some(received) = &self.received {
+            write!(f, "; received {}", received)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse and validate tool arguments against `T`'s JSON Schema
+pub fn parse_args<T: DeserializeOwned + JsonSchema>(tool_name: &str, raw: &str) -> Result<T> {
+    parse_value(tool_name, parse_json(tool_name, raw)?)
+}
+
+/// [`parse_args`] after running [`coerce_args`] with `T`'s schema, as `#[tool]` does
+pub fn parse_args_coerced<T: DeserializeOwned + JsonSchema>(
+    tool_name: &str,
+    raw: &str,
+) -> Result<T> {
+    let value = coerce_args(&args_schema::<T>(), parse_json(tool_name, raw)?);
+    parse_value(tool_name, value)
+}
+
+fn parse_json(tool_name: &str, raw: &str) -> Result<Value> {
+    serde_json::from_str(raw).map_err(|e| {
+        let mut err = ArgumentError::new("$", format!("invalid JSON ({})", e));
+        err.received = Some(excerpt(raw));
+        err.into_error(tool_name)
+    })
+}
+
+/// [`parse_args`] after running [`repair_json`] over the raw arguments
+pub fn parse_args_lenient<T: DeserializeOwned + JsonSchema>(
+    tool_name: &str,
+    raw: &str,
+) -> Result<T> {
+    parse_args(tool_name, &repair_json(raw))
+}
+
+/// Validate and deserialize already-parsed arguments
+pub fn parse_value<T: DeserializeOwned + JsonSchema>(tool_name: &str, value: Value) -> Result<T> {
+    let schema = args_schema::<T>();
+    if let Err(violation) = schema::check(&value, &schema) {
+        let path = match &violation.property {
+            Some(property) => format!("{}.{}", violation.path, property),
+            None => violation.path,
+        };
+        let mut err = ArgumentError::new(&path, violation.message);
+        if let Some(expected) = schema_at(&schema, &path) {
+            err = err.with_expected(expected.clone());
+        }
+        if let Some(received) = value_at(&value, &path) {
+            err = err.with_received(received);
+        }
+        return Err(err.into_error(tool_name));
+    }
+
+    // The schema check covers the common cases; serde has the last word (untagged enums, ranges)
+    serde_json::from_value(value.clone()).map_err(|e| {
+        ArgumentError::new("$", e.to_string())
+            .with_received(&value)
+            .into_error(tool_name)
+    })
+}
+
+/// Inlined JSON Schema for an argument type
+pub(crate) fn args_schema<T: JsonSchema>() -> Value {
+    let gen = schemars::gen::SchemaSettings::openapi3()
+        .with(|s| s.inline_subschemas = true)
+        .into_generator();
+    serde_json::to_value(gen.into_root_schema_for::<T>()).unwrap_or(Value::Bool(true))
+}
+
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// Split `$.a.b[2]` into segments
+fn segments(path: &str) -> Vec<Segment<'_>> {
+    let mut out = Vec::new();
+    let rest = path.strip_prefix('$').unwrap_or(path);
+    for part in rest.split('.').filter(|p| !p.is_empty()) {
+        let (key, indices) = part.split_once('[').map_or((part, ""), |(k, i)| (k, i));
+        if !key.is_empty() {
+            out.push(Segment::Key(key));
+        }
+        for index in indices.split('[') {
+            if let Ok(i) = index.trim_end_matches(']').parse() {
+                out.push(Segment::Index(i));
+            }
+        }
+    }
+    out
+}
+
+fn schema_at<'a>(schema: &'a Value, path: &str) -> Option<&'a Value> {
+    segments(path)
+        .into_iter()
+        .try_fold(schema, |schema, segment| match segment {
+            Segment::Key(key) => schema.get("properties")?.get(key),
+            Segment::Index(_) => schema.get("items"),
+        })
+}
+
+fn value_at<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    segments(path)
+        .into_iter()
+        .try_fold(value, |value, segment| match segment {
+            Segment::Key(key) => value.get(key),
+            Segment::Index(i) => value.get(i),
+        })
+}
+
+fn excerpt(text: &str) -> String {
+    if text.chars().count() <= MAX_EXCERPT_CHARS {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(MAX_EXCERPT_CHARS).collect();
+    format!("{}...", cut)
+}
+
+/// Best-effort cleanup of almost-JSON that models produce
+///
+/// Strips a markdown code fence, treats empty input as `{}`, drops trailing commas
+/// and maps Python-style `True`/`False`/`None` outside strings.
+pub fn repair_json(raw: &str) -> Cow<'_, str> {
+    let mut text = raw.trim();
+    if let Some(fenced) = text.strip_prefix("```") {
+        let body = fenced.split_once('\n').map_or("", |(_, body)| body);
+        text = body.trim_end().strip_suffix("```").unwrap_or(body).trim();
+    }
+    if text.is_empty() {
+        return Cow::Borrowed("{}");
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let len = c.len_utf8();
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            out.push(c);
+            rest = &rest[len..];
+            continue;
+        }
+
+        let after = &rest[len..];
+        match c {
+            '"' => in_string = true,
+            ',' if after.trim_start().starts_with(['}', ']']) => {
+                rest = after;
+                continue;
+            }
+            _ => {}
+        }
+        let at_word_start = !out
+            .chars()
+            .next_back()
+            .is_some_and(|p| p.is_alphanumeric() || p == '_');
+        let literal = [("True", "true"), ("False", "false"), ("None", "null")]
+            .into_iter()
+            .find(|(word, _)| {
+                at_word_start
+                    && rest.starts_with(word)
+                    && !rest[word.len()..].starts_with(|n: char| n.is_alphanumeric() || n == '_')
+            });
+        if let Some((word, json)) = literal {
+            out.push_str(json);
+            rest = &rest[word.len()..];
+            continue;
+        }
+        out.push(c);
+        rest = after;
+    }
+
+    if out == raw {
+        Cow::Borrowed(raw)
+    } else {
+        Cow::Owned(out)
+    }
+}
+
+/// Checks on parsed arguments that fail in the same shape as [`parse_args`]
+pub trait ArgsExt {
+    /// Reject an empty or whitespace-only string
+    fn require_non_empty(&self, field: &str, value: &str) -> Result<()>;
+
+    /// Reject a number outside `min..=max`
+    fn require_range<N>(&self, field: &str, value: N, min: N, max: N) -> Result<()>
+    where
+        N: PartialOrd + fmt::Display + Serialize;
+
+    /// Reject a string outside a fixed set of values
+    fn require_one_of(&self, field: &str, value: &str, allowed: &[&str]) -> Result<()>;
+}
+
+impl<T: Tool + ?Sized> ArgsExt for T {
+    fn require_non_empty(&self, field: &str, value: &str) -> Result<()> {
+        if !value.trim().is_empty() {
+            return Ok(());
+        }
+        Err(
+            ArgumentError::new(format!("$.{}", field), "must not be empty")
+                .with_expected(serde_json::json!({ "type": "string", "minLength": 1 }))
+                .with_received(&Value::from(value))
+                .into_error(self.name()),
+        )
+    }
+
+    fn require_range<N>(&self, field: &str, value: N, min: N, max: N) -> Result<()>
+    where
+        N: PartialOrd + fmt::Display + Serialize,
+    {
+        if value >= min && value <= max {
+            return Ok(());
+        }
+        Err(ArgumentError::new(
+            format!("$.{}", field),
+            format!("value {} is outside {}..={}", value, min, max),
+        )
+        .with_expected(serde_json::json!({ "minimum": min, "maximum": max }))
+        .with_received(&serde_json::to_value(&value).unwrap_or_default())
+        .into_error(self.name()))
+    }
+
+    fn require_one_of(&self, field: &str, value: &str, allowed: &[&str]) -> Result<()> {
+        if allowed.contains(&value) {
+            return Ok(());
+        }
+        let allowed = Value::from(allowed.to_vec());
+        Err(ArgumentError::new(
+            format!("$.{}", field),
+            format!("value {} is not one of {}", Value::from(value), allowed),
+        )
+        .with_expected(serde_json::json!({ "type": "string", "enum": allowed }))
+        .with_received(&Value::from(value))
+        .into_error(self.name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skills::tool::{ToolDefinition, ToolSet};
+    use async_trait::async_trait;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    enum Side {
+        Buy,
+        Sell,
+    }
+
+    /// Arguments shared by the macro tool and the manual tool
+    #[derive(Debug, Deserialize, JsonSchema)]
+    struct OrderArgs {
+        /// Token symbol
+        symbol: String,
+        /// Amount in USD
+        amount: f64,
+        side: Side,
+    }
+
+    #[aagt_macros::tool(name = "place_order", description = "Place an order", args = OrderArgs)]
+    struct MacroOrderTool;
+
+    impl MacroOrderTool {
+        async fn execute(&self, args: OrderArgs) -> Result<String> {
+            Ok(format!("{:?} {} {}", args.side, args.amount, args.symbol))
+        }
+    }
+
+    #[aagt_macros::tool(
+        name = "place_order",
+        description = "Place an order",
+        args = OrderArgs,
+        coerce = false
+    )]
+    struct StrictOrderTool;
+
+    impl StrictOrderTool {
+        async fn execute(&self, args: OrderArgs) -> Result<String> {
+            Ok(format!("{:?} {} {}", args.side, args.amount, args.symbol))
+        }
+    }
+
+    struct ManualOrderTool;
+
+    #[async_trait]
+    impl Tool for ManualOrderTool {
+        fn name(&self) -> String {
+            "place_order".to_string()
+        }
+
+        async fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: self.name(),
+                description: "Place an order".to_string(),
+                parameters: args_schema::<OrderArgs>(),
+                is_verified: true,
+                ..Default::default()
+            }
+        }
+
+        async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+            let args: OrderArgs = parse_args(&self.name(), arguments)?;
+            self.require_non_empty("symbol", &args.symbol)?;
+            self.require_range("amount", args.amount, 1.0, 10_000.0)?;
+            Ok(format!("{:?} {} {}", args.side, args.amount, args.symbol))
+        }
+    }
+
+    async fn error_of(tool: impl Tool + 'static, arguments: &str) -> String {
+        let mut tools = ToolSet::new();
+        tools.add(tool);
+        let err = tools.call("place_order", arguments).await.unwrap_err();
+        match err.downcast_ref::<Error>() {
+            Some(Error::ToolArguments { tool_name, message }) => {
+                assert_eq!(tool_name, "place_order");
+                message.clone()
+            }
+            other => panic!("expected ToolArguments, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_macro_and_manual_tools_report_identical_errors() {
+        let cases = [
+            (
+                r#"{"amount": 5, "side": "buy"}"#,
+                r#"$.symbol: missing required property 'symbol'; expected {"description":"Token symbol","type":"string"}"#,
+            ),
+            (
+                r#"{"symbol": "SOL", "amount": "five", "side": "buy"}"#,
+                r#"$.amount: expected type "number", got string; expected {"description":"Amount in USD","format":"double","type":"number"}; received "five""#,
+            ),
+            (
+                r#"{"symbol": "SOL", "amount": 5, "side": "hold"}"#,
+                r#"$.side: value "hold" is not one of ["buy","sell"]; expected {"enum":["buy","sell"],"type":"string"}; received "hold""#,
+            ),
+        ];
+        for (arguments, expected) in cases {
+            let from_macro = error_of(MacroOrderTool, arguments).await;
+            let from_manual = error_of(ManualOrderTool, arguments).await;
+            assert_eq!(from_macro, from_manual);
+            assert_eq!(from_macro, expected);
+        }
+
+        let malformed = error_of(ManualOrderTool, "{\"symbol\": ").await;
+        assert!(malformed.starts_with("$: invalid JSON ("), "{}", malformed);
+        assert_eq!(malformed, error_of(MacroOrderTool, "{\"symbol\": ").await);
+    }
+
+    #[tokio::test]
+    async fn test_sloppy_arguments_are_coerced_unless_the_tool_opts_out() {
+        let sloppy = r#"{"symbol": "SOL", "amount": "100", "side": "buy", "note": null}"#;
+        let mut tools = ToolSet::new();
+        tools.add(MacroOrderTool);
+        assert_eq!(tools.call("place_order", sloppy).await.unwrap(), "Buy 100 SOL");
+        assert_eq!(MacroOrderTool.call(sloppy).await.unwrap(), "Buy 100 SOL");
+
+        // Hand-written tools get it from the toolset, against their definition's schema
+        let mut tools = ToolSet::new();
+        tools.add(ManualOrderTool);
+        assert_eq!(tools.call("place_order", sloppy).await.unwrap(), "Buy 100 SOL");
+        assert!(ManualOrderTool.call(sloppy).await.is_err());
+
+        assert!(!StrictOrderTool.coerce_arguments());
+        assert!(error_of(StrictOrderTool, sloppy)
+            .await
+            .starts_with(r#"$.amount: expected type "number", got string"#));
+
+        // Coercion never fills in a missing required field
+        assert!(error_of(MacroOrderTool, r#"{"amount": "100", "side": "buy"}"#)
+            .await
+            .starts_with("$.symbol: missing required property 'symbol'"));
+    }
+
+    #[tokio::test]
+    async fn test_args_ext_checks() {
+        assert_eq!(
+            error_of(
+                ManualOrderTool,
+                r#"{"symbol": " ", "amount": 5, "side": "buy"}"#
+            )
+            .await,
+            r#"$.symbol: must not be empty; expected {"minLength":1,"type":"string"}; received " ""#
+        );
+        assert_eq!(
+            error_of(
+                ManualOrderTool,
+                r#"{"symbol": "SOL", "amount": 0.5, "side": "buy"}"#
+            )
+            .await,
+            r#"$.amount: value 0.5 is outside 1..=10000; expected {"maximum":10000.0,"minimum":1.0}; received 0.5"#
+        );
+        let err = ManualOrderTool
+            .require_one_of("side", "hold", &["buy", "sell"])
+            .unwrap_err();
+        assert!(err.to_string().ends_with(
+            r#"$.side: value "hold" is not one of ["buy","sell"]; expected {"enum":["buy","sell"],"type":"string"}; received "hold""#
+        ));
+    }
+
+    #[test]
+    fn test_lenient_parsing_repairs_json() {
+        let raw = "```json\n{\"symbol\": \"SOL, True\", \"amount\": 5, \"side\": \"sell\",}\n```";
+        assert!(parse_args::<OrderArgs>("place_order", raw).is_err());
+        let args: OrderArgs = parse_args_lenient("place_order", raw).unwrap();
+        assert_eq!(args.symbol, "SOL, True");
+
+        assert_eq!(repair_json("  "), "{}");
+        assert_eq!(
+            repair_json(r#"{"a": [1, 2,], "b": None, "c": True}"#),
+            r#"{"a": [1, 2], "b": null, "c": true}"#
+        );
+        assert!(matches!(repair_json(r#"{"a": 1}"#), Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,614 @@
+//! Per-tool circuit breakers
+//!
+//! A tool whose backend keeps failing is short-circuited with
+//! [`Error::ToolUnavailable`] instead of making every conversation wait out the
+//! same timeout. The breaker opens after `consecutive_failures` failures in a
+//! row, or when the failure rate over `window` reaches `failure_rate` with at
+//! least `min_window_calls` calls. After `cooldown` one probe call is let
+//! through (half-open); success closes the breaker, failure reopens it.
+//!
+//! Argument errors ([`Error::ToolArguments`]) are the model's mistake, not the
+//! tool's, and never count as failures.
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::error::Error;
+
+/// Thresholds for one tool's breaker
+#[derive(Debug, Clone)]
+pub struct BreakerConfig {
+    /// Failures in a row that open the breaker
+    pub consecutive_failures: u32,
+    /// Window for the failure-rate check
+    pub window: Duration,
+    /// Failure rate over `window` that opens the breaker
+    pub failure_rate: f64,
+    /// Calls needed in `window` before the rate is considered
+    pub min_window_calls: usize,
+    /// How long the breaker stays open before a probe call
+    pub cooldown: Duration,
+    /// Never open, only track (for tools that must always be attempted)
+    pub never_open: bool,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            consecutive_failures: 5,
+            window: Duration::from_secs(60),
+            failure_rate: 0.5,
+            min_window_calls: 10,
+            cooldown: Duration::from_secs(30),
+            never_open: false,
+        }
+    }
+}
+
+impl BreakerConfig {
+    /// A breaker that tracks failures but never short-circuits
+    pub fn never_open() -> Self {
+        Self {
+            never_open: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// Breaker position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls go through
+    Closed,
+    /// Calls are short-circuited until the cooldown ends
+    Open,
+    /// One probe call decides whether to close or reopen
+    HalfOpen,
+}
+
+/// Operator override that pins a breaker regardless of failures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerOverride {
+    /// Always short-circuit
+    ForceOpen,
+    /// Never short-circuit
+    ForceClosed,
+}
+
+/// How a tool call ended, as far as the breaker is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallOutcome {
+    /// The tool returned a result
+    Success,
+    /// The tool failed
+    Failure,
+    /// The model sent bad arguments (not counted)
+    InvalidArguments,
+}
+
+impl CallOutcome {
+    /// Classify a tool call result
+    pub fn of<T>(result: &anyhow::Result<T>) -> Self {
+        match result {
+            Ok(_) => Self::Success,
+            Err(e) => match e.downcast_ref::<Error>() {
+                Some(Error::ToolArguments { .. }) => Self::InvalidArguments,
+                _ => Self::Failure,
+            },
+        }
+    }
+}
+
+/// Snapshot of one tool's breaker
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    /// Tool name
+    pub tool: String,
+    /// Current position (ignoring overrides)
+    pub state: BreakerState,
+    /// Operator override, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forced: Option<BreakerOverride>,
+    /// Failures since the last success
+    pub consecutive_failures: u32,
+    /// Calls in the current window
+    pub window_calls: usize,
+    /// Failures in the current window
+    pub window_failures: usize,
+    /// Times the breaker has opened
+    pub trips: u64,
+    /// Seconds until a probe is allowed, while open
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+struct Breaker {
+    state: BreakerState,
+    forced: Option<BreakerOverride>,
+    opened_at: Instant,
+    probe_started: Option<Instant>,
+    consecutive: u32,
+    window: VecDeque<(Instant, bool)>,
+    trips: u64,
+}
+
+impl Breaker {
+    fn new() -> Self {
+        Self {
+            state: BreakerState::Closed,
+            forced: None,
+            opened_at: Instant::now(),
+            probe_started: None,
+            consecutive: 0,
+            window: VecDeque::new(),
+            trips: 0,
+        }
+    }
+
+    fn prune(&mut self, config: &BreakerConfig, now: Instant) {
+        while self
+            .window
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > config.window)
+        {
+            self.window.pop_front();
+        }
+    }
+
+    fn window_failures(&self) -> usize {
+        self.window.iter().filter(|(_, ok)| !ok).count()
+    }
+
+    fn should_trip(&self, config: &BreakerConfig) -> bool {
+        if self.consecutive >= config.consecutive_failures {
+            return true;
+        }
+        self.window.len() >= config.min_window_calls
+            && self.window_failures() as f64 / self.window.len() as f64 >= config.failure_rate
+    }
+
+    fn open(&mut self, now: Instant) {
+        self.state = BreakerState::Open;
+        self.opened_at = now;
+        self.probe_started = None;
+        self.trips += 1;
+    }
+
+    /// Time left before a call may go through, or `None` if it may go now
+    fn wait(&self, config: &BreakerConfig, now: Instant) -> Option<Duration> {
+        match self.forced {
+            Some(BreakerOverride::ForceOpen) => return Some(Duration::ZERO),
+            Some(BreakerOverride::ForceClosed) => return None,
+            None => {}
+        }
+        if config.never_open {
+            return None;
+        }
+        let since = match (self.state, self.probe_started) {
+            (BreakerState::Closed, _) | (BreakerState::HalfOpen, None) => return None,
+            (BreakerState::Open, _) => self.opened_at,
+            // A probe is in flight; give it one cooldown before allowing another
+            (BreakerState::HalfOpen, Some(started)) => started,
+        };
+        let elapsed = now.duration_since(since);
+        (elapsed < config.cooldown).then(|| config.cooldown - elapsed)
+    }
+
+    fn unavailable(&self, tool: &str, wait: Duration) -> Error {
+        let reason = if self.forced == Some(BreakerOverride::ForceOpen) {
+            "disabled by operator".to_string()
+        } else {
+            format!(
+                "{} recent failures, retry after {}s",
+                self.consecutive.max(self.window_failures() as u32),
+                wait.as_secs_f64().ceil().max(1.0) as u64
+            )
+        };
+        Error::ToolUnavailable {
+            tool_name: tool.to_string(),
+            reason,
+        }
+    }
+}
+
+struct Cell {
+    config: BreakerConfig,
+    /// Set by [`ToolBreakers::configure`]; unconfigured cells follow the default
+    configured: bool,
+    breaker: Breaker,
+}
+
+/// One tool's breaker
+///
+/// Held next to the tool in [`ToolSet`](super::ToolSet) so a call only ever
+/// locks its own tool's state, never the registry.
+pub(crate) struct ToolBreaker {
+    tool: String,
+    cell: Mutex<Cell>,
+}
+
+impl ToolBreaker {
+    fn new(tool: &str, config: BreakerConfig) -> Self {
+        Self {
+            tool: tool.to_string(),
+            cell: Mutex::new(Cell {
+                config,
+                configured: false,
+                breaker: Breaker::new(),
+            }),
+        }
+    }
+
+    /// The error a call would be short-circuited with right now, if any
+    pub(crate) fn short_circuit(&self) -> Option<Error> {
+        let cell = self.cell.lock();
+        let wait = cell.breaker.wait(&cell.config, Instant::now())?;
+        Some(cell.breaker.unavailable(&self.tool, wait))
+    }
+
+    /// Admit a call, claiming the probe if the breaker is due one
+    pub(crate) fn acquire(&self) -> Result<(), Error> {
+        let mut cell = self.cell.lock();
+        let Cell { config, breaker, .. } = &mut *cell;
+        let tool = self.tool.as_str();
+        let now = Instant::now();
+        if let Some(wait) = breaker.wait(config, now) {
+            return Err(breaker.unavailable(tool, wait));
+        }
+        if breaker.forced.is_none() && !config.never_open && breaker.state != BreakerState::Closed {
+            info!(tool, "Tool breaker half-open, letting a probe call through");
+            breaker.state = BreakerState::HalfOpen;
+            breaker.probe_started = Some(now);
+        }
+        Ok(())
+    }
+
+    /// Record how a call ended
+    pub(crate) fn record(&self, outcome: CallOutcome) {
+        let mut cell = self.cell.lock();
+        let Cell { config, breaker, .. } = &mut *cell;
+        let tool = self.tool.as_str();
+        let now = Instant::now();
+        breaker.prune(config, now);
+
+        match outcome {
+            CallOutcome::InvalidArguments => {
+                // Says nothing about the tool; free the probe for the next call
+                breaker.probe_started = None;
+            }
+            CallOutcome::Success => {
+                breaker.consecutive = 0;
+                breaker.window.push_back((now, true));
+                if breaker.state != BreakerState::Closed {
+                    info!(tool, "Tool breaker probe succeeded, closing");
+                    breaker.state = BreakerState::Closed;
+                    breaker.probe_started = None;
+                    breaker.window.clear();
+                }
+            }
+            CallOutcome::Failure => {
+                breaker.consecutive += 1;
+                breaker.window.push_back((now, false));
+                match breaker.state {
+                    BreakerState::HalfOpen => {
+                        warn!(tool, "Tool breaker probe failed, reopening");
+                        breaker.open(now);
+                    }
+                    BreakerState::Closed if !config.never_open && breaker.should_trip(config) => {
+                        warn!(
+                            tool,
+                            failures = breaker.consecutive,
+                            "Tool failing repeatedly, opening breaker"
+                        );
+                        breaker.open(now);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn set_override(&self, forced: Option<BreakerOverride>) {
+        warn!(tool = %self.tool, ?forced, "Tool breaker override set");
+        self.cell.lock().breaker.forced = forced;
+    }
+
+    fn status(&self) -> BreakerStatus {
+        let cell = self.cell.lock();
+        let Cell { config, breaker, .. } = &*cell;
+        let now = Instant::now();
+        let window: Vec<_> = breaker
+            .window
+            .iter()
+            .filter(|(at, _)| now.duration_since(*at) <= config.window)
+            .collect();
+        BreakerStatus {
+            tool: self.tool.clone(),
+            state: breaker.state,
+            forced: breaker.forced,
+            consecutive_failures: breaker.consecutive,
+            window_calls: window.len(),
+            window_failures: window.iter().filter(|(_, ok)| !ok).count(),
+            trips: breaker.trips,
+            retry_after_secs: (breaker.state == BreakerState::Open)
+                .then(|| breaker.wait(config, now))
+                .flatten()
+                .map(|d| d.as_secs()),
+        }
+    }
+}
+
+struct Registry {
+    default: BreakerConfig,
+    breakers: HashMap<String, Arc<ToolBreaker>>,
+}
+
+/// Circuit breakers for every tool in a [`ToolSet`](super::ToolSet), shared between clones
+///
+/// The registry lock is only taken by the name-based methods here (admin and
+/// introspection paths); [`ToolSet::call`](super::ToolSet::call) goes straight
+/// to the tool's own breaker.
+#[derive(Clone)]
+pub struct ToolBreakers {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl Default for ToolBreakers {
+    fn default() -> Self {
+        Self::new(BreakerConfig::default())
+    }
+}
+
+impl ToolBreakers {
+    /// Breakers using `default` for every tool without its own config
+    pub fn new(default: BreakerConfig) -> Self {
+        Self {
+            registry: Arc::new(Mutex::new(Registry {
+                default,
+                breakers: HashMap::new(),
+            })),
+        }
+    }
+
+    /// `tool`'s breaker, created with the default config if it has none yet
+    pub(crate) fn breaker(&self, tool: &str) -> Arc<ToolBreaker> {
+        let mut registry = self.registry.lock();
+        let default = registry.default.clone();
+        registry
+            .breakers
+            .entry(tool.to_string())
+            .or_insert_with(|| Arc::new(ToolBreaker::new(tool, default)))
+            .clone()
+    }
+
+    fn find(&self, tool: &str) -> Option<Arc<ToolBreaker>> {
+        self.registry.lock().breakers.get(tool).cloned()
+    }
+
+    /// Replace the default thresholds
+    pub fn set_default(&self, config: BreakerConfig) {
+        let mut registry = self.registry.lock();
+        for breaker in registry.breakers.values() {
+            let mut cell = breaker.cell.lock();
+            if !cell.configured {
+                cell.config = config.clone();
+            }
+        }
+        registry.default = config;
+    }
+
+    /// Use `config` for `tool`
+    pub fn configure(&self, tool: impl Into<String>, config: BreakerConfig) {
+        let breaker = self.breaker(&tool.into());
+        let mut cell = breaker.cell.lock();
+        cell.config = config;
+        cell.configured = true;
+    }
+
+    /// The error a call to `tool` would be short-circuited with right now, if any
+    ///
+    /// Unlike [`acquire`](Self::acquire) this does not claim the half-open probe.
+    pub fn short_circuit(&self, tool: &str) -> Option<Error> {
+        self.find(tool)?.short_circuit()
+    }
+
+    /// Admit a call to `tool`, claiming the probe if the breaker is due one
+    pub fn acquire(&self, tool: &str) -> Result<(), Error> {
+        self.find(tool).map_or(Ok(()), |b| b.acquire())
+    }
+
+    /// Record how a call to `tool` ended
+    pub fn record(&self, tool: &str, outcome: CallOutcome) {
+        self.breaker(tool).record(outcome);
+    }
+
+    /// Pin `tool`'s breaker open until [`reset`](Self::reset)
+    pub fn force_open(&self, tool: &str) {
+        self.breaker(tool).set_override(Some(BreakerOverride::ForceOpen));
+    }
+
+    /// Pin `tool`'s breaker closed until [`reset`](Self::reset)
+    pub fn force_close(&self, tool: &str) {
+        self.breaker(tool).set_override(Some(BreakerOverride::ForceClosed));
+    }
+
+    /// Clear any override and failure history for `tool`
+    pub fn reset(&self, tool: &str) {
+        if let Some(breaker) = self.find(tool) {
+            breaker.cell.lock().breaker = Breaker::new();
+        }
+    }
+
+    /// Status of `tool`'s breaker (`None` if the tool is unknown)
+    pub fn status(&self, tool: &str) -> Option<BreakerStatus> {
+        Some(self.find(tool)?.status())
+    }
+
+    /// Status of every tracked breaker, sorted by tool name
+    pub fn snapshot(&self) -> Vec<BreakerStatus> {
+        let mut breakers: Vec<_> = self.registry.lock().breakers.values().cloned().collect();
+        breakers.sort_by(|a, b| a.tool.cmp(&b.tool));
+        breakers.iter().map(|b| b.status()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skills::tool::{Tool, ToolDefinition, ToolSet};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Fails while `failing` is set; `{"bad": true}` is an argument error
+    struct FlakyTool {
+        failing: Arc<AtomicBool>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for FlakyTool {
+        fn name(&self) -> String {
+            "quote".to_string()
+        }
+
+        async fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: "quote".to_string(),
+                description: "Fetch a quote".to_string(),
+                parameters: serde_json::json!({ "type": "object" }),
+                is_verified: true,
+                ..Default::default()
+            }
+        }
+
+        async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if arguments.contains("bad") {
+                return Err(Error::ToolArguments {
+                    tool_name: "quote".to_string(),
+                    message: "bad".to_string(),
+                }
+                .into());
+            }
+            if self.failing.load(Ordering::SeqCst) {
+                anyhow::bail!("upstream timeout");
+            }
+            Ok("42".to_string())
+        }
+    }
+
+    fn toolset(config: BreakerConfig) -> (ToolSet, Arc<AtomicBool>, Arc<AtomicUsize>) {
+        let failing = Arc::new(AtomicBool::new(true));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut tools = ToolSet::new();
+        tools.add(FlakyTool {
+            failing: failing.clone(),
+            calls: calls.clone(),
+        });
+        tools.breakers().set_default(config);
+        (tools, failing, calls)
+    }
+
+    fn fast() -> BreakerConfig {
+        BreakerConfig {
+            consecutive_failures: 3,
+            cooldown: Duration::from_millis(40),
+            ..BreakerConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_half_open_close() {
+        let (tools, failing, calls) = toolset(fast());
+
+        for _ in 0..3 {
+            assert!(tools.call("quote", "{}").await.is_err());
+        }
+        let status = tools.breakers().status("quote").unwrap();
+        assert_eq!((status.state, status.trips), (BreakerState::Open, 1));
+
+        // Short-circuited without reaching the tool
+        let err = tools.call("quote", "{}").await.unwrap_err().to_string();
+        assert!(err.contains("temporarily unavailable: quote (3 recent failures, retry after 1s)"));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // Failed probe reopens
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(tools
+            .call("quote", "{}")
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("upstream"));
+        assert_eq!(tools.breakers().status("quote").unwrap().trips, 2);
+        assert!(tools.breakers().short_circuit("quote").is_some());
+
+        // Successful probe closes
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        failing.store(false, Ordering::SeqCst);
+        assert_eq!(tools.call("quote", "{}").await.unwrap(), "42");
+        let status = tools.breakers().status("quote").unwrap();
+        assert_eq!(
+            (status.state, status.consecutive_failures),
+            (BreakerState::Closed, 0)
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_argument_errors_not_counted() {
+        let (tools, _failing, _) = toolset(fast());
+
+        for _ in 0..10 {
+            assert!(tools.call("quote", r#"{"bad": true}"#).await.is_err());
+        }
+        assert!(tools.breakers().short_circuit("quote").is_none());
+        assert_eq!(
+            tools
+                .breakers()
+                .status("quote")
+                .unwrap()
+                .consecutive_failures,
+            0
+        );
+
+        // Failures interleaved with argument errors still add up
+        for _ in 0..2 {
+            assert!(tools.call("quote", "{}").await.is_err());
+            assert!(tools.call("quote", r#"{"bad": true}"#).await.is_err());
+        }
+        assert!(tools.breakers().short_circuit("quote").is_none());
+        assert!(tools.call("quote", "{}").await.is_err());
+        assert!(tools.breakers().short_circuit("quote").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_overrides_and_never_open() {
+        let (tools, failing, _) = toolset(fast());
+        tools
+            .breakers()
+            .configure("quote", BreakerConfig::never_open());
+        for _ in 0..10 {
+            assert!(tools.call("quote", "{}").await.is_err());
+        }
+        assert!(tools.breakers().short_circuit("quote").is_none());
+
+        failing.store(false, Ordering::SeqCst);
+        tools.breakers().force_open("quote");
+        let err = tools.call("quote", "{}").await.unwrap_err().to_string();
+        assert!(err.contains("(disabled by operator)"));
+        assert_eq!(
+            tools.breakers().snapshot()[0].forced,
+            Some(BreakerOverride::ForceOpen)
+        );
+
+        tools.breakers().reset("quote");
+        assert_eq!(tools.call("quote", "{}").await.unwrap(), "42");
+    }
+}
@@ -0,0 +1,797 @@
+//! Decimal-exact calculator for trading math
+//!
+//! Evaluates arithmetic with `rust_decimal` end to end so results such as
+//! `0.1 + 0.2` are exact, and converts between crypto denominations.
+
+use async_trait::async_trait;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::error::Error;
+use crate::skills::tool::{parse_args, Tool, ToolDefinition, ToolExample};
+
+/// Name the calculator is registered under
+pub const CALCULATOR_TOOL: &str = "calculator";
+
+/// Decimals of SOL (lamports)
+const SOL_DECIMALS: u32 = 9;
+/// Decimals of ETH (wei)
+const ETH_DECIMALS: u32 = 18;
+/// Decimals of gwei relative to wei
+const GWEI_DECIMALS: u32 = 9;
+
+/// Why an expression could not be evaluated
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CalcError {
+    /// The expression is malformed
+    #[error("syntax error at position {position}: {message}")]
+    Syntax {
+        /// Byte offset in the expression
+        position: usize,
+        /// What was wrong
+        message: String,
+    },
+    /// A variable is not bound in `vars`
+    #[error("unknown variable '{0}'")]
+    UnknownVariable(String),
+    /// A function name is not supported
+    #[error("unknown function '{0}'")]
+    UnknownFunction(String),
+    /// A function got the wrong number or kind of arguments
+    #[error("invalid arguments to {function}: {message}")]
+    InvalidArguments {
+        /// Function name
+        function: String,
+        /// What was wrong
+        message: String,
+    },
+    /// Division or remainder by zero
+    #[error("division by zero")]
+    DivisionByZero,
+    /// The result does not fit in a decimal
+    #[error("overflow in {0}")]
+    Overflow(String),
+    /// The value cannot be represented without rounding
+    #[error("precision loss: {0}")]
+    PrecisionLoss(String),
+}
+
+/// Result of evaluating an expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    /// Exact decimal value (trailing zeros removed)
+    pub value: Decimal,
+    /// False when a division had to round to 28 decimal digits
+    pub exact: bool,
+}
+
+/// Evaluate `expr` with `vars` bound
+///
+/// Supports `+ - * /`, `%` (remainder, or percent when postfix), `^` with integer
+/// exponents, parentheses, rounding functions and denomination helpers.
+pub fn evaluate(expr: &str, vars: &BTreeMap<String, Decimal>) -> Result<Evaluation, CalcError> {
+    let tokens = tokenize(expr)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        vars,
+        exact: true,
+    };
+    let value = parser.expression()?;
+    if let Some((position, token)) = parser.tokens.get(parser.pos) {
+        return Err(CalcError::Syntax {
+            position: *position,
+            message: format!("unexpected {}", token),
+        });
+    }
+    Ok(Evaluation {
+        value: value.normalize(),
+        exact: parser.exact,
+    })
+}
+
+/// Parse a decimal literal, rejecting inputs that would be rounded
+pub fn parse_decimal(text: &str) -> Result<Decimal, CalcError> {
+    let text = text.trim().replace('_', "");
+    let parsed = if text.contains(['e', 'E']) {
+        Decimal::from_scientific(&text)
+    } else {
+        Decimal::from_str_exact(&text)
+    };
+    parsed.map_err(|e| match e {
+        rust_decimal::Error::Underflow => {
+            CalcError::PrecisionLoss(format!("'{}' has more digits than a decimal holds", text))
+        }
+        rust_decimal::Error::ExceedsMaximumPossibleValue
+        | rust_decimal::Error::LessThanMinimumPossibleValue => {
+            CalcError::Overflow(format!("literal '{}'", text))
+        }
+        other => CalcError::Syntax {
+            position: 0,
+            message: format!("invalid number '{}': {}", text, other),
+        },
+    })
+}
+
+/// Format with thousands separators, e.g. `1234567.5` -> `1,234,567.5`
+pub fn format_human(value: Decimal) -> String {
+    let text = value.normalize().to_string();
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", text.as_str()),
+    };
+    let (int_part, frac_part) = match digits.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (digits, None),
+    };
+
+    let mut grouped = String::with_capacity(int_part.len() + int_part.len() / 3);
+    for (i, c) in int_part.chars().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    match frac_part {
+        Some(f) => format!("{}{}.{}", sign, grouped, f),
+        None => format!("{}{}", sign, grouped),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Decimal),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "number {}", n),
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Op(c) => write!(f, "'{}'", c),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+        }
+    }
+}
+
+fn tokenize(expr: &str) -> Result<Vec<(usize, Token)>, CalcError> {
+    let bytes = expr.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i] as char;
+        let start = i;
+        match c {
+            c if c.is_ascii_whitespace() => i += 1,
+            '0'..='9' | '.' => {
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_digit() || bytes[i] == b'.' || bytes[i] == b'_')
+                {
+                    i += 1;
+                }
+                // Scientific notation: 1e9, 2.5E-3
+                if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+                    let mut j = i + 1;
+                    if j < bytes.len() && (bytes[j] == b'+' || bytes[j] == b'-') {
+                        j += 1;
+                    }
+                    if j < bytes.len() && bytes[j].is_ascii_digit() {
+                        while j < bytes.len() && bytes[j].is_ascii_digit() {
+                            j += 1;
+                        }
+                        i = j;
+                    }
+                }
+                let value = parse_decimal(&expr[start..i]).map_err(|e| match e {
+                    CalcError::Syntax { message, .. } => CalcError::Syntax {
+                        position: start,
+                        message,
+                    },
+                    other => other,
+                })?;
+                tokens.push((start, Token::Number(value)));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                tokens.push((start, Token::Ident(expr[start..i].to_string())));
+            }
+            '+' | '-' | '*' | '/' | '%' | '^' => {
+                tokens.push((start, Token::Op(c)));
+                i += 1;
+            }
+            '(' => {
+                tokens.push((start, Token::LParen));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((start, Token::RParen));
+                i += 1;
+            }
+            ',' => {
+                tokens.push((start, Token::Comma));
+                i += 1;
+            }
+            other => {
+                return Err(CalcError::Syntax {
+                    position: start,
+                    message: format!("unexpected character '{}'", other),
+                })
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    vars: &'a BTreeMap<String, Decimal>,
+    exact: bool,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map(|(p, _)| *p)
+            .or_else(|| self.tokens.last().map(|(p, _)| p + 1))
+            .unwrap_or(0)
+    }
+
+    fn syntax(&self, message: impl Into<String>) -> CalcError {
+        CalcError::Syntax {
+            position: self.position(),
+            message: message.into(),
+        }
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), CalcError> {
+        if self.peek() == Some(&token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.syntax(format!("expected {}", token)))
+        }
+    }
+
+    /// Whether the token after `offset` can start an operand (decides `%` remainder vs percent)
+    fn operand_follows(&self, offset: usize) -> bool {
+        matches!(
+            self.tokens.get(self.pos + offset).map(|(_, t)| t),
+            Some(Token::Number(_) | Token::Ident(_) | Token::LParen | Token::Op('-' | '+'))
+        )
+    }
+
+    fn expression(&mut self) -> Result<Decimal, CalcError> {
+        let mut value = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = self.term()?;
+            value = if op == '+' {
+                value
+                    .checked_add(rhs)
+                    .ok_or_else(|| CalcError::Overflow("addition".into()))?
+            } else {
+                value
+                    .checked_sub(rhs)
+                    .ok_or_else(|| CalcError::Overflow("subtraction".into()))?
+            };
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<Decimal, CalcError> {
+        let mut value = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = self.unary()?;
+            value = match op {
+                '*' => value
+                    .checked_mul(rhs)
+                    .ok_or_else(|| CalcError::Overflow("multiplication".into()))?,
+                '/' => self.divide(value, rhs)?,
+                _ => {
+                    if rhs.is_zero() {
+                        return Err(CalcError::DivisionByZero);
+                    }
+                    value
+                        .checked_rem(rhs)
+                        .ok_or_else(|| CalcError::Overflow("remainder".into()))?
+                }
+            };
+        }
+        Ok(value)
+    }
+
+    fn divide(&mut self, lhs: Decimal, rhs: Decimal) -> Result<Decimal, CalcError> {
+        if rhs.is_zero() {
+            return Err(CalcError::DivisionByZero);
+        }
+        let quotient = lhs
+            .checked_div(rhs)
+            .ok_or_else(|| CalcError::Overflow("division".into()))?;
+        if quotient.checked_mul(rhs) != Some(lhs) {
+            self.exact = false;
+        }
+        Ok(quotient)
+    }
+
+    fn unary(&mut self) -> Result<Decimal, CalcError> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                Ok(-self.unary()?)
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<Decimal, CalcError> {
+        let base = self.postfix()?;
+        if self.peek() != Some(&Token::Op('^')) {
+            return Ok(base);
+        }
+        self.pos += 1;
+        // Right-associative: 2^3^2 = 2^(3^2)
+        let exponent = self.unary()?;
+        if !exponent.is_integer() {
+            return Err(CalcError::InvalidArguments {
+                function: "^".into(),
+                message: format!("exponent must be an integer, got {}", exponent),
+            });
+        }
+        let n = exponent
+            .to_i64()
+            .ok_or_else(|| CalcError::Overflow("exponent".into()))?;
+        self.powi(base, n)
+    }
+
+    fn powi(&mut self, base: Decimal, exponent: i64) -> Result<Decimal, CalcError> {
+        let mut result = Decimal::ONE;
+        let mut factor = base;
+        let mut remaining = exponent.unsigned_abs();
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = result
+                    .checked_mul(factor)
+                    .ok_or_else(|| CalcError::Overflow("exponentiation".into()))?;
+            }
+            remaining >>= 1;
+            if remaining > 0 {
+                factor = factor
+                    .checked_mul(factor)
+                    .ok_or_else(|| CalcError::Overflow("exponentiation".into()))?;
+            }
+        }
+        if exponent < 0 {
+            result = self.divide(Decimal::ONE, result)?;
+        }
+        Ok(result)
+    }
+
+    fn postfix(&mut self) -> Result<Decimal, CalcError> {
+        let mut value = self.primary()?;
+        // `5%` is a percentage; `10 % 3` (operand follows) is a remainder
+        while self.peek() == Some(&Token::Op('%')) && !self.operand_follows(1) {
+            self.pos += 1;
+            value = self.divide(value, Decimal::ONE_HUNDRED)?;
+        }
+        Ok(value)
+    }
+
+    fn primary(&mut self) -> Result<Decimal, CalcError> {
+        let Some(token) = self.peek().cloned() else {
+            return Err(self.syntax("unexpected end of expression"));
+        };
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Ok(n),
+            Token::LParen => {
+                let value = self.expression()?;
+                self.expect(Token::RParen)?;
+                Ok(value)
+            }
+            Token::Ident(name) if self.peek() == Some(&Token::LParen) => {
+                self.pos += 1;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    loop {
+                        args.push(self.expression()?);
+                        if self.peek() == Some(&Token::Comma) {
+                            self.pos += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                self.expect(Token::RParen)?;
+                self.call(&name, &args)
+            }
+            Token::Ident(name) => self
+                .vars
+                .get(&name)
+                .copied()
+                .ok_or(CalcError::UnknownVariable(name)),
+            other => {
+                self.pos -= 1;
+                Err(self.syntax(format!("unexpected {}", other)))
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Decimal]) -> Result<Decimal, CalcError> {
+        let invalid = |message: &str| CalcError::InvalidArguments {
+            function: name.to_string(),
+            message: message.to_string(),
+        };
+        let dp = |index: usize| -> Result<u32, CalcError> {
+            match args.get(index) {
+                None => Ok(0),
+                Some(d) if d.is_integer() && !d.is_sign_negative() && *d <= Decimal::from(28) => {
+                    Ok(d.to_u32().unwrap_or(0))
+                }
+                Some(_) => Err(invalid("decimal places must be an integer from 0 to 28")),
+            }
+        };
+        let rounding = |strategy: RoundingStrategy| -> Result<Decimal, CalcError> {
+            match args {
+                [x] | [x, _] => Ok(x.round_dp_with_strategy(dp(1)?, strategy)),
+                _ => Err(invalid("expected (x) or (x, dp)")),
+            }
+        };
+        let one = || -> Result<Decimal, CalcError> {
+            match args {
+                [x] => Ok(*x),
+                _ => Err(invalid("expected exactly one argument")),
+            }
+        };
+
+        match name {
+            "round" => rounding(RoundingStrategy::MidpointAwayFromZero),
+            "round_even" | "round_bankers" => rounding(RoundingStrategy::MidpointNearestEven),
+            "floor" => rounding(RoundingStrategy::ToNegativeInfinity),
+            "ceil" => rounding(RoundingStrategy::ToPositiveInfinity),
+            "trunc" => rounding(RoundingStrategy::ToZero),
+            "abs" => Ok(one()?.abs()),
+            "min" | "max" => {
+                let mut iter = args.iter().copied();
+                let first = iter
+                    .next()
+                    .ok_or_else(|| invalid("expected at least one argument"))?;
+                Ok(iter.fold(first, |acc, x| {
+                    if name == "min" {
+                        acc.min(x)
+                    } else {
+                        acc.max(x)
+                    }
+                }))
+            }
+            "sol_to_lamports" => to_base_units(one()?, SOL_DECIMALS, "lamports"),
+            "lamports_to_sol" => from_base_units(one()?, SOL_DECIMALS, "lamports"),
+            "eth_to_wei" => to_base_units(one()?, ETH_DECIMALS, "wei"),
+            "wei_to_eth" => from_base_units(one()?, ETH_DECIMALS, "wei"),
+            "eth_to_gwei" => scale(one()?, (ETH_DECIMALS - GWEI_DECIMALS) as i32),
+            "gwei_to_eth" => scale(one()?, -((ETH_DECIMALS - GWEI_DECIMALS) as i32)),
+            "gwei_to_wei" => to_base_units(one()?, GWEI_DECIMALS, "wei"),
+            "wei_to_gwei" => from_base_units(one()?, GWEI_DECIMALS, "wei"),
+            "to_base_units" | "from_base_units" => {
+                let [x, _] = args else {
+                    return Err(invalid("expected (amount, decimals)"));
+                };
+                let decimals = dp(1)?;
+                if name == "to_base_units" {
+                    to_base_units(*x, decimals, "base units")
+                } else {
+                    from_base_units(*x, decimals, "base units")
+                }
+            }
+            other => Err(CalcError::UnknownFunction(other.to_string())),
+        }
+    }
+}
+
+/// Multiply by `10^exponent` without rounding
+fn scale(value: Decimal, exponent: i32) -> Result<Decimal, CalcError> {
+    let factor = Decimal::from_i128_with_scale(10i128.pow(exponent.unsigned_abs()), 0);
+    if exponent >= 0 {
+        value
+            .checked_mul(factor)
+            .ok_or_else(|| CalcError::Overflow(format!("scaling {} by 10^{}", value, exponent)))
+    } else {
+        let scaled = value
+            .checked_div(factor)
+            .ok_or_else(|| CalcError::Overflow(format!("scaling {} by 10^{}", value, exponent)))?;
+        if scaled.checked_mul(factor) != Some(value) {
+            return Err(CalcError::PrecisionLoss(format!(
+                "{} has too many digits to scale by 10^{}",
+                value, exponent
+            )));
+        }
+        Ok(scaled)
+    }
+}
+
+/// Whole-unit amount to integer base units; fractional base units are an error
+fn to_base_units(amount: Decimal, decimals: u32, unit: &str) -> Result<Decimal, CalcError> {
+    let base = scale(amount, decimals as i32)?;
+    if !base.is_integer() {
+        return Err(CalcError::PrecisionLoss(format!(
+            "{} is not a whole number of {} ({} decimals)",
+            amount, unit, decimals
+        )));
+    }
+    Ok(base)
+}
+
+/// Integer base units to a whole-unit amount
+fn from_base_units(amount: Decimal, decimals: u32, unit: &str) -> Result<Decimal, CalcError> {
+    if !amount.is_integer() {
+        return Err(CalcError::PrecisionLoss(format!(
+            "{} {} is not a whole number",
+            amount, unit
+        )));
+    }
+    scale(amount, -(decimals as i32))
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct CalcArgs {
+    /// Expression to evaluate, e.g. "size * price * (1 - fee)"
+    expr: String,
+    /// Variables referenced by the expression; pass numbers as strings to keep them exact
+    #[serde(default)]
+    vars: BTreeMap<String, Value>,
+}
+
+/// Deterministic decimal calculator and denomination converter
+#[derive(Debug, Clone, Default)]
+pub struct CalculatorTool;
+
+impl CalculatorTool {
+    /// Create a calculator tool
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn bind(&self, vars: BTreeMap<String, Value>) -> Result<BTreeMap<String, Decimal>, Error> {
+        vars.into_iter()
+            .map(|(name, value)| {
+                let text = match &value {
+                    Value::String(s) => s.clone(),
+                    Value::Number(n) => n.to_string(),
+                    other => {
+                        return Err(Error::ToolArguments {
+                            tool_name: self.name(),
+                            message: format!(
+                                "vars.{}: expected a decimal string, received {}",
+                                name, other
+                            ),
+                        })
+                    }
+                };
+                let decimal = parse_decimal(&text).map_err(|e| Error::ToolArguments {
+                    tool_name: self.name(),
+                    message: format!("vars.{}: {}", name, e),
+                })?;
+                Ok((name, decimal))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Tool for CalculatorTool {
+    fn name(&self) -> String {
+        CALCULATOR_TOOL.to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Exact decimal arithmetic. Use for every calculation (P&L, position sizing, fees, \
+                unit conversions) instead of computing numbers yourself. Operators: + - * / ^ (integer \
+                exponent), % (remainder, or percent when postfix: 5%). Functions: round(x, dp), \
+                round_even(x, dp), floor(x, dp), ceil(x, dp), trunc(x, dp), abs, min, max, \
+                sol_to_lamports, lamports_to_sol, eth_to_wei, wei_to_eth, eth_to_gwei, gwei_to_eth, \
+                gwei_to_wei, wei_to_gwei, to_base_units(x, decimals), from_base_units(x, decimals)."
+                .to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "expr": {
+                        "type": "string",
+                        "description": "Expression to evaluate, e.g. \"size * price * (1 - fee)\""
+                    },
+                    "vars": {
+                        "type": "object",
+                        "additionalProperties": { "type": ["string", "number"] },
+                        "description": "Variables used in expr; pass values as strings to keep them exact"
+                    }
+                },
+                "required": ["expr"]
+            }),
+            parameters_ts: Some(
+                "interface CalculatorArgs {\n  expr: string; // e.g. \"size * price * (1 - fee)\"\n  vars?: Record<string, string>; // Decimal strings, e.g. { \"size\": \"12.5\" }\n}"
+                    .to_string(),
+            ),
+            is_verified: true,
+            examples: vec![
+                ToolExample::new(
+                    "Notional after fees",
+                    serde_json::json!({
+                        "expr": "size * price * (1 - fee)",
+                        "vars": { "size": "12.5", "price": "183.42", "fee": "0.0025" }
+                    }),
+                )
+                .with_result("{\"result\": \"2287.018125\", \"formatted\": \"2,287.018125\"}"),
+                ToolExample::new(
+                    "Percentage P&L rounded to 2 places",
+                    serde_json::json!({ "expr": "round((exit - entry) / entry * 100, 2)", "vars": { "entry": "142.10", "exit": "151.35" } }),
+                ),
+            ],
+            side_effect_free: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        let args: CalcArgs = parse_args(&self.name(), arguments)?;
+        let vars = self.bind(args.vars)?;
+        let evaluation = evaluate(&args.expr, &vars)
+            .map_err(|e| Error::tool_execution(self.name(), e.to_string()))?;
+
+        let mut output = serde_json::json!({
+            "expr": args.expr,
+            "result": evaluation.value.to_string(),
+            "formatted": format_human(evaluation.value),
+        });
+        if !evaluation.exact {
+            output["exact"] = Value::Bool(false);
+            output["note"] =
+                Value::String("a division was rounded to 28 significant digits".to_string());
+        }
+        Ok(output.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn eval(expr: &str) -> Result<Decimal, CalcError> {
+        evaluate(expr, &BTreeMap::new()).map(|e| e.value)
+    }
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_precedence_and_exactness() {
+        assert_eq!(eval("2 + 3 * 4").unwrap(), dec("14"));
+        assert_eq!(eval("(2 + 3) * 4").unwrap(), dec("20"));
+        assert_eq!(eval("-2 ^ 2").unwrap(), dec("-4"));
+        assert_eq!(eval("2 ^ 3 ^ 2").unwrap(), dec("512"));
+        assert_eq!(eval("2 ^ -2").unwrap(), dec("0.25"));
+        assert_eq!(eval("10 % 3").unwrap(), dec("1"));
+        assert_eq!(eval("200 * 5%").unwrap(), dec("10"));
+        assert_eq!(eval("100 - 10 / 4").unwrap(), dec("97.5"));
+
+        // Classic float traps are exact
+        assert_eq!(eval("0.1 + 0.2").unwrap(), dec("0.3"));
+        assert_eq!(eval("1.1 * 1.1").unwrap(), dec("1.21"));
+        assert_eq!(eval("0.3 - 0.1").unwrap(), dec("0.2"));
+
+        let mut vars = BTreeMap::new();
+        vars.insert("size".to_string(), dec("12.5"));
+        vars.insert("price".to_string(), dec("183.42"));
+        vars.insert("fee".to_string(), dec("0.0025"));
+        let result = evaluate("size * price * (1 - fee)", &vars).unwrap();
+        assert_eq!(result.value, dec("2287.018125"));
+        assert!(result.exact);
+        assert_eq!(format_human(result.value), "2,287.018125");
+
+        assert_eq!(eval("round(2.345, 2)").unwrap(), dec("2.35"));
+        assert_eq!(eval("round_even(2.345, 2)").unwrap(), dec("2.34"));
+        assert_eq!(eval("floor(-1.5)").unwrap(), dec("-2"));
+        assert_eq!(eval("ceil(1.01, 1)").unwrap(), dec("1.1"));
+        assert!(!evaluate("1 / 3", &BTreeMap::new()).unwrap().exact);
+    }
+
+    #[test]
+    fn test_unit_conversions() {
+        assert_eq!(eval("sol_to_lamports(1.5)").unwrap(), dec("1500000000"));
+        assert_eq!(eval("lamports_to_sol(1500000000)").unwrap(), dec("1.5"));
+        assert_eq!(eval("eth_to_wei(0.000000001)").unwrap(), dec("1000000000"));
+        assert_eq!(eval("wei_to_eth(1)").unwrap(), dec("0.000000000000000001"));
+        assert_eq!(eval("eth_to_gwei(0.02)").unwrap(), dec("20000000"));
+        assert_eq!(eval("gwei_to_eth(20000000)").unwrap(), dec("0.02"));
+        assert_eq!(eval("wei_to_gwei(gwei_to_wei(42))").unwrap(), dec("42"));
+        assert_eq!(
+            eval("to_base_units(12.345678, 6)").unwrap(),
+            dec("12345678")
+        );
+        assert_eq!(
+            eval("from_base_units(12345678, 6)").unwrap(),
+            dec("12.345678")
+        );
+        assert_eq!(format_human(dec("-1234567.50")), "-1,234,567.5");
+    }
+
+    #[tokio::test]
+    async fn test_error_cases() {
+        assert_eq!(eval("1 / 0"), Err(CalcError::DivisionByZero));
+        assert_eq!(eval("5 % (2 - 2)"), Err(CalcError::DivisionByZero));
+        assert!(matches!(
+            eval("79228162514264337593543950335 * 2"),
+            Err(CalcError::Overflow(_))
+        ));
+        assert!(matches!(eval("10 ^ 40"), Err(CalcError::Overflow(_))));
+        assert!(matches!(
+            eval("sol_to_lamports(0.0000000001)"),
+            Err(CalcError::PrecisionLoss(_))
+        ));
+        assert!(matches!(
+            eval("lamports_to_sol(1.5)"),
+            Err(CalcError::PrecisionLoss(_))
+        ));
+        assert!(matches!(
+            eval("0.12345678901234567890123456789"),
+            Err(CalcError::PrecisionLoss(_))
+        ));
+        assert!(matches!(
+            eval("2 ^ 0.5"),
+            Err(CalcError::InvalidArguments { .. })
+        ));
+        assert_eq!(
+            eval("qty * 2"),
+            Err(CalcError::UnknownVariable("qty".into()))
+        );
+        assert_eq!(
+            eval("sqrt(4)"),
+            Err(CalcError::UnknownFunction("sqrt".into()))
+        );
+        assert!(matches!(eval("(1 + 2"), Err(CalcError::Syntax { .. })));
+        assert!(matches!(
+            eval("1 + $"),
+            Err(CalcError::Syntax { position: 4, .. })
+        ));
+
+        let tool = CalculatorTool::new();
+        assert!(tool.definition().await.side_effect_free);
+        let out = tool
+            .call(r#"{"expr": "a + b", "vars": {"a": "0.1", "b": 0.2}}"#)
+            .await
+            .unwrap();
+        let out: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(out["result"], "0.3");
+
+        let err = tool.call(r#"{"expr": "1 / 0"}"#).await.unwrap_err();
+        let err = err.downcast::<Error>().unwrap();
+        assert!(
+            matches!(err, Error::ToolExecution { ref message, .. } if message == "division by zero")
+        );
+    }
+}
@@ -6,8 +6,7 @@ use schemars::JsonSchema;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use crate::skills::tool::Tool;
-use crate::error::Error;
+use crate::skills::tool::{parse_args, Tool};
 use crate::skills::capabilities::Sidecar;
 
 /// Arguments for the Code Interpreter tool
@@ -51,17 +50,13 @@ impl Tool for CodeInterpreter {
                 "required": ["code"]
             }),
             parameters_ts: Some("interface CodeArgs {\n  code: string; // Python code to execute\n}".to_string()),
-            is_binary: false,
             is_verified: true,
+            ..Default::default()
         }
     }
 
     async fn call(&self, arguments: &str) -> anyhow::Result<String> {
-        let args: CodeArgs = serde_json::from_str(arguments)
-            .map_err(|e| Error::ToolArguments {
-                tool_name: self.name(),
-                message: format!("Invalid JSON arguments: {}", e),
-            })?;
+        let args: CodeArgs = parse_args(&self.name(), arguments)?;
 
         let mut sidecar = self.sidecar.lock().await;
         let result = sidecar.execute(args.code).await?;
@@ -0,0 +1,278 @@
+//! Schema-driven repair of loosely typed tool arguments
+//!
+//! Models often send `"amount": "100"` for a number, `"true"` for a boolean, a
+//! bare value where a list is expected, or `null` for a field they mean to
+//! leave out. [`coerce_args`] rewrites those against the tool's JSON Schema so
+//! the strict parse that follows succeeds instead of costing a round trip.
+//!
+//! Coercion only converts values the schema says are the wrong type and drops
+//! fields the schema rejects; it never invents values, so a missing required
+//! field still fails validation.
+
+use serde_json::{Map, Number, Value};
+
+/// Most nested `$ref`s followed before giving up (guards against cycles)
+const MAX_REF_DEPTH: usize = 32;
+
+/// Rewrite `value` toward `schema`, leaving anything it can't fix as it was
+///
+/// Rules, applied wherever the schema declares a type the value doesn't have:
+/// - string → number/integer when it parses, e.g. `"100"` → `100`
+/// - number → string, e.g. `100` → `"100"`
+/// - `"true"`/`"false"` (any case) → boolean
+/// - a single non-array value → one-element array
+/// - object properties the schema forbids (`additionalProperties: false`) are dropped
+/// - `null` for an optional, non-nullable property is dropped
+///
+/// Local `$ref`s, single-entry `allOf` wrappers and `nullable` are understood;
+/// `anyOf`/`oneOf` branches are left alone.
+pub fn coerce_args(schema: &Value, value: Value) -> Value {
+    coerce_at(value, schema, schema, 0)
+}
+
+fn coerce_at(value: Value, schema: &Value, root: &Value, depth: usize) -> Value {
+    let Some(schema) = resolve(schema, root, depth) else {
+        return value;
+    };
+    if value.is_null() {
+        return value;
+    }
+
+    let value = match types(schema) {
+        Some(types) if !types.iter().any(|t| type_matches(&value, t)) => types
+            .iter()
+            .find_map(|t| convert(&value, t))
+            .unwrap_or(value),
+        _ => value,
+    };
+
+    match value {
+        Value::Object(map) => Value::Object(coerce_object(map, schema, root, depth)),
+        Value::Array(items) => match schema.get("items") {
+            Some(item_schema) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| coerce_at(item, item_schema, root, depth))
+                    .collect(),
+            ),
+            None => Value::Array(items),
+        },
+        other => other,
+    }
+}
+
+fn coerce_object(map: Map<String, Value>, schema: &Value, root: &Value, depth: usize) -> Map<String, Value> {
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let additional = schema.get("additionalProperties");
+
+    map.into_iter()
+        .filter_map(|(key, field)| match properties.and_then(|p| p.get(&key)) {
+            Some(field_schema) => {
+                let optional = !required.contains(&key.as_str());
+                if field.is_null() && optional && !accepts_null(field_schema, root, depth) {
+                    return None;
+                }
+                let field = coerce_at(field, field_schema, root, depth);
+                Some((key, field))
+            }
+            None => match additional {
+                Some(Value::Bool(false)) => None,
+                Some(values @ Value::Object(_)) => {
+                    let field = coerce_at(field, values, root, depth);
+                    Some((key, field))
+                }
+                _ => Some((key, field)),
+            },
+        })
+        .collect()
+}
+
+/// Follow `$ref`s and single-entry `allOf` wrappers to the schema that declares the type
+fn resolve<'a>(schema: &'a Value, root: &'a Value, depth: usize) -> Option<&'a Value> {
+    if depth > MAX_REF_DEPTH {
+        return None;
+    }
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        // By name, as schemars' OpenAPI refs point at `components` but land in `definitions`
+        let name = reference.rsplit('/').next()?;
+        let target = ["definitions", "$defs"]
+            .iter()
+            .find_map(|key| root.get(key)?.get(name))
+            .or_else(|| root.get("components")?.get("schemas")?.get(name))?;
+        return resolve(target, root, depth + 1);
+    }
+    if let Some(Value::Array(all)) = schema.get("allOf") {
+        if let [only] = all.as_slice() {
+            return resolve(only, root, depth + 1);
+        }
+    }
+    schema.is_object().then_some(schema)
+}
+
+fn accepts_null(schema: &Value, root: &Value, depth: usize) -> bool {
+    // Checked on the wrapper too: schemars puts `nullable` beside an `allOf` ref
+    let nullable = |s: &Value| {
+        s.get("nullable") == Some(&Value::Bool(true))
+            || types(s).is_some_and(|types| types.contains(&"null"))
+    };
+    nullable(schema) || resolve(schema, root, depth).is_some_and(nullable)
+}
+
+fn types(schema: &Value) -> Option<Vec<&str>> {
+    match schema.get("type")? {
+        Value::String(t) => Some(vec![t.as_str()]),
+        Value::Array(types) => Some(types.iter().filter_map(Value::as_str).collect()),
+        _ => None,
+    }
+}
+
+fn type_matches(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+/// `value` as type `target`, if one of the coercion rules applies
+fn convert(value: &Value, target: &str) -> Option<Value> {
+    match (target, value) {
+        ("number", Value::String(s)) => {
+            let text = s.trim();
+            integer(text).or_else(|| {
+                text.parse::<f64>()
+                    .ok()
+                    .and_then(Number::from_f64)
+                    .map(Value::Number)
+            })
+        }
+        ("integer", Value::String(s)) => integer(s.trim()),
+        ("integer", Value::Number(n)) => n
+            .as_f64()
+            .filter(|f| f.fract() == 0.0 && f.abs() < i64::MAX as f64)
+            .map(|f| Value::from(f as i64)),
+        ("string", Value::Number(n)) => Some(Value::String(n.to_string())),
+        ("boolean", Value::String(s)) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        ("array", other) => Some(Value::Array(vec![other.clone()])),
+        _ => None,
+    }
+}
+
+fn integer(text: &str) -> Option<Value> {
+    text.parse::<i64>()
+        .map(Value::from)
+        .or_else(|_| text.parse::<u64>().map(Value::from))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::skills::tool::args::args_schema;
+    use crate::skills::tool::schema;
+    use schemars::JsonSchema;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct SwapArgs {
+        symbol: String,
+        amount: f64,
+        legs: u32,
+        dry_run: bool,
+        venues: Vec<String>,
+        #[serde(default)]
+        memo: String,
+        slippage: Option<f64>,
+        route: Option<Route>,
+    }
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct Route {
+        hops: Vec<u32>,
+    }
+
+    fn coerce(value: Value) -> Value {
+        coerce_args(&args_schema::<SwapArgs>(), value)
+    }
+
+    /// Schema as `#[tool]` puts it in the definition, with `$ref`s left in
+    fn definition_schema() -> Value {
+        let gen = schemars::gen::SchemaSettings::openapi3().into_generator();
+        serde_json::to_value(gen.into_root_schema_for::<SwapArgs>()).unwrap()
+    }
+
+    #[test]
+    fn test_scalars_follow_the_schema() {
+        let coerced = coerce(json!({
+            "symbol": 42,
+            "amount": " 100.5 ",
+            "legs": "3",
+            "dry_run": "TRUE",
+            "venues": ["jupiter"],
+        }));
+        assert_eq!(
+            coerced,
+            json!({"symbol": "42", "amount": 100.5, "legs": 3, "dry_run": true, "venues": ["jupiter"]})
+        );
+        assert_eq!(coerce(json!({"legs": 2.0}))["legs"], json!(2));
+        assert_eq!(coerce(json!({"dry_run": "false"}))["dry_run"], json!(false));
+
+        // Unparseable values are left for validation to report
+        let untouched = json!({"amount": "lots", "legs": 2.5, "dry_run": "maybe"});
+        assert_eq!(coerce(untouched.clone()), untouched);
+    }
+
+    #[test]
+    fn test_single_value_becomes_an_array() {
+        assert_eq!(coerce(json!({"venues": "jupiter"}))["venues"], json!(["jupiter"]));
+        assert_eq!(coerce(json!({"route": {"hops": "7"}}))["route"], json!({"hops": [7]}));
+        // Through a `$ref` behind a `nullable` wrapper, with items coerced too
+        let coerced = coerce_args(&definition_schema(), json!({"route": {"hops": ["7", 8]}}));
+        assert_eq!(coerced["route"], json!({"hops": [7, 8]}));
+    }
+
+    #[test]
+    fn test_unknown_and_null_optional_fields_are_dropped() {
+        let coerced = coerce(json!({"symbol": "SOL", "confidence": 0.9, "memo": null, "slippage": null}));
+        // `slippage` is an Option, so its null stays
+        assert_eq!(coerced, json!({"symbol": "SOL", "slippage": null}));
+
+        // Without `additionalProperties: false`, extra fields are the tool's business
+        let open = json!({"type": "object", "properties": {"a": {"type": "integer"}}});
+        assert_eq!(coerce_args(&open, json!({"a": "1", "b": "2"})), json!({"a": 1, "b": "2"}));
+    }
+
+    #[test]
+    fn test_missing_required_field_still_fails() {
+        let schema = args_schema::<SwapArgs>();
+        let coerced = coerce_args(
+            &schema,
+            json!({"amount": "100", "legs": "1", "dry_run": "true", "venues": "jupiter"}),
+        );
+        let violation = schema::check(&coerced, &schema).unwrap_err();
+        assert_eq!(violation.property.as_deref(), Some("symbol"));
+        assert!(violation.message.contains("missing required property"));
+
+        // A required field sent as null is not dropped either
+        let coerced = coerce(json!({"symbol": null}));
+        assert_eq!(coerced, json!({"symbol": null}));
+    }
+}
@@ -0,0 +1,390 @@
+//! Content-aware reduction of large tool outputs
+//!
+//! JSON results are reduced structurally (long arrays keep their first and last
+//! items, deep or wide subtrees are pruned) so the output always stays valid
+//! JSON. Anything else falls back to head + tail text truncation.
+
+use serde_json::{Map, Value};
+
+/// Limits used when reducing tool output
+#[derive(Debug, Clone)]
+pub struct CompressionConfig {
+    /// Outputs longer than this are reduced
+    pub max_chars: usize,
+    /// Items kept at each end of a long array
+    pub edge_items: usize,
+    /// Nesting depth kept before subtrees are pruned
+    pub max_depth: usize,
+    /// Keys kept per object before the rest are pruned
+    pub max_object_keys: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            max_chars: 4096,
+            edge_items: 3,
+            max_depth: 4,
+            max_object_keys: 24,
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// Config with the given size threshold and default structural limits
+    pub fn with_max_chars(max_chars: usize) -> Self {
+        Self {
+            max_chars,
+            ..Default::default()
+        }
+    }
+}
+
+/// Result of reducing a tool output
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressedOutput {
+    /// Reduced output (valid JSON whenever the input was JSON)
+    pub text: String,
+    /// Original size in bytes
+    pub original_len: usize,
+    /// Human-readable description of each reduction applied
+    pub notes: Vec<String>,
+}
+
+impl CompressedOutput {
+    /// Whether anything was reduced
+    pub fn is_reduced(&self) -> bool {
+        !self.notes.is_empty()
+    }
+}
+
+/// Reduce `output` to fit `config.max_chars`, projecting to `projection` first if given
+pub fn compress(
+    output: &str,
+    projection: Option<&[String]>,
+    config: &CompressionConfig,
+) -> CompressedOutput {
+    let original_len = output.len();
+    if original_len <= config.max_chars {
+        return CompressedOutput {
+            text: output.to_string(),
+            original_len,
+            notes: Vec::new(),
+        };
+    }
+
+    let Ok(mut value) = serde_json::from_str::<Value>(output) else {
+        return CompressedOutput {
+            text: truncate_text(output, config.max_chars),
+            original_len,
+            notes: vec![format!(
+                "text truncated from {} to {} chars (head and tail kept)",
+                original_len, config.max_chars
+            )],
+        };
+    };
+
+    let mut notes = Vec::new();
+    if let Some(fields) = projection.filter(|f| !f.is_empty()) {
+        let dropped = project(&mut value, fields);
+        if dropped > 0 {
+            notes.push(format!(
+                "projected to fields [{}] ({} keys dropped)",
+                fields.join(", "),
+                dropped
+            ));
+        }
+        let text = value.to_string();
+        if text.len() <= config.max_chars {
+            return CompressedOutput {
+                text,
+                original_len,
+                notes,
+            };
+        }
+    }
+
+    // Tighten the limits until the serialized result fits
+    let mut edge_items = config.edge_items.max(1);
+    let mut max_depth = config.max_depth.max(1);
+    let mut max_keys = config.max_object_keys.max(1);
+    loop {
+        let mut stats = ReduceStats::default();
+        let reduced = reduce(&value, 0, edge_items, max_depth, max_keys, &mut stats);
+        let text = reduced.to_string();
+        let exhausted = edge_items == 1 && max_depth == 1 && max_keys == 1;
+
+        if text.len() <= config.max_chars || exhausted {
+            let mut notes = notes;
+            notes.extend(stats.notes());
+            if text.len() > config.max_chars {
+                // Even the skeleton is too large: keep a valid JSON preview
+                let preview = serde_json::json!({
+                    "truncated": true,
+                    "original_chars": original_len,
+                    "preview": truncate_text(&text, config.max_chars / 2),
+                });
+                notes.push("structure too large; replaced with a text preview".to_string());
+                return CompressedOutput {
+                    text: preview.to_string(),
+                    original_len,
+                    notes,
+                };
+            }
+            return CompressedOutput {
+                text,
+                original_len,
+                notes,
+            };
+        }
+
+        edge_items = (edge_items / 2).max(1);
+        max_depth = max_depth.saturating_sub(1).max(1);
+        max_keys = (max_keys / 2).max(1);
+    }
+}
+
+/// Keep only `fields` in every object that has at least one of them, returning dropped key count
+fn project(value: &mut Value, fields: &[String]) -> usize {
+    match value {
+        Value::Object(map) => {
+            if map.keys().any(|k| fields.contains(k)) {
+                let before = map.len();
+                map.retain(|k, _| fields.contains(k));
+                before - map.len()
+            } else {
+                map.values_mut().map(|v| project(v, fields)).sum()
+            }
+        }
+        Value::Array(items) => items.iter_mut().map(|v| project(v, fields)).sum(),
+        _ => 0,
+    }
+}
+
+#[derive(Default)]
+struct ReduceStats {
+    arrays_trimmed: usize,
+    items_omitted: usize,
+    subtrees_pruned: usize,
+    keys_pruned: usize,
+}
+
+impl ReduceStats {
+    fn notes(&self) -> Vec<String> {
+        let mut notes = Vec::new();
+        if self.arrays_trimmed > 0 {
+            notes.push(format!(
+                "{} items omitted from {} long arrays",
+                self.items_omitted, self.arrays_trimmed
+            ));
+        }
+        if self.subtrees_pruned > 0 {
+            notes.push(format!("{} nested subtrees pruned", self.subtrees_pruned));
+        }
+        if self.keys_pruned > 0 {
+            notes.push(format!("{} object keys pruned", self.keys_pruned));
+        }
+        notes
+    }
+}
+
+fn reduce(
+    value: &Value,
+    depth: usize,
+    edge: usize,
+    max_depth: usize,
+    max_keys: usize,
+    stats: &mut ReduceStats,
+) -> Value {
+    match value {
+        Value::Object(map) if depth >= max_depth && !map.is_empty() => {
+            stats.subtrees_pruned += 1;
+            Value::String(format!("…pruned ({} keys)", map.len()))
+        }
+        Value::Array(items) if depth >= max_depth && !items.is_empty() => {
+            stats.subtrees_pruned += 1;
+            Value::String(format!("…pruned ({} items)", items.len()))
+        }
+        Value::Object(map) => {
+            let mut out = Map::new();
+            for (k, v) in map.iter().take(max_keys) {
+                out.insert(
+                    k.clone(),
+                    reduce(v, depth + 1, edge, max_depth, max_keys, stats),
+                );
+            }
+            if map.len() > max_keys {
+                let pruned = map.len() - max_keys;
+                stats.keys_pruned += pruned;
+                out.insert(
+                    "…".to_string(),
+                    Value::String(format!("pruned ({} keys)", pruned)),
+                );
+            }
+            Value::Object(out)
+        }
+        Value::Array(items) if items.len() > edge * 2 => {
+            let omitted = items.len() - edge * 2;
+            stats.arrays_trimmed += 1;
+            stats.items_omitted += omitted;
+
+            let mut out: Vec<Value> = items[..edge]
+                .iter()
+                .map(|v| reduce(v, depth + 1, edge, max_depth, max_keys, stats))
+                .collect();
+            out.push(Value::String(omission_marker(items, omitted)));
+            out.extend(
+                items[items.len() - edge..]
+                    .iter()
+                    .map(|v| reduce(v, depth + 1, edge, max_depth, max_keys, stats)),
+            );
+            Value::Array(out)
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|v| reduce(v, depth + 1, edge, max_depth, max_keys, stats))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Marker placed where array items were removed, listing the union of object keys
+fn omission_marker(items: &[Value], omitted: usize) -> String {
+    let mut keys: Vec<&str> = Vec::new();
+    for key in items
+        .iter()
+        .filter_map(|v| v.as_object())
+        .flat_map(|m| m.keys())
+    {
+        if !keys.contains(&key.as_str()) {
+            keys.push(key);
+        }
+    }
+
+    if keys.is_empty() {
+        format!("…{} of {} items omitted", omitted, items.len())
+    } else {
+        format!(
+            "…{} of {} items omitted (keys: {})",
+            omitted,
+            items.len(),
+            keys.join(", ")
+        )
+    }
+}
+
+/// Keep the head and tail of a string, cutting on char boundaries
+pub fn truncate_text(text: &str, max_chars: usize) -> String {
+    if text.len() <= max_chars {
+        return text.to_string();
+    }
+
+    let head_len = floor_boundary(text, max_chars * 2 / 3);
+    let tail_start = ceil_boundary(text, text.len() - (max_chars - head_len) / 2);
+    format!(
+        "{}\n…[{} chars omitted]…\n{}",
+        &text[..head_len],
+        tail_start - head_len,
+        &text[tail_start..]
+    )
+}
+
+fn floor_boundary(text: &str, mut idx: usize) -> usize {
+    while !text.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    idx
+}
+
+fn ceil_boundary(text: &str, mut idx: usize) -> usize {
+    while !text.is_char_boundary(idx) {
+        idx += 1;
+    }
+    idx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_large_array_keeps_edges() {
+        let items: Vec<Value> = (0..5000)
+            .map(|i| json!({"id": i, "price": i as f64 * 0.5, "side": if i % 2 == 0 { "bid" } else { "ask" }}))
+            .collect();
+        let output = Value::Array(items).to_string();
+
+        let compressed = compress(&output, None, &CompressionConfig::default());
+        assert!(compressed.is_reduced());
+        assert!(compressed.text.len() <= 4096);
+
+        let parsed: Value = serde_json::from_str(&compressed.text).unwrap();
+        let arr = parsed.as_array().unwrap();
+        assert_eq!(arr.len(), 7);
+        assert_eq!(arr[0]["id"], 0);
+        assert_eq!(arr[6]["id"], 4999);
+        assert_eq!(
+            arr[3],
+            "…4994 of 5000 items omitted (keys: id, price, side)"
+        );
+        assert!(compressed.notes[0].contains("4994 items omitted"));
+    }
+
+    #[test]
+    fn test_deep_object_is_pruned() {
+        let mut deep = json!({"leaf": "x".repeat(200)});
+        for i in 0..30 {
+            deep = json!({ format!("level{}", i): deep, "padding": "y".repeat(200) });
+        }
+        let output = deep.to_string();
+
+        let compressed = compress(&output, None, &CompressionConfig::with_max_chars(1000));
+        let parsed: Value = serde_json::from_str(&compressed.text).unwrap();
+        assert!(compressed.text.len() <= 1000);
+        assert!(compressed.text.contains("…pruned (2 keys)"));
+        assert!(parsed["level29"]["level28"].is_object());
+        assert!(compressed
+            .notes
+            .iter()
+            .any(|n| n.contains("subtrees pruned")));
+    }
+
+    #[test]
+    fn test_projection_before_pruning() {
+        let items: Vec<Value> = (0..200)
+            .map(|i| json!({"symbol": format!("T{}", i), "price": i, "raw": "z".repeat(100), "meta": {"a": 1}}))
+            .collect();
+        let output = json!({"data": items, "count": 200}).to_string();
+        let fields = vec!["symbol".to_string(), "price".to_string()];
+
+        let compressed = compress(
+            &output,
+            Some(&fields),
+            &CompressionConfig::with_max_chars(4096),
+        );
+        let parsed: Value = serde_json::from_str(&compressed.text).unwrap();
+        assert_eq!(parsed["count"], 200);
+        let mut keys: Vec<_> = parsed["data"][0].as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["price", "symbol"]);
+        assert!(compressed.notes[0]
+            .starts_with("projected to fields [symbol, price] (400 keys dropped)"));
+    }
+
+    #[test]
+    fn test_text_fallback_and_small_output() {
+        let small = compress("ok", None, &CompressionConfig::default());
+        assert!(!small.is_reduced());
+        assert_eq!(small.text, "ok");
+
+        let text = format!("start{}end", "é".repeat(3000));
+        let compressed = compress(&text, None, &CompressionConfig::with_max_chars(300));
+        assert!(compressed.text.starts_with("start"));
+        assert!(compressed.text.ends_with("end"));
+        assert!(compressed.text.len() < 400);
+        assert!(compressed.notes[0].contains("head and tail"));
+    }
+}
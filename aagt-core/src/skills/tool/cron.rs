@@ -1,12 +1,13 @@
 //! Tool for agents to schedule future and periodic tasks
 
 use async_trait::async_trait;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use std::sync::Weak;
 use uuid::Uuid;
 use crate::agent::multi_agent::AgentRole;
-use crate::agent::scheduler::{Scheduler, JobSchedule, JobPayload};
-use crate::skills::tool::{Tool, ToolDefinition};
+use crate::agent::scheduler::{Scheduler, JobSchedule, JobPayload, JobOptions};
+use crate::skills::tool::{parse_args, ArgsExt, Tool, ToolDefinition};
 use crate::error::Error;
 
 /// Tool for managing scheduled tasks
@@ -22,7 +23,7 @@ impl CronTool {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 struct CronArgs {
     action: String,
     #[serde(default)]
@@ -33,6 +34,10 @@ struct CronArgs {
     prompt: String,
     #[serde(default)]
     id: Option<String>,
+    #[serde(default)]
+    tz: Option<String>,
+    #[serde(default)]
+    calendar: Option<String>,
 }
 
 #[async_trait]
@@ -44,7 +49,7 @@ impl Tool for CronTool {
     async fn definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: "cron".to_string(),
-            description: "Manage scheduled and periodic tasks (actions: schedule, list, cancel).".to_string(),
+            description: "Manage scheduled and periodic tasks (actions: schedule, list, cancel). Tasks survive restarts.".to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
@@ -67,20 +72,28 @@ impl Tool for CronTool {
                     },
                     "id": {
                         "type": "string",
-                        "description": "ID of the task to cancel"
+                        "description": "ID of the task to cancel, as shown by list"
+                    },
+                    "tz": {
+                        "type": "string",
+                        "description": "IANA time zone for cron schedules, e.g. America/New_York (default UTC)"
+                    },
+                    "calendar": {
+                        "type": "string",
+                        "description": "Only run while this trading calendar is open (crypto, weekdays, us_equities, or a custom one)"
                     }
                 },
                 "required": ["action"]
             }),
-            parameters_ts: Some("type Schedule = \n  | { kind: 'at', at: string } // ISO8601 timestamp\n  | { kind: 'every', intervalSecs: number };\n\ninterface CronArgs {\n  action: 'schedule' | 'list' | 'cancel';\n  name?: string;\n  schedule?: Schedule;\n  prompt?: string;\n  id?: string; // For cancel action\n}".to_string()),
-            is_binary: false,
+            parameters_ts: Some("type Schedule = \n  | { kind: 'at', at: string } // ISO8601 timestamp\n  | { kind: 'every', intervalSecs: number }\n  | { kind: 'cron', expr: string }; // 6 fields, seconds first\n\ninterface CronArgs {\n  action: 'schedule' | 'list' | 'cancel';\n  name?: string;\n  schedule?: Schedule;\n  prompt?: string;\n  id?: string; // For cancel action, from list\n  tz?: string; // IANA zone for cron schedules (default UTC)\n  calendar?: string; // Skip runs while this trading calendar is closed\n}".to_string()),
             is_verified: true,
+            ..Default::default()
         }
     }
 
     async fn call(&self, arguments: &str) -> anyhow::Result<String> {
-        let args: CronArgs = serde_json::from_str(arguments)
-            .map_err(|e| anyhow::Error::from(Error::tool_execution("cron", format!("Invalid arguments: {}", e))))?;
+        let args: CronArgs = parse_args(&self.name(), arguments)?;
+        self.require_one_of("action", &args.action, &["schedule", "list", "cancel"])?;
             
         let scheduler = self.scheduler.upgrade()
             .ok_or_else(|| anyhow::Error::from(Error::tool_execution("cron", "Scheduler not available")))?;
@@ -94,13 +107,14 @@ impl Tool for CronTool {
                 // For simplicity, we assume the task is for the agent role calling it.
                 // In a real system we might want to pass the role explicitly.
                 // Here we just use Assistant as default if we don't know the role.
-                let id = scheduler.add_job(
+                let id = scheduler.add_job_with(
                     args.name,
                     schedule,
                     JobPayload::AgentTurn {
                         role: AgentRole::Assistant, // Defaulting to Assistant for now
                         prompt: args.prompt,
                     },
+                    JobOptions { tz: args.tz, calendar: args.calendar },
                 ).await.map_err(|e| anyhow::Error::from(e))?;
                 Ok(format!("Successfully scheduled task with ID: {}", id))
             },
@@ -110,17 +124,19 @@ impl Tool for CronTool {
                     return Ok("No scheduled tasks found.".to_string());
                 }
 
-                let mut table = crate::infra::format::MarkdownTable::new(vec!["ID", "Name", "Schedule", "Enabled"]);
+                let mut table = crate::infra::format::MarkdownTable::new(vec!["ID", "Name", "Schedule", "Calendar", "Next run", "Enabled"]);
                 for job in jobs {
                     let schedule_str = match job.schedule {
                         JobSchedule::At { at } => format!("At {}", at),
                         JobSchedule::Every { interval_secs } => format!("Every {}s", interval_secs),
-                        JobSchedule::Cron { expr } => format!("Cron: {}", expr),
+                        JobSchedule::Cron { expr } => format!("Cron: {} ({})", expr, job.tz.as_deref().unwrap_or("UTC")),
                     };
                     table.add_row(vec![
                         job.id.to_string(),
                         job.name,
                         schedule_str,
+                        job.calendar.unwrap_or_else(|| "-".to_string()),
+                        job.next_run.map_or_else(|| "-".to_string(), |at| at.to_rfc3339()),
                         job.enabled.to_string(),
                     ]);
                 }
@@ -131,7 +147,7 @@ impl Tool for CronTool {
                 let id = Uuid::parse_str(&id_str)
                     .map_err(|e| anyhow::Error::from(Error::tool_execution("cron", format!("Invalid ID format: {}", e))))?;
                 
-                if scheduler.remove_job(id).await.map_err(|e| anyhow::Error::from(e))? {
+                if scheduler.cancel_job(id).await.map_err(anyhow::Error::from)? {
                     Ok(format!("Successfully canceled task {}", id))
                 } else {
                     Ok(format!("Task {} not found", id))
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::sync::Weak;
 use crate::agent::multi_agent::{Coordinator, AgentRole};
-use crate::skills::tool::{Tool, ToolDefinition};
+use crate::skills::tool::{parse_args, ArgsExt, Tool, ToolDefinition};
 
 /// Tool that allows an agent to delegate a task to another agent role
 pub struct DelegateTool {
@@ -14,9 +14,33 @@ impl DelegateTool {
     pub fn new(coordinator: Weak<Coordinator>) -> Self {
         Self { coordinator }
     }
+
+    /// One line per registered role listing its tools, so the model only delegates what the target can do
+    fn capabilities(&self) -> String {
+        let Some(coordinator) = self.coordinator.upgrade() else {
+            return String::new();
+        };
+        let mut roles: Vec<(AgentRole, Vec<String>)> =
+            coordinator.agent_capabilities().into_iter().collect();
+        if roles.is_empty() {
+            return String::new();
+        }
+        roles.sort_by(|(a, _), (b, _)| a.name().cmp(b.name()));
+
+        let mut text = String::from("\n\nAvailable roles and their tools:");
+        for (role, tools) in roles {
+            let tools = if tools.is_empty() {
+                "no tools".to_string()
+            } else {
+                tools.join(", ")
+            };
+            text.push_str(&format!("\n- {}: {}", role.name(), tools));
+        }
+        text
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, schemars::JsonSchema)]
 struct DelegateArgs {
     /// The role to delegate the task to (e.g., "researcher", "trader")
     role: String,
@@ -33,7 +57,10 @@ impl Tool for DelegateTool {
     async fn definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: self.name(),
-            description: "Delegate a sub-task to another specialized agent role. Use this when you need research, risk analysis, or trade execution that is outside your primary scope.".to_string(),
+            description: format!(
+                "Delegate a sub-task to another specialized agent role. Use this when you need research, risk analysis, or trade execution that is outside your primary scope.{}",
+                self.capabilities()
+            ),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
@@ -50,13 +77,14 @@ impl Tool for DelegateTool {
                 "required": ["role", "task"]
             }),
             parameters_ts: Some("interface DelegateArgs {\n  role: 'researcher' | 'trader' | 'risk_analyst' | 'strategist' | 'assistant';\n  task: string; // Instructions for the sub-agent\n}".to_string()),
-            is_binary: false,
             is_verified: true,
+            ..Default::default()
         }
     }
 
     async fn call(&self, arguments: &str) -> anyhow::Result<String> {
-        let args: DelegateArgs = serde_json::from_str(arguments)?;
+        let args: DelegateArgs = parse_args(&self.name(), arguments)?;
+        self.require_non_empty("task", &args.task)?;
         
         let coordinator = self.coordinator.upgrade().ok_or_else(|| {
             anyhow::anyhow!("Coordinator has been dropped")
@@ -71,13 +99,9 @@ impl Tool for DelegateTool {
             _ => AgentRole::Custom(args.role),
         };
 
-        let agent = coordinator.get(&role).ok_or_else(|| {
-            anyhow::anyhow!("No agent registered for role: {:?}", role)
-        })?;
-
-        // Execute the sub-agent's process
+        // Goes through the coordinator's job queue when handoffs are durable
         // Note: In a real system, we might want to pass more context here
-        let result = agent.process(&args.task).await?;
+        let result = coordinator.delegate(&role, &args.task).await?;
         
         Ok(result)
     }
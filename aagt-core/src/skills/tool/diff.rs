@@ -0,0 +1,784 @@
+//! Tool for comparing two documents or tool outputs
+//!
+//! Produces a deterministic unified diff (Myers algorithm) with a compact summary,
+//! or a structural path-level diff when both sides are JSON.
+
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use crate::agent::memory::Memory;
+use crate::error::Error;
+use crate::skills::tool::{parse_args, Tool, ToolDefinition, ToolExample};
+
+/// Limits for the diff tool
+#[derive(Debug, Clone)]
+pub struct DiffConfig {
+    /// Maximum size of an inline text source in bytes
+    pub max_inline_bytes: usize,
+    /// Maximum output characters (the summary is always kept)
+    pub max_output_chars: usize,
+    /// Default number of context lines around each hunk
+    pub context_lines: usize,
+    /// Edit distance beyond which the changed range is reported as fully replaced
+    pub max_edit_distance: usize,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self {
+            max_inline_bytes: 64 * 1024,
+            // Stays under the agent's default tool output cap
+            max_output_chars: 4000,
+            context_lines: 3,
+            max_edit_distance: 1000,
+        }
+    }
+}
+
+/// Tool for diffing inline text or stored documents
+pub struct DiffTool {
+    memory: Option<Arc<dyn Memory>>,
+    config: DiffConfig,
+}
+
+impl Default for DiffTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiffTool {
+    /// Create a diff tool that only accepts inline text
+    pub fn new() -> Self {
+        Self {
+            memory: None,
+            config: DiffConfig::default(),
+        }
+    }
+
+    /// Resolve document references through a memory backend
+    pub fn with_memory(mut self, memory: Arc<dyn Memory>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Set custom limits
+    pub fn with_config(mut self, config: DiffConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Resolve a source to a label and its text
+    async fn resolve(&self, source: DiffSource, side: &str) -> Result<(String, String), Error> {
+        match source {
+            DiffSource::Text { text } => {
+                if text.len() > self.config.max_inline_bytes {
+                    return Err(Error::ToolArguments {
+                        tool_name: self.name(),
+                        message: format!(
+                            "{} text is {} bytes, limit is {}; store it and pass a document reference instead",
+                            side,
+                            text.len(),
+                            self.config.max_inline_bytes
+                        ),
+                    });
+                }
+                Ok((side.to_string(), text))
+            }
+            DiffSource::Path { collection, path } => {
+                let memory = self.memory()?;
+                let doc = memory
+                    .fetch_document(&collection, &path)
+                    .await?
+                    .ok_or_else(|| {
+                        Error::tool_execution(
+                            self.name(),
+                            format!("Document not found: {}/{}", collection, path),
+                        )
+                    })?;
+                Ok((format!("{}/{}", collection, path), doc.content))
+            }
+            DiffSource::Docid { docid } => {
+                let memory = self.memory()?;
+                let doc = memory.fetch_document_by_id(&docid).await?.ok_or_else(|| {
+                    Error::tool_execution(self.name(), format!("Document not found: #{}", docid))
+                })?;
+                Ok((format!("#{}", docid), doc.content))
+            }
+        }
+    }
+
+    fn memory(&self) -> Result<&Arc<dyn Memory>, Error> {
+        self.memory.as_ref().ok_or_else(|| {
+            Error::tool_execution(
+                self.name(),
+                "No document store configured; only inline text can be compared",
+            )
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum DiffSource {
+    Text { text: String },
+    Path { collection: String, path: String },
+    Docid { docid: String },
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum DiffMode {
+    #[default]
+    Auto,
+    Text,
+    Json,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct DiffArgs {
+    left: DiffSource,
+    right: DiffSource,
+    #[serde(default)]
+    context_lines: Option<usize>,
+    #[serde(default)]
+    mode: DiffMode,
+}
+
+#[async_trait]
+impl Tool for DiffTool {
+    fn name(&self) -> String {
+        "diff".to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        let source = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "text": { "type": "string", "description": "Inline text" },
+                "collection": { "type": "string", "description": "Document collection" },
+                "path": { "type": "string", "description": "Document virtual path" },
+                "docid": { "type": "string", "description": "Document ID (short hash)" }
+            },
+            "additionalProperties": false
+        });
+
+        ToolDefinition {
+            name: self.name(),
+            description: "Compare two texts or stored documents. Returns a change summary (lines added/removed, \
+                changed markdown sections) followed by a unified diff. JSON inputs are compared by path."
+                .to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "left": source.clone(),
+                    "right": source,
+                    "context_lines": {
+                        "type": "integer",
+                        "description": "Unchanged lines shown around each change (default: 3)"
+                    },
+                    "mode": {
+                        "type": "string",
+                        "enum": ["auto", "text", "json"],
+                        "description": "Comparison mode (default: auto)"
+                    }
+                },
+                "required": ["left", "right"]
+            }),
+            parameters_ts: Some(
+                "type DiffSource =\n  | { text: string } // Inline text\n  | { collection: string; path: string } // Stored document\n  | { docid: string }; // Document by ID\n\ninterface DiffArgs {\n  left: DiffSource; // Old version\n  right: DiffSource; // New version\n  context_lines?: number; // Default: 3\n  mode?: 'auto' | 'text' | 'json'; // 'auto' uses JSON diff when both sides are JSON\n}"
+                    .to_string(),
+            ),
+            is_verified: true,
+            examples: vec![
+                ToolExample::new(
+                    "Compare yesterday's report with today's",
+                    serde_json::json!({
+                        "left": { "collection": "reports", "path": "daily/2024-05-01.md" },
+                        "right": { "collection": "reports", "path": "daily/2024-05-02.md" }
+                    }),
+                )
+                .with_result("summary of changed sections plus a unified diff"),
+                ToolExample::new(
+                    "Compare two JSON tool outputs",
+                    serde_json::json!({
+                        "left": { "text": "{\"price\": 101.5}" },
+                        "right": { "text": "{\"price\": 99.8}" },
+                        "mode": "json"
+                    }),
+                )
+                .with_result("~ $.price: 101.5 -> 99.8"),
+            ],
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        let args: DiffArgs = parse_args(&self.name(), arguments)?;
+
+        let (left_label, left) = self.resolve(args.left, "left").await?;
+        let (right_label, right) = self.resolve(args.right, "right").await?;
+
+        let json_pair = || -> Option<(Value, Value)> {
+            let a: Value = serde_json::from_str(&left).ok()?;
+            let b: Value = serde_json::from_str(&right).ok()?;
+            Some((a, b))
+        };
+
+        let max_chars = self.config.max_output_chars;
+        match args.mode {
+            DiffMode::Json => {
+                let (a, b) = json_pair().ok_or_else(|| Error::ToolArguments {
+                    tool_name: self.name(),
+                    message: "mode 'json' requires both sides to be valid JSON".to_string(),
+                })?;
+                Ok(render_json_diff(&a, &b, max_chars))
+            }
+            DiffMode::Auto => match json_pair() {
+                Some((a, b)) if is_container(&a) && is_container(&b) => {
+                    Ok(render_json_diff(&a, &b, max_chars))
+                }
+                _ => Ok(self.render_text(
+                    &left_label,
+                    &left,
+                    &right_label,
+                    &right,
+                    args.context_lines,
+                )),
+            },
+            DiffMode::Text => {
+                Ok(self.render_text(&left_label, &left, &right_label, &right, args.context_lines))
+            }
+        }
+    }
+}
+
+impl DiffTool {
+    fn render_text(
+        &self,
+        left_label: &str,
+        left: &str,
+        right_label: &str,
+        right: &str,
+        context_lines: Option<usize>,
+    ) -> String {
+        let a: Vec<&str> = left.lines().collect();
+        let b: Vec<&str> = right.lines().collect();
+        let ops = diff_lines(&a, &b, self.config.max_edit_distance);
+
+        let summary = summarize_text(&a, &b, &ops);
+        if ops.iter().all(|op| matches!(op, Op::Equal(..))) {
+            return summary;
+        }
+
+        let mut body = vec![
+            format!("--- {}", left_label),
+            format!("+++ {}", right_label),
+        ];
+        body.extend(unified_hunks(
+            &a,
+            &b,
+            &ops,
+            context_lines.unwrap_or(self.config.context_lines),
+        ));
+        assemble(summary, "diff", &body, self.config.max_output_chars)
+    }
+}
+
+fn is_container(value: &Value) -> bool {
+    value.is_object() || value.is_array()
+}
+
+/// Join the summary and as many detail lines as fit in `max_chars`
+fn assemble(summary: String, fence: &str, lines: &[String], max_chars: usize) -> String {
+    let mut out = summary;
+    out.push_str(&format!("\n\n```{}\n", fence));
+
+    // Reserve room for the closing fence and a truncation notice
+    let reserve = 64;
+    let budget = max_chars.saturating_sub(out.len() + reserve);
+    let mut used = 0;
+    let mut shown = 0;
+    for line in lines {
+        if used + line.len() + 1 > budget {
+            break;
+        }
+        out.push_str(line);
+        out.push('\n');
+        used += line.len() + 1;
+        shown += 1;
+    }
+    out.push_str("```");
+    if shown < lines.len() {
+        out.push_str(&format!(
+            "\n(diff truncated: {} of {} lines shown)",
+            shown,
+            lines.len()
+        ));
+    }
+    out
+}
+
+// --- Line diff ---
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+fn diff_lines(a: &[&str], b: &[&str], max_edit_distance: usize) -> Vec<Op> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let a_mid = &a[prefix..a.len() - suffix];
+    let b_mid = &b[prefix..b.len() - suffix];
+    let middle = myers(a_mid, b_mid, max_edit_distance).unwrap_or_else(|| {
+        // Too many edits to trace cheaply: report the whole range as replaced
+        (0..a_mid.len())
+            .map(Op::Delete)
+            .chain((0..b_mid.len()).map(Op::Insert))
+            .collect()
+    });
+
+    let mut ops: Vec<Op> = (0..prefix).map(|i| Op::Equal(i, i)).collect();
+    ops.extend(middle.into_iter().map(|op| match op {
+        Op::Equal(i, j) => Op::Equal(i + prefix, j + prefix),
+        Op::Delete(i) => Op::Delete(i + prefix),
+        Op::Insert(j) => Op::Insert(j + prefix),
+    }));
+    ops.extend((0..suffix).map(|i| Op::Equal(a.len() - suffix + i, b.len() - suffix + i)));
+    ops
+}
+
+/// Myers O(ND) shortest edit script, or `None` if the distance exceeds `max_d`
+fn myers(a: &[&str], b: &[&str], max_d: usize) -> Option<Vec<Op>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    let offset = max + 1;
+    let mut v = vec![0isize; (2 * max + 3) as usize];
+    // trace[d] holds v[-d-1..=d+1] as it was at the start of step d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    for d in 0..=max {
+        if d as usize > max_d {
+            return None;
+        }
+        trace.push(v[(offset - d - 1) as usize..=(offset + d + 1) as usize].to_vec());
+
+        let mut k = -d;
+        while k <= d {
+            let down =
+                k == -d || (k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize]);
+            let mut x = if down {
+                v[(offset + k + 1) as usize]
+            } else {
+                v[(offset + k - 1) as usize] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[(offset + k) as usize] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m));
+            }
+            k += 2;
+        }
+    }
+    Some(Vec::new())
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Op> {
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let get = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = get(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push(Op::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                y -= 1;
+                ops.push(Op::Insert(y as usize));
+            } else {
+                x -= 1;
+                ops.push(Op::Delete(x as usize));
+            }
+        }
+    }
+
+    ops.reverse();
+    ops
+}
+
+fn unified_hunks(a: &[&str], b: &[&str], ops: &[Op], context: usize) -> Vec<String> {
+    // Position in each file before op i
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut ai, mut bi) = (0, 0);
+    for op in ops {
+        positions.push((ai, bi));
+        match op {
+            Op::Equal(..) => {
+                ai += 1;
+                bi += 1;
+            }
+            Op::Delete(_) => ai += 1,
+            Op::Insert(_) => bi += 1,
+        }
+    }
+    positions.push((ai, bi));
+
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, Op::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+
+    // Group changes whose gaps fit inside shared context
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &i in &changes {
+        match groups.last_mut() {
+            Some((_, end)) if i - *end <= 2 * context + 1 => *end = i,
+            _ => groups.push((i, i)),
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (first, last) in groups {
+        let start = first.saturating_sub(context);
+        let end = (last + context + 1).min(ops.len());
+        let (a_start, b_start) = positions[start];
+        let (a_end, b_end) = positions[end];
+        let (a_count, b_count) = (a_end - a_start, b_end - b_start);
+        lines.push(format!(
+            "@@ -{},{} +{},{} @@",
+            if a_count == 0 { a_start } else { a_start + 1 },
+            a_count,
+            if b_count == 0 { b_start } else { b_start + 1 },
+            b_count
+        ));
+        for op in &ops[start..end] {
+            lines.push(match *op {
+                Op::Equal(i, _) => format!(" {}", a[i]),
+                Op::Delete(i) => format!("-{}", a[i]),
+                Op::Insert(j) => format!("+{}", b[j]),
+            });
+        }
+    }
+    lines
+}
+
+// --- Summaries ---
+
+/// For each line, the markdown heading it falls under (headings inside code fences are ignored)
+fn section_index(lines: &[&str]) -> (Vec<Option<usize>>, bool) {
+    let mut current = None;
+    let mut in_fence = false;
+    let mut has_heading = false;
+    let mut sections = Vec::with_capacity(lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_fence = !in_fence;
+        } else if !in_fence && is_heading(trimmed) {
+            current = Some(i);
+            has_heading = true;
+        }
+        sections.push(current);
+    }
+    (sections, has_heading)
+}
+
+fn is_heading(line: &str) -> bool {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    (1..=6).contains(&hashes) && line[hashes..].starts_with(' ')
+}
+
+struct SectionChange {
+    title: String,
+    added: usize,
+    removed: usize,
+}
+
+fn summarize_text(a: &[&str], b: &[&str], ops: &[Op]) -> String {
+    let added = ops.iter().filter(|op| matches!(op, Op::Insert(_))).count();
+    let removed = ops.iter().filter(|op| matches!(op, Op::Delete(_))).count();
+    let unchanged = ops.len() - added - removed;
+
+    if added == 0 && removed == 0 {
+        return "Diff summary: no differences".to_string();
+    }
+
+    let (a_sections, a_markdown) = section_index(a);
+    let (b_sections, b_markdown) = section_index(b);
+    let markdown = a_markdown && b_markdown;
+
+    let mut out = format!(
+        "Diff summary ({}): +{} -{} lines, {} unchanged",
+        if markdown { "markdown" } else { "text" },
+        added,
+        removed,
+        unchanged
+    );
+
+    if markdown {
+        let a_titles: BTreeSet<&str> = a_sections.iter().flatten().map(|&i| a[i].trim()).collect();
+        let b_titles: BTreeSet<&str> = b_sections.iter().flatten().map(|&i| b[i].trim()).collect();
+
+        // Sections in order of their first change
+        let mut sections: Vec<SectionChange> = Vec::new();
+        for op in ops {
+            let (title, is_insert) = match *op {
+                Op::Delete(i) => (a_sections[i].map(|h| a[h].trim()), false),
+                Op::Insert(j) => (b_sections[j].map(|h| b[h].trim()), true),
+                Op::Equal(..) => continue,
+            };
+            let title = title.unwrap_or("(before first heading)");
+            let pos = match sections.iter().position(|s| s.title == title) {
+                Some(pos) => pos,
+                None => {
+                    sections.push(SectionChange {
+                        title: title.to_string(),
+                        added: 0,
+                        removed: 0,
+                    });
+                    sections.len() - 1
+                }
+            };
+            if is_insert {
+                sections[pos].added += 1;
+            } else {
+                sections[pos].removed += 1;
+            }
+        }
+
+        out.push_str("\nChanged sections:");
+        for s in &sections {
+            let status = match (
+                a_titles.contains(s.title.as_str()),
+                b_titles.contains(s.title.as_str()),
+            ) {
+                (false, true) => "added",
+                (true, false) => "removed",
+                _ => "modified",
+            };
+            out.push_str(&format!(
+                "\n- {} \"{}\" (+{} -{})",
+                status, s.title, s.added, s.removed
+            ));
+        }
+    }
+
+    let moved = moved_blocks(a, b, ops);
+    if moved > 0 {
+        out.push_str(&format!("\nMoved blocks: {}", moved));
+    }
+    out
+}
+
+/// Count deleted runs of two or more lines that reappear verbatim as an inserted run
+fn moved_blocks(a: &[&str], b: &[&str], ops: &[Op]) -> usize {
+    let mut deleted: Vec<Vec<&str>> = Vec::new();
+    let mut inserted: Vec<Vec<&str>> = Vec::new();
+    let mut prev: Option<Op> = None;
+    for op in ops {
+        match (*op, prev) {
+            (Op::Delete(i), Some(Op::Delete(_))) => deleted.last_mut().unwrap().push(a[i]),
+            (Op::Delete(i), _) => deleted.push(vec![a[i]]),
+            (Op::Insert(j), Some(Op::Insert(_))) => inserted.last_mut().unwrap().push(b[j]),
+            (Op::Insert(j), _) => inserted.push(vec![b[j]]),
+            _ => {}
+        }
+        prev = Some(*op);
+    }
+
+    deleted
+        .iter()
+        .filter(|block| block.iter().filter(|l| !l.trim().is_empty()).count() >= 2)
+        .filter(|block| inserted.contains(block))
+        .count()
+}
+
+// --- JSON diff ---
+
+#[derive(Default)]
+struct JsonChanges {
+    added: Vec<(String, Value)>,
+    removed: Vec<(String, Value)>,
+    changed: Vec<(String, Value, Value)>,
+}
+
+fn json_diff(path: &str, a: &Value, b: &Value, out: &mut JsonChanges) {
+    match (a, b) {
+        (Value::Object(x), Value::Object(y)) => {
+            let keys: BTreeSet<&String> = x.keys().chain(y.keys()).collect();
+            for key in keys {
+                let child = format!("{}.{}", path, key);
+                match (x.get(key), y.get(key)) {
+                    (Some(old), Some(new)) => json_diff(&child, old, new, out),
+                    (Some(old), None) => out.removed.push((child, old.clone())),
+                    (None, Some(new)) => out.added.push((child, new.clone())),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(x), Value::Array(y)) => {
+            for i in 0..x.len().max(y.len()) {
+                let child = format!("{}[{}]", path, i);
+                match (x.get(i), y.get(i)) {
+                    (Some(old), Some(new)) => json_diff(&child, old, new, out),
+                    (Some(old), None) => out.removed.push((child, old.clone())),
+                    (None, Some(new)) => out.added.push((child, new.clone())),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if a != b => out.changed.push((path.to_string(), a.clone(), b.clone())),
+        _ => {}
+    }
+}
+
+fn compact(value: &Value) -> String {
+    const MAX: usize = 80;
+    let s = value.to_string();
+    if s.chars().count() > MAX {
+        format!("{}...", s.chars().take(MAX).collect::<String>())
+    } else {
+        s
+    }
+}
+
+fn render_json_diff(a: &Value, b: &Value, max_chars: usize) -> String {
+    let mut changes = JsonChanges::default();
+    json_diff("$", a, b, &mut changes);
+
+    let total = changes.added.len() + changes.removed.len() + changes.changed.len();
+    if total == 0 {
+        return "JSON diff summary: no differences".to_string();
+    }
+    let summary = format!(
+        "JSON diff summary: {} added, {} removed, {} changed paths",
+        changes.added.len(),
+        changes.removed.len(),
+        changes.changed.len()
+    );
+
+    let mut lines = Vec::with_capacity(total);
+    lines.extend(
+        changes
+            .added
+            .iter()
+            .map(|(p, v)| format!("+ {} = {}", p, compact(v))),
+    );
+    lines.extend(
+        changes
+            .removed
+            .iter()
+            .map(|(p, v)| format!("- {} = {}", p, compact(v))),
+    );
+    lines.extend(
+        changes
+            .changed
+            .iter()
+            .map(|(p, old, new)| format!("~ {}: {} -> {}", p, compact(old), compact(new))),
+    );
+    assemble(summary, "", &lines, max_chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_args(left: &str, right: &str) -> String {
+        serde_json::json!({ "left": { "text": left }, "right": { "text": right } }).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_markdown_section_summary() {
+        let left = "# Report\nintro\n## Risk\nexposure low\nno alerts\n## Outlook\nneutral\n";
+        let right = "# Report\nintro\n## Risk\nexposure high\nno alerts\n## Outlook\nneutral\n## Actions\nreduce size\n";
+
+        let out = DiffTool::new().call(&text_args(left, right)).await.unwrap();
+        assert!(out.starts_with("Diff summary (markdown): +3 -1 lines, 6 unchanged"));
+        assert!(out.contains("- modified \"## Risk\" (+1 -1)"));
+        assert!(out.contains("- added \"## Actions\" (+2 -0)"));
+        assert!(!out.contains("\"## Outlook\""));
+        assert!(out.contains("--- left\n+++ right\n@@ -1,7 +1,9 @@"));
+        assert!(out.contains("-exposure low\n+exposure high\n"));
+
+        // Deterministic output
+        assert_eq!(
+            out,
+            DiffTool::new().call(&text_args(left, right)).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_json_structural_diff() {
+        let left = r#"{"symbol":"SOL","price":101.5,"tags":["a","b"],"risk":{"level":"low"}}"#;
+        let right =
+            r#"{"symbol":"SOL","price":99.8,"tags":["a"],"risk":{"level":"low","score":3}}"#;
+
+        let out = DiffTool::new().call(&text_args(left, right)).await.unwrap();
+        assert!(out.starts_with("JSON diff summary: 1 added, 1 removed, 1 changed paths"));
+        assert!(out.contains("+ $.risk.score = 3"));
+        assert!(out.contains("- $.tags[1] = \"b\""));
+        assert!(out.contains("~ $.price: 101.5 -> 99.8"));
+
+        // Forced text mode falls back to a line diff
+        let args = serde_json::json!({ "left": { "text": left }, "right": { "text": right }, "mode": "text" });
+        let out = DiffTool::new().call(&args.to_string()).await.unwrap();
+        assert!(out.starts_with("Diff summary (text): +1 -1 lines"));
+    }
+
+    #[tokio::test]
+    async fn test_large_diff_is_truncated() {
+        let left: String = (0..2000).map(|i| format!("line {}\n", i)).collect();
+        let right: String = (0..2000).map(|i| format!("line {} changed\n", i)).collect();
+        let tool = DiffTool::new();
+
+        let out = tool.call(&text_args(&left, &right)).await.unwrap();
+        assert!(out.len() <= tool.config.max_output_chars);
+        assert!(out.starts_with("Diff summary (text): +2000 -2000 lines, 0 unchanged"));
+        assert!(out.contains("(diff truncated: "));
+        assert!(out.contains("```\n(diff truncated"));
+
+        let oversized = "x".repeat(tool.config.max_inline_bytes + 1);
+        assert!(tool.call(&text_args(&oversized, "x")).await.is_err());
+    }
+
+    #[test]
+    fn test_myers_minimal_script() {
+        let a = ["a", "b", "c", "a", "b", "b", "a"];
+        let b = ["c", "b", "a", "b", "a", "c"];
+        let ops = diff_lines(&a, &b, 100);
+        let edits = ops.iter().filter(|op| !matches!(op, Op::Equal(..))).count();
+        assert_eq!(edits, 5);
+    }
+}
@@ -0,0 +1,127 @@
+//! Conversational feedback capture
+//!
+//! Lets the model record feedback the user voices in chat ("that was wrong")
+//! against the session's latest response. Nothing is stored until the user
+//! explicitly confirms through the [`InteractionHandler`].
+
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::agent::core::InteractionHandler;
+use crate::agent::feedback::{FeedbackEntry, FeedbackStore, Rating};
+use crate::skills::tool::{parse_args, ArgsExt, Tool, ToolDefinition};
+
+/// Name under which the feedback tool is registered
+pub const FEEDBACK_TOOL: &str = "record_feedback";
+
+/// Tool that records user feedback on the latest response after confirmation
+pub struct FeedbackTool {
+    store: Arc<FeedbackStore>,
+    session_id: String,
+    confirm: Arc<dyn InteractionHandler>,
+}
+
+impl FeedbackTool {
+    /// Record into `store` for `session_id`, confirming each entry via `confirm`
+    pub fn new(
+        store: Arc<FeedbackStore>,
+        session_id: impl Into<String>,
+        confirm: Arc<dyn InteractionHandler>,
+    ) -> Self {
+        Self {
+            store,
+            session_id: session_id.into(),
+            confirm,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum FeedbackKind {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct FeedbackArgs {
+    /// Thumbs up or down
+    rating: FeedbackKind,
+    /// Optional score in [0, 1], overrides the thumbs value
+    #[serde(default)]
+    score: Option<f32>,
+    /// What the user said about the response
+    #[serde(default)]
+    comment: Option<String>,
+    /// Short labels, e.g. "wrong_price"
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[async_trait]
+impl Tool for FeedbackTool {
+    fn name(&self) -> String {
+        FEEDBACK_TOOL.to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Record the user's feedback on your previous response when they say it was good, wrong or unhelpful. The user is asked to confirm before anything is saved.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "rating": { "type": "string", "enum": ["up", "down"] },
+                    "score": { "type": "number", "minimum": 0, "maximum": 1 },
+                    "comment": { "type": "string" },
+                    "tags": { "type": "array", "items": { "type": "string" } }
+                },
+                "required": ["rating"]
+            }),
+            parameters_ts: Some("interface FeedbackArgs {\n  rating: 'up' | 'down';\n  score?: number; // 0..1\n  comment?: string;\n  tags?: string[];\n}".to_string()),
+            is_verified: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        let args: FeedbackArgs = parse_args(&self.name(), arguments)?;
+        let rating = match (args.score, args.rating) {
+            (Some(score), _) => {
+                self.require_range("score", score, 0.0, 1.0)?;
+                Rating::Score(score)
+            }
+            (None, FeedbackKind::Up) => Rating::Up,
+            (None, FeedbackKind::Down) => Rating::Down,
+        };
+
+        let response = self
+            .store
+            .latest_response(&self.session_id)
+            .ok_or_else(|| anyhow::anyhow!("No response to attach feedback to in this session"))?;
+
+        let question = format!(
+            "Save {} feedback on the previous response{}? (yes/no)",
+            if rating.is_positive() {
+                "positive"
+            } else {
+                "negative"
+            },
+            args.comment
+                .as_deref()
+                .map(|c| format!(" with comment \"{}\"", c))
+                .unwrap_or_default()
+        );
+        let answer = self.confirm.ask(&question).await?;
+        if !answer.trim().to_lowercase().starts_with('y') {
+            return Ok("Feedback not saved: the user did not confirm.".to_string());
+        }
+
+        let entry = FeedbackEntry::new(&response, rating, args.comment, args.tags);
+        self.store.submit(entry).await?;
+        Ok("Feedback saved. Thank the user.".to_string())
+    }
+}
@@ -0,0 +1,459 @@
+//! Read-only self-description for the model
+//!
+//! Answers "what can you do?" and "why won't you run this?" from the agent's
+//! actual configuration instead of leaving the model to guess, including which
+//! tools are currently short-circuited by their circuit breaker. Every response
+//! passes through [`redact`], so keys listed in [`REDACTED_FIELDS`] never reach
+//! the model even if a future section starts including them.
+
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::agent::core::{AgentConfig, ToolPolicy, MAX_AGENT_STEPS};
+use crate::agent::memory::Memory;
+use crate::skills::tool::{parse_args, BreakerState, Tool, ToolDefinition, ToolSet};
+
+/// Name under which the introspection tool is registered
+pub const INTROSPECT_TOOL: &str = "introspect";
+
+/// Object keys stripped from every introspection response (exact or `_`-suffixed match)
+pub const REDACTED_FIELDS: &[&str] = &[
+    "api_key",
+    "secret",
+    "token",
+    "password",
+    "private_key",
+    "credentials",
+    "path",
+    "preamble",
+    "extra_params",
+    "user_id",
+];
+
+/// Tool that lets the model query its own configuration, tools and limits
+pub struct IntrospectionTool {
+    config: AgentConfig,
+    tools: ToolSet,
+    provider: Option<ProviderInfo>,
+    memory: Option<Arc<dyn Memory>>,
+    session_id: Option<String>,
+    risk: Option<Value>,
+}
+
+struct ProviderInfo {
+    name: &'static str,
+    supports_tools: bool,
+    supports_streaming: bool,
+}
+
+impl IntrospectionTool {
+    /// Describe an agent with this config and toolset
+    pub fn new(config: &AgentConfig, tools: ToolSet) -> Self {
+        Self {
+            config: config.clone(),
+            tools,
+            provider: None,
+            memory: None,
+            session_id: None,
+            risk: None,
+        }
+    }
+
+    /// Report the provider's name and capabilities
+    pub fn with_provider(
+        mut self,
+        name: &'static str,
+        supports_tools: bool,
+        supports_streaming: bool,
+    ) -> Self {
+        self.provider = Some(ProviderInfo {
+            name,
+            supports_tools,
+            supports_streaming,
+        });
+        self
+    }
+
+    /// Report memory counts for the session's user
+    pub fn with_memory(mut self, memory: Arc<dyn Memory>, session_id: Option<String>) -> Self {
+        self.memory = Some(memory);
+        self.session_id = session_id;
+        self
+    }
+
+    /// Report the risk limits that apply to this agent (amounts only)
+    #[cfg(feature = "trading")]
+    pub fn with_risk_config(mut self, risk: &crate::trading::risk::RiskConfig) -> Self {
+        self.risk = Some(json!({
+            "max_single_trade_usd": risk.max_single_trade_usd.to_string(),
+            "max_daily_volume_usd": risk.max_daily_volume_usd.to_string(),
+            "max_slippage_percent": risk.max_slippage_percent.to_string(),
+            "min_liquidity_usd": risk.min_liquidity_usd.to_string(),
+            "trade_cooldown_secs": risk.trade_cooldown_secs,
+            "rug_detection": risk.enable_rug_detection,
+        }));
+        self
+    }
+
+    fn capabilities(&self) -> Value {
+        let mut out = json!({
+            "name": self.config.name,
+            "model": self.config.model,
+            "role": self.config.role,
+            "json_mode": self.config.json_mode,
+            "tools_enabled": !self.tools.is_empty(),
+            "tool_count": self.tools.len(),
+        });
+        if let Some(provider) = &self.provider {
+            out["provider"] = json!({
+                "name": provider.name,
+                "supports_tools": provider.supports_tools,
+                "supports_streaming": provider.supports_streaming,
+            });
+        }
+        if let Some(persona) = &self.config.persona {
+            out["persona"] = json!(persona.role);
+        }
+        out
+    }
+
+    async fn list_tools(&self) -> Value {
+        let mut names: Vec<&String> = self.tools.iter().map(|(name, _)| name).collect();
+        names.sort();
+
+        let mut tools = Vec::with_capacity(names.len());
+        for name in names {
+            let Some(tool) = self.tools.get(name) else {
+                continue;
+            };
+            let def = tool.definition().await;
+            let mut policy = self.config.tool_policy.policy_for(name).clone();
+            if def.is_binary && !def.is_verified && policy != ToolPolicy::Disabled {
+                policy = ToolPolicy::RequiresApproval;
+            }
+            let mut entry = json!({
+                "name": name,
+                "description": def.description.lines().next().unwrap_or_default(),
+                "policy": policy,
+            });
+            // Only breakers that are tripped or pinned are worth the model's attention
+            if let Some(status) = self.tools.breakers().status(name) {
+                if status.state != BreakerState::Closed || status.forced.is_some() {
+                    entry["breaker"] = json!(status);
+                }
+            }
+            tools.push(entry);
+        }
+        json!({ "tools": tools })
+    }
+
+    fn limits(&self) -> Value {
+        let mut out = json!({
+            "max_steps": MAX_AGENT_STEPS,
+            "max_parallel_tools": self.config.max_parallel_tools,
+            "max_tool_output_chars": self.config.max_tool_output_chars,
+            "max_history_messages": self.config.max_history_messages,
+            "max_context_tokens": self.config.max_context_tokens,
+            "max_response_tokens": self.config.max_tokens,
+        });
+        if let Some(risk) = &self.risk {
+            out["risk"] = json!({ "role": self.config.role, "limits": risk });
+        }
+        out
+    }
+
+    async fn memory_status(&self) -> anyhow::Result<Value> {
+        let Some(memory) = &self.memory else {
+            return Ok(json!({ "configured": false }));
+        };
+
+        let user_id = self.session_id.as_deref().unwrap_or("default");
+        let status = memory.status(user_id, Some(&self.config.name)).await;
+        let mut out = json!({
+            "configured": true,
+            "short_term_entries": status.short_term,
+            "long_term_entries": status.long_term,
+        });
+        if let Some(session_id) = &self.session_id {
+            let last = memory
+                .retrieve_session(session_id)
+                .await?
+                .map(|s| s.updated_at.to_rfc3339());
+            out["last_checkpoint"] = json!(last);
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum IntrospectAction {
+    Capabilities,
+    ListTools,
+    Limits,
+    MemoryStatus,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct IntrospectArgs {
+    action: IntrospectAction,
+}
+
+#[async_trait]
+impl Tool for IntrospectionTool {
+    fn name(&self) -> String {
+        INTROSPECT_TOOL.to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Look up your own configuration: capabilities, available tools and their approval policy, limits, and memory status. Use this instead of guessing what you can do.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["capabilities", "list_tools", "limits", "memory_status"]
+                    }
+                },
+                "required": ["action"]
+            }),
+            parameters_ts: Some("interface IntrospectArgs {\n  action: 'capabilities' | 'list_tools' | 'limits' | 'memory_status';\n}".to_string()),
+            is_verified: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        let args: IntrospectArgs = parse_args(&self.name(), arguments)?;
+        let mut out = match args.action {
+            IntrospectAction::Capabilities => self.capabilities(),
+            IntrospectAction::ListTools => self.list_tools().await,
+            IntrospectAction::Limits => self.limits(),
+            IntrospectAction::MemoryStatus => self.memory_status().await?,
+        };
+        redact(&mut out);
+        Ok(serde_json::to_string_pretty(&out)?)
+    }
+}
+
+/// Remove every object key matching [`REDACTED_FIELDS`]
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|key, _| !is_redacted(key));
+            map.values_mut().for_each(redact);
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+fn is_redacted(key: &str) -> bool {
+    let key = key.to_lowercase();
+    REDACTED_FIELDS
+        .iter()
+        .any(|field| key == *field || key.ends_with(&format!("_{}", field)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::StubProvider;
+    use crate::agent::core::{Agent, RiskyToolPolicy};
+    use crate::agent::memory::{MemoryManager, MemoryStatus, ShortTermMemory};
+    use crate::agent::message::Message;
+    use crate::agent::provider::{ChatRequest, Provider};
+    use crate::agent::session::{AgentSession, SessionStatus};
+    use crate::agent::streaming::{MockStreamBuilder, StreamingResponse};
+    #[cfg(feature = "trading")]
+    use crate::trading::risk::RiskConfig;
+    use std::collections::HashMap;
+
+    /// Cold tier that keeps sessions and reports a fixed long-term count
+    #[derive(Default)]
+    struct ColdTier {
+        sessions: parking_lot::Mutex<HashMap<String, AgentSession>>,
+    }
+
+    #[async_trait]
+    impl Memory for ColdTier {
+        async fn store(&self, _: &str, _: Option<&str>, _: Message) -> crate::error::Result<()> {
+            Ok(())
+        }
+
+        async fn retrieve(&self, _: &str, _: Option<&str>, _: usize) -> Vec<Message> {
+            Vec::new()
+        }
+
+        async fn clear(&self, _: &str, _: Option<&str>) -> crate::error::Result<()> {
+            Ok(())
+        }
+
+        async fn undo(&self, _: &str, _: Option<&str>) -> crate::error::Result<Option<Message>> {
+            Ok(None)
+        }
+
+        async fn store_session(&self, session: AgentSession) -> crate::error::Result<()> {
+            self.sessions.lock().insert(session.id.clone(), session);
+            Ok(())
+        }
+
+        async fn retrieve_session(&self, id: &str) -> crate::error::Result<Option<AgentSession>> {
+            Ok(self.sessions.lock().get(id).cloned())
+        }
+
+        async fn status(&self, _: &str, _: Option<&str>) -> MemoryStatus {
+            MemoryStatus {
+                short_term: None,
+                long_term: Some(3),
+            }
+        }
+    }
+
+    async fn introspect(agent: &Agent<StubProvider>, action: &str) -> (String, Value) {
+        let args = json!({ "action": action }).to_string();
+        let raw = agent.call_tool(INTROSPECT_TOOL, &args).await.unwrap();
+        let value = serde_json::from_str(&raw).unwrap();
+        (raw, value)
+    }
+
+    #[cfg(feature = "trading")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fully_configured_agent() {
+        let dir = tempfile::tempdir().unwrap();
+        let stm_path = dir.path().join("stm.json");
+        let stm = Arc::new(ShortTermMemory::new(50, 10, &stm_path).await);
+        stm.store("s1", Some("agent"), Message::user("hi"))
+            .await
+            .unwrap();
+        stm.store("s1", Some("agent"), Message::assistant("hello"))
+            .await
+            .unwrap();
+        stm.store(
+            "other",
+            Some("agent"),
+            Message::user("other user's wallet seed"),
+        )
+        .await
+        .unwrap();
+        let memory: Arc<dyn Memory> =
+            Arc::new(MemoryManager::new(stm, Arc::new(ColdTier::default())));
+
+        let mut policy = RiskyToolPolicy::default();
+        policy
+            .overrides
+            .insert("remember_this".to_string(), ToolPolicy::Disabled);
+        policy
+            .overrides
+            .insert("search_history".to_string(), ToolPolicy::RequiresApproval);
+
+        let agent = Agent::builder(StubProvider)
+            .model("gpt-4o")
+            .system_prompt("TOP SECRET PREAMBLE")
+            .extra_params(
+                json!({ "api_key": "sk-live-123", "nested": { "private_key": "0xdeadbeef" } }),
+            )
+            .tool_policy(policy)
+            .json_mode(true)
+            .with_memory(Arc::clone(&memory))
+            .session_id("s1")
+            .risk_config(RiskConfig::default())
+            .auto_load_skills(false)
+            .build()
+            .unwrap();
+        agent
+            .checkpoint(&[], 1, SessionStatus::Thinking)
+            .await
+            .unwrap();
+
+        let (caps_raw, caps) = introspect(&agent, "capabilities").await;
+        assert_eq!(caps["model"], "gpt-4o");
+        assert_eq!(caps["json_mode"], true);
+        assert_eq!(caps["tools_enabled"], true);
+        assert_eq!(caps["provider"]["name"], "stub");
+
+        let (tools_raw, tools) = introspect(&agent, "list_tools").await;
+        let policies: HashMap<&str, &str> = tools["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| (t["name"].as_str().unwrap(), t["policy"].as_str().unwrap()))
+            .collect();
+        assert_eq!(policies["remember_this"], "disabled");
+        assert_eq!(policies["search_history"], "requires_approval");
+        assert_eq!(policies["fetch_document"], "auto");
+
+        let (limits_raw, limits) = introspect(&agent, "limits").await;
+        assert_eq!(limits["max_steps"], MAX_AGENT_STEPS);
+        assert_eq!(limits["risk"]["limits"]["max_single_trade_usd"], "10000.0");
+
+        let (memory_raw, status) = introspect(&agent, "memory_status").await;
+        assert_eq!(status["short_term_entries"], 2);
+        assert_eq!(status["long_term_entries"], 3);
+        assert!(status["last_checkpoint"].is_string());
+
+        // Nothing sensitive leaks through any section
+        let stm_path = stm_path.to_string_lossy();
+        for raw in [caps_raw, tools_raw, limits_raw, memory_raw] {
+            for secret in [
+                "sk-live-123",
+                "0xdeadbeef",
+                "TOP SECRET",
+                "wallet seed",
+                "\"s1\"",
+                stm_path.as_ref(),
+            ] {
+                assert!(!raw.contains(secret), "leaked {:?} in {}", secret, raw);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_minimal_agent_omits_sections() {
+        let agent = Agent::builder(StubProvider)
+            .auto_load_skills(false)
+            .build()
+            .unwrap();
+
+        let (_, caps) = introspect(&agent, "capabilities").await;
+        assert_eq!(caps["tool_count"], 0);
+        assert_eq!(caps["tools_enabled"], false);
+        assert!(caps.get("persona").is_none());
+
+        let (_, tools) = introspect(&agent, "list_tools").await;
+        assert_eq!(tools["tools"], json!([]));
+
+        let (_, limits) = introspect(&agent, "limits").await;
+        assert!(limits.get("risk").is_none());
+        assert_eq!(limits["max_parallel_tools"], 5);
+
+        let (_, status) = introspect(&agent, "memory_status").await;
+        assert_eq!(status, json!({ "configured": false }));
+
+        let opted_out = Agent::builder(StubProvider)
+            .auto_load_skills(false)
+            .introspection(false)
+            .build()
+            .unwrap();
+        assert!(!opted_out.has_tool(INTROSPECT_TOOL));
+    }
+
+    #[test]
+    fn test_redact_nested_keys() {
+        let mut value = json!({
+            "max_tokens": 10,
+            "api_key": "x",
+            "nested": [{ "session_token": "y", "db_path": "/tmp/a", "keep": 1 }],
+        });
+        redact(&mut value);
+        assert_eq!(
+            value,
+            json!({ "max_tokens": 10, "nested": [{ "keep": 1 }] })
+        );
+    }
+}
@@ -1,18 +1,37 @@
 use async_trait::async_trait;
+use schemars::JsonSchema;
 use serde::Deserialize;
 use std::sync::Arc;
 use crate::error::Error;
-use crate::skills::tool::{Tool, ToolDefinition};
-use crate::agent::memory::Memory;
+use crate::skills::tool::{parse_args, Tool, ToolDefinition};
+use crate::agent::memory::{current_as_of, parse_as_of, Memory, RememberOutcome};
+
+/// A call's own `as_of`, else the one pinned by [`crate::agent::memory::with_as_of`]
+fn resolve_as_of(value: Option<&str>) -> crate::error::Result<Option<i64>> {
+    Ok(value.map(parse_as_of).transpose()?.or_else(current_as_of))
+}
 
 /// Tool for searching historical conversations and knowledge
 pub struct SearchHistoryTool {
     memory: Arc<dyn Memory>,
+    user_id: String,
+    agent_id: Option<String>,
 }
 
 impl SearchHistoryTool {
     pub fn new(memory: Arc<dyn Memory>) -> Self {
-        Self { memory }
+        Self {
+            memory,
+            user_id: "default".to_string(),
+            agent_id: None,
+        }
+    }
+
+    /// Search only the memories of this user and agent (default: user "default", no agent)
+    pub fn with_scope(mut self, user_id: impl Into<String>, agent_id: Option<String>) -> Self {
+        self.user_id = user_id.into();
+        self.agent_id = agent_id;
+        self
     }
 }
 
@@ -37,37 +56,35 @@ impl Tool for SearchHistoryTool {
                     "limit": {
                         "type": "integer",
                         "description": "Max number of results to return (default: 5)"
+                    },
+                    "as_of": {
+                        "type": "string",
+                        "description": "Search memory as it was at this time: unix seconds, RFC 3339 or YYYY-MM-DD (optional)"
                     }
                 },
                 "required": ["query"]
             }),
-            parameters_ts: Some("interface SearchArgs {\n  query: string; // The search query\n  limit?: number; // Max results (default: 5)\n}".to_string()),
-            is_binary: false,
+            parameters_ts: Some("interface SearchArgs {\n  query: string; // The search query\n  limit?: number; // Max results (default: 5)\n  as_of?: string; // Past time to search at\n}".to_string()),
             is_verified: true,
+            ..Default::default()
         }
     }
 
     async fn call(&self, arguments: &str) -> anyhow::Result<String> {
-        #[derive(Deserialize)]
+        #[derive(Deserialize, JsonSchema)]
         struct Args {
             query: String,
             #[serde(default = "default_limit")]
             limit: usize,
+            #[serde(default)]
+            as_of: Option<String>,
         }
         fn default_limit() -> usize { 5 }
 
-        let args: Args = serde_json::from_str(arguments)
-            .map_err(|e| Error::ToolArguments {
-                tool_name: self.name(),
-                message: e.to_string(),
-            })?;
-
-        // Context is currently not passed to tools, using placeholders.
-        // In a multi-user environment, the Tool trait should be updated to accept context.
-        let user_id = "default"; 
-        let agent_id = None;
+        let args: Args = parse_args(&self.name(), arguments)?;
 
-        let results = self.memory.search(user_id, agent_id, &args.query, args.limit).await
+        let as_of = resolve_as_of(args.as_of.as_deref())?;
+        let results = self.memory.search_as_of(&self.user_id, self.agent_id.as_deref(), &args.query, args.limit, as_of).await
             .map_err(|e| Error::Internal(format!("Search failed: {}", e)))?;
 
         if results.is_empty() {
@@ -110,6 +127,10 @@ impl Tool for RememberThisTool {
         "remember_this".to_string()
     }
 
+    fn writes_memory(&self) -> bool {
+        true
+    }
+
     async fn definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: self.name(),
@@ -134,13 +155,13 @@ impl Tool for RememberThisTool {
                 "required": ["title", "content"]
             }),
             parameters_ts: Some("interface RememberArgs {\n  title: string; // Short title\n  content: string; // Detail information\n  collection?: string; // Category (default: 'general')\n}".to_string()),
-            is_binary: false,
             is_verified: true,
+            ..Default::default()
         }
     }
 
     async fn call(&self, arguments: &str) -> anyhow::Result<String> {
-        #[derive(Deserialize)]
+        #[derive(Deserialize, JsonSchema)]
         struct Args {
             title: String,
             content: String,
@@ -149,19 +170,26 @@ impl Tool for RememberThisTool {
         }
         fn default_coll() -> String { "general".to_string() }
 
-        let args: Args = serde_json::from_str(arguments)
-            .map_err(|e| Error::ToolArguments {
-                tool_name: self.name(),
-                message: e.to_string(),
-            })?;
+        let args: Args = parse_args(&self.name(), arguments)?;
 
         // Context is currently not passed to tools, using placeholders.
         let user_id = "default";
         let agent_id = None;
 
-        self.memory.store_knowledge(user_id, agent_id, &args.title, &args.content, &args.collection).await?;
-
-        Ok(format!("Memory successfully saved as '{}' in collection '{}'.", args.title, args.collection))
+        let outcome = self.memory.remember(user_id, agent_id, &args.title, &args.content, &args.collection).await?;
+
+        // Say when nothing new was saved, so the model stops re-remembering it
+        Ok(match outcome {
+            RememberOutcome::Stored => format!("Memory successfully saved as '{}' in collection '{}'.", args.title, args.collection),
+            RememberOutcome::Merged { title } => format!(
+                "Merged into existing memory '{}', which already covered this; the new details were appended. No need to save it again.",
+                title
+            ),
+            RememberOutcome::Skipped { title } => format!(
+                "Not saved: existing memory '{}' already says this. No need to remember it again.",
+                title
+            ),
+        })
     }
 }
 
@@ -191,23 +219,25 @@ impl Tool for TieredSearchTool {
                 "type": "object",
                 "properties": {
                     "query": { "type": "string", "description": "Search query" },
-                    "limit": { "type": "integer", "description": "Max results (default: 5)" }
+                    "limit": { "type": "integer", "description": "Max results (default: 5)" },
+                    "as_of": { "type": "string", "description": "Answer as the memory was at this time: unix seconds, RFC 3339 or YYYY-MM-DD (optional)" }
                 },
                 "required": ["query"]
             }),
-            parameters_ts: Some("interface TieredSearchArgs {\n  query: string;\n  limit?: number;\n}".to_string()),
-            is_binary: false,
+            parameters_ts: Some("interface TieredSearchArgs {\n  query: string;\n  limit?: number;\n  as_of?: string;\n}".to_string()),
             is_verified: true,
+            ..Default::default()
         }
     }
 
     async fn call(&self, arguments: &str) -> anyhow::Result<String> {
-        #[derive(Deserialize)]
-        struct Args { query: String, #[serde(default = "default_limit")] limit: usize }
+        #[derive(Deserialize, JsonSchema)]
+        struct Args { query: String, #[serde(default = "default_limit")] limit: usize, #[serde(default)] as_of: Option<String> }
         fn default_limit() -> usize { 5 }
 
-        let args: Args = serde_json::from_str(arguments)?;
-        let results = self.memory.search("default", None, &args.query, args.limit).await?;
+        let args: Args = parse_args(&self.name(), arguments)?;
+        let as_of = resolve_as_of(args.as_of.as_deref())?;
+        let results = self.memory.search_as_of("default", None, &args.query, args.limit, as_of).await?;
 
         if results.is_empty() { return Ok("No results found.".to_string()); }
 
@@ -255,22 +285,24 @@ impl Tool for FetchDocumentTool {
                 "type": "object",
                 "properties": {
                     "collection": { "type": "string", "description": "Document collection" },
-                    "path": { "type": "string", "description": "Document virtual path" }
+                    "path": { "type": "string", "description": "Document virtual path" },
+                    "as_of": { "type": "string", "description": "Answer as the memory was at this time: unix seconds, RFC 3339 or YYYY-MM-DD (optional)" }
                 },
                 "required": ["collection", "path"]
             }),
-            parameters_ts: Some("interface FetchArgs {\n  collection: string;\n  path: string;\n}".to_string()),
-            is_binary: false,
+            parameters_ts: Some("interface FetchArgs {\n  collection: string;\n  path: string;\n  as_of?: string;\n}".to_string()),
             is_verified: true,
+            ..Default::default()
         }
     }
 
     async fn call(&self, arguments: &str) -> anyhow::Result<String> {
-        #[derive(Deserialize)]
-        struct Args { collection: String, path: String }
-        let args: Args = serde_json::from_str(arguments)?;
+        #[derive(Deserialize, JsonSchema)]
+        struct Args { collection: String, path: String, #[serde(default)] as_of: Option<String> }
+        let args: Args = parse_args(&self.name(), arguments)?;
 
-        let doc = self.memory.fetch_document(&args.collection, &args.path).await?;
+        let as_of = resolve_as_of(args.as_of.as_deref())?;
+        let doc = self.memory.fetch_document_as_of(&args.collection, &args.path, as_of).await?;
         match doc {
             Some(d) => Ok(format!("# {}\n\n{}", d.title, d.content)),
             None => Ok("Document not found.".to_string()),
@@ -4,22 +4,62 @@
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use futures::FutureExt;
+use tokio::sync::OnceCell;
 
 use crate::error::Error;
+use crate::skills::SkillLoader;
+use breaker::ToolBreaker;
 
+pub mod args;
+pub mod breaker;
+pub mod calculator;
 pub mod code_interpreter;
+pub mod coerce;
+pub mod compress;
 pub mod cron;
 pub mod delegation;
+pub mod diff;
+pub mod feedback;
+pub mod introspection;
 pub mod memory;
+pub mod recall;
+pub mod schema;
+#[cfg(feature = "trading")]
+pub mod strategy_history;
+pub mod subagent;
+pub mod task_board;
 
+pub use args::{parse_args, parse_args_coerced, parse_args_lenient, ArgsExt, ArgumentError};
+pub use breaker::{
+    BreakerConfig, BreakerOverride, BreakerState, BreakerStatus, CallOutcome, ToolBreakers,
+};
+pub use calculator::{CalculatorTool, CALCULATOR_TOOL};
+pub use coerce::coerce_args;
+pub use compress::{CompressedOutput, CompressionConfig};
 pub use cron::CronTool;
 pub use delegation::DelegateTool;
+pub use diff::{DiffConfig, DiffTool};
+pub use feedback::{FeedbackTool, FEEDBACK_TOOL};
+pub use introspection::IntrospectionTool;
 pub use memory::{RememberThisTool, SearchHistoryTool, TieredSearchTool, FetchDocumentTool};
+pub use recall::{RecallToolOutputTool, RECALL_TOOL_OUTPUT_TOOL};
+#[cfg(feature = "trading")]
+pub use strategy_history::{StrategyHistoryTool, STRATEGY_HISTORY_TOOL};
+pub use subagent::{SpawnSubagentTool, SubagentConfig, SubagentReport, TokenBudget};
+pub use task_board::{ClaimTaskTool, PostTaskTool, TaskStatusTool};
+
+/// First line of the system message [`ToolSet::render_catalog`] produces
+pub const TOOL_CATALOG_HEADING: &str = "## Tool Definitions (TypeScript)";
 
 /// Definition of a tool that can be sent to the LLM
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Set `name`, `description` and `parameters`, and fill the rest with
+/// `..Default::default()` so new optional fields don't break existing tools.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ToolDefinition {
     /// Name of the tool
     pub name: String,
@@ -35,6 +75,159 @@ pub struct ToolDefinition {
     /// Whether the tool is verified/trusted
     #[serde(default)]
     pub is_verified: bool,
+    /// Few-shot usage examples shown to the model
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<ToolExample>,
+    /// Fields to keep when a large JSON result is reduced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_projection: Option<Vec<String>>,
+    /// Secret keys the tool needs at call time (names only, never values)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_secrets: Vec<String>,
+    /// Deterministic and non-mutating, so results may be cached and calls repeated
+    #[serde(default)]
+    pub side_effect_free: bool,
+    /// JSON Schema of the result, for tools that return structured JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+}
+
+/// A worked example of calling a tool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolExample {
+    /// When to use the tool this way
+    pub description: String,
+    /// Example arguments (must satisfy the tool's parameter schema)
+    pub arguments: serde_json::Value,
+    /// Short summary of what the tool returns for these arguments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_summary: Option<String>,
+}
+
+impl ToolExample {
+    /// Create a new example
+    pub fn new(description: impl Into<String>, arguments: serde_json::Value) -> Self {
+        Self {
+            description: description.into(),
+            arguments,
+            result_summary: None,
+        }
+    }
+
+    /// Set the result summary
+    pub fn with_result(mut self, summary: impl Into<String>) -> Self {
+        self.result_summary = Some(summary.into());
+        self
+    }
+
+    /// Render as a compact `// Example:` block
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "// Example: {}\n// {}\n",
+            self.description,
+            serde_json::to_string(&self.arguments).unwrap_or_default()
+        );
+        if let Some(summary) = &self.result_summary {
+            out.push_str(&format!("// => {}\n", summary));
+        }
+        out
+    }
+}
+
+impl ToolDefinition {
+    /// Add a usage example
+    pub fn with_example(mut self, example: ToolExample) -> Self {
+        self.examples.push(example);
+        self
+    }
+
+    /// Keep only these fields when a large JSON result is reduced
+    pub fn with_result_projection(mut self, fields: Vec<String>) -> Self {
+        self.result_projection = Some(fields);
+        self
+    }
+
+    /// Declare the secrets this tool resolves at call time
+    pub fn with_required_secrets(mut self, keys: Vec<String>) -> Self {
+        self.required_secrets = keys;
+        self
+    }
+
+    /// Mark the tool as deterministic and non-mutating
+    pub fn with_side_effect_free(mut self) -> Self {
+        self.side_effect_free = true;
+        self
+    }
+
+    /// Declare the JSON Schema of the tool's result
+    pub fn with_output_schema(mut self, schema: serde_json::Value) -> Self {
+        self.output_schema = Some(schema);
+        self
+    }
+
+    /// Check that every example satisfies the parameter schema
+    pub fn validate_examples(&self) -> Result<(), Error> {
+        for (i, example) in self.examples.iter().enumerate() {
+            schema::validate(&example.arguments, &self.parameters).map_err(|e| Error::ToolArguments {
+                tool_name: self.name.clone(),
+                message: format!("example {} ('{}') does not match schema: {}", i, example.description, e),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Description with the first example folded in, for providers that only see descriptions
+    pub fn description_with_example(&self) -> String {
+        match self.examples.first() {
+            Some(example) => format!(
+                "{} Example ({}): {}",
+                self.description,
+                example.description,
+                serde_json::to_string(&example.arguments).unwrap_or_default()
+            ),
+            None => self.description.clone(),
+        }
+    }
+
+    /// Example whose argument keys overlap most with the attempted arguments
+    pub fn closest_example(&self, attempted: &serde_json::Value) -> Option<&ToolExample> {
+        let attempted_keys: Vec<&String> = attempted
+            .as_object()
+            .map(|m| m.keys().collect())
+            .unwrap_or_default();
+
+        self.examples.iter().enumerate().max_by_key(|(i, example)| {
+            let overlap = example
+                .arguments
+                .as_object()
+                .map(|m| m.keys().filter(|k| attempted_keys.contains(k)).count())
+                .unwrap_or(0);
+            // Prefer earlier examples on ties
+            (overlap, std::cmp::Reverse(*i))
+        })
+        .map(|(_, example)| example)
+    }
+}
+
+/// Drop examples that don't satisfy the parameter schema, returning why
+///
+/// Bad examples would teach the model wrong usage, so they are never rendered.
+/// Fail loudly on invalid examples in debug builds; warn and drop them in release builds
+fn report_invalid_examples(tool: &str, error: &Error) {
+    if cfg!(debug_assertions) {
+        panic!("Tool '{}' has invalid examples: {}", tool, error);
+    }
+    tracing::warn!(tool = %tool, "Dropping invalid tool examples: {}", error);
+}
+
+fn drop_invalid_examples(def: &mut ToolDefinition) -> Result<(), Error> {
+    let result = def.validate_examples();
+    if result.is_err() {
+        let parameters = def.parameters.clone();
+        def.examples
+            .retain(|example| schema::validate(&example.arguments, &parameters).is_ok());
+    }
+    result
 }
 
 /// Trait for implementing tools that AI agents can call
@@ -49,13 +242,121 @@ pub trait Tool: Send + Sync {
 
     /// Execute the tool with the given arguments (JSON string)
     async fn call(&self, arguments: &str) -> anyhow::Result<String>;
+
+    /// Let [`ToolSet::call`] run [`coerce_args`] over the arguments against the
+    /// definition's parameter schema first; return `false` to receive them verbatim
+    fn coerce_arguments(&self) -> bool {
+        true
+    }
+
+    /// Whether the tool writes to the agent's long-term memory; such tools are never given to sub-agents
+    fn writes_memory(&self) -> bool {
+        false
+    }
 }
 
+/// Default number of examples rendered per tool
+const DEFAULT_MAX_EXAMPLES_PER_TOOL: usize = 2;
+
+/// Default token budget for all rendered examples
+const DEFAULT_EXAMPLE_TOKEN_BUDGET: usize = 1000;
+
+/// A registered tool with its per-tool state
+#[derive(Clone)]
+struct ToolEntry {
+    tool: Arc<dyn Tool>,
+    /// Definition computed on first use; replaced, not cleared, on invalidation
+    definition: Arc<OnceCell<ToolDefinition>>,
+    breaker: Arc<ToolBreaker>,
+    /// Namespace the tool was added under, prefixed to its definition's name
+    namespace: Option<String>,
+}
+
+impl ToolEntry {
+    async fn definition(&self) -> ToolDefinition {
+        self.definition
+            .get_or_init(|| async {
+                let mut def = self.tool.definition().await;
+                if let Err(e) = drop_invalid_examples(&mut def) {
+                    report_invalid_examples(&def.name, &e);
+                }
+                self.namespaced(def)
+            })
+            .await
+            .clone()
+    }
+
+    /// Cache the definition now if it is ready without waiting, dropping invalid examples
+    ///
+    /// Tools whose definition has to wait are checked on first use instead.
+    fn check_examples(&self) -> Result<(), Error> {
+        let Some(mut def) = self.tool.definition().now_or_never() else {
+            return Ok(());
+        };
+        let result = drop_invalid_examples(&mut def);
+        let _ = self.definition.set(self.namespaced(def));
+        result
+    }
+
+    fn namespaced(&self, mut def: ToolDefinition) -> ToolDefinition {
+        if let Some(namespace) = &self.namespace {
+            def.name = namespaced(namespace, &def.name);
+        }
+        def
+    }
+}
+
+/// A [`SkillLoader`] whose skills a toolset mirrors
+#[derive(Clone)]
+struct SkillWatch {
+    loader: Weak<SkillLoader>,
+    namespace: String,
+    /// Loader generation and skill count at the last sync
+    seen: (u64, usize),
+}
+
+impl SkillWatch {
+    /// The loader, if it changed since the last sync
+    fn changed(&self) -> Option<Arc<SkillLoader>> {
+        let loader = self.loader.upgrade()?;
+        let revision = (loader.generation(), loader.len());
+        (revision != self.seen).then_some(loader)
+    }
+}
+
+/// Separator between a namespace and a tool name
+///
+/// Not a dot: OpenAI and Anthropic only accept `[a-zA-Z0-9_-]` in tool names.
+pub const NAMESPACE_SEPARATOR: &str = "__";
+
+/// Name of `name` added under `namespace`, e.g. `skills__get_token_price`
+pub fn namespaced(namespace: &str, name: &str) -> String {
+    format!("{}{}{}", namespace, NAMESPACE_SEPARATOR, name)
+}
+
+/// The tools an agent can call
+///
+/// Reads are lock-free: the tool map is an immutable snapshot shared by every
+/// clone, and each entry carries its own definition cache and breaker, so
+/// [`definitions`](Self::definitions) and [`call`](Self::call) never contend
+/// across tools. Mutation ([`add`](Self::add), [`remove`](Self::remove),
+/// [`invalidate_definitions`](Self::invalidate_definitions)) copies the map on
+/// write; it is cheap but belongs at build time, not in the chat loop.
 #[derive(Clone)]
 pub struct ToolSet {
-    tools: HashMap<String, Arc<dyn Tool>>,
-    /// Cached definitions to avoid async calls during prompt generation
-    cached_definitions: Arc<parking_lot::RwLock<HashMap<String, ToolDefinition>>>,
+    tools: Arc<HashMap<String, ToolEntry>>,
+    /// Max examples rendered per tool in the injected prompt
+    max_examples_per_tool: usize,
+    /// Approximate token budget for all rendered examples
+    example_token_budget: usize,
+    /// Per-tool circuit breakers, shared between clones
+    breakers: ToolBreakers,
+    /// Limit for tools without their own timeout
+    default_timeout: Option<Duration>,
+    /// Per-tool timeouts, overriding the default
+    timeouts: Arc<HashMap<String, Duration>>,
+    /// Loader whose skills are kept in sync by [`sync_skills`](Self::sync_skills)
+    skills: Option<SkillWatch>,
 }
 
 impl Default for ToolSet {
@@ -68,61 +369,364 @@ impl ToolSet {
     /// Create an empty toolset
     pub fn new() -> Self {
         Self {
-            tools: HashMap::new(),
-            cached_definitions: Arc::new(parking_lot::RwLock::new(HashMap::new())),
+            tools: Arc::new(HashMap::new()),
+            max_examples_per_tool: DEFAULT_MAX_EXAMPLES_PER_TOOL,
+            example_token_budget: DEFAULT_EXAMPLE_TOKEN_BUDGET,
+            breakers: ToolBreakers::default(),
+            default_timeout: None,
+            timeouts: Arc::new(HashMap::new()),
+            skills: None,
         }
     }
 
-    /// Add a tool to the set
-    pub fn add<T: Tool + 'static>(&mut self, tool: T) -> &mut Self {
-        self.tools.insert(tool.name().to_string(), Arc::new(tool));
+    /// Fail calls that run longer than `timeout`, unless the tool has its own limit
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail calls to `name` that run longer than `timeout`
+    pub fn with_tool_timeout(mut self, name: impl Into<String>, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.timeouts).insert(name.into(), timeout);
+        self
+    }
+
+    /// Time limit applied to calls to `name`, if any
+    pub fn timeout(&self, name: &str) -> Option<Duration> {
+        self.timeouts.get(name).copied().or(self.default_timeout)
+    }
+
+    /// Set how many examples are rendered per tool and their total token budget
+    ///
+    /// Examples are the first thing dropped when the budget runs out; tool
+    /// descriptions and interfaces are always rendered.
+    pub fn set_example_limits(&mut self, max_per_tool: usize, token_budget: usize) -> &mut Self {
+        self.max_examples_per_tool = max_per_tool;
+        self.example_token_budget = token_budget;
         self
     }
 
-    /// Add a shared tool to the set
+    /// Circuit breakers guarding [`call`](Self::call)
+    pub fn breakers(&self) -> &ToolBreakers {
+        &self.breakers
+    }
+
+    /// The error a call to `name` would be short-circuited with right now, if any
+    pub fn short_circuit(&self, name: &str) -> Option<Error> {
+        self.entry(name)?.breaker.short_circuit()
+    }
+
+    /// Add a tool to the set, replacing any tool with the same name
+    ///
+    /// Use [`try_add`](Self::try_add) to reject duplicates, or
+    /// [`replace`](Self::replace) when overriding a tool on purpose.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, if an example doesn't match the tool's parameter schema.
+    /// Release builds drop the example with a warning.
+    pub fn add<T: Tool + 'static>(&mut self, tool: T) -> &mut Self {
+        self.add_shared(Arc::new(tool))
+    }
+
+    /// Add a shared tool to the set, replacing any tool with the same name
     pub fn add_shared(&mut self, tool: Arc<dyn Tool>) -> &mut Self {
-        self.tools.insert(tool.name().to_string(), tool);
+        let name = tool.name();
+        if self.insert(name.clone(), None, tool).is_some() {
+            tracing::warn!(tool = %name, "Tool registered twice, replacing the earlier one");
+        }
         self
     }
 
+    /// Add a tool, failing with [`Error::ToolNameConflict`] if the name is taken
+    pub fn try_add<T: Tool + 'static>(&mut self, tool: T) -> Result<&mut Self, Error> {
+        self.try_add_shared(Arc::new(tool))
+    }
+
+    /// Add a shared tool, failing with [`Error::ToolNameConflict`] if the name is taken
+    /// or with [`Error::ToolArguments`] if an example doesn't match its schema
+    pub fn try_add_shared(&mut self, tool: Arc<dyn Tool>) -> Result<&mut Self, Error> {
+        let name = tool.name();
+        if self.tools.contains_key(&name) {
+            return Err(Error::ToolNameConflict(name));
+        }
+        let entry = self.new_entry(&name, None, tool);
+        entry.check_examples()?;
+        Arc::make_mut(&mut self.tools).insert(name, entry);
+        Ok(self)
+    }
+
+    /// Add a tool under `namespace`, e.g. `skills__get_token_price`
+    ///
+    /// Namespaced tools never shadow bare ones: [`call`](Self::call) prefers an
+    /// exact match and only falls back to the bare name when it is unambiguous.
+    pub fn add_namespaced<T: Tool + 'static>(
+        &mut self,
+        namespace: &str,
+        tool: T,
+    ) -> Result<&mut Self, Error> {
+        self.add_namespaced_shared(namespace, Arc::new(tool))
+    }
+
+    /// Add a shared tool under `namespace`
+    pub fn add_namespaced_shared(
+        &mut self,
+        namespace: &str,
+        tool: Arc<dyn Tool>,
+    ) -> Result<&mut Self, Error> {
+        let name = namespaced(namespace, &tool.name());
+        if self.tools.contains_key(&name) {
+            return Err(Error::ToolNameConflict(name));
+        }
+        self.insert(name, Some(namespace.to_string()), tool);
+        Ok(self)
+    }
+
+    /// Add every skill in `loader` under `namespace` and keep following it
+    ///
+    /// Skills installed or reloaded later are picked up by
+    /// [`sync_skills`](Self::sync_skills) without rebuilding the set.
+    pub fn watch_skills(&mut self, loader: &Arc<SkillLoader>, namespace: &str) -> Result<&mut Self, Error> {
+        let seen = (loader.generation(), loader.len());
+        for skill in loader.iter() {
+            self.add_namespaced_shared(namespace, skill as Arc<dyn Tool>)?;
+        }
+        self.skills = Some(SkillWatch {
+            loader: Arc::downgrade(loader),
+            namespace: namespace.to_string(),
+            seen,
+        });
+        Ok(self)
+    }
+
+    /// The loader followed since [`watch_skills`](Self::watch_skills), while it is alive
+    pub fn skill_loader(&self) -> Option<Arc<SkillLoader>> {
+        self.skills.as_ref()?.loader.upgrade()
+    }
+
+    /// Whether the watched loader changed since the last [`sync_skills`](Self::sync_skills)
+    pub fn skills_changed(&self) -> bool {
+        self.skills.as_ref().is_some_and(|watch| watch.changed().is_some())
+    }
+
+    /// Bring skill tools in line with the watched loader, returning whether anything changed
+    ///
+    /// New skills are added, removed ones dropped, and reloaded ones replaced so
+    /// their definitions are computed afresh. Names taken by other tools are skipped.
+    pub fn sync_skills(&mut self) -> bool {
+        let Some(watch) = &self.skills else {
+            return false;
+        };
+        let Some(loader) = watch.changed() else {
+            return false;
+        };
+        let namespace = watch.namespace.clone();
+        let seen = (loader.generation(), loader.len());
+        let current: HashMap<String, Arc<dyn Tool>> = loader
+            .iter()
+            .map(|skill| {
+                let tool = skill as Arc<dyn Tool>;
+                (namespaced(&namespace, &tool.name()), tool)
+            })
+            .collect();
+
+        let stale: Vec<String> = self
+            .tools
+            .iter()
+            .filter(|(name, entry)| {
+                entry.namespace.as_deref() == Some(namespace.as_str())
+                    && current.get(*name).is_none_or(|tool| !Arc::ptr_eq(tool, &entry.tool))
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        let mut changed = !stale.is_empty();
+        for name in stale {
+            self.remove(&name);
+        }
+        for (name, tool) in current {
+            if !self.tools.contains_key(&name) {
+                self.insert(name, Some(namespace.clone()), tool);
+                changed = true;
+            }
+        }
+
+        if let Some(watch) = &mut self.skills {
+            watch.seen = seen;
+        }
+        if changed {
+            tracing::info!(tools = self.tools.len(), "Skill tools resynced with the loader");
+        }
+        changed
+    }
+
+    /// Replace the tool with the same name, returning the previous one
+    pub fn replace<T: Tool + 'static>(&mut self, tool: T) -> Option<Arc<dyn Tool>> {
+        self.insert(tool.name(), None, Arc::new(tool))
+            .map(|entry| entry.tool)
+    }
+
+    fn insert(
+        &mut self,
+        name: String,
+        namespace: Option<String>,
+        tool: Arc<dyn Tool>,
+    ) -> Option<ToolEntry> {
+        let entry = self.new_entry(&name, namespace, tool);
+        if let Err(e) = entry.check_examples() {
+            report_invalid_examples(&name, &e);
+        }
+        Arc::make_mut(&mut self.tools).insert(name, entry)
+    }
+
+    fn new_entry(&self, name: &str, namespace: Option<String>, tool: Arc<dyn Tool>) -> ToolEntry {
+        ToolEntry {
+            breaker: self.breakers.breaker(name),
+            definition: Arc::new(OnceCell::new()),
+            tool,
+            namespace,
+        }
+    }
+
+    /// Registered name `name` refers to
+    ///
+    /// Exact names win; a bare name resolves to the one namespaced tool carrying it.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> Result<&'a str, Error> {
+        if self.tools.contains_key(name) {
+            return Ok(name);
+        }
+        let mut candidates: Vec<&str> = self
+            .tools
+            .iter()
+            .filter(|(key, entry)| {
+                entry
+                    .namespace
+                    .as_ref()
+                    .is_some_and(|ns| **key == namespaced(ns, name))
+            })
+            .map(|(key, _)| key.as_str())
+            .collect();
+        match candidates.len() {
+            0 => Err(Error::ToolNotFound(name.to_string())),
+            1 => Ok(candidates[0]),
+            _ => {
+                candidates.sort_unstable();
+                Err(Error::AmbiguousToolName {
+                    name: name.to_string(),
+                    candidates: candidates.into_iter().map(String::from).collect(),
+                })
+            }
+        }
+    }
+
+    fn entry(&self, name: &str) -> Option<&ToolEntry> {
+        self.tools.get(self.resolve(name).ok()?)
+    }
+
+    /// Remove a tool, returning it if it was registered
+    pub fn remove(&mut self, name: &str) -> Option<Arc<dyn Tool>> {
+        Arc::make_mut(&mut self.tools)
+            .remove(name)
+            .map(|entry| entry.tool)
+    }
+
+    /// Drop every cached definition so the next read asks the tools again
+    ///
+    /// Clones made before this keep their cached definitions.
+    pub fn invalidate_definitions(&mut self) {
+        for entry in Arc::make_mut(&mut self.tools).values_mut() {
+            entry.definition = Arc::new(OnceCell::new());
+        }
+    }
+
     /// Get a tool by name
     pub fn get(&self, name: &str) -> Option<&Arc<dyn Tool>> {
-        self.tools.get(name)
+        self.entry(name).map(|entry| &entry.tool)
     }
 
     /// Check if a tool exists
     pub fn contains(&self, name: &str) -> bool {
-        self.tools.contains_key(name)
+        self.entry(name).is_some()
+    }
+
+    /// Get a single tool's definition
+    pub async fn definition(&self, name: &str) -> Option<ToolDefinition> {
+        Some(self.entry(name)?.definition().await)
     }
 
     /// Get all tool definitions
     pub async fn definitions(&self) -> Vec<ToolDefinition> {
-        let mut defs = Vec::new();
-        for (name, tool) in &self.tools {
-            // Check cache in a small block to ensure guard is dropped
-            let cached = {
-                self.cached_definitions.read().get(name).cloned()
-            };
-
-            if let Some(def) = cached {
-                defs.push(def);
-            } else {
-                let def = tool.definition().await;
-                self.cached_definitions.write().insert(name.clone(), def.clone());
-                defs.push(def);
-            }
+        let mut defs = Vec::with_capacity(self.tools.len());
+        for entry in self.tools.values() {
+            defs.push(entry.definition().await);
         }
         defs
     }
 
-    /// Call a tool by name
+    /// Call a tool by its registered name, or its bare name when unambiguous
+    ///
+    /// Arguments are first coerced toward the tool's parameter schema unless
+    /// the tool opts out with [`Tool::coerce_arguments`].
     pub async fn call(&self, name: &str, arguments: &str) -> anyhow::Result<String> {
-        let tool = self
-            .tools
-            .get(name)
-            .ok_or_else(|| Error::ToolNotFound(name.to_string()))?;
+        let name = self.resolve(name)?;
+        let entry = &self.tools[name];
+        let arguments = &*Self::coerced(entry, arguments).await;
+
+        entry.breaker.acquire()?;
+        let result = match self.timeout(name) {
+            Some(limit) => {
+                let started = Instant::now();
+                match tokio::time::timeout(limit, entry.tool.call(arguments)).await {
+                    Ok(result) => result,
+                    Err(_) => Err(Error::ToolTimeout {
+                        tool_name: name.to_string(),
+                        elapsed: started.elapsed(),
+                    }
+                    .into()),
+                }
+            }
+            None => entry.tool.call(arguments).await,
+        };
+        entry.breaker.record(CallOutcome::of(&result));
+
+        match result {
+            Ok(output) => Ok(output),
+            Err(e) => {
+                // Argument repair: show the model a known-good call alongside the error
+                if let Some(Error::ToolArguments { tool_name, message }) = e.downcast_ref::<Error>() {
+                    let def = entry.definition().await;
+                    let attempted = serde_json::from_str(arguments).unwrap_or(serde_json::Value::Null);
+                    if let Some(example) = def.closest_example(&attempted) {
+                        return Err(Error::ToolArguments {
+                            tool_name: tool_name.clone(),
+                            message: format!(
+                                "{}\nExample of valid arguments ({}): {}",
+                                message,
+                                example.description,
+                                serde_json::to_string(&example.arguments).unwrap_or_default()
+                            ),
+                        }
+                        .into());
+                    }
+                }
+                Err(e)
+            }
+        }
+    }
 
-        tool.call(arguments).await
+    /// `arguments` rewritten by [`coerce_args`], or as given when nothing changed
+    async fn coerced<'a>(entry: &ToolEntry, arguments: &'a str) -> std::borrow::Cow<'a, str> {
+        if !entry.tool.coerce_arguments() {
+            return arguments.into();
+        }
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(arguments) else {
+            return arguments.into();
+        };
+        let coerced = coerce_args(&entry.definition().await.parameters, value.clone());
+        if coerced == value {
+            return arguments.into();
+        }
+        tracing::debug!("Coerced arguments for {}: {} -> {}", entry.tool.name(), value, coerced);
+        coerced.to_string().into()
     }
 
     /// Get the number of tools
@@ -137,37 +741,96 @@ impl ToolSet {
 
     /// Iterate over tools
     pub fn iter(&self) -> impl Iterator<Item = (&String, &Arc<dyn Tool>)> {
-        self.tools.iter()
+        self.tools.iter().map(|(name, entry)| (name, &entry.tool))
     }
-}
 
-#[async_trait::async_trait]
-impl crate::agent::context::ContextInjector for ToolSet {
-    async fn inject(&self) -> crate::error::Result<Vec<crate::agent::message::Message>> {
-        if self.tools.is_empty() {
-            return Ok(Vec::new());
+    /// Registered tool names, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.tools.keys().cloned().collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// A toolset with only the tools `keep` accepts, by registered name
+    ///
+    /// The result shares tool instances, cached definitions, breakers and
+    /// timeouts with this set.
+    pub fn filtered(&self, keep: impl Fn(&str, &Arc<dyn Tool>) -> bool) -> ToolSet {
+        let tools = self
+            .tools
+            .iter()
+            .filter(|(name, entry)| keep(name, &entry.tool))
+            .map(|(name, entry)| (name.clone(), entry.clone()))
+            .collect();
+        ToolSet {
+            tools: Arc::new(tools),
+            skills: None,
+            ..self.clone()
         }
+    }
 
-        let mut content = String::from("## Tool Definitions (TypeScript)\n\n");
-        content.push_str("You have access to the following tools. Use them to fulfill the user's request.\n\n");
+    /// A toolset with only the named tools, failing with [`Error::ToolNotFound`] on unknown names
+    ///
+    /// Names resolve as in [`call`](Self::call), so a bare name selects its
+    /// namespaced tool when unambiguous.
+    pub fn subset<S: AsRef<str>>(&self, names: &[S]) -> Result<ToolSet, Error> {
+        let keep = names
+            .iter()
+            .map(|name| self.resolve(name.as_ref()).map(String::from))
+            .collect::<Result<HashSet<_>, _>>()?;
+        Ok(self.filtered(|name, _| keep.contains(name)))
+    }
+}
 
+impl ToolSet {
+    /// Render the TypeScript tool catalog, limited to `visible` tools when given
+    pub async fn render_catalog(
+        &self,
+        visible: Option<&HashSet<String>>,
+    ) -> Vec<crate::agent::message::Message> {
+        let is_visible = |name: &str| visible.is_none_or(|v| v.contains(name));
         // Sort for determinism
-        let mut sorted_tools: Vec<_> = self.tools.iter().collect();
+        let mut sorted_tools: Vec<_> = self.tools.iter().filter(|(k, _)| is_visible(k)).collect();
+        if sorted_tools.is_empty() {
+            return Vec::new();
+        }
         sorted_tools.sort_by_key(|(k, _)| *k);
 
-        for (name, tool) in sorted_tools {
-            let cached_def = {
-                self.cached_definitions.read().get(name).cloned()
-            };
+        let mut content = format!("{}\n\n", TOOL_CATALOG_HEADING);
+        content.push_str("You have access to the following tools. Use them to fulfill the user's request.\n\n");
+        if is_visible(calculator::CALCULATOR_TOOL) && self.tools.contains_key(calculator::CALCULATOR_TOOL) {
+            content.push_str(&format!(
+                "Always use the `{}` tool for arithmetic (P&L, position sizes, fees, unit conversions); never compute numbers yourself.\n\n",
+                calculator::CALCULATOR_TOOL
+            ));
+        }
+
+        let mut example_budget = self.example_token_budget;
+
+        for (name, entry) in sorted_tools {
+            let def = entry.definition().await;
+
+            // Examples are rendered only while the example budget lasts
+            let mut examples = String::new();
+            for example in def.examples.iter().take(self.max_examples_per_tool) {
+                let rendered = example.render();
+                // Rough 4-chars-per-token estimate; loading a tokenizer per render is too slow
+                let cost = rendered.len() / 4 + 1;
+                if cost > example_budget {
+                    tracing::debug!(tool = %name, "Example budget exhausted, dropping remaining examples");
+                    example_budget = 0;
+                    break;
+                }
+                example_budget -= cost;
+                examples.push_str(&rendered);
+            }
+
+            let returns = def
+                .output_schema
+                .as_ref()
+                .map(|output| format!("// Returns: {}\n", schema::typescript(output)))
+                .unwrap_or_default();
 
-            let def = if let Some(d) = cached_def {
-                d
-            } else {
-                let d = tool.definition().await;
-                self.cached_definitions.write().insert(name.clone(), d.clone());
-                d
-            };
-            
             content.push_str(&format!("### {}\n{}\n", name, def.description));
             if let Some(ts) = def.parameters_ts {
                 content.push_str("```typescript\n");
@@ -175,16 +838,32 @@ impl crate::agent::context::ContextInjector for ToolSet {
                 if !ts.ends_with('\n') {
                     content.push('\n');
                 }
+                content.push_str(&returns);
+                content.push_str(&examples);
                 content.push_str("```\n\n");
             } else {
                 // Fallback to JSON if TS missing
                 content.push_str("```json\n");
                 content.push_str(&serde_json::to_string_pretty(&def.parameters).unwrap_or_default());
-                content.push_str("\n```\n\n");
+                content.push_str("\n```\n");
+                content.push_str(&returns);
+                content.push_str(&examples);
+                content.push('\n');
             }
         }
 
-        Ok(vec![crate::agent::message::Message::system(content)])
+        vec![crate::agent::message::Message::system(content)]
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::agent::context::ContextInjector for ToolSet {
+    async fn inject(&self) -> crate::error::Result<Vec<crate::agent::message::Message>> {
+        Ok(self.render_catalog(None).await)
+    }
+
+    fn source_key(&self) -> String {
+        "tool_catalog".to_string()
     }
 }
 
@@ -299,9 +978,13 @@ mod tests {
                     },
                     "required": ["message"]
                 }),
-                parameters_ts: None,
-                is_binary: false,
                 is_verified: true, // Internal tools are verified
+                examples: vec![ToolExample::new(
+                    "Echo a greeting",
+                    serde_json::json!({"message": "hi"}),
+                )
+                .with_result("hi")],
+                ..Default::default()
             }
         }
 
@@ -333,4 +1016,429 @@ mod tests {
             .expect("call should succeed");
         assert_eq!(result, "hello");
     }
+
+    #[tokio::test]
+    async fn test_conflicts_and_replace() {
+        let mut toolset = ToolSet::new();
+        toolset.try_add(EchoTool).unwrap();
+        assert!(matches!(
+            toolset.try_add(EchoTool),
+            Err(Error::ToolNameConflict(name)) if name == "echo"
+        ));
+
+        let previous = toolset.replace(CountingTool {
+            name: "echo".to_string(),
+            definitions: Default::default(),
+        });
+        assert!(previous.is_some());
+        assert_eq!(toolset.len(), 1);
+        assert_eq!(toolset.call("echo", r#"{"message": "hi"}"#).await.unwrap(), "echo");
+    }
+
+    #[tokio::test]
+    async fn test_namespaced_names_resolve_when_unambiguous() {
+        let mut toolset = ToolSet::new();
+        toolset.add_namespaced("skills", EchoTool).unwrap();
+        assert!(toolset.add_namespaced("skills", EchoTool).is_err());
+
+        // The definition carries the namespaced name the model should call
+        let def = toolset.definition("echo").await.unwrap();
+        assert_eq!(def.name, "skills__echo");
+        for name in ["skills__echo", "echo"] {
+            assert_eq!(toolset.call(name, r#"{"message": "hi"}"#).await.unwrap(), "hi");
+        }
+
+        // A second namespace makes the bare name ambiguous
+        toolset.add_namespaced("plugins", EchoTool).unwrap();
+        let err = toolset.call("echo", r#"{"message": "hi"}"#).await.unwrap_err();
+        match err.downcast_ref::<Error>() {
+            Some(Error::AmbiguousToolName { name, candidates }) => {
+                assert_eq!(name, "echo");
+                assert_eq!(candidates, &["plugins__echo", "skills__echo"]);
+            }
+            other => panic!("expected an ambiguous name, got {:?}", other),
+        }
+
+        // A bare tool wins over namespaced ones instead of being shadowed
+        toolset.add(CountingTool {
+            name: "echo".to_string(),
+            definitions: Default::default(),
+        });
+        assert_eq!(toolset.call("echo", "{}").await.unwrap(), "echo");
+    }
+
+    /// Counts how often its definition is computed to completion
+    struct CountingTool {
+        name: String,
+        definitions: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for CountingTool {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+
+        async fn definition(&self) -> ToolDefinition {
+            // Widen the window for racing first reads; registration polls once and gives up
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            self.definitions
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            ToolDefinition {
+                name: self.name.clone(),
+                description: "Counts definitions".to_string(),
+                parameters: serde_json::json!({"type": "object"}),
+                is_verified: true,
+                side_effect_free: true,
+                ..Default::default()
+            }
+        }
+
+        async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+            Ok(self.name.clone())
+        }
+    }
+
+    fn counting_toolset(tools: usize) -> (ToolSet, Arc<std::sync::atomic::AtomicUsize>) {
+        let definitions = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut toolset = ToolSet::new();
+        for i in 0..tools {
+            toolset.add(CountingTool {
+                name: format!("tool_{}", i),
+                definitions: definitions.clone(),
+            });
+        }
+        (toolset, definitions)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_reads_share_one_snapshot() {
+        let (toolset, definitions) = counting_toolset(8);
+        let toolset = Arc::new(toolset);
+
+        let started = std::time::Instant::now();
+        let tasks: Vec<_> = (0..64)
+            .map(|task| {
+                let toolset = toolset.clone();
+                tokio::spawn(async move {
+                    for i in 0..200 {
+                        assert_eq!(toolset.definitions().await.len(), 8);
+                        let name = format!("tool_{}", (task + i) % 8);
+                        assert_eq!(toolset.call(&name, "{}").await.unwrap(), name);
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        // Each per-tool cell initialised once despite 64 racing first reads
+        assert_eq!(definitions.load(std::sync::atomic::Ordering::SeqCst), 8);
+        // 12,800 definitions()+call() rounds; a global lock held across the
+        // 5ms definition await would serialise well past this bound
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(
+            toolset.breakers().status("tool_0").unwrap().window_calls,
+            64 * 200 / 8
+        );
+    }
+
+    #[derive(Debug, Deserialize, schemars::JsonSchema)]
+    struct QuoteArgs {
+        /// Token symbol
+        symbol: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+    struct Quote {
+        symbol: String,
+        price: f64,
+        /// Venue the quote came from, when known
+        venue: Option<String>,
+    }
+
+    #[aagt_macros::tool(name = "get_quote", description = "Quote a token", args = QuoteArgs, output = Quote)]
+    struct QuoteTool;
+
+    impl QuoteTool {
+        async fn execute(&self, args: QuoteArgs) -> crate::error::Result<Quote> {
+            Ok(Quote {
+                symbol: args.symbol,
+                price: 185.5,
+                venue: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_macro_output_type_round_trips() {
+        let mut toolset = ToolSet::new();
+        toolset.add(QuoteTool).add(EchoTool);
+
+        let def = toolset.definition("get_quote").await.unwrap();
+        let output_schema = def.output_schema.expect("output schema");
+        assert_eq!(output_schema["required"], serde_json::json!(["price", "symbol"]));
+        assert!(toolset.definition("echo").await.unwrap().output_schema.is_none());
+
+        let catalog = toolset.render_catalog(None).await[0].content.as_text();
+        assert!(
+            catalog.contains("// Returns: { price: number; symbol: string; venue?: string }"),
+            "{}",
+            catalog
+        );
+
+        let result = toolset.call("get_quote", r#"{"symbol": "SOL"}"#).await.unwrap();
+        let quote: Quote = serde_json::from_str(&result).unwrap();
+        assert_eq!(
+            quote,
+            Quote {
+                symbol: "SOL".to_string(),
+                price: 185.5,
+                venue: None
+            }
+        );
+    }
+
+    /// Tool with injected state, a generated constructor and a named handler
+    #[aagt_macros::tool(
+        name = "get_quote",
+        description = "Quote a token",
+        args = QuoteArgs,
+        derive_new = true,
+        handler = Self::quote
+    )]
+    struct VenueQuoteTool {
+        venue: String,
+        spread: f64,
+    }
+
+    impl VenueQuoteTool {
+        async fn quote(&self, args: QuoteArgs) -> crate::error::Result<String> {
+            Ok(format!("{} {} on {}", args.symbol, 185.5 + self.spread, self.venue))
+        }
+    }
+
+    /// Tool whose args type is read from its handler
+    struct CountTool {
+        base: usize,
+    }
+
+    #[derive(Deserialize, schemars::JsonSchema)]
+    struct CountRequest {
+        items: Vec<String>,
+    }
+
+    #[aagt_macros::tool(name = "count", description = "Count items")]
+    impl CountTool {
+        async fn execute(&self, args: CountRequest) -> crate::error::Result<String> {
+            Ok((self.base + args.items.len()).to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_macro_tools_with_state() {
+        let tool = VenueQuoteTool::new("jupiter".to_string(), 0.0).with_spread(0.5);
+        assert_eq!(tool.call(r#"{"symbol": "SOL"}"#).await.unwrap(), "SOL 186 on jupiter");
+
+        let tool = CountTool { base: 1 };
+        assert_eq!(tool.call(r#"{"items": ["a", "b"]}"#).await.unwrap(), "3");
+        let def = tool.definition().await;
+        assert!(def.parameters_ts.unwrap().contains("interface CountRequest"));
+    }
+
+    /// Sleeps before answering
+    struct SleepyTool;
+
+    #[async_trait]
+    impl Tool for SleepyTool {
+        fn name(&self) -> String {
+            "sleepy".to_string()
+        }
+
+        async fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: "sleepy".to_string(),
+                description: "Answers after a while".to_string(),
+                parameters: serde_json::json!({"type": "object"}),
+                is_verified: true,
+                side_effect_free: true,
+                ..Default::default()
+            }
+        }
+
+        async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok("awake".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timeouts_cancel_slow_tools_only() {
+        let (toolset, _) = counting_toolset(1);
+        let mut toolset = toolset
+            .with_default_timeout(Duration::from_secs(10))
+            .with_tool_timeout("sleepy", Duration::from_millis(50));
+        toolset.add(SleepyTool);
+
+        let started = Instant::now();
+        let err = toolset.call("sleepy", "{}").await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(2));
+        match err.downcast_ref::<Error>() {
+            Some(Error::ToolTimeout { tool_name, elapsed }) => {
+                assert_eq!(tool_name, "sleepy");
+                assert!(*elapsed >= Duration::from_millis(50));
+            }
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        assert_eq!(toolset.breakers().status("sleepy").unwrap().window_failures, 1);
+
+        // The fast tool runs under the default limit and is untouched by it
+        assert_eq!(toolset.timeout("tool_0"), Some(Duration::from_secs(10)));
+        assert_eq!(toolset.call("tool_0", "{}").await.unwrap(), "tool_0");
+    }
+
+    #[tokio::test]
+    async fn test_mutation_is_copy_on_write() {
+        let (toolset, definitions) = counting_toolset(2);
+        toolset.definitions().await;
+
+        let mut edited = toolset.clone();
+        edited.definitions().await;
+        assert_eq!(definitions.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        edited.remove("tool_1");
+        edited.invalidate_definitions();
+        edited.definitions().await;
+        assert_eq!(definitions.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert_eq!((edited.len(), toolset.len()), (1, 2));
+
+        // The original snapshot keeps its tools and cached definitions
+        toolset.definitions().await;
+        assert_eq!(definitions.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    /// Tool with a configurable set of examples
+    struct ExampleTool {
+        examples: Vec<ToolExample>,
+    }
+
+    #[async_trait]
+    impl Tool for ExampleTool {
+        fn name(&self) -> String {
+            "price".to_string()
+        }
+
+        async fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: "price".to_string(),
+                description: "Get a token price".to_string(),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": { "symbol": { "type": "string" } },
+                    "required": ["symbol"]
+                }),
+                parameters_ts: Some("interface PriceArgs {\n  symbol: string;\n}".to_string()),
+                is_verified: true,
+                examples: self.examples.clone(),
+                ..Default::default()
+            }
+        }
+
+        async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+            Ok("1.0".to_string())
+        }
+    }
+
+    fn price_examples(n: usize) -> Vec<ToolExample> {
+        (0..n)
+            .map(|i| ToolExample::new(format!("Price #{}", i), serde_json::json!({"symbol": "SOL"})))
+            .collect()
+    }
+
+    async fn injected_text(toolset: &ToolSet) -> String {
+        use crate::agent::context::ContextInjector;
+        toolset.inject().await.unwrap()[0].content.as_text()
+    }
+
+    #[tokio::test]
+    async fn test_examples_rendered_within_budget() {
+        let mut toolset = ToolSet::new();
+        toolset.add(ExampleTool { examples: price_examples(3) });
+
+        let text = injected_text(&toolset).await;
+        assert_eq!(text.matches("// Example:").count(), 2);
+        assert!(text.contains("// Example: Price #0\n// {\"symbol\":\"SOL\"}"));
+
+        // A tiny budget drops examples but keeps the interface
+        let mut toolset = ToolSet::new();
+        toolset.add(ExampleTool { examples: price_examples(3) });
+        toolset.set_example_limits(2, 5);
+        let text = injected_text(&toolset).await;
+        assert_eq!(text.matches("// Example:").count(), 0);
+        assert!(text.contains("interface PriceArgs"));
+    }
+
+    #[tokio::test]
+    async fn test_argument_error_includes_example() {
+        let mut toolset = ToolSet::new();
+        toolset.add(EchoTool);
+
+        let err = toolset.call("echo", r#"{"msg": "hello"}"#).await.unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("Example of valid arguments (Echo a greeting)"));
+        assert!(message.contains(r#"{"message":"hi"}"#));
+    }
+
+    #[test]
+    fn test_invalid_example_rejected() {
+        let def = ToolDefinition {
+            name: "price".to_string(),
+            description: "Get a token price".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": { "symbol": { "type": "string" } },
+                "required": ["symbol"]
+            }),
+            is_verified: true,
+            examples: vec![ToolExample::new("Wrong key", serde_json::json!({"ticker": "SOL"}))],
+            ..Default::default()
+        };
+
+        let err = def.validate_examples().unwrap_err();
+        assert!(err.to_string().contains("missing required property 'symbol'"));
+        assert!(def.description_with_example().contains("Example (Wrong key)"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_example_checked_on_registration() {
+        let examples = vec![
+            ToolExample::new("Bad", serde_json::json!({"symbol": 1})),
+            ToolExample::new("Good", serde_json::json!({"symbol": "SOL"})),
+        ];
+
+        let err = ToolSet::new()
+            .try_add(ExampleTool { examples: examples.clone() })
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("example 0 ('Bad')"));
+
+        // Release builds keep the tool and drop only the bad example
+        if !cfg!(debug_assertions) {
+            let mut toolset = ToolSet::new();
+            toolset.add(ExampleTool { examples });
+            let defs = toolset.definitions().await;
+            assert_eq!(defs[0].examples.len(), 1);
+            assert_eq!(defs[0].examples[0].description, "Good");
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Tool 'price' has invalid examples")]
+    fn test_invalid_example_panics_on_add_in_debug() {
+        ToolSet::new().add(ExampleTool {
+            examples: vec![ToolExample::new("Bad", serde_json::json!({"symbol": 1}))],
+        });
+    }
 }
@@ -0,0 +1,70 @@
+//! Recall of elided tool outputs
+//!
+//! Pairs with [`ToolOutputAging`]: once an old tool result is replaced by a
+//! stub in the context, this tool returns its full original text.
+//!
+//! [`ToolOutputAging`]: crate::agent::tool_aging::ToolOutputAging
+
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::agent::tool_aging::ToolOutputArchive;
+use crate::skills::tool::{parse_args, Tool, ToolDefinition};
+
+/// Name under which the recall tool is registered
+pub const RECALL_TOOL_OUTPUT_TOOL: &str = "recall_tool_output";
+
+/// Tool that restores the full text of a stubbed tool result
+pub struct RecallToolOutputTool {
+    archive: ToolOutputArchive,
+}
+
+impl RecallToolOutputTool {
+    /// Recall from the archive the context manager stubs into
+    pub fn new(archive: ToolOutputArchive) -> Self {
+        Self { archive }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct RecallArgs {
+    /// `call_id` from the elided result
+    call_id: String,
+}
+
+#[async_trait]
+impl Tool for RecallToolOutputTool {
+    fn name(&self) -> String {
+        RECALL_TOOL_OUTPUT_TOOL.to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Return the full text of an earlier tool result that was shortened to an 'output elided' note.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "call_id": { "type": "string", "description": "call_id from the elided note" }
+                },
+                "required": ["call_id"]
+            }),
+            parameters_ts: Some("interface RecallArgs {\n  call_id: string;\n}".to_string()),
+            is_verified: true,
+            side_effect_free: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        let args: RecallArgs = parse_args(&self.name(), arguments)?;
+        self.archive.get(&args.call_id).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No elided output for call_id '{}'; only results shown as elided can be recalled",
+                args.call_id
+            )
+        })
+    }
+}
@@ -0,0 +1,475 @@
+//! Minimal JSON Schema validation for tool arguments
+//!
+//! Covers the subset of JSON Schema that tool definitions use in practice:
+//! `type`, `properties`, `required`, `items`, `enum` and `additionalProperties`.
+//! [`typescript`] and [`typescript_interface`] render the same subset, plus
+//! `$ref`s and `anyOf`/`oneOf`/`allOf`, as TypeScript for prompts.
+
+use serde_json::Value;
+
+/// First schema violation found by [`check`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Path of the offending value, e.g. `$.legs[1].amount`
+    pub path: String,
+    /// Property involved when it is missing or unknown (not yet part of `path`)
+    pub property: Option<String>,
+    /// What is wrong
+    pub message: String,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Validate a value against a JSON Schema, returning the first violation
+pub fn validate(value: &Value, schema: &Value) -> std::result::Result<(), String> {
+    check(value, schema).map_err(|v| v.to_string())
+}
+
+/// Like [`validate`], but keeps the violation structured
+pub fn check(value: &Value, schema: &Value) -> std::result::Result<(), Violation> {
+    check_at(value, schema, "$")
+}
+
+fn violation(path: &str, property: Option<&str>, message: String) -> Violation {
+    Violation {
+        path: path.to_string(),
+        property: property.map(str::to_string),
+        message,
+    }
+}
+
+fn check_at(value: &Value, schema: &Value, path: &str) -> std::result::Result<(), Violation> {
+    let Some(schema) = schema.as_object() else {
+        // `true` / `{}` style schemas accept anything
+        return Ok(());
+    };
+
+    // OpenAPI-style optional fields
+    if value.is_null() && schema.get("nullable") == Some(&Value::Bool(true)) {
+        return Ok(());
+    }
+
+    if let Some(expected) = schema.get("type") {
+        let matches = match expected {
+            Value::String(t) => type_matches(value, t),
+            Value::Array(types) => types
+                .iter()
+                .filter_map(|t| t.as_str())
+                .any(|t| type_matches(value, t)),
+            _ => true,
+        };
+        if !matches {
+            return Err(violation(
+                path,
+                None,
+                format!("expected type {}, got {}", expected, type_name(value)),
+            ));
+        }
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            return Err(violation(
+                path,
+                None,
+                format!("value {} is not one of {}", value, Value::Array(allowed.clone())),
+            ));
+        }
+    }
+
+    if let Value::Object(map) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(|k| k.as_str()) {
+                if !map.contains_key(key) {
+                    return Err(violation(
+                        path,
+                        Some(key),
+                        format!("missing required property '{}'", key),
+                    ));
+                }
+            }
+        }
+
+        let properties = schema.get("properties").and_then(|p| p.as_object());
+        for (key, field) in map {
+            match properties.and_then(|p| p.get(key)) {
+                Some(field_schema) => {
+                    check_at(field, field_schema, &format!("{}.{}", path, key))?;
+                }
+                None => {
+                    if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+                        return Err(violation(
+                            path,
+                            Some(key),
+                            format!("unknown property '{}'", key),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            check_at(item, item_schema, &format!("{}[{}]", path, i))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn type_matches(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Render a schema as a compact TypeScript type, e.g. `{ price: number; symbol?: string }`
+///
+/// `$ref`s are inlined from the root's `definitions`, `$defs` or
+/// `components.schemas`; anything unrecognized renders as `unknown`.
+pub fn typescript(schema: &Value) -> String {
+    TsWriter::new(schema, false).ty(schema, 0)
+}
+
+/// Render an object schema as `interface <name> { ... }` for a tool prompt
+///
+/// Descriptions become `//` comments, optional and nullable properties become
+/// `field?:`, and each referenced type is declared after the interface: structs
+/// as interfaces, enums as unions of string literals or of tagged objects.
+pub fn typescript_interface(name: &str, schema: &Value) -> String {
+    let mut writer = TsWriter::new(schema, true);
+    let mut out = writer.declaration(name, schema);
+    let mut next = 0;
+    while let Some(reference) = writer.refs.get(next).cloned() {
+        if let Some(definition) = writer.definition(&reference) {
+            out.push('\n');
+            out.push_str(&writer.declaration(&reference, definition));
+        }
+        next += 1;
+    }
+    out
+}
+
+/// Deepest nesting rendered inline, so recursive types terminate
+const MAX_TS_DEPTH: usize = 8;
+
+/// One property of a rendered object
+struct TsField {
+    name: String,
+    optional: bool,
+    ty: String,
+    doc: Option<String>,
+}
+
+struct TsWriter<'a> {
+    root: &'a Value,
+    /// Render `$ref`s by name and collect them in `refs` instead of inlining them
+    named: bool,
+    refs: Vec<String>,
+}
+
+impl<'a> TsWriter<'a> {
+    fn new(root: &'a Value, named: bool) -> Self {
+        Self {
+            root,
+            named,
+            refs: Vec::new(),
+        }
+    }
+
+    fn definition(&self, name: &str) -> Option<&'a Value> {
+        let root = self.root;
+        ["definitions", "$defs"]
+            .iter()
+            .find_map(|key| root.get(key)?.get(name))
+            .or_else(|| root.get("components")?.get("schemas")?.get(name))
+    }
+
+    /// `interface` for objects with properties, `type` alias for anything else
+    fn declaration(&mut self, name: &str, schema: &Value) -> String {
+        let mut out = doc_comment(schema)
+            .map(|doc| format!("// {}\n", doc))
+            .unwrap_or_default();
+        if schema.get("properties").is_none() {
+            out.push_str(&format!("type {} = {};\n", name, self.ty(schema, 0)));
+            return out;
+        }
+        out.push_str(&format!("interface {} {{\n", name));
+        for field in self.fields(schema, 0) {
+            let optional = if field.optional { "?" } else { "" };
+            out.push_str(&format!("  {}{}: {};", field.name, optional, field.ty));
+            if let Some(doc) = field.doc {
+                out.push_str(&format!(" // {}", doc));
+            }
+            out.push('\n');
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn ty(&mut self, schema: &Value, depth: usize) -> String {
+        if depth > MAX_TS_DEPTH {
+            return "unknown".to_string();
+        }
+        let ty = self.base(schema, depth);
+        if schema.get("nullable").and_then(Value::as_bool) == Some(true) {
+            format!("{} | null", ty)
+        } else {
+            ty
+        }
+    }
+
+    fn base(&mut self, schema: &Value, depth: usize) -> String {
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let name = reference.rsplit('/').next().unwrap_or(reference);
+            if self.named {
+                if !self.refs.iter().any(|r| r == name) {
+                    self.refs.push(name.to_string());
+                }
+                return name.to_string();
+            }
+            return match self.definition(name) {
+                Some(target) => self.ty(target, depth + 1),
+                None => "unknown".to_string(),
+            };
+        }
+        if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+            return values.iter().map(Value::to_string).collect::<Vec<_>>().join(" | ");
+        }
+        for (key, separator) in [("anyOf", " | "), ("oneOf", " | "), ("allOf", " & ")] {
+            if let Some(variants) = schema.get(key).and_then(Value::as_array) {
+                return variants
+                    .iter()
+                    .map(|variant| self.ty(variant, depth + 1))
+                    .collect::<Vec<_>>()
+                    .join(separator);
+            }
+        }
+        match schema.get("type") {
+            Some(Value::String(ty)) => self.named_type(schema, ty, depth),
+            Some(Value::Array(types)) => types
+                .iter()
+                .map(|ty| self.named_type(schema, ty.as_str().unwrap_or_default(), depth))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            _ if schema.get("properties").is_some() => self.object(schema, depth),
+            _ => "unknown".to_string(),
+        }
+    }
+
+    fn named_type(&mut self, schema: &Value, ty: &str, depth: usize) -> String {
+        match ty {
+            "string" | "boolean" | "null" => ty.to_string(),
+            "integer" | "number" => "number".to_string(),
+            "array" => {
+                let item = match schema.get("items") {
+                    Some(items) => self.ty(items, depth + 1),
+                    None => "unknown".to_string(),
+                };
+                if item.contains(' ') && !item.starts_with('{') {
+                    format!("({})[]", item)
+                } else {
+                    format!("{}[]", item)
+                }
+            }
+            "object" => self.object(schema, depth),
+            _ => "unknown".to_string(),
+        }
+    }
+
+    fn object(&mut self, schema: &Value, depth: usize) -> String {
+        let fields = self.fields(schema, depth);
+        if !fields.is_empty() {
+            let fields: Vec<String> = fields
+                .into_iter()
+                .map(|f| format!("{}{}: {}", f.name, if f.optional { "?" } else { "" }, f.ty))
+                .collect();
+            return format!("{{ {} }}", fields.join("; "));
+        }
+        match schema.get("additionalProperties") {
+            Some(values @ Value::Object(_)) => {
+                format!("Record<string, {}>", self.ty(values, depth + 1))
+            }
+            _ => "object".to_string(),
+        }
+    }
+
+    /// Properties of an object schema, tags of tagged enums first; optional ones drop their `| null`
+    fn fields(&mut self, schema: &Value, depth: usize) -> Vec<TsField> {
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|names| names.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+            return Vec::new();
+        };
+        let is_tag = |property: &Value| {
+            property.get("enum").and_then(Value::as_array).is_some_and(|values| values.len() == 1)
+        };
+        let mut properties: Vec<_> = properties.iter().collect();
+        properties.sort_by_key(|(_, property)| !is_tag(property));
+        properties
+            .into_iter()
+            .map(|(name, property)| {
+                let optional = !required.contains(&name.as_str());
+                let ty = if optional {
+                    self.base(property, depth + 1)
+                } else {
+                    self.ty(property, depth + 1)
+                };
+                TsField {
+                    name: name.clone(),
+                    optional,
+                    ty,
+                    doc: doc_comment(property),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A schema's description on one line
+fn doc_comment(schema: &Value) -> Option<String> {
+    let description = schema.get("description").and_then(Value::as_str)?;
+    let doc = description.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!doc.is_empty()).then_some(doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "symbol": { "type": "string", "enum": ["SOL", "ETH"] },
+                "amount": { "type": "number" },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["symbol"]
+        });
+
+        assert!(validate(&json!({"symbol": "SOL", "amount": 1.5}), &schema).is_ok());
+        assert!(validate(&json!({"amount": 1.5}), &schema)
+            .unwrap_err()
+            .contains("missing required property 'symbol'"));
+        assert!(validate(&json!({"symbol": "BTC"}), &schema).is_err());
+        assert!(validate(&json!({"symbol": "SOL", "amount": "1"}), &schema)
+            .unwrap_err()
+            .contains("$.amount"));
+        assert!(
+            validate(&json!({"symbol": "SOL", "tags": ["a", 1]}), &schema)
+                .unwrap_err()
+                .contains("$.tags[1]")
+        );
+    }
+
+    #[test]
+    fn test_typescript_rendering() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "price": { "type": "number" },
+                "side": { "$ref": "#/components/schemas/Side" },
+                "fills": { "type": "array", "items": { "type": "integer" } },
+                "note": { "type": "string", "nullable": true }
+            },
+            "required": ["price", "side", "fills"],
+            "definitions": {
+                "Side": { "type": "string", "enum": ["buy", "sell"] }
+            }
+        });
+        assert_eq!(
+            typescript(&schema),
+            r#"{ fills: number[]; note?: string; price: number; side: "buy" | "sell" }"#
+        );
+    }
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    #[allow(dead_code)]
+    enum Side {
+        Buy,
+        Sell,
+    }
+
+    /// How the order executes
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    #[allow(dead_code)]
+    enum Execution {
+        Market,
+        Limit { price: f64 },
+        Twap { minutes: u32, slices: u32 },
+    }
+
+    /// One leg of a multi-leg order
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    #[allow(dead_code)]
+    struct Leg {
+        /// Token symbol
+        symbol: String,
+        side: Side,
+        /// Amount in USD
+        amount: f64,
+    }
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    #[allow(dead_code)]
+    struct PlaceOrderArgs {
+        /// Legs executed together,
+        /// in order
+        legs: Vec<Leg>,
+        execution: Execution,
+        /// Max slippage in percent
+        slippage: Option<f64>,
+        tags: Option<Vec<String>>,
+    }
+
+    #[aagt_macros::tool(name = "place_order", description = "Place an order")]
+    struct PlaceOrder;
+
+    impl PlaceOrder {
+        async fn execute(&self, _args: PlaceOrderArgs) -> crate::error::Result<String> {
+            Ok("filled".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_macro_generates_typescript_parameters() {
+        use crate::skills::tool::Tool;
+
+        let ts = PlaceOrder.definition().await.parameters_ts.expect("generated");
+        crate::agent::context::assert_snapshot(
+            &ts,
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots/place_order_ts.txt"),
+        );
+    }
+}
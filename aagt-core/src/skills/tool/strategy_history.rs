@@ -0,0 +1,113 @@
+//! Strategy run history lookup
+//!
+//! Lets the agent answer "what did my DCA strategy do last week?" from the
+//! recorded [`PipelineRun`]s instead of guessing.
+
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+use crate::skills::tool::{parse_args, ArgsExt, Tool, ToolDefinition};
+use crate::trading::history::{PipelineRun, RunHistory, RunQuery, RunStatus};
+
+/// Name under which the strategy history tool is registered
+pub const STRATEGY_HISTORY_TOOL: &str = "strategy_history";
+
+/// Tool that queries recorded strategy pipeline runs
+pub struct StrategyHistoryTool {
+    history: Arc<RunHistory>,
+}
+
+impl StrategyHistoryTool {
+    /// Query runs from `history`
+    pub fn new(history: Arc<RunHistory>) -> Self {
+        Self { history }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct StrategyHistoryArgs {
+    /// Strategy ID or name
+    #[serde(default)]
+    strategy: Option<String>,
+    /// Only runs started in the last N days
+    #[serde(default)]
+    days: Option<u32>,
+    /// Only runs with this status
+    #[serde(default)]
+    status: Option<RunStatus>,
+    /// Max runs to return
+    #[serde(default)]
+    limit: Option<usize>,
+    /// "markdown" (default) or "json"
+    #[serde(default)]
+    format: Option<String>,
+}
+
+#[async_trait]
+impl Tool for StrategyHistoryTool {
+    fn name(&self) -> String {
+        STRATEGY_HISTORY_TOOL.to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Look up past runs of automated trading strategies: what triggered them, the condition values they saw, which actions executed or were blocked by risk controls, and how they ended.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "strategy": { "type": "string", "description": "Strategy ID or name" },
+                    "days": { "type": "integer", "minimum": 1, "maximum": 365 },
+                    "status": {
+                        "type": "string",
+                        "enum": ["completed", "condition_not_met", "risk_denied", "failed", "cancelled"]
+                    },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": 50 },
+                    "format": { "type": "string", "enum": ["markdown", "json"] }
+                }
+            }),
+            parameters_ts: Some("interface StrategyHistoryArgs {\n  strategy?: string;\n  days?: number; // 1..365\n  status?: 'completed' | 'condition_not_met' | 'risk_denied' | 'failed' | 'cancelled';\n  limit?: number; // 1..50, default 10\n  format?: 'markdown' | 'json';\n}".to_string()),
+            is_verified: true,
+            side_effect_free: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        let args: StrategyHistoryArgs = parse_args(&self.name(), arguments)?;
+        let format = args.format.as_deref().unwrap_or("markdown");
+        self.require_one_of("format", format, &["markdown", "json"])?;
+        let limit = args.limit.unwrap_or(10);
+        self.require_range("limit", limit, 1, 50)?;
+
+        let mut query = RunQuery::new().limit(limit);
+        if let Some(strategy) = args.strategy {
+            query = query.strategy(strategy);
+        }
+        if let Some(days) = args.days {
+            self.require_range("days", days, 1, 365)?;
+            let now = Utc::now();
+            query = query.range(now - Duration::days(days as i64)..now + Duration::seconds(1));
+        }
+        if let Some(status) = args.status {
+            query = query.status(status);
+        }
+
+        let runs = self.history.query(&query);
+        if format == "json" {
+            return Ok(serde_json::to_string_pretty(&runs)?);
+        }
+        if runs.is_empty() {
+            return Ok("No strategy runs match.".to_string());
+        }
+        Ok(runs
+            .iter()
+            .map(PipelineRun::to_markdown)
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
@@ -0,0 +1,876 @@
+//! Ephemeral sub-agent spawning
+//!
+//! Lets an agent fan out a task to short-lived child agents that share its
+//! provider but run with a restricted toolset, a step cap and a token budget.
+//! Children draw from the parent's token budget and request [`Budget`], and see
+//! the parent's memory only through its read tools; tools that
+//! [write memory](Tool::writes_memory) are withheld.
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::Instrument;
+
+use crate::agent::budget::Budget;
+use crate::agent::core::{Agent, AgentConfig, AgentEvent};
+use crate::agent::events::EventHub;
+use crate::agent::provider::{ChatRequest, Provider};
+use crate::agent::streaming::{StreamingChoice, StreamingResponse, Usage};
+use crate::error::{Error, Result};
+use crate::skills::tool::{parse_args, ArgsExt, Tool, ToolDefinition, ToolSet};
+
+/// Name under which the spawn tool is registered
+pub const SPAWN_SUBAGENT_TOOL: &str = "spawn_subagent";
+
+/// Configuration for sub-agent spawning
+#[derive(Debug, Clone)]
+pub struct SubagentConfig {
+    /// Max children per spawn call
+    pub max_children: usize,
+    /// Max nesting depth (1 = children cannot spawn their own children)
+    pub max_depth: usize,
+    /// Default max provider calls per child
+    pub default_max_steps: usize,
+    /// Hard ceiling on max provider calls per child
+    pub max_steps_limit: usize,
+    /// Max children running at the same time
+    pub max_concurrent: usize,
+    /// Total token budget shared by the parent agent and all its children (None = unlimited)
+    pub token_budget: Option<u64>,
+    /// System prompt for child agents
+    pub preamble: String,
+}
+
+impl Default for SubagentConfig {
+    fn default() -> Self {
+        Self {
+            max_children: 5,
+            max_depth: 1,
+            default_max_steps: 5,
+            max_steps_limit: 15,
+            max_concurrent: 5,
+            token_budget: None,
+            preamble: "You are a focused sub-agent working on one part of a larger task. \
+                Complete the task and reply with the result only."
+                .to_string(),
+        }
+    }
+}
+
+/// Token budget shared between a parent agent and its children
+#[derive(Debug)]
+pub struct TokenBudget {
+    total: Option<u64>,
+    used: AtomicU64,
+}
+
+impl TokenBudget {
+    /// Create a budget with the given total (None = unlimited)
+    pub fn new(total: Option<u64>) -> Self {
+        Self {
+            total,
+            used: AtomicU64::new(0),
+        }
+    }
+
+    /// Record consumed tokens
+    pub fn consume(&self, tokens: u64) {
+        self.used.fetch_add(tokens, Ordering::SeqCst);
+    }
+
+    /// Tokens consumed so far
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::SeqCst)
+    }
+
+    /// Tokens left (None = unlimited)
+    pub fn remaining(&self) -> Option<u64> {
+        self.total.map(|t| t.saturating_sub(self.used()))
+    }
+
+    /// Whether the budget has been fully spent
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining() == Some(0)
+    }
+}
+
+/// Per-child accounting
+#[derive(Debug, Default)]
+struct ChildUsage {
+    steps: AtomicUsize,
+    prompt_tokens: AtomicU64,
+    completion_tokens: AtomicU64,
+    total_tokens: AtomicU64,
+}
+
+/// Provider wrapper that enforces a child's step cap and budget
+///
+/// The inner provider is type-erased so child agents have a single concrete type
+/// no matter how deeply they nest.
+struct SubagentProvider {
+    inner: Arc<dyn Provider>,
+    budget: Arc<TokenBudget>,
+    usage: Arc<ChildUsage>,
+    max_steps: usize,
+    max_tokens: Option<u64>,
+}
+
+#[async_trait]
+impl Provider for SubagentProvider {
+    async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+        let step = self.usage.steps.fetch_add(1, Ordering::SeqCst) + 1;
+        if step > self.max_steps {
+            return Err(Error::AgentExecution(format!(
+                "Subagent step limit ({}) reached",
+                self.max_steps
+            )));
+        }
+        if self.budget.is_exhausted() {
+            return Err(Error::AgentExecution(
+                "Subagent token budget exhausted".to_string(),
+            ));
+        }
+        if let Some(max) = self.max_tokens {
+            if self.usage.total_tokens.load(Ordering::SeqCst) >= max {
+                return Err(Error::AgentExecution(format!(
+                    "Subagent token cap ({}) reached",
+                    max
+                )));
+            }
+        }
+
+        let response = self.inner.stream_completion(request).await?;
+        let budget = Arc::clone(&self.budget);
+        let usage = Arc::clone(&self.usage);
+
+        let stream = response.into_inner().inspect(move |chunk| {
+            if let Ok(StreamingChoice::Usage(u)) = chunk {
+                usage
+                    .prompt_tokens
+                    .fetch_add(u.prompt_tokens as u64, Ordering::SeqCst);
+                usage
+                    .completion_tokens
+                    .fetch_add(u.completion_tokens as u64, Ordering::SeqCst);
+                usage
+                    .total_tokens
+                    .fetch_add(u.total_tokens as u64, Ordering::SeqCst);
+                budget.consume(u.total_tokens as u64);
+            }
+        });
+
+        Ok(StreamingResponse::from_stream(stream))
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.inner.supports_tools()
+    }
+
+    fn supports_vision(&self) -> bool {
+        self.inner.supports_vision()
+    }
+}
+
+/// Outcome of a single child run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubagentOutcome {
+    /// Child index (0-based)
+    pub index: usize,
+    /// Final response if the child succeeded
+    pub output: Option<String>,
+    /// Error message if the child failed
+    pub error: Option<String>,
+    /// Provider calls made by the child
+    pub steps: usize,
+    /// Tokens used by the child
+    pub usage: Usage,
+}
+
+/// Combined result returned to the parent model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubagentReport {
+    /// ID of the parent request, tagging all child activity
+    ///
+    /// This is the parent run's [`Budget::request_id`], so every spawn within
+    /// one top-level request (nested spawns included) shares it. A fresh ID is
+    /// used when the tool is called outside an agent run.
+    pub request_id: String,
+    /// Per-child outcomes, ordered by index
+    pub children: Vec<SubagentOutcome>,
+    /// Number of failed children
+    pub failed: usize,
+    /// Tokens used by all children in this call
+    pub total_tokens: u64,
+    /// Budget left after this call (None = unlimited)
+    pub remaining_budget: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct SpawnArgs {
+    task: String,
+    #[serde(default)]
+    tools: Option<Vec<String>>,
+    #[serde(default)]
+    max_steps: Option<usize>,
+    #[serde(default)]
+    max_tokens: Option<u64>,
+    #[serde(default)]
+    count: Option<usize>,
+}
+
+/// Tool that spawns ephemeral child agents with a restricted toolset
+pub struct SpawnSubagentTool<P: Provider> {
+    provider: Arc<P>,
+    tools: ToolSet,
+    agent_config: AgentConfig,
+    config: SubagentConfig,
+    budget: Arc<TokenBudget>,
+    events: Option<EventHub>,
+    depth: usize,
+}
+
+impl<P: Provider + 'static> SpawnSubagentTool<P> {
+    /// Create a new spawn tool over the parent's provider and tools
+    pub fn new(
+        provider: Arc<P>,
+        tools: ToolSet,
+        agent_config: AgentConfig,
+        config: SubagentConfig,
+    ) -> Self {
+        let budget = Arc::new(TokenBudget::new(config.token_budget));
+        Self {
+            provider,
+            tools,
+            agent_config,
+            config,
+            budget,
+            events: None,
+            depth: 0,
+        }
+    }
+
+    /// Forward child events to the parent's event channel
+    pub fn with_events(mut self, events: impl Into<EventHub>) -> Self {
+        self.events = Some(events.into());
+        self
+    }
+
+    /// Draw from an existing budget instead of creating a new one
+    pub fn with_budget(mut self, budget: Arc<TokenBudget>) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Shared token budget
+    pub fn budget(&self) -> Arc<TokenBudget> {
+        Arc::clone(&self.budget)
+    }
+
+    /// Nesting depth of the agent owning this tool (0 = top-level agent)
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Resolve the toolset for a child, enforcing that it is a subset of ours
+    fn child_tools(&self, requested: Option<&[String]>) -> Result<ToolSet> {
+        let child_depth = self.depth + 1;
+        let mut toolset = ToolSet::new();
+
+        let names: Vec<String> = match requested {
+            Some(names) => names.to_vec(),
+            None => self.tools.iter().map(|(name, _)| name.clone()).collect(),
+        };
+
+        for name in names {
+            if name == SPAWN_SUBAGENT_TOOL {
+                if child_depth >= self.config.max_depth {
+                    return Err(Error::ToolArguments {
+                        tool_name: SPAWN_SUBAGENT_TOOL.to_string(),
+                        message: format!(
+                            "Sub-agent depth limit ({}) reached",
+                            self.config.max_depth
+                        ),
+                    });
+                }
+                let nested = SpawnSubagentTool {
+                    provider: Arc::clone(&self.provider),
+                    tools: self.tools.clone(),
+                    agent_config: self.agent_config.clone(),
+                    config: self.config.clone(),
+                    budget: Arc::clone(&self.budget),
+                    events: self.events.clone(),
+                    depth: child_depth,
+                };
+                toolset.add(nested);
+                continue;
+            }
+
+            let tool = self.tools.get(&name).ok_or_else(|| Error::ToolArguments {
+                tool_name: SPAWN_SUBAGENT_TOOL.to_string(),
+                message: format!("Tool '{}' is not available to the parent agent", name),
+            })?;
+            if tool.writes_memory() {
+                if requested.is_some() {
+                    return Err(Error::ToolArguments {
+                        tool_name: SPAWN_SUBAGENT_TOOL.to_string(),
+                        message: format!("Tool '{}' writes to long-term memory and is not available to sub-agents", name),
+                    });
+                }
+                continue;
+            }
+            toolset.add_shared(Arc::clone(tool));
+        }
+
+        Ok(toolset)
+    }
+
+    /// Run one child to completion
+    async fn run_child(&self, request_id: &str, spec: ChildSpec) -> SubagentOutcome {
+        let usage = Arc::new(ChildUsage::default());
+        let provider = SubagentProvider {
+            inner: Arc::clone(&self.provider) as Arc<dyn Provider>,
+            budget: Arc::clone(&self.budget),
+            usage: Arc::clone(&usage),
+            max_steps: spec.max_steps,
+            max_tokens: spec.max_tokens,
+        };
+
+        let mut builder = Agent::builder(provider)
+            .model(self.agent_config.model.clone())
+            .system_prompt(self.config.preamble.clone())
+            .tool_policy(self.agent_config.tool_policy.clone())
+            .max_tool_output_chars(self.agent_config.max_tool_output_chars)
+            .role(self.agent_config.role.clone())
+            .auto_load_skills(false)
+            .tools(spec.tools);
+        if let Some(temp) = self.agent_config.temperature {
+            builder = builder.temperature(temp);
+        }
+        if let Some(tokens) = self.agent_config.max_tokens {
+            builder = builder.max_tokens(tokens);
+        }
+
+        let result = match builder.build() {
+            Ok(child) => {
+                let forwarder = self.events.clone().map(|parent| {
+                    let mut rx = child.subscribe();
+                    let request_id = request_id.to_string();
+                    let index = spec.index;
+                    tokio::spawn(async move {
+                        while let Ok(event) = rx.recv().await {
+                            let _ = parent.send(AgentEvent::Subagent {
+                                request_id: request_id.clone(),
+                                child: index,
+                                event: Box::new(event),
+                            });
+                        }
+                    })
+                });
+
+                let result = child.prompt(spec.prompt).await;
+
+                // Dropping the child closes its event channel so the forwarder drains and exits
+                drop(child);
+                if let Some(handle) = forwarder {
+                    let _ = handle.await;
+                }
+                result
+            }
+            Err(e) => Err(e),
+        };
+
+        let steps = usage.steps.load(Ordering::SeqCst).min(spec.max_steps);
+        let usage = Usage {
+            prompt_tokens: usage.prompt_tokens.load(Ordering::SeqCst) as u32,
+            completion_tokens: usage.completion_tokens.load(Ordering::SeqCst) as u32,
+            total_tokens: usage.total_tokens.load(Ordering::SeqCst) as u32,
+            ..Default::default()
+        };
+
+        match result {
+            Ok(output) => {
+                tracing::info!(tokens = usage.total_tokens, steps, "Sub-agent finished");
+                SubagentOutcome {
+                    index: spec.index,
+                    output: Some(output),
+                    error: None,
+                    steps,
+                    usage,
+                }
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, steps, "Sub-agent failed");
+                SubagentOutcome {
+                    index: spec.index,
+                    output: None,
+                    error: Some(e.to_string()),
+                    steps,
+                    usage,
+                }
+            }
+        }
+    }
+
+    /// Spawn children for a task and collect their outcomes
+    pub async fn spawn(&self, arguments: &str) -> Result<SubagentReport> {
+        let args: SpawnArgs = parse_args(SPAWN_SUBAGENT_TOOL, arguments)?;
+
+        if self.depth >= self.config.max_depth {
+            return Err(Error::ToolArguments {
+                tool_name: SPAWN_SUBAGENT_TOOL.to_string(),
+                message: format!("Sub-agent depth limit ({}) reached", self.config.max_depth),
+            });
+        }
+
+        let count = args.count.unwrap_or(1);
+        self.require_range("count", count, 1, self.config.max_children)?;
+        self.require_non_empty("task", &args.task)?;
+
+        if self.budget.is_exhausted() {
+            return Err(Error::AgentExecution(
+                "Subagent token budget exhausted".to_string(),
+            ));
+        }
+
+        let tools = self.child_tools(args.tools.as_deref())?;
+        let max_steps = args
+            .max_steps
+            .unwrap_or(self.config.default_max_steps)
+            .clamp(1, self.config.max_steps_limit);
+
+        // Children spend the parent's provider and tool attempts, not a fresh allowance
+        let parent_budget = Budget::current();
+        let request_id = parent_budget
+            .as_ref()
+            .map(|budget| budget.request_id().to_string())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        let used_before = self.budget.used();
+
+        let specs: Vec<ChildSpec> = (0..count)
+            .map(|index| ChildSpec {
+                index,
+                prompt: if count > 1 {
+                    format!(
+                        "{}\n\n(You are worker {} of {}.)",
+                        args.task,
+                        index + 1,
+                        count
+                    )
+                } else {
+                    args.task.clone()
+                },
+                tools: tools.clone(),
+                max_steps,
+                max_tokens: args.max_tokens,
+            })
+            .collect();
+
+        let mut children: Vec<SubagentOutcome> = futures::stream::iter(specs)
+            .map(|spec| {
+                let span = tracing::info_span!(
+                    "subagent",
+                    request_id = %request_id,
+                    child = spec.index,
+                    depth = self.depth + 1
+                );
+                let run = self.run_child(&request_id, spec).instrument(span);
+                let parent_budget = parent_budget.clone();
+                async move {
+                    match parent_budget {
+                        Some(budget) => budget.scope(run).await,
+                        None => run.await,
+                    }
+                }
+            })
+            .buffer_unordered(self.config.max_concurrent.max(1))
+            .collect()
+            .await;
+        children.sort_by_key(|c| c.index);
+
+        let failed = children.iter().filter(|c| c.error.is_some()).count();
+
+        Ok(SubagentReport {
+            request_id,
+            children,
+            failed,
+            total_tokens: self.budget.used().saturating_sub(used_before),
+            remaining_budget: self.budget.remaining(),
+        })
+    }
+}
+
+/// Everything needed to run a single child
+struct ChildSpec {
+    index: usize,
+    prompt: String,
+    tools: ToolSet,
+    max_steps: usize,
+    max_tokens: Option<u64>,
+}
+
+#[async_trait]
+impl<P: Provider + 'static> Tool for SpawnSubagentTool<P> {
+    fn name(&self) -> String {
+        SPAWN_SUBAGENT_TOOL.to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: format!(
+                "Spawn up to {} short-lived sub-agents that work on a task in parallel and return their combined results. \
+                Use this to split independent work (e.g. summarizing several documents). Sub-agents can only use tools you list.",
+                self.config.max_children
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "task": {
+                        "type": "string",
+                        "description": "Instructions for each sub-agent"
+                    },
+                    "tools": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Tool names the sub-agents may use (subset of your tools). Omit to allow all."
+                    },
+                    "max_steps": {
+                        "type": "integer",
+                        "description": "Max model calls per sub-agent"
+                    },
+                    "max_tokens": {
+                        "type": "integer",
+                        "description": "Max tokens each sub-agent may use"
+                    },
+                    "count": {
+                        "type": "integer",
+                        "description": "Number of sub-agents to spawn (default 1)"
+                    }
+                },
+                "required": ["task"]
+            }),
+            parameters_ts: Some("interface SpawnSubagentArgs {\n  task: string; // Instructions for each sub-agent\n  tools?: string[]; // Subset of your tools (default: all)\n  max_steps?: number; // Max model calls per sub-agent\n  max_tokens?: number; // Token cap per sub-agent\n  count?: number; // Number of sub-agents (default 1)\n}".to_string()),
+            is_verified: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        let report = self.spawn(arguments).await?;
+        Ok(serde_json::to_string_pretty(&report)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::streaming::MockStreamBuilder;
+
+    /// Provider whose reply depends on the worker number in the prompt
+    struct ScriptedProvider;
+
+    #[async_trait]
+    impl Provider for ScriptedProvider {
+        async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+            let has_result = request
+                .messages
+                .iter()
+                .any(|m| m.role == crate::agent::message::Role::Tool);
+            let prompt = request
+                .messages
+                .iter()
+                .find(|m| m.role == crate::agent::message::Role::User)
+                .map(|m| m.content.as_text())
+                .unwrap_or_default();
+
+            if prompt.contains("worker 2 of") {
+                return Err(Error::ProviderApi("scripted failure".to_string()));
+            }
+
+            let usage = Usage {
+                prompt_tokens: 6,
+                completion_tokens: 4,
+                total_tokens: 10,
+                ..Default::default()
+            };
+
+            if prompt.contains("use secret") && !has_result {
+                return Ok(MockStreamBuilder::new()
+                    .tool_call("call_1", "secret", serde_json::json!({}))
+                    .usage(usage)
+                    .done()
+                    .build());
+            }
+
+            Ok(MockStreamBuilder::new()
+                .message(format!(
+                    "done: {}",
+                    prompt.lines().next().unwrap_or_default()
+                ))
+                .usage(usage)
+                .done()
+                .build())
+        }
+
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+    }
+
+    struct CountingTool {
+        name: &'static str,
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Tool for CountingTool {
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+
+        async fn definition(&self) -> ToolDefinition {
+            ToolDefinition {
+                name: self.name.to_string(),
+                description: "Counts calls".to_string(),
+                parameters: serde_json::json!({"type": "object"}),
+                is_verified: true,
+                ..Default::default()
+            }
+        }
+
+        async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok("called".to_string())
+        }
+    }
+
+    fn spawn_tool(
+        config: SubagentConfig,
+    ) -> (SpawnSubagentTool<ScriptedProvider>, Arc<AtomicUsize>) {
+        let secret_calls = Arc::new(AtomicUsize::new(0));
+        let mut tools = ToolSet::new();
+        tools.add(CountingTool {
+            name: "echo",
+            calls: Arc::new(AtomicUsize::new(0)),
+        });
+        tools.add(CountingTool {
+            name: "secret",
+            calls: Arc::clone(&secret_calls),
+        });
+
+        let tool = SpawnSubagentTool::new(
+            Arc::new(ScriptedProvider),
+            tools,
+            AgentConfig::default(),
+            config,
+        );
+        (tool, secret_calls)
+    }
+
+    #[tokio::test]
+    async fn test_toolset_restriction_enforced() {
+        let (tool, secret_calls) = spawn_tool(SubagentConfig::default());
+
+        let err = tool
+            .spawn(r#"{"task": "x", "tools": ["missing"]}"#)
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("not available to the parent agent"));
+
+        // The child asks for "secret" but was only granted "echo"
+        let report = tool
+            .spawn(r#"{"task": "use secret", "tools": ["echo"]}"#)
+            .await
+            .unwrap();
+        assert_eq!(report.failed, 1);
+        assert!(report.children[0].error.as_deref().unwrap().contains("secret"));
+        assert_eq!(secret_calls.load(Ordering::SeqCst), 0);
+
+        // Granted: the call goes through
+        tool.spawn(r#"{"task": "use secret", "tools": ["secret"]}"#)
+            .await
+            .unwrap();
+        assert_eq!(secret_calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_budget_deduction() {
+        let (tool, _) = spawn_tool(SubagentConfig {
+            token_budget: Some(100),
+            ..Default::default()
+        });
+
+        let report = tool
+            .spawn(r#"{"task": "summarize", "count": 3, "tools": []}"#)
+            .await
+            .unwrap();
+        // Worker 2 fails before using any tokens
+        assert_eq!(report.total_tokens, 20);
+        assert_eq!(report.remaining_budget, Some(80));
+        assert_eq!(tool.budget().used(), 20);
+
+        let (tool, _) = spawn_tool(SubagentConfig {
+            token_budget: Some(10),
+            max_concurrent: 1,
+            ..Default::default()
+        });
+        let report = tool
+            .spawn(r#"{"task": "summarize", "tools": []}"#)
+            .await
+            .unwrap();
+        assert_eq!(report.remaining_budget, Some(0));
+        let err = tool.spawn(r#"{"task": "again"}"#).await.unwrap_err();
+        assert!(err.to_string().contains("budget exhausted"));
+    }
+
+    #[tokio::test]
+    async fn test_depth_limit() {
+        let (tool, _) = spawn_tool(SubagentConfig::default());
+        let err = tool
+            .spawn(r#"{"task": "x", "tools": ["spawn_subagent"]}"#)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("depth limit"));
+
+        let (tool, _) = spawn_tool(SubagentConfig {
+            max_depth: 2,
+            ..Default::default()
+        });
+        let nested = tool
+            .child_tools(Some(&["spawn_subagent".to_string()]))
+            .unwrap();
+        assert!(nested.contains(SPAWN_SUBAGENT_TOOL));
+
+        let mut deep = spawn_tool(SubagentConfig::default()).0;
+        deep.depth = 1;
+        let err = deep.spawn(r#"{"task": "x"}"#).await.unwrap_err();
+        assert!(err.to_string().contains("depth limit"));
+    }
+
+    #[tokio::test]
+    async fn test_aggregates_failures_and_tags_events() {
+        let (tx, mut rx) = tokio::sync::broadcast::channel(100);
+        let (tool, _) = spawn_tool(SubagentConfig::default());
+        let tool = tool.with_events(tx);
+
+        let output = tool
+            .call(r#"{"task": "summarize doc", "count": 3, "tools": []}"#)
+            .await
+            .unwrap();
+        let report: SubagentReport = serde_json::from_str(&output).unwrap();
+
+        assert_eq!(report.children.len(), 3);
+        assert_eq!(report.failed, 1);
+        assert!(report.children[0]
+            .output
+            .as_deref()
+            .unwrap()
+            .starts_with("done: summarize doc"));
+        assert!(report.children[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("scripted failure"));
+        assert!(report.children[2].output.is_some());
+        assert_eq!(report.children[0].usage.total_tokens, 10);
+
+        let mut tagged = 0;
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                AgentEvent::Subagent { request_id, .. } => {
+                    assert_eq!(request_id, report.request_id);
+                    tagged += 1;
+                }
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert!(tagged > 0);
+    }
+
+    #[tokio::test]
+    async fn test_children_tagged_with_parent_request_id() {
+        let (tool, _) = spawn_tool(SubagentConfig::default());
+        let parent = Budget::new(Default::default());
+
+        let report = parent
+            .clone()
+            .scope(tool.spawn(r#"{"task": "x", "tools": []}"#))
+            .await
+            .unwrap();
+        assert_eq!(report.request_id, parent.request_id());
+
+        let standalone = tool.spawn(r#"{"task": "x", "tools": []}"#).await.unwrap();
+        assert_ne!(standalone.request_id, parent.request_id());
+    }
+
+    struct MemoryWriter;
+
+    #[async_trait]
+    impl Tool for MemoryWriter {
+        fn name(&self) -> String {
+            "jot".to_string()
+        }
+
+        async fn definition(&self) -> ToolDefinition {
+            CountingTool { name: "jot", calls: Arc::default() }.definition().await
+        }
+
+        async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+            Ok("saved".to_string())
+        }
+
+        fn writes_memory(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_writers_withheld() {
+        let (mut tool, _) = spawn_tool(SubagentConfig::default());
+        tool.tools.add(MemoryWriter);
+
+        let err = tool
+            .spawn(r#"{"task": "x", "tools": ["jot"]}"#)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("writes to long-term memory"));
+
+        let inherited = tool.child_tools(None).unwrap();
+        assert!(inherited.contains("echo"));
+        assert!(!inherited.contains("jot"));
+    }
+
+    #[tokio::test]
+    async fn test_children_spend_the_parent_request_budget() {
+        use crate::agent::budget::BudgetConfig;
+
+        let (tool, _) = spawn_tool(SubagentConfig::default());
+        let budget = Budget::new(BudgetConfig {
+            max_provider_attempts: 1,
+            ..Default::default()
+        });
+
+        let (first, second) = budget
+            .clone()
+            .scope(async {
+                let first = tool.spawn(r#"{"task": "one", "tools": []}"#).await.unwrap();
+                let second = tool.spawn(r#"{"task": "two", "tools": []}"#).await.unwrap();
+                (first, second)
+            })
+            .await;
+        assert_eq!(first.failed, 0);
+        assert_eq!(second.failed, 1);
+        assert_eq!(budget.summary().provider_attempts, 1);
+    }
+}
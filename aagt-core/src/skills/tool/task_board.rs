@@ -0,0 +1,289 @@
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::agent::multi_agent::task_board::{NewTask, TaskBoard, TaskFilter, TaskState};
+use crate::skills::tool::{parse_args, Tool, ToolDefinition};
+
+/// Tool that posts tasks to a shared task board
+pub struct PostTaskTool {
+    board: Arc<TaskBoard>,
+    agent_id: String,
+}
+
+impl PostTaskTool {
+    /// Create a tool that posts tasks as `agent_id`
+    pub fn new(board: Arc<TaskBoard>, agent_id: impl Into<String>) -> Self {
+        Self {
+            board,
+            agent_id: agent_id.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct PostTaskArgs {
+    title: String,
+    #[serde(default)]
+    payload: serde_json::Value,
+    #[serde(default)]
+    priority: i32,
+}
+
+#[async_trait]
+impl Tool for PostTaskTool {
+    fn name(&self) -> String {
+        "post_task".to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Post a task to the shared task board for other agents to claim.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "title": { "type": "string", "description": "What needs to be done" },
+                    "payload": { "description": "Input data for the task" },
+                    "priority": { "type": "integer", "description": "Higher is claimed first (default 0)" }
+                },
+                "required": ["title"]
+            }),
+            parameters_ts: Some("interface PostTaskArgs {\n  title: string; // What needs to be done\n  payload?: any; // Input data for the task\n  priority?: number; // Higher is claimed first (default 0)\n}".to_string()),
+            is_verified: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        let args: PostTaskArgs = parse_args(&self.name(), arguments)?;
+        let task = self
+            .board
+            .post(
+                NewTask::new(args.title, &self.agent_id)
+                    .with_payload(args.payload)
+                    .with_priority(args.priority),
+            )
+            .await?;
+        Ok(serde_json::to_string(&task)?)
+    }
+}
+
+/// Tool that claims tasks from a shared task board and reports their outcome
+pub struct ClaimTaskTool {
+    board: Arc<TaskBoard>,
+    agent_id: String,
+}
+
+impl ClaimTaskTool {
+    /// Create a tool that claims tasks as `agent_id`
+    pub fn new(board: Arc<TaskBoard>, agent_id: impl Into<String>) -> Self {
+        Self {
+            board,
+            agent_id: agent_id.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum ClaimTaskArgs {
+    Claim {
+        #[serde(default)]
+        title_contains: Option<String>,
+    },
+    Complete {
+        id: String,
+        result: String,
+    },
+    Fail {
+        id: String,
+        error: String,
+    },
+}
+
+#[async_trait]
+impl Tool for ClaimTaskTool {
+    fn name(&self) -> String {
+        "claim_task".to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Claim the next open task from the shared task board, or report the result of a task you claimed.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": { "type": "string", "enum": ["claim", "complete", "fail"] },
+                    "title_contains": { "type": "string", "description": "Only claim tasks whose title contains this text" },
+                    "id": { "type": "string", "description": "Task ID (complete/fail)" },
+                    "result": { "type": "string", "description": "Task result (complete)" },
+                    "error": { "type": "string", "description": "Failure reason (fail)" }
+                },
+                "required": ["action"]
+            }),
+            parameters_ts: Some("type ClaimTaskArgs =\n  | { action: 'claim'; title_contains?: string }\n  | { action: 'complete'; id: string; result: string }\n  | { action: 'fail'; id: string; error: string };".to_string()),
+            is_verified: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        let args: ClaimTaskArgs = parse_args(&self.name(), arguments)?;
+        match args {
+            ClaimTaskArgs::Claim { title_contains } => {
+                let mut filter = TaskFilter::new();
+                filter.title_contains = title_contains;
+                match self.board.claim_next(&self.agent_id, &filter).await? {
+                    Some(task) => Ok(serde_json::to_string(&task)?),
+                    None => Ok("No open tasks available.".to_string()),
+                }
+            }
+            ClaimTaskArgs::Complete { id, result } => {
+                let task = self.board.complete(&id, &self.agent_id, result).await?;
+                Ok(serde_json::to_string(&task)?)
+            }
+            ClaimTaskArgs::Fail { id, error } => {
+                let task = self.board.fail(&id, &self.agent_id, error).await?;
+                Ok(serde_json::to_string(&task)?)
+            }
+        }
+    }
+}
+
+/// Tool that inspects tasks on a shared task board
+pub struct TaskStatusTool {
+    board: Arc<TaskBoard>,
+}
+
+impl TaskStatusTool {
+    pub fn new(board: Arc<TaskBoard>) -> Self {
+        Self { board }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct TaskStatusArgs {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    state: Option<TaskState>,
+}
+
+#[async_trait]
+impl Tool for TaskStatusTool {
+    fn name(&self) -> String {
+        "task_status".to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Check the status of one task, or list tasks on the shared task board.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string", "description": "Task ID to look up" },
+                    "state": { "type": "string", "enum": ["open", "claimed", "done", "failed"] }
+                }
+            }),
+            parameters_ts: Some("interface TaskStatusArgs {\n  id?: string; // Task ID to look up\n  state?: 'open' | 'claimed' | 'done' | 'failed'; // Filter when listing\n}".to_string()),
+            is_verified: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        let args: TaskStatusArgs = parse_args(&self.name(), arguments)?;
+        if let Some(id) = args.id {
+            let task = self
+                .board
+                .get(&id)
+                .await
+                .ok_or_else(|| anyhow::anyhow!("Task not found: {}", id))?;
+            return Ok(serde_json::to_string(&task)?);
+        }
+
+        let filter = TaskFilter {
+            state: args.state,
+            ..Default::default()
+        };
+        let tasks = self.board.list(&filter).await;
+        Ok(serde_json::json!({
+            "stats": self.board.stats().await,
+            "tasks": tasks,
+        })
+        .to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::multi_agent::task_board::InMemoryTaskStore;
+    use crate::skills::tool::ToolSet;
+
+    #[tokio::test]
+    async fn test_task_tools_round_trip() {
+        let board = Arc::new(TaskBoard::new(Arc::new(InMemoryTaskStore)).await.unwrap());
+        let mut analyst = ToolSet::new();
+        analyst.add(PostTaskTool::new(board.clone(), "analyst"));
+        analyst.add(TaskStatusTool::new(board.clone()));
+        let mut worker = ToolSet::new();
+        worker.add(ClaimTaskTool::new(board.clone(), "worker"));
+
+        let posted: serde_json::Value = serde_json::from_str(
+            &analyst
+                .call(
+                    "post_task",
+                    r#"{"title": "verify contract 0xabc", "payload": {"chain": "sol"}}"#,
+                )
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        let id = posted["id"].as_str().unwrap().to_string();
+
+        let claimed: serde_json::Value = serde_json::from_str(
+            &worker
+                .call("claim_task", r#"{"action": "claim"}"#)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(claimed["id"], id.as_str());
+        assert_eq!(claimed["status"]["by"], "worker");
+        assert_eq!(
+            worker
+                .call("claim_task", r#"{"action": "claim"}"#)
+                .await
+                .unwrap(),
+            "No open tasks available."
+        );
+
+        let args = serde_json::json!({"action": "complete", "id": id, "result": "verified"});
+        worker.call("claim_task", &args.to_string()).await.unwrap();
+
+        let status: serde_json::Value = serde_json::from_str(
+            &analyst
+                .call("task_status", &serde_json::json!({"id": id}).to_string())
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(status["status"]["state"], "done");
+        assert_eq!(status["status"]["result"], "verified");
+
+        let listing: serde_json::Value = serde_json::from_str(
+            &analyst
+                .call("task_status", r#"{"state": "done"}"#)
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(listing["stats"]["done"], 1);
+        assert_eq!(listing["tasks"].as_array().unwrap().len(), 1);
+    }
+}
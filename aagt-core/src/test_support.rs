@@ -0,0 +1,96 @@
+//! Fixtures shared by unit tests across modules
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+
+use crate::agent::message::Role;
+use crate::agent::provider::{ChatRequest, Provider};
+use crate::agent::streaming::{MockStreamBuilder, StreamingResponse};
+use crate::error::Result;
+use crate::infra::outbox::Clock;
+use crate::skills::tool::{Tool, ToolDefinition};
+
+/// Clock that only moves when a test advances it
+pub(crate) struct ManualClock(Mutex<DateTime<Utc>>);
+
+impl ManualClock {
+    /// Start at the current wall-clock time
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self(Mutex::new(Utc::now())))
+    }
+
+    pub(crate) fn advance(&self, by: Duration) {
+        *self.0.lock() += chrono::Duration::from_std(by).unwrap();
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock()
+    }
+}
+
+/// Answers "ok" to every request
+pub(crate) struct StubProvider;
+
+#[async_trait]
+impl Provider for StubProvider {
+    async fn stream_completion(&self, _request: ChatRequest) -> Result<StreamingResponse> {
+        Ok(MockStreamBuilder::new().message("ok").done().build())
+    }
+
+    fn name(&self) -> &'static str {
+        "stub"
+    }
+}
+
+/// Calls [`PriceTool`] on a fresh user prompt, otherwise answers
+pub(crate) struct PriceProvider;
+
+#[async_trait]
+impl Provider for PriceProvider {
+    async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+        let builder = match request.messages.last().map(|m| m.role.clone()) {
+            Some(Role::User) => MockStreamBuilder::new().tool_call(
+                "c1",
+                "price",
+                serde_json::json!({ "symbol": "SOL" }),
+            ),
+            _ => MockStreamBuilder::new().message("SOL is $150"),
+        };
+        Ok(builder.done().build())
+    }
+
+    fn name(&self) -> &'static str {
+        "price-provider"
+    }
+}
+
+/// `price` tool that always answers 150
+pub(crate) struct PriceTool;
+
+#[async_trait]
+impl Tool for PriceTool {
+    fn name(&self) -> String {
+        "price".to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Price lookup".to_string(),
+            parameters: serde_json::json!({ "type": "object" }),
+            is_verified: true,
+            side_effect_free: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, _: &str) -> anyhow::Result<String> {
+        Ok("150".to_string())
+    }
+}
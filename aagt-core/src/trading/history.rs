@@ -0,0 +1,709 @@
+//! Queryable history of strategy pipeline runs
+//!
+//! Every run executed by [`StrategyEngine`](crate::trading::strategy::StrategyEngine)
+//! with a [`RunHistory`] attached is recorded as a [`PipelineRun`]: the trigger,
+//! each condition with the value it measured, each action with its executor
+//! result or risk denial, and the final status. Risk reservations made during a
+//! run carry its `run_id` (see [`ReservationOrigin::with_run`]), so journal
+//! entries can be matched back to the run.
+//!
+//! [`ReservationOrigin::with_run`]: crate::trading::risk::ReservationOrigin::with_run
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+
+use crate::error::{Error, Result};
+use crate::trading::strategy::{Action, Condition, Strategy};
+
+/// What started a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RunTrigger {
+    /// Scheduled by a cron expression
+    Cron { schedule: String },
+    /// Started by a user or the agent
+    Manual,
+    /// Started by an external event
+    Event { name: String },
+}
+
+impl std::fmt::Display for RunTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cron { schedule } => write!(f, "cron ({})", schedule),
+            Self::Manual => f.write_str("manual"),
+            Self::Event { name } => write!(f, "event ({})", name),
+        }
+    }
+}
+
+/// Final status of a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    /// All actions ran
+    Completed,
+    /// Conditions were not met, nothing ran
+    ConditionNotMet,
+    /// An action was blocked by risk controls
+    RiskDenied,
+    /// An action or condition failed
+    Failed,
+    /// The pipeline aborted itself
+    Cancelled,
+}
+
+/// One evaluated (leaf) condition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConditionRecord {
+    /// The condition as configured
+    pub condition: Condition,
+    /// Whether it held
+    pub result: bool,
+    /// Value the evaluator measured, e.g. the price it saw
+    pub measured: Option<Value>,
+}
+
+/// What happened to one action
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActionOutcome {
+    /// Executor returned a result
+    Executed { result: String },
+    /// Risk controls blocked it
+    RiskDenied { check: String, reason: String },
+    /// Executor failed
+    Failed { error: String },
+    /// Not attempted because an earlier step stopped the run
+    Skipped,
+}
+
+impl ActionOutcome {
+    /// Classify an executor error, separating risk denials from other failures
+    pub fn from_error(error: &Error) -> Self {
+        match error {
+            Error::RiskCheckFailed { check_name, reason } => Self::RiskDenied {
+                check: check_name.clone(),
+                reason: reason.clone(),
+            },
+            Error::RiskLimitExceeded {
+                limit_type,
+                current,
+                max,
+            } => Self::RiskDenied {
+                check: limit_type.clone(),
+                reason: format!("current {}, max {}", current, max),
+            },
+            other => Self::Failed {
+                error: other.to_string(),
+            },
+        }
+    }
+}
+
+/// One attempted action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionRecord {
+    /// Position in the strategy's action list
+    pub index: usize,
+    /// The action as configured
+    pub action: Action,
+    /// What happened
+    pub outcome: ActionOutcome,
+    /// When it finished
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A recorded strategy run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineRun {
+    /// Unique run ID
+    pub run_id: String,
+    /// Strategy ID
+    pub strategy_id: String,
+    /// Strategy name at the time of the run
+    pub strategy_name: String,
+    /// Hash of the strategy's conditions and actions (see [`strategy_version`])
+    pub strategy_version: String,
+    /// Owner of the strategy
+    pub user_id: String,
+    /// What started the run
+    pub trigger: RunTrigger,
+    /// When the run started
+    pub started_at: DateTime<Utc>,
+    /// When the run finished
+    pub finished_at: DateTime<Utc>,
+    /// Leaf conditions in evaluation order (empty when conditions were bypassed)
+    pub conditions: Vec<ConditionRecord>,
+    /// Actions in order
+    pub actions: Vec<ActionRecord>,
+    /// Final status
+    pub status: RunStatus,
+    /// Error or cancellation reason
+    pub error: Option<String>,
+}
+
+impl PipelineRun {
+    /// Render the run as markdown, e.g. for a notification digest
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "### {} — {:?}\n\n- Run: `{}`\n- Trigger: {}\n- Started: {}\n- Duration: {} ms\n- Version: `{}`\n",
+            self.strategy_name,
+            self.status,
+            self.run_id,
+            self.trigger,
+            self.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            (self.finished_at - self.started_at).num_milliseconds(),
+            self.strategy_version,
+        );
+        if let Some(error) = &self.error {
+            let _ = writeln!(out, "- Error: {}", error);
+        }
+
+        if !self.conditions.is_empty() {
+            out.push_str("\n**Conditions**\n\n");
+            for c in &self.conditions {
+                let measured = c
+                    .measured
+                    .as_ref()
+                    .map(|v| format!(" (measured {})", v))
+                    .unwrap_or_default();
+                let _ = writeln!(
+                    out,
+                    "- {} `{}`{}",
+                    if c.result { "✅" } else { "❌" },
+                    serde_json::to_string(&c.condition).unwrap_or_default(),
+                    measured
+                );
+            }
+        }
+
+        if !self.actions.is_empty() {
+            out.push_str("\n**Actions**\n\n");
+            for a in &self.actions {
+                let detail = match &a.outcome {
+                    ActionOutcome::Executed { result } => format!("executed: {}", result),
+                    ActionOutcome::RiskDenied { check, reason } => {
+                        format!("risk denied by {}: {}", check, reason)
+                    }
+                    ActionOutcome::Failed { error } => format!("failed: {}", error),
+                    ActionOutcome::Skipped => "skipped".to_string(),
+                };
+                let _ = writeln!(
+                    out,
+                    "{}. `{}` — {}",
+                    a.index + 1,
+                    serde_json::to_string(&a.action).unwrap_or_default(),
+                    detail
+                );
+            }
+        }
+        out
+    }
+}
+
+/// Short hash identifying a strategy's logic (conditions and actions)
+pub fn strategy_version(strategy: &Strategy) -> String {
+    // Debug rather than JSON: internally tagged `And`/`Or` conditions don't serialize
+    let logic = format!("{:?}|{:?}", strategy.condition, strategy.actions);
+    let digest = Sha256::digest(logic.as_bytes());
+    digest
+        .iter()
+        .take(6)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Filter for [`RunHistory::query`]
+#[derive(Debug, Clone, Default)]
+pub struct RunQuery {
+    /// Strategy ID or name
+    pub strategy: Option<String>,
+    /// Start-time range
+    pub range: Option<Range<DateTime<Utc>>>,
+    /// Final status
+    pub status: Option<RunStatus>,
+    /// Max runs returned (most recent first)
+    pub limit: Option<usize>,
+}
+
+impl RunQuery {
+    /// Match all runs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only runs of this strategy (ID or name)
+    pub fn strategy(mut self, strategy: impl Into<String>) -> Self {
+        self.strategy = Some(strategy.into());
+        self
+    }
+
+    /// Only runs started within `range`
+    pub fn range(mut self, range: Range<DateTime<Utc>>) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Only runs with this status
+    pub fn status(mut self, status: RunStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Return at most `limit` runs
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn matches(&self, run: &PipelineRun) -> bool {
+        self.strategy
+            .as_ref()
+            .is_none_or(|s| *s == run.strategy_id || *s == run.strategy_name)
+            && self
+                .range
+                .as_ref()
+                .is_none_or(|r| r.contains(&run.started_at))
+            && self.status.is_none_or(|s| s == run.status)
+    }
+}
+
+/// How long runs are kept
+#[derive(Debug, Clone)]
+pub struct RunRetention {
+    /// Drop runs older than this
+    pub max_age: Option<Duration>,
+    /// Keep only the most recent N runs per strategy
+    pub max_runs_per_strategy: Option<usize>,
+}
+
+impl Default for RunRetention {
+    fn default() -> Self {
+        Self {
+            max_age: Some(Duration::days(90)),
+            max_runs_per_strategy: Some(1000),
+        }
+    }
+}
+
+/// Run records, optionally persisted as JSONL (one run per line)
+pub struct RunHistory {
+    path: Option<PathBuf>,
+    runs: RwLock<Vec<PipelineRun>>,
+    writer: tokio::sync::Mutex<()>,
+}
+
+impl RunHistory {
+    /// History kept in memory only
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            runs: RwLock::new(Vec::new()),
+            writer: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Open a JSONL-backed history, loading existing runs
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut runs = Vec::new();
+        if path.exists() {
+            let content = tokio::fs::read_to_string(&path).await?;
+            for (n, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str(line) {
+                    Ok(run) => runs.push(run),
+                    Err(e) => {
+                        tracing::warn!("Skipping malformed run line {} in {:?}: {}", n + 1, path, e)
+                    }
+                }
+            }
+        }
+        Ok(Self {
+            path: Some(path),
+            runs: RwLock::new(runs),
+            writer: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Persist a finished run
+    pub async fn record(&self, run: PipelineRun) -> Result<()> {
+        if let Some(path) = &self.path {
+            let mut line = serde_json::to_string(&run)?;
+            line.push('\n');
+            let _guard = self.writer.lock().await;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await.ok();
+            }
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            file.write_all(line.as_bytes()).await?;
+            file.flush().await?;
+        }
+        self.runs.write().push(run);
+        Ok(())
+    }
+
+    /// Look up a run by ID
+    pub fn get(&self, run_id: &str) -> Option<PipelineRun> {
+        self.runs
+            .read()
+            .iter()
+            .find(|r| r.run_id == run_id)
+            .cloned()
+    }
+
+    /// Runs matching `query`, most recent first
+    pub fn query(&self, query: &RunQuery) -> Vec<PipelineRun> {
+        let mut runs: Vec<PipelineRun> = self
+            .runs
+            .read()
+            .iter()
+            .filter(|r| query.matches(r))
+            .cloned()
+            .collect();
+        runs.sort_by_key(|r| std::cmp::Reverse(r.started_at));
+        if let Some(limit) = query.limit {
+            runs.truncate(limit);
+        }
+        runs
+    }
+
+    /// Drop runs outside `retention` and compact the file; returns how many were dropped
+    pub async fn apply_retention(&self, retention: &RunRetention) -> Result<usize> {
+        let _guard = self.writer.lock().await;
+        let (kept, dropped) = {
+            let mut runs = self.runs.write();
+            let before = runs.len();
+            if let Some(max_age) = retention.max_age {
+                let cutoff = Utc::now() - max_age;
+                runs.retain(|r| r.started_at >= cutoff);
+            }
+            if let Some(max) = retention.max_runs_per_strategy {
+                runs.sort_by_key(|r| std::cmp::Reverse(r.started_at));
+                let mut seen: HashMap<String, usize> = HashMap::new();
+                runs.retain(|r| {
+                    let count = seen.entry(r.strategy_id.clone()).or_default();
+                    *count += 1;
+                    *count <= max
+                });
+                runs.reverse();
+            }
+            (runs.clone(), before - runs.len())
+        };
+
+        if let (Some(path), true) = (&self.path, dropped > 0) {
+            let mut content = String::new();
+            for run in &kept {
+                content.push_str(&serde_json::to_string(run)?);
+                content.push('\n');
+            }
+            // Write tmp -> rename so a crash never leaves a truncated history
+            let tmp_path = path.with_extension(format!("tmp.{}", uuid::Uuid::new_v4()));
+            tokio::fs::write(&tmp_path, content).await?;
+            if let Err(e) = tokio::fs::rename(&tmp_path, path).await {
+                let _ = tokio::fs::remove_file(&tmp_path).await;
+                return Err(e.into());
+            }
+        }
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infra::notification::NotifyChannel;
+    use crate::trading::pipeline::Context;
+    use crate::trading::risk::{
+        InMemoryRiskStore, ReservationOrigin, ReservationResolution, RiskConfig, RiskManager,
+        TradeContext,
+    };
+    use crate::trading::strategy::{ActionExecutor, ConditionEvaluator, StrategyEngine};
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+    use std::sync::Arc;
+
+    /// Evaluates price conditions against a settable SOL price
+    struct FixedPrice(parking_lot::Mutex<Decimal>);
+
+    #[async_trait::async_trait]
+    impl ConditionEvaluator for FixedPrice {
+        async fn evaluate(&self, condition: &Condition) -> Result<bool> {
+            Ok(self.evaluate_with_value(condition).await?.0)
+        }
+
+        async fn evaluate_with_value(
+            &self,
+            condition: &Condition,
+        ) -> Result<(bool, Option<Value>)> {
+            let price = *self.0.lock();
+            let held = match condition {
+                Condition::PriceAbove { threshold, .. } => price > *threshold,
+                Condition::PriceBelow { threshold, .. } => price < *threshold,
+                _ => false,
+            };
+            Ok((held, Some(serde_json::json!(price.to_string()))))
+        }
+    }
+
+    /// Swaps go through the risk manager, attributed to the run
+    struct RiskCheckedExecutor(RiskManager);
+
+    #[async_trait::async_trait]
+    impl ActionExecutor for RiskCheckedExecutor {
+        async fn execute(&self, action: &Action, ctx: &Context) -> Result<String> {
+            match action {
+                Action::Swap {
+                    from_token,
+                    to_token,
+                    amount,
+                } => {
+                    let run_id = ctx.get("run_id").and_then(|v| v.as_str()).unwrap();
+                    let trade = TradeContext {
+                        user_id: "user1".to_string(),
+                        from_token: from_token.clone(),
+                        to_token: to_token.clone(),
+                        amount_usd: amount.parse().unwrap(),
+                        expected_slippage: dec!(0.5),
+                        liquidity_usd: Some(dec!(1_000_000)),
+                        is_flagged: false,
+                    };
+                    let origin = ReservationOrigin::new().with_tool("swap").with_run(run_id);
+                    let id = self.0.check_and_reserve_with_origin(&trade, origin).await?;
+                    self.0
+                        .resolve_reservation(&id, ReservationResolution::Commit)
+                        .await?;
+                    Ok(format!("swapped {} {}", amount, from_token))
+                }
+                _ => Ok("done".to_string()),
+            }
+        }
+    }
+
+    fn dca_strategy() -> Strategy {
+        let swap = |amount: &str| Action::Swap {
+            from_token: "USDC".to_string(),
+            to_token: "SOL".to_string(),
+            amount: amount.to_string(),
+        };
+        Strategy {
+            id: "dca-1".to_string(),
+            user_id: "user1".to_string(),
+            name: "SOL DCA".to_string(),
+            description: None,
+            condition: Condition::And(vec![
+                Condition::PriceAbove {
+                    token: "SOL".to_string(),
+                    threshold: dec!(100),
+                },
+                Condition::PriceBelow {
+                    token: "SOL".to_string(),
+                    threshold: dec!(200),
+                },
+            ]),
+            actions: vec![
+                swap("100"),
+                swap("1000"),
+                Action::Notify {
+                    channel: NotifyChannel::Telegram,
+                    message: "bought".to_string(),
+                },
+            ],
+            active: true,
+            created_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_within_calendar_gates_strategy_runs() {
+        use crate::agent::calendar::{CalendarRegistry, MaintenanceWindow, TradingCalendar};
+
+        let calendars = Arc::new(CalendarRegistry::default());
+        calendars.register(TradingCalendar::always_open("dex").maintenance(MaintenanceWindow {
+            start: Utc::now() - Duration::minutes(5),
+            end: Utc::now() + Duration::minutes(5),
+            reason: Some("router upgrade".to_string()),
+        }));
+        let evaluator = Arc::new(FixedPrice(parking_lot::Mutex::new(dec!(150))));
+        let risk = RiskManager::with_config(RiskConfig::default(), Arc::new(InMemoryRiskStore))
+            .await
+            .unwrap();
+        let engine = StrategyEngine::simple(evaluator, Arc::new(RiskCheckedExecutor(risk)))
+            .with_calendars(calendars);
+
+        let within = |name: &str| Condition::WithinCalendar {
+            calendar: name.to_string(),
+        };
+        let mut strategy = dca_strategy();
+        strategy.condition = Condition::And(vec![within("crypto"), within("dex")]);
+        let run = engine
+            .run_strategy(&strategy, RunTrigger::Manual)
+            .await
+            .unwrap();
+        assert_eq!(run.status, RunStatus::ConditionNotMet);
+        assert_eq!(
+            run.conditions.iter().map(|c| c.result).collect::<Vec<_>>(),
+            vec![true, false]
+        );
+        assert_eq!(
+            run.conditions[1].measured,
+            Some(serde_json::json!("maintenance: router upgrade"))
+        );
+
+        strategy.condition = within("lse");
+        let unknown = engine
+            .run_strategy(&strategy, RunTrigger::Manual)
+            .await
+            .unwrap();
+        assert_eq!(unknown.status, RunStatus::Failed);
+        assert!(unknown.error.unwrap().contains("Unknown trading calendar: lse"));
+    }
+
+    #[tokio::test]
+    async fn test_runs_record_conditions_outcomes_and_risk_linkage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("runs.jsonl");
+        let history = Arc::new(RunHistory::open(&path).await.unwrap());
+
+        let config = RiskConfig {
+            max_single_trade_usd: dec!(500),
+            trade_cooldown_secs: 0,
+            ..Default::default()
+        };
+        let risk = RiskManager::with_config(config, Arc::new(InMemoryRiskStore))
+            .await
+            .unwrap();
+        let executor = Arc::new(RiskCheckedExecutor(risk));
+        let evaluator = Arc::new(FixedPrice(parking_lot::Mutex::new(dec!(150))));
+        let engine = StrategyEngine::simple(evaluator.clone(), executor.clone())
+            .with_history(history.clone());
+        let strategy = dca_strategy();
+
+        // Conditions hold: first swap executes, second is denied, notify never runs
+        let denied = engine
+            .run_strategy(&strategy, RunTrigger::Manual)
+            .await
+            .unwrap();
+        assert_eq!(denied.status, RunStatus::RiskDenied);
+        assert_eq!(denied.conditions.len(), 2);
+        assert!(denied.conditions.iter().all(|c| c.result));
+        assert_eq!(
+            denied.conditions[0].measured,
+            Some(serde_json::json!("150"))
+        );
+        assert!(
+            matches!(&denied.actions[0].outcome, ActionOutcome::Executed { result } if result == "swapped 100 USDC")
+        );
+        assert!(
+            matches!(&denied.actions[1].outcome, ActionOutcome::RiskDenied { check, .. } if check == "single_trade")
+        );
+        assert_eq!(denied.actions[2].outcome, ActionOutcome::Skipped);
+
+        let journal = executor.0.reservation_journal().await;
+        assert_eq!(journal.len(), 1);
+        assert_eq!(journal[0].run_id.as_deref(), Some(denied.run_id.as_str()));
+
+        // Price moves out of range: nothing runs, but the measured value is kept
+        *evaluator.0.lock() = dec!(250);
+        let trigger = RunTrigger::Cron {
+            schedule: "0 9 * * *".to_string(),
+        };
+        let skipped = engine
+            .run_strategy(&strategy, trigger.clone())
+            .await
+            .unwrap();
+        assert_eq!(skipped.status, RunStatus::ConditionNotMet);
+        assert_eq!(
+            skipped
+                .conditions
+                .iter()
+                .map(|c| c.result)
+                .collect::<Vec<_>>(),
+            vec![true, false]
+        );
+        assert!(skipped.actions.is_empty());
+        assert_eq!(skipped.strategy_version, denied.strategy_version);
+
+        // Query survives a reload
+        let reloaded = RunHistory::open(&path).await.unwrap();
+        assert_eq!(
+            reloaded.query(&RunQuery::new().strategy("SOL DCA")).len(),
+            2
+        );
+        assert!(reloaded
+            .query(&RunQuery::new().strategy("other"))
+            .is_empty());
+        let latest = reloaded.query(&RunQuery::new().strategy("dca-1").limit(1));
+        assert_eq!(latest[0].run_id, skipped.run_id);
+        assert_eq!(latest[0].trigger, trigger);
+        let denials = reloaded.query(&RunQuery::new().status(RunStatus::RiskDenied));
+        assert_eq!(denials.len(), 1);
+        assert!(denials[0]
+            .to_markdown()
+            .contains("risk denied by single_trade"));
+        let future = Utc::now() + Duration::hours(1);
+        assert!(reloaded
+            .query(&RunQuery::new().range(future..future + Duration::hours(1)))
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retention_keeps_latest_runs_per_strategy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("runs.jsonl");
+        let history = RunHistory::open(&path).await.unwrap();
+
+        let now = Utc::now();
+        for (i, strategy_id) in ["a", "a", "a", "b"].iter().enumerate() {
+            let started_at = now - Duration::minutes(10 - i as i64);
+            history
+                .record(PipelineRun {
+                    run_id: format!("run-{}", i),
+                    strategy_id: strategy_id.to_string(),
+                    strategy_name: strategy_id.to_string(),
+                    strategy_version: "v".to_string(),
+                    user_id: "user1".to_string(),
+                    trigger: RunTrigger::Manual,
+                    started_at,
+                    finished_at: started_at,
+                    conditions: Vec::new(),
+                    actions: Vec::new(),
+                    status: RunStatus::Completed,
+                    error: None,
+                })
+                .await
+                .unwrap();
+        }
+
+        let retention = RunRetention {
+            max_age: Some(Duration::days(1)),
+            max_runs_per_strategy: Some(2),
+        };
+        assert_eq!(history.apply_retention(&retention).await.unwrap(), 1);
+        assert!(history.get("run-0").is_none());
+
+        let reloaded = RunHistory::open(&path).await.unwrap();
+        let ids: Vec<_> = reloaded
+            .query(&RunQuery::new())
+            .into_iter()
+            .map(|r| r.run_id)
+            .collect();
+        assert_eq!(ids, vec!["run-3", "run-2", "run-1"]);
+    }
+}
@@ -1,3 +1,5 @@
+pub mod history;
+pub mod paper;
 pub mod pipeline;
 pub mod risk;
 pub mod simulation;
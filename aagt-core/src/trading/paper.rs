@@ -0,0 +1,549 @@
+//! Paper trading: run the full pipeline without sending transactions
+//!
+//! [`PaperTradingExecutor`] is an [`ActionExecutor`] that fills swaps at the
+//! prices of a [`PriceSource`] and appends each fill to a [`PaperLedger`]
+//! instead of executing it. The ledger can be queried by user, token and date,
+//! and [`PaperLedger::summarize`] works out the P&L the fills would have made,
+//! so a strategy can run in dry-run for weeks before going live.
+//!
+//! The executor reads who and what started a trade from the pipeline
+//! [`Context`]: `user_id`, `strategy_id`, `skill` and `reservation_id` (set by
+//! the strategy engine and by dynamic skills after their risk check).
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+
+use crate::error::{Error, Result};
+use crate::trading::pipeline::Context;
+use crate::trading::simulation::PriceSource;
+use crate::trading::strategy::{Action, ActionExecutor};
+
+/// Result of the risk check a paper trade went through
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RiskCheckOutcome {
+    /// Approved by the risk manager, which reserved the trade
+    Approved { reservation_id: String },
+    /// No risk check ran before the executor
+    NotChecked,
+}
+
+/// A swap that was filled on paper
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperTrade {
+    /// Trade ID
+    pub id: String,
+    /// When the fill was simulated
+    pub timestamp: DateTime<Utc>,
+    /// User the trade was made for
+    pub user_id: String,
+    /// Strategy that triggered the trade, if any
+    pub strategy_id: Option<String>,
+    /// Skill that proposed the trade, if any
+    pub skill: Option<String>,
+    /// The action as requested
+    pub action: Action,
+    /// Token sold
+    pub from_token: String,
+    /// Token bought
+    pub to_token: String,
+    /// Amount of `from_token` sold
+    pub amount_in: Decimal,
+    /// Amount of `to_token` received at the simulated fill
+    pub amount_out: Decimal,
+    /// USD price of `from_token` at the fill
+    pub from_price_usd: Decimal,
+    /// USD price of `to_token` at the fill
+    pub to_price_usd: Decimal,
+    /// Risk check the trade passed
+    pub risk_check: RiskCheckOutcome,
+}
+
+impl PaperTrade {
+    /// USD value traded
+    pub fn volume_usd(&self) -> Decimal {
+        self.amount_in * self.from_price_usd
+    }
+
+    /// Price of `to_token` in `from_token`
+    pub fn fill_price(&self) -> Decimal {
+        self.to_price_usd / self.from_price_usd
+    }
+}
+
+/// Filter for [`PaperLedger::query`] and [`PaperLedger::summarize`]
+#[derive(Debug, Clone, Default)]
+pub struct LedgerQuery {
+    /// Trades made for this user
+    pub user_id: Option<String>,
+    /// Trades buying or selling this token
+    pub token: Option<String>,
+    /// Fill-time range
+    pub range: Option<Range<DateTime<Utc>>>,
+    /// Max trades returned by [`query`](PaperLedger::query) (most recent first)
+    pub limit: Option<usize>,
+}
+
+impl LedgerQuery {
+    /// Match all trades
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only trades made for `user_id`
+    pub fn user(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    /// Only trades buying or selling `token` (case-insensitive)
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Only trades filled within `range`
+    pub fn range(mut self, range: Range<DateTime<Utc>>) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Return at most `limit` trades
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn matches(&self, trade: &PaperTrade) -> bool {
+        self.user_id.as_ref().is_none_or(|u| *u == trade.user_id)
+            && self.token.as_ref().is_none_or(|t| {
+                t.eq_ignore_ascii_case(&trade.from_token) || t.eq_ignore_ascii_case(&trade.to_token)
+            })
+            && self
+                .range
+                .as_ref()
+                .is_none_or(|r| r.contains(&trade.timestamp))
+    }
+}
+
+/// Holding of one token after replaying the fills
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaperPosition {
+    /// Token
+    pub token: String,
+    /// Amount held
+    pub quantity: Decimal,
+    /// USD paid for the amount held (average cost)
+    pub cost_usd: Decimal,
+    /// Last simulated fill price of the token
+    pub mark_price_usd: Decimal,
+}
+
+impl PaperPosition {
+    /// Value at the mark price
+    pub fn value_usd(&self) -> Decimal {
+        self.quantity * self.mark_price_usd
+    }
+
+    /// Gain at the mark price over the cost
+    pub fn unrealized_pnl_usd(&self) -> Decimal {
+        self.value_usd() - self.cost_usd
+    }
+}
+
+/// P&L of a set of paper trades
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PaperSummary {
+    /// Trades replayed
+    pub trades: usize,
+    /// USD value traded
+    pub volume_usd: Decimal,
+    /// Gains locked in by selling above average cost
+    pub realized_pnl_usd: Decimal,
+    /// Gains of open positions at their last fill price
+    pub unrealized_pnl_usd: Decimal,
+    /// Open positions, sorted by token
+    pub positions: Vec<PaperPosition>,
+}
+
+impl PaperSummary {
+    /// Realized plus unrealized P&L
+    pub fn total_pnl_usd(&self) -> Decimal {
+        self.realized_pnl_usd + self.unrealized_pnl_usd
+    }
+}
+
+/// Paper trades, optionally persisted as JSONL (one trade per line)
+pub struct PaperLedger {
+    path: Option<PathBuf>,
+    trades: RwLock<Vec<PaperTrade>>,
+    writer: tokio::sync::Mutex<()>,
+}
+
+impl PaperLedger {
+    /// Ledger kept in memory only
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            trades: RwLock::new(Vec::new()),
+            writer: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Open a JSONL-backed ledger, loading existing trades
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let mut trades = Vec::new();
+        if path.exists() {
+            let content = tokio::fs::read_to_string(&path).await?;
+            for (n, line) in content.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str(line) {
+                    Ok(trade) => trades.push(trade),
+                    Err(e) => {
+                        tracing::warn!(
+                            "Skipping malformed trade line {} in {:?}: {}",
+                            n + 1,
+                            path,
+                            e
+                        )
+                    }
+                }
+            }
+        }
+        Ok(Self {
+            path: Some(path),
+            trades: RwLock::new(trades),
+            writer: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    /// Append a trade
+    pub async fn record(&self, trade: PaperTrade) -> Result<()> {
+        if let Some(path) = &self.path {
+            let mut line = serde_json::to_string(&trade)?;
+            line.push('\n');
+            let _guard = self.writer.lock().await;
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await.ok();
+            }
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            file.write_all(line.as_bytes()).await?;
+            file.flush().await?;
+        }
+        self.trades.write().push(trade);
+        Ok(())
+    }
+
+    /// Trades matching `query`, most recent first
+    pub fn query(&self, query: &LedgerQuery) -> Vec<PaperTrade> {
+        let mut trades: Vec<PaperTrade> = self
+            .trades
+            .read()
+            .iter()
+            .filter(|t| query.matches(t))
+            .cloned()
+            .collect();
+        trades.sort_by_key(|t| std::cmp::Reverse(t.timestamp));
+        if let Some(limit) = query.limit {
+            trades.truncate(limit);
+        }
+        trades
+    }
+
+    /// P&L of the trades matching `query` (its limit is ignored)
+    ///
+    /// Fills are replayed oldest first with average-cost accounting. Selling
+    /// more of a token than the replayed trades bought (e.g. the stablecoin
+    /// that funds the first buy) counts as bringing it in at the fill price,
+    /// with no gain or loss. Open positions are marked at the token's last
+    /// fill price in the ledger.
+    pub fn summarize(&self, query: &LedgerQuery) -> PaperSummary {
+        let mut trades: Vec<PaperTrade> = self
+            .trades
+            .read()
+            .iter()
+            .filter(|t| query.matches(t))
+            .cloned()
+            .collect();
+        trades.sort_by_key(|t| t.timestamp);
+
+        // Token -> (quantity, cost)
+        let mut holdings: HashMap<String, (Decimal, Decimal)> = HashMap::new();
+        let mut marks: HashMap<String, Decimal> = HashMap::new();
+        let mut summary = PaperSummary {
+            trades: trades.len(),
+            ..Default::default()
+        };
+        for trade in &trades {
+            summary.volume_usd += trade.volume_usd();
+            marks.insert(trade.from_token.clone(), trade.from_price_usd);
+            marks.insert(trade.to_token.clone(), trade.to_price_usd);
+
+            let (quantity, cost) = holdings.entry(trade.from_token.clone()).or_default();
+            let sold = trade.amount_in.min(*quantity);
+            if sold > Decimal::ZERO {
+                let sold_cost = *cost * sold / *quantity;
+                summary.realized_pnl_usd += sold * trade.from_price_usd - sold_cost;
+                *quantity -= sold;
+                *cost -= sold_cost;
+            }
+
+            let (quantity, cost) = holdings.entry(trade.to_token.clone()).or_default();
+            *quantity += trade.amount_out;
+            *cost += trade.volume_usd();
+        }
+
+        let mut positions: Vec<PaperPosition> = holdings
+            .into_iter()
+            .filter(|(_, (quantity, _))| *quantity > Decimal::ZERO)
+            .map(|(token, (quantity, cost_usd))| PaperPosition {
+                mark_price_usd: marks[&token],
+                token,
+                quantity,
+                cost_usd,
+            })
+            .collect();
+        positions.sort_by(|a, b| a.token.cmp(&b.token));
+        summary.unrealized_pnl_usd = positions
+            .iter()
+            .map(PaperPosition::unrealized_pnl_usd)
+            .sum();
+        summary.positions = positions;
+        summary
+    }
+}
+
+/// Executor that records swaps in a [`PaperLedger`] instead of sending them
+pub struct PaperTradingExecutor {
+    ledger: Arc<PaperLedger>,
+    prices: Arc<dyn PriceSource>,
+}
+
+impl PaperTradingExecutor {
+    /// Fill swaps at `prices`, recording them in `ledger`
+    pub fn new(ledger: Arc<PaperLedger>, prices: Arc<dyn PriceSource>) -> Self {
+        Self { ledger, prices }
+    }
+
+    /// The ledger trades are recorded in
+    pub fn ledger(&self) -> &Arc<PaperLedger> {
+        &self.ledger
+    }
+
+    async fn price(&self, token: &str) -> Result<Decimal> {
+        let price = self.prices.get_price_usd(token).await?;
+        if price <= Decimal::ZERO {
+            return Err(Error::Simulation(format!(
+                "No usable price for {}: {}",
+                token, price
+            )));
+        }
+        Ok(price)
+    }
+}
+
+fn context_str(ctx: &Context, key: &str) -> Option<String> {
+    ctx.get(key).and_then(|v| v.as_str()).map(String::from)
+}
+
+#[async_trait::async_trait]
+impl ActionExecutor for PaperTradingExecutor {
+    async fn execute(&self, action: &Action, ctx: &Context) -> Result<String> {
+        let Action::Swap {
+            from_token,
+            to_token,
+            amount,
+        } = action
+        else {
+            return Ok(format!("PAPER: {:?} not executed (paper trading)", action));
+        };
+
+        // Percentages and "max" need real balances
+        let amount_in = Decimal::from_str(amount.trim())
+            .ok()
+            .filter(|a| *a > Decimal::ZERO)
+            .ok_or_else(|| {
+                Error::Simulation(format!(
+                    "Paper trading needs a positive token amount, got '{}'",
+                    amount
+                ))
+            })?;
+        let from_price_usd = self.price(from_token).await?;
+        let to_price_usd = self.price(to_token).await?;
+
+        let trade = PaperTrade {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            user_id: context_str(ctx, "user_id").unwrap_or_else(|| "default_user".to_string()),
+            strategy_id: context_str(ctx, "strategy_id"),
+            skill: context_str(ctx, "skill"),
+            action: action.clone(),
+            from_token: from_token.clone(),
+            to_token: to_token.clone(),
+            amount_in,
+            amount_out: amount_in * from_price_usd / to_price_usd,
+            from_price_usd,
+            to_price_usd,
+            risk_check: match context_str(ctx, "reservation_id") {
+                Some(reservation_id) => RiskCheckOutcome::Approved { reservation_id },
+                None => RiskCheckOutcome::NotChecked,
+            },
+        };
+        let result = format!(
+            "PAPER TRADE {}: sold {} {} for {} {} at {} {} per {} (${} per {}); simulated fill, no transaction sent",
+            trade.id,
+            trade.amount_in.normalize(),
+            trade.from_token,
+            trade.amount_out.round_dp(8).normalize(),
+            trade.to_token,
+            trade.fill_price().round_dp(8).normalize(),
+            trade.from_token,
+            trade.to_token,
+            trade.to_price_usd.normalize(),
+            trade.to_token,
+        );
+        tracing::info!(trade_id = %trade.id, user = %trade.user_id, "Recorded paper trade");
+        self.ledger.record(trade).await?;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    /// Prices that the test moves between trades
+    struct Prices(parking_lot::Mutex<HashMap<String, Decimal>>);
+
+    impl Prices {
+        fn set(&self, token: &str, price: Decimal) {
+            self.0.lock().insert(token.to_string(), price);
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl PriceSource for Prices {
+        async fn get_price_usd(&self, token: &str) -> Result<Decimal> {
+            self.0
+                .lock()
+                .get(token)
+                .copied()
+                .ok_or_else(|| Error::Simulation(format!("No price for {}", token)))
+        }
+
+        async fn get_liquidity_usd(&self, _: &str, _: &str) -> Result<Decimal> {
+            Ok(dec!(10_000_000))
+        }
+    }
+
+    fn swap(from_token: &str, to_token: &str, amount: &str) -> Action {
+        Action::Swap {
+            from_token: from_token.to_string(),
+            to_token: to_token.to_string(),
+            amount: amount.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_two_paper_swaps_are_ledgered_with_pnl() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("paper.jsonl");
+        let prices = Arc::new(Prices(Default::default()));
+        prices.set("USDC", dec!(1));
+        prices.set("SOL", dec!(100));
+        let executor = PaperTradingExecutor::new(
+            Arc::new(PaperLedger::open(&path).await.unwrap()),
+            prices.clone(),
+        );
+
+        let mut ctx = Context::new("Skill execution: sol_momentum");
+        ctx.set("user_id", "alice");
+        ctx.set("skill", "sol_momentum");
+        ctx.set("reservation_id", "res-1");
+        let result = executor
+            .execute(&swap("USDC", "SOL", "1000"), &ctx)
+            .await
+            .unwrap();
+        assert!(
+            result.contains("sold 1000 USDC for 10 SOL at 100 USDC per SOL"),
+            "{}",
+            result
+        );
+
+        prices.set("SOL", dec!(120));
+        let ctx = Context::new("strategy run");
+        executor
+            .execute(&swap("SOL", "USDC", "5"), &ctx)
+            .await
+            .unwrap();
+
+        // Only absolute amounts can be filled on paper
+        assert!(executor
+            .execute(&swap("SOL", "USDC", "50%"), &ctx)
+            .await
+            .is_err());
+
+        // Reopening reads the same trades back
+        let ledger = PaperLedger::open(&path).await.unwrap();
+        let trades = ledger.query(&LedgerQuery::new());
+        assert_eq!(trades.len(), 2);
+        let first = &trades[1];
+        assert_eq!(first.user_id, "alice");
+        assert_eq!(first.skill.as_deref(), Some("sol_momentum"));
+        assert_eq!(first.amount_out, dec!(10));
+        assert_eq!(
+            first.risk_check,
+            RiskCheckOutcome::Approved {
+                reservation_id: "res-1".to_string()
+            }
+        );
+        assert_eq!(trades[0].user_id, "default_user");
+        assert_eq!(trades[0].amount_out, dec!(600));
+        assert_eq!(trades[0].risk_check, RiskCheckOutcome::NotChecked);
+
+        assert_eq!(ledger.query(&LedgerQuery::new().user("alice")).len(), 1);
+        assert_eq!(ledger.query(&LedgerQuery::new().token("sol")).len(), 2);
+        let hour = chrono::Duration::hours(1);
+        assert!(ledger
+            .query(&LedgerQuery::new().range(Utc::now() - hour * 48..Utc::now() - hour * 24))
+            .is_empty());
+
+        // Bought 10 SOL for $1000, sold 5 at $120: $100 realized; 5 SOL left at cost $500, marked at $120
+        let summary = ledger.summarize(&LedgerQuery::new());
+        assert_eq!(summary.trades, 2);
+        assert_eq!(summary.volume_usd, dec!(1600));
+        assert_eq!(summary.realized_pnl_usd, dec!(100));
+        assert_eq!(summary.unrealized_pnl_usd, dec!(100));
+        assert_eq!(summary.total_pnl_usd(), dec!(200));
+        let sol = summary.positions.iter().find(|p| p.token == "SOL").unwrap();
+        assert_eq!(
+            (sol.quantity, sol.cost_usd, sol.value_usd()),
+            (dec!(5), dec!(500), dec!(600))
+        );
+        // The USDC that came back is held at cost
+        let usdc = summary
+            .positions
+            .iter()
+            .find(|p| p.token == "USDC")
+            .unwrap();
+        assert_eq!(usdc.quantity, dec!(600));
+        assert_eq!(usdc.unrealized_pnl_usd(), dec!(0));
+    }
+}
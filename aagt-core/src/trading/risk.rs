@@ -4,7 +4,7 @@
 //! Refactored to use the Actor Model for lock-free concurrency and durability.
 
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
@@ -15,14 +15,22 @@ use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 
 use crate::error::{Error, Result};
+use crate::infra::instance::InstanceLock;
+use crate::infra::validation::{ConfigIssue, Validate};
 
 mod circuit_breaker;
 pub use circuit_breaker::DeadManSwitch;
 
 mod checks;
 pub use checks::{
-    CompositeCheck, LiquidityCheck, MaxTradeAmountCheck, 
-    RiskCheckBuilder, SlippageCheck, TokenSecurityCheck,
+    CompositeCheck, LiquidityCheck, MaxTradeAmountCheck, PerTokenExposureCheck,
+    RiskCheckBuilder, SlippageCheck, TokenAllowlistCheck, TokenDenylistCheck, TokenSecurityCheck,
+};
+
+mod ledger;
+pub use ledger::{
+    JournalEntry, ReconciliationPolicy, Reservation, ReservationLedger,
+    ReservationOrigin, ReservationResolution, ReservationStatus,
 };
 
 /// Persistence trait for risk state
@@ -30,42 +38,70 @@ pub use checks::{
 pub trait RiskStateStore: Send + Sync {
     async fn load(&self) -> Result<HashMap<String, UserState>>;
     async fn save(&self, states: &HashMap<String, UserState>) -> Result<()>;
+
+    /// Load the reservation ledger (stores without one start empty)
+    async fn load_ledger(&self) -> Result<ReservationLedger> {
+        Ok(ReservationLedger::default())
+    }
+
+    /// Persist the reservation ledger
+    async fn save_ledger(&self, _ledger: &ReservationLedger) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// Simple JSON file store for risk state
 pub struct FileRiskStore {
     path: PathBuf,
+    instance_lock: Option<Arc<InstanceLock>>,
 }
 
 impl FileRiskStore {
     pub fn new(path: impl Into<PathBuf>) -> Self {
-        Self { path: path.into() }
+        Self { path: path.into(), instance_lock: None }
     }
-}
 
-#[async_trait::async_trait]
-impl RiskStateStore for FileRiskStore {
-    async fn load(&self) -> Result<HashMap<String, UserState>> {
-        if !self.path.exists() {
-            return Ok(HashMap::new());
+    /// Only write while `lock` is the current writer; read-only instances skip writes
+    pub fn with_instance_lock(mut self, lock: Arc<InstanceLock>) -> Self {
+        self.instance_lock = Some(lock);
+        self
+    }
+
+    /// Whether a write should go ahead (errors once fenced)
+    fn may_write(&self) -> Result<bool> {
+        match self.instance_lock.as_ref().map(|l| l.check_writer("risk state write")) {
+            Some(Err(Error::InstanceReadOnly(_))) => Ok(false),
+            Some(Err(e)) => Err(e),
+            _ => Ok(true),
+        }
+    }
+
+    /// Ledger is kept next to the state file so existing state files stay readable
+    fn ledger_path(&self) -> PathBuf {
+        self.path.with_extension("ledger.json")
+    }
+
+    async fn read_json<T: serde::de::DeserializeOwned + Default>(path: &std::path::Path) -> Result<T> {
+        if !path.exists() {
+            return Ok(T::default());
         }
-        let content = tokio::fs::read_to_string(&self.path).await?;
+        let content = tokio::fs::read_to_string(path).await?;
         if content.trim().is_empty() {
-            return Ok(HashMap::new());
+            return Ok(T::default());
         }
-        
+
         serde_json::from_str(&content).map_err(|e| {
-            Error::Internal(format!("CORRUPTION: Risk state file at {:?} is malformed. Delete it to reset or fix JSON: {}", self.path, e))
+            Error::Internal(format!("CORRUPTION: Risk state file at {:?} is malformed. Delete it to reset or fix JSON: {}", path, e))
         })
     }
 
-    async fn save(&self, states: &HashMap<String, UserState>) -> Result<()> {
-        if let Some(parent) = self.path.parent() {
+    async fn write_json<T: Serialize + Clone + Send + 'static>(path: &std::path::Path, value: &T) -> Result<()> {
+        if let Some(parent) = path.parent() {
             tokio::fs::create_dir_all(parent).await.ok();
         }
 
-        let path = self.path.clone();
-        let states = states.clone(); 
+        let path = path.to_path_buf();
+        let value = value.clone();
 
         // Fix #3: Atomic Write Pattern (Write tmp -> Rename)
         tokio::task::spawn_blocking(move || {
@@ -77,7 +113,7 @@ impl RiskStateStore for FileRiskStore {
                     .map_err(|e| Error::Internal(format!("Failed to create tmp risk file: {}", e)))?;
                 let writer = std::io::BufWriter::new(file);
                 
-                serde_json::to_writer_pretty(writer, &states)
+                serde_json::to_writer_pretty(writer, &value)
                     .map_err(|e| Error::Internal(format!("Failed to serialize risk state: {}", e)))?;
                 // File closes here
             }
@@ -96,6 +132,31 @@ impl RiskStateStore for FileRiskStore {
     }
 }
 
+#[async_trait::async_trait]
+impl RiskStateStore for FileRiskStore {
+    async fn load(&self) -> Result<HashMap<String, UserState>> {
+        Self::read_json(&self.path).await
+    }
+
+    async fn save(&self, states: &HashMap<String, UserState>) -> Result<()> {
+        if !self.may_write()? {
+            return Ok(());
+        }
+        Self::write_json(&self.path, states).await
+    }
+
+    async fn load_ledger(&self) -> Result<ReservationLedger> {
+        Self::read_json(&self.ledger_path()).await
+    }
+
+    async fn save_ledger(&self, ledger: &ReservationLedger) -> Result<()> {
+        if !self.may_write()? {
+            return Ok(());
+        }
+        Self::write_json(&self.ledger_path(), ledger).await
+    }
+}
+
 /// No-op store for in-memory only execution
 pub struct InMemoryRiskStore;
 
@@ -138,6 +199,45 @@ pub struct RiskConfig {
     pub enable_rug_detection: bool,
     /// Cooldown between trades in seconds
     pub trade_cooldown_secs: u64,
+    /// Age after which unresolved reservations are reconciled, on startup or while running
+    #[serde(default = "default_reservation_staleness_secs")]
+    pub reservation_staleness_secs: u64,
+    /// How stale reservations are resolved
+    #[serde(default)]
+    pub reconciliation_policy: ReconciliationPolicy,
+    /// Days to keep resolved reservations and journal entries
+    #[serde(default = "default_ledger_retention_days")]
+    pub ledger_retention_days: u64,
+    /// Net realized loss per user over a rolling 24h window that halts new trades
+    #[serde(default)]
+    pub max_daily_loss_usd: Option<Decimal>,
+    /// Pause a user's trading after a run of failed or rolled-back trades
+    #[serde(default)]
+    pub cooldown_after_failures: Option<FailureCooldown>,
+}
+
+/// Cooldown tripped by consecutive failed trades
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailureCooldown {
+    /// Consecutive failures that trip the cooldown
+    pub count: u32,
+    /// How long new trades are refused
+    pub duration_secs: u64,
+}
+
+/// Rolling window of [`RiskConfig::max_daily_loss_usd`]
+pub const DAILY_LOSS_WINDOW_HOURS: i64 = 24;
+
+fn daily_loss_window() -> chrono::Duration {
+    chrono::Duration::hours(DAILY_LOSS_WINDOW_HOURS)
+}
+
+fn default_reservation_staleness_secs() -> u64 {
+    300
+}
+
+fn default_ledger_retention_days() -> u64 {
+    7
 }
 
 impl Default for RiskConfig {
@@ -149,7 +249,81 @@ impl Default for RiskConfig {
             min_liquidity_usd: dec!(100000.0),
             enable_rug_detection: true,
             trade_cooldown_secs: 5,
+            reservation_staleness_secs: default_reservation_staleness_secs(),
+            reconciliation_policy: ReconciliationPolicy::default(),
+            ledger_retention_days: default_ledger_retention_days(),
+            max_daily_loss_usd: None,
+            cooldown_after_failures: None,
+        }
+    }
+}
+
+impl Validate for RiskConfig {
+    fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        for (field, value) in [
+            ("max_single_trade_usd", self.max_single_trade_usd),
+            ("max_daily_volume_usd", self.max_daily_volume_usd),
+        ] {
+            if value <= Decimal::ZERO {
+                issues.push(ConfigIssue::error(
+                    format!("risk.{}", field),
+                    format!("must be positive, got {}", value),
+                ));
+            }
         }
+        if self.max_daily_volume_usd > Decimal::ZERO
+            && self.max_daily_volume_usd < self.max_single_trade_usd
+        {
+            issues.push(
+                ConfigIssue::error(
+                    "risk.max_daily_volume_usd",
+                    format!(
+                        "{} is below max_single_trade_usd ({}), so the per-trade limit can never be reached",
+                        self.max_daily_volume_usd, self.max_single_trade_usd
+                    ),
+                )
+                .suggest("raise max_daily_volume_usd or lower max_single_trade_usd"),
+            );
+        }
+        if self.max_slippage_percent <= Decimal::ZERO || self.max_slippage_percent > dec!(100) {
+            issues.push(ConfigIssue::error(
+                "risk.max_slippage_percent",
+                format!("{} is outside (0, 100]", self.max_slippage_percent),
+            ));
+        } else if self.max_slippage_percent > dec!(20) {
+            issues.push(ConfigIssue::warning(
+                "risk.max_slippage_percent",
+                format!("{}% slippage is unusually permissive", self.max_slippage_percent),
+            ));
+        }
+        if self.min_liquidity_usd < Decimal::ZERO {
+            issues.push(ConfigIssue::error("risk.min_liquidity_usd", "cannot be negative"));
+        }
+        if let Some(max) = self.max_daily_loss_usd.filter(|max| *max <= Decimal::ZERO) {
+            issues.push(
+                ConfigIssue::error("risk.max_daily_loss_usd", format!("must be positive, got {}", max))
+                    .suggest("leave unset to disable the daily loss limit"),
+            );
+        }
+        if let Some(cooldown) = &self.cooldown_after_failures {
+            if cooldown.count == 0 {
+                issues.push(ConfigIssue::error("risk.cooldown_after_failures.count", "must be at least 1"));
+            }
+            if cooldown.duration_secs == 0 {
+                issues.push(ConfigIssue::warning(
+                    "risk.cooldown_after_failures.duration_secs",
+                    "0 never pauses trading",
+                ));
+            }
+        }
+        if self.reservation_staleness_secs == 0 {
+            issues.push(ConfigIssue::warning(
+                "risk.reservation_staleness_secs",
+                "0 reconciles every pending reservation on startup, including in-flight trades",
+            ));
+        }
+        issues
     }
 }
 
@@ -160,6 +334,14 @@ pub trait RiskCheck: Send + Sync {
 
     /// Perform the check
     fn check(&self, context: &TradeContext) -> RiskCheckResult;
+
+    /// Check against the user's tracked state
+    ///
+    /// Runs inside the risk actor after every [`check`](Self::check) passed,
+    /// atomically with the reservation it guards.
+    fn check_state(&self, _context: &TradeContext, _state: &UserState) -> RiskCheckResult {
+        RiskCheckResult::Approved
+    }
 }
 
 /// Context for a trade being checked
@@ -191,6 +373,34 @@ pub struct UserState {
     pub last_trade: Option<DateTime<Utc>>,
     /// Volume reset time (Last date processed)
     pub volume_reset: DateTime<Utc>,
+    /// Trade outcomes within the daily loss window, oldest first
+    #[serde(default)]
+    pub recent_outcomes: VecDeque<TradeOutcome>,
+    /// Failed or rolled-back trades since the last success or cooldown
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    /// New trades are refused until then after a run of failures
+    #[serde(default)]
+    pub cooldown_until: Option<DateTime<Utc>>,
+    /// USD bought per token, keyed by [`UserState::token_key`]
+    #[serde(default)]
+    pub token_exposure: HashMap<String, TokenExposure>,
+}
+
+/// USD exposure to one token
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenExposure {
+    /// Held by pending buys
+    pub reserved_usd: Decimal,
+    /// Bought by committed trades and not yet released
+    pub committed_usd: Decimal,
+}
+
+impl TokenExposure {
+    /// Reserved plus committed exposure
+    pub fn total(&self) -> Decimal {
+        self.reserved_usd + self.committed_usd
+    }
 }
 
 impl Default for UserState {
@@ -200,23 +410,150 @@ impl Default for UserState {
             pending_volume_usd: Decimal::ZERO,
             last_trade: None,
             volume_reset: Utc::now(),
+            recent_outcomes: VecDeque::new(),
+            consecutive_failures: 0,
+            cooldown_until: None,
+            token_exposure: HashMap::new(),
         }
     }
 }
 
+/// Whether `token` looks like a contract address rather than a symbol
+///
+/// Solana mints are 32-44 base58 characters and EVM addresses 42; no ticker
+/// comes close.
+fn is_contract_address(token: &str) -> bool {
+    token.len() >= 32 && token.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// How a reserved trade ended
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeOutcome {
+    /// When the trade was committed or rolled back
+    pub at: DateTime<Utc>,
+    /// Trade size
+    pub amount_usd: Decimal,
+    /// Realized profit, negative for a loss (zero for rolled-back trades)
+    pub realized_pnl_usd: Decimal,
+    /// Whether the trade was rolled back instead of committed
+    pub rolled_back: bool,
+}
+
+impl TradeOutcome {
+    /// Rolled back or closed at a loss
+    pub fn is_failure(&self) -> bool {
+        self.rolled_back || self.realized_pnl_usd < Decimal::ZERO
+    }
+}
+
+impl UserState {
+    /// Exposure key of `token`: symbols match case-insensitively, contract
+    /// addresses exactly (they are case-sensitive on Solana)
+    pub fn token_key(token: &str) -> String {
+        let token = token.trim();
+        if is_contract_address(token) {
+            token.to_string()
+        } else {
+            token.to_uppercase()
+        }
+    }
+
+    /// Reserved plus committed USD exposure to `token`
+    pub fn exposure(&self, token: &str) -> Decimal {
+        self.token_exposure
+            .get(&Self::token_key(token))
+            .map(TokenExposure::total)
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    /// Net realized loss over the window ending at `now` (zero when in profit)
+    pub fn realized_loss(&self, now: DateTime<Utc>) -> Decimal {
+        (-self.window_pnl(now)).max(Decimal::ZERO)
+    }
+
+    /// Record `outcome`; returns true if it tripped the failure cooldown
+    pub fn record_outcome(&mut self, outcome: TradeOutcome, config: &RiskConfig) -> bool {
+        let since = outcome.at - daily_loss_window();
+        while self.recent_outcomes.front().is_some_and(|o| o.at <= since) {
+            self.recent_outcomes.pop_front();
+        }
+        let mut tripped = false;
+        if outcome.is_failure() {
+            self.consecutive_failures += 1;
+            if let Some(cooldown) = config.cooldown_after_failures {
+                if self.consecutive_failures >= cooldown.count {
+                    self.cooldown_until = Some(outcome.at + chrono::Duration::seconds(cooldown.duration_secs as i64));
+                    self.consecutive_failures = 0;
+                    tripped = true;
+                }
+            }
+        } else {
+            self.consecutive_failures = 0;
+        }
+        self.recent_outcomes.push_back(outcome);
+        tripped
+    }
+
+    /// Reject new trades while the daily loss limit or failure cooldown is tripped
+    pub fn check_circuit_breakers(&self, config: &RiskConfig, now: DateTime<Utc>) -> Result<()> {
+        if let Some(max) = config.max_daily_loss_usd {
+            let loss = self.realized_loss(now);
+            if loss >= max {
+                return Err(Error::risk_check_failed("daily_loss", format!(
+                    "daily loss limit reached (${:.2} of ${:.2}), resets at {}",
+                    loss, max, self.loss_resets_at(max, now).to_rfc3339()
+                )));
+            }
+        }
+        if let Some(until) = self.cooldown_until.filter(|until| now < *until) {
+            return Err(Error::risk_check_failed("failure_cooldown", format!(
+                "too many consecutive failed trades, cooling down until {}",
+                until.to_rfc3339()
+            )));
+        }
+        Ok(())
+    }
+
+    fn window_pnl(&self, now: DateTime<Utc>) -> Decimal {
+        let since = now - daily_loss_window();
+        self.recent_outcomes.iter().filter(|o| o.at > since).map(|o| o.realized_pnl_usd).sum()
+    }
+
+    /// When enough outcomes leave the window for the net loss to drop below `max`
+    fn loss_resets_at(&self, max: Decimal, now: DateTime<Utc>) -> DateTime<Utc> {
+        let since = now - daily_loss_window();
+        let mut loss = -self.window_pnl(now);
+        for outcome in self.recent_outcomes.iter().filter(|o| o.at > since) {
+            loss += outcome.realized_pnl_usd;
+            if loss < max {
+                return outcome.at + daily_loss_window();
+            }
+        }
+        now
+    }
+}
+
 // --- Actor Implementation ---
 
 enum RiskCommand {
-    CheckAndReserve { context: TradeContext, checks: Vec<Arc<dyn RiskCheck>>, reply: oneshot::Sender<Result<()>> },
-    Commit { user_id: String, amount_usd: Decimal, reply: oneshot::Sender<Result<()>> },
-    Rollback { user_id: String, amount_usd: Decimal },
+    CheckAndReserve { context: TradeContext, checks: Vec<Arc<dyn RiskCheck>>, origin: ReservationOrigin, reply: oneshot::Sender<Result<String>> },
+    Commit { user_id: String, amount_usd: Decimal, realized_pnl_usd: Decimal, reply: oneshot::Sender<Result<()>> },
+    Rollback { user_id: String, amount_usd: Decimal, failed: bool },
+    CommitReservation { id: String, realized_pnl_usd: Decimal, reply: oneshot::Sender<Result<()>> },
+    RollbackReservation { id: String, reply: oneshot::Sender<Result<()>> },
+    Resolve { id: String, resolution: ReservationResolution, reply: oneshot::Sender<Result<Reservation>> },
+    PendingReservations { user_id: String, reply: oneshot::Sender<Vec<Reservation>> },
+    Journal { reply: oneshot::Sender<Vec<JournalEntry>> },
     GetRemaining { user_id: String, reply: oneshot::Sender<Decimal> },
+    Exposure { user_id: String, token: String, reply: oneshot::Sender<TokenExposure> },
+    ReleaseExposure { user_id: String, token: String, amount_usd: Decimal, reply: oneshot::Sender<Result<()>> },
     LoadState { reply: oneshot::Sender<Result<()>> },
 }
 
 struct RiskActor {
     config: RiskConfig,
     state: HashMap<String, UserState>,
+    ledger: ReservationLedger,
     store: Arc<dyn RiskStateStore>,
     receiver: mpsc::Receiver<RiskCommand>,
     last_load_time: Option<DateTime<Utc>>,
@@ -226,28 +563,115 @@ impl RiskActor {
 
     async fn handle_load(&mut self) -> Result<()> {
         let mut loaded = self.store.load().await?;
-        
-        // Fix #2.1: Clear zombie pending volumes on startup
-        // Anything pending during a crash is considered failed/not-executed.
-        for (_, state) in loaded.iter_mut() {
-            if !state.pending_volume_usd.is_zero() {
-                tracing::warn!("Resetting zombie pending volume of ${} for user", state.pending_volume_usd);
-                state.pending_volume_usd = Decimal::ZERO;
+        let mut ledger = self.store.load_ledger().await?;
+        let now = Utc::now();
+
+        // Reconcile reservations left unresolved by a crash
+        let mut changed = !Self::reconcile_stale(&self.config, &mut ledger, now, "on startup").is_empty();
+        for r in ledger.reservations.iter().filter(|r| r.status == ReservationStatus::Held) {
+            tracing::error!(reservation = %r.id, user = %r.user_id, "Reservation of ${} is held and needs manual resolution", r.amount_usd);
+        }
+
+        // Fix #2.1: Pending volume is rebuilt from the ledger, clearing zombie volume
+        // that has no matching reservation.
+        let totals = ledger.pending_totals();
+        for user_id in totals.keys() {
+            loaded.entry(user_id.clone()).or_default();
+        }
+        let mut token_totals: HashMap<(String, String), Decimal> = HashMap::new();
+        for ((user_id, token), amount) in ledger.pending_token_totals() {
+            *token_totals.entry((user_id, UserState::token_key(&token))).or_default() += amount;
+        }
+        for (user_id, state) in loaded.iter_mut() {
+            let expected = totals.get(user_id).copied().unwrap_or(Decimal::ZERO);
+            if state.pending_volume_usd != expected {
+                tracing::warn!("Resetting pending volume for user {} from ${} to ${}", user_id, state.pending_volume_usd, expected);
+                state.pending_volume_usd = expected;
+                changed = true;
+            }
+            for (owner, token) in token_totals.keys() {
+                if owner == user_id {
+                    state.token_exposure.entry(token.clone()).or_default();
+                }
             }
+            for (token, exposure) in state.token_exposure.iter_mut() {
+                let expected = token_totals
+                    .get(&(user_id.clone(), token.clone()))
+                    .copied()
+                    .unwrap_or(Decimal::ZERO);
+                if exposure.reserved_usd != expected {
+                    exposure.reserved_usd = expected;
+                    changed = true;
+                }
+            }
+        }
+
+        let retention = chrono::Duration::days(self.config.ledger_retention_days as i64);
+        changed |= ledger.prune(now, retention) > 0;
+
+        if changed {
+            self.store.save_ledger(&ledger).await?;
+            self.store.save(&loaded).await?;
         }
 
         self.state = loaded;
-        self.last_load_time = Some(Utc::now());
+        self.ledger = ledger;
+        self.last_load_time = Some(now);
         Ok(())
     }
 
-    async fn handle_check_and_reserve(&mut self, context: TradeContext, checks: Vec<Arc<dyn RiskCheck>>) -> Result<()> {
+    /// Resolve reservations older than the staleness window per the reconciliation policy
+    ///
+    /// Returns the reservations that changed status.
+    fn reconcile_stale(config: &RiskConfig, ledger: &mut ReservationLedger, now: DateTime<Utc>, when: &str) -> Vec<Reservation> {
+        let window = chrono::Duration::seconds(config.reservation_staleness_secs as i64);
+        let mut reconciled = Vec::new();
+        for id in ledger.stale(now, window) {
+            match config.reconciliation_policy {
+                ReconciliationPolicy::AutoRollback => {
+                    let reason = format!("reconciled {}: stale reservation auto-rolled back", when);
+                    if let Some(r) = ledger.transition(&id, ReservationStatus::RolledBack, reason) {
+                        tracing::warn!(reservation = %r.id, user = %r.user_id, "Auto-rolled back stale reservation of ${}", r.amount_usd);
+                        reconciled.push(r);
+                    }
+                }
+                ReconciliationPolicy::HoldAndAlert => {
+                    let reason = format!("reconciled {}: held for manual resolution", when);
+                    if let Some(r) = ledger.transition(&id, ReservationStatus::Held, reason) {
+                        reconciled.push(r);
+                    }
+                }
+            }
+        }
+        reconciled
+    }
+
+    /// Reconcile reservations that went stale while running
+    async fn handle_reconcile(&mut self) {
+        let reconciled = Self::reconcile_stale(&self.config, &mut self.ledger, Utc::now(), "while running");
+        if reconciled.is_empty() {
+            return;
+        }
+        for r in &reconciled {
+            match r.status {
+                ReservationStatus::RolledBack => self.release_volume(&r.user_id, r.token.as_deref(), r.amount_usd),
+                _ => tracing::error!(reservation = %r.id, user = %r.user_id, "Reservation of ${} is held and needs manual resolution", r.amount_usd),
+            }
+        }
+        self.persist_ledger().await;
+        if let Err(e) = self.store.save(&self.state).await {
+            tracing::error!("Failed to persist risk state: {}", e);
+        }
+    }
+
+    async fn handle_check_and_reserve(&mut self, context: TradeContext, checks: Vec<Arc<dyn RiskCheck>>, origin: ReservationOrigin) -> Result<String> {
         // 1. Offload heavy/STATLESS checks to blocking thread
         // These checks don't need UserState (RAM) and could involve I/O in custom checks
         let config = self.config.clone();
         let ctx_clone = context.clone();
+        let stateless = checks.clone();
         tokio::task::spawn_blocking(move || {
-             Self::validate_stateless(&config, &ctx_clone, &checks)
+             Self::validate_stateless(&config, &ctx_clone, &stateless)
         }).await.map_err(|e| Error::Internal(format!("Task panic: {}", e)))??;
 
         // 2. Perform STATEFUL checks inside Actor (Atomic)
@@ -260,6 +684,9 @@ impl RiskActor {
             state.volume_reset = now;
         }
 
+        // Daily loss limit and failure cooldown
+        state.check_circuit_breakers(&self.config, now)?;
+
         // Daily limit check
         let projected = state.daily_volume_usd + state.pending_volume_usd + context.amount_usd;
         if projected > self.config.max_daily_volume_usd {
@@ -278,13 +705,31 @@ impl RiskActor {
             }
         }
 
+        // Checks on tracked state, such as per-token exposure
+        for check in &checks {
+            if let RiskCheckResult::Rejected { reason } = check.check_state(&context, state) {
+                return Err(Error::RiskCheckFailed { check_name: check.name().to_string(), reason });
+            }
+        }
+
         // Commit reservation
         state.pending_volume_usd += context.amount_usd;
+        state
+            .token_exposure
+            .entry(UserState::token_key(&context.to_token))
+            .or_default()
+            .reserved_usd += context.amount_usd;
+        let id = self.ledger.reserve(&context.user_id, &context.to_token, context.amount_usd, origin);
         
-        // Immediate save for reservation
-        self.store.save(&self.state).await?;
+        // The ledger entry must be durable before the reservation is acknowledged.
+        // Pending volume is rebuilt from the ledger on load, so the state file can be flushed lazily.
+        if let Err(e) = self.store.save_ledger(&self.ledger).await {
+            self.ledger.discard(&id);
+            self.release_volume(&context.user_id, Some(&context.to_token), context.amount_usd);
+            return Err(e);
+        }
         
-        Ok(())
+        Ok(id)
     }
 
     /// Stateless validation logic - can be run outside Actor
@@ -321,32 +766,155 @@ impl RiskActor {
         Ok(())
     }
 
-    async fn handle_commit(&mut self, user_id: String, amount: Decimal) -> Result<()> {
+    async fn handle_commit(&mut self, user_id: String, amount: Decimal, realized_pnl: Decimal) -> Result<()> {
+        // Resolve the oldest matching reservation, if the trade was reserved
+        let reservation_id = self.ledger.find_unresolved(&user_id, amount);
+        let token = reservation_id.as_deref().and_then(|id| self.reserved_token(id));
+        self.commit_volume(&user_id, token.as_deref(), amount, realized_pnl).await?;
+
+        if let Some(id) = reservation_id {
+            self.ledger.transition(&id, ReservationStatus::Committed, "committed");
+            self.persist_ledger().await;
+        }
+        Ok(())
+    }
+
+    /// Token bought by a reservation
+    fn reserved_token(&self, id: &str) -> Option<String> {
+        self.ledger.get(id).and_then(|r| r.token.clone())
+    }
+
+    /// Move reserved volume (and `token` exposure) into the committed totals,
+    /// record the outcome and save the state
+    async fn commit_volume(&mut self, user_id: &str, token: Option<&str>, amount: Decimal, realized_pnl: Decimal) -> Result<()> {
+        let user_id = user_id.to_string();
         let state = self.state.entry(user_id.clone()).or_default();
-        
-        let old_pending = state.pending_volume_usd;
-        let old_daily = state.daily_volume_usd;
-        let old_last = state.last_trade;
+        let previous = state.clone();
+        let now = Utc::now();
 
         state.pending_volume_usd = (state.pending_volume_usd - amount).max(Decimal::ZERO);
         state.daily_volume_usd += amount;
-        state.last_trade = Some(Utc::now());
+        if let Some(token) = token {
+            let exposure = state.token_exposure.entry(UserState::token_key(token)).or_default();
+            exposure.reserved_usd = (exposure.reserved_usd - amount).max(Decimal::ZERO);
+            exposure.committed_usd += amount;
+        }
+        state.last_trade = Some(now);
+        let outcome = TradeOutcome { at: now, amount_usd: amount, realized_pnl_usd: realized_pnl, rolled_back: false };
+        if state.record_outcome(outcome, &self.config) {
+            tracing::warn!(user = %user_id, "Losing trade streak tripped the failure cooldown");
+        }
 
         if let Err(e) = self.store.save(&self.state).await {
             // Rollback on failure
-            if let Some(s) = self.state.get_mut(&user_id) {
-                s.pending_volume_usd = old_pending;
-                s.daily_volume_usd = old_daily;
-                s.last_trade = old_last;
-            }
+            self.state.insert(user_id, previous);
             return Err(e);
         }
         Ok(())
     }
 
-    fn handle_rollback(&mut self, user_id: String, amount: Decimal) {
-        if let Some(state) = self.state.get_mut(&user_id) {
+    async fn handle_rollback(&mut self, user_id: String, amount: Decimal, failed: bool) {
+        let reservation_id = self.ledger.find_unresolved(&user_id, amount);
+        let token = reservation_id.as_deref().and_then(|id| self.reserved_token(id));
+        self.release_volume(&user_id, token.as_deref(), amount);
+        if failed {
+            self.record_failure(&user_id, amount).await;
+        }
+
+        if let Some(id) = reservation_id {
+            self.ledger.transition(&id, ReservationStatus::RolledBack, "rolled back");
+            self.persist_ledger().await;
+        }
+    }
+
+    /// Count a rolled-back trade towards the failure cooldown
+    async fn record_failure(&mut self, user_id: &str, amount: Decimal) {
+        let state = self.state.entry(user_id.to_string()).or_default();
+        let outcome = TradeOutcome { at: Utc::now(), amount_usd: amount, realized_pnl_usd: Decimal::ZERO, rolled_back: true };
+        if state.record_outcome(outcome, &self.config) {
+            // A tripped breaker must survive a restart, so don't wait for the periodic flush
+            tracing::warn!(user = %user_id, "Failed trade streak tripped the failure cooldown");
+            if let Err(e) = self.store.save(&self.state).await {
+                tracing::error!("Failed to persist risk state: {}", e);
+            }
+        }
+    }
+
+    /// The reservation `id`, if it is still waiting to be resolved
+    fn unresolved(&self, id: &str) -> Result<Reservation> {
+        let reservation = self.ledger.get(id).cloned()
+            .ok_or_else(|| Error::Internal(format!("Reservation not found: {}", id)))?;
+        if !reservation.status.is_unresolved() {
+            return Err(Error::Internal(format!("Reservation {} is already {:?}", id, reservation.status)));
+        }
+        Ok(reservation)
+    }
+
+    async fn handle_commit_reservation(&mut self, id: String, realized_pnl: Decimal) -> Result<()> {
+        let reservation = self.unresolved(&id)?;
+        self.commit_volume(&reservation.user_id, reservation.token.as_deref(), reservation.amount_usd, realized_pnl).await?;
+        self.ledger.transition(&id, ReservationStatus::Committed, "committed");
+        self.persist_ledger().await;
+        Ok(())
+    }
+
+    async fn handle_rollback_reservation(&mut self, id: String) -> Result<()> {
+        let reservation = self.unresolved(&id)?;
+        self.release_volume(&reservation.user_id, reservation.token.as_deref(), reservation.amount_usd);
+        self.record_failure(&reservation.user_id, reservation.amount_usd).await;
+        self.ledger.transition(&id, ReservationStatus::RolledBack, "rolled back");
+        self.persist_ledger().await;
+        Ok(())
+    }
+
+    fn release_volume(&mut self, user_id: &str, token: Option<&str>, amount: Decimal) {
+        if let Some(state) = self.state.get_mut(user_id) {
             state.pending_volume_usd = (state.pending_volume_usd - amount).max(Decimal::ZERO);
+            if let Some(exposure) = token.and_then(|t| state.token_exposure.get_mut(&UserState::token_key(t))) {
+                exposure.reserved_usd = (exposure.reserved_usd - amount).max(Decimal::ZERO);
+            }
+        }
+    }
+
+    async fn handle_release_exposure(&mut self, user_id: String, token: String, amount: Decimal) -> Result<()> {
+        let Some(state) = self.state.get_mut(&user_id) else {
+            return Ok(());
+        };
+        let previous = state.clone();
+        if let Some(exposure) = state.token_exposure.get_mut(&UserState::token_key(&token)) {
+            exposure.committed_usd = (exposure.committed_usd - amount).max(Decimal::ZERO);
+        }
+        if let Err(e) = self.store.save(&self.state).await {
+            self.state.insert(user_id, previous);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn handle_resolve(&mut self, id: String, resolution: ReservationResolution) -> Result<Reservation> {
+        let reservation = self.unresolved(&id)?;
+        let token = reservation.token.as_deref();
+        let status = match resolution {
+            ReservationResolution::Commit => {
+                self.commit_volume(&reservation.user_id, token, reservation.amount_usd, Decimal::ZERO).await?;
+                ReservationStatus::Committed
+            }
+            ReservationResolution::Rollback => {
+                self.release_volume(&reservation.user_id, token, reservation.amount_usd);
+                ReservationStatus::RolledBack
+            }
+        };
+
+        let resolved = self.ledger.transition(&id, status, "resolved manually")
+            .ok_or_else(|| Error::Internal(format!("Reservation {} could not be resolved", id)))?;
+        self.store.save_ledger(&self.ledger).await?;
+        Ok(resolved)
+    }
+
+    /// Best-effort ledger save; a lost resolution is reconciled on the next startup
+    async fn persist_ledger(&self) {
+        if let Err(e) = self.store.save_ledger(&self.ledger).await {
+            tracing::error!("Failed to persist risk reservation ledger: {}", e);
         }
     }
 
@@ -383,6 +951,7 @@ impl RiskManager {
         let actor = RiskActor {
             config: config.clone(),
             state: HashMap::new(),
+            ledger: ReservationLedger::default(),
             store,
             receiver: rx,
             last_load_time: None,
@@ -415,25 +984,56 @@ impl RiskManager {
                                 match maybe_msg {
                                     Some(msg) => {
                                          match msg {
-                                             RiskCommand::CheckAndReserve { context, checks, reply } => {
+                                             RiskCommand::CheckAndReserve { context, checks, origin, reply } => {
                                                  // Moved checks into the handler
-                                                 let res = actor.handle_check_and_reserve(context, checks).await;
+                                                 let res = actor.handle_check_and_reserve(context, checks, origin).await;
                                                  dirty = res.is_ok();  // Mark dirty if reservation succeeded
                                                  let _ = reply.send(res);
                                              }
-                                             RiskCommand::Commit { user_id, amount_usd, reply } => {
-                                                 let res = actor.handle_commit(user_id, amount_usd).await;
+                                             RiskCommand::Commit { user_id, amount_usd, realized_pnl_usd, reply } => {
+                                                 let res = actor.handle_commit(user_id, amount_usd, realized_pnl_usd).await;
                                                  // Commit already saves, no need to set dirty
                                                  let _ = reply.send(res);
                                              }
-                                             RiskCommand::Rollback { user_id, amount_usd } => {
-                                                 actor.handle_rollback(user_id, amount_usd);
+                                             RiskCommand::Rollback { user_id, amount_usd, failed } => {
+                                                 actor.handle_rollback(user_id, amount_usd, failed).await;
                                                  dirty = true;
                                              }
+                                             RiskCommand::CommitReservation { id, realized_pnl_usd, reply } => {
+                                                 let res = actor.handle_commit_reservation(id, realized_pnl_usd).await;
+                                                 let _ = reply.send(res);
+                                             }
+                                             RiskCommand::RollbackReservation { id, reply } => {
+                                                 let res = actor.handle_rollback_reservation(id).await;
+                                                 dirty |= res.is_ok();
+                                                 let _ = reply.send(res);
+                                             }
+                                             RiskCommand::Resolve { id, resolution, reply } => {
+                                                 let res = actor.handle_resolve(id, resolution).await;
+                                                 dirty |= res.is_ok();
+                                                 let _ = reply.send(res);
+                                             }
+                                             RiskCommand::PendingReservations { user_id, reply } => {
+                                                 let _ = reply.send(actor.ledger.pending(&user_id));
+                                             }
+                                             RiskCommand::Journal { reply } => {
+                                                 let _ = reply.send(actor.ledger.journal.clone());
+                                             }
                                              RiskCommand::GetRemaining { user_id, reply } => {
                                                  let val = actor.handle_get_remaining(user_id);
                                                  let _ = reply.send(val);
                                              }
+                                             RiskCommand::Exposure { user_id, token, reply } => {
+                                                 let exposure = actor.state.get(&user_id)
+                                                     .and_then(|s| s.token_exposure.get(&UserState::token_key(&token)))
+                                                     .cloned()
+                                                     .unwrap_or_default();
+                                                 let _ = reply.send(exposure);
+                                             }
+                                             RiskCommand::ReleaseExposure { user_id, token, amount_usd, reply } => {
+                                                 let res = actor.handle_release_exposure(user_id, token, amount_usd).await;
+                                                 let _ = reply.send(res);
+                                             }
                                              RiskCommand::LoadState { reply } => {
                                                  let res = actor.handle_load().await;
                                                  let _ = reply.send(res);
@@ -444,9 +1044,14 @@ impl RiskManager {
                                 }
                             }
                             _ = interval.tick() => {
+                                actor.handle_reconcile().await;
                                 // Fix L2: Only save if state was modified
                                 if dirty {
                                     tracing::debug!("RiskManager: performing periodic state flush");
+                                    let retention = chrono::Duration::days(actor.config.ledger_retention_days as i64);
+                                    if actor.ledger.prune(Utc::now(), retention) > 0 {
+                                        actor.persist_ledger().await;
+                                    }
                                     if let Err(e) = actor.store.save(&actor.state).await {
                                          tracing::error!("Periodic risk persistence failed: {}", e);
                                     } else {
@@ -504,6 +1109,11 @@ impl RiskManager {
 
     /// Perform all risk checks for a trade AND reserve the volume.
     pub async fn check_and_reserve(&self, context: &TradeContext) -> Result<()> {
+        self.check_and_reserve_with_origin(context, ReservationOrigin::default()).await.map(|_| ())
+    }
+
+    /// Perform all risk checks and reserve the volume, returning the ledger reservation ID
+    pub async fn check_and_reserve_with_origin(&self, context: &TradeContext, origin: ReservationOrigin) -> Result<String> {
         let checks = self.custom_checks.read()
             .map_err(|_| Error::Internal("Risk check lock poisoned".to_string()))?
             .clone();
@@ -512,6 +1122,7 @@ impl RiskManager {
         self.sender.send(RiskCommand::CheckAndReserve { 
             context: context.clone(), 
             checks, 
+            origin,
             reply: tx 
         }).await.map_err(|_| Error::Internal("Risk actor closed".to_string()))?;
         
@@ -522,30 +1133,108 @@ impl RiskManager {
     #[deprecated(note = "Use check_and_reserve for race-condition safety")]
     pub async fn check_trade(&self, context: &TradeContext) -> Result<()> {
         self.check_and_reserve(context).await?;
-        self.rollback_trade(&context.user_id, context.amount_usd).await;
+        // A dry run, not a failed trade
+        let _ = self.sender.send(RiskCommand::Rollback {
+            user_id: context.user_id.clone(),
+            amount_usd: context.amount_usd,
+            failed: false,
+        }).await;
         Ok(())
     }
 
     /// Commit a trade that was previously reserved
+    ///
+    /// Picks the oldest pending reservation of the same user and amount, so with
+    /// several open trades the exposure can land on the wrong token; prefer
+    /// [`commit_reservation`](Self::commit_reservation).
     pub async fn commit_trade(&self, user_id: &str, amount_usd: Decimal) -> Result<()> {
+        self.commit_trade_with_pnl(user_id, amount_usd, Decimal::ZERO).await
+    }
+
+    /// Commit a reserved trade and record its realized profit (negative for a loss)
+    ///
+    /// Losses count towards [`RiskConfig::max_daily_loss_usd`] and, like
+    /// rollbacks, towards [`RiskConfig::cooldown_after_failures`].
+    pub async fn commit_trade_with_pnl(&self, user_id: &str, amount_usd: Decimal, realized_pnl_usd: Decimal) -> Result<()> {
         let (tx, rx) = oneshot::channel();
         self.sender.send(RiskCommand::Commit { 
             user_id: user_id.to_string(), 
             amount_usd, 
+            realized_pnl_usd,
             reply: tx 
         }).await.map_err(|_| Error::Internal("Risk actor closed".to_string()))?;
         
         rx.await.map_err(|_| Error::Internal("Risk actor dropped reply".to_string()))?
     }
 
-    /// Rollback a reservation
+    /// Rollback a reservation, recording a failed trade
+    ///
+    /// Matches the reservation like [`commit_trade`](Self::commit_trade); prefer
+    /// [`rollback_reservation`](Self::rollback_reservation).
     pub async fn rollback_trade(&self, user_id: &str, amount_usd: Decimal) {
         let _ = self.sender.send(RiskCommand::Rollback { 
             user_id: user_id.to_string(), 
-            amount_usd 
+            amount_usd,
+            failed: true,
         }).await;
     }
 
+    /// Commit the reservation `id` and record its realized profit (negative for a loss)
+    pub async fn commit_reservation(&self, id: &str, realized_pnl_usd: Decimal) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.sender.send(RiskCommand::CommitReservation {
+            id: id.to_string(),
+            realized_pnl_usd,
+            reply: tx
+        }).await.map_err(|_| Error::Internal("Risk actor closed".to_string()))?;
+
+        rx.await.map_err(|_| Error::Internal("Risk actor dropped reply".to_string()))?
+    }
+
+    /// Roll back the reservation `id`, recording a failed trade
+    pub async fn rollback_reservation(&self, id: &str) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.sender.send(RiskCommand::RollbackReservation {
+            id: id.to_string(),
+            reply: tx
+        }).await.map_err(|_| Error::Internal("Risk actor closed".to_string()))?;
+
+        rx.await.map_err(|_| Error::Internal("Risk actor dropped reply".to_string()))?
+    }
+
+    /// Commit or roll back a reservation by ID (e.g. one held after a restart)
+    pub async fn resolve_reservation(&self, id: &str, resolution: ReservationResolution) -> Result<Reservation> {
+        let (tx, rx) = oneshot::channel();
+        self.sender.send(RiskCommand::Resolve {
+            id: id.to_string(),
+            resolution,
+            reply: tx
+        }).await.map_err(|_| Error::Internal("Risk actor closed".to_string()))?;
+
+        rx.await.map_err(|_| Error::Internal("Risk actor dropped reply".to_string()))?
+    }
+
+    /// Unresolved reservations for a user
+    pub async fn pending_reservations(&self, user_id: &str) -> Vec<Reservation> {
+        let (tx, rx) = oneshot::channel();
+        if self.sender.send(RiskCommand::PendingReservations {
+            user_id: user_id.to_string(),
+            reply: tx
+        }).await.is_err() {
+            return Vec::new();
+        }
+        rx.await.unwrap_or_default()
+    }
+
+    /// Journal of reservation status changes
+    pub async fn reservation_journal(&self) -> Vec<JournalEntry> {
+        let (tx, rx) = oneshot::channel();
+        if self.sender.send(RiskCommand::Journal { reply: tx }).await.is_err() {
+            return Vec::new();
+        }
+        rx.await.unwrap_or_default()
+    }
+
     /// Record a trade immediately
     pub async fn record_trade(&self, user_id: &str, amount_usd: Decimal) -> Result<()> {
         self.commit_trade(user_id, amount_usd).await
@@ -562,6 +1251,35 @@ impl RiskManager {
         }
         rx.await.unwrap_or(Decimal::ZERO)
     }
+
+    /// A user's reserved and committed exposure to `token`
+    pub async fn token_exposure(&self, user_id: &str, token: &str) -> TokenExposure {
+        let (tx, rx) = oneshot::channel();
+        if self.sender.send(RiskCommand::Exposure {
+            user_id: user_id.to_string(),
+            token: token.to_string(),
+            reply: tx
+        }).await.is_err() {
+            return TokenExposure::default();
+        }
+        rx.await.unwrap_or_default()
+    }
+
+    /// Lower a user's committed exposure to `token`, e.g. after selling it
+    ///
+    /// Committed exposure only grows with buys; sells are not matched to
+    /// positions, so closing one is reported here.
+    pub async fn release_exposure(&self, user_id: &str, token: &str, amount_usd: Decimal) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        self.sender.send(RiskCommand::ReleaseExposure {
+            user_id: user_id.to_string(),
+            token: token.to_string(),
+            amount_usd,
+            reply: tx
+        }).await.map_err(|_| Error::Internal("Risk actor closed".to_string()))?;
+
+        rx.await.map_err(|_| Error::Internal("Risk actor dropped reply".to_string()))?
+    }
 }
 
 // Default trait removed because new() is async. Use RiskManager::new().await instead.
@@ -571,6 +1289,31 @@ impl RiskManager {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_config_validation_reports_all_limits() {
+        let config = RiskConfig {
+            max_single_trade_usd: dec!(-5),
+            max_daily_volume_usd: dec!(0),
+            max_slippage_percent: dec!(150),
+            ..Default::default()
+        };
+        let paths: Vec<String> = config.validate().into_iter().map(|i| i.path).collect();
+        assert_eq!(
+            paths,
+            ["risk.max_single_trade_usd", "risk.max_daily_volume_usd", "risk.max_slippage_percent"]
+        );
+
+        let inverted = RiskConfig {
+            max_single_trade_usd: dec!(5000),
+            max_daily_volume_usd: dec!(1000),
+            ..Default::default()
+        };
+        let issues = inverted.validate();
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.contains("below max_single_trade_usd"));
+        assert!(RiskConfig::default().validate().is_empty());
+    }
+
     #[tokio::test]
     async fn test_single_trade_limit() {
         let manager = RiskManager::with_config(
@@ -619,4 +1362,299 @@ mod tests {
         let remaining = manager.remaining_daily_limit("user1").await;
         assert_eq!(remaining, dec!(50_000.0) - dec!(100.0));
     }
+
+    fn ledger_context(amount_usd: Decimal) -> TradeContext {
+        TradeContext {
+            user_id: "user1".to_string(),
+            from_token: "USDC".to_string(),
+            to_token: "SOL".to_string(),
+            amount_usd,
+            expected_slippage: dec!(0.5),
+            liquidity_usd: Some(dec!(1_000_000.0)),
+            is_flagged: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crash_between_reserve_and_commit_auto_rolls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("risk.json");
+        let config = RiskConfig {
+            trade_cooldown_secs: 0,
+            reservation_staleness_secs: 0,
+            ..Default::default()
+        };
+
+        let manager = RiskManager::with_config(config.clone(), Arc::new(FileRiskStore::new(&path))).await.unwrap();
+        manager.commit_trade("user1", dec!(1000.0)).await.unwrap();
+        let before = manager.remaining_daily_limit("user1").await;
+
+        let origin = ReservationOrigin::new().with_session("session-1").with_tool("swap");
+        let id = manager.check_and_reserve_with_origin(&ledger_context(dec!(250.0)), origin.clone()).await.unwrap();
+        let pending = manager.pending_reservations("user1").await;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].origin, origin);
+        assert_eq!(manager.remaining_daily_limit("user1").await, before - dec!(250.0));
+
+        // Crash before commit
+        drop(manager);
+
+        let manager = RiskManager::with_config(config, Arc::new(FileRiskStore::new(&path))).await.unwrap();
+        assert_eq!(manager.remaining_daily_limit("user1").await, before);
+        assert!(manager.pending_reservations("user1").await.is_empty());
+
+        let journal = manager.reservation_journal().await;
+        let entry = journal.iter().find(|e| e.reservation_id == id).unwrap();
+        assert_eq!(entry.status, ReservationStatus::RolledBack);
+        assert_eq!(entry.amount_usd, dec!(250.0));
+        assert!(entry.reason.contains("auto-rolled back"));
+    }
+
+    #[tokio::test]
+    async fn test_reservation_going_stale_while_running_auto_rolls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = RiskConfig {
+            trade_cooldown_secs: 0,
+            reservation_staleness_secs: 1,
+            ..Default::default()
+        };
+        let manager = RiskManager::with_config(config, Arc::new(FileRiskStore::new(dir.path().join("risk.json")))).await.unwrap();
+
+        let id = manager.check_and_reserve_with_origin(&ledger_context(dec!(250.0)), ReservationOrigin::new()).await.unwrap();
+        assert_eq!(manager.pending_reservations("user1").await.len(), 1);
+        assert_eq!(manager.remaining_daily_limit("user1").await, dec!(50_000.0) - dec!(250.0));
+
+        // Picked up by the periodic tick once the window passes
+        tokio::time::timeout(std::time::Duration::from_secs(15), async {
+            while !manager.pending_reservations("user1").await.is_empty() {
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(manager.remaining_daily_limit("user1").await, dec!(50_000.0));
+
+        let journal = manager.reservation_journal().await;
+        let entry = journal.iter().find(|e| e.reservation_id == id).unwrap();
+        assert_eq!(entry.status, ReservationStatus::RolledBack);
+        assert!(entry.reason.contains("while running"));
+    }
+
+    #[tokio::test]
+    async fn test_hold_and_alert_requires_manual_resolution() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("risk.json");
+        let config = RiskConfig {
+            reservation_staleness_secs: 0,
+            reconciliation_policy: ReconciliationPolicy::HoldAndAlert,
+            ..Default::default()
+        };
+
+        let manager = RiskManager::with_config(config.clone(), Arc::new(FileRiskStore::new(&path))).await.unwrap();
+        let id = manager.check_and_reserve_with_origin(&ledger_context(dec!(400.0)), ReservationOrigin::new()).await.unwrap();
+        drop(manager);
+
+        let manager = RiskManager::with_config(config, Arc::new(FileRiskStore::new(&path))).await.unwrap();
+        let held = manager.pending_reservations("user1").await;
+        assert_eq!(held.len(), 1);
+        assert_eq!(held[0].status, ReservationStatus::Held);
+        assert_eq!(manager.remaining_daily_limit("user1").await, dec!(50_000.0) - dec!(400.0));
+
+        let resolved = manager.resolve_reservation(&id, ReservationResolution::Rollback).await.unwrap();
+        assert_eq!(resolved.status, ReservationStatus::RolledBack);
+        assert_eq!(manager.remaining_daily_limit("user1").await, dec!(50_000.0));
+        assert!(manager.resolve_reservation(&id, ReservationResolution::Commit).await.is_err());
+
+        let statuses: Vec<_> = manager.reservation_journal().await.iter().map(|e| e.status).collect();
+        assert_eq!(statuses, vec![ReservationStatus::Held, ReservationStatus::RolledBack]);
+    }
+
+    #[tokio::test]
+    async fn test_commit_resolves_reservation() {
+        let manager = RiskManager::new().await.unwrap();
+        manager.check_and_reserve(&ledger_context(dec!(100.0))).await.unwrap();
+        manager.commit_trade("user1", dec!(100.0)).await.unwrap();
+
+        assert!(manager.pending_reservations("user1").await.is_empty());
+        let journal = manager.reservation_journal().await;
+        assert_eq!(journal.len(), 1);
+        assert_eq!(journal[0].status, ReservationStatus::Committed);
+    }
+
+    #[tokio::test]
+    async fn test_daily_loss_limit_trips_survives_restart_and_slides() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("risk.json");
+        let config = RiskConfig {
+            trade_cooldown_secs: 0,
+            max_daily_loss_usd: Some(dec!(500)),
+            ..Default::default()
+        };
+
+        let manager = RiskManager::with_config(config.clone(), Arc::new(FileRiskStore::new(&path))).await.unwrap();
+        for pnl in [dec!(-200), dec!(50), dec!(-250)] {
+            manager.check_and_reserve(&ledger_context(dec!(100))).await.unwrap();
+            manager.commit_trade_with_pnl("user1", dec!(100), pnl).await.unwrap();
+        }
+        // Net loss is 400: still under the limit
+        manager.check_and_reserve(&ledger_context(dec!(100))).await.unwrap();
+        manager.commit_trade_with_pnl("user1", dec!(100), dec!(-150)).await.unwrap();
+
+        let err = manager.check_and_reserve(&ledger_context(dec!(100))).await.unwrap_err();
+        assert!(err.to_string().contains("daily loss limit reached ($550.00 of $500.00), resets at"), "{}", err);
+        drop(manager);
+
+        let manager = RiskManager::with_config(config.clone(), Arc::new(FileRiskStore::new(&path))).await.unwrap();
+        assert!(manager.check_and_reserve(&ledger_context(dec!(100))).await.is_err());
+
+        // Once the first loss leaves the window the net loss is back under the limit
+        let mut state: HashMap<String, UserState> =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let state = state.remove("user1").unwrap();
+        let first = state.recent_outcomes[0].at;
+        let resets_at = first + chrono::Duration::hours(DAILY_LOSS_WINDOW_HOURS);
+        assert!(err.to_string().contains(&resets_at.to_rfc3339()));
+        assert!(state.check_circuit_breakers(&config, resets_at - chrono::Duration::seconds(1)).is_err());
+        state.check_circuit_breakers(&config, resets_at).unwrap();
+        assert_eq!(state.realized_loss(resets_at), dec!(350));
+    }
+
+    #[tokio::test]
+    async fn test_consecutive_failures_trip_cooldown() {
+        let config = RiskConfig {
+            trade_cooldown_secs: 0,
+            cooldown_after_failures: Some(FailureCooldown { count: 3, duration_secs: 3600 }),
+            ..Default::default()
+        };
+        let manager = RiskManager::with_config(config.clone(), Arc::new(InMemoryRiskStore)).await.unwrap();
+
+        // A success in between resets the streak
+        for _ in 0..2 {
+            manager.check_and_reserve(&ledger_context(dec!(100))).await.unwrap();
+            manager.rollback_trade("user1", dec!(100)).await;
+        }
+        manager.check_and_reserve(&ledger_context(dec!(100))).await.unwrap();
+        manager.commit_trade("user1", dec!(100)).await.unwrap();
+        for _ in 0..2 {
+            manager.check_and_reserve(&ledger_context(dec!(100))).await.unwrap();
+            manager.rollback_trade("user1", dec!(100)).await;
+        }
+        manager.check_and_reserve(&ledger_context(dec!(100))).await.unwrap();
+        manager.commit_trade_with_pnl("user1", dec!(100), dec!(-10)).await.unwrap();
+
+        let err = manager.check_and_reserve(&ledger_context(dec!(100))).await.unwrap_err();
+        assert!(err.to_string().contains("failure_cooldown"), "{}", err);
+
+        // The cooldown lapses on its own
+        let mut state = UserState::default();
+        let start = Utc::now();
+        for i in 0..3 {
+            let outcome = TradeOutcome {
+                at: start + chrono::Duration::seconds(i),
+                amount_usd: dec!(100),
+                realized_pnl_usd: Decimal::ZERO,
+                rolled_back: true,
+            };
+            assert_eq!(state.record_outcome(outcome, &config), i == 2);
+        }
+        assert!(state.check_circuit_breakers(&config, start + chrono::Duration::minutes(59)).is_err());
+        state.check_circuit_breakers(&config, start + chrono::Duration::hours(2)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_token_exposure_accumulates_across_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("risk.json");
+        let config = RiskConfig {
+            trade_cooldown_secs: 0,
+            ..Default::default()
+        };
+        let exposure_check = || -> Arc<dyn RiskCheck> {
+            Arc::new(PerTokenExposureCheck::new(dec!(2000)).with_cap("BONK", dec!(500)))
+        };
+
+        let manager = RiskManager::with_config(config.clone(), Arc::new(FileRiskStore::new(&path))).await.unwrap();
+        manager.add_check(exposure_check());
+        for _ in 0..3 {
+            manager.check_and_reserve(&ledger_context(dec!(600))).await.unwrap();
+            manager.commit_trade("user1", dec!(600)).await.unwrap();
+        }
+        let exposure = manager.token_exposure("user1", "sol").await;
+        assert_eq!(exposure, TokenExposure { reserved_usd: Decimal::ZERO, committed_usd: dec!(1800) });
+
+        // A pending reservation counts too
+        manager.check_and_reserve(&ledger_context(dec!(150))).await.unwrap();
+        let err = manager.check_and_reserve(&ledger_context(dec!(100))).await.unwrap_err();
+        assert!(err.to_string().contains("Exposure to SOL would reach $2050.00, above the $2000.00 cap"), "{}", err);
+
+        // Other tokens have their own budget, and per-token caps override the default
+        let bonk = TradeContext { to_token: "bonk".to_string(), ..ledger_context(dec!(600)) };
+        assert!(manager.check_and_reserve(&bonk).await.is_err());
+        let jup = TradeContext { to_token: "JUP".to_string(), ..ledger_context(dec!(600)) };
+        manager.check_and_reserve(&jup).await.unwrap();
+        manager.commit_trade("user1", dec!(600)).await.unwrap();
+        assert_eq!(manager.token_exposure("user1", "JUP").await.committed_usd, dec!(600));
+        drop(manager);
+
+        // Committed exposure persists; the pending reservation is restored from the ledger
+        let manager = RiskManager::with_config(config, Arc::new(FileRiskStore::new(&path))).await.unwrap();
+        manager.add_check(exposure_check());
+        let exposure = manager.token_exposure("user1", "SOL").await;
+        assert_eq!(exposure, TokenExposure { reserved_usd: dec!(150), committed_usd: dec!(1800) });
+        assert!(manager.check_and_reserve(&ledger_context(dec!(100))).await.is_err());
+
+        manager.release_exposure("user1", "SOL", dec!(1000)).await.unwrap();
+        manager.check_and_reserve(&ledger_context(dec!(100))).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rollback_releases_token_exposure() {
+        let manager = RiskManager::with_config(
+            RiskConfig { trade_cooldown_secs: 0, ..Default::default() },
+            Arc::new(InMemoryRiskStore),
+        ).await.unwrap();
+        manager.add_check(Arc::new(PerTokenExposureCheck::new(dec!(1000))));
+
+        manager.check_and_reserve(&ledger_context(dec!(800))).await.unwrap();
+        assert_eq!(manager.token_exposure("user1", "SOL").await.reserved_usd, dec!(800));
+        assert!(manager.check_and_reserve(&ledger_context(dec!(300))).await.is_err());
+
+        manager.rollback_trade("user1", dec!(800)).await;
+        assert_eq!(manager.token_exposure("user1", "SOL").await, TokenExposure::default());
+        manager.check_and_reserve(&ledger_context(dec!(300))).await.unwrap();
+
+        let id = manager.pending_reservations("user1").await[0].id.clone();
+        manager.resolve_reservation(&id, ReservationResolution::Rollback).await.unwrap();
+        assert_eq!(manager.token_exposure("user1", "SOL").await.total(), Decimal::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_commit_and_rollback_by_reservation_id() {
+        let manager = RiskManager::with_config(
+            RiskConfig { trade_cooldown_secs: 0, ..Default::default() },
+            Arc::new(InMemoryRiskStore),
+        ).await.unwrap();
+        let sol = manager.check_and_reserve_with_origin(&ledger_context(dec!(100)), ReservationOrigin::default()).await.unwrap();
+        let jup = TradeContext { to_token: "JUP".to_string(), ..ledger_context(dec!(100)) };
+        let jup = manager.check_and_reserve_with_origin(&jup, ReservationOrigin::default()).await.unwrap();
+
+        // Same user and amount: only the ID tells the trades apart
+        manager.commit_reservation(&jup, dec!(-5)).await.unwrap();
+        assert_eq!(manager.token_exposure("user1", "JUP").await, TokenExposure { reserved_usd: Decimal::ZERO, committed_usd: dec!(100) });
+        assert_eq!(manager.token_exposure("user1", "SOL").await, TokenExposure { reserved_usd: dec!(100), committed_usd: Decimal::ZERO });
+
+        manager.rollback_reservation(&sol).await.unwrap();
+        assert_eq!(manager.token_exposure("user1", "SOL").await, TokenExposure::default());
+        assert!(manager.pending_reservations("user1").await.is_empty());
+        assert!(manager.commit_reservation(&sol, Decimal::ZERO).await.is_err());
+        assert!(manager.rollback_reservation("missing").await.is_err());
+    }
+
+    #[test]
+    fn test_token_key_keeps_address_case() {
+        let mint = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+        assert_eq!(UserState::token_key(" sol "), "SOL");
+        assert_eq!(UserState::token_key(mint), mint);
+        assert_ne!(UserState::token_key(mint), UserState::token_key(&mint.to_lowercase()));
+    }
 }
@@ -1,7 +1,8 @@
 //! Enhanced Risk Check system with composable checks
 
-use super::{RiskCheck, RiskCheckResult, TradeContext};
+use super::{RiskCheck, RiskCheckResult, TradeContext, UserState};
 use rust_decimal::Decimal;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 /// Maximum trade amount check
@@ -128,6 +129,155 @@ impl RiskCheck for TokenSecurityCheck {
     }
 }
 
+/// Token symbols (matched case-insensitively) and contract addresses (matched exactly)
+#[derive(Debug, Clone, Default)]
+struct TokenList {
+    symbols: HashSet<String>,
+    addresses: HashSet<String>,
+}
+
+impl TokenList {
+    fn new<S: Into<String>>(symbols: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            symbols: symbols.into_iter().map(|s| s.into().trim().to_uppercase()).collect(),
+            addresses: HashSet::new(),
+        }
+    }
+
+    fn add_addresses<S: Into<String>>(&mut self, addresses: impl IntoIterator<Item = S>) {
+        self.addresses
+            .extend(addresses.into_iter().map(|a| a.into().trim().to_string()));
+    }
+
+    fn contains(&self, token: &str) -> bool {
+        let token = token.trim();
+        self.addresses.contains(token) || self.symbols.contains(&token.to_uppercase())
+    }
+}
+
+/// Only allow buying listed tokens
+pub struct TokenAllowlistCheck {
+    allowed: TokenList,
+}
+
+impl TokenAllowlistCheck {
+    pub fn new<S: Into<String>>(symbols: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            allowed: TokenList::new(symbols),
+        }
+    }
+
+    /// Also allow tokens given by contract address
+    pub fn with_addresses<S: Into<String>>(mut self, addresses: impl IntoIterator<Item = S>) -> Self {
+        self.allowed.add_addresses(addresses);
+        self
+    }
+}
+
+impl RiskCheck for TokenAllowlistCheck {
+    fn name(&self) -> &str {
+        "token_allowlist"
+    }
+
+    fn check(&self, context: &TradeContext) -> RiskCheckResult {
+        if self.allowed.contains(&context.to_token) {
+            RiskCheckResult::Approved
+        } else {
+            RiskCheckResult::Rejected {
+                reason: format!("Token {} is not on the allowlist", context.to_token),
+            }
+        }
+    }
+}
+
+/// Never allow buying listed tokens
+pub struct TokenDenylistCheck {
+    denied: TokenList,
+}
+
+impl TokenDenylistCheck {
+    pub fn new<S: Into<String>>(symbols: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            denied: TokenList::new(symbols),
+        }
+    }
+
+    /// Also deny tokens given by contract address
+    pub fn with_addresses<S: Into<String>>(mut self, addresses: impl IntoIterator<Item = S>) -> Self {
+        self.denied.add_addresses(addresses);
+        self
+    }
+}
+
+impl RiskCheck for TokenDenylistCheck {
+    fn name(&self) -> &str {
+        "token_denylist"
+    }
+
+    fn check(&self, context: &TradeContext) -> RiskCheckResult {
+        if self.denied.contains(&context.to_token) {
+            RiskCheckResult::Rejected {
+                reason: format!("Token {} is on the denylist", context.to_token),
+            }
+        } else {
+            RiskCheckResult::Approved
+        }
+    }
+}
+
+/// Cap a user's reserved plus committed USD exposure to any single token
+pub struct PerTokenExposureCheck {
+    max_usd: Decimal,
+    caps: HashMap<String, Decimal>,
+}
+
+impl PerTokenExposureCheck {
+    pub fn new(max_usd: Decimal) -> Self {
+        Self {
+            max_usd,
+            caps: HashMap::new(),
+        }
+    }
+
+    /// Use a different cap for `token`
+    pub fn with_cap(mut self, token: &str, max_usd: Decimal) -> Self {
+        self.caps.insert(UserState::token_key(token), max_usd);
+        self
+    }
+
+    fn cap(&self, token: &str) -> Decimal {
+        self.caps
+            .get(&UserState::token_key(token))
+            .copied()
+            .unwrap_or(self.max_usd)
+    }
+}
+
+impl RiskCheck for PerTokenExposureCheck {
+    fn name(&self) -> &str {
+        "token_exposure"
+    }
+
+    fn check(&self, _context: &TradeContext) -> RiskCheckResult {
+        RiskCheckResult::Approved
+    }
+
+    fn check_state(&self, context: &TradeContext, state: &UserState) -> RiskCheckResult {
+        let cap = self.cap(&context.to_token);
+        let projected = state.exposure(&context.to_token) + context.amount_usd;
+        if projected > cap {
+            RiskCheckResult::Rejected {
+                reason: format!(
+                    "Exposure to {} would reach ${:.2}, above the ${:.2} cap",
+                    context.to_token, projected, cap
+                ),
+            }
+        } else {
+            RiskCheckResult::Approved
+        }
+    }
+}
+
 /// Composite check that combines multiple checks
 pub struct CompositeCheck {
     checks: Vec<Arc<dyn RiskCheck>>,
@@ -154,6 +304,16 @@ impl RiskCheck for CompositeCheck {
         }
         RiskCheckResult::Approved
     }
+
+    fn check_state(&self, context: &TradeContext, state: &UserState) -> RiskCheckResult {
+        for check in &self.checks {
+            match check.check_state(context, state) {
+                RiskCheckResult::Approved => continue,
+                other => return other,
+            }
+        }
+        RiskCheckResult::Approved
+    }
 }
 
 /// Builder for creating risk check pipelines
@@ -187,6 +347,18 @@ impl RiskCheckBuilder {
         self.add_check(Arc::new(TokenSecurityCheck::new(blacklist)))
     }
 
+    pub fn token_allowlist<S: Into<String>>(self, symbols: impl IntoIterator<Item = S>) -> Self {
+        self.add_check(Arc::new(TokenAllowlistCheck::new(symbols)))
+    }
+
+    pub fn token_denylist<S: Into<String>>(self, symbols: impl IntoIterator<Item = S>) -> Self {
+        self.add_check(Arc::new(TokenDenylistCheck::new(symbols)))
+    }
+
+    pub fn max_token_exposure(self, max_usd: Decimal) -> Self {
+        self.add_check(Arc::new(PerTokenExposureCheck::new(max_usd)))
+    }
+
     pub fn build(self) -> Vec<Arc<dyn RiskCheck>> {
         self.checks
     }
@@ -205,6 +377,7 @@ impl Default for RiskCheckBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rust_decimal_macros::dec;
 
     #[test]
     fn test_risk_check_builder() {
@@ -257,4 +430,33 @@ mod tests {
 
         assert!(!composite.check(&bad_context).is_approved());
     }
+
+    #[test]
+    fn test_token_lists() {
+        let context = |token: &str| TradeContext {
+            user_id: "test".to_string(),
+            from_token: "USDC".to_string(),
+            to_token: token.to_string(),
+            amount_usd: dec!(100.0),
+            expected_slippage: dec!(1.0),
+            liquidity_usd: Some(dec!(200000.0)),
+            is_flagged: false,
+        };
+        let mint = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+        let allowlist = TokenAllowlistCheck::new(["SOL", "jup"]).with_addresses([mint]);
+        assert!(allowlist.check(&context("sol")).is_approved());
+        assert!(allowlist.check(&context("JUP")).is_approved());
+        assert!(allowlist.check(&context(mint)).is_approved());
+        match allowlist.check(&context("WIF")) {
+            RiskCheckResult::Rejected { reason } => assert_eq!(reason, "Token WIF is not on the allowlist"),
+            other => panic!("expected rejection, got {:?}", other),
+        }
+        // Addresses are case-sensitive on Solana
+        assert!(!allowlist.check(&context(&mint.to_lowercase())).is_approved());
+
+        let denylist = TokenDenylistCheck::new(["Bonk"]);
+        assert!(!denylist.check(&context("BONK")).is_approved());
+        assert!(denylist.check(&context("SOL")).is_approved());
+    }
 }
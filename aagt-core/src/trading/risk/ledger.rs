@@ -0,0 +1,300 @@
+//! Reservation ledger for crash-safe risk accounting
+//!
+//! Every reservation is recorded before it is acknowledged, so volume held by
+//! trades that were interrupted by a crash can be reconciled on the next startup.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// How stale reservations found at startup are resolved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconciliationPolicy {
+    /// Release the reserved volume back to the user
+    #[default]
+    AutoRollback,
+    /// Keep the volume reserved and alert until an operator resolves it
+    HoldAndAlert,
+}
+
+/// Manual resolution applied to a reservation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReservationResolution {
+    /// Count the volume as traded
+    Commit,
+    /// Release the volume
+    Rollback,
+}
+
+/// Lifecycle state of a reservation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReservationStatus {
+    /// Reserved and awaiting commit or rollback
+    Pending,
+    /// Stale after a restart and waiting for manual resolution
+    Held,
+    /// Trade was executed
+    Committed,
+    /// Reservation was released
+    RolledBack,
+}
+
+impl ReservationStatus {
+    /// Whether the reservation still counts against the user's limits
+    pub fn is_unresolved(&self) -> bool {
+        matches!(self, Self::Pending | Self::Held)
+    }
+}
+
+/// Where a reservation came from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReservationOrigin {
+    /// Agent session that requested the trade
+    pub session_id: Option<String>,
+    /// Tool or skill that requested the trade
+    pub tool: Option<String>,
+    /// Strategy pipeline run that requested the trade
+    #[serde(default)]
+    pub run_id: Option<String>,
+}
+
+impl ReservationOrigin {
+    /// Create an empty origin
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attribute the reservation to a session
+    pub fn with_session(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    /// Attribute the reservation to a tool
+    pub fn with_tool(mut self, tool: impl Into<String>) -> Self {
+        self.tool = Some(tool.into());
+        self
+    }
+
+    /// Attribute the reservation to a strategy pipeline run
+    pub fn with_run(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+}
+
+/// A single volume reservation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reservation {
+    /// Unique reservation ID
+    pub id: String,
+    /// User the volume is reserved for
+    pub user_id: String,
+    /// Token being bought, whose exposure the reservation counts towards
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Reserved amount in USD
+    pub amount_usd: Decimal,
+    /// When the reservation was made
+    pub created_at: DateTime<Utc>,
+    /// Session and tool that made the reservation
+    #[serde(default)]
+    pub origin: ReservationOrigin,
+    /// Current status
+    pub status: ReservationStatus,
+    /// When the reservation was committed or rolled back
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Audit record of a reservation status change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// When the change happened
+    pub timestamp: DateTime<Utc>,
+    /// Reservation that changed
+    pub reservation_id: String,
+    /// Owner of the reservation
+    pub user_id: String,
+    /// Reserved amount in USD
+    pub amount_usd: Decimal,
+    /// Status after the change
+    pub status: ReservationStatus,
+    /// Why the change happened
+    pub reason: String,
+    /// Strategy pipeline run that made the reservation
+    #[serde(default)]
+    pub run_id: Option<String>,
+}
+
+/// Persisted reservations and their journal
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReservationLedger {
+    /// Reservations in creation order
+    #[serde(default)]
+    pub reservations: Vec<Reservation>,
+    /// Status changes in the order they happened
+    #[serde(default)]
+    pub journal: Vec<JournalEntry>,
+}
+
+impl ReservationLedger {
+    /// Record a new pending reservation for buying `token` and return its ID
+    pub fn reserve(
+        &mut self,
+        user_id: &str,
+        token: &str,
+        amount_usd: Decimal,
+        origin: ReservationOrigin,
+    ) -> String {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.reservations.push(Reservation {
+            id: id.clone(),
+            user_id: user_id.to_string(),
+            token: Some(token.to_string()),
+            amount_usd,
+            created_at: Utc::now(),
+            origin,
+            status: ReservationStatus::Pending,
+            resolved_at: None,
+        });
+        id
+    }
+
+    /// Drop a reservation that was never acknowledged
+    pub fn discard(&mut self, id: &str) {
+        self.reservations.retain(|r| r.id != id);
+    }
+
+    /// Look up a reservation by ID
+    pub fn get(&self, id: &str) -> Option<&Reservation> {
+        self.reservations.iter().find(|r| r.id == id)
+    }
+
+    /// Unresolved reservations for a user
+    pub fn pending(&self, user_id: &str) -> Vec<Reservation> {
+        self.reservations
+            .iter()
+            .filter(|r| r.user_id == user_id && r.status.is_unresolved())
+            .cloned()
+            .collect()
+    }
+
+    /// Oldest unresolved reservation matching a user and amount
+    pub fn find_unresolved(&self, user_id: &str, amount_usd: Decimal) -> Option<String> {
+        self.reservations
+            .iter()
+            .find(|r| {
+                r.user_id == user_id && r.amount_usd == amount_usd && r.status.is_unresolved()
+            })
+            .map(|r| r.id.clone())
+    }
+
+    /// Unresolved reservations created before `now - window`
+    pub fn stale(&self, now: DateTime<Utc>, window: Duration) -> Vec<String> {
+        self.reservations
+            .iter()
+            .filter(|r| r.status.is_unresolved() && now - r.created_at >= window)
+            .map(|r| r.id.clone())
+            .collect()
+    }
+
+    /// Total unresolved volume per user
+    pub fn pending_totals(&self) -> HashMap<String, Decimal> {
+        let mut totals: HashMap<String, Decimal> = HashMap::new();
+        for r in self
+            .reservations
+            .iter()
+            .filter(|r| r.status.is_unresolved())
+        {
+            *totals.entry(r.user_id.clone()).or_default() += r.amount_usd;
+        }
+        totals
+    }
+
+    /// Total unresolved volume per user and token
+    pub fn pending_token_totals(&self) -> HashMap<(String, String), Decimal> {
+        let mut totals: HashMap<(String, String), Decimal> = HashMap::new();
+        for r in self.reservations.iter().filter(|r| r.status.is_unresolved()) {
+            if let Some(token) = &r.token {
+                *totals.entry((r.user_id.clone(), token.clone())).or_default() += r.amount_usd;
+            }
+        }
+        totals
+    }
+
+    /// Move an unresolved reservation to a new status and journal it
+    ///
+    /// Returns the updated reservation, or `None` if it was missing or already resolved.
+    pub fn transition(
+        &mut self,
+        id: &str,
+        status: ReservationStatus,
+        reason: impl Into<String>,
+    ) -> Option<Reservation> {
+        let now = Utc::now();
+        let reservation = self
+            .reservations
+            .iter_mut()
+            .find(|r| r.id == id && r.status.is_unresolved() && r.status != status)?;
+
+        reservation.status = status;
+        if !status.is_unresolved() {
+            reservation.resolved_at = Some(now);
+        }
+        let updated = reservation.clone();
+
+        self.journal.push(JournalEntry {
+            timestamp: now,
+            reservation_id: updated.id.clone(),
+            user_id: updated.user_id.clone(),
+            amount_usd: updated.amount_usd,
+            status,
+            reason: reason.into(),
+            run_id: updated.origin.run_id.clone(),
+        });
+        Some(updated)
+    }
+
+    /// Remove resolved reservations and journal entries older than the retention period
+    pub fn prune(&mut self, now: DateTime<Utc>, retention: Duration) -> usize {
+        let cutoff = now - retention;
+        let before = self.reservations.len() + self.journal.len();
+        self.reservations
+            .retain(|r| r.status.is_unresolved() || r.resolved_at.is_none_or(|t| t > cutoff));
+        self.journal.retain(|e| e.timestamp > cutoff);
+        before - (self.reservations.len() + self.journal.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_prune_keeps_unresolved() {
+        let mut ledger = ReservationLedger::default();
+        let open = ledger.reserve("user1", "SOL", dec!(10), ReservationOrigin::new());
+        let done = ledger.reserve("user1", "SOL", dec!(20), ReservationOrigin::new());
+        ledger
+            .transition(&done, ReservationStatus::Committed, "committed")
+            .unwrap();
+
+        // Nothing is old enough yet
+        assert_eq!(ledger.prune(Utc::now(), Duration::days(7)), 0);
+
+        let later = Utc::now() + Duration::days(8);
+        assert_eq!(ledger.prune(later, Duration::days(7)), 2);
+        assert!(ledger.get(&open).is_some());
+        assert!(ledger.get(&done).is_none());
+        assert!(ledger.journal.is_empty());
+        assert_eq!(ledger.pending_totals().get("user1"), Some(&dec!(10)));
+        let key = ("user1".to_string(), "SOL".to_string());
+        assert_eq!(ledger.pending_token_totals().get(&key), Some(&dec!(10)));
+    }
+}
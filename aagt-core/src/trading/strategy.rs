@@ -5,7 +5,12 @@ use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
+use crate::agent::calendar::CalendarRegistry;
 use crate::error::Result;
+use crate::trading::history::{
+    strategy_version, ActionOutcome, ActionRecord, ConditionRecord, PipelineRun, RunHistory,
+    RunStatus, RunTrigger,
+};
 use crate::trading::pipeline::{self, Step, Context};
 use rust_decimal::Decimal;
 
@@ -35,6 +40,10 @@ pub enum Condition {
     },
     /// Manual trigger
     Manual,
+    /// Holds while the named trading calendar is open
+    WithinCalendar {
+        calendar: String,
+    },
     /// All conditions must be true
     And(Vec<Condition>),
     /// Any condition must be true
@@ -155,6 +164,14 @@ pub struct StepResult {
 pub trait ConditionEvaluator: Send + Sync {
     /// Evaluate if condition is met
     async fn evaluate(&self, condition: &Condition) -> Result<bool>;
+
+    /// Evaluate a condition and report the value it was judged on (e.g. the current price)
+    async fn evaluate_with_value(
+        &self,
+        condition: &Condition,
+    ) -> Result<(bool, Option<serde_json::Value>)> {
+        Ok((self.evaluate(condition).await?, None))
+    }
 }
 
 #[async_trait::async_trait]
@@ -163,22 +180,50 @@ pub trait ActionExecutor: Send + Sync {
     async fn execute(&self, action: &Action, context: &pipeline::Context) -> Result<String>;
 }
 
+/// Shared log of action outcomes for one run
+pub type ActionLog = Arc<parking_lot::Mutex<Vec<ActionRecord>>>;
+
 /// Adapter to run a strategy Action as a pipeline Step
 pub struct ActionStep {
     action: Action,
     executor: Arc<dyn ActionExecutor>,
+    recorder: Option<(usize, ActionLog)>,
 }
 
 impl ActionStep {
     pub fn new(action: Action, executor: Arc<dyn ActionExecutor>) -> Self {
-        Self { action, executor }
+        Self { action, executor, recorder: None }
+    }
+
+    /// Record this step's outcome (as action `index`) into `log`, including failures
+    pub fn with_recorder(mut self, index: usize, log: ActionLog) -> Self {
+        self.recorder = Some((index, log));
+        self
     }
 }
 
 #[async_trait::async_trait]
 impl Step for ActionStep {
     async fn execute(&self, ctx: &mut Context) -> anyhow::Result<()> {
-        let res = self.executor.execute(&self.action, ctx).await?;
+        let result = self.executor.execute(&self.action, ctx).await;
+
+        if let Some((index, log)) = &self.recorder {
+            let outcome = match &result {
+                Ok(res) => ActionOutcome::Executed { result: res.clone() },
+                Err(e) => ActionOutcome::from_error(e),
+            };
+            let mut log = log.lock();
+            // Only the last attempt counts when the step is retried
+            log.retain(|r| r.index != *index);
+            log.push(ActionRecord {
+                index: *index,
+                action: self.action.clone(),
+                outcome,
+                timestamp: chrono::Utc::now(),
+            });
+        }
+
+        let res = result?;
         ctx.log(format!("Action '{}' result: {}", self.name(), res));
         Ok(())
     }
@@ -476,6 +521,10 @@ pub struct StrategyEngine {
     store: Arc<dyn StrategyStore>,
     /// Shutdown signal receiver
     shutdown_rx: Option<mpsc::Receiver<()>>,
+    /// Run history, if runs should be recorded
+    history: Option<Arc<RunHistory>>,
+    /// Calendars for `WithinCalendar` conditions
+    calendars: Arc<CalendarRegistry>,
 }
 
 impl StrategyEngine {
@@ -490,6 +539,8 @@ impl StrategyEngine {
             executor,
             store,
             shutdown_rx: None,
+            history: None,
+            calendars: Arc::new(CalendarRegistry::default()),
         }
     }
     
@@ -506,6 +557,25 @@ impl StrategyEngine {
         self.shutdown_rx = Some(rx);
         self
     }
+
+    /// Evaluate `WithinCalendar` against `calendars` instead of the built-ins
+    ///
+    /// Share the scheduler's registry so jobs and conditions agree.
+    pub fn with_calendars(mut self, calendars: Arc<CalendarRegistry>) -> Self {
+        self.calendars = calendars;
+        self
+    }
+
+    /// Record every run into `history`
+    pub fn with_history(mut self, history: Arc<RunHistory>) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// The attached run history, if any
+    pub fn history(&self) -> Option<&Arc<RunHistory>> {
+        self.history.as_ref()
+    }
     
     /// Load all active strategies from store
     pub async fn load_active_strategies(&self) -> Result<Vec<Strategy>> {
@@ -523,19 +593,103 @@ impl StrategyEngine {
         self.store.delete(id).await
     }
 
-    /// Execute a pipeline with timeout and graceful shutdown
-    pub async fn execute_pipeline(
+    /// Evaluate a strategy's conditions and, if they hold, run its actions
+    ///
+    /// Condition failures, risk denials and action errors are reported in the
+    /// returned run's status rather than as errors. The run is recorded if a
+    /// history is attached.
+    pub async fn run_strategy(&self, strategy: &Strategy, trigger: RunTrigger) -> Result<PipelineRun> {
+        let run_id = uuid::Uuid::new_v4().to_string();
+        let started_at = chrono::Utc::now();
+
+        let mut conditions = Vec::new();
+        let (actions, status, error) = match self.evaluate_recorded(&strategy.condition, &mut conditions).await {
+            Ok(true) => {
+                let (actions, result) = self.run_actions(strategy, &run_id, &run_id).await;
+                let (status, error) = run_status(&actions, &result);
+                (actions, status, error)
+            }
+            Ok(false) => (Vec::new(), RunStatus::ConditionNotMet, None),
+            Err(e) => (Vec::new(), RunStatus::Failed, Some(format!("Condition evaluation failed: {}", e))),
+        };
+
+        let run = PipelineRun {
+            run_id,
+            strategy_id: strategy.id.clone(),
+            strategy_name: strategy.name.clone(),
+            strategy_version: strategy_version(strategy),
+            user_id: strategy.user_id.clone(),
+            trigger,
+            started_at,
+            finished_at: chrono::Utc::now(),
+            conditions,
+            actions,
+            status,
+            error,
+        };
+        if let Some(history) = &self.history {
+            history.record(run.clone()).await?;
+        }
+        Ok(run)
+    }
+
+    /// Evaluate a condition, recording every leaf with its measured value
+    ///
+    /// And/Or children are all evaluated (no short-circuit) so the record is complete.
+    fn evaluate_recorded<'a>(
+        &'a self,
+        condition: &'a Condition,
+        records: &'a mut Vec<ConditionRecord>,
+    ) -> futures::future::BoxFuture<'a, Result<bool>> {
+        Box::pin(async move {
+            match condition {
+                Condition::And(children) => {
+                    let mut all = true;
+                    for child in children {
+                        all &= self.evaluate_recorded(child, records).await?;
+                    }
+                    Ok(all)
+                }
+                Condition::Or(children) => {
+                    let mut any = false;
+                    for child in children {
+                        any |= self.evaluate_recorded(child, records).await?;
+                    }
+                    Ok(any)
+                }
+                Condition::WithinCalendar { calendar } => {
+                    // Evaluated here so every evaluator gets calendars for free
+                    let closed = self.calendars.closed_reason(calendar, chrono::Utc::now())?;
+                    records.push(ConditionRecord {
+                        condition: condition.clone(),
+                        result: closed.is_none(),
+                        measured: closed.clone().map(serde_json::Value::String),
+                    });
+                    Ok(closed.is_none())
+                }
+                leaf => {
+                    let (result, measured) = self.evaluator.evaluate_with_value(leaf).await?;
+                    records.push(ConditionRecord { condition: leaf.clone(), result, measured });
+                    Ok(result)
+                }
+            }
+        })
+    }
+
+    /// Run a strategy's actions, returning one record per action and the pipeline result
+    async fn run_actions(
         &self,
         strategy: &Strategy,
-        pipeline_id: String,
-    ) -> Result<Pipeline> {
-        let now = chrono::Utc::now().timestamp();
-        
+        run_id: &str,
+        pipeline_id: &str,
+    ) -> (Vec<ActionRecord>, anyhow::Result<Context>) {
+        let log: ActionLog = Arc::default();
+
         // 1. Build the generic pipeline
         let mut generic_pipeline = pipeline::Pipeline::new(&strategy.name);
-        
-        for action in &strategy.actions {
-            let step = ActionStep::new(action.clone(), self.executor.clone());
+        for (index, action) in strategy.actions.iter().enumerate() {
+            let step = ActionStep::new(action.clone(), self.executor.clone())
+                .with_recorder(index, log.clone());
             generic_pipeline = generic_pipeline.add_step(step);
         }
 
@@ -543,13 +697,80 @@ impl StrategyEngine {
         let mut ctx = Context::new(format!("Strategy execution: {}", strategy.name));
         ctx.set("user_id", strategy.user_id.clone());
         ctx.set("strategy_id", strategy.id.clone());
-        ctx.set("pipeline_id", pipeline_id.clone());
+        ctx.set("pipeline_id", pipeline_id.to_string());
+        ctx.set("run_id", run_id.to_string());
 
         // 3. Run (using shared logic from pipeline.rs)
-        let result_ctx = generic_pipeline.run_with_context(ctx).await
+        let result = generic_pipeline.run_with_context(ctx).await;
+
+        // 4. Anything not attempted was skipped
+        let mut records = std::mem::take(&mut *log.lock());
+        for (index, action) in strategy.actions.iter().enumerate() {
+            if !records.iter().any(|r| r.index == index) {
+                records.push(ActionRecord {
+                    index,
+                    action: action.clone(),
+                    outcome: ActionOutcome::Skipped,
+                    timestamp: chrono::Utc::now(),
+                });
+            }
+        }
+        records.sort_by_key(|r| r.index);
+        (records, result)
+    }
+
+    /// Execute a pipeline with timeout and graceful shutdown
+    ///
+    /// Conditions are not evaluated; the run is recorded as a manual trigger
+    /// with `pipeline_id` as its run ID.
+    pub async fn execute_pipeline(
+        &self,
+        strategy: &Strategy,
+        pipeline_id: String,
+    ) -> Result<Pipeline> {
+        let started_at = chrono::Utc::now();
+        let (actions, result) = self.run_actions(strategy, &pipeline_id, &pipeline_id).await;
+        let (status, error) = run_status(&actions, &result);
+
+        if let Some(history) = &self.history {
+            let run = PipelineRun {
+                run_id: pipeline_id.clone(),
+                strategy_id: strategy.id.clone(),
+                strategy_name: strategy.name.clone(),
+                strategy_version: strategy_version(strategy),
+                user_id: strategy.user_id.clone(),
+                trigger: RunTrigger::Manual,
+                started_at,
+                finished_at: chrono::Utc::now(),
+                conditions: Vec::new(),
+                actions: actions.clone(),
+                status,
+                error,
+            };
+            if let Err(e) = history.record(run).await {
+                tracing::warn!("Failed to record pipeline run {}: {}", pipeline_id, e);
+            }
+        }
+
+        let result_ctx = result
             .map_err(|e| crate::error::Error::Internal(format!("Pipeline execution failed: {}", e)))?;
 
-        // 4. Map back to Strategy-specific Pipeline record for compatibility
+        // Map back to Strategy-specific Pipeline record for compatibility
+        let step_results = actions
+            .iter()
+            .filter(|a| a.outcome != ActionOutcome::Skipped)
+            .map(|a| StepResult {
+                index: a.index,
+                action: a.action.clone(),
+                success: matches!(a.outcome, ActionOutcome::Executed { .. }),
+                message: match &a.outcome {
+                    ActionOutcome::Executed { result } => result.clone(),
+                    other => format!("{:?}", other),
+                },
+                timestamp: a.timestamp.timestamp(),
+            })
+            .collect();
+
         let pipeline = Pipeline {
             id: pipeline_id,
             strategy_id: strategy.id.clone(),
@@ -560,11 +781,26 @@ impl StrategyEngine {
                 PipelineStatus::Completed 
             },
             current_step: strategy.actions.len(), // Assume finished if generic pipeline finished
-            step_results: Vec::new(), // We could populate this from result_ctx.trace if needed
-            started_at: now,
+            step_results,
+            started_at: started_at.timestamp(),
             completed_at: Some(chrono::Utc::now().timestamp()),
         };
 
         Ok(pipeline)
     }
 }
+
+/// Final status of a run that reached its actions
+fn run_status(actions: &[ActionRecord], result: &anyhow::Result<Context>) -> (RunStatus, Option<String>) {
+    if let Some(reason) = actions.iter().find_map(|a| match &a.outcome {
+        ActionOutcome::RiskDenied { check, reason } => Some(format!("{}: {}", check, reason)),
+        _ => None,
+    }) {
+        return (RunStatus::RiskDenied, Some(reason));
+    }
+    match result {
+        Err(e) => (RunStatus::Failed, Some(e.to_string())),
+        Ok(ctx) if ctx.aborted => (RunStatus::Cancelled, ctx.outcome.clone()),
+        Ok(_) => (RunStatus::Completed, None),
+    }
+}
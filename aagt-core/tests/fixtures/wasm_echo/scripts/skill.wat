@@ -0,0 +1,32 @@
+;; Test skill for the allocate/call ABI: answers {"echo":<arguments>}
+;;
+;; skill.wasm is this file assembled to binary (e.g. `wat2wasm skill.wat`).
+(module
+  (memory (export "memory") 1)
+  (data (i32.const 0) "{\"echo\":")
+  (global $next (mut i32) (i32.const 1024))
+
+  ;; Bump allocator; memory grows as needed and is dropped with the instance
+  (func $allocate (export "allocate") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (local.get $ptr) (local.get $len)))
+    (if (i32.gt_u (global.get $next) (i32.mul (memory.size) (i32.const 65536)))
+      (then
+        (drop (memory.grow
+          (i32.add (i32.div_u (local.get $len) (i32.const 65536)) (i32.const 1))))))
+    (local.get $ptr))
+
+  (func (export "call") (param $ptr i32) (param $len i32) (result i64)
+    (local $out i32)
+    (local $out_len i32)
+    (local.set $out_len (i32.add (local.get $len) (i32.const 9)))
+    (local.set $out (call $allocate (local.get $out_len)))
+    (memory.copy (local.get $out) (i32.const 0) (i32.const 8))
+    (memory.copy (i32.add (local.get $out) (i32.const 8)) (local.get $ptr) (local.get $len))
+    (i32.store8
+      (i32.add (local.get $out) (i32.add (local.get $len) (i32.const 8)))
+      (i32.const 125))
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $out)) (i64.const 32))
+      (i64.extend_i32_u (local.get $out_len)))))
@@ -30,6 +30,7 @@ fn test_tool_definition() {
                 "symbol": {"type": "string"}
             }
         }),
+        ..Default::default()
     };
 
     assert_eq!(def.name, "get_price");
@@ -126,6 +127,7 @@ async fn test_risk_manager_basic_checks() {
         min_liquidity_usd: dec!(100000.0),
         enable_rug_detection: true,
         trade_cooldown_secs: 5,
+        ..Default::default()
     };
 
     let manager = RiskManager::with_config(config, Arc::new(InMemoryRiskStore)).await.unwrap();
@@ -37,13 +37,13 @@ Notes:
     loader.load_all().await?;
 
     // 4. Verify Skill Loaded
-    assert_eq!(loader.skills.len(), 1);
-    let skill = loader.skills.get("deployment-guide").expect("Skill not found");
+    assert_eq!(loader.len(), 1);
+    let skill = loader.get("deployment-guide").expect("Skill not found");
     // script should be None
     assert!(skill.metadata().script.is_none());
 
     // 5. Verify Context Injection
-    println!("Test: Calling inject() on loader with {} skills...", loader.skills.len());
+    println!("Test: Calling inject() on loader with {} skills...", loader.len());
     let messages = loader.inject()?;
     println!("Test: inject() returned {} messages", messages.len());
     assert_eq!(messages.len(), 1);
@@ -0,0 +1,68 @@
+//! Skills with missing dependencies are flagged at load time, not at call time
+
+use aagt_core::prelude::*;
+use aagt_core::skills::SkillHealth;
+
+/// Skills directory with a healthy bash skill and one needing a binary nobody has
+fn skills_dir() -> tempfile::TempDir {
+    let root = tempfile::tempdir().unwrap();
+    for (name, binaries) in [("quote", "[sh]"), ("chart", "[sh, aagt-no-such-binary]")] {
+        let dir = root.path().join(name);
+        std::fs::create_dir_all(dir.join("scripts")).unwrap();
+        std::fs::write(
+            dir.join("SKILL.md"),
+            format!(
+                "---\nname: {name}\ndescription: Render a {name}\nruntime: bash\nscript: run.sh\ndependencies:\n  binaries: {binaries}\n---\nRuns.\n"
+            ),
+        )
+        .unwrap();
+        std::fs::write(dir.join("scripts/run.sh"), "echo ok\n").unwrap();
+    }
+    root
+}
+
+#[tokio::test]
+async fn test_missing_binary_marks_skill_unavailable() {
+    let root = skills_dir();
+    let loader = SkillLoader::new(root.path());
+    loader.load_all().await.unwrap();
+
+    let health = loader.skills_health();
+    assert_eq!(health["quote"], SkillHealth::Healthy);
+    assert_eq!(
+        health["chart"],
+        SkillHealth::Unhealthy {
+            reasons: vec!["missing aagt-no-such-binary".to_string()]
+        }
+    );
+
+    // Still registered, but the model is told it can't be used and calls fail fast
+    let chart = loader.get("chart").unwrap();
+    assert!(!chart.health().is_available());
+    assert_eq!(
+        chart.definition().await.description,
+        "Render a chart (unavailable: missing aagt-no-such-binary)"
+    );
+    let err = chart.call("{}").await.unwrap_err();
+    assert!(err.to_string().contains("Skill unavailable"), "{}", err);
+    assert_eq!(
+        loader
+            .get("quote")
+            .unwrap()
+            .definition()
+            .await
+            .description,
+        "Render a quote"
+    );
+}
+
+#[tokio::test]
+async fn test_skip_unhealthy_leaves_skill_out() {
+    let root = skills_dir();
+    let loader = SkillLoader::new(root.path()).skip_unhealthy(true);
+    loader.load_all().await.unwrap();
+
+    assert!(loader.get("quote").is_some());
+    assert!(!loader.get("chart").is_some());
+    assert!(!loader.skills_health()["chart"].is_available());
+}
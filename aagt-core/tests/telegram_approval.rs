@@ -0,0 +1,210 @@
+//! Telegram approvals against a mocked Bot API: buttons, chat restrictions and timeouts
+#![cfg(feature = "telegram")]
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use aagt_core::agent::core::ApprovalHandler;
+use aagt_core::infra::TelegramApprovalHandler;
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Bot API stand-in: records every call and serves queued updates to `getUpdates`
+#[derive(Default)]
+struct MockBotApi {
+    calls: Mutex<Vec<(String, Value)>>,
+    updates: Mutex<VecDeque<Value>>,
+}
+
+impl MockBotApi {
+    async fn start() -> (Arc<Self>, String) {
+        let api = Arc::new(Self::default());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = api.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(server.clone().serve(stream));
+            }
+        });
+        (api, base)
+    }
+
+    /// Answer one request and close the connection
+    async fn serve(self: Arc<Self>, mut stream: TcpStream) {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        let header_end = loop {
+            let n = stream.read(&mut chunk).await.unwrap();
+            if n == 0 {
+                return;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+        let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+        let length: usize = head
+            .lines()
+            .find_map(|l| {
+                l.to_lowercase()
+                    .strip_prefix("content-length:")
+                    .map(|v| v.trim().parse().unwrap())
+            })
+            .unwrap_or(0);
+        while buf.len() < header_end + length {
+            let n = stream.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+        }
+        let path = head.split_whitespace().nth(1).unwrap_or_default();
+        let method = path.rsplit('/').next().unwrap_or_default().to_string();
+        let body: Value =
+            serde_json::from_slice(&buf[header_end..header_end + length]).unwrap_or(Value::Null);
+
+        let result = match method.as_str() {
+            "sendMessage" => json!({ "message_id": self.calls.lock().unwrap().len() + 1 }),
+            "getUpdates" => {
+                let updates: Vec<Value> = self.updates.lock().unwrap().drain(..).collect();
+                if updates.is_empty() {
+                    // A short long-poll keeps the test quick
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+                Value::Array(updates)
+            }
+            _ => json!(true),
+        };
+        self.calls.lock().unwrap().push((method, body));
+
+        let response = json!({ "ok": true, "result": result }).to_string();
+        let reply = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.len(),
+            response
+        );
+        let _ = stream.write_all(reply.as_bytes()).await;
+        let _ = stream.shutdown().await;
+    }
+
+    fn calls(&self, method: &str) -> Vec<Value> {
+        let calls = self.calls.lock().unwrap();
+        calls
+            .iter()
+            .filter(|(m, _)| m == method)
+            .map(|(_, b)| b.clone())
+            .collect()
+    }
+
+    /// Callback data of the `button` (0 approve, 1 deny) on the message about `tool`
+    fn button(&self, tool: &str, button: usize) -> Option<String> {
+        self.calls("sendMessage")
+            .into_iter()
+            .find(|m| m["text"].as_str().unwrap_or_default().contains(tool))
+            .map(|m| {
+                m["reply_markup"]["inline_keyboard"][0][button]["callback_data"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+    }
+
+    fn press(&self, update_id: i64, user: i64, data: &str) {
+        self.updates.lock().unwrap().push_back(json!({
+            "update_id": update_id,
+            "callback_query": {
+                "id": format!("q{}", update_id),
+                "from": { "id": user },
+                "message": { "message_id": 1, "chat": { "id": user } },
+                "data": data
+            }
+        }));
+    }
+}
+
+fn handler(base: &str) -> TelegramApprovalHandler {
+    TelegramApprovalHandler::new("TOKEN", "42")
+        .with_api_base(base)
+        .with_poll_timeout(0)
+        .allowed_chat_ids([42])
+}
+
+#[tokio::test]
+async fn test_concurrent_approvals_resolve_by_request_id() {
+    let (api, base) = MockBotApi::start().await;
+    let handler = Arc::new(handler(&base).with_timeout(Duration::from_secs(10)));
+
+    let buy = tokio::spawn({
+        let handler = handler.clone();
+        async move {
+            handler
+                .approve("place_order", r#"{"symbol":"SOL","qty":5}"#)
+                .await
+        }
+    });
+    let cancel = tokio::spawn({
+        let handler = handler.clone();
+        async move { handler.approve("cancel_all", "{}").await }
+    });
+
+    let (approve_buy, deny_cancel) = loop {
+        if let (Some(a), Some(d)) = (api.button("place_order", 0), api.button("cancel_all", 1)) {
+            break (a, d);
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+    let message = &api.calls("sendMessage")[0];
+    assert_eq!(message["chat_id"], "42");
+    assert!(message["text"]
+        .as_str()
+        .unwrap()
+        .contains("\"symbol\": \"SOL\""));
+
+    // A stranger's press is ignored; the owner's presses land on the right requests
+    api.press(1, 99, &approve_buy);
+    api.press(2, 42, &deny_cancel);
+    api.press(3, 42, &approve_buy);
+
+    assert!(buy.await.unwrap().unwrap());
+    assert!(!cancel.await.unwrap().unwrap());
+    assert_eq!(handler.pending(), 0);
+
+    // The approval resolves before the press is acknowledged
+    while api.calls("answerCallbackQuery").len() < 3 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let answers: Vec<String> = api
+        .calls("answerCallbackQuery")
+        .iter()
+        .map(|a| a["text"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        answers,
+        ["You are not allowed to approve this", "Denied", "Approved"]
+    );
+    assert_eq!(api.calls("editMessageReplyMarkup").len(), 2);
+}
+
+#[tokio::test]
+async fn test_unanswered_approval_is_denied_after_timeout() {
+    let (api, base) = MockBotApi::start().await;
+    let handler = handler(&base).with_timeout(Duration::from_millis(200));
+
+    let approved = handler.approve("place_order", "{}").await.unwrap();
+    assert!(!approved);
+    assert_eq!(handler.pending(), 0);
+    assert_eq!(api.calls("sendMessage").len(), 1);
+
+    // A late press finds nothing to resolve
+    let late = api.button("place_order", 0).unwrap();
+    let handler = handler.with_timeout(Duration::from_millis(300));
+    api.press(10, 42, &late);
+    assert!(!handler.approve("close_position", "{}").await.unwrap());
+    assert!(api
+        .calls("answerCallbackQuery")
+        .iter()
+        .any(|a| a["text"] == "This request has expired"));
+    // Later polls acknowledge the press so it isn't delivered again
+    assert!(api.calls("getUpdates").iter().any(|u| u["offset"] == 11));
+}
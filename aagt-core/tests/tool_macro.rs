@@ -0,0 +1,82 @@
+//! `#[tool]` expansion: definitions with and without `output =`, and typed results
+
+use aagt_core::error::Result;
+use aagt_core::skills::tool::{Tool, ToolSet};
+use aagt_macros::tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[tool(name = "echo", description = "Echo a message")]
+struct Echo;
+
+#[derive(Deserialize, JsonSchema)]
+struct EchoArgs {
+    /// Text to echo back
+    message: String,
+}
+
+impl Echo {
+    async fn execute(&self, args: EchoArgs) -> Result<String> {
+        Ok(args.message)
+    }
+}
+
+#[tool(name = "get_quote", description = "Quote a token", output = Quote)]
+struct GetQuote;
+
+#[derive(Deserialize, JsonSchema)]
+struct GetQuoteArgs {
+    /// Token symbol
+    symbol: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+struct Quote {
+    symbol: String,
+    price: f64,
+}
+
+impl GetQuote {
+    async fn execute(&self, args: GetQuoteArgs) -> Result<Quote> {
+        Ok(Quote {
+            symbol: args.symbol,
+            price: 185.5,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_output_schema_only_with_output_type() {
+    let echo = Echo.definition().await;
+    assert!(echo.output_schema.is_none());
+
+    let quote = GetQuote.definition().await;
+    let schema = quote.output_schema.expect("output = Quote sets an output schema");
+    assert_eq!(schema["title"], "Quote");
+    assert_eq!(schema["properties"]["symbol"]["type"], "string");
+    assert_eq!(schema["properties"]["price"]["type"], "number");
+    assert_eq!(quote.parameters["properties"]["symbol"]["type"], "string");
+
+    // The schema survives registration in a tool set
+    let mut tools = ToolSet::new();
+    tools.add(Echo).add(GetQuote);
+    let definitions = tools.definitions().await;
+    let by_name = |name: &str| definitions.iter().find(|d| d.name == name).unwrap();
+    assert!(by_name("echo").output_schema.is_none());
+    assert_eq!(by_name("get_quote").output_schema, Some(schema));
+}
+
+#[tokio::test]
+async fn test_structured_output_round_trips_through_call() {
+    assert_eq!(Echo.call(r#"{"message": "gm"}"#).await.unwrap(), "gm");
+
+    let text = GetQuote.call(r#"{"symbol": "SOL"}"#).await.unwrap();
+    let quote: Quote = serde_json::from_str(&text).unwrap();
+    assert_eq!(
+        quote,
+        Quote {
+            symbol: "SOL".to_string(),
+            price: 185.5,
+        }
+    );
+}
@@ -16,6 +16,7 @@ impl Tool for DangerousTool {
             name: "nuke_db".to_string(),
             description: "Delete everything".to_string(),
             parameters: json!({"type": "object"}),
+            ..Default::default()
         }
     }
     async fn call(&self, _args: &str) -> anyhow::Result<String> {
@@ -33,6 +34,7 @@ impl Tool for SafeTool {
             name: "read_db".to_string(),
             description: "Read data".to_string(),
             parameters: json!({"type": "object"}),
+            ..Default::default()
         }
     }
     async fn call(&self, _args: &str) -> anyhow::Result<String> {
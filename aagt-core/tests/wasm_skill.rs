@@ -0,0 +1,72 @@
+//! Wasm skills run through the allocate/call ABI without `bwrap`
+#![cfg(feature = "wasm")]
+
+use std::path::Path;
+
+use aagt_core::prelude::*;
+
+fn fixture() -> &'static Path {
+    Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/wasm_echo"
+    ))
+}
+
+#[tokio::test]
+async fn test_bundled_wasm_skill_round_trips_arguments() -> anyhow::Result<()> {
+    let loader = SkillLoader::new(fixture().parent().unwrap());
+    let skill = loader.load_skill(fixture()).await?;
+
+    // Unverified binary: the agent forces an approval before running it
+    let definition = skill.definition().await;
+    assert!(definition.is_binary && !definition.is_verified);
+
+    let output = skill.call(r#"{"message":"gm"}"#).await?;
+    let value: serde_json::Value = serde_json::from_str(&output)?;
+    assert_eq!(value, serde_json::json!({ "echo": { "message": "gm" } }));
+
+    let capped = skill.with_execution_config(SkillExecutionConfig {
+        max_output_bytes: 16,
+        ..Default::default()
+    });
+    let err = capped.call(r#"{"message":"gm"}"#).await.unwrap_err();
+    assert!(
+        err.to_string().contains("exceeds max_output_bytes (16)"),
+        "{}",
+        err
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_wasm_skill_is_interrupted_at_the_timeout() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let skill_dir = dir.path().join("spin");
+    std::fs::create_dir_all(skill_dir.join("scripts"))?;
+    std::fs::write(
+        skill_dir.join("SKILL.md"),
+        "---\nname: spin\ndescription: Never returns\nruntime: wasm\nscript: spin.wat\n---\n",
+    )?;
+    std::fs::write(
+        skill_dir.join("scripts/spin.wat"),
+        r#"(module
+             (memory (export "memory") 1)
+             (func (export "allocate") (param i32) (result i32) (i32.const 0))
+             (func (export "call") (param i32 i32) (result i64)
+               (loop $forever (br $forever))
+               (i64.const 0)))"#,
+    )?;
+
+    let skill = SkillLoader::new(dir.path())
+        .load_skill(&skill_dir)
+        .await?
+        .with_execution_config(SkillExecutionConfig {
+            timeout_secs: 1,
+            ..Default::default()
+        });
+    let started = std::time::Instant::now();
+    let err = skill.call("{}").await.unwrap_err();
+    assert!(err.to_string().contains("timed out after 1s"), "{}", err);
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    Ok(())
+}
@@ -16,7 +16,8 @@
 //!
 //! #[tool(
 //!     name = "get_token_price",
-//!     description = "Get the current price of a cryptocurrency token"
+//!     description = "Get the current price of a cryptocurrency token",
+//!     example = r#"{"description": "Price of SOL", "arguments": {"symbol": "SOL"}}"#
 //! )]
 //! struct GetTokenPrice;
 //!
@@ -32,17 +33,243 @@
 //!     }
 //! }
 //! ```
+//!
+//! The definition's `parameters_ts` is generated from the args type's schema,
+//! so the tool catalog shows the model a TypeScript interface.
+//!
+//! With `output = Type`, `execute` returns `Result<Type>` instead: the value is
+//! serialized to JSON for the model and `Type`'s schema becomes the
+//! definition's `output_schema`.
+//!
+//! ```ignore
+//! #[tool(name = "get_quote", description = "Quote a token", output = Quote)]
+//! struct GetQuote;
+//!
+//! #[derive(serde::Serialize, schemars::JsonSchema)]
+//! struct Quote {
+//!     symbol: String,
+//!     price: f64,
+//! }
+//!
+//! impl GetQuote {
+//!     async fn execute(&self, args: GetQuoteArgs) -> Result<Quote> {
+//!         Ok(Quote { symbol: args.symbol, price: 185.5 })
+//!     }
+//! }
+//! ```
+//!
+//! Tools that hold clients, keys or config can keep them as fields. With
+//! `derive_new = true` the macro adds `new(fields...)` and a `with_<field>`
+//! setter per field; tools with their own constructors leave it off. The
+//! handler can live in any impl block and be named with `handler = path`:
+//!
+//! ```ignore
+//! #[tool(name = "get_price", description = "Get a price", derive_new = true, handler = Self::fetch)]
+//! struct GetPrice {
+//!     client: reqwest::Client,
+//!     api_key: String,
+//! }
+//!
+//! impl GetPrice {
+//!     async fn fetch(&self, args: GetPriceArgs) -> Result<String> {
+//!         // ... self.client, self.api_key
+//!     }
+//! }
+//! ```
+//!
+//! Placed on an impl block instead, `#[tool]` reads the args type from the
+//! handler's signature, so no `StructNameArgs` naming is needed:
+//!
+//! ```ignore
+//! struct GetPrice { client: reqwest::Client }
+//!
+//! #[tool(name = "get_price", description = "Get a price")]
+//! impl GetPrice {
+//!     async fn execute(&self, args: PriceQuery) -> Result<String> {
+//!         // ...
+//!     }
+//! }
+//! ```
 
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::parse::{Parse, ParseStream};
-use syn::{parse_macro_input, DeriveInput, Ident, LitStr, Token};
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Fields, FnArg, Ident, ImplItem, Item, ItemImpl, LitBool,
+    LitStr, Path, Token, Type,
+};
 
 /// Arguments for the `#[tool]` attribute
 struct ToolArgs {
     name: String,
     description: String,
-    args_type: Option<String>,
+    args_type: Option<Ident>,
+    output_type: Option<String>,
+    examples: Vec<ExampleSpec>,
+    result_projection: Option<Vec<String>>,
+    required_secrets: Vec<String>,
+    /// Generate `new` and `with_*` for the struct's fields
+    derive_new: bool,
+    /// Function called as `handler(self, args)` instead of `self.execute(args)`
+    handler: Option<Path>,
+    /// Coerce loosely typed arguments toward the schema before parsing
+    coerce: bool,
+    /// Span of the attribute, for errors about the whole tool
+    span: proc_macro2::Span,
+}
+
+/// A usage example parsed from `example = r#"{...}"#`
+#[derive(Debug)]
+struct ExampleSpec {
+    description: String,
+    arguments: String,
+    result_summary: Option<String>,
+}
+
+/// Parse an example attribute value at compile time so malformed JSON fails the build
+fn parse_example(lit: &LitStr) -> syn::Result<ExampleSpec> {
+    let value: serde_json::Value = serde_json::from_str(&lit.value())
+        .map_err(|e| syn::Error::new(lit.span(), format!("example is not valid JSON: {}", e)))?;
+
+    let description = value
+        .get("description")
+        .and_then(|d| d.as_str())
+        .ok_or_else(|| syn::Error::new(lit.span(), "example is missing a 'description' string"))?
+        .to_string();
+    let arguments = value
+        .get("arguments")
+        .filter(|a| a.is_object())
+        .ok_or_else(|| syn::Error::new(lit.span(), "example is missing an 'arguments' object"))?
+        .to_string();
+    let result_summary = value
+        .get("result_summary")
+        .and_then(|r| r.as_str())
+        .map(|r| r.to_string());
+
+    Ok(ExampleSpec {
+        description,
+        arguments,
+        result_summary,
+    })
+}
+
+/// Generate the `examples` vector for a ToolDefinition
+fn examples_tokens(examples: &[ExampleSpec]) -> proc_macro2::TokenStream {
+    let items = examples.iter().map(|e| {
+        let description = &e.description;
+        let arguments = &e.arguments;
+        let result_summary = match &e.result_summary {
+            Some(r) => quote! { Some(#r.to_string()) },
+            None => quote! { None },
+        };
+        quote! {
+            aagt_core::skills::tool::ToolExample {
+                description: #description.to_string(),
+                // Validated as JSON when the macro expanded
+                arguments: serde_json::from_str(#arguments).unwrap_or_default(),
+                result_summary: #result_summary,
+            }
+        }
+    });
+    quote! { vec![#(#items),*] }
+}
+
+/// Parse a comma-separated attribute like `result_projection = "field_a, field_b"`
+fn parse_list(lit: &LitStr, attr: &str) -> syn::Result<Vec<String>> {
+    let fields: Vec<String> = lit
+        .value()
+        .split(',')
+        .map(|f| f.trim().to_string())
+        .filter(|f| !f.is_empty())
+        .collect();
+    if fields.is_empty() {
+        return Err(syn::Error::new(lit.span(), format!("{} must list at least one field", attr)));
+    }
+    Ok(fields)
+}
+
+/// Generate the `required_secrets` value for a ToolDefinition
+fn secrets_tokens(keys: &[String]) -> proc_macro2::TokenStream {
+    quote! { vec![#(#keys.to_string()),*] }
+}
+
+/// Generate the `output_schema` value for a ToolDefinition
+fn output_schema_tokens(output_type: &Option<String>) -> proc_macro2::TokenStream {
+    match output_type {
+        Some(output_type) => {
+            let output_type = format_ident!("{}", output_type);
+            quote! {
+                serde_json::to_value(
+                    schemars::gen::SchemaSettings::openapi3()
+                        .into_generator()
+                        .into_root_schema_for::<#output_type>(),
+                )
+                .ok()
+            }
+        }
+        None => quote! { None },
+    }
+}
+
+/// Generate `Tool::call`, serializing structured outputs to JSON, plus the
+/// `coerce_arguments` override when coercion is off
+fn call_tokens(
+    tool_name: &str,
+    args_type: &Type,
+    output_type: &Option<String>,
+    handler: &Option<Path>,
+    coerce: bool,
+) -> proc_macro2::TokenStream {
+    let invoke = match handler {
+        Some(handler) => quote! { #handler(self, args) },
+        None => quote! { self.execute(args) },
+    };
+    let execute = match output_type {
+        Some(output_type) => {
+            let output_type = format_ident!("{}", output_type);
+            quote! {
+                let output: #output_type = #invoke
+                    .await
+                    .map_err(|e| -> aagt_core::anyhow::Error { e.into() })?;
+                Ok(serde_json::to_string(&output)?)
+            }
+        }
+        None => quote! {
+            #invoke.await
+                .map_err(|e| e.into())
+        },
+    };
+    let (parse, coerce_override) = if coerce {
+        (quote! { parse_args_coerced }, quote! {})
+    } else {
+        (
+            quote! { parse_args },
+            quote! {
+                fn coerce_arguments(&self) -> bool {
+                    false
+                }
+            },
+        )
+    };
+    quote! {
+        async fn call(&self, arguments: &str) -> aagt_core::anyhow::Result<String> {
+            let args: #args_type =
+                aagt_core::skills::tool::args::#parse(#tool_name, arguments)?;
+
+            #execute
+        }
+
+        #coerce_override
+    }
+}
+
+/// Generate the `result_projection` value for a ToolDefinition
+fn projection_tokens(fields: &Option<Vec<String>>) -> proc_macro2::TokenStream {
+    match fields {
+        Some(fields) => quote! { Some(vec![#(#fields.to_string()),*]) },
+        None => quote! { None },
+    }
 }
 
 impl Parse for ToolArgs {
@@ -50,6 +277,14 @@ impl Parse for ToolArgs {
         let mut name = None;
         let mut description = None;
         let mut args_type = None;
+        let mut output_type = None;
+        let mut examples = Vec::new();
+        let mut result_projection = None;
+        let mut required_secrets = Vec::new();
+        let mut derive_new = false;
+        let mut handler = None;
+        let mut coerce = true;
+        let span = input.span();
 
         while !input.is_empty() {
             let key: Ident = input.parse()?;
@@ -65,8 +300,34 @@ impl Parse for ToolArgs {
                     description = Some(value.value());
                 }
                 "args" => {
+                    args_type = Some(input.parse()?);
+                }
+                "output" => {
                     let value: Ident = input.parse()?;
-                    args_type = Some(value.to_string());
+                    output_type = Some(value.to_string());
+                }
+                "example" => {
+                    let value: LitStr = input.parse()?;
+                    examples.push(parse_example(&value)?);
+                }
+                "result_projection" => {
+                    let value: LitStr = input.parse()?;
+                    result_projection = Some(parse_list(&value, "result_projection")?);
+                }
+                "secrets" => {
+                    let value: LitStr = input.parse()?;
+                    required_secrets = parse_list(&value, "secrets")?;
+                }
+                "derive_new" => {
+                    let value: LitBool = input.parse()?;
+                    derive_new = value.value;
+                }
+                "handler" => {
+                    handler = Some(input.parse()?);
+                }
+                "coerce" => {
+                    let value: LitBool = input.parse()?;
+                    coerce = value.value;
                 }
                 _ => {
                     return Err(syn::Error::new(key.span(), "unknown attribute"));
@@ -83,7 +344,110 @@ impl Parse for ToolArgs {
             description: description
                 .ok_or_else(|| syn::Error::new(input.span(), "missing 'description'"))?,
             args_type,
+            output_type,
+            examples,
+            result_projection,
+            required_secrets,
+            derive_new,
+            handler,
+            coerce,
+            span,
+        })
+    }
+}
+
+/// `new(fields...)` and `with_<field>` setters for a struct with named fields
+fn constructor_tokens(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "derive_new needs a struct with named fields",
+                ))
+            }
+        },
+        _ => return Err(syn::Error::new(input.ident.span(), "derive_new needs a struct")),
+    };
+    let struct_name = &input.ident;
+    let vis = &input.vis;
+    let names: Vec<_> = fields.iter().filter_map(|f| f.ident.as_ref()).collect();
+    let types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let setters = names.iter().zip(&types).map(|(name, ty)| {
+        let setter = format_ident!("with_{}", name);
+        let doc = format!("Replace `{}`", name);
+        quote! {
+            #[doc = #doc]
+            #vis fn #setter(mut self, #name: #ty) -> Self {
+                self.#name = #name;
+                self
+            }
+        }
+    });
+    let doc = format!("Create a `{}` from its fields", struct_name);
+
+    Ok(quote! {
+        #[allow(dead_code)]
+        impl #struct_name {
+            #[doc = #doc]
+            #[allow(clippy::too_many_arguments)]
+            #vis fn new(#(#names: #types),*) -> Self {
+                Self { #(#names),* }
+            }
+
+            #(#setters)*
+        }
+    })
+}
+
+/// Name of a type as shown to the model, e.g. `PriceQuery` for `args::PriceQuery`
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default(),
+        _ => quote!(#ty).to_string(),
+    }
+}
+
+/// Args type of the handler method in an impl block
+fn handler_args_type(item: &ItemImpl, args: &ToolArgs) -> syn::Result<Type> {
+    let method = match &args.handler {
+        // Handlers defined elsewhere can't be inspected
+        Some(handler) if handler.segments.len() > 1 && !handler.segments[0].ident.eq("Self") => {
+            return Err(syn::Error::new(
+                handler.span(),
+                "name the args type with `args = ...` when the handler is outside this impl",
+            ))
+        }
+        Some(handler) => handler.segments.last().map(|s| s.ident.clone()),
+        None => None,
+    }
+    .unwrap_or_else(|| format_ident!("execute"));
+
+    let signature = item
+        .items
+        .iter()
+        .find_map(|item| match item {
+            ImplItem::Fn(f) if f.sig.ident == method => Some(&f.sig),
+            _ => None,
         })
+        .ok_or_else(|| {
+            syn::Error::new(
+                args.span,
+                format!("no `{}` method in this impl; name the handler with `handler = Self::...`", method),
+            )
+        })?;
+    match signature.inputs.iter().nth(1) {
+        Some(FnArg::Typed(arg)) if signature.inputs.len() == 2 => Ok((*arg.ty).clone()),
+        _ => Err(syn::Error::new(
+            signature.span(),
+            format!("`{}` must take `&self` and one args value", method),
+        )),
     }
 }
 
@@ -93,7 +457,22 @@ impl Parse for ToolArgs {
 ///
 /// * `name` - The tool name (used by LLM)
 /// * `description` - Description for the LLM
-/// * `args` - (Optional) The arguments struct type name
+/// * `args` - (Optional) The arguments struct type name (default: `StructNameArgs`,
+///   or the handler's parameter type when placed on an impl block)
+/// * `output` - (Optional) Type `execute` returns; serialized to JSON, and its
+///   schema becomes the definition's `output_schema`
+/// * `handler` - (Optional) Function called as `handler(self, args)` instead of
+///   `self.execute(args)`, e.g. `Self::fetch`
+/// * `derive_new` - (Optional) `true` generates `new(fields...)` and a
+///   `with_<field>` setter per field
+/// * `example` - (Optional, repeatable) JSON usage example:
+///   `{"description": "...", "arguments": {...}, "result_summary": "..."}`
+/// * `result_projection` - (Optional) Comma-separated fields kept when a large
+///   JSON result is reduced, e.g. `"symbol, price"`
+/// * `secrets` - (Optional) Comma-separated secret keys the tool resolves at
+///   call time, e.g. `"BIRDEYE_API_KEY"`
+/// * `coerce` - (Optional, default `true`) `false` parses arguments strictly
+///   instead of first running `coerce_args` (`"100"` → `100` and the like)
 ///
 /// # Example
 ///
@@ -103,31 +482,87 @@ impl Parse for ToolArgs {
 ///     // ... fields
 /// }
 /// ```
+///
+/// On an impl block, the args type is read from the handler's signature:
+///
+/// ```ignore
+/// #[tool(name = "swap_tokens", description = "Swap cryptocurrency tokens")]
+/// impl SwapTokens {
+///     async fn execute(&self, args: SwapRequest) -> Result<String> { ... }
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
     let args = parse_macro_input!(attr as ToolArgs);
-    let input = parse_macro_input!(item as DeriveInput);
+    let item = parse_macro_input!(item as Item);
+
+    match expand_tool(&args, item) {
+        Ok(expanded) => TokenStream::from(expanded),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand_tool(args: &ToolArgs, item: Item) -> syn::Result<proc_macro2::TokenStream> {
+    let (self_ty, args_type, extra): (Box<Type>, Type, _) = match item {
+        Item::Impl(item) => {
+            if args.derive_new {
+                return Err(syn::Error::new(
+                    args.span,
+                    "derive_new goes on the tool struct, not an impl block",
+                ));
+            }
+            let args_type = match &args.args_type {
+                Some(name) => syn::parse_quote!(#name),
+                None => handler_args_type(&item, args)?,
+            };
+            (item.self_ty.clone(), args_type, quote! { #item })
+        }
+        item => {
+            let input: DeriveInput = syn::parse2(quote! { #item })?;
+            let struct_name = &input.ident;
+            // Spanned at the struct, so a missing `StructNameArgs` is reported there
+            // rather than inside the generated impl
+            let args_type = args.args_type.clone().unwrap_or_else(|| {
+                Ident::new(&format!("{}Args", struct_name), struct_name.span())
+            });
+            let constructor = if args.derive_new {
+                constructor_tokens(&input)?
+            } else {
+                quote! {}
+            };
+            (
+                syn::parse_quote!(#struct_name),
+                syn::parse_quote!(#args_type),
+                quote! { #input #constructor },
+            )
+        }
+    };
 
-    let struct_name = &input.ident;
     let tool_name = &args.name;
     let tool_description = &args.description;
+    let args_type_name = type_name(&args_type);
+    let examples = examples_tokens(&args.examples);
+    let result_projection = projection_tokens(&args.result_projection);
+    let required_secrets = secrets_tokens(&args.required_secrets);
+    let output_schema = output_schema_tokens(&args.output_type);
+    let call = call_tokens(
+        tool_name,
+        &args_type,
+        &args.output_type,
+        &args.handler,
+        args.coerce,
+    );
 
-    // Default args type is StructNameArgs
-    let args_type_name = args
-        .args_type
-        .unwrap_or_else(|| format!("{}Args", struct_name));
-    let args_type = format_ident!("{}", args_type_name);
-
-    let expanded = quote! {
-        #input
+    Ok(quote! {
+        #extra
 
         #[async_trait::async_trait]
-        impl aagt_core::tool::Tool for #struct_name {
-            fn name(&self) -> &str {
-                #tool_name
+        impl aagt_core::skills::tool::Tool for #self_ty {
+            fn name(&self) -> String {
+                #tool_name.to_string()
             }
 
-            fn definition(&self) -> aagt_core::tool::ToolDefinition {
+            async fn definition(&self) -> aagt_core::skills::tool::ToolDefinition {
                 let gen = schemars::gen::SchemaSettings::openapi3().into_generator();
                 let schema = gen.into_root_schema_for::<#args_type>();
                 let schema_json = serde_json::to_value(schema).unwrap_or(serde_json::json!({
@@ -135,29 +570,26 @@ pub fn tool(attr: TokenStream, item: TokenStream) -> TokenStream {
                     "properties": {},
                     "required": []
                 }));
+                let parameters_ts =
+                    aagt_core::skills::tool::schema::typescript_interface(#args_type_name, &schema_json);
 
-                aagt_core::tool::ToolDefinition {
+                aagt_core::skills::tool::ToolDefinition {
                     name: #tool_name.to_string(),
                     description: #tool_description.to_string(),
                     parameters: schema_json,
-                    parameters_ts: None, // TODO: Implement TS generation from schema
+                    parameters_ts: Some(parameters_ts),
+                    is_verified: true,
+                    examples: #examples,
+                    result_projection: #result_projection,
+                    required_secrets: #required_secrets,
+                    output_schema: #output_schema,
+                    ..::core::default::Default::default()
                 }
             }
 
-            async fn call(&self, arguments: &str) -> aagt_core::anyhow::Result<String> {
-                let args: #args_type = serde_json::from_str(arguments)
-                    .map_err(|e| aagt_core::error::Error::ToolArguments {
-                        tool_name: #tool_name.to_string(),
-                        message: e.to_string(),
-                    })?;
-
-                self.execute(args).await
-                    .map_err(|e| e.into())
-            }
+            #call
         }
-    };
-
-    TokenStream::from(expanded)
+    })
 }
 
 /// Derive macro for generating Tool implementation with JSON schema.
@@ -187,34 +619,64 @@ pub fn derive_tool(input: TokenStream) -> TokenStream {
     // Parse attributes to find tool(name = "...", description = "...")
     let mut tool_name = None;
     let mut tool_description = None;
+    let mut output_type = None;
+    let mut examples = Vec::new();
+    let mut result_projection = None;
+    let mut required_secrets = Vec::new();
+    let mut coerce = true;
 
     for attr in &input.attrs {
         if attr.path().is_ident("tool") {
-            let _ = attr.parse_nested_meta(|meta| {
+            let parsed = attr.parse_nested_meta(|meta| {
                 if meta.path.is_ident("name") {
                     let value: LitStr = meta.value()?.parse()?;
                     tool_name = Some(value.value());
                 } else if meta.path.is_ident("description") {
                     let value: LitStr = meta.value()?.parse()?;
                     tool_description = Some(value.value());
+                } else if meta.path.is_ident("output") {
+                    let value: Ident = meta.value()?.parse()?;
+                    output_type = Some(value.to_string());
+                } else if meta.path.is_ident("example") {
+                    let value: LitStr = meta.value()?.parse()?;
+                    examples.push(parse_example(&value)?);
+                } else if meta.path.is_ident("result_projection") {
+                    let value: LitStr = meta.value()?.parse()?;
+                    result_projection = Some(parse_list(&value, "result_projection")?);
+                } else if meta.path.is_ident("secrets") {
+                    let value: LitStr = meta.value()?.parse()?;
+                    required_secrets = parse_list(&value, "secrets")?;
+                } else if meta.path.is_ident("coerce") {
+                    let value: LitBool = meta.value()?.parse()?;
+                    coerce = value.value;
                 }
                 Ok(())
             });
+            if let Err(e) = parsed {
+                return e.to_compile_error().into();
+            }
         }
     }
 
     let name = tool_name.unwrap_or_else(|| struct_name.to_string().to_lowercase());
     let description = tool_description.unwrap_or_else(|| format!("Tool: {}", struct_name));
-    let args_type = format_ident!("{}Args", struct_name);
+    let args_type_name = format!("{}Args", struct_name);
+    let args_ident = Ident::new(&args_type_name, struct_name.span());
+    let args_type: Type = syn::parse_quote!(#args_ident);
+    let examples = examples_tokens(&examples);
+    let result_projection = projection_tokens(&result_projection);
+    let required_secrets = secrets_tokens(&required_secrets);
+    let output_schema = output_schema_tokens(&output_type);
+    let call = call_tokens(&name, &args_type, &output_type, &None, coerce);
 
     let expanded = quote! {
         #[async_trait::async_trait]
-        impl aagt_core::tool::Tool for #struct_name {
-            fn name(&self) -> &str {
-                #name
+        impl aagt_core::skills::tool::Tool for #struct_name {
+            fn name(&self) -> String {
+                #name.to_string()
             }
 
-            fn definition(&self) -> aagt_core::tool::ToolDefinition {
+            async fn definition(&self) -> aagt_core::skills::tool::ToolDefinition {
                 let gen = schemars::gen::SchemaSettings::openapi3().into_generator();
                 let schema = gen.into_root_schema_for::<#args_type>();
                 let schema_json = serde_json::to_value(schema).unwrap_or(serde_json::json!({
@@ -222,27 +684,95 @@ pub fn derive_tool(input: TokenStream) -> TokenStream {
                     "properties": {},
                     "required": []
                 }));
+                let parameters_ts =
+                    aagt_core::skills::tool::schema::typescript_interface(#args_type_name, &schema_json);
 
-                aagt_core::tool::ToolDefinition {
+                aagt_core::skills::tool::ToolDefinition {
                     name: #name.to_string(),
                     description: #description.to_string(),
                     parameters: schema_json,
-                    parameters_ts: None,
+                    parameters_ts: Some(parameters_ts),
+                    is_verified: true,
+                    examples: #examples,
+                    result_projection: #result_projection,
+                    required_secrets: #required_secrets,
+                    output_schema: #output_schema,
+                    ..::core::default::Default::default()
                 }
             }
 
-            async fn call(&self, arguments: &str) -> aagt_core::anyhow::Result<String> {
-                let args: #args_type = serde_json::from_str(arguments)
-                    .map_err(|e| aagt_core::error::Error::ToolArguments {
-                        tool_name: #name.to_string(),
-                        message: e.to_string(),
-                    })?;
-
-                self.execute(args).await
-                    .map_err(|e| e.into())
-            }
+            #call
         }
     };
 
     TokenStream::from(expanded)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_args_with_examples() {
+        let args: ToolArgs = syn::parse_str(
+            r##"name = "get_price", description = "Get a price",
+                example = r#"{"description": "SOL price", "arguments": {"symbol": "SOL"}, "result_summary": "USD price"}"#,
+                example = r#"{"description": "ETH price", "arguments": {"symbol": "ETH"}}"#"##,
+        )
+        .unwrap();
+
+        assert_eq!(args.name, "get_price");
+        assert_eq!(args.examples.len(), 2);
+        assert_eq!(args.examples[0].description, "SOL price");
+        assert_eq!(args.examples[0].arguments, r#"{"symbol":"SOL"}"#);
+        assert_eq!(args.examples[0].result_summary.as_deref(), Some("USD price"));
+        assert!(args.examples[1].result_summary.is_none());
+    }
+
+    #[test]
+    fn test_parse_output_type() {
+        let args: ToolArgs =
+            syn::parse_str(r#"name = "get_quote", description = "Quote", output = Quote"#).unwrap();
+        assert_eq!(args.output_type.as_deref(), Some("Quote"));
+        assert!(args.args_type.is_none());
+        assert!(args.coerce);
+
+        let strict: ToolArgs =
+            syn::parse_str(r#"name = "get_quote", description = "Quote", coerce = false"#).unwrap();
+        assert!(!strict.coerce);
+    }
+
+    #[test]
+    fn test_impl_block_tool_reads_args_from_the_handler() {
+        let args: ToolArgs = syn::parse_str(
+            r#"name = "count", description = "Count", handler = Self::run"#,
+        )
+        .unwrap();
+        assert!(!args.derive_new);
+        let item: ItemImpl = syn::parse_str(
+            "impl Counter { async fn run(&self, args: query::CountRequest) -> Result<String> { todo!() } }",
+        )
+        .unwrap();
+        let args_type = handler_args_type(&item, &args).unwrap();
+        assert_eq!(type_name(&args_type), "CountRequest");
+
+        let missing: ToolArgs =
+            syn::parse_str(r#"name = "count", description = "Count", derive_new = true"#).unwrap();
+        assert!(missing.derive_new);
+        let err = handler_args_type(&item, &missing).unwrap_err();
+        assert!(err.to_string().contains("no `execute` method"));
+    }
+
+    #[test]
+    fn test_parse_invalid_example() {
+        let bad_json = syn::parse_str::<ToolArgs>(
+            r##"name = "t", description = "d", example = r#"{not json"#"##,
+        );
+        assert!(bad_json.is_err());
+
+        let missing_args = syn::parse_str::<ToolArgs>(
+            r##"name = "t", description = "d", example = r#"{"description": "x"}"#"##,
+        );
+        assert!(missing_args.err().unwrap().to_string().contains("arguments"));
+    }
+}
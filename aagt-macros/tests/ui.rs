@@ -0,0 +1,8 @@
+//! Expansion of `#[tool]` on methods and impl blocks, and its misuse errors
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/pass/*.rs");
+    t.compile_fail("tests/ui/fail/*.rs");
+}
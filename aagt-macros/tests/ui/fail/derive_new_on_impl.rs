@@ -0,0 +1,17 @@
+use aagt_macros::tool;
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct Query {
+    symbol: String,
+}
+
+struct GetPrice;
+
+#[tool(name = "get_price", description = "Get a price", derive_new = true)]
+impl GetPrice {
+    async fn execute(&self, args: Query) -> aagt_core::error::Result<String> {
+        Ok(args.symbol)
+    }
+}
+
+fn main() {}
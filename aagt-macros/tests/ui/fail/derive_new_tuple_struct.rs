@@ -0,0 +1,18 @@
+use aagt_core::error::Result;
+use aagt_macros::tool;
+
+#[tool(name = "get_price", description = "Get a price", derive_new = true)]
+struct GetPrice(String);
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct GetPriceArgs {
+    symbol: String,
+}
+
+impl GetPrice {
+    async fn execute(&self, args: GetPriceArgs) -> Result<String> {
+        Ok(args.symbol)
+    }
+}
+
+fn main() {}
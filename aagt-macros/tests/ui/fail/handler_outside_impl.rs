@@ -0,0 +1,23 @@
+use aagt_macros::tool;
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct Query {
+    symbol: String,
+}
+
+struct GetPrice;
+
+mod handlers {
+    pub async fn fetch(_tool: &super::GetPrice, args: super::Query) -> aagt_core::error::Result<String> {
+        Ok(args.symbol)
+    }
+}
+
+#[tool(name = "get_price", description = "Get a price", handler = handlers::fetch)]
+impl GetPrice {
+    async fn execute(&self, args: Query) -> aagt_core::error::Result<String> {
+        Ok(args.symbol)
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use aagt_macros::tool;
+
+struct GetPrice;
+
+#[tool(name = "get_price", description = "Get a price")]
+impl GetPrice {
+    async fn execute(&self, symbol: String, venue: String) -> aagt_core::error::Result<String> {
+        Ok(format!("{}@{}", symbol, venue))
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use aagt_core::error::Result;
+use aagt_macros::tool;
+
+#[tool(name = "get_price", description = "Get a price")]
+struct GetPrice;
+
+impl GetPrice {
+    async fn execute(&self, _args: ()) -> Result<String> {
+        Ok(String::new())
+    }
+}
+
+fn main() {}
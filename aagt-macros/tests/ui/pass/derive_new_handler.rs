@@ -0,0 +1,30 @@
+use aagt_core::error::Result;
+use aagt_core::skills::tool::Tool;
+use aagt_macros::tool;
+
+#[tool(name = "get_price", description = "Get a price", derive_new = true, handler = Self::fetch)]
+struct GetPrice {
+    base_url: String,
+    api_key: String,
+}
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct GetPriceArgs {
+    symbol: String,
+}
+
+impl GetPrice {
+    async fn fetch(&self, args: GetPriceArgs) -> Result<String> {
+        Ok(format!("{}/{}?key={}", self.base_url, args.symbol, self.api_key))
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let tool = GetPrice::new("https://api".to_string(), "k1".to_string())
+        .with_api_key("k2".to_string());
+    assert_eq!(
+        tool.call(r#"{"symbol": "SOL"}"#).await.unwrap(),
+        "https://api/SOL?key=k2"
+    );
+}
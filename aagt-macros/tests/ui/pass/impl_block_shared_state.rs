@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use aagt_core::error::Result;
+use aagt_core::skills::tool::Tool;
+use aagt_macros::tool;
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct CountRequest {
+    by: usize,
+}
+
+/// Several tool instances share one counter
+struct Counter {
+    total: Arc<AtomicUsize>,
+}
+
+#[tool(name = "count", description = "Add to a shared counter")]
+impl Counter {
+    async fn execute(&self, args: CountRequest) -> Result<String> {
+        let total = self.total.fetch_add(args.by, Ordering::SeqCst) + args.by;
+        Ok(total.to_string())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let total = Arc::new(AtomicUsize::new(0));
+    let a = Counter { total: total.clone() };
+    let b = Counter { total: total.clone() };
+    assert_eq!(a.call(r#"{"by": 2}"#).await.unwrap(), "2");
+    assert_eq!(b.call(r#"{"by": 3}"#).await.unwrap(), "5");
+    assert!(a.definition().await.parameters_ts.unwrap().contains("CountRequest"));
+}
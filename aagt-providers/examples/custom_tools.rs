@@ -36,6 +36,7 @@ impl Tool for GetWeather {
                 },
                 "required": ["city"]
             }),
+            ..Default::default()
         }
     }
 
@@ -77,6 +78,7 @@ impl Tool for Calculate {
                 },
                 "required": ["expression"]
             }),
+            ..Default::default()
         }
     }
 
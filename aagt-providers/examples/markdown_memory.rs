@@ -41,6 +41,7 @@ impl Tool for WriteNote {
                 },
                 "required": ["filename", "content"]
             }),
+            ..Default::default()
         }
     }
 
@@ -38,6 +38,7 @@ impl Tool for RegisterMoltbook {
                 },
                 "required": ["name", "description"]
             }),
+            ..Default::default()
         }
     }
 
@@ -81,6 +82,7 @@ impl Tool for CreatePost {
                 },
                 "required": ["title", "content"]
             }),
+            ..Default::default()
         }
     }
 
@@ -124,6 +126,7 @@ impl Tool for GetFeed {
                 },
                 "required": []
             }),
+            ..Default::default()
         }
     }
 
@@ -176,6 +179,7 @@ impl Tool for UpvotePost {
                 },
                 "required": ["post_id"]
             }),
+            ..Default::default()
         }
     }
 
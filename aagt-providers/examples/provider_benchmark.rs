@@ -63,7 +63,7 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn test_provider<P: Provider>(
+async fn test_provider<P: Provider + 'static>(
     name: &str,
     provider: P,
     model: &str,
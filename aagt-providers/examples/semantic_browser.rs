@@ -84,6 +84,7 @@ impl Tool for BrowserTool {
                 },
                 "required": ["url"]
             }),
+            ..Default::default()
         }
     }
 
@@ -277,7 +277,7 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn test_provider<P: Provider>(
+async fn test_provider<P: Provider + 'static>(
     name: &str,
     provider_result: Result<P>,
     model: &str,
@@ -0,0 +1,517 @@
+//! Building blocks of the trading copilot
+//!
+//! Shared by the terminal REPL in `main.rs` and the headless test in
+//! `tests/trading_copilot.rs`, so both run exactly the same wiring:
+//! spec -> agent with QMD memory tools, dynamic skills, a risk-checked
+//! `place_order` tool behind approval, and sessions persisted in QMD.
+
+// The binary and the test each use part of this module
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use aagt_core::agent::core::{
+    AgentEvent, ApprovalRequest, ChannelApprovalHandler, RiskyToolPolicy, ToolPolicy,
+};
+use aagt_core::agent::memory::Memory;
+use aagt_core::agent::provider::ChatRequest;
+use aagt_core::agent::system_prompt::SystemPrompt;
+use aagt_core::prelude::*;
+use aagt_core::trading::risk::FileRiskStore;
+use aagt_providers::mock::{MockProvider, MockTurn};
+use aagt_qmd::{Collection, QmdMemory, QmdStore};
+use anyhow::Context as _;
+use async_trait::async_trait;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::{broadcast, mpsc};
+
+/// Collection the bundled knowledge base is indexed into
+pub const PLAYBOOK: &str = "playbook";
+
+/// Spec the copilot uses when none is given on the command line
+pub const BUNDLED_SPEC: &str = include_str!("copilot.json");
+
+/// Sample knowledge base indexed on first run
+const KNOWLEDGE: [(&str, &str); 3] = [
+    ("sol_rsi.md", include_str!("knowledge/sol_rsi.md")),
+    (
+        "position_sizing.md",
+        include_str!("knowledge/position_sizing.md"),
+    ),
+    ("btc_breakout.md", include_str!("knowledge/btc_breakout.md")),
+];
+
+/// Prompts of the scripted offline demo, in order
+pub const DEMO_PROMPTS: [&str; 4] = [
+    "What does the playbook say about entering SOL?",
+    "Buy $500 of SOL.",
+    "Now put $5,000 into BTC.",
+    "Sell $200 of SOL.",
+];
+
+/// What the copilot is: model, prompt, risk limits and which tools need approval
+#[derive(Debug, Clone, Deserialize)]
+pub struct CopilotSpec {
+    pub name: String,
+    pub model: String,
+    /// Env var holding the API key; without it the copilot runs the offline demo
+    pub api_key_env: String,
+    /// Env var overriding the OpenAI-compatible base URL
+    #[serde(default)]
+    pub base_url_env: Option<String>,
+    /// User the risk manager tracks volume for
+    pub user_id: String,
+    pub prompt: SystemPrompt,
+    pub risk: RiskConfig,
+    #[serde(default)]
+    pub approval_required: Vec<String>,
+}
+
+impl CopilotSpec {
+    /// Spec from `path`, or the bundled `copilot.json`
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let raw = match path {
+            Some(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("reading spec {}", path.display()))?,
+            None => BUNDLED_SPEC.to_string(),
+        };
+        serde_json::from_str(&raw).context("parsing copilot spec")
+    }
+
+    /// API key from the configured env var, if set
+    pub fn api_key(&self) -> Option<String> {
+        std::env::var(&self.api_key_env)
+            .ok()
+            .filter(|key| !key.is_empty())
+    }
+
+    /// Base URL override from the configured env var, if set
+    pub fn base_url(&self) -> Option<String> {
+        let var = self.base_url_env.as_deref()?;
+        std::env::var(var).ok().filter(|url| !url.is_empty())
+    }
+
+    fn tool_policy(&self) -> RiskyToolPolicy {
+        RiskyToolPolicy {
+            default_policy: ToolPolicy::Auto,
+            overrides: self
+                .approval_required
+                .iter()
+                .map(|tool| (tool.clone(), ToolPolicy::RequiresApproval))
+                .collect(),
+        }
+    }
+}
+
+/// Provider shared by the agents of successive sessions
+#[derive(Clone)]
+pub struct SharedProvider(pub Arc<dyn Provider>);
+
+#[async_trait]
+impl Provider for SharedProvider {
+    async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+        self.0.stream_completion(request).await
+    }
+
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+}
+
+/// Mock provider that plays the replies for [`DEMO_PROMPTS`]
+pub fn demo_provider() -> MockProvider {
+    let order = |side: &str, token: &str, amount: u32| {
+        MockTurn::tool_call(
+            "place_order",
+            json!({ "side": side, "token": token, "amount_usd": amount }),
+        )
+    };
+    MockProvider::scripted(
+        [
+            MockTurn::tool_call("tiered_search", json!({ "query": "SOL RSI" })),
+            MockTurn::tool_call(
+                "fetch_document",
+                json!({ "collection": PLAYBOOK, "path": "sol_rsi.md" }),
+            ),
+            MockTurn::text(
+                "Per playbook/sol_rsi.md: enter SOL when the 14-period RSI on the 4h chart drops below 30, \
+                 exit above 70, and keep a 5% stop loss.",
+            ),
+            order("buy", "SOL", 500),
+            MockTurn::text("I sent the $500 SOL buy. The order result above shows the fill and your remaining daily limit."),
+            order("buy", "BTC", 5000),
+            MockTurn::text(
+                "The $5,000 BTC order did not go through. Orders above $1,000 must be split \
+                 (playbook/position_sizing.md), so try a smaller size.",
+            ),
+            order("sell", "SOL", 200),
+            MockTurn::text("Understood, I did not sell any SOL."),
+        ],
+        "This is the offline demo, so I have no more scripted answers. Set OPENAI_API_KEY to talk to a real model.",
+    )
+}
+
+/// Place a simulated market order after the risk manager accepts it
+pub struct PlaceOrderTool {
+    risk: Arc<RiskManager>,
+    user_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderArgs {
+    side: String,
+    token: String,
+    amount_usd: f64,
+    #[serde(default)]
+    slippage_percent: Option<f64>,
+}
+
+/// Demo pool liquidity, in USD
+fn liquidity(token: &str) -> Option<Decimal> {
+    match token {
+        "SOL" => Some(Decimal::from(40_000_000)),
+        "BTC" => Some(Decimal::from(900_000_000)),
+        "ETH" => Some(Decimal::from(300_000_000)),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl Tool for PlaceOrderTool {
+    fn name(&self) -> String {
+        "place_order".to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Buy or sell a token against USDC at market. Needs user approval and passes the risk manager first.".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "side": { "type": "string", "enum": ["buy", "sell"] },
+                    "token": { "type": "string", "description": "Token symbol, e.g. SOL" },
+                    "amount_usd": { "type": "number", "description": "Order size in USD" },
+                    "slippage_percent": { "type": "number", "description": "Max slippage (default 0.5)" }
+                },
+                "required": ["side", "token", "amount_usd"]
+            }),
+            parameters_ts: Some("interface OrderArgs {\n  side: 'buy' | 'sell';\n  token: string;\n  amount_usd: number;\n  slippage_percent?: number;\n}".to_string()),
+            is_verified: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, arguments: &str) -> anyhow::Result<String> {
+        let args: OrderArgs = serde_json::from_str(arguments)
+            .map_err(|e| anyhow::anyhow!("Invalid place_order arguments: {}", e))?;
+        let token = args.token.to_uppercase();
+        let (from_token, to_token) = match args.side.as_str() {
+            "buy" => ("USDC".to_string(), token.clone()),
+            "sell" => (token.clone(), "USDC".to_string()),
+            other => anyhow::bail!("side must be 'buy' or 'sell', got '{}'", other),
+        };
+        let amount_usd = Decimal::from_f64(args.amount_usd)
+            .filter(|amount| *amount > Decimal::ZERO)
+            .ok_or_else(|| anyhow::anyhow!("amount_usd must be a positive number"))?;
+        let context = TradeContext {
+            user_id: self.user_id.clone(),
+            from_token,
+            to_token,
+            amount_usd,
+            expected_slippage: Decimal::from_f64(args.slippage_percent.unwrap_or(0.5))
+                .unwrap_or(Decimal::ONE),
+            liquidity_usd: liquidity(&token),
+            is_flagged: false,
+        };
+
+        self.risk
+            .check_and_reserve(&context)
+            .await
+            .map_err(|e| anyhow::anyhow!("Order rejected by the risk manager: {}", e))?;
+        // The demo fills instantly; a real executor would swap here and roll back on failure
+        self.risk.commit_trade(&self.user_id, amount_usd).await?;
+        let remaining = self.risk.remaining_daily_limit(&self.user_id).await;
+
+        Ok(json!({
+            "status": "filled",
+            "side": args.side,
+            "token": token,
+            "amount_usd": amount_usd.to_f64(),
+            "remaining_daily_usd": remaining.to_f64(),
+        })
+        .to_string())
+    }
+}
+
+/// Everything that outlives a single session: memory, knowledge and risk state
+pub struct Copilot {
+    pub spec: CopilotSpec,
+    pub memory: Arc<QmdMemory>,
+    pub store: Arc<QmdStore>,
+    pub risk: Arc<RiskManager>,
+    skills_dir: PathBuf,
+}
+
+impl Copilot {
+    /// Open the data directory, indexing the knowledge base on first run
+    pub async fn open(
+        spec: CopilotSpec,
+        data_dir: impl AsRef<Path>,
+        skills_dir: impl Into<PathBuf>,
+    ) -> anyhow::Result<Self> {
+        let data_dir = data_dir.as_ref();
+        std::fs::create_dir_all(data_dir)
+            .with_context(|| format!("creating {}", data_dir.display()))?;
+
+        let store = Arc::new(QmdStore::new(data_dir.join("copilot.db"))?);
+        index_knowledge(&store)?;
+
+        let risk_store = Arc::new(FileRiskStore::new(data_dir.join("risk_state.json")));
+        let risk = Arc::new(RiskManager::with_config(spec.risk.clone(), risk_store).await?);
+
+        Ok(Self {
+            spec,
+            memory: Arc::new(QmdMemory::new(Arc::clone(&store))),
+            store,
+            risk,
+            skills_dir: skills_dir.into(),
+        })
+    }
+
+    /// Agent for `session_id`; approval requests arrive on `approvals`
+    pub async fn agent<P: Provider + 'static>(
+        &self,
+        provider: P,
+        session_id: &str,
+        approvals: mpsc::Sender<ApprovalRequest>,
+    ) -> anyhow::Result<Agent<P>> {
+        let skills = SkillLoader::new(&self.skills_dir).with_risk_manager(Arc::clone(&self.risk));
+        skills.load_all().await?;
+
+        let agent = Agent::builder(provider)
+            .model(&self.spec.model)
+            .prompt(self.spec.prompt.clone())
+            .tool_policy(self.spec.tool_policy())
+            .approval_handler(ChannelApprovalHandler::new(approvals))
+            .with_memory(Arc::clone(&self.memory) as Arc<dyn Memory>)
+            .session_id(session_id)
+            .tool(PlaceOrderTool {
+                risk: Arc::clone(&self.risk),
+                user_id: self.spec.user_id.clone(),
+            })
+            .with_dynamic_skills(Arc::new(skills))?
+            .build()?;
+        Ok(agent)
+    }
+
+    /// Stored history of `session_id`, empty for a new session
+    pub async fn history(&self, session_id: &str) -> anyhow::Result<Vec<Message>> {
+        Ok(self
+            .memory
+            .retrieve_session(session_id)
+            .await?
+            .map(|session| session.messages)
+            .unwrap_or_default())
+    }
+
+    /// Ids of stored sessions with their message counts
+    pub async fn sessions(&self) -> anyhow::Result<Vec<(String, usize)>> {
+        let mut sessions = self.memory.list_sessions().await?;
+        sessions.sort_by_key(|s| s.updated_at);
+        Ok(sessions
+            .into_iter()
+            .map(|s| (s.id, s.messages.len()))
+            .collect())
+    }
+
+    /// Risk limits and the volume left for today
+    pub async fn limits(&self) -> String {
+        let risk = &self.spec.risk;
+        format!(
+            "max per trade ${} | max daily ${} | max slippage {}% | remaining today ${}",
+            risk.max_single_trade_usd,
+            risk.max_daily_volume_usd,
+            risk.max_slippage_percent,
+            self.risk.remaining_daily_limit(&self.spec.user_id).await
+        )
+    }
+}
+
+/// Index the bundled knowledge base into [`PLAYBOOK`] unless it is already there
+pub fn index_knowledge(store: &QmdStore) -> anyhow::Result<usize> {
+    if store.list_collections()?.iter().any(|c| c.name == PLAYBOOK) {
+        return Ok(0);
+    }
+    store.create_collection(Collection {
+        name: PLAYBOOK.to_string(),
+        description: Some("Team trading playbook".to_string()),
+        glob_pattern: "**/*.md".to_string(),
+        root_path: None,
+    })?;
+    for (path, body) in KNOWLEDGE {
+        let title = body.lines().next().unwrap_or(path).trim_start_matches("# ");
+        store.store_document(PLAYBOOK, path, title, body)?;
+    }
+    Ok(KNOWLEDGE.len())
+}
+
+/// Formats agent events as terminal lines
+#[derive(Debug, Clone, Default)]
+pub struct ConsoleRenderer {
+    /// Longest tool output shown, in characters
+    pub max_output_chars: Option<usize>,
+}
+
+impl ConsoleRenderer {
+    /// Line for `event`, or `None` for events the REPL prints itself
+    pub fn render(&self, event: &AgentEvent) -> Option<String> {
+        match event {
+            AgentEvent::ToolCall { tool, input } => Some(format!("  -> {} {}", tool, input)),
+            AgentEvent::ApprovalPending { tool, input, .. } => {
+                Some(format!("  ?  {} {} needs approval", tool, input))
+            }
+            AgentEvent::ToolResult { tool, output } => {
+                let first = output.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+                let limit = self.max_output_chars.unwrap_or(120);
+                let shown = match first.char_indices().nth(limit) {
+                    Some((end, _)) => format!("{}...", &first[..end]),
+                    None => first.to_string(),
+                };
+                Some(format!("  <- {}: {}", tool, shown))
+            }
+            AgentEvent::ToolUnavailable { tool, reason } => {
+                Some(format!("  !  {} unavailable: {}", tool, reason))
+            }
+            AgentEvent::Error { message } => Some(format!("  !  {}", message)),
+            AgentEvent::Cancelled { step, .. } => Some(format!("  x  cancelled at step {}", step)),
+            AgentEvent::PersonaChanged { current, .. } => Some(format!(
+                "  *  persona: {}",
+                current.as_deref().unwrap_or("none")
+            )),
+            AgentEvent::Subagent { event, .. } => self.render(event),
+            AgentEvent::Thinking { .. }
+            | AgentEvent::StreamDelta { .. }
+            | AgentEvent::Reasoning { .. }
+            | AgentEvent::ToolCallDelta { .. }
+            | AgentEvent::Usage { .. }
+            | AgentEvent::Response { .. } => None,
+        }
+    }
+}
+
+/// Receives events and answers approvals while a turn runs
+#[async_trait]
+pub trait TurnHandler: Send {
+    /// Called for each agent event, in emission order
+    fn event(&mut self, event: &AgentEvent);
+
+    /// Whether to let the tool call in `request` run
+    async fn approve(&mut self, request: &ApprovalRequest) -> bool;
+}
+
+/// Run one chat turn, feeding events and approval requests to `handler`
+pub async fn run_turn<P: Provider>(
+    agent: &Agent<P>,
+    history: Vec<Message>,
+    events: &mut broadcast::Receiver<AgentEvent>,
+    approvals: &mut mpsc::Receiver<ApprovalRequest>,
+    handler: &mut dyn TurnHandler,
+) -> Result<String> {
+    let chat = agent.chat(history);
+    tokio::pin!(chat);
+    let result = loop {
+        tokio::select! {
+            biased;
+            Ok(event) = events.recv() => handler.event(&event),
+            Some(request) = approvals.recv() => {
+                let approved = handler.approve(&request).await;
+                let _ = request.responder.send(approved);
+            }
+            result = &mut chat => break result,
+        }
+    };
+    while let Ok(event) = events.try_recv() {
+        handler.event(&event);
+    }
+    result
+}
+
+/// What a headless run saw
+#[derive(Debug, Default)]
+pub struct Transcript {
+    pub events: Vec<AgentEvent>,
+    /// Tool name and decision, per approval request
+    pub approvals: Vec<(String, bool)>,
+    /// Final response per prompt
+    pub responses: Vec<String>,
+}
+
+impl Transcript {
+    /// Outputs of successful calls to `tool`
+    pub fn tool_results<'a>(&'a self, tool: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.events.iter().filter_map(move |e| match e {
+            AgentEvent::ToolResult { tool: t, output } if t == tool => Some(output.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Error events, e.g. rejected or declined tool calls
+    pub fn errors(&self) -> impl Iterator<Item = &str> {
+        self.events.iter().filter_map(|e| match e {
+            AgentEvent::Error { message } => Some(message.as_str()),
+            _ => None,
+        })
+    }
+}
+
+struct Headless {
+    answers: VecDeque<bool>,
+    transcript: Transcript,
+}
+
+#[async_trait]
+impl TurnHandler for Headless {
+    fn event(&mut self, event: &AgentEvent) {
+        self.transcript.events.push(event.clone());
+    }
+
+    async fn approve(&mut self, request: &ApprovalRequest) -> bool {
+        let approved = self.answers.pop_front().unwrap_or(false);
+        self.transcript
+            .approvals
+            .push((request.tool_name.clone(), approved));
+        approved
+    }
+}
+
+/// Play [`DEMO_PROMPTS`] against `provider` without a terminal
+///
+/// `answers` are the approval decisions in order; missing ones decline.
+pub async fn run_headless<P: Provider + 'static>(
+    copilot: &Copilot,
+    provider: P,
+    session_id: &str,
+    answers: impl IntoIterator<Item = bool>,
+) -> anyhow::Result<Transcript> {
+    let (tx, mut approvals) = mpsc::channel(8);
+    let agent = copilot.agent(provider, session_id, tx).await?;
+    let mut events = agent.subscribe();
+    let mut handler = Headless {
+        answers: answers.into_iter().collect(),
+        transcript: Transcript::default(),
+    };
+
+    for prompt in DEMO_PROMPTS {
+        let mut history = copilot.history(session_id).await?;
+        history.push(Message::user(prompt));
+        let response = run_turn(&agent, history, &mut events, &mut approvals, &mut handler).await?;
+        handler.transcript.responses.push(response);
+    }
+    Ok(handler.transcript)
+}
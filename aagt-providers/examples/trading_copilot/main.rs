@@ -0,0 +1,237 @@
+//! Terminal trading copilot: skills, risk, approvals and QMD wired together
+//!
+//! This example demonstrates:
+//! - Building an agent from a spec file (`copilot.json`, or `--spec <path>`)
+//! - Answering from a bundled playbook indexed into QMD on first run
+//! - A `place_order` tool behind a y/n approval prompt and the risk manager
+//! - Sessions persisted in QMD, listed with `/sessions` and reopened with `/resume <id>`
+//!
+//! Without `OPENAI_API_KEY` it runs offline against a scripted mock provider
+//! that plays a demo conversation. `--headless` plays the same demo with
+//! scripted approvals and no terminal input.
+//!
+//! Usage:
+//!   cargo run -p aagt-providers --example trading_copilot -- [--spec PATH] [--data DIR] [--skills DIR] [--headless]
+
+mod copilot;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use aagt_core::agent::core::{AgentEvent, ApprovalRequest};
+use aagt_core::prelude::*;
+use aagt_providers::openai::OpenAI;
+use anyhow::Result;
+use async_trait::async_trait;
+use copilot::{ConsoleRenderer, Copilot, CopilotSpec, SharedProvider, TurnHandler, DEMO_PROMPTS};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines, Stdin};
+use tokio::sync::mpsc;
+
+struct Args {
+    spec: Option<PathBuf>,
+    data: PathBuf,
+    skills: PathBuf,
+    headless: bool,
+}
+
+impl Args {
+    fn parse() -> Result<Self> {
+        let mut args = Self {
+            spec: None,
+            data: PathBuf::from("./copilot-data"),
+            skills: PathBuf::from("./skills"),
+            headless: false,
+        };
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .ok_or_else(|| anyhow::anyhow!("{} needs a value", arg))
+            };
+            match arg.as_str() {
+                "--spec" => args.spec = Some(value()?.into()),
+                "--data" => args.data = value()?.into(),
+                "--skills" => args.skills = value()?.into(),
+                "--headless" => args.headless = true,
+                other => anyhow::bail!("unknown argument: {}", other),
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// Reads REPL input and approval answers from the same stdin
+struct Terminal {
+    lines: Lines<BufReader<Stdin>>,
+    renderer: ConsoleRenderer,
+}
+
+impl Terminal {
+    async fn read_line(&mut self, prompt: &str) -> Option<String> {
+        let mut stdout = tokio::io::stdout();
+        let _ = stdout.write_all(prompt.as_bytes()).await;
+        let _ = stdout.flush().await;
+        self.lines.next_line().await.ok().flatten()
+    }
+}
+
+#[async_trait]
+impl TurnHandler for Terminal {
+    fn event(&mut self, event: &AgentEvent) {
+        if let Some(line) = self.renderer.render(event) {
+            println!("{}", line);
+        }
+    }
+
+    async fn approve(&mut self, request: &ApprovalRequest) -> bool {
+        let question = format!(
+            "  Approve {} {}? [y/N] ",
+            request.tool_name, request.arguments
+        );
+        let answer = self.read_line(&question).await.unwrap_or_default();
+        matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    let args = Args::parse()?;
+    let spec = CopilotSpec::load(args.spec.as_deref())?;
+    let copilot = Copilot::open(spec, &args.data, &args.skills).await?;
+
+    if args.headless {
+        let session_id = format!("headless-{}", short_id());
+        let transcript = copilot::run_headless(
+            &copilot,
+            copilot::demo_provider(),
+            &session_id,
+            [true, true, false],
+        )
+        .await?;
+        let renderer = ConsoleRenderer::default();
+        for event in &transcript.events {
+            if let Some(line) = renderer.render(event) {
+                println!("{}", line);
+            }
+        }
+        for (prompt, response) in DEMO_PROMPTS.iter().zip(&transcript.responses) {
+            println!("you> {}\ncopilot> {}\n", prompt, response);
+        }
+        println!("{}", copilot.limits().await);
+        return Ok(());
+    }
+
+    let (provider, demo): (Arc<dyn Provider>, bool) = match copilot.spec.api_key() {
+        Some(key) => {
+            let provider = match copilot.spec.base_url() {
+                Some(url) => OpenAI::with_base_url(key, url)?,
+                None => OpenAI::new(key)?,
+            };
+            (Arc::new(provider), false)
+        }
+        None => {
+            println!(
+                "{} is not set: running the offline demo with a scripted model.",
+                copilot.spec.api_key_env
+            );
+            (Arc::new(copilot::demo_provider()), true)
+        }
+    };
+
+    println!("{} ({})", copilot.spec.name, copilot.spec.model);
+    println!("limits: {}", copilot.limits().await);
+    println!("commands: /sessions, /resume <id>, /limits, /quit\n");
+
+    let mut terminal = Terminal {
+        lines: BufReader::new(tokio::io::stdin()).lines(),
+        renderer: ConsoleRenderer::default(),
+    };
+    let mut session_id = format!("session-{}", short_id());
+    let (tx, mut approvals) = mpsc::channel(8);
+    let mut agent = copilot
+        .agent(
+            SharedProvider(Arc::clone(&provider)),
+            &session_id,
+            tx.clone(),
+        )
+        .await?;
+    let mut events = agent.subscribe();
+    println!("session {}", session_id);
+
+    // The demo types its own prompts first, then hands over to the user
+    let mut scripted: Vec<&str> = if demo {
+        DEMO_PROMPTS.to_vec()
+    } else {
+        Vec::new()
+    };
+    scripted.reverse();
+
+    loop {
+        let line = match scripted.pop() {
+            Some(prompt) => {
+                println!("you> {}", prompt);
+                prompt.to_string()
+            }
+            None => match terminal.read_line("you> ").await {
+                Some(line) => line,
+                None => break,
+            },
+        };
+        let line = line.trim();
+        match line
+            .split_once(' ')
+            .map_or((line, ""), |(cmd, rest)| (cmd, rest.trim()))
+        {
+            ("", _) => continue,
+            ("/quit" | "/exit", _) => break,
+            ("/limits", _) => println!("{}", copilot.limits().await),
+            ("/sessions", _) => {
+                for (id, messages) in copilot.sessions().await? {
+                    let marker = if id == session_id { "*" } else { " " };
+                    println!("{} {} ({} messages)", marker, id, messages);
+                }
+            }
+            ("/resume", id) if !id.is_empty() => {
+                let history = copilot.history(id).await?;
+                if history.is_empty() {
+                    println!("no session '{}'", id);
+                    continue;
+                }
+                session_id = id.to_string();
+                agent = copilot
+                    .agent(
+                        SharedProvider(Arc::clone(&provider)),
+                        &session_id,
+                        tx.clone(),
+                    )
+                    .await?;
+                events = agent.subscribe();
+                println!("resumed {} ({} messages)", session_id, history.len());
+                if let Some(last) = history.iter().rev().find(|m| m.role == Role::Assistant) {
+                    println!("copilot> {}", last.content.as_text());
+                }
+            }
+            ("/resume", _) => println!("usage: /resume <id>"),
+            _ => {
+                let mut history = copilot.history(&session_id).await?;
+                history.push(Message::user(line));
+                match copilot::run_turn(&agent, history, &mut events, &mut approvals, &mut terminal)
+                    .await
+                {
+                    Ok(response) => println!("copilot> {}\n", response),
+                    Err(e) => println!("  !  {}\n", e),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Short random id for a new session
+fn short_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..8].to_string()
+}
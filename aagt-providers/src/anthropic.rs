@@ -1,6 +1,12 @@
 //! Anthropic (Claude) provider implementation
+//!
+//! The system prompt is sent as text blocks: the request's system prompt
+//! followed by the system messages the context manager injected, such as the
+//! tool catalog. [`Anthropic::with_cache_system_prompt`] marks the preamble and
+//! tool definitions as a prompt-cache breakpoint; cache writes and reads are
+//! reported in [`Usage`].
 
-
+use std::collections::BTreeMap;
 
 use async_trait::async_trait;
 use futures::{Stream, StreamExt};
@@ -8,7 +14,12 @@ use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
 
 use crate::{Error, Result, Message, StreamingChoice, StreamingResponse, ToolDefinition, Provider, HttpConfig};
-use aagt_core::agent::message::{Role, Content};
+use crate::utils::{error_from_response, extend_headers, ErrorDetails};
+use aagt_core::agent::message::{Role, Content, ImageSource};
+use aagt_core::agent::provider::{ChatRequest, CompletionResponse, KeyPool, PooledProvider};
+use aagt_core::agent::streaming::Usage;
+use aagt_core::infra::secrets::SecretProvider;
+use aagt_core::skills::tool::TOOL_CATALOG_HEADING;
 
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
 const ANTHROPIC_VERSION: &str = "2023-06-01";
@@ -17,6 +28,7 @@ const ANTHROPIC_VERSION: &str = "2023-06-01";
 pub struct Anthropic {
     client: reqwest::Client,
     api_key: String,
+    cache_system_prompt: bool,
 }
 
 impl Anthropic {
@@ -28,9 +40,24 @@ impl Anthropic {
         Ok(Self {
             client,
             api_key: api_key.into(),
+            cache_system_prompt: false,
         })
     }
 
+    /// Cache the preamble and tool definitions between requests
+    ///
+    /// Cached prompt tokens are billed at a fraction of the input price, so
+    /// this pays off for long system prompts that are identical every call.
+    pub fn with_cache_system_prompt(mut self, enabled: bool) -> Self {
+        self.cache_system_prompt = enabled;
+        self
+    }
+
+    /// Spread requests across a pool of keys resolved through `secrets`
+    pub fn pooled(pool: KeyPool, secrets: &dyn SecretProvider) -> Result<PooledProvider<Self>> {
+        PooledProvider::new(pool, secrets, |key| Self::new(key))
+    }
+
     /// Create from environment variable
     pub fn from_env() -> Result<Self> {
         let api_key = std::env::var("ANTHROPIC_API_KEY")
@@ -38,7 +65,7 @@ impl Anthropic {
         Self::new(api_key)
     }
 
-    fn build_headers(&self) -> Result<HeaderMap> {
+    fn build_headers(&self, extra: &BTreeMap<String, String>) -> Result<HeaderMap> {
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
         headers.insert(
@@ -50,6 +77,7 @@ impl Anthropic {
             "anthropic-version",
             HeaderValue::from_static(ANTHROPIC_VERSION),
         );
+        extend_headers(&mut headers, extra)?;
         Ok(headers)
     }
 }
@@ -60,8 +88,8 @@ struct AnthropicRequest {
     model: String,
     messages: Vec<AnthropicMessage>,
     max_tokens: u64,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    system: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    system: Vec<SystemBlock>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f64>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -69,6 +97,39 @@ struct AnthropicRequest {
     stream: bool,
 }
 
+/// Text block of the system prompt
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct SystemBlock {
+    #[serde(rename = "type")]
+    block_type: &'static str,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_control: Option<CacheControl>,
+}
+
+impl SystemBlock {
+    fn text(text: String) -> Self {
+        Self {
+            block_type: "text",
+            text,
+            cache_control: None,
+        }
+    }
+}
+
+/// Prompt-cache breakpoint: everything up to and including the marked block is cached
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct CacheControl {
+    #[serde(rename = "type")]
+    cache_type: &'static str,
+}
+
+impl CacheControl {
+    fn ephemeral() -> Self {
+        Self { cache_type: "ephemeral" }
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct AnthropicMessage {
     role: String,
@@ -98,6 +159,9 @@ enum ContentBlock {
         tool_use_id: String,
         content: String,
     },
+    /// Same shape as [`ImageSource`]: `{"type": "base64", "media_type", "data"}` or `{"type": "url", "url"}`
+    #[serde(rename = "image")]
+    Image { source: ImageSource },
 }
 
 #[derive(Debug, Serialize)]
@@ -107,6 +171,71 @@ struct AnthropicTool {
     input_schema: serde_json::Value,
 }
 
+/// Non-streaming reply from the messages endpoint
+#[derive(Debug, Deserialize)]
+struct MessageBody {
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+    usage: Option<MessageUsage>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct MessageUsage {
+    /// Absent from `message_delta` events, which only count output
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+    /// Prompt tokens written to the cache, not counted in `input_tokens`
+    #[serde(default)]
+    cache_creation_input_tokens: u32,
+    /// Prompt tokens read from the cache, not counted in `input_tokens`
+    #[serde(default)]
+    cache_read_input_tokens: u32,
+}
+
+impl MessageUsage {
+    /// Prompt counts from `message_start` combined with the output count of `delta`
+    fn with_output(&self, delta: &MessageUsage) -> Self {
+        Self {
+            input_tokens: self.input_tokens.max(delta.input_tokens),
+            output_tokens: delta.output_tokens,
+            cache_creation_input_tokens: self
+                .cache_creation_input_tokens
+                .max(delta.cache_creation_input_tokens),
+            cache_read_input_tokens: self.cache_read_input_tokens.max(delta.cache_read_input_tokens),
+        }
+    }
+
+    /// Usage whose prompt count includes cached tokens
+    fn to_usage(&self) -> Usage {
+        let prompt_tokens =
+            self.input_tokens + self.cache_creation_input_tokens + self.cache_read_input_tokens;
+        Usage::new(prompt_tokens, self.output_tokens)
+            .with_cache(self.cache_creation_input_tokens, self.cache_read_input_tokens)
+    }
+}
+
+impl MessageBody {
+    fn into_response(self) -> CompletionResponse {
+        let mut response = CompletionResponse {
+            usage: self.usage.map(|u| u.to_usage()),
+            finish_reason: self.stop_reason,
+            ..Default::default()
+        };
+        for block in self.content {
+            match block {
+                ContentBlock::Text { text } => response.text.push_str(&text),
+                ContentBlock::ToolUse { id, name, input } => response
+                    .tool_calls
+                    .push(aagt_core::agent::message::ToolCall::new(id, name, input)),
+                ContentBlock::ToolResult { .. } | ContentBlock::Image { .. } => {}
+            }
+        }
+        response
+    }
+}
+
 /// Streaming event from Anthropic
 #[derive(Debug, Deserialize)]
 struct StreamEvent {
@@ -116,6 +245,17 @@ struct StreamEvent {
     delta: Option<StreamDelta>,
     #[serde(default)]
     content_block: Option<ContentBlockStart>,
+    /// Set on `message_start`; carries the prompt token count
+    #[serde(default)]
+    message: Option<MessageStart>,
+    /// Set on `message_delta`; carries the completion token count
+    #[serde(default)]
+    usage: Option<MessageUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageStart {
+    usage: Option<MessageUsage>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -164,7 +304,10 @@ impl Anthropic {
                                     content,
                                 }
                             },
-                            _ => ContentBlock::Text { text: "[Image not supported]".to_string() },
+                            aagt_core::agent::message::ContentPart::Image { source } => ContentBlock::Image { source },
+                            file @ aagt_core::agent::message::ContentPart::FileRef { .. } => ContentBlock::Text {
+                                text: file.placeholder().unwrap_or_default(),
+                            },
                         }).collect();
                         AnthropicContent::Blocks(blocks)
                     }
@@ -178,60 +321,98 @@ impl Anthropic {
             .collect()
     }
 
-    fn convert_tools(tools: Vec<ToolDefinition>) -> Vec<AnthropicTool> {
-        tools
+    /// System blocks: `system_prompt`, then the text of each system message
+    ///
+    /// Messages already contained in an earlier block, like the preamble the
+    /// context manager repeats, are skipped. With `cache`, the breakpoint goes
+    /// on the tool catalog, or on the preamble when there is none, so later
+    /// per-request blocks such as RAG context don't invalidate the cache.
+    fn system_blocks(system_prompt: Option<String>, messages: &[Message], cache: bool) -> Vec<SystemBlock> {
+        let mut blocks: Vec<SystemBlock> = Vec::new();
+        let texts = system_prompt
             .into_iter()
-            .map(|t| AnthropicTool {
-                name: t.name,
-                description: t.description,
-                input_schema: t.parameters,
-            })
-            .collect()
+            .chain(messages.iter().filter(|m| m.role == Role::System).map(Message::text));
+        for text in texts {
+            if text.trim().is_empty() || blocks.iter().any(|b| b.text.contains(&text)) {
+                continue;
+            }
+            blocks.push(SystemBlock::text(text));
+        }
+
+        if cache {
+            let breakpoint = blocks
+                .iter()
+                .position(|b| b.text.starts_with(TOOL_CATALOG_HEADING))
+                .or((!blocks.is_empty()).then_some(0));
+            if let Some(i) = breakpoint {
+                blocks[i].cache_control = Some(CacheControl::ephemeral());
+            }
+        }
+        blocks
     }
-}
 
-#[async_trait]
-impl Provider for Anthropic {
-    async fn stream_completion(
-        &self,
-        request: aagt_core::agent::provider::ChatRequest,
-    ) -> Result<StreamingResponse> {
-        let aagt_core::agent::provider::ChatRequest {
+    /// Build the API request body for `request`
+    fn api_request(&self, request: ChatRequest, stream: bool) -> AnthropicRequest {
+        let ChatRequest {
             model,
             system_prompt,
             messages,
             tools,
             temperature,
             max_tokens,
+            max_reasoning_tokens: _,
             extra_params: _,
+            headers: _,
         } = request;
 
-        let anthropic_request = AnthropicRequest {
-            model: model.to_string(),
+        AnthropicRequest {
+            model,
+            system: Self::system_blocks(system_prompt, &messages, self.cache_system_prompt),
             messages: Self::convert_messages(messages),
             max_tokens: max_tokens.unwrap_or(4096),
-            system: system_prompt,
             temperature,
             tools: Self::convert_tools(tools),
-            stream: true,
-        };
+            stream,
+        }
+    }
 
+    /// POST `anthropic_request` to the messages endpoint, with the request's extra `headers`
+    async fn send(
+        &self,
+        anthropic_request: &AnthropicRequest,
+        headers: &BTreeMap<String, String>,
+    ) -> Result<reqwest::Response> {
         let response = self
             .client
             .post(ANTHROPIC_API_URL)
-            .headers(self.build_headers()?)
-            .json(&anthropic_request)
+            .headers(self.build_headers(headers)?)
+            .json(anthropic_request)
             .send()
             .await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(Error::ProviderApi(format!(
-                "Anthropic API error {}: {}",
-                status, text
-            )));
+            return Err(error_from_response("anthropic", response, error_details).await);
         }
+        Ok(response)
+    }
+
+    fn convert_tools(tools: Vec<ToolDefinition>) -> Vec<AnthropicTool> {
+        tools
+            .into_iter()
+            .map(|t| AnthropicTool {
+                name: t.name,
+                description: t.description,
+                input_schema: t.parameters,
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Provider for Anthropic {
+    async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+        let headers = request.headers.clone();
+        let response = self.send(&self.api_request(request, true), &headers).await?;
 
         let stream = response.bytes_stream();
         let parsed_stream = parse_anthropic_stream(stream);
@@ -239,9 +420,30 @@ impl Provider for Anthropic {
         Ok(StreamingResponse::from_stream(parsed_stream))
     }
 
+    async fn complete(&self, request: ChatRequest) -> Result<CompletionResponse> {
+        let headers = request.headers.clone();
+        let response = self.send(&self.api_request(request, false), &headers).await?;
+        let body: MessageBody = response.json().await?;
+        Ok(body.into_response())
+    }
+
     fn name(&self) -> &'static str {
         "anthropic"
     }
+
+    fn supports_vision(&self) -> bool {
+        true
+    }
+}
+
+/// Read an Anthropic error body: `{"type": "error", "error": {"type", "message"}}`
+fn error_details(body: &serde_json::Value) -> ErrorDetails {
+    let error = &body["error"];
+    ErrorDetails {
+        code: error["type"].as_str().map(String::from),
+        message: error["message"].as_str().map(String::from),
+        retry_after: None,
+    }
 }
 
 /// Parse Server-Sent Events stream from Anthropic
@@ -260,10 +462,12 @@ where
     let sse_buffer = crate::utils::SseBuffer::new();
     let string_buffer = String::new();
     let current_tool: Option<ToolState> = None;
+    // Prompt counts from `message_start`, reported with the output count at `message_delta`
+    let prompt_usage = MessageUsage::default();
 
     futures::stream::unfold(
-        (stream, sse_buffer, string_buffer, current_tool),
-        move |(mut stream, mut bytes_buffer, mut text_buffer, mut current_tool)| async move {
+        (stream, sse_buffer, string_buffer, current_tool, prompt_usage),
+        move |(mut stream, mut bytes_buffer, mut text_buffer, mut current_tool, mut prompt_usage)| async move {
             loop {
                 // Try to extract complete SSE message
                 if let Some(pos) = text_buffer.find("\n\n") {
@@ -275,6 +479,20 @@ where
                         match serde_json::from_str::<StreamEvent>(data) {
                             Ok(event) => {
                                 match event.event_type.as_str() {
+                                    "message_start" => {
+                                        if let Some(usage) = event.message.and_then(|m| m.usage) {
+                                            prompt_usage = usage;
+                                        }
+                                    }
+                                    "message_delta" => {
+                                        if let Some(usage) = event.usage {
+                                            let usage = prompt_usage.with_output(&usage).to_usage();
+                                            return Some((
+                                                Ok(StreamingChoice::Usage(usage)),
+                                                (stream, bytes_buffer, text_buffer, current_tool, prompt_usage),
+                                            ));
+                                        }
+                                    }
                                     "content_block_start" => {
                                         if let Some(block) = event.content_block {
                                             if block.block_type == "tool_use" {
@@ -293,7 +511,7 @@ where
                                                 if !text.is_empty() {
                                                     return Some((
                                                         Ok(StreamingChoice::Message(text)),
-                                                        (stream, bytes_buffer, text_buffer, current_tool),
+                                                        (stream, bytes_buffer, text_buffer, current_tool, prompt_usage),
                                                     ));
                                                 }
                                             }
@@ -315,14 +533,14 @@ where
                                                     name: tool.name,
                                                     arguments: args,
                                                 }),
-                                                (stream, bytes_buffer, text_buffer, None),
+                                                (stream, bytes_buffer, text_buffer, None, prompt_usage),
                                             ));
                                         }
                                     }
                                     "message_stop" => {
                                         return Some((
                                             Ok(StreamingChoice::Done),
-                                            (stream, bytes_buffer, text_buffer, current_tool),
+                                            (stream, bytes_buffer, text_buffer, current_tool, prompt_usage),
                                         ));
                                     }
                                     _ => {}
@@ -346,15 +564,15 @@ where
                             Err(e) => {
                                 return Some((
                                     Err(e),
-                                    (stream, bytes_buffer, text_buffer, current_tool),
+                                    (stream, bytes_buffer, text_buffer, current_tool, prompt_usage),
                                 ));
                             }
                         }
                     }
                     Some(Err(e)) => {
                         return Some((
-                            Err(Error::Http(e)),
-                            (stream, bytes_buffer, text_buffer, current_tool),
+                            Err(Error::from(e)),
+                            (stream, bytes_buffer, text_buffer, current_tool, prompt_usage),
                         ));
                     }
                     None => return None,
@@ -392,16 +610,179 @@ mod tests {
         assert_eq!(converted[1].role, "assistant");
     }
 
+    fn attachments() -> Message {
+        use aagt_core::agent::message::ContentPart;
+        Message::user(Content::Parts(vec![
+            ContentPart::Text { text: "Compare these charts".to_string() },
+            ContentPart::Image {
+                source: ImageSource::Base64 { media_type: "image/png".to_string(), data: "iVBORw0K".to_string() },
+            },
+            ContentPart::Image {
+                source: ImageSource::Url { url: "https://example.com/sol.png".to_string() },
+            },
+            ContentPart::FileRef { virtual_path: "qmd://reports/sol.md".to_string(), docid: "a1b2c3".to_string() },
+        ]))
+    }
+
+    #[test]
+    fn test_image_and_file_parts() {
+        let converted = Anthropic::convert_messages(vec![attachments()]);
+        assert_eq!(
+            serde_json::to_value(&converted[0]).unwrap(),
+            serde_json::json!({
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "Compare these charts"},
+                    {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0K"}},
+                    {"type": "image", "source": {"type": "url", "url": "https://example.com/sol.png"}},
+                    {"type": "text", "text": "[file: qmd://reports/sol.md #a1b2c3]"}
+                ]
+            })
+        );
+    }
+
     #[test]
     fn test_tool_conversion() {
         let tools = vec![ToolDefinition {
             name: "test".to_string(),
             description: "A test tool".to_string(),
             parameters: serde_json::json!({"type": "object"}),
+            is_verified: true,
+            ..Default::default()
         }];
 
         let converted = Anthropic::convert_tools(tools);
         assert_eq!(converted.len(), 1);
         assert_eq!(converted[0].name, "test");
     }
+
+    #[test]
+    fn test_message_body_conversion() {
+        let body: MessageBody = serde_json::from_value(serde_json::json!({
+            "content": [
+                {"type": "text", "text": "Checking the price."},
+                {"type": "tool_use", "id": "toolu_1", "name": "get_price", "input": {"symbol": "SOL"}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 30, "output_tokens": 12}
+        }))
+        .unwrap();
+
+        let response = body.into_response();
+        assert_eq!(response.text, "Checking the price.");
+        assert_eq!(response.tool_calls[0].id, "toolu_1");
+        assert_eq!(response.usage.map(|u| u.total_tokens), Some(42));
+        assert_eq!(response.finish_reason.as_deref(), Some("tool_use"));
+    }
+
+    #[test]
+    fn test_cache_usage_is_counted_in_the_prompt() {
+        let body: MessageBody = serde_json::from_value(serde_json::json!({
+            "content": [{"type": "text", "text": "ok"}],
+            "stop_reason": "end_turn",
+            "usage": {
+                "input_tokens": 20,
+                "output_tokens": 5,
+                "cache_creation_input_tokens": 0,
+                "cache_read_input_tokens": 1800
+            }
+        }))
+        .unwrap();
+
+        let usage = body.into_response().usage.unwrap();
+        assert_eq!(usage.prompt_tokens, 1820);
+        assert_eq!(usage.total_tokens, 1825);
+        assert_eq!(usage.cache_read_tokens, 1800);
+        assert_eq!(usage.cache_creation_tokens, 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_reports_cache_usage() {
+        let events = [
+            r#"{"type": "message_start", "message": {"usage": {"input_tokens": 12, "cache_creation_input_tokens": 900, "output_tokens": 1}}}"#,
+            r#"{"type": "message_delta", "usage": {"output_tokens": 7}}"#,
+            r#"{"type": "message_stop"}"#,
+        ];
+        let body: String = events.iter().map(|e| format!("data: {}\n\n", e)).collect();
+        let bytes = futures::stream::iter(vec![Ok::<_, reqwest::Error>(bytes::Bytes::from(body))]);
+
+        let chunks: Vec<_> = parse_anthropic_stream(bytes).collect().await;
+        let usage = chunks
+            .into_iter()
+            .find_map(|c| match c {
+                Ok(StreamingChoice::Usage(usage)) => Some(usage),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(usage.prompt_tokens, 912);
+        assert_eq!(usage.completion_tokens, 7);
+        assert_eq!(usage.cache_creation_tokens, 900);
+    }
+
+    #[tokio::test]
+    async fn test_system_blocks_are_identical_across_requests() {
+        use aagt_core::skills::tool::{CalculatorTool, DiffTool, ToolSet};
+
+        let anthropic = Anthropic::new("test-key").unwrap().with_cache_system_prompt(true);
+        let mut tools = ToolSet::new();
+        tools.add(DiffTool::new()).add(CalculatorTool::new());
+
+        let mut bodies = Vec::new();
+        for question in ["Price of SOL?", "And ETH?"] {
+            let mut messages = vec![Message::system("You are a trading agent.")];
+            messages.extend(tools.render_catalog(None).await);
+            messages.push(Message::system(format!("Relevant notes for: {}", question)));
+            messages.push(Message::user(question));
+            let request = ChatRequest {
+                model: CLAUDE_3_5_SONNET.to_string(),
+                system_prompt: Some("You are a trading agent.".to_string()),
+                messages,
+                ..Default::default()
+            };
+            let body = anthropic.api_request(request, false);
+            bodies.push(body);
+        }
+
+        let system = |body: &AnthropicRequest| {
+            let blocks = &body.system[..2];
+            serde_json::to_string(blocks).unwrap()
+        };
+        assert_eq!(system(&bodies[0]), system(&bodies[1]));
+
+        let blocks = &bodies[0].system;
+        assert_eq!(blocks.len(), 3, "the repeated preamble is sent once");
+        assert!(blocks[1].text.starts_with(TOOL_CATALOG_HEADING));
+        assert_eq!(blocks[1].cache_control, Some(CacheControl::ephemeral()));
+        assert!(blocks[0].cache_control.is_none() && blocks[2].cache_control.is_none());
+        assert_eq!(bodies[0].messages.len(), 1);
+
+        let uncached = Anthropic::new("test-key").unwrap();
+        let body = uncached.api_request(
+            ChatRequest {
+                system_prompt: Some("You are a trading agent.".to_string()),
+                ..Default::default()
+            },
+            false,
+        );
+        let json = serde_json::to_value(&body).unwrap();
+        assert_eq!(json["system"], serde_json::json!([{"type": "text", "text": "You are a trading agent."}]));
+    }
+
+    #[test]
+    fn test_error_body_parsing() {
+        let body = r#"{"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}"#;
+        let error = crate::utils::provider_error("anthropic", 529, None, body, error_details);
+        assert!(matches!(
+            &error,
+            Error::Provider { status: Some(529), code: Some(code), message, .. }
+                if code == "overloaded_error" && message == "Overloaded"
+        ));
+        assert!(error.is_retryable());
+        assert_eq!(error.to_string(), "anthropic API error 529 (overloaded_error): Overloaded");
+
+        let body = r#"{"type": "error", "error": {"type": "authentication_error", "message": "invalid x-api-key"}}"#;
+        let error = crate::utils::provider_error("anthropic", 401, None, body, error_details);
+        assert!(error.is_auth_failure());
+        assert!(!error.is_retryable());
+    }
 }
@@ -1,4 +1,9 @@
 //! DeepSeek provider implementation
+//!
+//! Reasoning models such as [`DEEPSEEK_REASONER`] stream their thinking in
+//! `reasoning_content`; it arrives as [`StreamingChoice::Thought`](crate::StreamingChoice::Thought)
+//! chunks, apart from the reply. A request's `max_reasoning_tokens` is sent as
+//! the `max_reasoning_tokens` body field.
 
 use async_trait::async_trait;
 
@@ -13,7 +18,9 @@ pub struct DeepSeek {
 impl DeepSeek {
     /// Create from API key
     pub fn new(api_key: impl Into<String>) -> Result<Self> {
-        let inner = OpenAI::with_base_url(api_key, "https://api.deepseek.com/v1")?;
+        let inner = OpenAI::with_base_url(api_key, "https://api.deepseek.com/v1")?
+            .with_errors("deepseek", crate::openai::error_details)
+            .with_reasoning_budget("max_reasoning_tokens");
         Ok(Self { inner })
     }
 
@@ -34,6 +41,13 @@ impl Provider for DeepSeek {
         self.inner.stream_completion(request).await
     }
 
+    async fn complete(
+        &self,
+        request: aagt_core::agent::provider::ChatRequest,
+    ) -> Result<aagt_core::agent::provider::CompletionResponse> {
+        self.inner.complete(request).await
+    }
+
     fn name(&self) -> &'static str {
         "deepseek"
     }
@@ -44,3 +58,5 @@ impl Provider for DeepSeek {
 pub const DEEPSEEK_CHAT: &str = "deepseek-chat";
 /// DeepSeek Coder
 pub const DEEPSEEK_CODER: &str = "deepseek-coder";
+/// DeepSeek Reasoner (R1), which streams its reasoning separately
+pub const DEEPSEEK_REASONER: &str = "deepseek-reasoner";
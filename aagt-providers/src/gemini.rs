@@ -1,14 +1,16 @@
 //! Google Gemini provider implementation
 
-
+use std::collections::{HashMap, VecDeque};
 
 use async_trait::async_trait;
 use futures::{Stream, StreamExt};
-use reqwest::header::CONTENT_TYPE;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
 
 use crate::{Error, Result, Message, StreamingChoice, StreamingResponse, ToolDefinition, Provider, HttpConfig};
-use aagt_core::agent::message::{Role, Content};
+use crate::utils::{error_from_response, extend_headers, retry_delay, ErrorDetails};
+use aagt_core::agent::message::{Role, Content, ImageSource};
+use aagt_core::skills::tool::TOOL_CATALOG_HEADING;
 
 const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta/models";
 
@@ -60,9 +62,29 @@ struct GeminiContent {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 enum Part {
-    Text { text: String },
-    FunctionCall { function_call: FunctionCall },
-    FunctionResponse { function_response: FunctionResponse },
+    Text {
+        text: String,
+    },
+    FunctionCall {
+        #[serde(rename = "functionCall")]
+        function_call: FunctionCall,
+    },
+    FunctionResponse {
+        #[serde(rename = "functionResponse")]
+        function_response: FunctionResponse,
+    },
+    InlineData {
+        #[serde(rename = "inlineData")]
+        inline_data: Blob,
+    },
+}
+
+/// Base64 image sent inline
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Blob {
+    mime_type: String,
+    data: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -84,9 +106,19 @@ struct GenerationConfig {
     temperature: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_output_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking_config: Option<ThinkingConfig>,
+}
+
+/// Reasoning budget of thinking models
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThinkingConfig {
+    thinking_budget: u64,
 }
 
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 struct GeminiTool {
     function_declarations: Vec<FunctionDeclaration>,
 }
@@ -95,7 +127,9 @@ struct GeminiTool {
 struct FunctionDeclaration {
     name: String,
     description: String,
-    parameters: serde_json::Value,
+    /// Omitted for tools without arguments (Gemini rejects an object schema with no properties)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parameters: Option<serde_json::Value>,
 }
 
 /// Streaming response chunk
@@ -134,11 +168,237 @@ enum ResponsePart {
 #[derive(Debug, Deserialize)]
 struct ResponseFunctionCall {
     name: String,
+    #[serde(default)]
     args: serde_json::Value,
 }
 
+/// Schema keywords Gemini's `Schema` object accepts; everything else is stripped
+const SCHEMA_KEYWORDS: &[&str] = &[
+    "type",
+    "format",
+    "description",
+    "nullable",
+    "enum",
+    "properties",
+    "required",
+    "items",
+    "minItems",
+    "maxItems",
+    "minimum",
+    "maximum",
+    "minLength",
+    "maxLength",
+    "pattern",
+    "minProperties",
+    "maxProperties",
+];
+
+/// How deep `$ref`s are inlined before a recursive schema is cut off
+const MAX_SCHEMA_DEPTH: usize = 8;
+
+/// Translate a tool's JSON schema into the subset Gemini supports
+///
+/// `$ref`s are inlined from `root`, `allOf` branches are merged, and `oneOf`/`anyOf`
+/// collapse to their first non-null variant (a null variant, like a `["T", "null"]`
+/// type, becomes `nullable`). Non-string enums and unknown keywords are dropped.
+fn gemini_schema(schema: &serde_json::Value, root: &serde_json::Value, depth: usize) -> serde_json::Value {
+    use serde_json::{json, Value};
+
+    let Some(object) = schema.as_object() else {
+        return json!({});
+    };
+    if depth > MAX_SCHEMA_DEPTH {
+        return json!({"type": "object"});
+    }
+    let mut schema = object.clone();
+
+    if let Some(target) = schema
+        .remove("$ref")
+        .and_then(|r| root.pointer(r.as_str()?.strip_prefix('#')?).cloned())
+    {
+        merge_schema(&mut schema, gemini_schema(&target, root, depth + 1));
+    }
+    if let Some(Value::Array(branches)) = schema.remove("allOf") {
+        for branch in &branches {
+            merge_schema(&mut schema, gemini_schema(branch, root, depth + 1));
+        }
+    }
+    for key in ["oneOf", "anyOf"] {
+        if let Some(Value::Array(variants)) = schema.remove(key) {
+            let is_null = |v: &&Value| v.get("type").and_then(Value::as_str) == Some("null");
+            if variants.iter().any(|v| is_null(&v)) {
+                schema.insert("nullable".into(), Value::Bool(true));
+            }
+            if let Some(variant) = variants.iter().find(|v| !is_null(v)) {
+                merge_schema(&mut schema, gemini_schema(variant, root, depth + 1));
+            }
+        }
+    }
+
+    if let Some(Value::Array(types)) = schema.get("type").cloned() {
+        if types.iter().any(|t| t == "null") {
+            schema.insert("nullable".into(), Value::Bool(true));
+        }
+        match types.into_iter().find(|t| t != "null") {
+            Some(first) => schema.insert("type".into(), first),
+            None => schema.remove("type"),
+        };
+    }
+    if let Some(value) = schema.remove("const") {
+        schema.entry("enum").or_insert(json!([value]));
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        if values.iter().all(Value::is_string) {
+            schema.entry("type").or_insert(json!("string"));
+        } else {
+            schema.remove("enum");
+        }
+    }
+
+    let mut out = serde_json::Map::new();
+    for (key, value) in schema {
+        if !SCHEMA_KEYWORDS.contains(&key.as_str()) {
+            continue;
+        }
+        let value = match key.as_str() {
+            "properties" => Value::Object(
+                value
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .map(|(name, property)| (name.clone(), gemini_schema(property, root, depth + 1)))
+                    .collect(),
+            ),
+            "items" => gemini_schema(&value, root, depth + 1),
+            _ => value,
+        };
+        out.insert(key, value);
+    }
+
+    let properties = out.get("properties").and_then(Value::as_object);
+    let required: Vec<Value> = out
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|name| name.as_str().is_some_and(|n| properties.is_some_and(|p| p.contains_key(n))))
+        .cloned()
+        .collect();
+    if required.is_empty() {
+        out.remove("required");
+    } else {
+        out.insert("required".into(), Value::Array(required));
+    }
+    Value::Object(out)
+}
+
+/// Fold `other` into `schema`: properties and required names are unioned, other keys kept if unset
+fn merge_schema(schema: &mut serde_json::Map<String, serde_json::Value>, other: serde_json::Value) {
+    use serde_json::Value;
+
+    let Value::Object(other) = other else {
+        return;
+    };
+    for (key, value) in other {
+        match (key.as_str(), schema.get_mut(&key), value) {
+            ("properties", Some(Value::Object(existing)), Value::Object(added)) => {
+                for (name, property) in added {
+                    existing.entry(name).or_insert(property);
+                }
+            }
+            ("required", Some(Value::Array(existing)), Value::Array(added)) => {
+                for name in added {
+                    if !existing.contains(&name) {
+                        existing.push(name);
+                    }
+                }
+            }
+            (_, Some(_), _) => {}
+            (_, None, value) => {
+                schema.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Normalize tool call arguments to the JSON object Gemini sends and expects
+///
+/// Arguments recorded as a JSON string are parsed, a missing value becomes `{}`,
+/// and any other non-object is wrapped as `{"value": ...}`.
+fn function_call_args(arguments: serde_json::Value) -> serde_json::Value {
+    use serde_json::Value;
+
+    match arguments {
+        Value::Object(_) => arguments,
+        Value::Null => Value::Object(Default::default()),
+        Value::String(text) => match serde_json::from_str::<Value>(&text) {
+            Ok(parsed) if !parsed.is_string() => function_call_args(parsed),
+            _ => serde_json::json!({ "value": text }),
+        },
+        other => serde_json::json!({ "value": other }),
+    }
+}
+
 impl Gemini {
+    /// Build the API request body for `request`
+    fn api_request(request: aagt_core::agent::provider::ChatRequest) -> GeminiRequest {
+        let aagt_core::agent::provider::ChatRequest {
+            model: _,
+            system_prompt,
+            messages,
+            tools,
+            temperature,
+            max_tokens,
+            max_reasoning_tokens,
+            extra_params: _,
+            headers: _,
+        } = request;
+
+        GeminiRequest {
+            system_instruction: Self::system_instruction(system_prompt, &messages, !tools.is_empty()),
+            contents: Self::convert_messages(messages),
+            generation_config: Some(GenerationConfig {
+                temperature,
+                max_output_tokens: max_tokens,
+                thinking_config: max_reasoning_tokens.map(|thinking_budget| ThinkingConfig { thinking_budget }),
+            }),
+            tools: Self::convert_tools(tools),
+        }
+    }
+
+    /// Collect the system prompt and system messages into `systemInstruction`
+    ///
+    /// When tools are declared natively the TypeScript tool catalog is left out so
+    /// the model doesn't see every definition twice.
+    fn system_instruction(
+        system_prompt: Option<String>,
+        messages: &[Message],
+        native_tools: bool,
+    ) -> Option<GeminiContent> {
+        let mut texts: Vec<String> = Vec::new();
+        let candidates = system_prompt
+            .into_iter()
+            .chain(messages.iter().filter(|m| m.role == Role::System).map(Message::text));
+        for text in candidates {
+            if text.trim().is_empty()
+                || (native_tools && text.starts_with(TOOL_CATALOG_HEADING))
+                || texts.iter().any(|t| t.contains(&text))
+            {
+                continue;
+            }
+            texts.push(text);
+        }
+
+        (!texts.is_empty()).then(|| GeminiContent {
+            role: "user".to_string(),
+            parts: texts.into_iter().map(|text| Part::Text { text }).collect(),
+        })
+    }
+
     fn convert_messages(messages: Vec<Message>) -> Vec<GeminiContent> {
+        // Gemini identifies tool results by function name; recover it from the call when missing
+        let mut call_names: HashMap<String, String> = HashMap::new();
+
         messages
             .into_iter()
             .filter(|m| m.role != Role::System)
@@ -155,22 +415,24 @@ impl Gemini {
                         .into_iter()
                         .filter_map(|p| match p {
                             aagt_core::agent::message::ContentPart::Text { text } => Some(Part::Text { text }),
-                            aagt_core::agent::message::ContentPart::ToolCall { name, arguments, .. } => {
+                            aagt_core::agent::message::ContentPart::ToolCall { id, name, arguments } => {
+                                call_names.insert(id, name.clone());
                                 Some(Part::FunctionCall {
                                     function_call: FunctionCall {
                                         name,
-                                        args: arguments,
+                                        args: function_call_args(arguments),
                                     }
                                 })
                             },
-                            aagt_core::agent::message::ContentPart::ToolResult { name, content, .. } => {
-                                // Gemini requires a name here. If it's missing, we are in trouble.
-                                // We fallback to "unknown" or hope caller provided it.
-                                let name = name.unwrap_or_else(|| "unknown".to_string());
-                                
-                                // Parse content as JSON if possible, otherwise wrap string
+                            aagt_core::agent::message::ContentPart::ToolResult { tool_call_id, name, content } => {
+                                let name = name
+                                    .or_else(|| call_names.get(&tool_call_id).cloned())
+                                    .unwrap_or_else(|| "unknown".to_string());
+
+                                // The response must be an object: keep JSON objects, wrap anything else
                                 let response_json = match serde_json::from_str::<serde_json::Value>(&content) {
-                                    Ok(v) => v,
+                                    Ok(v) if v.is_object() => v,
+                                    Ok(v) => serde_json::json!({ "result": v }),
                                     Err(_) => serde_json::json!({ "result": content })
                                 };
                                 
@@ -181,7 +443,13 @@ impl Gemini {
                                     }
                                 })
                             },
-                            _ => None // Images not supported yet
+                            aagt_core::agent::message::ContentPart::Image {
+                                source: ImageSource::Base64 { media_type, data },
+                            } => Some(Part::InlineData {
+                                inline_data: Blob { mime_type: media_type, data },
+                            }),
+                            // URLs would have to be uploaded to the Files API first
+                            part => part.placeholder().map(|text| Part::Text { text }),
                         })
                         .collect(),
                 };
@@ -202,10 +470,16 @@ impl Gemini {
         vec![GeminiTool {
             function_declarations: tools
                 .into_iter()
-                .map(|t| FunctionDeclaration {
-                    name: t.name,
-                    description: t.description,
-                    parameters: t.parameters,
+                .map(|t| {
+                    let parameters = gemini_schema(&t.parameters, &t.parameters, 0);
+                    let has_properties = parameters["properties"]
+                        .as_object()
+                        .is_some_and(|p| !p.is_empty());
+                    FunctionDeclaration {
+                        name: t.name,
+                        description: t.description,
+                        parameters: has_properties.then_some(parameters),
+                    }
                 })
                 .collect(),
         }]
@@ -218,49 +492,26 @@ impl Provider for Gemini {
         &self,
         request: aagt_core::agent::provider::ChatRequest,
     ) -> Result<StreamingResponse> {
-        let aagt_core::agent::provider::ChatRequest {
-            model,
-            system_prompt,
-            messages,
-            tools,
-            temperature,
-            max_tokens,
-            extra_params: _,
-        } = request;
-
-        let gemini_request = GeminiRequest {
-            contents: Self::convert_messages(messages),
-            system_instruction: system_prompt.map(|s| GeminiContent {
-                role: "user".to_string(),
-                parts: vec![Part::Text { text: s }],
-            }),
-            generation_config: Some(GenerationConfig {
-                temperature,
-                max_output_tokens: max_tokens,
-            }),
-            tools: Self::convert_tools(tools),
-        };
-
         let url = format!(
-            "{}{}:streamGenerateContent?alt=sse&key={}",
-            GEMINI_API_BASE, model, self.api_key
+            "{}/{}:streamGenerateContent?alt=sse&key={}",
+            GEMINI_API_BASE, request.model, self.api_key
         );
 
+        let mut request_headers = HeaderMap::new();
+        request_headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        extend_headers(&mut request_headers, &request.headers)?;
+
+        let gemini_request = Self::api_request(request);
         let response = self
             .client
             .post(&url)
-            .header(CONTENT_TYPE, "application/json")
+            .headers(request_headers)
             .json(&gemini_request)
             .send()
             .await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(Error::ProviderApi(format!(
-                "Gemini API error {}: {}",
-                status, text
-            )));
+            return Err(error_from_response("gemini", response, error_details).await);
         }
 
         let stream = response.bytes_stream();
@@ -272,6 +523,95 @@ impl Provider for Gemini {
     fn name(&self) -> &'static str {
         "gemini"
     }
+
+    fn supports_vision(&self) -> bool {
+        true
+    }
+}
+
+/// Read a Google API error body: `{"error": {"code", "message", "status", "details"}}`
+///
+/// The code is the `ErrorInfo` reason when there is one (e.g. `API_KEY_INVALID`,
+/// which comes with a plain 400), otherwise the status; `RetryInfo` gives the delay.
+fn error_details(body: &serde_json::Value) -> ErrorDetails {
+    let error = &body["error"];
+    let details = error["details"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let detail = |kind: &str| {
+        details.iter().find(|d| {
+            d["@type"]
+                .as_str()
+                .is_some_and(|t| t.ends_with(kind))
+        })
+    };
+    ErrorDetails {
+        code: detail("ErrorInfo")
+            .and_then(|d| d["reason"].as_str())
+            .or_else(|| error["status"].as_str())
+            .map(String::from),
+        message: error["message"].as_str().map(String::from),
+        retry_after: detail("RetryInfo")
+            .and_then(|d| d["retryDelay"].as_str())
+            .and_then(|delay| delay.strip_suffix('s')?.parse::<f64>().ok())
+            .and_then(retry_delay),
+    }
+}
+
+/// Synthesizes tool call IDs, which Gemini doesn't provide
+///
+/// IDs combine a per-stream nonce with a counter so calls from different turns
+/// in the same history don't collide.
+struct CallIds {
+    nonce: String,
+    next: usize,
+}
+
+impl CallIds {
+    fn new() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Self {
+            nonce: format!("{:x}", nanos),
+            next: 0,
+        }
+    }
+
+    fn next(&mut self) -> String {
+        self.next += 1;
+        format!("call_{}_{}", self.nonce, self.next)
+    }
+}
+
+/// Everything one SSE chunk carries, in order
+///
+/// A chunk can hold several parts (e.g. parallel function calls) and a finish
+/// reason alongside its final content, so nothing here may short-circuit.
+fn chunk_choices(chunk: StreamChunk, call_ids: &mut CallIds) -> Vec<StreamingChoice> {
+    let mut choices = Vec::new();
+    let Some(candidate) = chunk.candidates.and_then(|c| c.into_iter().next()) else {
+        return choices;
+    };
+
+    for part in candidate.content.and_then(|c| c.parts).unwrap_or_default() {
+        match part {
+            ResponsePart::Text { text } if !text.is_empty() => choices.push(StreamingChoice::Message(text)),
+            ResponsePart::FunctionCall { function_call } => choices.push(StreamingChoice::ToolCall {
+                id: call_ids.next(),
+                name: function_call.name,
+                arguments: function_call_args(function_call.args),
+            }),
+            ResponsePart::Thought { thought } if !thought.is_empty() => {
+                choices.push(StreamingChoice::Thought(thought))
+            }
+            _ => {}
+        }
+    }
+
+    if candidate.finish_reason.as_deref() == Some("STOP") {
+        choices.push(StreamingChoice::Done);
+    }
+    choices
 }
 
 /// Parse SSE stream from Gemini
@@ -283,12 +623,16 @@ where
 {
     let sse_buffer = crate::utils::SseBuffer::new();
     let string_buffer = String::new();
-    let tool_call_counter: usize = 0;
+    let pending: VecDeque<StreamingChoice> = VecDeque::new();
 
     futures::stream::unfold(
-        (stream, sse_buffer, string_buffer, tool_call_counter),
-        move |(mut stream, mut bytes_buffer, mut text_buffer, mut tool_counter)| async move {
+        (stream, sse_buffer, string_buffer, pending, CallIds::new()),
+        move |(mut stream, mut bytes_buffer, mut text_buffer, mut pending, mut call_ids)| async move {
             loop {
+                if let Some(choice) = pending.pop_front() {
+                    return Some((Ok(choice), (stream, bytes_buffer, text_buffer, pending, call_ids)));
+                }
+
                 // Try to extract complete SSE message
                 if let Some(pos) = text_buffer.find("\n\n") {
                     let line = text_buffer[..pos].to_string();
@@ -296,55 +640,7 @@ where
 
                     if let Some(data) = line.strip_prefix("data: ") {
                         match serde_json::from_str::<StreamChunk>(data) {
-                            Ok(chunk) => {
-                                if let Some(candidates) = chunk.candidates {
-                                    if let Some(candidate) = candidates.first() {
-                                        // Check finish reason
-                                        if candidate.finish_reason.as_deref() == Some("STOP") {
-                                            return Some((
-                                                Ok(StreamingChoice::Done),
-                                                (stream, bytes_buffer, text_buffer, tool_counter),
-                                            ));
-                                        }
-
-                                        if let Some(content) = &candidate.content {
-                                            if let Some(parts) = &content.parts {
-                                                for part in parts {
-                                                    match part {
-                                                        ResponsePart::Text { text } => {
-                                                            if !text.is_empty() {
-                                                                 return Some((
-                                                                    Ok(StreamingChoice::Message(text.clone())),
-                                                                    (stream, bytes_buffer, text_buffer, tool_counter),
-                                                                ));
-                                                            }
-                                                        }
-                                                        ResponsePart::FunctionCall { function_call } => {
-                                                            tool_counter += 1;
-                                                            return Some((
-                                                                Ok(StreamingChoice::ToolCall {
-                                                                    id: format!("call_{}", tool_counter),
-                                                                    name: function_call.name.clone(),
-                                                                    arguments: function_call.args.clone(),
-                                                                }),
-                                                                (stream, bytes_buffer, text_buffer, tool_counter),
-                                                            ));
-                                                        }
-                                                        ResponsePart::Thought { thought } => {
-                                                            if !thought.is_empty() {
-                                                                return Some((
-                                                                    Ok(StreamingChoice::Thought(thought.clone())),
-                                                                    (stream, bytes_buffer, text_buffer, tool_counter),
-                                                                ));
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+                            Ok(chunk) => pending.extend(chunk_choices(chunk, &mut call_ids)),
                             Err(e) => {
                                 tracing::debug!("Failed to parse Gemini chunk: {}", e);
                             }
@@ -363,15 +659,15 @@ where
                             Err(e) => {
                                 return Some((
                                     Err(e),
-                                    (stream, bytes_buffer, text_buffer, tool_counter),
+                                    (stream, bytes_buffer, text_buffer, pending, call_ids),
                                 ));
                             }
                         }
                     }
                     Some(Err(e)) => {
                         return Some((
-                            Err(Error::Http(e)),
-                            (stream, bytes_buffer, text_buffer, tool_counter),
+                            Err(Error::from(e)),
+                            (stream, bytes_buffer, text_buffer, pending, call_ids),
                         ));
                     }
                     None => return None,
@@ -394,6 +690,8 @@ pub const GEMINI_1_5_FLASH: &str = "gemini-1.5-flash";
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aagt_core::agent::message::ContentPart;
+    use aagt_core::agent::provider::ChatRequest;
 
     #[test]
     fn test_message_conversion() {
@@ -414,10 +712,206 @@ mod tests {
             name: "test".to_string(),
             description: "A test tool".to_string(),
             parameters: serde_json::json!({"type": "object"}),
+            is_verified: true,
+            ..Default::default()
         }];
 
         let converted = Gemini::convert_tools(tools);
         assert_eq!(converted.len(), 1);
         assert_eq!(converted[0].function_declarations.len(), 1);
     }
+
+    #[test]
+    fn test_schema_is_flattened_to_the_supported_subset() {
+        let schema = serde_json::json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "SwapArgs",
+            "type": "object",
+            "additionalProperties": false,
+            "properties": {
+                "order": {"$ref": "#/$defs/Order", "description": "What to trade"},
+                "slippage": {"type": ["number", "null"], "default": 0.5},
+                "venue": {"anyOf": [{"type": "null"}, {"type": "string", "enum": ["jupiter", "raydium"]}]},
+                "side": {"const": "buy"},
+                "route": {"oneOf": [{"type": "array", "items": {"type": "string"}}, {"type": "string"}]}
+            },
+            "required": ["order", "ghost"],
+            "$defs": {
+                "Order": {
+                    "type": "object",
+                    "properties": {"token": {"type": "string"}, "amount": {"type": "number", "exclusiveMinimum": 0}},
+                    "required": ["token"]
+                }
+            }
+        });
+
+        let converted = gemini_schema(&schema, &schema, 0);
+        assert_eq!(
+            converted,
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "order": {
+                        "type": "object",
+                        "description": "What to trade",
+                        "properties": {"token": {"type": "string"}, "amount": {"type": "number"}},
+                        "required": ["token"]
+                    },
+                    "slippage": {"type": "number", "nullable": true},
+                    "venue": {"type": "string", "enum": ["jupiter", "raydium"], "nullable": true},
+                    "side": {"type": "string", "enum": ["buy"]},
+                    "route": {"type": "array", "items": {"type": "string"}}
+                },
+                "required": ["order"]
+            })
+        );
+
+        let recursive = serde_json::json!({
+            "type": "object",
+            "properties": {"node": {"$ref": "#"}}
+        });
+        assert!(gemini_schema(&recursive, &recursive, 0).is_object());
+    }
+
+    #[test]
+    fn test_request_uses_native_tools_and_system_instruction() {
+        let tool = |name: &str, parameters| ToolDefinition {
+            name: name.to_string(),
+            description: format!("{} tool", name),
+            parameters,
+            is_verified: true,
+            side_effect_free: true,
+            ..Default::default()
+        };
+        let messages = vec![
+            Message::system("You are a trading agent."),
+            Message::system(format!("{}\n\nfunction get_price(...)", TOOL_CATALOG_HEADING)),
+            Message::user("What is SOL at?"),
+            Message::assistant(Content::Parts(vec![ContentPart::ToolCall {
+                id: "call_1".to_string(),
+                name: "get_price".to_string(),
+                arguments: serde_json::json!(r#"{"symbol": "SOL"}"#),
+            }])),
+            Message::tool_result("call_1", "150.2"),
+        ];
+        let request = ChatRequest {
+            model: GEMINI_1_5_PRO.to_string(),
+            system_prompt: Some("You are a trading agent.".to_string()),
+            messages,
+            tools: vec![
+                tool("get_price", serde_json::json!({"type": "object", "properties": {"symbol": {"type": "string"}}, "required": ["symbol"]})),
+                tool("get_time", serde_json::json!({"type": "object", "properties": {}})),
+            ],
+            ..Default::default()
+        };
+
+        let body = serde_json::to_value(Gemini::api_request(request)).unwrap();
+        assert_eq!(
+            body["systemInstruction"]["parts"],
+            serde_json::json!([{"text": "You are a trading agent."}]),
+            "the TypeScript catalog is left out when tools are declared natively"
+        );
+        assert_eq!(
+            body["contents"][1]["parts"][0],
+            serde_json::json!({"functionCall": {"name": "get_price", "args": {"symbol": "SOL"}}})
+        );
+        assert_eq!(
+            body["contents"][2]["parts"][0],
+            serde_json::json!({"functionResponse": {"name": "get_price", "response": {"result": 150.2}}})
+        );
+        assert_eq!(
+            body["tools"][0]["functionDeclarations"],
+            serde_json::json!([
+                {
+                    "name": "get_price",
+                    "description": "get_price tool",
+                    "parameters": {"type": "object", "properties": {"symbol": {"type": "string"}}, "required": ["symbol"]}
+                },
+                {"name": "get_time", "description": "get_time tool"}
+            ])
+        );
+    }
+
+    async fn parse_payloads(payloads: &[&str]) -> Vec<StreamingChoice> {
+        let body: String = payloads.iter().map(|p| format!("data: {}\n\n", p)).collect();
+        let bytes = futures::stream::iter(vec![Ok::<_, reqwest::Error>(bytes::Bytes::from(body))]);
+        parse_gemini_stream(bytes).map(|c| c.unwrap()).collect().await
+    }
+
+    fn tool_calls(choices: &[StreamingChoice]) -> Vec<(String, String, serde_json::Value)> {
+        choices
+            .iter()
+            .filter_map(|c| match c {
+                StreamingChoice::ToolCall { id, name, arguments } => Some((id.clone(), name.clone(), arguments.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_stream_parses_a_function_call() {
+        let choices = parse_payloads(&[
+            r#"{"candidates": [{"content": {"role": "model", "parts": [{"functionCall": {"name": "get_price", "args": {"symbol": "SOL"}}}]}, "finishReason": "STOP"}]}"#,
+        ])
+        .await;
+
+        let calls = tool_calls(&choices);
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].0.starts_with("call_"));
+        assert_eq!(calls[0].1, "get_price");
+        assert_eq!(calls[0].2, serde_json::json!({"symbol": "SOL"}));
+        assert!(matches!(choices.last(), Some(StreamingChoice::Done)));
+    }
+
+    #[tokio::test]
+    async fn test_stream_parses_multiple_function_calls() {
+        let choices = parse_payloads(&[
+            r#"{"candidates": [{"content": {"role": "model", "parts": [{"text": "Checking both."}]}}]}"#,
+            r#"{"candidates": [{"content": {"role": "model", "parts": [
+                {"functionCall": {"name": "get_price", "args": {"symbol": "SOL"}}},
+                {"functionCall": {"name": "get_price", "args": {"symbol": "ETH"}}},
+                {"functionCall": {"name": "get_time"}}
+            ]}, "finishReason": "STOP"}]}"#,
+        ])
+        .await;
+
+        assert!(matches!(&choices[0], StreamingChoice::Message(text) if text == "Checking both."));
+        let calls = tool_calls(&choices);
+        let args: Vec<_> = calls.iter().map(|(_, _, args)| args.clone()).collect();
+        assert_eq!(
+            args,
+            vec![serde_json::json!({"symbol": "SOL"}), serde_json::json!({"symbol": "ETH"}), serde_json::json!({})]
+        );
+        let ids: std::collections::HashSet<_> = calls.iter().map(|(id, _, _)| id.clone()).collect();
+        assert_eq!(ids.len(), 3);
+        assert!(matches!(choices.last(), Some(StreamingChoice::Done)));
+    }
+
+    #[test]
+    fn test_error_body_parsing() {
+        let body = r#"{"error": {"code": 429, "message": "Resource has been exhausted", "status": "RESOURCE_EXHAUSTED", "details": [
+            {"@type": "type.googleapis.com/google.rpc.RetryInfo", "retryDelay": "7.5s"}
+        ]}}"#;
+        let error = crate::utils::provider_error("gemini", 429, None, body, error_details);
+        assert!(matches!(
+            &error,
+            Error::Provider { code: Some(code), message, .. }
+                if code == "RESOURCE_EXHAUSTED" && message == "Resource has been exhausted"
+        ));
+        assert_eq!(error.retry_after(), Some(std::time::Duration::from_millis(7500)));
+        assert!(error.is_retryable());
+
+        let body = r#"{"error": {"code": 429, "message": "Slow down", "status": "RESOURCE_EXHAUSTED", "details": [
+            {"@type": "type.googleapis.com/google.rpc.RetryInfo", "retryDelay": "1e30s"}
+        ]}}"#;
+        let error = crate::utils::provider_error("gemini", 429, None, body, error_details);
+        assert_eq!(error.retry_after(), None);
+
+        let body = r#"{"error": {"code": 400, "message": "API key not valid. Please pass a valid API key.", "status": "INVALID_ARGUMENT", "details": [
+            {"@type": "type.googleapis.com/google.rpc.ErrorInfo", "reason": "API_KEY_INVALID", "domain": "googleapis.com"}
+        ]}}"#;
+        let error = crate::utils::provider_error("gemini", 400, None, body, error_details);
+        assert!(error.is_auth_failure());
+        assert!(!error.is_invalid_request());
+    }
 }
@@ -21,7 +21,8 @@ pub struct Groq {
 impl Groq {
     /// Create from API key
     pub fn new(api_key: impl Into<String>) -> Result<Self> {
-        let inner = OpenAI::with_base_url(api_key, "https://api.groq.com/openai/v1")?;
+        let inner = OpenAI::with_base_url(api_key, "https://api.groq.com/openai/v1")?
+            .with_errors("groq", crate::openai::error_details);
         Ok(Self { inner })
     }
 
@@ -42,6 +43,13 @@ impl Provider for Groq {
         self.inner.stream_completion(request).await
     }
 
+    async fn complete(
+        &self,
+        request: aagt_core::agent::provider::ChatRequest,
+    ) -> Result<aagt_core::agent::provider::CompletionResponse> {
+        self.inner.complete(request).await
+    }
+
     fn name(&self) -> &'static str {
         "groq"
     }
@@ -1,14 +1,47 @@
 //! Mock provider for testing
 
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use async_trait::async_trait;
+use parking_lot::Mutex;
+
+use crate::{Result, StreamingResponse, Provider};
+use aagt_core::agent::message::ToolCall;
+use aagt_core::agent::provider::{ChatRequest, CompletionResponse};
+use aagt_core::agent::streaming::{MockStreamBuilder, Usage};
+
+/// One scripted reply of a [`MockProvider`]
+#[derive(Debug, Clone)]
+pub enum MockTurn {
+    /// Answer with text
+    Text(String),
+    /// Call tools: `(name, arguments)` pairs, with ids assigned in order
+    ToolCalls(Vec<(String, serde_json::Value)>),
+}
+
+impl MockTurn {
+    /// Reply with `text`
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
 
-use crate::{Result, Message, StreamingResponse, ToolDefinition, Provider};
-use aagt_core::agent::streaming::MockStreamBuilder;
+    /// Call a single tool
+    pub fn tool_call(name: impl Into<String>, arguments: serde_json::Value) -> Self {
+        Self::ToolCalls(vec![(name.into(), arguments)])
+    }
+}
 
 /// A mock provider for testing
 pub struct MockProvider {
     /// Response to return
     response: String,
+    /// Replies returned in order before falling back to `response`
+    script: Mutex<VecDeque<MockTurn>>,
+    /// Tool call ids handed out so far
+    calls: AtomicUsize,
+    /// Usage reported with every reply
+    usage: Option<Usage>,
 }
 
 impl MockProvider {
@@ -16,19 +49,56 @@ impl MockProvider {
     pub fn new(response: impl Into<String>) -> Self {
         Self {
             response: response.into(),
+            script: Mutex::new(VecDeque::new()),
+            calls: AtomicUsize::new(0),
+            usage: None,
         }
     }
-}
 
-#[async_trait]
-impl Provider for MockProvider {
-    async fn stream_completion(
-        &self,
-        _request: aagt_core::agent::provider::ChatRequest,
-    ) -> Result<StreamingResponse> {
+    /// Mock that plays `turns` one per request, then answers with `fallback`
+    pub fn scripted(turns: impl IntoIterator<Item = MockTurn>, fallback: impl Into<String>) -> Self {
+        Self {
+            script: Mutex::new(turns.into_iter().collect()),
+            ..Self::new(fallback)
+        }
+    }
+
+    /// Report `usage` with every reply
+    pub fn with_usage(mut self, usage: Usage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+
+    /// Scripted replies not played yet
+    pub fn remaining(&self) -> usize {
+        self.script.lock().len()
+    }
+
+    /// Next reply: the next scripted turn, or the fallback text
+    fn next_reply(&self) -> Result<CompletionResponse> {
+        let turn = self.script.lock().pop_front();
+        let mut reply = CompletionResponse {
+            usage: self.usage.clone(),
+            finish_reason: Some("stop".to_string()),
+            ..Default::default()
+        };
+        match turn {
+            Some(MockTurn::Text(text)) => reply.text = text,
+            Some(MockTurn::ToolCalls(calls)) => {
+                for (name, arguments) in calls {
+                    let n = self.calls.fetch_add(1, Ordering::Relaxed) + 1;
+                    reply.tool_calls.push(ToolCall::new(format!("call_{}", n), name, arguments));
+                }
+                reply.finish_reason = Some("tool_calls".to_string());
+            }
+            None => reply.text = self.response.clone(),
+        }
+        Ok(reply)
+    }
+
+    fn text_stream(text: &str) -> MockStreamBuilder {
         // Split response into chunks for realistic streaming simulation
-        let chunks: Vec<String> = self
-            .response
+        let chunks: Vec<String> = text
             .chars()
             .collect::<Vec<_>>()
             .chunks(10)
@@ -39,9 +109,26 @@ impl Provider for MockProvider {
         for chunk in chunks {
             builder = builder.message(chunk);
         }
-        builder = builder.done();
+        builder
+    }
+}
 
-        Ok(builder.build())
+#[async_trait]
+impl Provider for MockProvider {
+    async fn stream_completion(&self, _request: ChatRequest) -> Result<StreamingResponse> {
+        let reply = self.next_reply()?;
+        let mut builder = Self::text_stream(&reply.text);
+        for call in reply.tool_calls {
+            builder = builder.tool_call(call.id, call.name, call.arguments);
+        }
+        if let Some(usage) = reply.usage {
+            builder = builder.usage(usage);
+        }
+        Ok(builder.done().build())
+    }
+
+    async fn complete(&self, _request: ChatRequest) -> Result<CompletionResponse> {
+        self.next_reply()
     }
 
     fn name(&self) -> &'static str {
@@ -52,6 +139,7 @@ impl Provider for MockProvider {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Message;
 
     #[tokio::test]
     async fn test_mock_provider() {
@@ -68,5 +156,37 @@ mod tests {
         let text = stream.collect_text().await.expect("collect should succeed");
         assert_eq!(text, "Hello, world!");
     }
-}
 
+    #[tokio::test]
+    async fn test_scripted_turns_play_in_order() {
+        use aagt_core::agent::streaming::StreamingChoice;
+        use futures::StreamExt;
+
+        let provider = MockProvider::scripted(
+            [
+                MockTurn::tool_call("get_price", serde_json::json!({"symbol": "SOL"})),
+                MockTurn::text("SOL is $150."),
+            ],
+            "Done.",
+        );
+        let request = || aagt_core::agent::provider::ChatRequest {
+            model: "test".to_string(),
+            messages: vec![Message::user("Price?")],
+            ..Default::default()
+        };
+
+        let mut first = provider.stream_completion(request()).await.expect("should succeed").into_inner();
+        match first.next().await {
+            Some(Ok(StreamingChoice::ToolCall { id, name, .. })) => {
+                assert_eq!(id, "call_1");
+                assert_eq!(name, "get_price");
+            }
+            other => panic!("expected a tool call, got {:?}", other),
+        }
+        for expected in ["SOL is $150.", "Done.", "Done."] {
+            let stream = provider.stream_completion(request()).await.expect("should succeed");
+            assert_eq!(stream.collect_text().await.expect("collect should succeed"), expected);
+        }
+        assert_eq!(provider.remaining(), 0);
+    }
+}
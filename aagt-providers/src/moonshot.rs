@@ -16,7 +16,8 @@ pub struct Moonshot {
 impl Moonshot {
     /// Create from API key
     pub fn new(api_key: impl Into<String>) -> Result<Self> {
-        let inner = OpenAI::with_base_url(api_key, "https://api.moonshot.cn/v1")?;
+        let inner = OpenAI::with_base_url(api_key, "https://api.moonshot.cn/v1")?
+            .with_errors("moonshot", crate::openai::error_details);
         Ok(Self { inner })
     }
 
@@ -37,6 +38,13 @@ impl Provider for Moonshot {
         self.inner.stream_completion(request).await
     }
 
+    async fn complete(
+        &self,
+        request: aagt_core::agent::provider::ChatRequest,
+    ) -> Result<aagt_core::agent::provider::CompletionResponse> {
+        self.inner.complete(request).await
+    }
+
     fn name(&self) -> &'static str {
         "moonshot"
     }
@@ -36,7 +36,8 @@ impl Ollama {
     /// ```
     pub fn new(base_url: impl Into<String>) -> Result<Self> {
         // Ollama doesn't require an API key, use dummy key
-        let inner = OpenAI::with_base_url("ollama", base_url)?;
+        let inner = OpenAI::with_base_url("ollama", base_url)?
+            .with_errors("ollama", crate::openai::error_details);
         Ok(Self { inner })
     }
 
@@ -60,6 +61,13 @@ impl Provider for Ollama {
         self.inner.stream_completion(request).await
     }
 
+    async fn complete(
+        &self,
+        request: aagt_core::agent::provider::ChatRequest,
+    ) -> Result<aagt_core::agent::provider::CompletionResponse> {
+        self.inner.complete(request).await
+    }
+
     fn name(&self) -> &'static str {
         "ollama"
     }
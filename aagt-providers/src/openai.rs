@@ -4,19 +4,32 @@
 
 
 
+use std::collections::BTreeMap;
+
 use async_trait::async_trait;
 use futures::{Stream, StreamExt};
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
 
 use crate::{Error, Result, Message, StreamingChoice, StreamingResponse, ToolDefinition, Provider, HttpConfig};
-use aagt_core::agent::message::{Role, Content};
+use crate::utils::{error_from_response, extend_headers, ErrorDetails, ErrorParser};
+use aagt_core::agent::message::{Role, Content, ImageSource};
+use aagt_core::agent::provider::{ChatRequest, CompletionResponse, KeyPool, PooledProvider};
+use aagt_core::agent::streaming::{ResponseMetadata, Usage};
+use aagt_core::infra::secrets::SecretProvider;
 
 /// OpenAI API client
 pub struct OpenAI {
     client: reqwest::Client,
     api_key: String,
     base_url: String,
+    /// Provider named in errors
+    provider: &'static str,
+    error_parser: ErrorParser,
+    /// Provider-specific fields added to every request body
+    body_fields: serde_json::Map<String, serde_json::Value>,
+    /// Body field carrying [`ChatRequest::max_reasoning_tokens`], if the API takes a budget
+    reasoning_budget_field: Option<&'static str>,
 }
 
 impl OpenAI {
@@ -25,6 +38,11 @@ impl OpenAI {
         Self::with_base_url(api_key, "https://api.openai.com/v1")
     }
 
+    /// Spread requests across a pool of keys resolved through `secrets`
+    pub fn pooled(pool: KeyPool, secrets: &dyn SecretProvider) -> Result<PooledProvider<Self>> {
+        PooledProvider::new(pool, secrets, |key| Self::new(key))
+    }
+
     /// Create from environment variable
     pub fn from_env() -> Result<Self> {
         let api_key = std::env::var("OPENAI_API_KEY")
@@ -41,9 +59,32 @@ impl OpenAI {
             client,
             api_key: api_key.into(),
             base_url: base_url.into(),
+            provider: "openai",
+            error_parser: error_details,
+            body_fields: serde_json::Map::new(),
+            reasoning_budget_field: None,
         })
     }
 
+    /// Add `fields` to every request body, for APIs that extend the OpenAI format
+    pub(crate) fn with_body_fields(mut self, fields: serde_json::Map<String, serde_json::Value>) -> Self {
+        self.body_fields = fields;
+        self
+    }
+
+    /// Send reasoning budgets in the body field `field`
+    pub(crate) fn with_reasoning_budget(mut self, field: &'static str) -> Self {
+        self.reasoning_budget_field = Some(field);
+        self
+    }
+
+    /// Report errors as coming from `provider`, reading their bodies with `parse`
+    pub(crate) fn with_errors(mut self, provider: &'static str, parse: ErrorParser) -> Self {
+        self.provider = provider;
+        self.error_parser = parse;
+        self
+    }
+
     /// Create for Groq
     pub fn groq(api_key: impl Into<String>) -> Result<Self> {
         Self::with_base_url(api_key, "https://api.groq.com/openai/v1")
@@ -54,7 +95,7 @@ impl OpenAI {
         Self::with_base_url(api_key, "https://api.mistral.ai/v1")
     }
 
-    fn build_headers(&self) -> Result<HeaderMap> {
+    fn build_headers(&self, extra: &BTreeMap<String, String>) -> Result<HeaderMap> {
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
         headers.insert(
@@ -62,13 +103,14 @@ impl OpenAI {
             HeaderValue::from_str(&format!("Bearer {}", self.api_key))
                 .map_err(|e| Error::Internal(e.to_string()))?,
         );
+        extend_headers(&mut headers, extra)?;
         Ok(headers)
     }
 }
 
 /// OpenAI chat completion request (Internal API structure)
 #[derive(Debug, Serialize)]
-struct OpenAIChatRequest {
+pub(crate) struct OpenAIChatRequest {
     model: String,
     messages: Vec<OpenAIMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -80,6 +122,17 @@ struct OpenAIChatRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     response_format: Option<ResponseFormat>,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+    /// Fields set with [`OpenAI::with_body_fields`]
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Ask for a final streamed chunk carrying token usage
+#[derive(Debug, Serialize)]
+struct StreamOptions {
+    include_usage: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -131,7 +184,14 @@ struct OpenAIToolFunction {
 /// Streaming chunk from OpenAI
 #[derive(Debug, Deserialize)]
 struct StreamChunk {
+    #[serde(default)]
     choices: Vec<StreamChoice>,
+    /// Model that served the request
+    model: Option<String>,
+    /// Upstream provider, sent by routers such as OpenRouter
+    provider: Option<String>,
+    /// Set on the final chunk when `stream_options.include_usage` is sent
+    usage: Option<Usage>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -143,6 +203,8 @@ struct StreamChoice {
 #[derive(Debug, Deserialize)]
 struct StreamDelta {
     content: Option<String>,
+    /// Thinking of reasoning models (DeepSeek R1), streamed apart from `content`
+    reasoning_content: Option<String>,
     tool_calls: Option<Vec<StreamToolCall>>,
 }
 
@@ -159,7 +221,114 @@ struct StreamFunction {
     arguments: Option<String>,
 }
 
+/// Non-streaming completion from OpenAI
+#[derive(Debug, Deserialize)]
+struct CompletionBody {
+    choices: Vec<CompletionChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionChoice {
+    message: CompletionMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionMessage {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<OpenAIToolCall>,
+}
+
+impl CompletionBody {
+    fn into_response(self) -> CompletionResponse {
+        let usage = self.usage;
+        let Some(choice) = self.choices.into_iter().next() else {
+            return CompletionResponse { usage, ..Default::default() };
+        };
+        CompletionResponse {
+            text: choice.message.content.unwrap_or_default(),
+            tool_calls: choice
+                .message
+                .tool_calls
+                .into_iter()
+                .map(|tc| aagt_core::agent::message::ToolCall {
+                    id: tc.id,
+                    name: tc.function.name,
+                    arguments: serde_json::from_str(&tc.function.arguments)
+                        .unwrap_or(serde_json::Value::Null),
+                })
+                .collect(),
+            usage,
+            finish_reason: choice.finish_reason,
+        }
+    }
+}
+
 impl OpenAI {
+    /// Build the API request body for `request`
+    pub(crate) fn api_request(&self, request: ChatRequest, stream: bool) -> OpenAIChatRequest {
+        let ChatRequest {
+            model,
+            system_prompt,
+            messages,
+            tools,
+            temperature,
+            max_tokens,
+            max_reasoning_tokens,
+            extra_params,
+            headers: _,
+        } = request;
+
+        // Check for response_format in extra_params
+        let response_format = extra_params
+            .as_ref()
+            .and_then(|params| params.get("response_format"))
+            .and_then(|format_val| serde_json::from_value(format_val.clone()).ok());
+
+        let mut extra = self.body_fields.clone();
+        if let (Some(field), Some(budget)) = (self.reasoning_budget_field, max_reasoning_tokens) {
+            extra.insert(field.to_string(), budget.into());
+        }
+
+        // For OpenAI, we still MUST send the JSON schema in the `tools` parameter,
+        // even when tools have TS interfaces.
+        OpenAIChatRequest {
+            model,
+            messages: Self::convert_messages(system_prompt.as_deref(), messages),
+            temperature,
+            max_tokens,
+            tools: Self::convert_tools(tools),
+            response_format,
+            stream,
+            stream_options: stream.then_some(StreamOptions {
+                include_usage: true,
+            }),
+            extra,
+        }
+    }
+
+    /// POST `api_request` to the chat completions endpoint, with the request's extra `headers`
+    async fn send(
+        &self,
+        api_request: &OpenAIChatRequest,
+        headers: &BTreeMap<String, String>,
+    ) -> Result<reqwest::Response> {
+        let response = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .headers(self.build_headers(headers)?)
+            .json(api_request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(error_from_response(self.provider, response, self.error_parser).await);
+        }
+        Ok(response)
+    }
+
     fn convert_messages(
         system_prompt: Option<&str>,
         messages: Vec<Message>,
@@ -210,8 +379,8 @@ impl OpenAI {
                                     aagt_core::agent::message::ContentPart::Image { source } => {
                                 // Fix #8: Support Images (Url and Base64)
                                 let url = match source {
-                                    aagt_core::agent::message::ImageSource::Url { url } => url,
-                                    aagt_core::agent::message::ImageSource::Base64 { media_type, data } => {
+                                    ImageSource::Url { url } => url,
+                                    ImageSource::Base64 { media_type, data } => {
                                         format!("data:{};base64,{}", media_type, data)
                                     }
                                 };
@@ -238,6 +407,14 @@ impl OpenAI {
                                 tool_call_id = Some(id);
                                 text_acc = content; // Tool result content is simple string usually
                             },
+                            file @ aagt_core::agent::message::ContentPart::FileRef { .. } => {
+                                let text = file.placeholder().unwrap_or_default();
+                                text_acc.push_str(&text);
+                                json_parts.push(serde_json::json!({
+                                    "type": "text",
+                                    "text": text
+                                }));
+                            },
                             // Audio/Video skipped for now
 
                         }
@@ -297,63 +474,9 @@ impl OpenAI {
 
 #[async_trait]
 impl Provider for OpenAI {
-    async fn stream_completion(
-        &self,
-        request: aagt_core::agent::provider::ChatRequest,
-    ) -> Result<StreamingResponse> {
-        let aagt_core::agent::provider::ChatRequest {
-            model,
-            system_prompt,
-            messages,
-            tools,
-            temperature,
-            max_tokens,
-            extra_params,
-        } = request;
-
-        // Check for response_format in extra_params
-        let response_format = if let Some(params) = &extra_params {
-            if let Some(format_val) = params.get("response_format") {
-                 serde_json::from_value(format_val.clone()).ok()
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-
-        let request_messages = Self::convert_messages(system_prompt.as_deref(), messages);
-
-        // If tools have TS interfaces, we might want to prioritize them.
-        // For OpenAI, we still MUST send the JSON schema in the `tools` parameter.
-        // However, we can enhance the system prompt or tool descriptions.
-        
-        let api_request = OpenAIChatRequest {
-            model: model.to_string(),
-            messages: request_messages,
-            temperature,
-            max_tokens,
-            tools: Self::convert_tools(tools),
-            response_format,
-            stream: true,
-        };
-
-        let response = self
-            .client
-            .post(format!("{}/chat/completions", self.base_url))
-            .headers(self.build_headers()?)
-            .json(&api_request)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(Error::ProviderApi(format!(
-                "OpenAI API error {}: {}",
-                status, text
-            )));
-        }
+    async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+        let headers = request.headers.clone();
+        let response = self.send(&self.api_request(request, true), &headers).await?;
 
         // Parse SSE stream
         let stream = response.bytes_stream();
@@ -362,9 +485,45 @@ impl Provider for OpenAI {
         Ok(StreamingResponse::from_stream(parsed_stream))
     }
 
+    async fn complete(&self, request: ChatRequest) -> Result<CompletionResponse> {
+        let headers = request.headers.clone();
+        let response = self.send(&self.api_request(request, false), &headers).await?;
+        let body: CompletionBody = response.json().await?;
+        Ok(body.into_response())
+    }
+
     fn name(&self) -> &'static str {
         "openai"
     }
+
+    fn supports_vision(&self) -> bool {
+        true
+    }
+}
+
+/// Read an OpenAI-style error body: `{"error": {"message", "type", "code"}}`
+///
+/// `code` (e.g. `invalid_api_key`) is preferred over the broader `type`.
+pub(crate) fn error_details(body: &serde_json::Value) -> ErrorDetails {
+    let error = &body["error"];
+    ErrorDetails {
+        code: error["code"]
+            .as_str()
+            .or_else(|| error["type"].as_str())
+            .map(String::from),
+        message: error["message"].as_str().map(String::from),
+        retry_after: None,
+    }
+}
+
+/// `data` with the first choice's `reasoning_content` removed
+fn without_reasoning(data: &str) -> Option<String> {
+    let mut chunk: serde_json::Value = serde_json::from_str(data).ok()?;
+    chunk
+        .pointer_mut("/choices/0/delta")?
+        .as_object_mut()?
+        .remove("reasoning_content");
+    Some(chunk.to_string())
 }
 
 /// Parse Server-Sent Events stream from OpenAI
@@ -387,8 +546,8 @@ where
     let current_tools: std::collections::HashMap<usize, ToolCallState> = std::collections::HashMap::new();
 
     futures::stream::unfold(
-        (stream, sse_buffer, string_buffer, current_tools),
-        move |(mut stream, mut bytes_buffer, mut text_buffer, mut current_tools)| async move {
+        (stream, sse_buffer, string_buffer, current_tools, false),
+        move |(mut stream, mut bytes_buffer, mut text_buffer, mut current_tools, mut announced)| async move {
             loop {
                 // Try to extract a complete SSE message from buffer
                 if let Some(pos) = text_buffer.find("\n\n") {
@@ -398,18 +557,49 @@ where
                     // Parse the SSE message
                     if let Some(data) = message.strip_prefix("data: ") {
                         if data.trim() == "[DONE]" {
-                            return Some((Ok(StreamingChoice::Done), (stream, bytes_buffer, text_buffer, current_tools)));
+                            return Some((Ok(StreamingChoice::Done), (stream, bytes_buffer, text_buffer, current_tools, announced)));
                         }
 
                         match serde_json::from_str::<StreamChunk>(data) {
                             Ok(chunk) => {
+                                // Announce the serving model once, then handle this chunk again
+                                if let (false, Some(model)) = (announced, &chunk.model) {
+                                    announced = true;
+                                    text_buffer.insert_str(0, &format!("{}\n\n", message));
+                                    let metadata = ResponseMetadata {
+                                        model: Some(model.clone()),
+                                        provider: chunk.provider.clone(),
+                                    };
+                                    return Some((
+                                        Ok(StreamingChoice::Metadata(metadata)),
+                                        (stream, bytes_buffer, text_buffer, current_tools, announced),
+                                    ));
+                                }
+
                                 if let Some(choice) = chunk.choices.first() {
+                                    if let Some(reasoning) = choice.delta.reasoning_content.as_deref().filter(|r| !r.is_empty()) {
+                                        // Emit the thinking, then handle whatever else the chunk carries
+                                        let rest = choice.delta.content.as_deref().is_some_and(|c| !c.is_empty())
+                                            || choice.delta.tool_calls.is_some()
+                                            || choice.finish_reason.is_some()
+                                            || chunk.usage.is_some();
+                                        if rest {
+                                            if let Some(data) = without_reasoning(data) {
+                                                text_buffer.insert_str(0, &format!("data: {}\n\n", data));
+                                            }
+                                        }
+                                        return Some((
+                                            Ok(StreamingChoice::Thought(reasoning.to_string())),
+                                            (stream, bytes_buffer, text_buffer, current_tools, announced),
+                                        ));
+                                    }
+
                                     // Check for content
                                     if let Some(content) = &choice.delta.content {
                                         if !content.is_empty() {
                                             return Some((
                                                 Ok(StreamingChoice::Message(content.clone())),
-                                                (stream, bytes_buffer, text_buffer, current_tools),
+                                                (stream, bytes_buffer, text_buffer, current_tools, announced),
                                             ));
                                         }
                                     }
@@ -467,11 +657,18 @@ where
                                         if !tools_map.is_empty() {
                                             return Some((
                                                 Ok(StreamingChoice::ParallelToolCalls(tools_map)),
-                                                (stream, bytes_buffer, text_buffer, current_tools),
+                                                (stream, bytes_buffer, text_buffer, current_tools, announced),
                                             ));
                                         }
                                     }
                                 }
+
+                                if let Some(usage) = chunk.usage {
+                                    return Some((
+                                        Ok(StreamingChoice::Usage(usage)),
+                                        (stream, bytes_buffer, text_buffer, current_tools, announced),
+                                    ));
+                                }
                             }
                             Err(e) => {
                                 tracing::warn!("Failed to parse SSE chunk: {}", e);
@@ -492,15 +689,15 @@ where
                             Err(e) => {
                                 return Some((
                                     Err(e),
-                                    (stream, bytes_buffer, text_buffer, current_tools),
+                                    (stream, bytes_buffer, text_buffer, current_tools, announced),
                                 ));
                             }
                         }
                     }
                     Some(Err(e)) => {
                         return Some((
-                            Err(Error::Http(e)),
-                            (stream, bytes_buffer, text_buffer, current_tools),
+                            Err(Error::from(e)),
+                            (stream, bytes_buffer, text_buffer, current_tools, announced),
                         ));
                     }
                     None => {
@@ -540,6 +737,162 @@ mod tests {
         assert_eq!(converted[1].role, "user");
         assert_eq!(converted[2].role, "assistant");
     }
+
+    fn attachments() -> Message {
+        use aagt_core::agent::message::ContentPart;
+        Message::user(Content::Parts(vec![
+            ContentPart::Text { text: "Compare these charts".to_string() },
+            ContentPart::Image {
+                source: ImageSource::Base64 { media_type: "image/png".to_string(), data: "iVBORw0K".to_string() },
+            },
+            ContentPart::Image {
+                source: ImageSource::Url { url: "https://example.com/sol.png".to_string() },
+            },
+            ContentPart::FileRef { virtual_path: "qmd://reports/sol.md".to_string(), docid: "a1b2c3".to_string() },
+        ]))
+    }
+
+    #[test]
+    fn test_image_and_file_parts() {
+        let converted = OpenAI::convert_messages(None, vec![attachments()]);
+        assert_eq!(
+            serde_json::to_value(&converted[0]).unwrap(),
+            serde_json::json!({
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "Compare these charts"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0K"}},
+                    {"type": "image_url", "image_url": {"url": "https://example.com/sol.png"}},
+                    {"type": "text", "text": "[file: qmd://reports/sol.md #a1b2c3]"}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_completion_body_conversion() {
+        let body: CompletionBody = serde_json::from_value(serde_json::json!({
+            "choices": [{
+                "message": {
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "get_price", "arguments": "{\"symbol\":\"SOL\"}"}
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }],
+            "usage": {"prompt_tokens": 20, "completion_tokens": 8, "total_tokens": 28}
+        }))
+        .unwrap();
+
+        let response = body.into_response();
+        assert_eq!(response.text, "");
+        assert_eq!(response.tool_calls[0].arguments["symbol"], "SOL");
+        assert_eq!(response.usage.map(|u| u.completion_tokens), Some(8));
+        assert_eq!(response.finish_reason.as_deref(), Some("tool_calls"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_announces_the_serving_model() {
+        let sse = concat!(
+            "data: {\"model\":\"openai/gpt-4o\",\"provider\":\"Azure\",\"choices\":[{\"delta\":{\"content\":\"Hel\"},\"finish_reason\":null}]}\n\n",
+            "data: {\"model\":\"openai/gpt-4o\",\"provider\":\"Azure\",\"choices\":[{\"delta\":{\"content\":\"lo\"},\"finish_reason\":null}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let bytes = futures::stream::iter(vec![Ok(bytes::Bytes::from_static(sse.as_bytes()))]);
+        let chunks: Vec<_> = parse_sse_stream(bytes).collect().await;
+
+        assert!(matches!(
+            &chunks[0],
+            Ok(StreamingChoice::Metadata(ResponseMetadata { model: Some(model), provider: Some(provider) }))
+                if model == "openai/gpt-4o" && provider == "Azure"
+        ));
+        // The first chunk's content still follows, and the model is announced once
+        let text: String = chunks
+            .iter()
+            .filter_map(|c| match c {
+                Ok(StreamingChoice::Message(text)) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "Hello");
+        assert_eq!(chunks.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_stream_separates_reasoning_from_content() {
+        let sse = concat!(
+            "data: {\"choices\":[{\"delta\":{\"reasoning_content\":\"SOL is up 4%,\",\"content\":null},\"finish_reason\":null}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"reasoning_content\":\" so hold.\",\"content\":\"Hold\"},\"finish_reason\":null}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\" SOL.\"},\"finish_reason\":\"stop\"}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let bytes = futures::stream::iter(vec![Ok(bytes::Bytes::from_static(sse.as_bytes()))]);
+        let chunks: Vec<_> = parse_sse_stream(bytes).collect().await;
+
+        let labelled: Vec<_> = chunks
+            .iter()
+            .filter_map(|c| match c {
+                Ok(StreamingChoice::Thought(text)) => Some(("thought", text.as_str())),
+                Ok(StreamingChoice::Message(text)) => Some(("message", text.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            labelled,
+            vec![
+                ("thought", "SOL is up 4%,"),
+                ("thought", " so hold."),
+                ("message", "Hold"),
+                ("message", " SOL."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reasoning_budget_goes_in_the_body() {
+        let request = || ChatRequest {
+            model: "deepseek-reasoner".to_string(),
+            messages: vec![Message::user("Should I hold SOL?")],
+            max_reasoning_tokens: Some(2048),
+            ..Default::default()
+        };
+        let openai = OpenAI::with_base_url("key", "http://localhost").unwrap();
+        let body = serde_json::to_value(openai.api_request(request(), true)).unwrap();
+        assert!(body.get("max_reasoning_tokens").is_none());
+
+        let deepseek = OpenAI::with_base_url("key", "http://localhost")
+            .unwrap()
+            .with_reasoning_budget("max_reasoning_tokens");
+        let body = serde_json::to_value(deepseek.api_request(request(), true)).unwrap();
+        assert_eq!(body["max_reasoning_tokens"], 2048);
+    }
+
+    #[test]
+    fn test_error_body_parsing() {
+        let body = r#"{"error": {"message": "You exceeded your current quota", "type": "insufficient_quota", "param": null, "code": "insufficient_quota"}}"#;
+        let error = crate::utils::provider_error(
+            "openai",
+            429,
+            Some(std::time::Duration::from_secs(20)),
+            body,
+            error_details,
+        );
+        assert!(matches!(
+            &error,
+            Error::Provider { provider, status: Some(429), code: Some(code), message, retry_after: Some(_) }
+                if provider == "openai" && code == "insufficient_quota" && message == "You exceeded your current quota"
+        ));
+        assert!(error.is_auth_failure());
+        assert!(!error.is_retryable());
+
+        let body = r#"{"error": {"message": "This model's maximum context length is 8192 tokens.", "type": "invalid_request_error", "param": "messages", "code": "context_length_exceeded"}}"#;
+        let error = crate::utils::provider_error("openai", 400, None, body, error_details);
+        assert!(error.is_context_overflow());
+        assert!(error.is_invalid_request());
+    }
 }
 
 // --- Embeddings Implementation ---
@@ -572,18 +925,13 @@ impl Embeddings for OpenAI {
 
         let response = self.client
             .post(format!("{}/embeddings", self.base_url))
-            .headers(self.build_headers()?)
+            .headers(self.build_headers(&BTreeMap::new())?)
             .json(&request)
             .send()
             .await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(Error::ProviderApi(format!(
-                "OpenAI Embeddings API error {}: {}",
-                status, text
-            )));
+            return Err(error_from_response(self.provider, response, self.error_parser).await);
         }
 
         let body: EmbeddingResponse = response.json().await
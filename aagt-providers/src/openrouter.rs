@@ -1,22 +1,104 @@
 //! OpenRouter provider implementation
+//!
+//! [`RoutingConfig`] sets OpenRouter's routing fields on every request: fallback
+//! models tried in order and preferences for which upstream providers may
+//! serve them. The model that actually answered is streamed as
+//! [`StreamingChoice::Metadata`](crate::StreamingChoice::Metadata).
 
 use async_trait::async_trait;
+use serde::Serialize;
 
 use crate::{Error, Result, Message, StreamingResponse, ToolDefinition, Provider};
 use crate::openai::OpenAI;
+use crate::utils::ErrorDetails;
+use aagt_core::agent::provider::{KeyPool, PooledProvider};
+use aagt_core::infra::secrets::SecretProvider;
 
 /// OpenRouter API client (OpenAI compatible with model routing)
 pub struct OpenRouter {
     inner: OpenAI,
 }
 
+/// Model fallbacks and provider routing sent with each request
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RoutingConfig {
+    /// Models to try in order when the requested one is unavailable
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    /// Routing strategy (e.g. `fallback`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route: Option<String>,
+    /// Which upstream providers may serve the request
+    #[serde(rename = "provider", skip_serializing_if = "Option::is_none")]
+    pub provider_prefs: Option<ProviderPreferences>,
+}
+
+impl RoutingConfig {
+    /// Fall back to `models`, in order
+    pub fn with_models<S: Into<String>>(mut self, models: impl IntoIterator<Item = S>) -> Self {
+        self.models = models.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Use routing strategy `route`
+    pub fn with_route(mut self, route: impl Into<String>) -> Self {
+        self.route = Some(route.into());
+        self
+    }
+
+    /// Restrict or order the upstream providers
+    pub fn with_provider_prefs(mut self, prefs: ProviderPreferences) -> Self {
+        self.provider_prefs = Some(prefs);
+        self
+    }
+}
+
+/// Upstream provider preferences (OpenRouter's `provider` object)
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProviderPreferences {
+    /// Providers to try first, in order (e.g. `["Anthropic", "Together"]`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+    /// Whether other providers may serve the request when those in `order` fail
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_fallbacks: Option<bool>,
+    /// Only use providers that support every parameter in the request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_parameters: Option<bool>,
+    /// Only use providers with these quantizations (e.g. `fp8`, `bf16`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub quantizations: Vec<String>,
+    /// Never use these providers
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// Rank providers by `price`, `throughput` or `latency`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+}
+
 impl OpenRouter {
     /// Create from API key
     pub fn new(api_key: impl Into<String>) -> Result<Self> {
-        let inner = OpenAI::with_base_url(api_key, "https://openrouter.ai/api/v1")?;
+        let inner = OpenAI::with_base_url(api_key, "https://openrouter.ai/api/v1")?
+            .with_errors("openrouter", error_details);
         Ok(Self { inner })
     }
 
+    /// Send `routing` with every request
+    pub fn with_routing(mut self, routing: RoutingConfig) -> Self {
+        let fields = match serde_json::to_value(routing) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => serde_json::Map::new(),
+        };
+        self.inner = self.inner.with_body_fields(fields);
+        self
+    }
+
+    /// Spread requests across a pool of keys resolved through `secrets`
+    pub fn pooled(pool: KeyPool, secrets: &dyn SecretProvider) -> Result<PooledProvider<Self>> {
+        PooledProvider::new(pool, secrets, |key| Self::new(key))
+    }
+
     /// Create from environment variable
     pub fn from_env() -> Result<Self> {
         let api_key = std::env::var("OPENROUTER_API_KEY")
@@ -34,9 +116,38 @@ impl Provider for OpenRouter {
         self.inner.stream_completion(request).await
     }
 
+    async fn complete(
+        &self,
+        request: aagt_core::agent::provider::ChatRequest,
+    ) -> Result<aagt_core::agent::provider::CompletionResponse> {
+        self.inner.complete(request).await
+    }
+
     fn name(&self) -> &'static str {
         "openrouter"
     }
+
+    fn supports_vision(&self) -> bool {
+        self.inner.supports_vision()
+    }
+}
+
+/// Read an OpenRouter error body: `{"error": {"code", "message", "metadata"}}`
+///
+/// `code` repeats the HTTP status, so the OpenAI-style fields are used when the
+/// upstream error passed them through; the upstream provider is named in the message.
+fn error_details(body: &serde_json::Value) -> ErrorDetails {
+    let mut details = crate::openai::error_details(body);
+    let metadata = &body["error"]["metadata"];
+    if metadata["reasons"].is_array() {
+        details.code.get_or_insert_with(|| "moderation".to_string());
+    }
+    if let (Some(message), Some(upstream)) =
+        (details.message.as_mut(), metadata["provider_name"].as_str())
+    {
+        message.push_str(&format!(" (via {})", upstream));
+    }
+    details
 }
 
 /// Popular models on OpenRouter
@@ -48,3 +159,63 @@ pub const GPT_4O: &str = "openai/gpt-4o";
 pub const GEMINI_FLASH: &str = "google/gemini-2.0-flash-exp";
 /// Llama 3.3 70B via OpenRouter
 pub const LLAMA_70B: &str = "meta-llama/llama-3.3-70b-instruct";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aagt_core::agent::provider::ChatRequest;
+
+    #[test]
+    fn test_error_body_parsing() {
+        let body = r#"{"error": {"code": 502, "message": "Provider returned error", "metadata": {"raw": "upstream timeout", "provider_name": "Together"}}}"#;
+        let error = crate::utils::provider_error("openrouter", 502, None, body, error_details);
+        assert!(matches!(
+            &error,
+            Error::Provider { provider, code: None, message, .. }
+                if provider == "openrouter" && message == "Provider returned error (via Together)"
+        ));
+        assert!(error.is_retryable());
+
+        let body = r#"{"error": {"code": 403, "message": "Input was flagged", "metadata": {"reasons": ["violence"], "flagged_input": "..."}}}"#;
+        let error = crate::utils::provider_error("openrouter", 403, None, body, error_details);
+        assert_eq!(error.to_string(), "openrouter API error 403 (moderation): Input was flagged");
+    }
+
+    #[test]
+    fn test_routing_config_is_sent_in_the_request_body() {
+        let provider = OpenRouter::new("test-key").unwrap().with_routing(
+            RoutingConfig::default()
+                .with_models(["anthropic/claude-3.5-sonnet", "openai/gpt-4o"])
+                .with_route("fallback")
+                .with_provider_prefs(ProviderPreferences {
+                    order: vec!["Anthropic".to_string(), "Together".to_string()],
+                    allow_fallbacks: Some(false),
+                    quantizations: vec!["fp8".to_string()],
+                    ..Default::default()
+                }),
+        );
+        let request = ChatRequest {
+            model: GPT_4O.to_string(),
+            messages: vec![Message::user("hi")],
+            ..Default::default()
+        };
+
+        let body = serde_json::to_value(provider.inner.api_request(request, true)).unwrap();
+        assert_eq!(body["model"], GPT_4O);
+        assert_eq!(body["models"], serde_json::json!(["anthropic/claude-3.5-sonnet", "openai/gpt-4o"]));
+        assert_eq!(body["route"], "fallback");
+        assert_eq!(
+            body["provider"],
+            serde_json::json!({
+                "order": ["Anthropic", "Together"],
+                "allow_fallbacks": false,
+                "quantizations": ["fp8"]
+            })
+        );
+
+        // Without routing the body is plain OpenAI
+        let request = ChatRequest { model: GPT_4O.to_string(), ..Default::default() };
+        let body = serde_json::to_value(OpenRouter::new("test-key").unwrap().inner.api_request(request, true)).unwrap();
+        assert!(body.get("models").is_none() && body.get("provider").is_none());
+    }
+}
@@ -0,0 +1,329 @@
+//! Request and token budgets shared by every client of one provider account
+//!
+//! A [`RateLimiter`] enforces requests-per-minute and tokens-per-minute budgets
+//! over a sliding window. Several agents using the same API key should share
+//! one `Arc<RateLimiter>`, each wrapping its provider in [`RateLimited`], so
+//! together they stay under the account's limits instead of stampeding into
+//! 429s. A request is charged its estimated prompt tokens when admitted, and
+//! the charge is corrected to the usage the provider reports.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use aagt_core::agent::provider::{ChatRequest, CompletionResponse};
+use aagt_core::agent::streaming::{StreamingChoice, Usage};
+use async_trait::async_trait;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::{Provider, Result, StreamingResponse};
+
+/// Budgets enforced by a [`RateLimiter`]
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Requests admitted per window (`None` = unlimited)
+    pub requests_per_minute: Option<u32>,
+    /// Prompt and completion tokens per window (`None` = unlimited)
+    pub tokens_per_minute: Option<u64>,
+    /// Length of the sliding window; one minute unless testing
+    pub window: Duration,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Limit requests per minute
+    pub fn requests_per_minute(mut self, limit: u32) -> Self {
+        self.requests_per_minute = Some(limit);
+        self
+    }
+
+    /// Limit tokens per minute
+    pub fn tokens_per_minute(mut self, limit: u64) -> Self {
+        self.tokens_per_minute = Some(limit);
+        self
+    }
+}
+
+/// What a [`RateLimiter`] has admitted in the current window, for metrics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct RateLimitStatus {
+    /// Requests admitted in the window
+    pub requests: u32,
+    /// Tokens charged in the window (estimates until usage is reported)
+    pub tokens: u64,
+    /// `requests` as a fraction of the request budget
+    pub request_utilization: Option<f64>,
+    /// `tokens` as a fraction of the token budget
+    pub token_utilization: Option<f64>,
+}
+
+struct Admission {
+    id: u64,
+    at: Instant,
+    tokens: u64,
+}
+
+#[derive(Default)]
+struct Window {
+    admissions: VecDeque<Admission>,
+    next_id: u64,
+}
+
+impl Window {
+    fn prune(&mut self, now: Instant, window: Duration) {
+        while self
+            .admissions
+            .front()
+            .is_some_and(|a| now.duration_since(a.at) >= window)
+        {
+            self.admissions.pop_front();
+        }
+    }
+
+    fn tokens(&self) -> u64 {
+        self.admissions.iter().map(|a| a.tokens).sum()
+    }
+}
+
+/// Sliding-window limiter shared by the providers of one account
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    window: Mutex<Window>,
+    /// Serializes waiters so they are admitted in arrival order
+    turnstile: tokio::sync::Mutex<()>,
+}
+
+impl RateLimiter {
+    /// Limiter enforcing `config`
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            window: Mutex::new(Window::default()),
+            turnstile: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Wait until a request charged `tokens` fits both budgets, then admit it
+    ///
+    /// A request larger than the whole token budget is admitted once the window
+    /// is otherwise empty, rather than never.
+    pub async fn acquire(self: &Arc<Self>, tokens: u64) -> RatePermit {
+        let _turn = self.turnstile.lock().await;
+        loop {
+            let wait = {
+                let now = Instant::now();
+                let mut window = self.window.lock();
+                window.prune(now, self.config.window);
+                match self.wait_time(&window, now, tokens) {
+                    None => {
+                        let id = window.next_id;
+                        window.next_id += 1;
+                        window.admissions.push_back(Admission {
+                            id,
+                            at: now,
+                            tokens,
+                        });
+                        return RatePermit {
+                            limiter: Arc::clone(self),
+                            id,
+                        };
+                    }
+                    Some(wait) => wait,
+                }
+            };
+            tracing::debug!("Rate limited, waiting {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// How long until a request of `tokens` fits, or `None` if it fits now
+    fn wait_time(&self, window: &Window, now: Instant, tokens: u64) -> Option<Duration> {
+        let expires = |a: &Admission| (a.at + self.config.window).saturating_duration_since(now);
+        let mut wait = None;
+        if let Some(limit) = self.config.requests_per_minute {
+            let excess = (window.admissions.len() + 1).saturating_sub(limit.max(1) as usize);
+            if excess > 0 {
+                wait = window.admissions.get(excess - 1).map(expires);
+            }
+        }
+        if let Some(limit) = self.config.tokens_per_minute {
+            // Wait for the oldest admissions to expire until the new request fits
+            let mut charged = window.tokens();
+            let mut expired = window.admissions.iter();
+            let mut until = Duration::ZERO;
+            while charged + tokens > limit {
+                match expired.next() {
+                    Some(a) => {
+                        charged -= a.tokens;
+                        until = expires(a);
+                    }
+                    None => break,
+                }
+            }
+            if until > Duration::ZERO {
+                wait = Some(wait.map_or(until, |w: Duration| w.max(until)));
+            }
+        }
+        wait.map(|w| w.max(Duration::from_millis(1)))
+    }
+
+    /// Replace the charge of admission `id` with the tokens it actually used
+    fn reconcile(&self, id: u64, tokens: u64) {
+        let mut window = self.window.lock();
+        if let Some(admission) = window.admissions.iter_mut().find(|a| a.id == id) {
+            admission.tokens = tokens;
+        }
+    }
+
+    /// Requests and tokens in the current window
+    pub fn status(&self) -> RateLimitStatus {
+        let mut window = self.window.lock();
+        window.prune(Instant::now(), self.config.window);
+        let requests = window.admissions.len() as u32;
+        let tokens = window.tokens();
+        RateLimitStatus {
+            requests,
+            tokens,
+            request_utilization: self
+                .config
+                .requests_per_minute
+                .map(|limit| requests as f64 / limit.max(1) as f64),
+            token_utilization: self
+                .config
+                .tokens_per_minute
+                .map(|limit| tokens as f64 / limit.max(1) as f64),
+        }
+    }
+}
+
+/// An admitted request; report its usage to correct the estimate
+pub struct RatePermit {
+    limiter: Arc<RateLimiter>,
+    id: u64,
+}
+
+impl RatePermit {
+    /// Charge the request the tokens it actually used
+    pub fn reconcile(&self, usage: &Usage) {
+        self.limiter.reconcile(self.id, usage.total_tokens as u64);
+    }
+}
+
+/// Tokens `request` is charged before the provider reports usage
+fn estimated_prompt_tokens(request: &ChatRequest) -> u64 {
+    let chars = request.system_prompt.as_deref().map_or(0, str::len)
+        + request
+            .messages
+            .iter()
+            .map(|m| m.text().len())
+            .sum::<usize>();
+    Usage::estimate(chars, 0).prompt_tokens as u64
+}
+
+/// A provider whose requests wait for a shared [`RateLimiter`]
+pub struct RateLimited<P> {
+    inner: P,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<P: Provider> RateLimited<P> {
+    /// Send `inner`'s requests through `limiter`
+    pub fn new(inner: P, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+
+    /// The shared limiter
+    pub fn limiter(&self) -> &Arc<RateLimiter> {
+        &self.limiter
+    }
+}
+
+#[async_trait]
+impl<P: Provider> Provider for RateLimited<P> {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.inner.supports_tools()
+    }
+
+    fn supports_vision(&self) -> bool {
+        self.inner.supports_vision()
+    }
+
+    async fn stream_completion(&self, request: ChatRequest) -> Result<StreamingResponse> {
+        let permit = self
+            .limiter
+            .acquire(estimated_prompt_tokens(&request))
+            .await;
+        let stream = self.inner.stream_completion(request).await?;
+        Ok(StreamingResponse::from_stream(stream.into_inner().map(
+            move |item| {
+                if let Ok(StreamingChoice::Usage(usage)) = &item {
+                    permit.reconcile(usage);
+                }
+                item
+            },
+        )))
+    }
+
+    async fn complete(&self, request: ChatRequest) -> Result<CompletionResponse> {
+        let permit = self
+            .limiter
+            .acquire(estimated_prompt_tokens(&request))
+            .await;
+        let response = self.inner.complete(request).await?;
+        if let Some(usage) = &response.usage {
+            permit.reconcile(usage);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_token_budget_waits_and_reconciles() {
+        let limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            tokens_per_minute: Some(100),
+            window: Duration::from_millis(300),
+            ..Default::default()
+        }));
+
+        let first = limiter.acquire(80).await;
+        assert_eq!(limiter.status().token_utilization, Some(0.8));
+        // The estimate was high: reporting real usage frees room at once
+        first.reconcile(&Usage::new(10, 10));
+        let started = Instant::now();
+        limiter.acquire(70).await;
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert_eq!(limiter.status().tokens, 90);
+
+        // Nothing fits until the window slides past both admissions
+        limiter.acquire(50).await;
+        assert!(
+            started.elapsed() >= Duration::from_millis(250),
+            "{:?}",
+            started.elapsed()
+        );
+        assert_eq!(limiter.status().requests, 1);
+    }
+}
@@ -1,7 +1,12 @@
 //! Utilities for LLM providers
 
+use std::collections::BTreeMap;
+use std::time::Duration;
+
 use crate::{Error, Result};
 use bytes::{BufMut, BytesMut};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use serde_json::Value;
 
 /// A buffer for accumulating SSE (Server-Sent Events) bytes.
 ///
@@ -113,6 +118,97 @@ impl SseBuffer {
     }
 }
 
+/// Add per-request headers to `headers`, replacing any with the same name
+pub fn extend_headers(headers: &mut HeaderMap, extra: &BTreeMap<String, String>) -> Result<()> {
+    for (name, value) in extra {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|e| Error::Internal(format!("Invalid header name '{}': {}", name, e)))?;
+        let value = HeaderValue::from_str(value)
+            .map_err(|e| Error::Internal(format!("Invalid value for header '{}': {}", name, e)))?;
+        headers.insert(name, value);
+    }
+    Ok(())
+}
+
+/// What a provider's error body says about a failed request
+#[derive(Debug, Default)]
+pub(crate) struct ErrorDetails {
+    /// Provider-specific error code or type
+    pub code: Option<String>,
+    /// Human-readable message
+    pub message: Option<String>,
+    /// Retry delay given in the body
+    pub retry_after: Option<Duration>,
+}
+
+/// Reads a provider's JSON error body
+pub(crate) type ErrorParser = fn(&Value) -> ErrorDetails;
+
+/// Turn a non-success response into [`Error::Provider`], reading its body with `parse`
+pub(crate) async fn error_from_response(
+    provider: &str,
+    response: reqwest::Response,
+    parse: ErrorParser,
+) -> Error {
+    let status = response.status().as_u16();
+    let retry_after = retry_after_header(response.headers());
+    let body = response.text().await.unwrap_or_default();
+    provider_error(provider, status, retry_after, &body, parse)
+}
+
+/// Build [`Error::Provider`] from a response's status, `Retry-After` and body
+///
+/// Bodies that aren't JSON (e.g. a proxy's HTML page) become the message as is.
+pub(crate) fn provider_error(
+    provider: &str,
+    status: u16,
+    retry_after: Option<Duration>,
+    body: &str,
+    parse: ErrorParser,
+) -> Error {
+    let details = serde_json::from_str::<Value>(body)
+        .map(|value| parse(&value))
+        .unwrap_or_default();
+    let message = details.message.unwrap_or_else(|| {
+        let body = body.trim();
+        if body.is_empty() {
+            reqwest::StatusCode::from_u16(status)
+                .ok()
+                .and_then(|s| s.canonical_reason())
+                .unwrap_or("request failed")
+                .to_string()
+        } else {
+            body.to_string()
+        }
+    });
+    Error::Provider {
+        provider: provider.to_string(),
+        status: Some(status),
+        code: details.code,
+        message,
+        retry_after: retry_after.or(details.retry_after),
+    }
+}
+
+/// Delay from a `Retry-After` (seconds) or `retry-after-ms` header
+fn retry_after_header(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.trim().parse::<f64>().ok()) {
+        return retry_delay(ms / 1000.0);
+    }
+    header(RETRY_AFTER.as_str())
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .and_then(retry_delay)
+}
+
+/// Server-sent delay in seconds, ignoring values that are not finite or don't fit a `Duration`
+pub(crate) fn retry_delay(secs: f64) -> Option<Duration> {
+    if !secs.is_finite() {
+        return None;
+    }
+    Duration::try_from_secs_f64(secs.max(0.0)).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -134,6 +230,31 @@ mod tests {
         assert_eq!(buffer.buffer.len(), 0);
     }
 
+    #[test]
+    fn test_retry_after_header_and_plain_bodies() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("12"));
+        assert_eq!(retry_after_header(&headers), Some(Duration::from_secs(12)));
+        headers.insert("retry-after-ms", HeaderValue::from_static("1500"));
+        assert_eq!(retry_after_header(&headers), Some(Duration::from_millis(1500)));
+
+        // Nonsense from the server is ignored rather than panicking mid error handling
+        for bad in ["inf", "1e30", "NaN"] {
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, HeaderValue::from_static(bad));
+            assert_eq!(retry_after_header(&headers), None, "Retry-After: {}", bad);
+            let mut headers = HeaderMap::new();
+            headers.insert("retry-after-ms", HeaderValue::from_static(bad));
+            assert_eq!(retry_after_header(&headers), None, "retry-after-ms: {}", bad);
+        }
+
+        let parse: ErrorParser = |_| ErrorDetails::default();
+        let error = provider_error("openai", 502, None, "<html>Bad Gateway</html>", parse);
+        assert_eq!(error.to_string(), "openai API error 502: <html>Bad Gateway</html>");
+        let error = provider_error("openai", 503, None, "", parse);
+        assert_eq!(error.to_string(), "openai API error 503: Service Unavailable");
+    }
+
     #[test]
     fn test_sse_buffer_overflow() {
         let mut buffer = SseBuffer::with_capacity_limit(10);
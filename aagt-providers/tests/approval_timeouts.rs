@@ -0,0 +1,103 @@
+//! Approvals nobody answers time out instead of blocking the run
+
+use aagt_core::agent::core::{
+    AgentEvent, ApprovalHandler, ApprovalTimeoutAction, RiskyToolPolicy, ToolPolicy,
+};
+use aagt_core::prelude::*;
+use aagt_providers::mock::{MockProvider, MockTurn};
+use async_trait::async_trait;
+use serde_json::json;
+
+/// Handler whose consumer went away: it never answers
+struct Unanswered;
+
+#[async_trait]
+impl ApprovalHandler for Unanswered {
+    async fn approve(&self, _tool_name: &str, _arguments: &str) -> anyhow::Result<bool> {
+        std::future::pending().await
+    }
+}
+
+struct OrderTool;
+
+#[async_trait]
+impl Tool for OrderTool {
+    fn name(&self) -> String {
+        "place_order".to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Place a market order".to_string(),
+            parameters: json!({"type": "object", "properties": {"symbol": {"type": "string"}}}),
+            is_verified: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+        Ok(r#"{"status": "filled"}"#.to_string())
+    }
+}
+
+fn agent(action: ApprovalTimeoutAction) -> Agent<MockProvider> {
+    let provider = MockProvider::scripted(
+        [
+            MockTurn::tool_call("place_order", json!({"symbol": "SOL"})),
+            MockTurn::text("The order was not approved in time."),
+        ],
+        "Done.",
+    );
+    let mut policy = RiskyToolPolicy::default();
+    policy
+        .overrides
+        .insert("place_order".to_string(), ToolPolicy::RequiresApproval);
+    Agent::builder(provider)
+        .tool(OrderTool)
+        .tool_policy(policy)
+        .approval_handler(Unanswered)
+        .approval_timeout_secs(Some(1))
+        .on_approval_timeout(action)
+        .auto_load_skills(false)
+        .build()
+        .expect("agent builds")
+}
+
+#[tokio::test]
+async fn test_timed_out_approval_is_denied_and_the_model_continues() {
+    let agent = agent(ApprovalTimeoutAction::DenyAndContinue);
+    let mut rx = agent.subscribe();
+    let text = agent.prompt("Buy SOL").await.expect("chat succeeds");
+    assert_eq!(text, "The order was not approved in time.");
+
+    let mut deadline = None;
+    let mut errors = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            AgentEvent::ApprovalPending { deadline: d, .. } => deadline = d,
+            AgentEvent::ToolResult { tool, .. } => panic!("{} ran without approval", tool),
+            AgentEvent::Error { message } => errors.push(message),
+            _ => {}
+        }
+    }
+    assert!(deadline.expect("deadline set") > chrono::Utc::now() - chrono::Duration::seconds(5));
+    assert!(
+        errors
+            .iter()
+            .any(|e| e.contains("Approval for place_order timed out after 1s")),
+        "{:?}",
+        errors
+    );
+}
+
+#[tokio::test]
+async fn test_timed_out_approval_can_fail_the_run() {
+    let agent = agent(ApprovalTimeoutAction::FailRun);
+    let err = agent.prompt("Buy SOL").await.expect_err("run fails");
+    assert!(
+        matches!(err, Error::ToolApprovalRequired { ref tool_name } if tool_name == "place_order"),
+        "{:?}",
+        err
+    );
+}
@@ -0,0 +1,35 @@
+//! Images sent to a provider without vision arrive as text placeholders
+
+use aagt_core::agent::provider::{RecordedExchange, RecordingProvider};
+use aagt_core::prelude::*;
+use aagt_providers::mock::MockProvider;
+
+#[tokio::test]
+async fn test_images_degrade_to_placeholders_without_vision() {
+    let dir = tempfile::tempdir().unwrap();
+    let chart = dir.path().join("sol.png");
+    std::fs::write(&chart, b"\x89PNG\r\n").unwrap();
+    let trace = dir.path().join("trace.jsonl");
+
+    let mock = MockProvider::new("Looks bullish.");
+    assert!(!mock.supports_vision());
+    let agent = Agent::builder(RecordingProvider::new(mock, &trace).unwrap())
+        .model("mock-model")
+        .auto_load_skills(false)
+        .build()
+        .expect("agent builds");
+
+    let question = Message::user_with_image("What does this chart show?", &chart).unwrap();
+    assert_eq!(agent.chat(vec![question]).await.unwrap(), "Looks bullish.");
+
+    let recorded: RecordedExchange =
+        serde_json::from_str(std::fs::read_to_string(&trace).unwrap().lines().next().unwrap()).unwrap();
+    let sent = recorded.request.messages.last().unwrap();
+    assert_eq!(
+        sent["content"],
+        serde_json::json!([
+            {"type": "text", "text": "What does this chart show?"},
+            {"type": "text", "text": "[image: image/png]"}
+        ])
+    );
+}
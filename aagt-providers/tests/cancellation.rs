@@ -0,0 +1,147 @@
+//! Cancelling a run stops tool calls and model calls, and checkpoints the session for resume
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use aagt_core::agent::core::{AgentEvent, CancellationToken};
+use aagt_core::agent::memory::Memory;
+use aagt_core::agent::session::SessionStatus;
+use aagt_core::error::Error;
+use aagt_core::prelude::*;
+use aagt_providers::mock::{MockProvider, MockTurn};
+use async_trait::async_trait;
+use serde_json::json;
+
+mod common;
+
+use common::{Counting, Sessions};
+
+/// Tool that takes far longer than the test waits, recording whether it ever finished
+struct SlowTool {
+    finished: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl Tool for SlowTool {
+    fn name(&self) -> String {
+        "backtest".to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Backtest a strategy".to_string(),
+            parameters: json!({"type": "object", "properties": {"symbol": {"type": "string"}}}),
+            is_verified: true,
+            side_effect_free: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        self.finished.store(true, Ordering::SeqCst);
+        Ok(r#"{"sharpe": 1.2}"#.to_string())
+    }
+}
+
+fn agent(
+    memory: Arc<Sessions>,
+    finished: Arc<AtomicBool>,
+    calls: Arc<AtomicUsize>,
+) -> Agent<Counting> {
+    let inner = MockProvider::scripted(
+        [
+            MockTurn::tool_call("backtest", json!({"symbol": "SOL"})),
+            MockTurn::text("The backtest was cancelled; want me to retry?"),
+        ],
+        "Done.",
+    );
+    Agent::builder(Counting { streamed: calls, ..Counting::new(inner) })
+        .tool(SlowTool { finished })
+        .with_memory(memory)
+        .session_id("s1")
+        .auto_load_skills(false)
+        .build()
+        .expect("agent builds")
+}
+
+#[tokio::test]
+async fn test_cancel_mid_tool_checkpoints_and_stops_model_calls() {
+    let memory = Arc::new(Sessions::default());
+    let finished = Arc::new(AtomicBool::new(false));
+    let calls = Arc::new(AtomicUsize::new(0));
+    let agent = agent(memory.clone(), finished.clone(), calls.clone());
+    let mut rx = agent.subscribe();
+    let token = CancellationToken::new();
+
+    let cancel = async {
+        // Cancel once the tool is running
+        while let Ok(event) = rx.recv().await {
+            if matches!(event, AgentEvent::ToolCall { .. }) {
+                break;
+            }
+        }
+        token.cancel();
+        rx
+    };
+    let (result, mut rx) = tokio::join!(
+        agent.chat_with_cancel(vec![Message::user("Backtest SOL")], token.clone()),
+        cancel
+    );
+
+    assert!(matches!(result, Err(Error::Cancelled)), "{:?}", result);
+    assert!(!finished.load(Ordering::SeqCst));
+    // No model call after the cancellation
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    let cancelled = std::iter::from_fn(|| rx.try_recv().ok())
+        .find(|e| matches!(e, AgentEvent::Cancelled { .. }))
+        .expect("cancelled event");
+    assert!(matches!(
+        cancelled,
+        AgentEvent::Cancelled { step: 1, ref interrupted_tools } if interrupted_tools == &["backtest"]
+    ));
+
+    // The checkpoint pairs the cut-off call with a result, so the history stays valid
+    let session = memory.retrieve_session("s1").await.unwrap().unwrap();
+    assert_eq!(session.status, SessionStatus::Cancelled);
+    let Content::Parts(parts) = &session.messages.last().unwrap().content else {
+        panic!("expected a tool result");
+    };
+    assert!(matches!(
+        &parts[0],
+        ContentPart::ToolResult { content, .. } if content.contains("Cancelled before the call finished")
+    ));
+
+    // Resuming continues from the checkpoint
+    let text = agent.resume("s1").await.unwrap();
+    assert_eq!(text, "The backtest was cancelled; want me to retry?");
+    assert!(!finished.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn test_cancelled_direct_tool_call_returns_promptly() {
+    let finished = Arc::new(AtomicBool::new(false));
+    let agent = agent(
+        Arc::new(Sessions::default()),
+        finished.clone(),
+        Arc::new(AtomicUsize::new(0)),
+    );
+    let token = CancellationToken::new();
+
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        canceller.cancel();
+    });
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        agent.call_tool_with_cancel("backtest", r#"{"symbol":"SOL"}"#, token),
+    )
+    .await
+    .expect("cancellation interrupts the call");
+    assert!(matches!(result, Err(Error::Cancelled)));
+    assert!(!finished.load(Ordering::SeqCst));
+}
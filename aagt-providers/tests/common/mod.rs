@@ -0,0 +1,138 @@
+//! Fixtures shared by the provider integration tests
+//!
+//! Each test binary compiles this module on its own and uses only part of it.
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use aagt_core::agent::memory::Memory;
+use aagt_core::agent::provider::{ChatRequest, CompletionResponse};
+use aagt_core::agent::session::AgentSession;
+use aagt_core::prelude::*;
+use aagt_providers::mock::MockProvider;
+use async_trait::async_trait;
+use serde_json::json;
+
+/// `get_price` tool that always quotes 150
+pub struct PriceTool;
+
+#[async_trait]
+impl Tool for PriceTool {
+    fn name(&self) -> String {
+        "get_price".to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Current price of a token".to_string(),
+            parameters: json!({"type": "object", "properties": {"symbol": {"type": "string"}}}),
+            is_verified: true,
+            side_effect_free: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+        Ok(r#"{"price": 150}"#.to_string())
+    }
+}
+
+/// Memory that only keeps checkpointed sessions
+#[derive(Default)]
+pub struct Sessions(Mutex<HashMap<String, AgentSession>>);
+
+#[async_trait]
+impl Memory for Sessions {
+    async fn store(&self, _: &str, _: Option<&str>, _: Message) -> aagt_core::error::Result<()> {
+        Ok(())
+    }
+
+    async fn retrieve(&self, _: &str, _: Option<&str>, _: usize) -> Vec<Message> {
+        Vec::new()
+    }
+
+    async fn clear(&self, _: &str, _: Option<&str>) -> aagt_core::error::Result<()> {
+        Ok(())
+    }
+
+    async fn undo(&self, _: &str, _: Option<&str>) -> aagt_core::error::Result<Option<Message>> {
+        Ok(None)
+    }
+
+    async fn store_session(&self, session: AgentSession) -> aagt_core::error::Result<()> {
+        self.0.lock().unwrap().insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn retrieve_session(&self, id: &str) -> aagt_core::error::Result<Option<AgentSession>> {
+        Ok(self.0.lock().unwrap().get(id).cloned())
+    }
+}
+
+/// Mock that counts which of its two paths the agent used
+pub struct Counting {
+    pub inner: MockProvider,
+    pub streamed: Arc<AtomicUsize>,
+    pub completed: Arc<AtomicUsize>,
+}
+
+impl Counting {
+    pub fn new(inner: MockProvider) -> Self {
+        Self {
+            inner,
+            streamed: Arc::default(),
+            completed: Arc::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Counting {
+    async fn stream_completion(&self, request: ChatRequest) -> aagt_core::error::Result<StreamingResponse> {
+        self.streamed.fetch_add(1, Ordering::SeqCst);
+        self.inner.stream_completion(request).await
+    }
+
+    async fn complete(&self, request: ChatRequest) -> aagt_core::error::Result<CompletionResponse> {
+        self.completed.fetch_add(1, Ordering::SeqCst);
+        self.inner.complete(request).await
+    }
+
+    fn name(&self) -> &'static str {
+        "counting"
+    }
+}
+
+/// Mock that keeps every request it receives and when it arrived
+pub struct Recording {
+    pub inner: MockProvider,
+    pub requests: Arc<Mutex<Vec<ChatRequest>>>,
+    pub sent: Arc<Mutex<Vec<Instant>>>,
+}
+
+impl Recording {
+    pub fn new(inner: MockProvider) -> Self {
+        Self {
+            inner,
+            requests: Arc::default(),
+            sent: Arc::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for Recording {
+    async fn stream_completion(&self, request: ChatRequest) -> aagt_core::error::Result<StreamingResponse> {
+        self.sent.lock().unwrap().push(Instant::now());
+        self.requests.lock().unwrap().push(request.clone());
+        self.inner.stream_completion(request).await
+    }
+
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+}
@@ -0,0 +1,97 @@
+//! Non-streaming completions and the usage events both paths emit
+
+use std::sync::atomic::Ordering;
+
+use aagt_core::agent::core::AgentEvent;
+use aagt_core::agent::streaming::Usage;
+use aagt_core::prelude::*;
+use aagt_providers::mock::{MockProvider, MockTurn};
+use serde_json::json;
+
+mod common;
+
+use common::{Counting, PriceTool};
+
+async fn run(prefer_complete: bool) -> (usize, usize, Vec<AgentEvent>) {
+    let provider = Counting::new(
+        MockProvider::scripted(
+            [
+                MockTurn::tool_call("get_price", json!({"symbol": "SOL"})),
+                MockTurn::text("SOL is at $150."),
+            ],
+            "Done.",
+        )
+        .with_usage(Usage {
+            prompt_tokens: 40,
+            completion_tokens: 10,
+            total_tokens: 50,
+            ..Default::default()
+        }),
+    );
+    let (streamed, completed) = (provider.streamed.clone(), provider.completed.clone());
+    let agent = Agent::builder(provider)
+        .model("mock-model")
+        .tool(PriceTool)
+        .auto_load_skills(false)
+        .prefer_complete(prefer_complete)
+        .build()
+        .expect("agent builds");
+    let mut rx = agent.subscribe();
+
+    let text = agent
+        .prompt("What is SOL at?")
+        .await
+        .expect("chat succeeds");
+    assert_eq!(text, "SOL is at $150.");
+
+    let mut events = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        events.push(event);
+    }
+    (
+        streamed.load(Ordering::SeqCst),
+        completed.load(Ordering::SeqCst),
+        events,
+    )
+}
+
+fn usage(events: &[AgentEvent]) -> Vec<(String, u32, u32, u32)> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            AgentEvent::Usage {
+                model,
+                prompt_tokens,
+                completion_tokens,
+                total_tokens,
+                ..
+            } => Some((
+                model.clone(),
+                *prompt_tokens,
+                *completion_tokens,
+                *total_tokens,
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_prefer_complete_skips_streaming_and_reports_usage() {
+    let (streamed, completed, events) = run(true).await;
+    assert_eq!((streamed, completed), (0, 2));
+    assert_eq!(
+        usage(&events),
+        vec![("mock-model".to_string(), 40, 10, 50); 2]
+    );
+    assert!(events
+        .iter()
+        .any(|e| matches!(e, AgentEvent::ToolResult { tool, .. } if tool == "get_price")));
+}
+
+#[tokio::test]
+async fn test_streaming_path_reports_usage_too() {
+    let (streamed, completed, events) = run(false).await;
+    assert_eq!((streamed, completed), (2, 0));
+    assert_eq!(usage(&events).len(), 2);
+}
@@ -0,0 +1,53 @@
+//! Old history is folded into a summary produced by a separate provider
+
+use aagt_core::agent::context::PreviewOptions;
+use aagt_core::agent::SummarizeConfig;
+use aagt_core::prelude::*;
+use aagt_providers::mock::{MockProvider, MockTurn};
+
+#[tokio::test]
+async fn test_context_keeps_summary_and_recent_tail() {
+    let summaries = MockProvider::scripted(
+        [MockTurn::text(
+            "User's stop loss is 5% and they only trade SOL.",
+        )],
+        "Nothing new.",
+    );
+    let agent = Agent::builder(MockProvider::new("SOL is at $150."))
+        .auto_load_skills(false)
+        .summarize_history(SummarizeConfig {
+            trigger_messages: 4,
+            ..Default::default()
+        })
+        .summary_provider(summaries)
+        .build()
+        .expect("agent builds");
+
+    let history = vec![
+        Message::user("My stop loss is always 5%."),
+        Message::assistant("Noted."),
+        Message::user("I only trade SOL."),
+        Message::assistant("Got it."),
+        Message::user("What is SOL at?"),
+    ];
+    let reply = agent.chat(history.clone()).await.expect("chat succeeds");
+    assert_eq!(reply, "SOL is at $150.");
+
+    let preview = agent
+        .render_context_preview(&history, PreviewOptions::default())
+        .await
+        .expect("preview renders");
+    let summary = preview
+        .sections
+        .iter()
+        .find(|s| s.source == "history_summary")
+        .expect("summary section");
+    assert_eq!(
+        summary.text,
+        "Conversation summary so far: User's stop loss is 5% and they only trade SOL."
+    );
+    let texts: Vec<_> = preview.sections.iter().map(|s| s.text.as_str()).collect();
+    assert!(texts.contains(&"Got it."));
+    assert!(texts.contains(&"What is SOL at?"));
+    assert!(!texts.iter().any(|t| t.contains("My stop loss is always")));
+}
@@ -0,0 +1,62 @@
+//! Providers sharing one rate limiter stay under its request budget together
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use aagt_core::agent::provider::ChatRequest;
+use aagt_core::prelude::*;
+use aagt_providers::mock::MockProvider;
+use aagt_providers::rate_limit::{RateLimitConfig, RateLimited, RateLimiter};
+
+mod common;
+
+use common::Recording;
+
+#[tokio::test]
+async fn test_concurrent_requests_never_exceed_the_request_budget() {
+    const RPM: usize = 5;
+    let window = Duration::from_millis(200);
+    let limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+        window,
+        ..RateLimitConfig::default().requests_per_minute(RPM as u32)
+    }));
+    let sent = Arc::new(Mutex::new(Vec::new()));
+
+    // Two providers, e.g. two agents on one API key, share the limiter
+    let providers: Vec<Arc<RateLimited<Recording>>> = (0..2)
+        .map(|_| {
+            Arc::new(RateLimited::new(
+                Recording {
+                    sent: Arc::clone(&sent),
+                    ..Recording::new(MockProvider::new("ok"))
+                },
+                Arc::clone(&limiter),
+            ))
+        })
+        .collect();
+
+    let requests = (0..16).map(|i| {
+        let provider = Arc::clone(&providers[i % 2]);
+        tokio::spawn(async move {
+            provider
+                .complete(ChatRequest {
+                    messages: vec![Message::user("hello")],
+                    ..Default::default()
+                })
+                .await
+        })
+    });
+    for result in futures::future::join_all(requests).await {
+        assert_eq!(result.unwrap().unwrap().text, "ok");
+    }
+
+    let mut sent = sent.lock().unwrap().clone();
+    sent.sort();
+    assert_eq!(sent.len(), 16);
+    // Any RPM + 1 consecutive requests span at least one window
+    for burst in sent.windows(RPM + 1) {
+        let span = burst[RPM].duration_since(burst[0]);
+        assert!(span + Duration::from_millis(5) >= window, "{:?}", span);
+    }
+    assert!(limiter.status().request_utilization.unwrap() <= 1.0);
+}
@@ -0,0 +1,50 @@
+//! Recording a mock conversation and replaying it without the provider
+
+use aagt_core::agent::provider::{RecordingProvider, ReplayProvider};
+use aagt_core::prelude::*;
+use aagt_providers::mock::{MockProvider, MockTurn};
+use serde_json::json;
+
+mod common;
+
+use common::PriceTool;
+
+fn agent<P: Provider + 'static>(provider: P) -> Agent<P> {
+    Agent::builder(provider)
+        .model("mock-model")
+        .system_prompt("You are a trading agent.")
+        .tool(PriceTool)
+        .auto_load_skills(false)
+        .build()
+        .expect("agent builds")
+}
+
+#[tokio::test]
+async fn test_replayed_conversation_matches_the_recording() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("trace.jsonl");
+    let question = vec![Message::user("What is SOL at?")];
+
+    let mock = MockProvider::scripted(
+        [
+            MockTurn::tool_call("get_price", json!({"symbol": "SOL"})),
+            MockTurn::text("SOL is at $150."),
+        ],
+        "Done.",
+    );
+    let recorded = agent(RecordingProvider::new(mock, &path).unwrap())
+        .chat(question.clone())
+        .await
+        .expect("recorded chat succeeds");
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+    let replay = ReplayProvider::from_file(&path).unwrap();
+    let replayed = agent(replay).chat(question).await.expect("replayed chat succeeds");
+    assert_eq!(replayed, recorded);
+
+    let err = agent(ReplayProvider::from_file(&path).unwrap())
+        .chat(vec![Message::user("What is ETH at?")])
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("replay mismatch at messages["), "{}", err);
+}
@@ -0,0 +1,140 @@
+//! Agents built from one shared toolset see only their role's tools, and delegation lists them
+
+use std::sync::Arc;
+
+use aagt_core::agent::multi_agent::{AgentRole, Coordinator};
+use aagt_core::error::Error;
+use aagt_core::prelude::*;
+use aagt_core::skills::tool::ToolSet;
+use aagt_providers::mock::MockProvider;
+use async_trait::async_trait;
+use serde_json::json;
+
+/// Tool that only has a name
+struct Named(&'static str);
+
+#[async_trait]
+impl Tool for Named {
+    fn name(&self) -> String {
+        self.0.to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: format!("The {} tool", self.0),
+            parameters: json!({"type": "object", "properties": {}}),
+            is_verified: true,
+            side_effect_free: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+        Ok("{}".to_string())
+    }
+}
+
+fn shared_tools() -> ToolSet {
+    let mut tools = ToolSet::new();
+    for name in ["get_price", "get_news", "swap_token", "place_order"] {
+        tools.add(Named(name));
+    }
+    tools
+}
+
+fn agent(role: AgentRole, tools: ToolSet) -> Agent<MockProvider> {
+    Agent::builder(MockProvider::new("ok"))
+        .role(role)
+        .tools(tools)
+        .auto_load_skills(false)
+        .build()
+        .expect("agent builds")
+}
+
+async fn definition_names(agent: &Agent<MockProvider>) -> Vec<String> {
+    let mut names: Vec<String> = agent
+        .tool_definitions()
+        .await
+        .into_iter()
+        .map(|d| d.name)
+        .collect();
+    names.sort();
+    names
+}
+
+#[tokio::test]
+async fn test_roles_get_disjoint_subsets_of_one_toolset() {
+    let coordinator = Arc::new(
+        Coordinator::new()
+            .with_role_tools(AgentRole::Researcher, ["get_price", "get_news"])
+            .with_role_tools(AgentRole::Trader, ["swap_token", "place_order"]),
+    );
+    let tools = shared_tools();
+
+    let research_tools = coordinator
+        .tools_for(&AgentRole::Researcher, &tools)
+        .unwrap();
+    // Subsets share the tool instances
+    assert!(Arc::ptr_eq(
+        tools.get("get_price").unwrap(),
+        research_tools.get("get_price").unwrap()
+    ));
+
+    let researcher = agent(AgentRole::Researcher, research_tools);
+    let trader = agent(
+        AgentRole::Trader,
+        coordinator.tools_for(&AgentRole::Trader, &tools).unwrap(),
+    );
+    // Besides the built-in introspection tool, the two agents share nothing
+    assert_eq!(
+        definition_names(&researcher).await,
+        ["get_news", "get_price", "introspect"]
+    );
+    assert_eq!(
+        definition_names(&trader).await,
+        ["introspect", "place_order", "swap_token"]
+    );
+
+    // Roles without an allowlist get everything
+    let strategist_tools = coordinator
+        .tools_for(&AgentRole::Strategist, &tools)
+        .unwrap();
+    assert_eq!(strategist_tools.len(), 4);
+
+    coordinator.register(Arc::new(researcher));
+    coordinator.register(Arc::new(trader));
+    let capabilities = coordinator.agent_capabilities();
+    assert_eq!(
+        capabilities[&AgentRole::Trader],
+        ["introspect", "place_order", "swap_token"]
+    );
+
+    // The planner's delegate tool tells it what each role can do
+    let planner = Agent::builder(MockProvider::new("ok"))
+        .role(AgentRole::Strategist)
+        .with_delegation(coordinator.clone())
+        .auto_load_skills(false)
+        .build()
+        .unwrap();
+    let delegate = planner
+        .tool_definitions()
+        .await
+        .into_iter()
+        .find(|d| d.name == "delegate")
+        .unwrap();
+    assert!(
+        delegate.description.contains(
+            "- researcher: get_news, get_price, introspect\n- trader: introspect, place_order, swap_token"
+        ),
+        "{}",
+        delegate.description
+    );
+}
+
+#[tokio::test]
+async fn test_unknown_allowlisted_tool_is_an_error() {
+    let coordinator = Coordinator::new().with_role_tools(AgentRole::Researcher, ["get_prise"]);
+    let result = coordinator.tools_for(&AgentRole::Researcher, &shared_tools());
+    assert!(matches!(result, Err(Error::ToolNotFound(name)) if name == "get_prise"));
+}
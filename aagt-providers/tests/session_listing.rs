@@ -0,0 +1,77 @@
+//! Checkpointed sessions can be listed by user through the Memory trait and expired by age
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use aagt_core::agent::memory::Memory;
+use aagt_core::agent::session::{SessionQuery, META_AGENT, META_TITLE, META_USER_ID};
+use aagt_core::prelude::*;
+use aagt_providers::mock::MockProvider;
+use aagt_qmd::agent_memory::QmdMemory;
+use aagt_qmd::QmdStore;
+
+fn agent(memory: Arc<dyn Memory>, session: &str, user: &str) -> Agent<MockProvider> {
+    Agent::builder(MockProvider::new("SOL is at $150."))
+        .name("desk")
+        .with_memory(memory)
+        .session_id(session)
+        .user_id(user)
+        .auto_load_skills(false)
+        .build()
+        .expect("agent builds")
+}
+
+#[tokio::test]
+async fn test_checkpointed_sessions_are_listed_by_user() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = Arc::new(QmdStore::new(dir.path().join("agent.db")).unwrap());
+    let memory: Arc<dyn Memory> = Arc::new(QmdMemory::new(store));
+
+    agent(memory.clone(), "alice-1", "alice")
+        .prompt("What is   SOL trading at?\nThanks")
+        .await
+        .unwrap();
+    agent(memory.clone(), "alice-2", "alice")
+        .prompt("Hedge my ETH")
+        .await
+        .unwrap();
+    agent(memory.clone(), "bob-1", "bob")
+        .prompt("Hi")
+        .await
+        .unwrap();
+
+    let alice = memory
+        .list_session_summaries(&SessionQuery::new().user("alice"))
+        .await
+        .unwrap();
+    let ids: Vec<_> = alice.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, ["alice-2", "alice-1"]);
+    let first = &alice[1];
+    assert_eq!(first.status.as_deref(), Some("completed"));
+    assert_eq!(first.metadata[META_USER_ID], "alice");
+    assert_eq!(first.metadata[META_AGENT], "desk");
+    assert_eq!(first.metadata[META_TITLE], "What is SOL trading at? Thanks");
+
+    let page = memory
+        .list_session_summaries(&SessionQuery::new().limit(1).offset(1))
+        .await
+        .unwrap();
+    assert_eq!(page[0].id, "alice-2");
+
+    // Nothing is a day old yet; everything is older than zero
+    assert_eq!(
+        memory
+            .delete_sessions_older_than(Duration::from_secs(86_400))
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        memory
+            .delete_sessions_older_than(Duration::ZERO)
+            .await
+            .unwrap(),
+        3
+    );
+    assert!(memory.list_sessions().await.unwrap().is_empty());
+}
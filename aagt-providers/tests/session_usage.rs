@@ -0,0 +1,117 @@
+//! Token usage summed per chat, priced, and carried across checkpoints
+
+use std::sync::Arc;
+
+use aagt_core::agent::core::AgentEvent;
+use aagt_core::agent::memory::Memory;
+use aagt_core::agent::provider::{ModelPrice, PriceTable};
+use aagt_core::agent::streaming::Usage;
+use aagt_core::prelude::*;
+use aagt_providers::mock::{MockProvider, MockTurn};
+use serde_json::json;
+
+mod common;
+
+use common::{PriceTool, Sessions};
+
+/// Agent over a mock reporting 40 prompt and 10 completion tokens per call
+fn priced_agent(memory: Arc<Sessions>, turns: Vec<MockTurn>) -> Agent<MockProvider> {
+    Agent::builder(MockProvider::scripted(turns, "Done.").with_usage(Usage::new(40, 10)))
+        .model("mock-model")
+        .tool(PriceTool)
+        .pricing(PriceTable::empty().set("mock-model", ModelPrice::new(1000.0, 2000.0)))
+        .with_memory(memory)
+        .session_id("s1")
+        .auto_load_skills(false)
+        .build()
+        .expect("agent builds")
+}
+
+#[tokio::test]
+async fn test_usage_sums_per_chat_and_persists_with_the_session() {
+    let memory = Arc::new(Sessions::default());
+    let agent = priced_agent(
+        memory.clone(),
+        vec![
+            MockTurn::tool_call("get_price", json!({"symbol": "SOL"})),
+            MockTurn::text("SOL is at $150."),
+        ],
+    );
+    let mut rx = agent.subscribe();
+
+    agent.prompt("What is SOL at?").await.unwrap();
+    let last = agent.last_usage().unwrap();
+    assert_eq!(
+        (
+            last.prompt_tokens,
+            last.completion_tokens,
+            last.total_tokens
+        ),
+        (80, 20, 100)
+    );
+    assert!(!last.estimated);
+
+    // Each call is priced at 40 * $1000/M + 10 * $2000/M
+    let mut costs = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        if let AgentEvent::Usage {
+            cost_usd,
+            estimated,
+            ..
+        } = event
+        {
+            assert!(!estimated);
+            costs.push(cost_usd.unwrap());
+        }
+    }
+    assert_eq!(costs.len(), 2);
+    assert!(costs.iter().all(|c| (c - 0.06).abs() < 1e-9));
+
+    // last_usage covers one chat; the session counter keeps growing
+    agent.prompt("And again?").await.unwrap();
+    assert_eq!(agent.last_usage().unwrap().total_tokens, 50);
+    let session = agent.session_usage();
+    assert_eq!((session.calls, session.total_tokens), (3, 150));
+    assert!((session.cost_usd - 0.18).abs() < 1e-9);
+
+    let saved = memory.retrieve_session("s1").await.unwrap().unwrap();
+    assert_eq!(saved.usage, session);
+
+    // A fresh agent resuming the session continues the count
+    let resumed = priced_agent(memory.clone(), Vec::new());
+    resumed.resume("s1").await.unwrap();
+    assert_eq!(resumed.session_usage().calls, 4);
+    assert_eq!(resumed.session_usage().prompt_tokens, 160);
+}
+
+#[tokio::test]
+async fn test_unreported_usage_is_estimated() {
+    let agent = Agent::builder(MockProvider::scripted(
+        [MockTurn::text("SOL is at $150.")],
+        "Done.",
+    ))
+    .auto_load_skills(false)
+    .build()
+    .expect("agent builds");
+    let mut rx = agent.subscribe();
+
+    agent.prompt("What is SOL at?").await.unwrap();
+    let usage = agent.last_usage().unwrap();
+    assert!(usage.estimated);
+    // 15 characters at four per token
+    assert_eq!(usage.completion_tokens, 4);
+    assert!(usage.prompt_tokens > 0);
+    assert!(agent.session_usage().estimated);
+
+    let event = std::iter::from_fn(|| rx.try_recv().ok())
+        .find(|e| matches!(e, AgentEvent::Usage { .. }))
+        .unwrap();
+    assert!(matches!(
+        event,
+        AgentEvent::Usage {
+            estimated: true,
+            cost_usd: None,
+            ..
+        }
+    ));
+}
@@ -0,0 +1,83 @@
+//! Stream delta events emitted while the agent consumes a provider stream
+
+use aagt_core::agent::core::AgentEvent;
+use aagt_core::prelude::*;
+use aagt_providers::mock::{MockProvider, MockTurn};
+use serde_json::json;
+
+mod common;
+
+use common::PriceTool;
+
+const ANSWER: &str = "SOL is trading at $150, up 3% over the last day.";
+
+fn agent(emit_deltas: bool) -> Agent<MockProvider> {
+    let provider = MockProvider::scripted(
+        [
+            MockTurn::tool_call("get_price", json!({"symbol": "SOL"})),
+            MockTurn::text(ANSWER),
+        ],
+        "Done.",
+    );
+    Agent::builder(provider)
+        .tool(PriceTool)
+        .auto_load_skills(false)
+        .emit_stream_deltas(emit_deltas)
+        .build()
+        .expect("agent builds")
+}
+
+#[tokio::test]
+async fn test_deltas_arrive_before_response_and_add_up_to_it() {
+    let agent = agent(true);
+    let mut rx = agent.subscribe();
+    let text = agent.prompt("What is SOL at?").await.expect("chat succeeds");
+    assert_eq!(text, ANSWER);
+
+    let mut events = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        events.push(event);
+    }
+    let position = |pred: &dyn Fn(&AgentEvent) -> bool| events.iter().position(pred);
+
+    let call_delta = position(&|e| {
+        matches!(e, AgentEvent::ToolCallDelta { id, tool, arguments }
+            if id == "call_1" && tool == "get_price" && arguments.contains("SOL"))
+    })
+    .expect("tool call delta");
+    let call = position(&|e| matches!(e, AgentEvent::ToolCall { .. })).expect("tool call");
+    assert!(call_delta < call);
+
+    let response = position(&|e| matches!(e, AgentEvent::Response { .. })).expect("response");
+    let deltas: Vec<(usize, &str)> = events
+        .iter()
+        .enumerate()
+        .filter_map(|(i, e)| match e {
+            AgentEvent::StreamDelta { content } => Some((i, content.as_str())),
+            _ => None,
+        })
+        .collect();
+    assert!(deltas.len() > 1, "expected several chunks: {:?}", deltas);
+    assert!(deltas.iter().all(|(i, _)| *i > call && *i < response));
+    let streamed: String = deltas.iter().map(|(_, chunk)| *chunk).collect();
+    assert_eq!(streamed, ANSWER);
+    match &events[response] {
+        AgentEvent::Response { content, .. } => assert_eq!(content, &streamed),
+        other => panic!("expected a response, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_deltas_can_be_turned_off() {
+    let agent = agent(false);
+    let mut rx = agent.subscribe();
+    agent.prompt("What is SOL at?").await.expect("chat succeeds");
+
+    while let Ok(event) = rx.try_recv() {
+        assert!(
+            !matches!(event, AgentEvent::StreamDelta { .. } | AgentEvent::ToolCallDelta { .. }),
+            "{:?}",
+            event
+        );
+    }
+}
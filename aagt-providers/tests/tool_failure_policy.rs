@@ -0,0 +1,191 @@
+//! Parallel tool results keep the model's call order, and failures follow the agent's policy
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use aagt_core::agent::core::ToolFailurePolicy;
+use aagt_core::agent::message::{Content, ContentPart};
+use aagt_core::agent::provider::{ChatRequest, ProviderMiddleware, WithMiddleware};
+use aagt_core::prelude::*;
+use aagt_providers::mock::{MockProvider, MockTurn};
+use async_trait::async_trait;
+use serde_json::json;
+
+/// Quote tool that answers after `delay`, failing its first `failures` calls
+struct QuoteTool {
+    name: &'static str,
+    delay: Duration,
+    failures: AtomicUsize,
+    calls: Arc<AtomicUsize>,
+    finished: Arc<AtomicUsize>,
+}
+
+impl QuoteTool {
+    fn new(name: &'static str, delay_ms: u64, failures: usize) -> Self {
+        Self {
+            name,
+            delay: Duration::from_millis(delay_ms),
+            failures: AtomicUsize::new(failures),
+            calls: Arc::default(),
+            finished: Arc::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for QuoteTool {
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Quote a token price".to_string(),
+            parameters: json!({"type": "object", "properties": {}}),
+            is_verified: true,
+            side_effect_free: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        self.finished.fetch_add(1, Ordering::SeqCst);
+        let failing = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if failing {
+            anyhow::bail!("{} feed unavailable", self.name);
+        }
+        Ok(format!("{} ok", self.name))
+    }
+}
+
+/// Records the tool results each request carries
+#[derive(Default)]
+struct ToolResults(Mutex<Vec<Vec<(String, String)>>>);
+
+#[async_trait]
+impl ProviderMiddleware for ToolResults {
+    async fn on_request(&self, request: &mut ChatRequest) -> aagt_core::error::Result<()> {
+        let results = request
+            .messages
+            .iter()
+            .filter_map(|m| match &m.content {
+                Content::Parts(parts) => Some(parts),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|part| match part {
+                ContentPart::ToolResult {
+                    tool_call_id,
+                    content,
+                    ..
+                } => Some((tool_call_id.clone(), content.clone())),
+                _ => None,
+            })
+            .collect();
+        self.0.lock().unwrap().push(results);
+        Ok(())
+    }
+}
+
+struct Run {
+    result: aagt_core::error::Result<String>,
+    results: Vec<(String, String)>,
+    slow_finished: Arc<AtomicUsize>,
+    broken_calls: Arc<AtomicUsize>,
+}
+
+/// One step calling slow (300ms), broken (50ms, failing once) and fast (10ms) quotes
+async fn run(policy: ToolFailurePolicy) -> Run {
+    let slow = QuoteTool::new("slow_quote", 300, 0);
+    let broken = QuoteTool::new("broken_quote", 50, 1);
+    let fast = QuoteTool::new("fast_quote", 10, 0);
+    let (slow_finished, broken_calls) = (slow.finished.clone(), broken.calls.clone());
+
+    let recorder = Arc::new(ToolResults::default());
+    let provider = WithMiddleware::new(
+        MockProvider::scripted(
+            [
+                MockTurn::ToolCalls(vec![
+                    ("slow_quote".to_string(), json!({})),
+                    ("broken_quote".to_string(), json!({})),
+                    ("fast_quote".to_string(), json!({})),
+                ]),
+                MockTurn::text("Quotes are in."),
+            ],
+            "Done.",
+        ),
+        vec![recorder.clone()],
+    );
+    let agent = Agent::builder(provider)
+        .tool(slow)
+        .tool(broken)
+        .tool(fast)
+        .tool_failure_policy(policy)
+        .auto_load_skills(false)
+        .build()
+        .expect("agent builds");
+
+    let result = agent.prompt("Quote everything").await;
+    let results = recorder
+        .0
+        .lock()
+        .unwrap()
+        .last()
+        .cloned()
+        .unwrap_or_default();
+    Run {
+        result,
+        results,
+        slow_finished,
+        broken_calls,
+    }
+}
+
+fn ids(results: &[(String, String)]) -> Vec<&str> {
+    results.iter().map(|(id, _)| id.as_str()).collect()
+}
+
+#[tokio::test]
+async fn test_errors_become_results_in_call_order() {
+    let run = run(ToolFailurePolicy::ContinueWithErrors).await;
+    assert_eq!(run.result.unwrap(), "Quotes are in.");
+    // Completion order was fast, broken, slow; results follow the calls
+    assert_eq!(ids(&run.results), ["call_1", "call_2", "call_3"]);
+    assert_eq!(run.results[0].1, "slow_quote ok");
+    assert!(
+        run.results[1].1.starts_with("Error:") && run.results[1].1.contains("feed unavailable"),
+        "{}",
+        run.results[1].1
+    );
+    assert_eq!(run.results[2].1, "fast_quote ok");
+}
+
+#[tokio::test]
+async fn test_abort_step_cancels_calls_in_flight() {
+    let run = run(ToolFailurePolicy::AbortStep).await;
+    let err = run.result.unwrap_err();
+    assert!(err.to_string().contains("broken_quote"), "{}", err);
+    // No follow-up request was made with partial results
+    assert!(run.results.is_empty());
+
+    // The slow call was dropped, not left running in the background
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert_eq!(run.slow_finished.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_retry_failed_reruns_only_the_failed_call() {
+    let run = run(ToolFailurePolicy::RetryFailed { attempts: 1 }).await;
+    assert_eq!(run.result.unwrap(), "Quotes are in.");
+    assert_eq!(run.broken_calls.load(Ordering::SeqCst), 2);
+    assert_eq!(ids(&run.results), ["call_1", "call_2", "call_3"]);
+    assert_eq!(run.results[1].1, "broken_quote ok");
+    assert_eq!(run.slow_finished.load(Ordering::SeqCst), 1);
+}
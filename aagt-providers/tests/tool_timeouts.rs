@@ -0,0 +1,91 @@
+//! Tool timeouts surface to the model as tool results instead of failing the chat
+
+use std::time::Duration;
+
+use aagt_core::agent::core::AgentEvent;
+use aagt_core::prelude::*;
+use aagt_providers::mock::{MockProvider, MockTurn};
+use async_trait::async_trait;
+use serde_json::json;
+
+/// Quote tool that takes `delay` to answer
+struct QuoteTool {
+    name: &'static str,
+    delay: Duration,
+}
+
+#[async_trait]
+impl Tool for QuoteTool {
+    fn name(&self) -> String {
+        self.name.to_string()
+    }
+
+    async fn definition(&self) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: "Quote a token price".to_string(),
+            parameters: json!({"type": "object", "properties": {"symbol": {"type": "string"}}}),
+            is_verified: true,
+            side_effect_free: true,
+            ..Default::default()
+        }
+    }
+
+    async fn call(&self, _arguments: &str) -> anyhow::Result<String> {
+        tokio::time::sleep(self.delay).await;
+        Ok(r#"{"price": 150}"#.to_string())
+    }
+}
+
+#[tokio::test]
+async fn test_slow_tool_times_out_and_the_model_recovers() {
+    let provider = MockProvider::scripted(
+        [
+            MockTurn::tool_call("slow_quote", json!({"symbol": "SOL"})),
+            MockTurn::tool_call("fast_quote", json!({"symbol": "SOL"})),
+            MockTurn::text("SOL is at $150."),
+        ],
+        "Done.",
+    );
+    let agent = Agent::builder(provider)
+        .tool(QuoteTool {
+            name: "slow_quote",
+            delay: Duration::from_secs(5),
+        })
+        .tool(QuoteTool {
+            name: "fast_quote",
+            delay: Duration::from_millis(1),
+        })
+        .default_tool_timeout(Duration::from_secs(2))
+        .tool_timeout("slow_quote", Duration::from_millis(50))
+        .auto_load_skills(false)
+        .build()
+        .expect("agent builds");
+    let mut rx = agent.subscribe();
+
+    let text = agent
+        .prompt("What is SOL at?")
+        .await
+        .expect("chat succeeds");
+    assert_eq!(text, "SOL is at $150.");
+
+    let mut errors = Vec::new();
+    let mut results = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            AgentEvent::Error { message } => errors.push(message),
+            AgentEvent::ToolResult { tool, output } => results.push((tool, output)),
+            _ => {}
+        }
+    }
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(
+        errors[0].contains("slow_quote timed out after"),
+        "{}",
+        errors[0]
+    );
+    assert_eq!(
+        results,
+        [("fast_quote".to_string(), r#"{"price": 150}"#.to_string())]
+    );
+}
@@ -0,0 +1,67 @@
+//! Headless run of the trading copilot example against its scripted mock provider
+
+#[path = "../examples/trading_copilot/copilot.rs"]
+mod copilot;
+
+use copilot::{Copilot, CopilotSpec};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_headless_demo_exercises_every_subsystem() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let spec = CopilotSpec::load(None).expect("bundled spec parses");
+    let copilot = Copilot::open(spec, dir.path().join("data"), dir.path().join("skills"))
+        .await
+        .expect("copilot opens");
+
+    let transcript = copilot::run_headless(
+        &copilot,
+        copilot::demo_provider(),
+        "ci",
+        [true, true, false],
+    )
+    .await
+    .expect("demo runs");
+
+    // Knowledge retrieval: the playbook was indexed, fetched and cited
+    assert!(
+        transcript
+            .tool_results("tiered_search")
+            .any(|o| o.contains("sol_rsi.md")),
+        "{:#?}",
+        transcript.events
+    );
+    assert!(transcript
+        .tool_results("fetch_document")
+        .any(|o| o.contains("RSI drops below 30")));
+    assert!(transcript.responses[0].contains("playbook/sol_rsi.md"));
+
+    // Approval flow: every order asked first, the last one was declined
+    assert_eq!(
+        transcript.approvals,
+        [
+            ("place_order".to_string(), true),
+            ("place_order".to_string(), true),
+            ("place_order".to_string(), false),
+        ]
+    );
+
+    // Tool execution and the risk denial path
+    let fills: Vec<_> = transcript.tool_results("place_order").collect();
+    assert_eq!(fills.len(), 1, "{:?}", fills);
+    assert!(fills[0].contains(r#""status":"filled""#), "{}", fills[0]);
+    assert!(
+        transcript
+            .errors()
+            .any(|e| e.contains("rejected by the risk manager") && e.contains("5000")),
+        "{:?}",
+        transcript.errors().collect::<Vec<_>>()
+    );
+    assert_eq!(
+        copilot.risk.remaining_daily_limit("demo").await,
+        rust_decimal::Decimal::from(2000)
+    );
+
+    // Session persisted for /resume: each prompt, tool call, tool result and answer
+    let history = copilot.history("ci").await.expect("session stored");
+    assert_eq!(history.len(), 18);
+}
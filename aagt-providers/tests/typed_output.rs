@@ -0,0 +1,86 @@
+//! Typed prompts re-prompt until the model's JSON parses
+
+use std::sync::{Arc, Mutex};
+
+use aagt_core::agent::provider::ChatRequest;
+use aagt_core::prelude::*;
+use aagt_providers::mock::{MockProvider, MockTurn};
+use serde::Deserialize;
+
+mod common;
+
+use common::Recording;
+
+#[derive(Debug, Deserialize, schemars::JsonSchema, PartialEq)]
+struct Quote {
+    symbol: String,
+    price: f64,
+}
+
+fn agent(turns: Vec<MockTurn>, retries: usize) -> (Agent<Recording>, Arc<Mutex<Vec<ChatRequest>>>) {
+    let provider = Recording::new(MockProvider::scripted(turns, "not json"));
+    let requests = provider.requests.clone();
+    let agent = Agent::builder(provider)
+        .auto_load_skills(false)
+        .typed_output_retries(retries)
+        .build()
+        .expect("agent builds");
+    (agent, requests)
+}
+
+#[tokio::test]
+async fn test_malformed_then_valid_response_parses() {
+    let (agent, requests) = agent(
+        vec![
+            MockTurn::text(r#"Sure: {"symbol": "SOL"}"#),
+            MockTurn::text("```json\n{\"symbol\": \"SOL\", \"price\": 150.5}\n```"),
+        ],
+        2,
+    );
+
+    let quote: Quote = agent.prompt_typed("Quote SOL").await.expect("parses");
+    assert_eq!(
+        quote,
+        Quote {
+            symbol: "SOL".to_string(),
+            price: 150.5
+        }
+    );
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    let system = requests[0].system_prompt.as_deref().unwrap_or_default();
+    assert!(system.contains("Respond only with JSON"), "{}", system);
+    assert!(system.contains("\"price\""), "{}", system);
+    let extra = requests[0].extra_params.as_ref().expect("extra params");
+    assert_eq!(extra["response_format"]["type"], "json_object");
+
+    // The retry shows the model its reply and the parse error
+    let retry = requests[1]
+        .messages
+        .last()
+        .expect("retry prompt")
+        .content
+        .as_text();
+    assert!(retry.contains("missing field `price`"), "{}", retry);
+}
+
+#[tokio::test]
+async fn test_schema_validation_error_after_retries() {
+    let (agent, requests) = agent(
+        vec![
+            MockTurn::text("no idea"),
+            MockTurn::text(r#"{"symbol": 1}"#),
+        ],
+        1,
+    );
+
+    match agent.prompt_typed::<Quote>("Quote SOL").await {
+        Err(Error::SchemaValidation { raw, error }) => {
+            assert_eq!(raw, r#"{"symbol": 1}"#);
+            assert!(error.to_string().contains("invalid type"), "{}", error);
+        }
+        other => panic!("expected a schema validation error, got {:?}", other),
+    }
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
@@ -1,7 +1,7 @@
 use crate::store::QmdStore;
 use aagt_core::agent::memory::Memory;
 use aagt_core::agent::message::Message;
-use aagt_core::agent::session::AgentSession;
+use aagt_core::agent::session::{AgentSession, SessionQuery, SessionSummary};
 use aagt_core::knowledge::rag::Document;
 use async_trait::async_trait;
 use std::sync::Arc;
@@ -49,19 +49,21 @@ impl Memory for QmdMemory {
 
     async fn search(&self, _user_id: &str, _agent_id: Option<&str>, query: &str, limit: usize) -> aagt_core::error::Result<Vec<Document>> {
         let results = self.store.search_fts(query, limit).map_err(|e| aagt_core::error::Error::Internal(e.to_string()))?;
-        
-        let docs = results.into_iter().map(|r| Document {
-            id: r.document.docid,
-            title: r.document.title,
-            content: r.document.body.unwrap_or_default(),
-            summary: r.document.summary,
-            collection: Some(r.document.collection),
-            path: Some(r.document.path),
-            metadata: std::collections::HashMap::new(), // TODO: populate
-            score: r.score as f32,
-        }).collect();
-        
-        Ok(docs)
+        Ok(results.into_iter().map(search_result_to_rag).collect())
+    }
+
+    async fn search_as_of(&self, user_id: &str, agent_id: Option<&str>, query: &str, limit: usize, as_of: Option<i64>) -> aagt_core::error::Result<Vec<Document>> {
+        let Some(as_of) = as_of else {
+            return self.search(user_id, agent_id, query, limit).await;
+        };
+        let results = self.store.search_fts_as_of(query, limit, as_of).map_err(|e| aagt_core::error::Error::Internal(e.to_string()))?;
+        Ok(results.into_iter().map(search_result_to_rag).collect())
+    }
+
+    async fn store_knowledge(&self, _user_id: &str, _agent_id: Option<&str>, title: &str, content: &str, collection: &str) -> aagt_core::error::Result<()> {
+        // The title doubles as the virtual path so the document can be fetched back
+        self.store.store_document(collection, title, title, content).map_err(|e| aagt_core::error::Error::Internal(e.to_string()))?;
+        Ok(())
     }
 
     async fn store_session(&self, session: AgentSession) -> aagt_core::error::Result<()> {
@@ -80,6 +82,50 @@ impl Memory for QmdMemory {
         }
     }
 
+    async fn list_sessions(&self) -> aagt_core::error::Result<Vec<AgentSession>> {
+        let rows = self.store.list_sessions().map_err(|e| aagt_core::error::Error::Internal(e.to_string()))?;
+        rows.into_iter()
+            .map(|(_, json)| serde_json::from_str(&json).map_err(|e| aagt_core::error::Error::Internal(e.to_string())))
+            .collect()
+    }
+
+    async fn delete_session(&self, session_id: &str) -> aagt_core::error::Result<bool> {
+        let existed = self.store.load_session(session_id).map_err(|e| aagt_core::error::Error::Internal(e.to_string()))?.is_some();
+        self.store.delete_session(session_id).map_err(|e| aagt_core::error::Error::Internal(e.to_string()))?;
+        Ok(existed)
+    }
+
+    async fn list_session_summaries(&self, query: &SessionQuery) -> aagt_core::error::Result<Vec<SessionSummary>> {
+        self.store.list_session_summaries(query).map_err(|e| aagt_core::error::Error::Internal(e.to_string()))
+    }
+
+    async fn delete_sessions_older_than(&self, age: std::time::Duration) -> aagt_core::error::Result<usize> {
+        self.store.delete_sessions_older_than(age).map_err(|e| aagt_core::error::Error::Internal(e.to_string()))
+    }
+
+    async fn try_acquire_lease(&self, name: &str, holder: &str, ttl: std::time::Duration) -> aagt_core::error::Result<bool> {
+        self.store.try_acquire_lease(name, holder, ttl).map_err(|e| aagt_core::error::Error::Internal(e.to_string()))
+    }
+
+    async fn fetch_document(&self, collection: &str, path: &str) -> aagt_core::error::Result<Option<Document>> {
+        let doc = self.store.get_by_path(collection, path).map_err(|e| aagt_core::error::Error::Internal(e.to_string()))?;
+        Ok(doc.map(to_rag_document))
+    }
+
+    async fn fetch_document_as_of(&self, collection: &str, path: &str, as_of: Option<i64>) -> aagt_core::error::Result<Option<Document>> {
+        let doc = match as_of {
+            Some(as_of) => self.store.get_by_path_as_of(collection, path, as_of),
+            None => self.store.get_by_path(collection, path),
+        }
+        .map_err(|e| aagt_core::error::Error::Internal(e.to_string()))?;
+        Ok(doc.map(to_rag_document))
+    }
+
+    async fn fetch_document_by_id(&self, id: &str) -> aagt_core::error::Result<Option<Document>> {
+        let doc = self.store.get_by_docid(id).map_err(|e| aagt_core::error::Error::Internal(e.to_string()))?;
+        Ok(doc.map(to_rag_document))
+    }
+
     async fn clear(&self, _user_id: &str, _agent_id: Option<&str>) -> aagt_core::error::Result<()> {
         Ok(())
     }
@@ -88,3 +134,79 @@ impl Memory for QmdMemory {
         Ok(None)
     }
 }
+
+fn to_rag_document(doc: crate::store::Document) -> Document {
+    Document {
+        id: doc.docid,
+        title: doc.title,
+        content: doc.body.unwrap_or_default(),
+        summary: doc.summary,
+        collection: Some(doc.collection),
+        path: Some(doc.path),
+        metadata: std::collections::HashMap::new(),
+        score: 0.0,
+    }
+}
+
+fn search_result_to_rag(result: crate::store::SearchResult) -> Document {
+    Document {
+        score: result.score as f32,
+        ..to_rag_document(result.document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aagt_core::skills::tool::{DiffTool, Tool};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_diff_tool_resolves_docids() {
+        let dir = TempDir::new().unwrap();
+        let store = Arc::new(QmdStore::new(dir.path().join("test.db")).unwrap());
+        let old = store.store_document("reports", "daily/1.md", "Day 1", "# Report\nSOL flat\n").unwrap();
+        store.store_document("reports", "daily/2.md", "Day 2", "# Report\nSOL up 5%\n").unwrap();
+
+        let tool = DiffTool::new().with_memory(Arc::new(QmdMemory::new(store)));
+        let args = serde_json::json!({
+            "left": { "docid": old.docid },
+            "right": { "collection": "reports", "path": "daily/2.md" }
+        });
+        let out = tool.call(&args.to_string()).await.unwrap();
+
+        assert!(out.contains(&format!("--- #{}", old.docid)));
+        assert!(out.contains("+++ reports/daily/2.md"));
+        assert!(out.contains("-SOL flat\n+SOL up 5%"));
+
+        let missing = serde_json::json!({ "left": { "docid": "abcdef" }, "right": { "text": "x" } });
+        assert!(tool.call(&missing.to_string()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_memory_tools_answer_as_of() {
+        use aagt_core::agent::memory::with_as_of;
+        use aagt_core::skills::tool::{FetchDocumentTool, TieredSearchTool};
+        use chrono::{TimeZone, Utc};
+
+        let dir = TempDir::new().unwrap();
+        let store = Arc::new(QmdStore::new(dir.path().join("test.db")).unwrap());
+        let jan = Utc.with_ymd_and_hms(2024, 1, 10, 0, 0, 0).unwrap();
+        let mar = Utc.with_ymd_and_hms(2024, 3, 10, 0, 0, 0).unwrap();
+        store.store_document_at("plans", "q1.md", "Q1", "Hedge exposure with puts", jan).unwrap();
+        store.store_document_at("plans", "q1.md", "Q1", "Hedge exposure with futures", mar).unwrap();
+        let memory: Arc<dyn Memory> = Arc::new(QmdMemory::new(store));
+
+        let fetch = FetchDocumentTool::new(memory.clone());
+        let args = serde_json::json!({ "collection": "plans", "path": "q1.md", "as_of": "2024-02-01" });
+        assert!(fetch.call(&args.to_string()).await.unwrap().contains("with puts"));
+        let args = serde_json::json!({ "collection": "plans", "path": "q1.md" });
+        assert!(fetch.call(&args.to_string()).await.unwrap().contains("with futures"));
+
+        // A pinned time applies to calls that don't name one
+        let search = TieredSearchTool::new(memory);
+        let out = with_as_of(jan.timestamp() - 1, search.call(r#"{"query": "hedge"}"#)).await.unwrap();
+        assert_eq!(out, "No results found.");
+    }
+
+}
@@ -0,0 +1,404 @@
+//! Online backup and restore of a search engine's files
+//!
+//! [`HybridSearchEngine::backup_to`] snapshots the SQLite database (through
+//! [`QmdStore::backup_to`](crate::store::QmdStore::backup_to)) and, when the
+//! engine persists one, the vector store into a directory, then writes a
+//! [`BackupManifest`] with the size and SHA-256 of each file. Every file is
+//! written under a temp name and renamed, and the manifest goes last, so a
+//! directory with a manifest holds a complete backup.
+//!
+//! [`HybridSearchEngine::restore_from`] checks every file against the manifest
+//! before it replaces anything, then swaps the files in and opens the engine.
+//!
+//! [`ScheduledBackup`] is a [`BackupJob`] for
+//! [`MaintenanceManager::start_backups`](aagt_core::infra::maintenance::MaintenanceManager::start_backups):
+//! each run writes a timestamped directory and prunes the oldest beyond `keep`.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use aagt_core::infra::maintenance::BackupJob;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::content_hash::hash_file;
+use crate::error::{QmdError, Result};
+use crate::hybrid_search::{HybridSearchConfig, HybridSearchEngine};
+
+/// Manifest file name inside a backup directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Backup file holding the SQLite database
+pub const DB_FILE: &str = "qmd.db";
+
+/// Backup file holding the vector store
+pub const VECTORS_FILE: &str = "vectors.bin";
+
+/// Manifest layout version written by this build
+const MANIFEST_VERSION: u32 = 1;
+
+/// Backups [`ScheduledBackup`] keeps by default
+const DEFAULT_KEEP: usize = 7;
+
+/// Contents of a backup directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Manifest layout version
+    pub version: u32,
+    /// When the backup was taken
+    pub created_at: DateTime<Utc>,
+    /// Files in the directory, by name
+    pub files: Vec<BackupFile>,
+}
+
+/// One file of a backup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupFile {
+    /// File name inside the backup directory
+    pub name: String,
+    /// Size in bytes
+    pub bytes: u64,
+    /// Hex SHA-256 of the contents
+    pub sha256: String,
+}
+
+impl BackupManifest {
+    /// Read the manifest of the backup in `dir`
+    pub fn read(dir: impl AsRef<Path>) -> Result<Self> {
+        let path = dir.as_ref().join(MANIFEST_FILE);
+        let data = std::fs::read(&path).map_err(|e| {
+            QmdError::InvalidBackup(format!("cannot read {}: {}", path.display(), e))
+        })?;
+        let manifest: Self = serde_json::from_slice(&data)?;
+        if manifest.version != MANIFEST_VERSION {
+            return Err(QmdError::InvalidBackup(format!(
+                "manifest version {} is not supported (expected {})",
+                manifest.version, MANIFEST_VERSION
+            )));
+        }
+        Ok(manifest)
+    }
+
+    /// Check every listed file in `dir` against its recorded size and hash
+    pub fn verify(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        for file in &self.files {
+            let path = dir.join(&file.name);
+            let bytes = std::fs::metadata(&path)
+                .map_err(|e| QmdError::InvalidBackup(format!("{}: {}", path.display(), e)))?
+                .len();
+            if bytes != file.bytes {
+                return Err(QmdError::InvalidBackup(format!(
+                    "{} is {} bytes, manifest says {}",
+                    file.name, bytes, file.bytes
+                )));
+            }
+            let actual = hash_file(&path)?;
+            if actual != file.sha256 {
+                return Err(QmdError::ChecksumMismatch {
+                    file: file.name.clone(),
+                    expected: file.sha256.clone(),
+                    actual,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Entry for file `name`, if the backup has it
+    pub fn file(&self, name: &str) -> Option<&BackupFile> {
+        self.files.iter().find(|f| f.name == name)
+    }
+}
+
+/// `path` with `suffix` appended to its file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name: OsString = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Where `config` keeps the vector store, if it persists one
+fn vector_target(config: &HybridSearchConfig) -> Option<PathBuf> {
+    #[cfg(feature = "vector-index")]
+    {
+        config.vector_store_path.clone()
+    }
+    #[cfg(not(feature = "vector-index"))]
+    {
+        let _ = config;
+        None
+    }
+}
+
+/// Manifest entry for a file already written to `dir`
+fn describe(dir: &Path, name: &str) -> Result<BackupFile> {
+    let path = dir.join(name);
+    Ok(BackupFile {
+        name: name.to_string(),
+        bytes: std::fs::metadata(&path)?.len(),
+        sha256: hash_file(&path)?,
+    })
+}
+
+impl HybridSearchEngine {
+    /// Back up the database and vector store into `dir` while the engine is in use
+    ///
+    /// The vector store is included when the config has a `vector_store_path`.
+    /// Backing up into a directory that already holds a backup replaces it.
+    pub fn backup_to(&self, dir: impl AsRef<Path>) -> Result<BackupManifest> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        // Without a manifest a half-rewritten directory is never taken for a backup
+        let manifest_path = dir.join(MANIFEST_FILE);
+        if manifest_path.exists() {
+            std::fs::remove_file(&manifest_path)?;
+        }
+
+        self.qmd_store().backup_to(dir.join(DB_FILE))?;
+        let db = describe(dir, DB_FILE)?;
+        let vectors = self.snapshot_vectors(dir)?;
+
+        let manifest = BackupManifest {
+            version: MANIFEST_VERSION,
+            created_at: Utc::now(),
+            files: std::iter::once(db).chain(vectors).collect(),
+        };
+        let tmp_path = with_suffix(&manifest_path, ".tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(&manifest)?)?;
+        std::fs::rename(&tmp_path, &manifest_path)?;
+
+        info!("Backed up search engine to {:?}", dir);
+        Ok(manifest)
+    }
+
+    /// Write the vector store into `dir`, if the engine persists one
+    fn snapshot_vectors(&self, dir: &Path) -> Result<Option<BackupFile>> {
+        #[cfg(feature = "vector-index")]
+        if self.config().vector_store_path.is_some() {
+            self.vector_store().snapshot_to(dir.join(VECTORS_FILE))?;
+            return describe(dir, VECTORS_FILE).map(Some);
+        }
+        let _ = dir;
+        Ok(None)
+    }
+
+    /// Replace the files `config` points at with the backup in `dir` and open the engine
+    ///
+    /// The whole backup is verified first; on any mismatch nothing is touched.
+    /// Engines open on the same files must be dropped before calling this, as
+    /// their connections would keep writing to the replaced database.
+    pub fn restore_from(dir: impl AsRef<Path>, config: HybridSearchConfig) -> Result<Self> {
+        let dir = dir.as_ref();
+        let manifest = BackupManifest::read(dir)?;
+        if manifest.file(DB_FILE).is_none() {
+            return Err(QmdError::InvalidBackup(format!("manifest lists no {}", DB_FILE)));
+        }
+        manifest.verify(dir)?;
+
+        let vector_target = vector_target(&config).filter(|path| {
+            let included = manifest.file(VECTORS_FILE).is_some();
+            if !included {
+                warn!("Backup in {:?} has no vector store; keeping {:?}", dir, path);
+            }
+            included
+        });
+        let targets = std::iter::once((DB_FILE, config.db_path.clone()))
+            .chain(vector_target.map(|path| (VECTORS_FILE, path)));
+
+        // Stage every file next to its target first, so a failed copy leaves the originals
+        let mut staged = Vec::new();
+        for (name, target) in targets {
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let tmp_path = with_suffix(&target, ".restore");
+            std::fs::copy(dir.join(name), &tmp_path)?;
+            staged.push((tmp_path, target));
+        }
+
+        // A leftover WAL from the old database would be replayed over the restored one
+        for suffix in ["-wal", "-shm"] {
+            let sidecar = with_suffix(&config.db_path, suffix);
+            if sidecar.exists() {
+                std::fs::remove_file(&sidecar)?;
+            }
+        }
+        for (tmp_path, target) in staged {
+            std::fs::rename(&tmp_path, &target)?;
+        }
+
+        info!("Restored search engine from backup taken {}", manifest.created_at);
+        Self::new(config)
+    }
+}
+
+/// Periodic engine backups into timestamped directories under one root
+#[derive(Clone)]
+pub struct ScheduledBackup {
+    engine: Arc<HybridSearchEngine>,
+    root: PathBuf,
+    keep: usize,
+}
+
+impl ScheduledBackup {
+    /// Back up `engine` under `root`, keeping the 7 most recent backups
+    pub fn new(engine: Arc<HybridSearchEngine>, root: impl Into<PathBuf>) -> Self {
+        Self {
+            engine,
+            root: root.into(),
+            keep: DEFAULT_KEEP,
+        }
+    }
+
+    /// Keep the `keep` most recent backups (at least one)
+    pub fn keep(mut self, keep: usize) -> Self {
+        self.keep = keep.max(1);
+        self
+    }
+
+    /// Take a backup now and prune old ones, returning the new backup's directory
+    pub fn run_once(&self) -> Result<PathBuf> {
+        let dir = self
+            .root
+            .join(Utc::now().format("%Y%m%dT%H%M%S%.3fZ").to_string());
+        self.engine.backup_to(&dir)?;
+        self.prune()?;
+        Ok(dir)
+    }
+
+    /// Complete backups under the root, oldest first
+    pub fn backups(&self) -> Result<Vec<PathBuf>> {
+        let mut dirs: Vec<PathBuf> = std::fs::read_dir(&self.root)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.join(MANIFEST_FILE).is_file())
+            .collect();
+        // Timestamped names sort chronologically
+        dirs.sort();
+        Ok(dirs)
+    }
+
+    fn prune(&self) -> Result<()> {
+        let backups = self.backups()?;
+        let excess = backups.len().saturating_sub(self.keep);
+        for dir in &backups[..excess] {
+            info!("Removing old backup {:?}", dir);
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl BackupJob for ScheduledBackup {
+    async fn run(&self) -> aagt_core::error::Result<PathBuf> {
+        // SQLite and file hashing block, so keep them off the async workers
+        let job = self.clone();
+        tokio::task::spawn_blocking(move || job.run_once())
+            .await
+            .map_err(|e| aagt_core::error::Error::Internal(format!("Backup task failed: {}", e)))?
+            .map_err(|e| aagt_core::error::Error::Internal(format!("Backup failed: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::Collection;
+    use crate::test_support::with_test_embeddings;
+    use tempfile::TempDir;
+
+    fn config(dir: &Path) -> HybridSearchConfig {
+        let config = HybridSearchConfig {
+            db_path: dir.join("live").join("qmd.db"),
+            ..Default::default()
+        };
+        with_test_embeddings(config, dir)
+    }
+
+    fn seeded_engine(config: HybridSearchConfig) -> HybridSearchEngine {
+        let engine = HybridSearchEngine::new(config).unwrap();
+        engine
+            .create_collection(Collection {
+                name: "notes".to_string(),
+                description: None,
+                glob_pattern: "**/*.md".to_string(),
+                root_path: None,
+            })
+            .unwrap();
+        engine
+            .index_document("notes", "sol.md", "SOL", "Buy SOL when funding turns negative")
+            .unwrap();
+        engine
+            .index_document("notes", "eth.md", "ETH", "Hedge ETH exposure with perps")
+            .unwrap();
+        engine
+    }
+
+    #[test]
+    fn test_restore_after_corruption() {
+        let temp = TempDir::new().unwrap();
+        let config = config(temp.path());
+        let backup_dir = temp.path().join("backup");
+
+        let engine = seeded_engine(config.clone());
+        let manifest = engine.backup_to(&backup_dir).unwrap();
+        assert_eq!(manifest.file(DB_FILE).unwrap().sha256, hash_file(&backup_dir.join(DB_FILE)).unwrap());
+        assert_eq!(manifest.file(VECTORS_FILE).is_some(), cfg!(feature = "vector-index"));
+        assert_eq!(BackupManifest::read(&backup_dir).unwrap(), manifest);
+        // Written after the backup, so gone after the restore
+        engine
+            .index_document("notes", "late.md", "Late", "Written after the snapshot")
+            .unwrap();
+        drop(engine);
+
+        std::fs::write(&config.db_path, b"not a database at all").unwrap();
+        assert!(HybridSearchEngine::new(config.clone()).is_err());
+
+        let restored = HybridSearchEngine::restore_from(&backup_dir, config).unwrap();
+        let results = restored.search("funding", 10).unwrap();
+        assert_eq!(results[0].document.path, "sol.md");
+        assert!(results.iter().all(|r| r.document.path != "late.md"));
+        assert_eq!(restored.count_documents("notes", None).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_tampered_backup_is_rejected() {
+        let temp = TempDir::new().unwrap();
+        let config = config(temp.path());
+        let backup_dir = temp.path().join("backup");
+
+        seeded_engine(config.clone()).backup_to(&backup_dir).unwrap();
+        let mut bytes = std::fs::read(backup_dir.join(DB_FILE)).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        std::fs::write(backup_dir.join(DB_FILE), &bytes).unwrap();
+        let original = std::fs::read(&config.db_path).unwrap();
+
+        let err = HybridSearchEngine::restore_from(&backup_dir, config.clone()).err().unwrap();
+        assert!(matches!(err, QmdError::ChecksumMismatch { ref file, .. } if file == DB_FILE), "{}", err);
+        assert_eq!(std::fs::read(&config.db_path).unwrap(), original);
+
+        std::fs::remove_file(backup_dir.join(MANIFEST_FILE)).unwrap();
+        let err = HybridSearchEngine::restore_from(&backup_dir, config).err().unwrap();
+        assert!(matches!(err, QmdError::InvalidBackup(_)), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_backup_prunes_old_backups() {
+        let temp = TempDir::new().unwrap();
+        let engine = Arc::new(seeded_engine(config(temp.path())));
+        let job = ScheduledBackup::new(engine, temp.path().join("backups")).keep(2);
+
+        let mut written = Vec::new();
+        for _ in 0..3 {
+            written.push(job.run().await.unwrap());
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert_eq!(job.backups().unwrap(), written[1..]);
+        assert!(!written[0].exists());
+        BackupManifest::read(&written[2]).unwrap().verify(&written[2]).unwrap();
+    }
+}
@@ -2,10 +2,28 @@
 //!
 //! Splits long documents into overlapping chunks for better vector retrieval.
 //! Uses sliding window with 800 tokens per chunk and 15% overlap.
+//!
+//! [`ChunkMode::Markdown`] splits at headings first and windows within each
+//! section, keeping fenced code blocks whole and tagging every chunk with the
+//! headings above it.
 
 use crate::error::Result;
 use tokenizers::Tokenizer;
 
+/// How a document is split into chunks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChunkMode {
+    /// Sliding token window over the whole text
+    #[default]
+    Plain,
+    /// Split at markdown headings, then window within each section
+    ///
+    /// Paragraphs, tables and fenced code blocks are packed whole while they
+    /// fit; only an oversized paragraph is windowed (with overlap). A code
+    /// block is never split, even when it exceeds `chunk_size` on its own.
+    Markdown,
+}
+
 /// Configuration for text chunking
 #[derive(Debug, Clone)]
 pub struct ChunkerConfig {
@@ -15,6 +33,8 @@ pub struct ChunkerConfig {
     pub overlap: usize,
     /// Path to tokenizer file
     pub tokenizer_path: std::path::PathBuf,
+    /// Splitting strategy (default: plain)
+    pub mode: ChunkMode,
 }
 
 impl Default for ChunkerConfig {
@@ -23,6 +43,7 @@ impl Default for ChunkerConfig {
             chunk_size: 800,
             overlap: 40, // 5% overlap (Reduced to save tokens)
             tokenizer_path: std::path::PathBuf::from("models/tokenizer.json"),
+            mode: ChunkMode::Plain,
         }
     }
 }
@@ -42,6 +63,8 @@ pub struct Chunk {
     pub start_token: usize,
     /// End position in tokens
     pub end_token: usize,
+    /// Headings above the chunk, e.g. `## Strategy > ### Entry rules` (markdown mode only)
+    pub heading_path: Option<String>,
 }
 
 /// Text chunker for creating overlapping text segments
@@ -114,6 +137,15 @@ impl Chunker {
             ));
         }
 
+        if self.config.mode == ChunkMode::Markdown {
+            return Ok(chunk_markdown(
+                text,
+                offsets,
+                self.config.chunk_size,
+                stride,
+            ));
+        }
+
         let mut chunks = Vec::new();
         let mut chunk_seq = 0;
 
@@ -141,6 +173,7 @@ impl Chunker {
                 end_char,
                 start_token: window_start_token,
                 end_token: window_end_token,
+                heading_path: None,
             });
 
             chunk_seq += 1;
@@ -181,6 +214,172 @@ impl Chunker {
     }
 }
 
+/// A run of markdown lines packed as a unit: a paragraph, table or fenced code block
+#[derive(Debug)]
+struct Block {
+    /// Byte range in the document
+    start: usize,
+    end: usize,
+    /// Fenced code, never split
+    code: bool,
+    /// Index of the heading section the block belongs to
+    section: usize,
+    heading_path: Option<String>,
+}
+
+/// Level of an ATX heading line (`## Title` is 2)
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' '))).then_some(level)
+}
+
+/// Marker character and length of a code fence line (`` ``` `` or `~~~`)
+fn fence(line: &str) -> Option<(char, usize)> {
+    let marker = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = line.chars().take_while(|&c| c == marker).count();
+    (len >= 3).then_some((marker, len))
+}
+
+fn breadcrumb(headings: &[(usize, String)]) -> Option<String> {
+    (!headings.is_empty()).then(|| {
+        headings
+            .iter()
+            .map(|(_, heading)| heading.as_str())
+            .collect::<Vec<_>>()
+            .join(" > ")
+    })
+}
+
+/// Split `text` into blocks, tracking the heading breadcrumb of each
+fn markdown_blocks(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut section = 0;
+    let mut open_fence: Option<(char, usize)> = None;
+    let mut current: Option<Block> = None;
+    let mut pos = 0;
+
+    for line in text.split_inclusive('\n') {
+        let start = pos;
+        pos += line.len();
+        let trimmed = line.trim();
+
+        if let Some((marker, len)) = open_fence {
+            if let Some(block) = current.as_mut() {
+                block.end = pos;
+            }
+            let closes = fence(trimmed)
+                .is_some_and(|(m, l)| m == marker && l >= len && l == trimmed.chars().count());
+            if closes {
+                open_fence = None;
+                blocks.extend(current.take());
+            }
+            continue;
+        }
+
+        let new_block = |code: bool, headings: &[(usize, String)], section: usize| Block {
+            start,
+            end: pos,
+            code,
+            section,
+            heading_path: breadcrumb(headings),
+        };
+        if let Some(marker) = fence(trimmed) {
+            blocks.extend(current.take());
+            open_fence = Some(marker);
+            current = Some(new_block(true, &headings, section));
+        } else if let Some(level) = heading_level(trimmed) {
+            blocks.extend(current.take());
+            headings.retain(|(l, _)| *l < level);
+            headings.push((level, trimmed.to_string()));
+            section += 1;
+            current = Some(new_block(false, &headings, section));
+        } else if trimmed.is_empty() {
+            blocks.extend(current.take());
+        } else if let Some(block) = current.as_mut() {
+            block.end = pos;
+        } else {
+            current = Some(new_block(false, &headings, section));
+        }
+    }
+    blocks.extend(current);
+    blocks
+}
+
+/// Markdown chunking over a tokenized `text`
+///
+/// `offsets` are the byte ranges of the tokens, in order.
+fn chunk_markdown(
+    text: &str,
+    offsets: &[(usize, usize)],
+    chunk_size: usize,
+    stride: usize,
+) -> Vec<Chunk> {
+    // Tokens lying within a byte range
+    let tokens = |start: usize, end: usize| {
+        let first = offsets.partition_point(|o| o.0 < start);
+        let last = offsets.partition_point(|o| o.1 <= end).max(first);
+        (first, last)
+    };
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut emit = |start: usize, end: usize, heading_path: &Option<String>| {
+        let slice = &text[start..end];
+        let start = start + (slice.len() - slice.trim_start().len());
+        let end = start + slice.trim().len();
+        if start >= end {
+            return;
+        }
+        let (start_token, end_token) = tokens(start, end);
+        chunks.push(Chunk {
+            seq: chunks.len(),
+            text: text[start..end].to_string(),
+            start_char: start,
+            end_char: end,
+            start_token,
+            end_token,
+            heading_path: heading_path.clone(),
+        });
+    };
+
+    let blocks = markdown_blocks(text);
+    for section in blocks.chunk_by(|a, b| a.section == b.section) {
+        let heading_path = &section[0].heading_path;
+        let mut packed: Option<(usize, usize)> = None;
+        for block in section {
+            if let Some((start, end)) = packed.as_mut() {
+                let (first, last) = tokens(*start, block.end);
+                if last - first <= chunk_size {
+                    *end = block.end;
+                    continue;
+                }
+                emit(*start, *end, heading_path);
+                packed = None;
+            }
+
+            let (first, last) = tokens(block.start, block.end);
+            if block.code || last - first <= chunk_size {
+                packed = Some((block.start, block.end));
+                continue;
+            }
+            // An oversized paragraph gets the plain sliding window
+            let mut window = first;
+            loop {
+                let window_end = (window + chunk_size).min(last);
+                emit(offsets[window].0, offsets[window_end - 1].1, heading_path);
+                if window_end >= last {
+                    break;
+                }
+                window += stride;
+            }
+        }
+        if let Some((start, end)) = packed {
+            emit(start, end, heading_path);
+        }
+    }
+    chunks
+}
+
 /// Statistics about chunking
 #[derive(Debug, Clone)]
 pub struct ChunkStats {
@@ -262,6 +461,102 @@ mod tests {
         assert!(stats.estimated_chunks > 0);
     }
 
+    /// Byte ranges of whitespace-separated words, standing in for tokenizer offsets
+    fn word_offsets(text: &str) -> Vec<(usize, usize)> {
+        let mut offsets = Vec::new();
+        let mut start = None;
+        for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+            match (c.is_whitespace(), start) {
+                (true, Some(s)) => {
+                    offsets.push((s, i));
+                    start = None;
+                }
+                (false, None) => start = Some(i),
+                _ => {}
+            }
+        }
+        offsets
+    }
+
+    const DOC: &str = "Intro before any heading.
+
+## Strategy
+
+Trade the trend with small size.
+
+### Entry rules
+
+Buy when RSI drops below thirty and volume confirms the move higher today.
+
+```python
+# not a heading
+def entry(rsi, volume):
+    return rsi < 30 and volume > 1.5
+```
+
+| rule | value |
+| stop | 5% |
+
+## Risk
+
+Never risk more than one percent.
+";
+
+    #[test]
+    fn test_markdown_chunks_keep_code_whole_and_track_headings() {
+        let chunks = chunk_markdown(DOC, &word_offsets(DOC), 8, 6);
+        let code_start = DOC.find("```python").unwrap();
+        let code_end = DOC.rfind("```").unwrap() + 3;
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert_eq!(chunk.seq, i);
+            assert_eq!(&DOC[chunk.start_char..chunk.end_char], chunk.text);
+            // No chunk starts or ends inside the fence
+            assert!(!(code_start < chunk.start_char && chunk.start_char < code_end));
+            assert!(!(code_start < chunk.end_char && chunk.end_char < code_end));
+            assert_eq!(chunk.text.matches("```").count() % 2, 0, "{}", chunk.text);
+        }
+
+        let heading_of = |needle: &str| {
+            chunks
+                .iter()
+                .find(|c| c.text.contains(needle))
+                .unwrap()
+                .heading_path
+                .clone()
+        };
+        assert_eq!(heading_of("Intro"), None);
+        assert_eq!(heading_of("Trade the trend"), Some("## Strategy".to_string()));
+        let entry = Some("## Strategy > ### Entry rules".to_string());
+        assert_eq!(heading_of("def entry"), entry);
+        assert_eq!(heading_of("| stop"), entry);
+        assert_eq!(heading_of("one percent"), Some("## Risk".to_string()));
+
+        // The oversized paragraph is windowed with overlap, the oversized code block is not
+        assert!(chunks.iter().filter(|c| c.text.contains("and volume")).count() > 1);
+        let code = chunks.iter().find(|c| c.text.contains("def entry")).unwrap();
+        assert!(code.text.starts_with("```python") && code.text.ends_with("```"));
+        assert!(code.end_token - code.start_token > 8);
+    }
+
+    #[test]
+    fn test_markdown_sections_never_merge() {
+        // Everything fits in one window, but each section gets its own chunk
+        let chunks = chunk_markdown(DOC, &word_offsets(DOC), 1000, 900);
+        let paths: Vec<Option<&str>> = chunks.iter().map(|c| c.heading_path.as_deref()).collect();
+        assert_eq!(
+            paths,
+            [
+                None,
+                Some("## Strategy"),
+                Some("## Strategy > ### Entry rules"),
+                Some("## Risk")
+            ]
+        );
+        assert!(chunks[2].text.starts_with("### Entry rules"));
+        assert!(chunks[2].text.ends_with("| stop | 5% |"));
+    }
+
     #[test]
     fn test_chunk_overlap() {
         let chunker = create_test_chunker();
@@ -1,4 +1,7 @@
 use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use crate::error::Result;
 
 /// Compute SHA-256 hash of content (content-addressable storage)
 pub fn hash_content(content: &str) -> String {
@@ -7,6 +10,14 @@ pub fn hash_content(content: &str) -> String {
     format!("{:x}", hasher.finalize())
 }
 
+/// Hex SHA-256 of the file at `path`, read in a streaming pass
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
 /// Extract short docid from full hash (first 6 characters)
 /// Example: "abc123def456..." -> "abc123"
 pub fn get_docid(hash: &str) -> String {
@@ -4,21 +4,55 @@
 //! Supports both local ONNX models and provides mean pooling for sentence embeddings.
 
 use crate::error::{QmdError, Result};
+use crate::model_cache::ModelCache;
+use aagt_core::knowledge::rag::Embeddings;
+use async_trait::async_trait;
 use candle_core::{Device, Tensor};
 use candle_nn::VarBuilder;
 use candle_transformers::models::bert::{BertModel, Config};
 use std::path::PathBuf;
+use std::sync::Arc;
 use tokenizers::{PaddingParams, Tokenizer};
 
+/// Where the embedder's model files come from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ModelSource {
+    /// `model_path`, `tokenizer_path` and `config_path` as configured
+    #[default]
+    LocalPath,
+    /// Weights plus `tokenizer.json` and `config.json` from a Hugging Face Hub repo,
+    /// downloaded into the [`ModelCache`] on first use
+    HuggingFace {
+        /// Repository id, e.g. `sentence-transformers/all-MiniLM-L6-v2`
+        repo: String,
+        /// Branch, tag or commit
+        revision: String,
+        /// Weights file, e.g. `model.safetensors`
+        filename: String,
+        /// Expected hex SHA-256 of the weights; unchecked when `None`
+        sha256: Option<String>,
+    },
+}
+
 /// Configuration for the embedder
 #[derive(Debug, Clone)]
 pub struct EmbedderConfig {
     pub model_path: PathBuf,
     pub tokenizer_path: PathBuf,
     pub config_path: PathBuf,
+    /// Where the files above come from. Default: [`ModelSource::LocalPath`]
+    pub model_source: ModelSource,
+    /// Hub cache directory. Default: `HF_HUB_CACHE`, else `$HF_HOME/hub`
+    pub cache_dir: Option<PathBuf>,
+    /// Fail instead of downloading missing Hub files (`HF_HUB_OFFLINE=1` also works)
+    pub offline: bool,
     pub normalize: bool,
     /// Device to use (cpu, cuda, metal, or auto). Default: auto
     pub device: Option<String>,
+    /// Most texts run through the model in one forward pass. Default: 16
+    pub batch_size: usize,
+    /// Most padded tokens (texts × longest text) in one forward pass. Default: 8192
+    pub max_batch_tokens: usize,
 }
 
 impl Default for EmbedderConfig {
@@ -27,13 +61,67 @@ impl Default for EmbedderConfig {
             model_path: PathBuf::from("models/model.safetensors"),
             tokenizer_path: PathBuf::from("models/tokenizer.json"),
             config_path: PathBuf::from("models/config.json"),
+            model_source: ModelSource::LocalPath,
+            cache_dir: None,
+            offline: false,
             normalize: true,
             device: None, // Auto-detect
+            batch_size: 16,
+            max_batch_tokens: 8192,
+        }
+    }
+}
+
+impl EmbedderConfig {
+    /// Model, tokenizer and config paths, downloading Hub files first if needed
+    pub fn resolve_files(&self) -> Result<(PathBuf, PathBuf, PathBuf)> {
+        match &self.model_source {
+            ModelSource::LocalPath => {
+                for path in [&self.model_path, &self.tokenizer_path, &self.config_path] {
+                    if !path.is_file() {
+                        return Err(QmdError::Custom(format!(
+                            "Embedding model file not found: {} (set EmbedderConfig::model_source to download it)",
+                            path.display()
+                        )));
+                    }
+                }
+                Ok((
+                    self.model_path.clone(),
+                    self.tokenizer_path.clone(),
+                    self.config_path.clone(),
+                ))
+            }
+            ModelSource::HuggingFace {
+                repo,
+                revision,
+                filename,
+                sha256,
+            } => {
+                let mut cache = ModelCache::from_env();
+                if let Some(dir) = &self.cache_dir {
+                    cache = cache.with_root(dir);
+                }
+                if self.offline {
+                    cache = cache.offline(true);
+                }
+                Ok((
+                    cache.fetch(repo, revision, filename, sha256.as_deref())?,
+                    cache.fetch(repo, revision, "tokenizer.json", None)?,
+                    cache.fetch(repo, revision, "config.json", None)?,
+                ))
+            }
         }
     }
 }
 
+/// Sentence embedder; clones share one loaded model
+#[derive(Clone)]
 pub struct Embedder {
+    inner: Arc<LoadedModel>,
+}
+
+/// Model, tokenizer and settings behind an [`Embedder`]
+struct LoadedModel {
     model: BertModel,
     tokenizer: Tokenizer,
     config: EmbedderConfig,
@@ -71,14 +159,16 @@ impl Embedder {
                 Some(d) => return Err(QmdError::Custom(format!("Unknown device: {}", d))),
             };
 
-        let config_content = std::fs::read_to_string(&config.config_path)
+        let (model_path, tokenizer_path, config_path) = config.resolve_files()?;
+
+        let config_content = std::fs::read_to_string(&config_path)
             .map_err(|e| QmdError::Custom(format!("Failed to read config file: {}", e)))?;
         let bert_config: Config = serde_json::from_str(&config_content)
             .map_err(|e| QmdError::Custom(format!("Failed to parse config: {}", e)))?;
 
         let vb = unsafe {
             VarBuilder::from_mmaped_safetensors(
-                &[config.model_path.clone()],
+                &[model_path],
                 candle_core::DType::F32,
                 &device,
             )
@@ -88,11 +178,13 @@ impl Embedder {
         let model = BertModel::load(vb, &bert_config)
             .map_err(|e| QmdError::Custom(format!("Failed to load BertModel: {}", e)))?;
 
-        let mut tokenizer = Tokenizer::from_file(&config.tokenizer_path)
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_path)
             .map_err(|e| QmdError::Custom(format!("Failed to load tokenizer: {}", e)))?;
 
+        // Batches are cut down to their own longest text, which needs padding on the right
         if let Some(pp) = tokenizer.get_padding_mut() {
             pp.strategy = tokenizers::PaddingStrategy::BatchLongest;
+            pp.direction = tokenizers::PaddingDirection::Right;
         } else {
             let pp = PaddingParams {
                 strategy: tokenizers::PaddingStrategy::BatchLongest,
@@ -104,11 +196,13 @@ impl Embedder {
         let dimension = bert_config.hidden_size;
 
         Ok(Self {
-            model,
-            tokenizer,
-            config,
-            device,
-            dimension,
+            inner: Arc::new(LoadedModel {
+                model,
+                tokenizer,
+                config,
+                device,
+                dimension,
+            }),
         })
     }
 
@@ -116,22 +210,78 @@ impl Embedder {
         if text.is_empty() {
             return Err(QmdError::Custom("Cannot embed empty text".to_string()));
         }
-        self.embed_batch(&[text])
-            .map(|v| v.into_iter().next().unwrap())
+        self.embed_batch(&[text])?
+            .pop()
+            .ok_or_else(|| QmdError::Custom("Embedder returned no vector".to_string()))
     }
 
+    /// Embed `texts` in as few forward passes as the batch limits allow
+    ///
+    /// Texts are tokenized once, sorted by length so each pass pads little,
+    /// and split by [`EmbedderConfig::batch_size`] and
+    /// [`EmbedderConfig::max_batch_tokens`]. Results keep the input order.
     pub fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        self.inner.embed_batch(texts)
+    }
+
+    /// Run one throwaway inference so the first real query doesn't pay for setup
+    pub fn warmup(&self) -> Result<()> {
+        let started = std::time::Instant::now();
+        self.embed("warmup")?;
+        tracing::debug!("Embedder warmed up in {:?}", started.elapsed());
+        Ok(())
+    }
+
+    pub fn dimension(&self) -> usize {
+        self.inner.dimension
+    }
+
+    /// L2 normalize a vector (helper for tests)
+    #[allow(dead_code)]
+    fn normalize_vector(vec: &[f32]) -> Vec<f32> {
+        let norm: f32 = vec.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm == 0.0 {
+            vec.to_vec()
+        } else {
+            vec.iter().map(|x| x / norm).collect()
+        }
+    }
+}
+
+impl LoadedModel {
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
         let tokens = self
             .tokenizer
             .encode_batch(texts.to_vec(), true)
             .map_err(|e| QmdError::Custom(format!("Tokenization failed: {}", e)))?;
+        let lengths: Vec<usize> = tokens
+            .iter()
+            .map(|t| t.get_attention_mask().iter().filter(|&&m| m == 1).count())
+            .collect();
+
+        let mut embeddings = vec![Vec::new(); texts.len()];
+        for batch in plan_batches(
+            &lengths,
+            self.config.batch_size,
+            self.config.max_batch_tokens,
+        ) {
+            let width = batch.iter().map(|&i| lengths[i]).max().unwrap_or(0);
+            let ids: Vec<&[u32]> = batch.iter().map(|&i| &tokens[i].get_ids()[..width]).collect();
+            for (i, embedding) in batch.into_iter().zip(self.forward(&ids)?) {
+                embeddings[i] = embedding;
+            }
+        }
+        Ok(embeddings)
+    }
 
-        let token_ids = tokens
+    /// One forward pass with mean pooling over equally long token id rows
+    fn forward(&self, ids: &[&[u32]]) -> Result<Vec<Vec<f32>>> {
+        let token_ids = ids
             .iter()
-            .map(|t| {
-                let ids = t.get_ids().to_vec();
-                Tensor::new(ids.as_slice(), &self.device)
-            })
+            .map(|ids| Tensor::new(*ids, &self.device))
             .collect::<std::result::Result<Vec<_>, _>>()
             .map_err(|e| QmdError::Custom(format!("Tensor creation failed: {}", e)))?;
 
@@ -223,20 +373,63 @@ impl Embedder {
             .to_vec2::<f32>()
             .map_err(|e| QmdError::Custom(format!("To vec2 failed: {}", e)))
     }
+}
 
-    pub fn dimension(&self) -> usize {
-        self.dimension
+/// Group text indices into forward passes, shortest texts first
+///
+/// A pass holds at most `max_items` texts and `max_tokens` padded tokens; a
+/// text longer than `max_tokens` gets a pass of its own.
+fn plan_batches(lengths: &[usize], max_items: usize, max_tokens: usize) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..lengths.len()).collect();
+    order.sort_by_key(|&i| lengths[i]);
+
+    let mut batches: Vec<Vec<usize>> = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+    for i in order {
+        // Sorted, so the newest text is the longest and sets the padded width
+        let padded = (current.len() + 1) * lengths[i];
+        if !current.is_empty() && (current.len() >= max_items || padded > max_tokens) {
+            batches.push(std::mem::take(&mut current));
+        }
+        current.push(i);
     }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
 
-    /// L2 normalize a vector (helper for tests)
-    #[allow(dead_code)]
-    fn normalize_vector(vec: &[f32]) -> Vec<f32> {
-        let norm: f32 = vec.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if norm == 0.0 {
-            vec.to_vec()
-        } else {
-            vec.iter().map(|x| x / norm).collect()
-        }
+/// Run inference on the blocking pool so it doesn't stall the async workers
+async fn off_runtime<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> aagt_core::error::Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| aagt_core::error::Error::Internal(format!("Embedding task failed: {}", e)))?
+        .map_err(|e| aagt_core::error::Error::Internal(e.to_string()))
+}
+
+/// Lets the local model back core stores too (e.g. `InMemoryVectorStore`)
+#[async_trait]
+impl Embeddings for Embedder {
+    async fn embed(&self, text: &str) -> aagt_core::error::Result<Vec<f32>> {
+        let embedder = self.clone();
+        let text = text.to_string();
+        off_runtime(move || embedder.embed(&text)).await
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> aagt_core::error::Result<Vec<Vec<f32>>> {
+        let embedder = self.clone();
+        let texts: Vec<String> = texts.iter().map(|t| t.to_string()).collect();
+        off_runtime(move || {
+            let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+            embedder.embed_batch(&texts)
+        })
+        .await
+    }
+
+    fn dimension(&self) -> Option<usize> {
+        Some(self.inner.dimension)
     }
 }
 
@@ -278,6 +471,70 @@ mod tests {
         }
     }
 
+    #[test]
+    #[ignore] // Requires model file
+    fn test_batched_matches_sequential() {
+        let config = EmbedderConfig {
+            batch_size: 16,
+            max_batch_tokens: 256,
+            ..Default::default()
+        };
+        let embedder = Embedder::with_config(config).unwrap();
+        let texts: Vec<String> = (0..100)
+            .map(|i| format!("Chunk {} about SOL. {}", i, "Momentum holds. ".repeat(i % 7)))
+            .collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+
+        let batched = embedder.embed_batch(&texts).unwrap();
+        assert_eq!(batched.len(), texts.len());
+        for (text, embedding) in texts.iter().zip(&batched) {
+            let sequential = embedder.embed(text).unwrap();
+            let max_diff = sequential
+                .iter()
+                .zip(embedding)
+                .map(|(a, b)| (a - b).abs())
+                .fold(0.0f32, f32::max);
+            assert!(max_diff < 1e-4, "{}: differs by {}", text, max_diff);
+        }
+    }
+
+    #[test]
+    fn test_missing_local_model_names_path() {
+        let config = EmbedderConfig {
+            model_path: PathBuf::from("/nonexistent/model.safetensors"),
+            ..Default::default()
+        };
+        let err = config.resolve_files().unwrap_err().to_string();
+        assert!(err.contains("/nonexistent/model.safetensors"), "{}", err);
+
+        let cache = tempfile::tempdir().unwrap();
+        let offline = EmbedderConfig {
+            model_source: ModelSource::HuggingFace {
+                repo: "org/model".to_string(),
+                revision: "main".to_string(),
+                filename: "model.safetensors".to_string(),
+                sha256: None,
+            },
+            cache_dir: Some(cache.path().to_path_buf()),
+            offline: true,
+            ..Default::default()
+        };
+        assert!(matches!(offline.resolve_files(), Err(QmdError::ModelNotCached(_))));
+    }
+
+    #[test]
+    fn test_plan_batches_caps_items_and_tokens() {
+        let lengths = [10, 3, 7, 3, 50, 9];
+        let batches = plan_batches(&lengths, 2, 20);
+        assert_eq!(batches, vec![vec![1, 3], vec![2, 5], vec![0], vec![4]]);
+
+        // Every text is planned exactly once
+        let mut planned: Vec<usize> = batches.concat();
+        planned.sort_unstable();
+        assert_eq!(planned, (0..lengths.len()).collect::<Vec<_>>());
+        assert!(plan_batches(&[], 16, 8192).is_empty());
+    }
+
     #[test]
     fn test_normalize_vector() {
         let vec = vec![3.0, 4.0]; // Length 5
@@ -0,0 +1,178 @@
+//! Sync bridge over the shared async embeddings trait
+//!
+//! QMD indexing and search are synchronous, while embedding providers implement
+//! [`aagt_core::knowledge::rag::Embeddings`] (async). [`BlockingEmbeddings`] drives
+//! such a provider from sync code so QMD and core stores can share one
+//! `Arc<dyn Embeddings>` and therefore one loaded model.
+
+use crate::error::{QmdError, Result};
+use aagt_core::knowledge::rag::Embeddings;
+use std::sync::{Arc, OnceLock};
+use tokio::runtime::{Builder, Handle, Runtime};
+
+/// Blocking adapter around a shared `Arc<dyn Embeddings>`
+pub struct BlockingEmbeddings {
+    inner: Arc<dyn Embeddings>,
+    /// Always `Some` until dropped
+    runtime: Option<Runtime>,
+    dimension: OnceLock<usize>,
+}
+
+impl BlockingEmbeddings {
+    /// Wrap `inner`, creating a private runtime to drive it
+    pub fn new(inner: Arc<dyn Embeddings>) -> Result<Self> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(Self {
+            inner,
+            runtime: Some(runtime),
+            dimension: OnceLock::new(),
+        })
+    }
+
+    /// The shared provider
+    pub fn inner(&self) -> &Arc<dyn Embeddings> {
+        &self.inner
+    }
+
+    /// Embed one text
+    pub fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        if text.is_empty() {
+            return Err(QmdError::Custom("Cannot embed empty text".to_string()));
+        }
+        self.block_on(self.inner.embed(text))
+    }
+
+    /// Embed several texts in one provider call
+    pub fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        self.block_on(self.inner.embed_batch(texts))
+    }
+
+    /// Output dimension, probing the provider once if it doesn't report one
+    pub fn dimension(&self) -> Result<usize> {
+        if let Some(d) = self.dimension.get() {
+            return Ok(*d);
+        }
+        let d = match self.inner.dimension() {
+            Some(d) => d,
+            None => self.embed("dimension probe")?.len(),
+        };
+        Ok(*self.dimension.get_or_init(|| d))
+    }
+
+    /// Run `fut` to completion on the private runtime
+    ///
+    /// Tokio refuses to block inside a runtime worker, so when called from async
+    /// code the future is driven on a scoped helper thread instead.
+    fn block_on<T, F>(&self, fut: F) -> Result<T>
+    where
+        F: std::future::Future<Output = aagt_core::error::Result<T>> + Send,
+        T: Send,
+    {
+        let runtime = self
+            .runtime
+            .as_ref()
+            .ok_or_else(|| QmdError::Custom("Embeddings runtime is shut down".to_string()))?;
+        let output = if Handle::try_current().is_ok() {
+            std::thread::scope(|s| s.spawn(|| runtime.block_on(fut)).join())
+                .map_err(|_| QmdError::Custom("Embedding thread panicked".to_string()))?
+        } else {
+            runtime.block_on(fut)
+        };
+        output.map_err(|e| QmdError::Custom(e.to_string()))
+    }
+}
+
+impl Drop for BlockingEmbeddings {
+    fn drop(&mut self) {
+        // A plain drop blocks on the runtime's threads, which panics inside async code
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aagt_core::knowledge::rag::VectorStore;
+    use aagt_core::knowledge::store::InMemoryVectorStore;
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Deterministic provider that counts calls, standing in for a loaded model
+    struct CountingEmbeddings {
+        calls: AtomicUsize,
+        report_dimension: bool,
+    }
+
+    impl CountingEmbeddings {
+        fn new(report_dimension: bool) -> Self {
+            Self {
+                calls: AtomicUsize::new(0),
+                report_dimension,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Embeddings for CountingEmbeddings {
+        async fn embed(&self, text: &str) -> aagt_core::error::Result<Vec<f32>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let mut v = vec![0.0f32; 4];
+            for (i, b) in text.bytes().enumerate() {
+                v[i % 4] += b as f32;
+            }
+            Ok(v)
+        }
+
+        fn dimension(&self) -> Option<usize> {
+            self.report_dimension.then_some(4)
+        }
+    }
+
+    #[test]
+    fn test_one_provider_serves_core_and_qmd() {
+        let provider = Arc::new(CountingEmbeddings::new(true));
+        let shared: Arc<dyn Embeddings> = provider.clone();
+
+        let blocking = BlockingEmbeddings::new(Arc::clone(&shared)).unwrap();
+        let sync_vec = blocking.embed("buy sol").unwrap();
+        assert_eq!(blocking.dimension().unwrap(), 4);
+
+        let rt = Builder::new_multi_thread().enable_all().build().unwrap();
+        let async_vec = rt.block_on(async {
+            let store = InMemoryVectorStore::with_embedder(Arc::clone(&shared));
+            store.store("buy sol", HashMap::new()).await.unwrap();
+            let hits = store.search("buy sol", 1).await.unwrap();
+            assert_eq!(hits[0].content, "buy sol");
+
+            // The sync bridge also works from inside a runtime
+            let nested = blocking.embed("buy sol").unwrap();
+            assert!(Arc::ptr_eq(store.embedder(), blocking.inner()));
+            nested
+        });
+
+        assert_eq!(sync_vec, async_vec);
+        // store + search + two bridge calls, all on the one provider
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_drop_inside_async_context() {
+        let blocking = BlockingEmbeddings::new(Arc::new(CountingEmbeddings::new(true))).unwrap();
+        assert_eq!(blocking.embed("buy sol").unwrap().len(), 4);
+        drop(blocking);
+    }
+
+    #[test]
+    fn test_dimension_probe_is_cached() {
+        let provider = Arc::new(CountingEmbeddings::new(false));
+        let blocking = BlockingEmbeddings::new(provider.clone()).unwrap();
+
+        assert_eq!(blocking.dimension().unwrap(), 4);
+        assert_eq!(blocking.dimension().unwrap(), 4);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+        assert!(blocking.embed("").is_err());
+    }
+}
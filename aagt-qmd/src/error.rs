@@ -26,9 +26,31 @@ pub enum QmdError {
     #[error("Glob pattern error: {0}")]
     GlobPattern(#[from] glob::PatternError),
 
+    #[error("Store is read-only: {0} is not allowed")]
+    ReadOnly(&'static str),
+
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
     #[error("Content hash mismatch")]
     HashMismatch,
 
+    #[error("Model download failed: {0}")]
+    ModelDownload(String),
+
+    #[error("Checksum mismatch for {file}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Model file not cached and offline mode is on: expected {}", .0.display())]
+    ModelNotCached(std::path::PathBuf),
+
+    #[error("Invalid backup: {0}")]
+    InvalidBackup(String),
+
     #[error("{0}")]
     Custom(String),
 }
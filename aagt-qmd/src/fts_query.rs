@@ -0,0 +1,118 @@
+//! Turning user text into FTS5 MATCH expressions
+//!
+//! FTS5 gives meaning to quotes, parentheses, `-`, `*`, `:` and the
+//! `AND`/`OR`/`NOT`/`NEAR` keywords, so plain user text like `what's "RSI"?`
+//! can fail to parse. [`FtsQuery`] keeps only the searchable tokens and renders
+//! them as quoted strings, which FTS5 always accepts.
+
+/// One whitespace-separated word of the query
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Word {
+    /// Letter/digit runs, matched as a phrase when there is more than one
+    tokens: Vec<String>,
+    /// The word ended in `*`: match its last token as a prefix
+    prefix: bool,
+}
+
+impl Word {
+    fn render(&self) -> String {
+        let mut out = quote(&self.tokens.join(" "));
+        if self.prefix {
+            out.push('*');
+        }
+        out
+    }
+}
+
+/// User text reduced to plain search terms
+///
+/// Tokens are runs of alphanumeric characters (as FTS5's `unicode61` tokenizer
+/// sees them), so `stop-loss` becomes the phrase `"stop loss"`, CJK runs stay
+/// whole and emoji or punctuation disappear.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FtsQuery {
+    words: Vec<Word>,
+}
+
+impl FtsQuery {
+    /// Parse `text`, dropping everything FTS5 would treat as syntax
+    pub fn parse(text: &str) -> Self {
+        let words = text
+            .split_whitespace()
+            .filter_map(|word| {
+                let tokens: Vec<String> = word
+                    .split(|c: char| !c.is_alphanumeric())
+                    .filter(|t| !t.is_empty())
+                    .map(String::from)
+                    .collect();
+                if tokens.is_empty() {
+                    return None;
+                }
+                let prefix = word
+                    .trim_end_matches(|c: char| !c.is_alphanumeric() && c != '*')
+                    .ends_with('*');
+                Some(Word { tokens, prefix })
+            })
+            .collect();
+        Self { words }
+    }
+
+    /// Whether nothing searchable is left
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Number of words
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Every word must match (FTS5's implicit AND), e.g. `"what s" "RSI"`
+    pub fn all(&self) -> String {
+        self.join(" ")
+    }
+
+    /// Any word may match, ranked by BM25, e.g. `"what s" OR "RSI"`
+    pub fn any(&self) -> String {
+        self.join(" OR ")
+    }
+
+    /// All tokens as one phrase, e.g. `"what s RSI"`
+    pub fn phrase(&self) -> String {
+        let tokens: Vec<&str> = self
+            .words
+            .iter()
+            .flat_map(|w| w.tokens.iter().map(String::as_str))
+            .collect();
+        quote(&tokens.join(" "))
+    }
+
+    fn join(&self, separator: &str) -> String {
+        self.words
+            .iter()
+            .map(Word::render)
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+}
+
+/// An FTS5 string literal
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syntax_is_neutralized() {
+        let query = FtsQuery::parse(r#"what's "RSI"? NOT (stop-loss) sol*"#);
+        assert_eq!(query.all(), r#""what s" "RSI" "NOT" "stop loss" "sol"*"#);
+        assert_eq!(query.any(), r#""what s" OR "RSI" OR "NOT" OR "stop loss" OR "sol"*"#);
+        assert_eq!(query.phrase(), r#""what s RSI NOT stop loss sol""#);
+
+        assert!(FtsQuery::parse(r#"🚀 "" -- * ()"#).is_empty());
+        assert_eq!(FtsQuery::parse("如何在熊市赚钱？").all(), r#""如何在熊市赚钱""#);
+    }
+}
@@ -2,39 +2,85 @@
 //!
 //! Integrates keyword-based (BM25/FTS5) and semantic (vector) search using RRF fusion.
 
-#[cfg(feature = "vector")]
-use crate::chunker::{Chunker, ChunkerConfig};
-#[cfg(feature = "vector")]
-use crate::embedder::{Embedder, EmbedderConfig};
+#[cfg(feature = "vector-index")]
+use crate::chunker::Chunker;
+#[cfg(feature = "vector-index")]
+use crate::embeddings::BlockingEmbeddings;
+use crate::error::QmdError;
 use crate::error::Result;
+use crate::index_plan::EmbeddingThroughput;
 use crate::rrf::RrfFusion;
 use crate::store::{Collection, Document, QmdStore};
-#[cfg(feature = "vector")]
-use crate::vector_store::{VectorSearchResult, VectorStore};
-use std::path::PathBuf;
+#[cfg(feature = "vector-index")]
+use crate::vector_store::{TierStats, VectorSearchResult, VectorStore, VectorStoreConfig};
+use aagt_core::infra::validation::{ConfigIssue, Validate};
+#[cfg(feature = "vector-index")]
+use aagt_core::knowledge::rag::Embeddings;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "vector-index")]
+use std::sync::Arc;
 
 /// Configuration for hybrid search
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct HybridSearchConfig {
     /// Database path for QMD store
     pub db_path: PathBuf,
     /// Number of BM25 results to retrieve for fusion
     pub bm25_candidates: usize,
     /// Number of vector results to retrieve for fusion
-    #[cfg(feature = "vector")]
+    #[cfg(feature = "vector-index")]
     pub vector_candidates: usize,
-    /// Embedder configuration
-    #[cfg(feature = "vector")]
+    /// Shared embeddings provider; takes precedence over the local embedder
+    #[cfg(feature = "vector-index")]
+    pub embeddings: Option<Arc<dyn Embeddings>>,
+    /// Local embedder configuration, used when no shared provider is set
+    #[cfg(feature = "embeddings")]
     pub embedder_config: crate::embedder::EmbedderConfig,
     /// Chunker configuration
-    #[cfg(feature = "vector")]
+    #[cfg(feature = "vector-index")]
     pub chunker_config: crate::chunker::ChunkerConfig,
     /// Vector store persistence path
-    #[cfg(feature = "vector")]
+    #[cfg(feature = "vector-index")]
     pub vector_store_path: Option<PathBuf>,
     /// Max elements for HNSW index
-    #[cfg(feature = "vector")]
+    #[cfg(feature = "vector-index")]
     pub hnsw_max_elements: usize,
+    /// Vector store options, e.g. cold-data tiering
+    #[cfg(feature = "vector-index")]
+    pub vector_store_config: VectorStoreConfig,
+    /// Embedding throughput and price, used to estimate index plans
+    pub embedding_throughput: EmbeddingThroughput,
+}
+
+impl std::fmt::Debug for HybridSearchConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut s = f.debug_struct("HybridSearchConfig");
+        s.field("db_path", &self.db_path)
+            .field("bm25_candidates", &self.bm25_candidates)
+            .field("embedding_throughput", &self.embedding_throughput);
+        #[cfg(feature = "vector-index")]
+        s.field("vector_candidates", &self.vector_candidates)
+            .field("embeddings", &self.embeddings.as_ref().map(|_| "shared"))
+            .field("chunker_config", &self.chunker_config)
+            .field("vector_store_path", &self.vector_store_path)
+            .field("hnsw_max_elements", &self.hnsw_max_elements)
+            .field("vector_store_config", &self.vector_store_config);
+        #[cfg(feature = "embeddings")]
+        s.field("embedder_config", &self.embedder_config);
+        s.finish()
+    }
+}
+
+impl HybridSearchConfig {
+    /// Embed with a shared provider instead of loading a local model
+    ///
+    /// Pass the same `Arc` given to core stores so both use one model.
+    #[cfg(feature = "vector-index")]
+    pub fn with_embeddings(mut self, embeddings: Arc<dyn Embeddings>) -> Self {
+        self.embeddings = Some(embeddings);
+        self
+    }
 }
 
 impl Default for HybridSearchConfig {
@@ -42,17 +88,101 @@ impl Default for HybridSearchConfig {
         Self {
             db_path: PathBuf::from("qmd.db"),
             bm25_candidates: 50,
-            #[cfg(feature = "vector")]
+            #[cfg(feature = "vector-index")]
             vector_candidates: 50,
-            #[cfg(feature = "vector")]
+            #[cfg(feature = "vector-index")]
+            embeddings: None,
+            #[cfg(feature = "embeddings")]
             embedder_config: crate::embedder::EmbedderConfig::default(),
-            #[cfg(feature = "vector")]
+            #[cfg(feature = "vector-index")]
             chunker_config: crate::chunker::ChunkerConfig::default(),
-            #[cfg(feature = "vector")]
+            #[cfg(feature = "vector-index")]
             vector_store_path: None,
-            #[cfg(feature = "vector")]
+            #[cfg(feature = "vector-index")]
             hnsw_max_elements: 100_000,
+            #[cfg(feature = "vector-index")]
+            vector_store_config: VectorStoreConfig::default(),
+            embedding_throughput: EmbeddingThroughput::default(),
+        }
+    }
+}
+
+/// Error if `path` cannot be created or written: its parent must be a writable directory
+fn check_writable(field: &str, path: &Path, issues: &mut Vec<ConfigIssue>) {
+    if path.is_dir() {
+        issues.push(ConfigIssue::error(field, format!("{} is a directory", path.display())));
+        return;
+    }
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    match std::fs::metadata(parent) {
+        Ok(meta) if !meta.is_dir() => issues.push(ConfigIssue::error(
+            field,
+            format!("{} is not a directory", parent.display()),
+        )),
+        Ok(meta) if meta.permissions().readonly() => issues.push(ConfigIssue::error(
+            field,
+            format!("{} is not writable", parent.display()),
+        )),
+        Ok(_) => {}
+        Err(_) => issues.push(
+            ConfigIssue::error(field, format!("{} does not exist", parent.display()))
+                .suggest("create the directory before opening the store"),
+        ),
+    }
+}
+
+impl Validate for HybridSearchConfig {
+    fn validate(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        check_writable("search.db_path", &self.db_path, &mut issues);
+        if self.bm25_candidates == 0 {
+            issues.push(ConfigIssue::error("search.bm25_candidates", "must be at least 1"));
+        } else if self.bm25_candidates < 10 {
+            issues.push(ConfigIssue::warning(
+                "search.bm25_candidates",
+                format!(
+                    "searches asking for more than {} results are silently truncated",
+                    self.bm25_candidates
+                ),
+            ));
+        }
+        #[cfg(feature = "vector-index")]
+        {
+            if self.vector_candidates == 0 {
+                issues.push(ConfigIssue::error("search.vector_candidates", "must be at least 1"));
+            }
+            if self.hnsw_max_elements == 0 {
+                issues.push(ConfigIssue::error("search.hnsw_max_elements", "must be at least 1"));
+            }
+            if let Some(path) = &self.vector_store_path {
+                check_writable("search.vector_store_path", path, &mut issues);
+            }
+            if let Some(tiering) = &self.vector_store_config.tiering {
+                if tiering.hot_max_elements == 0 {
+                    issues.push(ConfigIssue::error(
+                        "search.vector_store_config.tiering.hot_max_elements",
+                        "must be at least 1",
+                    ));
+                }
+            }
+        }
+        let tps = self.embedding_throughput.tokens_per_second;
+        if tps.is_nan() || tps <= 0.0 {
+            issues.push(ConfigIssue::error(
+                "search.embedding_throughput.tokens_per_second",
+                "must be positive",
+            ));
         }
+        if self.embedding_throughput.usd_per_million_tokens < 0.0 {
+            issues.push(ConfigIssue::error(
+                "search.embedding_throughput.usd_per_million_tokens",
+                "cannot be negative",
+            ));
+        }
+        issues
     }
 }
 
@@ -65,22 +195,35 @@ pub struct HybridSearchResult {
     pub document: Document,
     /// Combined RRF score
     pub rrf_score: f64,
+    /// RRF score after the collection weight; equals `rrf_score` outside
+    /// [`HybridSearchEngine::search_multi`]
+    pub weighted_score: f64,
     /// BM25 score (if found via BM25)
     pub bm25_score: Option<f64>,
     /// Vector similarity score (if found via vector search)
     pub vector_score: Option<f64>,
     /// Snippet (if available from BM25)
     pub snippet: Option<String>,
+    /// Headings above the best-matching chunk (vector hits on markdown-chunked documents)
+    pub heading_path: Option<String>,
+}
+
+/// A validated, chunked document ready to be stored
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PreparedDocument {
+    /// Chunks to embed, in order
+    #[cfg(feature = "vector-index")]
+    pub(crate) chunks: Vec<crate::chunker::Chunk>,
 }
 
 /// Hybrid search engine
 pub struct HybridSearchEngine {
     qmd_store: QmdStore,
-    #[cfg(feature = "vector")]
+    #[cfg(feature = "vector-index")]
     vector_store: VectorStore,
-    #[cfg(feature = "vector")]
-    embedder: Embedder,
-    #[cfg(feature = "vector")]
+    #[cfg(feature = "vector-index")]
+    embedder: BlockingEmbeddings,
+    #[cfg(feature = "vector-index")]
     chunker: Chunker,
     rrf_fusion: RrfFusion,
     config: HybridSearchConfig,
@@ -93,38 +236,87 @@ impl HybridSearchEngine {
         let rrf_fusion = RrfFusion::new();
 
         // Create or load vector store
-        #[cfg(feature = "vector")]
+        #[cfg(feature = "vector-index")]
         let (vector_store, embedder, chunker) = {
-            let embedder = Embedder::with_config(config.embedder_config.clone())?;
+            let embedder = BlockingEmbeddings::new(Self::resolve_embeddings(&config)?)?;
             let chunker = Chunker::with_config(config.chunker_config.clone())?;
+            let dimension = embedder.dimension()?;
 
             let vector_store = if let Some(ref path) = config.vector_store_path {
                 if path.exists() {
                     tracing::info!("Loading existing vector store from {:?}", path);
-                    VectorStore::load(path)?
+                    let store = VectorStore::load_with_config(path, config.vector_store_config.clone())?;
+                    if store.dimension() != dimension {
+                        return Err(QmdError::Custom(format!(
+                            "Vector store {:?} has dimension {}, embedder produces {}",
+                            path,
+                            store.dimension(),
+                            dimension
+                        )));
+                    }
+                    store
                 } else {
                     tracing::info!("Creating new vector store");
-                    VectorStore::new(embedder.dimension(), config.hnsw_max_elements)
+                    VectorStore::with_config(
+                        dimension,
+                        config.hnsw_max_elements,
+                        config.vector_store_config.clone(),
+                    )
                 }
             } else {
-                VectorStore::new(embedder.dimension(), config.hnsw_max_elements)
+                VectorStore::with_config(
+                    dimension,
+                    config.hnsw_max_elements,
+                    config.vector_store_config.clone(),
+                )
             };
             (vector_store, embedder, chunker)
         };
 
         Ok(Self {
             qmd_store,
-            #[cfg(feature = "vector")]
+            #[cfg(feature = "vector-index")]
             vector_store,
-            #[cfg(feature = "vector")]
+            #[cfg(feature = "vector-index")]
             embedder,
-            #[cfg(feature = "vector")]
+            #[cfg(feature = "vector-index")]
             chunker,
             rrf_fusion,
             config,
         })
     }
 
+    /// Pick the shared provider if configured, else load the local model
+    #[cfg(feature = "vector-index")]
+    fn resolve_embeddings(config: &HybridSearchConfig) -> Result<Arc<dyn Embeddings>> {
+        if let Some(embeddings) = &config.embeddings {
+            return Ok(Arc::clone(embeddings));
+        }
+        #[cfg(feature = "embeddings")]
+        {
+            Ok(Arc::new(crate::embedder::Embedder::with_config(
+                config.embedder_config.clone(),
+            )?))
+        }
+        #[cfg(not(feature = "embeddings"))]
+        {
+            Err(QmdError::Custom(
+                "No embeddings provider: set HybridSearchConfig::with_embeddings or enable the `embeddings` feature".to_string(),
+            ))
+        }
+    }
+
+    /// The underlying document store
+    pub(crate) fn qmd_store(&self) -> &QmdStore {
+        &self.qmd_store
+    }
+
+    /// The in-memory vector index
+    #[cfg(feature = "vector-index")]
+    pub(crate) fn vector_store(&self) -> &VectorStore {
+        &self.vector_store
+    }
+
     /// Create collection
     pub fn create_collection(&self, collection: Collection) -> Result<()> {
         self.qmd_store.create_collection(collection)
@@ -135,7 +327,7 @@ impl HybridSearchEngine {
     /// Saves the vector store to disk if there are unsaved changes.
     /// The SQLite store is auto-committed, but vector store requires    /// Commit changes to persistent storage
     pub fn commit(&self) -> Result<()> {
-        #[cfg(feature = "vector")]
+        #[cfg(feature = "vector-index")]
         if let Some(ref path) = self.config.vector_store_path {
             if self.vector_store.is_dirty() {
                 tracing::info!("Saving vector store to {:?}", path);
@@ -155,6 +347,26 @@ impl HybridSearchEngine {
         self.qmd_store.get_by_path(collection, path)
     }
 
+    /// Page through the active documents of `collection`, newest first
+    ///
+    /// See [`QmdStore::list_documents`].
+    pub fn list_documents(
+        &self,
+        collection: &str,
+        path_prefix: Option<&str>,
+        offset: usize,
+        limit: usize,
+        include_body: bool,
+    ) -> Result<Vec<Document>> {
+        self.qmd_store
+            .list_documents(collection, path_prefix, offset, limit, include_body)
+    }
+
+    /// Number of active documents in `collection`, optionally under `path_prefix`
+    pub fn count_documents(&self, collection: &str, path_prefix: Option<&str>) -> Result<usize> {
+        self.qmd_store.count_documents(collection, path_prefix)
+    }
+
     /// Index a document (stores in both BM25 and vector stores)
     ///
     /// # Examples
@@ -180,35 +392,11 @@ impl HybridSearchEngine {
     ) -> Result<()> {
         tracing::debug!("Indexing document: {}/{}", collection, path);
 
-        // 1. Store in QMD (BM25/FTS5)
-        let doc = self
-            .qmd_store
-            .store_document(collection, path, title, content)?;
-
-        tracing::debug!("Stored in QMD with docid: {}", doc.docid);
-
-        // 2. Chunk the document
-        #[cfg(feature = "vector")]
-        let chunks = self.chunker.chunk(content)?;
-        #[cfg(feature = "vector")]
-        let num_chunks = chunks.len();
-        #[cfg(feature = "vector")]
-        tracing::debug!("Created {} chunks", num_chunks);
-
-        // 3. Generate embeddings for each chunk
-        #[cfg(feature = "vector")]
-        for chunk in &chunks {
-            let embedding = self.embedder.embed(&chunk.text)?;
+        let prepared = self.prepare(content)?;
+        self.store_prepared(&[(collection, path, title, content, &prepared)])?;
 
-            self.vector_store
-                .add(collection, doc.docid.clone(), chunk.seq, embedding)?;
-        }
-
-        #[cfg(feature = "vector")]
-        tracing::debug!("Indexed {} chunks for document {}", num_chunks, doc.docid);
-
-        // 4. Persistence: Save vector store immediately to match SQLite durability
-        #[cfg(feature = "vector")]
+        // Persistence: Save vector store immediately to match SQLite durability
+        #[cfg(feature = "vector-index")]
         if let Some(ref path) = self.config.vector_store_path {
             self.vector_store.save_force(path)?;
         }
@@ -218,7 +406,8 @@ impl HybridSearchEngine {
 
     /// Index multiple documents in batch (More efficient than loop)
     ///
-    /// Saves the vector store only once at the end.
+    /// Chunks of all documents are embedded together and the vector store is
+    /// saved only once at the end.
     pub fn index_batch(
         &self,
         documents: Vec<(&str, &str, &str, &str)>, // (collection, path, title, content)
@@ -226,38 +415,183 @@ impl HybridSearchEngine {
         let total = documents.len();
         tracing::info!("Batch indexing {} documents", total);
 
-        for (i, (collection, path, title, content)) in documents.into_iter().enumerate() {
-            tracing::debug!("[{}/{}] Indexing {}/{}", i + 1, total, collection, path);
+        let mut prepared = Vec::with_capacity(total);
+        for (i, (collection, path, _, content)) in documents.iter().enumerate() {
+            tracing::debug!("[{}/{}] Preparing {}/{}", i + 1, total, collection, path);
+            prepared.push(self.prepare(content)?);
+        }
+        let entries: Vec<_> = documents
+            .iter()
+            .zip(&prepared)
+            .map(|(&(collection, path, title, content), prepared)| {
+                (collection, path, title, content, prepared)
+            })
+            .collect();
+        self.store_prepared(&entries)?;
+
+        // Save ONCE at the end
+        self.save_after_batch()
+    }
+
+    /// Validate and chunk a document: everything indexing does before writing
+    ///
+    /// Shared by the real indexing path and [`HybridSearchEngine::plan_index`] so
+    /// a plan always matches what indexing would do.
+    pub(crate) fn prepare(&self, content: &str) -> Result<PreparedDocument> {
+        QmdStore::validate_body(content)?;
+        if content.trim().is_empty() {
+            return Err(crate::error::QmdError::Custom(
+                "Document is empty".to_string(),
+            ));
+        }
 
-            // 1. Store in QMD (BM25)
-            let _doc = self
+        #[cfg(feature = "vector-index")]
+        {
+            let chunks = self.chunker.chunk(content)?;
+            tracing::debug!("Created {} chunks", chunks.len());
+            Ok(PreparedDocument { chunks })
+        }
+        #[cfg(not(feature = "vector-index"))]
+        {
+            Ok(PreparedDocument {})
+        }
+    }
+
+    /// Store prepared documents in both BM25 and vector stores (no vector store save)
+    ///
+    /// Takes `(collection, path, title, content, prepared)` entries. Chunk
+    /// vectors from earlier revisions of these paths are removed, so indexing a
+    /// path again replaces its vectors instead of adding to them. The new chunks
+    /// go to the embedder in a single batch call, which splits it into forward
+    /// passes as its own limits allow.
+    pub(crate) fn store_prepared(
+        &self,
+        documents: &[(&str, &str, &str, &str, &PreparedDocument)],
+    ) -> Result<()> {
+        // 1. Store in QMD (BM25/FTS5), remembering the revisions being replaced
+        let mut stored = Vec::with_capacity(documents.len());
+        let mut replaced = Vec::new();
+        for &(collection, path, title, content, prepared) in documents {
+            let previous = self.qmd_store.get_by_path(collection, path)?;
+            let doc = self
                 .qmd_store
                 .store_document(collection, path, title, content)?;
+            tracing::debug!("Stored in QMD with docid: {}", doc.docid);
+            if let Some(previous) = previous.filter(|p| p.hash != doc.hash) {
+                replaced.push((collection, previous));
+            }
+            stored.push((collection, doc.docid, prepared));
+        }
 
-            // 2. Chunk (Only if vector is enabled)
-            #[cfg(feature = "vector")]
-            {
-                let chunks = self.chunker.chunk(content)?;
-
-                // 3. Embed and Add to Vector Store
-                for chunk in chunks {
-                    let embedding = self.embedder.embed(&chunk.text)?;
-                    self.vector_store
-                        .add(collection, doc.docid.clone(), chunk.seq, embedding)?;
+        // 2. Replace the chunk vectors of every stored document in one embedding batch
+        #[cfg(feature = "vector-index")]
+        {
+            // Old content no other active document shares, and this content, which is embedded again
+            let mut stale: Vec<(&str, &str)> = Vec::new();
+            for (collection, previous) in &replaced {
+                if !self.qmd_store.has_active_content(collection, &previous.hash)? {
+                    stale.push((collection, &previous.docid));
+                }
+            }
+            let mut seen = std::collections::HashSet::new();
+            stored.retain(|(collection, docid, _)| seen.insert((*collection, docid.clone())));
+            stale.extend(stored.iter().map(|(collection, docid, _)| (*collection, docid.as_str())));
+            self.vector_store.remove_documents(stale)?;
+
+            let texts: Vec<&str> = stored
+                .iter()
+                .flat_map(|(_, _, prepared)| prepared.chunks.iter().map(|c| c.text.as_str()))
+                .collect();
+            let embeddings = self.embedder.embed_batch(&texts)?;
+            if embeddings.len() != texts.len() {
+                return Err(crate::error::QmdError::Custom(format!(
+                    "Embedder returned {} vectors for {} chunks",
+                    embeddings.len(),
+                    texts.len()
+                )));
+            }
+            let mut embeddings = embeddings.into_iter();
+            for (collection, docid, prepared) in &stored {
+                for (chunk, embedding) in prepared.chunks.iter().zip(embeddings.by_ref()) {
+                    self.vector_store.add_with_heading(
+                        *collection,
+                        docid.clone(),
+                        chunk.seq,
+                        chunk.heading_path.clone(),
+                        embedding,
+                    )?;
                 }
             }
+            tracing::debug!(
+                "Indexed {} chunks for {} documents",
+                texts.len(),
+                stored.len()
+            );
         }
+        #[cfg(not(feature = "vector-index"))]
+        let _ = (stored, replaced);
+
+        Ok(())
+    }
 
-        // 4. Save ONCE at the end
-        #[cfg(feature = "vector")]
+    /// Remove a document from search: its FTS entry and its chunk vectors
+    ///
+    /// Returns false when there is no active document at `path`. Vectors are
+    /// tombstoned and dropped when the vector store compacts. Past revisions
+    /// stay available to as-of search; [`QmdStore::vacuum_content`] only frees
+    /// content that no revision references.
+    pub fn delete_document(&self, collection: &str, path: &str) -> Result<bool> {
+        let Some(doc) = self.qmd_store.get_by_path(collection, path)? else {
+            return Ok(false);
+        };
+        if !self.qmd_store.delete_document(collection, path)? {
+            return Ok(false);
+        }
+        tracing::debug!("Deleted document: {}/{}", collection, path);
+
+        // Identical content at another path keeps the shared vectors
+        #[cfg(feature = "vector-index")]
+        if !self.qmd_store.has_active_content(collection, &doc.hash)? {
+            self.vector_store
+                .remove_documents([(collection, doc.docid.as_str())])?;
+            if let Some(ref path) = self.config.vector_store_path {
+                self.vector_store.save_force(path)?;
+            }
+        }
+        #[cfg(not(feature = "vector-index"))]
+        let _ = doc;
+
+        Ok(true)
+    }
+
+    /// Chunk and embed a stored document again, replacing its chunk vectors
+    ///
+    /// For when the chunker or embedder changed. Returns false when there is
+    /// no active document at `path`.
+    pub fn reindex_document(&self, collection: &str, path: &str) -> Result<bool> {
+        let Some(doc) = self.qmd_store.get_by_path(collection, path)? else {
+            return Ok(false);
+        };
+        let body = doc.body.as_deref().unwrap_or_default();
+        self.index_document(collection, path, &doc.title, body)?;
+        Ok(true)
+    }
+
+    /// Save the vector store once after a batch of writes
+    pub(crate) fn save_after_batch(&self) -> Result<()> {
+        #[cfg(feature = "vector-index")]
         if let Some(ref path) = self.config.vector_store_path {
             tracing::info!("Saving vector store after batch index...");
             self.vector_store.save_force(path)?;
         }
-
         Ok(())
     }
 
+    /// Configuration the engine was created with
+    pub fn config(&self) -> &HybridSearchConfig {
+        &self.config
+    }
+
     /// Hybrid search combining BM25 and vector search
     ///
     /// # Arguments
@@ -269,33 +603,66 @@ impl HybridSearchEngine {
     ///
     /// Results ordered by relevance (RRF fusion of BM25 and vector scores)
     pub fn search(&self, query: &str, limit: usize) -> Result<Vec<HybridSearchResult>> {
-        tracing::debug!("Hybrid search: '{}' (limit: {})", query, limit);
+        self.search_at(query, limit, None)
+    }
 
-        // 1. BM25 search
-        let bm25_results = self
-            .qmd_store
-            .search_fts(query, self.config.bm25_candidates)?;
+    /// Hybrid search over the documents as they were at `as_of` (unix seconds)
+    ///
+    /// BM25 runs over revision history. Vectors of superseded revisions stay
+    /// in the index, so vector hits are kept only if their docid was the
+    /// current revision at `as_of`.
+    pub fn search_as_of(
+        &self,
+        query: &str,
+        limit: usize,
+        as_of: i64,
+    ) -> Result<Vec<HybridSearchResult>> {
+        self.search_at(query, limit, Some(as_of))
+    }
+
+    fn search_at(
+        &self,
+        query: &str,
+        limit: usize,
+        as_of: Option<i64>,
+    ) -> Result<Vec<HybridSearchResult>> {
+        tracing::debug!(
+            "Hybrid search: '{}' (limit: {}, as_of: {:?})",
+            query,
+            limit,
+            as_of
+        );
+
+        // 1. BM25 search (plain-text query)
+        let bm25_results = match as_of {
+            Some(as_of) => {
+                self.qmd_store
+                    .search_fts_as_of(query, self.config.bm25_candidates, as_of)?
+            }
+            None => self
+                .qmd_store
+                .search_fts(query, self.config.bm25_candidates)?,
+        };
 
         tracing::debug!("BM25 found {} results", bm25_results.len());
 
         // 2. Vector search (Optional - Only if configured via feature flag)
-        let vector_results: Vec<(String, f64)> = {
-            #[cfg(feature = "vector")]
+        let (vector_results, headings) = {
+            #[cfg(feature = "vector-index")]
             {
                 if self.vector_store.len() > 0 {
                     let query_embedding = self.embedder.embed(query)?;
-                    self.vector_store
-                        .search(&query_embedding, self.config.vector_candidates)?
-                        .into_iter()
-                        .map(|r| (r.docid, r.score))
-                        .collect()
+                    split_vector_hits(
+                        self.vector_store
+                            .search(&query_embedding, self.config.vector_candidates)?,
+                    )
                 } else {
-                    Vec::new()
+                    (Vec::new(), HashMap::new())
                 }
             }
-            #[cfg(not(feature = "vector"))]
+            #[cfg(not(feature = "vector-index"))]
             {
-                Vec::new()
+                (Vec::<(String, f64)>::new(), HashMap::<String, String>::new())
             }
         };
 
@@ -309,7 +676,7 @@ impl HybridSearchEngine {
 
         // 4. RRF fusion
         // Get more candidates for deduplication if vector search is enabled
-        let fusion_limit = if cfg!(feature = "vector") {
+        let fusion_limit = if cfg!(feature = "vector-index") {
             limit * 2
         } else {
             limit
@@ -321,7 +688,13 @@ impl HybridSearchEngine {
         // 5. Build initial results
         let mut candidates = Vec::new();
         for fused_result in fused.iter().take(fusion_limit) {
-            if let Some(doc) = self.qmd_store.get_by_docid(&fused_result.docid)? {
+            let doc = match as_of {
+                Some(as_of) => self
+                    .qmd_store
+                    .get_by_docid_as_of(&fused_result.docid, as_of)?,
+                None => self.qmd_store.get_by_docid(&fused_result.docid)?,
+            };
+            if let Some(doc) = doc {
                 let snippet = bm25_results
                     .iter()
                     .find(|r| r.document.docid == fused_result.docid)
@@ -331,17 +704,19 @@ impl HybridSearchEngine {
                     rank: 0, // Placeholder
                     document: doc,
                     rrf_score: fused_result.rrf_score,
+                    weighted_score: fused_result.rrf_score,
                     bm25_score: fused_result.bm25_score,
                     vector_score: fused_result.vector_score,
                     snippet,
+                    heading_path: headings.get(&fused_result.docid).cloned(),
                 });
             }
         }
 
         // 6. Semantic Deduplication
-        #[cfg(feature = "vector")]
+        #[cfg(feature = "vector-index")]
         let mut final_results = self.apply_semantic_deduplication(candidates, 0.85, limit)?;
-        #[cfg(not(feature = "vector"))]
+        #[cfg(not(feature = "vector-index"))]
         let mut final_results = candidates.into_iter().take(limit).collect::<Vec<_>>();
 
         // 7. Final ranking assignment
@@ -376,27 +751,23 @@ impl HybridSearchEngine {
         tracing::debug!("BM25 found {} results in collection", bm25_results.len());
 
         // 2. Vector search (Optional)
-        let vector_results: Vec<(String, f64)> = {
-            #[cfg(feature = "vector")]
+        let (vector_results, headings) = {
+            #[cfg(feature = "vector-index")]
             {
                 if self.vector_store.len() > 0 {
                     let query_embedding = self.embedder.embed(query)?;
-                    self.vector_store
-                        .search_in_collection(
-                            &query_embedding,
-                            Some(collection),
-                            self.config.vector_candidates,
-                        )?
-                        .into_iter()
-                        .map(|r| (r.docid, r.score))
-                        .collect()
+                    split_vector_hits(self.vector_store.search_in_collection(
+                        &query_embedding,
+                        Some(collection),
+                        self.config.vector_candidates,
+                    )?)
                 } else {
-                    Vec::new()
+                    (Vec::new(), HashMap::new())
                 }
             }
-            #[cfg(not(feature = "vector"))]
+            #[cfg(not(feature = "vector-index"))]
             {
-                Vec::new()
+                (Vec::<(String, f64)>::new(), HashMap::<String, String>::new())
             }
         };
 
@@ -412,7 +783,7 @@ impl HybridSearchEngine {
             .collect();
 
         // 4. RRF fusion
-        let fusion_limit = if cfg!(feature = "vector") {
+        let fusion_limit = if cfg!(feature = "vector-index") {
             limit * 2
         } else {
             limit
@@ -433,17 +804,19 @@ impl HybridSearchEngine {
                     rank: 0, // Placeholder
                     document: doc,
                     rrf_score: fused_result.rrf_score,
+                    weighted_score: fused_result.rrf_score,
                     bm25_score: fused_result.bm25_score,
                     vector_score: fused_result.vector_score,
                     snippet,
+                    heading_path: headings.get(&fused_result.docid).cloned(),
                 });
             }
         }
 
         // 5. Semantic Deduplication
-        #[cfg(feature = "vector")]
+        #[cfg(feature = "vector-index")]
         let mut final_results = self.apply_semantic_deduplication(candidates, 0.85, limit)?;
-        #[cfg(not(feature = "vector"))]
+        #[cfg(not(feature = "vector-index"))]
         let mut final_results = candidates.into_iter().take(limit).collect::<Vec<_>>();
 
         // 6. Final ranking assignment
@@ -454,8 +827,121 @@ impl HybridSearchEngine {
         Ok(final_results)
     }
 
+    /// Search several collections at once, scaling each one's RRF scores by its weight
+    ///
+    /// BM25 and vector search run per collection; the weighted hits are then
+    /// merged and ranked by `weighted_score`. Semantic deduplication runs over
+    /// the merged set, so near-duplicates across collections collapse too.
+    pub fn search_multi(
+        &self,
+        query: &str,
+        collections: &[(&str, f64)],
+        limit: usize,
+    ) -> Result<Vec<HybridSearchResult>> {
+        tracing::debug!(
+            "Hybrid search across {:?}: '{}' (limit: {})",
+            collections,
+            query,
+            limit
+        );
+        if let Some((name, weight)) = collections
+            .iter()
+            .find(|(_, weight)| !weight.is_finite() || *weight < 0.0)
+        {
+            return Err(QmdError::Custom(format!(
+                "Invalid weight {} for collection '{}': must be finite and non-negative",
+                weight, name
+            )));
+        }
+
+        #[cfg(feature = "vector-index")]
+        let query_embedding = if !self.vector_store.is_empty() {
+            Some(self.embedder.embed(query)?)
+        } else {
+            None
+        };
+
+        let fusion_limit = if cfg!(feature = "vector-index") {
+            limit * 2
+        } else {
+            limit
+        };
+
+        let mut candidates = Vec::new();
+        for &(collection, weight) in collections {
+            let bm25_results = self.qmd_store.search_fts_in_collection(
+                query,
+                collection,
+                self.config.bm25_candidates,
+            )?;
+
+            let (vector_results, headings) = {
+                #[cfg(feature = "vector-index")]
+                {
+                    match &query_embedding {
+                        Some(embedding) => {
+                            split_vector_hits(self.vector_store.search_in_collection(
+                                embedding,
+                                Some(collection),
+                                self.config.vector_candidates,
+                            )?)
+                        }
+                        None => (Vec::new(), HashMap::new()),
+                    }
+                }
+                #[cfg(not(feature = "vector-index"))]
+                {
+                    (Vec::<(String, f64)>::new(), HashMap::<String, String>::new())
+                }
+            };
+
+            let bm25_for_rrf: Vec<(String, f64)> = bm25_results
+                .iter()
+                .map(|r| (r.document.docid.clone(), r.score))
+                .collect();
+            let fused = self.rrf_fusion.fuse(&bm25_for_rrf, &vector_results);
+
+            for fused_result in fused.into_iter().take(fusion_limit) {
+                // Docids are content hashes, so prefer the BM25 hit's document,
+                // which is known to live in this collection
+                let bm25_hit = bm25_results
+                    .iter()
+                    .find(|r| r.document.docid == fused_result.docid);
+                let doc = match bm25_hit {
+                    Some(hit) => Some(hit.document.clone()),
+                    None => self.qmd_store.get_by_docid(&fused_result.docid)?,
+                };
+                if let Some(doc) = doc {
+                    candidates.push(HybridSearchResult {
+                        rank: 0, // Placeholder
+                        document: doc,
+                        rrf_score: fused_result.rrf_score,
+                        weighted_score: fused_result.rrf_score * weight,
+                        bm25_score: fused_result.bm25_score,
+                        vector_score: fused_result.vector_score,
+                        snippet: bm25_hit.and_then(|hit| hit.snippet.clone()),
+                        heading_path: headings.get(&fused_result.docid).cloned(),
+                    });
+                }
+            }
+        }
+
+        candidates.sort_by(|a, b| b.weighted_score.total_cmp(&a.weighted_score));
+
+        #[cfg(feature = "vector-index")]
+        let mut final_results = self.apply_semantic_deduplication(candidates, 0.85, limit)?;
+        #[cfg(not(feature = "vector-index"))]
+        let mut final_results = candidates.into_iter().take(limit).collect::<Vec<_>>();
+
+        for (i, res) in final_results.iter_mut().enumerate() {
+            res.rank = i + 1;
+        }
+
+        Ok(final_results)
+    }
+
     /// Apply semantic deduplication to search results
-    #[cfg(feature = "vector")]
+    #[cfg(feature = "vector-index")]
     fn apply_semantic_deduplication(
         &self,
         candidates: Vec<HybridSearchResult>,
@@ -489,7 +975,7 @@ impl HybridSearchEngine {
     }
 
     /// Calculate cosine similarity between two u8-quantized vectors
-    #[cfg(feature = "vector")]
+    #[cfg(feature = "vector-index")]
     fn cosine_similarity_u8(a: &[u8], b: &[u8]) -> f32 {
         if a.len() != b.len() || a.is_empty() {
             return 0.0;
@@ -528,19 +1014,25 @@ impl HybridSearchEngine {
             ..Default::default()
         };
 
-        #[cfg(feature = "vector")]
+        #[cfg(feature = "vector-index")]
         {
             let mut final_stats = stats;
             final_stats.total_vectors = self.vector_store.len();
             final_stats.vector_dimension = self.vector_store.dimension();
             final_stats
         }
-        #[cfg(not(feature = "vector"))]
+        #[cfg(not(feature = "vector-index"))]
         {
             stats
         }
     }
 
+    /// Hot/cold sizes and cold-scan counts of the vector index
+    #[cfg(feature = "vector-index")]
+    pub fn vector_tier_stats(&self) -> TierStats {
+        self.vector_store.tier_stats()
+    }
+
     /// Save vector store to disk
     pub fn save_vectors(&self) -> Result<()> {
         self.commit()
@@ -552,6 +1044,25 @@ impl HybridSearchEngine {
     }
 }
 
+/// Vector hits as `(docid, score)` pairs for RRF, plus the heading path of
+/// each document's best-scoring chunk
+#[cfg(feature = "vector-index")]
+fn split_vector_hits(
+    hits: Vec<VectorSearchResult>,
+) -> (Vec<(String, f64)>, HashMap<String, String>) {
+    let mut headings = HashMap::new();
+    let ranked = hits
+        .into_iter()
+        .map(|hit| {
+            if let Some(path) = hit.heading_path {
+                headings.entry(hit.docid.clone()).or_insert(path);
+            }
+            (hit.docid, hit.score)
+        })
+        .collect();
+    (ranked, headings)
+}
+
 /// Hybrid search statistics
 #[derive(Debug, Clone, Default)]
 pub struct HybridSearchStats {
@@ -570,14 +1081,34 @@ mod tests {
     fn create_test_config(temp_dir: &TempDir) -> HybridSearchConfig {
         let mut config = HybridSearchConfig::default();
         config.db_path = temp_dir.path().join("test.db");
-        #[cfg(feature = "vector")]
+        #[cfg(feature = "vector-index")]
         {
             config.vector_store_path = Some(temp_dir.path().join("test_vectors.bin"));
             config.bm25_candidates = 10;
             config.vector_candidates = 10;
             config.hnsw_max_elements = 1000;
         }
-        config
+        crate::test_support::with_test_embeddings(config, temp_dir.path())
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let temp_dir = TempDir::new().unwrap();
+        assert!(create_test_config(&temp_dir).validate().is_empty());
+
+        let mut config = create_test_config(&temp_dir);
+        config.db_path = temp_dir.path().join("missing").join("test.db");
+        config.bm25_candidates = 0;
+        config.embedding_throughput.tokens_per_second = 0.0;
+        let paths: Vec<String> = config.validate().into_iter().map(|i| i.path).collect();
+        assert_eq!(
+            paths,
+            [
+                "search.db_path",
+                "search.bm25_candidates",
+                "search.embedding_throughput.tokens_per_second"
+            ]
+        );
     }
 
     #[test]
@@ -678,7 +1209,7 @@ mod tests {
     fn test_save_and_load_vectors() {
         let temp_dir = TempDir::new().unwrap();
         let config = create_test_config(&temp_dir);
-        #[cfg(feature = "vector")]
+        #[cfg(feature = "vector-index")]
         let vector_path = config.vector_store_path.clone().unwrap();
 
         {
@@ -701,14 +1232,14 @@ mod tests {
         }
 
         // Vector file should exist
-        #[cfg(feature = "vector")]
+        #[cfg(feature = "vector-index")]
         assert!(vector_path.exists());
 
         // Load in new engine
         let engine2 = HybridSearchEngine::new(config).unwrap();
-        #[cfg(feature = "vector")]
+        #[cfg(feature = "vector-index")]
         assert!(engine2.vector_store.len() > 0);
-        #[cfg(not(feature = "vector"))]
+        #[cfg(not(feature = "vector-index"))]
         assert_eq!(engine2.stats().total_documents, 1);
     }
 
@@ -755,7 +1286,7 @@ mod tests {
     }
 
     #[test]
-    #[cfg(feature = "vector")]
+    #[cfg(feature = "vector-index")]
     fn test_cosine_similarity_u8() {
         let a = vec![255, 0, 127]; // [1.0, -1.0, 0.0] approx
         let b = vec![255, 0, 127];
@@ -767,9 +1298,86 @@ mod tests {
         assert!(sim_neg < -0.99);
     }
 
+    #[test]
+    #[ignore] // Chunker requires tokenizer.json
+    #[cfg(feature = "vector-index")]
+    fn test_shared_embeddings_provider() {
+        use aagt_core::knowledge::store::InMemoryVectorStore;
+
+        struct Fixed;
+
+        #[async_trait::async_trait]
+        impl Embeddings for Fixed {
+            async fn embed(&self, text: &str) -> aagt_core::error::Result<Vec<f32>> {
+                Ok(vec![text.len() as f32, 1.0, 0.0])
+            }
+
+            fn dimension(&self) -> Option<usize> {
+                Some(3)
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let shared: Arc<dyn Embeddings> = Arc::new(Fixed);
+        let config = create_test_config(&temp_dir).with_embeddings(Arc::clone(&shared));
+        let engine = HybridSearchEngine::new(config).unwrap();
+        let memory = InMemoryVectorStore::with_embedder(Arc::clone(&shared));
+
+        assert!(Arc::ptr_eq(engine.embedder.inner(), memory.embedder()));
+        assert_eq!(engine.stats().vector_dimension, 3);
+    }
+
+    #[test]
+    #[ignore] // Chunker requires tokenizer.json
+    #[cfg(feature = "vector-index")]
+    fn test_index_batch_embeds_chunks_together() {
+        use std::sync::Mutex;
+
+        /// Records the size of every batch it is asked to embed
+        #[derive(Default)]
+        struct Recording {
+            batches: Mutex<Vec<usize>>,
+        }
+
+        #[async_trait::async_trait]
+        impl Embeddings for Recording {
+            async fn embed(&self, text: &str) -> aagt_core::error::Result<Vec<f32>> {
+                Ok(vec![text.len() as f32, 1.0, 0.0])
+            }
+
+            async fn embed_batch(&self, texts: &[&str]) -> aagt_core::error::Result<Vec<Vec<f32>>> {
+                self.batches.lock().unwrap().push(texts.len());
+                Ok(texts.iter().map(|t| vec![t.len() as f32, 1.0, 0.0]).collect())
+            }
+
+            fn dimension(&self) -> Option<usize> {
+                Some(3)
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let provider = Arc::new(Recording::default());
+        let config = create_test_config(&temp_dir).with_embeddings(provider.clone());
+        let engine = HybridSearchEngine::new(config).unwrap();
+
+        let docs: Vec<(String, String)> = (0..100)
+            .map(|i| (format!("note_{}.md", i), format!("Note {} on SOL momentum", i)))
+            .collect();
+        engine
+            .index_batch(
+                docs.iter()
+                    .map(|(path, body)| ("notes", path.as_str(), "Note", body.as_str()))
+                    .collect(),
+            )
+            .unwrap();
+
+        assert_eq!(*provider.batches.lock().unwrap(), [100]);
+        assert_eq!(engine.stats().total_vectors, 100);
+    }
+
     #[test]
     #[ignore] // Requires model file
-    #[cfg(feature = "vector")]
+    #[cfg(feature = "vector-index")]
     fn test_semantic_deduplication() {
         let temp_dir = TempDir::new().unwrap();
         let config = create_test_config(&temp_dir);
@@ -802,4 +1410,124 @@ mod tests {
         assert!(!results.is_empty());
         // In a real scenario with a local model, we'd check if results.len() == 1
     }
+
+    #[test]
+    #[cfg_attr(feature = "vector-index", ignore)] // Chunker requires tokenizer.json
+    fn test_deleted_documents_leave_search() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = HybridSearchEngine::new(create_test_config(&temp_dir)).unwrap();
+        engine
+            .index_document("notes", "sol.md", "SOL", "Leverage on SOL capped at two times")
+            .unwrap();
+        engine
+            .index_document("notes", "eth.md", "ETH", "Leverage on ETH capped at three times")
+            .unwrap();
+
+        assert!(engine.delete_document("notes", "sol.md").unwrap());
+        assert!(!engine.delete_document("notes", "sol.md").unwrap());
+        let hits = engine.search("leverage", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].document.path, "eth.md");
+        assert!(engine.get_by_path("notes", "sol.md").unwrap().is_none());
+        assert!(!engine.reindex_document("notes", "sol.md").unwrap());
+
+        // Indexing the path again brings it back
+        engine
+            .index_document("notes", "sol.md", "SOL", "Leverage on SOL capped at two times")
+            .unwrap();
+        assert_eq!(engine.search("leverage", 10).unwrap().len(), 2);
+        assert!(engine.reindex_document("notes", "sol.md").unwrap());
+    }
+
+    #[test]
+    #[cfg_attr(feature = "vector-index", ignore)] // Chunker requires tokenizer.json
+    fn test_user_text_never_breaks_search() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = HybridSearchEngine::new(create_test_config(&temp_dir)).unwrap();
+        engine
+            .index_document("notes", "rsi.md", "RSI", "What's RSI? A momentum oscillator.")
+            .unwrap();
+
+        for query in [r#"what's "RSI"?"#, "(RSI", "NEAR(", "-", "*", "🚀"] {
+            engine
+                .search(query, 5)
+                .unwrap_or_else(|e| panic!("{:?} failed: {}", query, e));
+        }
+        let hits = engine.search(r#"what's "RSI"?"#, 5).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].document.path, "rsi.md");
+    }
+
+    #[test]
+    #[cfg_attr(feature = "vector-index", ignore)] // Chunker requires tokenizer.json
+    fn test_search_as_of_sees_past_revisions() {
+        use chrono::{TimeZone, Utc};
+
+        let temp_dir = TempDir::new().unwrap();
+        let engine = HybridSearchEngine::new(create_test_config(&temp_dir)).unwrap();
+        let jan = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mar = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        engine
+            .qmd_store
+            .store_document_at("notes", "risk.md", "Risk", "Leverage capped at three times", jan)
+            .unwrap();
+        engine
+            .qmd_store
+            .store_document_at("notes", "risk.md", "Risk", "Leverage capped at two times", mar)
+            .unwrap();
+
+        let past = engine
+            .search_as_of("leverage", 5, jan.timestamp() + 60)
+            .unwrap();
+        assert_eq!(past.len(), 1);
+        assert_eq!(past[0].document.body.as_deref(), Some("Leverage capped at three times"));
+        let current = engine.search("leverage", 5).unwrap();
+        assert_eq!(current[0].document.body.as_deref(), Some("Leverage capped at two times"));
+        assert!(engine.search_as_of("leverage", 5, jan.timestamp() - 60).unwrap().is_empty());
+    }
+
+
+    #[test]
+    fn test_search_multi_applies_collection_weights() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = HybridSearchEngine::new(create_test_config(&temp_dir)).unwrap();
+        engine
+            .index_document("trading", "sol.md", "SOL", "SOL breakout setup on the daily chart")
+            .unwrap();
+        engine
+            .index_document("notes", "sol.md", "SOL", "SOL breakout idea from the weekly call")
+            .unwrap();
+        engine
+            .index_document("research", "sol.md", "SOL", "SOL breakout backtest results")
+            .unwrap();
+
+        let collections = |results: &[HybridSearchResult]| -> Vec<String> {
+            results
+                .iter()
+                .map(|r| r.document.collection.clone())
+                .collect()
+        };
+
+        let results = engine
+            .search_multi("breakout", &[("trading", 1.0), ("notes", 0.2)], 10)
+            .unwrap();
+        assert_eq!(collections(&results), ["trading", "notes"]);
+        assert_eq!(results[1].rank, 2);
+        assert!((results[1].weighted_score - results[1].rrf_score * 0.2).abs() < 1e-12);
+
+        let results = engine
+            .search_multi("breakout", &[("trading", 0.2), ("notes", 1.0)], 10)
+            .unwrap();
+        assert_eq!(collections(&results), ["notes", "trading"]);
+        assert_eq!(results[0].weighted_score, results[0].rrf_score);
+    }
+
+    #[test]
+    fn test_search_multi_rejects_bad_weights() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = HybridSearchEngine::new(create_test_config(&temp_dir)).unwrap();
+        for weight in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(engine.search_multi("sol", &[("notes", weight)], 5).is_err());
+        }
+    }
 }
@@ -0,0 +1,1096 @@
+//! Import conversations from external chat exports
+//!
+//! Parses ChatGPT (`conversations.json`), Claude and generic JSONL exports
+//! into [`ImportedConversation`]s. [`Importer`] then writes each one as a
+//! markdown transcript at `imported/{source}/{conversation_id}.md` and can
+//! optionally copy the user's own messages into an agent [`Memory`].
+//!
+//! Exports are read one conversation at a time, so multi-hundred-megabyte
+//! files never sit in memory at once. Documents are keyed by conversation id
+//! and skipped when their rendered content hash is unchanged, so re-running an
+//! import is a no-op. [`Importer::plan_file`] is the dry run: it goes through
+//! [`HybridSearchEngine::plan_index`] and writes nothing.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Arc;
+
+use aagt_core::agent::memory::Memory;
+use aagt_core::agent::message::{Message, Role};
+use chrono::{DateTime, Utc};
+use serde::de::{DeserializeOwned, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::content_hash::hash_content;
+use crate::error::{QmdError, Result};
+use crate::hybrid_search::HybridSearchEngine;
+use crate::index_plan::IndexPlan;
+
+/// Collection imported transcripts are stored in
+pub const IMPORT_COLLECTION: &str = "imported";
+
+/// Agent id under which imported user messages are written to memory
+pub const IMPORTED_MEMORY_TAG: &str = "imported";
+
+/// Export format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportSource {
+    /// ChatGPT data export (`conversations.json`, a tree per conversation)
+    ChatGpt,
+    /// Claude data export (`conversations.json`, a flat list per conversation)
+    Claude,
+    /// One JSON conversation per line: `{"id", "title", "messages": [{"role", "content", "timestamp"}]}`
+    Jsonl,
+}
+
+impl ExportSource {
+    /// Name used in document paths
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ChatGpt => "chatgpt",
+            Self::Claude => "claude",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+impl fmt::Display for ExportSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One message of an imported conversation
+#[derive(Debug, Clone)]
+pub struct ImportedMessage {
+    /// The message
+    pub message: Message,
+    /// When it was sent, if the export says
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+/// A conversation parsed from an export
+#[derive(Debug, Clone)]
+pub struct ImportedConversation {
+    /// Export format it came from
+    pub source: ExportSource,
+    /// Conversation id from the export
+    pub id: String,
+    /// Title, if any
+    pub title: Option<String>,
+    /// Creation time from the export
+    pub created_at: Option<DateTime<Utc>>,
+    /// Messages in conversation order (the active branch for tree exports)
+    pub messages: Vec<ImportedMessage>,
+}
+
+impl ImportedConversation {
+    /// Document path inside [`IMPORT_COLLECTION`]
+    pub fn path(&self) -> String {
+        let id: String = self
+            .id
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        format!("{}/{}.md", self.source, id)
+    }
+
+    /// Title, falling back to the first user message
+    pub fn display_title(&self) -> String {
+        if let Some(title) = self.title.as_deref().filter(|t| !t.trim().is_empty()) {
+            return title.trim().to_string();
+        }
+        self.messages
+            .iter()
+            .find(|m| m.message.role == Role::User)
+            .map(|m| m.message.content.as_text().chars().take(60).collect())
+            .unwrap_or_else(|| "Untitled conversation".to_string())
+    }
+
+    /// Earliest and latest message (or creation) time
+    pub fn date_range(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let times = self
+            .messages
+            .iter()
+            .filter_map(|m| m.timestamp)
+            .chain(self.created_at);
+        let (mut min, mut max): (Option<DateTime<Utc>>, Option<DateTime<Utc>>) = (None, None);
+        for t in times {
+            min = Some(min.map_or(t, |m| m.min(t)));
+            max = Some(max.map_or(t, |m| m.max(t)));
+        }
+        Some((min?, max?))
+    }
+
+    /// Markdown transcript with a metadata header
+    pub fn to_markdown(&self) -> String {
+        let mut participants: Vec<&str> = Vec::new();
+        for m in &self.messages {
+            let role = role_label(&m.message.role);
+            if !participants.contains(&role) {
+                participants.push(role);
+            }
+        }
+
+        let mut out = format!("# {}\n\n", self.display_title());
+        out.push_str(&format!("- Source: {}\n", self.source));
+        out.push_str(&format!("- Conversation: {}\n", self.id));
+        out.push_str(&format!("- Participants: {}\n", participants.join(", ")));
+        if let Some((from, to)) = self.date_range() {
+            out.push_str(&format!(
+                "- Date range: {} to {}\n",
+                from.to_rfc3339(),
+                to.to_rfc3339()
+            ));
+        }
+        out.push_str(&format!("- Messages: {}\n", self.messages.len()));
+
+        for m in &self.messages {
+            out.push_str(&format!("\n## {}", role_label(&m.message.role)));
+            if let Some(t) = m.timestamp {
+                out.push_str(&format!(" ({})", t.format("%Y-%m-%d %H:%M:%S UTC")));
+            }
+            out.push_str("\n\n");
+            out.push_str(m.message.content.as_text().trim());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn role_label(role: &Role) -> &'static str {
+    match role {
+        Role::System => "System",
+        Role::User => "User",
+        Role::Assistant => "Assistant",
+        Role::Tool => "Tool",
+    }
+}
+
+fn role_from(name: &str) -> Option<Role> {
+    match name {
+        "user" | "human" => Some(Role::User),
+        "assistant" => Some(Role::Assistant),
+        "system" => Some(Role::System),
+        "tool" => Some(Role::Tool),
+        _ => None,
+    }
+}
+
+fn imported(role: Role, text: String, timestamp: Option<DateTime<Utc>>) -> Option<ImportedMessage> {
+    if text.trim().is_empty() {
+        return None;
+    }
+    Some(ImportedMessage {
+        message: Message {
+            role,
+            content: text.into(),
+            name: None,
+            response_id: None,
+        },
+        timestamp,
+    })
+}
+
+fn from_unix(secs: f64) -> Option<DateTime<Utc>> {
+    let whole = secs.floor();
+    let nanos = ((secs - whole) * 1e9).round() as u32;
+    DateTime::from_timestamp(whole as i64, nanos.min(999_999_999))
+}
+
+// ---------- ChatGPT ----------
+
+#[derive(Deserialize)]
+struct OpenAiConversation {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    conversation_id: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    create_time: Option<f64>,
+    #[serde(default)]
+    mapping: HashMap<String, OpenAiNode>,
+    #[serde(default)]
+    current_node: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiNode {
+    #[serde(default)]
+    message: Option<OpenAiMessage>,
+    #[serde(default)]
+    parent: Option<String>,
+    #[serde(default)]
+    children: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiMessage {
+    author: OpenAiAuthor,
+    #[serde(default)]
+    create_time: Option<f64>,
+    #[serde(default)]
+    content: Option<OpenAiContent>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiAuthor {
+    role: String,
+}
+
+#[derive(Deserialize)]
+struct OpenAiContent {
+    #[serde(default)]
+    parts: Vec<serde_json::Value>,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+impl OpenAiConversation {
+    /// Node ids of the active branch, root first
+    ///
+    /// Follows parent pointers up from `current_node`; without one, the
+    /// branch ending at the latest child of each node is used.
+    fn active_branch(&self) -> Vec<&str> {
+        let leaf = match self
+            .current_node
+            .as_deref()
+            .filter(|n| self.mapping.contains_key(*n))
+        {
+            Some(node) => node,
+            None => {
+                let Some(mut node) = self
+                    .mapping
+                    .iter()
+                    .filter(|(_, n)| {
+                        n.parent
+                            .as_ref()
+                            .is_none_or(|p| !self.mapping.contains_key(p))
+                    })
+                    .map(|(id, _)| id.as_str())
+                    .min()
+                else {
+                    return Vec::new();
+                };
+                let mut seen = HashSet::new();
+                while let Some(next) = self.mapping[node]
+                    .children
+                    .last()
+                    .filter(|c| self.mapping.contains_key(c.as_str()))
+                {
+                    if !seen.insert(node) {
+                        break;
+                    }
+                    node = next;
+                }
+                node
+            }
+        };
+
+        let mut branch = Vec::new();
+        let mut seen = HashSet::new();
+        let mut cursor = Some(leaf);
+        while let Some(id) = cursor {
+            if !seen.insert(id) {
+                break;
+            }
+            branch.push(id);
+            cursor = self.mapping.get(id).and_then(|n| n.parent.as_deref());
+            if cursor.is_some_and(|p| !self.mapping.contains_key(p)) {
+                break;
+            }
+        }
+        branch.reverse();
+        branch
+    }
+
+    fn into_conversation(self) -> ImportedConversation {
+        let mut messages = Vec::new();
+        for id in self.active_branch() {
+            let Some(msg) = &self.mapping[id].message else {
+                continue;
+            };
+            let Some(role) = role_from(&msg.author.role) else {
+                continue;
+            };
+            if role == Role::System {
+                continue;
+            }
+            let text = match &msg.content {
+                Some(content) => {
+                    let parts: Vec<&str> =
+                        content.parts.iter().filter_map(|p| p.as_str()).collect();
+                    if parts.is_empty() {
+                        content.text.clone().unwrap_or_default()
+                    } else {
+                        parts.join("\n")
+                    }
+                }
+                None => String::new(),
+            };
+            messages.extend(imported(role, text, msg.create_time.and_then(from_unix)));
+        }
+        ImportedConversation {
+            source: ExportSource::ChatGpt,
+            id: self
+                .conversation_id
+                .clone()
+                .or_else(|| self.id.clone())
+                .unwrap_or_default(),
+            title: self.title.clone(),
+            created_at: self.create_time.and_then(from_unix),
+            messages,
+        }
+    }
+}
+
+// ---------- Claude ----------
+
+#[derive(Deserialize)]
+struct ClaudeConversation {
+    #[serde(default)]
+    uuid: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    chat_messages: Vec<ClaudeMessage>,
+}
+
+#[derive(Deserialize)]
+struct ClaudeMessage {
+    #[serde(default)]
+    sender: String,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    content: Vec<ClaudeContent>,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct ClaudeContent {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+impl ClaudeConversation {
+    fn into_conversation(self) -> ImportedConversation {
+        let messages = self
+            .chat_messages
+            .into_iter()
+            .filter_map(|m| {
+                let role = role_from(&m.sender)?;
+                let parts: Vec<String> = m
+                    .content
+                    .into_iter()
+                    .filter(|c| c.kind == "text")
+                    .filter_map(|c| c.text)
+                    .collect();
+                let text = if parts.is_empty() {
+                    m.text.unwrap_or_default()
+                } else {
+                    parts.join("\n")
+                };
+                imported(role, text, m.created_at)
+            })
+            .collect();
+        ImportedConversation {
+            source: ExportSource::Claude,
+            id: self.uuid,
+            title: self.name,
+            created_at: self.created_at,
+            messages,
+        }
+    }
+}
+
+// ---------- JSONL ----------
+
+#[derive(Deserialize)]
+struct JsonlConversation {
+    id: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    messages: Vec<JsonlMessage>,
+}
+
+#[derive(Deserialize)]
+struct JsonlMessage {
+    role: String,
+    content: String,
+    #[serde(default)]
+    timestamp: Option<DateTime<Utc>>,
+}
+
+impl JsonlConversation {
+    fn into_conversation(self) -> ImportedConversation {
+        let messages = self
+            .messages
+            .into_iter()
+            .filter_map(|m| imported(role_from(&m.role)?, m.content, m.timestamp))
+            .collect();
+        ImportedConversation {
+            source: ExportSource::Jsonl,
+            id: self.id,
+            title: self.title,
+            created_at: self.created_at,
+            messages,
+        }
+    }
+}
+
+/// Stream conversations out of an export, calling `f` for each
+///
+/// Returns how many conversations were read. An error from `f` stops parsing.
+pub fn parse_export<R: Read>(
+    source: ExportSource,
+    reader: R,
+    mut f: impl FnMut(ImportedConversation) -> Result<()>,
+) -> Result<usize> {
+    let reader = BufReader::new(reader);
+    match source {
+        ExportSource::ChatGpt => {
+            for_each_in_array(reader, |c: OpenAiConversation| f(c.into_conversation()))
+        }
+        ExportSource::Claude => {
+            for_each_in_array(reader, |c: ClaudeConversation| f(c.into_conversation()))
+        }
+        ExportSource::Jsonl => {
+            let mut count = 0;
+            for (n, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let conversation: JsonlConversation = serde_json::from_str(&line)
+                    .map_err(|e| QmdError::Custom(format!("Line {}: {}", n + 1, e)))?;
+                f(conversation.into_conversation())?;
+                count += 1;
+            }
+            Ok(count)
+        }
+    }
+}
+
+/// Deserialize a top-level JSON array one element at a time
+fn for_each_in_array<T, R, F>(reader: R, f: F) -> Result<usize>
+where
+    T: DeserializeOwned,
+    R: Read,
+    F: FnMut(T) -> Result<()>,
+{
+    struct ArrayVisitor<T, F> {
+        f: F,
+        count: usize,
+        failed: Option<QmdError>,
+        _item: PhantomData<T>,
+    }
+
+    impl<'de, T: DeserializeOwned, F: FnMut(T) -> Result<()>> Visitor<'de> for &mut ArrayVisitor<T, F> {
+        type Value = ();
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an array of conversations")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+            while let Some(item) = seq.next_element::<T>()? {
+                self.count += 1;
+                if let Err(e) = (self.f)(item) {
+                    self.failed = Some(e);
+                    return Err(serde::de::Error::custom("import aborted"));
+                }
+            }
+            Ok(())
+        }
+    }
+
+    let mut visitor = ArrayVisitor {
+        f,
+        count: 0,
+        failed: None,
+        _item: PhantomData,
+    };
+    let mut de = serde_json::Deserializer::from_reader(reader);
+    let parsed = de.deserialize_seq(&mut visitor);
+    if let Some(e) = visitor.failed.take() {
+        return Err(e);
+    }
+    parsed?;
+    de.end()?;
+    Ok(visitor.count)
+}
+
+// ---------- Importer ----------
+
+/// Whether a conversation's document would be created, updated or left alone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportStatus {
+    /// Not imported before
+    New,
+    /// Imported before with different content
+    Changed,
+    /// Imported before with identical content
+    Unchanged,
+    /// No importable messages
+    Empty,
+}
+
+/// One conversation in an [`ImportPlan`]
+#[derive(Debug, Clone)]
+pub struct PlannedConversation {
+    /// Conversation id
+    pub id: String,
+    /// Document title
+    pub title: String,
+    /// Document path inside [`IMPORT_COLLECTION`]
+    pub path: String,
+    /// Messages that would be imported
+    pub messages: usize,
+    /// What importing would do
+    pub status: ImportStatus,
+}
+
+/// Dry-run result of [`Importer::plan_file`]
+#[derive(Debug, Clone)]
+pub struct ImportPlan {
+    /// Every conversation in the export
+    pub conversations: Vec<PlannedConversation>,
+    /// Index plan for the documents that would be written
+    pub index: IndexPlan,
+}
+
+/// Counters after an import
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Conversations read from the export
+    pub conversations: usize,
+    /// Documents created
+    pub created: usize,
+    /// Documents rewritten because the conversation grew or changed
+    pub updated: usize,
+    /// Conversations already imported with identical content
+    pub unchanged: usize,
+    /// Conversations without importable messages
+    pub empty: usize,
+    /// User messages written to memory
+    pub memory_entries: usize,
+    /// Conversations the index refused, with the reason
+    pub rejected: Vec<(String, String)>,
+}
+
+/// Progress callback payload
+#[derive(Debug, Clone, Default)]
+pub struct ImportProgress {
+    /// Conversations read so far
+    pub conversations: usize,
+    /// Documents written so far
+    pub written: usize,
+    /// Memory entries written so far
+    pub memory_entries: usize,
+}
+
+type MemoryGuard = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+type ProgressFn = Arc<dyn Fn(&ImportProgress) + Send + Sync>;
+
+/// Writes imported conversations into a [`HybridSearchEngine`] (and optionally memory)
+pub struct Importer<'a> {
+    engine: &'a HybridSearchEngine,
+    batch_size: usize,
+    memory: Option<(Arc<dyn Memory>, String)>,
+    guard: Option<MemoryGuard>,
+    progress: Option<ProgressFn>,
+}
+
+struct Pending {
+    conversation: ImportedConversation,
+    rendered: String,
+    status: ImportStatus,
+    /// Messages already imported by an earlier run
+    previous_messages: usize,
+}
+
+impl<'a> Importer<'a> {
+    /// Import into `engine`
+    pub fn new(engine: &'a HybridSearchEngine) -> Self {
+        Self {
+            engine,
+            batch_size: 32,
+            memory: None,
+            guard: None,
+            progress: None,
+        }
+    }
+
+    /// Documents indexed per batch (default 32)
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Also write user-authored messages to `memory` for `user_id`, under agent id [`IMPORTED_MEMORY_TAG`]
+    pub fn with_memory(mut self, memory: Arc<dyn Memory>, user_id: impl Into<String>) -> Self {
+        self.memory = Some((memory, user_id.into()));
+        self
+    }
+
+    /// Filter applied before each memory write: return `None` to drop the
+    /// message or the (possibly redacted) text to store
+    pub fn with_memory_guard(
+        mut self,
+        guard: impl Fn(&str) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.guard = Some(Arc::new(guard));
+        self
+    }
+
+    /// Called after every conversation
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(&ImportProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Dry run: parse the export and plan indexing without writing anything
+    pub fn plan_file(&self, path: impl AsRef<Path>, source: ExportSource) -> Result<ImportPlan> {
+        let file = std::fs::File::open(path)?;
+        let mut conversations = Vec::new();
+        let mut to_index = Vec::new();
+        parse_export(source, file, |conversation| {
+            let pending = self.classify(conversation)?;
+            let c = &pending.conversation;
+            conversations.push(PlannedConversation {
+                id: c.id.clone(),
+                title: c.display_title(),
+                path: c.path(),
+                messages: c.messages.len(),
+                status: pending.status,
+            });
+            if matches!(pending.status, ImportStatus::New | ImportStatus::Changed) {
+                to_index.push((c.path(), c.display_title(), pending.rendered));
+            }
+            Ok(())
+        })?;
+        let index = self.engine.plan_index(
+            to_index
+                .iter()
+                .map(|(path, title, content)| {
+                    (
+                        IMPORT_COLLECTION,
+                        path.as_str(),
+                        title.as_str(),
+                        content.as_str(),
+                    )
+                })
+                .collect(),
+        );
+        Ok(ImportPlan {
+            conversations,
+            index,
+        })
+    }
+
+    /// Import an export file
+    ///
+    /// The file is parsed on a blocking thread and handed over one
+    /// conversation at a time, then indexed in batches.
+    pub async fn import_file(
+        &self,
+        path: impl AsRef<Path>,
+        source: ExportSource,
+    ) -> Result<ImportReport> {
+        let file = std::fs::File::open(path)?;
+        let (tx, mut rx) = tokio::sync::mpsc::channel(self.batch_size * 2);
+        let parser = tokio::task::spawn_blocking(move || {
+            parse_export(source, file, |conversation| {
+                tx.blocking_send(conversation)
+                    .map_err(|_| QmdError::Custom("Import receiver dropped".to_string()))
+            })
+        });
+
+        let mut report = ImportReport::default();
+        let mut progress = ImportProgress::default();
+        let mut batch = Vec::with_capacity(self.batch_size);
+        while let Some(conversation) = rx.recv().await {
+            report.conversations += 1;
+            progress.conversations += 1;
+            let pending = self.classify(conversation)?;
+            match pending.status {
+                ImportStatus::Unchanged => report.unchanged += 1,
+                ImportStatus::Empty => report.empty += 1,
+                ImportStatus::New | ImportStatus::Changed => batch.push(pending),
+            }
+            if batch.len() >= self.batch_size {
+                self.flush(&mut batch, &mut report, &mut progress).await?;
+            }
+            if let Some(callback) = &self.progress {
+                callback(&progress);
+            }
+        }
+        self.flush(&mut batch, &mut report, &mut progress).await?;
+        if let Some(callback) = &self.progress {
+            callback(&progress);
+        }
+
+        parser
+            .await
+            .map_err(|e| QmdError::Custom(format!("Export parser panicked: {}", e)))??;
+        tracing::info!(
+            "Imported {} conversations from {} export: {} new, {} updated, {} unchanged",
+            report.conversations,
+            source,
+            report.created,
+            report.updated,
+            report.unchanged
+        );
+        Ok(report)
+    }
+
+    fn classify(&self, conversation: ImportedConversation) -> Result<Pending> {
+        let rendered = conversation.to_markdown();
+        if conversation.messages.is_empty() || conversation.id.is_empty() {
+            return Ok(Pending {
+                conversation,
+                rendered,
+                status: ImportStatus::Empty,
+                previous_messages: 0,
+            });
+        }
+        let existing = self
+            .engine
+            .get_by_path(IMPORT_COLLECTION, &conversation.path())?;
+        let (status, previous_messages) = match existing {
+            None => (ImportStatus::New, 0),
+            Some(doc) if doc.hash == hash_content(&rendered) => (ImportStatus::Unchanged, 0),
+            Some(doc) => (
+                ImportStatus::Changed,
+                doc.body
+                    .as_deref()
+                    .and_then(imported_message_count)
+                    .unwrap_or(0),
+            ),
+        };
+        Ok(Pending {
+            conversation,
+            rendered,
+            status,
+            previous_messages,
+        })
+    }
+
+    async fn flush(
+        &self,
+        batch: &mut Vec<Pending>,
+        report: &mut ImportReport,
+        progress: &mut ImportProgress,
+    ) -> Result<()> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let pending = std::mem::take(batch);
+        let titles: Vec<(String, String)> = pending
+            .iter()
+            .map(|p| (p.conversation.path(), p.conversation.display_title()))
+            .collect();
+        let plan = self.engine.plan_index(
+            pending
+                .iter()
+                .zip(&titles)
+                .map(|(p, (path, title))| {
+                    (
+                        IMPORT_COLLECTION,
+                        path.as_str(),
+                        title.as_str(),
+                        p.rendered.as_str(),
+                    )
+                })
+                .collect(),
+        );
+        self.engine.execute_plan(&plan)?;
+
+        let rejected: HashSet<&str> = plan.rejected().map(|d| d.path.as_str()).collect();
+        for doc in plan.rejected() {
+            report
+                .rejected
+                .push((doc.path.clone(), doc.error.clone().unwrap_or_default()));
+        }
+        for p in &pending {
+            if rejected.contains(p.conversation.path().as_str()) {
+                continue;
+            }
+            progress.written += 1;
+            match p.status {
+                ImportStatus::Changed => report.updated += 1,
+                _ => report.created += 1,
+            }
+            let written = self.write_memory(p).await?;
+            report.memory_entries += written;
+            progress.memory_entries += written;
+        }
+        Ok(())
+    }
+
+    /// Write user messages not imported by an earlier run
+    async fn write_memory(&self, pending: &Pending) -> Result<usize> {
+        let Some((memory, user_id)) = &self.memory else {
+            return Ok(0);
+        };
+        let mut written = 0;
+        for m in pending
+            .conversation
+            .messages
+            .iter()
+            .skip(pending.previous_messages)
+        {
+            if m.message.role != Role::User {
+                continue;
+            }
+            let text = m.message.content.as_text();
+            let text = match &self.guard {
+                Some(guard) => match guard(&text) {
+                    Some(text) => text,
+                    None => continue,
+                },
+                None => text,
+            };
+            let mut message = Message::user(text);
+            message.name = Some(format!(
+                "{}:{}",
+                pending.conversation.source, pending.conversation.id
+            ));
+            memory
+                .store(user_id, Some(IMPORTED_MEMORY_TAG), message)
+                .await
+                .map_err(|e| QmdError::Custom(format!("Memory write failed: {}", e)))?;
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
+/// Message count recorded in a transcript header
+fn imported_message_count(body: &str) -> Option<usize> {
+    body.lines()
+        .find_map(|l| l.strip_prefix("- Messages: "))
+        .and_then(|n| n.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_search::HybridSearchConfig;
+    use aagt_core::agent::memory::Memory;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+    use tempfile::TempDir;
+
+    const CHATGPT: &str = r#"[
+      {
+        "id": "conv-1",
+        "title": "Staking SOL",
+        "create_time": 1700000000.5,
+        "current_node": "a2",
+        "mapping": {
+          "root": { "message": null, "parent": null, "children": ["sys"] },
+          "sys": { "message": { "author": { "role": "system" }, "content": { "parts": [""] } }, "parent": "root", "children": ["u1"] },
+          "u1": { "message": { "author": { "role": "user" }, "create_time": 1700000001.25, "content": { "content_type": "text", "parts": ["How do I stake SOL?"] } }, "parent": "sys", "children": ["a1", "a2"] },
+          "a1": { "message": { "author": { "role": "assistant" }, "create_time": 1700000002.0, "content": { "parts": ["Old discarded answer"] } }, "parent": "u1", "children": [] },
+          "a2": { "message": { "author": { "role": "assistant" }, "create_time": 1700000003.0, "content": { "parts": ["Delegate to a validator with a low commission."] } }, "parent": "u1", "children": [] }
+        }
+      }
+    ]"#;
+
+    const CLAUDE: &str = r#"[
+      {
+        "uuid": "c-42",
+        "name": "ETH gas",
+        "created_at": "2024-03-01T10:00:00Z",
+        "chat_messages": [
+          { "sender": "human", "text": "Why is gas so high?", "content": [], "created_at": "2024-03-01T10:00:05Z" },
+          { "sender": "assistant", "text": "", "content": [{ "type": "text", "text": "Blob demand spiked after the upgrade." }], "created_at": "2024-03-01T10:00:09Z" }
+        ]
+      }
+    ]"#;
+
+    #[derive(Default)]
+    struct Recorded(Mutex<Vec<(String, Option<String>, Message)>>);
+
+    #[async_trait]
+    impl Memory for Recorded {
+        async fn store(
+            &self,
+            user_id: &str,
+            agent_id: Option<&str>,
+            message: Message,
+        ) -> aagt_core::error::Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .push((user_id.to_string(), agent_id.map(String::from), message));
+            Ok(())
+        }
+
+        async fn retrieve(&self, _: &str, _: Option<&str>, _: usize) -> Vec<Message> {
+            Vec::new()
+        }
+
+        async fn clear(&self, _: &str, _: Option<&str>) -> aagt_core::error::Result<()> {
+            Ok(())
+        }
+
+        async fn undo(
+            &self,
+            _: &str,
+            _: Option<&str>,
+        ) -> aagt_core::error::Result<Option<Message>> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn test_branch_linearization_and_timestamps() {
+        let mut parsed = Vec::new();
+        parse_export(ExportSource::ChatGpt, CHATGPT.as_bytes(), |c| {
+            parsed.push(c);
+            Ok(())
+        })
+        .unwrap();
+        let conv = &parsed[0];
+        let texts: Vec<String> = conv
+            .messages
+            .iter()
+            .map(|m| m.message.content.as_text())
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "How do I stake SOL?",
+                "Delegate to a validator with a low commission."
+            ]
+        );
+        assert_eq!(
+            conv.messages[0].timestamp.unwrap().to_rfc3339(),
+            "2023-11-14T22:13:21.250+00:00"
+        );
+
+        // Without current_node the latest child wins too
+        let headless = CHATGPT.replace(r#""current_node": "a2","#, "");
+        parse_export(ExportSource::ChatGpt, headless.as_bytes(), |c| {
+            assert!(c.messages[1]
+                .message
+                .content
+                .as_text()
+                .starts_with("Delegate"));
+            Ok(())
+        })
+        .unwrap();
+
+        let mut claude = Vec::new();
+        parse_export(ExportSource::Claude, CLAUDE.as_bytes(), |c| {
+            claude.push(c);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            claude[0].messages[1].message.content.as_text(),
+            "Blob demand spiked after the upgrade."
+        );
+        let md = claude[0].to_markdown();
+        assert!(md.contains("- Participants: User, Assistant"));
+        assert!(md.contains("- Date range: 2024-03-01T10:00:00+00:00 to 2024-03-01T10:00:09+00:00"));
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_import_and_search() {
+        let dir = TempDir::new().unwrap();
+        let config = HybridSearchConfig {
+            db_path: dir.path().join("import.db"),
+            ..Default::default()
+        };
+        let config = crate::test_support::with_test_embeddings(config, dir.path());
+        let engine = HybridSearchEngine::new(config).unwrap();
+        let chatgpt = dir.path().join("conversations.json");
+        std::fs::write(&chatgpt, CHATGPT).unwrap();
+        let claude = dir.path().join("claude.json");
+        std::fs::write(&claude, CLAUDE).unwrap();
+
+        let memory = Arc::new(Recorded::default());
+        let importer = Importer::new(&engine)
+            .with_memory(memory.clone(), "alice")
+            .with_memory_guard(|text| Some(text.replace("SOL", "[asset]")));
+
+        let plan = importer.plan_file(&chatgpt, ExportSource::ChatGpt).unwrap();
+        assert_eq!(plan.conversations[0].status, ImportStatus::New);
+        assert_eq!(plan.index.accepted().count(), 1);
+        assert_eq!(engine.stats().total_documents, 0);
+
+        let report = importer
+            .import_file(&chatgpt, ExportSource::ChatGpt)
+            .await
+            .unwrap();
+        assert_eq!((report.created, report.memory_entries), (1, 1));
+        importer
+            .import_file(&claude, ExportSource::Claude)
+            .await
+            .unwrap();
+
+        let again = importer
+            .import_file(&chatgpt, ExportSource::ChatGpt)
+            .await
+            .unwrap();
+        assert_eq!(
+            (again.created, again.unchanged, again.memory_entries),
+            (0, 1, 0)
+        );
+        assert_eq!(engine.stats().total_documents, 2);
+        assert_eq!(
+            importer
+                .plan_file(&chatgpt, ExportSource::ChatGpt)
+                .unwrap()
+                .conversations[0]
+                .status,
+            ImportStatus::Unchanged
+        );
+
+        let stored = memory.0.lock().unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].0, "alice");
+        assert_eq!(stored[0].1.as_deref(), Some(IMPORTED_MEMORY_TAG));
+        assert_eq!(stored[0].2.content.as_text(), "How do I stake [asset]?");
+        drop(stored);
+
+        let hits = engine.search("validator commission", 5).unwrap();
+        assert_eq!(hits[0].document.path, "chatgpt/conv-1.md");
+        assert_eq!(hits[0].document.collection, IMPORT_COLLECTION);
+        let doc = engine
+            .get_by_path(IMPORT_COLLECTION, "claude/c-42.md")
+            .unwrap()
+            .unwrap();
+        assert_eq!(doc.title, "ETH gas");
+        assert!(doc
+            .body
+            .unwrap()
+            .contains("## User (2024-03-01 10:00:05 UTC)"));
+    }
+}
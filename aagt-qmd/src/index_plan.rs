@@ -0,0 +1,369 @@
+//! Dry-run planning for bulk indexing
+//!
+//! [`HybridSearchEngine::plan_index`] runs the same validation and chunking as
+//! [`HybridSearchEngine::index_document`] but stops before embedding and
+//! storage, so a large import can be previewed for free. The resulting
+//! [`IndexPlan`] keeps the prepared documents, and
+//! [`HybridSearchEngine::execute_plan`] indexes exactly the accepted ones
+//! without validating or chunking them again.
+
+use std::path::Path;
+
+use crate::error::{QmdError, Result};
+use crate::hybrid_search::{HybridSearchEngine, PreparedDocument};
+
+/// Embedding throughput and price used to estimate a plan's cost
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmbeddingThroughput {
+    /// Tokens the backend embeds per second
+    pub tokens_per_second: f64,
+    /// Price in USD per million embedded tokens (0 for local models)
+    pub usd_per_million_tokens: f64,
+}
+
+impl Default for EmbeddingThroughput {
+    fn default() -> Self {
+        // Local MiniLM on a laptop CPU
+        Self {
+            tokens_per_second: 5_000.0,
+            usd_per_million_tokens: 0.0,
+        }
+    }
+}
+
+/// One document in an [`IndexPlan`]
+#[derive(Debug, Clone)]
+pub struct PlannedDocument {
+    /// Target collection
+    pub collection: String,
+    /// Document path
+    pub path: String,
+    /// Title that will be stored
+    pub title: String,
+    /// Size of the content in bytes
+    pub size_bytes: usize,
+    /// Token count of each chunk, in order (empty without the `vector-index` feature)
+    pub chunk_tokens: Vec<usize>,
+    /// Why the document would be rejected, if it would be
+    pub error: Option<String>,
+    content: String,
+    prepared: Option<PreparedDocument>,
+}
+
+impl PlannedDocument {
+    /// Whether the document would be indexed
+    pub fn is_accepted(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Number of chunks that would be embedded
+    pub fn chunk_count(&self) -> usize {
+        self.chunk_tokens.len()
+    }
+
+    fn rejected(
+        collection: &str,
+        path: &str,
+        title: &str,
+        size_bytes: usize,
+        error: String,
+    ) -> Self {
+        Self {
+            collection: collection.to_string(),
+            path: path.to_string(),
+            title: title.to_string(),
+            size_bytes,
+            chunk_tokens: Vec::new(),
+            error: Some(error),
+            content: String::new(),
+            prepared: None,
+        }
+    }
+}
+
+/// Preview of an indexing run
+#[derive(Debug, Clone)]
+pub struct IndexPlan {
+    /// Every input document, accepted or not, in input order
+    pub documents: Vec<PlannedDocument>,
+    /// Throughput the estimates were computed with
+    pub throughput: EmbeddingThroughput,
+}
+
+impl IndexPlan {
+    /// Documents that would be indexed
+    pub fn accepted(&self) -> impl Iterator<Item = &PlannedDocument> {
+        self.documents.iter().filter(|d| d.is_accepted())
+    }
+
+    /// Documents that would be rejected
+    pub fn rejected(&self) -> impl Iterator<Item = &PlannedDocument> {
+        self.documents.iter().filter(|d| !d.is_accepted())
+    }
+
+    /// Chunks that would be embedded
+    pub fn total_chunks(&self) -> usize {
+        self.accepted().map(PlannedDocument::chunk_count).sum()
+    }
+
+    /// Tokens that would be embedded
+    pub fn total_tokens(&self) -> usize {
+        self.accepted().flat_map(|d| &d.chunk_tokens).sum()
+    }
+
+    /// Bytes of accepted content
+    pub fn total_bytes(&self) -> usize {
+        self.accepted().map(|d| d.size_bytes).sum()
+    }
+
+    /// Estimated embedding time in seconds
+    pub fn estimated_seconds(&self) -> f64 {
+        if self.throughput.tokens_per_second <= 0.0 {
+            return 0.0;
+        }
+        self.total_tokens() as f64 / self.throughput.tokens_per_second
+    }
+
+    /// Estimated embedding cost in USD
+    pub fn estimated_cost_usd(&self) -> f64 {
+        self.total_tokens() as f64 / 1_000_000.0 * self.throughput.usd_per_million_tokens
+    }
+
+    /// One-paragraph summary, e.g. for a CLI preview
+    pub fn summary(&self) -> String {
+        let rejected = self.rejected().count();
+        format!(
+            "{} documents: {} accepted ({} bytes, {} chunks, {} tokens), {} rejected. Estimated embedding: {:.1}s, ${:.4}",
+            self.documents.len(),
+            self.documents.len() - rejected,
+            self.total_bytes(),
+            self.total_chunks(),
+            self.total_tokens(),
+            rejected,
+            self.estimated_seconds(),
+            self.estimated_cost_usd()
+        )
+    }
+}
+
+impl HybridSearchEngine {
+    /// Validate and chunk documents without embedding or storing anything
+    ///
+    /// Takes the same `(collection, path, title, content)` tuples as
+    /// [`HybridSearchEngine::index_batch`].
+    pub fn plan_index(&self, documents: Vec<(&str, &str, &str, &str)>) -> IndexPlan {
+        let documents = documents
+            .into_iter()
+            .map(|(collection, path, title, content)| {
+                self.plan_document(collection, path, title, content)
+            })
+            .collect();
+        IndexPlan {
+            documents,
+            throughput: self.config().embedding_throughput,
+        }
+    }
+
+    /// Plan indexing every `.md` file under `root` into `collection`
+    ///
+    /// Unreadable and non-UTF-8 files are reported as rejected entries. Titles
+    /// come from the first `# ` heading, falling back to the file stem.
+    pub fn plan_directory(&self, root: impl AsRef<Path>, collection: &str) -> Result<IndexPlan> {
+        let pattern = format!("{}/**/*.md", root.as_ref().to_string_lossy());
+        let mut documents = Vec::new();
+        for entry in glob::glob(&pattern)? {
+            let path_buf = match entry {
+                Ok(p) => p,
+                Err(e) => {
+                    let path = e.path().to_string_lossy().to_string();
+                    documents.push(PlannedDocument::rejected(
+                        collection,
+                        &path,
+                        "",
+                        0,
+                        e.error().to_string(),
+                    ));
+                    continue;
+                }
+            };
+            let path = path_buf.to_string_lossy().to_string();
+            let stem = path_buf
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("Untitled");
+
+            let planned = match std::fs::read(&path_buf) {
+                Err(e) => PlannedDocument::rejected(collection, &path, stem, 0, e.to_string()),
+                Ok(bytes) => match String::from_utf8(bytes) {
+                    Err(e) => PlannedDocument::rejected(
+                        collection,
+                        &path,
+                        stem,
+                        e.as_bytes().len(),
+                        format!("Not valid UTF-8: {}", e.utf8_error()),
+                    ),
+                    Ok(content) => {
+                        let title = detect_title(&content).unwrap_or(stem).to_string();
+                        self.plan_document(collection, &path, &title, &content)
+                    }
+                },
+            };
+            documents.push(planned);
+        }
+        Ok(IndexPlan {
+            documents,
+            throughput: self.config().embedding_throughput,
+        })
+    }
+
+    /// Index the accepted documents of `plan`, reusing its validation and chunks
+    ///
+    /// Returns how many documents were indexed.
+    pub fn execute_plan(&self, plan: &IndexPlan) -> Result<usize> {
+        let mut entries = Vec::new();
+        for doc in plan.accepted() {
+            let prepared = doc.prepared.as_ref().ok_or_else(|| {
+                QmdError::Custom(format!("Plan entry {} was not prepared", doc.path))
+            })?;
+            entries.push((
+                doc.collection.as_str(),
+                doc.path.as_str(),
+                doc.title.as_str(),
+                doc.content.as_str(),
+                prepared,
+            ));
+        }
+        let indexed = entries.len();
+        self.store_prepared(&entries)?;
+        self.save_after_batch()?;
+        tracing::info!("Executed index plan: {} documents", indexed);
+        Ok(indexed)
+    }
+
+    fn plan_document(
+        &self,
+        collection: &str,
+        path: &str,
+        title: &str,
+        content: &str,
+    ) -> PlannedDocument {
+        match self.prepare(content) {
+            Ok(prepared) => PlannedDocument {
+                collection: collection.to_string(),
+                path: path.to_string(),
+                title: title.to_string(),
+                size_bytes: content.len(),
+                #[cfg(feature = "vector-index")]
+                chunk_tokens: prepared
+                    .chunks
+                    .iter()
+                    .map(|c| c.end_token - c.start_token)
+                    .collect(),
+                #[cfg(not(feature = "vector-index"))]
+                chunk_tokens: Vec::new(),
+                error: None,
+                content: content.to_string(),
+                prepared: Some(prepared),
+            },
+            Err(e) => {
+                PlannedDocument::rejected(collection, path, title, content.len(), e.to_string())
+            }
+        }
+    }
+}
+
+/// Text of the first level-one markdown heading
+pub(crate) fn detect_title(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_search::HybridSearchConfig;
+    use tempfile::TempDir;
+
+    fn engine(dir: &TempDir) -> HybridSearchEngine {
+        let config = HybridSearchConfig {
+            db_path: dir.path().join("plan.db"),
+            embedding_throughput: EmbeddingThroughput {
+                tokens_per_second: 100.0,
+                usd_per_million_tokens: 0.02,
+            },
+            ..Default::default()
+        };
+        let config = crate::test_support::with_test_embeddings(config, dir.path());
+        HybridSearchEngine::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_plan_leaves_store_untouched_then_executes() {
+        let dir = TempDir::new().unwrap();
+        let engine = engine(&dir);
+        engine
+            .index_document("wiki", "seed.md", "Seed", "Seed page")
+            .unwrap();
+
+        let docs = dir.path().join("docs");
+        std::fs::create_dir_all(docs.join("nested")).unwrap();
+        std::fs::write(docs.join("sol.md"), "# SOL Notes\n\nBuy SOL on dips.\n").unwrap();
+        std::fs::write(docs.join("nested/eth.md"), "ETH staking yields.\n").unwrap();
+        std::fs::write(docs.join("empty.md"), "  \n\n").unwrap();
+        std::fs::write(docs.join("binary.md"), [0x66, 0x6f, 0xff, 0xfe]).unwrap();
+        std::fs::write(docs.join("skip.txt"), "not markdown").unwrap();
+
+        let db_path = dir.path().join("plan.db");
+        let before = std::fs::read(&db_path).unwrap();
+        let plan = engine.plan_directory(&docs, "wiki").unwrap();
+        assert_eq!(std::fs::read(&db_path).unwrap(), before);
+        assert_eq!(engine.stats().total_documents, 1);
+        let seed_vectors = engine.stats().total_vectors;
+
+        assert_eq!(plan.documents.len(), 4);
+        let find = |suffix: &str| {
+            plan.documents
+                .iter()
+                .find(|d| d.path.ends_with(suffix))
+                .unwrap()
+        };
+        assert_eq!(find("sol.md").title, "SOL Notes");
+        assert_eq!(find("eth.md").title, "eth");
+        assert!(find("empty.md").error.as_deref().unwrap().contains("empty"));
+        let binary = find("binary.md");
+        assert!(binary.error.as_deref().unwrap().contains("UTF-8"));
+        assert_eq!(binary.size_bytes, 4);
+        assert_eq!(plan.accepted().count(), 2);
+        assert!(plan.summary().starts_with("4 documents: 2 accepted"));
+        assert_eq!(plan.estimated_seconds(), plan.total_tokens() as f64 / 100.0);
+
+        // Oversized content is rejected by the same check the store applies
+        let big = "x".repeat(10 * 1024 * 1024 + 1);
+        let batch = engine.plan_index(vec![("wiki", "big.md", "Big", big.as_str())]);
+        assert!(batch.documents[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("too large"));
+        assert!(engine
+            .index_document("wiki", "big.md", "Big", &big)
+            .is_err());
+
+        assert_eq!(engine.execute_plan(&plan).unwrap(), 2);
+        let stats = engine.stats();
+        assert_eq!(stats.total_documents, 3);
+        assert_eq!(stats.total_vectors, seed_vectors + plan.total_chunks());
+        let sol = engine
+            .get_by_path("wiki", &find("sol.md").path)
+            .unwrap()
+            .unwrap();
+        assert_eq!(sol.title, "SOL Notes");
+        assert!(engine
+            .get_by_path("wiki", &find("empty.md").path)
+            .unwrap()
+            .is_none());
+    }
+}
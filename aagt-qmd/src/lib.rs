@@ -10,7 +10,7 @@
 //!
 //! ## Quick Start (Phase 1: BM25/FTS5)
 //!
-//! ```rust
+//! ```rust,no_run
 //! use aagt_qmd::{QmdStore, Collection};
 //!
 //! # fn main() -> aagt_qmd::Result<()> {
@@ -101,35 +101,56 @@
 //! ## Features
 //!
 //! - **fts** (default): FTS5 full-text search (Phase 1)
-//! - **vector**: Vector similarity search + hybrid search (Phase 2)
-//! - **embeddings**: Alternative pure Rust embeddings via Candle (experimental)
+//! - **vector-index**: HNSW vector index + chunker; embeddings come from a shared
+//!   [`aagt_core::knowledge::rag::Embeddings`] passed in via [`HybridSearchConfig::with_embeddings`]
+//! - **embeddings**: Local Candle embedding model ([`Embedder`])
+//! - **vector**: `vector-index` + `embeddings` (Phase 2)
 //! - **full**: FTS + Vector (recommended for production)
 
 // Phase 1 modules (always available)
 pub mod agent_memory;
+pub mod backup;
 pub mod content_hash;
+pub mod embeddings;
 pub mod error;
+pub mod fts_query;
+pub mod metrics;
+pub mod model_cache;
 pub mod store;
 pub mod virtual_path;
 pub mod watcher;
 
 // Phase 2 modules (vector feature)
 pub mod hybrid_search;
+pub mod import;
+pub mod index_plan;
+pub mod rag_injector;
 pub mod rrf;
+pub mod sync;
+#[cfg(test)]
+mod test_support;
 
 // Phase 2 modules (vector feature)
-#[cfg(feature = "vector")]
+#[cfg(feature = "vector-index")]
 pub mod chunker;
-#[cfg(feature = "vector")]
+#[cfg(feature = "embeddings")]
 pub mod embedder;
-#[cfg(feature = "vector")]
+#[cfg(feature = "vector-index")]
 pub mod vector_store;
 
 // Re-exports: Phase 1
 pub use agent_memory::QmdMemory;
-pub use content_hash::{get_docid, hash_content, normalize_docid, validate_docid};
+pub use backup::{BackupFile, BackupManifest, ScheduledBackup};
+pub use content_hash::{get_docid, hash_content, hash_file, normalize_docid, validate_docid};
+pub use embeddings::BlockingEmbeddings;
 pub use error::{QmdError, Result};
-pub use store::{Collection, Document, QmdStore, SearchResult, StoreStats};
+pub use fts_query::FtsQuery;
+pub use metrics::{OperationStats, QueryMetrics};
+pub use model_cache::{DownloadProgress, ModelCache};
+pub use store::{
+    Collection, Document, MigrationPolicy, QmdStore, SearchResult, StoreStats, CURRENT_RECORD_VERSION,
+    MAX_CONTENT_SIZE,
+};
 pub use virtual_path::VirtualPath;
 pub use watcher::FileWatcher;
 
@@ -137,15 +158,25 @@ pub use watcher::FileWatcher;
 pub use hybrid_search::{
     HybridSearchConfig, HybridSearchEngine, HybridSearchResult, HybridSearchStats,
 };
+pub use import::{
+    ExportSource, ImportPlan, ImportProgress, ImportReport, ImportStatus, ImportedConversation,
+    ImportedMessage, Importer,
+};
+pub use index_plan::{EmbeddingThroughput, IndexPlan, PlannedDocument};
+pub use rag_injector::QmdRagInjector;
 pub use rrf::{FusedResult, RrfConfig, RrfFusion};
+pub use sync::SyncReport;
 
 // Re-exports: Phase 2
-#[cfg(feature = "vector")]
+#[cfg(feature = "vector-index")]
 pub use chunker::{Chunk, ChunkStats, Chunker, ChunkerConfig};
-#[cfg(feature = "vector")]
-pub use embedder::{Embedder, EmbedderConfig};
-#[cfg(feature = "vector")]
-pub use vector_store::{VectorEntry, VectorSearchResult, VectorStore};
+#[cfg(feature = "embeddings")]
+pub use embedder::{Embedder, EmbedderConfig, ModelSource};
+#[cfg(feature = "vector-index")]
+pub use vector_store::{
+    ColdScanPolicy, TierStats, TieringConfig, VectorEntry, VectorSearchResult, VectorStore,
+    VectorStoreConfig,
+};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,112 @@
+//! Query metrics registry for QmdStore
+//!
+//! Records per-operation timings so slow statements can be identified
+//! without attaching a profiler to the SQLite database.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Aggregated timings for a single logical operation
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OperationStats {
+    /// Number of times the operation ran
+    pub count: u64,
+    /// Number of runs that exceeded the slow-query threshold
+    pub slow_count: u64,
+    /// Number of runs that returned an error
+    pub error_count: u64,
+    /// Sum of all durations in microseconds
+    pub total_micros: u64,
+    /// Longest observed duration in microseconds
+    pub max_micros: u64,
+}
+
+impl OperationStats {
+    /// Mean duration across all runs
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_micros(self.total_micros / self.count)
+    }
+}
+
+/// Thread-safe registry of query timings keyed by operation name
+#[derive(Debug, Default)]
+pub struct QueryMetrics {
+    operations: Mutex<HashMap<&'static str, OperationStats>>,
+}
+
+impl QueryMetrics {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a single run of an operation
+    pub fn record(&self, operation: &'static str, elapsed: Duration, slow: bool, failed: bool) {
+        let micros = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        if let Ok(mut ops) = self.operations.lock() {
+            let stats = ops.entry(operation).or_default();
+            stats.count += 1;
+            stats.total_micros = stats.total_micros.saturating_add(micros);
+            stats.max_micros = stats.max_micros.max(micros);
+            if slow {
+                stats.slow_count += 1;
+            }
+            if failed {
+                stats.error_count += 1;
+            }
+        }
+    }
+
+    /// Get stats for a single operation
+    pub fn get(&self, operation: &str) -> Option<OperationStats> {
+        self.operations
+            .lock()
+            .ok()
+            .and_then(|ops| ops.get(operation).cloned())
+    }
+
+    /// Copy of all recorded stats
+    pub fn snapshot(&self) -> HashMap<String, OperationStats> {
+        self.operations
+            .lock()
+            .map(|ops| {
+                ops.iter()
+                    .map(|(k, v)| (k.to_string(), v.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Clear all recorded stats
+    pub fn reset(&self) {
+        if let Ok(mut ops) = self.operations.lock() {
+            ops.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_aggregates() {
+        let metrics = QueryMetrics::new();
+        metrics.record("search_fts", Duration::from_micros(100), false, false);
+        metrics.record("search_fts", Duration::from_micros(300), true, false);
+
+        let stats = metrics.get("search_fts").unwrap();
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.slow_count, 1);
+        assert_eq!(stats.max_micros, 300);
+        assert_eq!(stats.mean(), Duration::from_micros(200));
+
+        metrics.reset();
+        assert!(metrics.get("search_fts").is_none());
+    }
+}
@@ -0,0 +1,409 @@
+//! Local cache for model files fetched from the Hugging Face Hub
+//!
+//! Files land under `<cache>/models--<org>--<name>/snapshots/<revision>/`,
+//! with the cache root taken from `HF_HUB_CACHE` or `HF_HOME` like the Hub
+//! tooling does. Downloads go to a `.part` file first, resume from it after an
+//! interruption, and are checked against a SHA-256 digest before being moved
+//! into place.
+
+use crate::content_hash::hash_file;
+use crate::error::{QmdError, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Hub endpoint used unless `HF_ENDPOINT` says otherwise
+pub const DEFAULT_HF_ENDPOINT: &str = "https://huggingface.co";
+
+/// Progress callback payload
+#[derive(Debug, Clone)]
+pub struct DownloadProgress {
+    /// File being downloaded, relative to the repository root
+    pub filename: String,
+    /// Bytes on disk so far, including any resumed prefix
+    pub downloaded: u64,
+    /// Full size, when the server reports it
+    pub total: Option<u64>,
+}
+
+type ProgressFn = Arc<dyn Fn(&DownloadProgress) + Send + Sync>;
+
+/// Downloads Hub files once and serves them from disk afterwards
+#[derive(Clone)]
+pub struct ModelCache {
+    root: PathBuf,
+    endpoint: String,
+    token: Option<String>,
+    offline: bool,
+    progress: Option<ProgressFn>,
+}
+
+impl std::fmt::Debug for ModelCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModelCache")
+            .field("root", &self.root)
+            .field("endpoint", &self.endpoint)
+            .field("offline", &self.offline)
+            .finish()
+    }
+}
+
+impl ModelCache {
+    /// Cache rooted at `root`, talking to the public Hub
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            endpoint: DEFAULT_HF_ENDPOINT.to_string(),
+            token: None,
+            offline: false,
+            progress: None,
+        }
+    }
+
+    /// Cache configured like the Hub tooling: `HF_HUB_CACHE`/`HF_HOME` for the
+    /// location, `HF_ENDPOINT`, `HF_TOKEN` and `HF_HUB_OFFLINE`
+    pub fn from_env() -> Self {
+        let mut cache = Self::new(default_cache_dir());
+        if let Ok(endpoint) = std::env::var("HF_ENDPOINT") {
+            cache = cache.with_endpoint(endpoint);
+        }
+        cache.token = std::env::var("HF_TOKEN").ok().filter(|t| !t.is_empty());
+        cache.offline = std::env::var("HF_HUB_OFFLINE")
+            .map(|v| matches!(v.as_str(), "1" | "true" | "TRUE" | "yes"))
+            .unwrap_or(false);
+        cache
+    }
+
+    /// Keep files under `root` instead of the default cache directory
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    /// Fetch from another Hub (or a mirror) instead of [`DEFAULT_HF_ENDPOINT`]
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Never touch the network; missing files fail with [`QmdError::ModelNotCached`]
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Called as download chunks arrive (progress is also logged at info level)
+    pub fn with_progress(
+        mut self,
+        progress: impl Fn(&DownloadProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Cache root
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Where `filename` from `repo` at `revision` is (or will be) stored
+    pub fn path(&self, repo: &str, revision: &str, filename: &str) -> PathBuf {
+        self.root
+            .join(format!("models--{}", repo.replace('/', "--")))
+            .join("snapshots")
+            .join(revision)
+            .join(filename)
+    }
+
+    /// Path of `filename`, downloading it first unless it is already cached
+    ///
+    /// `sha256` (hex) is checked on download; cached files are trusted as-is.
+    pub fn fetch(
+        &self,
+        repo: &str,
+        revision: &str,
+        filename: &str,
+        sha256: Option<&str>,
+    ) -> Result<PathBuf> {
+        let path = self.path(repo, revision, filename);
+        if path.is_file() {
+            tracing::debug!("Model file cache hit: {}", path.display());
+            return Ok(path);
+        }
+        if self.offline {
+            return Err(QmdError::ModelNotCached(path));
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let partial = PathBuf::from(format!("{}.part", path.display()));
+        let url = format!("{}/{}/resolve/{}/{}", self.endpoint, repo, revision, filename);
+        self.download(&url, filename, &partial)?;
+
+        if let Some(expected) = sha256 {
+            let actual = hash_file(&partial)?;
+            if !actual.eq_ignore_ascii_case(expected) {
+                // A resumed prefix may be what is corrupt, so start over next time
+                std::fs::remove_file(&partial)?;
+                return Err(QmdError::ChecksumMismatch {
+                    file: filename.to_string(),
+                    expected: expected.to_lowercase(),
+                    actual,
+                });
+            }
+        }
+        std::fs::rename(&partial, &path)?;
+        tracing::info!("Cached {} at {}", filename, path.display());
+        Ok(path)
+    }
+
+    /// Download `url` into `partial`, continuing from whatever it already holds
+    fn download(&self, url: &str, filename: &str, partial: &Path) -> Result<()> {
+        let offset = std::fs::metadata(partial).map(|m| m.len()).unwrap_or(0);
+        let response = self.get(url, offset)?;
+
+        let resumed = response.status() == 206;
+        let remaining: Option<u64> = response
+            .header("Content-Length")
+            .and_then(|len| len.parse().ok());
+        let mut downloaded = if resumed { offset } else { 0 };
+        let total = remaining.map(|len| len + downloaded);
+        if resumed {
+            tracing::info!("Resuming {} at {} bytes", url, offset);
+        } else {
+            tracing::info!("Downloading {}", url);
+        }
+
+        let mut file = if resumed {
+            OpenOptions::new().append(true).open(partial)?
+        } else {
+            File::create(partial)?
+        };
+        let mut reader = response.into_reader();
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut logged_decile = 0;
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read])?;
+            downloaded += read as u64;
+
+            if let Some(total) = total.filter(|&t| t > 0) {
+                let decile = downloaded * 10 / total;
+                if decile > logged_decile {
+                    logged_decile = decile;
+                    tracing::info!("{}: {}% of {} bytes", filename, decile * 10, total);
+                }
+            }
+            if let Some(callback) = &self.progress {
+                callback(&DownloadProgress {
+                    filename: filename.to_string(),
+                    downloaded,
+                    total,
+                });
+            }
+        }
+        file.sync_all()?;
+
+        if let Some(total) = total {
+            if downloaded != total {
+                return Err(QmdError::ModelDownload(format!(
+                    "{}: connection closed after {} of {} bytes",
+                    url, downloaded, total
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// GET `url` from byte `offset` on; the caller checks for 206 to know if it resumed
+    fn get(&self, url: &str, offset: u64) -> Result<ureq::Response> {
+        let mut request = ureq::get(url);
+        if offset > 0 {
+            request = request.set("Range", &format!("bytes={}-", offset));
+        }
+        if let Some(token) = &self.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+        match request.call() {
+            Ok(response) => Ok(response),
+            // The server no longer accepts our prefix (e.g. the file shrank): refetch it whole
+            Err(ureq::Error::Status(416, _)) if offset > 0 => self.get(url, 0),
+            Err(e) => Err(QmdError::ModelDownload(format!("{}: {}", url, e))),
+        }
+    }
+}
+
+/// `HF_HUB_CACHE`, else `$HF_HOME/hub`, else `~/.cache/huggingface/hub`
+pub fn default_cache_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var("HF_HUB_CACHE") {
+        return PathBuf::from(dir);
+    }
+    let home = std::env::var("HF_HOME").map(PathBuf::from).unwrap_or_else(|_| {
+        std::env::var("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| {
+                PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string()))
+                    .join(".cache")
+            })
+            .join("huggingface")
+    });
+    home.join("hub")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use std::io::BufRead;
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    const WEIGHTS: &[u8] = b"tiny safetensors fixture for the model cache tests";
+
+    /// Serves `WEIGHTS` for every path, honouring `Range: bytes=N-`
+    struct FixtureServer {
+        endpoint: String,
+        requests: Arc<AtomicUsize>,
+        ranges: Arc<Mutex<Vec<String>>>,
+    }
+
+    fn serve() -> FixtureServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let (count, seen) = (requests.clone(), ranges.clone());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                count.fetch_add(1, Ordering::SeqCst);
+                let mut offset = 0usize;
+                let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(range) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                        let range = range.trim().trim_end_matches('-').to_string();
+                        offset = range.parse().unwrap();
+                        seen.lock().unwrap().push(range);
+                    }
+                }
+                let (status, body) = if offset > 0 {
+                    ("206 Partial Content", &WEIGHTS[offset..])
+                } else {
+                    ("200 OK", WEIGHTS)
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+        FixtureServer {
+            endpoint,
+            requests,
+            ranges,
+        }
+    }
+
+    fn digest() -> String {
+        hex::encode(Sha256::digest(WEIGHTS))
+    }
+
+    #[test]
+    fn test_fetch_downloads_once_then_hits_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = serve();
+        let seen = Arc::new(AtomicUsize::new(0));
+        let progress = seen.clone();
+        let cache = ModelCache::new(dir.path())
+            .with_endpoint(&server.endpoint)
+            .with_progress(move |p| {
+                assert_eq!(p.total, Some(WEIGHTS.len() as u64));
+                progress.store(p.downloaded as usize, Ordering::SeqCst);
+            });
+
+        let path = cache
+            .fetch("sentence-transformers/all-MiniLM-L6-v2", "main", "model.safetensors", Some(&digest()))
+            .unwrap();
+        assert_eq!(
+            path,
+            dir.path()
+                .join("models--sentence-transformers--all-MiniLM-L6-v2/snapshots/main/model.safetensors")
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), WEIGHTS);
+        assert_eq!(seen.load(Ordering::SeqCst), WEIGHTS.len());
+
+        let again = cache
+            .fetch("sentence-transformers/all-MiniLM-L6-v2", "main", "model.safetensors", Some(&digest()))
+            .unwrap();
+        assert_eq!(again, path);
+        assert_eq!(server.requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_checksum_mismatch_discards_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = serve();
+        let cache = ModelCache::new(dir.path()).with_endpoint(&server.endpoint);
+
+        let wrong = "0".repeat(64);
+        let err = cache
+            .fetch("org/model", "main", "model.safetensors", Some(&wrong))
+            .unwrap_err();
+        match err {
+            QmdError::ChecksumMismatch { file, expected, actual } => {
+                assert_eq!(file, "model.safetensors");
+                assert_eq!(expected, wrong);
+                assert_eq!(actual, digest());
+            }
+            other => panic!("expected checksum mismatch, got {}", other),
+        }
+        let path = cache.path("org/model", "main", "model.safetensors");
+        assert!(!path.exists());
+        assert!(!PathBuf::from(format!("{}.part", path.display())).exists());
+    }
+
+    #[test]
+    fn test_partial_download_resumes() {
+        let dir = tempfile::tempdir().unwrap();
+        let server = serve();
+        let cache = ModelCache::new(dir.path()).with_endpoint(&server.endpoint);
+
+        let path = cache.path("org/model", "main", "model.safetensors");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(format!("{}.part", path.display()), &WEIGHTS[..10]).unwrap();
+
+        cache
+            .fetch("org/model", "main", "model.safetensors", Some(&digest()))
+            .unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), WEIGHTS);
+        assert_eq!(*server.ranges.lock().unwrap(), vec!["10".to_string()]);
+    }
+
+    #[test]
+    fn test_offline_names_expected_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ModelCache::new(dir.path()).offline(true);
+
+        let err = cache
+            .fetch("org/model", "main", "model.safetensors", None)
+            .unwrap_err();
+        let expected = cache.path("org/model", "main", "model.safetensors");
+        assert!(matches!(&err, QmdError::ModelNotCached(path) if *path == expected));
+        assert!(err.to_string().contains(&expected.display().to_string()));
+    }
+}
@@ -0,0 +1,232 @@
+//! Retrieval-augmented context from a QMD collection
+//!
+//! [`QmdRagInjector`] is a [`ContextInjector`] that searches one collection for
+//! the latest user message and injects the top results, with their virtual
+//! paths (and the section they matched in, for markdown-chunked documents),
+//! as a single system message.
+
+use std::sync::Arc;
+
+use aagt_core::agent::context::ContextInjector;
+use aagt_core::agent::message::{Message, Role};
+use aagt_core::error::{Error, Result};
+
+use crate::fts_query::FtsQuery;
+use crate::hybrid_search::{HybridSearchEngine, HybridSearchResult};
+use crate::virtual_path::VirtualPath;
+
+/// Characters of document body used when a result has no BM25 snippet
+const FALLBACK_SNIPPET_CHARS: usize = 300;
+
+/// Injects the top-k documents of a collection matching the latest user message
+pub struct QmdRagInjector {
+    engine: Arc<HybridSearchEngine>,
+    collection: String,
+    k: usize,
+    max_chars: usize,
+}
+
+impl QmdRagInjector {
+    /// Search `collection` for up to `k` documents, injecting at most `max_chars` characters
+    pub fn new(
+        engine: Arc<HybridSearchEngine>,
+        collection: impl Into<String>,
+        k: usize,
+        max_chars: usize,
+    ) -> Self {
+        Self {
+            engine,
+            collection: collection.into(),
+            k,
+            max_chars,
+        }
+    }
+
+    /// Render results in rank order, dropping lower-ranked ones that don't fit in `max_chars`
+    fn render(&self, results: &[HybridSearchResult]) -> Option<String> {
+        let mut text = format!("Relevant documents from '{}':", self.collection);
+        let mut included = 0;
+        for result in results {
+            let doc = &result.document;
+            let snippet = match &result.snippet {
+                Some(snippet) => snippet.replace("<mark>", "").replace("</mark>", ""),
+                None => doc
+                    .body
+                    .as_deref()
+                    .unwrap_or_default()
+                    .chars()
+                    .take(FALLBACK_SNIPPET_CHARS)
+                    .collect(),
+            };
+            let section = result
+                .heading_path
+                .as_ref()
+                .map(|path| format!(" \u{2014} {}", path))
+                .unwrap_or_default();
+            let entry = format!(
+                "\n\n[{}] {} ({}){}\n{}",
+                included + 1,
+                VirtualPath::build(&doc.collection, &doc.path),
+                doc.title,
+                section,
+                snippet.trim()
+            );
+
+            let room = self.max_chars.saturating_sub(text.chars().count());
+            if entry.chars().count() <= room {
+                text.push_str(&entry);
+                included += 1;
+            } else {
+                // The best match is worth a cut-off snippet; lower ranks are dropped whole
+                if included == 0 && room > 0 {
+                    text.extend(entry.chars().take(room));
+                    included += 1;
+                }
+                break;
+            }
+        }
+        (included > 0).then_some(text)
+    }
+}
+
+#[async_trait::async_trait]
+impl ContextInjector for QmdRagInjector {
+    /// Nothing to search for without a conversation
+    async fn inject(&self) -> Result<Vec<Message>> {
+        Ok(Vec::new())
+    }
+
+    async fn inject_with_history(&self, history: &[Message]) -> Result<Vec<Message>> {
+        let Some(last) = history.iter().rev().find(|m| m.role == Role::User) else {
+            return Ok(Vec::new());
+        };
+        let query = last.text();
+        if FtsQuery::parse(&query).is_empty() || self.k == 0 || self.max_chars == 0 {
+            return Ok(Vec::new());
+        }
+
+        // SQLite and the embedder block, so keep them off the async workers
+        let engine = Arc::clone(&self.engine);
+        let collection = self.collection.clone();
+        let k = self.k;
+        let results = tokio::task::spawn_blocking(move || {
+            engine.search_in_collection(&query, &collection, k)
+        })
+        .await
+        .map_err(|e| Error::Internal(format!("QMD search task failed: {}", e)))?
+        .map_err(|e| Error::Internal(format!("QMD search failed: {}", e)))?;
+
+        Ok(self
+            .render(&results)
+            .map(Message::system)
+            .into_iter()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_search::HybridSearchConfig;
+    use crate::store::{Collection, QmdStore};
+    use tempfile::TempDir;
+
+    fn engine(temp_dir: &TempDir) -> Arc<HybridSearchEngine> {
+        let db_path = temp_dir.path().join("rag.db");
+        {
+            let store = QmdStore::new(&db_path).unwrap();
+            for name in ["trading", "empty"] {
+                store
+                    .create_collection(Collection {
+                        name: name.to_string(),
+                        description: None,
+                        glob_pattern: "**/*.md".to_string(),
+                        root_path: None,
+                    })
+                    .unwrap();
+            }
+            store
+                .store_document(
+                    "trading",
+                    "strategies/sol.md",
+                    "SOL Strategy",
+                    "Buy SOL when RSI drops below 30 and sell above 70.",
+                )
+                .unwrap();
+            store
+                .store_document(
+                    "trading",
+                    "notes/btc.md",
+                    "BTC Notes",
+                    "BTC halving cycles drive long-term trends.",
+                )
+                .unwrap();
+            store
+                .store_document(
+                    "trading",
+                    "notes/risk.md",
+                    "Risk Notes",
+                    "Cut size or hedge when volatility spikes.",
+                )
+                .unwrap();
+        }
+
+        let config = HybridSearchConfig {
+            db_path,
+            ..Default::default()
+        };
+        let config = crate::test_support::with_test_embeddings(config, temp_dir.path());
+        Arc::new(HybridSearchEngine::new(config).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_injects_snippets_for_latest_user_message() {
+        let temp_dir = TempDir::new().unwrap();
+        let engine = engine(&temp_dir);
+        let history = [
+            Message::user("How are BTC cycles?"),
+            Message::assistant("They follow halvings."),
+            Message::user("What's my SOL entry rule?"),
+        ];
+
+        let injector = QmdRagInjector::new(engine.clone(), "trading", 3, 2000);
+        let messages = injector.inject_with_history(&history).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].role, Role::System);
+        let text = messages[0].text();
+        assert!(
+            text.contains("[1] aagt://trading/strategies/sol.md (SOL Strategy)"),
+            "{}",
+            text
+        );
+        assert!(text.contains("RSI drops below 30"), "{}", text);
+        assert!(!text.contains("btc.md"), "{}", text);
+        // The user's words are searched, not an OR-joined query with "or" as a term
+        assert!(!text.contains("risk.md"), "{}", text);
+
+        // Too small a budget keeps only the top result, cut short
+        let injector = QmdRagInjector::new(engine.clone(), "trading", 3, 60);
+        let text = injector.inject_with_history(&history).await.unwrap()[0].text();
+        assert_eq!(text.chars().count(), 60);
+
+        // Markdown-chunked hits name the section they matched in
+        let mut results = engine.search_in_collection("SOL", "trading", 1).unwrap();
+        results[0].heading_path = Some("## Strategy > ### Entry rules".to_string());
+        let text = QmdRagInjector::new(engine.clone(), "trading", 3, 2000)
+            .render(&results)
+            .unwrap();
+        assert!(
+            text.contains("(SOL Strategy) \u{2014} ## Strategy > ### Entry rules\n"),
+            "{}",
+            text
+        );
+
+        // An empty collection injects nothing
+        let injector = QmdRagInjector::new(engine, "empty", 3, 2000);
+        assert!(injector
+            .inject_with_history(&history)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
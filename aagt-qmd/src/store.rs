@@ -1,10 +1,22 @@
 use crate::content_hash::{get_docid, hash_content, normalize_docid, validate_docid};
 use crate::error::{QmdError, Result};
-use chrono::Utc;
-use rusqlite::{params, Connection, OptionalExtension};
+use crate::fts_query::FtsQuery;
+use crate::metrics::QueryMetrics;
+use aagt_core::agent::session::{SessionQuery, SessionSummary, META_USER_ID};
+use aagt_core::infra::encryption::{
+    self, EncryptedStore, EncryptionConfig, EncryptionProvider, RewrapProgress,
+};
+use aagt_core::infra::response_format::DocidResolver;
+use aagt_core::infra::instance::{InstanceLock, InstanceMode};
+use chrono::{DateTime, Utc};
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{params, params_from_iter, Connection, OpenFlags, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use tracing::{debug, info};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 
 /// Document metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +32,37 @@ pub struct Document {
     pub created_at: String,
     pub modified_at: String,
     pub active: bool,
+    /// Record format version (rows written before versioning are 1)
+    #[serde(default = "default_record_version")]
+    pub record_version: i64,
+    /// Where the content came from (v2), e.g. `watcher`, `agent:analyst`, `tool:web_fetch`
+    #[serde(default)]
+    pub source: Option<String>,
+    /// When the source material was produced or fetched (v2)
+    #[serde(default)]
+    pub source_time: Option<String>,
+}
+
+fn default_record_version() -> i64 {
+    1
+}
+
+/// Record format version written by this build
+///
+/// - v1: the original document row
+/// - v2: adds `source` (provenance) and `source_time`
+pub const CURRENT_RECORD_VERSION: i64 = 2;
+
+/// When older document records are upgraded to [`CURRENT_RECORD_VERSION`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationPolicy {
+    /// Upgrade records whenever they are rewritten and during `vacuum`
+    #[default]
+    Lazy,
+    /// Keep each record's version until `migrate_in_place` is called
+    /// (for fleets where older binaries still read the database)
+    Manual,
 }
 
 /// Search result with score
@@ -45,9 +88,165 @@ use std::sync::Mutex;
 pub struct QmdStore {
     conn: Mutex<Connection>,
     db_path: PathBuf,
+    metrics: Arc<QueryMetrics>,
+    slow_query_threshold: Option<Duration>,
+    read_only: bool,
+    encryption: Option<Arc<dyn EncryptionProvider>>,
+    migration_policy: MigrationPolicy,
 }
 
-const MAX_CONTENT_SIZE: usize = 10 * 1024 * 1024; // 10MB limit
+/// Associated data binding encrypted session blobs to the sessions table
+const SESSION_AAD: &[u8] = b"qmd_sessions";
+
+/// Associated data binding encrypted session metadata to the sessions table
+const SESSION_META_AAD: &[u8] = b"qmd_session_metadata";
+
+/// Keyed-hash context for the `user_id` column of encrypted sessions
+const SESSION_USER_CONTEXT: &[u8] = b"qmd_session_user_id";
+
+/// Largest document body the store accepts, in bytes
+pub const MAX_CONTENT_SIZE: usize = 10 * 1024 * 1024; // 10MB limit
+
+/// How long a read-only store waits for the writer's exclusive locks
+const READ_ONLY_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Wait before retrying a backup step that found the source busy
+const BACKUP_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Queries slower than this are logged at warn level by default
+pub const DEFAULT_SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(250);
+
+/// Max characters of a parameter shown in slow-query logs
+const MAX_PARAM_SUMMARY_CHARS: usize = 64;
+
+const SQL_FIND_EXISTING: &str = "SELECT id, hash, record_version FROM documents
+     WHERE collection = ? AND path = ?";
+
+const SQL_GET_BY_PATH: &str =
+    "SELECT d.id, d.collection, d.path, d.title, d.hash, d.created_at, d.modified_at,
+            d.active, c.doc, d.summary, d.record_version, d.source, d.source_time
+     FROM documents d
+     JOIN content c ON d.hash = c.hash
+     WHERE d.collection = ? AND d.path = ? AND d.active = 1";
+
+// Path prefixes compare with substr() rather than LIKE/GLOB, so paths need no escaping
+const SQL_LIST_DOCUMENTS: &str =
+    "SELECT d.id, d.collection, d.path, d.title, d.hash, d.created_at, d.modified_at,
+            d.active, CASE WHEN ?3 THEN c.doc ELSE '' END, d.summary, d.record_version,
+            d.source, d.source_time
+     FROM documents d
+     JOIN content c ON d.hash = c.hash
+     WHERE d.collection = ?1 AND d.active = 1 AND substr(d.path, 1, length(?2)) = ?2
+     ORDER BY d.modified_at DESC, d.id DESC
+     LIMIT ?4 OFFSET ?5";
+
+const SQL_COUNT_DOCUMENTS: &str = "SELECT COUNT(*) FROM documents
+     WHERE collection = ?1 AND active = 1 AND substr(path, 1, length(?2)) = ?2";
+
+// GLOB (not LIKE) so the prefix scan can use idx_documents_hash: LIKE is
+// case-insensitive by default and SQLite only applies its range optimization
+// to it for NOCASE columns, while GLOB matches the BINARY collation of `hash`.
+const SQL_GET_BY_DOCID: &str =
+    "SELECT d.id, d.collection, d.path, d.title, d.hash, d.created_at, d.modified_at,
+            d.active, c.doc, d.summary, d.record_version, d.source, d.source_time
+     FROM documents d
+     JOIN content c ON d.hash = c.hash
+     WHERE d.hash GLOB ? AND d.active = 1
+     LIMIT 1";
+
+const SQL_SEARCH_FTS: &str =
+    "SELECT d.id, d.collection, d.path, d.title, d.hash, d.created_at, d.modified_at,
+            d.active, bm25(documents_fts) as score,
+            snippet(documents_fts, 2, '<mark>', '</mark>', '...', 32) as snippet,
+            d.summary, d.record_version, d.source, d.source_time
+     FROM documents d
+     JOIN documents_fts ON documents_fts.rowid = d.id
+     WHERE documents_fts MATCH ? AND d.active = 1
+     ORDER BY score
+     LIMIT ?";
+
+const SQL_SEARCH_FTS_IN_COLLECTION: &str =
+    "SELECT d.id, d.collection, d.path, d.title, d.hash, d.created_at, d.modified_at,
+            d.active, bm25(documents_fts) as score,
+            snippet(documents_fts, 2, '<mark>', '</mark>', '...', 32) as snippet,
+            d.summary, d.record_version, d.source, d.source_time
+     FROM documents d
+     JOIN documents_fts ON documents_fts.rowid = d.id
+     WHERE documents_fts MATCH ? AND d.collection = ? AND d.active = 1
+     ORDER BY score
+     LIMIT ?";
+
+// As-of queries read document_history: a revision is current at `as_of` if it is
+// the newest revision of its (collection, path) with modified_ts <= as_of.
+const SQL_GET_BY_PATH_AS_OF: &str =
+    "SELECT h.doc_id, h.collection, h.path, h.title, h.hash, h.created_at, h.modified_at,
+            1, c.doc, NULL, h.record_version, h.source, h.source_time
+     FROM document_history h
+     JOIN content c ON h.hash = c.hash
+     WHERE h.collection = ? AND h.path = ? AND h.modified_ts <= ?
+     ORDER BY h.modified_ts DESC, h.id DESC
+     LIMIT 1";
+
+const SQL_GET_BY_DOCID_AS_OF: &str =
+    "SELECT h.doc_id, h.collection, h.path, h.title, h.hash, h.created_at, h.modified_at,
+            1, c.doc, NULL, h.record_version, h.source, h.source_time
+     FROM document_history h
+     JOIN content c ON h.hash = c.hash
+     WHERE h.hash GLOB ?1 AND h.modified_ts <= ?2
+       AND NOT EXISTS (
+           SELECT 1 FROM document_history n
+           WHERE n.collection = h.collection AND n.path = h.path AND n.modified_ts <= ?2
+             AND (n.modified_ts > h.modified_ts OR (n.modified_ts = h.modified_ts AND n.id > h.id)))
+     LIMIT 1";
+
+const SQL_SEARCH_FTS_AS_OF: &str =
+    "SELECT h.doc_id, h.collection, h.path, h.title, h.hash, h.created_at, h.modified_at,
+            1, bm25(history_fts) as score,
+            snippet(history_fts, 2, '<mark>', '</mark>', '...', 32) as snippet,
+            NULL, h.record_version, h.source, h.source_time
+     FROM document_history h
+     JOIN history_fts ON history_fts.rowid = h.id
+     WHERE history_fts MATCH ?1 AND h.modified_ts <= ?2
+       AND NOT EXISTS (
+           SELECT 1 FROM document_history n
+           WHERE n.collection = h.collection AND n.path = h.path AND n.modified_ts <= ?2
+             AND (n.modified_ts > h.modified_ts OR (n.modified_ts = h.modified_ts AND n.id > h.id)))
+     ORDER BY score
+     LIMIT ?3";
+
+const SQL_LOAD_SESSION: &str = "SELECT data FROM sessions WHERE id = ?";
+
+/// Messages FTS5 uses when it can't parse a MATCH expression
+///
+/// `no such column` covers column filters like `title:RSI` on a column the
+/// index doesn't have.
+const FTS5_QUERY_ERRORS: &[&str] = &[
+    "fts5: syntax error",
+    "fts5: parser stack overflow",
+    "unterminated string",
+    "unknown special query",
+    "no such column",
+];
+
+/// Whether FTS5 refused the MATCH expression itself, rather than the database failing
+fn is_query_error(e: &rusqlite::Error) -> bool {
+    match e {
+        rusqlite::Error::SqliteFailure(f, Some(message)) if f.code == rusqlite::ErrorCode::Unknown => {
+            FTS5_QUERY_ERRORS.iter().any(|prefix| message.starts_with(prefix))
+        }
+        _ => false,
+    }
+}
+
+/// Truncate a parameter for logging so document content never ends up in logs
+fn summarize_param(value: &str) -> String {
+    if value.chars().count() <= MAX_PARAM_SUMMARY_CHARS {
+        value.to_string()
+    } else {
+        let truncated: String = value.chars().take(MAX_PARAM_SUMMARY_CHARS).collect();
+        format!("{}... ({} bytes)", truncated, value.len())
+    }
+}
 
 impl QmdStore {
     /// Create or open a QMD store at the given path
@@ -64,11 +263,231 @@ impl QmdStore {
         let store = Self {
             conn: Mutex::new(conn),
             db_path,
+            metrics: Arc::new(QueryMetrics::new()),
+            slow_query_threshold: Some(DEFAULT_SLOW_QUERY_THRESHOLD),
+            read_only: false,
+            encryption: None,
+            migration_policy: MigrationPolicy::default(),
         };
         store.init_schema()?;
+
+        if let Some(min) = store.min_record_version()? {
+            if min < CURRENT_RECORD_VERSION {
+                warn!(
+                    "QMD store has v{} document records (current is v{}); run migrate_in_place to upgrade them",
+                    min, CURRENT_RECORD_VERSION
+                );
+            }
+        }
         Ok(store)
     }
 
+    /// Open an existing store read-only, e.g. from a dashboard process while
+    /// the agent process owns writes.
+    ///
+    /// Consistency model: the store runs in WAL mode, so every query reads a
+    /// snapshot of all transactions committed before it started. There is no
+    /// cached index, so staleness is bounded by the duration of the query
+    /// itself, and a write is never observed half-applied. Readers and the
+    /// writer do not block each other; the rare exclusive operations on the
+    /// writer side (checkpoints, `vacuum`) are waited out via a busy timeout.
+    ///
+    /// All mutating methods return [`QmdError::ReadOnly`].
+    pub fn open_read_only(db_path: impl Into<PathBuf>) -> Result<Self> {
+        let db_path = db_path.into();
+        info!("Opening QMD store read-only at: {:?}", db_path);
+
+        if !db_path.exists() {
+            return Err(QmdError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("QMD store not found at {:?}", db_path),
+            )));
+        }
+
+        let conn = Connection::open_with_flags(
+            &db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+                | OpenFlags::SQLITE_OPEN_URI
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        conn.busy_timeout(READ_ONLY_BUSY_TIMEOUT)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+            db_path,
+            metrics: Arc::new(QueryMetrics::new()),
+            slow_query_threshold: Some(DEFAULT_SLOW_QUERY_THRESHOLD),
+            read_only: true,
+            encryption: None,
+            migration_policy: MigrationPolicy::default(),
+        })
+    }
+
+    /// Open as writer or read-only replica depending on the instance's role
+    pub fn open_for_instance(
+        db_path: impl Into<PathBuf>,
+        lock: &InstanceLock,
+    ) -> Result<Self> {
+        match lock.mode() {
+            InstanceMode::Writer => Self::new(db_path),
+            InstanceMode::ReadOnly => Self::open_read_only(db_path),
+        }
+    }
+
+    /// Whether this store was opened with [`QmdStore::open_read_only`]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn ensure_writable(&self, operation: &'static str) -> Result<()> {
+        if self.read_only {
+            return Err(QmdError::ReadOnly(operation));
+        }
+        Ok(())
+    }
+
+    /// Set the slow-query threshold (`None` disables slow-query logging)
+    pub fn with_slow_query_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.slow_query_threshold = threshold;
+        self
+    }
+
+    /// Encrypt session data at rest
+    ///
+    /// Plaintext sessions written before encryption was enabled still load and
+    /// are encrypted the next time they are stored.
+    /// Document content and embeddings stay plaintext: the FTS and vector
+    /// indexes need them as written.
+    pub fn with_encryption(mut self, provider: Arc<dyn EncryptionProvider>) -> Self {
+        self.encryption = Some(provider);
+        self
+    }
+
+    /// Encrypt session data if `config` enables it for sessions
+    pub fn with_encryption_config(
+        mut self,
+        config: &EncryptionConfig,
+        provider: &Arc<dyn EncryptionProvider>,
+    ) -> Self {
+        self.encryption = config.provider_for(EncryptedStore::Sessions, provider);
+        self
+    }
+
+    /// Set when older document records are upgraded
+    pub fn with_migration_policy(mut self, policy: MigrationPolicy) -> Self {
+        self.migration_policy = policy;
+        self
+    }
+
+    /// Share an existing metrics registry with this store
+    pub fn with_metrics(mut self, metrics: Arc<QueryMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Query metrics recorded by this store
+    pub fn metrics(&self) -> Arc<QueryMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Run `f` against the connection, recording its duration under `operation`.
+    ///
+    /// `params` is only evaluated when the query is slow, and must summarize
+    /// the parameters rather than include document content.
+    fn timed<T>(
+        &self,
+        operation: &'static str,
+        params: impl FnOnce() -> String,
+        f: impl FnOnce(&mut Connection) -> Result<T>,
+    ) -> Result<T> {
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+
+        let start = Instant::now();
+        let result = f(&mut conn);
+        let elapsed = start.elapsed();
+        drop(conn);
+
+        let slow = self
+            .slow_query_threshold
+            .is_some_and(|threshold| elapsed >= threshold);
+        if slow {
+            warn!(
+                operation,
+                elapsed_ms = elapsed.as_secs_f64() * 1000.0,
+                params = %params(),
+                "Slow QMD query"
+            );
+        }
+        self.metrics
+            .record(operation, elapsed, slow, result.is_err());
+
+        result
+    }
+
+    /// Run EXPLAIN QUERY PLAN for one of the canned store statements.
+    ///
+    /// Supported operations: `store_document`, `get_by_path`, `get_by_docid`,
+    /// `list_documents`, `count_documents`, `search_fts`,
+    /// `search_fts_in_collection`, `load_session` and the as-of
+    /// variants `get_by_path_as_of`, `get_by_docid_as_of`, `search_fts_as_of`.
+    /// Sample params are bound positionally; the docid lookups take a docid and
+    /// convert it to the same prefix pattern the real lookup uses.
+    pub fn explain(&self, operation: &str, sample_params: &[&str]) -> Result<String> {
+        let sql = match operation {
+            "store_document" => SQL_FIND_EXISTING,
+            "get_by_path" => SQL_GET_BY_PATH,
+            "get_by_docid" => SQL_GET_BY_DOCID,
+            "list_documents" => SQL_LIST_DOCUMENTS,
+            "count_documents" => SQL_COUNT_DOCUMENTS,
+            "search_fts" => SQL_SEARCH_FTS,
+            "search_fts_in_collection" => SQL_SEARCH_FTS_IN_COLLECTION,
+            "load_session" => SQL_LOAD_SESSION,
+            "get_by_path_as_of" => SQL_GET_BY_PATH_AS_OF,
+            "get_by_docid_as_of" => SQL_GET_BY_DOCID_AS_OF,
+            "search_fts_as_of" => SQL_SEARCH_FTS_AS_OF,
+            other => {
+                return Err(QmdError::Custom(format!(
+                    "Unknown operation for explain: {}",
+                    other
+                )))
+            }
+        };
+
+        let mut bound: Vec<String> = sample_params.iter().map(|p| p.to_string()).collect();
+        if operation == "get_by_docid" || operation == "get_by_docid_as_of" {
+            if let Some(first) = bound.first_mut() {
+                *first = format!("{}*", normalize_docid(first));
+            }
+        }
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql))?;
+
+        let expected = stmt.parameter_count();
+        if bound.len() != expected {
+            return Err(QmdError::Custom(format!(
+                "Operation {} expects {} params, got {}",
+                operation,
+                expected,
+                bound.len()
+            )));
+        }
+
+        let lines = stmt
+            .query_map(params_from_iter(bound.iter()), |row| {
+                row.get::<_, String>(3)
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(lines.join("\n"))
+    }
+
     /// Initialize database schema
     fn init_schema(&self) -> Result<()> {
         debug!("Initializing QMD schema");
@@ -103,6 +522,9 @@ impl QmdStore {
                 created_at TEXT NOT NULL,
                 modified_at TEXT NOT NULL,
                 active INTEGER NOT NULL DEFAULT 1,
+                record_version INTEGER NOT NULL DEFAULT 1,
+                source TEXT,
+                source_time TEXT,
                 FOREIGN KEY (hash) REFERENCES content(hash) ON DELETE CASCADE,
                 UNIQUE(collection, path)
             )",
@@ -121,6 +543,23 @@ impl QmdStore {
             conn.execute("ALTER TABLE documents ADD COLUMN summary TEXT", [])?;
         }
 
+        // Migration: versioned records. Rows from before versioning read as v1.
+        for (column, decl) in [
+            ("record_version", "INTEGER NOT NULL DEFAULT 1"),
+            ("source", "TEXT"),
+            ("source_time", "TEXT"),
+        ] {
+            let exists: bool = conn.query_row(
+                "SELECT count(*) FROM pragma_table_info('documents') WHERE name = ?",
+                params![column],
+                |row| row.get::<_, i64>(0).map(|c| c > 0),
+            )?;
+            if !exists {
+                debug!("Migrating: Adding '{}' column to 'documents' table", column);
+                conn.execute(&format!("ALTER TABLE documents ADD COLUMN {} {}", column, decl), [])?;
+            }
+        }
+
         // Indexes for fast lookup
         conn.execute(
             "CREATE INDEX IF NOT EXISTS idx_documents_collection ON documents(collection, active)",
@@ -131,6 +570,55 @@ impl QmdStore {
             [],
         )?;
 
+        // Revision history for as-of queries: one row per stored content version
+        let has_history: bool = conn.query_row(
+            "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = 'document_history'",
+            [],
+            |row| row.get::<_, i64>(0).map(|c| c > 0),
+        )?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS document_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                doc_id INTEGER NOT NULL,
+                collection TEXT NOT NULL,
+                path TEXT NOT NULL,
+                title TEXT NOT NULL,
+                hash TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                modified_at TEXT NOT NULL,
+                modified_ts INTEGER NOT NULL,
+                record_version INTEGER NOT NULL,
+                source TEXT,
+                source_time TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_history_path ON document_history(collection, path, modified_ts);
+            CREATE INDEX IF NOT EXISTS idx_history_hash ON document_history(hash);
+            CREATE VIRTUAL TABLE IF NOT EXISTS history_fts USING fts5(
+                filepath, title, body,
+                tokenize='porter unicode61'
+            );
+            CREATE TRIGGER IF NOT EXISTS document_history_ai AFTER INSERT ON document_history
+            BEGIN
+                INSERT INTO history_fts(rowid, filepath, title, body)
+                SELECT new.id, new.collection || '/' || new.path, new.title,
+                       (SELECT doc FROM content WHERE hash = new.hash);
+            END",
+        )?;
+        if !has_history {
+            // Existing documents start their history at their last modification
+            let seeded = conn.execute(
+                "INSERT INTO document_history (doc_id, collection, path, title, hash, created_at,
+                                               modified_at, modified_ts, record_version, source, source_time)
+                 SELECT id, collection, path, title, hash, created_at, modified_at,
+                        CAST(strftime('%s', modified_at) AS INTEGER), record_version, source, source_time
+                 FROM documents WHERE active = 1",
+                [],
+            )?;
+            if seeded > 0 {
+                debug!("Migrating: seeded document history with {} current documents", seeded);
+            }
+        }
+
         // Collections table
         conn.execute(
             "CREATE TABLE IF NOT EXISTS collections (
@@ -164,6 +652,50 @@ impl QmdStore {
             [],
         )?;
 
+        // Migration: listing columns for sessions. Plaintext rows are backfilled
+        // from their JSON; encrypted rows list without a status until next stored.
+        let mut backfill = false;
+        for column in ["status", "user_id", "metadata"] {
+            let exists: bool = conn.query_row(
+                "SELECT count(*) FROM pragma_table_info('sessions') WHERE name = ?",
+                params![column],
+                |row| row.get::<_, i64>(0).map(|c| c > 0),
+            )?;
+            if !exists {
+                debug!("Migrating: Adding '{}' column to 'sessions' table", column);
+                conn.execute(&format!("ALTER TABLE sessions ADD COLUMN {} TEXT", column), [])?;
+                backfill = true;
+            }
+        }
+        if backfill {
+            let filled = conn.execute(
+                "UPDATE sessions SET
+                    status = CASE json_type(data, '$.status')
+                        WHEN 'text' THEN json_extract(data, '$.status')
+                        ELSE (SELECT key FROM json_each(data, '$.status') LIMIT 1)
+                    END,
+                    user_id = json_extract(data, '$.metadata.user_id'),
+                    metadata = json_extract(data, '$.metadata')
+                 WHERE json_valid(data)",
+                [],
+            )?;
+            debug!("Migrating: backfilled listing columns of {} sessions", filled);
+        }
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_sessions_user ON sessions(user_id, updated_at)",
+            [],
+        )?;
+
+        // Named leases so only one process runs singleton jobs (e.g. session recovery)
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS leases (
+                name TEXT PRIMARY KEY,
+                holder TEXT NOT NULL,
+                expires_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
         info!("QMD schema initialized successfully");
         Ok(())
     }
@@ -206,6 +738,18 @@ impl QmdStore {
         Ok(())
     }
 
+    /// Check that a document body can be stored, without touching the database
+    pub fn validate_body(body: &str) -> Result<()> {
+        if body.len() > MAX_CONTENT_SIZE {
+            return Err(QmdError::Custom(format!(
+                "Document too large: {} bytes (max {} bytes)",
+                body.len(),
+                MAX_CONTENT_SIZE
+            )));
+        }
+        Ok(())
+    }
+
     /// Store a document with content-addressable storage
     pub fn store_document(
         &self,
@@ -214,76 +758,152 @@ impl QmdStore {
         title: &str,
         body: &str,
     ) -> Result<Document> {
-        if body.len() > MAX_CONTENT_SIZE {
-            return Err(QmdError::Custom(format!(
-                "Document too large: {} bytes (max {} bytes)",
-                body.len(),
-                MAX_CONTENT_SIZE
-            )));
-        }
+        self.store_document_with_source(collection, path, title, body, None, None)
+    }
+
+    /// Store a document recording where it came from
+    pub fn store_document_with_source(
+        &self,
+        collection: &str,
+        path: &str,
+        title: &str,
+        body: &str,
+        source: Option<&str>,
+        source_time: Option<&str>,
+    ) -> Result<Document> {
+        self.store_revision(collection, path, title, body, source, source_time, Utc::now())
+    }
+
+    /// Store a document as if written at `at`, e.g. when importing dated history
+    ///
+    /// As-of queries order revisions by this time.
+    pub fn store_document_at(
+        &self,
+        collection: &str,
+        path: &str,
+        title: &str,
+        body: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Document> {
+        self.store_revision(collection, path, title, body, None, None, at)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn store_revision(
+        &self,
+        collection: &str,
+        path: &str,
+        title: &str,
+        body: &str,
+        source: Option<&str>,
+        source_time: Option<&str>,
+        at: DateTime<Utc>,
+    ) -> Result<Document> {
+        self.ensure_writable("store_document")?;
+        Self::validate_body(body)?;
 
         let hash = hash_content(body);
         let docid = get_docid(&hash);
-        let now = Utc::now().to_rfc3339();
+        let now = at.to_rfc3339();
 
         debug!(
             "Storing document: {}/{} (docid: #{})",
             collection, path, docid
         );
 
-        let mut conn = self
-            .conn
-            .lock()
-            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
-
-        // Begin transaction
-        let tx = conn.transaction()?;
+        let summary = || {
+            format!(
+                "collection={}, path={}, bytes={}",
+                summarize_param(collection),
+                summarize_param(path),
+                body.len()
+            )
+        };
 
-        // 1. Store content (content-addressable, auto-dedup)
-        tx.execute(
-            "INSERT OR IGNORE INTO content (hash, doc, created_at) VALUES (?, ?, ?)",
-            params![hash, body, now],
-        )?;
+        let (doc_id, version) = self.timed("store_document", summary, |conn| {
+            // Begin transaction
+            let tx = conn.transaction()?;
 
-        // 2. Check if document exists
-        let existing: Option<(i64, String, String)> = tx
-            .query_row(
-                "SELECT id, hash, modified_at FROM documents 
-                 WHERE collection = ? AND path = ?",
-                params![collection, path],
-                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
-            )
-            .optional()?;
+            // 1. Store content (content-addressable, auto-dedup)
+            tx.execute(
+                "INSERT OR IGNORE INTO content (hash, doc, created_at) VALUES (?, ?, ?)",
+                params![hash, body, now],
+            )?;
 
-        let doc_id = if let Some((id, old_hash, _old_modified)) = existing {
-            if old_hash == hash {
-                // Content unchanged, just update modified_at and title
-                debug!("Content unchanged, updating metadata only");
+            // 2. Check if document exists
+            let existing: Option<(i64, String, i64)> = tx
+                .query_row(SQL_FIND_EXISTING, params![collection, path], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                })
+                .optional()?;
+
+            let changed = existing.as_ref().is_none_or(|(_, old_hash, _)| *old_hash != hash);
+            let (doc_id, version) = if let Some((id, old_hash, old_version)) = existing {
+                // Rewrites upgrade the record unless migrations are manual
+                let version = match self.migration_policy {
+                    MigrationPolicy::Lazy => CURRENT_RECORD_VERSION,
+                    MigrationPolicy::Manual => old_version,
+                };
+                let provenance = version >= 2;
+                if old_hash == hash {
+                    // Content unchanged, just update modified_at and title
+                    debug!("Content unchanged, updating metadata only");
+                    tx.execute(
+                        "UPDATE documents SET title = ?, modified_at = ?, record_version = ?, active = 1,
+                         source = CASE WHEN ?5 THEN COALESCE(?6, source) ELSE source END,
+                         source_time = CASE WHEN ?5 THEN COALESCE(?7, source_time, modified_at) ELSE source_time END
+                         WHERE id = ?4",
+                        params![title, now, version, id, provenance, source, source_time],
+                    )?;
+                } else {
+                    // Content changed, update document
+                    debug!("Content changed, updating document");
+                    tx.execute(
+                        "UPDATE documents SET title = ?, hash = ?, modified_at = ?, summary = NULL, record_version = ?, active = 1,
+                         source = CASE WHEN ?6 THEN ?7 ELSE source END,
+                         source_time = CASE WHEN ?6 THEN COALESCE(?8, ?3) ELSE source_time END
+                         WHERE id = ?5",
+                        params![title, hash, now, version, id, provenance, source, source_time],
+                    )?;
+                }
+                (id, version)
+            } else {
+                // New document, insert
+                debug!("New document, inserting");
                 tx.execute(
-                    "UPDATE documents SET title = ?, modified_at = ? WHERE id = ?",
-                    params![title, now, id],
+                    "INSERT INTO documents (collection, path, title, hash, created_at, modified_at, active,
+                                            record_version, source, source_time)
+                     VALUES (?, ?, ?, ?, ?, ?, 1, ?, ?, ?)",
+                    params![
+                        collection,
+                        path,
+                        title,
+                        hash,
+                        now,
+                        now,
+                        CURRENT_RECORD_VERSION,
+                        source,
+                        source_time.unwrap_or(&now)
+                    ],
                 )?;
-            } else {
-                // Content changed, update document
-                debug!("Content changed, updating document");
+                (tx.last_insert_rowid(), CURRENT_RECORD_VERSION)
+            };
+
+            // 3. New content is a new revision for as-of queries
+            if changed {
                 tx.execute(
-                    "UPDATE documents SET title = ?, hash = ?, modified_at = ?, summary = NULL WHERE id = ?",
-                    params![title, hash, now, id],
+                    "INSERT INTO document_history (doc_id, collection, path, title, hash, created_at,
+                                                   modified_at, modified_ts, record_version, source, source_time)
+                     SELECT id, collection, path, title, hash, created_at, modified_at, ?2,
+                            record_version, source, source_time
+                     FROM documents WHERE id = ?1",
+                    params![doc_id, at.timestamp()],
                 )?;
             }
-            id
-        } else {
-            // New document, insert
-            debug!("New document, inserting");
-            tx.execute(
-                "INSERT INTO documents (collection, path, title, hash, created_at, modified_at, active)
-                 VALUES (?, ?, ?, ?, ?, ?, 1)",
-                params![collection, path, title, hash, now, now],
-            )?;
-            tx.last_insert_rowid()
-        };
 
-        tx.commit()?;
+            tx.commit()?;
+            Ok((doc_id, version))
+        })?;
 
         Ok(Document {
             id: Some(doc_id),
@@ -295,44 +915,137 @@ impl QmdStore {
             body: Some(body.to_string()),
             summary: None, // Summary is generated asynchronously
             created_at: now.clone(),
-            modified_at: now,
+            modified_at: now.clone(),
             active: true,
+            record_version: version,
+            source: source.map(str::to_string),
+            source_time: Some(source_time.unwrap_or(&now).to_string()),
+        })
+    }
+
+    /// Map a row from SQL_GET_BY_PATH / SQL_GET_BY_DOCID into a Document
+    fn document_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Document> {
+        Ok(Document {
+            id: Some(row.get(0)?),
+            collection: row.get(1)?,
+            path: row.get(2)?,
+            title: row.get(3)?,
+            hash: row.get(4)?,
+            docid: get_docid(&row.get::<_, String>(4)?),
+            created_at: row.get(5)?,
+            modified_at: row.get(6)?,
+            active: row.get(7)?,
+            body: Some(row.get(8)?),
+            summary: row.get(9)?,
+            record_version: row.get(10)?,
+            source: row.get(11)?,
+            source_time: row.get(12)?,
+        })
+    }
+
+    /// Map a row from the FTS search statements into a SearchResult
+    fn search_result_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SearchResult> {
+        let hash: String = row.get(4)?;
+        Ok(SearchResult {
+            document: Document {
+                id: Some(row.get(0)?),
+                collection: row.get(1)?,
+                path: row.get(2)?,
+                title: row.get(3)?,
+                hash: hash.clone(),
+                docid: get_docid(&hash),
+                created_at: row.get(5)?,
+                modified_at: row.get(6)?,
+                active: row.get(7)?,
+                body: None, // Don't load body in search results
+                summary: row.get(10)?,
+                record_version: row.get(11)?,
+                source: row.get(12)?,
+                source_time: row.get(13)?,
+            },
+            score: row.get::<_, f64>(8)?.abs(), // BM25 score (absolute value)
+            snippet: Some(row.get(9)?),
         })
     }
 
     /// Get document by virtual path
     pub fn get_by_path(&self, collection: &str, path: &str) -> Result<Option<Document>> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
-        let row = conn
-            .query_row(
-                "SELECT d.id, d.collection, d.path, d.title, d.hash, d.created_at, d.modified_at,
-                        d.active, c.doc, d.summary
-                 FROM documents d
-                 JOIN content c ON d.hash = c.hash
-                 WHERE d.collection = ? AND d.path = ? AND d.active = 1",
-                params![collection, path],
-                |row| {
-                    Ok(Document {
-                        id: Some(row.get(0)?),
-                        collection: row.get(1)?,
-                        path: row.get(2)?,
-                        title: row.get(3)?,
-                        hash: row.get(4)?,
-                        docid: get_docid(&row.get::<_, String>(4)?),
-                        created_at: row.get(5)?,
-                        modified_at: row.get(6)?,
-                        active: row.get(7)?,
-                        body: Some(row.get(8)?),
-                        summary: row.get(9)?,
-                    })
-                },
+        let summary = || {
+            format!(
+                "collection={}, path={}",
+                summarize_param(collection),
+                summarize_param(path)
+            )
+        };
+
+        self.timed("get_by_path", summary, |conn| {
+            Ok(conn
+                .query_row(
+                    SQL_GET_BY_PATH,
+                    params![collection, path],
+                    Self::document_from_row,
+                )
+                .optional()?)
+        })
+    }
+
+    /// Page through the active documents of `collection`, newest first
+    ///
+    /// `path_prefix` limits the listing to a folder such as `"strategies/"`.
+    /// Without `include_body` the documents come back with `body: None`.
+    pub fn list_documents(
+        &self,
+        collection: &str,
+        path_prefix: Option<&str>,
+        offset: usize,
+        limit: usize,
+        include_body: bool,
+    ) -> Result<Vec<Document>> {
+        let prefix = path_prefix.unwrap_or_default();
+        let summary = || {
+            format!(
+                "collection={}, prefix={}, offset={}, limit={}",
+                summarize_param(collection),
+                summarize_param(prefix),
+                offset,
+                limit
+            )
+        };
+
+        self.timed("list_documents", summary, |conn| {
+            let mut stmt = conn.prepare(SQL_LIST_DOCUMENTS)?;
+            let documents = stmt
+                .query_map(
+                    params![collection, prefix, include_body, limit as i64, offset as i64],
+                    |row| {
+                        let mut doc = Self::document_from_row(row)?;
+                        if !include_body {
+                            doc.body = None;
+                        }
+                        Ok(doc)
+                    },
+                )?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            Ok(documents)
+        })
+    }
+
+    /// Number of active documents in `collection`, optionally under `path_prefix`
+    pub fn count_documents(&self, collection: &str, path_prefix: Option<&str>) -> Result<usize> {
+        let prefix = path_prefix.unwrap_or_default();
+        let summary = || {
+            format!(
+                "collection={}, prefix={}",
+                summarize_param(collection),
+                summarize_param(prefix)
             )
-            .optional()?;
+        };
 
-        Ok(row)
+        self.timed("count_documents", summary, |conn| {
+            let count: i64 =
+                conn.query_row(SQL_COUNT_DOCUMENTS, params![collection, prefix], |row| row.get(0))?;
+            Ok(count as usize)
+        })
     }
 
     /// Get document by docid (short hash)
@@ -343,137 +1056,232 @@ impl QmdStore {
             return Err(QmdError::InvalidDocid(docid.to_string()));
         }
 
-        let pattern = format!("{}%", normalized);
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        // Docids are validated hex, so there are no GLOB metacharacters to escape
+        let pattern = format!("{}*", normalized);
+
+        self.timed(
+            "get_by_docid",
+            || format!("docid={}", normalized),
+            |conn| {
+                Ok(conn
+                    .query_row(SQL_GET_BY_DOCID, params![pattern], Self::document_from_row)
+                    .optional()?)
+            },
+        )
+    }
 
-        let row = conn
-            .query_row(
-                "SELECT d.id, d.collection, d.path, d.title, d.hash, d.created_at, d.modified_at,
-                        d.active, c.doc, d.summary
-                 FROM documents d
-                 JOIN content c ON d.hash = c.hash
-                 WHERE d.hash LIKE ? AND d.active = 1
-                 LIMIT 1",
-                params![pattern],
-                |row| {
-                    Ok(Document {
-                        id: Some(row.get(0)?),
-                        collection: row.get(1)?,
-                        path: row.get(2)?,
-                        title: row.get(3)?,
-                        hash: row.get(4)?,
-                        docid: get_docid(&row.get::<_, String>(4)?),
-                        created_at: row.get(5)?,
-                        modified_at: row.get(6)?,
-                        active: row.get(7)?,
-                        body: Some(row.get(8)?),
-                        summary: row.get(9)?,
-                    })
-                },
+    /// BM25 full-text search
+    ///
+    /// `query` is plain text: FTS5 syntax in it is neutralized (see [`FtsQuery`]),
+    /// so any user input is safe. Use [`search_fts_raw`](Self::search_fts_raw) for
+    /// column filters and boolean operators.
+    pub fn search_fts(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        self.search_fts_mode(query, limit, false)
+    }
+
+    /// BM25 search passing `query` to FTS5 MATCH unchanged
+    ///
+    /// For callers who want FTS5 syntax (`title:rsi`, `AND`/`OR`/`NOT`, `NEAR`).
+    /// A query FTS5 can't parse is retried as plain text rather than failing.
+    pub fn search_fts_raw(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
+        self.search_fts_mode(query, limit, true)
+    }
+
+    fn search_fts_mode(&self, query: &str, limit: usize, raw: bool) -> Result<Vec<SearchResult>> {
+        let summary = || format!("query={}, limit={}, raw={}", summarize_param(query), limit, raw);
+
+        self.timed("search_fts", summary, |conn| {
+            Self::match_fts(query, raw, |expr| {
+                Self::fts_rows(conn, SQL_SEARCH_FTS, params![expr, limit])
+            })
+        })
+    }
+
+    /// Search within a specific collection
+    ///
+    /// `query` is plain text, as in [`search_fts`](Self::search_fts).
+    pub fn search_fts_in_collection(
+        &self,
+        query: &str,
+        collection: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_fts_in_collection_mode(query, collection, limit, false)
+    }
+
+    /// Search within a specific collection, passing `query` to FTS5 unchanged
+    pub fn search_fts_raw_in_collection(
+        &self,
+        query: &str,
+        collection: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        self.search_fts_in_collection_mode(query, collection, limit, true)
+    }
+
+    fn search_fts_in_collection_mode(
+        &self,
+        query: &str,
+        collection: &str,
+        limit: usize,
+        raw: bool,
+    ) -> Result<Vec<SearchResult>> {
+        let summary = || {
+            format!(
+                "query={}, collection={}, limit={}, raw={}",
+                summarize_param(query),
+                summarize_param(collection),
+                limit,
+                raw
             )
-            .optional()?;
+        };
 
-        Ok(row)
+        self.timed("search_fts_in_collection", summary, |conn| {
+            Self::match_fts(query, raw, |expr| {
+                Self::fts_rows(
+                    conn,
+                    SQL_SEARCH_FTS_IN_COLLECTION,
+                    params![expr, collection, limit],
+                )
+            })
+        })
     }
 
-    /// BM25 full-text search
-    pub fn search_fts(&self, query: &str, limit: usize) -> Result<Vec<SearchResult>> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
-        let mut stmt = conn.prepare(
-            "SELECT d.id, d.collection, d.path, d.title, d.hash, d.created_at, d.modified_at,
-                    d.active, bm25(documents_fts) as score,
-                    snippet(documents_fts, 2, '<mark>', '</mark>', '...', 32) as snippet,
-                    d.summary
-             FROM documents d
-             JOIN documents_fts ON documents_fts.rowid = d.id
-             WHERE documents_fts MATCH ? AND d.active = 1
-             ORDER BY score
-             LIMIT ?",
-        )?;
+    fn fts_rows(
+        conn: &Connection,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> rusqlite::Result<Vec<SearchResult>> {
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt
+            .query_map(params, Self::search_result_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>();
+        rows
+    }
+
+    /// Run an FTS search, falling back to safer forms of `query` instead of failing on syntax
+    ///
+    /// Raw queries go to MATCH unchanged first. Plain text is searched as all of
+    /// its words, then as any of them if that finds nothing; if FTS5 still
+    /// rejects a form, the words are tried as one phrase. Text with nothing
+    /// searchable in it finds nothing.
+    fn match_fts(
+        query: &str,
+        raw: bool,
+        run: impl Fn(&str) -> rusqlite::Result<Vec<SearchResult>>,
+    ) -> Result<Vec<SearchResult>> {
+        let rejected = |expr: &str, e: rusqlite::Error| -> Result<()> {
+            if !is_query_error(&e) {
+                return Err(e.into());
+            }
+            debug!("FTS5 rejected {}: {}", summarize_param(expr), e);
+            Ok(())
+        };
+
+        if raw {
+            match run(query) {
+                Ok(hits) => return Ok(hits),
+                Err(e) => rejected(query, e)?,
+            }
+        }
+
+        let parsed = FtsQuery::parse(query);
+        if parsed.is_empty() {
+            return Ok(Vec::new());
+        }
+        let all = parsed.all();
+        match run(&all) {
+            Ok(hits) if !hits.is_empty() || parsed.len() < 2 => return Ok(hits),
+            Ok(_) => {
+                let any = parsed.any();
+                match run(&any) {
+                    Ok(hits) => return Ok(hits),
+                    Err(e) => rejected(&any, e)?,
+                }
+            }
+            Err(e) => rejected(&all, e)?,
+        }
 
-        let results = stmt
-            .query_map(params![query, limit], |row| {
-                let hash: String = row.get(4)?;
-                Ok(SearchResult {
-                    document: Document {
-                        id: Some(row.get(0)?),
-                        collection: row.get(1)?,
-                        path: row.get(2)?,
-                        title: row.get(3)?,
-                        hash: hash.clone(),
-                        docid: get_docid(&hash),
-                        created_at: row.get(5)?,
-                        modified_at: row.get(6)?,
-                        active: row.get(7)?,
-                        body: None, // Don't load body in search results
-                        summary: row.get(10)?,
-                    },
-                    score: row.get::<_, f64>(8)?.abs(), // BM25 score (absolute value)
-                    snippet: Some(row.get(9)?),
-                })
-            })?
-            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let phrase = parsed.phrase();
+        match run(&phrase) {
+            Ok(hits) => Ok(hits),
+            Err(e) => {
+                rejected(&phrase, e)?;
+                warn!("No form of FTS query {} could be searched", summarize_param(query));
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// The revision of a document current at `as_of` (unix seconds)
+    ///
+    /// `None` if the document didn't exist yet. Summaries aren't versioned and
+    /// are always `None`.
+    pub fn get_by_path_as_of(&self, collection: &str, path: &str, as_of: i64) -> Result<Option<Document>> {
+        let summary = || {
+            format!(
+                "collection={}, path={}, as_of={}",
+                summarize_param(collection),
+                summarize_param(path),
+                as_of
+            )
+        };
 
-        Ok(results)
+        self.timed("get_by_path_as_of", summary, |conn| {
+            Ok(conn
+                .query_row(
+                    SQL_GET_BY_PATH_AS_OF,
+                    params![collection, path, as_of],
+                    Self::document_from_row,
+                )
+                .optional()?)
+        })
     }
 
-    /// Search within a specific collection
-    pub fn search_fts_in_collection(
-        &self,
-        query: &str,
-        collection: &str,
-        limit: usize,
-    ) -> Result<Vec<SearchResult>> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
-        let mut stmt = conn.prepare(
-            "SELECT d.id, d.collection, d.path, d.title, d.hash, d.created_at, d.modified_at,
-                    d.active, bm25(documents_fts) as score,
-                    snippet(documents_fts, 2, '<mark>', '</mark>', '...', 32) as snippet,
-                    d.summary
-             FROM documents d
-             JOIN documents_fts ON documents_fts.rowid = d.id
-             WHERE documents_fts MATCH ? AND d.collection = ? AND d.active = 1
-             ORDER BY score
-             LIMIT ?",
-        )?;
+    /// The revision with this docid, if it was the current one at `as_of`
+    ///
+    /// Resolves vector hits (which are keyed by docid) against history.
+    pub fn get_by_docid_as_of(&self, docid: &str, as_of: i64) -> Result<Option<Document>> {
+        let normalized = normalize_docid(docid);
+        if !validate_docid(&normalized) {
+            return Err(QmdError::InvalidDocid(docid.to_string()));
+        }
+        let pattern = format!("{}*", normalized);
+
+        self.timed(
+            "get_by_docid_as_of",
+            || format!("docid={}, as_of={}", normalized, as_of),
+            |conn| {
+                Ok(conn
+                    .query_row(SQL_GET_BY_DOCID_AS_OF, params![pattern, as_of], Self::document_from_row)
+                    .optional()?)
+            },
+        )
+    }
 
-        let results = stmt
-            .query_map(params![query, collection, limit], |row| {
-                let hash: String = row.get(4)?;
-                Ok(SearchResult {
-                    document: Document {
-                        id: Some(row.get(0)?),
-                        collection: row.get(1)?,
-                        path: row.get(2)?,
-                        title: row.get(3)?,
-                        hash: hash.clone(),
-                        docid: get_docid(&hash),
-                        created_at: row.get(5)?,
-                        modified_at: row.get(6)?,
-                        active: row.get(7)?,
-                        body: None,
-                        summary: row.get(10)?,
-                    },
-                    score: row.get::<_, f64>(8)?.abs(),
-                    snippet: Some(row.get(9)?),
-                })
-            })?
-            .collect::<std::result::Result<Vec<_>, _>>()?;
+    /// BM25 search over the revisions that were current at `as_of`
+    ///
+    /// `query` is plain text, as in [`search_fts`](Self::search_fts).
+    pub fn search_fts_as_of(&self, query: &str, limit: usize, as_of: i64) -> Result<Vec<SearchResult>> {
+        let summary = || {
+            format!(
+                "query={}, limit={}, as_of={}",
+                summarize_param(query),
+                limit,
+                as_of
+            )
+        };
 
-        Ok(results)
+        self.timed("search_fts_as_of", summary, |conn| {
+            Self::match_fts(query, false, |expr| {
+                Self::fts_rows(conn, SQL_SEARCH_FTS_AS_OF, params![expr, as_of, limit])
+            })
+        })
     }
 
     /// Create a collection
     pub fn create_collection(&self, collection: Collection) -> Result<()> {
+        self.ensure_writable("create_collection")?;
         let now = Utc::now().to_rfc3339();
 
         let conn = self
@@ -549,20 +1357,105 @@ impl QmdStore {
 
     /// Vacuum database (reclaim space)
     pub fn vacuum(&self) -> Result<()> {
+        self.ensure_writable("vacuum")?;
         info!("Vacuuming database");
         let conn = self
             .conn
             .lock()
             .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        if self.migration_policy == MigrationPolicy::Lazy {
+            let upgraded = Self::upgrade_records(&conn, CURRENT_RECORD_VERSION)?;
+            if upgraded > 0 {
+                info!("Upgraded {} document records to v{} during vacuum", upgraded, CURRENT_RECORD_VERSION);
+            }
+        }
         conn.execute_batch("VACUUM")?;
         Ok(())
     }
 
+    /// Snapshot the database to `path` with SQLite's online backup API
+    ///
+    /// Safe while the store is in use: the copy is one read transaction, so it
+    /// sees a consistent state and never blocks writers in other processes
+    /// (calls on this store wait for it). The snapshot is written next to
+    /// `path` and renamed into place, so `path` never holds a partial copy.
+    pub fn backup_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut tmp_name = path.as_os_str().to_owned();
+        tmp_name.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_name);
+        if tmp_path.exists() {
+            std::fs::remove_file(&tmp_path)?;
+        }
+
+        self.timed("backup", || path.display().to_string(), |conn| {
+            let mut dst = Connection::open(&tmp_path)?;
+            let backup = Backup::new(conn, &mut dst)?;
+            // All pages in one step, i.e. a single read snapshot of the source;
+            // in WAL mode that read doesn't block writers
+            while backup.step(-1)? != StepResult::Done {
+                std::thread::sleep(BACKUP_RETRY_DELAY);
+            }
+            Ok(())
+        })?;
+        std::fs::rename(&tmp_path, path)?;
+        info!("Backed up QMD store to {:?}", path);
+        Ok(())
+    }
+
+    /// Lowest record version among stored documents (`None` when empty)
+    pub fn min_record_version(&self) -> Result<Option<i64>> {
+        self.timed("min_record_version", String::new, |conn| {
+            Ok(conn.query_row("SELECT MIN(record_version) FROM documents", [], |row| row.get(0))?)
+        })
+    }
+
+    /// Rewrite every document record older than `target_version`, returning how many changed
+    pub fn migrate_in_place(&self, target_version: i64) -> Result<usize> {
+        self.ensure_writable("migrate_in_place")?;
+        if !(1..=CURRENT_RECORD_VERSION).contains(&target_version) {
+            return Err(QmdError::Custom(format!(
+                "Unsupported record version {} (supported: 1-{})",
+                target_version, CURRENT_RECORD_VERSION
+            )));
+        }
+
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let tx = conn.unchecked_transaction()?;
+        let upgraded = Self::upgrade_records(&tx, target_version)?;
+        tx.commit()?;
+
+        info!("Migrated {} document records to v{}", upgraded, target_version);
+        Ok(upgraded)
+    }
+
+    /// Apply each version step to records below `target_version`
+    fn upgrade_records(conn: &Connection, target_version: i64) -> Result<usize> {
+        let mut upgraded = 0;
+        if target_version >= 2 {
+            // v1 -> v2: provenance is unknown; the best source time we have is the last modification
+            upgraded += conn.execute(
+                "UPDATE documents SET record_version = 2, source_time = COALESCE(source_time, modified_at)
+                 WHERE record_version < 2",
+                [],
+            )?;
+        }
+        Ok(upgraded)
+    }
+
     /// Garbage collect orphaned content
     ///
-    /// Deletes content blobs that are no longer referenced by any document.
+    /// Deletes content blobs that are no longer referenced by any document
+    /// or past revision.
     /// This should be called periodically to free disk space.
     pub fn vacuum_content(&self) -> Result<usize> {
+        self.ensure_writable("vacuum_content")?;
         info!("Vacuuming orphaned content");
 
         let conn = self
@@ -574,9 +1467,11 @@ impl QmdStore {
         // Find and delete orphaned content
         // sqlite doesn't support DELETE ... JOIN properly in all versions,
         // using subquery is safer standard SQL
+        // Content of past revisions stays for as-of queries
         let deleted_count = tx.execute(
             "DELETE FROM content 
-             WHERE hash NOT IN (SELECT hash FROM documents)",
+             WHERE hash NOT IN (SELECT hash FROM documents)
+               AND hash NOT IN (SELECT hash FROM document_history)",
             [],
         )?;
 
@@ -591,6 +1486,7 @@ impl QmdStore {
 
     /// Update the summary for a document
     pub fn update_summary(&self, collection: &str, path: &str, summary: &str) -> Result<()> {
+        self.ensure_writable("update_summary")?;
         let conn = self
             .conn
             .lock()
@@ -604,42 +1500,272 @@ impl QmdStore {
         Ok(())
     }
 
+    /// Mark the document at `path` inactive, dropping it from search
+    ///
+    /// Returns false when there was no active document. Storing the path again
+    /// reactivates it; past revisions stay available to as-of queries.
+    pub fn delete_document(&self, collection: &str, path: &str) -> Result<bool> {
+        self.ensure_writable("delete_document")?;
+        let now = Utc::now().to_rfc3339();
+        let summary = || {
+            format!(
+                "collection={}, path={}",
+                summarize_param(collection),
+                summarize_param(path)
+            )
+        };
+
+        self.timed("delete_document", summary, |conn| {
+            let deleted = conn.execute(
+                "UPDATE documents SET active = 0, modified_at = ?
+                 WHERE collection = ? AND path = ? AND active = 1",
+                params![now, collection, path],
+            )?;
+            Ok(deleted > 0)
+        })
+    }
+
+    /// Whether an active document in `collection` holds the content `hash`
+    #[cfg(feature = "vector-index")]
+    pub(crate) fn has_active_content(&self, collection: &str, hash: &str) -> Result<bool> {
+        self.timed("has_active_content", String::new, |conn| {
+            Ok(conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM documents WHERE collection = ? AND hash = ? AND active = 1)",
+                params![collection, hash],
+                |row| row.get(0),
+            )?)
+        })
+    }
+
     /// Store an agent session (JSON blob)
+    ///
+    /// The session's `status` and `metadata` fields, if present, are copied into
+    /// their own columns for [`list_session_summaries`](Self::list_session_summaries).
+    /// With encryption on, the user id column holds a keyed hash of it.
     pub fn store_session(&self, id: &str, data: &str) -> Result<()> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        self.ensure_writable("store_session")?;
         let now = Utc::now().to_rfc3339();
+        let summary = || format!("id={}, bytes={}", summarize_param(id), data.len());
+        let (status, metadata) = session_listing(data);
+        let user_id = metadata
+            .get(META_USER_ID)
+            .map(|user_id| session_user_key(self.encryption.as_deref(), user_id))
+            .transpose()?;
+        let metadata = if metadata.is_empty() {
+            None
+        } else {
+            let json = serde_json::to_string(&metadata)?;
+            Some(
+                encryption::seal_text(self.encryption.as_deref(), &json, SESSION_META_AAD)
+                    .map_err(|e| QmdError::Encryption(e.to_string()))?,
+            )
+        };
+        let data = encryption::seal_text(self.encryption.as_deref(), data, SESSION_AAD)
+            .map_err(|e| QmdError::Encryption(e.to_string()))?;
+
+        self.timed("store_session", summary, |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO sessions (id, data, updated_at, status, user_id, metadata)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                params![id, data, now, status, user_id, metadata],
+            )?;
+            Ok(())
+        })
+    }
 
-        conn.execute(
-            "INSERT OR REPLACE INTO sessions (id, data, updated_at) VALUES (?, ?, ?)",
-            params![id, data, now],
+    /// Sessions matching `query` without their data, most recently updated first
+    pub fn list_session_summaries(&self, query: &SessionQuery) -> Result<Vec<SessionSummary>> {
+        type Row = (String, String, Option<String>, Option<String>);
+        let limit = query.limit.map_or(-1, |limit| limit.min(i64::MAX as usize) as i64);
+        let user_id = query
+            .user_id
+            .as_deref()
+            .map(|user_id| session_user_key(self.encryption.as_deref(), user_id))
+            .transpose()?;
+        let rows: Vec<Row> = self.timed(
+            "list_session_summaries",
+            || format!("{:?}", query),
+            |conn| {
+                let mut stmt = conn.prepare(
+                    "SELECT id, updated_at, status, metadata FROM sessions
+                     WHERE (?1 IS NULL OR substr(id, 1, length(?1)) = ?1)
+                       AND (?2 IS NULL OR user_id = ?2)
+                     ORDER BY julianday(updated_at) DESC, id
+                     LIMIT ?3 OFFSET ?4",
+                )?;
+                let rows = stmt.query_map(
+                    params![query.id_prefix, user_id, limit, query.offset as i64],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+                )?;
+                Ok(rows.collect::<std::result::Result<_, _>>()?)
+            },
         )?;
 
-        Ok(())
+        rows.into_iter()
+            .map(|(id, updated_at, status, metadata)| {
+                let updated_at = DateTime::parse_from_rfc3339(&updated_at)
+                    .map_err(|e| QmdError::Custom(format!("session {}: {}", id, e)))?
+                    .with_timezone(&Utc);
+                let metadata = match metadata {
+                    Some(sealed) => {
+                        let json = encryption::open_text(
+                            self.encryption.as_deref(),
+                            sealed,
+                            SESSION_META_AAD,
+                        )
+                        .map_err(|e| QmdError::Encryption(e.to_string()))?;
+                        serde_json::from_str(&json)?
+                    }
+                    None => BTreeMap::new(),
+                };
+                Ok(SessionSummary {
+                    id,
+                    updated_at,
+                    status,
+                    metadata,
+                })
+            })
+            .collect()
+    }
+
+    /// Delete sessions not updated for `age`; returns how many were deleted
+    pub fn delete_sessions_older_than(&self, age: Duration) -> Result<usize> {
+        self.ensure_writable("delete_sessions_older_than")?;
+        let Some(cutoff) = chrono::Duration::from_std(age)
+            .ok()
+            .and_then(|age| Utc::now().checked_sub_signed(age))
+        else {
+            return Ok(0);
+        };
+        let cutoff = cutoff.to_rfc3339();
+
+        self.timed(
+            "delete_sessions_older_than",
+            || format!("cutoff={}", cutoff),
+            |conn| {
+                Ok(conn.execute(
+                    "DELETE FROM sessions WHERE julianday(updated_at) < julianday(?)",
+                    params![cutoff],
+                )?)
+            },
+        )
     }
 
     /// Load an agent session
     pub fn load_session(&self, id: &str) -> Result<Option<String>> {
-        let conn = self
+        let data: Option<String> = self.timed(
+            "load_session",
+            || format!("id={}", summarize_param(id)),
+            |conn| {
+                Ok(conn
+                    .query_row(SQL_LOAD_SESSION, params![id], |row| row.get(0))
+                    .optional()?)
+            },
+        )?;
+
+        data.map(|data| {
+            encryption::open_text(self.encryption.as_deref(), data, SESSION_AAD)
+                .map_err(|e| QmdError::Encryption(e.to_string()))
+        })
+        .transpose()
+    }
+
+    /// Load every session as `(id, data)`, oldest update first
+    pub fn list_sessions(&self) -> Result<Vec<(String, String)>> {
+        let rows: Vec<(String, String)> = self.timed("list_sessions", String::new, |conn| {
+            let mut stmt = conn.prepare("SELECT id, data FROM sessions ORDER BY updated_at")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            Ok(rows.collect::<std::result::Result<_, _>>()?)
+        })?;
+
+        rows.into_iter()
+            .map(|(id, data)| {
+                encryption::open_text(self.encryption.as_deref(), data, SESSION_AAD)
+                    .map(|data| (id, data))
+                    .map_err(|e| QmdError::Encryption(e.to_string()))
+            })
+            .collect()
+    }
+
+    /// Take or renew the lease `name` for `ttl`; false while another holder's lease is live
+    pub fn try_acquire_lease(&self, name: &str, holder: &str, ttl: Duration) -> Result<bool> {
+        self.ensure_writable("try_acquire_lease")?;
+        let now = Utc::now().timestamp_millis();
+        let expires_at = now + ttl.as_millis() as i64;
+
+        self.timed("try_acquire_lease", || format!("name={}", name), |conn| {
+            let changed = conn.execute(
+                "INSERT INTO leases (name, holder, expires_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(name) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+                 WHERE leases.holder = excluded.holder OR leases.expires_at <= ?4",
+                params![name, holder, expires_at, now],
+            )?;
+            Ok(changed == 1)
+        })
+    }
+
+    /// Re-encrypt every session under `new`, encrypting legacy plaintext rows too
+    ///
+    /// `progress` is called after each row. Returns the number of rows rewritten.
+    pub fn rewrap_sessions(
+        &self,
+        old: &dyn EncryptionProvider,
+        new: &dyn EncryptionProvider,
+        mut progress: impl FnMut(RewrapProgress),
+    ) -> Result<usize> {
+        self.ensure_writable("rewrap_sessions")?;
+        let mut conn = self
             .conn
             .lock()
             .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
 
-        let data: Option<String> = conn
-            .query_row(
-                "SELECT data FROM sessions WHERE id = ?",
-                params![id],
-                |row| row.get(0),
-            )
-            .optional()?;
+        let rows: Vec<(String, String, Option<String>)> = {
+            let mut stmt = conn.prepare("SELECT id, data, metadata FROM sessions")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            rows.collect::<std::result::Result<_, _>>()?
+        };
 
-        Ok(data)
+        let total = rows.len();
+        let tx = conn.transaction()?;
+        for (done, (id, data, metadata)) in rows.into_iter().enumerate() {
+            let rewrap = |text: &str, aad: &[u8]| {
+                encryption::rewrap_text(text, old, new, aad)
+                    .map_err(|e| QmdError::Encryption(format!("session {}: {}", id, e)))
+            };
+            let rewrapped = rewrap(&data, SESSION_AAD)?;
+            let metadata = metadata
+                .map(|metadata| rewrap(&metadata, SESSION_META_AAD))
+                .transpose()?;
+            // Re-key the user id hash (or hash a legacy plaintext one) under `new`
+            let user_id = match &metadata {
+                Some(sealed) => {
+                    let json = encryption::open_text(Some(new), sealed.clone(), SESSION_META_AAD)
+                        .map_err(|e| QmdError::Encryption(format!("session {}: {}", id, e)))?;
+                    serde_json::from_str::<BTreeMap<String, String>>(&json)?
+                        .get(META_USER_ID)
+                        .map(|user_id| session_user_key(Some(new), user_id))
+                        .transpose()?
+                }
+                None => None,
+            };
+            tx.execute(
+                "UPDATE sessions SET data = ?, metadata = ?, user_id = ? WHERE id = ?",
+                params![rewrapped, metadata, user_id, id],
+            )?;
+            progress(RewrapProgress {
+                done: done + 1,
+                total,
+            });
+        }
+        tx.commit()?;
+
+        info!("Re-encrypted {} sessions under key '{}'", total, new.key_id());
+        Ok(total)
     }
 
     /// Delete a session
     pub fn delete_session(&self, id: &str) -> Result<()> {
+        self.ensure_writable("delete_session")?;
         let conn = self
             .conn
             .lock()
@@ -651,6 +1777,30 @@ impl QmdStore {
     }
 }
 
+/// Value of the `user_id` column: the id itself, or its keyed hash when encrypted
+fn session_user_key(provider: Option<&dyn EncryptionProvider>, user_id: &str) -> Result<String> {
+    encryption::hash_text(provider, user_id, SESSION_USER_CONTEXT)
+        .map_err(|e| QmdError::Encryption(e.to_string()))
+}
+
+/// Status label and metadata of a session's JSON, for the listing columns
+fn session_listing(data: &str) -> (Option<String>, BTreeMap<String, String>) {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(data) else {
+        return (None, BTreeMap::new());
+    };
+    // Unit statuses serialize as strings, the rest as single-key objects
+    let status = match value.get("status") {
+        Some(serde_json::Value::String(status)) => Some(status.clone()),
+        Some(serde_json::Value::Object(status)) => status.keys().next().cloned(),
+        _ => None,
+    };
+    let metadata = value
+        .get("metadata")
+        .and_then(|metadata| serde_json::from_value(metadata.clone()).ok())
+        .unwrap_or_default();
+    (status, metadata)
+}
+
 /// Store statistics
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct StoreStats {
@@ -660,6 +1810,12 @@ pub struct StoreStats {
     pub database_size_bytes: u64,
 }
 
+impl DocidResolver for QmdStore {
+    fn title(&self, docid: &str) -> Option<String> {
+        self.get_by_docid(docid).ok().flatten().map(|doc| doc.title)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -715,6 +1871,42 @@ mod tests {
         assert_eq!(by_docid.title, "SOL Trading Strategy");
     }
 
+    #[test]
+    fn test_list_documents_pages_active_documents() {
+        use chrono::TimeZone;
+
+        let (store, _temp) = create_test_store();
+        for (day, path) in ["strategies/sol.md", "notes/eth.md", "strategies/btc.md", "strategies/old.md"]
+            .iter()
+            .enumerate()
+        {
+            let at = Utc.with_ymd_and_hms(2024, 1, day as u32 + 1, 0, 0, 0).unwrap();
+            store
+                .store_document_at("trading", path, path, &format!("Body of {}", path), at)
+                .unwrap();
+        }
+        store.store_document("other", "strategies/sol.md", "SOL", "Elsewhere").unwrap();
+        store.delete_document("trading", "strategies/old.md").unwrap();
+
+        assert_eq!(store.count_documents("trading", None).unwrap(), 3);
+        assert_eq!(store.count_documents("trading", Some("strategies/")).unwrap(), 2);
+
+        // Newest first, split across pages; the deleted document never shows up
+        let paths = |docs: Vec<Document>| docs.into_iter().map(|d| d.path).collect::<Vec<_>>();
+        let first = store.list_documents("trading", None, 0, 2, false).unwrap();
+        assert!(first.iter().all(|d| d.body.is_none()));
+        assert_eq!(paths(first), ["strategies/btc.md", "notes/eth.md"]);
+        let last = store.list_documents("trading", None, 2, 2, true).unwrap();
+        assert_eq!(last[0].body.as_deref(), Some("Body of strategies/sol.md"));
+        assert_eq!(paths(last), ["strategies/sol.md"]);
+        assert!(store.list_documents("trading", None, 3, 2, false).unwrap().is_empty());
+
+        let folder = store
+            .list_documents("trading", Some("strategies/"), 0, 10, false)
+            .unwrap();
+        assert_eq!(paths(folder), ["strategies/btc.md", "strategies/sol.md"]);
+    }
+
     #[test]
     fn test_content_deduplication() {
         let (store, _temp) = create_test_store();
@@ -769,6 +1961,88 @@ mod tests {
         assert!(trading_only[0].document.path.contains("sol.md"));
     }
 
+    #[test]
+    fn test_fts_database_errors_propagate() {
+        let (store, _temp) = create_test_store();
+        store.store_document("trading", "rsi.md", "RSI Guide", "RSI basics").unwrap();
+        store.conn.lock().unwrap().execute_batch("DROP TABLE documents_fts").unwrap();
+
+        assert!(store.search_fts("RSI", 10).is_err());
+        assert!(store.search_fts_raw("RSI", 10).is_err());
+    }
+
+    #[test]
+    fn test_fts_survives_nasty_queries() {
+        let (store, _temp) = create_test_store();
+        store
+            .store_document("trading", "rsi.md", "RSI Guide", "What's RSI? The relative strength index, used with a stop-loss.")
+            .unwrap();
+        store
+            .store_document("trading", "cn.md", "熊市", "如何在熊市赚钱 with SOL")
+            .unwrap();
+        store
+            .store_document("trading", "near.md", "Near Protocol", "NEAR is not a NOT operator")
+            .unwrap();
+
+        let nasty = [
+            r#"what's "RSI"?"#,
+            r#""unbalanced quote"#,
+            "(RSI",
+            "RSI)",
+            "-RSI",
+            "stop-loss",
+            "*",
+            "RSI*",
+            "NOT",
+            "NEAR(",
+            "AND OR NOT",
+            "title:RSI",
+            "rsi 🚀🚀",
+            "🚀",
+            "如何在熊市赚钱？",
+            "^RSI",
+            "\"\"\"",
+            "",
+            "   ",
+            "{RSI}",
+            "RSI + SOL - ETH",
+        ];
+        for query in nasty {
+            store.search_fts(query, 10).unwrap_or_else(|e| panic!("{:?} failed: {}", query, e));
+            store
+                .search_fts_in_collection(query, "trading", 10)
+                .unwrap_or_else(|e| panic!("{:?} failed in collection: {}", query, e));
+            store
+                .search_fts_raw(query, 10)
+                .unwrap_or_else(|e| panic!("{:?} failed raw: {}", query, e));
+        }
+
+        let paths = |query: &str| -> Vec<String> {
+            let mut paths: Vec<String> = store
+                .search_fts(query, 10)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.document.path)
+                .collect();
+            paths.sort();
+            paths
+        };
+        assert_eq!(paths(r#"what's "RSI"?"#), ["rsi.md"]);
+        assert_eq!(paths("stop-loss"), ["rsi.md"]);
+        assert_eq!(paths("stren*"), ["rsi.md"]);
+        assert_eq!(paths("如何在熊市赚钱？"), ["cn.md"]);
+        assert_eq!(paths("NEAR NOT"), ["near.md"]);
+        assert!(paths("🚀").is_empty());
+        // No document has both words, so any of them will do
+        assert_eq!(paths("SOL index"), ["cn.md", "rsi.md"]);
+
+        // Raw mode keeps FTS5 syntax for those who want it
+        let raw = store.search_fts_raw("title:guide OR title:熊市", 10).unwrap();
+        assert_eq!(raw.len(), 2);
+        assert!(store.search_fts_raw("title:strength", 10).unwrap().is_empty());
+        assert_eq!(store.search_fts_raw("SOL NOT NEAR", 10).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_store_document_too_large() {
         let (mut store, _temp) = create_test_store();
@@ -783,4 +2057,373 @@ mod tests {
             _ => panic!("Expected Custom error for large document"),
         }
     }
+
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_slow_query_logging() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = QmdStore::new(temp_dir.path().join("slow.db"))
+            .unwrap()
+            .with_slow_query_threshold(Some(Duration::ZERO));
+
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            store
+                .store_document("trading", "sol.md", "SOL", "secret body text")
+                .unwrap();
+            store.search_fts("SOL", 5).unwrap();
+        });
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("Slow QMD query"));
+        assert!(output.contains("store_document"));
+        assert!(output.contains("search_fts"));
+        assert!(!output.contains("secret body text"));
+
+        let stats = store.metrics().get("search_fts").unwrap();
+        assert_eq!(stats.count, 1);
+        assert_eq!(stats.slow_count, 1);
+    }
+
+    #[test]
+    fn test_slow_query_logging_disabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let store = QmdStore::new(temp_dir.path().join("fast.db"))
+            .unwrap()
+            .with_slow_query_threshold(None);
+
+        store.search_fts("SOL", 5).unwrap();
+
+        let stats = store.metrics().get("search_fts").unwrap();
+        assert_eq!(stats.count, 1);
+        assert_eq!(stats.slow_count, 0);
+    }
+
+    #[test]
+    fn test_explain_docid_uses_index() {
+        let (store, _temp) = create_test_store();
+        store
+            .store_document("trading", "sol.md", "SOL", "Buy SOL")
+            .unwrap();
+
+        let plan = store.explain("get_by_docid", &["#abc123"]).unwrap();
+        assert!(plan.contains("idx_documents_hash"), "plan: {}", plan);
+
+        assert!(store.explain("get_by_docid", &[]).is_err());
+        assert!(store.explain("drop_everything", &[]).is_err());
+    }
+
+    #[test]
+    fn test_read_only_replica() {
+        let (writer, temp) = create_test_store();
+        let db_path = temp.path().join("test.db");
+        writer.store_document("notes", "a.md", "A", "alpha").unwrap();
+
+        let reader = QmdStore::open_read_only(&db_path).unwrap();
+        assert!(reader.is_read_only());
+        assert!(reader.get_by_path("notes", "a.md").unwrap().is_some());
+
+        // Writes from the owner are visible to the next read
+        writer.store_document("notes", "b.md", "B", "beta").unwrap();
+        assert!(reader.get_by_path("notes", "b.md").unwrap().is_some());
+
+        // Compaction on the writer side does not break the reader
+        writer.store_document("notes", "a.md", "A", "alpha v2").unwrap();
+        writer.vacuum_content().unwrap();
+        writer.vacuum().unwrap();
+        let a = reader.get_by_path("notes", "a.md").unwrap().unwrap();
+        assert_eq!(a.body.as_deref(), Some("alpha v2"));
+        assert_eq!(reader.search_fts("beta", 10).unwrap().len(), 1);
+
+        assert!(matches!(
+            reader.store_document("notes", "c.md", "C", "gamma"),
+            Err(QmdError::ReadOnly("store_document"))
+        ));
+        assert!(matches!(reader.vacuum(), Err(QmdError::ReadOnly(_))));
+        assert!(reader.get_by_path("notes", "c.md").unwrap().is_none());
+
+        assert!(QmdStore::open_read_only(temp.path().join("missing.db")).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_sessions() {
+        use aagt_core::infra::encryption::AesGcmProvider;
+
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("test.db");
+        let k1: Arc<dyn EncryptionProvider> = Arc::new(AesGcmProvider::new("k1", &[1; 32]).unwrap());
+
+        // Legacy plaintext row alongside an encrypted one
+        let s1 = r#"{"id":"s1","metadata":{"user_id":"alice"}}"#;
+        QmdStore::new(&path)
+            .unwrap()
+            .store_session("legacy", r#"{"id":"legacy","metadata":{"user_id":"alice"}}"#)
+            .unwrap();
+        let store = QmdStore::new(&path).unwrap().with_encryption(k1.clone());
+        store.store_session("s1", s1).unwrap();
+
+        let row = |store: &QmdStore, id: &str| -> Vec<String> {
+            let conn = store.conn.lock().unwrap();
+            conn.query_row(
+                "SELECT id, data, updated_at, status, user_id, metadata FROM sessions WHERE id = ?",
+                params![id],
+                |r| (0..6).map(|i| r.get::<_, Option<String>>(i).map(Option::unwrap_or_default)).collect(),
+            )
+            .unwrap()
+        };
+        let raw = row(&store, "s1");
+        assert!(raw[1].starts_with(encryption::TEXT_PREFIX));
+        assert!(!raw.iter().any(|column| column.contains("alice")), "{:?}", raw);
+        assert_eq!(store.load_session("s1").unwrap().unwrap(), s1);
+        let alice = SessionQuery::new().user("alice");
+        let ids = |store: &QmdStore| -> Vec<String> {
+            store.list_session_summaries(&alice).unwrap().into_iter().map(|s| s.id).collect()
+        };
+        assert_eq!(ids(&store), vec!["s1"]);
+        assert!(store.load_session("legacy").unwrap().unwrap().contains("alice"));
+
+        // Wrong key and missing key both fail instead of returning ciphertext
+        let wrong = QmdStore::new(&path)
+            .unwrap()
+            .with_encryption(Arc::new(AesGcmProvider::new("k1", &[2; 32]).unwrap()));
+        assert!(matches!(wrong.load_session("s1"), Err(QmdError::Encryption(_))));
+        assert!(QmdStore::new(&path).unwrap().load_session("s1").is_err());
+
+        // Rotation re-encrypts every row, including the legacy one
+        let k2: Arc<dyn EncryptionProvider> = Arc::new(AesGcmProvider::new("k2", &[3; 32]).unwrap());
+        let mut seen = Vec::new();
+        let count = store
+            .rewrap_sessions(k1.as_ref(), k2.as_ref(), |p| seen.push((p.done, p.total)))
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(seen, vec![(1, 2), (2, 2)]);
+
+        let rotated = QmdStore::new(&path).unwrap().with_encryption(k2);
+        assert_eq!(rotated.load_session("s1").unwrap().unwrap(), s1);
+        assert!(!row(&rotated, "legacy").iter().any(|column| column.contains("alice")));
+        let mut listed = ids(&rotated);
+        listed.sort();
+        assert_eq!(listed, vec!["legacy", "s1"]);
+        assert!(store.load_session("s1").is_err());
+    }
+
+    #[test]
+    fn test_record_versions_migrate() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("test.db");
+
+        // Simulate a database written before records were versioned
+        {
+            let store = QmdStore::new(&path).unwrap();
+            store.store_document("notes", "a.md", "A", "alpha legacy").unwrap();
+            store.store_document("notes", "b.md", "B", "beta legacy").unwrap();
+            let conn = store.conn.lock().unwrap();
+            for column in ["record_version", "source", "source_time"] {
+                conn.execute(&format!("ALTER TABLE documents DROP COLUMN {}", column), [])
+                    .unwrap();
+            }
+        }
+
+        let store = QmdStore::new(&path).unwrap();
+        assert_eq!(store.min_record_version().unwrap(), Some(1));
+        let doc = store
+            .store_document_with_source("notes", "c.md", "C", "gamma", Some("tool:web_fetch"), None)
+            .unwrap();
+        assert_eq!(doc.record_version, CURRENT_RECORD_VERSION);
+
+        // Mixed v1/v2 reads and search
+        let a = store.get_by_path("notes", "a.md").unwrap().unwrap();
+        assert_eq!((a.record_version, a.source, a.source_time), (1, None, None));
+        let c = store.get_by_docid(&doc.docid).unwrap().unwrap();
+        assert_eq!(c.source.as_deref(), Some("tool:web_fetch"));
+        assert!(c.source_time.is_some());
+        let hits = store.search_fts("legacy", 10).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|h| h.document.record_version == 1));
+
+        // Manual policy keeps the old version on rewrite; lazy upgrades it
+        let manual = QmdStore::new(&path).unwrap().with_migration_policy(MigrationPolicy::Manual);
+        let a = manual.store_document("notes", "a.md", "A", "alpha edited").unwrap();
+        assert_eq!(a.record_version, 1);
+        manual.vacuum().unwrap();
+        assert_eq!(manual.min_record_version().unwrap(), Some(1));
+
+        let a = store.store_document("notes", "a.md", "A", "alpha edited again").unwrap();
+        assert_eq!(a.record_version, CURRENT_RECORD_VERSION);
+        let b = store.get_by_path("notes", "b.md").unwrap().unwrap();
+        assert_eq!(b.record_version, 1);
+
+        // Compaction upgrades the rest
+        store.vacuum().unwrap();
+        let b = store.get_by_path("notes", "b.md").unwrap().unwrap();
+        assert_eq!(b.record_version, CURRENT_RECORD_VERSION);
+        assert_eq!(b.source_time.as_deref(), Some(b.modified_at.as_str()));
+
+        // Explicit migration leaves nothing older than the target
+        let manual = QmdStore::new(&path).unwrap().with_migration_policy(MigrationPolicy::Manual);
+        manual.store_document("notes", "d.md", "D", "delta").unwrap();
+        manual.conn.lock().unwrap().execute("UPDATE documents SET record_version = 1", []).unwrap();
+        assert_eq!(manual.migrate_in_place(CURRENT_RECORD_VERSION).unwrap(), 4);
+        assert_eq!(manual.migrate_in_place(CURRENT_RECORD_VERSION).unwrap(), 0);
+        let old: i64 = manual
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT count(*) FROM documents WHERE record_version < 2", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(old, 0);
+        assert!(manual.migrate_in_place(CURRENT_RECORD_VERSION + 1).is_err());
+    }
+
+    #[test]
+    fn test_session_listing_and_leases() {
+        let (store, _temp) = create_test_store();
+        store.store_session("a", r#"{"id":"a"}"#).unwrap();
+        store.store_session("b", r#"{"id":"b"}"#).unwrap();
+        let ids: Vec<String> = store.list_sessions().unwrap().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+
+        let ttl = Duration::from_secs(60);
+        assert!(store.try_acquire_lease("recovery", "node-1", ttl).unwrap());
+        assert!(!store.try_acquire_lease("recovery", "node-2", ttl).unwrap());
+        assert!(store.try_acquire_lease("recovery", "node-1", ttl).unwrap());
+
+        // An expired lease can be taken over
+        assert!(store.try_acquire_lease("other", "node-1", Duration::ZERO).unwrap());
+        assert!(store.try_acquire_lease("other", "node-2", ttl).unwrap());
+    }
+
+    #[test]
+    fn test_session_listing_columns_migrate() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("test.db");
+
+        // Simulate a database written before sessions had listing columns
+        {
+            let store = QmdStore::new(&path).unwrap();
+            store
+                .store_session("legacy", r#"{"id":"legacy","status":{"failed":"provider down"}}"#)
+                .unwrap();
+            let conn = store.conn.lock().unwrap();
+            conn.execute("DROP INDEX idx_sessions_user", []).unwrap();
+            for column in ["status", "user_id", "metadata"] {
+                conn.execute(&format!("ALTER TABLE sessions DROP COLUMN {}", column), [])
+                    .unwrap();
+            }
+            conn.execute(
+                "UPDATE sessions SET updated_at = '2020-01-01T00:00:00+00:00'",
+                [],
+            )
+            .unwrap();
+        }
+
+        let store = QmdStore::new(&path).unwrap();
+        store
+            .store_session(
+                "chat-1",
+                r#"{"id":"chat-1","status":"completed","metadata":{"user_id":"alice","title":"SOL?"}}"#,
+            )
+            .unwrap();
+
+        let all = store.list_session_summaries(&SessionQuery::new()).unwrap();
+        let listed: Vec<_> = all.iter().map(|s| (s.id.as_str(), s.status.as_deref())).collect();
+        assert_eq!(listed, [("chat-1", Some("completed")), ("legacy", Some("failed"))]);
+        assert!(all[1].metadata.is_empty());
+
+        let alice = store
+            .list_session_summaries(&SessionQuery::new().user("alice"))
+            .unwrap();
+        assert_eq!(alice.len(), 1);
+        assert_eq!(alice[0].metadata["title"], "SOL?");
+        let page = store
+            .list_session_summaries(&SessionQuery::new().id_prefix("l").offset(1))
+            .unwrap();
+        assert!(page.is_empty());
+
+        let day = Duration::from_secs(24 * 3600);
+        assert_eq!(store.delete_sessions_older_than(day).unwrap(), 1);
+        assert!(store.load_session("legacy").unwrap().is_none());
+        assert!(store.load_session("chat-1").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_docid_link_rewriting() {
+        use aagt_core::infra::response_format::{
+            DocidLinkRewriter, FormatTarget, ResponseFormatter,
+        };
+
+        let (store, _temp) = create_test_store();
+        let doc = store
+            .store_document("trading", "sol.md", "SOL Strategy", "Buy the dip")
+            .unwrap();
+
+        let rewriter = DocidLinkRewriter::new(Arc::new(store), "/docs/{docid}");
+        let text = format!("Based on aagt://#{} and #abcdef.", doc.docid);
+        assert_eq!(
+            rewriter.format(&text, FormatTarget::Markdown),
+            format!("Based on [SOL Strategy](/docs/{}) and #abcdef.", doc.docid)
+        );
+    }
+
+    #[test]
+    fn test_as_of_returns_historical_revisions() {
+        use chrono::TimeZone;
+
+        let (store, _temp) = create_test_store();
+        let jan = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let feb = Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+        let mar = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let v1 = store
+            .store_document_at("trading", "sol.md", "SOL", "Accumulate SOL below forty dollars", jan)
+            .unwrap();
+        let v2 = store
+            .store_document_at("trading", "sol.md", "SOL", "Take profit on SOL above ninety dollars", mar)
+            .unwrap();
+        store
+            .store_document_at("trading", "eth.md", "ETH", "Accumulate ETH on weakness", mar)
+            .unwrap();
+        let mid = feb.timestamp();
+
+        let old = store.get_by_path_as_of("trading", "sol.md", mid).unwrap().unwrap();
+        assert_eq!(old.body.as_deref(), Some("Accumulate SOL below forty dollars"));
+        assert!(store.get_by_path_as_of("trading", "eth.md", mid).unwrap().is_none());
+        let now = store.get_by_path_as_of("trading", "sol.md", mar.timestamp()).unwrap().unwrap();
+        assert_eq!(now.hash, v2.hash);
+
+        // FTS only sees revisions current at the time
+        let hits = store.search_fts_as_of("accumulate", 10, mid).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].document.hash, v1.hash);
+        assert!(store.search_fts_as_of("profit", 10, mid).unwrap().is_empty());
+        assert_eq!(store.search_fts_as_of("accumulate", 10, mar.timestamp()).unwrap().len(), 1);
+
+        // Docid lookups (the vector leg) reject superseded revisions
+        assert!(store.get_by_docid_as_of(&v1.docid, mid).unwrap().is_some());
+        assert!(store.get_by_docid_as_of(&v1.docid, mar.timestamp()).unwrap().is_none());
+        assert!(store.get_by_docid_as_of(&v2.docid, mid).unwrap().is_none());
+
+        // Past revisions keep their content through a vacuum
+        assert_eq!(store.vacuum_content().unwrap(), 0);
+        assert!(store.get_by_path_as_of("trading", "sol.md", mid).unwrap().is_some());
+    }
+
 }
@@ -0,0 +1,414 @@
+//! Collection sync from the filesystem
+//!
+//! A collection with a `root_path` mirrors the files under it that match its
+//! `glob_pattern`. [`QmdStore::sync_collection`] walks the directory, stores
+//! new and changed files (compared by content hash) and marks documents whose
+//! files are gone inactive. [`HybridSearchEngine::sync_collection`] does the
+//! same and keeps the chunk vectors in step.
+//!
+//! Document paths are relative to `root_path` with `/` separators, so they
+//! double as the path part of a [`VirtualPath`]. Symlinks are not followed.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use tracing::{debug, info, warn};
+
+use crate::content_hash::hash_content;
+use crate::error::{QmdError, Result};
+use crate::hybrid_search::HybridSearchEngine;
+use crate::index_plan::detect_title;
+use crate::store::{Collection, QmdStore, MAX_CONTENT_SIZE};
+use crate::virtual_path::VirtualPath;
+
+/// Documents listed per page when looking for deleted files
+const LIST_PAGE: usize = 500;
+
+/// Outcome of syncing a collection
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Files stored for the first time (or again after being removed)
+    pub added: usize,
+    /// Files whose content changed since the last sync
+    pub updated: usize,
+    /// Files whose content is already stored
+    pub unchanged: usize,
+    /// Documents marked inactive because their file is gone
+    pub removed: usize,
+    /// Matching files left out: too large, unreadable, not UTF-8 or rejected by the indexer
+    pub skipped: Vec<String>,
+}
+
+/// A new or changed file
+struct ChangedFile {
+    path: String,
+    title: String,
+    content: String,
+    is_new: bool,
+}
+
+/// What a sync has to do, worked out before anything is written
+#[derive(Default)]
+struct SyncScan {
+    changed: Vec<ChangedFile>,
+    unchanged: usize,
+    /// Paths of active documents without a matching file
+    stale: Vec<String>,
+    skipped: Vec<String>,
+}
+
+impl VirtualPath {
+    /// Whether this path matches `pattern`, e.g. `**/*.md`
+    ///
+    /// `*` stays within one path segment; `**/` spans any number of them,
+    /// including none.
+    pub fn matches_glob(&self, pattern: &str) -> Result<bool> {
+        let pattern = glob::Pattern::new(pattern)?;
+        Ok(pattern.matches_with(&self.path, glob_options()))
+    }
+}
+
+fn glob_options() -> glob::MatchOptions {
+    glob::MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    }
+}
+
+/// Every regular file under `dir`, recursively
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// `path` relative to `root`, `/`-separated
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Option<Vec<&str>> = relative
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect();
+    Some(parts?.join("/"))
+}
+
+/// Compare the files under the collection's root with what `store` holds
+fn scan(store: &QmdStore, name: &str) -> Result<SyncScan> {
+    let collection: Collection = store
+        .list_collections()?
+        .into_iter()
+        .find(|c| c.name == name)
+        .ok_or_else(|| QmdError::Custom(format!("Collection not found: {}", name)))?;
+    let root = collection.root_path.ok_or_else(|| {
+        QmdError::Custom(format!("Collection {} has no root_path to sync from", name))
+    })?;
+    let pattern = glob::Pattern::new(&collection.glob_pattern)?;
+
+    let mut files = Vec::new();
+    walk(&root, &mut files)?;
+    files.sort();
+
+    let mut scan = SyncScan::default();
+    let mut seen = HashSet::new();
+    for file in files {
+        let Some(path) = relative_path(&root, &file) else {
+            continue;
+        };
+        if !pattern.matches_with(&path, glob_options()) {
+            continue;
+        }
+        seen.insert(path.clone());
+
+        let size = std::fs::metadata(&file)?.len();
+        if size > MAX_CONTENT_SIZE as u64 {
+            warn!(
+                "Skipping {}/{}: {} bytes exceeds the {} byte limit",
+                name, path, size, MAX_CONTENT_SIZE
+            );
+            scan.skipped.push(path);
+            continue;
+        }
+        let content = match std::fs::read(&file).map(String::from_utf8) {
+            Ok(Ok(content)) => content,
+            Ok(Err(e)) => {
+                warn!(
+                    "Skipping {}/{}: not valid UTF-8 ({})",
+                    name,
+                    path,
+                    e.utf8_error()
+                );
+                scan.skipped.push(path);
+                continue;
+            }
+            Err(e) => {
+                warn!("Skipping {}/{}: {}", name, path, e);
+                scan.skipped.push(path);
+                continue;
+            }
+        };
+
+        let existing = store.get_by_path(name, &path)?;
+        if existing
+            .as_ref()
+            .is_some_and(|doc| doc.hash == hash_content(&content))
+        {
+            scan.unchanged += 1;
+            continue;
+        }
+        let stem = file
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("Untitled");
+        scan.changed.push(ChangedFile {
+            title: detect_title(&content).unwrap_or(stem).to_string(),
+            path,
+            content,
+            is_new: existing.is_none(),
+        });
+    }
+
+    let mut offset = 0;
+    loop {
+        let page = store.list_documents(name, None, offset, LIST_PAGE, false)?;
+        let done = page.len() < LIST_PAGE;
+        offset += page.len();
+        scan.stale.extend(
+            page.into_iter()
+                .map(|doc| doc.path)
+                .filter(|path| !seen.contains(path)),
+        );
+        if done {
+            break;
+        }
+    }
+    Ok(scan)
+}
+
+impl SyncScan {
+    fn report(&self) -> SyncReport {
+        SyncReport {
+            skipped: self.skipped.clone(),
+            unchanged: self.unchanged,
+            ..Default::default()
+        }
+    }
+}
+
+impl QmdStore {
+    /// Bring collection `name` in line with the matching files under its `root_path`
+    ///
+    /// New and changed files are stored with the title of their first `# `
+    /// heading (or their file name); documents whose file disappeared are
+    /// marked inactive. Files over the size limit are skipped with a warning.
+    pub fn sync_collection(&self, name: &str) -> Result<SyncReport> {
+        let scan = scan(self, name)?;
+        let mut report = scan.report();
+        for file in &scan.changed {
+            match self.store_document(name, &file.path, &file.title, &file.content) {
+                Ok(_) if file.is_new => report.added += 1,
+                Ok(_) => report.updated += 1,
+                Err(e) => {
+                    warn!("Skipping {}/{}: {}", name, file.path, e);
+                    report.skipped.push(file.path.clone());
+                }
+            }
+        }
+        for path in &scan.stale {
+            debug!("File for {}/{} is gone, removing", name, path);
+            if self.delete_document(name, path)? {
+                report.removed += 1;
+            }
+        }
+        info!("Synced collection {}: {:?}", name, report);
+        Ok(report)
+    }
+}
+
+impl HybridSearchEngine {
+    /// [`QmdStore::sync_collection`], also embedding added and updated files
+    /// and dropping the vectors of removed ones
+    pub fn sync_collection(&self, name: &str) -> Result<SyncReport> {
+        let scan = scan(self.qmd_store(), name)?;
+        let mut report = scan.report();
+
+        let plan = self.plan_index(
+            scan.changed
+                .iter()
+                .map(|f| (name, f.path.as_str(), f.title.as_str(), f.content.as_str()))
+                .collect(),
+        );
+        for (planned, file) in plan.documents.iter().zip(&scan.changed) {
+            match &planned.error {
+                Some(error) => {
+                    warn!("Skipping {}/{}: {}", name, file.path, error);
+                    report.skipped.push(file.path.clone());
+                }
+                None if file.is_new => report.added += 1,
+                None => report.updated += 1,
+            }
+        }
+        self.execute_plan(&plan)?;
+
+        for path in &scan.stale {
+            debug!("File for {}/{} is gone, removing", name, path);
+            if self.delete_document(name, path)? {
+                report.removed += 1;
+            }
+        }
+        info!("Synced collection {}: {:?}", name, report);
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hybrid_search::HybridSearchConfig;
+    use tempfile::TempDir;
+
+    fn collection(root: &Path) -> Collection {
+        Collection {
+            name: "notes".to_string(),
+            description: None,
+            glob_pattern: "**/*.md".to_string(),
+            root_path: Some(root.to_path_buf()),
+        }
+    }
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let file = root.join(path);
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(file, content).unwrap();
+    }
+
+    #[test]
+    fn test_glob_matching() {
+        let vpath = VirtualPath::parse("aagt://notes/strategies/sol.md").unwrap();
+        assert!(vpath.matches_glob("**/*.md").unwrap());
+        assert!(vpath.matches_glob("strategies/*.md").unwrap());
+        assert!(!vpath.matches_glob("*.md").unwrap());
+        assert!(VirtualPath::parse("aagt://notes/top.md")
+            .unwrap()
+            .matches_glob("**/*.md")
+            .unwrap());
+    }
+
+    #[test]
+    fn test_store_sync_adds_updates_and_removes() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("notes");
+        write(&root, "sol.md", "# SOL Plan\n\nBuy below 120.\n");
+        write(&root, "research/eth.md", "ETH staking yields 4%.\n");
+        write(
+            &root,
+            "research/deep/btc.md",
+            "# BTC\n\nHalving in April.\n",
+        );
+        write(&root, "research/data.csv", "not,markdown\n");
+
+        let store = QmdStore::new(dir.path().join("sync.db")).unwrap();
+        store.create_collection(collection(&root)).unwrap();
+
+        let report = store.sync_collection("notes").unwrap();
+        assert_eq!(
+            (
+                report.added,
+                report.updated,
+                report.unchanged,
+                report.removed
+            ),
+            (3, 0, 0, 0)
+        );
+        assert_eq!(
+            store.get_by_path("notes", "sol.md").unwrap().unwrap().title,
+            "SOL Plan"
+        );
+        assert_eq!(
+            store
+                .get_by_path("notes", "research/eth.md")
+                .unwrap()
+                .unwrap()
+                .title,
+            "eth"
+        );
+        assert!(store
+            .get_by_path("notes", "research/data.csv")
+            .unwrap()
+            .is_none());
+
+        // Second run: one edit, one deletion, one new file
+        write(&root, "sol.md", "# SOL Plan\n\nBuy below 110.\n");
+        std::fs::remove_file(root.join("research/eth.md")).unwrap();
+        write(&root, "research/deep/jup.md", "JUP airdrop notes.\n");
+
+        let report = store.sync_collection("notes").unwrap();
+        assert_eq!(
+            (
+                report.added,
+                report.updated,
+                report.unchanged,
+                report.removed
+            ),
+            (1, 1, 1, 1)
+        );
+        assert!(store
+            .get_by_path("notes", "research/eth.md")
+            .unwrap()
+            .is_none());
+        assert!(store
+            .get_by_path("notes", "sol.md")
+            .unwrap()
+            .unwrap()
+            .body
+            .unwrap()
+            .contains("110"));
+        assert_eq!(store.count_documents("notes", None).unwrap(), 3);
+
+        // Nothing changed since
+        let report = store.sync_collection("notes").unwrap();
+        assert_eq!(
+            report,
+            SyncReport {
+                unchanged: 3,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_engine_sync_indexes_and_skips_rejected() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("notes");
+        write(&root, "sol.md", "# SOL Plan\n\nBuy below 120.\n");
+        write(&root, "nested/blank.md", "   \n");
+
+        let config = HybridSearchConfig {
+            db_path: dir.path().join("engine.db"),
+            ..Default::default()
+        };
+        let config = crate::test_support::with_test_embeddings(config, dir.path());
+        let engine = HybridSearchEngine::new(config).unwrap();
+        engine.create_collection(collection(&root)).unwrap();
+
+        let report = engine.sync_collection("notes").unwrap();
+        assert_eq!(report.added, 1);
+        assert_eq!(report.skipped, ["nested/blank.md"]);
+        let results = engine.search_in_collection("SOL", "notes", 5).unwrap();
+        assert_eq!(results[0].document.path, "sol.md");
+
+        std::fs::remove_file(root.join("sol.md")).unwrap();
+        assert_eq!(engine.sync_collection("notes").unwrap().removed, 1);
+        assert!(engine
+            .search_in_collection("SOL", "notes", 5)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+//! Fixtures for tests that open a search engine in every feature set
+//!
+//! With `vector-index` the engine needs an embeddings provider and a tokenizer
+//! file. [`with_test_embeddings`] supplies both without any model download: a
+//! byte-level tokenizer written next to the test database, and
+//! `StubEmbeddings`, which embeds by hashing words.
+
+use std::path::Path;
+
+#[cfg(feature = "vector-index")]
+use aagt_core::knowledge::rag::Embeddings;
+
+use crate::hybrid_search::HybridSearchConfig;
+
+/// Output dimension of `StubEmbeddings`
+#[cfg(feature = "vector-index")]
+const STUB_DIMENSION: usize = 32;
+
+/// Deterministic embeddings: a normalized bag of hashed lowercase words
+///
+/// Texts sharing words score closer, which is enough for hybrid search to rank
+/// the obvious match first.
+#[cfg(feature = "vector-index")]
+pub(crate) struct StubEmbeddings;
+
+#[cfg(feature = "vector-index")]
+#[async_trait::async_trait]
+impl Embeddings for StubEmbeddings {
+    async fn embed(&self, text: &str) -> aagt_core::error::Result<Vec<f32>> {
+        let mut vector = vec![0.0f32; STUB_DIMENSION];
+        for word in text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+            let hash = word
+                .to_lowercase()
+                .bytes()
+                .fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100_0000_01b3));
+            vector[(hash % STUB_DIMENSION as u64) as usize] += 1.0;
+        }
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        Ok(vector)
+    }
+
+    fn dimension(&self) -> Option<usize> {
+        Some(STUB_DIMENSION)
+    }
+}
+
+/// `config` with what `vector-index` builds need to open an engine in `dir`
+///
+/// Also persists vectors to `dir`, so saves and backups cover them. Without
+/// `vector-index` the config is returned unchanged.
+pub(crate) fn with_test_embeddings(config: HybridSearchConfig, dir: &Path) -> HybridSearchConfig {
+    #[cfg(feature = "vector-index")]
+    {
+        let mut config = config.with_embeddings(std::sync::Arc::new(StubEmbeddings));
+        config.chunker_config.tokenizer_path = write_tokenizer(dir);
+        config.vector_store_path = Some(dir.join("test_vectors.bin"));
+        config
+    }
+    #[cfg(not(feature = "vector-index"))]
+    {
+        let _ = dir;
+        config
+    }
+}
+
+/// Save a one-token-per-byte tokenizer to `dir`, returning its path
+///
+/// Decoding reproduces the input exactly, so chunk texts match the document.
+#[cfg(feature = "vector-index")]
+fn write_tokenizer(dir: &Path) -> std::path::PathBuf {
+    use tokenizers::models::bpe::BPE;
+    use tokenizers::pre_tokenizers::byte_level::ByteLevel;
+    use tokenizers::Tokenizer;
+
+    let vocab = ByteLevel::alphabet()
+        .into_iter()
+        .enumerate()
+        .map(|(id, c)| (c.to_string(), id as u32))
+        .collect();
+    let bpe = BPE::builder().vocab_and_merges(vocab, Vec::new()).build().unwrap();
+    let mut tokenizer = Tokenizer::new(bpe);
+    tokenizer.with_pre_tokenizer(ByteLevel::new(false, true, true));
+    tokenizer.with_decoder(ByteLevel::default());
+
+    let path = dir.join("tokenizer.json");
+    tokenizer.save(&path, false).unwrap();
+    path
+}
@@ -1,15 +1,89 @@
 //! Vector storage and similarity search using HNSW index
 //!
 //! Provides efficient k-NN search for dense vectors using Hierarchical Navigable Small World graphs.
+//!
+//! With [`TieringConfig`] set, only the most recently hit entries stay in the
+//! HNSW graph (the hot tier). Colder entries keep their quantized embeddings
+//! and are saved with the store, but are only brute-force scanned when the
+//! hot tier can't answer a query; a cold entry that makes it into results is
+//! promoted back to hot. Recency is a logical clock (one tick per add or
+//! search), saved with the store, so tiers are recomputed on load.
+//!
+//! HNSW can't delete, so removed entries are tombstoned: searches skip them
+//! until [`VectorStore::compact`] rebuilds the index without them, which
+//! happens on its own once a quarter of the entries are dead. Saves only
+//! write live entries.
 
 use crate::error::{QmdError, Result};
 
 use hnsw_rs::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashSet;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use std::sync::RwLock;
 
+/// When a search also scans the cold tier
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColdScanPolicy {
+    /// When the hot tier returns fewer than `k` results scoring at least `min_score`
+    ///
+    /// Scores are `1 / (1 + d)` for the squared L2 distance `d` between
+    /// quantized vectors, so only near-duplicates score above 0.5.
+    Insufficient { min_score: f64 },
+    /// On every search
+    Always,
+}
+
+impl Default for ColdScanPolicy {
+    fn default() -> Self {
+        Self::Insufficient { min_score: 0.0 }
+    }
+}
+
+/// Limits for the in-memory HNSW index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TieringConfig {
+    /// Most entries kept in the HNSW index; the least recently hit beyond this go cold
+    pub hot_max_elements: usize,
+    /// When searches fall back to the cold tier
+    pub cold_scan_policy: ColdScanPolicy,
+}
+
+/// Options for a [`VectorStore`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VectorStoreConfig {
+    /// Evict cold entries from the HNSW index; `None` keeps every entry in it
+    pub tiering: Option<TieringConfig>,
+}
+
+/// Tier sizes and tiering activity since the store was created or loaded
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TierStats {
+    /// Entries in the HNSW index
+    pub hot: usize,
+    /// Entries only reachable by a cold scan
+    pub cold: usize,
+    /// Searches run
+    pub searches: u64,
+    /// Searches that scanned the cold tier
+    pub cold_scans: u64,
+    /// Cold entries moved back into the index
+    pub promotions: u64,
+    /// Hot entries moved out of the index
+    pub evictions: u64,
+}
+
+/// Which entries are in the HNSW index, and when each was last used
+#[derive(Default)]
+struct Tiers {
+    hot: Vec<bool>,
+    last_access: Vec<AtomicU64>,
+}
+
 /// A vector entry with metadata (Quantized to u8)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VectorEntry {
@@ -22,6 +96,12 @@ pub struct VectorEntry {
     /// Quantized vector embedding (u8)
     /// Range [-1.0, 1.0] mapped to [0, 255]
     pub embedding: Vec<u8>,
+    /// Headings above the chunk, when it was chunked as markdown
+    ///
+    /// Saved alongside the entries rather than in them, so stores written
+    /// before this field existed still load.
+    #[serde(skip)]
+    pub heading_path: Option<String>,
 }
 
 /// Vector search result
@@ -33,6 +113,8 @@ pub struct VectorSearchResult {
     pub collection: String,
     /// Chunk sequence number
     pub chunk_seq: usize,
+    /// Headings above the matching chunk
+    pub heading_path: Option<String>,
     /// Similarity score (approximate)
     pub score: f64,
 }
@@ -47,23 +129,151 @@ pub struct VectorStore {
     dimension: usize,
     /// Max elements for HNSW
     max_elements: usize,
+    /// Tier membership and recency, parallel to `entries`
+    tiers: RwLock<Tiers>,
+    /// Indices of removed entries, still in `entries` and the index until compaction
+    tombstones: RwLock<HashSet<usize>>,
+    config: VectorStoreConfig,
+    /// Logical clock for access recency
+    clock: AtomicU64,
+    /// Bumped (under the `entries` write lock) whenever entry indices shift
+    generation: AtomicU64,
+    searches: AtomicU64,
+    cold_scans: AtomicU64,
+    promotions: AtomicU64,
+    evictions: AtomicU64,
     /// Dirty flag
     dirty: RwLock<bool>,
 }
 
 impl VectorStore {
     pub fn new(dimension: usize, max_elements: usize) -> Self {
-        // M=16, ef_construction=200
-        let hnsw = Hnsw::new(16, max_elements, 16, 200, DistU8L2);
+        Self::with_config(dimension, max_elements, VectorStoreConfig::default())
+    }
+
+    /// Store with tiering options
+    pub fn with_config(dimension: usize, max_elements: usize, mut config: VectorStoreConfig) -> Self {
+        if let Some(tiering) = &mut config.tiering {
+            tiering.hot_max_elements = tiering.hot_max_elements.max(1);
+        }
         Self {
             entries: RwLock::new(Vec::new()),
-            hnsw: RwLock::new(hnsw),
+            hnsw: RwLock::new(Self::empty_index(max_elements)),
             dimension,
             max_elements,
+            tiers: RwLock::new(Tiers::default()),
+            tombstones: RwLock::new(HashSet::new()),
+            config,
+            clock: AtomicU64::new(1),
+            generation: AtomicU64::new(0),
+            searches: AtomicU64::new(0),
+            cold_scans: AtomicU64::new(0),
+            promotions: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
             dirty: RwLock::new(false),
         }
     }
 
+    fn empty_index(max_elements: usize) -> Hnsw<'static, u8, DistU8L2> {
+        // M=16, ef_construction=200
+        Hnsw::new(16, max_elements, 16, 200, DistU8L2)
+    }
+
+    /// Index holding only the hot entries
+    fn build_index(&self, entries: &[VectorEntry], hot: &[bool]) -> Hnsw<'static, u8, DistU8L2> {
+        let hnsw = Self::empty_index(self.max_elements);
+        let items: Vec<(&Vec<u8>, usize)> = entries
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| hot[*i])
+            .map(|(i, e)| (&e.embedding, i))
+            .collect();
+        if !items.is_empty() {
+            hnsw.parallel_insert(&items);
+        }
+        hnsw
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Move the least recently used hot entries out of the index once over the cap
+    fn evict_over_cap(
+        &self,
+        entries: &[VectorEntry],
+        hnsw: &mut Hnsw<'static, u8, DistU8L2>,
+        tiers: &mut Tiers,
+    ) {
+        let Some(tiering) = &self.config.tiering else {
+            return;
+        };
+        let mut hot: Vec<usize> = (0..tiers.hot.len()).filter(|&i| tiers.hot[i]).collect();
+        if hot.len() <= tiering.hot_max_elements {
+            return;
+        }
+        // Go down to 90% of the cap so index rebuilds are batched
+        let keep = tiering.hot_max_elements - tiering.hot_max_elements / 10;
+        hot.sort_by_key(|&i| Reverse((tiers.last_access[i].load(Ordering::Relaxed), i)));
+        for &i in &hot[keep..] {
+            tiers.hot[i] = false;
+        }
+        self.evictions
+            .fetch_add((hot.len() - keep) as u64, Ordering::Relaxed);
+        *hnsw = self.build_index(entries, &tiers.hot);
+    }
+
+    /// Put cold entries back into the index
+    ///
+    /// `indices` were picked under `generation`; if a compaction or clear has
+    /// since moved entries around they no longer name the same entries, so
+    /// nothing is promoted.
+    fn promote(&self, indices: &[usize], generation: u64) -> Result<()> {
+        let entries = self
+            .entries
+            .read()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let mut hnsw = self
+            .hnsw
+            .write()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let mut tiers = self
+            .tiers
+            .write()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        if self.generation.load(Ordering::Acquire) != generation {
+            return Ok(());
+        }
+        let tombstones = self
+            .tombstones
+            .read()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+
+        let cold: Vec<usize> = indices
+            .iter()
+            .copied()
+            .filter(|&i| i < tiers.hot.len() && !tiers.hot[i] && !tombstones.contains(&i))
+            .collect();
+        drop(tombstones);
+        if cold.is_empty() {
+            return Ok(());
+        }
+        let items: Vec<(&Vec<u8>, usize)> = cold.iter().map(|&i| (&entries[i].embedding, i)).collect();
+        hnsw.parallel_insert(&items);
+        for &i in &cold {
+            tiers.hot[i] = true;
+        }
+        self.promotions.fetch_add(cold.len() as u64, Ordering::Relaxed);
+        self.evict_over_cap(&entries, &mut hnsw, &mut tiers);
+
+        let mut dirty = self
+            .dirty
+            .write()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        *dirty = true;
+        Ok(())
+    }
+
     /// Quantize f32 vector to u8
     /// Assumes input is normalized to roughly [-1.0, 1.0]
     fn quantize(vec: &[f32]) -> Vec<u8> {
@@ -84,6 +294,18 @@ impl VectorStore {
         docid: impl Into<String>,
         chunk_seq: usize,
         embedding: Vec<f32>,
+    ) -> Result<()> {
+        self.add_with_heading(collection, docid, chunk_seq, None, embedding)
+    }
+
+    /// Add a chunk's vector along with the headings above it
+    pub fn add_with_heading(
+        &self,
+        collection: impl Into<String>,
+        docid: impl Into<String>,
+        chunk_seq: usize,
+        heading_path: Option<String>,
+        embedding: Vec<f32>,
     ) -> Result<()> {
         if embedding.len() != self.dimension {
             return Err(QmdError::Custom(format!(
@@ -94,15 +316,20 @@ impl VectorStore {
         }
 
         let quantized = Self::quantize(&embedding);
+        let tick = self.tick();
 
         let mut entries = self
             .entries
             .write()
             .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
-        let hnsw = self
+        let mut hnsw = self
             .hnsw
             .write()
             .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let mut tiers = self
+            .tiers
+            .write()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
         let mut dirty = self
             .dirty
             .write()
@@ -116,7 +343,11 @@ impl VectorStore {
             collection: collection.into(),
             chunk_seq,
             embedding: quantized,
+            heading_path,
         });
+        tiers.hot.push(true);
+        tiers.last_access.push(AtomicU64::new(tick));
+        self.evict_over_cap(&entries, &mut hnsw, &mut tiers);
 
         *dirty = true;
         Ok(())
@@ -138,6 +369,7 @@ impl VectorStore {
             return Err(QmdError::Custom("Dimension mismatch".to_string()));
         }
 
+        let tick = self.tick();
         let entries = self
             .entries
             .read()
@@ -145,11 +377,21 @@ impl VectorStore {
         if entries.is_empty() {
             return Ok(Vec::new());
         }
+        let generation = self.generation.load(Ordering::Acquire);
 
         let hnsw = self
             .hnsw
             .read()
             .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let tiers = self
+            .tiers
+            .read()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let tombstones = self
+            .tombstones
+            .read()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        self.searches.fetch_add(1, Ordering::Relaxed);
         let query_u8 = Self::quantize(query_embedding);
 
         // If we have a collection filter, we increase search depth to ensure we find enough candidates
@@ -157,14 +399,15 @@ impl VectorStore {
             (k * 4).max(100)
         } else {
             k
-        };
+        } + tombstones.len();
         let ef_search = (search_k * 2).max(50);
 
         let neighbors = hnsw.search(&query_u8, search_k, ef_search);
 
-        let mut results = Vec::new();
+        // (entry index, score)
+        let mut hits: Vec<(usize, f64)> = Vec::new();
         for neighbor in neighbors {
-            if neighbor.d_id < entries.len() {
+            if neighbor.d_id < entries.len() && !tombstones.contains(&neighbor.d_id) {
                 let entry = &entries[neighbor.d_id];
 
                 // Post-filtering by collection
@@ -174,42 +417,197 @@ impl VectorStore {
                     }
                 }
 
-                let score = 1.0 / (1.0 + neighbor.distance as f64);
-
-                results.push(VectorSearchResult {
-                    docid: entry.docid.clone(),
-                    collection: entry.collection.clone(),
-                    chunk_seq: entry.chunk_seq,
-                    score,
-                });
+                hits.push((neighbor.d_id, score(neighbor.distance)));
 
-                if results.len() >= k {
+                if hits.len() >= k {
                     break;
                 }
             }
         }
 
+        let mut promote = Vec::new();
+        if let Some(tiering) = &self.config.tiering {
+            let scan = match tiering.cold_scan_policy {
+                ColdScanPolicy::Always => true,
+                ColdScanPolicy::Insufficient { min_score } => {
+                    hits.iter().filter(|(_, s)| *s >= min_score).count() < k
+                }
+            };
+            if scan && tiers.hot.iter().enumerate().any(|(i, hot)| !hot && !tombstones.contains(&i)) {
+                self.cold_scans.fetch_add(1, Ordering::Relaxed);
+                hits.extend(
+                    entries
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, e)| {
+                            !tiers.hot[*i]
+                                && !tombstones.contains(i)
+                                && collection.is_none_or(|col| e.collection == col)
+                        })
+                        .map(|(i, e)| (i, score(DistU8L2.eval(&query_u8, &e.embedding)))),
+                );
+                hits.sort_by(|a, b| b.1.total_cmp(&a.1));
+                hits.truncate(k);
+                promote = hits.iter().map(|(i, _)| *i).filter(|&i| !tiers.hot[i]).collect();
+            }
+        }
+        for (i, _) in &hits {
+            tiers.last_access[*i].store(tick, Ordering::Relaxed);
+        }
+
+        let results = hits
+            .into_iter()
+            .map(|(i, score)| VectorSearchResult {
+                docid: entries[i].docid.clone(),
+                collection: entries[i].collection.clone(),
+                chunk_seq: entries[i].chunk_seq,
+                heading_path: entries[i].heading_path.clone(),
+                score,
+            })
+            .collect();
+
+        drop((tombstones, tiers, hnsw, entries));
+        if !promote.is_empty() {
+            self.promote(&promote, generation)?;
+        }
         Ok(results)
     }
 
+    /// Tier sizes and how often searches needed the cold tier
+    pub fn tier_stats(&self) -> TierStats {
+        let (hot, total) = self
+            .tiers
+            .read()
+            .map(|t| (t.hot.iter().filter(|h| **h).count(), t.hot.len()))
+            .unwrap_or_default();
+        TierStats {
+            hot,
+            cold: total - hot,
+            searches: self.searches.load(Ordering::Relaxed),
+            cold_scans: self.cold_scans.load(Ordering::Relaxed),
+            promotions: self.promotions.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
     /// Get the representative embedding for a document (first chunk)
     pub fn get_vector(&self, docid: &str) -> Result<Option<Vec<u8>>> {
         let entries = self
             .entries
             .read()
             .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let tombstones = self
+            .tombstones
+            .read()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let chunks: Vec<&VectorEntry> = entries
+            .iter()
+            .enumerate()
+            .filter(|(i, e)| e.docid == docid && !tombstones.contains(i))
+            .map(|(_, e)| e)
+            .collect();
 
         // Find the first chunk (seq 0) for this docid
-        let entry = entries
+        let entry = chunks
             .iter()
-            .find(|e| e.docid == docid && e.chunk_seq == 0)
-            .or_else(|| entries.iter().find(|e| e.docid == docid)); // Fallback to any chunk if seq 0 not found
+            .find(|e| e.chunk_seq == 0)
+            .or_else(|| chunks.first()); // Fallback to any chunk if seq 0 not found
 
         Ok(entry.map(|e| e.embedding.clone()))
     }
 
+    /// Tombstone every chunk of the given `(collection, docid)` documents
+    ///
+    /// Returns how many entries were removed. Compacts the store once more
+    /// than a quarter of its entries are tombstones.
+    pub fn remove_documents<'a>(
+        &self,
+        documents: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<usize> {
+        let documents: HashSet<(&str, &str)> = documents.into_iter().collect();
+        if documents.is_empty() {
+            return Ok(0);
+        }
+        let (removed, compact) = {
+            let entries = self
+                .entries
+                .read()
+                .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+            let mut tombstones = self
+                .tombstones
+                .write()
+                .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+            let before = tombstones.len();
+            for (i, entry) in entries.iter().enumerate() {
+                if documents.contains(&(entry.collection.as_str(), entry.docid.as_str())) {
+                    tombstones.insert(i);
+                }
+            }
+            let removed = tombstones.len() - before;
+            if removed == 0 {
+                return Ok(0);
+            }
+            let mut dirty = self
+                .dirty
+                .write()
+                .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+            *dirty = true;
+            (removed, tombstones.len() * 4 > entries.len())
+        };
+        if compact {
+            self.compact()?;
+        }
+        Ok(removed)
+    }
+
+    /// Drop tombstoned entries and rebuild the index without them
+    ///
+    /// Returns how many entries were dropped.
+    pub fn compact(&self) -> Result<usize> {
+        let mut entries = self
+            .entries
+            .write()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let mut hnsw = self
+            .hnsw
+            .write()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let mut tiers = self
+            .tiers
+            .write()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let mut tombstones = self
+            .tombstones
+            .write()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        if tombstones.is_empty() {
+            return Ok(0);
+        }
+
+        let mut live = Tiers::default();
+        let mut kept = Vec::with_capacity(entries.len() - tombstones.len());
+        for (i, entry) in std::mem::take(&mut *entries).into_iter().enumerate() {
+            if !tombstones.contains(&i) {
+                kept.push(entry);
+                live.hot.push(tiers.hot[i]);
+                live.last_access
+                    .push(AtomicU64::new(tiers.last_access[i].load(Ordering::Relaxed)));
+            }
+        }
+        *hnsw = self.build_index(&kept, &live.hot);
+        *entries = kept;
+        self.generation.fetch_add(1, Ordering::Release);
+        *tiers = live;
+        let removed = tombstones.len();
+        tombstones.clear();
+        tracing::debug!("Compacted vector store: dropped {} entries", removed);
+        Ok(removed)
+    }
+
+    /// Number of live (not removed) entries
     pub fn len(&self) -> usize {
-        self.entries.read().map(|e| e.len()).unwrap_or(0)
+        let total = self.entries.read().map(|e| e.len()).unwrap_or(0);
+        total - self.tombstones.read().map(|t| t.len()).unwrap_or(0)
     }
 
     pub fn is_empty(&self) -> bool {
@@ -229,16 +627,51 @@ impl VectorStore {
             return Ok(());
         }
 
+        self.snapshot_to(path)?;
+
+        {
+            let mut dirty = self
+                .dirty
+                .write()
+                .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+            *dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Write the live entries to `path` (via a temp file) without touching the dirty flag
+    pub fn snapshot_to(&self, path: impl AsRef<Path>) -> Result<()> {
         let path = path.as_ref();
         let entries = self
             .entries
             .read()
             .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
 
+        let tiers = self
+            .tiers
+            .read()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        let tombstones = self
+            .tombstones
+            .read()
+            .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
+        // Tombstoned entries are left out, so a reload is already compacted
+        let live: Vec<usize> = (0..entries.len())
+            .filter(|i| !tombstones.contains(i))
+            .collect();
         let data = VectorStoreData {
-            entries: entries.clone(),
+            entries: live.iter().map(|&i| entries[i].clone()).collect(),
             dimension: self.dimension,
+            last_access: live
+                .iter()
+                .map(|&i| tiers.last_access[i].load(Ordering::Relaxed))
+                .collect(),
+            heading_paths: live
+                .iter()
+                .map(|&i| entries[i].heading_path.clone())
+                .collect(),
         };
+        drop((tombstones, tiers));
 
         let tmp_path = path.with_extension("tmp");
         {
@@ -249,14 +682,6 @@ impl VectorStore {
         }
 
         std::fs::rename(tmp_path, path).map_err(QmdError::Io)?;
-
-        {
-            let mut dirty = self
-                .dirty
-                .write()
-                .map_err(|_| QmdError::Custom("Lock poisoned".to_string()))?;
-            *dirty = false;
-        }
         Ok(())
     }
 
@@ -272,21 +697,82 @@ impl VectorStore {
     }
 
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
-        let file = std::fs::File::open(path).map_err(QmdError::Io)?;
-        let reader = std::io::BufReader::new(file);
+        Self::load_with_config(path, VectorStoreConfig::default())
+    }
 
-        let store_data: VectorStoreData = bincode::deserialize_from(reader)
-            .map_err(|e| QmdError::Custom(format!("Deserialization failed: {}", e)))?;
+    /// Load a saved store, recomputing tiers from the saved access recency
+    pub fn load_with_config(path: impl AsRef<Path>, config: VectorStoreConfig) -> Result<Self> {
+        let path = path.as_ref();
+        let open = || -> Result<_> {
+            Ok(std::io::BufReader::new(
+                std::fs::File::open(path).map_err(QmdError::Io)?,
+            ))
+        };
 
-        let store = Self::new(store_data.dimension, store_data.entries.len().max(100));
+        let mut store_data: VectorStoreData = match bincode::deserialize_from(open()?) {
+            Ok(data) => data,
+            // Stores saved before heading paths end where `heading_paths` would start
+            Err(_) => match bincode::deserialize_from::<_, VectorStoreDataWithoutHeadings>(open()?) {
+                Ok(data) => VectorStoreData {
+                    entries: data.entries,
+                    dimension: data.dimension,
+                    last_access: data.last_access,
+                    heading_paths: Vec::new(),
+                },
+                // Stores saved before access tracking have no `last_access`
+                Err(_) => {
+                    let legacy: LegacyVectorStoreData = bincode::deserialize_from(open()?)
+                        .map_err(|e| QmdError::Custom(format!("Deserialization failed: {}", e)))?;
+                    VectorStoreData {
+                        entries: legacy.entries,
+                        dimension: legacy.dimension,
+                        last_access: Vec::new(),
+                        heading_paths: Vec::new(),
+                    }
+                }
+            },
+        };
+        store_data.last_access.resize(store_data.entries.len(), 0);
+        for (entry, heading_path) in store_data
+            .entries
+            .iter_mut()
+            .zip(std::mem::take(&mut store_data.heading_paths))
         {
-            let mut entries_lock = store.entries.write().unwrap();
-            let hnsw_lock = store.hnsw.write().unwrap();
-            for entry in store_data.entries {
-                let idx = entries_lock.len();
-                hnsw_lock.parallel_insert(&[(&entry.embedding, idx)]);
-                entries_lock.push(entry);
+            entry.heading_path = heading_path;
+        }
+
+        let store = Self::with_config(
+            store_data.dimension,
+            store_data.entries.len().max(100),
+            config,
+        );
+        {
+            // Most recently used first; ties go to later entries
+            let mut order: Vec<usize> = (0..store_data.entries.len()).collect();
+            order.sort_by_key(|&i| Reverse((store_data.last_access[i], i)));
+            let hot_max = store
+                .config
+                .tiering
+                .as_ref()
+                .map_or(usize::MAX, |t| t.hot_max_elements);
+            let mut hot = vec![false; order.len()];
+            for &i in order.iter().take(hot_max) {
+                hot[i] = true;
             }
+
+            let mut entries_lock = store.entries.write().unwrap();
+            let mut hnsw_lock = store.hnsw.write().unwrap();
+            let mut tiers_lock = store.tiers.write().unwrap();
+            *hnsw_lock = store.build_index(&store_data.entries, &hot);
+            let next = store_data.last_access.iter().max().map_or(1, |t| t + 1);
+            store.clock.store(next, Ordering::Relaxed);
+            tiers_lock.last_access = store_data
+                .last_access
+                .into_iter()
+                .map(AtomicU64::new)
+                .collect();
+            tiers_lock.hot = hot;
+            *entries_lock = store_data.entries;
         }
         // dirty is false by default in new(), which is correct after load
         Ok(store)
@@ -295,9 +781,16 @@ impl VectorStore {
     pub fn clear(&self) {
         if let Ok(mut entries) = self.entries.write() {
             entries.clear();
+            self.generation.fetch_add(1, Ordering::Release);
         }
         if let Ok(mut hnsw) = self.hnsw.write() {
-            *hnsw = Hnsw::new(16, self.max_elements, 16, 200, DistU8L2);
+            *hnsw = Self::empty_index(self.max_elements);
+        }
+        if let Ok(mut tiers) = self.tiers.write() {
+            *tiers = Tiers::default();
+        }
+        if let Ok(mut tombstones) = self.tombstones.write() {
+            tombstones.clear();
         }
         if let Ok(mut dirty) = self.dirty.write() {
             *dirty = true;
@@ -310,6 +803,30 @@ impl VectorStore {
 struct VectorStoreData {
     entries: Vec<VectorEntry>,
     dimension: usize,
+    /// Clock tick of each entry's last use, for tiering
+    last_access: Vec<u64>,
+    /// Heading path of each entry
+    heading_paths: Vec<Option<String>>,
+}
+
+/// Saved format before `heading_paths` was added
+#[derive(Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
+struct VectorStoreDataWithoutHeadings {
+    entries: Vec<VectorEntry>,
+    dimension: usize,
+    last_access: Vec<u64>,
+}
+
+/// Saved format before `last_access` was added
+#[derive(Deserialize)]
+struct LegacyVectorStoreData {
+    entries: Vec<VectorEntry>,
+    dimension: usize,
+}
+
+fn score(distance: f32) -> f64 {
+    1.0 / (1.0 + distance as f64)
 }
 
 /// L2 Squared Distance for u8
@@ -430,6 +947,51 @@ mod tests {
         assert_eq!(results[0].docid, "doc1");
     }
 
+    #[test]
+    fn test_heading_paths_survive_save_and_older_files_load() {
+        let temp_file = tempfile::NamedTempFile::new().unwrap();
+        let path = temp_file.path();
+
+        let store = VectorStore::new(3, 100);
+        store
+            .add_with_heading(
+                "notes",
+                "doc1",
+                0,
+                Some("## Strategy > ### Entry rules".to_string()),
+                vec![1.0, 0.0, 0.0],
+            )
+            .unwrap();
+        store.add("notes", "doc2", 0, vec![0.0, 1.0, 0.0]).unwrap();
+        store.save(path).unwrap();
+
+        let loaded = VectorStore::load(path).unwrap();
+        let results = loaded.search(&[1.0, 0.0, 0.0], 2).unwrap();
+        assert_eq!(
+            results[0].heading_path.as_deref(),
+            Some("## Strategy > ### Entry rules")
+        );
+        assert_eq!(results[1].heading_path, None);
+
+        // A store saved before heading paths loads without them
+        let old = VectorStoreDataWithoutHeadings {
+            entries: vec![VectorEntry {
+                docid: "doc1".to_string(),
+                collection: "notes".to_string(),
+                chunk_seq: 0,
+                embedding: VectorStore::quantize(&[1.0, 0.0, 0.0]),
+                heading_path: None,
+            }],
+            dimension: 3,
+            last_access: vec![1],
+        };
+        bincode::serialize_into(std::fs::File::create(path).unwrap(), &old).unwrap();
+        let loaded = VectorStore::load(path).unwrap();
+        let results = loaded.search(&[1.0, 0.0, 0.0], 1).unwrap();
+        assert_eq!(results[0].docid, "doc1");
+        assert_eq!(results[0].heading_path, None);
+    }
+
     #[test]
     fn test_clear() {
         let store = VectorStore::new(3, 100);
@@ -445,6 +1007,33 @@ mod tests {
         assert!(store.is_empty());
     }
 
+    #[test]
+    fn test_removed_documents_stay_out_of_search_and_saves() {
+        let store = VectorStore::new(3, 100);
+        for i in 0..8 {
+            store.add("notes", format!("doc{}", i), 0, vec![1.0, 0.0, 0.0]).unwrap();
+        }
+        store.add("notes", "doc0", 1, vec![0.9, 0.1, 0.0]).unwrap();
+        store.add("other", "doc0", 0, vec![1.0, 0.0, 0.0]).unwrap();
+
+        // Both chunks go; the same docid in another collection stays
+        assert_eq!(store.remove_documents([("notes", "doc0")]).unwrap(), 2);
+        assert_eq!(store.len(), 8);
+        let hits = store.search(&[1.0, 0.0, 0.0], 10).unwrap();
+        assert_eq!(hits.len(), 8);
+        assert!(!hits.iter().any(|h| h.docid == "doc0" && h.collection == "notes"));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        store.save_force(file.path()).unwrap();
+        assert_eq!(VectorStore::load(file.path()).unwrap().len(), 8);
+
+        // Passing a quarter of dead entries compacts the index
+        store.remove_documents([("notes", "doc1"), ("notes", "doc2")]).unwrap();
+        assert_eq!(store.entries.read().unwrap().len(), 6);
+        assert_eq!(store.compact().unwrap(), 0);
+        assert_eq!(store.search(&[1.0, 0.0, 0.0], 10).unwrap().len(), 6);
+    }
+
     #[test]
     fn test_similarity_ranking() {
         let store = VectorStore::new(3, 100);
@@ -477,4 +1066,86 @@ mod tests {
         assert!(results[0].score > results[1].score);
         assert!(results[1].score > results[2].score);
     }
+
+    #[test]
+    fn test_tiering_caps_hot_set_and_promotes_cold_hits() {
+        let vector = |i: usize| -> Vec<f32> {
+            let v: Vec<f32> = (0..8)
+                .map(|j| {
+                    let x = ((i * 8 + j) as u64)
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    ((x >> 33) % 1000) as f32 / 500.0 - 1.0
+                })
+                .collect();
+            let norm: f32 = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+            v.iter().map(|x| x / norm).collect()
+        };
+        let config = VectorStoreConfig {
+            tiering: Some(TieringConfig {
+                hot_max_elements: 20,
+                cold_scan_policy: ColdScanPolicy::Insufficient { min_score: 0.9 },
+            }),
+        };
+        let tiered = VectorStore::with_config(8, 1000, config.clone());
+        let flat = VectorStore::new(8, 1000);
+        for i in 0..200 {
+            tiered.add("docs", format!("doc{}", i), 0, vector(i)).unwrap();
+            flat.add("docs", format!("doc{}", i), 0, vector(i)).unwrap();
+        }
+        let stats = tiered.tier_stats();
+        assert!(stats.hot <= 20, "{:?}", stats);
+        assert_eq!(stats.hot + stats.cold, 200);
+
+        // Recent documents are answered from the index, as without tiering
+        for i in 190..200 {
+            let hot = tiered.search(&vector(i), 1).unwrap();
+            assert_eq!(hot[0].docid, flat.search(&vector(i), 1).unwrap()[0].docid);
+        }
+        assert_eq!(tiered.tier_stats().cold_scans, 0);
+
+        // Old documents are still found through a cold scan, then promoted
+        assert_eq!(tiered.search(&vector(3), 1).unwrap()[0].docid, "doc3");
+        let stats = tiered.tier_stats();
+        assert_eq!((stats.cold_scans, stats.promotions), (1, 1));
+        assert!(stats.hot <= 20);
+        assert_eq!(tiered.search(&vector(3), 1).unwrap()[0].docid, "doc3");
+        assert_eq!(tiered.tier_stats().cold_scans, 1);
+
+        // Tiers are recomputed from saved recency
+        let file = tempfile::NamedTempFile::new().unwrap();
+        tiered.save_force(file.path()).unwrap();
+        let loaded = VectorStore::load_with_config(file.path(), config).unwrap();
+        assert_eq!(loaded.tier_stats().hot, 20);
+        assert_eq!(loaded.search(&vector(3), 1).unwrap()[0].docid, "doc3");
+        assert_eq!(loaded.tier_stats().cold_scans, 0);
+    }
+
+    #[test]
+    fn test_promotion_skips_indices_from_before_a_compaction() {
+        let config = VectorStoreConfig {
+            tiering: Some(TieringConfig {
+                hot_max_elements: 1,
+                cold_scan_policy: ColdScanPolicy::Always,
+            }),
+        };
+        let store = VectorStore::with_config(3, 100, config);
+        for i in 0..4 {
+            store.add("notes", format!("doc{}", i), 0, vec![1.0, 0.0, i as f32 / 4.0]).unwrap();
+        }
+        let generation = store.generation.load(Ordering::Acquire);
+        let cold: Vec<usize> = (0..4).filter(|&i| !store.tiers.read().unwrap().hot[i]).collect();
+        assert_eq!(cold.len(), 3);
+
+        // A search picked these indices, then a removal compacted the store under it
+        store.remove_documents([("notes", "doc0"), ("notes", "doc1")]).unwrap();
+        assert_eq!(store.entries.read().unwrap().len(), 2);
+        store.promote(&cold, generation).unwrap();
+        assert_eq!(store.tier_stats().promotions, 0);
+
+        store.clear();
+        store.promote(&cold, generation).unwrap();
+        assert_eq!(store.tier_stats().promotions, 0);
+    }
+
 }
@@ -1,10 +1,11 @@
 use crate::hybrid_search::HybridSearchEngine;
 use crate::error::Result;
+use crate::index_plan::{detect_title, IndexPlan};
 use notify::{Watcher, RecursiveMode, Event, EventKind};
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tracing::{info, error};
+use tracing::{info, error, warn};
 
 /// Watcher for Active Indexing
 pub struct FileWatcher {
@@ -69,15 +70,19 @@ impl FileWatcher {
         Ok(())
     }
 
+    /// Preview the initial crawl of `root` without indexing anything
+    pub fn plan(&self, root: impl AsRef<Path>, collection: &str) -> Result<IndexPlan> {
+        self.engine.plan_directory(root, collection)
+    }
+
     /// Crawl the directory and index all .md files
     async fn crawl(&self, root: &Path, collection: &str) -> Result<()> {
         info!("Crawling directory for initial indexing: {:?}", root);
-        let pattern = format!("{}/**/*.md", root.to_string_lossy());
-        for entry in glob::glob(&pattern).map_err(|e| crate::error::QmdError::Custom(e.to_string()))? {
-            if let Ok(path_buf) = entry {
-                Self::index_file(&self.engine, collection, &path_buf).await?;
-            }
+        let plan = self.plan(root, collection)?;
+        for doc in plan.rejected() {
+            warn!("Skipping {}: {}", doc.path, doc.error.as_deref().unwrap_or_default());
         }
+        self.engine.execute_plan(&plan)?;
         Ok(())
     }
 
@@ -86,8 +91,8 @@ impl FileWatcher {
         let content = tokio::fs::read_to_string(path).await
             .map_err(|e| crate::error::QmdError::Io(e))?;
         
-        let title = path.file_stem()
-            .and_then(|s| s.to_str())
+        let title = detect_title(&content)
+            .or_else(|| path.file_stem().and_then(|s| s.to_str()))
             .unwrap_or("Untitled")
             .to_string();
             